@@ -0,0 +1,493 @@
+//! Structured diffs for error resolutions.
+//!
+//! A fix is stored in an error's history as a [`StructuredDiff`]: per-file
+//! hunks with the line ranges they cover before and after the change, so tools
+//! can tell which lines a fix touched without parsing patch text. It renders
+//! back to a unified diff for `git apply`, and to colored terminal output or
+//! Markdown for people and models.
+//!
+//! Diffs whose text exceeds [`MAX_INLINE_DIFF_BYTES`] stay out of the record:
+//! the text goes to `.agent/blobs/<hash>.diff` and the record keeps a
+//! [`StoredDiff::Blob`] with per-file line counts. Records written before
+//! structured storage keep their raw text in [`ErrorVersion::diff`], which
+//! [`AgentManager::version_patch`] still reads.
+
+use crate::{AgentManager, ErrorVersion};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Write as _;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// Directory under `.agent` holding diffs too large for their record.
+pub const BLOBS_DIR: &str = "blobs";
+/// Diffs with more bytes of unified text than this are stored as blobs.
+pub const MAX_INLINE_DIFF_BYTES: usize = 64 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct StructuredDiff {
+    pub files: Vec<FileDiff>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileDiff {
+    /// Path before the change; `None` for a created file.
+    pub old_path: Option<String>,
+    /// Path after the change; `None` for a deleted file.
+    pub new_path: Option<String>,
+    /// Git's extended header lines (`new file mode 100644`, `rename from …`),
+    /// kept so the patch applies the way it was recorded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extended: Vec<String>,
+    /// The change is to a binary file, whose content is not kept.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub binary: bool,
+    pub hunks: Vec<Hunk>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    /// The text after the second `@@`, usually the enclosing item.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub section: String,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub kind: LineKind,
+    pub text: String,
+    /// The last line of its file, without a trailing newline.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_newline: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+/// How a resolution diff is kept in an [`ErrorVersion`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "storage", rename_all = "snake_case")]
+pub enum StoredDiff {
+    Inline(StructuredDiff),
+    /// The unified text is in `.agent/blobs/<hash>.diff`.
+    Blob { hash: String, bytes: usize, files: Vec<FileStat> },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FileStat {
+    pub path: String,
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl Hunk {
+    /// Lines of the old file the hunk covers.
+    pub fn before(&self) -> Range<u32> {
+        self.old_start..self.old_start + self.old_lines
+    }
+
+    /// Lines of the new file the hunk covers.
+    pub fn after(&self) -> Range<u32> {
+        self.new_start..self.new_start + self.new_lines
+    }
+
+    fn header(&self) -> String {
+        let mut header = format!("@@ -{},{} +{},{} @@", self.old_start, self.old_lines, self.new_start, self.new_lines);
+        if !self.section.is_empty() {
+            header.push(' ');
+            header.push_str(&self.section);
+        }
+        header
+    }
+}
+
+impl FileDiff {
+    /// The path after the change, or before it for a deleted file.
+    pub fn path(&self) -> &str {
+        self.new_path.as_deref().or(self.old_path.as_deref()).unwrap_or_default()
+    }
+
+    pub fn added(&self) -> usize {
+        self.count(LineKind::Added)
+    }
+
+    pub fn removed(&self) -> usize {
+        self.count(LineKind::Removed)
+    }
+
+    fn count(&self, kind: LineKind) -> usize {
+        self.hunks.iter().flat_map(|h| &h.lines).filter(|l| l.kind == kind).count()
+    }
+
+    pub fn stat(&self) -> FileStat {
+        FileStat { path: self.path().to_string(), added: self.added(), removed: self.removed() }
+    }
+
+    fn old_name(&self) -> String {
+        self.old_path.as_ref().map(|p| format!("a/{}", p)).unwrap_or_else(|| "/dev/null".to_string())
+    }
+
+    fn new_name(&self) -> String {
+        self.new_path.as_ref().map(|p| format!("b/{}", p)).unwrap_or_else(|| "/dev/null".to_string())
+    }
+}
+
+impl StructuredDiff {
+    /// Parses unified diff text as produced by `git diff` or `diff -u`.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut files: Vec<FileDiff> = Vec::new();
+        // Lines still expected in the current hunk: (old, new).
+        let mut remaining = (0u32, 0u32);
+        // Split on `\n` only, so `\r` of CRLF files survives.
+        for (n, line) in text.strip_suffix('\n').unwrap_or(text).split('\n').enumerate() {
+            let in_hunk = remaining != (0, 0);
+            if let Some(rest) = line.strip_prefix('\\') {
+                let last = files.last_mut().and_then(|f| f.hunks.last_mut()).and_then(|h| h.lines.last_mut());
+                match last {
+                    Some(last) if rest.trim_start().starts_with("No newline") => last.no_newline = true,
+                    _ => anyhow::bail!("Line {}: unexpected `{}`", n + 1, line),
+                }
+            } else if in_hunk {
+                let hunk = files.last_mut().and_then(|f| f.hunks.last_mut()).expect("a hunk is open");
+                // Some tools strip the space of empty context lines.
+                let (kind, text) = match line.chars().next() {
+                    Some(' ') | None => (LineKind::Context, line.get(1..).unwrap_or_default()),
+                    Some('-') => (LineKind::Removed, &line[1..]),
+                    Some('+') => (LineKind::Added, &line[1..]),
+                    _ => anyhow::bail!("Line {}: hunk ends early; expected {} more old and {} more new lines", n + 1, remaining.0, remaining.1),
+                };
+                let (old, new) = match kind {
+                    LineKind::Context => (1, 1),
+                    LineKind::Removed => (1, 0),
+                    LineKind::Added => (0, 1),
+                };
+                if remaining.0 < old || remaining.1 < new {
+                    anyhow::bail!("Line {}: more lines than the hunk header announced", n + 1);
+                }
+                remaining = (remaining.0 - old, remaining.1 - new);
+                hunk.lines.push(DiffLine { kind, text: text.to_string(), no_newline: false });
+            } else if let Some(paths) = line.strip_prefix("diff --git ") {
+                let (old, new) = paths.split_once(" b/").map(|(a, b)| (a.trim_start_matches("a/"), b)).unwrap_or((paths, paths));
+                files.push(FileDiff { old_path: Some(old.to_string()), new_path: Some(new.to_string()), ..Default::default() });
+            } else if let Some(path) = line.strip_prefix("--- ") {
+                // A `---` right after `diff --git` and its extended headers belongs to that file.
+                let continues = files.last().is_some_and(|f| f.hunks.is_empty() && !f.binary);
+                if !continues {
+                    files.push(FileDiff::default());
+                }
+                files.last_mut().expect("just pushed").old_path = patch_path(path, "a/");
+            } else if let Some(path) = line.strip_prefix("+++ ") {
+                let file = files.last_mut().with_context(|| format!("Line {}: `+++` without `---`", n + 1))?;
+                file.new_path = patch_path(path, "b/");
+            } else if let Some(header) = line.strip_prefix("@@ -") {
+                let file = files.last_mut().with_context(|| format!("Line {}: hunk outside a file", n + 1))?;
+                let hunk = parse_hunk_header(header).with_context(|| format!("Line {}: invalid hunk header `{}`", n + 1, line))?;
+                remaining = (hunk.old_lines, hunk.new_lines);
+                file.hunks.push(hunk);
+            } else if let Some(file) = files.last_mut().filter(|f| f.hunks.is_empty()) {
+                if line.starts_with("Binary files ") || line == "GIT binary patch" {
+                    file.binary = true;
+                } else if !file.binary && !line.is_empty() {
+                    file.extended.push(line.to_string());
+                    if line.starts_with("new file mode ") {
+                        file.old_path = None;
+                    } else if line.starts_with("deleted file mode ") {
+                        file.new_path = None;
+                    }
+                }
+            }
+            // Anything else between files is commentary, as in `git format-patch`.
+        }
+        if remaining != (0, 0) {
+            anyhow::bail!("The diff ends inside a hunk");
+        }
+        Ok(Self { files })
+    }
+
+    /// Unified diff text that `git apply` accepts.
+    pub fn to_unified(&self) -> String {
+        let mut out = String::new();
+        for file in &self.files {
+            if !file.extended.is_empty() || file.binary {
+                let old = file.old_path.as_deref().unwrap_or(file.path());
+                let new = file.new_path.as_deref().unwrap_or(file.path());
+                let _ = writeln!(out, "diff --git a/{} b/{}", old, new);
+                for line in &file.extended {
+                    let _ = writeln!(out, "{}", line);
+                }
+            }
+            if file.binary {
+                let _ = writeln!(out, "Binary files {} and {} differ", file.old_name(), file.new_name());
+                continue;
+            }
+            if file.hunks.is_empty() {
+                continue;
+            }
+            let _ = writeln!(out, "--- {}\n+++ {}", file.old_name(), file.new_name());
+            for hunk in &file.hunks {
+                let _ = writeln!(out, "{}", hunk.header());
+                for line in &hunk.lines {
+                    let _ = writeln!(out, "{}{}", marker(line.kind), line.text);
+                    if line.no_newline {
+                        out.push_str("\\ No newline at end of file\n");
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// The diff that undoes this one.
+    pub fn reverse(&self) -> Self {
+        let files = self
+            .files
+            .iter()
+            .map(|file| FileDiff {
+                old_path: file.new_path.clone(),
+                new_path: file.old_path.clone(),
+                extended: file.extended.iter().map(|line| reverse_extended(line)).collect(),
+                binary: file.binary,
+                hunks: file.hunks.iter().map(reverse_hunk).collect(),
+            })
+            .collect();
+        Self { files }
+    }
+
+    /// Line counts per file.
+    pub fn stats(&self) -> Vec<FileStat> {
+        self.files.iter().map(FileDiff::stat).collect()
+    }
+
+    /// Colored (when `color`) output for a terminal.
+    pub fn to_terminal(&self, color: bool) -> String {
+        let paint = |code: &str, text: &str| if color { format!("\x1b[{}m{}\x1b[0m", code, text) } else { text.to_string() };
+        let mut out = String::new();
+        for file in &self.files {
+            let _ = writeln!(out, "{} {}", paint("1", &file_title(file)), paint("2", &format!("(+{} -{})", file.added(), file.removed())));
+            if file.binary {
+                out.push_str("  binary file changed\n");
+            }
+            for hunk in &file.hunks {
+                let _ = writeln!(out, "{}", paint("36", &hunk.header()));
+                for line in &hunk.lines {
+                    let text = format!("{}{}", marker(line.kind), line.text);
+                    let _ = writeln!(
+                        out,
+                        "{}",
+                        match line.kind {
+                            LineKind::Added => paint("32", &text),
+                            LineKind::Removed => paint("31", &text),
+                            LineKind::Context => text,
+                        }
+                    );
+                }
+            }
+        }
+        out
+    }
+
+    /// One heading and `diff` code block per file.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        for file in &self.files {
+            let _ = writeln!(out, "#### `{}` (+{} -{})\n", file_title(file), file.added(), file.removed());
+            if file.binary {
+                out.push_str("Binary file changed.\n\n");
+                continue;
+            }
+            out.push_str("```diff\n");
+            for hunk in &file.hunks {
+                let _ = writeln!(out, "{}", hunk.header());
+                for line in &hunk.lines {
+                    let _ = writeln!(out, "{}{}", marker(line.kind), line.text);
+                }
+            }
+            out.push_str("```\n\n");
+        }
+        out
+    }
+}
+
+fn marker(kind: LineKind) -> char {
+    match kind {
+        LineKind::Context => ' ',
+        LineKind::Added => '+',
+        LineKind::Removed => '-',
+    }
+}
+
+fn file_title(file: &FileDiff) -> String {
+    match (&file.old_path, &file.new_path) {
+        (None, Some(new)) => format!("{} (new)", new),
+        (Some(old), None) => format!("{} (deleted)", old),
+        (Some(old), Some(new)) if old != new => format!("{} → {}", old, new),
+        _ => file.path().to_string(),
+    }
+}
+
+/// `a/src/lib.rs\t2024-01-01 …` → `src/lib.rs`; `/dev/null` → `None`.
+fn patch_path(path: &str, prefix: &str) -> Option<String> {
+    let path = path.split('\t').next().unwrap_or_default().trim_end();
+    (path != "/dev/null").then(|| path.strip_prefix(prefix).unwrap_or(path).to_string())
+}
+
+/// Parses `a,b +c,d @@ section` (after `@@ -`). Counts default to 1.
+fn parse_hunk_header(header: &str) -> Option<Hunk> {
+    let (ranges, section) = header.split_once(" @@").unwrap_or((header, ""));
+    let (old, new) = ranges.split_once(" +")?;
+    let range = |range: &str| -> Option<(u32, u32)> {
+        match range.split_once(',') {
+            Some((start, lines)) => Some((start.parse().ok()?, lines.parse().ok()?)),
+            None => Some((range.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_lines) = range(old)?;
+    let (new_start, new_lines) = range(new)?;
+    Some(Hunk { old_start, old_lines, new_start, new_lines, section: section.trim().to_string(), lines: Vec::new() })
+}
+
+fn reverse_hunk(hunk: &Hunk) -> Hunk {
+    let mut lines = Vec::with_capacity(hunk.lines.len());
+    // Within a run of changed lines, keep removals before additions.
+    let mut added = Vec::new();
+    for line in &hunk.lines {
+        let kind = match line.kind {
+            LineKind::Added => LineKind::Removed,
+            LineKind::Removed => LineKind::Added,
+            LineKind::Context => LineKind::Context,
+        };
+        let line = DiffLine { kind, ..line.clone() };
+        match kind {
+            LineKind::Added => added.push(line),
+            LineKind::Removed => lines.push(line),
+            LineKind::Context => {
+                lines.append(&mut added);
+                lines.push(line);
+            }
+        }
+    }
+    lines.append(&mut added);
+    Hunk {
+        old_start: hunk.new_start,
+        old_lines: hunk.new_lines,
+        new_start: hunk.old_start,
+        new_lines: hunk.old_lines,
+        section: hunk.section.clone(),
+        lines,
+    }
+}
+
+fn reverse_extended(line: &str) -> String {
+    const SWAPS: [(&str, &str); 4] =
+        [("new file mode ", "deleted file mode "), ("rename from ", "rename to "), ("old mode ", "new mode "), ("copy from ", "copy to ")];
+    for (a, b) in SWAPS {
+        if let Some(rest) = line.strip_prefix(a) {
+            return format!("{}{}", b, rest);
+        }
+        if let Some(rest) = line.strip_prefix(b) {
+            return format!("{}{}", a, rest);
+        }
+    }
+    if let Some((hashes, mode)) = line.strip_prefix("index ").map(|rest| rest.split_once(' ').unwrap_or((rest, "")))
+        && let Some((old, new)) = hashes.split_once("..")
+    {
+        return format!("index {}..{}{}{}", new, old, if mode.is_empty() { "" } else { " " }, mode);
+    }
+    line.to_string()
+}
+
+impl AgentManager {
+    pub fn blobs_dir(&self) -> PathBuf {
+        self.agent_dir().join(BLOBS_DIR)
+    }
+
+    /// Converts diff text for an [`ErrorVersion`]: `(diff, changes)`. Text that
+    /// does not parse as a unified diff is kept raw in `diff`; large diffs are
+    /// written to a blob.
+    pub fn store_diff(&self, diff: Option<String>) -> Result<(Option<String>, Option<StoredDiff>)> {
+        let Some(text) = diff.filter(|d| !d.trim().is_empty()) else {
+            return Ok((None, None));
+        };
+        let structured = match StructuredDiff::parse(&text) {
+            Ok(structured) if !structured.files.is_empty() => structured,
+            _ => return Ok((Some(text), None)),
+        };
+        let unified = structured.to_unified();
+        if unified.len() <= MAX_INLINE_DIFF_BYTES {
+            return Ok((None, Some(StoredDiff::Inline(structured))));
+        }
+        let mut hasher = DefaultHasher::new();
+        unified.hash(&mut hasher);
+        let hash = format!("{:016x}", hasher.finish());
+        if self.is_persistent() {
+            fs::create_dir_all(self.blobs_dir())?;
+            fs::write(self.blobs_dir().join(format!("{}.diff", hash)), &unified)?;
+        }
+        Ok((None, Some(StoredDiff::Blob { hash, bytes: unified.len(), files: structured.stats() })))
+    }
+
+    /// The unified diff text of `version`, wherever it is stored.
+    pub fn version_patch(&self, version: &ErrorVersion) -> Result<Option<String>> {
+        match &version.changes {
+            Some(StoredDiff::Inline(diff)) => Ok(Some(diff.to_unified())),
+            Some(StoredDiff::Blob { hash, .. }) => {
+                let path = self.blobs_dir().join(format!("{}.diff", hash));
+                fs::read_to_string(&path).map(Some).with_context(|| format!("Failed to read diff blob {}", path.display()))
+            }
+            None => Ok(version.diff.clone()),
+        }
+    }
+
+    /// The structured diff of `version`, if it has one that parses.
+    pub fn version_diff(&self, version: &ErrorVersion) -> Result<Option<StructuredDiff>> {
+        match &version.changes {
+            Some(StoredDiff::Inline(diff)) => Ok(Some(diff.clone())),
+            _ => self.version_patch(version)?.map(|text| StructuredDiff::parse(&text)).transpose(),
+        }
+    }
+
+    /// The latest diff in the history of error `id`, or the latest one recorded
+    /// at `version`.
+    pub fn resolution_diff(&self, id: &str, version: Option<u32>) -> Result<StructuredDiff> {
+        let record = self.find_error(id)?;
+        let entry = record
+            .history
+            .iter()
+            .rev()
+            .filter(|v| version.is_none_or(|n| v.version == n))
+            .find(|v| v.changes.is_some() || v.diff.is_some())
+            .with_context(|| match version {
+                Some(n) => format!("Error {} has no diff recorded at version {}", id, n),
+                None => format!("Error {} has no recorded diff", id),
+            })?;
+        self.version_diff(entry)?.with_context(|| format!("Error {} has no recorded diff", id))
+    }
+
+    /// Applies the resolution diff of error `id` to the project with
+    /// `git apply`, after checking that it applies cleanly.
+    pub fn apply_resolution(&self, id: &str, version: Option<u32>) -> Result<StructuredDiff> {
+        let diff = self.resolution_diff(id, version)?;
+        crate::fix::git_apply_checked(&self.root_path, &diff.to_unified())?;
+        Ok(diff)
+    }
+
+    /// Undoes the resolution diff of error `id`, e.g. to reproduce the error.
+    pub fn revert_resolution(&self, id: &str, version: Option<u32>) -> Result<StructuredDiff> {
+        let diff = self.resolution_diff(id, version)?.reverse();
+        crate::fix::git_apply_checked(&self.root_path, &diff.to_unified())?;
+        Ok(diff)
+    }
+}
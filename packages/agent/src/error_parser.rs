@@ -1,9 +1,13 @@
 use crate::{ProjectError, AgentErrorMetadata};
+use montrs_core::AGENT_ERROR_NOTE;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::sync::OnceLock;
 
 static ERROR_REGEX: OnceLock<Regex> = OnceLock::new();
 
+/// Parses human-readable rustc output. Prefer [`parse_json_diagnostics`], which
+/// also sees warnings, clippy lints and suggested replacements.
 pub fn parse_rustc_errors(output: &str) -> Vec<ProjectError> {
     let re = ERROR_REGEX.get_or_init(|| {
         Regex::new(r"error\[(?P<code>E\d+)\]: (?P<msg>.*)\n\s+--> (?P<file>.*):(?P<line>\d+):(?P<col>\d+)").unwrap()
@@ -30,8 +34,282 @@ pub fn parse_rustc_errors(output: &str) -> Vec<ProjectError> {
                 explanation: format!("Rust compiler error {}: {}", code, message),
                 suggested_fixes: Vec::new(),
                 rustc_error: Some(output.to_string()),
+                suggestions: Vec::new(),
+                model_explanation: None,
             }),
         });
     }
     errors
 }
+
+/// How safe it is to apply a suggested replacement without review, as reported by rustc.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Applicability {
+    MachineApplicable,
+    MaybeIncorrect,
+    HasPlaceholders,
+    Unspecified,
+}
+
+/// A replacement of a source range suggested by rustc or clippy.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    /// The help message the replacement belongs to.
+    pub message: String,
+    pub file: String,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub line_start: u32,
+    pub column_start: u32,
+    pub line_end: u32,
+    pub column_end: u32,
+    pub replacement: String,
+    pub applicability: Applicability,
+}
+
+impl Suggestion {
+    pub fn is_machine_applicable(&self) -> bool {
+        self.applicability == Applicability::MachineApplicable
+    }
+}
+
+/// A line of `cargo --message-format=json` output.
+#[derive(Deserialize, Debug)]
+struct CargoMessage {
+    reason: String,
+    #[serde(default)]
+    package_id: Option<String>,
+    #[serde(default)]
+    message: Option<Diagnostic>,
+}
+
+/// A rustc diagnostic, as emitted by `--error-format=json`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Diagnostic {
+    pub message: String,
+    #[serde(default)]
+    pub code: Option<DiagnosticCode>,
+    pub level: String,
+    #[serde(default)]
+    pub spans: Vec<DiagnosticSpan>,
+    #[serde(default)]
+    pub children: Vec<Diagnostic>,
+    #[serde(default)]
+    pub rendered: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DiagnosticCode {
+    /// `E0308`, or a lint name such as `unused_variables` or `clippy::needless_return`.
+    pub code: String,
+    #[serde(default)]
+    pub explanation: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DiagnosticSpan {
+    pub file_name: String,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub line_start: u32,
+    pub line_end: u32,
+    pub column_start: u32,
+    pub column_end: u32,
+    pub is_primary: bool,
+    #[serde(default)]
+    pub text: Vec<SpanLine>,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub suggested_replacement: Option<String>,
+    #[serde(default)]
+    pub suggestion_applicability: Option<Applicability>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SpanLine {
+    pub text: String,
+}
+
+/// Parses `cargo check/build/clippy --message-format=json` output, or bare
+/// `rustc --error-format=json` diagnostics, one JSON object per line.
+/// Errors and warnings with a source location become `ProjectError`s; other
+/// lines (build-script output, artifacts, summaries) are ignored.
+pub fn parse_json_diagnostics(output: &str) -> Vec<ProjectError> {
+    let mut errors = Vec::new();
+    for line in output.lines().map(str::trim).filter(|l| l.starts_with('{')) {
+        let (package, diagnostic) = match serde_json::from_str::<CargoMessage>(line) {
+            Ok(CargoMessage { reason, package_id, message: Some(diagnostic) }) if reason == "compiler-message" => {
+                (package_id.as_deref().and_then(package_name), diagnostic)
+            }
+            Ok(_) => continue,
+            Err(_) => match serde_json::from_str::<Diagnostic>(line) {
+                Ok(diagnostic) => (None, diagnostic),
+                Err(_) => continue,
+            },
+        };
+        if let Some(error) = to_project_error(package, diagnostic) {
+            errors.push(error);
+        }
+    }
+    attach_macro_metadata(errors)
+}
+
+/// Metadata a MontRS proc macro attaches to one of its compile errors.
+#[derive(Deserialize)]
+struct MacroErrorMetadata {
+    error_code: String,
+    explanation: String,
+    #[serde(default)]
+    suggested_fixes: Vec<String>,
+}
+
+/// MontRS proc macros pair each compile error with a deprecation warning at the
+/// same span whose note carries the error's `AgentError` metadata (see
+/// `montrs_core::AGENT_ERROR_NOTE`). Moves that metadata onto the error and
+/// drops the warning.
+fn attach_macro_metadata(errors: Vec<ProjectError>) -> Vec<ProjectError> {
+    let (notes, mut errors): (Vec<_>, Vec<_>) = errors
+        .into_iter()
+        .partition(|e| e.level == "Warning" && e.message.contains(AGENT_ERROR_NOTE));
+    for note in notes {
+        let Some(metadata) = note
+            .message
+            .split_once(AGENT_ERROR_NOTE)
+            .and_then(|(_, json)| serde_json::from_str::<MacroErrorMetadata>(json.trim()).ok())
+        else {
+            continue;
+        };
+        let Some(error) = errors
+            .iter_mut()
+            .find(|e| e.level == "Error" && e.file == note.file && e.line == note.line && e.column == note.column)
+        else {
+            continue;
+        };
+        if let Some(meta) = error.agent_metadata.as_mut() {
+            meta.error_code = metadata.error_code;
+            meta.explanation = metadata.explanation;
+            meta.suggested_fixes.splice(0..0, metadata.suggested_fixes);
+        }
+    }
+    errors
+}
+
+fn to_project_error(package: Option<String>, diagnostic: Diagnostic) -> Option<ProjectError> {
+    let level = match diagnostic.level.as_str() {
+        "error" | "error: internal compiler error" => "Error",
+        "warning" => "Warning",
+        _ => return None,
+    };
+    // Summaries such as "aborting due to 2 previous errors" have no location.
+    let primary = diagnostic.spans.iter().find(|s| s.is_primary)?;
+
+    let code = diagnostic.code.as_ref().map(|c| c.code.clone()).unwrap_or_default();
+    let suggestions = suggestions(&diagnostic);
+    let mut suggested_fixes: Vec<String> = suggestions.iter().map(describe_suggestion).collect();
+    for child in &diagnostic.children {
+        if child.level == "help" && child.spans.iter().all(|s| s.suggested_replacement.is_none()) {
+            suggested_fixes.push(child.message.clone());
+        }
+    }
+    if let Some(url) = lint_url(&code)
+        && !suggested_fixes.iter().any(|f| f.contains("rust-clippy"))
+    {
+        suggested_fixes.push(format!("See {} for details on this lint.", url));
+    }
+
+    Some(ProjectError {
+        package,
+        file: primary.file_name.clone(),
+        line: primary.line_start,
+        column: primary.column_start,
+        message: diagnostic.message.clone(),
+        code_context: primary.text.iter().map(|l| l.text.as_str()).collect::<Vec<_>>().join("\n"),
+        level: level.to_string(),
+        agent_metadata: Some(AgentErrorMetadata {
+            explanation: explanation(&code, &diagnostic),
+            error_code: code,
+            suggested_fixes,
+            rustc_error: diagnostic.rendered.clone(),
+            suggestions,
+            model_explanation: None,
+        }),
+    })
+}
+
+/// Collects the replacements from the diagnostic and its `help` children.
+fn suggestions(diagnostic: &Diagnostic) -> Vec<Suggestion> {
+    std::iter::once(diagnostic)
+        .chain(&diagnostic.children)
+        .flat_map(|d| {
+            d.spans.iter().filter_map(move |span| {
+                Some(Suggestion {
+                    message: d.message.clone(),
+                    file: span.file_name.clone(),
+                    byte_start: span.byte_start,
+                    byte_end: span.byte_end,
+                    line_start: span.line_start,
+                    column_start: span.column_start,
+                    line_end: span.line_end,
+                    column_end: span.column_end,
+                    replacement: span.suggested_replacement.clone()?,
+                    applicability: span.suggestion_applicability.unwrap_or(Applicability::Unspecified),
+                })
+            })
+        })
+        .collect()
+}
+
+fn describe_suggestion(suggestion: &Suggestion) -> String {
+    let action = if suggestion.replacement.is_empty() {
+        "remove the highlighted code".to_string()
+    } else {
+        format!("replace with `{}`", suggestion.replacement)
+    };
+    let note = if suggestion.is_machine_applicable() { " (machine-applicable)" } else { "" };
+    format!(
+        "{}: {} at {}:{}:{}{}",
+        suggestion.message, action, suggestion.file, suggestion.line_start, suggestion.column_start, note
+    )
+}
+
+fn explanation(code: &str, diagnostic: &Diagnostic) -> String {
+    if let Some(lint) = code.strip_prefix("clippy::") {
+        return format!("Clippy lint `{}`: {}", lint, diagnostic.message);
+    }
+    // The first paragraph of `rustc --explain` is a one-line summary.
+    let summary = diagnostic
+        .code
+        .as_ref()
+        .and_then(|c| c.explanation.as_deref())
+        .and_then(|e| e.trim().lines().next())
+        .map(|l| format!(" {}", l.trim()))
+        .unwrap_or_default();
+    match code {
+        "" => format!("Rust compiler {}: {}", diagnostic.level, diagnostic.message),
+        c if c.starts_with('E') => format!("Rust compiler error {}: {}.{}", c, diagnostic.message, summary),
+        lint => format!("Rust lint `{}`: {}", lint, diagnostic.message),
+    }
+}
+
+fn lint_url(code: &str) -> Option<String> {
+    code.strip_prefix("clippy::")
+        .map(|lint| format!("https://rust-lang.github.io/rust-clippy/master/index.html#{}", lint))
+}
+
+/// Extracts the crate name from a cargo package id, in either the
+/// `path+file:///…/core#montrs-core@0.1.0` or the `montrs-core 0.1.0 (…)` form.
+fn package_name(package_id: &str) -> Option<String> {
+    match package_id.rsplit_once('#') {
+        Some((url, spec)) => {
+            let name = spec.split('@').next().unwrap_or(spec);
+            // `…/core#0.1.0` omits the name when it matches the directory.
+            if name.chars().next().is_some_and(|c| c.is_ascii_digit()) {
+                url.rsplit('/').next().map(str::to_string)
+            } else {
+                Some(name.to_string())
+            }
+        }
+        None => package_id.split_whitespace().next().map(str::to_string),
+    }
+}
@@ -0,0 +1,232 @@
+//! Model-written explanations of tracked errors.
+//!
+//! [`AgentManager::explain_context`] gathers what a model needs to reason about
+//! an error: the record, the surrounding source, the invariants of the package
+//! it occurred in and the documentation snippets that mention it. [`explain`]
+//! sends that to an [`LlmProvider`] and the answer is stored in the record's
+//! `agent_metadata.model_explanation`, so later sessions can reuse it.
+
+use crate::llm::{ChatMessage, CompletionOptions, LlmError, LlmProvider};
+use crate::{AgentErrorMetadata, AgentManager, ErrorRecord};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::fs;
+
+/// Lines of source shown on each side of the error line.
+const CODE_RADIUS: usize = 8;
+/// Documentation snippets included in the prompt.
+const MAX_DOCS: usize = 3;
+/// Characters kept from each invariants file or documentation snippet.
+const MAX_EXCERPT: usize = 3000;
+
+/// An explanation produced by a language model.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ModelExplanation {
+    pub explanation: String,
+    #[serde(default)]
+    pub suggested_fixes: Vec<String>,
+    /// The model that wrote it.
+    pub model: String,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Everything sent to the model about one error.
+#[derive(Debug, Clone)]
+pub struct ExplainContext {
+    pub record: ErrorRecord,
+    /// Numbered source lines around the error, when the file is readable.
+    pub code: Option<String>,
+    /// `(package, invariants)` for the package the error occurred in.
+    pub invariants: Vec<(String, String)>,
+    /// `(key, excerpt)` of the most relevant documentation snippets.
+    pub docs: Vec<(String, String)>,
+}
+
+impl ExplainContext {
+    /// The conversation sent to the model.
+    pub fn messages(&self) -> Vec<ChatMessage> {
+        let detail = &self.record.detail;
+        let mut prompt = String::new();
+        let _ = writeln!(prompt, "## Error\n\n{} at {}:{}:{}", detail.level, detail.file, detail.line, detail.column);
+        if let Some(package) = &detail.package {
+            let _ = writeln!(prompt, "Package: {}", package);
+        }
+        let _ = writeln!(prompt, "Message: {}", detail.message);
+        if let Some(meta) = &detail.agent_metadata {
+            if !meta.error_code.is_empty() {
+                let _ = writeln!(prompt, "Code: {}", meta.error_code);
+            }
+            if let Some(rendered) = &meta.rustc_error {
+                let _ = writeln!(prompt, "\n```text\n{}\n```", truncate(rendered, MAX_EXCERPT));
+            }
+            if !meta.suggested_fixes.is_empty() {
+                let _ = writeln!(prompt, "\nCompiler hints:");
+                for fix in &meta.suggested_fixes {
+                    let _ = writeln!(prompt, "- {}", fix);
+                }
+            }
+        }
+        if let Some(code) = &self.code {
+            let _ = writeln!(prompt, "\n## Code\n\n```rust\n{}```", code);
+        }
+        for (package, invariants) in &self.invariants {
+            let _ = writeln!(prompt, "\n## Invariants of `{}`\n\n{}", package, invariants);
+        }
+        for (key, excerpt) in &self.docs {
+            let _ = writeln!(prompt, "\n## Documentation: {}\n\n{}", key, excerpt);
+        }
+        if !self.record.history.is_empty() {
+            let _ = writeln!(prompt, "\n## Earlier attempts");
+            for entry in &self.record.history {
+                let _ = writeln!(prompt, "- {}", entry.message);
+            }
+        }
+
+        vec![
+            ChatMessage::system(
+                "You help developers fix errors in MontRS projects (Rust, Leptos, plates, loaders and actions). \
+                 Explain the root cause of the error in a few sentences, then give concrete fixes that respect the \
+                 listed invariants. Reply with only a JSON object: \
+                 {\"explanation\": string, \"suggested_fixes\": [string]}.",
+            ),
+            ChatMessage::user(prompt),
+        ]
+    }
+}
+
+/// Asks `provider` to explain the error in `context`.
+pub async fn explain(provider: &dyn LlmProvider, context: &ExplainContext) -> Result<ModelExplanation, LlmError> {
+    let options = CompletionOptions::default().with_temperature(0.2);
+    let response = provider.chat(&context.messages(), &options).await?;
+    let (explanation, suggested_fixes) = parse_reply(&response.content);
+    Ok(ModelExplanation {
+        explanation,
+        suggested_fixes,
+        model: if response.model.is_empty() { provider.name().to_string() } else { response.model },
+        generated_at: Utc::now(),
+    })
+}
+
+/// Reads the JSON object the model was asked for, tolerating code fences and
+/// surrounding prose. A reply without one becomes a plain explanation.
+pub fn parse_reply(reply: &str) -> (String, Vec<String>) {
+    #[derive(Deserialize)]
+    struct Reply {
+        explanation: String,
+        #[serde(default)]
+        suggested_fixes: Vec<String>,
+    }
+
+    let parsed = reply
+        .find('{')
+        .zip(reply.rfind('}'))
+        .filter(|(start, end)| start < end)
+        .and_then(|(start, end)| serde_json::from_str::<Reply>(&reply[start..=end]).ok());
+    match parsed {
+        Some(reply) => (reply.explanation, reply.suggested_fixes),
+        None => (reply.trim().to_string(), Vec::new()),
+    }
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => format!("{}\n[...]", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Lowercase words of four or more characters, used to rank snippets.
+fn terms(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|w| w.len() >= 4)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+impl AgentManager {
+    /// Gathers the code, invariants and documentation relevant to error `id`.
+    pub fn explain_context(&self, id: &str) -> Result<ExplainContext> {
+        let redactor = self.redactor()?;
+        let mut redactions = crate::redact::Redactions::default();
+        let mut record = self.find_error(id)?;
+        record.detail.message = redactor.redact(&record.detail.message, &mut redactions).into_owned();
+        record.detail.code_context = redactor.redact_file(&record.detail.file, &record.detail.code_context, &mut redactions);
+        if let Some(rendered) = record.detail.agent_metadata.as_mut().and_then(|meta| meta.rustc_error.as_mut()) {
+            *rendered = redactor.redact(rendered, &mut redactions).into_owned();
+        }
+        for entry in &mut record.history {
+            entry.message = redactor.redact(&entry.message, &mut redactions).into_owned();
+        }
+        let detail = &record.detail;
+
+        let code = fs::read_to_string(self.root_path.join(&detail.file)).ok().map(|source| {
+            let line = detail.line as usize;
+            let start = line.saturating_sub(CODE_RADIUS + 1);
+            source
+                .lines()
+                .enumerate()
+                .skip(start)
+                .take(CODE_RADIUS * 2 + 1)
+                .map(|(i, text)| format!("{}{:>5} | {}\n", if i + 1 == line { ">" } else { " " }, i + 1, text))
+                .collect::<String>()
+        });
+        let code = code.map(|code| redactor.redact_file(&detail.file, &code, &mut redactions));
+
+        // Snapshots written with `--no-redact` are redacted again here.
+        let mut snapshot = self.read_snapshot().unwrap_or_else(|_| self.generate_framework_snapshot());
+        redactor.redact_snapshot(&mut snapshot);
+        // Errors outside a package are checked against the core invariants.
+        let package = detail.package.clone().unwrap_or_else(|| "core".to_string());
+        let invariants = snapshot
+            .packages
+            .iter()
+            .filter(|p| p.name == package)
+            .filter_map(|p| Some((p.name.clone(), truncate(p.invariants.as_ref()?, MAX_EXCERPT))))
+            .collect();
+
+        let mut query = terms(&detail.message);
+        query.extend(terms(&detail.file));
+        if let Some(meta) = &detail.agent_metadata {
+            query.extend(terms(&meta.error_code));
+        }
+        let mut ranked: Vec<(usize, &String, &String)> = snapshot
+            .documentation_snippets
+            .iter()
+            .map(|(key, content)| {
+                let words = terms(content);
+                let mut score = query.iter().filter(|t| words.contains(*t)).count();
+                if key.contains(&package) {
+                    score += 2;
+                }
+                (score, key, content)
+            })
+            .filter(|(score, _, _)| *score > 0)
+            .collect();
+        ranked.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+        let docs = ranked
+            .into_iter()
+            .take(MAX_DOCS)
+            .map(|(_, key, content)| (key.clone(), truncate(content, MAX_EXCERPT)))
+            .collect();
+
+        Ok(ExplainContext { record, code, invariants, docs })
+    }
+
+    /// Stores a model explanation on the latest version of error `id`.
+    pub fn record_explanation(&self, id: &str, explanation: ModelExplanation) -> Result<()> {
+        let mut record = self.find_error(id)?;
+        let metadata = record.detail.agent_metadata.get_or_insert_with(|| AgentErrorMetadata {
+            error_code: String::new(),
+            explanation: String::new(),
+            suggested_fixes: Vec::new(),
+            rustc_error: None,
+            suggestions: Vec::new(),
+            model_explanation: None,
+        });
+        metadata.model_explanation = Some(explanation);
+        self.write_error_record(&record)
+    }
+}
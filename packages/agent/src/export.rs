@@ -0,0 +1,244 @@
+//! Human- and SQL-friendly exports of the agent snapshot.
+//!
+//! `agent.md` renders the snapshot as an architecture document with route and
+//! plate tables. `agent.db` (behind the `sqlite` feature) loads the same data,
+//! plus tracked errors, into tables that can be queried with any SQLite client:
+//!
+//! ```sql
+//! SELECT path, description FROM routes WHERE kind = 'action';
+//! SELECT file, COUNT(*) FROM errors WHERE status = 'Pending' GROUP BY file;
+//! ```
+
+use crate::AgentSnapshot;
+use std::fmt::Write;
+
+pub const SNAPSHOT_MARKDOWN: &str = "agent.md";
+pub const SNAPSHOT_SQLITE: &str = "agent.db";
+
+/// Renders the snapshot as Markdown.
+pub fn to_markdown(snapshot: &AgentSnapshot) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# {}\n", snapshot.project_name);
+    let _ = writeln!(
+        out,
+        "MontRS {} · generated {}\n",
+        snapshot.framework_version,
+        snapshot.timestamp.format("%Y-%m-%d %H:%M UTC")
+    );
+    if let Some(entry) = &snapshot.agent_entry_point {
+        let _ = writeln!(out, "Agent entry point: `{}`\n", entry);
+    }
+
+    if !snapshot.packages.is_empty() {
+        out.push_str("## Packages\n\n| Package | Path | Depends on | Description |\n| --- | --- | --- | --- |\n");
+        for package in &snapshot.packages {
+            row(&mut out, &[
+                &package.name,
+                &format!("`{}`", package.path),
+                &package.dependencies.join(", "),
+                package.description.as_deref().unwrap_or_default(),
+            ]);
+        }
+        out.push('\n');
+    }
+
+    out.push_str("## Plates\n\n");
+    if snapshot.plates.is_empty() {
+        out.push_str("No plates registered.\n\n");
+    } else {
+        out.push_str("| Plate | Depends on | Description |\n| --- | --- | --- |\n");
+        for plate in &snapshot.plates {
+            row(&mut out, &[&plate.name, &plate.dependencies.join(", "), &plate.description]);
+        }
+        out.push('\n');
+    }
+
+    out.push_str("## Routes\n\n");
+    if snapshot.routes.is_empty() {
+        out.push_str("No routes registered.\n\n");
+    } else {
+        out.push_str("| Path | Kind | Description |\n| --- | --- | --- |\n");
+        for route in &snapshot.routes {
+            row(&mut out, &[&format!("`{}`", route.path), &route.kind, &route.description]);
+        }
+        out.push('\n');
+    }
+
+    if !snapshot.deprecations.is_empty() {
+        out.push_str("## Deprecations\n\n| Kind | Name | Sunset | Replacement |\n| --- | --- | --- | --- |\n");
+        for deprecation in &snapshot.deprecations {
+            let sunset = match (&deprecation.deprecation.sunset, deprecation.past_sunset) {
+                (Some(date), true) => format!("{} (passed)", date),
+                (Some(date), false) => date.to_string(),
+                (None, _) => String::new(),
+            };
+            row(&mut out, &[
+                &deprecation.kind,
+                &deprecation.name,
+                &sunset,
+                deprecation.deprecation.replacement.as_deref().unwrap_or_default(),
+            ]);
+        }
+        out.push('\n');
+    }
+
+    if !snapshot.authorization.is_empty() {
+        out.push_str("## Authorization\n\n| Route | Requires |\n| --- | --- |\n");
+        for access in &snapshot.authorization {
+            let requires: Vec<String> = access.requires.iter().map(|p| format!("`{}`", p)).collect();
+            let requires = if requires.is_empty() { "public".to_string() } else { requires.join(", ") };
+            row(&mut out, &[&format!("`{}`", access.route), &requires]);
+        }
+        out.push('\n');
+    }
+
+    if let Some(summary) = &snapshot.preset {
+        let preset = &summary.preset;
+        let origins = if preset.cors.allowed_origins.is_empty() {
+            "same origin".to_string()
+        } else {
+            preset.cors.allowed_origins.join(", ")
+        };
+        let _ = writeln!(out, "## Preset\n\nServing defaults for the `{}` target.\n", summary.target);
+        out.push_str("| Setting | Value |\n| --- | --- |\n");
+        row(&mut out, &["CORS origins", &origins]);
+        let headers: Vec<String> = preset.security.headers().into_iter().map(|(name, _)| format!("`{}`", name)).collect();
+        row(&mut out, &["Security headers", &headers.join(", ")]);
+        row(&mut out, &["Rate limiter", &format!("{} ({} req/s)", preset.limits.limiter, preset.limits.requests_per_second)]);
+        row(&mut out, &["Max body", &format!("{} bytes", preset.limits.max_body_bytes)]);
+        row(&mut out, &["Serves assets", if preset.assets.serve { "yes" } else { "no" }]);
+        out.push('\n');
+    }
+
+    if let Some(boot) = &snapshot.boot {
+        let _ = writeln!(out, "## Boot\n\nLast boot took {:.1} ms.\n", boot.total_us as f64 / 1000.0);
+    }
+
+    if !snapshot.structure.is_empty() {
+        out.push_str("## Files\n\n");
+        for file in &snapshot.structure {
+            match &file.description {
+                Some(description) => {
+                    let _ = writeln!(out, "- `{}`: {}", file.path, description);
+                }
+                None => {
+                    let _ = writeln!(out, "- `{}`", file.path);
+                }
+            }
+        }
+    }
+    out
+}
+
+fn row(out: &mut String, cells: &[&str]) {
+    out.push('|');
+    for cell in cells {
+        let cell = cell.replace('|', "\\|").replace('\n', " ");
+        let _ = write!(out, " {} |", cell.trim());
+    }
+    out.push('\n');
+}
+
+/// Writes the snapshot and tracked errors to a fresh SQLite database at `path`.
+#[cfg(feature = "sqlite")]
+pub fn write_sqlite(snapshot: &AgentSnapshot, errors: &[crate::ConsolidatedError], path: &std::path::Path) -> anyhow::Result<()> {
+    use rusqlite::{params, Connection};
+
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let mut conn = Connection::open(path)?;
+    let tx = conn.transaction()?;
+    tx.execute_batch(
+        "CREATE TABLE meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+         CREATE TABLE files (path TEXT PRIMARY KEY, description TEXT);
+         CREATE TABLE packages (name TEXT NOT NULL, path TEXT NOT NULL, description TEXT, invariants TEXT, dependencies TEXT NOT NULL);
+         CREATE TABLE plates (name TEXT NOT NULL, description TEXT NOT NULL, dependencies TEXT NOT NULL, metadata TEXT NOT NULL);
+         CREATE TABLE routes (
+             path TEXT NOT NULL, kind TEXT NOT NULL, description TEXT NOT NULL,
+             params_schema TEXT, loader_output_schema TEXT, action_input_schema TEXT, action_output_schema TEXT,
+             metadata TEXT NOT NULL
+         );
+         CREATE TABLE errors (
+             id TEXT PRIMARY KEY, package TEXT, file TEXT NOT NULL, line INTEGER NOT NULL, column INTEGER NOT NULL,
+             level TEXT NOT NULL, message TEXT NOT NULL, status TEXT NOT NULL, timestamp TEXT NOT NULL
+         );
+         CREATE INDEX routes_path ON routes (path);
+         CREATE INDEX errors_file ON errors (file);",
+    )?;
+
+    let json = |value: &Option<serde_json::Value>| value.as_ref().map(|v| v.to_string());
+    {
+        let mut meta = tx.prepare("INSERT INTO meta (key, value) VALUES (?1, ?2)")?;
+        meta.execute(params!["project_name", snapshot.project_name])?;
+        meta.execute(params!["framework_version", snapshot.framework_version])?;
+        meta.execute(params!["timestamp", snapshot.timestamp.to_rfc3339()])?;
+
+        let mut files = tx.prepare("INSERT OR REPLACE INTO files (path, description) VALUES (?1, ?2)")?;
+        for file in &snapshot.structure {
+            files.execute(params![file.path, file.description])?;
+        }
+
+        let mut packages = tx.prepare(
+            "INSERT INTO packages (name, path, description, invariants, dependencies) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for package in &snapshot.packages {
+            packages.execute(params![
+                package.name,
+                package.path,
+                package.description,
+                package.invariants,
+                serde_json::to_string(&package.dependencies)?,
+            ])?;
+        }
+
+        let mut plates =
+            tx.prepare("INSERT INTO plates (name, description, dependencies, metadata) VALUES (?1, ?2, ?3, ?4)")?;
+        for plate in &snapshot.plates {
+            plates.execute(params![
+                plate.name,
+                plate.description,
+                serde_json::to_string(&plate.dependencies)?,
+                serde_json::to_string(&plate.metadata)?,
+            ])?;
+        }
+
+        let mut routes = tx.prepare(
+            "INSERT INTO routes (path, kind, description, params_schema, loader_output_schema,
+                                 action_input_schema, action_output_schema, metadata)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )?;
+        for route in &snapshot.routes {
+            routes.execute(params![
+                route.path,
+                route.kind,
+                route.description,
+                json(&route.params_schema),
+                json(&route.loader_output_schema.clone().or_else(|| route.output_schema.clone())),
+                json(&route.action_input_schema.clone().or_else(|| route.input_schema.clone())),
+                json(&route.action_output_schema),
+                serde_json::to_string(&route.metadata)?,
+            ])?;
+        }
+
+        let mut rows = tx.prepare(
+            "INSERT OR REPLACE INTO errors (id, package, file, line, column, level, message, status, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        )?;
+        for error in errors {
+            rows.execute(params![
+                error.id,
+                error.package,
+                error.file,
+                error.line,
+                error.column,
+                error.level,
+                error.message,
+                error.status,
+                error.timestamp.to_rfc3339(),
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
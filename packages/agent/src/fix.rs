@@ -0,0 +1,244 @@
+//! Applies the fixes carried by error records.
+//!
+//! A record can be fixed from the machine-applicable replacements rustc or clippy
+//! attached to it, or from a diff stored in its history. `plan_fix` works out the
+//! patch without touching the tree so it can be previewed; `FixPlan::apply`
+//! writes it.
+
+use crate::error_parser::Suggestion;
+use crate::{AgentManager, ErrorRecord};
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Lines of unchanged context around each hunk in rendered patches.
+const CONTEXT_LINES: usize = 3;
+
+/// The new contents of one file after applying suggestions.
+#[derive(Debug, Clone)]
+pub struct FilePatch {
+    pub file: String,
+    pub original: String,
+    pub patched: String,
+}
+
+impl FilePatch {
+    /// Renders the change as a unified diff with a single hunk.
+    pub fn diff(&self) -> String {
+        unified_diff(&self.file, &self.original, &self.patched)
+    }
+}
+
+/// A fix ready to be previewed or applied.
+#[derive(Debug, Clone)]
+pub enum FixPlan {
+    /// Replacements suggested by the compiler, already applied in memory.
+    Suggestions {
+        /// The help message of the suggestion being applied.
+        message: String,
+        patches: Vec<FilePatch>,
+    },
+    /// A patch from the record's history, applied with `git apply`.
+    Diff(String),
+}
+
+impl FixPlan {
+    /// A one-line description of where the fix comes from.
+    pub fn summary(&self) -> String {
+        match self {
+            FixPlan::Suggestions { message, patches } => {
+                format!("compiler suggestion '{}' ({} file(s))", message, patches.len())
+            }
+            FixPlan::Diff(_) => "stored diff from the error history".to_string(),
+        }
+    }
+
+    /// The fix as a unified diff.
+    pub fn patch(&self) -> String {
+        match self {
+            FixPlan::Suggestions { patches, .. } => patches.iter().map(FilePatch::diff).collect(),
+            FixPlan::Diff(diff) => diff.clone(),
+        }
+    }
+
+    /// Writes the fix to the tree under `root`. Files changed since the plan was
+    /// made are left alone and reported as an error.
+    pub fn apply(&self, root: &Path) -> Result<()> {
+        match self {
+            FixPlan::Suggestions { patches, .. } => {
+                for patch in patches {
+                    let path = root.join(&patch.file);
+                    if fs::read_to_string(&path)? != patch.original {
+                        anyhow::bail!("{} changed since the fix was planned; re-run the check", patch.file);
+                    }
+                }
+                for patch in patches {
+                    fs::write(root.join(&patch.file), &patch.patched)?;
+                }
+                Ok(())
+            }
+            FixPlan::Diff(diff) => git_apply_checked(root, diff),
+        }
+    }
+}
+
+/// Works out how to fix `record` in the project of `manager`. Machine-applicable
+/// suggestions win over a stored diff. Returns `None` when the record carries
+/// neither.
+pub fn plan_fix(manager: &AgentManager, record: &ErrorRecord) -> Result<Option<FixPlan>> {
+    let root = manager.root_path.as_path();
+    let suggestions = record
+        .detail
+        .agent_metadata
+        .as_ref()
+        .map(|m| m.suggestions.as_slice())
+        .unwrap_or_default();
+    if let Some((message, edits)) = first_applicable(suggestions) {
+        return Ok(Some(FixPlan::Suggestions {
+            message: message.to_string(),
+            patches: apply_suggestions(root, &edits)?,
+        }));
+    }
+    for version in record.history.iter().rev() {
+        if let Some(diff) = manager.version_patch(version)?.filter(|d| !d.trim().is_empty()) {
+            return Ok(Some(FixPlan::Diff(diff)));
+        }
+    }
+    Ok(None)
+}
+
+/// Each help message is one alternative fix, possibly spanning several edits.
+/// Picks the first alternative whose edits are all machine-applicable.
+fn first_applicable(suggestions: &[Suggestion]) -> Option<(&str, Vec<&Suggestion>)> {
+    let mut messages: Vec<&str> = suggestions.iter().map(|s| s.message.as_str()).collect();
+    messages.dedup();
+    messages.into_iter().find_map(|message| {
+        let edits: Vec<&Suggestion> = suggestions.iter().filter(|s| s.message == message).collect();
+        edits.iter().all(|s| s.is_machine_applicable()).then_some((message, edits))
+    })
+}
+
+/// Applies byte-range replacements, grouped per file. Fails if an edit no longer
+/// lines up with the line and column rustc reported, or if edits overlap.
+pub fn apply_suggestions(root: &Path, suggestions: &[&Suggestion]) -> Result<Vec<FilePatch>> {
+    let mut files: Vec<&str> = suggestions.iter().map(|s| s.file.as_str()).collect();
+    files.sort();
+    files.dedup();
+
+    let mut patches = Vec::new();
+    for file in files {
+        let original = fs::read_to_string(root.join(file)).with_context(|| format!("Failed to read {}", file))?;
+        let mut edits: Vec<&Suggestion> = suggestions.iter().copied().filter(|s| s.file == file).collect();
+        edits.sort_by_key(|s| (s.byte_start, s.byte_end));
+        for pair in edits.windows(2) {
+            if pair[1].byte_start < pair[0].byte_end {
+                anyhow::bail!("Overlapping suggestions in {} at bytes {}..{}", file, pair[1].byte_start, pair[0].byte_end);
+            }
+        }
+
+        let mut patched = original.clone();
+        for edit in edits.iter().rev() {
+            if !original.is_char_boundary(edit.byte_start)
+                || !original.is_char_boundary(edit.byte_end)
+                || edit.byte_end > original.len()
+                || position(&original, edit.byte_start) != (edit.line_start, edit.column_start)
+            {
+                anyhow::bail!(
+                    "{} changed since the error was recorded (expected the edit at {}:{})",
+                    file,
+                    edit.line_start,
+                    edit.column_start
+                );
+            }
+            patched.replace_range(edit.byte_start..edit.byte_end, &edit.replacement);
+        }
+        patches.push(FilePatch {
+            file: file.to_string(),
+            original,
+            patched,
+        });
+    }
+    Ok(patches)
+}
+
+/// 1-based line and character column of a byte offset, as rustc reports them.
+fn position(text: &str, offset: usize) -> (u32, u32) {
+    let before = &text[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+    (line as u32, column as u32)
+}
+
+fn unified_diff(file: &str, original: &str, patched: &str) -> String {
+    let old: Vec<&str> = original.split_inclusive('\n').collect();
+    let new: Vec<&str> = patched.split_inclusive('\n').collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if prefix == old.len() && prefix == new.len() {
+        return String::new();
+    }
+
+    let start = prefix.saturating_sub(CONTEXT_LINES);
+    let old_end = (old.len() - suffix + CONTEXT_LINES).min(old.len());
+    let new_end = (new.len() - suffix + CONTEXT_LINES).min(new.len());
+    let mut out = format!("--- a/{file}\n+++ b/{file}\n");
+    out.push_str(&format!(
+        "@@ -{},{} +{},{} @@\n",
+        start + 1,
+        old_end - start,
+        start + 1,
+        new_end - start
+    ));
+    let line = |out: &mut String, mark: char, text: &str| {
+        out.push(mark);
+        out.push_str(text);
+        if !text.ends_with('\n') {
+            out.push_str("\n\\ No newline at end of file\n");
+        }
+    };
+    for text in &old[start..prefix] {
+        line(&mut out, ' ', text);
+    }
+    for text in &old[prefix..old.len() - suffix] {
+        line(&mut out, '-', text);
+    }
+    for text in &new[prefix..new.len() - suffix] {
+        line(&mut out, '+', text);
+    }
+    for text in &old[old.len() - suffix..old_end] {
+        line(&mut out, ' ', text);
+    }
+    out
+}
+
+/// Applies `diff` to the tree under `root` only if all of it applies.
+pub(crate) fn git_apply_checked(root: &Path, diff: &str) -> Result<()> {
+    git_apply(root, diff, false).and_then(|_| git_apply(root, diff, true))
+}
+
+fn git_apply(root: &Path, diff: &str, write: bool) -> Result<()> {
+    let mut cmd = Command::new("git");
+    cmd.arg("apply").current_dir(root);
+    if !write {
+        cmd.arg("--check");
+    }
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run git apply")?;
+    child.stdin.take().context("git apply has no stdin")?.write_all(diff.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!("git apply failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
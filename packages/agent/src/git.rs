@@ -0,0 +1,159 @@
+//! Git metadata for error records.
+//!
+//! Everything here shells out to `git` in the project root and degrades to
+//! `None` or an empty list outside a repository, so error tracking keeps working
+//! in exported trees and CI checkouts without history.
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+/// Where an error was first seen in version control.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct GitMetadata {
+    /// `HEAD` when the error was first reported.
+    pub first_seen_commit: Option<String>,
+    /// The checked-out branch, or `None` on a detached `HEAD`.
+    pub branch: Option<String>,
+    /// Who last touched the offending line.
+    pub blame: Option<BlameInfo>,
+}
+
+/// `git blame` for a single line.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BlameInfo {
+    pub line: u32,
+    /// The commit that last changed the line. All zeros for uncommitted edits.
+    pub commit: String,
+    pub author: String,
+    pub author_email: String,
+    pub author_time: DateTime<Utc>,
+    /// Subject line of the commit.
+    pub summary: String,
+}
+
+impl BlameInfo {
+    /// True when the line has changes that are not committed yet.
+    pub fn is_uncommitted(&self) -> bool {
+        self.commit.bytes().all(|b| b == b'0')
+    }
+}
+
+/// A commit as listed by [`commits_since`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitInfo {
+    pub hash: String,
+    /// The full commit message, subject and body.
+    pub message: String,
+}
+
+impl CommitInfo {
+    pub fn subject(&self) -> &str {
+        self.message.lines().next().unwrap_or_default()
+    }
+}
+
+/// Collects the metadata for an error at `file:line`. Blame is skipped for
+/// errors without a location.
+pub fn capture(root: &Path, file: &str, line: u32) -> Option<GitMetadata> {
+    let commit = head_commit(root);
+    let branch = current_branch(root);
+    if commit.is_none() && branch.is_none() {
+        return None;
+    }
+    let blame = (line > 0 && file != "unknown").then(|| blame_line(root, file, line)).flatten();
+    Some(GitMetadata {
+        first_seen_commit: commit,
+        branch,
+        blame,
+    })
+}
+
+pub fn head_commit(root: &Path) -> Option<String> {
+    git(root, &["rev-parse", "--verify", "-q", "HEAD"])
+}
+
+pub fn current_branch(root: &Path) -> Option<String> {
+    git(root, &["symbolic-ref", "--short", "-q", "HEAD"])
+}
+
+/// Runs `git blame --porcelain` on one line of `file`, relative to `root`.
+pub fn blame_line(root: &Path, file: &str, line: u32) -> Option<BlameInfo> {
+    let range = format!("{},{}", line, line);
+    let output = git(root, &["blame", "--porcelain", "-L", &range, "--", file])?;
+    parse_blame(&output, line)
+}
+
+/// Parses the header of a single-line `git blame --porcelain` block.
+pub fn parse_blame(output: &str, line: u32) -> Option<BlameInfo> {
+    let mut lines = output.lines();
+    let commit = lines.next()?.split_whitespace().next()?.to_string();
+    let (mut author, mut author_email, mut author_time, mut summary) = (String::new(), String::new(), 0, String::new());
+    for header in lines.take_while(|l| !l.starts_with('\t')) {
+        let (key, value) = header.split_once(' ').unwrap_or((header, ""));
+        match key {
+            "author" => author = value.to_string(),
+            "author-mail" => author_email = value.trim_matches(['<', '>']).to_string(),
+            "author-time" => author_time = value.parse().unwrap_or(0),
+            "summary" => summary = value.to_string(),
+            _ => {}
+        }
+    }
+    Some(BlameInfo {
+        line,
+        commit,
+        author,
+        author_email,
+        author_time: Utc.timestamp_opt(author_time, 0).single()?,
+        summary,
+    })
+}
+
+/// Commits reachable from `HEAD` and committed at or after `since`, newest first.
+pub fn commits_since(root: &Path, since: DateTime<Utc>) -> Vec<CommitInfo> {
+    let since = format!("--since={}", since.timestamp());
+    git(root, &["log", &since, "--format=%H%x1f%B%x1e"])
+        .map(|log| parse_log(&log))
+        .unwrap_or_default()
+}
+
+/// Parses `git log --format=%H%x1f%B%x1e` output.
+pub fn parse_log(log: &str) -> Vec<CommitInfo> {
+    log.split('\x1e')
+        .filter_map(|entry| {
+            let (hash, message) = entry.split_once('\x1f')?;
+            Some(CommitInfo {
+                hash: hash.trim().to_string(),
+                message: message.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The patch introduced by `commit`, without the commit header.
+pub fn commit_patch(root: &Path, commit: &str) -> Option<String> {
+    git(root, &["show", "--format=", commit])
+}
+
+/// Files under `root` changed since `base`: committed, staged and unstaged
+/// changes, plus untracked files. Paths are relative to `root`. `None` when
+/// `root` is not in a repository or `base` does not exist.
+pub fn changed_files(root: &Path, base: &str) -> Option<Vec<String>> {
+    git(root, &["rev-parse", "--verify", "-q", base])?;
+    let diff = git(root, &["diff", "--name-only", "--relative", base, "--"]).unwrap_or_default();
+    let untracked = git(root, &["ls-files", "--others", "--exclude-standard"]).unwrap_or_default();
+    let mut files: Vec<String> = diff.lines().chain(untracked.lines()).map(String::from).collect();
+    files.sort();
+    files.dedup();
+    Some(files)
+}
+
+fn git(root: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).current_dir(root).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let out = String::from_utf8_lossy(&output.stdout).trim_end().to_string();
+    (!out.is_empty()).then_some(out)
+}
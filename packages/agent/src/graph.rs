@@ -0,0 +1,478 @@
+//! Architecture graph of packages and plates.
+//!
+//! Builds a dependency graph from an `AgentSnapshot`, detects cycles and
+//! unusually heavy nodes, diffs two snapshots, and renders the result as
+//! DOT, Mermaid, or a self-contained SVG.
+
+use crate::AgentSnapshot;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+
+/// The kind of node in the architecture graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    Package,
+    Plate,
+}
+
+impl NodeKind {
+    fn prefix(self) -> &'static str {
+        match self {
+            NodeKind::Package => "package",
+            NodeKind::Plate => "plate",
+        }
+    }
+}
+
+/// A node identifier: its kind plus its name.
+pub type NodeId = (NodeKind, String);
+
+/// A directed dependency graph where an edge `a -> b` means "a depends on b".
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchitectureGraph {
+    pub nodes: BTreeSet<NodeId>,
+    pub edges: BTreeSet<(NodeId, NodeId)>,
+}
+
+/// Output format for rendered graphs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+    Svg,
+}
+
+impl std::str::FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dot" | "graphviz" => Ok(GraphFormat::Dot),
+            "mermaid" | "mmd" => Ok(GraphFormat::Mermaid),
+            "svg" => Ok(GraphFormat::Svg),
+            other => Err(format!("Unknown graph format '{}' (expected dot, mermaid, or svg)", other)),
+        }
+    }
+}
+
+/// Nodes and edges that changed between two graphs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphDiff {
+    pub added_nodes: BTreeSet<NodeId>,
+    pub removed_nodes: BTreeSet<NodeId>,
+    pub added_edges: BTreeSet<(NodeId, NodeId)>,
+    pub removed_edges: BTreeSet<(NodeId, NodeId)>,
+}
+
+impl GraphDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+    }
+
+    /// A short human-readable summary of the changes.
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        for (k, n) in &self.added_nodes {
+            let _ = writeln!(out, "+ {} {}", k.prefix(), n);
+        }
+        for (k, n) in &self.removed_nodes {
+            let _ = writeln!(out, "- {} {}", k.prefix(), n);
+        }
+        for ((_, a), (_, b)) in &self.added_edges {
+            let _ = writeln!(out, "+ {} -> {}", a, b);
+        }
+        for ((_, a), (_, b)) in &self.removed_edges {
+            let _ = writeln!(out, "- {} -> {}", a, b);
+        }
+        if out.is_empty() {
+            out.push_str("No architectural changes.\n");
+        }
+        out
+    }
+}
+
+impl ArchitectureGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds the graph from snapshot packages and plates.
+    pub fn from_snapshot(snapshot: &AgentSnapshot) -> Self {
+        let mut graph = Self::new();
+        for pkg in &snapshot.packages {
+            graph.add_node(NodeKind::Package, &pkg.name);
+            for dep in &pkg.dependencies {
+                graph.add_edge((NodeKind::Package, pkg.name.clone()), (NodeKind::Package, dep.clone()));
+            }
+        }
+        for plate in &snapshot.plates {
+            graph.add_node(NodeKind::Plate, &plate.name);
+            for dep in &plate.dependencies {
+                graph.add_edge((NodeKind::Plate, plate.name.clone()), (NodeKind::Plate, dep.clone()));
+            }
+        }
+        graph
+    }
+
+    pub fn add_node(&mut self, kind: NodeKind, name: &str) {
+        self.nodes.insert((kind, name.to_string()));
+    }
+
+    /// Adds an edge, inserting both endpoints if needed.
+    pub fn add_edge(&mut self, from: NodeId, to: NodeId) {
+        self.nodes.insert(from.clone());
+        self.nodes.insert(to.clone());
+        self.edges.insert((from, to));
+    }
+
+    /// Returns the strongly connected components that form dependency cycles.
+    pub fn cycles(&self) -> Vec<Vec<NodeId>> {
+        let nodes: Vec<&NodeId> = self.nodes.iter().collect();
+        let index_of: HashMap<&NodeId, usize> = nodes.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+        let mut adj = vec![Vec::new(); nodes.len()];
+        for (a, b) in &self.edges {
+            adj[index_of[a]].push(index_of[b]);
+        }
+
+        // Iterative Tarjan's algorithm.
+        let n = nodes.len();
+        let mut index = vec![usize::MAX; n];
+        let mut low = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut stack = Vec::new();
+        let mut next = 0;
+        let mut sccs = Vec::new();
+
+        for root in 0..n {
+            if index[root] != usize::MAX {
+                continue;
+            }
+            let mut work = vec![(root, 0usize)];
+            while let Some((v, child)) = work.pop() {
+                if child == 0 {
+                    index[v] = next;
+                    low[v] = next;
+                    next += 1;
+                    stack.push(v);
+                    on_stack[v] = true;
+                }
+                if child < adj[v].len() {
+                    work.push((v, child + 1));
+                    let w = adj[v][child];
+                    if index[w] == usize::MAX {
+                        work.push((w, 0));
+                    } else if on_stack[w] {
+                        low[v] = low[v].min(index[w]);
+                    }
+                    continue;
+                }
+                if low[v] == index[v] {
+                    let mut scc = Vec::new();
+                    while let Some(w) = stack.pop() {
+                        on_stack[w] = false;
+                        scc.push(w);
+                        if w == v {
+                            break;
+                        }
+                    }
+                    let self_loop = adj[v].contains(&v);
+                    if scc.len() > 1 || self_loop {
+                        let mut ids: Vec<NodeId> = scc.into_iter().map(|i| nodes[i].clone()).collect();
+                        ids.sort();
+                        sccs.push(ids);
+                    }
+                }
+                if let Some(&(parent, _)) = work.last() {
+                    low[parent] = low[parent].min(low[v]);
+                }
+            }
+        }
+        sccs.sort();
+        sccs
+    }
+
+    /// Edges that participate in a cycle.
+    pub fn cycle_edges(&self) -> BTreeSet<(NodeId, NodeId)> {
+        let mut member = HashMap::new();
+        for (i, scc) in self.cycles().into_iter().enumerate() {
+            for id in scc {
+                member.insert(id, i);
+            }
+        }
+        self.edges
+            .iter()
+            .filter(|(a, b)| matches!((member.get(a), member.get(b)), (Some(x), Some(y)) if x == y))
+            .cloned()
+            .collect()
+    }
+
+    /// Nodes whose total degree is unusually high: more than two standard
+    /// deviations above the mean, and at least four.
+    pub fn heavy_nodes(&self) -> Vec<NodeId> {
+        if self.nodes.is_empty() {
+            return Vec::new();
+        }
+        let degrees = self.degrees();
+        let n = degrees.len() as f64;
+        let mean = degrees.values().sum::<usize>() as f64 / n;
+        let var = degrees.values().map(|d| (*d as f64 - mean).powi(2)).sum::<f64>() / n;
+        let threshold = (mean + 2.0 * var.sqrt()).max(4.0);
+        degrees
+            .into_iter()
+            .filter(|(_, d)| *d as f64 >= threshold)
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    fn degrees(&self) -> BTreeMap<NodeId, usize> {
+        let mut degrees: BTreeMap<NodeId, usize> = self.nodes.iter().map(|n| (n.clone(), 0)).collect();
+        for (a, b) in &self.edges {
+            *degrees.entry(a.clone()).or_default() += 1;
+            *degrees.entry(b.clone()).or_default() += 1;
+        }
+        degrees
+    }
+
+    /// Computes what changed from `old` to `self`.
+    pub fn diff(&self, old: &ArchitectureGraph) -> GraphDiff {
+        GraphDiff {
+            added_nodes: self.nodes.difference(&old.nodes).cloned().collect(),
+            removed_nodes: old.nodes.difference(&self.nodes).cloned().collect(),
+            added_edges: self.edges.difference(&old.edges).cloned().collect(),
+            removed_edges: old.edges.difference(&self.edges).cloned().collect(),
+        }
+    }
+
+    /// Renders the graph, optionally overlaying a diff against an older graph.
+    /// Removed nodes and edges from the diff are drawn so the change is visible.
+    pub fn render(&self, format: GraphFormat, diff: Option<&GraphDiff>) -> String {
+        let mut view = self.clone();
+        if let Some(d) = diff {
+            view.nodes.extend(d.removed_nodes.iter().cloned());
+            view.edges.extend(d.removed_edges.iter().cloned());
+        }
+        let styles = Styles::new(&view, self, diff);
+        match format {
+            GraphFormat::Dot => view.to_dot(&styles),
+            GraphFormat::Mermaid => view.to_mermaid(&styles),
+            GraphFormat::Svg => view.to_svg(&styles),
+        }
+    }
+
+    fn to_dot(&self, styles: &Styles) -> String {
+        let mut out = String::from("digraph montrs {\n  rankdir=LR;\n  node [fontname=\"Helvetica\"];\n");
+        for id in &self.nodes {
+            let shape = match id.0 {
+                NodeKind::Package => "box",
+                NodeKind::Plate => "ellipse",
+            };
+            let mut attrs = format!("label=\"{}\", shape={}", id.1, shape);
+            if let Some(fill) = styles.node_fill(id) {
+                let _ = write!(attrs, ", style=filled, fillcolor=\"{}\"", fill);
+            }
+            if styles.removed_nodes.contains(id) {
+                attrs.push_str(", style=\"dashed\"");
+            }
+            let _ = writeln!(out, "  \"{}\" [{}];", dot_id(id), attrs);
+        }
+        for edge in &self.edges {
+            let mut attrs = Vec::new();
+            if let Some(color) = styles.edge_color(edge) {
+                attrs.push(format!("color=\"{}\"", color));
+            }
+            if styles.removed_edges.contains(edge) {
+                attrs.push("style=dashed".to_string());
+            }
+            if styles.cycle_edges.contains(edge) {
+                attrs.push("penwidth=2".to_string());
+            }
+            let attrs = if attrs.is_empty() { String::new() } else { format!(" [{}]", attrs.join(", ")) };
+            let _ = writeln!(out, "  \"{}\" -> \"{}\"{};", dot_id(&edge.0), dot_id(&edge.1), attrs);
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    fn to_mermaid(&self, styles: &Styles) -> String {
+        let mut out = String::from("graph LR\n");
+        let ids: HashMap<&NodeId, String> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id, format!("n{}", i)))
+            .collect();
+        for id in &self.nodes {
+            let (open, close) = match id.0 {
+                NodeKind::Package => ("[", "]"),
+                NodeKind::Plate => ("([", "])"),
+            };
+            let _ = writeln!(out, "  {}{}\"{}\"{}", ids[id], open, id.1.replace('"', "'"), close);
+        }
+        for (i, edge) in self.edges.iter().enumerate() {
+            let arrow = if styles.removed_edges.contains(edge) { "-.->" } else { "-->" };
+            let _ = writeln!(out, "  {} {} {}", ids[&edge.0], arrow, ids[&edge.1]);
+            if let Some(color) = styles.edge_color(edge) {
+                let _ = writeln!(out, "  linkStyle {} stroke:{},stroke-width:2px", i, color);
+            }
+        }
+        for id in &self.nodes {
+            if let Some(fill) = styles.node_fill(id) {
+                let _ = writeln!(out, "  style {} fill:{}", ids[id], fill);
+            }
+        }
+        out
+    }
+
+    fn to_svg(&self, styles: &Styles) -> String {
+        const ROW: f64 = 90.0;
+        const GAP: f64 = 30.0;
+        const HEIGHT: f64 = 36.0;
+
+        // Layer nodes by longest dependency chain so dependencies sit below dependents.
+        let mut layer: BTreeMap<&NodeId, usize> = self.nodes.iter().map(|n| (n, 0)).collect();
+        for _ in 0..self.nodes.len() {
+            let mut changed = false;
+            for (a, b) in &self.edges {
+                if a == b {
+                    continue;
+                }
+                let want = layer[b] + 1;
+                if layer[a] < want && want <= self.nodes.len() {
+                    layer.insert(a, want);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        let depth = layer.values().copied().max().unwrap_or(0);
+        let mut rows: Vec<Vec<&NodeId>> = vec![Vec::new(); depth + 1];
+        for (id, l) in &layer {
+            rows[depth - l].push(id);
+        }
+
+        let width_of = |id: &NodeId| id.1.chars().count() as f64 * 7.5 + 24.0;
+        let mut pos: HashMap<&NodeId, (f64, f64, f64)> = HashMap::new();
+        let mut canvas_w: f64 = 0.0;
+        for (r, row) in rows.iter().enumerate() {
+            let mut x = GAP;
+            for id in row {
+                let w = width_of(id);
+                pos.insert(id, (x, GAP + r as f64 * ROW, w));
+                x += w + GAP;
+            }
+            canvas_w = canvas_w.max(x);
+        }
+        let canvas_h = GAP * 2.0 + depth as f64 * ROW + HEIGHT;
+
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" font-family=\"Helvetica, sans-serif\" font-size=\"12\">",
+            canvas_w, canvas_h
+        );
+        out.push_str("  <defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"6\" markerHeight=\"6\" orient=\"auto\"><path d=\"M0,0 L10,5 L0,10 z\" fill=\"#555\"/></marker></defs>\n");
+        for edge in &self.edges {
+            let (ax, ay, aw) = pos[&edge.0];
+            let (bx, by, bw) = pos[&edge.1];
+            let color = styles.edge_color(edge).unwrap_or("#555");
+            let dash = if styles.removed_edges.contains(edge) { " stroke-dasharray=\"4 3\"" } else { "" };
+            let stroke = if styles.cycle_edges.contains(edge) { 2.5 } else { 1.2 };
+            let (y1, y2) = if ay <= by { (ay + HEIGHT, by) } else { (ay, by + HEIGHT) };
+            let _ = writeln!(
+                out,
+                "  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" stroke-width=\"{}\"{} marker-end=\"url(#arrow)\"/>",
+                ax + aw / 2.0, y1, bx + bw / 2.0, y2, color, stroke, dash
+            );
+        }
+        for id in &self.nodes {
+            let (x, y, w) = pos[id];
+            let fill = styles.node_fill(id).unwrap_or("#f5f5f5");
+            let rx = match id.0 {
+                NodeKind::Package => 4.0,
+                NodeKind::Plate => HEIGHT / 2.0,
+            };
+            let dash = if styles.removed_nodes.contains(id) { " stroke-dasharray=\"4 3\"" } else { "" };
+            let _ = writeln!(
+                out,
+                "  <rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{}\" rx=\"{}\" fill=\"{}\" stroke=\"#333\"{}/>",
+                x, y, w, HEIGHT, rx, fill, dash
+            );
+            let _ = writeln!(
+                out,
+                "  <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" dominant-baseline=\"middle\">{}</text>",
+                x + w / 2.0,
+                y + HEIGHT / 2.0,
+                xml_escape(&id.1)
+            );
+        }
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+/// Precomputed highlight sets used by the renderers.
+struct Styles {
+    cycle_edges: BTreeSet<(NodeId, NodeId)>,
+    cycle_nodes: BTreeSet<NodeId>,
+    heavy: BTreeSet<NodeId>,
+    added_nodes: BTreeSet<NodeId>,
+    removed_nodes: BTreeSet<NodeId>,
+    added_edges: BTreeSet<(NodeId, NodeId)>,
+    removed_edges: BTreeSet<(NodeId, NodeId)>,
+}
+
+impl Styles {
+    fn new(view: &ArchitectureGraph, current: &ArchitectureGraph, diff: Option<&GraphDiff>) -> Self {
+        let d = diff.cloned().unwrap_or_default();
+        Self {
+            cycle_edges: current.cycle_edges(),
+            cycle_nodes: current.cycles().into_iter().flatten().collect(),
+            heavy: view.heavy_nodes().into_iter().collect(),
+            added_nodes: d.added_nodes,
+            removed_nodes: d.removed_nodes,
+            added_edges: d.added_edges,
+            removed_edges: d.removed_edges,
+        }
+    }
+
+    fn node_fill(&self, id: &NodeId) -> Option<&'static str> {
+        if self.added_nodes.contains(id) {
+            Some("#c8f7c5")
+        } else if self.removed_nodes.contains(id) {
+            Some("#f7c5c5")
+        } else if self.cycle_nodes.contains(id) {
+            Some("#ffb3b3")
+        } else if self.heavy.contains(id) {
+            Some("#ffd59e")
+        } else {
+            None
+        }
+    }
+
+    fn edge_color(&self, edge: &(NodeId, NodeId)) -> Option<&'static str> {
+        if self.cycle_edges.contains(edge) {
+            Some("#d00000")
+        } else if self.added_edges.contains(edge) {
+            Some("#2e9e44")
+        } else if self.removed_edges.contains(edge) {
+            Some("#b03030")
+        } else {
+            None
+        }
+    }
+}
+
+fn dot_id(id: &NodeId) -> String {
+    format!("{}:{}", id.0.prefix(), id.1.replace('"', "\\\""))
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,124 @@
+//! Test impact analysis.
+//!
+//! `montrs test --changed` maps the files changed since a git revision to the
+//! workspace packages that contain them, adds every package that depends on
+//! those, and tests only that set. The result is saved as
+//! `.agent/test-impact.json` so agents can scope their own verification runs
+//! to the same packages.
+
+use crate::AgentManager;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+
+pub const IMPACT_FILE: &str = "test-impact.json";
+
+/// Files at the workspace root that affect how every package builds.
+const WORKSPACE_FILES: [&str; 4] = ["Cargo.toml", "Cargo.lock", "rust-toolchain.toml", "rust-toolchain"];
+
+/// A workspace member, as far as impact analysis needs it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WorkspacePackage {
+    pub name: String,
+    /// Directory of the package's `Cargo.toml`, relative to the workspace root.
+    pub dir: String,
+    /// Names of the workspace members this package depends on.
+    pub dependencies: Vec<String>,
+}
+
+/// Which packages a set of changes affects.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TestImpact {
+    /// The revision the changes were taken against.
+    pub base: String,
+    pub generated_at: DateTime<Utc>,
+    /// Each changed file with the package that owns it; `None` for files
+    /// outside every package, such as docs at the root.
+    pub files: BTreeMap<String, Option<String>>,
+    /// Packages containing a changed file.
+    pub changed_packages: BTreeSet<String>,
+    /// The changed packages plus everything that depends on them.
+    pub affected_packages: BTreeSet<String>,
+    /// A workspace-wide file changed, so every package is affected.
+    pub workspace_wide: bool,
+}
+
+impl TestImpact {
+    /// Maps `changed` (paths relative to the workspace root) onto `packages`.
+    /// A file belongs to the package with the deepest directory containing it.
+    pub fn compute(base: &str, changed: &[String], packages: &[WorkspacePackage]) -> Self {
+        let mut files = BTreeMap::new();
+        let mut changed_packages = BTreeSet::new();
+        let mut workspace_wide = false;
+        for file in changed {
+            let owner = packages
+                .iter()
+                .filter(|p| p.dir.is_empty() || file.starts_with(&format!("{}/", p.dir)))
+                .max_by_key(|p| p.dir.len());
+            if WORKSPACE_FILES.contains(&file.as_str()) || file.starts_with(".cargo/") {
+                workspace_wide = true;
+            }
+            if let Some(owner) = owner {
+                changed_packages.insert(owner.name.clone());
+            }
+            files.insert(file.clone(), owner.map(|p| p.name.clone()));
+        }
+
+        let affected_packages = if workspace_wide {
+            packages.iter().map(|p| p.name.clone()).collect()
+        } else {
+            dependents(&changed_packages, packages)
+        };
+        Self {
+            base: base.to_string(),
+            generated_at: Utc::now(),
+            files,
+            changed_packages,
+            affected_packages,
+            workspace_wide,
+        }
+    }
+}
+
+/// `roots` and every package that depends on one of them, directly or not.
+fn dependents(roots: &BTreeSet<String>, packages: &[WorkspacePackage]) -> BTreeSet<String> {
+    let mut affected = roots.clone();
+    loop {
+        let before = affected.len();
+        for package in packages {
+            if package.dependencies.iter().any(|d| affected.contains(d)) {
+                affected.insert(package.name.clone());
+            }
+        }
+        if affected.len() == before {
+            return affected;
+        }
+    }
+}
+
+impl AgentManager {
+    pub fn test_impact_file(&self) -> PathBuf {
+        self.agent_dir().join(IMPACT_FILE)
+    }
+
+    pub fn write_test_impact(&self, impact: &TestImpact) -> Result<()> {
+        if !self.is_persistent() {
+            return Ok(());
+        }
+        fs::create_dir_all(self.agent_dir())?;
+        fs::write(self.test_impact_file(), serde_json::to_string_pretty(impact)?)?;
+        Ok(())
+    }
+
+    /// The impact recorded by the last `montrs test --changed`, if any.
+    pub fn read_test_impact(&self) -> Result<Option<TestImpact>> {
+        let path = self.test_impact_file();
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+    }
+}
@@ -1,13 +1,35 @@
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::OnceLock;
+use serde::de::DeserializeOwned;
+use snapshot::{SnapshotReader, SnapshotRecord, SNAPSHOT_JSONL};
+use storage::AgentStorage;
 use std::fs;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 
 pub mod guides;
+pub mod diff;
 pub mod error_parser;
+pub mod explain;
+pub mod export;
+pub mod fix;
 pub mod framework;
+pub mod git;
+pub mod graph;
+pub mod impact;
+pub mod llm;
+pub mod query;
+pub mod redact;
+pub mod search;
+pub mod session;
+pub mod snapshot;
+pub mod storage;
+pub mod workspace;
+
+/// This crate's version and the `montrs-core` it needs; see [`montrs_core::compat`].
+pub const CRATE_VERSION: montrs_core::CrateVersion = montrs_core::crate_version!();
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AgentSnapshot {
@@ -20,6 +42,42 @@ pub struct AgentSnapshot {
     pub packages: Vec<PackageSummary>,
     pub agent_entry_point: Option<String>,
     pub documentation_snippets: HashMap<String, String>,
+    /// Deprecated routes and plates, with their sunset dates and replacements.
+    #[serde(default)]
+    pub deprecations: Vec<DeprecationSummary>,
+    /// Every route with the roles and permissions it requires; public routes
+    /// have none.
+    #[serde(default)]
+    pub authorization: Vec<AccessSummary>,
+    /// The CORS, security header, limit and asset settings the app serves with.
+    #[serde(default)]
+    pub preset: Option<PresetSummary>,
+    /// Timings of the last app boot under the CLI, to track startup regressions.
+    #[serde(default)]
+    pub boot: Option<montrs_core::BootTrace>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeprecationSummary {
+    /// "route" or "plate".
+    pub kind: String,
+    pub name: String,
+    pub deprecation: montrs_core::Deprecation,
+    /// Whether the sunset date has already passed.
+    pub past_sunset: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccessSummary {
+    pub route: String,
+    /// Requirements declared with `.requires(..)`, such as `role:admin`.
+    pub requires: Vec<montrs_core::Permission>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PresetSummary {
+    pub target: montrs_core::Target,
+    pub preset: montrs_core::Preset,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -28,6 +86,9 @@ pub struct PackageSummary {
     pub path: String,
     pub invariants: Option<String>,
     pub description: Option<String>,
+    /// Other workspace packages this package depends on (by directory name).
+    #[serde(default)]
+    pub dependencies: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -56,6 +117,9 @@ pub struct RouteSummary {
     pub action_input_schema: Option<serde_json::Value>,
     pub action_output_schema: Option<serde_json::Value>,
     pub metadata: HashMap<String, String>,
+    /// The layout the route is nested in, from the `RouterSpec`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -66,9 +130,12 @@ pub struct ErrorRecord {
     pub status: ErrorStatus,
     pub detail: ProjectError,
     pub history: Vec<ErrorVersion>,
+    /// Commit, branch and blame at the time the error was first reported.
+    #[serde(default)]
+    pub git: Option<git::GitMetadata>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum ErrorStatus {
     Active,
     Resolved,
@@ -79,7 +146,16 @@ pub struct ErrorVersion {
     pub version: u32,
     pub timestamp: DateTime<Utc>,
     pub message: String,
+    /// Diff text that is not a unified diff, or any diff in records written
+    /// before [`ErrorVersion::changes`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub diff: Option<String>,
+    /// The diff of the fix, as hunks or a reference to a blob (see [`diff`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changes: Option<diff::StoredDiff>,
+    /// The commit that resolved the error, when it was closed from a commit message.
+    #[serde(default)]
+    pub commit: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -118,19 +194,84 @@ pub struct AgentErrorMetadata {
     pub explanation: String,
     pub suggested_fixes: Vec<String>,
     pub rustc_error: Option<String>,
+    /// Source replacements proposed by rustc or clippy, in the order they were reported.
+    #[serde(default)]
+    pub suggestions: Vec<error_parser::Suggestion>,
+    /// Written by `montrs explain`; kept so later sessions need not ask again.
+    #[serde(default)]
+    pub model_explanation: Option<explain::ModelExplanation>,
 }
 
+/// Source lines shown on each side of a panic's location.
+const PANIC_CONTEXT_LINES: u32 = 3;
+
 pub struct AgentManager {
     root_path: PathBuf,
+    /// Resolved on first use, so constructing a manager never touches the disk.
+    storage: OnceLock<storage::Resolved>,
+    settings: OnceLock<storage::AgentSettings>,
+    redactor: OnceLock<Result<redact::Redactor, String>>,
 }
 
 impl AgentManager {
+    /// A manager for the project at `root`, storing artifacts where
+    /// `montrs.toml` and the environment say (see [`storage`]).
     pub fn new(root: impl Into<PathBuf>) -> Self {
-        Self { root_path: root.into() }
+        Self { root_path: root.into(), storage: OnceLock::new(), settings: OnceLock::new(), redactor: OnceLock::new() }
+    }
+
+    /// A manager that uses `storage` as given, without checking it.
+    pub fn with_storage(root: impl Into<PathBuf>, storage: AgentStorage) -> Self {
+        let manager = Self::new(root);
+        let _ = manager.storage.set(storage::Resolved { storage, fallback: None });
+        manager
+    }
+
+    fn resolved(&self) -> &storage::Resolved {
+        self.storage.get_or_init(|| AgentStorage::resolve(&self.root_path, self.settings()))
     }
 
+    /// The `[agent]` settings, read once.
+    pub fn settings(&self) -> &storage::AgentSettings {
+        self.settings.get_or_init(|| storage::AgentSettings::load(&self.root_path))
+    }
+
+    /// The redactor built from `[agent.redact]`, or none after `--no-redact`.
+    pub fn redactor(&self) -> Result<&redact::Redactor> {
+        self.redactor
+            .get_or_init(|| redact::Redactor::for_process(&self.settings().redact).map_err(|e| format!("{:#}", e)))
+            .as_ref()
+            .map_err(|e| anyhow::anyhow!("{}", e))
+    }
+
+    /// A copy of `snapshot` with secrets redacted (see [`redact`]).
+    pub fn redact_snapshot(&self, snapshot: &AgentSnapshot) -> Result<AgentSnapshot> {
+        let mut snapshot = snapshot.clone();
+        self.redactor()?.redact_snapshot(&mut snapshot);
+        Ok(snapshot)
+    }
+
+    pub fn storage(&self) -> &AgentStorage {
+        &self.resolved().storage
+    }
+
+    /// Whether artifacts are written to disk rather than kept in memory.
+    pub fn is_persistent(&self) -> bool {
+        self.storage().dir().is_some()
+    }
+
+    /// Why the configured directory was not used, if it was not.
+    pub fn storage_fallback(&self) -> Option<&str> {
+        self.resolved().fallback.as_deref()
+    }
+
+    /// Where artifacts are read from and written to. In memory mode this is
+    /// the project's `.agent`, which is still read but never written.
     pub fn agent_dir(&self) -> PathBuf {
-        self.root_path.join(".agent")
+        match self.storage() {
+            AgentStorage::Dir(dir) => dir.clone(),
+            AgentStorage::Memory => self.root_path.join(storage::DEFAULT_AGENT_DIR),
+        }
     }
 
     pub fn errorfiles_dir(&self) -> PathBuf {
@@ -142,6 +283,9 @@ impl AgentManager {
     }
 
     pub fn ensure_dir(&self) -> Result<()> {
+        if !self.is_persistent() {
+            return Ok(());
+        }
         let dir = self.agent_dir();
         if !dir.exists() {
             fs::create_dir_all(&dir)?;
@@ -153,8 +297,32 @@ impl AgentManager {
         Ok(())
     }
 
+    pub fn snapshot_file(&self) -> PathBuf {
+        self.agent_dir().join("agent.json")
+    }
+
     pub fn write_snapshot(&self, snapshot: &AgentSnapshot, format: &str) -> Result<()> {
+        if !self.is_persistent() {
+            return Ok(());
+        }
         self.ensure_dir()?;
+        let redacted = self.redact_snapshot(snapshot)?;
+        let snapshot = &redacted;
+        match format {
+            "jsonl" => return snapshot::write_stream(snapshot, &self.agent_dir()),
+            "md" | "markdown" => {
+                fs::write(self.agent_dir().join(export::SNAPSHOT_MARKDOWN), export::to_markdown(snapshot))?;
+                return Ok(());
+            }
+            #[cfg(feature = "sqlite")]
+            "sqlite" => {
+                let tracking = self.load_tracking()?;
+                return export::write_sqlite(snapshot, &tracking.errors, &self.agent_dir().join(export::SNAPSHOT_SQLITE));
+            }
+            #[cfg(not(feature = "sqlite"))]
+            "sqlite" => anyhow::bail!("SQLite export needs montrs-agent built with the `sqlite` feature"),
+            _ => {}
+        }
         let content = match format {
             "yaml" => serde_yaml::to_string(snapshot)?,
             "txt" => format!("{:#?}", snapshot),
@@ -165,7 +333,93 @@ impl AgentManager {
         Ok(())
     }
 
+    /// Loads the whole snapshot, from `agent.jsonl` or `agent.json` whichever is newer.
+    pub fn read_snapshot(&self) -> Result<AgentSnapshot> {
+        if self.streamed()? {
+            snapshot::read_stream(&self.agent_dir())
+        } else {
+            Ok(serde_json::from_reader(std::io::BufReader::new(fs::File::open(self.snapshot_file())?))?)
+        }
+    }
+
+    /// Reads only the routes of the current snapshot.
+    pub fn snapshot_routes(&self) -> Result<Vec<RouteSummary>> {
+        self.snapshot_records("routes", |record| match record {
+            SnapshotRecord::Route(route) => Some(route),
+            _ => None,
+        })
+    }
+
+    /// Reads only the plates of the current snapshot.
+    pub fn snapshot_plates(&self) -> Result<Vec<PlateSummary>> {
+        self.snapshot_records("plates", |record| match record {
+            SnapshotRecord::Plate(plate) => Some(plate),
+            _ => None,
+        })
+    }
+
+    /// Reads only the packages of the current snapshot.
+    pub fn snapshot_packages(&self) -> Result<Vec<PackageSummary>> {
+        self.snapshot_records("packages", |record| match record {
+            SnapshotRecord::Package(package) => Some(package),
+            _ => None,
+        })
+    }
+
+    /// Reads one documentation snippet without loading the others.
+    pub fn documentation_snippet(&self, key: &str) -> Result<Option<String>> {
+        if !self.streamed()? {
+            return snapshot::read_snippet(&self.snapshot_file(), key);
+        }
+        for record in SnapshotReader::open(&self.agent_dir().join(SNAPSHOT_JSONL))? {
+            if let SnapshotRecord::Snippet(snippet) = record?
+                && snippet.key == key
+            {
+                return Ok(Some(fs::read_to_string(self.agent_dir().join(snippet.file))?));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Lists the documentation snippet keys, sorted.
+    pub fn snippet_keys(&self) -> Result<Vec<String>> {
+        if !self.streamed()? {
+            return snapshot::read_snippet_keys(&self.snapshot_file());
+        }
+        let mut keys = self.snapshot_records("documentation_snippets", |record| match record {
+            SnapshotRecord::Snippet(snippet) => Some(snippet.key),
+            _ => None,
+        })?;
+        keys.sort();
+        Ok(keys)
+    }
+
+    /// Collects matching records from the stream, or the `field` array from `agent.json`.
+    fn snapshot_records<T: DeserializeOwned>(&self, field: &str, pick: impl Fn(SnapshotRecord) -> Option<T>) -> Result<Vec<T>> {
+        if self.streamed()? {
+            SnapshotReader::open(&self.agent_dir().join(SNAPSHOT_JSONL))?
+                .filter_map(|record| record.map(&pick).transpose())
+                .collect()
+        } else {
+            Ok(snapshot::read_section(&self.snapshot_file(), field)?.unwrap_or_default())
+        }
+    }
+
+    /// Whether queries should use `agent.jsonl`: it exists and is not older than `agent.json`.
+    fn streamed(&self) -> Result<bool> {
+        let modified = |path: PathBuf| fs::metadata(path).and_then(|m| m.modified()).ok();
+        match (modified(self.agent_dir().join(SNAPSHOT_JSONL)), modified(self.snapshot_file())) {
+            (Some(stream), Some(json)) => Ok(stream >= json),
+            (Some(_), None) => Ok(true),
+            (None, Some(_)) => Ok(false),
+            (None, None) => anyhow::bail!("No snapshot in {}; run `montrs spec` first", self.agent_dir().display()),
+        }
+    }
+
     pub fn write_error_record(&self, record: &ErrorRecord) -> Result<()> {
+        if !self.is_persistent() {
+            return Ok(());
+        }
         self.ensure_dir()?;
         let version_dir = self.errorfiles_dir().join(format!("v{}", record.version));
         if !version_dir.exists() {
@@ -222,6 +476,9 @@ impl AgentManager {
     }
 
     pub fn save_tracking(&self, tracking: &ErrorTracking) -> Result<()> {
+        if !self.is_persistent() {
+            return Ok(());
+        }
         self.ensure_dir()?;
         let content = serde_json::to_string_pretty(tracking)?;
         fs::write(self.tracking_file(), content)?;
@@ -282,6 +539,7 @@ impl AgentManager {
             timestamp: Utc::now(),
             version: 1,
             status: ErrorStatus::Active,
+            git: git::capture(&self.root_path, &error.file, error.line),
             detail: error,
             history: Vec::new(),
         };
@@ -328,6 +586,41 @@ impl AgentManager {
         Ok(())
     }
 
+    /// Records a captured panic as an active error, with the source lines
+    /// around its location as code context. Returns the error ID.
+    pub fn report_panic(&self, report: &montrs_core::PanicReport) -> Result<String> {
+        let file = report.file.clone().unwrap_or_else(|| "unknown".to_string());
+        let code_context = fs::read_to_string(self.root_path.join(&file))
+            .map(|source| {
+                let first = report.line.saturating_sub(PANIC_CONTEXT_LINES + 1) as usize;
+                source
+                    .lines()
+                    .skip(first)
+                    .take(PANIC_CONTEXT_LINES as usize * 2 + 1)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default();
+        let scope = report.scope.as_deref().map(|s| format!(" in {}", s)).unwrap_or_default();
+        self.report_project_error(ProjectError {
+            package: None,
+            file,
+            line: report.line,
+            column: report.column,
+            message: format!("panic{}: {}", scope, report.message),
+            code_context,
+            level: "Error".to_string(),
+            agent_metadata: Some(AgentErrorMetadata {
+                error_code: "PANIC".to_string(),
+                explanation: format!("Correlation ID {}. Backtrace:\n{}", report.id, report.backtrace),
+                suggested_fixes: Vec::new(),
+                rustc_error: None,
+                suggestions: Vec::new(),
+                model_explanation: None,
+            }),
+        })
+    }
+
     fn determine_package(&self, file_path: &str) -> Option<String> {
         let path = std::path::Path::new(file_path);
         // Look for "packages/NAME" or "apps/NAME"
@@ -374,34 +667,93 @@ impl AgentManager {
     }
 
     pub fn resolve_error(&self, id: &str, fix_message: String, diff: Option<String>) -> Result<()> {
+        self.resolve_error_at(id, fix_message, diff, None)
+    }
+
+    fn resolve_error_at(&self, id: &str, fix_message: String, diff: Option<String>, commit: Option<String>) -> Result<()> {
         let mut record = self.find_error(id)?;
         if let ErrorStatus::Active = record.status {
             record.status = ErrorStatus::Resolved;
             let history_version = record.version;
             record.version += 1;
+            let (diff, changes) = self.store_diff(diff)?;
             record.history.push(ErrorVersion {
                 version: history_version,
                 timestamp: Utc::now(),
                 message: fix_message,
                 diff,
+                changes,
+                commit,
             });
             self.write_error_record(&record)?;
         }
         Ok(())
     }
 
-    fn find_error(&self, id: &str) -> Result<ErrorRecord> {
+    /// Resolves active errors whose id appears in the message of a commit made
+    /// since they were reported, e.g. `Fix off-by-one in pager (fixes 3f2a…)`.
+    /// The commit hash and its patch are stored in the error's history.
+    /// Returns the ids that were resolved.
+    pub fn resolve_referenced_errors(&self) -> Result<Vec<String>> {
+        let active = self.list_active_errors()?;
+        let Some(since) = active.iter().map(|r| r.timestamp).min() else {
+            return Ok(Vec::new());
+        };
+        let commits = git::commits_since(&self.root_path, since);
+
+        let mut resolved = Vec::new();
+        for record in active {
+            // Older versions of an already resolved error are still on disk.
+            if resolved.contains(&record.id) || !matches!(self.find_error(&record.id)?.status, ErrorStatus::Active) {
+                continue;
+            }
+            // The log is newest first; the earliest referencing commit is the fix.
+            if let Some(commit) = commits.iter().rev().find(|c| c.message.contains(&record.id)) {
+                let short = &commit.hash[..commit.hash.len().min(12)];
+                self.resolve_error_at(
+                    &record.id,
+                    format!("Resolved by commit {}: {}", short, commit.subject()),
+                    git::commit_patch(&self.root_path, &commit.hash),
+                    Some(commit.hash.clone()),
+                )?;
+                resolved.push(record.id);
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// Appends a fix attempt to an error's history without resolving it, e.g. when
+    /// a patch was applied but the check still reports the error.
+    pub fn record_fix_attempt(&self, id: &str, message: String, diff: Option<String>) -> Result<()> {
+        let mut record = self.find_error(id)?;
+        let (diff, changes) = self.store_diff(diff)?;
+        record.history.push(ErrorVersion {
+            version: record.version,
+            timestamp: Utc::now(),
+            message,
+            diff,
+            changes,
+            commit: None,
+        });
+        self.write_error_record(&record)
+    }
+
+    /// Loads the latest version of an error record.
+    pub fn find_error(&self, id: &str) -> Result<ErrorRecord> {
         let error_dir = self.errorfiles_dir();
-        for entry in fs::read_dir(error_dir)?.flatten() {
-            if entry.path().is_dir() {
+        let mut latest: Option<ErrorRecord> = None;
+        if error_dir.exists() {
+            for entry in fs::read_dir(error_dir)?.flatten() {
                 let file_path = entry.path().join(format!("{}.json", id));
                 if file_path.exists() {
-                    let content = fs::read_to_string(file_path)?;
-                    return Ok(serde_json::from_str(&content)?);
+                    let record: ErrorRecord = serde_json::from_str(&fs::read_to_string(file_path)?)?;
+                    if latest.as_ref().is_none_or(|l| record.version > l.version) {
+                        latest = Some(record);
+                    }
                 }
             }
         }
-        anyhow::bail!("Error record not found: {}", id)
+        latest.ok_or_else(|| anyhow::anyhow!("Error record not found: {}", id))
     }
 
     pub fn generate_tools_spec(&self) -> Result<serde_json::Value> {
@@ -427,6 +779,17 @@ impl AgentManager {
                     }
                 }
             }),
+            serde_json::json!({
+                "name": "montrs_config_resolve",
+                "description": "Prints the effective configuration, with the CORS, security header, limit and asset preset of a target.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "target": { "type": "string", "enum": ["server", "wasm", "edge", "desktop", "mobile-android", "mobile-ios", "wasi"] },
+                        "format": { "type": "string", "enum": ["toml", "json"] }
+                    }
+                }
+            }),
             serde_json::json!({
                 "name": "montrs_fmt",
                 "description": "Formats the project's Rust and view! code.",
@@ -573,6 +936,37 @@ impl AgentManager {
         None
     }
 
+    /// Reads a package manifest and returns the directory names of its
+    /// sibling path dependencies (e.g. `path = "../core"` yields `core`).
+    fn local_dependencies(manifest: &std::path::Path) -> Vec<String> {
+        let Some(doc) = fs::read_to_string(manifest)
+            .ok()
+            .and_then(|c| c.parse::<toml::Table>().ok())
+        else {
+            return Vec::new();
+        };
+
+        let mut deps = Vec::new();
+        for section in ["dependencies", "dev-dependencies", "build-dependencies"] {
+            let Some(table) = doc.get(section).and_then(|t| t.as_table()) else {
+                continue;
+            };
+            for value in table.values() {
+                let Some(path) = value.get("path").and_then(|p| p.as_str()) else {
+                    continue;
+                };
+                if let Some(name) = std::path::Path::new(path).file_name() {
+                    let name = name.to_string_lossy().to_string();
+                    if !deps.contains(&name) {
+                        deps.push(name);
+                    }
+                }
+            }
+        }
+        deps.sort();
+        deps
+    }
+
     fn discover_plates_heuristically(&self) -> (Vec<PlateSummary>, Vec<RouteSummary>) {
         let mut plates = Vec::new();
         let mut routes = Vec::new();
@@ -604,6 +998,18 @@ impl AgentManager {
 
         let plate_re = regex::Regex::new(r"impl\s+Plate(?:<[^>]+>)?\s+for\s+(\w+)").unwrap();
         let route_re = regex::Regex::new(r"impl\s+Route(?:<[^>]+>)?\s+for\s+(\w+)").unwrap();
+        // `router.register(X).with_meta(..).deprecated(..).requires(..)` for routes, `X.with_meta(..)` for plates.
+        let annotated_re = regex::Regex::new(
+            r"(\w+)\s*\)?\s*((?:\.(?:with_meta|deprecated|requires)\((?:[^()]|\([^()]*\))*\)\s*)+)",
+        )
+        .unwrap();
+        let meta_re = regex::Regex::new(r#"\.with_meta\(\s*([\w:]+|"[^"]*")\s*,\s*"([^"]*)"\s*\)"#).unwrap();
+        let deprecation_re = regex::Regex::new(r#"\.(since|sunset|replacement|note)\(\s*"([^"]*)"\s*\)"#).unwrap();
+        let requires_re = regex::Regex::new(
+            r#"\.requires\(\s*(?:Permission::(Admin|role|named)\s*(?:\(\s*"([^"]*)"\s*\))?|"([^"]*)")\s*\)"#,
+        )
+        .unwrap();
+        let mut annotations: HashMap<String, HashMap<String, String>> = HashMap::new();
 
         for src_dir in scan_dirs {
             if !src_dir.exists() { continue; }
@@ -626,6 +1032,37 @@ impl AgentManager {
                             }
                         }
 
+                        // Collect `.with_meta(..)` annotations; applied once every file is scanned.
+                        for caps in annotated_re.captures_iter(&content) {
+                            let entry = annotations.entry(caps[1].to_string()).or_default();
+                            for m in meta_re.captures_iter(&caps[2]) {
+                                entry.insert(Self::meta_key(&m[1]), m[2].to_string());
+                            }
+                            if let Some(pos) = caps[2].find(".deprecated(") {
+                                let mut deprecation = montrs_core::Deprecation::new();
+                                for m in deprecation_re.captures_iter(&caps[2][pos..]) {
+                                    let value = m[2].to_string();
+                                    deprecation = match &m[1] {
+                                        "since" => deprecation.since(value),
+                                        "sunset" => deprecation.sunset(value),
+                                        "replacement" => deprecation.replacement(value),
+                                        _ => deprecation.note(value),
+                                    };
+                                }
+                                deprecation.apply_to(entry);
+                            }
+                            for m in requires_re.captures_iter(&caps[2]) {
+                                let value = m.get(2).or_else(|| m.get(3)).map_or("", |v| v.as_str());
+                                let permission = match m.get(1).map(|kind| kind.as_str()) {
+                                    Some("Admin") => montrs_core::Permission::Admin,
+                                    Some("role") => montrs_core::Permission::role(value),
+                                    Some(_) => montrs_core::Permission::named(value),
+                                    None => montrs_core::Permission::from(value),
+                                };
+                                permission.apply_to(entry);
+                            }
+                        }
+
                         // Discover Routes
                         for caps in route_re.captures_iter(&content) {
                             let name = caps[1].to_string();
@@ -641,6 +1078,7 @@ impl AgentManager {
                                 action_input_schema: None,
                                 action_output_schema: None,
                                 metadata: HashMap::new(),
+                                parent: None,
                             });
                         }
                     }
@@ -648,10 +1086,61 @@ impl AgentManager {
             }
         }
 
+        for plate in &mut plates {
+            if let Some(meta) = annotations.get(&plate.name) {
+                plate.metadata.extend(meta.clone());
+            }
+        }
+        for route in &mut routes {
+            let name = route.path.trim_start_matches("(impl) ");
+            if let Some(meta) = annotations.get(name) {
+                route.metadata.extend(meta.clone());
+            }
+        }
+
         (plates, routes)
     }
 
+    fn deprecation_report(plates: &[PlateSummary], routes: &[RouteSummary]) -> Vec<DeprecationSummary> {
+        let plates = plates.iter().map(|p| ("plate", &p.name, &p.metadata));
+        let routes = routes.iter().map(|r| ("route", &r.path, &r.metadata));
+        plates
+            .chain(routes)
+            .filter_map(|(kind, name, metadata)| {
+                montrs_core::Deprecation::from_meta(metadata).map(|deprecation| DeprecationSummary {
+                    kind: kind.to_string(),
+                    name: name.clone(),
+                    past_sunset: deprecation.is_past_sunset(),
+                    deprecation,
+                })
+            })
+            .collect()
+    }
+
+    fn access_report(routes: &[RouteSummary]) -> Vec<AccessSummary> {
+        let mut report: Vec<_> = routes
+            .iter()
+            .map(|route| AccessSummary {
+                route: route.path.clone(),
+                requires: montrs_core::Permission::from_meta(&route.metadata),
+            })
+            .collect();
+        report.sort_by(|a, b| a.route.cmp(&b.route));
+        report
+    }
+
+    /// Resolves a `with_meta` key written as a string literal or a `meta::*` constant.
+    fn meta_key(raw: &str) -> String {
+        match raw.strip_prefix('"').and_then(|r| r.strip_suffix('"')) {
+            Some(literal) => literal.to_string(),
+            None => raw.rsplit("::").next().unwrap_or(raw).to_lowercase(),
+        }
+    }
+
     pub fn write_tools_spec(&self) -> Result<()> {
+        if !self.is_persistent() {
+            return Ok(());
+        }
         let tools = self.generate_tools_spec()?;
         let content = serde_json::to_string_pretty(&tools)?;
         let path = self.agent_dir().join("tools.json");
@@ -682,6 +1171,7 @@ impl AgentManager {
                 path: format!("packages/{}", name),
                 invariants: Some(invariants.to_string()),
                 description: Some(format!("MontRS {} package (embedded reference)", name)),
+                dependencies: Vec::new(),
             });
         }
 
@@ -695,17 +1185,22 @@ impl AgentManager {
             packages,
             agent_entry_point: Some(framework::AGENT_INDEX.to_string()),
             documentation_snippets,
+            deprecations: Vec::new(),
+            authorization: Vec::new(),
+            preset: None,
+            boot: None,
         }
     }
 
     pub fn generate_snapshot_with_spec(&self, project_name: &str, spec: Option<montrs_core::AppSpecExport>) -> Result<AgentSnapshot> {
         let mut structure = Vec::new();
+        let agent_dir = self.agent_dir();
         let walker = ignore::WalkBuilder::new(&self.root_path)
             .hidden(false)
             .git_ignore(true)
-            .filter_entry(|entry| {
+            .filter_entry(move |entry| {
                 let name = entry.file_name().to_string_lossy();
-                name != ".git" && name != "target" && name != ".agent"
+                name != ".git" && name != "target" && name != ".agent" && entry.path() != agent_dir
             })
             .build();
 
@@ -795,11 +1290,13 @@ impl AgentManager {
                             }
                         }
 
+                        let dependencies = Self::local_dependencies(&entry.path().join("Cargo.toml"));
                         packages.push(PackageSummary {
                             name,
                             path: relative_path,
                             invariants,
                             description: None,
+                            dependencies,
                         });
                     }
                 }
@@ -815,6 +1312,11 @@ impl AgentManager {
             Some(framework::AGENT_INDEX.to_string())
         };
 
+        let preset = match &spec {
+            Some(s) => s.preset.clone().map(|preset| PresetSummary { target: s.target, preset }),
+            None => self.configured_preset(),
+        };
+
         let (plates, routes) = if let Some(s) = spec {
             let mut plates = Vec::new();
             let mut routes = Vec::new();
@@ -839,7 +1341,8 @@ impl AgentManager {
                     loader_output_schema: None,
                     action_input_schema: None,
                     action_output_schema: None,
-                    metadata: HashMap::new(),
+                    metadata: meta.meta,
+                    parent: meta.parent,
                 });
             }
             (plates, routes)
@@ -847,6 +1350,12 @@ impl AgentManager {
             self.discover_plates_heuristically()
         };
 
+        let deprecations = Self::deprecation_report(&plates, &routes);
+        let authorization = Self::access_report(&routes);
+        let boot = fs::read_to_string(self.root_path.join(montrs_core::boot::BOOT_TRACE_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+
         Ok(AgentSnapshot {
             project_name: project_name.to_string(),
             timestamp: Utc::now(),
@@ -857,9 +1366,27 @@ impl AgentManager {
             packages,
             agent_entry_point,
             documentation_snippets,
+            deprecations,
+            authorization,
+            preset,
+            boot,
         })
     }
 
+    /// The preset `[preset]` in `montrs.toml` resolves to, for a snapshot
+    /// taken without the app's spec. Uses `[preset] target`, or `server`.
+    fn configured_preset(&self) -> Option<PresetSummary> {
+        let overrides = fs::read_to_string(self.root_path.join("montrs.toml"))
+            .ok()
+            .and_then(|content| toml::from_str::<toml::Value>(&content).ok())
+            .and_then(|value| value.get("preset").cloned())
+            .and_then(|preset| serde_json::to_value(preset).ok())
+            .unwrap_or_default();
+        let target = montrs_core::Preset::configured_target(&overrides).ok()?.unwrap_or(montrs_core::Target::Server);
+        let preset = montrs_core::Preset::resolve(target, &overrides).ok()?;
+        Some(PresetSummary { target, preset })
+    }
+
     pub fn check_invariants(&self, snapshot: &AgentSnapshot) -> Result<Vec<String>> {
         let mut violations = Vec::new();
 
@@ -908,7 +1435,17 @@ impl AgentManager {
             }
         }
 
-        // 4. Check for unified entry point
+        // 4. Every route must declare an owner
+        for route in &snapshot.routes {
+            if !route.metadata.contains_key(montrs_core::meta::OWNER) {
+                violations.push(format!(
+                    "Route '{}' does not declare an owner. Add `.with_meta(\"owner\", \"<team>\")` where it is registered.",
+                    route.path
+                ));
+            }
+        }
+
+        // 5. Check for unified entry point
         if snapshot.agent_entry_point.is_none() {
             violations.push("Project is missing a unified agent entry point (docs/agent/index.md).".to_string());
         }
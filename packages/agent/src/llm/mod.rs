@@ -0,0 +1,345 @@
+//! Language model providers.
+//!
+//! [`LlmProvider`] is the one interface higher-level agent commands use to talk
+//! to a model. Two backends ship behind features: `openai` for the OpenAI API
+//! and any server that speaks its protocol (vLLM, LM Studio, OpenRouter...), and
+//! `ollama` for a local Ollama daemon. [`provider_from_env`] picks and
+//! configures one from environment variables:
+//!
+//! | Variable | Meaning |
+//! | --- | --- |
+//! | `MONTRS_LLM_PROVIDER` | `openai` or `ollama`. Defaults to `openai` when an API key is set, otherwise `ollama`. |
+//! | `MONTRS_LLM_MODEL` | Chat model name. |
+//! | `MONTRS_LLM_EMBED_MODEL` | Embedding model name. |
+//! | `MONTRS_LLM_BASE_URL` | Server URL, e.g. `http://localhost:8000/v1`. |
+//! | `MONTRS_LLM_API_KEY` | API key; `OPENAI_API_KEY` is used when unset. |
+//! | `MONTRS_LLM_TIMEOUT_SECS` | Request timeout, 60 seconds by default. |
+
+#[cfg(feature = "ollama")]
+pub mod ollama;
+#[cfg(feature = "openai")]
+pub mod openai;
+
+use async_trait::async_trait;
+use montrs_core::{AgentError, EnvConfig};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub const PROVIDER_VAR: &str = "MONTRS_LLM_PROVIDER";
+pub const MODEL_VAR: &str = "MONTRS_LLM_MODEL";
+pub const EMBED_MODEL_VAR: &str = "MONTRS_LLM_EMBED_MODEL";
+pub const BASE_URL_VAR: &str = "MONTRS_LLM_BASE_URL";
+pub const API_KEY_VAR: &str = "MONTRS_LLM_API_KEY";
+pub const TIMEOUT_VAR: &str = "MONTRS_LLM_TIMEOUT_SECS";
+
+#[derive(Debug, thiserror::Error)]
+pub enum LlmError {
+    #[error("LLM configuration error: {0}")]
+    Config(String),
+    #[error("Provider '{0}' is not compiled in; enable the `{0}` feature of montrs-agent")]
+    NotCompiled(String),
+    #[error("Request to {url} failed: {reason}")]
+    Http { url: String, reason: String },
+    #[error("{provider} returned HTTP {status}: {message}")]
+    Api { provider: String, status: u16, message: String },
+    #[error("Unexpected response from {provider}: {reason}")]
+    InvalidResponse { provider: String, reason: String },
+}
+
+impl AgentError for LlmError {
+    fn error_code(&self) -> &'static str {
+        match self {
+            LlmError::Config(_) => "LLM_CONFIG",
+            LlmError::NotCompiled(_) => "LLM_NOT_COMPILED",
+            LlmError::Http { .. } => "LLM_HTTP",
+            LlmError::Api { .. } => "LLM_API",
+            LlmError::InvalidResponse { .. } => "LLM_INVALID_RESPONSE",
+        }
+    }
+
+    fn explanation(&self) -> String {
+        match self {
+            LlmError::Config(e) => format!("The language model provider could not be configured: {}", e),
+            LlmError::NotCompiled(p) => format!("This build of montrs-agent does not include the {} provider.", p),
+            LlmError::Http { url, reason } => format!("The model server at {} could not be reached: {}", url, reason),
+            LlmError::Api { provider, status, message } => {
+                format!("{} rejected the request with status {}: {}", provider, status, message)
+            }
+            LlmError::InvalidResponse { provider, reason } => {
+                format!("{} answered, but not in the expected format: {}", provider, reason)
+            }
+        }
+    }
+
+    fn suggested_fixes(&self) -> Vec<String> {
+        match self {
+            LlmError::Config(_) => vec![format!("Set {} to `openai` or `ollama`, and {} to a model name.", PROVIDER_VAR, MODEL_VAR)],
+            LlmError::NotCompiled(p) => vec![format!("Add `features = [\"{}\"]` to the montrs-agent dependency.", p)],
+            LlmError::Http { .. } => vec![
+                format!("Check {} and that the server is running (`ollama serve` for Ollama).", BASE_URL_VAR),
+                format!("Raise {} for slow local models.", TIMEOUT_VAR),
+            ],
+            LlmError::Api { status: 401 | 403, .. } => vec![format!("Check {} (or OPENAI_API_KEY).", API_KEY_VAR)],
+            LlmError::Api { status: 404, .. } => vec![format!("Check that {} names a model the server provides.", MODEL_VAR)],
+            LlmError::Api { .. } | LlmError::InvalidResponse { .. } => {
+                vec![format!("Check that {} points at an API of the configured provider.", BASE_URL_VAR)]
+            }
+        }
+    }
+
+    fn subsystem(&self) -> &'static str {
+        "agent"
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub role: Role,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn system(content: impl Into<String>) -> Self {
+        Self { role: Role::System, content: content.into() }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self { role: Role::User, content: content.into() }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self { role: Role::Assistant, content: content.into() }
+    }
+}
+
+/// Sampling options shared by all providers. Unset fields use the server's defaults.
+#[derive(Debug, Clone, Default)]
+pub struct CompletionOptions {
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    pub stop: Vec<String>,
+}
+
+impl CompletionOptions {
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_stop(mut self, stop: impl Into<String>) -> Self {
+        self.stop.push(stop.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatResponse {
+    pub content: String,
+    /// The model that answered, as reported by the server.
+    pub model: String,
+    pub usage: Option<Usage>,
+}
+
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// Short provider name used in errors and logs, e.g. `openai`.
+    fn name(&self) -> &str;
+
+    /// The model behind [`embed`](Self::embed). Vectors from different models
+    /// are not comparable, so stored embeddings are tagged with it.
+    fn embed_model(&self) -> &str {
+        self.name()
+    }
+
+    /// Sends a conversation and returns the assistant's reply.
+    async fn chat(&self, messages: &[ChatMessage], options: &CompletionOptions) -> Result<ChatResponse, LlmError>;
+
+    /// Embeds each input, returning one vector per input in the same order.
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, LlmError>;
+
+    /// Completes a single prompt. The default sends it as one user message.
+    async fn complete(&self, prompt: &str, options: &CompletionOptions) -> Result<String, LlmError> {
+        Ok(self.chat(&[ChatMessage::user(prompt)], options).await?.content)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderKind {
+    OpenAi,
+    Ollama,
+}
+
+impl ProviderKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProviderKind::OpenAi => "openai",
+            ProviderKind::Ollama => "ollama",
+        }
+    }
+}
+
+/// Provider settings, usually read with [`LlmConfig::from_env`].
+#[derive(Debug, Clone)]
+pub struct LlmConfig {
+    pub provider: ProviderKind,
+    pub model: String,
+    pub embed_model: String,
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub timeout: Duration,
+}
+
+impl LlmConfig {
+    pub fn new(provider: ProviderKind) -> Self {
+        let (model, embed_model, base_url) = match provider {
+            ProviderKind::OpenAi => ("gpt-4o-mini", "text-embedding-3-small", "https://api.openai.com/v1"),
+            ProviderKind::Ollama => ("llama3.1", "nomic-embed-text", "http://localhost:11434"),
+        };
+        Self {
+            provider,
+            model: model.to_string(),
+            embed_model: embed_model.to_string(),
+            base_url: base_url.to_string(),
+            api_key: None,
+            timeout: Duration::from_secs(60),
+        }
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    pub fn with_embed_model(mut self, model: impl Into<String>) -> Self {
+        self.embed_model = model.into();
+        self
+    }
+
+    pub fn with_base_url(mut self, url: impl Into<String>) -> Self {
+        self.base_url = url.into();
+        self
+    }
+
+    pub fn with_api_key(mut self, key: impl Into<String>) -> Self {
+        self.api_key = Some(key.into());
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Reads the `MONTRS_LLM_*` variables, filling the gaps with the provider's defaults.
+    pub fn from_env(env: &dyn EnvConfig) -> Result<Self, LlmError> {
+        let var = |key: &str| env.get_var(key).ok().filter(|v| !v.trim().is_empty());
+        let api_key = var(API_KEY_VAR).or_else(|| var("OPENAI_API_KEY"));
+        let provider = match var(PROVIDER_VAR).as_deref().map(str::to_lowercase).as_deref() {
+            Some("openai") => ProviderKind::OpenAi,
+            Some("ollama") => ProviderKind::Ollama,
+            Some(other) => {
+                return Err(LlmError::Config(format!("unknown provider '{}' in {}", other, PROVIDER_VAR)));
+            }
+            None if api_key.is_some() => ProviderKind::OpenAi,
+            None => ProviderKind::Ollama,
+        };
+
+        let mut config = Self::new(provider);
+        config.api_key = api_key;
+        if let Some(model) = var(MODEL_VAR) {
+            config.model = model;
+        }
+        if let Some(model) = var(EMBED_MODEL_VAR) {
+            config.embed_model = model;
+        }
+        if let Some(url) = var(BASE_URL_VAR) {
+            config.base_url = url;
+        }
+        if let Some(secs) = var(TIMEOUT_VAR) {
+            let secs = secs
+                .parse()
+                .map_err(|_| LlmError::Config(format!("{} must be a number of seconds, got '{}'", TIMEOUT_VAR, secs)))?;
+            config.timeout = Duration::from_secs(secs);
+        }
+        if provider == ProviderKind::OpenAi && config.api_key.is_none() && config.base_url.contains("api.openai.com") {
+            return Err(LlmError::Config(format!("the OpenAI API needs {} or OPENAI_API_KEY", API_KEY_VAR)));
+        }
+        config.base_url = config.base_url.trim_end_matches('/').to_string();
+        Ok(config)
+    }
+
+    /// Builds the configured provider.
+    pub fn build(self) -> Result<Box<dyn LlmProvider>, LlmError> {
+        match self.provider {
+            #[cfg(feature = "openai")]
+            ProviderKind::OpenAi => Ok(Box::new(openai::OpenAiProvider::new(self)?)),
+            #[cfg(feature = "ollama")]
+            ProviderKind::Ollama => Ok(Box::new(ollama::OllamaProvider::new(self)?)),
+            #[allow(unreachable_patterns)]
+            kind => Err(LlmError::NotCompiled(kind.as_str().to_string())),
+        }
+    }
+}
+
+/// Configures a provider from the environment; see the module docs for the variables.
+pub fn provider_from_env(env: &dyn EnvConfig) -> Result<Box<dyn LlmProvider>, LlmError> {
+    LlmConfig::from_env(env)?.build()
+}
+
+#[cfg(any(feature = "openai", feature = "ollama"))]
+pub(crate) fn http_client(config: &LlmConfig) -> Result<reqwest::Client, LlmError> {
+    reqwest::Client::builder()
+        .timeout(config.timeout)
+        .build()
+        .map_err(|e| LlmError::Config(e.to_string()))
+}
+
+/// Posts `body` as JSON and decodes the JSON answer, mapping failures to [`LlmError`].
+#[cfg(any(feature = "openai", feature = "ollama"))]
+pub(crate) async fn post_json<T: serde::de::DeserializeOwned>(
+    provider: &str,
+    client: &reqwest::Client,
+    url: &str,
+    bearer: Option<&str>,
+    body: &serde_json::Value,
+) -> Result<T, LlmError> {
+    let mut request = client.post(url).json(body);
+    if let Some(token) = bearer {
+        request = request.bearer_auth(token);
+    }
+    let http = |e: reqwest::Error| LlmError::Http { url: url.to_string(), reason: e.to_string() };
+    let response = request.send().await.map_err(http)?;
+    let status = response.status();
+    let text = response.text().await.map_err(http)?;
+    if !status.is_success() {
+        // Both APIs wrap failures as `{"error": "..."}` or `{"error": {"message": "..."}}`.
+        let message = serde_json::from_str::<serde_json::Value>(&text)
+            .ok()
+            .and_then(|v| {
+                let error = v.get("error")?;
+                error.get("message").unwrap_or(error).as_str().map(str::to_string)
+            })
+            .unwrap_or(text);
+        return Err(LlmError::Api { provider: provider.to_string(), status: status.as_u16(), message });
+    }
+    serde_json::from_str(&text).map_err(|e| LlmError::InvalidResponse { provider: provider.to_string(), reason: e.to_string() })
+}
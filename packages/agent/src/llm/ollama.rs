@@ -0,0 +1,122 @@
+//! Client for a local Ollama daemon.
+
+use super::{post_json, ChatMessage, ChatResponse, CompletionOptions, LlmConfig, LlmError, LlmProvider, Usage};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+pub struct OllamaProvider {
+    config: LlmConfig,
+    client: reqwest::Client,
+}
+
+impl OllamaProvider {
+    pub fn new(config: LlmConfig) -> Result<Self, LlmError> {
+        Ok(Self { client: super::http_client(&config)?, config })
+    }
+
+    fn url(&self, endpoint: &str) -> String {
+        format!("{}/api/{}", self.config.base_url, endpoint)
+    }
+
+    /// Ollama takes sampling settings in an `options` object, with `num_predict`
+    /// in place of `max_tokens`.
+    fn options(options: &CompletionOptions) -> Value {
+        let mut out = json!({});
+        if let Some(temperature) = options.temperature {
+            out["temperature"] = json!(temperature);
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            out["num_predict"] = json!(max_tokens);
+        }
+        if !options.stop.is_empty() {
+            out["stop"] = json!(options.stop);
+        }
+        out
+    }
+
+    fn usage(prompt: Option<u32>, completion: Option<u32>) -> Option<Usage> {
+        Some(Usage {
+            prompt_tokens: prompt?,
+            completion_tokens: completion?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct ChatReply {
+    #[serde(default)]
+    model: String,
+    message: ReplyMessage,
+    #[serde(default)]
+    prompt_eval_count: Option<u32>,
+    #[serde(default)]
+    eval_count: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ReplyMessage {
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct GenerateReply {
+    response: String,
+}
+
+#[derive(Deserialize)]
+struct EmbedReply {
+    embeddings: Vec<Vec<f32>>,
+}
+
+#[async_trait]
+impl LlmProvider for OllamaProvider {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    fn embed_model(&self) -> &str {
+        &self.config.embed_model
+    }
+
+    async fn chat(&self, messages: &[ChatMessage], options: &CompletionOptions) -> Result<ChatResponse, LlmError> {
+        let body = json!({
+            "model": self.config.model,
+            "messages": messages,
+            "stream": false,
+            "options": Self::options(options),
+        });
+        let reply: ChatReply = post_json(self.name(), &self.client, &self.url("chat"), None, &body).await?;
+        Ok(ChatResponse {
+            content: reply.message.content,
+            model: reply.model,
+            usage: Self::usage(reply.prompt_eval_count, reply.eval_count),
+        })
+    }
+
+    async fn complete(&self, prompt: &str, options: &CompletionOptions) -> Result<String, LlmError> {
+        let body = json!({
+            "model": self.config.model,
+            "prompt": prompt,
+            "stream": false,
+            "options": Self::options(options),
+        });
+        let reply: GenerateReply = post_json(self.name(), &self.client, &self.url("generate"), None, &body).await?;
+        Ok(reply.response)
+    }
+
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, LlmError> {
+        if inputs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let body = json!({ "model": self.config.embed_model, "input": inputs });
+        let reply: EmbedReply = post_json(self.name(), &self.client, &self.url("embed"), None, &body).await?;
+        if reply.embeddings.len() != inputs.len() {
+            return Err(LlmError::InvalidResponse {
+                provider: self.name().to_string(),
+                reason: format!("expected {} embeddings, got {}", inputs.len(), reply.embeddings.len()),
+            });
+        }
+        Ok(reply.embeddings)
+    }
+}
@@ -0,0 +1,125 @@
+//! Client for the OpenAI API and servers compatible with it.
+
+use super::{post_json, ChatMessage, ChatResponse, CompletionOptions, LlmConfig, LlmError, LlmProvider, Usage};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+
+pub struct OpenAiProvider {
+    config: LlmConfig,
+    client: reqwest::Client,
+}
+
+impl OpenAiProvider {
+    pub fn new(config: LlmConfig) -> Result<Self, LlmError> {
+        Ok(Self { client: super::http_client(&config)?, config })
+    }
+
+    fn url(&self, endpoint: &str) -> String {
+        format!("{}/{}", self.config.base_url, endpoint)
+    }
+}
+
+#[derive(Deserialize)]
+struct ChatCompletion {
+    #[serde(default)]
+    model: String,
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<TokenUsage>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct ChoiceMessage {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenUsage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
+}
+
+#[derive(Deserialize)]
+struct Embeddings {
+    data: Vec<Embedding>,
+}
+
+#[derive(Deserialize)]
+struct Embedding {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiProvider {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    fn embed_model(&self) -> &str {
+        &self.config.embed_model
+    }
+
+    async fn chat(&self, messages: &[ChatMessage], options: &CompletionOptions) -> Result<ChatResponse, LlmError> {
+        let mut body = json!({ "model": self.config.model, "messages": messages });
+        if let Some(temperature) = options.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            body["max_tokens"] = json!(max_tokens);
+        }
+        if !options.stop.is_empty() {
+            body["stop"] = json!(options.stop);
+        }
+
+        let completion: ChatCompletion = post_json(
+            self.name(),
+            &self.client,
+            &self.url("chat/completions"),
+            self.config.api_key.as_deref(),
+            &body,
+        )
+        .await?;
+        let content = completion
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|c| c.message.content)
+            .ok_or_else(|| LlmError::InvalidResponse {
+                provider: self.name().to_string(),
+                reason: "the completion has no message content".to_string(),
+            })?;
+        Ok(ChatResponse {
+            content,
+            model: completion.model,
+            usage: completion.usage.map(|u| Usage {
+                prompt_tokens: u.prompt_tokens,
+                completion_tokens: u.completion_tokens,
+            }),
+        })
+    }
+
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, LlmError> {
+        if inputs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let body = json!({ "model": self.config.embed_model, "input": inputs });
+        let mut embeddings: Embeddings =
+            post_json(self.name(), &self.client, &self.url("embeddings"), self.config.api_key.as_deref(), &body).await?;
+        if embeddings.data.len() != inputs.len() {
+            return Err(LlmError::InvalidResponse {
+                provider: self.name().to_string(),
+                reason: format!("expected {} embeddings, got {}", inputs.len(), embeddings.data.len()),
+            });
+        }
+        embeddings.data.sort_by_key(|e| e.index);
+        Ok(embeddings.data.into_iter().map(|e| e.embedding).collect())
+    }
+}
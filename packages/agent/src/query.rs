@@ -0,0 +1,191 @@
+//! Searching tracked error records.
+//!
+//! [`AgentManager::query_errors`] reads the latest version of each record in
+//! `.agent/errorfiles`, keeps those matching an [`ErrorQuery`], sorts them and
+//! returns one page. The CLI's `agent errors` and the MCP `agent_list_errors`
+//! tool are thin wrappers around it.
+
+use crate::{AgentManager, ErrorRecord, ErrorStatus};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::collections::HashMap;
+use std::fs;
+
+/// Which records [`AgentManager::query_errors`] returns. Unset filters match
+/// everything.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorQuery {
+    /// Package name; `orm` also matches `montrs-orm`.
+    pub package: Option<String>,
+    /// Glob over the file path, e.g. `src/routes/**` or `*.rs`.
+    pub file: Option<String>,
+    /// `error` or `warning`, case-insensitive.
+    pub level: Option<String>,
+    pub status: Option<ErrorStatus>,
+    /// Compiler or agent code such as `E0308`, case-insensitive.
+    pub error_code: Option<String>,
+    /// Only records last updated at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only records last updated before this time.
+    pub until: Option<DateTime<Utc>>,
+    pub sort: ErrorSort,
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorSort {
+    #[default]
+    Newest,
+    Oldest,
+    /// By file, then line.
+    File,
+    /// By package, then file and line.
+    Package,
+}
+
+impl std::str::FromStr for ErrorSort {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "newest" => Ok(ErrorSort::Newest),
+            "oldest" => Ok(ErrorSort::Oldest),
+            "file" => Ok(ErrorSort::File),
+            "package" => Ok(ErrorSort::Package),
+            other => anyhow::bail!("Unknown sort '{}'; expected newest, oldest, file or package", other),
+        }
+    }
+}
+
+/// Parses `active`/`pending` and `resolved`/`fixed`, the names used by error
+/// records and by `error_tracking.json` respectively.
+pub fn parse_status(s: &str) -> Result<ErrorStatus> {
+    match s.to_ascii_lowercase().as_str() {
+        "active" | "pending" => Ok(ErrorStatus::Active),
+        "resolved" | "fixed" => Ok(ErrorStatus::Resolved),
+        other => anyhow::bail!("Unknown error status '{}'; expected active or resolved", other),
+    }
+}
+
+/// Parses a point in time relative to now (`30m`, `12h`, `7d`, `2w`), a date
+/// (`2024-05-01`, midnight UTC) or an RFC 3339 timestamp.
+pub fn parse_time(s: &str) -> Result<DateTime<Utc>> {
+    parse_time_at(s, Utc::now())
+}
+
+/// [`parse_time`] with relative times counted back from `now`.
+pub fn parse_time_at(s: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let s = s.trim();
+    if let Some(unit) = s.chars().last().filter(|c| c.is_ascii_alphabetic())
+        && let Ok(amount) = s[..s.len() - 1].parse::<i64>()
+    {
+        let duration = match unit {
+            'm' => Duration::minutes(amount),
+            'h' => Duration::hours(amount),
+            'd' => Duration::days(amount),
+            'w' => Duration::weeks(amount),
+            _ => anyhow::bail!("Unknown time unit in '{}'; expected m, h, d or w", s),
+        };
+        return Ok(now - duration);
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).expect("midnight exists").and_utc());
+    }
+    DateTime::parse_from_rfc3339(s)
+        .map(|time| time.with_timezone(&Utc))
+        .with_context(|| format!("Invalid time '{}'; expected e.g. 7d, 2024-05-01 or an RFC 3339 timestamp", s))
+}
+
+/// One page of matching records.
+#[derive(Debug, Clone)]
+pub struct ErrorPage {
+    pub errors: Vec<ErrorRecord>,
+    /// Matching records before paging.
+    pub total: usize,
+    pub offset: usize,
+}
+
+impl ErrorPage {
+    /// The offset of the next page, if there is one.
+    pub fn next_offset(&self) -> Option<usize> {
+        let end = self.offset + self.errors.len();
+        (end < self.total).then_some(end)
+    }
+}
+
+impl ErrorQuery {
+    fn matches(&self, record: &ErrorRecord, file: Option<&regex::Regex>) -> bool {
+        let detail = &record.detail;
+        if let Some(package) = &self.package {
+            let name = detail.package.as_deref().unwrap_or_default();
+            if name != package && name.strip_prefix("montrs-") != Some(package.as_str()) {
+                return false;
+            }
+        }
+        if file.is_some_and(|glob| !glob.is_match(detail.file.trim_start_matches("./"))) {
+            return false;
+        }
+        if self.level.as_ref().is_some_and(|level| !detail.level.eq_ignore_ascii_case(level)) {
+            return false;
+        }
+        if self.status.as_ref().is_some_and(|status| status != &record.status) {
+            return false;
+        }
+        if let Some(code) = &self.error_code {
+            let actual = detail.agent_metadata.as_ref().map(|meta| meta.error_code.as_str()).unwrap_or_default();
+            if !actual.eq_ignore_ascii_case(code) {
+                return false;
+            }
+        }
+        self.since.is_none_or(|since| record.timestamp >= since) && self.until.is_none_or(|until| record.timestamp < until)
+    }
+}
+
+impl AgentManager {
+    /// The latest version of every tracked error record.
+    pub fn error_records(&self) -> Result<Vec<ErrorRecord>> {
+        let mut latest: HashMap<String, ErrorRecord> = HashMap::new();
+        let error_dir = self.errorfiles_dir();
+        if !error_dir.exists() {
+            return Ok(Vec::new());
+        }
+        for version_dir in fs::read_dir(error_dir)?.flatten().filter(|entry| entry.path().is_dir()) {
+            for file in fs::read_dir(version_dir.path())?.flatten() {
+                if file.path().extension().and_then(|s| s.to_str()) != Some("json") {
+                    continue;
+                }
+                let Some(record) = fs::read_to_string(file.path()).ok().and_then(|c| serde_json::from_str::<ErrorRecord>(&c).ok()) else {
+                    continue;
+                };
+                if latest.get(&record.id).is_none_or(|current| record.version > current.version) {
+                    latest.insert(record.id.clone(), record);
+                }
+            }
+        }
+        Ok(latest.into_values().collect())
+    }
+
+    /// The page of records matching `query`.
+    pub fn query_errors(&self, query: &ErrorQuery) -> Result<ErrorPage> {
+        let file = query
+            .file
+            .as_deref()
+            .map(|glob| crate::redact::glob_regex(glob).with_context(|| format!("Invalid file glob '{}'", glob)))
+            .transpose()?;
+        let mut errors: Vec<ErrorRecord> = self.error_records()?.into_iter().filter(|record| query.matches(record, file.as_ref())).collect();
+        match query.sort {
+            ErrorSort::Newest => errors.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then_with(|| a.id.cmp(&b.id))),
+            ErrorSort::Oldest => errors.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id))),
+            ErrorSort::File => errors.sort_by(|a, b| location(a).cmp(&location(b))),
+            ErrorSort::Package => errors.sort_by(|a, b| (&a.detail.package, location(a)).cmp(&(&b.detail.package, location(b)))),
+        }
+        let total = errors.len();
+        let errors = errors.into_iter().skip(query.offset).take(query.limit.unwrap_or(usize::MAX)).collect();
+        Ok(ErrorPage { errors, total, offset: query.offset })
+    }
+}
+
+fn location(record: &ErrorRecord) -> (&str, u32, u32, &str) {
+    (&record.detail.file, record.detail.line, record.detail.column, &record.id)
+}
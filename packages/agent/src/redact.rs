@@ -0,0 +1,286 @@
+//! Redaction of secrets from agent snapshots and model prompts.
+//!
+//! Documentation snippets, file descriptions and source excerpts are copied
+//! verbatim from the project, so a `.env` example or a key pasted into a
+//! comment would end up in `.agent` or in a request to a model. A
+//! [`Redactor`] replaces matches of its pattern rules with
+//! `[REDACTED:<rule>]` and withholds the whole content of files matching its
+//! path rules. [`Redactor::default`] covers common secret formats; the
+//! `[agent.redact]` section of `montrs.toml` adds rules or turns the defaults
+//! off:
+//!
+//! ```toml
+//! [agent.redact]
+//! defaults = true
+//! paths = ["config/production/**"]
+//! rules = [{ name = "internal-host", pattern = "[a-z0-9-]+\\.corp\\.example\\.com" }]
+//! ```
+
+use crate::AgentSnapshot;
+use regex::Regex;
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Pattern rules applied unless `defaults = false`: `(name, regex)`. When a
+/// group whose name starts with `secret` matches, only that group is replaced,
+/// so the key in `API_KEY=...` stays readable. Assignments only count as
+/// secrets when the value is quoted or follows `=` without spaces, which keeps
+/// code like `let token = fetch_token()` intact.
+pub const DEFAULT_RULES: &[(&str, &str)] = &[
+    ("private-key", r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?(?:-----END [A-Z ]*PRIVATE KEY-----|\z)"),
+    ("aws-access-key", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+    ("github-token", r"\b(?:gh[pousr]_[A-Za-z0-9]{36,}|github_pat_[A-Za-z0-9_]{22,})\b"),
+    ("slack-token", r"\bxox[abposr]-[A-Za-z0-9-]{10,}"),
+    ("stripe-key", r"\b[rs]k_(?:live|test)_[A-Za-z0-9]{16,}\b"),
+    ("openai-key", r"\bsk-(?:proj-|ant-)?[A-Za-z0-9_-]{20,}"),
+    ("google-api-key", r"\bAIza[0-9A-Za-z_-]{35}\b"),
+    ("jwt", r"\beyJ[A-Za-z0-9_-]{8,}\.eyJ[A-Za-z0-9_-]{8,}\.[A-Za-z0-9_-]{8,}"),
+    ("url-credentials", r"[a-z][a-z0-9+.-]*://[^\s:/@]+:(?P<secret>[^\s@/]+)@"),
+    (
+        "assignment",
+        r#"(?i)\b[A-Z0-9_.-]*(?:password|passwd|secret|token|api[_-]?key|access[_-]?key|private[_-]?key)[A-Z0-9_]*(?:=(?P<secret>[^\s"'$<\[][^\s"']{5,})|["']?\s*[:=]\s*["'](?P<secret_quoted>[^\s"'$<\[][^"'\n]{5,})["'])"#,
+    ),
+];
+
+/// Files whose content is withheld unless `defaults = false`. Globs without
+/// a `/` match the file name anywhere in the project.
+pub const DEFAULT_PATHS: &[&str] = &[".env", ".env.*", "*.pem", "*.key", "*.p12", "*.pfx", "id_rsa*", "id_ed25519*", "secrets.enc.toml"];
+
+static DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turns redaction off for the rest of the process (`--no-redact`).
+pub fn disable() {
+    DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether [`disable`] was called.
+pub fn is_disabled() -> bool {
+    DISABLED.load(Ordering::Relaxed)
+}
+
+/// The `[agent.redact]` section of `montrs.toml`.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RedactConfig {
+    /// Whether [`DEFAULT_RULES`] and [`DEFAULT_PATHS`] apply.
+    #[serde(default = "default_true")]
+    pub defaults: bool,
+    /// Extra path globs whose content is withheld.
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Extra pattern rules.
+    #[serde(default)]
+    pub rules: Vec<RuleConfig>,
+}
+
+impl Default for RedactConfig {
+    fn default() -> Self {
+        Self { defaults: true, paths: Vec::new(), rules: Vec::new() }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RuleConfig {
+    pub name: String,
+    pub pattern: String,
+}
+
+struct PatternRule {
+    name: String,
+    regex: Regex,
+}
+
+struct PathRule {
+    glob: String,
+    regex: Regex,
+}
+
+/// Replaces secrets in text before it leaves the machine or lands in `.agent`.
+pub struct Redactor {
+    rules: Vec<PatternRule>,
+    paths: Vec<PathRule>,
+}
+
+/// How many replacements each rule made.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Redactions(pub BTreeMap<String, usize>);
+
+impl Redactions {
+    pub fn total(&self) -> usize {
+        self.0.values().sum()
+    }
+
+    fn add(&mut self, rule: &str) {
+        *self.0.entry(rule.to_string()).or_default() += 1;
+    }
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self::from_config(&RedactConfig::default()).expect("default redaction rules compile")
+    }
+}
+
+impl Redactor {
+    /// A redactor without rules, which changes nothing.
+    pub fn none() -> Self {
+        Self { rules: Vec::new(), paths: Vec::new() }
+    }
+
+    /// The defaults (unless turned off) plus the configured rules. Fails on an
+    /// invalid pattern or glob, so a typo never silently disables a rule.
+    pub fn from_config(config: &RedactConfig) -> anyhow::Result<Self> {
+        let mut redactor = Self::none();
+        if config.defaults {
+            for (name, pattern) in DEFAULT_RULES {
+                redactor = redactor.with_rule(name, pattern)?;
+            }
+            for glob in DEFAULT_PATHS {
+                redactor = redactor.with_path(glob)?;
+            }
+        }
+        for rule in &config.rules {
+            redactor = redactor.with_rule(&rule.name, &rule.pattern)?;
+        }
+        for glob in &config.paths {
+            redactor = redactor.with_path(glob)?;
+        }
+        Ok(redactor)
+    }
+
+    /// For `config`, or [`Redactor::none`] after [`disable`].
+    pub fn for_process(config: &RedactConfig) -> anyhow::Result<Self> {
+        if is_disabled() { Ok(Self::none()) } else { Self::from_config(config) }
+    }
+
+    pub fn with_rule(mut self, name: &str, pattern: &str) -> anyhow::Result<Self> {
+        let regex = Regex::new(pattern).map_err(|e| anyhow::anyhow!("Invalid redaction rule `{}`: {}", name, e))?;
+        self.rules.push(PatternRule { name: name.to_string(), regex });
+        Ok(self)
+    }
+
+    pub fn with_path(mut self, glob: &str) -> anyhow::Result<Self> {
+        let regex = glob_regex(glob).map_err(|e| anyhow::anyhow!("Invalid redaction path `{}`: {}", glob, e))?;
+        self.paths.push(PathRule { glob: glob.to_string(), regex });
+        Ok(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty() && self.paths.is_empty()
+    }
+
+    /// The path glob `path` matches, if any. `path` is relative to the
+    /// project root, with `/` separators.
+    pub fn path_rule(&self, path: &str) -> Option<&str> {
+        let path = path.trim_start_matches("./");
+        self.paths.iter().find(|rule| rule.regex.is_match(path)).map(|rule| rule.glob.as_str())
+    }
+
+    /// Applies the pattern rules to `text`.
+    pub fn redact<'a>(&self, text: &'a str, redactions: &mut Redactions) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for rule in &self.rules {
+            if !rule.regex.is_match(&text) {
+                continue;
+            }
+            let replacement = format!("[REDACTED:{}]", rule.name);
+            let replaced = rule.regex.replace_all(&text, |caps: &regex::Captures| {
+                redactions.add(&rule.name);
+                let secret = rule.regex.capture_names().flatten().filter(|name| name.starts_with("secret")).find_map(|name| caps.name(name));
+                match secret {
+                    Some(secret) => {
+                        let whole = caps.get(0).expect("group 0 always matches");
+                        let start = secret.start() - whole.start();
+                        let end = secret.end() - whole.start();
+                        format!("{}{}{}", &whole.as_str()[..start], replacement, &whole.as_str()[end..])
+                    }
+                    None => replacement.clone(),
+                }
+            });
+            text = Cow::Owned(replaced.into_owned());
+        }
+        text
+    }
+
+    /// Content that came from the file at `path`: withheld entirely when a
+    /// path rule matches, otherwise passed through the pattern rules.
+    pub fn redact_file(&self, path: &str, content: &str, redactions: &mut Redactions) -> String {
+        match self.path_rule(path) {
+            Some(glob) => {
+                redactions.add(glob);
+                format!("[REDACTED: {} matches `{}`]", path, glob)
+            }
+            None => self.redact(content, redactions).into_owned(),
+        }
+    }
+
+    /// Redacts the parts of `snapshot` copied from the project: file
+    /// descriptions, documentation snippets, package invariants and the
+    /// descriptions and metadata of plates and routes.
+    pub fn redact_snapshot(&self, snapshot: &mut AgentSnapshot) -> Redactions {
+        let mut redactions = Redactions::default();
+        if self.is_empty() {
+            return redactions;
+        }
+        for file in &mut snapshot.structure {
+            if let Some(description) = &mut file.description {
+                *description = self.redact_file(&file.path, description, &mut redactions);
+            }
+        }
+        for (key, content) in &mut snapshot.documentation_snippets {
+            *content = self.redact_file(key, content, &mut redactions);
+        }
+        for package in &mut snapshot.packages {
+            if let Some(invariants) = &mut package.invariants {
+                *invariants = self.redact(invariants, &mut redactions).into_owned();
+            }
+            if let Some(description) = &mut package.description {
+                *description = self.redact(description, &mut redactions).into_owned();
+            }
+        }
+        for plate in &mut snapshot.plates {
+            plate.description = self.redact(&plate.description, &mut redactions).into_owned();
+            for value in plate.metadata.values_mut() {
+                *value = self.redact(value, &mut redactions).into_owned();
+            }
+        }
+        for route in &mut snapshot.routes {
+            route.description = self.redact(&route.description, &mut redactions).into_owned();
+            for value in route.metadata.values_mut() {
+                *value = self.redact(value, &mut redactions).into_owned();
+            }
+        }
+        redactions
+    }
+}
+
+/// `*` and `?` stay within a path segment and `**` crosses them. Globs without
+/// a `/` match the last segment, like `.gitignore`.
+pub(crate) fn glob_regex(glob: &str) -> Result<Regex, regex::Error> {
+    let glob = glob.trim_start_matches("./");
+    let mut pattern = String::from(if glob.contains('/') { "^" } else { "(?:^|/)" });
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    pattern.push_str("(?:.*/)?");
+                } else {
+                    pattern.push_str(".*");
+                }
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern)
+}
@@ -0,0 +1,265 @@
+//! Documentation search for agents.
+//!
+//! Documentation snippets and package invariants are split into chunks at
+//! Markdown headings. [`AgentManager::update_embeddings`] embeds them with an
+//! [`LlmProvider`] into `.agent/embeddings/index.json`, re-embedding only chunks
+//! whose text changed. [`AgentManager::semantic_search`] ranks chunks by cosine
+//! similarity to the query; [`keyword_search`] works without a model.
+
+use crate::llm::LlmProvider;
+use crate::{AgentManager, AgentSnapshot};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::PathBuf;
+
+pub const EMBEDDINGS_DIR: &str = "embeddings";
+pub const EMBEDDINGS_INDEX: &str = "index.json";
+/// Chunks longer than this are split further at paragraph breaks.
+const MAX_CHUNK_CHARS: usize = 1500;
+/// Inputs per embedding request.
+const EMBED_BATCH: usize = 32;
+
+/// A piece of documentation that is indexed and returned as a unit.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DocChunk {
+    /// The snippet key, e.g. `packages/core/docs/router.md`.
+    pub key: String,
+    /// Position of the chunk within its snippet.
+    pub chunk: usize,
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EmbeddedChunk {
+    #[serde(flatten)]
+    pub doc: DocChunk,
+    /// FNV-1a hash of the text, to skip unchanged chunks on update.
+    pub hash: u64,
+    pub vector: Vec<f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct EmbeddingIndex {
+    /// `provider/model` that produced the vectors.
+    pub model: String,
+    pub dimensions: usize,
+    pub chunks: Vec<EmbeddedChunk>,
+}
+
+/// What an index update did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexStats {
+    pub chunks: usize,
+    pub embedded: usize,
+    pub reused: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    pub key: String,
+    pub text: String,
+    /// Cosine similarity for semantic search, share of query terms for keyword search.
+    pub score: f32,
+}
+
+/// Splits the snapshot's documentation snippets and package invariants into chunks.
+pub fn chunk_documents(snapshot: &AgentSnapshot) -> Vec<DocChunk> {
+    let mut sources: Vec<(String, &str)> = snapshot
+        .documentation_snippets
+        .iter()
+        .map(|(key, text)| (key.clone(), text.as_str()))
+        .collect();
+    for package in &snapshot.packages {
+        if let Some(invariants) = &package.invariants {
+            sources.push((format!("packages/{}/invariants", package.name), invariants));
+        }
+    }
+    sources.sort_by(|a, b| a.0.cmp(&b.0));
+    sources.dedup_by(|a, b| a.0 == b.0);
+
+    let mut chunks = Vec::new();
+    for (key, text) in sources {
+        for (chunk, text) in split_markdown(text).into_iter().enumerate() {
+            chunks.push(DocChunk { key: key.clone(), chunk, text });
+        }
+    }
+    chunks
+}
+
+/// Splits at headings, then at blank lines when a section is too long.
+fn split_markdown(text: &str) -> Vec<String> {
+    let mut sections: Vec<String> = Vec::new();
+    let mut in_fence = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if (!in_fence && line.starts_with('#')) || sections.is_empty() {
+            sections.push(String::new());
+        }
+        let current = sections.last_mut().expect("a section was pushed");
+        current.push_str(line);
+        current.push('\n');
+    }
+
+    let mut chunks = Vec::new();
+    for section in sections {
+        let mut current = String::new();
+        for paragraph in section.split("\n\n") {
+            if !current.is_empty() && current.len() + paragraph.len() > MAX_CHUNK_CHARS {
+                chunks.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push_str("\n\n");
+            }
+            current.push_str(paragraph);
+        }
+        chunks.push(current);
+    }
+    chunks.into_iter().map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect()
+}
+
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denominator = norm(a) * norm(b);
+    if denominator == 0.0 { 0.0 } else { dot / denominator }
+}
+
+fn terms(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|w| w.len() >= 3)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Ranks chunks by the share of query terms they contain.
+pub fn keyword_search(chunks: &[DocChunk], query: &str, top_k: usize) -> Vec<SearchResult> {
+    let query = terms(query);
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let mut results: Vec<SearchResult> = chunks
+        .iter()
+        .filter_map(|chunk| {
+            let words = terms(&chunk.text);
+            let hits = query.iter().filter(|t| words.contains(*t)).count();
+            (hits > 0).then(|| SearchResult {
+                key: chunk.key.clone(),
+                text: chunk.text.clone(),
+                score: hits as f32 / query.len() as f32,
+            })
+        })
+        .collect();
+    sort_and_truncate(&mut results, top_k);
+    results
+}
+
+fn sort_and_truncate(results: &mut Vec<SearchResult>, top_k: usize) {
+    results.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.key.cmp(&b.key)));
+    results.truncate(top_k);
+}
+
+impl EmbeddingIndex {
+    pub fn search(&self, query: &[f32], top_k: usize) -> Vec<SearchResult> {
+        let mut results: Vec<SearchResult> = self
+            .chunks
+            .iter()
+            .map(|chunk| SearchResult {
+                key: chunk.doc.key.clone(),
+                text: chunk.doc.text.clone(),
+                score: cosine(query, &chunk.vector),
+            })
+            .collect();
+        sort_and_truncate(&mut results, top_k);
+        results
+    }
+}
+
+impl AgentManager {
+    pub fn embeddings_file(&self) -> PathBuf {
+        self.agent_dir().join(EMBEDDINGS_DIR).join(EMBEDDINGS_INDEX)
+    }
+
+    /// The snapshot whose documentation is searched: the written one, or the
+    /// embedded framework docs when the project has none yet.
+    fn searchable_snapshot(&self) -> AgentSnapshot {
+        self.read_snapshot().unwrap_or_else(|_| self.generate_framework_snapshot())
+    }
+
+    pub fn load_embeddings(&self) -> Result<Option<EmbeddingIndex>> {
+        let path = self.embeddings_file();
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_reader(std::io::BufReader::new(fs::File::open(path)?))?))
+    }
+
+    /// Brings the embeddings index in line with the current documentation,
+    /// embedding new or changed chunks only. Vectors from another embedding
+    /// model are discarded.
+    pub async fn update_embeddings(&self, provider: &dyn LlmProvider) -> Result<IndexStats> {
+        let model = format!("{}/{}", provider.name(), provider.embed_model());
+        let chunks = chunk_documents(&self.searchable_snapshot());
+        let previous = self.load_embeddings()?.filter(|index| index.model == model).unwrap_or_default();
+        let mut known: HashMap<u64, Vec<f32>> =
+            previous.chunks.into_iter().map(|chunk| (chunk.hash, chunk.vector)).collect();
+
+        let hashes: Vec<u64> = chunks.iter().map(|c| fnv1a(&c.text)).collect();
+        let missing: Vec<usize> = (0..chunks.len()).filter(|&i| !known.contains_key(&hashes[i])).collect();
+        for batch in missing.chunks(EMBED_BATCH) {
+            let inputs: Vec<String> = batch.iter().map(|&i| chunks[i].text.clone()).collect();
+            let vectors = provider.embed(&inputs).await?;
+            for (&i, vector) in batch.iter().zip(vectors) {
+                known.insert(hashes[i], vector);
+            }
+        }
+
+        let embedded = missing.len();
+        let chunks: Vec<EmbeddedChunk> = chunks
+            .into_iter()
+            .zip(hashes)
+            .map(|(doc, hash)| EmbeddedChunk { doc, hash, vector: known.get(&hash).cloned().unwrap_or_default() })
+            .collect();
+        let index = EmbeddingIndex {
+            model,
+            dimensions: chunks.first().map(|c| c.vector.len()).unwrap_or_default(),
+            chunks,
+        };
+
+        if self.is_persistent() {
+            let path = self.embeddings_file();
+            fs::create_dir_all(path.parent().expect("the index lives in a directory"))?;
+            fs::write(&path, serde_json::to_string(&index)?)?;
+        }
+        Ok(IndexStats { chunks: index.chunks.len(), embedded, reused: index.chunks.len() - embedded })
+    }
+
+    /// Returns the `top_k` chunks closest in meaning to `query`, updating the
+    /// index first.
+    pub async fn semantic_search(&self, provider: &dyn LlmProvider, query: &str, top_k: usize) -> Result<Vec<SearchResult>> {
+        self.update_embeddings(provider).await?;
+        let index = self.load_embeddings()?.unwrap_or_default();
+        let query = provider.embed(&[query.to_string()]).await?.pop().unwrap_or_default();
+        if !index.chunks.is_empty() && query.len() != index.dimensions {
+            anyhow::bail!(
+                "{} returned a {}-dimensional query embedding for a {}-dimensional index",
+                index.model,
+                query.len(),
+                index.dimensions
+            );
+        }
+        Ok(index.search(&query, top_k))
+    }
+
+    /// Keyword search over the same chunks, for when no model is configured.
+    pub fn keyword_search(&self, query: &str, top_k: usize) -> Vec<SearchResult> {
+        keyword_search(&chunk_documents(&self.searchable_snapshot()), query, top_k)
+    }
+}
@@ -0,0 +1,264 @@
+//! Memory of earlier agent sessions.
+//!
+//! Each task an agent works on is a session stored as an append-only event log in
+//! `.agent/sessions/<id>.jsonl`: the goal, then the files it touched, the
+//! commands it ran and notes, then the outcome. Appending keeps concurrent
+//! writers from clobbering each other and a crash loses at most one line.
+//! [`AgentManager::sessions`] folds the logs back into [`Session`]s and
+//! [`AgentManager::resume_context`] summarizes the latest ones for the next
+//! session to read.
+
+use crate::AgentManager;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write as _};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+pub const SESSIONS_DIR: &str = "sessions";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionOutcome {
+    Completed,
+    Failed,
+    Abandoned,
+}
+
+impl fmt::Display for SessionOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SessionOutcome::Completed => "completed",
+            SessionOutcome::Failed => "failed",
+            SessionOutcome::Abandoned => "abandoned",
+        })
+    }
+}
+
+impl std::str::FromStr for SessionOutcome {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "completed" => Ok(SessionOutcome::Completed),
+            "failed" => Ok(SessionOutcome::Failed),
+            "abandoned" => Ok(SessionOutcome::Abandoned),
+            other => anyhow::bail!("Unknown session outcome '{}'; expected completed, failed or abandoned", other),
+        }
+    }
+}
+
+/// One line of a session log.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SessionEvent {
+    Started { goal: String },
+    FileTouched { path: String },
+    CommandRun { command: String, exit_code: Option<i32> },
+    Note { text: String },
+    Finished { outcome: SessionOutcome, summary: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct LogLine {
+    at: DateTime<Utc>,
+    #[serde(flatten)]
+    event: SessionEvent,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CommandRecord {
+    pub command: String,
+    pub exit_code: Option<i32>,
+}
+
+/// A session rebuilt from its log.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Session {
+    pub id: String,
+    pub goal: String,
+    pub started_at: DateTime<Utc>,
+    /// Time of the last event.
+    pub updated_at: DateTime<Utc>,
+    /// Files in the order they were first touched.
+    pub files: Vec<String>,
+    pub commands: Vec<CommandRecord>,
+    pub notes: Vec<String>,
+    pub outcome: Option<SessionOutcome>,
+    pub summary: Option<String>,
+}
+
+impl Session {
+    pub fn is_open(&self) -> bool {
+        self.outcome.is_none()
+    }
+
+    fn from_log(id: &str, lines: Vec<LogLine>) -> Result<Self> {
+        let Some(LogLine { at, event: SessionEvent::Started { goal } }) = lines.first().cloned() else {
+            anyhow::bail!("Session {} does not start with a `started` event", id);
+        };
+        let mut session = Session {
+            id: id.to_string(),
+            goal,
+            started_at: at,
+            updated_at: at,
+            files: Vec::new(),
+            commands: Vec::new(),
+            notes: Vec::new(),
+            outcome: None,
+            summary: None,
+        };
+        for line in lines.into_iter().skip(1) {
+            session.updated_at = line.at;
+            match line.event {
+                SessionEvent::Started { .. } => {}
+                SessionEvent::FileTouched { path } => {
+                    if !session.files.contains(&path) {
+                        session.files.push(path);
+                    }
+                }
+                SessionEvent::CommandRun { command, exit_code } => session.commands.push(CommandRecord { command, exit_code }),
+                SessionEvent::Note { text } => session.notes.push(text),
+                SessionEvent::Finished { outcome, summary } => {
+                    session.outcome = Some(outcome);
+                    session.summary = Some(summary);
+                }
+            }
+        }
+        Ok(session)
+    }
+
+    /// A Markdown section describing the session.
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let status = self.outcome.map(|o| o.to_string()).unwrap_or_else(|| "in progress".to_string());
+        let _ = writeln!(out, "### {} ({})\n", self.goal, status);
+        let _ = writeln!(out, "- Session: `{}`, started {}", self.id, self.started_at.format("%Y-%m-%d %H:%M UTC"));
+        if let Some(summary) = &self.summary {
+            let _ = writeln!(out, "- Summary: {}", summary);
+        }
+        if !self.files.is_empty() {
+            let files: Vec<String> = self.files.iter().map(|f| format!("`{}`", f)).collect();
+            let _ = writeln!(out, "- Files: {}", files.join(", "));
+        }
+        for command in &self.commands {
+            match command.exit_code {
+                Some(code) if code != 0 => {
+                    let _ = writeln!(out, "- Ran `{}` (exit {})", command.command, code);
+                }
+                _ => {
+                    let _ = writeln!(out, "- Ran `{}`", command.command);
+                }
+            }
+        }
+        for note in &self.notes {
+            let _ = writeln!(out, "- Note: {}", note);
+        }
+        out
+    }
+}
+
+impl AgentManager {
+    pub fn sessions_dir(&self) -> PathBuf {
+        self.agent_dir().join(SESSIONS_DIR)
+    }
+
+    fn session_file(&self, id: &str) -> PathBuf {
+        self.sessions_dir().join(format!("{}.jsonl", id))
+    }
+
+    /// Opens a session for `goal` and returns its id.
+    pub fn start_session(&self, goal: impl Into<String>) -> Result<String> {
+        if !self.is_persistent() {
+            anyhow::bail!(
+                "Agent artifacts are kept in memory, so sessions cannot be recorded; set {} to a writable directory",
+                crate::storage::AGENT_DIR_VAR
+            );
+        }
+        fs::create_dir_all(self.sessions_dir())?;
+        let now = Utc::now();
+        let suffix = uuid::Uuid::new_v4().simple().to_string();
+        let id = format!("{}-{}", now.format("%Y%m%dT%H%M%S"), &suffix[..6]);
+        self.append_session_line(&id, LogLine { at: now, event: SessionEvent::Started { goal: goal.into() } })?;
+        Ok(id)
+    }
+
+    /// Appends an event to an existing session.
+    pub fn record_session_event(&self, id: &str, event: SessionEvent) -> Result<()> {
+        if !self.session_file(id).exists() {
+            anyhow::bail!("No session with id {}", id);
+        }
+        if matches!(event, SessionEvent::Started { .. }) {
+            anyhow::bail!("Session {} has already started", id);
+        }
+        self.append_session_line(id, LogLine { at: Utc::now(), event })
+    }
+
+    pub fn finish_session(&self, id: &str, outcome: SessionOutcome, summary: impl Into<String>) -> Result<()> {
+        self.record_session_event(id, SessionEvent::Finished { outcome, summary: summary.into() })
+    }
+
+    fn append_session_line(&self, id: &str, line: LogLine) -> Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(self.session_file(id))?;
+        writeln!(file, "{}", serde_json::to_string(&line)?)?;
+        Ok(())
+    }
+
+    pub fn load_session(&self, id: &str) -> Result<Session> {
+        let path = self.session_file(id);
+        let file = fs::File::open(&path).with_context(|| format!("No session with id {}", id))?;
+        let mut lines = Vec::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            lines.push(
+                serde_json::from_str(&line).with_context(|| format!("{}:{}", path.display(), number + 1))?,
+            );
+        }
+        Session::from_log(id, lines)
+    }
+
+    /// All sessions, most recently started first.
+    pub fn sessions(&self) -> Result<Vec<Session>> {
+        let dir = self.sessions_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let ids: Vec<String> = fs::read_dir(dir)?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != "jsonl" {
+                    return None;
+                }
+                path.file_stem()?.to_str().map(str::to_string)
+            })
+            .collect();
+        let mut sessions = ids.iter().map(|id| self.load_session(id)).collect::<Result<Vec<_>>>()?;
+        sessions.sort_by(|a, b| b.started_at.cmp(&a.started_at).then_with(|| b.id.cmp(&a.id)));
+        Ok(sessions)
+    }
+
+    /// The most recent session that has not finished, if any.
+    pub fn open_session(&self) -> Result<Option<Session>> {
+        Ok(self.sessions()?.into_iter().find(Session::is_open))
+    }
+
+    /// A Markdown digest of the latest `limit` sessions, for a new session to
+    /// pick up where earlier ones left off.
+    pub fn resume_context(&self, limit: usize) -> Result<String> {
+        let sessions = self.sessions()?;
+        if sessions.is_empty() {
+            return Ok("No previous agent sessions.\n".to_string());
+        }
+        let mut out = format!("## Previous agent sessions ({} of {})\n\n", sessions.len().min(limit), sessions.len());
+        for session in sessions.iter().take(limit) {
+            out.push_str(&session.to_markdown());
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
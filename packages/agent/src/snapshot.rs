@@ -0,0 +1,333 @@
+//! Streaming storage for agent snapshots.
+//!
+//! `agent.json` holds the whole snapshot in one object, including every documentation
+//! snippet, so reading one route means parsing everything. This module adds:
+//! - `agent.jsonl`: one `SnapshotRecord` per line, read back with serde_json's
+//!   `StreamDeserializer` so only one record is in memory at a time.
+//! - `snippets/`: each documentation snippet in its own file, referenced from the
+//!   JSONL stream by a `SnippetRef`.
+//! - `read_section` / `read_snippet`: pull one field out of a regular `agent.json`
+//!   while skipping the rest of the document without allocating it.
+
+use crate::{
+    AccessSummary, AgentSnapshot, DeprecationSummary, FileEntry, PackageSummary, PlateSummary, PresetSummary, RouteSummary,
+};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::de::{DeserializeOwned, DeserializeSeed, IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::marker::PhantomData;
+use std::path::{Component, Path, PathBuf};
+
+/// File name of the streamed snapshot inside `.agent/`.
+pub const SNAPSHOT_JSONL: &str = "agent.jsonl";
+/// Directory inside `.agent/` holding one file per documentation snippet.
+pub const SNIPPETS_DIR: &str = "snippets";
+
+/// The scalar fields of an `AgentSnapshot`. Always the first record of a stream.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SnapshotHeader {
+    pub project_name: String,
+    pub timestamp: DateTime<Utc>,
+    pub framework_version: String,
+    pub agent_entry_point: Option<String>,
+}
+
+/// Points at a documentation snippet stored under `.agent/snippets/`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SnippetRef {
+    pub key: String,
+    /// Path relative to `.agent/`.
+    pub file: String,
+    pub bytes: usize,
+}
+
+/// One line of `agent.jsonl`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum SnapshotRecord {
+    Header(SnapshotHeader),
+    File(FileEntry),
+    Plate(PlateSummary),
+    Route(RouteSummary),
+    Package(PackageSummary),
+    Deprecation(DeprecationSummary),
+    Access(AccessSummary),
+    Preset(PresetSummary),
+    Snippet(SnippetRef),
+    Boot(montrs_core::BootTrace),
+}
+
+/// Writes snapshot records as JSON lines.
+pub struct SnapshotWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> SnapshotWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn write(&mut self, record: &SnapshotRecord) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Iterates over the records of a snapshot stream.
+pub struct SnapshotReader<R: Read> {
+    records: serde_json::StreamDeserializer<'static, serde_json::de::IoRead<R>, SnapshotRecord>,
+}
+
+impl<R: Read> SnapshotReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            records: serde_json::Deserializer::from_reader(reader).into_iter(),
+        }
+    }
+}
+
+impl SnapshotReader<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("Failed to open snapshot {}", path.display()))?;
+        Ok(Self::new(BufReader::new(file)))
+    }
+}
+
+impl<R: Read> Iterator for SnapshotReader<R> {
+    type Item = Result<SnapshotRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records
+            .next()
+            .map(|record| record.context("Malformed snapshot record"))
+    }
+}
+
+/// Streams `snapshot` into `agent_dir`: records go to `agent.jsonl` and each
+/// documentation snippet to its own file under `snippets/`.
+pub fn write_stream(snapshot: &AgentSnapshot, agent_dir: &Path) -> Result<()> {
+    let snippets_dir = agent_dir.join(SNIPPETS_DIR);
+    if snippets_dir.exists() {
+        fs::remove_dir_all(&snippets_dir)?;
+    }
+
+    let file = File::create(agent_dir.join(SNAPSHOT_JSONL))?;
+    let mut writer = SnapshotWriter::new(BufWriter::new(file));
+    writer.write(&SnapshotRecord::Header(SnapshotHeader {
+        project_name: snapshot.project_name.clone(),
+        timestamp: snapshot.timestamp,
+        framework_version: snapshot.framework_version.clone(),
+        agent_entry_point: snapshot.agent_entry_point.clone(),
+    }))?;
+    for entry in &snapshot.structure {
+        writer.write(&SnapshotRecord::File(entry.clone()))?;
+    }
+    for package in &snapshot.packages {
+        writer.write(&SnapshotRecord::Package(package.clone()))?;
+    }
+    for plate in &snapshot.plates {
+        writer.write(&SnapshotRecord::Plate(plate.clone()))?;
+    }
+    for route in &snapshot.routes {
+        writer.write(&SnapshotRecord::Route(route.clone()))?;
+    }
+    for deprecation in &snapshot.deprecations {
+        writer.write(&SnapshotRecord::Deprecation(deprecation.clone()))?;
+    }
+    for access in &snapshot.authorization {
+        writer.write(&SnapshotRecord::Access(access.clone()))?;
+    }
+    if let Some(preset) = &snapshot.preset {
+        writer.write(&SnapshotRecord::Preset(preset.clone()))?;
+    }
+    if let Some(boot) = &snapshot.boot {
+        writer.write(&SnapshotRecord::Boot(boot.clone()))?;
+    }
+
+    let mut keys: Vec<&String> = snapshot.documentation_snippets.keys().collect();
+    keys.sort();
+    for key in keys {
+        let content = &snapshot.documentation_snippets[key];
+        let file = snippet_file(key);
+        let path = agent_dir.join(&file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, content)?;
+        writer.write(&SnapshotRecord::Snippet(SnippetRef {
+            key: key.clone(),
+            file,
+            bytes: content.len(),
+        }))?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// Rebuilds a full snapshot from a stream written by [`write_stream`].
+pub fn read_stream(agent_dir: &Path) -> Result<AgentSnapshot> {
+    let mut header = None;
+    let mut snapshot = AgentSnapshot {
+        project_name: String::new(),
+        timestamp: Utc::now(),
+        framework_version: String::new(),
+        structure: Vec::new(),
+        plates: Vec::new(),
+        routes: Vec::new(),
+        packages: Vec::new(),
+        agent_entry_point: None,
+        documentation_snippets: HashMap::new(),
+        deprecations: Vec::new(),
+        authorization: Vec::new(),
+        preset: None,
+        boot: None,
+    };
+    for record in SnapshotReader::open(&agent_dir.join(SNAPSHOT_JSONL))? {
+        match record? {
+            SnapshotRecord::Header(h) => header = Some(h),
+            SnapshotRecord::File(f) => snapshot.structure.push(f),
+            SnapshotRecord::Plate(p) => snapshot.plates.push(p),
+            SnapshotRecord::Route(r) => snapshot.routes.push(r),
+            SnapshotRecord::Package(p) => snapshot.packages.push(p),
+            SnapshotRecord::Deprecation(d) => snapshot.deprecations.push(d),
+            SnapshotRecord::Access(a) => snapshot.authorization.push(a),
+            SnapshotRecord::Preset(p) => snapshot.preset = Some(p),
+            SnapshotRecord::Boot(b) => snapshot.boot = Some(b),
+            SnapshotRecord::Snippet(s) => {
+                let content = fs::read_to_string(agent_dir.join(&s.file))
+                    .with_context(|| format!("Missing snippet file {}", s.file))?;
+                snapshot.documentation_snippets.insert(s.key, content);
+            }
+        }
+    }
+    let header = header.context("Snapshot stream has no header record")?;
+    snapshot.project_name = header.project_name;
+    snapshot.timestamp = header.timestamp;
+    snapshot.framework_version = header.framework_version;
+    snapshot.agent_entry_point = header.agent_entry_point;
+    Ok(snapshot)
+}
+
+/// Maps a snippet key such as `docs/core/router.md` to a path under `snippets/`.
+/// Segments that could escape the directory are replaced.
+fn snippet_file(key: &str) -> String {
+    let mut path = PathBuf::from(SNIPPETS_DIR);
+    for component in Path::new(key).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            _ => path.push("_"),
+        }
+    }
+    if path.extension().is_none() {
+        path.set_extension("md");
+    }
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// Reads the top-level `field` of the JSON object in `path` as `T`. Other fields
+/// are skipped without being materialized. Returns `None` if the field is absent.
+pub fn read_section<T: DeserializeOwned>(path: &Path, field: &str) -> Result<Option<T>> {
+    read_field(path, Field { name: field, seed: PhantomData::<T> })
+}
+
+/// Reads one entry of `documentation_snippets` from a regular `agent.json`.
+pub fn read_snippet(path: &Path, key: &str) -> Result<Option<String>> {
+    let snippets = Field {
+        name: "documentation_snippets",
+        seed: Field { name: key, seed: PhantomData::<String> },
+    };
+    Ok(read_field(path, snippets)?.flatten())
+}
+
+/// Lists the keys of `documentation_snippets` in a regular `agent.json`.
+pub fn read_snippet_keys(path: &Path) -> Result<Vec<String>> {
+    let keys = Field { name: "documentation_snippets", seed: Keys };
+    let mut keys = read_field(path, keys)?.unwrap_or_default();
+    keys.sort();
+    Ok(keys)
+}
+
+fn read_field<S, V>(path: &Path, seed: S) -> Result<V>
+where
+    S: for<'de> DeserializeSeed<'de, Value = V>,
+{
+    let file = File::open(path).with_context(|| format!("Failed to open snapshot {}", path.display()))?;
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(file));
+    seed.deserialize(&mut deserializer)
+        .with_context(|| format!("Failed to read snapshot {}", path.display()))
+}
+
+/// Deserializes the value under `name` in a JSON object with `seed`, skipping the other entries.
+struct Field<'a, S> {
+    name: &'a str,
+    seed: S,
+}
+
+impl<'de, S: DeserializeSeed<'de>> DeserializeSeed<'de> for Field<'_, S> {
+    type Value = Option<S::Value>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, S: DeserializeSeed<'de>> Visitor<'de> for Field<'_, S> {
+    type Value = Option<S::Value>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an object with a '{}' field", self.name)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut seed = Some(self.seed);
+        let mut found = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match seed.take_if(|_| key == self.name) {
+                Some(seed) => found = Some(map.next_value_seed(seed)?),
+                None => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// Collects the keys of a JSON object, skipping the values.
+struct Keys;
+
+impl<'de> DeserializeSeed<'de> for Keys {
+    type Value = Vec<String>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Keys {
+    type Value = Vec<String>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut keys = Vec::new();
+        while let Some(key) = map.next_key::<String>()? {
+            map.next_value::<IgnoredAny>()?;
+            keys.push(key);
+        }
+        Ok(keys)
+    }
+}
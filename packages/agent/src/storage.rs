@@ -0,0 +1,137 @@
+//! Where agent artifacts are written.
+//!
+//! By default everything lives in `.agent` at the project root. CI images and
+//! read-only containers cannot write there, so [`AgentStorage::resolve`]
+//! checks the directory first and degrades in order:
+//!
+//! 1. `MONTRS_AGENT_DISABLE` (or `disable = true` under `[agent]` in
+//!    `montrs.toml`) selects [`AgentStorage::Memory`].
+//! 2. `MONTRS_AGENT_DIR` (or `dir` under `[agent]`) replaces `.agent`; relative
+//!    paths are resolved against the project root.
+//! 3. When the chosen directory is not writable, a per-project directory in
+//!    the system temp directory is used instead.
+//! 4. When that is not writable either, [`AgentStorage::Memory`] is used.
+//!
+//! In memory mode artifacts are produced but never written: writers succeed
+//! without touching the disk and readers see whatever `.agent` already holds.
+
+use serde::Deserialize;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// Replaces the `.agent` directory.
+pub const AGENT_DIR_VAR: &str = "MONTRS_AGENT_DIR";
+/// Keeps agent artifacts in memory unless empty, `0`, `false`, `no` or `off`.
+pub const AGENT_DISABLE_VAR: &str = "MONTRS_AGENT_DISABLE";
+pub const DEFAULT_AGENT_DIR: &str = ".agent";
+
+/// The `[agent]` section of `montrs.toml`. Environment variables take
+/// precedence over it.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct AgentSettings {
+    /// Where artifacts are written instead of `.agent`.
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+    /// Keeps artifacts in memory.
+    #[serde(default)]
+    pub disable: bool,
+    /// Redaction rules applied to snapshots and model prompts.
+    #[serde(default)]
+    pub redact: crate::redact::RedactConfig,
+}
+
+impl AgentSettings {
+    /// Reads `[agent]` from `root/montrs.toml`, then applies the environment.
+    pub fn load(root: &Path) -> Self {
+        let mut settings: Self = std::fs::read_to_string(root.join("montrs.toml"))
+            .ok()
+            .and_then(|content| toml::from_str::<toml::Value>(&content).ok())
+            .and_then(|value| value.get("agent").cloned())
+            .and_then(|agent| agent.try_into().ok())
+            .unwrap_or_default();
+        settings.apply_env(|key| std::env::var(key).ok());
+        settings
+    }
+
+    /// Overrides the settings with `MONTRS_AGENT_DIR` and `MONTRS_AGENT_DISABLE`
+    /// as returned by `var`.
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) {
+        if let Some(dir) = var(AGENT_DIR_VAR).filter(|dir| !dir.is_empty()) {
+            self.dir = Some(PathBuf::from(dir));
+        }
+        if let Some(flag) = var(AGENT_DISABLE_VAR) {
+            self.disable = !matches!(flag.trim().to_ascii_lowercase().as_str(), "" | "0" | "false" | "no" | "off");
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AgentStorage {
+    /// Artifacts are written to this directory.
+    Dir(PathBuf),
+    /// Artifacts are not written.
+    Memory,
+}
+
+/// The storage chosen for a project, and why it is not the configured one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolved {
+    pub storage: AgentStorage,
+    /// Set when the configured directory was not writable.
+    pub fallback: Option<String>,
+}
+
+impl AgentStorage {
+    /// Chooses the storage for the project at `root` from `settings`, checking
+    /// that directories are writable (and creating them) on the way.
+    pub fn resolve(root: &Path, settings: &AgentSettings) -> Resolved {
+        if settings.disable {
+            return Resolved { storage: AgentStorage::Memory, fallback: None };
+        }
+        let configured = match &settings.dir {
+            Some(dir) => root.join(dir),
+            None => root.join(DEFAULT_AGENT_DIR),
+        };
+        if writable(&configured) {
+            return Resolved { storage: AgentStorage::Dir(configured), fallback: None };
+        }
+
+        let temp = temp_dir_for(root);
+        if writable(&temp) {
+            let fallback = format!("{} is not writable; writing agent artifacts to {}", configured.display(), temp.display());
+            return Resolved { storage: AgentStorage::Dir(temp), fallback: Some(fallback) };
+        }
+        let fallback = format!("{} is not writable; keeping agent artifacts in memory", configured.display());
+        Resolved { storage: AgentStorage::Memory, fallback: Some(fallback) }
+    }
+
+    /// The directory artifacts are written to, if any.
+    pub fn dir(&self) -> Option<&Path> {
+        match self {
+            AgentStorage::Dir(dir) => Some(dir),
+            AgentStorage::Memory => None,
+        }
+    }
+}
+
+/// `<temp>/montrs-agent/<project>-<hash of the root>`, so two checkouts of the
+/// same project do not share artifacts.
+fn temp_dir_for(root: &Path) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    root.hash(&mut hasher);
+    let name = root.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| "project".to_string());
+    std::env::temp_dir().join("montrs-agent").join(format!("{}-{:016x}", name, hasher.finish()))
+}
+
+/// Creates `dir` if needed and checks that a file can be written in it.
+/// Permission bits alone are not enough on read-only mounts.
+fn writable(dir: &Path) -> bool {
+    if std::fs::create_dir_all(dir).is_err() {
+        return false;
+    }
+    let probe = dir.join(format!(".write-probe-{}", std::process::id()));
+    let ok = std::fs::write(&probe, b"").is_ok();
+    let _ = std::fs::remove_file(&probe);
+    ok
+}
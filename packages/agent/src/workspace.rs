@@ -0,0 +1,278 @@
+//! Workspaces spanning several MontRS projects.
+//!
+//! Workspaces are declared in `~/.montrs/workspaces.toml` (or under
+//! `$MONTRS_HOME`), each listing the roots of the projects it groups:
+//!
+//! ```toml
+//! [workspaces.acme]
+//! projects = [
+//!     { path = "~/code/shop" },
+//!     { name = "admin", path = "~/code/admin-portal" },
+//! ]
+//! ```
+//!
+//! [`WorkspaceIndex::build`] reads each project's `.agent` snapshot into one
+//! index that can be searched for routes, packages and plates across projects.
+
+use crate::{AgentManager, PackageSummary, PlateSummary, RouteSummary};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const WORKSPACES_FILE: &str = "workspaces.toml";
+/// Directory under the MontRS home where combined indexes are written.
+pub const INDEX_DIR: &str = "workspaces";
+
+/// The user's MontRS directory: `$MONTRS_HOME`, or `~/.montrs`.
+pub fn montrs_home() -> Option<PathBuf> {
+    if let Some(home) = std::env::var_os("MONTRS_HOME") {
+        return Some(PathBuf::from(home));
+    }
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".montrs"))
+}
+
+/// Expands a leading `~/` to the user's home directory.
+fn expand_home(path: &Path) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(rest))
+            .unwrap_or_else(|| path.to_path_buf()),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct WorkspacesConfig {
+    #[serde(default)]
+    pub workspaces: BTreeMap<String, WorkspaceConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct WorkspaceConfig {
+    #[serde(default)]
+    pub projects: Vec<ProjectEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProjectEntry {
+    /// Display name; defaults to the directory name.
+    #[serde(default)]
+    pub name: Option<String>,
+    pub path: PathBuf,
+}
+
+impl ProjectEntry {
+    pub fn root(&self) -> PathBuf {
+        expand_home(&self.path)
+    }
+
+    pub fn display_name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            self.root()
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| self.path.display().to_string())
+        })
+    }
+}
+
+impl WorkspacesConfig {
+    /// `workspaces.toml` in the MontRS home.
+    pub fn default_path() -> Option<PathBuf> {
+        montrs_home().map(|home| home.join(WORKSPACES_FILE))
+    }
+
+    /// Loads the config at `path`; a missing file is an empty config.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)?;
+        toml::from_str(&content).with_context(|| format!("Invalid workspace config {}", path.display()))
+    }
+
+    pub fn load_default() -> Result<Self> {
+        match Self::default_path() {
+            Some(path) => Self::load(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn workspace(&self, name: &str) -> Result<&WorkspaceConfig> {
+        self.workspaces.get(name).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Unknown workspace '{}'; configured: {}",
+                name,
+                self.workspaces.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        })
+    }
+}
+
+/// What one project contributes to a workspace index.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProjectIndex {
+    pub name: String,
+    pub root: PathBuf,
+    /// When the project's snapshot was taken.
+    pub snapshot_timestamp: Option<DateTime<Utc>>,
+    pub routes: Vec<RouteSummary>,
+    pub packages: Vec<PackageSummary>,
+    pub plates: Vec<PlateSummary>,
+    /// Why the project could not be indexed, if it couldn't.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The combined index of a workspace.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WorkspaceIndex {
+    pub name: String,
+    pub generated: DateTime<Utc>,
+    pub projects: Vec<ProjectIndex>,
+}
+
+/// One search result, tagged with the project it came from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SearchHit {
+    pub project: String,
+    /// `route`, `package` or `plate`.
+    pub kind: String,
+    pub name: String,
+    pub description: String,
+}
+
+impl WorkspaceIndex {
+    /// Indexes every project of `workspace`. Projects that fail to load are kept
+    /// with their error so the rest of the workspace stays searchable.
+    pub fn build(name: &str, workspace: &WorkspaceConfig) -> Self {
+        let projects = workspace
+            .projects
+            .iter()
+            .map(|entry| {
+                let root = entry.root();
+                AgentManager::new(&root).project_index(&entry.display_name()).unwrap_or_else(|e| ProjectIndex {
+                    name: entry.display_name(),
+                    root,
+                    snapshot_timestamp: None,
+                    routes: Vec::new(),
+                    packages: Vec::new(),
+                    plates: Vec::new(),
+                    error: Some(e.to_string()),
+                })
+            })
+            .collect();
+        Self {
+            name: name.to_string(),
+            generated: Utc::now(),
+            projects,
+        }
+    }
+
+    /// Where the index of workspace `name` is stored in the MontRS home.
+    pub fn default_path(name: &str) -> Option<PathBuf> {
+        montrs_home().map(|home| home.join(INDEX_DIR).join(format!("{}.json", name)))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Case-insensitive search over route paths, package and plate names, and
+    /// descriptions. A query starting with `/` also finds the routes whose
+    /// pattern matches it as a concrete path, so `/users/42` finds `/users/:id`.
+    pub fn search(&self, query: &str) -> Vec<SearchHit> {
+        let needle = query.to_lowercase();
+        let matches = |text: &str| text.to_lowercase().contains(&needle);
+        let mut hits = Vec::new();
+        for project in &self.projects {
+            let hit = |kind: &str, name: &str, description: &str| SearchHit {
+                project: project.name.clone(),
+                kind: kind.to_string(),
+                name: name.to_string(),
+                description: description.to_string(),
+            };
+            for route in &project.routes {
+                if matches(&route.path) || matches(&route.description) || (query.starts_with('/') && route_matches(&route.path, query)) {
+                    hits.push(hit("route", &route.path, &route.description));
+                }
+            }
+            for package in &project.packages {
+                let description = package.description.as_deref().unwrap_or_default();
+                if matches(&package.name) || matches(description) {
+                    hits.push(hit("package", &package.name, description));
+                }
+            }
+            for plate in &project.plates {
+                if matches(&plate.name) || matches(&plate.description) {
+                    hits.push(hit("plate", &plate.name, &plate.description));
+                }
+            }
+        }
+        hits
+    }
+}
+
+/// Whether `path` matches a route `pattern` with `:param` and `*wildcard` segments.
+fn route_matches(pattern: &str, path: &str) -> bool {
+    let mut pattern = pattern.split('/').filter(|s| !s.is_empty());
+    let mut path = path.split('/').filter(|s| !s.is_empty());
+    loop {
+        match (pattern.next(), path.next()) {
+            (Some(p), _) if p.starts_with('*') => return true,
+            (Some(p), Some(s)) if p.starts_with(':') || p == s => continue,
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+impl AgentManager {
+    /// Summarizes this project for a workspace index. Reads only the routes,
+    /// packages and plates of an existing snapshot, and falls back to a fresh
+    /// discovery pass when none has been written yet.
+    pub fn project_index(&self, name: &str) -> Result<ProjectIndex> {
+        if !self.root_path.is_dir() {
+            anyhow::bail!("Project root {} does not exist", self.root_path.display());
+        }
+        let written = [self.snapshot_file(), self.agent_dir().join(crate::snapshot::SNAPSHOT_JSONL)]
+            .iter()
+            .filter_map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+            .max();
+        let (routes, packages, plates, timestamp) = match written {
+            Some(modified) => (
+                self.snapshot_routes()?,
+                self.snapshot_packages()?,
+                self.snapshot_plates()?,
+                DateTime::<Utc>::from(modified),
+            ),
+            None => {
+                let snapshot = self.generate_snapshot(name)?;
+                (snapshot.routes, snapshot.packages, snapshot.plates, snapshot.timestamp)
+            }
+        };
+        Ok(ProjectIndex {
+            name: name.to_string(),
+            root: self.root_path.clone(),
+            snapshot_timestamp: Some(timestamp),
+            routes,
+            packages,
+            plates,
+            error: None,
+        })
+    }
+}
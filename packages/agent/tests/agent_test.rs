@@ -1,4 +1,4 @@
-use montrs_agent::{AgentManager, AgentSnapshot};
+use montrs_agent::AgentManager;
 use tempfile::tempdir;
 use std::fs;
 
@@ -11,7 +11,7 @@ async fn test_agent_generation() {
     fs::write(root.join("test.rs"), "fn main() {}").unwrap();
     
     let manager = AgentManager::new(root);
-    let snapshot = manager.generate_snapshot("test-project".to_string()).unwrap();
+    let snapshot = manager.generate_snapshot("test-project").unwrap();
     
     assert_eq!(snapshot.project_name, "test-project");
     assert!(snapshot.structure.iter().any(|f| f.path == "test.rs"));
@@ -76,3 +76,35 @@ async fn test_consolidated_error_tracking() {
     assert_eq!(tracking.errors[0].package, Some("test-pkg".to_string()));
     assert_eq!(tracking.errors[0].status, "Pending");
 }
+
+#[test]
+fn test_panic_reports_become_errors_with_context() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    let source: Vec<String> = (1..=20).map(|n| format!("line {}", n)).collect();
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("src/cart.rs"), source.join("\n")).unwrap();
+
+    let manager = AgentManager::new(root);
+    let report = montrs_core::PanicReport {
+        id: "00c0ffee00c0ffee".to_string(),
+        timestamp: chrono::Utc::now(),
+        message: "index out of bounds".to_string(),
+        file: Some("src/cart.rs".to_string()),
+        line: 10,
+        column: 5,
+        thread: None,
+        scope: Some("route /cart (load)".to_string()),
+        backtrace: "0: cart::total".to_string(),
+    };
+    let id = manager.report_panic(&report).unwrap();
+
+    let error = manager.find_error(&id).unwrap().detail;
+    assert_eq!(error.message, "panic in route /cart (load): index out of bounds");
+    assert_eq!(error.code_context, source[6..13].join("\n"));
+    let metadata = error.agent_metadata.unwrap();
+    assert_eq!(metadata.error_code, "PANIC");
+    assert!(metadata.explanation.contains("00c0ffee00c0ffee") && metadata.explanation.contains("cart::total"));
+    // The same panic again is the same error.
+    assert_eq!(manager.report_panic(&report).unwrap(), id);
+}
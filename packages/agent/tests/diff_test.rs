@@ -0,0 +1,118 @@
+use montrs_agent::diff::{LineKind, StoredDiff, StructuredDiff, MAX_INLINE_DIFF_BYTES};
+use montrs_agent::storage::AgentStorage;
+use montrs_agent::{AgentManager, ProjectError};
+use std::fs;
+use tempfile::tempdir;
+
+const PATCH: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 3b18e51..a1c2d3e 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,3 @@ fn main() {
+ fn a() {}
+-fn b() -> u32 { \"1\" }
++fn b() -> u32 { 1 }
+ fn c() {}
+diff --git a/src/new.rs b/src/new.rs
+new file mode 100644
+index 0000000..e69de29
+--- /dev/null
++++ b/src/new.rs
+@@ -0,0 +1 @@
++pub fn new() {}
+\\ No newline at end of file
+";
+
+fn error_at(file: &str) -> ProjectError {
+    ProjectError {
+        package: None,
+        file: file.to_string(),
+        line: 2,
+        column: 1,
+        message: "mismatched types".to_string(),
+        code_context: String::new(),
+        level: "Error".to_string(),
+        agent_metadata: None,
+    }
+}
+
+#[test]
+fn test_parses_hunks_and_round_trips() {
+    let diff = StructuredDiff::parse(PATCH).unwrap();
+    assert_eq!(diff.files.len(), 2);
+
+    let changed = &diff.files[0];
+    assert_eq!(changed.path(), "src/lib.rs");
+    assert_eq!((changed.added(), changed.removed()), (1, 1));
+    let hunk = &changed.hunks[0];
+    assert_eq!((hunk.before(), hunk.after()), (1..4, 1..4));
+    assert_eq!(hunk.section, "fn main() {");
+    assert_eq!(hunk.lines[1].kind, LineKind::Removed);
+
+    let created = &diff.files[1];
+    assert_eq!((created.old_path.as_deref(), created.new_path.as_deref()), (None, Some("src/new.rs")));
+    assert!(created.hunks[0].lines[0].no_newline);
+
+    assert_eq!(StructuredDiff::parse(&diff.to_unified()).unwrap(), diff);
+    assert_eq!(diff.reverse().reverse(), diff);
+    assert!(StructuredDiff::parse("@@ -1,2 +1,2 @@\n-a\n").is_err());
+}
+
+#[test]
+fn test_renders_for_terminal_and_markdown() {
+    let diff = StructuredDiff::parse(PATCH).unwrap();
+    let markdown = diff.to_markdown();
+    assert!(markdown.contains("#### `src/lib.rs` (+1 -1)"));
+    assert!(markdown.contains("#### `src/new.rs (new)` (+1 -0)"));
+    assert!(markdown.contains("```diff\n@@ -1,3 +1,3 @@ fn main() {\n fn a() {}\n-fn b()"));
+
+    assert!(diff.to_terminal(true).contains("\x1b[32m+fn b() -> u32 { 1 }\x1b[0m"));
+    assert!(!diff.to_terminal(false).contains('\x1b'));
+}
+
+#[test]
+fn test_large_diffs_go_to_blobs() {
+    let dir = tempdir().unwrap();
+    let manager = AgentManager::with_storage(dir.path(), AgentStorage::Dir(dir.path().join(".agent")));
+
+    let (raw, changes) = manager.store_diff(Some(PATCH.to_string())).unwrap();
+    assert_eq!(raw, None);
+    assert!(matches!(changes, Some(StoredDiff::Inline(_))));
+
+    let lines: String = (0..MAX_INLINE_DIFF_BYTES / 8).map(|i| format!("+{:06}\n", i)).collect();
+    let huge = format!("--- /dev/null\n+++ b/big.txt\n@@ -0,0 +1,{} @@\n{}", MAX_INLINE_DIFF_BYTES / 8, lines);
+    let (_, changes) = manager.store_diff(Some(huge)).unwrap();
+    let Some(StoredDiff::Blob { hash, files, .. }) = changes else { panic!("expected a blob") };
+    assert_eq!((files[0].path.as_str(), files[0].added), ("big.txt", MAX_INLINE_DIFF_BYTES / 8));
+    assert!(manager.blobs_dir().join(format!("{}.diff", hash)).exists());
+
+    // Text that is not a diff is kept as it was.
+    assert_eq!(manager.store_diff(Some("see PR 12".to_string())).unwrap(), (Some("see PR 12".to_string()), None));
+}
+
+#[test]
+fn test_apply_and_revert_resolution() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir(root.join("src")).unwrap();
+    fs::write(root.join("src/lib.rs"), "fn a() {}\nfn b() -> u32 { \"1\" }\nfn c() {}\n").unwrap();
+    let manager = AgentManager::with_storage(root, AgentStorage::Dir(root.join(".agent")));
+    let id = manager.report_project_error(error_at("src/lib.rs")).unwrap();
+    manager.resolve_error(&id, "Return a number".to_string(), Some(PATCH.to_string())).unwrap();
+
+    let resolved = manager.find_error(&id).unwrap();
+    assert!(matches!(resolved.history[0].changes, Some(StoredDiff::Inline(_))));
+    assert_eq!(resolved.history[0].diff, None);
+
+    manager.apply_resolution(&id, None).unwrap();
+    assert_eq!(fs::read_to_string(root.join("src/lib.rs")).unwrap(), "fn a() {}\nfn b() -> u32 { 1 }\nfn c() {}\n");
+    assert_eq!(fs::read_to_string(root.join("src/new.rs")).unwrap(), "pub fn new() {}");
+    // Applying twice fails without touching the tree.
+    assert!(manager.apply_resolution(&id, None).is_err());
+
+    manager.revert_resolution(&id, Some(1)).unwrap();
+    assert_eq!(fs::read_to_string(root.join("src/lib.rs")).unwrap(), "fn a() {}\nfn b() -> u32 { \"1\" }\nfn c() {}\n");
+    assert!(!root.join("src/new.rs").exists());
+    assert!(manager.revert_resolution(&id, Some(7)).unwrap_err().to_string().contains("version 7"));
+}
@@ -0,0 +1,80 @@
+use montrs_agent::error_parser::{parse_json_diagnostics, parse_rustc_errors, Applicability};
+
+const CARGO_OUTPUT: &str = include_str!("fixtures/cargo-diagnostics.jsonl");
+
+#[test]
+fn test_parses_cargo_json_diagnostics() {
+    let errors = parse_json_diagnostics(CARGO_OUTPUT);
+    // The "For more information" summary and the artifact line carry no location.
+    assert_eq!(errors.len(), 3);
+
+    let mismatch = &errors[0];
+    assert_eq!(mismatch.level, "Error");
+    assert_eq!(mismatch.package.as_deref(), Some("web"));
+    assert_eq!((mismatch.file.as_str(), mismatch.line, mismatch.column), ("src/lib.rs", 2, 5));
+    assert_eq!(mismatch.code_context, "    x");
+    let meta = mismatch.agent_metadata.as_ref().unwrap();
+    assert_eq!(meta.error_code, "E0308");
+    assert!(meta.explanation.contains("Expected type did not match the received type."));
+    assert!(meta.rustc_error.as_deref().unwrap().starts_with("error[E0308]"));
+    assert_eq!(meta.suggestions.len(), 1);
+    assert_eq!(meta.suggestions[0].replacement, ".to_string()");
+    assert_eq!(meta.suggestions[0].applicability, Applicability::MaybeIncorrect);
+    assert!(!meta.suggestions[0].is_machine_applicable());
+
+    let unused = errors[1].agent_metadata.as_ref().unwrap();
+    assert_eq!(errors[1].level, "Warning");
+    assert_eq!(unused.error_code, "unused_variables");
+    assert!(unused.suggestions[0].is_machine_applicable());
+    assert_eq!(unused.suggestions[0].replacement, "_unused");
+    assert!(unused.suggested_fixes[0].contains("replace with `_unused` at src/lib.rs:2:9 (machine-applicable)"));
+}
+
+#[test]
+fn test_maps_clippy_lints() {
+    let errors = parse_json_diagnostics(CARGO_OUTPUT);
+    let lint = errors[2].agent_metadata.as_ref().unwrap();
+    assert_eq!(lint.error_code, "clippy::needless_return");
+    assert!(lint.explanation.starts_with("Clippy lint `needless_return`"));
+    assert!(lint.suggested_fixes.iter().any(|f| f.contains("rust-clippy") && f.contains("#needless_return")));
+
+    // Two edits: the `return` keyword and the trailing semicolon.
+    let edits: Vec<_> = lint.suggestions.iter().map(|s| (s.byte_start, s.byte_end, s.replacement.as_str())).collect();
+    assert_eq!(edits, vec![(50, 62, "x + 1"), (62, 63, "")]);
+    assert!(lint.suggested_fixes.iter().any(|f| f.starts_with("remove `return`: remove the highlighted code")));
+}
+
+#[test]
+fn test_text_and_bare_rustc_output() {
+    let text = "error[E0425]: cannot find value `y` in this scope\n --> src/main.rs:3:13\n";
+    let errors = parse_rustc_errors(text);
+    assert_eq!(errors[0].line, 3);
+    assert!(errors[0].agent_metadata.as_ref().unwrap().suggestions.is_empty());
+
+    let bare = r#"{"message":"unused import: `std::fmt`","code":{"code":"unused_imports","explanation":null},"level":"warning","spans":[{"file_name":"src/main.rs","byte_start":4,"byte_end":12,"line_start":1,"line_end":1,"column_start":5,"column_end":13,"is_primary":true,"text":[]}],"children":[],"rendered":null}"#;
+    let errors = parse_json_diagnostics(&format!("Compiling app\n{}\n", bare));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].package, None);
+    assert_eq!(errors[0].agent_metadata.as_ref().unwrap().error_code, "unused_imports");
+}
+
+#[test]
+fn test_attaches_proc_macro_metadata() {
+    // `#[derive(Schema)]` on an enum and an unknown `#[schema(maxlen(3))]`: each
+    // compile error is followed by a deprecation warning carrying its metadata.
+    let errors = parse_json_diagnostics(include_str!("fixtures/schema-diagnostics.jsonl"));
+    assert_eq!(errors.len(), 2);
+    assert!(errors.iter().all(|e| e.level == "Error" && e.package.as_deref() == Some("shop")));
+
+    let unsupported = &errors[0];
+    assert_eq!((unsupported.file.as_str(), unsupported.line, unsupported.column), ("src/models.rs", 7, 14));
+    assert!(unsupported.message.contains("supported attributes are min_len = N, email"));
+    let meta = unsupported.agent_metadata.as_ref().unwrap();
+    assert_eq!(meta.error_code, "SCHEMA_UNSUPPORTED_ATTRIBUTE");
+    assert!(meta.explanation.contains("'maxlen' is not supported"));
+    assert!(meta.suggested_fixes[0].starts_with("Use only supported schema attributes"));
+
+    let meta = errors[1].agent_metadata.as_ref().unwrap();
+    assert_eq!(meta.error_code, "SCHEMA_INVALID_STRUCT_TYPE");
+    assert!(meta.explanation.contains("'enum E'"));
+}
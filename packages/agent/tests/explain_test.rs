@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use montrs_agent::explain::{explain, parse_reply};
+use montrs_agent::llm::{ChatMessage, ChatResponse, CompletionOptions, LlmError, LlmProvider, Role};
+use montrs_agent::{AgentManager, ProjectError};
+use std::sync::Mutex;
+use tempfile::tempdir;
+
+/// Answers every chat with a fixed reply and keeps the prompt it was sent.
+struct StubProvider {
+    reply: &'static str,
+    prompt: Mutex<String>,
+}
+
+#[async_trait]
+impl LlmProvider for StubProvider {
+    fn name(&self) -> &str {
+        "stub"
+    }
+
+    async fn chat(&self, messages: &[ChatMessage], _options: &CompletionOptions) -> Result<ChatResponse, LlmError> {
+        assert_eq!(messages[0].role, Role::System);
+        *self.prompt.lock().unwrap() = messages[1].content.clone();
+        Ok(ChatResponse { content: self.reply.to_string(), model: "stub-1".to_string(), usage: None })
+    }
+
+    async fn embed(&self, _inputs: &[String]) -> Result<Vec<Vec<f32>>, LlmError> {
+        Ok(Vec::new())
+    }
+}
+
+#[test]
+fn test_parse_reply() {
+    let fenced = "Sure!\n```json\n{\"explanation\": \"The loader borrows state\", \"suggested_fixes\": [\"Clone it\"]}\n```";
+    assert_eq!(parse_reply(fenced), ("The loader borrows state".to_string(), vec!["Clone it".to_string()]));
+    assert_eq!(parse_reply("  Just prose.  "), ("Just prose.".to_string(), vec![]));
+}
+
+#[tokio::test]
+async fn test_explain_gathers_context_and_stores_the_answer() {
+    let dir = tempdir().unwrap();
+    let source: String = (1..=30).map(|i| format!("let line{} = {};\n", i, i)).collect();
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join("src/router.rs"), source).unwrap();
+
+    let manager = AgentManager::new(dir.path());
+    let id = manager
+        .report_project_error(ProjectError {
+            package: None,
+            file: "src/router.rs".to_string(),
+            line: 20,
+            column: 5,
+            message: "loader output does not implement Serialize".to_string(),
+            code_context: String::new(),
+            level: "Error".to_string(),
+            agent_metadata: None,
+        })
+        .unwrap();
+
+    let context = manager.explain_context(&id).unwrap();
+    let code = context.code.as_deref().unwrap();
+    assert!(code.contains(">   20 | let line20 = 20;"));
+    assert!(code.contains("   12 | let line12") && !code.contains("let line11 "));
+    assert_eq!(context.invariants[0].0, "core");
+    assert!(!context.docs.is_empty() && context.docs.len() <= 3);
+
+    let provider = StubProvider {
+        reply: r#"{"explanation": "Loader outputs are sent as JSON.", "suggested_fixes": ["Derive Serialize"]}"#,
+        prompt: Mutex::new(String::new()),
+    };
+    let explanation = explain(&provider, &context).await.unwrap();
+    assert_eq!(explanation.model, "stub-1");
+    assert!(provider.prompt.lock().unwrap().contains("## Invariants of `core`"));
+
+    manager.record_explanation(&id, explanation).unwrap();
+    let stored = manager.find_error(&id).unwrap().detail.agent_metadata.unwrap().model_explanation.unwrap();
+    assert_eq!(stored.suggested_fixes, ["Derive Serialize"]);
+}
@@ -0,0 +1,76 @@
+use montrs_agent::export::{self, SNAPSHOT_MARKDOWN};
+use montrs_agent::{AgentManager, AgentSnapshot, PlateSummary, RouteSummary};
+use std::collections::HashMap;
+use tempfile::tempdir;
+
+fn snapshot(manager: &AgentManager) -> AgentSnapshot {
+    let mut snapshot = manager.generate_framework_snapshot();
+    snapshot.project_name = "shop".to_string();
+    snapshot.routes.push(RouteSummary {
+        path: "/orders/:id".to_string(),
+        kind: "loader".to_string(),
+        description: "Loads an order | with items".to_string(),
+        input_schema: None,
+        output_schema: None,
+        params_schema: Some(serde_json::json!({ "type": "object" })),
+        loader_output_schema: None,
+        action_input_schema: None,
+        action_output_schema: None,
+        metadata: HashMap::from([("auth".to_string(), "required".to_string())]),
+        parent: None,
+    });
+    snapshot.plates.push(PlateSummary {
+        name: "billing".to_string(),
+        description: "Invoices".to_string(),
+        dependencies: vec!["db".to_string()],
+        metadata: HashMap::new(),
+    });
+    snapshot
+}
+
+#[test]
+fn test_markdown_export() {
+    let dir = tempdir().unwrap();
+    let manager = AgentManager::new(dir.path());
+    let snapshot = snapshot(&manager);
+    manager.write_snapshot(&snapshot, "md").unwrap();
+
+    let markdown = std::fs::read_to_string(manager.agent_dir().join(SNAPSHOT_MARKDOWN)).unwrap();
+    assert_eq!(markdown, export::to_markdown(&snapshot));
+    assert!(markdown.starts_with("# shop\n"));
+    assert!(markdown.contains("| `/orders/:id` | loader | Loads an order \\| with items |"));
+    assert!(markdown.contains("| billing | db | Invoices |"));
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_export() {
+    use montrs_agent::ProjectError;
+
+    let dir = tempdir().unwrap();
+    let manager = AgentManager::new(dir.path());
+    let error_id = manager
+        .report_project_error(ProjectError {
+            package: Some("web".to_string()),
+            file: "src/orders.rs".to_string(),
+            line: 12,
+            column: 4,
+            message: "mismatched types".to_string(),
+            code_context: String::new(),
+            level: "Error".to_string(),
+            agent_metadata: None,
+        })
+        .unwrap();
+    manager.write_snapshot(&snapshot(&manager), "sqlite").unwrap();
+    // Re-exporting replaces the database instead of appending to it.
+    manager.write_snapshot(&snapshot(&manager), "sqlite").unwrap();
+
+    let conn = rusqlite::Connection::open(manager.agent_dir().join(export::SNAPSHOT_SQLITE)).unwrap();
+    let query = |sql: &str| -> String { conn.query_row(sql, [], |row| row.get(0)).unwrap() };
+    assert_eq!(query("SELECT value FROM meta WHERE key = 'project_name'"), "shop");
+    assert_eq!(query("SELECT json_extract(metadata, '$.auth') FROM routes WHERE path = '/orders/:id'"), "required");
+    assert_eq!(query("SELECT json_extract(dependencies, '$[0]') FROM plates WHERE name = 'billing'"), "db");
+    assert_eq!(query("SELECT id FROM errors WHERE status = 'Pending' AND line = 12"), error_id);
+    let routes: i64 = conn.query_row("SELECT COUNT(*) FROM routes", [], |row| row.get(0)).unwrap();
+    assert_eq!(routes, 1);
+}
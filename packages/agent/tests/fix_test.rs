@@ -0,0 +1,123 @@
+use chrono::Utc;
+use montrs_agent::error_parser::{Applicability, Suggestion};
+use montrs_agent::fix::{plan_fix, FixPlan};
+use montrs_agent::{AgentErrorMetadata, AgentManager, ErrorRecord, ErrorStatus, ErrorVersion, ProjectError};
+use std::fs;
+use tempfile::tempdir;
+
+const SOURCE: &str = "fn main() {\n    let unused = 1;\n    return;\n}\n";
+
+fn suggestion(message: &str, byte_start: usize, byte_end: usize, line: u32, column: u32, replacement: &str) -> Suggestion {
+    Suggestion {
+        message: message.to_string(),
+        file: "src/main.rs".to_string(),
+        byte_start,
+        byte_end,
+        line_start: line,
+        column_start: column,
+        line_end: line,
+        column_end: column + (byte_end - byte_start) as u32,
+        replacement: replacement.to_string(),
+        applicability: Applicability::MachineApplicable,
+    }
+}
+
+fn record(suggestions: Vec<Suggestion>) -> ErrorRecord {
+    ErrorRecord {
+        id: "e1".to_string(),
+        timestamp: Utc::now(),
+        version: 1,
+        status: ErrorStatus::Active,
+        detail: ProjectError {
+            package: None,
+            file: "src/main.rs".to_string(),
+            line: 2,
+            column: 9,
+            message: "unused variable: `unused`".to_string(),
+            code_context: String::new(),
+            level: "Warning".to_string(),
+            agent_metadata: Some(AgentErrorMetadata {
+                error_code: "unused_variables".to_string(),
+                explanation: String::new(),
+                suggested_fixes: vec![],
+                rustc_error: None,
+                suggestions,
+                model_explanation: None,
+            }),
+        },
+        history: vec![],
+        git: None,
+    }
+}
+
+#[test]
+fn test_applies_first_machine_applicable_suggestion() {
+    let dir = tempdir().unwrap();
+    let manager = AgentManager::new(dir.path());
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/main.rs"), SOURCE).unwrap();
+
+    let mut maybe = suggestion("if this is intentional, remove it", 16, 31, 2, 5, "");
+    maybe.applicability = Applicability::MaybeIncorrect;
+    let record = record(vec![
+        maybe,
+        suggestion("prefix it with an underscore", 20, 26, 2, 9, "_unused"),
+    ]);
+
+    let plan = plan_fix(&manager, &record).unwrap().unwrap();
+    assert!(matches!(&plan, FixPlan::Suggestions { message, .. } if message == "prefix it with an underscore"));
+    let patch = plan.patch();
+    assert!(patch.contains("--- a/src/main.rs"));
+    assert!(patch.contains("-    let unused = 1;\n+    let _unused = 1;\n"));
+    // Planning alone leaves the tree untouched.
+    assert_eq!(fs::read_to_string(dir.path().join("src/main.rs")).unwrap(), SOURCE);
+
+    plan.apply(dir.path()).unwrap();
+    assert_eq!(
+        fs::read_to_string(dir.path().join("src/main.rs")).unwrap(),
+        SOURCE.replace("let unused", "let _unused")
+    );
+    // Applying twice would corrupt the file, so the stale plan is rejected.
+    assert!(plan.apply(dir.path()).is_err());
+}
+
+#[test]
+fn test_rejects_stale_suggestions_and_falls_back_to_history() {
+    let dir = tempdir().unwrap();
+    let manager = AgentManager::new(dir.path());
+    fs::create_dir(dir.path().join("src")).unwrap();
+    fs::write(dir.path().join("src/main.rs"), format!("// moved\n{}", SOURCE)).unwrap();
+
+    let stale = record(vec![suggestion("prefix it with an underscore", 20, 26, 2, 9, "_unused")]);
+    assert!(plan_fix(&manager, &stale).is_err());
+
+    let mut manual = record(vec![]);
+    assert!(plan_fix(&manager, &manual).unwrap().is_none());
+    manual.history.push(ErrorVersion {
+        version: 1,
+        timestamp: Utc::now(),
+        message: "Renamed the binding".to_string(),
+        diff: Some("--- a/src/main.rs\n+++ b/src/main.rs\n".to_string()),
+        changes: None,
+        commit: None,
+    });
+    assert!(matches!(plan_fix(&manager, &manual).unwrap(), Some(FixPlan::Diff(_))));
+}
+
+#[test]
+fn test_fix_attempts_are_kept_in_history() {
+    let dir = tempdir().unwrap();
+    let manager = AgentManager::new(dir.path());
+    manager.write_error_record(&record(vec![])).unwrap();
+
+    manager.record_fix_attempt("e1", "Tried a patch".to_string(), Some("diff".to_string())).unwrap();
+    let attempted = manager.find_error("e1").unwrap();
+    assert!(matches!(attempted.status, ErrorStatus::Active));
+    assert_eq!(attempted.history[0].diff.as_deref(), Some("diff"));
+
+    manager.resolve_error("e1", "Fixed".to_string(), None).unwrap();
+    let resolved = manager.find_error("e1").unwrap();
+    assert_eq!(resolved.version, 2);
+    assert!(matches!(resolved.status, ErrorStatus::Resolved));
+    assert_eq!(resolved.history.len(), 2);
+}
@@ -0,0 +1,103 @@
+use montrs_agent::git::{self, parse_blame, parse_log};
+use montrs_agent::{AgentManager, ErrorStatus, ProjectError};
+use std::path::Path;
+use std::process::Command;
+use tempfile::tempdir;
+
+fn git(root: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=Ada", "-c", "user.email=ada@example.com", "-c", "commit.gpgsign=false"])
+        .args(args)
+        .current_dir(root)
+        .output()
+        .unwrap();
+    assert!(status.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&status.stderr));
+}
+
+fn error_at(file: &str, line: u32) -> ProjectError {
+    ProjectError {
+        package: None,
+        file: file.to_string(),
+        line,
+        column: 5,
+        message: "mismatched types".to_string(),
+        code_context: String::new(),
+        level: "Error".to_string(),
+        agent_metadata: None,
+    }
+}
+
+#[test]
+fn test_parses_porcelain_blame_and_log() {
+    let blame = "\
+5f3c1e2a9b0d4c6e8f1a2b3c4d5e6f708192a3b4 3 3 1
+author Ada
+author-mail <ada@example.com>
+author-time 1700000000
+author-tz +0000
+summary Add pager
+filename src/lib.rs
+\tlet x: u32 = \"1\";
+";
+    let info = parse_blame(blame, 3).unwrap();
+    assert_eq!(info.author, "Ada");
+    assert_eq!(info.author_email, "ada@example.com");
+    assert_eq!(info.summary, "Add pager");
+    assert_eq!(info.author_time.timestamp(), 1_700_000_000);
+    assert!(!info.is_uncommitted());
+
+    let commits = parse_log("aaa\x1fFix pager\n\nFixes 42\n\x1e\nbbb\x1fAdd pager\n\x1e");
+    assert_eq!(commits.len(), 2);
+    assert_eq!(commits[0].hash, "aaa");
+    assert_eq!(commits[0].subject(), "Fix pager");
+    assert!(commits[0].message.contains("Fixes 42"));
+}
+
+#[test]
+fn test_error_records_link_to_commits() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    git(root, &["init", "-q", "-b", "main"]);
+    std::fs::write(root.join("lib.rs"), "fn a() {}\nfn b() -> u32 { \"1\" }\n").unwrap();
+    git(root, &["add", "lib.rs"]);
+    git(root, &["commit", "-q", "-m", "Add b"]);
+    let head = git::head_commit(root).unwrap();
+
+    let manager = AgentManager::new(root);
+    let id = manager.report_project_error(error_at("lib.rs", 2)).unwrap();
+    let record = manager.find_error(&id).unwrap();
+    let meta = record.git.unwrap();
+    assert_eq!(meta.first_seen_commit.as_deref(), Some(head.as_str()));
+    assert_eq!(meta.branch.as_deref(), Some("main"));
+    let blame = meta.blame.unwrap();
+    assert_eq!((blame.line, blame.commit.as_str(), blame.author.as_str()), (2, head.as_str(), "Ada"));
+    assert_eq!(blame.summary, "Add b");
+
+    // Unrelated commits leave the error open.
+    std::fs::write(root.join("README.md"), "docs\n").unwrap();
+    git(root, &["add", "README.md"]);
+    git(root, &["commit", "-q", "-m", "Add readme"]);
+    assert!(manager.resolve_referenced_errors().unwrap().is_empty());
+
+    std::fs::write(root.join("lib.rs"), "fn a() {}\nfn b() -> u32 { 1 }\n").unwrap();
+    git(root, &["commit", "-q", "-am", &format!("Return a number from b\n\nFixes {}", id)]);
+    let fix = git::head_commit(root).unwrap();
+    assert_eq!(manager.resolve_referenced_errors().unwrap(), vec![id.clone()]);
+
+    let resolved = manager.find_error(&id).unwrap();
+    assert!(matches!(resolved.status, ErrorStatus::Resolved));
+    let entry = resolved.history.last().unwrap();
+    assert_eq!(entry.commit.as_deref(), Some(fix.as_str()));
+    assert!(entry.message.contains("Return a number from b"));
+    assert!(manager.version_patch(entry).unwrap().unwrap().contains("+fn b() -> u32 { 1 }"));
+    let changes = manager.version_diff(entry).unwrap().unwrap();
+    assert_eq!(changes.stats()[0].path, "lib.rs");
+    assert!(manager.resolve_referenced_errors().unwrap().is_empty());
+}
+
+#[test]
+fn test_no_git_metadata_outside_a_repository() {
+    let dir = tempdir().unwrap();
+    assert!(git::capture(dir.path(), "lib.rs", 1).is_none());
+    assert!(git::commits_since(dir.path(), chrono::Utc::now()).is_empty());
+}
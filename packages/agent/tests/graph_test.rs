@@ -0,0 +1,51 @@
+use montrs_agent::graph::{ArchitectureGraph, GraphFormat, NodeKind};
+
+fn plate(name: &str) -> (NodeKind, String) {
+    (NodeKind::Plate, name.to_string())
+}
+
+#[test]
+fn test_cycle_detection() {
+    let mut graph = ArchitectureGraph::new();
+    graph.add_edge(plate("auth"), plate("users"));
+    graph.add_edge(plate("users"), plate("billing"));
+    graph.add_edge(plate("billing"), plate("auth"));
+    graph.add_edge(plate("blog"), plate("auth"));
+
+    let cycles = graph.cycles();
+    assert_eq!(cycles, vec![vec![plate("auth"), plate("billing"), plate("users")]]);
+    assert_eq!(graph.cycle_edges().len(), 3);
+}
+
+#[test]
+fn test_heavy_nodes() {
+    let mut graph = ArchitectureGraph::new();
+    for i in 0..12 {
+        graph.add_edge(plate(&format!("p{}", i)), plate("core"));
+    }
+    graph.add_edge(plate("a"), plate("b"));
+    assert_eq!(graph.heavy_nodes(), vec![plate("core")]);
+}
+
+#[test]
+fn test_diff_and_render() {
+    let mut old = ArchitectureGraph::new();
+    old.add_edge(plate("blog"), plate("auth"));
+
+    let mut new = ArchitectureGraph::new();
+    new.add_edge(plate("blog"), plate("users"));
+    new.add_node(NodeKind::Plate, "auth");
+
+    let diff = new.diff(&old);
+    assert!(diff.added_nodes.contains(&plate("users")));
+    assert!(diff.removed_edges.contains(&(plate("blog"), plate("auth"))));
+
+    let dot = new.render(GraphFormat::Dot, Some(&diff));
+    assert!(dot.contains("\"plate:blog\" -> \"plate:auth\" [color=\"#b03030\", style=dashed]"));
+
+    let mermaid = new.render(GraphFormat::Mermaid, None);
+    assert!(mermaid.starts_with("graph LR"));
+
+    let svg = new.render(GraphFormat::Svg, None);
+    assert!(svg.contains("<svg") && svg.contains(">users</text>"));
+}
@@ -0,0 +1,62 @@
+use montrs_agent::AgentManager;
+use montrs_agent::impact::{TestImpact, WorkspacePackage};
+use tempfile::tempdir;
+
+fn package(name: &str, dir: &str, dependencies: &[&str]) -> WorkspacePackage {
+    WorkspacePackage {
+        name: name.to_string(),
+        dir: dir.to_string(),
+        dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+    }
+}
+
+fn workspace() -> Vec<WorkspacePackage> {
+    vec![
+        package("montrs-core", "packages/core", &[]),
+        package("montrs-orm", "packages/orm", &["montrs-core"]),
+        package("montrs-cli", "packages/cli", &["montrs-orm"]),
+        package("montrs-fmt", "packages/fmt", &[]),
+    ]
+}
+
+#[test]
+fn test_changed_package_pulls_in_transitive_dependents() {
+    let changed = vec!["packages/core/src/router.rs".to_string(), "docs/index.md".to_string()];
+    let impact = TestImpact::compute("HEAD", &changed, &workspace());
+
+    assert_eq!(impact.files["packages/core/src/router.rs"].as_deref(), Some("montrs-core"));
+    assert_eq!(impact.files["docs/index.md"], None);
+    assert_eq!(impact.changed_packages.iter().collect::<Vec<_>>(), ["montrs-core"]);
+    assert_eq!(
+        impact.affected_packages.iter().collect::<Vec<_>>(),
+        ["montrs-cli", "montrs-core", "montrs-orm"]
+    );
+    assert!(!impact.workspace_wide);
+}
+
+#[test]
+fn test_prefix_match_respects_directory_boundaries() {
+    let packages = vec![package("core", "packages/core", &[]), package("core-macros", "packages/core-macros", &[])];
+    let changed = vec!["packages/core-macros/src/lib.rs".to_string()];
+    let impact = TestImpact::compute("HEAD", &changed, &packages);
+    assert_eq!(impact.affected_packages.iter().collect::<Vec<_>>(), ["core-macros"]);
+}
+
+#[test]
+fn test_lockfile_change_affects_every_package() {
+    let impact = TestImpact::compute("main", &["Cargo.lock".to_string()], &workspace());
+    assert!(impact.workspace_wide);
+    assert_eq!(impact.affected_packages.len(), 4);
+}
+
+#[test]
+fn test_impact_is_recorded_in_agent_dir() {
+    let dir = tempdir().unwrap();
+    let manager = AgentManager::new(dir.path());
+    assert!(manager.read_test_impact().unwrap().is_none());
+
+    let impact = TestImpact::compute("HEAD", &["packages/fmt/src/lib.rs".to_string()], &workspace());
+    manager.write_test_impact(&impact).unwrap();
+    assert_eq!(manager.read_test_impact().unwrap(), Some(impact));
+    assert!(dir.path().join(".agent/test-impact.json").exists());
+}
@@ -0,0 +1,143 @@
+use montrs_agent::llm::{LlmConfig, LlmError, ProviderKind};
+use montrs_core::{EnvConfig, EnvError};
+use std::collections::HashMap;
+
+struct MapEnv(HashMap<&'static str, &'static str>);
+
+impl EnvConfig for MapEnv {
+    fn get_var(&self, key: &str) -> Result<String, EnvError> {
+        self.0.get(key).map(|v| v.to_string()).ok_or_else(|| EnvError::MissingKey(key.to_string()))
+    }
+}
+
+fn env(vars: &[(&'static str, &'static str)]) -> MapEnv {
+    MapEnv(vars.iter().copied().collect())
+}
+
+#[test]
+fn test_config_from_env() {
+    let local = LlmConfig::from_env(&env(&[])).unwrap();
+    assert_eq!(local.provider, ProviderKind::Ollama);
+    assert_eq!(local.base_url, "http://localhost:11434");
+
+    let hosted = LlmConfig::from_env(&env(&[("OPENAI_API_KEY", "sk-test"), ("MONTRS_LLM_MODEL", "gpt-4o")])).unwrap();
+    assert_eq!((hosted.provider, hosted.model.as_str()), (ProviderKind::OpenAi, "gpt-4o"));
+    assert_eq!(hosted.api_key.as_deref(), Some("sk-test"));
+
+    // A compatible server without a key, e.g. vLLM.
+    let compatible = LlmConfig::from_env(&env(&[
+        ("MONTRS_LLM_PROVIDER", "OpenAI"),
+        ("MONTRS_LLM_BASE_URL", "http://localhost:8000/v1/"),
+        ("MONTRS_LLM_TIMEOUT_SECS", "5"),
+    ]))
+    .unwrap();
+    assert_eq!(compatible.base_url, "http://localhost:8000/v1");
+    assert_eq!(compatible.timeout.as_secs(), 5);
+
+    assert!(matches!(LlmConfig::from_env(&env(&[("MONTRS_LLM_PROVIDER", "openai")])), Err(LlmError::Config(_))));
+    assert!(matches!(LlmConfig::from_env(&env(&[("MONTRS_LLM_PROVIDER", "bard")])), Err(LlmError::Config(_))));
+    assert!(LlmConfig::from_env(&env(&[("MONTRS_LLM_TIMEOUT_SECS", "soon")])).is_err());
+}
+
+#[cfg(any(feature = "openai", feature = "ollama"))]
+mod http {
+    use montrs_agent::llm::{ChatMessage, CompletionOptions, LlmConfig, LlmError, ProviderKind};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    /// Serves one canned response per request and reports each request's path and body.
+    fn serve(responses: Vec<(u16, &'static str)>) -> (String, mpsc::Receiver<(String, serde_json::Value)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for (status, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':')
+                        && name.eq_ignore_ascii_case("content-length")
+                    {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut request = vec![0; length];
+                reader.read_exact(&mut request).unwrap();
+                let path = request_line.split_whitespace().nth(1).unwrap().to_string();
+                tx.send((path, serde_json::from_slice(&request).unwrap())).unwrap();
+
+                let mut stream = reader.into_inner();
+                write!(
+                    stream,
+                    "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        (url, rx)
+    }
+
+    #[cfg(feature = "openai")]
+    #[tokio::test]
+    async fn test_openai_compatible_provider() {
+        let (url, requests) = serve(vec![
+            (200, r#"{"model":"m1","choices":[{"message":{"role":"assistant","content":"Hi"}}],"usage":{"prompt_tokens":3,"completion_tokens":1}}"#),
+            (200, r#"{"data":[{"index":1,"embedding":[0.5]},{"index":0,"embedding":[0.25]}]}"#),
+            (401, r#"{"error":{"message":"bad key"}}"#),
+        ]);
+        let provider = LlmConfig::new(ProviderKind::OpenAi)
+            .with_base_url(format!("{}/v1", url))
+            .with_model("m1")
+            .with_api_key("sk-test")
+            .build()
+            .unwrap();
+
+        let options = CompletionOptions::default().with_temperature(0.0).with_max_tokens(16);
+        let reply = provider.chat(&[ChatMessage::system("Be brief"), ChatMessage::user("Hello")], &options).await.unwrap();
+        assert_eq!((reply.content.as_str(), reply.usage.unwrap().prompt_tokens), ("Hi", 3));
+        let (path, body) = requests.recv().unwrap();
+        assert_eq!(path, "/v1/chat/completions");
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert_eq!(body["max_tokens"], 16);
+
+        let vectors = provider.embed(&["a".to_string(), "b".to_string()]).await.unwrap();
+        assert_eq!(vectors, vec![vec![0.25], vec![0.5]]);
+
+        let err = provider.complete("Hello", &CompletionOptions::default()).await.unwrap_err();
+        assert!(matches!(err, LlmError::Api { status: 401, ref message, .. } if message == "bad key"));
+    }
+
+    #[cfg(feature = "ollama")]
+    #[tokio::test]
+    async fn test_ollama_provider() {
+        let (url, requests) = serve(vec![
+            (200, r#"{"model":"llama3.1","message":{"role":"assistant","content":"Pong"},"prompt_eval_count":4,"eval_count":2}"#),
+            (200, r#"{"response":"Done"}"#),
+            (200, r#"{"embeddings":[[1.0,2.0]]}"#),
+        ]);
+        let provider = LlmConfig::new(ProviderKind::Ollama).with_base_url(url).build().unwrap();
+
+        let options = CompletionOptions::default().with_max_tokens(8).with_stop("\n");
+        let reply = provider.chat(&[ChatMessage::user("Ping")], &options).await.unwrap();
+        assert_eq!(reply.content, "Pong");
+        let (path, body) = requests.recv().unwrap();
+        assert_eq!(path, "/api/chat");
+        assert_eq!((body["stream"].as_bool(), body["options"]["num_predict"].as_u64()), (Some(false), Some(8)));
+
+        assert_eq!(provider.complete("Go", &CompletionOptions::default()).await.unwrap(), "Done");
+        assert_eq!(requests.recv().unwrap().0, "/api/generate");
+        assert_eq!(provider.embed(&["x".to_string()]).await.unwrap(), vec![vec![1.0, 2.0]]);
+    }
+}
@@ -0,0 +1,120 @@
+use montrs_agent::AgentManager;
+use tempfile::tempdir;
+
+const APP: &str = r#"
+pub struct CheckoutRoute;
+impl Route<AppCfg> for CheckoutRoute {}
+
+pub struct HealthRoute;
+impl Route<AppCfg> for HealthRoute {}
+
+pub struct BillingPlate;
+impl Plate<AppCfg> for BillingPlate {}
+
+fn routes(router: &mut Router<AppCfg>) {
+    router
+        .register(CheckoutRoute)
+        .with_meta(meta::OWNER, "payments-team")
+        .with_meta("stability", "beta");
+    router.register(HealthRoute);
+}
+
+fn spec() {
+    AppSpec::new(cfg, env).with_plate(Box::new(BillingPlate.with_meta("owner", "payments-team")));
+}
+"#;
+
+#[test]
+fn test_heuristic_discovery_reads_meta_and_flags_missing_owner() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("src")).unwrap();
+    std::fs::write(dir.path().join("src/main.rs"), APP).unwrap();
+
+    let manager = AgentManager::new(dir.path());
+    let snapshot = manager.generate_snapshot("app").unwrap();
+
+    let checkout = snapshot.routes.iter().find(|r| r.path.ends_with("CheckoutRoute")).unwrap();
+    assert_eq!(checkout.metadata["owner"], "payments-team");
+    assert_eq!(checkout.metadata["stability"], "beta");
+
+    let billing = snapshot.plates.iter().find(|p| p.name == "BillingPlate").unwrap();
+    assert_eq!(billing.metadata["owner"], "payments-team");
+
+    let violations = manager.check_invariants(&snapshot).unwrap();
+    let ownership: Vec<_> = violations.iter().filter(|v| v.contains("does not declare an owner")).collect();
+    assert_eq!(ownership.len(), 1);
+    assert!(ownership[0].contains("HealthRoute"));
+}
+
+#[test]
+fn test_deprecations_are_reported_in_snapshot() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("src")).unwrap();
+    std::fs::write(
+        dir.path().join("src/main.rs"),
+        r#"
+pub struct UsersV1Route;
+impl Route<AppCfg> for UsersV1Route {}
+
+fn routes(router: &mut Router<AppCfg>) {
+    router
+        .register(UsersV1Route)
+        .with_meta(meta::OWNER, "identity")
+        .deprecated(Deprecation::new().sunset("2001-01-01").replacement("/api/v2/users"));
+}
+"#,
+    )
+    .unwrap();
+
+    let snapshot = AgentManager::new(dir.path()).generate_snapshot("app").unwrap();
+    assert_eq!(snapshot.deprecations.len(), 1);
+    let report = &snapshot.deprecations[0];
+    assert_eq!(report.kind, "route");
+    assert_eq!(report.deprecation.replacement.as_deref(), Some("/api/v2/users"));
+    assert!(report.past_sunset);
+}
+
+#[test]
+fn test_route_requirements_are_reported_in_snapshot() {
+    let dir = tempdir().unwrap();
+    std::fs::create_dir_all(dir.path().join("src")).unwrap();
+    std::fs::write(
+        dir.path().join("src/main.rs"),
+        r#"
+pub struct UsersAdminRoute;
+impl Route<AppCfg> for UsersAdminRoute {}
+
+pub struct RefundRoute;
+impl Route<AppCfg> for RefundRoute {}
+
+pub struct HealthRoute;
+impl Route<AppCfg> for HealthRoute {}
+
+fn routes(router: &mut Router<AppCfg>) {
+    router.register(UsersAdminRoute).requires(Permission::Admin);
+    router
+        .register(RefundRoute)
+        .with_meta(meta::OWNER, "payments")
+        .requires(Permission::role("support"))
+        .requires("orders:refund");
+    router.register(HealthRoute);
+}
+"#,
+    )
+    .unwrap();
+
+    let snapshot = AgentManager::new(dir.path()).generate_snapshot("app").unwrap();
+    let access: Vec<(&str, Vec<String>)> = snapshot
+        .authorization
+        .iter()
+        .map(|a| (a.route.as_str(), a.requires.iter().map(ToString::to_string).collect()))
+        .collect();
+    assert_eq!(
+        access,
+        [
+            ("(impl) HealthRoute", vec![]),
+            ("(impl) RefundRoute", vec!["role:support".to_string(), "orders:refund".to_string()]),
+            ("(impl) UsersAdminRoute", vec!["role:admin".to_string()]),
+        ]
+    );
+}
@@ -0,0 +1,108 @@
+use chrono::{Duration, TimeZone, Utc};
+use montrs_agent::query::{parse_status, parse_time_at, ErrorQuery, ErrorSort};
+use montrs_agent::storage::AgentStorage;
+use montrs_agent::{AgentErrorMetadata, AgentManager, ErrorRecord, ErrorStatus, ProjectError};
+use tempfile::tempdir;
+
+fn record(id: &str, package: &str, file: &str, level: &str, code: &str, days_ago: i64) -> ErrorRecord {
+    ErrorRecord {
+        id: id.to_string(),
+        timestamp: Utc::now() - Duration::days(days_ago),
+        version: 1,
+        status: ErrorStatus::Active,
+        detail: ProjectError {
+            package: Some(package.to_string()),
+            file: file.to_string(),
+            line: 1,
+            column: 1,
+            message: format!("{} in {}", code, file),
+            code_context: String::new(),
+            level: level.to_string(),
+            agent_metadata: Some(AgentErrorMetadata {
+                error_code: code.to_string(),
+                explanation: String::new(),
+                suggested_fixes: vec![],
+                rustc_error: None,
+                suggestions: vec![],
+                model_explanation: None,
+            }),
+        },
+        history: vec![],
+        git: None,
+    }
+}
+
+fn manager_with_errors() -> (tempfile::TempDir, AgentManager) {
+    let dir = tempdir().unwrap();
+    let manager = AgentManager::with_storage(dir.path(), AgentStorage::Dir(dir.path().join(".agent")));
+    for record in [
+        record("orm-1", "montrs-orm", "packages/orm/src/query.rs", "Error", "E0308", 1),
+        record("orm-2", "montrs-orm", "packages/orm/src/lib.rs", "Warning", "unused_variables", 10),
+        record("core-1", "montrs-core", "packages/core/src/router.rs", "Error", "E0599", 3),
+    ] {
+        manager.write_error_record(&record).unwrap();
+    }
+    // A later version of orm-1 that resolved it replaces the first.
+    let mut resolved = record("orm-1", "montrs-orm", "packages/orm/src/query.rs", "Error", "E0308", 0);
+    resolved.version = 2;
+    resolved.status = ErrorStatus::Resolved;
+    manager.write_error_record(&resolved).unwrap();
+    (dir, manager)
+}
+
+fn ids(manager: &AgentManager, query: &ErrorQuery) -> Vec<String> {
+    manager.query_errors(query).unwrap().errors.into_iter().map(|e| e.id).collect()
+}
+
+#[test]
+fn test_latest_versions_newest_first() {
+    let (_dir, manager) = manager_with_errors();
+    assert_eq!(ids(&manager, &ErrorQuery::default()), ["orm-1", "core-1", "orm-2"]);
+    assert_eq!(ids(&manager, &ErrorQuery { sort: ErrorSort::Oldest, ..Default::default() }), ["orm-2", "core-1", "orm-1"]);
+    assert_eq!(ids(&manager, &ErrorQuery { sort: ErrorSort::File, ..Default::default() }), ["core-1", "orm-2", "orm-1"]);
+}
+
+#[test]
+fn test_filters() {
+    let (_dir, manager) = manager_with_errors();
+    let query = |query: ErrorQuery| ids(&manager, &query);
+
+    assert_eq!(query(ErrorQuery { package: Some("orm".to_string()), ..Default::default() }), ["orm-1", "orm-2"]);
+    assert_eq!(query(ErrorQuery { file: Some("packages/core/**".to_string()), ..Default::default() }), ["core-1"]);
+    assert_eq!(query(ErrorQuery { file: Some("lib.rs".to_string()), ..Default::default() }), ["orm-2"]);
+    assert_eq!(query(ErrorQuery { level: Some("warning".to_string()), ..Default::default() }), ["orm-2"]);
+    assert_eq!(query(ErrorQuery { status: Some(ErrorStatus::Active), ..Default::default() }), ["core-1", "orm-2"]);
+    assert_eq!(query(ErrorQuery { error_code: Some("e0599".to_string()), ..Default::default() }), ["core-1"]);
+    assert_eq!(
+        query(ErrorQuery { package: Some("orm".to_string()), since: Some(Utc::now() - Duration::days(7)), ..Default::default() }),
+        ["orm-1"]
+    );
+    assert_eq!(query(ErrorQuery { until: Some(Utc::now() - Duration::days(2)), ..Default::default() }), ["core-1", "orm-2"]);
+}
+
+#[test]
+fn test_pagination() {
+    let (_dir, manager) = manager_with_errors();
+    let first = manager.query_errors(&ErrorQuery { limit: Some(2), ..Default::default() }).unwrap();
+    assert_eq!((first.errors.len(), first.total, first.next_offset()), (2, 3, Some(2)));
+
+    let last = manager.query_errors(&ErrorQuery { limit: Some(2), offset: 2, ..Default::default() }).unwrap();
+    assert_eq!(last.errors.iter().map(|e| e.id.as_str()).collect::<Vec<_>>(), ["orm-2"]);
+    assert_eq!(last.next_offset(), None);
+}
+
+#[test]
+fn test_parse_time_and_status() {
+    let now = Utc.with_ymd_and_hms(2024, 5, 10, 12, 0, 0).unwrap();
+    assert_eq!(parse_time_at("7d", now).unwrap(), now - Duration::days(7));
+    assert_eq!(parse_time_at("12h", now).unwrap(), now - Duration::hours(12));
+    assert_eq!(parse_time_at("2w", now).unwrap(), now - Duration::weeks(2));
+    assert_eq!(parse_time_at("2024-05-01", now).unwrap(), Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap());
+    assert_eq!(parse_time_at("2024-05-01T08:30:00+02:00", now).unwrap(), Utc.with_ymd_and_hms(2024, 5, 1, 6, 30, 0).unwrap());
+    assert!(parse_time_at("7y", now).is_err());
+    assert!(parse_time_at("last week", now).is_err());
+
+    assert_eq!(parse_status("Pending").unwrap(), ErrorStatus::Active);
+    assert_eq!(parse_status("fixed").unwrap(), ErrorStatus::Resolved);
+    assert!(parse_status("open").is_err());
+}
@@ -0,0 +1,112 @@
+use async_trait::async_trait;
+use montrs_agent::llm::{ChatMessage, ChatResponse, CompletionOptions, LlmError, LlmProvider};
+use montrs_agent::search::{chunk_documents, cosine, keyword_search};
+use montrs_agent::{AgentManager, AgentSnapshot};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tempfile::tempdir;
+
+const VOCABULARY: [&str; 4] = ["guard", "route", "database", "migration"];
+
+/// Embeds text as counts of a few words and counts the inputs it was sent.
+struct StubProvider {
+    model: &'static str,
+    embedded: AtomicUsize,
+}
+
+impl StubProvider {
+    fn new(model: &'static str) -> Self {
+        Self { model, embedded: AtomicUsize::new(0) }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for StubProvider {
+    fn name(&self) -> &str {
+        "stub"
+    }
+
+    fn embed_model(&self) -> &str {
+        self.model
+    }
+
+    async fn chat(&self, _messages: &[ChatMessage], _options: &CompletionOptions) -> Result<ChatResponse, LlmError> {
+        unreachable!("search only embeds")
+    }
+
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, LlmError> {
+        self.embedded.fetch_add(inputs.len(), Ordering::SeqCst);
+        Ok(inputs
+            .iter()
+            .map(|input| {
+                let input = input.to_lowercase();
+                VOCABULARY.iter().map(|word| input.matches(word).count() as f32).collect()
+            })
+            .collect())
+    }
+}
+
+fn snapshot(manager: &AgentManager, docs: &[(&str, &str)]) -> AgentSnapshot {
+    let mut snapshot = manager.generate_framework_snapshot();
+    snapshot.packages.clear();
+    snapshot.documentation_snippets = docs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<HashMap<_, _>>();
+    snapshot
+}
+
+#[test]
+fn test_chunks_and_keyword_search() {
+    let dir = tempdir().unwrap();
+    let manager = AgentManager::new(dir.path());
+    let snapshot = snapshot(
+        &manager,
+        &[
+            ("docs/router.md", "# Router\n\nIntro.\n\n## Guards\n\nA route guard runs first.\n\n```rust\n# hidden line\n```"),
+            ("docs/orm.md", "# ORM\n\nRun a database migration."),
+        ],
+    );
+
+    let chunks = chunk_documents(&snapshot);
+    let keys: Vec<(&str, usize)> = chunks.iter().map(|c| (c.key.as_str(), c.chunk)).collect();
+    assert_eq!(keys, [("docs/orm.md", 0), ("docs/router.md", 0), ("docs/router.md", 1)]);
+    assert!(chunks[2].text.starts_with("## Guards") && chunks[2].text.contains("# hidden line"));
+
+    let results = keyword_search(&chunks, "How do I add a route guard?", 2);
+    assert_eq!(results[0].key, "docs/router.md");
+    assert!(results[0].text.starts_with("## Guards"));
+    assert_eq!(results.len(), 1);
+    assert!(keyword_search(&chunks, "a", 5).is_empty());
+
+    assert_eq!(cosine(&[1.0, 0.0], &[2.0, 0.0]), 1.0);
+    assert_eq!(cosine(&[1.0, 0.0], &[0.0, 0.0]), 0.0);
+}
+
+#[tokio::test]
+async fn test_semantic_search_reuses_unchanged_embeddings() {
+    let dir = tempdir().unwrap();
+    let manager = AgentManager::new(dir.path());
+    let docs = [
+        ("docs/guards.md", "Protect a route with a guard. The guard runs before the route loader."),
+        ("docs/migrations.md", "Each database migration runs once per database."),
+    ];
+    manager.write_snapshot(&snapshot(&manager, &docs), "json").unwrap();
+
+    let provider = StubProvider::new("words-v1");
+    let results = manager.semantic_search(&provider, "add a route guard", 1).await.unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].key, "docs/guards.md");
+    assert!(results[0].score > 0.9);
+    assert!(manager.embeddings_file().ends_with(".agent/embeddings/index.json"));
+    // Two chunks and the query.
+    assert_eq!(provider.embedded.load(Ordering::SeqCst), 3);
+
+    let changed = [docs[0], ("docs/migrations.md", "Migrations change the database schema.")];
+    manager.write_snapshot(&snapshot(&manager, &changed), "json").unwrap();
+    let stats = manager.update_embeddings(&provider).await.unwrap();
+    assert_eq!((stats.chunks, stats.embedded, stats.reused), (2, 1, 1));
+
+    let index = manager.load_embeddings().unwrap().unwrap();
+    assert_eq!((index.model.as_str(), index.dimensions), ("stub/words-v1", VOCABULARY.len()));
+
+    let stats = manager.update_embeddings(&StubProvider::new("words-v2")).await.unwrap();
+    assert_eq!(stats.embedded, 2);
+}
@@ -0,0 +1,58 @@
+use montrs_agent::AgentManager;
+use montrs_agent::session::{CommandRecord, SessionEvent, SessionOutcome};
+use tempfile::tempdir;
+
+#[test]
+fn test_session_log_round_trip() {
+    let dir = tempdir().unwrap();
+    let manager = AgentManager::new(dir.path());
+    assert!(manager.sessions().unwrap().is_empty());
+    assert_eq!(manager.resume_context(5).unwrap(), "No previous agent sessions.\n");
+
+    let id = manager.start_session("Add a route guard to /admin").unwrap();
+    for event in [
+        SessionEvent::FileTouched { path: "src/routes/admin.rs".to_string() },
+        SessionEvent::CommandRun { command: "montrs test".to_string(), exit_code: Some(101) },
+        SessionEvent::FileTouched { path: "src/routes/admin.rs".to_string() },
+        SessionEvent::Note { text: "Guards run before loaders".to_string() },
+    ] {
+        manager.record_session_event(&id, event).unwrap();
+    }
+
+    let open = manager.open_session().unwrap().unwrap();
+    assert_eq!(open.id, id);
+    assert_eq!(open.files, ["src/routes/admin.rs"]);
+    assert_eq!(open.commands, [CommandRecord { command: "montrs test".to_string(), exit_code: Some(101) }]);
+
+    manager.finish_session(&id, SessionOutcome::Completed, "Guard added, tests pass").unwrap();
+    assert!(manager.open_session().unwrap().is_none());
+    let lines = std::fs::read_to_string(manager.sessions_dir().join(format!("{}.jsonl", id))).unwrap();
+    assert_eq!(lines.lines().count(), 6);
+    assert!(lines.lines().next().unwrap().contains(r#""event":"started""#));
+
+    let session = manager.load_session(&id).unwrap();
+    assert_eq!(session.outcome, Some(SessionOutcome::Completed));
+    let markdown = session.to_markdown();
+    assert!(markdown.starts_with("### Add a route guard to /admin (completed)"));
+    assert!(markdown.contains("- Ran `montrs test` (exit 101)"));
+
+    assert!(manager.record_session_event("missing", SessionEvent::Note { text: String::new() }).is_err());
+    assert!(manager.record_session_event(&id, SessionEvent::Started { goal: String::new() }).is_err());
+    assert!("done".parse::<SessionOutcome>().is_err());
+}
+
+#[test]
+fn test_resume_context_lists_newest_first() {
+    let dir = tempdir().unwrap();
+    let manager = AgentManager::new(dir.path());
+    let first = manager.start_session("First task").unwrap();
+    manager.finish_session(&first, SessionOutcome::Failed, "Blocked on a migration").unwrap();
+    manager.start_session("Second task").unwrap();
+
+    let sessions = manager.sessions().unwrap();
+    assert_eq!(sessions.iter().map(|s| s.goal.as_str()).collect::<Vec<_>>(), ["Second task", "First task"]);
+
+    let context = manager.resume_context(1).unwrap();
+    assert!(context.starts_with("## Previous agent sessions (1 of 2)"));
+    assert!(context.contains("Second task (in progress)") && !context.contains("First task"));
+}
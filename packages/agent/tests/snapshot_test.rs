@@ -0,0 +1,102 @@
+use montrs_agent::snapshot::{self, SnapshotReader, SnapshotRecord, SNAPSHOT_JSONL};
+use montrs_agent::{AgentManager, PlateSummary, RouteSummary};
+use std::collections::HashMap;
+use tempfile::tempdir;
+
+fn manager_with_snapshot(root: &std::path::Path) -> (AgentManager, montrs_agent::AgentSnapshot) {
+    let manager = AgentManager::new(root);
+    let mut snapshot = manager.generate_framework_snapshot();
+    snapshot.routes.push(RouteSummary {
+        path: "/users/:id".to_string(),
+        kind: "loader".to_string(),
+        description: "Loads a user".to_string(),
+        input_schema: None,
+        output_schema: None,
+        params_schema: None,
+        loader_output_schema: None,
+        action_input_schema: None,
+        action_output_schema: None,
+        metadata: HashMap::new(),
+        parent: None,
+    });
+    snapshot.plates.push(PlateSummary {
+        name: "auth".to_string(),
+        description: "Sessions".to_string(),
+        dependencies: vec![],
+        metadata: HashMap::new(),
+    });
+    snapshot
+        .documentation_snippets
+        .insert("docs/../escape".to_string(), "stays inside".to_string());
+    (manager, snapshot)
+}
+
+#[test]
+fn test_stream_round_trip_and_queries() {
+    let dir = tempdir().unwrap();
+    let (manager, snapshot) = manager_with_snapshot(dir.path());
+    manager.write_snapshot(&snapshot, "jsonl").unwrap();
+
+    let agent_dir = manager.agent_dir();
+    let first = SnapshotReader::open(&agent_dir.join(SNAPSHOT_JSONL)).unwrap().next().unwrap().unwrap();
+    assert!(matches!(first, SnapshotRecord::Header(h) if h.project_name == snapshot.project_name));
+    assert!(agent_dir.join("snippets/docs/_/escape.md").exists());
+
+    let restored = manager.read_snapshot().unwrap();
+    assert_eq!(restored.routes.len(), 1);
+    assert_eq!(restored.documentation_snippets, snapshot.documentation_snippets);
+
+    assert_eq!(manager.snapshot_routes().unwrap()[0].path, "/users/:id");
+    assert_eq!(manager.snapshot_plates().unwrap()[0].name, "auth");
+    assert_eq!(
+        manager.documentation_snippet("architecture").unwrap().as_deref(),
+        Some(snapshot.documentation_snippets["architecture"].as_str())
+    );
+    assert_eq!(manager.documentation_snippet("missing").unwrap(), None);
+    assert_eq!(manager.snippet_keys().unwrap().len(), snapshot.documentation_snippets.len());
+}
+
+#[test]
+fn test_sections_of_a_regular_snapshot() {
+    let dir = tempdir().unwrap();
+    let (manager, snapshot) = manager_with_snapshot(dir.path());
+    assert!(manager.snapshot_routes().is_err());
+
+    manager.write_snapshot(&snapshot, "json").unwrap();
+    let path = manager.snapshot_file();
+
+    let routes: Vec<RouteSummary> = snapshot::read_section(&path, "routes").unwrap().unwrap();
+    assert_eq!(routes[0].description, "Loads a user");
+    assert!(snapshot::read_section::<Vec<RouteSummary>>(&path, "nope").unwrap().is_none());
+    assert_eq!(
+        snapshot::read_snippet(&path, "docs/../escape").unwrap().as_deref(),
+        Some("stays inside")
+    );
+
+    assert_eq!(manager.snapshot_plates().unwrap()[0].name, "auth");
+    let mut keys: Vec<_> = snapshot.documentation_snippets.keys().cloned().collect();
+    keys.sort();
+    assert_eq!(manager.snippet_keys().unwrap(), keys);
+}
+
+#[test]
+fn test_configured_preset_is_resolved_and_streamed() {
+    let dir = tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("montrs.toml"),
+        "[preset]\ntarget = \"edge\"\n\n[preset.cors]\nallowed_origins = [\"https://app.example.com\"]\n\n[preset.edge.limits]\nmax_body_bytes = 4096\n",
+    )
+    .unwrap();
+    let manager = AgentManager::new(dir.path());
+    let snapshot = manager.generate_snapshot("app").unwrap();
+
+    let summary = snapshot.preset.as_ref().unwrap();
+    assert_eq!(summary.target, montrs_core::Target::Edge);
+    assert_eq!(summary.preset.cors.allowed_origins, ["https://app.example.com"]);
+    assert_eq!(summary.preset.limits.max_body_bytes, 4096);
+    assert!(!summary.preset.assets.serve, "unset fields keep the edge defaults");
+    assert!(montrs_agent::export::to_markdown(&snapshot).contains("## Preset"));
+
+    manager.write_snapshot(&snapshot, "jsonl").unwrap();
+    assert_eq!(manager.read_snapshot().unwrap().preset.unwrap().preset, summary.preset);
+}
@@ -0,0 +1,63 @@
+use montrs_agent::AgentManager;
+use montrs_agent::storage::{AgentSettings, AgentStorage};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tempfile::tempdir;
+
+#[test]
+fn test_writable_project_keeps_agent_dir() {
+    let dir = tempdir().unwrap();
+    let resolved = AgentStorage::resolve(dir.path(), &AgentSettings::default());
+    assert_eq!(resolved.storage, AgentStorage::Dir(dir.path().join(".agent")));
+    assert_eq!(resolved.fallback, None);
+    assert!(dir.path().join(".agent").is_dir());
+
+    let settings = AgentSettings { dir: Some(PathBuf::from("build/agent")), disable: false, ..Default::default() };
+    let resolved = AgentStorage::resolve(dir.path(), &settings);
+    assert_eq!(resolved.storage, AgentStorage::Dir(dir.path().join("build/agent")));
+}
+
+#[test]
+fn test_unwritable_dir_falls_back_to_temp() {
+    let dir = tempdir().unwrap();
+    // A file where the directory should be cannot be created over, even as root.
+    std::fs::write(dir.path().join(".agent"), "").unwrap();
+
+    let resolved = AgentStorage::resolve(dir.path(), &AgentSettings::default());
+    let fallback = resolved.storage.dir().expect("temp directory is writable").to_path_buf();
+    assert!(fallback.starts_with(std::env::temp_dir()));
+    assert!(resolved.fallback.unwrap().contains("is not writable"));
+
+    // The same project always gets the same fallback.
+    assert_eq!(AgentStorage::resolve(dir.path(), &AgentSettings::default()).storage.dir(), Some(fallback.as_path()));
+    let _ = std::fs::remove_dir_all(fallback);
+}
+
+#[test]
+fn test_env_overrides_config() {
+    let env: HashMap<&str, &str> = HashMap::from([("MONTRS_AGENT_DIR", "/var/cache/agent"), ("MONTRS_AGENT_DISABLE", "1")]);
+    let mut settings = AgentSettings { dir: Some(PathBuf::from("artifacts")), disable: false, ..Default::default() };
+    settings.apply_env(|key| env.get(key).map(|v| v.to_string()));
+    assert_eq!(settings, AgentSettings { dir: Some(PathBuf::from("/var/cache/agent")), disable: true, ..Default::default() });
+
+    settings.apply_env(|key| (key == "MONTRS_AGENT_DISABLE").then(|| "false".to_string()));
+    assert!(!settings.disable);
+    assert_eq!(AgentStorage::resolve(std::path::Path::new("/"), &AgentSettings { disable: true, ..settings }).storage, AgentStorage::Memory);
+}
+
+#[test]
+fn test_memory_mode_writes_nothing() {
+    let dir = tempdir().unwrap();
+    let manager = AgentManager::with_storage(dir.path(), AgentStorage::Memory);
+    assert!(!manager.is_persistent());
+
+    manager.ensure_dir().unwrap();
+    let snapshot = manager.generate_framework_snapshot();
+    manager.write_snapshot(&snapshot, "json").unwrap();
+    manager.write_tools_spec().unwrap();
+    manager.report_error("boom".to_string()).unwrap();
+
+    assert!(!dir.path().join(".agent").exists());
+    assert!(manager.list_active_errors().unwrap().is_empty());
+    assert!(manager.start_session("fix the build").unwrap_err().to_string().contains("MONTRS_AGENT_DIR"));
+}
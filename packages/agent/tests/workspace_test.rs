@@ -0,0 +1,80 @@
+use montrs_agent::workspace::{WorkspaceIndex, WorkspacesConfig};
+use montrs_agent::{AgentManager, PlateSummary, RouteSummary};
+use std::collections::HashMap;
+use tempfile::tempdir;
+
+fn route(path: &str, description: &str) -> RouteSummary {
+    RouteSummary {
+        path: path.to_string(),
+        kind: "loader".to_string(),
+        description: description.to_string(),
+        input_schema: None,
+        output_schema: None,
+        params_schema: None,
+        loader_output_schema: None,
+        action_input_schema: None,
+        action_output_schema: None,
+        metadata: HashMap::new(),
+        parent: None,
+    }
+}
+
+fn project(root: &std::path::Path, routes: Vec<RouteSummary>, plate: &str) {
+    std::fs::create_dir_all(root).unwrap();
+    let manager = AgentManager::new(root);
+    let mut snapshot = manager.generate_framework_snapshot();
+    snapshot.routes = routes;
+    snapshot.plates = vec![PlateSummary {
+        name: plate.to_string(),
+        description: format!("The {} plate", plate),
+        dependencies: vec![],
+        metadata: HashMap::new(),
+    }];
+    manager.write_snapshot(&snapshot, "json").unwrap();
+}
+
+#[test]
+fn test_workspace_index_and_search() {
+    let dir = tempdir().unwrap();
+    project(&dir.path().join("shop"), vec![route("/users/:id", "Loads a customer")], "billing");
+    project(&dir.path().join("admin-portal"), vec![route("/reports/*rest", "Renders reports")], "audit");
+
+    let config: WorkspacesConfig = toml::from_str(&format!(
+        r#"
+        [workspaces.acme]
+        projects = [
+            {{ path = "{0}/shop" }},
+            {{ name = "admin", path = "{0}/admin-portal" }},
+            {{ path = "{0}/missing" }},
+        ]
+        "#,
+        dir.path().display()
+    ))
+    .unwrap();
+
+    let index = WorkspaceIndex::build("acme", config.workspace("acme").unwrap());
+    let names: Vec<_> = index.projects.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["shop", "admin", "missing"]);
+    assert_eq!(index.projects[0].routes.len(), 1);
+    assert!(index.projects[0].snapshot_timestamp.is_some());
+    assert!(index.projects[2].error.is_some());
+
+    let hits = index.search("customer");
+    assert_eq!((hits.len(), hits[0].project.as_str(), hits[0].name.as_str()), (1, "shop", "/users/:id"));
+    assert_eq!(index.search("/users/42")[0].name, "/users/:id");
+    assert_eq!(index.search("/reports/2024/q1")[0].project, "admin");
+    assert!(index.search("/users/42/orders").is_empty());
+    assert_eq!(index.search("AUDIT")[0].kind, "plate");
+
+    let path = dir.path().join("index/acme.json");
+    index.write(&path).unwrap();
+    assert_eq!(WorkspaceIndex::load(&path).unwrap().search("billing").len(), 1);
+    assert!(config.workspace("other").is_err());
+}
+
+#[test]
+fn test_missing_config_is_empty() {
+    let dir = tempdir().unwrap();
+    let config = WorkspacesConfig::load(&dir.path().join("workspaces.toml")).unwrap();
+    assert!(config.workspaces.is_empty());
+}
@@ -0,0 +1,202 @@
+//! Loader response encoding on a multi-megabyte payload: `Router::load` followed by
+//! `serde_json::to_vec` (the `Value` round-trip), `Router::load_to` straight into the
+//! body buffer, and a pre-serialized `JsonBytes` handed through `Router::load_bytes`.
+//!
+//! Run with `montrs bench large_payloads` or `cargo bench -p montrs-bench --bench large_payloads`.
+
+use async_trait::async_trait;
+use montrs_bench::{BenchConfig, BenchRunner, SimpleBench};
+use montrs_core::{
+    AppConfig, EnvConfig, EnvError, IntoView, JsonBytes, Route, RouteAction, RouteContext, RouteError,
+    RouteLoader, RouteParams, RouteView, Router,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::hint::black_box;
+use std::sync::Arc;
+
+const ROWS: u32 = 40_000;
+
+#[derive(Clone)]
+struct BenchConfigApp;
+impl AppConfig for BenchConfigApp {
+    type Error = std::io::Error;
+    type Env = BenchEnv;
+}
+
+#[derive(Clone)]
+struct BenchEnv;
+impl EnvConfig for BenchEnv {
+    fn get_var(&self, key: &str) -> Result<String, EnvError> {
+        Err(EnvError::MissingKey(key.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct NoParams {}
+impl RouteParams for NoParams {}
+
+#[derive(Serialize, Deserialize)]
+struct Row {
+    id: u32,
+    email: String,
+    display_name: String,
+    tags: Vec<String>,
+    score: f64,
+    active: bool,
+}
+
+/// Shares the rows between iterations so the benchmark measures encoding, not cloning.
+#[derive(Clone)]
+struct Rows(Arc<Vec<Row>>);
+
+impl Serialize for Rows {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rows {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<Row>::deserialize(deserializer).map(|rows| Rows(Arc::new(rows)))
+    }
+}
+
+struct RowsLoader(Rows);
+#[async_trait]
+impl RouteLoader<NoParams, BenchConfigApp> for RowsLoader {
+    type Output = Rows;
+    async fn load(&self, _ctx: RouteContext<'_, BenchConfigApp>, _params: NoParams) -> Result<Rows, RouteError> {
+        Ok(self.0.clone())
+    }
+}
+
+struct CachedLoader(JsonBytes);
+#[async_trait]
+impl RouteLoader<NoParams, BenchConfigApp> for CachedLoader {
+    type Output = JsonBytes;
+    async fn load(&self, _ctx: RouteContext<'_, BenchConfigApp>, _params: NoParams) -> Result<JsonBytes, RouteError> {
+        Ok(self.0.clone())
+    }
+}
+
+struct NoAction;
+#[async_trait]
+impl RouteAction<NoParams, BenchConfigApp> for NoAction {
+    type Input = ();
+    type Output = ();
+    async fn act(&self, _ctx: RouteContext<'_, BenchConfigApp>, _params: NoParams, _input: ()) -> Result<(), RouteError> {
+        Ok(())
+    }
+}
+
+struct NoView;
+impl RouteView for NoView {
+    fn render(&self) -> impl IntoView {}
+}
+
+struct RowsRoute(Rows);
+impl Route<BenchConfigApp> for RowsRoute {
+    type Params = NoParams;
+    type Loader = RowsLoader;
+    type Action = NoAction;
+    type View = NoView;
+    fn path() -> &'static str {
+        "/rows"
+    }
+    fn loader(&self) -> RowsLoader {
+        RowsLoader(self.0.clone())
+    }
+    fn action(&self) -> NoAction {
+        NoAction
+    }
+    fn view(&self) -> NoView {
+        NoView
+    }
+}
+
+struct CachedRoute(JsonBytes);
+impl Route<BenchConfigApp> for CachedRoute {
+    type Params = NoParams;
+    type Loader = CachedLoader;
+    type Action = NoAction;
+    type View = NoView;
+    fn path() -> &'static str {
+        "/rows/cached"
+    }
+    fn loader(&self) -> CachedLoader {
+        CachedLoader(self.0.clone())
+    }
+    fn action(&self) -> NoAction {
+        NoAction
+    }
+    fn view(&self) -> NoView {
+        NoView
+    }
+}
+
+fn rows() -> Rows {
+    Rows(Arc::new(
+        (0..ROWS)
+            .map(|id| Row {
+                id,
+                email: format!("user{}@example.com", id),
+                display_name: format!("User Number {}", id),
+                tags: vec!["customer".to_string(), format!("cohort-{}", id % 12)],
+                score: f64::from(id) * 1.5,
+                active: id % 3 != 0,
+            })
+            .collect(),
+    ))
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // `cargo bench` passes `--bench` to harness-less targets.
+    let config = BenchConfig::from_iter(std::env::args().filter(|arg| arg != "--bench"));
+    let mut runner = BenchRunner::with_config(config);
+
+    let rows = rows();
+    let cached = JsonBytes::encode(&rows)?;
+    println!("Payload: {:.2} MB ({} rows)", cached.len() as f64 / 1024.0 / 1024.0, ROWS);
+
+    let mut router = Router::<BenchConfigApp>::new();
+    router.register(RowsRoute(rows));
+    router.register(CachedRoute(cached));
+    let router = Arc::new(router);
+
+    {
+        let router = router.clone();
+        runner.add(SimpleBench::new("large_payloads/value_round_trip", move || {
+            let router = router.clone();
+            async move {
+                let ctx = RouteContext { config: &BenchConfigApp, env: &BenchEnv };
+                let value = router.load("/rows", ctx, serde_json::json!({})).await?;
+                black_box(serde_json::to_vec(&value)?);
+                Ok(())
+            }
+        }));
+    }
+    {
+        let router = router.clone();
+        runner.add(SimpleBench::new("large_payloads/load_to_writer", move || {
+            let router = router.clone();
+            async move {
+                let ctx = RouteContext { config: &BenchConfigApp, env: &BenchEnv };
+                let mut body = Vec::new();
+                router.load_to("/rows", ctx, serde_json::json!({}), &mut body).await?;
+                black_box(body);
+                Ok(())
+            }
+        }));
+    }
+    runner.add(SimpleBench::new("large_payloads/json_bytes_pass_through", move || {
+        let router = router.clone();
+        async move {
+            let ctx = RouteContext { config: &BenchConfigApp, env: &BenchEnv };
+            black_box(router.load_bytes("/rows/cached", ctx, serde_json::json!({})).await?);
+            Ok(())
+        }
+    }));
+
+    runner.run().await
+}
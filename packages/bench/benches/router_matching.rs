@@ -0,0 +1,153 @@
+//! Route matching on a 1k-route table: a linear pattern scan against `RouteTrie`,
+//! plus lookups shaped to make a backtracking matcher blow up.
+//!
+//! Run with `montrs bench router_matching` or `cargo bench -p montrs-bench --bench router_matching`.
+
+use montrs_bench::{BenchConfig, BenchRunner, SimpleBench};
+use montrs_core::matcher::{MAX_SEGMENTS, RouteTrie};
+use std::hint::black_box;
+use std::sync::Arc;
+
+const ROUTES: usize = 1000;
+
+/// A realistic mix: static listings, param detail pages, nested params and a few catch-alls.
+fn route_table() -> Vec<&'static str> {
+    (0..ROUTES)
+        .map(|i| {
+            let pattern = match i % 4 {
+                0 => format!("/svc{}/items", i / 4),
+                1 => format!("/svc{}/items/:id", i / 4),
+                2 => format!("/svc{}/items/:id/revisions/:rev", i / 4),
+                _ => format!("/svc{}/assets/*path", i / 4),
+            };
+            &*Box::leak(pattern.into_boxed_str())
+        })
+        .collect()
+}
+
+/// Request paths spread across the table, including misses.
+fn request_paths() -> Vec<String> {
+    (0..ROUTES / 4)
+        .step_by(5)
+        .flat_map(|i| {
+            [
+                format!("/svc{}/items", i),
+                format!("/svc{}/items/{}", i, i * 7),
+                format!("/svc{}/items/{}/revisions/3", i, i),
+                format!("/svc{}/assets/img/logo.png", i),
+                format!("/svc{}/unknown", i),
+            ]
+        })
+        .collect()
+}
+
+/// The matcher this benchmark replaces: try every pattern in registration order.
+fn linear_match(patterns: &[&'static str], path: &str) -> Option<&'static str> {
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    patterns.iter().copied().find(|pattern| {
+        let parts: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+        if let Some(last) = parts.last()
+            && last.starts_with('*')
+        {
+            let fixed = &parts[..parts.len() - 1];
+            return segments.len() >= fixed.len() && matches_segments(fixed, &segments[..fixed.len()]);
+        }
+        parts.len() == segments.len() && matches_segments(&parts, &segments)
+    })
+}
+
+fn matches_segments(parts: &[&str], segments: &[&str]) -> bool {
+    parts.iter().zip(segments).all(|(part, segment)| part.starts_with(':') || part == segment)
+}
+
+/// Every depth has both a static and a param branch, and none of them end in
+/// the requested leaf, so a naive backtracking matcher would try 2^depth paths.
+fn ambiguous_trie(depth: usize) -> RouteTrie {
+    let mut trie = RouteTrie::new();
+    for i in 0..depth {
+        let mut pattern = String::new();
+        for d in 0..depth {
+            pattern.push_str(if d == i { "/x" } else { "/:p" });
+        }
+        pattern.push_str("/end");
+        trie.insert(Box::leak(pattern.into_boxed_str()));
+    }
+    trie
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // `cargo bench` passes `--bench` to harness-less targets.
+    let config = BenchConfig::from_iter(std::env::args().filter(|arg| arg != "--bench"));
+    let mut runner = BenchRunner::with_config(config);
+
+    let patterns = Arc::new(route_table());
+    let paths = Arc::new(request_paths());
+    let trie = Arc::new({
+        let mut trie = RouteTrie::new();
+        for pattern in patterns.iter() {
+            trie.insert(pattern);
+        }
+        trie
+    });
+
+    {
+        let (patterns, paths) = (patterns.clone(), paths.clone());
+        runner.add(SimpleBench::new("router_matching/linear_1k", move || {
+            let (patterns, paths) = (patterns.clone(), paths.clone());
+            async move {
+                for path in paths.iter() {
+                    black_box(linear_match(&patterns, path));
+                }
+                Ok(())
+            }
+        }));
+    }
+    {
+        let (trie, paths) = (trie.clone(), paths.clone());
+        runner.add(SimpleBench::new("router_matching/trie_1k", move || {
+            let (trie, paths) = (trie.clone(), paths.clone());
+            async move {
+                for path in paths.iter() {
+                    black_box(trie.at(path));
+                }
+                Ok(())
+            }
+        }));
+    }
+    {
+        let patterns = patterns.clone();
+        runner.add(SimpleBench::new("router_matching/trie_build_1k", move || {
+            let patterns = patterns.clone();
+            async move {
+                let mut trie = RouteTrie::new();
+                for pattern in patterns.iter() {
+                    trie.insert(pattern);
+                }
+                black_box(trie.len());
+                Ok(())
+            }
+        }));
+    }
+
+    let ambiguous = Arc::new(ambiguous_trie(24));
+    let miss = Arc::new("/x".repeat(24) + "/nope");
+    runner.add(SimpleBench::new("router_matching/pathological_ambiguous_miss", move || {
+        let (trie, miss) = (ambiguous.clone(), miss.clone());
+        async move {
+            black_box(trie.at(&miss));
+            Ok(())
+        }
+    }));
+
+    let deep = Arc::new("/a".repeat(MAX_SEGMENTS * 64));
+    runner.add(SimpleBench::new("router_matching/pathological_deep_path", move || {
+        let (trie, deep) = (trie.clone(), deep.clone());
+        async move {
+            black_box(trie.at(&deep));
+            Ok(())
+        }
+    }));
+
+    runner.run().await
+}
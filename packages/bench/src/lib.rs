@@ -19,6 +19,9 @@ pub use report::Report;
 pub use runner::{BenchRunner, Benchmark};
 pub use weights::Weight;
 
+/// This crate's version and the `montrs-core` it needs; see [`montrs_core::compat`].
+pub const CRATE_VERSION: montrs_core::CrateVersion = montrs_core::crate_version!();
+
 use montrs_core::AgentError;
 use std::future::Future;
 use thiserror::Error;
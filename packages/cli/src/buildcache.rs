@@ -0,0 +1,202 @@
+//! Shared compilation caches for `montrs build` (`[build.cache]`).
+//!
+//! The build runs rustc through a `RUSTC_WRAPPER`: sccache when it is
+//! installed, or any wrapper the project names. For sccache the backend
+//! settings become its `SCCACHE_*` variables, so a CI fleet can share one
+//! bucket, and the build summary reports how many compilations the cache
+//! answered.
+
+use crate::config::{BuildCacheConfig, CacheBackend};
+use crate::report::reporter;
+use anyhow::{Result, bail};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The wrapper a build runs under.
+#[derive(Debug)]
+pub struct CacheWrapper {
+    pub program: PathBuf,
+    /// Whether `RUSTC_WRAPPER` was already set rather than picked from `[build.cache]`.
+    pub from_env: bool,
+}
+
+impl CacheWrapper {
+    fn is_sccache(&self) -> bool {
+        self.program.file_stem().is_some_and(|stem| stem == "sccache")
+    }
+
+    /// sccache's hit and miss counters, for comparing before and after a build.
+    pub fn stats(&self) -> Option<CacheStats> {
+        if !self.is_sccache() {
+            return None;
+        }
+        let output = Command::new(&self.program).args(["--show-stats", "--stats-format", "json"]).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        CacheStats::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+/// Compilations sccache answered from the cache and compiled itself.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Reads `sccache --show-stats --stats-format json`.
+    pub fn parse(json: &str) -> Option<Self> {
+        let value: serde_json::Value = serde_json::from_str(json).ok()?;
+        let stats = value.get("stats")?;
+        let count = |key: &str| -> u64 {
+            stats
+                .get(key)
+                .and_then(|c| c.get("counts"))
+                .and_then(|c| c.as_object())
+                .map(|counts| counts.values().filter_map(|n| n.as_u64()).sum())
+                .unwrap_or(0)
+        };
+        Some(Self { hits: count("cache_hits"), misses: count("cache_misses") })
+    }
+
+    /// The counts added since `earlier`.
+    pub fn since(self, earlier: CacheStats) -> CacheStats {
+        CacheStats { hits: self.hits.saturating_sub(earlier.hits), misses: self.misses.saturating_sub(earlier.misses) }
+    }
+
+    /// Share of compilations answered by the cache, in percent.
+    pub fn hit_rate(&self) -> Option<f64> {
+        let total = self.hits + self.misses;
+        (total > 0).then(|| self.hits as f64 / total as f64 * 100.0)
+    }
+}
+
+/// Sets up the wrapper `config` asks for. Returns `None` when the build runs
+/// without one.
+pub fn configure(config: &BuildCacheConfig) -> Result<Option<CacheWrapper>> {
+    let step = reporter().step("compilation cache");
+    if let Some(existing) = std::env::var_os("RUSTC_WRAPPER").filter(|w| !w.is_empty()) {
+        let wrapper = CacheWrapper { program: which::which(&existing).unwrap_or_else(|_| existing.into()), from_env: true };
+        step.finish();
+        return Ok(Some(wrapper));
+    }
+
+    let program = match config.wrapper.as_str() {
+        "none" => {
+            step.skip("turned off in [build.cache]");
+            return Ok(None);
+        }
+        "auto" => match which::which("sccache") {
+            Ok(path) => path,
+            Err(_) => {
+                step.skip("sccache not found; run `cargo install sccache` to share compilation results");
+                return Ok(None);
+            }
+        },
+        name => match which::which(name) {
+            Ok(path) => path,
+            Err(_) => {
+                step.fail();
+                bail!("The `[build.cache] wrapper` `{}` was not found", name);
+            }
+        },
+    };
+    let wrapper = CacheWrapper { program, from_env: false };
+    if wrapper.is_sccache() {
+        if let Err(e) = set_backend(config) {
+            step.fail();
+            return Err(e);
+        }
+    } else if config.backend != CacheBackend::Local {
+        reporter().warn(format!(
+            "[build.cache] backend only applies to sccache, not {}",
+            wrapper.program.display()
+        ));
+    }
+    unsafe {
+        std::env::set_var("RUSTC_WRAPPER", &wrapper.program);
+    }
+    step.finish();
+    Ok(Some(wrapper))
+}
+
+/// Exports the backend as `SCCACHE_*` variables. Variables already set in
+/// the environment are kept, so CI can override any of them.
+fn set_backend(config: &BuildCacheConfig) -> Result<()> {
+    // The sccache server may run from another directory.
+    let dir = config.dir.as_ref().map(|dir| {
+        let dir = Path::new(dir);
+        std::env::current_dir().map(|cwd| cwd.join(dir)).unwrap_or_else(|_| dir.to_path_buf()).to_string_lossy().into_owned()
+    });
+    let mut vars: Vec<(&str, Option<&str>)> = Vec::new();
+    let require = |value: &Option<String>, key: &str| -> Result<()> {
+        if value.is_none() {
+            bail!("`[build.cache] {}` is required for the {:?} backend", key, config.backend);
+        }
+        Ok(())
+    };
+    match config.backend {
+        CacheBackend::Local => {
+            vars.push(("SCCACHE_DIR", dir.as_deref()));
+            vars.push(("SCCACHE_CACHE_SIZE", config.size.as_deref()));
+        }
+        CacheBackend::S3 => {
+            require(&config.bucket, "bucket")?;
+            vars.push(("SCCACHE_BUCKET", config.bucket.as_deref()));
+            vars.push(("SCCACHE_REGION", config.region.as_deref()));
+            vars.push(("SCCACHE_ENDPOINT", config.endpoint.as_deref()));
+            vars.push(("SCCACHE_S3_KEY_PREFIX", config.prefix.as_deref()));
+        }
+        CacheBackend::Gcs => {
+            require(&config.bucket, "bucket")?;
+            vars.push(("SCCACHE_GCS_BUCKET", config.bucket.as_deref()));
+            vars.push(("SCCACHE_GCS_KEY_PREFIX", config.prefix.as_deref()));
+        }
+        CacheBackend::Azure => {
+            require(&config.bucket, "bucket")?;
+            vars.push(("SCCACHE_AZURE_BLOB_CONTAINER", config.bucket.as_deref()));
+            vars.push(("SCCACHE_AZURE_KEY_PREFIX", config.prefix.as_deref()));
+        }
+        CacheBackend::Redis => {
+            require(&config.endpoint, "endpoint")?;
+            vars.push(("SCCACHE_REDIS_ENDPOINT", config.endpoint.as_deref()));
+            vars.push(("SCCACHE_REDIS_KEY_PREFIX", config.prefix.as_deref()));
+        }
+        CacheBackend::Webdav => {
+            require(&config.endpoint, "endpoint")?;
+            vars.push(("SCCACHE_WEBDAV_ENDPOINT", config.endpoint.as_deref()));
+            vars.push(("SCCACHE_WEBDAV_KEY_PREFIX", config.prefix.as_deref()));
+        }
+        CacheBackend::Gha => vars.push(("SCCACHE_GHA_ENABLED", Some("on"))),
+    }
+    for (key, value) in vars {
+        if let Some(value) = value
+            && std::env::var_os(key).is_none()
+        {
+            unsafe {
+                std::env::set_var(key, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints what the cache did for a build, given the counters from before it.
+pub fn report(wrapper: &CacheWrapper, before: Option<CacheStats>) {
+    let name = wrapper.program.file_name().map(Path::new).unwrap_or(&wrapper.program).display();
+    let source = if wrapper.from_env { " (RUSTC_WRAPPER)" } else { "" };
+    let Some(after) = wrapper.stats() else {
+        reporter().info(format!("  Compiled through {}{}", name, source));
+        return;
+    };
+    let build = after.since(before.unwrap_or_default());
+    match build.hit_rate() {
+        Some(rate) => reporter().info(format!(
+            "  {}{}: {} cache hits, {} misses ({:.1}% hit rate)",
+            name, source, build.hits, build.misses, rate
+        )),
+        None => reporter().info(format!("  {}{}: nothing compiled", name, source)),
+    }
+}
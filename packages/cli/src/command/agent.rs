@@ -1,4 +1,5 @@
-use crate::AgentSubcommand;
+use crate::{AgentSubcommand, SessionSubcommand};
+use std::io::Write;
 
 pub async fn run(subcommand: AgentSubcommand) -> anyhow::Result<String> {
     let mut output = String::new();
@@ -28,12 +29,59 @@ pub async fn run(subcommand: AgentSubcommand) -> anyhow::Result<String> {
             Ok(output)
         }
         AgentSubcommand::Doctor { package } => {
-            if let Some(pkg) = package {
+            if let Some(pkg) = &package {
                 output.push_str(&format!("Running agent doctor for package {}...\n", pkg));
             } else {
                 output.push_str("Running agent doctor for the entire project...\n");
             }
-            // TODO: Implement health diagnostics
+
+            let cwd = std::env::current_dir()?;
+            let manager = montrs_agent::AgentManager::new(cwd);
+            match crate::command::upgrade::crate_compat() {
+                Ok(None) => output.push_str("✅ The montrs crates and the CLI are compatible.\n"),
+                Ok(Some(e)) => {
+                    output.push_str(&format!("❌ {}\n\n", e));
+                    output.push_str("| Crate | Version | Needs core | Fix |\n| --- | --- | --- | --- |\n");
+                    for mismatch in &e.mismatches {
+                        output.push_str(&format!(
+                            "| {} | {} | ^{} | {} |\n",
+                            mismatch.name,
+                            mismatch.version,
+                            mismatch.min_core,
+                            mismatch.suggestion()
+                        ));
+                    }
+                    output.push('\n');
+                    manager.report_project_error(compat_error(&e))?;
+                }
+                Err(e) => output.push_str(&format!("⚠️ Could not check the montrs crate versions: {}\n", e)),
+            }
+            let diagnostics = crate::utils::collect_diagnostics(true, package.as_deref())?;
+            if diagnostics.is_empty() {
+                output.push_str("✅ No compiler errors or clippy warnings.\n");
+                return Ok(output);
+            }
+
+            let errors = diagnostics.iter().filter(|d| d.level == "Error").count();
+            let fixable = diagnostics
+                .iter()
+                .filter(|d| d.agent_metadata.as_ref().is_some_and(|m| m.suggestions.iter().any(|s| s.is_machine_applicable())))
+                .count();
+            output.push_str(&format!(
+                "Found {} errors and {} warnings ({} with machine-applicable fixes):\n\n",
+                errors,
+                diagnostics.len() - errors,
+                fixable
+            ));
+            output.push_str("| ID | Level | Location | Code | Message |\n");
+            output.push_str("| --- | --- | --- | --- | --- |\n");
+            for diagnostic in diagnostics {
+                let location = format!("{}:{}:{}", diagnostic.file, diagnostic.line, diagnostic.column);
+                let code = diagnostic.agent_metadata.as_ref().map(|m| m.error_code.clone()).unwrap_or_default();
+                let (level, message) = (diagnostic.level.clone(), diagnostic.message.clone());
+                let id = manager.report_project_error(diagnostic)?;
+                output.push_str(&format!("| {} | {} | {} | {} | {} |\n", id, level, location, code, message));
+            }
             Ok(output)
         }
         AgentSubcommand::Diff { path } => {
@@ -52,41 +100,257 @@ pub async fn run(subcommand: AgentSubcommand) -> anyhow::Result<String> {
             
             Ok(output)
         }
-        AgentSubcommand::ListErrors { status } => {
+        AgentSubcommand::ListErrors { status, package, file, level, code, since, until, sort, limit, offset } => {
+            use montrs_agent::query::{self, ErrorQuery};
+
             let cwd = std::env::current_dir()?;
             let manager = montrs_agent::AgentManager::new(cwd);
-            let tracking = manager.load_tracking()?;
-            
+            let query = ErrorQuery {
+                package,
+                file,
+                level,
+                status: status.as_deref().map(query::parse_status).transpose()?,
+                error_code: code,
+                since: since.as_deref().map(query::parse_time).transpose()?,
+                until: until.as_deref().map(query::parse_time).transpose()?,
+                sort: sort.parse()?,
+                limit,
+                offset,
+            };
+            let page = manager.query_errors(&query)?;
+
             output.push_str("### Agent Error Tracking\n\n");
-            
-            let filtered_errors: Vec<_> = tracking.errors.into_iter().filter(|e| {
-                if let Some(s) = &status {
-                    e.status.to_lowercase() == s.to_lowercase()
-                } else {
-                    true
-                }
-            }).collect();
 
-            if filtered_errors.is_empty() {
+            if page.total == 0 {
                 output.push_str("No errors tracked yet.\n");
+            } else if page.errors.is_empty() {
+                output.push_str(&format!("No errors past offset {} ({} match).\n", page.offset, page.total));
             } else {
-                output.push_str("| ID | Package | File | Line | Level | Status | Message |\n");
-                output.push_str("| --- | --- | --- | --- | --- | --- | --- |\n");
-                for error in filtered_errors {
+                output.push_str("| ID | Package | File | Line | Level | Status | Code | Updated | Message |\n");
+                output.push_str("| --- | --- | --- | --- | --- | --- | --- | --- | --- |\n");
+                for error in &page.errors {
+                    let detail = &error.detail;
                     output.push_str(&format!(
-                        "| {} | {} | {} | {} | {} | {} | {} |\n",
+                        "| {} | {} | {} | {} | {} | {:?} | {} | {} | {} |\n",
                         error.id,
-                        error.package.unwrap_or_else(|| "-".to_string()),
-                        error.file,
-                        error.line,
-                        error.level,
+                        detail.package.as_deref().unwrap_or("-"),
+                        detail.file,
+                        detail.line,
+                        detail.level,
                         error.status,
-                        error.message
+                        detail.agent_metadata.as_ref().map(|m| m.error_code.as_str()).filter(|c| !c.is_empty()).unwrap_or("-"),
+                        error.timestamp.format("%Y-%m-%d %H:%M"),
+                        detail.message.lines().next().unwrap_or_default()
                     ));
                 }
+                output.push_str(&format!(
+                    "\nShowing {}-{} of {}.",
+                    page.offset + 1,
+                    page.offset + page.errors.len(),
+                    page.total
+                ));
+                if let Some(next) = page.next_offset() {
+                    output.push_str(&format!(" Next page: --offset {}.", next));
+                }
+                output.push('\n');
             }
-            
+
             Ok(output)
         }
+        AgentSubcommand::Workspace { name, search } => {
+            use montrs_agent::workspace::{WorkspaceIndex, WorkspacesConfig};
+
+            let config_path = WorkspacesConfig::default_path()
+                .ok_or_else(|| anyhow::anyhow!("Cannot locate the MontRS home; set MONTRS_HOME"))?;
+            let config = WorkspacesConfig::load(&config_path)?;
+            let Some(name) = name else {
+                if config.workspaces.is_empty() {
+                    output.push_str(&format!("No workspaces configured in {}.\n", config_path.display()));
+                }
+                for (name, workspace) in &config.workspaces {
+                    output.push_str(&format!("{} ({} projects)\n", name, workspace.projects.len()));
+                    for project in &workspace.projects {
+                        output.push_str(&format!("  - {}: {}\n", project.display_name(), project.root().display()));
+                    }
+                }
+                return Ok(output);
+            };
+
+            let index = WorkspaceIndex::build(&name, config.workspace(&name)?);
+            if let Some(path) = WorkspaceIndex::default_path(&name) {
+                index.write(&path)?;
+            }
+
+            if let Some(query) = search {
+                let hits = index.search(&query);
+                output.push_str(&format!("### Workspace {}: {} matches for '{}'\n\n", name, hits.len(), query));
+                if !hits.is_empty() {
+                    output.push_str("| Project | Kind | Name | Description |\n");
+                    output.push_str("| --- | --- | --- | --- |\n");
+                    for hit in hits {
+                        output.push_str(&format!("| {} | {} | {} | {} |\n", hit.project, hit.kind, hit.name, hit.description));
+                    }
+                }
+                return Ok(output);
+            }
+
+            output.push_str(&format!("### Workspace {}\n\n", name));
+            output.push_str("| Project | Root | Routes | Packages | Plates | Status |\n");
+            output.push_str("| --- | --- | --- | --- | --- | --- |\n");
+            for project in &index.projects {
+                output.push_str(&format!(
+                    "| {} | {} | {} | {} | {} | {} |\n",
+                    project.name,
+                    project.root.display(),
+                    project.routes.len(),
+                    project.packages.len(),
+                    project.plates.len(),
+                    project.error.as_deref().unwrap_or("ok")
+                ));
+            }
+            Ok(output)
+        }
+        AgentSubcommand::Fix { id, yes } => {
+            let cwd = std::env::current_dir()?;
+            let manager = montrs_agent::AgentManager::new(&cwd);
+            let record = manager.find_error(&id)?;
+            let Some(plan) = montrs_agent::fix::plan_fix(&manager, &record)? else {
+                anyhow::bail!(
+                    "Error {} has no machine-applicable suggestion or stored diff; see `montrs agent diff`",
+                    id
+                );
+            };
+            let patch = plan.patch();
+            let rendered = montrs_agent::diff::StructuredDiff::parse(&patch)
+                .map(|diff| diff.to_markdown())
+                .unwrap_or_else(|_| format!("```diff\n{}```\n", patch));
+            output.push_str(&format!("Fix for {} from {}:\n\n{}", id, plan.summary(), rendered));
+            if crate::dryrun::enabled() {
+                output.push_str("Dry run: no files were changed.\n");
+                return Ok(output);
+            }
+            if !yes {
+                print!("{}Apply this patch? [y/N] ", output);
+                std::io::stdout().flush()?;
+                output.clear();
+                let mut answer = String::new();
+                std::io::stdin().read_line(&mut answer)?;
+                if !matches!(answer.trim(), "y" | "Y" | "yes") {
+                    output.push_str("Aborted: no files were changed.\n");
+                    return Ok(output);
+                }
+            }
+
+            plan.apply(&cwd)?;
+            output.push_str("✅ Patch applied. Re-running cargo check...\n");
+            let remaining = crate::utils::collect_diagnostics(false, record.detail.package.as_deref())?;
+            let still_reported = remaining
+                .iter()
+                .any(|e| e.file == record.detail.file && e.message == record.detail.message);
+            if still_reported {
+                manager.record_fix_attempt(&id, format!("Applied {}; the error is still reported", plan.summary()), Some(patch))?;
+                output.push_str(&format!("⚠️  Error {} is still reported after the fix; it stays active.\n", id));
+            } else {
+                manager.resolve_error(&id, format!("Applied {}", plan.summary()), Some(patch))?;
+                output.push_str(&format!("✅ Error {} resolved.\n", id));
+            }
+            Ok(output)
+        }
+        AgentSubcommand::Session { subcommand } => {
+            use montrs_agent::session::{SessionEvent, SessionOutcome};
+
+            let cwd = std::env::current_dir()?;
+            let manager = montrs_agent::AgentManager::new(&cwd);
+            let session_id = |id: Option<String>| -> anyhow::Result<String> {
+                match id {
+                    Some(id) => Ok(id),
+                    None => manager
+                        .open_session()?
+                        .map(|s| s.id)
+                        .ok_or_else(|| anyhow::anyhow!("No open session; start one with `montrs agent session start <goal>`")),
+                }
+            };
+            match subcommand {
+                SessionSubcommand::Start { goal } => {
+                    let id = manager.start_session(goal)?;
+                    output.push_str(&format!("Started session {}\n", id));
+                }
+                SessionSubcommand::Log { session, files, command, exit_code, note } => {
+                    let id = session_id(session)?;
+                    let mut events: Vec<SessionEvent> = files.into_iter().map(|path| SessionEvent::FileTouched { path }).collect();
+                    if let Some(command) = command {
+                        events.push(SessionEvent::CommandRun { command, exit_code });
+                    }
+                    if let Some(text) = note {
+                        events.push(SessionEvent::Note { text });
+                    }
+                    if events.is_empty() {
+                        anyhow::bail!("Nothing to record; pass --file, --command or --note");
+                    }
+                    let count = events.len();
+                    for event in events {
+                        manager.record_session_event(&id, event)?;
+                    }
+                    output.push_str(&format!("Recorded {} event(s) in session {}\n", count, id));
+                }
+                SessionSubcommand::End { summary, outcome, session } => {
+                    let id = session_id(session)?;
+                    let outcome: SessionOutcome = outcome.parse()?;
+                    manager.finish_session(&id, outcome, summary)?;
+                    output.push_str(&format!("Session {} {}\n", id, outcome));
+                }
+                SessionSubcommand::List { limit } => output.push_str(&manager.resume_context(limit)?),
+                SessionSubcommand::Show { id } => output.push_str(&manager.load_session(&id)?.to_markdown()),
+            }
+            Ok(output)
+        }
+        AgentSubcommand::Search { query, semantic, top } => {
+            let cwd = std::env::current_dir()?;
+            let manager = montrs_agent::AgentManager::new(&cwd);
+            let results = if semantic {
+                let provider = crate::command::explain::llm_provider(&crate::config::MontrsConfig::load()?)?;
+                let stats = manager.update_embeddings(provider.as_ref()).await?;
+                if stats.embedded > 0 {
+                    output.push_str(&format!(
+                        "Embedded {} of {} snippets into {}.\n\n",
+                        stats.embedded,
+                        stats.chunks,
+                        manager.embeddings_file().display()
+                    ));
+                }
+                manager.semantic_search(provider.as_ref(), &query, top).await?
+            } else {
+                manager.keyword_search(&query, top)
+            };
+
+            output.push_str(&format!("### {} results for '{}'\n", results.len(), query));
+            for result in results {
+                output.push_str(&format!("\n#### {} (score {:.3})\n\n{}\n", result.key, result.score, result.text));
+            }
+            Ok(output)
+        }
+    }
+}
+
+/// Tracks a crate version mismatch like a compiler error, against `Cargo.toml`.
+fn compat_error(error: &montrs_core::CompatError) -> montrs_agent::ProjectError {
+    use montrs_core::AgentError;
+
+    montrs_agent::ProjectError {
+        package: None,
+        file: "Cargo.toml".to_string(),
+        line: 1,
+        column: 1,
+        message: error.to_string(),
+        code_context: String::new(),
+        level: "Error".to_string(),
+        agent_metadata: Some(montrs_agent::AgentErrorMetadata {
+            error_code: error.error_code().to_string(),
+            explanation: error.explanation(),
+            suggested_fixes: error.suggested_fixes(),
+            rustc_error: None,
+            suggestions: Vec::new(),
+            model_explanation: None,
+        }),
     }
 }
@@ -0,0 +1,1131 @@
+//! Shared API types.
+//!
+//! `generate api-types` finds the types that cross the wire between server and
+//! front-end: route params, loader outputs, action inputs and outputs, and
+//! action errors deriving `DomainError`, along with the workspace types their
+//! fields use. It moves their definitions
+//! into one crate (`api-types` by default) that both sides depend on. Each
+//! moved definition is replaced by a `use` of the shared one, so existing
+//! paths keep compiling. Identical copies in other packages are replaced the
+//! same way. Copies that differ are reported as drift and left for a person to
+//! reconcile.
+//!
+//! Types are told apart by package and module path, and the paths a route
+//! impl or a field names are resolved through the `use` items in scope, so
+//! `todos::Input` and `users::Input` stay two types. When moved types share a
+//! name, each goes into a module of the shared crate named after the module
+//! it came from.
+//!
+//! With `--check` nothing is written: the command fails if a route type is
+//! defined outside the shared crate or in more than one package.
+
+use crate::dryrun;
+use crate::report::reporter;
+use anyhow::{Context, Result};
+use cargo_metadata::MetadataCommand;
+use console::style;
+use proc_macro2::{LineColumn, TokenStream, TokenTree};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+use syn::spanned::Spanned;
+use syn::visit::Visit;
+
+/// Associated types of the route traits that carry data over the wire.
+const ROUTE_TRAITS: &[(&str, &[&str])] =
+    &[("Route", &["Params"]), ("RouteLoader", &["Output"]), ("RouteAction", &["Input", "Output"])];
+
+/// How many `use` items a path is followed through before giving up, which
+/// also stops glob imports that point at each other.
+const MAX_DEPTH: usize = 8;
+
+pub async fn run(name: String, check: bool) -> Result<()> {
+    let metadata = MetadataCommand::new().no_deps().exec().context("Failed to read cargo metadata")?;
+    let root = metadata.workspace_root.as_std_path().to_path_buf();
+    let packages: Vec<Package> = metadata
+        .workspace_packages()
+        .iter()
+        .filter_map(|p| {
+            let dir = p.manifest_path.parent()?.as_std_path().to_path_buf();
+            Some(Package { name: p.name.clone(), dir })
+        })
+        .collect();
+
+    let mut step = reporter().step("scan route types");
+    let scan = Scan::run(&root, &packages, &name)?;
+    let shared = scan.shared_closure();
+    step.set_detail(format!("{} type(s) in {} package(s)", shared.len(), packages.len()));
+    step.finish();
+
+    if check {
+        return verify(&scan, &shared, &name);
+    }
+    extract(&root, &packages, &scan, &shared, &name)
+}
+
+struct Package {
+    name: String,
+    dir: PathBuf,
+}
+
+/// A path in a workspace package's crate, e.g. `routes::todos::Input`. Keys
+/// name both types and the modules that hold them.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Key {
+    package: usize,
+    path: Vec<String>,
+}
+
+impl Key {
+    fn root(package: usize) -> Self {
+        Key { package, path: Vec::new() }
+    }
+
+    fn child(&self, name: &str) -> Self {
+        let mut path = self.path.clone();
+        path.push(name.to_string());
+        Key { package: self.package, path }
+    }
+
+    /// The enclosing module, or `None` for the crate root.
+    fn parent(&self) -> Option<Self> {
+        let (_, module) = self.path.split_last()?;
+        Some(Key { package: self.package, path: module.to_vec() })
+    }
+
+    fn name(&self) -> &str {
+        self.path.last().map_or("", String::as_str)
+    }
+}
+
+/// A top-level `struct` or `enum` in a workspace package.
+struct Definition {
+    key: Key,
+    file: PathBuf,
+    /// `file` relative to the workspace root, for messages.
+    shown: String,
+    line: usize,
+    /// Byte range of the item in its file, attributes and doc comments included.
+    range: (usize, usize),
+    /// The item as written.
+    text: String,
+    /// Edits to `text` that make the item and its fields public, so both
+    /// sides can build and read it.
+    publish: Vec<(usize, usize, String)>,
+    /// The type paths the item names.
+    references: Vec<Reference>,
+    /// Tokens without doc comments, to compare copies.
+    normalized: String,
+    /// The original visibility, kept on the `use` that replaces it.
+    vis: String,
+}
+
+impl Definition {
+    fn location(&self) -> String {
+        format!("{}:{}", self.shown, self.line)
+    }
+
+    /// The module the item's paths are resolved in.
+    fn scope(&self) -> Key {
+        self.key.parent().unwrap_or_else(|| Key::root(self.key.package))
+    }
+}
+
+/// A type path in a definition, e.g. `models::Todo`, with its byte range in
+/// the definition's text.
+struct Reference {
+    range: (usize, usize),
+    segments: Vec<String>,
+}
+
+/// A `use` leaf: the name it brings into its module and the path it names.
+struct Import {
+    /// `None` for a glob, whose path is the module it imports from.
+    name: Option<String>,
+    /// The segments, starting with an empty one for a leading `::`.
+    path: Vec<String>,
+    /// The leaf as written, e.g. `serde_json::Value as Json`, to copy over.
+    text: String,
+}
+
+/// A type a route impl sends or receives.
+struct RouteType {
+    key: Key,
+    role: &'static str,
+}
+
+/// A shared type and the definitions that stand for it.
+struct Group<'a> {
+    /// The definition the shared one is taken from.
+    primary: &'a Definition,
+    /// Identical definitions in other packages, replaced by a `use` too.
+    copies: Vec<&'a Definition>,
+    /// Definitions at the same path in other packages that differ.
+    drift: Vec<&'a Definition>,
+}
+
+impl Group<'_> {
+    fn members(&self) -> impl Iterator<Item = &Definition> {
+        std::iter::once(self.primary).chain(self.copies.iter().copied())
+    }
+}
+
+struct Scan {
+    definitions: Vec<Definition>,
+    /// Definitions by key. When `lib.rs` and `main.rs` define the same name,
+    /// the first one read wins.
+    index: BTreeMap<Key, usize>,
+    /// Every module seen, inline or in a file of its own.
+    modules: BTreeSet<Key>,
+    imports: BTreeMap<Key, Vec<Import>>,
+    /// Workspace packages by the name their crate is imported as.
+    crates: BTreeMap<String, usize>,
+    route_types: Vec<RouteType>,
+    /// Index of the shared crate in the package list, if it exists yet.
+    shared_package: Option<usize>,
+}
+
+impl Scan {
+    fn run(root: &Path, packages: &[Package], shared_name: &str) -> Result<Self> {
+        let mut scan = Scan {
+            definitions: Vec::new(),
+            index: BTreeMap::new(),
+            modules: BTreeSet::new(),
+            imports: BTreeMap::new(),
+            crates: packages.iter().enumerate().map(|(i, p)| (p.name.replace('-', "_"), i)).collect(),
+            route_types: Vec::new(),
+            shared_package: packages.iter().position(|p| p.name == shared_name),
+        };
+        let mut unresolved = Vec::new();
+        for (index, package) in packages.iter().enumerate() {
+            for path in rust_sources(&package.dir) {
+                let Some(module) = module_path(&package.dir, &path) else { continue };
+                let source =
+                    std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+                let Ok(file) = syn::parse_file(&source) else { continue };
+                let shown = path.strip_prefix(root).unwrap_or(&path).display().to_string();
+                let module = Key { package: index, path: module };
+                scan.modules.insert(module.clone());
+                let mut visitor = ItemVisitor {
+                    scan: &mut scan,
+                    unresolved: &mut unresolved,
+                    module,
+                    path: &path,
+                    shown: &shown,
+                    source: &source,
+                };
+                visitor.visit_file(&file);
+            }
+        }
+        // A route impl can name types from files read after it.
+        for (scope, segments, role) in unresolved {
+            if let Some(key) = scan.resolve(&scope, &segments) {
+                scan.route_types.push(RouteType { key, role });
+            }
+        }
+        Ok(scan)
+    }
+
+    fn definition(&self, key: &Key) -> Option<&Definition> {
+        self.index.get(key).map(|&i| &self.definitions[i])
+    }
+
+    /// The definition a path written in `scope` names, if it is in the workspace.
+    fn resolve(&self, scope: &Key, segments: &[String]) -> Option<Key> {
+        self.resolve_from(scope, segments, 0)
+    }
+
+    fn resolve_from(&self, scope: &Key, segments: &[String], depth: usize) -> Option<Key> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        let (first, rest) = segments.split_first()?;
+        match first.as_str() {
+            "crate" => self.walk(&Key::root(scope.package), rest, depth),
+            "self" => self.walk(scope, rest, depth),
+            "super" => {
+                let parent = scope.parent()?;
+                match rest.first().map(String::as_str) {
+                    Some("super") => self.resolve_from(&parent, rest, depth),
+                    _ => self.walk(&parent, rest, depth),
+                }
+            }
+            "" => self.external(rest, depth),
+            _ => self.walk(scope, segments, depth).or_else(|| self.external(segments, depth)),
+        }
+    }
+
+    /// Follows `segments` from `module`: through child modules, then through
+    /// the module's `use` items.
+    fn walk(&self, module: &Key, segments: &[String], depth: usize) -> Option<Key> {
+        let (name, rest) = segments.split_first()?;
+        let key = module.child(name);
+        if rest.is_empty() && self.index.contains_key(&key) {
+            return Some(key);
+        }
+        if !rest.is_empty() && self.modules.contains(&key) {
+            return self.walk(&key, rest, depth);
+        }
+        self.imports.get(module).into_iter().flatten().find_map(|import| {
+            let target = match &import.name {
+                Some(imported) if imported == name => [&import.path[..], rest].concat(),
+                None => [&import.path[..], segments].concat(),
+                Some(_) => return None,
+            };
+            self.resolve_from(module, &target, depth + 1)
+        })
+    }
+
+    /// A path starting with the name of another workspace crate.
+    fn external(&self, segments: &[String], depth: usize) -> Option<Key> {
+        let (first, rest) = segments.split_first()?;
+        let package = *self.crates.get(first)?;
+        self.walk(&Key::root(package), rest, depth)
+    }
+
+    /// The keys to share: the route types defined in the workspace and,
+    /// transitively, the workspace types their definitions name.
+    fn shared_closure(&self) -> BTreeSet<Key> {
+        let mut shared = BTreeSet::new();
+        let mut queue: Vec<Key> = self.route_types.iter().map(|t| t.key.clone()).collect();
+        while let Some(key) = queue.pop() {
+            let Some(definition) = self.definition(&key) else { continue };
+            if !shared.insert(key) {
+                continue;
+            }
+            let scope = definition.scope();
+            queue.extend(definition.references.iter().filter_map(|r| self.resolve(&scope, &r.segments)));
+        }
+        shared
+    }
+
+    /// The shared keys grouped with their copies in other packages. The
+    /// primary of a group is the one already in the shared crate, else one a
+    /// route uses, else the first.
+    fn groups(&self, shared: &BTreeSet<Key>) -> Vec<Group<'_>> {
+        let mut grouped = BTreeSet::new();
+        let mut groups = Vec::new();
+        for key in shared {
+            let Some(definition) = self.definition(key) else { continue };
+            if grouped.contains(key) {
+                continue;
+            }
+            let mut members = vec![definition];
+            members.extend(self.definitions.iter().filter(|d| {
+                d.key.package != key.package && d.key.name() == key.name() && self.same(definition, d)
+            }));
+            grouped.extend(members.iter().map(|d| d.key.clone()));
+            let in_shared = |d: &&&Definition| Some(d.key.package) == self.shared_package;
+            let routed = |d: &&&Definition| self.route_types.iter().any(|t| t.key == d.key);
+            let primary = *members.iter().find(in_shared).or_else(|| members.iter().find(routed)).unwrap_or(&members[0]);
+            let drift = self
+                .definitions
+                .iter()
+                .filter(|d| {
+                    d.key.path == key.path
+                        && !members.iter().any(|m| m.key.package == d.key.package)
+                        && !shared.contains(&d.key)
+                })
+                .collect();
+            let copies = members.into_iter().filter(|d| !std::ptr::eq(*d, primary)).collect();
+            groups.push(Group { primary, copies, drift });
+        }
+        groups
+    }
+
+    /// Whether two definitions are copies: the same tokens, naming types with
+    /// the same names or types from outside the workspace.
+    fn same(&self, a: &Definition, b: &Definition) -> bool {
+        let (a_scope, b_scope) = (a.scope(), b.scope());
+        a.normalized == b.normalized
+            && a.references.len() == b.references.len()
+            && a.references.iter().zip(&b.references).all(|(ra, rb)| {
+                match (self.resolve(&a_scope, &ra.segments), self.resolve(&b_scope, &rb.segments)) {
+                    (None, None) => true,
+                    (Some(ka), Some(kb)) => ka.name() == kb.name(),
+                    _ => false,
+                }
+            })
+    }
+
+    fn roles(&self, group: &Group) -> String {
+        let roles: BTreeSet<&str> = self
+            .route_types
+            .iter()
+            .filter(|t| group.members().any(|d| d.key == t.key))
+            .map(|t| t.role)
+            .collect();
+        if roles.is_empty() { "used by a route type".to_string() } else { roles.into_iter().collect::<Vec<_>>().join(", ") }
+    }
+}
+
+struct ItemVisitor<'a> {
+    scan: &'a mut Scan,
+    /// Types named by route impls, resolved once every file is read.
+    unresolved: &'a mut Vec<(Key, Vec<String>, &'static str)>,
+    /// The module being visited.
+    module: Key,
+    path: &'a Path,
+    shown: &'a str,
+    source: &'a str,
+}
+
+impl ItemVisitor<'_> {
+    fn define(
+        &mut self,
+        item: &syn::Item,
+        ident: &syn::Ident,
+        generics: &syn::Generics,
+        vis: &syn::Visibility,
+        keyword: proc_macro2::Span,
+        fields: Vec<&syn::Field>,
+    ) {
+        let span = item.span();
+        let (start, end) = (offset(self.source, span.start()), offset(self.source, span.end()));
+        let text = &self.source[start..end];
+
+        let mut publish = Vec::new();
+        let mut public = |vis: &syn::Visibility, before: proc_macro2::Span| match vis {
+            syn::Visibility::Public(_) => {}
+            syn::Visibility::Inherited => {
+                let at = offset(self.source, before.start()) - start;
+                publish.push((at, at, "pub ".to_string()));
+            }
+            restricted => {
+                let span = restricted.span();
+                publish.push((
+                    offset(self.source, span.start()) - start,
+                    offset(self.source, span.end()) - start,
+                    "pub".to_string(),
+                ));
+            }
+        };
+        public(vis, keyword);
+        for field in fields {
+            let first = field.ident.as_ref().map(|i| i.span()).unwrap_or_else(|| field.ty.span());
+            public(&field.vis, first);
+        }
+
+        // A generic parameter shadows any type of the same name.
+        let parameters: BTreeSet<String> = generics.type_params().map(|p| p.ident.to_string()).collect();
+        let mut paths = TypePaths::default();
+        paths.visit_item(item);
+        let references = paths
+            .0
+            .into_iter()
+            .filter(|(segments, ..)| !matches!(segments.as_slice(), [only] if parameters.contains(only)))
+            .map(|(segments, from, to)| Reference {
+                range: (offset(self.source, from) - start, offset(self.source, to) - start),
+                segments,
+            })
+            .collect();
+
+        let vis = match vis {
+            syn::Visibility::Inherited => String::new(),
+            other => format!("{} ", &self.source[offset(self.source, other.span().start())..offset(self.source, other.span().end())]),
+        };
+        let key = self.module.child(&ident.to_string());
+        self.scan.index.entry(key.clone()).or_insert(self.scan.definitions.len());
+        self.scan.definitions.push(Definition {
+            key,
+            file: self.path.to_path_buf(),
+            shown: self.shown.to_string(),
+            line: span.start().line,
+            range: (start, end),
+            text: text.to_string(),
+            publish,
+            references,
+            normalized: normalize(text),
+            vis,
+        });
+    }
+
+    fn route_type(&mut self, ty: &syn::Type, role: &'static str) {
+        let mut paths = TypePaths::default();
+        paths.visit_type(ty);
+        for (segments, ..) in paths.0 {
+            self.unresolved.push((self.module.clone(), segments, role));
+        }
+    }
+
+    /// Types deriving `DomainError` reach the client inside an `ActionError`,
+    /// which decodes them with the shared definition.
+    fn action_error(&mut self, attrs: &[syn::Attribute], ident: &syn::Ident) {
+        let derives_domain_error = attrs.iter().filter(|a| a.path().is_ident("derive")).any(|a| {
+            a.parse_args_with(syn::punctuated::Punctuated::<syn::Path, syn::Token![,]>::parse_terminated)
+                .is_ok_and(|paths| paths.iter().any(|p| p.segments.last().is_some_and(|s| s.ident == "DomainError")))
+        });
+        if derives_domain_error {
+            self.scan.route_types.push(RouteType { key: self.module.child(&ident.to_string()), role: "action error" });
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for ItemVisitor<'_> {
+    fn visit_item(&mut self, item: &'ast syn::Item) {
+        match item {
+            syn::Item::Struct(s) => {
+                let fields = s.fields.iter().collect();
+                self.define(item, &s.ident, &s.generics, &s.vis, s.struct_token.span, fields);
+                self.action_error(&s.attrs, &s.ident);
+            }
+            syn::Item::Enum(e) => {
+                // Variant fields take the enum's visibility.
+                self.define(item, &e.ident, &e.generics, &e.vis, e.enum_token.span, Vec::new());
+                self.action_error(&e.attrs, &e.ident);
+            }
+            syn::Item::Impl(imp) => {
+                let Some((_, trait_path, _)) = &imp.trait_ else { return };
+                let Some(segment) = trait_path.segments.last() else { return };
+                let Some((_, associated)) = ROUTE_TRAITS.iter().find(|(name, _)| segment.ident == name) else {
+                    return;
+                };
+                // `RouteLoader<P, C>` and `RouteAction<P, C>` name the params first.
+                if segment.ident != "Route"
+                    && let syn::PathArguments::AngleBracketed(args) = &segment.arguments
+                    && let Some(syn::GenericArgument::Type(params)) = args.args.first()
+                {
+                    self.route_type(params, "params");
+                }
+                for impl_item in &imp.items {
+                    if let syn::ImplItem::Type(ty) = impl_item
+                        && associated.iter().any(|a| ty.ident == a)
+                    {
+                        let role = match (segment.ident.to_string().as_str(), ty.ident.to_string().as_str()) {
+                            ("Route", _) => "params",
+                            ("RouteLoader", _) => "loader output",
+                            (_, "Input") => "action input",
+                            _ => "action output",
+                        };
+                        self.route_type(&ty.ty, role);
+                    }
+                }
+            }
+            syn::Item::Use(u) => {
+                let start = if u.leading_colon.is_some() { vec![String::new()] } else { Vec::new() };
+                let imports = self.scan.imports.entry(self.module.clone()).or_default();
+                use_leaves(&u.tree, &start, imports);
+            }
+            syn::Item::Mod(m) => {
+                let module = self.module.child(&m.ident.to_string());
+                self.scan.modules.insert(module.clone());
+                if let Some((_, items)) = &m.content {
+                    let outer = std::mem::replace(&mut self.module, module);
+                    for item in items {
+                        self.visit_item(item);
+                    }
+                    self.module = outer;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The type paths in a piece of syntax, e.g. `Vec` and `Todo` in `Vec<Todo>`,
+/// with where each starts and where its last segment ends. Paths through
+/// `<T as Trait>` are left out.
+#[derive(Default)]
+struct TypePaths(Vec<(Vec<String>, LineColumn, LineColumn)>);
+
+impl<'ast> Visit<'ast> for TypePaths {
+    fn visit_type_path(&mut self, ty: &'ast syn::TypePath) {
+        if ty.qself.is_none()
+            && let Some(last) = ty.path.segments.last()
+        {
+            let mut segments: Vec<String> = ty.path.leading_colon.iter().map(|_| String::new()).collect();
+            segments.extend(ty.path.segments.iter().map(|s| s.ident.to_string()));
+            self.0.push((segments, ty.path.span().start(), last.ident.span().end()));
+        }
+        syn::visit::visit_type_path(self, ty);
+    }
+}
+
+fn collect_idents(tokens: TokenStream, idents: &mut BTreeSet<String>) {
+    for token in tokens {
+        match token {
+            TokenTree::Ident(ident) => {
+                idents.insert(ident.to_string());
+            }
+            TokenTree::Group(group) => collect_idents(group.stream(), idents),
+            _ => {}
+        }
+    }
+}
+
+/// The item's tokens without doc comments, so copies that differ only in
+/// docs or formatting compare equal.
+fn normalize(text: &str) -> String {
+    let code: Vec<&str> = text.lines().filter(|l| !l.trim_start().starts_with("///")).collect();
+    code.join("\n").parse::<TokenStream>().map(|t| t.to_string()).unwrap_or_default()
+}
+
+/// The leaves of a `use` tree under `prefix`, `{a, b}` groups, `self` and
+/// renames spelled out.
+fn use_leaves(tree: &syn::UseTree, prefix: &[String], out: &mut Vec<Import>) {
+    let under = |ident: &syn::Ident| {
+        if ident == "self" { prefix.to_vec() } else { [prefix, &[ident.to_string()]].concat() }
+    };
+    match tree {
+        syn::UseTree::Path(p) => use_leaves(&p.tree, &under(&p.ident), out),
+        syn::UseTree::Name(n) => {
+            let path = under(&n.ident);
+            if let Some(name) = path.last() {
+                out.push(Import { name: Some(name.clone()), text: path.join("::"), path });
+            }
+        }
+        syn::UseTree::Rename(r) => {
+            let path = under(&r.ident);
+            let text = format!("{}{} as {}", path.join("::"), if r.ident == "self" { "::self" } else { "" }, r.rename);
+            out.push(Import { name: Some(r.rename.to_string()), path, text });
+        }
+        syn::UseTree::Group(g) => g.items.iter().for_each(|t| use_leaves(t, prefix, out)),
+        syn::UseTree::Glob(_) => {
+            out.push(Import { name: None, text: format!("{}::*", prefix.join("::")), path: prefix.to_vec() })
+        }
+    }
+}
+
+/// Byte offset of a span position: lines are 1-based, columns count characters.
+fn offset(source: &str, position: LineColumn) -> usize {
+    let line_start: usize = source.split_inclusive('\n').take(position.line - 1).map(str::len).sum();
+    let line = &source[line_start..];
+    line_start + line.char_indices().nth(position.column).map_or(line.len(), |(i, _)| i)
+}
+
+/// The `.rs` files under `dir`, honouring `.gitignore` and skipping `target`.
+fn rust_sources(dir: &Path) -> impl Iterator<Item = PathBuf> {
+    ignore::WalkBuilder::new(dir).build().flatten().map(|e| e.into_path()).filter(|path| {
+        path.extension().is_some_and(|e| e == "rs") && !path.components().any(|c| c.as_os_str() == "target")
+    })
+}
+
+/// The module a source file holds, from its place under `src/`: `src/lib.rs`
+/// is the crate root and `src/routes/todos.rs` is `routes::todos`. Tests,
+/// examples and `src/bin` are crates of their own and are left out.
+fn module_path(dir: &Path, file: &Path) -> Option<Vec<String>> {
+    let relative = file.strip_prefix(dir.join("src")).ok()?.with_extension("");
+    let mut path: Vec<String> =
+        relative.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect();
+    match path.as_slice() {
+        [first, _, ..] if first == "bin" => return None,
+        [only] if only == "lib" || only == "main" => path.clear(),
+        [.., last] if last == "mod" => {
+            path.pop();
+        }
+        _ => {}
+    }
+    Some(path)
+}
+
+/// Reports where each shared type lives and fails unless all of them are
+/// defined once, in the shared crate.
+fn verify(scan: &Scan, shared: &BTreeSet<Key>, name: &str) -> Result<()> {
+    let groups = scan.groups(shared);
+    let mut problems = 0;
+    for group in &groups {
+        let definitions: Vec<&Definition> = group.members().chain(group.drift.iter().copied()).collect();
+        let in_shared = Some(group.primary.key.package) == scan.shared_package;
+        let locations: Vec<String> = definitions.iter().map(|d| d.location()).collect();
+        let (icon, verdict) = if definitions.len() > 1 {
+            (style("✘").red(), format!("defined {} times: {}", definitions.len(), locations.join(", ")))
+        } else if !in_shared {
+            (style("✘").red(), format!("not shared: {}", locations.join(", ")))
+        } else {
+            (style("✔").green(), name.to_string())
+        };
+        if definitions.len() > 1 || !in_shared {
+            problems += 1;
+        }
+        let type_name = group.primary.key.path.join("::");
+        reporter().output(format!("  {} {} ({}) {}", icon, style(type_name).bold(), scan.roles(group), verdict));
+    }
+    if problems > 0 {
+        anyhow::bail!("{} route type(s) are not shared through `{}`; run `montrs generate api-types`", problems, name);
+    }
+    reporter().info(format!("{} All {} route type(s) live in {}", style("✔").green(), groups.len(), name));
+    Ok(())
+}
+
+/// Moves the shared types into the shared crate, creating it if needed.
+fn extract(root: &Path, packages: &[Package], scan: &Scan, shared: &BTreeSet<Key>, name: &str) -> Result<()> {
+    let lib_name = name.replace('-', "_");
+    let crate_dir = match scan.shared_package {
+        Some(index) => packages[index].dir.clone(),
+        None if root.join("packages").is_dir() => root.join("packages").join(name),
+        None => root.join(name),
+    };
+
+    // What moves, where it lands, and which definitions become `use`s.
+    let groups = scan.groups(shared);
+    let moved: Vec<&Definition> =
+        groups.iter().map(|g| g.primary).filter(|d| Some(d.key.package) != scan.shared_package).collect();
+    let placed = placements(packages, scan, &moved);
+    let mut locations: BTreeMap<Key, Vec<String>> = BTreeMap::new();
+    let mut replaced: Vec<&Definition> = Vec::new();
+    for group in &groups {
+        let location = placed.get(&group.primary.key).cloned().unwrap_or_else(|| group.primary.key.path.clone());
+        if placed.contains_key(&group.primary.key) {
+            replaced.push(group.primary);
+        }
+        replaced.extend(&group.copies);
+        for member in group.members() {
+            locations.insert(member.key.clone(), location.clone());
+        }
+        for copy in &group.drift {
+            reporter().warn(format!(
+                "`{}` at {} differs from the one at {}; reconcile them by hand and rerun",
+                group.primary.key.name(),
+                copy.location(),
+                group.primary.location()
+            ));
+        }
+    }
+    if moved.is_empty() && replaced.is_empty() {
+        reporter().info(format!("{} Every route type already lives in {}", style("✔").green(), name));
+        return Ok(());
+    }
+
+    let mut step = reporter().step(format!("write {}", name));
+    let manifest = crate_dir.join("Cargo.toml");
+    let lib = crate_dir.join("src").join("lib.rs");
+    let existing = std::fs::read_to_string(&lib).unwrap_or_default();
+
+    // The moved items by module of the shared crate, the imports they need
+    // there, and the dependencies behind them.
+    let mut items: BTreeMap<Vec<String>, Vec<String>> = BTreeMap::new();
+    let mut imports: BTreeMap<Vec<String>, BTreeSet<String>> = BTreeMap::new();
+    let mut dependencies = BTreeMap::new();
+    for definition in &moved {
+        let location = &locations[&definition.key];
+        let module = location[..location.len() - 1].to_vec();
+        let text = shared_text(scan, definition, &locations);
+        let mut idents = BTreeSet::new();
+        collect_idents(text.parse().unwrap_or_default(), &mut idents);
+
+        let source_manifest = packages[definition.key.package].dir.join("Cargo.toml");
+        let deps = manifest_dependencies(&source_manifest);
+        let scope = definition.scope();
+        let mut crates = BTreeSet::new();
+        for import in scan.imports.get(&scope).into_iter().flatten() {
+            let Some(imported) = &import.name else { continue };
+            if !idents.contains(imported) {
+                continue;
+            }
+            let first = import.path.iter().find(|s| !s.is_empty()).map_or("", String::as_str);
+            let local = match scan.resolve(&scope, std::slice::from_ref(imported)) {
+                // Paths to shared types were rewritten to their new place.
+                Some(key) if locations.contains_key(&key) => continue,
+                Some(_) => true,
+                None => matches!(first, "crate" | "self" | "super") || scan.modules.contains(&scope.child(first)),
+            };
+            if local {
+                reporter().warn(format!(
+                    "`{}` uses `{}` from its own crate; move it to {} by hand",
+                    definition.key.name(),
+                    import.text,
+                    name
+                ));
+                continue;
+            }
+            imports.entry(module.clone()).or_default().insert(format!("use {};", import.text));
+            crates.insert(first.to_string());
+        }
+        // Crates named in paths and attributes, e.g. `#[serde(default)]`, are needed too.
+        crates.extend(idents.iter().cloned());
+        // `#[derive(DomainError)]` expands to paths into `montrs_core`.
+        if idents.contains("DomainError") {
+            crates.insert("montrs_core".to_string());
+        }
+        for (dep, spec) in &deps {
+            if crates.contains(&dep.replace('-', "_")) {
+                dependencies.insert(dep.clone(), rebase(spec, &packages[definition.key.package].dir, &crate_dir));
+            }
+        }
+        items.entry(module).or_default().push(text);
+    }
+    if scan.shared_package.is_none() {
+        dryrun::create_dir_all(&crate_dir.join("src"))?;
+        dryrun::write(&manifest, crate_manifest(root, name, &dependencies)?)?;
+        add_member(root, &crate_dir)?;
+    } else {
+        for (dep, spec) in &dependencies {
+            super::generate::add_dependency(&manifest, "dependencies", dep, Some(spec))?;
+        }
+    }
+
+    let mut content = if existing.is_empty() {
+        "//! Types shared by the server and the front-end: route params, loader\n//! outputs, action inputs and outputs, and action errors. Generated by `montrs generate api-types`.\n".to_string()
+    } else {
+        existing.clone()
+    };
+    let root_imports = imports.remove(&Vec::new()).unwrap_or_default();
+    let missing: Vec<&String> = root_imports.iter().filter(|i| !content.contains(i.as_str())).collect();
+    if !missing.is_empty() {
+        let at = content.lines().take_while(|l| l.starts_with("//!")).map(|l| l.len() + 1).sum::<usize>();
+        let block: String = missing.iter().map(|i| format!("{}\n", i)).collect();
+        content.insert_str(at.min(content.len()), &format!("\n{}", block));
+    }
+    for (module, texts) in &items {
+        if module.is_empty() {
+            for text in texts {
+                content.push('\n');
+                content.push_str(text);
+                content.push('\n');
+            }
+        } else {
+            let needed = imports.get(module).cloned().unwrap_or_default();
+            add_to_module(&mut content, &module.join("::"), &needed, texts);
+        }
+    }
+    dryrun::write(&lib, content)?;
+    step.set_detail(format!("{} type(s) moved", moved.len()));
+    step.finish();
+
+    // Replace the definitions, last first so earlier offsets stay valid.
+    let mut by_file: BTreeMap<&Path, Vec<&Definition>> = BTreeMap::new();
+    for definition in &replaced {
+        by_file.entry(definition.file.as_path()).or_default().push(definition);
+    }
+    let mut dependents = BTreeSet::new();
+    for (file, mut definitions) in by_file {
+        definitions.sort_by_key(|d| std::cmp::Reverse(d.range.0));
+        let mut source = std::fs::read_to_string(file)?;
+        for definition in &definitions {
+            let replacement = format!("{}use {}::{};", definition.vis, lib_name, locations[&definition.key].join("::"));
+            source.replace_range(definition.range.0..definition.range.1, &replacement);
+            dependents.insert(definition.key.package);
+        }
+        dryrun::write(file, source)?;
+    }
+    for package in dependents {
+        let dir = &packages[package].dir;
+        let spec = format!("{{ path = \"{}\" }}", relative(dir, &crate_dir).display());
+        super::generate::add_dependency(&dir.join("Cargo.toml"), "dependencies", name, Some(&spec))?;
+    }
+
+    if !dryrun::enabled() {
+        for definition in &replaced {
+            reporter().info(format!(
+                "{} {} -> {}",
+                style("✔").green(),
+                definition.location(),
+                style(format!("{}::{}", lib_name, locations[&definition.key].join("::"))).cyan()
+            ));
+        }
+        reporter().info("Imports only the moved types used may now be unused; `cargo fix` removes them.");
+    }
+    Ok(())
+}
+
+/// Where each moved type goes in the shared crate: at the root under its own
+/// name, or, when that is taken, in a module named after the one it came
+/// from, e.g. `todos::Input`, or after its package and module if that clashes
+/// too.
+fn placements(packages: &[Package], scan: &Scan, moved: &[&Definition]) -> BTreeMap<Key, Vec<String>> {
+    let taken = |path: &Vec<String>| {
+        scan.shared_package.is_some_and(|package| scan.index.contains_key(&Key { package, path: path.clone() }))
+    };
+    let mut by_name: BTreeMap<&str, Vec<&Definition>> = BTreeMap::new();
+    for definition in moved {
+        by_name.entry(definition.key.name()).or_default().push(definition);
+    }
+    let mut placed = BTreeMap::new();
+    for (name, definitions) in by_name {
+        let crate_name = |d: &Definition| packages[d.key.package].name.replace('-', "_");
+        let module = |d: &Definition| d.scope().path;
+        let short = |d: &Definition| module(d).last().cloned().unwrap_or_else(|| crate_name(d));
+        let long = |d: &Definition| [vec![crate_name(d)], module(d)].concat().join("_");
+        let candidates: [Vec<Vec<String>>; 3] = [
+            definitions.iter().map(|_| vec![name.to_string()]).collect(),
+            definitions.iter().map(|d| vec![short(d), name.to_string()]).collect(),
+            definitions.iter().map(|d| vec![long(d), name.to_string()]).collect(),
+        ];
+        let fits = |paths: &Vec<Vec<String>>| {
+            !paths.iter().any(taken) && paths.iter().collect::<BTreeSet<_>>().len() == paths.len()
+        };
+        let mut candidates = candidates.into_iter();
+        let paths = candidates.clone().find(fits).or_else(|| candidates.next_back()).unwrap_or_default();
+        for (definition, path) in definitions.iter().zip(paths) {
+            placed.insert(definition.key.clone(), path);
+        }
+    }
+    placed
+}
+
+/// A moved definition as it reads in the shared crate: public, with paths to
+/// other shared types pointing at their new place.
+fn shared_text(scan: &Scan, definition: &Definition, locations: &BTreeMap<Key, Vec<String>>) -> String {
+    let scope = definition.scope();
+    let mut edits = definition.publish.clone();
+    for reference in &definition.references {
+        if let Some(location) = scan.resolve(&scope, &reference.segments).and_then(|key| locations.get(&key)) {
+            edits.push((reference.range.0, reference.range.1, format!("crate::{}", location.join("::"))));
+        }
+    }
+    // Last first; a tuple field's `pub ` goes in after the path it precedes is rewritten.
+    edits.sort_by_key(|e| (std::cmp::Reverse(e.0), std::cmp::Reverse(e.1)));
+    let mut text = definition.text.clone();
+    for (from, to, replacement) in edits {
+        text.replace_range(from..to, &replacement);
+    }
+    text
+}
+
+/// Adds items, and the imports they need, to the inline module `name` of the
+/// shared crate's `lib.rs`, creating the module at the end if it is missing.
+fn add_to_module(content: &mut String, name: &str, imports: &BTreeSet<String>, items: &[String]) {
+    let indent = |text: &str| -> String {
+        text.lines().map(|l| if l.is_empty() { "\n".to_string() } else { format!("    {}\n", l) }).collect()
+    };
+    let existing = syn::parse_file(content).ok().and_then(|file| {
+        file.items.iter().find_map(|item| match item {
+            syn::Item::Mod(m) if m.ident == name && m.content.is_some() => {
+                Some((offset(content, m.span().start()), offset(content, m.span().end())))
+            }
+            _ => None,
+        })
+    });
+    let module = existing.map_or("", |(start, end)| &content[start..end]);
+    let uses: String = imports.iter().filter(|i| !module.contains(i.as_str())).map(|i| indent(i)).collect();
+    let mut parts: Vec<String> = if uses.is_empty() { Vec::new() } else { vec![uses] };
+    parts.extend(items.iter().map(|i| indent(i)));
+    let body = parts.join("\n");
+    match existing {
+        // Before the closing brace.
+        Some((_, end)) => content.insert_str(end - 1, &format!("\n{}", body)),
+        None => content.push_str(&format!("\npub mod {} {{\n{}}}\n", name, body)),
+    }
+}
+
+/// The manifest of a new shared crate, inheriting the workspace's version and
+/// edition when it declares them.
+fn crate_manifest(root: &Path, name: &str, dependencies: &BTreeMap<String, String>) -> Result<String> {
+    let workspace: toml::Table = std::fs::read_to_string(root.join("Cargo.toml"))?.parse()?;
+    let inherited = |key: &str| {
+        workspace.get("workspace").and_then(|w| w.get("package")).and_then(|p| p.get(key)).is_some()
+    };
+    let field = |key: &str, default: &str| {
+        if inherited(key) { format!("{}.workspace = true\n", key) } else { format!("{} = \"{}\"\n", key, default) }
+    };
+    let dependencies: String = dependencies.iter().map(|(dep, spec)| format!("{} = {}\n", dep, spec)).collect();
+    Ok(format!(
+        "[package]\nname = \"{}\"\n{}{}\n[dependencies]\n{}",
+        name,
+        field("version", "0.1.0"),
+        field("edition", "2024"),
+        dependencies
+    ))
+}
+
+/// Adds the crate to `[workspace] members` unless a member pattern already covers it.
+fn add_member(root: &Path, crate_dir: &Path) -> Result<()> {
+    let path = root.join("Cargo.toml");
+    let text = std::fs::read_to_string(&path)?;
+    let workspace: toml::Table = text.parse()?;
+    let member = relative(root, crate_dir).to_string_lossy().replace('\\', "/");
+    let parent = member.rsplit_once('/').map(|(p, _)| format!("{}/*", p));
+    let members: Vec<&str> = workspace
+        .get("workspace")
+        .and_then(|w| w.get("members"))
+        .and_then(|m| m.as_array())
+        .map(|m| m.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+    if members.iter().any(|m| *m == member || Some(*m) == parent.as_deref()) {
+        return Ok(());
+    }
+    let Some(start) = text.find("members").and_then(|i| text[i..].find('[').map(|j| i + j + 1)) else {
+        reporter().warn(format!("Add \"{}\" to the workspace members in {}", member, path.display()));
+        return Ok(());
+    };
+    let mut text = text;
+    text.insert_str(start, &format!("\"{}\", ", member));
+    dryrun::write_with(&path, text, format!("add \"{}\" to [workspace] members", member))?;
+    Ok(())
+}
+
+/// `[dependencies]` of a manifest as `name = spec` strings.
+fn manifest_dependencies(manifest: &Path) -> Vec<(String, toml::Value)> {
+    let Some(doc) = std::fs::read_to_string(manifest).ok().and_then(|c| c.parse::<toml::Table>().ok()) else {
+        return Vec::new();
+    };
+    doc.get("dependencies")
+        .and_then(|d| d.as_table())
+        .map(|t| t.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+        .unwrap_or_default()
+}
+
+/// A dependency spec copied from `from_dir` to `to_dir`, with a `path` made
+/// relative to the new manifest.
+fn rebase(spec: &toml::Value, from_dir: &Path, to_dir: &Path) -> String {
+    let mut spec = spec.clone();
+    if let Some(table) = spec.as_table_mut()
+        && let Some(path) = table.get("path").and_then(|p| p.as_str())
+    {
+        let target = from_dir.join(path);
+        table.insert("path".to_string(), toml::Value::String(relative(to_dir, &target).to_string_lossy().replace('\\', "/")));
+    }
+    match &spec {
+        toml::Value::Table(table) => {
+            let fields: Vec<String> = table.iter().map(|(k, v)| format!("{} = {}", k, v)).collect();
+            format!("{{ {} }}", fields.join(", "))
+        }
+        other => other.to_string(),
+    }
+}
+
+/// `to` relative to the directory `from`, e.g. `../../packages/api-types`.
+fn relative(from: &Path, to: &Path) -> PathBuf {
+    fn clean(p: &Path) -> Vec<Component<'_>> {
+        let mut parts = Vec::new();
+        for component in p.components() {
+            match component {
+                Component::ParentDir if matches!(parts.last(), Some(Component::Normal(_))) => {
+                    parts.pop();
+                }
+                Component::CurDir => {}
+                other => parts.push(other),
+            }
+        }
+        parts
+    }
+    let (from, to) = (clean(from), clean(to));
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut path: PathBuf = from[common..].iter().map(|_| Component::ParentDir).collect();
+    path.extend(&to[common..]);
+    if path.as_os_str().is_empty() { PathBuf::from(".") } else { path }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A workspace with the given files and a package for each top-level directory.
+    fn workspace(files: &[(&str, &str)]) -> (tempfile::TempDir, Vec<Package>) {
+        let root = tempfile::tempdir().unwrap();
+        let mut names: Vec<&str> = files.iter().filter_map(|(path, _)| path.split('/').next()).collect();
+        names.dedup();
+        let members: Vec<String> = names.iter().map(|n| format!("\"{}\"", n)).collect();
+        std::fs::write(root.path().join("Cargo.toml"), format!("[workspace]\nmembers = [{}]\n", members.join(", "))).unwrap();
+        for name in &names {
+            std::fs::create_dir_all(root.path().join(name)).unwrap();
+            let manifest = format!("[package]\nname = \"{}\"\n\n[dependencies]\nserde = \"1\"\n", name);
+            std::fs::write(root.path().join(name).join("Cargo.toml"), manifest).unwrap();
+        }
+        for (path, content) in files {
+            let path = root.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        let packages = names.iter().map(|n| Package { name: n.to_string(), dir: root.path().join(n) }).collect();
+        (root, packages)
+    }
+
+    fn shown(scan: &Scan, keys: &BTreeSet<Key>) -> Vec<String> {
+        keys.iter().map(|k| format!("{}::{}", scan.crates.iter().find(|c| *c.1 == k.package).unwrap().0, k.path.join("::"))).collect()
+    }
+
+    const SERVER: &[(&str, &str)] = &[
+        ("server/src/lib.rs", "pub mod models;\npub mod todos;\npub mod users;\n\npub use models::Todo;\n"),
+        (
+            "server/src/models.rs",
+            "use serde::Serialize;\n\n#[derive(Serialize)]\npub struct Todo {\n    pub id: u32,\n    owner: Owner,\n}\n\n#[derive(Serialize)]\npub struct Owner(String);\n\npub struct Unrelated;\n",
+        ),
+        (
+            "server/src/todos.rs",
+            "use crate::Todo;\n\n/// A new todo.\npub struct Input {\n    pub title: String,\n    pub status: Status,\n}\n\npub enum Status {\n    Unrelated,\n    Done,\n}\n\npub struct Params;\n\nimpl RouteAction<Params, Ctx> for Create {\n    type Input = Input;\n    type Output = Vec<Todo>;\n}\n",
+        ),
+        (
+            "server/src/users.rs",
+            "pub struct Input {\n    pub name: String,\n}\n\nmod inner {\n    pub struct Params;\n}\n\nimpl RouteAction<inner::Params, Ctx> for Rename {\n    type Input = Input;\n    type Output = super::models::Owner;\n}\n",
+        ),
+    ];
+
+    #[test]
+    fn test_scan_keys_types_by_module_and_resolves_uses() {
+        let (root, packages) = workspace(SERVER);
+        let scan = Scan::run(root.path(), &packages, "api-types").unwrap();
+        let shared = scan.shared_closure();
+        assert_eq!(
+            shown(&scan, &shared),
+            [
+                "server::models::Owner",
+                "server::models::Todo",
+                "server::todos::Input",
+                "server::todos::Params",
+                "server::todos::Status",
+                "server::users::Input",
+                "server::users::inner::Params",
+            ]
+        );
+
+        // Only the types a definition names are followed, not every identifier in it.
+        assert!(!shared.iter().any(|k| k.name() == "Unrelated"));
+
+        let groups = scan.groups(&shared);
+        assert_eq!(groups.len(), shared.len());
+        assert!(groups.iter().all(|g| g.copies.is_empty() && g.drift.is_empty()));
+    }
+
+    #[test]
+    fn test_scan_groups_copies_and_drift_across_packages() {
+        let web: &[(&str, &str)] = &[
+            ("web/src/lib.rs", "pub mod models;\npub mod todos;\n"),
+            (
+                "web/src/models.rs",
+                "use serde::Serialize;\n\n/// Mirrors the server's.\n#[derive(Serialize)]\npub struct Todo {\n    pub id: u32,\n    owner: Owner,\n}\n\n#[derive(Serialize)]\npub struct Owner(String);\n",
+            ),
+            ("web/src/todos.rs", "pub struct Input {\n    pub title: String,\n}\n"),
+        ];
+        let (root, packages) = workspace(&[SERVER, web].concat());
+        let scan = Scan::run(root.path(), &packages, "api-types").unwrap();
+        let groups = scan.groups(&scan.shared_closure());
+        let group = |path: &str| groups.iter().find(|g| g.primary.key.path.join("::") == path).unwrap();
+
+        let todo = group("models::Todo");
+        assert_eq!(todo.primary.shown, "server/src/models.rs");
+        assert_eq!(todo.copies.iter().map(|d| d.shown.as_str()).collect::<Vec<_>>(), ["web/src/models.rs"]);
+        assert!(todo.drift.is_empty());
+
+        let input = group("todos::Input");
+        assert!(input.copies.is_empty());
+        assert_eq!(input.drift.iter().map(|d| d.shown.as_str()).collect::<Vec<_>>(), ["web/src/todos.rs"]);
+        assert!(group("users::Input").drift.is_empty());
+    }
+
+    #[test]
+    fn test_extract_places_clashing_names_in_modules_and_rewrites_paths() {
+        let web: &[(&str, &str)] = &[(
+            "web/src/lib.rs",
+            "use serde::Serialize;\n\n#[derive(Serialize)]\npub struct Owner(String);\n",
+        )];
+        let (root, mut packages) = workspace(&[SERVER, web].concat());
+        let scan = Scan::run(root.path(), &packages, "api-types").unwrap();
+        extract(root.path(), &packages, &scan, &scan.shared_closure(), "api-types").unwrap();
+
+        let read = |path: &str| std::fs::read_to_string(root.path().join(path)).unwrap();
+        let lib = read("api-types/src/lib.rs");
+        assert!(lib.contains("use serde::Serialize;\n"));
+        assert!(lib.contains("pub struct Todo {\n    pub id: u32,\n    pub owner: crate::Owner,\n}"));
+        assert!(lib.contains("pub struct Owner(pub String);"));
+        assert!(lib.contains("pub mod todos {\n    /// A new todo.\n    pub struct Input {\n        pub title: String,\n        pub status: crate::Status,\n    }\n"));
+        assert!(lib.contains("pub mod users {\n    pub struct Input {"));
+        assert!(lib.contains("pub mod inner {\n    pub struct Params;"));
+        assert!(lib.contains("pub mod todos {") && lib.contains("    pub struct Params;\n"));
+        syn::parse_file(&lib).unwrap();
+
+        assert!(read("server/src/todos.rs").contains("pub use api_types::todos::Input;"));
+        assert!(read("server/src/users.rs").contains("    pub use api_types::inner::Params;\n"));
+        assert!(read("server/src/models.rs").contains("pub use api_types::Todo;"));
+        assert!(read("web/src/lib.rs").contains("pub use api_types::Owner;"));
+        assert!(read("Cargo.toml").contains("\"api-types\""));
+        assert!(read("web/Cargo.toml").contains("api-types = { path = \"../api-types\" }"));
+
+        // A rescan finds every route type in the shared crate, once.
+        packages.push(Package { name: "api-types".to_string(), dir: root.path().join("api-types") });
+        let scan = Scan::run(root.path(), &packages, "api-types").unwrap();
+        let shared = scan.shared_closure();
+        assert!(shared.iter().all(|k| Some(k.package) == scan.shared_package));
+        assert_eq!(shared.len(), 7);
+        verify(&scan, &shared, "api-types").unwrap();
+    }
+}
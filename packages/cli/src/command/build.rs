@@ -1,18 +1,331 @@
-use crate::config::MontrsConfig;
+//! Build command.
+//!
+//! Builds the site with cargo-leptos, through sccache or the wrapper set in
+//! `[build.cache]`. `--optimize size|speed` builds in release mode with a
+//! tuned Cargo profile (set through `CARGO_PROFILE_*` variables, so
+//! `Cargo.toml` is left alone), runs `wasm-opt` on the WASM bundle (reusing
+//! cached output for a module it has seen) and reports the size of every
+//! artifact before and after the build.
+//!
+//! `--wasi` builds the app's library for `wasm32-wasip2` instead, producing a
+//! WASI HTTP component (see `montrs_core::wasi`).
+
+use crate::buildcache::CacheWrapper;
+use crate::config::{MontrsConfig, ProjectConfig};
+use crate::devproxy::leptos_metadata;
+use crate::report::reporter;
 use crate::utils::run_cargo_leptos;
+use crate::workers::{WASM_OPT_CACHE_DIR, WasmOptCache};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Where `--optimize` writes the size report.
+pub const SIZE_REPORT_FILE: &str = "target/montrs/build-sizes.json";
+
+/// The target `--wasi` builds for.
+pub const WASI_TARGET: &str = "wasm32-wasip2";
+
+/// What `--optimize` tunes the release build for.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Optimize {
+    /// Smallest binaries and WASM: `opt-level = "z"`.
+    Size,
+    /// Fastest code: `opt-level = 3`.
+    Speed,
+}
+
+impl Optimize {
+    /// Profile settings, as `CARGO_PROFILE_<NAME>_<KEY>` keys and values.
+    fn profile(self) -> [(&'static str, &'static str); 4] {
+        let opt_level = match self {
+            Optimize::Size => "z",
+            Optimize::Speed => "3",
+        };
+        [("OPT_LEVEL", opt_level), ("LTO", "fat"), ("CODEGEN_UNITS", "1"), ("STRIP", "symbols")]
+    }
+
+    fn wasm_opt_args(self) -> Vec<String> {
+        let level = match self {
+            Optimize::Size => "-Oz",
+            Optimize::Speed => "-O3",
+        };
+        [level, "--strip-debug", "--strip-producers"].map(String::from).to_vec()
+    }
+}
+
+/// One artifact in the size report.
+#[derive(Debug, Serialize)]
+struct ArtifactSize {
+    path: PathBuf,
+    /// Size left by the previous build, if there was one.
+    before: Option<u64>,
+    after: u64,
+}
 
-pub async fn run() -> anyhow::Result<()> {
+#[derive(Debug, Serialize)]
+struct SizeReport {
+    optimize: Optimize,
+    artifacts: Vec<ArtifactSize>,
+}
+
+/// `project` carries the global flags (`--release`, `--features`, ...).
+pub async fn run(optimize: Option<Optimize>, wasi: bool, project: ProjectConfig) -> Result<()> {
     let mut config = MontrsConfig::load()?;
+    config.project = project;
+
+    if wasi {
+        return build_wasi(&config);
+    }
 
-    // Handle tailwind.toml
-    if let Ok(Some(js_path)) = crate::config::tailwind::ensure_tailwind_config(
-        std::path::Path::new("."),
-        config.project.tailwind_style.unwrap_or_default(),
-    ) {
-        if config.build.tailwind_config_file.is_none() {
-            config.build.tailwind_config_file = Some(js_path.to_string_lossy().into_owned());
+    crate::utils::prepare_tailwind(&mut config);
+
+    if config.database.check {
+        crate::command::db::check(&config.database, std::path::Path::new("."), config.database.check_against).await?;
+    }
+
+    let cache = crate::buildcache::configure(&config.build.cache)?;
+    let Some(optimize) = optimize else {
+        return build(&config, cache.as_ref()).await;
+    };
+
+    config.project.release = true;
+    let before = artifact_sizes(&config);
+    apply_profile(optimize);
+    build(&config, cache.as_ref()).await?;
+
+    wasm_opt(optimize, &pkg_dir(&config))?;
+
+    let after = artifact_sizes(&config);
+    if after.is_empty() {
+        reporter().warn("No build artifacts found to measure");
+        return Ok(());
+    }
+    let report = SizeReport {
+        optimize,
+        artifacts: after
+            .into_iter()
+            .map(|(path, after)| ArtifactSize { before: before.get(&path).copied(), path, after })
+            .collect(),
+    };
+    print_sizes(&report);
+    std::fs::create_dir_all(Path::new(SIZE_REPORT_FILE).parent().unwrap_or(Path::new(".")))?;
+    std::fs::write(SIZE_REPORT_FILE, serde_json::to_string_pretty(&report)?)?;
+    Ok(())
+}
+
+/// Runs the cargo-leptos build and reports what the compilation cache did.
+async fn build(config: &MontrsConfig, cache: Option<&CacheWrapper>) -> Result<()> {
+    let stats = cache.and_then(CacheWrapper::stats);
+    let step = reporter().stream_step("build");
+    run_cargo_leptos("build", &[], config).await?;
+    step.finish();
+    if let Some(cache) = cache {
+        crate::buildcache::report(cache, stats);
+    }
+    Ok(())
+}
+
+/// Builds the component: `cargo build --lib --target wasm32-wasip2`, then
+/// lists the `.wasm` files it left. The crate must be a `cdylib` calling
+/// `montrs_core::export_component!` with the `wasi` feature of montrs-core on.
+fn build_wasi(config: &MontrsConfig) -> Result<()> {
+    let step = reporter().step("wasi target");
+    let installed = std::process::Command::new("rustup")
+        .args(["target", "list", "--installed"])
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).lines().any(|line| line.trim() == WASI_TARGET));
+    match installed {
+        Ok(true) => step.finish(),
+        Ok(false) => {
+            step.fail();
+            anyhow::bail!("The {} target is not installed. Run `rustup target add {}`.", WASI_TARGET, WASI_TARGET);
         }
+        // Without rustup, let cargo report a missing target.
+        Err(_) => step.skip("rustup not found"),
     }
 
-    run_cargo_leptos("build", &[], &config).await
+    let mut cmd = std::process::Command::new("cargo");
+    cmd.args(["build", "--lib", "--target", WASI_TARGET]);
+    if config.project.release {
+        cmd.arg("--release");
+    }
+    if let Some(package) = &config.build.wasi.package {
+        cmd.args(["--package", package]);
+    }
+    for feature in &config.project.features {
+        cmd.args(["--features", feature]);
+    }
+    let step = reporter().stream_step("build");
+    if let Err(e) = crate::ext::exe_command(&mut cmd) {
+        step.fail();
+        return Err(e);
+    }
+    step.finish();
+
+    let target_dir = cargo_metadata::MetadataCommand::new()
+        .no_deps()
+        .exec()
+        .map(|metadata| metadata.target_directory.into_std_path_buf())
+        .unwrap_or_else(|_| PathBuf::from("target"));
+    let profile = if config.project.release { "release" } else { "debug" };
+    let out_dir = target_dir.join(WASI_TARGET).join(profile);
+    let components: Vec<_> = std::fs::read_dir(&out_dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "wasm"))
+        .collect();
+    if components.is_empty() {
+        reporter().warn(format!("No .wasm files in {}: is the crate a `cdylib`?", out_dir.display()));
+    }
+    for path in components {
+        let size = std::fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+        reporter().info(format!("  {}  {}", path.display(), human_size(size)));
+    }
+    Ok(())
+}
+
+/// Sets the profile for `opt`. `panic = "abort"` only goes on a WASM profile
+/// the server does not share: the server needs unwinding so a panicking
+/// handler becomes a 500 instead of killing the process.
+fn apply_profile(opt: Optimize) {
+    let mut step = reporter().step("release profile");
+    let profile_of = |key: &str| {
+        leptos_metadata(key).and_then(|v| v.as_str().map(String::from)).unwrap_or_else(|| "release".to_string())
+    };
+    let lib_profile = profile_of("lib-profile-release");
+    let bin_profile = profile_of("bin-profile-release");
+    let mut profiles = vec![lib_profile.clone()];
+    if bin_profile != lib_profile {
+        profiles.push(bin_profile.clone());
+    }
+
+    let env_name = |profile: &str, key: &str| format!("CARGO_PROFILE_{}_{}", profile.to_uppercase().replace('-', "_"), key);
+    unsafe {
+        for profile in &profiles {
+            for (key, value) in opt.profile() {
+                std::env::set_var(env_name(profile, key), value);
+            }
+        }
+        if bin_profile != lib_profile {
+            std::env::set_var(env_name(&lib_profile, "PANIC"), "abort");
+        }
+    }
+
+    let settings = opt.profile().map(|(key, value)| format!("{}={}", key.to_lowercase().replace('_', "-"), value)).join(", ");
+    let panic = if bin_profile == lib_profile {
+        "panic=abort skipped: the server shares the profile".to_string()
+    } else {
+        format!("panic=abort on `{}`", lib_profile)
+    };
+    step.set_detail(format!("{} on `{}`; {}", settings, profiles.join("`, `"), panic));
+    step.finish();
+}
+
+/// Runs `wasm-opt` in place on every `.wasm` file in `pkg_dir`, reusing
+/// the output of earlier runs on the same module.
+fn wasm_opt(opt: Optimize, pkg_dir: &Path) -> Result<()> {
+    let mut step = reporter().step("wasm-opt");
+    let Ok(wasm_opt) = which::which("wasm-opt") else {
+        step.skip("wasm-opt not found; install binaryen to optimize the WASM bundle");
+        return Ok(());
+    };
+    let bundles: Vec<PathBuf> = walkdir::WalkDir::new(pkg_dir)
+        .into_iter()
+        .flatten()
+        .map(|entry| entry.into_path())
+        .filter(|path| path.extension().is_some_and(|e| e == "wasm"))
+        .collect();
+    if bundles.is_empty() {
+        step.skip(format!("no .wasm files in {}", pkg_dir.display()));
+        return Ok(());
+    }
+    let cache = WasmOptCache::new(WASM_OPT_CACHE_DIR);
+    let mut hits = 0;
+    for bundle in &bundles {
+        match cache.optimize(&wasm_opt, &opt.wasm_opt_args(), bundle) {
+            Ok(hit) => hits += usize::from(hit),
+            Err(e) => {
+                step.fail();
+                return Err(e);
+            }
+        }
+    }
+    step.set_detail(format!("{} of {} from cache", hits, bundles.len()));
+    step.finish();
+    Ok(())
+}
+
+/// The site's package directory: `site-root`/`site-pkg-dir` from the leptos
+/// metadata, then `[build]`.
+pub(crate) fn pkg_dir(config: &MontrsConfig) -> PathBuf {
+    let meta = |key: &str| leptos_metadata(key).and_then(|v| v.as_str().map(String::from));
+    let root = meta("site-root").unwrap_or_else(|| config.build.site_root.clone());
+    let pkg = meta("site-pkg-dir").unwrap_or_else(|| config.build.site_pkg_name.clone());
+    Path::new(&root).join(pkg)
+}
+
+/// Sizes of the server binary and the WASM, JS and CSS bundles.
+fn artifact_sizes(config: &MontrsConfig) -> BTreeMap<PathBuf, u64> {
+    let mut sizes = BTreeMap::new();
+    for entry in walkdir::WalkDir::new(pkg_dir(config)).into_iter().flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()).is_some_and(|e| matches!(e, "wasm" | "js" | "css"))
+            && let Ok(meta) = entry.metadata()
+        {
+            sizes.insert(path.to_path_buf(), meta.len());
+        }
+    }
+    if let Some(bin) = server_binary()
+        && let Ok(meta) = std::fs::metadata(&bin)
+    {
+        sizes.insert(bin, meta.len());
+    }
+    sizes
+}
+
+/// `target/release/<bin-package>`, or the package name when the metadata has none.
+fn server_binary() -> Option<PathBuf> {
+    let name = leptos_metadata("bin-package")
+        .and_then(|v| v.as_str().map(String::from))
+        .or_else(|| {
+            let manifest: toml::Value = toml::from_str(&std::fs::read_to_string("Cargo.toml").ok()?).ok()?;
+            manifest.get("package")?.get("name")?.as_str().map(String::from)
+        })?;
+    let target = leptos_metadata("bin-target-dir").and_then(|v| v.as_str().map(String::from)).unwrap_or_else(|| "target".to_string());
+    Some(Path::new(&target).join("release").join(format!("{}{}", name, std::env::consts::EXE_SUFFIX)))
+}
+
+fn print_sizes(report: &SizeReport) {
+    let width = report.artifacts.iter().map(|a| a.path.display().to_string().len()).max().unwrap_or(0);
+    reporter().info(format!("  {:<width$}  {:>10}  {:>10}  {:>8}", "artifact", "before", "after", "change"));
+    for artifact in &report.artifacts {
+        let (before, change) = match artifact.before {
+            Some(before) if before > 0 => {
+                let change = (artifact.after as f64 - before as f64) / before as f64 * 100.0;
+                (human_size(before), format!("{:+.1}%", change))
+            }
+            _ => ("-".to_string(), "new".to_string()),
+        };
+        reporter().info(format!(
+            "  {:<width$}  {:>10}  {:>10}  {:>8}",
+            artifact.path.display(),
+            before,
+            human_size(artifact.after),
+            change
+        ));
+    }
+    reporter().info(format!("  Size report written to {}", SIZE_REPORT_FILE));
+}
+
+fn human_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1024 * 1024 => format!("{:.1} MB", b as f64 / 1024.0 / 1024.0),
+        b if b >= 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{} B", b),
+    }
 }
@@ -0,0 +1,139 @@
+//! Changelog command.
+//!
+//! Boots the app to export its `AppSpecExport` and compares it with the spec of
+//! a previous release: a JSON file, `montrs.spec.json` at a git revision, or,
+//! when the revision did not commit one, the app built from a temporary
+//! worktree of that revision. Prints the changes as a Markdown changelog
+//! section and writes them as JSON for release tooling.
+
+use crate::config::ProjectConfig;
+use crate::dryrun;
+use crate::report::reporter;
+use anyhow::{Context, Result};
+use console::style;
+use montrs_core::changelog::{SPEC_EXPORT_FILE, SPEC_EXPORT_OUT_VAR};
+use montrs_core::{AppSpecExport, SpecDiff};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Where the app writes its exported spec for `changelog`.
+const EXPORT_OUT: &str = "target/montrs/spec-export.json";
+
+pub struct ChangelogOptions {
+    pub since: Option<String>,
+    pub to: Option<String>,
+    pub json: PathBuf,
+    pub save: bool,
+}
+
+pub async fn run(options: ChangelogOptions, project: &ProjectConfig) -> Result<()> {
+    let root = std::env::current_dir()?;
+    let since = match options.since {
+        Some(since) => since,
+        None => latest_tag()?,
+    };
+
+    let current = export(&root, &root.join(EXPORT_OUT), project).await?;
+    let previous = load_previous(&root, &since, project).await?;
+    let to = options.to.unwrap_or_else(|| "Unreleased".to_string());
+    let diff = SpecDiff::between(&since, &previous, to, &current);
+
+    println!("{}", diff.to_markdown());
+    let json = root.join(&options.json);
+    if !dryrun::enabled()
+        && let Some(parent) = json.parent()
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+    dryrun::write(&json, serde_json::to_string_pretty(&diff)? + "\n")?;
+    if options.save {
+        dryrun::write(&root.join(SPEC_EXPORT_FILE), serde_json::to_string_pretty(&current)? + "\n")?;
+    }
+    if !dryrun::enabled() {
+        let summary = format!("{} change(s) since {}", diff.changes.len(), since);
+        if diff.breaking {
+            reporter().warn(format!("{}, including breaking changes", summary));
+        } else {
+            reporter().info(format!("{} {}", style("✔").green(), summary));
+        }
+        reporter().info(format!("Wrote {}", options.json.display()));
+        if options.save {
+            reporter().info(format!("Wrote {}; commit it with the release", SPEC_EXPORT_FILE));
+        }
+    }
+    Ok(())
+}
+
+/// The most recent tag reachable from HEAD.
+fn latest_tag() -> Result<String> {
+    let output = Command::new("git")
+        .args(["describe", "--tags", "--abbrev=0"])
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!("No tag to compare against; pass --since <rev or spec file>");
+    }
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
+
+/// Boots the app in `dir` and reads the spec it exports to `out`.
+async fn export(dir: &Path, out: &Path, project: &ProjectConfig) -> Result<AppSpecExport> {
+    let _ = std::fs::remove_file(out);
+
+    let step = reporter().stream_step(format!("export spec ({})", dir.display()));
+    let mut cmd = tokio::process::Command::new("cargo");
+    cmd.arg("run").current_dir(dir);
+    if project.release {
+        cmd.arg("--release");
+    }
+    for feature in &project.features {
+        cmd.args(["--features", feature]);
+    }
+    let status = cmd.env(SPEC_EXPORT_OUT_VAR, out).status().await.context("Failed to run cargo")?;
+    if !status.success() || !out.exists() {
+        step.fail();
+        anyhow::bail!(
+            "The app exited without exporting its spec; make sure its server binary calls `AppSpec::boot` \
+             (pass --features if it needs e.g. `ssr`)"
+        );
+    }
+    step.finish();
+    AppSpecExport::read_from(out).with_context(|| format!("Failed to read {}", out.display()))
+}
+
+/// Loads the previous spec from a file, from `montrs.spec.json` at a git
+/// revision, or by exporting it from a worktree of that revision.
+async fn load_previous(root: &Path, since: &str, project: &ProjectConfig) -> Result<AppSpecExport> {
+    if Path::new(since).is_file() {
+        return AppSpecExport::read_from(Path::new(since)).with_context(|| format!("Failed to read '{}'", since));
+    }
+
+    let output = Command::new("git")
+        .args(["show", &format!("{}:{}", since, SPEC_EXPORT_FILE)])
+        .output()
+        .context("Failed to run git")?;
+    if output.status.success() {
+        return serde_json::from_slice(&output.stdout)
+            .with_context(|| format!("Failed to parse {} at '{}'", SPEC_EXPORT_FILE, since));
+    }
+
+    // Outside the project, so Cargo does not take the checkout for a member of it.
+    let worktree = std::env::temp_dir().join(format!("montrs-changelog-{}", std::process::id()));
+    let added = Command::new("git")
+        .args(["worktree", "add", "--detach"])
+        .arg(&worktree)
+        .arg(since)
+        .output()
+        .context("Failed to run git")?;
+    if !added.status.success() {
+        anyhow::bail!(
+            "'{}' is neither a spec file nor a git revision: {}",
+            since,
+            String::from_utf8_lossy(&added.stderr).trim()
+        );
+    }
+    reporter().info(format!("{} has no {}; building it to export one", since, SPEC_EXPORT_FILE));
+    let previous = export(&worktree, &root.join("target/montrs/spec-export-previous.json"), project).await;
+    let _ = Command::new("git").args(["worktree", "remove", "--force"]).arg(&worktree).output();
+    previous
+}
@@ -0,0 +1,41 @@
+//! Config command.
+//!
+//! `montrs config resolve` prints the settings a command or the app would
+//! use: `montrs.toml` with its layers merged and defaults filled in, and the
+//! `[preset]` table replaced by the preset it resolves to for the target.
+
+use crate::ConfigSubcommand;
+use crate::config::MontrsConfig;
+use anyhow::Result;
+use montrs_core::Target;
+
+pub fn run(subcommand: ConfigSubcommand, config: &MontrsConfig) -> Result<()> {
+    match subcommand {
+        ConfigSubcommand::Resolve { target, format } => {
+            println!("{}", resolve(config, target, &format)?);
+            Ok(())
+        }
+    }
+}
+
+/// The effective configuration as TOML, or JSON when `format` is `json`.
+pub fn resolve(config: &MontrsConfig, target: Option<Target>, format: &str) -> Result<String> {
+    let (target, preset) = config.preset.resolve(target)?;
+    match format {
+        "toml" => {
+            let mut resolved = toml::Table::try_from(&preset)?;
+            resolved.insert("target".to_string(), target.name().into());
+            let mut effective = toml::Table::try_from(config)?;
+            effective.insert("preset".to_string(), resolved.into());
+            Ok(toml::to_string_pretty(&effective)?)
+        }
+        "json" => {
+            let mut effective = serde_json::to_value(config)?;
+            let mut resolved = serde_json::to_value(&preset)?;
+            resolved["target"] = target.name().into();
+            effective["preset"] = resolved;
+            Ok(serde_json::to_string_pretty(&effective)?)
+        }
+        other => anyhow::bail!("Unknown format '{}'; expected toml or json", other),
+    }
+}
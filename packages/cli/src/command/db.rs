@@ -0,0 +1,686 @@
+//! Database command.
+//!
+//! `db check` finds the SQL string literals passed to `execute`, `query` and
+//! the query builder in the project's Rust sources and checks them against
+//! the schema: table and column names, and the number of placeholders against
+//! the parameters passed. The schema comes from replaying the migrations, or
+//! from the live database with `--live`. With `[database] check = true` the
+//! same check runs before every `montrs build`.
+//!
+//! `db diff` compares the live database with the schema the migrations
+//! describe and reports the drift, optionally drafting a migration to fix it.
+//!
+//! `db rotate-keys` finds the `#[orm(encrypted)]` fields of the project's
+//! models and re-encrypts their columns with the current key from
+//! `DATABASE_ENCRYPTION_KEYS`. With `--generate` it first adds a new key to
+//! the front of that secret.
+//!
+//! `db export` writes tables to `<out>/<table>.jsonl` or `.csv`, reading each
+//! one page at a time in primary key order. `db import` loads such files back
+//! in a single transaction, so a failed import leaves the database as it was;
+//! `--on-conflict` decides whether rows with an existing key are skipped,
+//! overwritten, or whether each table is emptied first. On PostgreSQL the
+//! sequences behind integer keys are moved past the imported ids.
+//!
+//! With `--dry-run`, `diff`, `rotate-keys`, `export` and `import` print the
+//! files they would write and the statements they would run, one per row for
+//! `rotate-keys`, without running them.
+
+use crate::DbSubcommand;
+use crate::config::{DatabaseConfig, MontrsConfig, SchemaSource};
+use crate::dryrun;
+use crate::report::{ActionKind, Planned, reporter};
+use anyhow::{Context, Result};
+use console::style;
+use montrs_core::secrets::{SecretKey, SecretsEnv, SecretsFile};
+use montrs_core::{EnvChain, TypedEnv};
+use montrs_orm::encryption::{ENCRYPTION_KEYS_VAR, rotate_column, stale_rows};
+use montrs_orm::transfer::{DataFormat, Export, Import, OnConflict};
+use montrs_orm::{
+    DbBackend, FieldCipher, PostgresBackend, SchemaDrift, SchemaSnapshot, SqliteBackend, Transaction, check_query,
+};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use syn::visit::Visit;
+
+/// Methods whose first argument is SQL.
+const QUERY_METHODS: &[&str] = &["execute", "query", "query_one", "query_opt", "query_as", "query_scalar"];
+
+/// Statements a string literal must start with to be taken for SQL.
+const SQL_VERBS: &[&str] = &["select", "insert", "update", "delete", "with", "replace", "create", "alter", "drop"];
+
+pub async fn run(subcommand: DbSubcommand, config: &MontrsConfig) -> Result<()> {
+    match subcommand {
+        DbSubcommand::Check { path, live } => {
+            let source = if live { SchemaSource::Live } else { config.database.check_against };
+            check(&config.database, Path::new(&path), source).await
+        }
+        DbSubcommand::Diff { path, report, migration } => {
+            diff(&config.database, Path::new(&path), report.as_deref(), migration).await
+        }
+        DbSubcommand::RotateKeys { path, generate } => rotate_keys(config, Path::new(&path), generate).await,
+        DbSubcommand::Export { format, tables, out } => export(&config.database, &tables, format.into(), &out).await,
+        DbSubcommand::Import { path, tables, format, on_conflict } => {
+            import(&config.database, &path, &tables, format.map(Into::into), on_conflict.into()).await
+        }
+    }
+}
+
+/// File format of `db export` and `db import`.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferFormat {
+    /// One JSON object per line.
+    Jsonl,
+    /// A header of column names, then one line per row.
+    Csv,
+}
+
+impl From<TransferFormat> for DataFormat {
+    fn from(format: TransferFormat) -> Self {
+        match format {
+            TransferFormat::Jsonl => DataFormat::Jsonl,
+            TransferFormat::Csv => DataFormat::Csv,
+        }
+    }
+}
+
+/// What `db import` does with a row whose primary key is already taken.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictStrategy {
+    /// Keep the existing row.
+    Skip,
+    /// Overwrite the existing row.
+    Upsert,
+    /// Empty each table before loading it.
+    Replace,
+}
+
+impl From<ConflictStrategy> for OnConflict {
+    fn from(strategy: ConflictStrategy) -> Self {
+        match strategy {
+            ConflictStrategy::Skip => OnConflict::Skip,
+            ConflictStrategy::Upsert => OnConflict::Upsert,
+            ConflictStrategy::Replace => OnConflict::Replace,
+        }
+    }
+}
+
+/// Checks every SQL literal under `root` and fails with one diagnostic per
+/// problem found.
+pub async fn check(database: &DatabaseConfig, root: &Path, source: SchemaSource) -> Result<()> {
+    let mut step = reporter().step("sql check");
+    let schema = load_schema(database, root, source).await?;
+    let queries = extract_queries(root)?;
+    step.set_detail(format!("{} queries, {} tables", queries.len(), schema.tables.len()));
+
+    let mut errors = 0;
+    for query in &queries {
+        for issue in check_query(&query.sql, query.params, &schema) {
+            let (line, column) = query.position(issue.offset);
+            reporter().output(format!(
+                "{}: {}\n  {} {}:{}:{}",
+                style("error[sql]").red().bold(),
+                style(&issue.message).bold(),
+                style("-->").blue(),
+                query.file.display(),
+                line,
+                column
+            ));
+            errors += 1;
+        }
+    }
+    if errors > 0 {
+        step.fail();
+        anyhow::bail!("{} SQL error(s) found; fix the queries or the migrations", errors);
+    }
+    step.finish();
+    Ok(())
+}
+
+/// Reports how the live database differs from the migrations. Fails when it
+/// does, unless a corrective migration was written.
+async fn diff(database: &DatabaseConfig, root: &Path, report: Option<&Path>, migration: bool) -> Result<()> {
+    let step = reporter().step("schema diff");
+    let expected = load_schema(database, root, SchemaSource::Migrations).await?;
+    let actual = introspect(database).await?;
+    let drift = SchemaDrift::between(&expected, &actual);
+    if drift.is_empty() {
+        step.finish();
+        reporter().info(format!("{} The database matches the migrations", style("✔").green()));
+    } else {
+        step.fail();
+        reporter().output(format!("{}", style(format!("Schema drift ({}):", drift.drifts.len())).bold()));
+        for item in &drift.drifts {
+            reporter().output(format!("  {} {}", style("✘").red(), item));
+        }
+    }
+
+    if let Some(path) = report {
+        dryrun::write(path, serde_json::to_string_pretty(&drift)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        if !dryrun::enabled() {
+            reporter().info(format!("Drift report written to {}", path.display()));
+        }
+    }
+    if drift.is_empty() {
+        return Ok(());
+    }
+    if !migration {
+        anyhow::bail!("The database has drifted from the migrations; rerun with --migration to draft a fix");
+    }
+    let secs = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let path = root.join(&database.migrations).join(format!("{}_fix_schema_drift.sql", secs));
+    dryrun::write(&path, drift.migration()).with_context(|| format!("Failed to write {}", path.display()))?;
+    if !dryrun::enabled() {
+        reporter().info(format!("{} Migration skeleton written to {}", style("✔").green(), path.display()));
+    }
+    Ok(())
+}
+
+async fn load_schema(database: &DatabaseConfig, root: &Path, source: SchemaSource) -> Result<SchemaSnapshot> {
+    match source {
+        SchemaSource::Migrations => {
+            let dir = root.join(&database.migrations);
+            if !dir.is_dir() {
+                anyhow::bail!(
+                    "No migrations in {}; set `[database].migrations` or check against the live database with --live",
+                    dir.display()
+                );
+            }
+            Ok(SchemaSnapshot::from_migrations(&dir)?)
+        }
+        SchemaSource::Live => introspect(database).await,
+    }
+}
+
+/// Reads the schema of the database at `[database].url`.
+pub async fn introspect(database: &DatabaseConfig) -> Result<SchemaSnapshot> {
+    Database::connect(database)?.introspect().await
+}
+
+/// The database at `[database].url`.
+enum Database {
+    Sqlite(SqliteBackend),
+    Postgres(PostgresBackend),
+}
+
+impl Database {
+    fn connect(database: &DatabaseConfig) -> Result<Self> {
+        let url = database.resolved_url()?;
+        let backend = database.backend.clone().unwrap_or_else(|| {
+            if url.starts_with("postgres") { "postgres" } else { "sqlite" }.to_string()
+        });
+        Ok(match backend.as_str() {
+            "sqlite" => {
+                let path = url.trim_start_matches("sqlite://").trim_start_matches("sqlite:");
+                Database::Sqlite(SqliteBackend::new(path)?)
+            }
+            "postgres" => {
+                let config = montrs_orm::PostgresConfig { url: Some(url), ..Default::default() };
+                Database::Postgres(PostgresBackend::new(config)?)
+            }
+            other => anyhow::bail!("Unknown database backend `{}` (expected sqlite or postgres)", other),
+        })
+    }
+
+    async fn introspect(&self) -> Result<SchemaSnapshot> {
+        Ok(match self {
+            Database::Sqlite(db) => db.introspect().await?,
+            Database::Postgres(db) => db.introspect().await?,
+        })
+    }
+
+    async fn rotate(&self, cipher: &FieldCipher, table: &str, key: &str, column: &str) -> Result<usize> {
+        Ok(match self {
+            Database::Sqlite(db) => rotate_column(db, cipher, table, key, column).await?,
+            Database::Postgres(db) => rotate_column(db, cipher, table, key, column).await?,
+        })
+    }
+
+    async fn stale_rows(&self, cipher: &FieldCipher, table: &str, key: &str, column: &str) -> Result<Vec<String>> {
+        Ok(match self {
+            Database::Sqlite(db) => stale_rows(db, cipher, table, key, column).await?,
+            Database::Postgres(db) => stale_rows(db, cipher, table, key, column).await?,
+        })
+    }
+
+    async fn export(
+        &self,
+        export: &Export,
+        format: DataFormat,
+        out: &mut impl Write,
+        progress: impl FnMut(u64),
+    ) -> Result<u64> {
+        Ok(match self {
+            Database::Sqlite(db) => export.write(db, format, out, progress).await?,
+            Database::Postgres(db) => export.write(db, format, out, progress).await?,
+        })
+    }
+
+    async fn transaction(&self) -> Result<Transaction> {
+        Ok(match self {
+            Database::Sqlite(db) => db.transaction().await?,
+            Database::Postgres(db) => db.transaction().await?,
+        })
+    }
+}
+
+/// Primary key columns of `table`, in declaration order.
+fn primary_key(schema: &SchemaSnapshot, table: &str) -> Result<Vec<String>> {
+    let table_schema = schema.table(table).with_context(|| format!("Table `{}` is not in the database", table))?;
+    Ok(table_schema.columns.iter().filter(|c| c.primary_key).map(|c| c.name.clone()).collect())
+}
+
+/// Writes each of `tables`, or every table, to `<out>/<table>.<ext>`.
+async fn export(database: &DatabaseConfig, tables: &[String], format: DataFormat, out: &Path) -> Result<()> {
+    let db = Database::connect(database)?;
+    let schema = db.introspect().await?;
+    let tables: Vec<String> = if tables.is_empty() { schema.tables.keys().cloned().collect() } else { tables.to_vec() };
+    if tables.is_empty() {
+        reporter().info("No tables to export");
+        return Ok(());
+    }
+    dryrun::create_dir_all(out).with_context(|| format!("Failed to create {}", out.display()))?;
+    for table in &tables {
+        let key = primary_key(&schema, table)?;
+        let path = out.join(format!("{}.{}", table, format.extension()));
+        if dryrun::enabled() {
+            dryrun::plan(
+                Planned::new(ActionKind::WriteFile, path.display().to_string()).with_detail(format!("rows of {}", table)),
+            );
+            continue;
+        }
+        let mut step = reporter().step(format!("export {}", table));
+        let file = std::fs::File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let key: Vec<&str> = key.iter().map(String::as_str).collect();
+        let export = Export::table(table).key(&key);
+        let rows = db
+            .export(&export, format, &mut BufWriter::new(file), |n| step.set_detail(format!("{} rows", n)))
+            .await?;
+        step.set_detail(format!("{} row(s) to {}", rows, path.display()));
+        step.finish();
+    }
+    Ok(())
+}
+
+/// Loads an exported file, or every exported file in a directory, in one
+/// transaction.
+async fn import(
+    database: &DatabaseConfig,
+    path: &Path,
+    tables: &[String],
+    format: Option<DataFormat>,
+    on_conflict: OnConflict,
+) -> Result<()> {
+    let files = import_files(path, tables, format)?;
+    if files.is_empty() {
+        reporter().info(format!("No exported files in {}", path.display()));
+        return Ok(());
+    }
+    let db = Database::connect(database)?;
+    let schema = db.introspect().await?;
+    if dryrun::enabled() {
+        for (file, table, _) in &files {
+            primary_key(&schema, table)?;
+            dryrun::plan(
+                Planned::new(ActionKind::ExecuteSql, table.clone())
+                    .with_detail(format!("load {} ({:?} on conflict)", file.display(), on_conflict)),
+            );
+        }
+        return Ok(());
+    }
+
+    // Dropping the transaction on an error rolls every table back.
+    let tx = db.transaction().await?;
+    for (file, table, format) in &files {
+        let mut step = reporter().step(format!("import {}", table));
+        let key = primary_key(&schema, table)?;
+        let input = std::fs::File::open(file).with_context(|| format!("Failed to read {}", file.display()))?;
+        let columns: Vec<&str> = key.iter().map(String::as_str).collect();
+        let rows = Import::table(table)
+            .key(&columns)
+            .on_conflict(on_conflict)
+            .read(&tx, *format, BufReader::new(input), |n| step.set_detail(format!("{} rows", n)))
+            .await?;
+        if let (Database::Postgres(_), [key]) = (&db, key.as_slice()) {
+            let integer = schema.table(table).and_then(|t| t.columns.iter().find(|c| &c.name == key)).is_some_and(|c| {
+                let sql_type = c.sql_type.to_lowercase();
+                sql_type.contains("int") || sql_type.contains("serial")
+            });
+            if integer {
+                tx.execute(
+                    &format!(
+                        "SELECT setval(pg_get_serial_sequence($1, $2), COALESCE(MAX({}), 0) + 1, false) FROM {}",
+                        key, table
+                    ),
+                    &[table, key],
+                )
+                .await?;
+            }
+        }
+        step.set_detail(format!("{} row(s) from {}", rows, file.display()));
+        step.finish();
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// `(file, table, format)` of each file to import. A directory yields its
+/// exported files in name order, or those of `tables` in that order.
+fn import_files(path: &Path, tables: &[String], format: Option<DataFormat>) -> Result<Vec<(PathBuf, String, DataFormat)>> {
+    let format_of = |file: &Path| {
+        format.or_else(|| file.extension().and_then(|e| e.to_str()).and_then(DataFormat::from_extension))
+    };
+    let table_of = |file: &Path| file.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+
+    if !path.is_dir() {
+        let format = format_of(path)
+            .with_context(|| format!("Cannot tell the format of {}; pass --format jsonl or csv", path.display()))?;
+        let table = match tables {
+            [] => table_of(path),
+            [table] => table.clone(),
+            _ => anyhow::bail!("A single file is loaded into one table; pass --table once"),
+        };
+        return Ok(vec![(path.to_path_buf(), table, format)]);
+    }
+
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(path).with_context(|| format!("Failed to read {}", path.display()))? {
+        let file = entry?.path();
+        let ext = file.extension().and_then(|e| e.to_str()).and_then(DataFormat::from_extension);
+        if let Some(found) = ext.filter(|f| format.is_none_or(|wanted| wanted == *f)) {
+            files.push((file.clone(), table_of(&file), found));
+        }
+    }
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    if tables.is_empty() {
+        return Ok(files);
+    }
+    tables
+        .iter()
+        .map(|table| {
+            files
+                .iter()
+                .find(|(_, name, _)| name == table)
+                .cloned()
+                .with_context(|| format!("No export of `{}` in {}", table, path.display()))
+        })
+        .collect()
+}
+
+/// Re-encrypts every `#[orm(encrypted)]` column with the current key. Rows
+/// already under it are left alone, so an interrupted run can be repeated.
+async fn rotate_keys(config: &MontrsConfig, root: &Path, generate: bool) -> Result<()> {
+    let generated = if generate { Some(generate_key(config)?) } else { None };
+    let cipher = match generated {
+        // A dry run did not save the new key, so plan against it directly.
+        Some(keys) if dryrun::enabled() => FieldCipher::parse(&keys)?,
+        _ => {
+            let mut env = EnvChain::new().with(TypedEnv {});
+            match SecretsEnv::load(&config.secrets.file, &config.project.name) {
+                Ok(secrets) => env = env.with(secrets),
+                Err(e) => reporter().warn(format!("Secrets not loaded: {}", e)),
+            }
+            FieldCipher::from_env(&env)?
+        }
+    };
+
+    let columns = extract_encrypted_columns(root)?;
+    if columns.is_empty() {
+        reporter().info("No #[orm(encrypted)] fields found");
+        return Ok(());
+    }
+    let db = Database::connect(&config.database)?;
+    let schema = db.introspect().await?;
+    for (table, column) in &columns {
+        let mut step = reporter().step(format!("rotate {}.{}", table, column));
+        let keys: Vec<_> = schema
+            .table(table)
+            .map(|t| t.columns.iter().filter(|c| c.primary_key).map(|c| c.name.as_str()).collect())
+            .unwrap_or_default();
+        let [key] = keys.as_slice() else {
+            step.fail();
+            anyhow::bail!("Table `{}` needs a single-column primary key to rotate `{}`", table, column);
+        };
+        if dryrun::enabled() {
+            let rows = db.stale_rows(&cipher, table, key, column).await?;
+            for row in &rows {
+                dryrun::plan(Planned::new(ActionKind::ExecuteSql, format!("{}.{}", table, column)).with_detail(format!(
+                    "UPDATE {} SET {} = <re-encrypted with key {}> WHERE CAST({} AS TEXT) = '{}'",
+                    table,
+                    column,
+                    cipher.current_key_id(),
+                    key,
+                    row.replace('\'', "''")
+                )));
+            }
+            step.skip(format!("dry run: {} row(s) to re-encrypt", rows.len()));
+            continue;
+        }
+        let rewritten = db.rotate(&cipher, table, key, column).await?;
+        step.set_detail(format!("{} row(s) re-encrypted with key {}", rewritten, cipher.current_key_id()));
+        step.finish();
+    }
+    Ok(())
+}
+
+/// Puts a new key in front of `DATABASE_ENCRYPTION_KEYS` in the secrets file
+/// and returns the new value.
+fn generate_key(config: &MontrsConfig) -> Result<String> {
+    let path = Path::new(&config.secrets.file);
+    let mut file = SecretsFile::load(path)
+        .with_context(|| format!("Failed to read {}. Run `montrs secrets init` first.", path.display()))?;
+    let existing = if file.contains(ENCRYPTION_KEYS_VAR) {
+        file.get(ENCRYPTION_KEYS_VAR, &SecretKey::resolve(&config.project.name)?)?
+    } else {
+        None
+    };
+    let secs = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let id = format!("k{}", secs);
+    let mut keys = format!("{}:{}", id, FieldCipher::generate_key());
+    if let Some(old) = existing.as_deref().map(str::trim).filter(|k| !k.is_empty()) {
+        keys.push(',');
+        keys.push_str(old);
+    }
+    if dryrun::enabled() {
+        dryrun::plan(
+            Planned::new(ActionKind::WriteFile, path.display().to_string())
+                .with_detail(format!("add key {} in front of {}", id, ENCRYPTION_KEYS_VAR)),
+        );
+        return Ok(keys);
+    }
+    file.set(ENCRYPTION_KEYS_VAR, &keys)?;
+    file.save(path)?;
+    reporter().info(format!("{} Added key {} to {}", style("✔").green(), style(&id).cyan(), path.display()));
+    if std::env::var_os(ENCRYPTION_KEYS_VAR).is_some() {
+        reporter().warn(format!("{} is set in the environment and overrides the secrets file", ENCRYPTION_KEYS_VAR));
+    }
+    Ok(keys)
+}
+
+/// `(table, column)` of every `#[orm(encrypted)]` field of an
+/// `EncryptedModel` under `root`.
+fn extract_encrypted_columns(root: &Path) -> Result<Vec<(String, String)>> {
+    let mut visitor = ModelVisitor { found: Vec::new() };
+    for path in rust_sources(root) {
+        let source = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        if let Ok(file) = syn::parse_file(&source) {
+            visitor.visit_file(&file);
+        }
+    }
+    visitor.found.sort();
+    visitor.found.dedup();
+    Ok(visitor.found)
+}
+
+struct ModelVisitor {
+    found: Vec<(String, String)>,
+}
+
+impl<'ast> Visit<'ast> for ModelVisitor {
+    fn visit_item_struct(&mut self, item: &'ast syn::ItemStruct) {
+        let derives = item.attrs.iter().any(|a| {
+            a.path().is_ident("derive")
+                && a.meta.require_list().is_ok_and(|l| l.tokens.to_string().contains("EncryptedModel"))
+        });
+        if derives {
+            // The same defaults as `#[derive(EncryptedModel)]`.
+            let mut table = snake_case(&item.ident.to_string());
+            for attr in item.attrs.iter().filter(|a| a.path().is_ident("orm")) {
+                let _ = attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("table") {
+                        table = meta.value()?.parse::<syn::LitStr>()?.value();
+                    } else if meta.input.peek(syn::Token![=]) {
+                        // e.g. `retain = "90d"` from `#[derive(Retained)]`.
+                        meta.value()?.parse::<syn::Expr>()?;
+                    }
+                    Ok(())
+                });
+            }
+            for field in &item.fields {
+                let Some(ident) = &field.ident else { continue };
+                let (mut encrypted, mut column) = (false, ident.to_string());
+                for attr in field.attrs.iter().filter(|a| a.path().is_ident("orm")) {
+                    let _ = attr.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("encrypted") {
+                            encrypted = true;
+                        } else if meta.path.is_ident("column") {
+                            column = meta.value()?.parse::<syn::LitStr>()?.value();
+                        }
+                        Ok(())
+                    });
+                }
+                if encrypted {
+                    self.found.push((table.clone(), column));
+                }
+            }
+        }
+        syn::visit::visit_item_struct(self, item);
+    }
+}
+
+fn snake_case(ident: &str) -> String {
+    let mut name = String::new();
+    for (i, c) in ident.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            name.push('_');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    name
+}
+
+/// A SQL literal found in the sources.
+struct SqlLiteral {
+    file: PathBuf,
+    sql: String,
+    /// Number of parameters passed with it, when it can be told.
+    params: Option<usize>,
+    /// Line and column of the first character inside the quotes.
+    line: usize,
+    column: usize,
+}
+
+impl SqlLiteral {
+    /// Source position of a byte offset into the SQL, 1-based.
+    fn position(&self, offset: Option<usize>) -> (usize, usize) {
+        let before = &self.sql[..offset.unwrap_or(0).min(self.sql.len())];
+        match before.rfind('\n') {
+            Some(nl) => (self.line + before.matches('\n').count(), before[nl + 1..].chars().count() + 1),
+            None => (self.line, self.column + before.chars().count()),
+        }
+    }
+}
+
+/// The `.rs` files under `root`, honouring `.gitignore` and skipping `target`.
+fn rust_sources(root: &Path) -> impl Iterator<Item = PathBuf> {
+    ignore::WalkBuilder::new(root).build().flatten().map(|e| e.into_path()).filter(|path| {
+        path.extension().is_some_and(|e| e == "rs") && !path.components().any(|c| c.as_os_str() == "target")
+    })
+}
+
+fn extract_queries(root: &Path) -> Result<Vec<SqlLiteral>> {
+    let mut queries = Vec::new();
+    for path in rust_sources(root) {
+        let source = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let Ok(file) = syn::parse_file(&source) else { continue };
+        let mut visitor = QueryVisitor { file: path.strip_prefix(root).unwrap_or(&path).to_path_buf(), found: Vec::new() };
+        visitor.visit_file(&file);
+        queries.extend(visitor.found);
+    }
+    Ok(queries)
+}
+
+struct QueryVisitor {
+    file: PathBuf,
+    found: Vec<SqlLiteral>,
+}
+
+impl QueryVisitor {
+    fn record(&mut self, lit: &syn::LitStr, params: Option<usize>) {
+        let start = lit.span().start();
+        // Skip the opening quote, or `r#"` for raw strings.
+        let prefix = lit.token().to_string().find('"').map_or(1, |n| n + 1);
+        if self.found.iter().any(|q| q.line == start.line && q.column == start.column + prefix + 1 && q.file == self.file) {
+            return;
+        }
+        self.found.push(SqlLiteral {
+            file: self.file.clone(),
+            sql: lit.value(),
+            params,
+            line: start.line,
+            column: start.column + prefix + 1,
+        });
+    }
+}
+
+impl<'ast> Visit<'ast> for QueryVisitor {
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        if call.method == "bind" {
+            // `query("...").bind(a).bind(b)`: the outermost bind sees them all.
+            let mut binds = 0;
+            let mut current = call;
+            while current.method == "bind" {
+                binds += 1;
+                match &*current.receiver {
+                    syn::Expr::MethodCall(inner) => current = inner,
+                    _ => break,
+                }
+            }
+            if current.args.len() == 1
+                && let Some(lit) = sql_literal(current)
+            {
+                self.record(lit, Some(binds));
+            }
+        } else if let Some(lit) = sql_literal(call) {
+            let params = match call.args.iter().nth(1) {
+                None => Some(0),
+                Some(arg) => param_count(arg),
+            };
+            self.record(lit, params);
+        }
+        syn::visit::visit_expr_method_call(self, call);
+    }
+}
+
+/// The SQL string a query method is called with, if any.
+fn sql_literal(call: &syn::ExprMethodCall) -> Option<&syn::LitStr> {
+    if !QUERY_METHODS.iter().any(|m| call.method == m) {
+        return None;
+    }
+    let syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(lit), .. }) = call.args.first()? else {
+        return None;
+    };
+    let sql = lit.value();
+    let verb = sql.split_whitespace().next()?.to_lowercase();
+    SQL_VERBS.contains(&verb.as_str()).then_some(lit)
+}
+
+/// The length of a `&[a, b]` parameter slice.
+fn param_count(arg: &syn::Expr) -> Option<usize> {
+    match arg {
+        syn::Expr::Reference(r) => param_count(&r.expr),
+        syn::Expr::Array(array) => Some(array.elems.len()),
+        _ => None,
+    }
+}
@@ -0,0 +1,80 @@
+//! Demo command.
+//!
+//! Serves the app with demo data and opens it in the browser. The seed files
+//! in `[demo] seeds` are checked first, then `MONTRS_SEED_DIR` points the app
+//! at them: `montrs_orm::Seeds::from_env` applies them at boot. The browser
+//! opens on `[demo] path` once the site answers.
+
+use crate::config::{MontrsConfig, ProjectConfig};
+use crate::report::reporter;
+use anyhow::{Result, bail};
+use montrs_orm::Seeds;
+use montrs_orm::seed::SEED_DIR_VAR;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How long to wait for the site before giving up on the browser.
+const READY_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// `seeds` overrides `[demo] seeds`; `project` carries the global flags.
+pub async fn run(seeds: Option<String>, no_open: bool, project: ProjectConfig) -> Result<()> {
+    let config = MontrsConfig::load()?;
+    let dir = PathBuf::from(seeds.unwrap_or_else(|| config.demo.seeds.clone()));
+
+    let mut step = reporter().step("demo data");
+    if !dir.is_dir() {
+        step.fail();
+        bail!("No demo data in {}. Add seed files there, or point [demo] seeds at them.", dir.display());
+    }
+    let loaded = match Seeds::load_dir(&dir) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            step.fail();
+            return Err(e.into());
+        }
+    };
+    step.set_detail(format!("{} rows in {} tables from {}", loaded.len(), loaded.tables.len(), dir.display()));
+    step.finish();
+    unsafe {
+        std::env::set_var(SEED_DIR_VAR, dir.canonicalize()?);
+    }
+
+    if config.demo.open && !no_open {
+        let url = format!("{}{}", super::perf::site_url(&config).trim_end_matches('/'), config.demo.path);
+        tokio::spawn(open_when_ready(url));
+    }
+    super::serve::run(None, false, false, false, project).await
+}
+
+/// Waits until `url` answers, then opens it.
+async fn open_when_ready(url: String) {
+    let started = Instant::now();
+    while reqwest::get(&url).await.is_err() {
+        if started.elapsed() > READY_TIMEOUT {
+            reporter().warn(format!("{} did not answer in {}s; open it yourself", url, READY_TIMEOUT.as_secs()));
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    reporter().info(format!("Opening {}", url));
+    if let Err(e) = open_browser(&url) {
+        reporter().warn(format!("Could not open a browser ({}); visit {}", e, url));
+    }
+}
+
+fn open_browser(url: &str) -> std::io::Result<()> {
+    let mut cmd = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(windows) {
+        let mut cmd = std::process::Command::new("cmd");
+        cmd.args(["/C", "start", ""]);
+        cmd
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    let status = cmd.arg(url).status()?;
+    if !status.success() {
+        return Err(std::io::Error::other(format!("exited with {}", status)));
+    }
+    Ok(())
+}
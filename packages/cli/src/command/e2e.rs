@@ -4,25 +4,104 @@
 //! 1. Building the application.
 //! 2. Starting the backend server.
 //! 3. Running the E2E test suite against the running server.
+//! 4. Optionally auditing route performance while the server is still up.
+//!
+//! The server runs with `MONTRS_E2E=1`, and the loaders listed under
+//! `[e2e.fixtures]` answer from their fixture files instead of live data.
+//!
+//! With `--shard i/n`, only every n-th test file of the e2e package runs, on a
+//! server with its own port, and the results are written as a JUnit report.
+//! `--merge-reports` combines the reports of all shards into one.
 //!
 //! It delegates the heavy lifting to `cargo-leptos` but ensures the
 //! MontRS configuration is correctly mapped.
 
+use super::test::{TestCase, TestStatus, TestSuite, generate_junit_report, read_junit_report};
 use crate::config::MontrsConfig;
+use crate::report::reporter;
 use crate::utils::run_cargo_leptos;
+use anyhow::{Context, bail};
+use console::style;
+use montrs_core::Mocks;
+use montrs_core::mock::{E2E_FIXTURES_VAR, E2E_VAR};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::mpsc;
+
+/// Where shard reports go unless `--junit` says otherwise.
+pub const REPORTS_DIR: &str = "target/montrs/e2e";
+
+/// Options of `montrs e2e`.
+#[derive(Debug, Default)]
+pub struct E2eOptions {
+    pub headless: bool,
+    pub keep_alive: bool,
+    pub browser: Option<String>,
+    pub perf: bool,
+    /// Run only this part of the suite.
+    pub shard: Option<Shard>,
+    /// Write the results as a JUnit report.
+    pub junit: Option<PathBuf>,
+    /// Merge the JUnit reports in this directory instead of running tests.
+    pub merge_reports: Option<PathBuf>,
+    /// Test command to run and record; used by the wrapped e2e command.
+    pub record: Option<String>,
+}
+
+/// One part of a suite split across machines: `index` of `count`, from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl std::str::FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, count) = s.split_once('/').ok_or_else(|| format!("expected i/n, got '{}'", s))?;
+        let index: usize = index.trim().parse().map_err(|_| format!("invalid shard index '{}'", index))?;
+        let count: usize = count.trim().parse().map_err(|_| format!("invalid shard count '{}'", count))?;
+        if count == 0 || index == 0 || index > count {
+            return Err(format!("shard {}/{} is out of range; use 1/{} to {}/{}", index, count, count, count, count));
+        }
+        Ok(Self { index, count })
+    }
+}
+
+impl Shard {
+    /// The files of this shard: every `count`-th file, in name order.
+    pub fn select<'a>(&self, files: &'a [String]) -> Vec<&'a String> {
+        files.iter().skip(self.index - 1).step_by(self.count).collect()
+    }
+}
 
 /// Executes the E2E tests.
-pub async fn run(headless: bool, keep_alive: bool, browser: Option<String>) -> anyhow::Result<()> {
+pub async fn run(opts: E2eOptions) -> anyhow::Result<()> {
+    if let Some(dir) = &opts.merge_reports {
+        let output = opts.junit.clone().unwrap_or_else(|| Path::new(REPORTS_DIR).join("junit.xml"));
+        return merge_reports(dir, &output);
+    }
+    if let Some(cmd) = &opts.record {
+        let output = opts.junit.as_deref().context("--junit is required to record a test run")?;
+        return record(cmd, output).await;
+    }
+
     let config = MontrsConfig::load()?;
 
     // Determine final configuration (CLI > Config > Default)
-    let final_headless = headless || config.e2e.headless.unwrap_or(false);
-    let final_browser = browser.or(config.e2e.browser.clone()).unwrap_or_else(|| "chromium".to_string());
+    let final_headless = opts.headless || config.e2e.headless.unwrap_or(false);
+    let final_browser = opts.browser.clone().or(config.e2e.browser.clone()).unwrap_or_else(|| "chromium".to_string());
+
+    pin_fixtures(&config.e2e.fixtures)?;
 
     // Set environment variables for runtime configuration
     unsafe {
+        std::env::set_var(E2E_VAR, "1");
         std::env::set_var("MONTRS_E2E_HEADLESS", final_headless.to_string());
-        if keep_alive {
+        if opts.keep_alive {
             std::env::set_var("MONTRS_E2E_KEEP_ALIVE", "true");
         }
         std::env::set_var("MONTRS_E2E_BROWSER", final_browser);
@@ -38,6 +117,30 @@ pub async fn run(headless: bool, keep_alive: bool, browser: Option<String>) -> a
         if std::env::var("LEPTOS_END2END_DIR").is_err() {
             std::env::set_var("LEPTOS_END2END_DIR", "e2e");
         }
+
+        if let Some(shard) = opts.shard {
+            let Some(cmd) = shard_command(shard, &config)? else {
+                reporter().stream_step(format!("end-to-end (shard {}/{})", shard.index, shard.count)).skip("no test files");
+                return Ok(());
+            };
+            std::env::set_var("LEPTOS_END2END_CMD", cmd);
+        }
+
+        let junit = opts.junit.clone().or_else(|| {
+            opts.shard
+                .map(|s| Path::new(REPORTS_DIR).join(format!("junit-{}-of-{}.xml", s.index, s.count)))
+        });
+        if let Some(junit) = junit {
+            let e2e_cmd = std::env::var("LEPTOS_END2END_CMD")?;
+            std::env::set_var("LEPTOS_END2END_CMD", wrap_record_command(&e2e_cmd, &junit)?);
+        }
+
+        // cargo-leptos stops the server once the e2e command exits, so the audit
+        // runs inside that command, after the tests.
+        if opts.perf || config.e2e.perf.enabled {
+            let e2e_cmd = std::env::var("LEPTOS_END2END_CMD")?;
+            std::env::set_var("LEPTOS_END2END_CMD", super::perf::wrap_e2e_command(&e2e_cmd)?);
+        }
     }
 
     // We use "end-to-end" command of cargo-leptos.
@@ -46,5 +149,262 @@ pub async fn run(headless: bool, keep_alive: bool, browser: Option<String>) -> a
     
     // Ensure we pass necessary flags via args if supported, or rely on env vars set above.
     
-    run_cargo_leptos("end-to-end", &[], &config).await
+    let step = match opts.shard {
+        Some(shard) => reporter().stream_step(format!("end-to-end (shard {}/{})", shard.index, shard.count)),
+        None => reporter().stream_step("end-to-end"),
+    };
+    run_cargo_leptos("end-to-end", &[], &config).await?;
+    step.finish();
+    Ok(())
+}
+
+/// Checks that every fixture file parses, then hands the absolute paths to
+/// the server; `AppSpec::new` installs them in the server process.
+fn pin_fixtures(fixtures: &HashMap<String, String>) -> anyhow::Result<()> {
+    if fixtures.is_empty() {
+        return Ok(());
+    }
+    let cwd = std::env::current_dir()?;
+    let resolved: HashMap<String, PathBuf> =
+        fixtures.iter().map(|(route, path)| (route.clone(), cwd.join(path))).collect();
+    Mocks::load_fixtures(&resolved)?;
+
+    reporter().info(format!("{} Pinning {} route(s) to fixtures:", style("✔").green(), resolved.len()));
+    let mut routes: Vec<_> = fixtures.iter().collect();
+    routes.sort();
+    for (route, path) in routes {
+        reporter().info(format!("    {} -> {}", route, path));
+    }
+    unsafe {
+        std::env::set_var(E2E_FIXTURES_VAR, serde_json::to_string(&resolved)?);
+    }
+    Ok(())
+}
+
+/// Test targets of the e2e package: the file stems under `<dir>/tests`,
+/// sorted so that every shard sees the same order.
+fn discover_test_files(e2e_dir: &Path) -> Vec<String> {
+    let mut files: Vec<String> = std::fs::read_dir(e2e_dir.join("tests"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rs"))
+        .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Restricts the e2e command to the shard's test files and moves its server
+/// to a port of its own, so shards can also run side by side on one machine.
+/// Returns `None` when the shard has no files.
+fn shard_command(shard: Shard, config: &MontrsConfig) -> anyhow::Result<Option<String>> {
+    let dir = std::env::var("LEPTOS_END2END_DIR")?;
+    let files = discover_test_files(Path::new(&dir));
+    if files.is_empty() {
+        bail!("No e2e test files found in {}/tests", dir);
+    }
+    let selected = shard.select(&files);
+    if selected.is_empty() {
+        return Ok(None);
+    }
+    reporter().info(format!(
+        "{} Shard {}/{}: {} of {} test file(s)",
+        style("✔").green(),
+        shard.index,
+        shard.count,
+        selected.len(),
+        files.len()
+    ));
+
+    let mut cmd = std::env::var("LEPTOS_END2END_CMD")?;
+    for file in selected {
+        cmd.push_str(" --test ");
+        cmd.push_str(&shlex::try_quote(file).context("test file name cannot be quoted")?);
+    }
+
+    let port = config.serve.port + shard.index as u16;
+    let addr = format!("{}:{}", config.serve.addr, port);
+    unsafe {
+        std::env::set_var("LEPTOS_SITE_ADDR", &addr);
+        std::env::set_var("LEPTOS_RELOAD_PORT", (port + 1000).to_string());
+        std::env::set_var("MONTRS_SITE_URL", format!("http://{}", addr));
+    }
+    Ok(Some(cmd))
+}
+
+/// Runs `e2e_cmd` through `montrs e2e --record`, which writes the JUnit report.
+fn wrap_record_command(e2e_cmd: &str, junit: &Path) -> anyhow::Result<String> {
+    let exe = std::env::current_exe().context("Cannot locate the montrs binary")?;
+    let exe = exe.to_string_lossy();
+    // cargo-leptos runs the command in the e2e directory.
+    let junit = std::env::current_dir()?.join(junit);
+    let junit = junit.to_string_lossy();
+    Ok(format!(
+        "{} e2e --record {} --junit {}",
+        shlex::try_quote(&exe).context("montrs path cannot be quoted")?,
+        shlex::try_quote(e2e_cmd).context("e2e command cannot be quoted")?,
+        shlex::try_quote(&junit).context("report path cannot be quoted")?
+    ))
+}
+
+/// Runs the test command, passing its output through, and writes the test
+/// results it prints to `output` as JUnit. Fails when the command fails.
+async fn record(cmd: &str, output: &Path) -> anyhow::Result<()> {
+    #[cfg(windows)]
+    let mut command = tokio::process::Command::new("powershell");
+    #[cfg(windows)]
+    command.arg("-Command");
+    #[cfg(not(windows))]
+    let mut command = tokio::process::Command::new("sh");
+    #[cfg(not(windows))]
+    command.arg("-c");
+
+    let mut child = command
+        .arg(cmd)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run '{}'", cmd))?;
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    forward(child.stdout.take(), tx.clone(), false);
+    forward(child.stderr.take(), tx, true);
+    let mut parser = LibtestParser::default();
+    while let Some(line) = rx.recv().await {
+        parser.feed(&line);
+    }
+    let status = child.wait().await?;
+
+    let suites = parser.finish();
+    let output = output.to_string_lossy();
+    generate_junit_report(&suites, &output)?;
+    reporter().info(format!("JUnit report generated at {}", output));
+    if !status.success() {
+        bail!("E2E tests failed");
+    }
+    Ok(())
+}
+
+fn forward(stream: Option<impl AsyncRead + Unpin + Send + 'static>, tx: mpsc::UnboundedSender<String>, stderr: bool) {
+    let Some(stream) = stream else { return };
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stream).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if stderr {
+                eprintln!("{}", line);
+            } else {
+                println!("{}", line);
+            }
+            let _ = tx.send(line);
+        }
+    });
+}
+
+/// Collects test results from libtest's human output, which works on stable
+/// Rust unlike `--format json`.
+#[derive(Default)]
+struct LibtestParser {
+    suites: Vec<TestSuite>,
+    /// Test whose `---- name stdout ----` section is being read.
+    capturing: Option<(String, Vec<String>)>,
+}
+
+impl LibtestParser {
+    fn feed(&mut self, line: &str) {
+        let trimmed = line.trim();
+        // cargo announces each test binary: "Running tests/login.rs (target/...)".
+        if let Some(target) = trimmed.strip_prefix("Running ") {
+            let name = target.split(" (").next().unwrap_or(target);
+            self.suites.push(TestSuite { name: name.to_string(), tests: Vec::new() });
+            return;
+        }
+        if let Some(name) = trimmed.strip_prefix("---- ").and_then(|rest| rest.strip_suffix(" stdout ----")) {
+            self.flush_capture();
+            self.capturing = Some((name.to_string(), Vec::new()));
+            return;
+        }
+        if trimmed == "failures:" || trimmed.starts_with("test result:") {
+            self.flush_capture();
+        }
+        if let Some((_, lines)) = &mut self.capturing {
+            lines.push(line.to_string());
+            return;
+        }
+
+        let Some(rest) = trimmed.strip_prefix("test ") else { return };
+        let Some((name, outcome)) = rest.rsplit_once(" ... ") else { return };
+        let status = match outcome {
+            "ok" => TestStatus::Pass,
+            "FAILED" => TestStatus::Fail,
+            o if o.starts_with("ignored") => TestStatus::Ignored,
+            _ => return,
+        };
+        if self.suites.is_empty() {
+            self.suites.push(TestSuite::default());
+        }
+        self.suites.last_mut().unwrap().tests.push(TestCase {
+            name: name.to_string(),
+            status,
+            message: None,
+            duration: 0.0,
+        });
+    }
+
+    fn flush_capture(&mut self) {
+        let Some((name, lines)) = self.capturing.take() else { return };
+        let message = lines.join("\n").trim().to_string();
+        let case = self
+            .suites
+            .iter_mut()
+            .rev()
+            .flat_map(|s| s.tests.iter_mut())
+            .find(|t| t.name == name && matches!(t.status, TestStatus::Fail));
+        if let Some(case) = case {
+            case.message = Some(message);
+        }
+    }
+
+    fn finish(mut self) -> Vec<TestSuite> {
+        self.flush_capture();
+        self.suites.retain(|s| !s.tests.is_empty());
+        self.suites
+    }
+}
+
+/// Combines the JUnit reports in `dir` into one report at `output`.
+fn merge_reports(dir: &Path, output: &Path) -> anyhow::Result<()> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "xml"))
+        .filter(|path| path.file_name() != output.file_name())
+        .collect();
+    files.sort();
+    if files.is_empty() {
+        bail!("No JUnit reports found in {}", dir.display());
+    }
+
+    let mut suites = Vec::new();
+    for file in &files {
+        suites.extend(read_junit_report(file).with_context(|| format!("Failed to read {}", file.display()))?);
+    }
+    let tests: usize = suites.iter().map(|s| s.tests.len()).sum();
+    let failures: usize = suites
+        .iter()
+        .flat_map(|s| &s.tests)
+        .filter(|t| matches!(t.status, TestStatus::Fail))
+        .count();
+    generate_junit_report(&suites, &output.to_string_lossy())?;
+    reporter().info(format!(
+        "{} Merged {} report(s): {} test(s), {} failure(s) -> {}",
+        style("✔").green(),
+        files.len(),
+        tests,
+        failures,
+        output.display()
+    ));
+    Ok(())
 }
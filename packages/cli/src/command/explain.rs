@@ -0,0 +1,82 @@
+//! Explain command.
+//!
+//! Sends a tracked error, with its code, package invariants and related docs, to
+//! the language model configured by the `MONTRS_LLM_*` variables. The answer is
+//! stored on the error record, so repeated calls print it without a new request.
+
+use crate::config::MontrsConfig;
+use anyhow::Result;
+use console::style;
+use montrs_agent::AgentManager;
+use montrs_agent::llm::{LlmProvider, provider_from_env};
+use montrs_core::secrets::SecretsEnv;
+use montrs_core::{EnvChain, TypedEnv};
+
+/// The provider configured by `MONTRS_LLM_*`. Keys may live in the encrypted
+/// secrets file; the shell environment wins.
+pub(crate) fn llm_provider(config: &MontrsConfig) -> Result<Box<dyn LlmProvider>> {
+    let mut env = EnvChain::new().with(TypedEnv {});
+    match SecretsEnv::load(&config.secrets.file, &config.project.name) {
+        Ok(secrets) => env = env.with(secrets),
+        Err(e) => eprintln!("{} Secrets not loaded: {}", style("⚠").yellow(), e),
+    }
+    Ok(provider_from_env(&env)?)
+}
+
+pub async fn run(id: String, refresh: bool, config: &MontrsConfig) -> Result<()> {
+    let cwd = std::env::current_dir()?;
+    let manager = AgentManager::new(&cwd);
+    let context = manager.explain_context(&id)?;
+
+    let cached = context
+        .record
+        .detail
+        .agent_metadata
+        .as_ref()
+        .and_then(|m| m.model_explanation.clone())
+        .filter(|_| !refresh);
+    let explanation = match cached {
+        Some(explanation) => explanation,
+        None => {
+            let provider = llm_provider(config)?;
+            println!(
+                "{} Asking {} about {} ({} docs, {} invariants files)...",
+                style("→").cyan(),
+                provider.name(),
+                id,
+                context.docs.len(),
+                context.invariants.len()
+            );
+            let explanation = montrs_agent::explain::explain(provider.as_ref(), &context).await?;
+            manager.record_explanation(&id, explanation.clone())?;
+            explanation
+        }
+    };
+
+    let detail = &context.record.detail;
+    println!(
+        "\n{} {} at {}:{}",
+        style(&detail.level).red().bold(),
+        detail.message,
+        detail.file,
+        detail.line
+    );
+    println!("\n{}\n", explanation.explanation);
+    if !explanation.suggested_fixes.is_empty() {
+        println!("{}", style("Suggested fixes:").bold());
+        for (i, fix) in explanation.suggested_fixes.iter().enumerate() {
+            println!("  {}. {}", i + 1, fix);
+        }
+    }
+    println!(
+        "\n{}",
+        style(format!(
+            "Explained by {} on {}{}",
+            explanation.model,
+            explanation.generated_at.format("%Y-%m-%d %H:%M UTC"),
+            if refresh { "" } else { "; pass --refresh to ask again" }
+        ))
+        .dim()
+    );
+    Ok(())
+}
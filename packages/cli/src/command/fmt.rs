@@ -67,8 +67,8 @@ fn format_one_file(
             println!("{} {} is not formatted", "✘".red(), path.display());
             return Ok(true);
         } else {
-            std::fs::write(path, formatted)?;
-            if verbose {
+            crate::dryrun::write_with(path, formatted, "reformat")?;
+            if verbose && !crate::dryrun::enabled() {
                 println!("{} Formatted {}", "✓".green(), path.display());
             }
         }
@@ -1,13 +1,15 @@
 use anyhow::{Result, anyhow};
 use console::style;
+use crate::dryrun;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use montrs_utils::{to_pascal_case, to_snake_case};
 
-pub async fn plate(name: String) -> Result<()> {
+pub async fn plate(name: String, no_tests: bool) -> Result<()> {
     let name_pascal = to_pascal_case(&name);
     let name_snake = to_snake_case(&name);
-    
+    let layout = if no_tests { None } else { Some(TestLayout::detect()?) };
+
     println!(
         "{} Generating plate: {}",
         style("🔨").bold(),
@@ -39,24 +41,39 @@ impl<C: AppConfig> Plate<C> for {name_pascal}Plate {{
     }}
 }}
 "#);
+    let content = match &layout {
+        Some(layout) => layout.attach_test_modules(content),
+        None => content,
+    };
 
     let dir = Path::new("src/plates");
-    if !dir.exists() {
-        fs::create_dir_all(dir)?;
-    }
+    dryrun::create_dir_all(dir)?;
 
     let file_path = dir.join(format!("{}.rs", name_snake));
     if file_path.exists() {
         return Err(anyhow!("Plate file already exists: {:?}", file_path));
     }
 
-    fs::write(&file_path, content)?;
+    dryrun::write(&file_path, content)?;
+    if !dryrun::enabled() {
+        println!(
+            "{} Created plate at: {}",
+            style("✨").green().bold(),
+            style(file_path.display()).underlined()
+        );
+    }
+
+    if let Some(layout) = &layout {
+        layout.wire_dependencies()?;
+        let module = format!("plates::{}", name_snake);
+        let tests_dir = dir.join(&name_snake);
+        write_file(&tests_dir.join("tests.rs"), plate_unit_test(&name_pascal, &name_snake))?;
+        layout.write_integration(&tests_dir, &format!("{}_plate", name_snake), |krate| {
+            plate_integration_test(krate, &module, &name_pascal)
+        })?;
+        layout.write_e2e(&name_snake, || e2e_stub(&name_snake, &format!("/{}", name_snake)))?;
+    }
 
-    println!(
-        "{} Created plate at: {}",
-        style("✨").green().bold(),
-        style(file_path.display()).underlined()
-    );
     println!(
         "Next steps:\n  1. Add `pub mod {};` to `src/plates/mod.rs`\n  2. Register the plate in `src/main.rs` using `.with_plate(Box::new({}Plate))`",
         name_snake, name_pascal
@@ -65,11 +82,12 @@ impl<C: AppConfig> Plate<C> for {name_pascal}Plate {{
     Ok(())
 }
 
-pub async fn route(path: String, plate: String) -> Result<()> {
+pub async fn route(path: String, plate: String, no_tests: bool) -> Result<()> {
     let plate_snake = to_snake_case(&plate);
     let route_name = path.replace('/', "_").replace(':', "").trim_matches('_').to_string();
     let route_name_pascal = if route_name.is_empty() { "Index".to_string() } else { to_pascal_case(&route_name) };
-    
+    let layout = if no_tests { None } else { Some(TestLayout::detect()?) };
+
     println!(
         "{} Generated route {} for plate {}",
         style("🛣️").bold(),
@@ -127,32 +145,424 @@ impl<C: AppConfig> Route<C> for {route_name_pascal}Route {{
     fn view(&self) -> Self::View {{ {route_name_pascal}View }}
 }}
 "#);
+    let content = match &layout {
+        Some(layout) => layout.attach_test_modules(content),
+        None => content,
+    };
 
     let dir = Path::new("src/plates").join(&plate_snake).join("routes");
-    if !dir.exists() {
-        fs::create_dir_all(&dir)?;
-    }
+    dryrun::create_dir_all(&dir)?;
 
-    let file_name = format!("{}.rs", if route_name.is_empty() { "index".to_string() } else { route_name.to_lowercase() });
+    let module_name = if route_name.is_empty() { "index".to_string() } else { route_name.to_lowercase() };
+    let file_name = format!("{}.rs", module_name);
     let file_path = dir.join(&file_name);
     
     if file_path.exists() {
         return Err(anyhow!("Route file already exists: {:?}", file_path));
     }
 
-    fs::write(&file_path, content)?;
+    dryrun::write(&file_path, content)?;
+    if !dryrun::enabled() {
+        println!(
+            "{} Created route at: {}",
+            style("✨").green().bold(),
+            style(file_path.display()).underlined()
+        );
+    }
+
+    if let Some(layout) = &layout {
+        layout.wire_dependencies()?;
+        let module = format!("plates::{}::routes::{}", plate_snake, module_name);
+        let tests_dir = dir.join(&module_name);
+        write_file(&tests_dir.join("tests.rs"), route_unit_test(&route_name_pascal, &path))?;
+        layout.write_integration(&tests_dir, &format!("{}_{}_route", plate_snake, module_name), |krate| {
+            route_integration_test(krate, &module, &route_name_pascal, &path)
+        })?;
+        layout.write_e2e(&format!("{}_{}", plate_snake, module_name), || e2e_stub(&module_name, &path))?;
+    }
 
     println!(
-        "{} Created route at: {}",
-        style("✨").green().bold(),
-        style(file_path.display()).underlined()
+        "Next steps:\n  1. Add `pub mod {};` to `src/plates/{}/mod.rs`\n  2. Register the route in `{}Plate::register_routes`",
+        module_name, plate_snake, to_pascal_case(&plate)
     );
+
+    Ok(())
+}
+
+pub async fn model(name: String, no_tests: bool) -> Result<()> {
+    let name_pascal = to_pascal_case(&name);
+    let name_snake = to_snake_case(&name);
+    let layout = if no_tests { None } else { Some(TestLayout::detect()?) };
+
     println!(
-        "Next steps:\n  1. Add `pub mod {};` to `src/plates/{}/mod.rs`\n  2. Register the route in `{}Plate::register_routes`",
-        if route_name.is_empty() { "index".to_string() } else { route_name.to_lowercase() }, plate_snake, to_pascal_case(&plate)
+        "{} Generating model: {}",
+        style("🔨").bold(),
+        style(&name_pascal).cyan().bold()
     );
 
+    let content = format!(r#"use montrs_schema::Schema;
+use serde::{{Deserialize, Serialize}};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Schema)]
+pub struct {name_pascal} {{
+    pub id: i32,
+    #[schema(min_len = 1)]
+    pub name: String,
+}}
+
+impl {name_pascal} {{
+    /// The table backing this model.
+    pub const TABLE: &'static str = "{name_snake}s";
+
+    pub fn new(id: i32, name: impl Into<String>) -> Self {{
+        Self {{ id, name: name.into() }}
+    }}
+}}
+"#);
+    let content = match &layout {
+        Some(layout) => layout.attach_test_modules(content),
+        None => content,
+    };
+
+    let dir = Path::new("src/models");
+    dryrun::create_dir_all(dir)?;
+
+    let file_path = dir.join(format!("{}.rs", name_snake));
+    if file_path.exists() {
+        return Err(anyhow!("Model file already exists: {:?}", file_path));
+    }
+
+    dryrun::write(&file_path, content)?;
+    add_dependency(Path::new("Cargo.toml"), "dependencies", "montrs-schema", None)?;
+    add_dependency(Path::new("Cargo.toml"), "dependencies", "serde", Some(r#"{ version = "1", features = ["derive"] }"#))?;
+
+    if !dryrun::enabled() {
+        println!(
+            "{} Created model at: {}",
+            style("✨").green().bold(),
+            style(file_path.display()).underlined()
+        );
+    }
+
+    if let Some(layout) = &layout {
+        layout.wire_dependencies()?;
+        add_dependency(Path::new("Cargo.toml"), "dev-dependencies", "async-trait", Some(r#""0.1""#))?;
+        add_dependency(Path::new("Cargo.toml"), "dev-dependencies", "anyhow", Some(r#""1""#))?;
+        let module = format!("models::{}", name_snake);
+        let tests_dir = dir.join(&name_snake);
+        write_file(&tests_dir.join("tests.rs"), model_unit_test(&name_pascal))?;
+        layout.write_integration(&tests_dir, &format!("{}_model", name_snake), |krate| {
+            model_integration_test(krate, &module, &name_pascal)
+        })?;
+    }
+
+    println!(
+        "Next steps:\n  1. Add `pub mod {};` to `src/models/mod.rs`\n  2. Add your fields and `#[schema(...)]` rules to `{}`",
+        name_snake, name_pascal
+    );
+
+    Ok(())
+}
+
+/// Where generated test scaffolds go for the package in the current directory.
+///
+/// Packages with a `src/lib.rs` get integration tests under `tests/`. Binary-only
+/// packages cannot be imported from `tests/`, so their integration tests become
+/// `#[cfg(test)]` modules next to the generated code instead.
+struct TestLayout {
+    lib_crate: Option<String>,
+    e2e_dir: Option<PathBuf>,
+}
+
+impl TestLayout {
+    fn detect() -> Result<Self> {
+        let lib_crate = if Path::new("src/lib.rs").exists() {
+            let manifest: toml::Value = toml::from_str(&fs::read_to_string("Cargo.toml")?)?;
+            manifest
+                .get("lib")
+                .and_then(|l| l.get("name"))
+                .or_else(|| manifest.get("package").and_then(|p| p.get("name")))
+                .and_then(|n| n.as_str())
+                .map(|n| n.replace('-', "_"))
+        } else {
+            None
+        };
+        let e2e_dir = ["e2e", "../e2e"]
+            .iter()
+            .map(PathBuf::from)
+            .find(|d| d.join("Cargo.toml").exists());
+        Ok(Self { lib_crate, e2e_dir })
+    }
+
+    fn attach_test_modules(&self, mut content: String) -> String {
+        content.push_str("\n#[cfg(test)]\nmod tests;\n");
+        if self.lib_crate.is_none() {
+            content.push_str("\n#[cfg(test)]\nmod integration_tests;\n");
+        }
+        content
+    }
+
+    /// Adds the dev-dependencies the scaffolds rely on.
+    fn wire_dependencies(&self) -> Result<()> {
+        let manifest = Path::new("Cargo.toml");
+        add_dependency(manifest, "dev-dependencies", "montrs-test", None)?;
+        add_dependency(manifest, "dev-dependencies", "tokio", Some(r#"{ version = "1", features = ["macros", "rt-multi-thread"] }"#))?;
+        if let Some(dir) = &self.e2e_dir {
+            add_dependency(&dir.join("Cargo.toml"), "dependencies", "montrs-test", Some(&format!(
+                r#"{{ {}, features = ["e2e"] }}"#,
+                montrs_source(&fs::read_to_string(dir.join("Cargo.toml"))?, "montrs-test")
+            )))?;
+        }
+        Ok(())
+    }
+
+    fn write_integration(&self, module_dir: &Path, test_name: &str, render: impl FnOnce(&str) -> String) -> Result<()> {
+        match &self.lib_crate {
+            Some(krate) => write_file(&Path::new("tests").join(format!("{}.rs", test_name)), render(krate)),
+            None => write_file(&module_dir.join("integration_tests.rs"), render("crate")),
+        }
+    }
+
+    fn write_e2e(&self, name: &str, render: impl FnOnce() -> String) -> Result<()> {
+        match &self.e2e_dir {
+            Some(dir) => write_file(&dir.join("tests").join(format!("{}.rs", name)), render()),
+            None => {
+                println!(
+                    "{} No e2e package found; skipping the e2e stub.",
+                    style("⚠").yellow()
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+fn write_file(path: &Path, content: String) -> Result<()> {
+    if path.exists() {
+        return Err(anyhow!("Test file already exists: {:?}", path));
+    }
+    if let Some(parent) = path.parent() {
+        dryrun::create_dir_all(parent)?;
+    }
+    dryrun::write(path, content)?;
+    if !dryrun::enabled() {
+        println!(
+            "{} Created test at: {}",
+            style("🧪").bold(),
+            style(path.display()).underlined()
+        );
+    }
+    Ok(())
+}
+
+/// Adds `name = spec` to `[section]` unless the crate is already listed there
+/// or under `[dependencies]`. A `None` spec mirrors how `montrs-core` is pulled in.
+pub(crate) fn add_dependency(manifest: &Path, section: &str, name: &str, spec: Option<&str>) -> Result<()> {
+    if !manifest.exists() {
+        return Ok(());
+    }
+    let mut text = fs::read_to_string(manifest)?;
+    let parsed: toml::Value = toml::from_str(&text)?;
+    let listed = |table: &str| parsed.get(table).and_then(|t| t.get(name)).is_some();
+    if listed(section) || listed("dependencies") {
+        return Ok(());
+    }
+
+    let spec = match spec {
+        Some(spec) => spec.to_string(),
+        None => format!("{{ {} }}", montrs_source(&text, name)),
+    };
+    let entry = format!("{} = {}\n", name, spec);
+    let header = format!("[{}]", section);
+    match text.find(&header) {
+        Some(start) => {
+            let after_header = text[start..].find('\n').map(|i| start + i + 1).unwrap_or(text.len());
+            if after_header == text.len() && !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text.insert_str(after_header.min(text.len()), &entry);
+        }
+        None => {
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text.push_str(&format!("\n{}\n{}", header, entry));
+        }
+    }
+    dryrun::write_with(manifest, text, format!("add {} to [{}]", name, section))?;
     Ok(())
 }
 
+/// Returns `path = "..."` or `version = "..."` for a MontRS crate, following
+/// the source already used for `montrs-core` so path checkouts keep working.
+fn montrs_source(manifest: &str, name: &str) -> String {
+    let suffix = name.trim_start_matches("montrs-");
+    manifest
+        .lines()
+        .filter(|l| l.trim_start().starts_with("montrs-"))
+        .find_map(|l| {
+            let start = l.find("path")?;
+            let open = l[start..].find('"')? + start + 1;
+            let close = l[open..].find('"')? + open;
+            let base = l[open..close].rsplit_once('/')?.0;
+            Some(format!("path = \"{}/{}\"", base, suffix))
+        })
+        .unwrap_or_else(|| format!("version = \"{}\"", crate::command::upgrade::CLI_VERSION))
+}
+
+/// Replaces `:param` segments with a placeholder value so the path is navigable.
+fn sample_path(path: &str) -> String {
+    path.split('/')
+        .map(|seg| if seg.starts_with(':') || seg.starts_with('*') { "1" } else { seg })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn plate_unit_test(name_pascal: &str, name_snake: &str) -> String {
+    format!(r#"use super::*;
+use montrs_test::unit::expect;
+use montrs_test::TestConfig;
+
+#[test]
+fn has_a_stable_name() {{
+    expect(Plate::<TestConfig>::name(&{name_pascal}Plate)).to_equal("{name_snake}");
+}}
+
+#[test]
+fn declares_no_dependencies() {{
+    expect(Plate::<TestConfig>::dependencies(&{name_pascal}Plate)).to_have_length(0);
+}}
+"#)
+}
+
+fn plate_integration_test(krate: &str, module: &str, name_pascal: &str) -> String {
+    format!(r#"use {krate}::{module}::{name_pascal}Plate;
+use montrs_core::{{Plate, PlateContext}};
+use montrs_test::{{TestClient, TestConfig, TestEnv}};
+
+#[tokio::test]
+async fn initializes_and_registers_routes() {{
+    let env = TestEnv::new();
+    let mut ctx = PlateContext {{ config: &TestConfig, env: &env }};
+    {name_pascal}Plate.init(&mut ctx).await.expect("plate init failed");
+
+    let client = TestClient::new(TestConfig, env.clone()).with_plate(&{name_pascal}Plate);
+    // Exercise the plate's routes once they are registered:
+    // let out: String = client.load("/path", MyParams {{}}).await.unwrap();
+    let _ = client.router().spec();
+}}
+"#)
+}
+
+fn route_unit_test(route_pascal: &str, path: &str) -> String {
+    format!(r#"use super::*;
+use montrs_test::unit::expect;
+use montrs_test::{{TestConfig, TestEnv}};
+
+#[test]
+fn is_mounted_at_its_path() {{
+    expect(<{route_pascal}Route as Route<TestConfig>>::path()).to_equal("{path}");
+}}
+
+#[tokio::test]
+async fn loader_succeeds() {{
+    let env = TestEnv::new();
+    let ctx = RouteContext {{ config: &TestConfig, env: &env }};
+    let out = RouteLoader::<{route_pascal}Params, TestConfig>::load(&{route_pascal}Loader, ctx, {route_pascal}Params {{}}).await;
+    expect(out).to_be_ok();
+}}
+"#)
+}
 
+fn route_integration_test(krate: &str, module: &str, route_pascal: &str, path: &str) -> String {
+    format!(r#"use {krate}::{module}::{{{route_pascal}Params, {route_pascal}Route}};
+use montrs_test::unit::expect;
+use montrs_test::{{TestClient, TestConfig, TestEnv}};
+
+fn client() -> TestClient<TestConfig> {{
+    TestClient::new(TestConfig, TestEnv::new()).with_route({route_pascal}Route)
+}}
+
+#[tokio::test]
+async fn load_returns_data() {{
+    let out: String = client().load("{path}", {route_pascal}Params {{}}).await.unwrap();
+    expect(out.is_empty()).to_be_false();
+}}
+
+#[tokio::test]
+async fn action_accepts_input() {{
+    let out: String = client().act("{path}", {route_pascal}Params {{}}, "ping").await.unwrap();
+    expect(out.contains("ping")).to_be_true();
+}}
+"#)
+}
+
+fn model_unit_test(name_pascal: &str) -> String {
+    format!(r#"use super::*;
+use montrs_core::Validate;
+use montrs_test::unit::expect;
+
+#[test]
+fn valid_model_passes_validation() {{
+    expect({name_pascal}::new(1, "example").validate()).to_be_ok();
+}}
+
+#[test]
+fn empty_name_is_rejected() {{
+    expect({name_pascal}::new(1, "").validate()).to_be_err();
+}}
+"#)
+}
+
+fn model_integration_test(krate: &str, module: &str, name_pascal: &str) -> String {
+    format!(r#"use {krate}::{module}::{name_pascal};
+use async_trait::async_trait;
+use montrs_core::Validate;
+use montrs_test::{{Fixture, run_fixture_test}};
+
+/// Seed data for tests. Replace with a fixture that inserts rows into a test database.
+struct Seed;
+
+#[async_trait]
+impl Fixture for Seed {{
+    type Context = Vec<{name_pascal}>;
+
+    async fn setup(&self) -> anyhow::Result<Self::Context> {{
+        Ok(vec![{name_pascal}::new(1, "first"), {name_pascal}::new(2, "second")])
+    }}
+}}
+
+#[tokio::test]
+async fn seeded_rows_are_valid() -> anyhow::Result<()> {{
+    run_fixture_test(Seed, |rows| {{
+        let valid = rows.iter().all(|r| r.validate().is_ok());
+        async move {{
+            anyhow::ensure!(valid, "seed data failed validation");
+            Ok(())
+        }}
+    }})
+    .await
+}}
+"#)
+}
+
+fn e2e_stub(name: &str, path: &str) -> String {
+    let url = sample_path(path);
+    format!(r#"use montrs_test::e2e::MontrsDriver;
+
+/// Runs under `montrs e2e`, which starts the app and sets `MONTRS_E2E_BROWSER`.
+#[tokio::test]
+async fn {name}_page_loads() -> anyhow::Result<()> {{
+    if std::env::var("MONTRS_E2E_BROWSER").is_err() {{
+        eprintln!("skipping: run with `montrs e2e`");
+        return Ok(());
+    }}
+
+    let driver = MontrsDriver::new().await?;
+    driver.goto("{url}").await?;
+    // assertions::assert_title_contains(&driver.page, "...").await?;
+    driver.close().await?;
+    Ok(())
+}}
+"#)
+}
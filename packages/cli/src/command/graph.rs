@@ -0,0 +1,128 @@
+//! Graph command.
+//!
+//! Renders the package and plate dependency graph as DOT, Mermaid, or SVG.
+//! Package edges come from `cargo metadata` and plate edges from the agent snapshot.
+//! Cycles and unusually heavy nodes are highlighted. `--diff` compares the graph
+//! against an older snapshot file or git revision.
+
+use anyhow::{Context, Result};
+use cargo_metadata::MetadataCommand;
+use console::style;
+use montrs_agent::graph::{ArchitectureGraph, GraphFormat};
+use montrs_agent::{AgentManager, AgentSnapshot, PackageSummary};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub async fn run(
+    project_name: String,
+    format: String,
+    output: Option<PathBuf>,
+    diff: Option<String>,
+    to: Option<String>,
+) -> Result<()> {
+    let format: GraphFormat = format.parse().map_err(|e: String| anyhow::anyhow!(e))?;
+
+    let current = match &to {
+        Some(spec) => load_snapshot(spec)?,
+        None => current_snapshot(&project_name)?,
+    };
+    let graph = ArchitectureGraph::from_snapshot(&current);
+
+    let graph_diff = match &diff {
+        Some(spec) => {
+            let old = ArchitectureGraph::from_snapshot(&load_snapshot(spec)?);
+            Some(graph.diff(&old))
+        }
+        None => None,
+    };
+
+    let rendered = graph.render(format, graph_diff.as_ref());
+    let mut report = Vec::new();
+    for cycle in graph.cycles() {
+        let names: Vec<_> = cycle.iter().map(|(_, n)| n.as_str()).collect();
+        report.push(format!("{} Dependency cycle: {}", style("✘").red().bold(), names.join(" -> ")));
+    }
+    for (_, name) in graph.heavy_nodes() {
+        report.push(format!("{} Heavy node: {} has unusually many dependency edges", style("⚠").yellow(), name));
+    }
+    if let Some(d) = &graph_diff {
+        report.push(format!("{}", style("Architecture changes:").bold()));
+        report.extend(d.summary().lines().map(|l| format!("  {}", l)));
+    }
+
+    match output {
+        Some(path) => {
+            std::fs::write(&path, rendered).with_context(|| format!("Failed to write {}", path.display()))?;
+            for line in report {
+                println!("{}", line);
+            }
+            println!("{} Graph written to {}", style("✔").green(), path.display());
+        }
+        None => {
+            print!("{}", rendered);
+            for line in report {
+                eprintln!("{}", line);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Snapshots the working tree, replacing package edges with `cargo metadata`
+/// so crates outside `packages/` are included.
+fn current_snapshot(project_name: &str) -> Result<AgentSnapshot> {
+    let cwd = std::env::current_dir()?;
+    let mut snapshot = AgentManager::new(&cwd).generate_snapshot(project_name)?;
+
+    let Ok(metadata) = MetadataCommand::new().no_deps().exec() else {
+        return Ok(snapshot);
+    };
+    let dir_name = |p: &Path| p.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+
+    for pkg in metadata.workspace_packages() {
+        let Some(dir) = pkg.manifest_path.parent() else {
+            continue;
+        };
+        let name = dir_name(dir.as_std_path());
+        let mut dependencies: Vec<String> = pkg
+            .dependencies
+            .iter()
+            .filter_map(|d| d.path.as_ref().map(|p| dir_name(p.as_std_path())))
+            .collect();
+        dependencies.sort();
+        dependencies.dedup();
+
+        match snapshot.packages.iter_mut().find(|p| p.name == name) {
+            Some(existing) => existing.dependencies = dependencies,
+            None => snapshot.packages.push(PackageSummary {
+                name,
+                path: dir.strip_prefix(&metadata.workspace_root).map(|p| p.to_string()).unwrap_or_default(),
+                invariants: None,
+                description: pkg.description.clone(),
+                dependencies,
+            }),
+        }
+    }
+    Ok(snapshot)
+}
+
+/// Loads a snapshot from a JSON file, or from `.agent/agent.json` at a git revision.
+fn load_snapshot(spec: &str) -> Result<AgentSnapshot> {
+    let content = if Path::new(spec).is_file() {
+        std::fs::read_to_string(spec)?
+    } else {
+        let output = Command::new("git")
+            .args(["show", &format!("{}:.agent/agent.json", spec)])
+            .output()
+            .context("Failed to run git")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "'{}' is neither a snapshot file nor a git revision with .agent/agent.json: {}",
+                spec,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        String::from_utf8(output.stdout)?
+    };
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse snapshot '{}'", spec))
+}
@@ -1,14 +1,27 @@
 pub mod agent;
+pub mod api_types;
 pub mod bench;
 pub mod build;
+pub mod changelog;
+pub mod config;
+pub mod db;
+pub mod demo;
 pub mod e2e;
+pub mod explain;
 pub mod expand;
 pub mod fmt;
 pub mod generate;
+pub mod graph;
 pub mod mcp;
 pub mod new;
+pub mod perf;
+pub mod plate;
+pub mod plugin;
+pub mod profile;
+pub mod replay;
 pub mod run;
 pub mod serve;
+pub mod secrets;
 pub mod sketch;
 pub mod spec;
 pub mod test;
@@ -1,34 +1,90 @@
+use crate::config::MontrsConfig;
+use crate::plugin;
+use crate::report::reporter;
+use crate::sandbox::{Hook, Policy, Sandbox};
+use anyhow::Context;
 use cargo_generate::{GenerateArgs, TemplatePath, generate};
 use console::style;
+use serde::Deserialize;
 use std::env;
+use std::path::Path;
 
-pub async fn run(name: String, template_url: String) -> anyhow::Result<()> {
+/// Template metadata, read from the generated project and then removed.
+pub const TEMPLATE_MANIFEST: &str = "montrs-template.toml";
+
+/// Prefixes that make `--template` a git repository instead of a name under `templates/`.
+pub(crate) const REMOTE_PREFIXES: [&str; 7] = ["https://", "http://", "ssh://", "git@", "gh:", "gl:", "bb:"];
+
+#[derive(Deserialize, Default)]
+struct TemplateManifest {
+    #[serde(default)]
+    hooks: TemplateHooks,
+}
+
+#[derive(Deserialize, Default)]
+struct TemplateHooks {
+    /// Commands run in the new project, in order.
+    #[serde(default)]
+    post_generate: Vec<HookSpec>,
+}
+
+/// `"cargo fmt"`, or `{ run = "npm install", dir = "style" }`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(crate) enum HookSpec {
+    Command(String),
+    Detailed {
+        run: String,
+        #[serde(default)]
+        dir: Option<String>,
+    },
+}
+
+impl HookSpec {
+    pub(crate) fn into_hook(self, source: &str) -> Hook {
+        let (run, dir) = match self {
+            HookSpec::Command(run) => (run, None),
+            HookSpec::Detailed { run, dir } => (run, dir),
+        };
+        Hook { source: source.to_string(), run, dir }
+    }
+}
+
+/// `template` is a name under `templates/` or a git URL. Post-generate hooks
+/// run in a [`Sandbox`] with `policy`.
+pub async fn run(name: String, template_url: String, policy: Policy, config: &MontrsConfig) -> anyhow::Result<()> {
     println!(
         "{} Creating new MontRS project: {}",
         style("🚀").bold(),
         style(&name).cyan().bold()
     );
 
-    // Use local template path
-    let template_path = format!("templates/{}", template_url);
-
-    // In a real CLI, we might use include_dir! to embed templates
-    // or look relative to the binary path. For now, we look in the CWD
-    // assuming the user is in the montrs root.
+    let template_path = if REMOTE_PREFIXES.iter().any(|p| template_url.starts_with(p)) {
+        TemplatePath {
+            git: Some(template_url),
+            ..Default::default()
+        }
+    } else {
+        // In a real CLI, we might use include_dir! to embed templates
+        // or look relative to the binary path. For now, we look in the CWD
+        // assuming the user is in the montrs root.
+        TemplatePath {
+            path: Some(format!("templates/{}", template_url)),
+            ..Default::default()
+        }
+    };
 
     let args = GenerateArgs {
         name: Some(name.clone()),
-        template_path: TemplatePath {
-            path: Some(template_path),
-            ..Default::default()
-        },
+        template_path,
         destination: Some(env::current_dir()?),
         force: false,
         verbose: true,
         ..Default::default()
     };
 
-    generate(args).map_err(|e| anyhow::anyhow!("Scaffolding failed: {}", e))?;
+    let project = generate(args).map_err(|e| anyhow::anyhow!("Scaffolding failed: {}", e))?;
+    run_hooks(&project, policy, config)?;
 
     println!(
         "\n{} Project {} created successfully!",
@@ -39,6 +95,46 @@ pub async fn run(name: String, template_url: String) -> anyhow::Result<()> {
         "Next steps:\n  cd {}\n  montrs build\n  montrs serve",
         name
     );
+    println!(
+        "{} Error pages are branded via `AppSpec::with_error_pages`; see docs/core/error-pages.md",
+        style("💡").yellow()
+    );
+
+    Ok(())
+}
+
+/// Runs the template's post-generate hooks, then those of the plugins in
+/// `montrs.toml`, stopping at the first that is refused or fails. A plugin
+/// may always run its own executable.
+fn run_hooks(project: &Path, policy: Policy, config: &MontrsConfig) -> anyhow::Result<()> {
+    let mut hooks = Vec::new();
+    let manifest_path = project.join(TEMPLATE_MANIFEST);
+    if manifest_path.exists() {
+        let manifest: TemplateManifest = toml::from_str(&std::fs::read_to_string(&manifest_path)?)
+            .with_context(|| format!("Invalid {} in the template", TEMPLATE_MANIFEST))?;
+        std::fs::remove_file(&manifest_path)?;
+        hooks.extend(manifest.hooks.post_generate.into_iter().map(|spec| (spec.into_hook("template"), policy.clone())));
+    }
+    for (plugin, manifest) in plugin::configured_manifests(config) {
+        let mut policy = policy.clone();
+        if let Some(executable) = plugin.command.file_name() {
+            policy.allow.push(executable.to_string_lossy().to_string());
+        }
+        let source = format!("plugin:{}", plugin.name);
+        hooks.extend(manifest.post_generate.into_iter().map(|run| (Hook { source: source.clone(), run, dir: None }, policy.clone())));
+    }
 
+    for (hook, policy) in hooks {
+        let step = reporter().step(format!("{} hook: {}", hook.source, hook.run));
+        if let Err(e) = Sandbox::new(project, policy)?.run(&hook) {
+            step.fail();
+            return Err(e.context(format!(
+                "{} was created, but its post-generate hooks did not complete; see .agent/{}",
+                project.display(),
+                crate::sandbox::TRANSCRIPT_FILE
+            )));
+        }
+        step.finish();
+    }
     Ok(())
 }
@@ -0,0 +1,312 @@
+//! Perf command.
+//!
+//! Audits the running site with Lighthouse and writes FCP, LCP, TTI and bundle
+//! sizes per route to a JSON report. The report is checked against the budgets
+//! in `[e2e.perf]` and, when a baseline report exists, against the previous run.
+//! Any violation makes the command fail, so CI stops on a regression.
+//!
+//! Without a `lighthouse` binary, only response time and bundle sizes
+//! are recorded.
+
+use crate::config::{MontrsConfig, PerfConfig};
+use anyhow::{Context, Result, bail};
+use console::style;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+
+/// Measurements for a single route.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteMetrics {
+    pub route: String,
+    pub url: String,
+    pub fcp_ms: Option<f64>,
+    pub lcp_ms: Option<f64>,
+    pub tti_ms: Option<f64>,
+    pub response_ms: f64,
+}
+
+/// Size of the built site assets, in bytes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BundleSizes {
+    pub wasm: u64,
+    pub js: u64,
+    pub css: u64,
+}
+
+impl BundleSizes {
+    pub fn total_kb(&self) -> f64 {
+        (self.wasm + self.js + self.css) as f64 / 1024.0
+    }
+}
+
+/// The JSON report written by an audit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerfReport {
+    pub base_url: String,
+    pub tool: String,
+    pub routes: Vec<RouteMetrics>,
+    pub bundle: BundleSizes,
+    pub violations: Vec<String>,
+}
+
+pub async fn run(url: Option<String>, after: Option<String>, update_baseline: bool) -> Result<()> {
+    let config = MontrsConfig::load()?;
+    let perf = &config.e2e.perf;
+
+    // `montrs e2e --perf` runs the test suite through us so the server is still up.
+    if let Some(cmd) = after {
+        run_first(&cmd)?;
+    }
+
+    let base_url = url.unwrap_or_else(|| site_url(&config));
+    println!("{} Auditing {} route(s) on {}", style("🔍").bold(), perf.routes.len(), base_url);
+
+    let lighthouse = find_lighthouse();
+    if lighthouse.is_none() {
+        println!(
+            "{} lighthouse not found; recording response times and bundle sizes only. Install it with `npm i -g lighthouse`.",
+            style("⚠").yellow()
+        );
+    }
+
+    let mut routes = Vec::new();
+    for route in &perf.routes {
+        let metrics = audit_route(&base_url, route, lighthouse.as_deref())
+            .await
+            .with_context(|| format!("Failed to audit {}", route))?;
+        print_metrics(&metrics);
+        routes.push(metrics);
+    }
+
+    let bundle = bundle_sizes(&Path::new(&config.build.site_root).join(&config.build.site_pkg_name));
+    println!(
+        "  bundle: {:.1} KB (wasm {:.1}, js {:.1}, css {:.1})",
+        bundle.total_kb(),
+        bundle.wasm as f64 / 1024.0,
+        bundle.js as f64 / 1024.0,
+        bundle.css as f64 / 1024.0
+    );
+
+    let mut report = PerfReport {
+        base_url,
+        tool: if lighthouse.is_some() { "lighthouse" } else { "http" }.to_string(),
+        routes,
+        bundle,
+        violations: Vec::new(),
+    };
+    report.violations = check_budgets(&report, perf);
+    if let Some(baseline) = perf.baseline.as_deref().map(Path::new).filter(|p| p.exists()) {
+        let previous: PerfReport = serde_json::from_str(&std::fs::read_to_string(baseline)?)
+            .with_context(|| format!("Failed to parse baseline {}", baseline.display()))?;
+        report.violations.extend(check_regressions(&report, &previous, perf.regression_threshold));
+    }
+
+    write_json(Path::new(&perf.report), &report)?;
+    println!("{} Report written to {}", style("✔").green(), perf.report);
+
+    if update_baseline {
+        let Some(baseline) = &perf.baseline else {
+            bail!("`--update-baseline` needs `[e2e.perf].baseline` in montrs.toml");
+        };
+        write_json(Path::new(baseline), &report)?;
+        println!("{} Baseline updated: {}", style("✔").green(), baseline);
+    }
+
+    if !report.violations.is_empty() {
+        for violation in &report.violations {
+            println!("{} {}", style("✘").red(), violation);
+        }
+        bail!("{} performance check(s) failed", report.violations.len());
+    }
+    println!("{} All routes within budget", style("✔").green());
+    Ok(())
+}
+
+fn run_first(cmd: &str) -> Result<()> {
+    let parts = shlex::split(cmd).with_context(|| format!("Invalid command: {}", cmd))?;
+    let Some((program, args)) = parts.split_first() else {
+        return Ok(());
+    };
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !status.success() {
+        bail!("`{}` failed; skipping the performance audit", cmd);
+    }
+    Ok(())
+}
+
+/// Resolves the site URL: `MONTRS_SITE_URL`, `[e2e].base_url`, cargo-leptos'
+/// `LEPTOS_SITE_ADDR`, then `[serve]`.
+pub(crate) fn site_url(config: &MontrsConfig) -> String {
+    if let Ok(url) = std::env::var("MONTRS_SITE_URL") {
+        return url;
+    }
+    if let Some(url) = &config.e2e.base_url {
+        return url.clone();
+    }
+    match std::env::var("LEPTOS_SITE_ADDR") {
+        Ok(addr) => format!("http://{}", addr),
+        Err(_) => format!("http://{}:{}", config.serve.addr, config.serve.port),
+    }
+}
+
+/// Finds Lighthouse on `PATH` or in the project's `node_modules`.
+fn find_lighthouse() -> Option<PathBuf> {
+    which::which("lighthouse").ok().or_else(|| {
+        let local = Path::new("node_modules/.bin/lighthouse");
+        local.exists().then(|| local.to_path_buf())
+    })
+}
+
+async fn audit_route(base_url: &str, route: &str, lighthouse: Option<&Path>) -> Result<RouteMetrics> {
+    let url = format!("{}/{}", base_url.trim_end_matches('/'), route.trim_start_matches('/'));
+
+    let started = Instant::now();
+    let response = reqwest::get(&url).await.with_context(|| format!("{} is not reachable", url))?;
+    let response_ms = started.elapsed().as_secs_f64() * 1000.0;
+    if !response.status().is_success() {
+        bail!("{} returned {}", url, response.status());
+    }
+
+    let mut metrics = RouteMetrics {
+        route: route.to_string(),
+        url: url.clone(),
+        fcp_ms: None,
+        lcp_ms: None,
+        tti_ms: None,
+        response_ms,
+    };
+
+    if let Some(lighthouse) = lighthouse {
+        let output = Command::new(lighthouse)
+            .arg(&url)
+            .args([
+                "--output=json",
+                "--output-path=stdout",
+                "--only-categories=performance",
+                "--quiet",
+                "--chrome-flags=--headless=new --no-sandbox",
+            ])
+            .output()
+            .context("Failed to run lighthouse")?;
+        if !output.status.success() {
+            bail!("lighthouse failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        let lhr: serde_json::Value =
+            serde_json::from_slice(&output.stdout).context("lighthouse returned invalid JSON")?;
+        let audit = |id: &str| lhr["audits"][id]["numericValue"].as_f64();
+        metrics.fcp_ms = audit("first-contentful-paint");
+        metrics.lcp_ms = audit("largest-contentful-paint");
+        metrics.tti_ms = audit("interactive");
+    }
+
+    Ok(metrics)
+}
+
+fn print_metrics(m: &RouteMetrics) {
+    let fmt = |v: Option<f64>| v.map(|v| format!("{:.0}ms", v)).unwrap_or_else(|| "-".to_string());
+    println!(
+        "  {} FCP {} | LCP {} | TTI {} | response {:.0}ms",
+        style(&m.route).cyan(),
+        fmt(m.fcp_ms),
+        fmt(m.lcp_ms),
+        fmt(m.tti_ms),
+        m.response_ms
+    );
+}
+
+/// Sums the wasm, JS and CSS files under the site package directory.
+fn bundle_sizes(pkg_dir: &Path) -> BundleSizes {
+    let mut sizes = BundleSizes::default();
+    for entry in walkdir::WalkDir::new(pkg_dir).into_iter().flatten() {
+        let Ok(meta) = entry.metadata() else { continue };
+        if !meta.is_file() {
+            continue;
+        }
+        match entry.path().extension().and_then(|e| e.to_str()) {
+            Some("wasm") => sizes.wasm += meta.len(),
+            Some("js") => sizes.js += meta.len(),
+            Some("css") => sizes.css += meta.len(),
+            _ => {}
+        }
+    }
+    sizes
+}
+
+fn check_budgets(report: &PerfReport, perf: &PerfConfig) -> Vec<String> {
+    let mut violations = Vec::new();
+    for m in &report.routes {
+        let budget = perf
+            .route_budgets
+            .get(&m.route)
+            .map(|b| b.or(&perf.budget))
+            .unwrap_or_else(|| perf.budget.clone());
+        for (name, value, limit) in [
+            ("FCP", m.fcp_ms, budget.fcp_ms),
+            ("LCP", m.lcp_ms, budget.lcp_ms),
+            ("TTI", m.tti_ms, budget.tti_ms),
+        ] {
+            if let (Some(value), Some(limit)) = (value, limit)
+                && value > limit
+            {
+                violations.push(format!("{} {} is {:.0}ms (budget {:.0}ms)", m.route, name, value, limit));
+            }
+        }
+    }
+    if let Some(limit) = perf.budget.bundle_kb
+        && report.bundle.total_kb() > limit
+    {
+        violations.push(format!("bundle is {:.1} KB (budget {:.1} KB)", report.bundle.total_kb(), limit));
+    }
+    violations
+}
+
+fn check_regressions(report: &PerfReport, previous: &PerfReport, threshold: f64) -> Vec<String> {
+    let factor = 1.0 + threshold / 100.0;
+    let mut violations = Vec::new();
+    for m in &report.routes {
+        let Some(old) = previous.routes.iter().find(|o| o.route == m.route) else {
+            continue;
+        };
+        for (name, value, before) in [
+            ("FCP", m.fcp_ms, old.fcp_ms),
+            ("LCP", m.lcp_ms, old.lcp_ms),
+            ("TTI", m.tti_ms, old.tti_ms),
+        ] {
+            if let (Some(value), Some(before)) = (value, before)
+                && value > before * factor
+            {
+                violations.push(format!(
+                    "{} {} regressed from {:.0}ms to {:.0}ms (> {}%)",
+                    m.route, name, before, value, threshold
+                ));
+            }
+        }
+    }
+    let (now, before) = (report.bundle.total_kb(), previous.bundle.total_kb());
+    if before > 0.0 && now > before * factor {
+        violations.push(format!("bundle grew from {:.1} KB to {:.1} KB (> {}%)", before, now, threshold));
+    }
+    violations
+}
+
+fn write_json(path: &Path, report: &PerfReport) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(report)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// The command `montrs e2e --perf` hands to cargo-leptos: the e2e suite followed by the audit.
+pub fn wrap_e2e_command(e2e_cmd: &str) -> Result<String> {
+    let exe = std::env::current_exe().context("Cannot locate the montrs binary")?;
+    let exe = exe.to_string_lossy();
+    let exe = shlex::try_quote(&exe).context("montrs path cannot be quoted")?;
+    let cmd = shlex::try_quote(e2e_cmd).context("e2e command cannot be quoted")?;
+    Ok(format!("{} perf --after {}", exe, cmd))
+}
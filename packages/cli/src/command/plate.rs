@@ -0,0 +1,492 @@
+//! `montrs plate add`: install a community plate.
+//!
+//! A plate is named in a registry index, a TOML file mapping names to crates,
+//! or given as a git URL. Adding one puts its crate in the app's Cargo.toml,
+//! registers it between the `// montrs:plates:begin` and `// montrs:plates:end`
+//! comments of the `AppSpec` chain, copies its migrations and assets, runs its
+//! post-install hooks in a [`Sandbox`] and records where it came from under
+//! `[plates.installed]` in montrs.toml so the install can be reproduced.
+
+use crate::command::generate::add_dependency;
+use crate::command::new::{HookSpec, REMOTE_PREFIXES};
+use crate::config::{InstalledPlate, MontrsConfig};
+use crate::dryrun;
+use crate::report::reporter;
+use crate::sandbox::{Policy, Sandbox, TRANSCRIPT_FILE};
+use crate::PlateSubcommand;
+use anyhow::{Context, Result, bail};
+use cargo_metadata::MetadataCommand;
+use console::style;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::TempDir;
+use walkdir::WalkDir;
+
+/// The community index used when `[plates].registry` is not set.
+pub const DEFAULT_REGISTRY: &str = "https://raw.githubusercontent.com/afsall-labs/montrs-plates/main/index.toml";
+
+/// Install metadata at the root of a plate's crate.
+pub const PLATE_MANIFEST: &str = "montrs-plate.toml";
+
+const BEGIN_MARKER: &str = "// montrs:plates:begin";
+const END_MARKER: &str = "// montrs:plates:end";
+
+/// The registry index: `[plates.<name>]` tables.
+#[derive(Deserialize, Default)]
+struct RegistryIndex {
+    #[serde(default)]
+    plates: BTreeMap<String, RegistryEntry>,
+}
+
+/// `{ crate = "montrs-auth", version = "0.3" }`, or with `git` (and
+/// optionally `rev`) instead of `version`.
+#[derive(Deserialize, Clone)]
+struct RegistryEntry {
+    #[serde(rename = "crate")]
+    crate_name: String,
+    #[serde(default)]
+    version: Option<String>,
+    #[serde(default)]
+    git: Option<String>,
+    #[serde(default)]
+    rev: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct PlateManifest {
+    #[serde(default)]
+    plate: PlateSection,
+    #[serde(default)]
+    install: InstallSection,
+}
+
+#[derive(Deserialize, Default)]
+struct PlateSection {
+    /// Expression passed to `with_plate(Box::new(...))` (default: `<crate>::Plate::default()`).
+    #[serde(default)]
+    register: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct InstallSection {
+    /// Directory of migration scripts copied into `[database].migrations`.
+    #[serde(default)]
+    migrations: Option<String>,
+    /// Directory copied into `<assets-dir>/<plate>`.
+    #[serde(default)]
+    assets: Option<String>,
+    /// Commands run in the project after the files are copied.
+    #[serde(default)]
+    post_install: Vec<HookSpec>,
+}
+
+/// What `plate add` was asked for.
+pub struct AddOptions {
+    /// Registry name, or a git URL.
+    pub name: String,
+    pub version: Option<String>,
+    pub rev: Option<String>,
+    pub registry: Option<String>,
+    /// The file that builds the `AppSpec`.
+    pub app: Option<PathBuf>,
+    pub policy: Policy,
+    pub no_hooks: bool,
+}
+
+pub async fn run(subcommand: PlateSubcommand, config: &MontrsConfig) -> Result<()> {
+    match subcommand {
+        PlateSubcommand::Add { name, version, rev, registry, app, allow, allow_network, no_hooks } => {
+            let policy = Policy { allow, network: allow_network };
+            add(AddOptions { name, version, rev, registry, app, policy, no_hooks }, config).await
+        }
+    }
+}
+
+pub async fn add(options: AddOptions, config: &MontrsConfig) -> Result<()> {
+    let project = std::env::current_dir()?;
+    let app = find_app(&project, options.app.as_deref())?;
+    let manifest = app
+        .ancestors()
+        .map(|dir| dir.join("Cargo.toml"))
+        .find(|manifest| manifest.exists())
+        .with_context(|| format!("No Cargo.toml above {}", app.display()))?;
+
+    let (name, mut record, checkout) = resolve(&options, config).await?;
+    add_dependency(&manifest, "dependencies", &record.crate_name, Some(&dependency_spec(&record)))?;
+
+    let plate_dir = match &checkout {
+        Some(checkout) => Some(checkout.path().to_path_buf()),
+        None => locate_crate(&manifest, &mut record)?,
+    };
+    let plate: PlateManifest = match plate_dir.as_ref().map(|dir| dir.join(PLATE_MANIFEST)).filter(|path| path.exists()) {
+        Some(path) => toml::from_str(&fs::read_to_string(&path)?).with_context(|| format!("Invalid {} in plate '{}'", PLATE_MANIFEST, name))?,
+        None => PlateManifest::default(),
+    };
+    if plate_dir.is_none() {
+        reporter().warn(format!("{} is not downloaded in a dry run, so its install steps are not shown", record.crate_name));
+    }
+
+    record.register = plate.plate.register.clone().unwrap_or_else(|| format!("{}::Plate::default()", record.crate_name.replace('-', "_")));
+    register(&app, &name, &record.register)?;
+    if let Some(plate_dir) = &plate_dir {
+        record.files = install_files(&project, plate_dir, &name, &plate.install, config)?;
+    }
+    record_provenance(&project.join("montrs.toml"), &name, &record)?;
+
+    if !options.no_hooks && !plate.install.post_install.is_empty() {
+        if dryrun::enabled() {
+            reporter().info(format!("Skipping {} post-install hook(s) of '{}' in a dry run", plate.install.post_install.len(), name));
+        } else {
+            run_hooks(&project, &name, plate.install.post_install, options.policy)?;
+        }
+    }
+
+    println!(
+        "{} Added plate {} ({}) to {}",
+        style("✨").green().bold(),
+        style(&name).cyan().bold(),
+        record.crate_name,
+        app.strip_prefix(&project).unwrap_or(&app).display()
+    );
+    Ok(())
+}
+
+/// Turns the requested name into the plate's name, its provenance so far and,
+/// for git sources, a checkout of the plate.
+async fn resolve(options: &AddOptions, config: &MontrsConfig) -> Result<(String, InstalledPlate, Option<TempDir>)> {
+    if REMOTE_PREFIXES.iter().any(|p| options.name.starts_with(p)) {
+        if options.version.is_some() {
+            bail!("--version applies to registry plates; pin a git plate with --rev");
+        }
+        let git = expand_git_url(&options.name);
+        let (checkout, rev) = clone(&git, options.rev.as_deref())?;
+        let crate_name = package_name(checkout.path())?;
+        let name = crate_name.trim_start_matches("montrs-").to_string();
+        let record = InstalledPlate { crate_name, git: Some(git), rev: Some(rev), ..Default::default() };
+        return Ok((name, record, Some(checkout)));
+    }
+
+    let registry = options.registry.clone().unwrap_or_else(|| config.plates.registry.clone());
+    let index = load_registry(&registry).await?;
+    let entry = index
+        .plates
+        .get(&options.name)
+        .cloned()
+        .with_context(|| format!("Plate '{}' is not in the registry {}", options.name, registry))?;
+    let mut record = InstalledPlate { crate_name: entry.crate_name, registry: Some(registry), ..Default::default() };
+    match entry.git {
+        Some(git) => {
+            if options.version.is_some() {
+                bail!("Plate '{}' is published from git; pin it with --rev instead of --version", options.name);
+            }
+            let (checkout, rev) = clone(&git, options.rev.as_deref().or(entry.rev.as_deref()))?;
+            record.git = Some(git);
+            record.rev = Some(rev);
+            Ok((options.name.clone(), record, Some(checkout)))
+        }
+        None => {
+            if options.rev.is_some() {
+                bail!("Plate '{}' is published to crates.io; pick a release with --version instead of --rev", options.name);
+            }
+            record.version = Some(options.version.clone().or(entry.version).unwrap_or_else(|| "*".to_string()));
+            Ok((options.name.clone(), record, None))
+        }
+    }
+}
+
+/// Reads the index from an http(s) URL or a local file.
+async fn load_registry(location: &str) -> Result<RegistryIndex> {
+    let text = if location.starts_with("https://") || location.starts_with("http://") {
+        let response = reqwest::get(location).await.with_context(|| format!("Plate registry {} is not reachable", location))?;
+        if !response.status().is_success() {
+            bail!("Plate registry {} returned {}", location, response.status());
+        }
+        response.text().await?
+    } else {
+        fs::read_to_string(location).with_context(|| format!("Failed to read plate registry {}", location))?
+    };
+    toml::from_str(&text).with_context(|| format!("Invalid plate registry index {}", location))
+}
+
+/// `gh:owner/repo` and friends, as accepted by `montrs new --template`.
+fn expand_git_url(url: &str) -> String {
+    for (prefix, host) in [("gh:", "https://github.com/"), ("gl:", "https://gitlab.com/"), ("bb:", "https://bitbucket.org/")] {
+        if let Some(path) = url.strip_prefix(prefix) {
+            return format!("{}{}", host, path);
+        }
+    }
+    url.to_string()
+}
+
+/// Clones `url` at `rev` (default: the remote HEAD) and returns the commit it
+/// resolved to.
+fn clone(url: &str, rev: Option<&str>) -> Result<(TempDir, String)> {
+    let checkout = tempfile::tempdir()?;
+    let step = reporter().step(format!("clone {}", url));
+    let head = (|| {
+        let mut command = Command::new("git");
+        command.args(["clone", "--quiet"]);
+        if rev.is_none() {
+            command.args(["--depth", "1"]);
+        }
+        git(command.arg(url).arg(checkout.path()))?;
+        if let Some(rev) = rev {
+            git(Command::new("git").arg("-C").arg(checkout.path()).args(["checkout", "--quiet", rev]))?;
+        }
+        git(Command::new("git").arg("-C").arg(checkout.path()).args(["rev-parse", "HEAD"]))
+    })();
+    match head {
+        Ok(head) => {
+            step.finish();
+            Ok((checkout, head))
+        }
+        Err(e) => {
+            step.fail();
+            Err(e.context(format!("Failed to fetch plate from {}", url)))
+        }
+    }
+}
+
+fn git(command: &mut Command) -> Result<String> {
+    let output = command.output().context("Failed to run git")?;
+    if !output.status.success() {
+        bail!("git failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn package_name(dir: &Path) -> Result<String> {
+    let manifest: toml::Value = toml::from_str(&fs::read_to_string(dir.join("Cargo.toml")).context("The plate repository has no Cargo.toml")?)?;
+    manifest
+        .get("package")
+        .and_then(|p| p.get("name"))
+        .and_then(|n| n.as_str())
+        .map(str::to_string)
+        .context("The plate repository's Cargo.toml has no [package] name")
+}
+
+/// Finds the downloaded crate through cargo and notes the version it
+/// resolved. `None` when the dependency is not written yet (`--dry-run`).
+fn locate_crate(manifest: &Path, record: &mut InstalledPlate) -> Result<Option<PathBuf>> {
+    let metadata = match MetadataCommand::new().manifest_path(manifest).exec() {
+        Ok(metadata) => metadata,
+        Err(_) if dryrun::enabled() => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("cargo could not resolve {}", record.crate_name)),
+    };
+    let Some(package) = metadata.packages.iter().find(|p| p.name == record.crate_name) else {
+        if dryrun::enabled() {
+            return Ok(None);
+        }
+        bail!("cargo did not resolve {} after adding it to {}", record.crate_name, manifest.display());
+    };
+    record.resolved = Some(package.version.to_string());
+    Ok(package.manifest_path.parent().map(|dir| dir.as_std_path().to_path_buf()))
+}
+
+fn dependency_spec(record: &InstalledPlate) -> String {
+    let quote = |s: &str| toml::Value::String(s.to_string()).to_string();
+    match (&record.git, &record.rev) {
+        (Some(git), Some(rev)) => format!("{{ git = {}, rev = {} }}", quote(git), quote(rev)),
+        _ => quote(record.version.as_deref().unwrap_or("*")),
+    }
+}
+
+/// `--app`, or the first `main.rs` with plate markers, or else the first
+/// that calls `AppSpec::new`.
+fn find_app(project: &Path, app: Option<&Path>) -> Result<PathBuf> {
+    if let Some(app) = app {
+        return Ok(project.join(app));
+    }
+    let mains: Vec<(PathBuf, String)> = WalkDir::new(project)
+        .max_depth(5)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| !matches!(e.file_name().to_str(), Some("target" | "node_modules" | ".git" | ".agent")))
+        .flatten()
+        .filter(|e| e.file_name() == "main.rs")
+        .filter_map(|e| Some((e.path().to_path_buf(), fs::read_to_string(e.path()).ok()?)))
+        .collect();
+    mains
+        .iter()
+        .find(|(_, text)| text.contains(BEGIN_MARKER))
+        .or_else(|| mains.iter().find(|(_, text)| text.contains("AppSpec::new(")))
+        .map(|(path, _)| path.clone())
+        .context("No main.rs that builds an `AppSpec` was found; pass it with --app")
+}
+
+/// Adds `.with_plate(Box::new(<expression>)) // plate:<name>` inside the
+/// markers, replacing the line from an earlier add of the same plate.
+fn register(app: &Path, name: &str, expression: &str) -> Result<()> {
+    let text = fs::read_to_string(app).with_context(|| format!("Failed to read {}", app.display()))?;
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let (begin, end) = match markers(&lines) {
+        Some(found) => found,
+        None => insert_markers(&mut lines, app)?,
+    };
+    let indent: String = lines[begin].chars().take_while(|c| c.is_whitespace()).collect();
+    let tag = format!("// plate:{}", name);
+    let entry = format!("{}.with_plate(Box::new({})) {}", indent, expression, tag);
+    match lines[begin + 1..end].iter().position(|line| line.trim_end().ends_with(&tag)) {
+        Some(existing) => lines[begin + 1 + existing] = entry,
+        None => lines.insert(end, entry),
+    }
+
+    let mut updated = lines.join("\n");
+    if text.ends_with('\n') {
+        updated.push('\n');
+    }
+    if updated != text {
+        dryrun::write_with(app, updated, format!("register plate {} with the AppSpec", name))?;
+    }
+    Ok(())
+}
+
+fn markers(lines: &[String]) -> Option<(usize, usize)> {
+    let begin = lines.iter().position(|line| line.trim() == BEGIN_MARKER)?;
+    let end = lines[begin..].iter().position(|line| line.trim() == END_MARKER)? + begin;
+    Some((begin, end))
+}
+
+/// Puts the markers right after the `AppSpec::new(...)` call, which must be
+/// followed by a builder chain rather than end the statement.
+fn insert_markers(lines: &mut Vec<String>, app: &Path) -> Result<(usize, usize)> {
+    let hint = format!("add `{}` and `{}` comments where `.with_plate(...)` calls may go", BEGIN_MARKER, END_MARKER);
+    let start = lines
+        .iter()
+        .position(|line| line.contains("AppSpec::new("))
+        .with_context(|| format!("{} has no `AppSpec::new(`; {}", app.display(), hint))?;
+
+    let mut depth = 0i32;
+    let mut close = None;
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        let from = if i == start { line.find("AppSpec::new(").unwrap_or_default() } else { 0 };
+        for c in line[from..].chars() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+        }
+        if depth <= 0 {
+            close = Some(i);
+            break;
+        }
+    }
+    let close = close.with_context(|| format!("The `AppSpec::new(` call in {} is not closed; {}", app.display(), hint))?;
+    if lines[close].trim_end().ends_with(';') {
+        bail!("`AppSpec::new(...)` in {} is not followed by a builder chain; {}", app.display(), hint);
+    }
+
+    let indent: String = match lines.get(close + 1).filter(|next| next.trim_start().starts_with('.')) {
+        Some(next) => next.chars().take_while(|c| c.is_whitespace()).collect(),
+        None => format!("{}    ", lines[start].chars().take_while(|c| c.is_whitespace()).collect::<String>()),
+    };
+    lines.insert(close + 1, format!("{}{}", indent, BEGIN_MARKER));
+    lines.insert(close + 2, format!("{}{}", indent, END_MARKER));
+    Ok((close + 1, close + 2))
+}
+
+/// Copies the plate's migrations and assets into the project and returns
+/// the files it added, relative to the project.
+fn install_files(project: &Path, plate_dir: &Path, name: &str, install: &InstallSection, config: &MontrsConfig) -> Result<Vec<String>> {
+    let mut copied = Vec::new();
+    if let Some(migrations) = &install.migrations {
+        copy_tree(&plate_dir.join(migrations), &project.join(&config.database.migrations), project, &mut copied)?;
+    }
+    if let Some(assets) = &install.assets {
+        let assets_dir = config.build.assets_dir.as_deref().unwrap_or("assets");
+        copy_tree(&plate_dir.join(assets), &project.join(assets_dir).join(name), project, &mut copied)?;
+    }
+    Ok(copied)
+}
+
+/// Copies every file under `from` to `to`. A file that is already there with
+/// the same contents is skipped; one with different contents is an error,
+/// since it is the project's now.
+fn copy_tree(from: &Path, to: &Path, project: &Path, copied: &mut Vec<String>) -> Result<()> {
+    if !from.is_dir() {
+        bail!("The plate has no {} directory", from.display());
+    }
+    for entry in WalkDir::new(from).sort_by_file_name().into_iter().flatten().filter(|e| e.file_type().is_file()) {
+        let relative = entry.path().strip_prefix(from)?;
+        let target = to.join(relative);
+        let contents = fs::read(entry.path())?;
+        if target.exists() {
+            if fs::read(&target)? == contents {
+                continue;
+            }
+            bail!("{} already exists with different contents; move it aside and add the plate again", target.display());
+        }
+        if let Some(parent) = target.parent() {
+            dryrun::create_dir_all(parent)?;
+        }
+        dryrun::write(&target, contents)?;
+        copied.push(target.strip_prefix(project).unwrap_or(&target).to_string_lossy().replace('\\', "/"));
+    }
+    Ok(())
+}
+
+fn run_hooks(project: &Path, name: &str, hooks: Vec<HookSpec>, policy: Policy) -> Result<()> {
+    let source = format!("plate:{}", name);
+    for hook in hooks.into_iter().map(|spec| spec.into_hook(&source)) {
+        let step = reporter().step(format!("{} hook: {}", hook.source, hook.run));
+        if let Err(e) = Sandbox::new(project, policy.clone())?.run(&hook) {
+            step.fail();
+            return Err(e.context(format!(
+                "Plate '{}' was added, but its post-install hooks did not complete; see .agent/{}",
+                name, TRANSCRIPT_FILE
+            )));
+        }
+        step.finish();
+    }
+    Ok(())
+}
+
+/// Writes `[plates.installed.<name>]` to montrs.toml, replacing the table
+/// from an earlier add and leaving the rest of the file as it was.
+fn record_provenance(path: &Path, name: &str, record: &InstalledPlate) -> Result<()> {
+    let mut text = fs::read_to_string(path).unwrap_or_default();
+    let key = if name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        name.to_string()
+    } else {
+        toml::Value::String(name.to_string()).to_string()
+    };
+    let header = format!("[plates.installed.{}]", key);
+    let table = format!("{}\n{}", header, toml::to_string(record)?);
+
+    let existing = text.lines().scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len() + 1;
+        Some((start, line))
+    });
+    let mut start = None;
+    let mut end = text.len();
+    for (offset, line) in existing {
+        if start.is_none() && line.trim() == header {
+            start = Some(offset);
+        } else if start.is_some() && line.trim_start().starts_with('[') {
+            end = offset;
+            break;
+        }
+    }
+    match start {
+        Some(start) => {
+            let table = if end < text.len() { format!("{}\n", table) } else { table };
+            text.replace_range(start..end.min(text.len()), &table);
+        }
+        None => {
+            if !text.is_empty() && !text.ends_with('\n') {
+                text.push('\n');
+            }
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.push_str(&table);
+        }
+    }
+    dryrun::write_with(path, text, format!("record plate {} under [plates.installed]", name))?;
+    Ok(())
+}
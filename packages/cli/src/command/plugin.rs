@@ -0,0 +1,47 @@
+use crate::config::MontrsConfig;
+use crate::plugin::{self, PluginSource};
+use console::style;
+
+pub async fn run(args: Vec<String>, config: &MontrsConfig) -> anyhow::Result<()> {
+    let Some((name, rest)) = args.split_first() else {
+        anyhow::bail!("No subcommand given");
+    };
+
+    let plugin = plugin::find(name, config).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Unknown command '{}'. No plugin named '{}{}' was found on PATH or in montrs.toml [plugins].",
+            name,
+            plugin::PLUGIN_PREFIX,
+            name
+        )
+    })?;
+
+    let status = plugin::invoke(&plugin, rest, config)?;
+    if !status.success() {
+        anyhow::bail!("Plugin '{}' exited with {}", plugin.name, status);
+    }
+    Ok(())
+}
+
+pub async fn list(config: &MontrsConfig) -> anyhow::Result<()> {
+    let plugins = plugin::discover(config);
+    if plugins.is_empty() {
+        println!("No plugins found. Install a `montrs-<name>` executable or declare one under [plugins] in montrs.toml.");
+        return Ok(());
+    }
+
+    println!("{}", style("Installed Plugins:").bold());
+    for p in plugins {
+        let source = match p.source {
+            PluginSource::Config => "montrs.toml",
+            PluginSource::Path => "PATH",
+        };
+        println!(
+            "  - {} {} {}",
+            style(&p.name).cyan(),
+            style(format!("({})", source)).dim(),
+            p.description.as_deref().unwrap_or("")
+        );
+    }
+    Ok(())
+}
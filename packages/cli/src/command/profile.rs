@@ -0,0 +1,78 @@
+//! Profile command.
+//!
+//! Reads the route profile written by `montrs serve --profile` and prints the
+//! slowest routes with their CPU, await, database and allocation breakdown.
+//! `--folded` prints the raw folded stacks, e.g. for
+//! `montrs profile --folded | inferno-flamegraph > profile.svg`.
+
+use anyhow::{Context, Result};
+use console::style;
+use montrs_core::profile::{FOLDED_FILE, RouteProfile, SUMMARY_FILE};
+use std::path::Path;
+
+/// Where `serve --profile` writes the session profile, relative to the project.
+pub const PROFILE_DIR: &str = "target/montrs/profile";
+
+pub fn run(top: usize, folded: bool) -> Result<()> {
+    let dir = Path::new(PROFILE_DIR);
+    if folded {
+        let stacks = std::fs::read_to_string(dir.join(FOLDED_FILE))
+            .with_context(|| format!("No profile in {}. Run `montrs serve --profile` first.", PROFILE_DIR))?;
+        print!("{}", stacks);
+        return Ok(());
+    }
+
+    let summary = std::fs::read_to_string(dir.join(SUMMARY_FILE))
+        .with_context(|| format!("No profile in {}. Run `montrs serve --profile` first.", PROFILE_DIR))?;
+    let mut routes: Vec<RouteProfile> = serde_json::from_str(&summary).context("Invalid profile summary")?;
+    if routes.is_empty() {
+        println!("{} No route calls were profiled", style("⚠").yellow());
+        return Ok(());
+    }
+    routes.sort_by_key(|p| std::cmp::Reverse(p.avg_wall_us()));
+
+    println!(
+        "{:<32} {:>4} {:>6} {:>10} {:>10} {:>8} {:>8} {:>7} {:>9}",
+        style("route").bold(),
+        "op",
+        "calls",
+        "avg",
+        "max",
+        "cpu%",
+        "db%",
+        "awaits",
+        "allocs"
+    );
+    for p in routes.iter().take(top) {
+        let share = |part: u64| match p.wall_us {
+            0 => 0.0,
+            wall => part as f64 * 100.0 / wall as f64,
+        };
+        println!(
+            "{:<32} {:>4} {:>6} {:>10} {:>10} {:>7.1}% {:>7.1}% {:>7} {:>9}",
+            p.route,
+            p.operation,
+            p.calls,
+            format_us(p.avg_wall_us()),
+            format_us(p.max_wall_us),
+            share(p.cpu_us),
+            share(p.db_us),
+            p.await_points / p.calls.max(1),
+            p.allocations / p.calls.max(1)
+        );
+    }
+    println!(
+        "\nAwaits and allocations are per call. Allocations need `TrackingAllocator` as the global allocator."
+    );
+    Ok(())
+}
+
+fn format_us(us: u64) -> String {
+    if us >= 1_000_000 {
+        format!("{:.2}s", us as f64 / 1_000_000.0)
+    } else if us >= 1_000 {
+        format!("{:.1}ms", us as f64 / 1_000.0)
+    } else {
+        format!("{}µs", us)
+    }
+}
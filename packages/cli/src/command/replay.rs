@@ -0,0 +1,90 @@
+//! Replay command.
+//!
+//! Sends requests recorded by `montrs serve --capture` to a running server
+//! again and prints how each was answered next to the status it got when it
+//! was captured. A directory replays every capture in it, oldest first.
+
+use crate::config::MontrsConfig;
+use crate::devproxy::{self, capture::REPLAY_HEADER};
+use anyhow::{Context, Result};
+use console::style;
+use montrs_core::Capture;
+use std::path::Path;
+
+/// Longest part of an unexpected response body that is printed.
+const MAX_BODY_PREVIEW: usize = 400;
+
+pub async fn run(capture: String, url: Option<String>) -> Result<()> {
+    let path = Path::new(&capture);
+    let captures = if path.is_dir() {
+        Capture::read_dir(path)?
+    } else {
+        vec![(path.to_path_buf(), Capture::read(path).with_context(|| format!("Cannot read capture {}", capture))?)]
+    };
+    if captures.is_empty() {
+        println!("{} No captures in {}", style("⚠").yellow(), capture);
+        return Ok(());
+    }
+    let base = match url {
+        Some(url) => url,
+        None => format!("http://{}", devproxy::app_addr(&MontrsConfig::load()?)),
+    };
+    let base = base.trim_end_matches('/');
+
+    let client = reqwest::Client::new();
+    let mut changed = 0;
+    for (file, capture) in &captures {
+        if let Some(bytes) = capture.omitted_bytes {
+            println!(
+                "{} {} was captured without its {}-byte body",
+                style("⚠").yellow(),
+                file.display(),
+                bytes
+            );
+        }
+        let method = reqwest::Method::from_bytes(capture.method.as_bytes())
+            .with_context(|| format!("Invalid method in {}", file.display()))?;
+        let mut request = client
+            .request(method, format!("{}{}", base, capture.path_with_query))
+            .header(REPLAY_HEADER, "1")
+            .body(capture.body.clone());
+        for (name, value) in &capture.headers {
+            request = request.header(name, value);
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("{} is not reachable; is `montrs serve` running?", base))?;
+        let status = response.status().as_u16();
+
+        let was = match capture.status {
+            Some(recorded) if recorded != status => {
+                changed += 1;
+                format!(" (was {})", recorded)
+            }
+            _ => String::new(),
+        };
+        let shown = match status {
+            200..=399 => style(status).green(),
+            400..=499 => style(status).yellow(),
+            _ => style(status).red(),
+        };
+        println!("{} {} -> {}{}", capture.method, capture.path_with_query, shown, was);
+        if status >= 400 {
+            let body = response.text().await.unwrap_or_default();
+            let preview: String = body.chars().take(MAX_BODY_PREVIEW).collect();
+            if !preview.is_empty() {
+                println!("    {}", style(preview).dim());
+            }
+        }
+    }
+    if changed > 0 {
+        println!(
+            "{} {} of {} request(s) were answered with a different status than when captured",
+            style("⚠").yellow(),
+            changed,
+            captures.len()
+        );
+    }
+    Ok(())
+}
@@ -1,69 +1,153 @@
 use crate::config::{MontrsConfig, TaskConfig};
 use crate::ext::exe_command;
+use crate::report::reporter;
+use crate::taskcache::{TaskCache, matching_dirs};
 use console::style;
-use std::collections::{HashMap, HashSet};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::Path;
 use std::process::Command;
 
-pub async fn run(task_name: String) -> anyhow::Result<()> {
-    let config = MontrsConfig::load()?;
+pub async fn run(task_name: String, force: bool) -> anyhow::Result<()> {
+    let mut config = MontrsConfig::load()?;
+    if !config.tasks.contains_key(&task_name) {
+        crate::plugin::merge_tasks(&mut config);
+    }
 
     // Resolve dependencies and run them in order
-    let mut executed = HashSet::new();
-    execute_task_recursive(&task_name, &config, &mut executed).await?;
-
+    let mut pipeline = Pipeline {
+        config: &config,
+        cache: TaskCache::new(&std::env::current_dir()?),
+        force,
+        executed: HashMap::new(),
+        cached_runs: 0,
+        hits: 0,
+    };
+    pipeline.execute(&task_name)?;
+
+    if pipeline.cached_runs > 0 {
+        reporter().info(format!(
+            "{} {} of {} cached run(s) replayed from {}",
+            style("↺").cyan(),
+            pipeline.hits,
+            pipeline.cached_runs,
+            crate::taskcache::CACHE_DIR
+        ));
+    }
     Ok(())
 }
 
-async fn execute_task_recursive(
-    name: &str,
-    config: &MontrsConfig,
-    executed: &mut HashSet<String>,
-) -> anyhow::Result<()> {
-    if executed.contains(name) {
-        return Ok(());
-    }
+struct Pipeline<'a> {
+    config: &'a MontrsConfig,
+    cache: TaskCache,
+    force: bool,
+    /// Tasks already run, with the cache key of their result (`None` when
+    /// the task has no inputs).
+    executed: HashMap<String, Option<String>>,
+    cached_runs: usize,
+    hits: usize,
+}
 
-    let task = config
-        .tasks
-        .get(name)
-        .ok_or_else(|| anyhow::anyhow!("Task '{}' not found in montrs.toml", name))?;
+/// A task as run: simple tasks have no environment, inputs or directories.
+struct Spec<'a> {
+    command: &'a str,
+    env: Cow<'a, HashMap<String, String>>,
+    description: Option<&'a str>,
+    inputs: &'a [String],
+    outputs: &'a [String],
+}
 
-    // 1. Run dependencies first
-    if let TaskConfig::Detailed { dependencies, .. } = task {
-        for dep in dependencies {
-            Box::pin(execute_task_recursive(dep, config, executed)).await?;
+impl Pipeline<'_> {
+    fn execute(&mut self, name: &str) -> anyhow::Result<Option<String>> {
+        if let Some(key) = self.executed.get(name) {
+            return Ok(key.clone());
         }
-    }
-
-    // 2. Run the task itself
-    println!(
-        "{} Running task: {}",
-        style("🛠").bold(),
-        style(name).cyan().bold()
-    );
 
-    match task {
-        TaskConfig::Simple(cmd_str) => {
-            run_shell_cmd(cmd_str, &HashMap::new())?;
+        let config = self.config;
+        let task = config
+            .tasks
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("Task '{}' not found in montrs.toml", name))?;
+
+        // 1. Run dependencies first; their keys become part of this task's.
+        let mut upstream = Vec::new();
+        if let TaskConfig::Detailed { dependencies, .. } = task {
+            for dep in dependencies {
+                let key = self.execute(dep)?;
+                upstream.push(format!("{}={}", dep, key.as_deref().unwrap_or("uncached")));
+            }
         }
-        TaskConfig::Detailed {
-            command,
-            env,
-            description,
-            ..
-        } => {
-            if let Some(desc) = description {
-                println!("   {}", style(desc).italic().dim());
+
+        // 2. Run the task itself, once per directory for `each` tasks
+        let (spec, each) = match task {
+            TaskConfig::Simple(command) => {
+                (Spec { command, env: Cow::Owned(HashMap::new()), description: None, inputs: &[], outputs: &[] }, None)
+            }
+            TaskConfig::Detailed { command, env, description, inputs, outputs, each, .. } => (
+                Spec { command, env: Cow::Borrowed(env), description: description.as_deref(), inputs, outputs },
+                each.as_deref(),
+            ),
+        };
+        let dirs = match each {
+            Some(pattern) => {
+                let dirs = matching_dirs(Path::new("."), pattern);
+                if dirs.is_empty() {
+                    anyhow::bail!("Task '{}' runs in each of '{}', but no directory matches", name, pattern);
+                }
+                dirs.into_iter().map(Some).collect()
             }
-            run_shell_cmd(command, env)?;
+            None => vec![None],
+        };
+
+        let mut keys = Vec::new();
+        for dir in &dirs {
+            keys.push(self.run_once(name, dir.as_deref(), &spec, &upstream)?);
         }
+        let key = keys.into_iter().collect::<Option<Vec<_>>>().map(|keys| keys.join("+"));
+        self.executed.insert(name.to_string(), key.clone());
+        Ok(key)
     }
 
-    executed.insert(name.to_string());
-    Ok(())
+    /// Runs `spec` in `dir`, or restores its outputs from the cache.
+    fn run_once(&mut self, name: &str, dir: Option<&str>, spec: &Spec, upstream: &[String]) -> anyhow::Result<Option<String>> {
+        let label = match dir {
+            Some(dir) => format!("task {} [{}]", name, dir),
+            None => format!("task {}", name),
+        };
+        let expand = |s: &str| s.replace("{dir}", dir.unwrap_or("."));
+        let command = expand(spec.command);
+
+        let key = if spec.inputs.is_empty() {
+            None
+        } else {
+            let inputs: Vec<String> = spec.inputs.iter().map(|s| expand(s)).collect();
+            Some(self.cache.key(&label, &command, &spec.env, &inputs, upstream)?)
+        };
+        if let Some(key) = &key {
+            self.cached_runs += 1;
+            if !self.force && self.cache.restore(key)? {
+                self.hits += 1;
+                reporter().info(format!("{} {} {}", style("↺").cyan(), label, style("cache hit").dim()));
+                reporter().step(label).skip("cache hit");
+                return Ok(Some(key.clone()));
+            }
+        }
+
+        let step = reporter().stream_step(label);
+        if let Some(desc) = spec.description {
+            reporter().info(format!("   {}", style(desc).italic().dim()));
+        }
+        run_shell_cmd(&command, &spec.env, dir)?;
+        if let Some(key) = &key {
+            let outputs: Vec<String> = spec.outputs.iter().map(|s| expand(s)).collect();
+            self.cache.store(key, name, &outputs)?;
+        }
+        step.finish();
+        Ok(key)
+    }
 }
 
-fn run_shell_cmd(cmd_str: &str, env_vars: &HashMap<String, String>) -> anyhow::Result<()> {
+fn run_shell_cmd(cmd_str: &str, env_vars: &HashMap<String, String>, dir: Option<&str>) -> anyhow::Result<()> {
     #[cfg(windows)]
     let mut cmd = Command::new("powershell");
     #[cfg(windows)]
@@ -79,13 +163,17 @@ fn run_shell_cmd(cmd_str: &str, env_vars: &HashMap<String, String>) -> anyhow::R
     for (key, val) in env_vars {
         cmd.env(key, val);
     }
+    if let Some(dir) = dir {
+        cmd.current_dir(dir);
+    }
 
     exe_command(&mut cmd)?;
     Ok(())
 }
 
 pub async fn list() -> anyhow::Result<()> {
-    let config = MontrsConfig::load()?;
+    let mut config = MontrsConfig::load()?;
+    crate::plugin::merge_tasks(&mut config);
 
     if config.tasks.is_empty() {
         println!("No tasks defined in montrs.toml");
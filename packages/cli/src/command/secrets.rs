@@ -0,0 +1,166 @@
+//! Secrets command.
+//!
+//! Manages the encrypted secrets file (`[secrets].file`, default `secrets.enc.toml`).
+//! Values are encrypted with age to every recipient listed in the file, so the file
+//! can be committed. The private key comes from `MONTRS_SECRETS_KEY`, a key file
+//! named by `MONTRS_SECRETS_KEY_FILE`, or the OS keychain.
+
+use crate::SecretsSubcommand;
+use crate::config::MontrsConfig;
+use crate::dryrun;
+use crate::report::{ActionKind, Planned};
+use anyhow::{Context, Result};
+use console::style;
+use montrs_core::secrets::{SECRETS_KEY_VAR, SecretKey, SecretsFile};
+use std::io::Read;
+use std::path::Path;
+
+pub async fn run(subcommand: SecretsSubcommand, config: &MontrsConfig) -> Result<()> {
+    let path = Path::new(&config.secrets.file);
+    let project = &config.project.name;
+
+    match subcommand {
+        SecretsSubcommand::Init => init(path, project),
+        SecretsSubcommand::Set { key, value } => {
+            let mut file = open(path)?;
+            let value = match value {
+                Some(v) => v,
+                None => read_stdin()?,
+            };
+            let existed = file.contains(&key);
+            file.set(&key, &value)?;
+            if save(&file, path, format!("{} {}", if existed { "update" } else { "add" }, key))? {
+                println!(
+                    "{} {} {}",
+                    style("✔").green(),
+                    if existed { "Updated" } else { "Added" },
+                    style(&key).cyan()
+                );
+            }
+            Ok(())
+        }
+        SecretsSubcommand::Get { key } => {
+            let file = open(path)?;
+            let secret_key = SecretKey::resolve(project)?;
+            match file.get(&key, &secret_key)? {
+                Some(value) => {
+                    println!("{}", value);
+                    Ok(())
+                }
+                None => anyhow::bail!("No secret named '{}' in {}", key, path.display()),
+            }
+        }
+        SecretsSubcommand::List => {
+            let file = open(path)?;
+            if file.keys().next().is_none() {
+                println!("No secrets in {}", path.display());
+            }
+            for key in file.keys() {
+                println!("  - {}", style(key).cyan());
+            }
+            Ok(())
+        }
+        SecretsSubcommand::Rm { key } => {
+            let mut file = open(path)?;
+            if !file.remove(&key) {
+                anyhow::bail!("No secret named '{}' in {}", key, path.display());
+            }
+            if save(&file, path, format!("remove {}", key))? {
+                println!("{} Removed {}", style("✔").green(), style(&key).cyan());
+            }
+            Ok(())
+        }
+        SecretsSubcommand::AddRecipient { public_key } => {
+            let mut file = open(path)?;
+            if !file.add_recipient(&public_key)? {
+                println!("{} {} is already a recipient", style("⚠").yellow(), public_key);
+                return Ok(());
+            }
+            let secret_key = SecretKey::resolve(project)
+                .context("Re-encrypting existing secrets for the new recipient requires your key")?;
+            file.rekey(&secret_key)?;
+            if save(&file, path, format!("add recipient {} and re-encrypt every secret", public_key))? {
+                println!("{} Added recipient and re-encrypted secrets", style("✔").green());
+            }
+            Ok(())
+        }
+    }
+}
+
+fn open(path: &Path) -> Result<SecretsFile> {
+    SecretsFile::load(path).with_context(|| {
+        format!("Failed to read {}. Run `montrs secrets init` first.", path.display())
+    })
+}
+
+/// Writes the file, or plans `change` to it on a dry run. Returns whether it was written.
+fn save(file: &SecretsFile, path: &Path, change: String) -> Result<bool> {
+    if dryrun::enabled() {
+        dryrun::plan(Planned::new(ActionKind::WriteFile, path.display().to_string()).with_detail(change));
+        return Ok(false);
+    }
+    file.save(path)?;
+    Ok(true)
+}
+
+fn read_stdin() -> Result<String> {
+    let mut value = String::new();
+    std::io::stdin().read_to_string(&mut value)?;
+    Ok(value.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Creates or reuses this machine's key and adds it as a recipient.
+fn init(path: &Path, project: &str) -> Result<()> {
+    let key = match SecretKey::resolve(project) {
+        Ok(key) => key,
+        // The key is only needed for its public half, to plan the recipient change.
+        Err(_) if dryrun::enabled() => {
+            dryrun::plan(Planned::new(ActionKind::StoreKey, "OS keychain").with_detail(format!("a new key for {}", project)));
+            SecretKey::generate()
+        }
+        Err(_) => {
+            let key = SecretKey::generate();
+            match key.store_in_keychain(project) {
+                Ok(()) => println!(
+                    "{} Generated a new key and stored it in the OS keychain",
+                    style("✔").green()
+                ),
+                Err(e) => println!("{} Could not store the key in the OS keychain: {}", style("⚠").yellow(), e),
+            }
+            // Keychains are per machine (and per login session on Linux), so the
+            // key must also be saved somewhere durable.
+            println!("   Back up this key; it is the only way to decrypt the secrets elsewhere:");
+            println!("   export {}={}", SECRETS_KEY_VAR, key.expose());
+            key
+        }
+    };
+
+    let mut file = if path.exists() { open(path)? } else { SecretsFile::default() };
+    let public_key = key.public_key();
+    let is_recipient = file.recipients.contains(&public_key);
+    if !is_recipient && file.keys().next().is_some() {
+        // Existing secrets are encrypted to other keys; only a current recipient can re-encrypt them.
+        println!(
+            "{} {} already holds secrets. Ask a teammate to run:",
+            style("⚠").yellow(),
+            path.display()
+        );
+        println!("   montrs secrets add-recipient {}", public_key);
+        return Ok(());
+    }
+    if file.add_recipient(&public_key)? {
+        if !save(&file, path, "add this machine's key as a recipient".to_string())? {
+            return Ok(());
+        }
+        println!(
+            "{} Added {} as a recipient in {}",
+            style("✔").green(),
+            style(&public_key).cyan(),
+            path.display()
+        );
+    } else {
+        println!("{} {} is already set up", style("✔").green(), path.display());
+    }
+    println!("Your public key: {}", public_key);
+    Ok(())
+}
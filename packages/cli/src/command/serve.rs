@@ -1,21 +1,233 @@
-use crate::config::MontrsConfig;
+use crate::config::{MontrsConfig, ProjectConfig};
+use crate::devproc::{self, Supervisor};
+use crate::devproxy::dashboard::DASHBOARD_PATH;
+use crate::devproxy::capture::captures_dir;
+use crate::devproxy::{self, Capturer, Dashboard, DevProxy, ProxyRule};
+use anyhow::Context;
+use crate::devproxy::cert::{CertSource, DevCert};
+use crate::report::reporter;
 use crate::utils::run_cargo_leptos;
+use console::style;
+use montrs_core::analytics::{ANALYTICS_FILE, ANALYTICS_VAR};
+use montrs_core::devstate::{DEV_STATE_FILE, DEV_STATE_VAR};
+use montrs_core::mock::{MOCK_ROUTES_VAR, MOCKS_DIR_VAR, MockSelection, Mocks};
+use montrs_core::profile::PROFILE_VAR;
+use montrs_core::signal_graph::{SIGNAL_GRAPH_FILE, SIGNAL_GRAPH_VAR};
 
-pub async fn run() -> anyhow::Result<()> {
+/// `project` carries the global flags (`--release`, `--features`, ...) that
+/// the dev processes are started with.
+pub async fn run(mock: Option<String>, profile: bool, tls: bool, capture: bool, project: ProjectConfig) -> anyhow::Result<()> {
     let mut config = MontrsConfig::load()?;
+    config.project = project;
 
-    // Handle tailwind.toml
-    if let Ok(Some(js_path)) = crate::config::tailwind::ensure_tailwind_config(
-        std::path::Path::new("."),
-        config.project.tailwind_style.unwrap_or_default(),
-    ) {
-        if config.build.tailwind_config_file.is_none() {
-            config.build.tailwind_config_file = Some(js_path.to_string_lossy().into_owned());
+    crate::utils::prepare_tailwind(&mut config);
+
+    if let Some(routes) = mock {
+        enable_mocks(&config.serve.mocks_dir, &routes)?;
+    }
+    if profile {
+        let dir = std::env::current_dir()?.join(super::profile::PROFILE_DIR);
+        reporter().info(format!(
+            "{} Profiling routes; run `montrs profile` to see the slowest ({})",
+            style("✔").green(),
+            dir.display()
+        ));
+        unsafe {
+            std::env::set_var(PROFILE_VAR, dir);
         }
     }
 
+    crate::crash::watch_app(&config.crash, std::env::current_dir()?);
+    let rules = ProxyRule::from_config(&config.serve.proxy)?;
+    let dashboard = if config.serve.dashboard { Some(enable_dashboard()?) } else { None };
+    let capture = if capture { Some(enable_capture(&config)?) } else { None };
+    if tls {
+        start_tls_proxy(&config, rules, dashboard, capture)?;
+    } else if !rules.is_empty() || dashboard.is_some() || capture.is_some() {
+        start_api_proxy(&config, rules, dashboard, capture)?;
+    }
+
+    if config.serve.orchestrate {
+        let processes = devproc::processes(&config)?;
+        let step = reporter().stream_step("dev processes");
+        Supervisor::new(processes, config.serve.max_restarts).run().await?;
+        step.finish();
+        return Ok(());
+    }
+
     // "serve" in montrs usually implies watching/running the server.
     // We map it to "watch" as cargo-leptos doesn't have a standalone "serve" command exposed clearly via CLI
     // other than running the binary, but "watch" is safer for dev.
-    run_cargo_leptos("watch", &[], &config).await
+    // Orchestrated, the `watch` child starts the build workers itself.
+    crate::workers::start(&config, std::env::current_dir()?);
+    let step = reporter().stream_step("dev server");
+    run_cargo_leptos("watch", &[], &config).await?;
+    step.finish();
+    Ok(())
+}
+
+/// Puts an HTTPS front end on `[serve.tls] port` in front of the app and
+/// tells the live-reload script to connect through it over `wss://`.
+fn start_tls_proxy(
+    config: &MontrsConfig,
+    rules: Vec<ProxyRule>,
+    dashboard: Option<Dashboard>,
+    capture: Option<Capturer>,
+) -> anyhow::Result<()> {
+    let step = reporter().step("tls certificate");
+    let cert = DevCert::resolve(&config.serve.tls)?;
+    let server_config = cert.server_config()?;
+    step.finish();
+    if cert.source == CertSource::SelfSigned {
+        reporter().warn(format!(
+            "Using a self-signed certificate ({}); install mkcert for one your browser trusts",
+            cert.cert.display()
+        ));
+    }
+
+    let listen: std::net::SocketAddr = format!("{}:{}", config.serve.addr, config.serve.tls.port).parse()?;
+    let app = devproxy::app_addr(config);
+    let mut proxy = DevProxy::new(listen, app.clone())
+        .with_tls(server_config)
+        .with_reload(devproxy::reload_addr(config))
+        .with_rules(rules.clone());
+    let has_dashboard = dashboard.is_some();
+    if let Some(dashboard) = dashboard {
+        proxy = proxy.with_dashboard(dashboard);
+    }
+    if let Some(capture) = capture {
+        proxy = proxy.with_capture(capture);
+    }
+    unsafe {
+        std::env::set_var("LEPTOS_RELOAD_WS_PROTOCOL", "wss");
+        std::env::set_var("LEPTOS_RELOAD_EXTERNAL_PORT", config.serve.tls.port.to_string());
+    }
+    tokio::spawn(async move {
+        if let Err(e) = proxy.run().await {
+            reporter().warn(format!("HTTPS proxy stopped: {:#}", e));
+        }
+    });
+
+    let host = if config.serve.addr == "127.0.0.1" { "localhost" } else { config.serve.addr.as_str() };
+    reporter().info(format!(
+        "{} Serving https://{}:{} (HTTP/2) -> http://{}",
+        style("✔").green(),
+        host,
+        config.serve.tls.port,
+        app
+    ));
+    report_rules(&rules);
+    if has_dashboard {
+        report_dashboard(&format!("https://{}:{}", host, config.serve.tls.port));
+    }
+    Ok(())
+}
+
+/// Serves `[serve.proxy]` on the app's own address. The app moves to a free
+/// local port, so the browser keeps using the URL it already knows.
+fn start_api_proxy(
+    config: &MontrsConfig,
+    rules: Vec<ProxyRule>,
+    dashboard: Option<Dashboard>,
+    capture: Option<Capturer>,
+) -> anyhow::Result<()> {
+    let public = devproxy::app_addr(config);
+    let listen: std::net::SocketAddr = public
+        .parse()
+        .with_context(|| format!("The site address '{}' is not an IP address and port", public))?;
+    let internal = std::net::TcpListener::bind((listen.ip(), 0))?.local_addr()?;
+    unsafe {
+        std::env::set_var("LEPTOS_SITE_ADDR", internal.to_string());
+    }
+
+    let mut proxy = DevProxy::new(listen, internal.to_string()).with_rules(rules.clone());
+    let has_dashboard = dashboard.is_some();
+    if let Some(dashboard) = dashboard {
+        proxy = proxy.with_dashboard(dashboard);
+    }
+    if let Some(capture) = capture {
+        proxy = proxy.with_capture(capture);
+    }
+    tokio::spawn(async move {
+        if let Err(e) = proxy.run().await {
+            reporter().warn(format!("Dev proxy stopped: {:#}", e));
+        }
+    });
+    reporter().info(format!("{} Serving http://{} -> http://{}", style("✔").green(), public, internal));
+    report_rules(&rules);
+    if has_dashboard {
+        report_dashboard(&format!("http://{}", public));
+    }
+    Ok(())
+}
+
+fn report_rules(rules: &[ProxyRule]) {
+    for rule in rules {
+        reporter().info(format!("    {} -> {}", rule.prefix, rule.target));
+    }
+}
+
+/// Tells the app where to write its routes and feature flags at boot, and
+/// its signal graph and route usage while it runs, for the `/_montrs` dashboard.
+fn enable_dashboard() -> anyhow::Result<Dashboard> {
+    let root = std::env::current_dir()?;
+    let state_file = root.join(DEV_STATE_FILE);
+    let graph_file = root.join(SIGNAL_GRAPH_FILE);
+    let analytics_file = root.join(ANALYTICS_FILE);
+    // Files left by an earlier session would describe an app that is not running.
+    let _ = std::fs::remove_file(&state_file);
+    let _ = std::fs::remove_file(&graph_file);
+    let _ = std::fs::remove_file(&analytics_file);
+    unsafe {
+        std::env::set_var(DEV_STATE_VAR, &state_file);
+        std::env::set_var(SIGNAL_GRAPH_VAR, &graph_file);
+        std::env::set_var(ANALYTICS_VAR, &analytics_file);
+    }
+    Ok(Dashboard::new(root, state_file).with_signal_graph(graph_file).with_analytics(analytics_file))
+}
+
+/// Records the requests the dev proxy forwards into `.agent/captures`.
+fn enable_capture(config: &MontrsConfig) -> anyhow::Result<Capturer> {
+    let dir = captures_dir(&std::env::current_dir()?);
+    let pkg_dir = devproxy::leptos_metadata("site-pkg-dir")
+        .and_then(|v| v.as_str().map(String::from))
+        .unwrap_or_else(|| config.build.site_pkg_name.clone());
+    reporter().info(format!(
+        "{} Capturing requests to {}; replay one with `montrs replay <file>`",
+        style("✔").green(),
+        dir.display()
+    ));
+    Ok(Capturer::new(dir, &pkg_dir))
+}
+
+fn report_dashboard(base: &str) {
+    reporter().info(format!("{} Dashboard at {}{}", style("✔").green(), base, DASHBOARD_PATH));
+}
+
+/// Validates the mock files up front and hands the selection to the app
+/// through the environment; `AppSpec::new` picks it up in the server process.
+fn enable_mocks(dir: &str, routes: &str) -> anyhow::Result<()> {
+    let selection = MockSelection::parse(routes);
+    let mocks = Mocks::load_dir(dir)?.with_selection(selection.clone());
+    let active = mocks.routes();
+
+    if active.is_empty() {
+        reporter().warn(format!("No mocks found in {}/ for the selected routes", dir));
+    } else {
+        reporter().info(format!("{} Mocking {} route(s) from {}/:", style("✔").green(), active.len(), dir));
+        for route in &active {
+            reporter().info(format!("    {}", route));
+        }
+    }
+    if let MockSelection::Only(paths) = &selection {
+        for path in paths.iter().filter(|p| !active.contains(&p.as_str())) {
+            reporter().warn(format!("No mock defined for {}", path));
+        }
+    }
+
+    unsafe {
+        std::env::set_var(MOCKS_DIR_VAR, std::fs::canonicalize(dir).unwrap_or_else(|_| dir.into()));
+        std::env::set_var(MOCK_ROUTES_VAR, routes);
+    }
+    Ok(())
 }
@@ -1,5 +1,14 @@
+use crate::config::ProjectConfig;
+use crate::dryrun;
+use crate::report::reporter;
 use montrs_agent::AgentManager;
-use anyhow::Result;
+use anyhow::{Context, Result};
+use console::style;
+use montrs_core::SpecFingerprint;
+use montrs_core::fingerprint::{SPEC_FINGERPRINT_OUT_VAR, SPEC_MANIFEST_FILE};
+
+/// Where the app writes its fingerprint for `spec --fingerprint`.
+const FINGERPRINT_OUT: &str = "target/montrs/spec-fingerprint.json";
 
 pub async fn run(include_docs: bool, format: String) -> Result<()> {
     let output = run_to_string(include_docs, format).await?;
@@ -35,7 +44,37 @@ pub async fn run_to_string(include_docs: bool, format: String) -> Result<String>
         // AgentManager already includes some documentation, but we can add more if needed
     }
 
+    let snapshot = manager.redact_snapshot(&snapshot)?;
+
+    if matches!(format.as_str(), "jsonl" | "sqlite") && !manager.is_persistent() {
+        anyhow::bail!(
+            "The {} snapshot is written to the agent directory, but agent artifacts are kept in memory; set {} to a writable directory",
+            format,
+            montrs_agent::storage::AGENT_DIR_VAR
+        );
+    }
+
     let output = match format.as_str() {
+        "jsonl" => {
+            manager.write_snapshot(&snapshot, "jsonl")?;
+            format!(
+                "Wrote {} and {} snippet files to {}",
+                montrs_agent::snapshot::SNAPSHOT_JSONL,
+                snapshot.documentation_snippets.len(),
+                manager.agent_dir().join(montrs_agent::snapshot::SNIPPETS_DIR).display()
+            )
+        }
+        "md" | "markdown" => {
+            manager.write_snapshot(&snapshot, "md")?;
+            montrs_agent::export::to_markdown(&snapshot)
+        }
+        "sqlite" => {
+            manager.write_snapshot(&snapshot, "sqlite")?;
+            format!(
+                "Wrote {}",
+                manager.agent_dir().join(montrs_agent::export::SNAPSHOT_SQLITE).display()
+            )
+        }
         "yaml" => serde_yaml::to_string(&snapshot)?,
         "txt" => format!("{:#?}", snapshot),
         _ => serde_json::to_string_pretty(&snapshot)?,
@@ -43,3 +82,58 @@ pub async fn run_to_string(include_docs: bool, format: String) -> Result<String>
 
     Ok(output)
 }
+
+/// Runs the app with `cargo run` until `AppSpec::boot` has registered its
+/// routes and written the fingerprint, then saves it to
+/// `montrs.fingerprint.json`. With `check`, fails instead when it differs
+/// from the committed file.
+pub async fn fingerprint(check: bool, project: &ProjectConfig) -> Result<()> {
+    let root = std::env::current_dir()?;
+    let out = root.join(FINGERPRINT_OUT);
+    let _ = std::fs::remove_file(&out);
+
+    let step = reporter().stream_step("boot app");
+    let mut cmd = tokio::process::Command::new("cargo");
+    cmd.arg("run");
+    if project.release {
+        cmd.arg("--release");
+    }
+    for feature in &project.features {
+        cmd.args(["--features", feature]);
+    }
+    let status = cmd.env(SPEC_FINGERPRINT_OUT_VAR, &out).status().await.context("Failed to run cargo")?;
+    if !status.success() || !out.exists() {
+        step.fail();
+        anyhow::bail!(
+            "The app exited without writing its fingerprint; make sure its server binary calls `AppSpec::boot` \
+             (pass --features if it needs e.g. `ssr`)"
+        );
+    }
+    step.finish();
+    let actual = SpecFingerprint::read_from(&out).with_context(|| format!("Failed to read {}", out.display()))?;
+
+    let manifest = root.join(SPEC_MANIFEST_FILE);
+    if check {
+        let expected = SpecFingerprint::read_from(&manifest)
+            .with_context(|| format!("Failed to read {}; run `montrs spec --fingerprint` first", SPEC_MANIFEST_FILE))?;
+        let changes = actual.changes(&expected);
+        if !changes.is_empty() {
+            for change in &changes {
+                reporter().output(format!("  {} {}", style("✘").red(), change));
+            }
+            anyhow::bail!(
+                "Fingerprint {} differs from the committed {}; rerun `montrs spec --fingerprint` if the change is intended",
+                actual,
+                expected
+            );
+        }
+        reporter().info(format!("{} Fingerprint {} matches {}", style("✔").green(), actual, SPEC_MANIFEST_FILE));
+        return Ok(());
+    }
+
+    dryrun::write(&manifest, serde_json::to_string_pretty(&actual)? + "\n")?;
+    if !dryrun::enabled() {
+        reporter().info(format!("{} Wrote fingerprint {} to {}", style("✔").green(), actual, SPEC_MANIFEST_FILE));
+    }
+    Ok(())
+}
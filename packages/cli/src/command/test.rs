@@ -5,6 +5,11 @@
 //! automated environment setup.
 
 use crate::config::MontrsConfig;
+use crate::report::reporter;
+use anyhow::Context;
+use cargo_metadata::MetadataCommand;
+use montrs_agent::AgentManager;
+use montrs_agent::impact::{TestImpact, WorkspacePackage};
 use std::process::Stdio;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use quick_xml::events::{BytesDecl, BytesStart, Event};
@@ -24,11 +29,13 @@ use quick_xml::Writer;
 /// * `report` - The format of the report to generate ("human", "json", "junit").
 /// * `output` - Optional path to write the report file.
 /// * `jobs` - Number of parallel jobs to run.
+/// * `changed_since` - Only test packages affected by changes since this git revision.
 pub async fn run(
     filter: Option<String>,
     report: String,
     output: Option<String>,
     jobs: Option<usize>,
+    changed_since: Option<String>,
 ) -> anyhow::Result<()> {
     // If human report and no special processing, and no filter/jobs,
     // delegate to cargo-leptos to handle wasm/server split correctly if possible.
@@ -39,9 +46,21 @@ pub async fn run(
     // Load config just to ensure valid project
     let _ = MontrsConfig::load()?;
 
-    println!("Running MontRS Unit Tests...");
-    
-    let mut args = vec!["test".to_string(), "--workspace".to_string()];
+    let mut args = vec!["test".to_string()];
+    match changed_since {
+        Some(base) => {
+            let impact = test_impact(&base)?;
+            if impact.affected_packages.is_empty() {
+                reporter().step("cargo test").skip(format!("no package changed since {}", base));
+                return Ok(());
+            }
+            for package in &impact.affected_packages {
+                args.push("-p".to_string());
+                args.push(package.clone());
+            }
+        }
+        None => args.push("--workspace".to_string()),
+    }
     
     if let Some(f) = filter {
         args.push(f);
@@ -67,12 +86,17 @@ pub async fn run(
     
     if !use_json_internal {
         // Just wait for it
+        let step = reporter().stream_step("cargo test");
         let status = child.wait().await?;
         if !status.success() {
+            step.fail();
             anyhow::bail!("Tests failed");
         }
+        step.finish();
         return Ok(());
     }
+    let mut step = reporter().step("cargo test");
+    let (mut passed, mut failed) = (0, 0);
 
     // Process JSON output
     let stdout = child.stdout.take().unwrap();
@@ -98,7 +122,8 @@ pub async fn run(
                                 message: None,
                                 duration: json.get("exec_time").and_then(|v| v.as_f64()).unwrap_or(0.0),
                             });
-                            println!("PASS: {}", name);
+                            passed += 1;
+                            reporter().info(format!("PASS: {}", name));
                         },
                         "failed" => {
                             let stdout = json.get("stdout").and_then(|v| v.as_str());
@@ -108,10 +133,12 @@ pub async fn run(
                                 message: stdout.map(|s| s.to_string()),
                                 duration: 0.0,
                             });
-                            println!("FAIL: {}", name);
+                            failed += 1;
+                            reporter().info(format!("FAIL: {}", name));
                         },
                         _ => {}
                     }
+                    step.set_detail(format!("{} passed, {} failed", passed, failed));
                 } else if type_field == "suite" {
                     let event = json.get("event").and_then(|v| v.as_str()).unwrap_or("");
                      if event == "started" {
@@ -138,45 +165,90 @@ pub async fn run(
     if report == "junit" {
         let output_path = output.unwrap_or_else(|| "report.xml".to_string());
         generate_junit_report(&test_suites, &output_path)?;
-        println!("JUnit report generated at {}", output_path);
+        reporter().info(format!("JUnit report generated at {}", output_path));
     } else if report == "json" {
         let output_path = output.unwrap_or_else(|| "report.json".to_string());
         let f = std::fs::File::create(&output_path)?;
         serde_json::to_writer_pretty(f, &test_suites)?;
-        println!("JSON report generated at {}", output_path);
+        reporter().info(format!("JSON report generated at {}", output_path));
     }
 
     if !status.success() {
+        step.fail();
         anyhow::bail!("Tests failed");
     }
+    step.finish();
 
     Ok(())
 }
 
+/// Maps the files changed since `base` to the workspace packages they affect,
+/// records the result in `.agent/test-impact.json` and reports it.
+fn test_impact(base: &str) -> anyhow::Result<TestImpact> {
+    let metadata = MetadataCommand::new().no_deps().exec().context("Failed to read cargo metadata")?;
+    let root = metadata.workspace_root.as_std_path();
+    let members: Vec<_> = metadata.workspace_packages();
+    let packages: Vec<WorkspacePackage> = members
+        .iter()
+        .map(|p| {
+            let dir = p.manifest_path.parent().map(|d| d.as_std_path()).unwrap_or(root);
+            WorkspacePackage {
+                name: p.name.clone(),
+                dir: dir.strip_prefix(root).unwrap_or(dir).to_string_lossy().replace('\\', "/"),
+                dependencies: p
+                    .dependencies
+                    .iter()
+                    .filter(|d| members.iter().any(|m| m.name == d.name))
+                    .map(|d| d.name.clone())
+                    .collect(),
+            }
+        })
+        .collect();
+
+    let changed = montrs_agent::git::changed_files(root, base)
+        .with_context(|| format!("Could not diff against `{}`: not a git repository or unknown revision", base))?;
+    let impact = TestImpact::compute(base, &changed, &packages);
+    if let Err(e) = AgentManager::new(root).write_test_impact(&impact) {
+        reporter().warn(format!("Could not record test impact: {}", e));
+    }
+
+    let list = |names: &std::collections::BTreeSet<String>| names.iter().cloned().collect::<Vec<_>>().join(", ");
+    reporter().info(format!("{} file(s) changed since {}", changed.len(), base));
+    if impact.workspace_wide {
+        reporter().info("A workspace-wide file changed; testing every package");
+    } else if !impact.affected_packages.is_empty() {
+        reporter().info(format!("Changed: {}", list(&impact.changed_packages)));
+        reporter().info(format!("Affected: {}", list(&impact.affected_packages)));
+    }
+    Ok(impact)
+}
+
 #[derive(Default, serde::Serialize)]
-struct TestSuite {
-    name: String,
-    tests: Vec<TestCase>,
+pub(crate) struct TestSuite {
+    pub name: String,
+    pub tests: Vec<TestCase>,
 }
 
 #[derive(serde::Serialize)]
-struct TestCase {
-    name: String,
-    status: TestStatus,
-    message: Option<String>,
-    duration: f64,
+pub(crate) struct TestCase {
+    pub name: String,
+    pub status: TestStatus,
+    pub message: Option<String>,
+    pub duration: f64,
 }
 
 #[derive(serde::Serialize)]
-enum TestStatus {
+pub(crate) enum TestStatus {
     Pass,
     Fail,
-    #[allow(dead_code)]
     Ignored,
 }
 
 /// Generates a JUnit XML report from the test results.
-fn generate_junit_report(suites: &[TestSuite], path: &str) -> anyhow::Result<()> {
+pub(crate) fn generate_junit_report(suites: &[TestSuite], path: &str) -> anyhow::Result<()> {
+    if let Some(parent) = std::path::Path::new(path).parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
     let mut writer = Writer::new_with_indent(std::fs::File::create(path)?, b' ', 4);
     
     writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
@@ -186,9 +258,11 @@ fn generate_junit_report(suites: &[TestSuite], path: &str) -> anyhow::Result<()>
 
     for (i, suite) in suites.iter().enumerate() {
         let mut elem = BytesStart::new("testsuite");
-        elem.push_attribute(("name", format!("suite-{}", i).as_str()));
+        let name = if suite.name.is_empty() { format!("suite-{}", i) } else { suite.name.clone() };
+        elem.push_attribute(("name", name.as_str()));
         elem.push_attribute(("tests", suite.tests.len().to_string().as_str()));
         elem.push_attribute(("failures", suite.tests.iter().filter(|t| matches!(t.status, TestStatus::Fail)).count().to_string().as_str()));
+        elem.push_attribute(("skipped", suite.tests.iter().filter(|t| matches!(t.status, TestStatus::Ignored)).count().to_string().as_str()));
         
         writer.write_event(Event::Start(elem.clone()))?;
 
@@ -208,6 +282,10 @@ fn generate_junit_report(suites: &[TestSuite], path: &str) -> anyhow::Result<()>
                 }
                 writer.write_event(Event::End(failure.to_end()))?;
                 
+                writer.write_event(Event::End(t.to_end()))?;
+            } else if let TestStatus::Ignored = test.status {
+                writer.write_event(Event::Start(t.clone()))?;
+                writer.write_event(Event::Empty(BytesStart::new("skipped")))?;
                 writer.write_event(Event::End(t.to_end()))?;
             } else {
                 writer.write_event(Event::Empty(t))?;
@@ -220,3 +298,62 @@ fn generate_junit_report(suites: &[TestSuite], path: &str) -> anyhow::Result<()>
     writer.write_event(Event::End(root.to_end()))?;
     Ok(())
 }
+
+/// Reads the suites of a JUnit XML report, such as one written by
+/// [`generate_junit_report`].
+pub(crate) fn read_junit_report(path: &std::path::Path) -> anyhow::Result<Vec<TestSuite>> {
+    use quick_xml::Reader;
+
+    let xml = std::fs::read_to_string(path)?;
+    let mut reader = Reader::from_str(&xml);
+    let mut suites: Vec<TestSuite> = Vec::new();
+    loop {
+        let event = reader.read_event()?;
+        let (Event::Start(e) | Event::Empty(e)) = &event else {
+            if let Event::Eof = event {
+                break;
+            }
+            continue;
+        };
+        let attr = |key: &[u8]| {
+            e.try_get_attribute(key)
+                .ok()
+                .flatten()
+                .and_then(|a| a.decode_and_unescape_value(reader.decoder()).ok())
+                .map(|v| v.into_owned())
+        };
+        match e.name().as_ref() {
+            b"testsuite" => suites.push(TestSuite { name: attr(b"name").unwrap_or_default(), tests: Vec::new() }),
+            b"testcase" => {
+                let case = TestCase {
+                    name: attr(b"name").unwrap_or_default(),
+                    status: TestStatus::Pass,
+                    message: None,
+                    duration: attr(b"time").and_then(|t| t.parse().ok()).unwrap_or(0.0),
+                };
+                if suites.is_empty() {
+                    suites.push(TestSuite::default());
+                }
+                suites.last_mut().unwrap().tests.push(case);
+            }
+            b"failure" | b"error" => {
+                let mut message = attr(b"message");
+                if matches!(event, Event::Start(_)) {
+                    let text = reader.read_text(e.name())?;
+                    message = Some(quick_xml::escape::unescape(&text)?.into_owned());
+                }
+                if let Some(case) = suites.last_mut().and_then(|s| s.tests.last_mut()) {
+                    case.status = TestStatus::Fail;
+                    case.message = message;
+                }
+            }
+            b"skipped" => {
+                if let Some(case) = suites.last_mut().and_then(|s| s.tests.last_mut()) {
+                    case.status = TestStatus::Ignored;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(suites)
+}
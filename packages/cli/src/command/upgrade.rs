@@ -1,13 +1,86 @@
-use crate::config::MontrsConfig;
+//! Upgrade command.
+//!
+//! Installs the CLI from a release channel (stable via crates.io, nightly via git),
+//! honors `required_cli_version` pins from `montrs.toml`, and can install an
+//! offline artifact after verifying its sha256 checksum and minisign signature.
+//! The signature must verify against the release key built into the binary
+//! or the one in `[upgrade].public_key`; only `--insecure` installs without it.
+
+use crate::config::{MontrsConfig, ReleaseChannel};
+use anyhow::{Context, Result};
+use cargo_metadata::semver::{Version, VersionReq};
+use cargo_metadata::MetadataCommand;
+use console::style;
+use montrs_core::{CompatError, CrateVersion};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-pub async fn run() -> anyhow::Result<()> {
-    let _config = MontrsConfig::load().unwrap_or_default();
+/// The version of the running CLI.
+pub const CLI_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const NIGHTLY_GIT: &str = "https://github.com/afsall-labs/montrs";
 
-    println!("Upgrading montrs...");
+/// The minisign key release artifacts are signed with, embedded by release
+/// builds through `MONTRS_RELEASE_PUBLIC_KEY`.
+const RELEASE_PUBLIC_KEY: Option<&str> = option_env!("MONTRS_RELEASE_PUBLIC_KEY");
+
+/// Options accepted by `montrs upgrade`.
+#[derive(Debug, Default)]
+pub struct UpgradeOptions {
+    pub channel: Option<ReleaseChannel>,
+    pub version: Option<String>,
+    pub artifact: Option<PathBuf>,
+    pub sha256: Option<String>,
+    pub signature: Option<PathBuf>,
+    /// Install an artifact without checking its signature.
+    pub insecure: bool,
+    pub check: bool,
+}
+
+pub async fn run(opts: UpgradeOptions) -> Result<()> {
+    let config = MontrsConfig::load().unwrap_or_default();
+
+    if opts.check {
+        let mut warnings = version_warnings(&config);
+        if let Ok(Some(e)) = crate_compat() {
+            warnings.push(e.to_string());
+            warnings.extend(e.mismatches.iter().map(|m| m.suggestion()));
+        }
+        if warnings.is_empty() {
+            println!("{} montrs {} satisfies this workspace.", style("✔").green(), CLI_VERSION);
+        } else {
+            for w in &warnings {
+                println!("{} {}", style("⚠").yellow(), w);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(artifact) = &opts.artifact {
+        return install_artifact(&config, artifact, &opts);
+    }
+
+    let channel = opts.channel.unwrap_or(config.upgrade.channel);
+    let version = opts.version.clone().or_else(|| pinned_version(&config));
+
+    println!("Upgrading montrs ({} channel)...", channel);
 
     let mut cmd = Command::new("cargo");
-    cmd.args(["install", "montrs"]);
+    cmd.args(["install", "montrs", "--locked"]);
+    match channel {
+        ReleaseChannel::Stable => {
+            if let Some(v) = &version {
+                cmd.args(["--version", v]);
+            }
+        }
+        ReleaseChannel::Nightly => {
+            if version.is_some() {
+                anyhow::bail!("Version pinning is not supported on the nightly channel; use --channel stable");
+            }
+            cmd.args(["--git", &config.upgrade.nightly_git, "--force"]);
+        }
+    }
 
     let status = cmd.status()?;
 
@@ -18,3 +91,185 @@ pub async fn run() -> anyhow::Result<()> {
     }
     Ok(())
 }
+
+/// Converts `required_cli_version = "=0.2.1"` into an installable version.
+fn pinned_version(config: &MontrsConfig) -> Option<String> {
+    let req = config.project.required_cli_version.as_deref()?;
+    let exact = req.trim().strip_prefix('=')?;
+    Version::parse(exact.trim()).ok().map(|v| v.to_string())
+}
+
+/// Returns the default git source for nightly builds.
+pub fn default_nightly_git() -> String {
+    NIGHTLY_GIT.to_string()
+}
+
+/// Installs a pre-downloaded binary after verifying its checksum and its
+/// minisign signature, unless `--insecure` skips the signature.
+fn install_artifact(config: &MontrsConfig, artifact: &Path, opts: &UpgradeOptions) -> Result<()> {
+    let expected = match &opts.sha256 {
+        Some(hash) => hash.clone(),
+        None => {
+            let sidecar = sidecar(artifact, "sha256");
+            let content = std::fs::read_to_string(&sidecar).with_context(|| {
+                format!("No --sha256 given and no checksum file at {}", sidecar.display())
+            })?;
+            content.split_whitespace().next().unwrap_or_default().to_string()
+        }
+    };
+    verify_sha256(artifact, &expected)?;
+    println!("{} sha256 verified", style("✔").green());
+
+    if opts.insecure {
+        println!("{} --insecure: installing {} without checking its signature", style("⚠").yellow(), artifact.display());
+    } else {
+        let keys = trusted_keys(config);
+        if keys.is_empty() {
+            anyhow::bail!(
+                "This montrs build has no release key and [upgrade].public_key is not set, so {} cannot be verified; \
+                 set the key, or pass --insecure to install it unsigned",
+                artifact.display()
+            );
+        }
+        let signature = opts.signature.clone().unwrap_or_else(|| sidecar(artifact, "minisig"));
+        if !signature.exists() {
+            anyhow::bail!(
+                "No signature for {} at {}; pass --signature, or --insecure to install it unsigned",
+                artifact.display(),
+                signature.display()
+            );
+        }
+        verify_signed(artifact, &signature, &keys)?;
+        println!("{} signature verified", style("✔").green());
+    }
+
+    let current = std::env::current_exe().context("Failed to locate the running montrs binary")?;
+    let staged = current.with_extension("new");
+    std::fs::copy(artifact, &staged)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))?;
+    }
+    std::fs::rename(&staged, &current)
+        .with_context(|| format!("Failed to replace {}", current.display()))?;
+
+    println!("Successfully installed montrs from {}", artifact.display());
+    Ok(())
+}
+
+fn sidecar(artifact: &Path, ext: &str) -> PathBuf {
+    let mut name = artifact.as_os_str().to_os_string();
+    name.push(".");
+    name.push(ext);
+    PathBuf::from(name)
+}
+
+/// Checks that the file's sha256 digest matches `expected` (hex, case-insensitive).
+pub fn verify_sha256(path: &Path, expected: &str) -> Result<()> {
+    let bytes = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let actual = hex::encode(Sha256::digest(&bytes));
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        anyhow::bail!("Checksum mismatch for {}: expected {}, got {}", path.display(), expected.trim(), actual);
+    }
+    Ok(())
+}
+
+/// The keys an artifact may be signed with: the embedded release key and
+/// `[upgrade].public_key`, for builds signed by someone else.
+fn trusted_keys(config: &MontrsConfig) -> Vec<&str> {
+    RELEASE_PUBLIC_KEY
+        .into_iter()
+        .chain(config.upgrade.public_key.as_deref())
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+        .collect()
+}
+
+/// Verifies a minisign signature against any of `keys`.
+fn verify_signed(path: &Path, signature: &Path, keys: &[&str]) -> Result<()> {
+    let mut last = None;
+    for key in keys {
+        match verify_signature(path, signature, key) {
+            Ok(()) => return Ok(()),
+            Err(e) => last = Some(e),
+        }
+    }
+    Err(last.unwrap_or_else(|| anyhow::anyhow!("No key to verify {} with", path.display())))
+}
+
+/// Verifies a minisign signature against a base64 public key.
+pub fn verify_signature(path: &Path, signature: &Path, public_key: &str) -> Result<()> {
+    let key = minisign_verify::PublicKey::from_base64(public_key.trim())
+        .map_err(|e| anyhow::anyhow!("Invalid public key {}: {}", public_key.trim(), e))?;
+    let sig_text = std::fs::read_to_string(signature)
+        .with_context(|| format!("Failed to read signature {}", signature.display()))?;
+    let sig = minisign_verify::Signature::decode(&sig_text)
+        .map_err(|e| anyhow::anyhow!("Invalid signature file {}: {}", signature.display(), e))?;
+    let bytes = std::fs::read(path)?;
+    key.verify(&bytes, &sig, false)
+        .map_err(|e| anyhow::anyhow!("Signature verification failed for {}: {}", path.display(), e))
+}
+
+/// Compares the running CLI against `required_cli_version` and the
+/// `montrs-core` version used by the workspace.
+pub fn version_warnings(config: &MontrsConfig) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let Ok(cli) = Version::parse(CLI_VERSION) else {
+        return warnings;
+    };
+
+    if let Some(required) = &config.project.required_cli_version {
+        match VersionReq::parse(required) {
+            Ok(req) if !req.matches(&cli) => warnings.push(format!(
+                "montrs.toml requires CLI {} but {} is installed. Run `montrs upgrade`.",
+                required, cli
+            )),
+            Err(e) => warnings.push(format!("Invalid required_cli_version '{}': {}", required, e)),
+            _ => {}
+        }
+    }
+
+    if let Ok(metadata) = MetadataCommand::new().no_deps().exec() {
+        let core = metadata
+            .packages
+            .iter()
+            .flat_map(|p| p.dependencies.iter())
+            .find(|d| d.name == "montrs-core" || d.name == "montrs");
+        if let Some(dep) = core
+            && !dep.req.matches(&cli)
+            && dep.path.is_none()
+        {
+            warnings.push(format!(
+                "Workspace depends on {} {} but the CLI is {}. Mismatched versions may generate incompatible code.",
+                dep.name, dep.req, cli
+            ));
+        }
+    }
+
+    warnings
+}
+
+/// Checks the montrs crates Cargo resolved for the workspace, and this CLI,
+/// against the resolved `montrs-core`. `None` when they are compatible or the
+/// workspace does not use montrs-core.
+pub fn crate_compat() -> Result<Option<CompatError>> {
+    let metadata = MetadataCommand::new().exec().context("Failed to resolve the workspace dependencies")?;
+    let montrs = metadata.packages.iter().filter(|p| p.name == "montrs" || p.name.starts_with("montrs-"));
+    let Some(core) = montrs.clone().filter(|p| p.name == "montrs-core").map(|p| &p.version).max() else {
+        return Ok(None);
+    };
+    let mut crates: Vec<CrateVersion> = montrs
+        .filter(|p| p.name != "montrs-core" && p.name != "montrs-cli")
+        .map(|p| CrateVersion::new(p.name.clone(), p.version.to_string()))
+        .collect();
+    crates.push(crate::CRATE_VERSION);
+    Ok(montrs_core::compat::check(&core.to_string(), &crates).err())
+}
+
+/// Prints version mismatch warnings at startup.
+pub fn warn_on_mismatch(config: &MontrsConfig) {
+    for w in version_warnings(config) {
+        eprintln!("{} {}", style("⚠").yellow(), w);
+    }
+}
@@ -1,18 +1,15 @@
 use crate::config::MontrsConfig;
+use crate::report::reporter;
 use crate::utils::run_cargo_leptos;
 
 pub async fn run() -> anyhow::Result<()> {
     let mut config = MontrsConfig::load()?;
 
-    // Handle tailwind.toml
-    if let Ok(Some(js_path)) = crate::config::tailwind::ensure_tailwind_config(
-        std::path::Path::new("."),
-        config.project.tailwind_style.unwrap_or_default(),
-    ) {
-        if config.build.tailwind_config_file.is_none() {
-            config.build.tailwind_config_file = Some(js_path.to_string_lossy().into_owned());
-        }
-    }
+    crate::utils::prepare_tailwind(&mut config);
+    crate::workers::start(&config, std::env::current_dir()?);
 
-    run_cargo_leptos("watch", &[], &config).await
+    let step = reporter().stream_step("watch");
+    run_cargo_leptos("watch", &[], &config).await?;
+    step.finish();
+    Ok(())
 }
@@ -0,0 +1,398 @@
+//! Layered loading for `montrs.toml`.
+//!
+//! A config file may list other files under a top-level `include` key. Included
+//! files are merged over the including file in the order they are listed, so
+//! `include = ["montrs.local.toml"]` lets a developer override ports or database
+//! URLs without touching the committed config. Missing includes are skipped.
+//!
+//! After merging, every string value is interpolated: `${VAR}` is replaced by the
+//! environment variable `VAR` (an error if unset), `${VAR:-default}` falls back to
+//! `default`, and `$${` produces a literal `${`.
+//!
+//! Interpolated values stay strings. [`deserialize`] lets the field decide: a
+//! number or boolean field parses the string, so `port = "${PORT:-8080}"`
+//! works, while `password = "${DB_PASS}"` stays a string even if the password
+//! is `12345`.
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::de::value::{MapAccessDeserializer, MapDeserializer, SeqDeserializer};
+use serde::de::{DeserializeOwned, Error as _, IntoDeserializer, Unexpected, Visitor};
+use serde::{Deserialize, Deserializer, forward_to_deserialize_any};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use toml::{Table, Value};
+
+/// Top-level key listing files to merge over the current one.
+pub const INCLUDE_KEY: &str = "include";
+
+/// Reads `path`, merges its includes, and interpolates environment variables.
+pub fn read_layered(path: &Path) -> Result<Table> {
+    let mut stack = Vec::new();
+    let mut table = read_with_includes(path, &mut stack)?;
+    for (key, value) in table.iter_mut() {
+        interpolate_value(value).with_context(|| format!("In `{}` of {}", key, path.display()))?;
+    }
+    Ok(table)
+}
+
+fn read_with_includes(path: &Path, stack: &mut Vec<PathBuf>) -> Result<Table> {
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if stack.contains(&canonical) {
+        bail!("Config include cycle detected at {}", path.display());
+    }
+
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    let mut table: Table = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+
+    let includes = match table.remove(INCLUDE_KEY) {
+        None => Vec::new(),
+        Some(Value::String(s)) => vec![s],
+        Some(Value::Array(items)) => items
+            .into_iter()
+            .map(|v| match v {
+                Value::String(s) => Ok(s),
+                other => Err(anyhow!("`include` entries must be strings, found {}", other.type_str())),
+            })
+            .collect::<Result<_>>()?,
+        Some(other) => bail!("`include` must be a string or an array of strings, found {}", other.type_str()),
+    };
+
+    stack.push(canonical);
+    let base = path.parent().unwrap_or_else(|| Path::new("."));
+    for include in includes {
+        let include_path = base.join(&include);
+        if !include_path.exists() {
+            tracing::debug!("Skipping missing config include: {}", include_path.display());
+            continue;
+        }
+        let overlay = read_with_includes(&include_path, stack)?;
+        merge(&mut table, overlay);
+    }
+    stack.pop();
+
+    Ok(table)
+}
+
+/// Deep-merges `overlay` into `base`. Tables merge key by key; any other value
+/// (including arrays) in `overlay` replaces the one in `base`.
+pub fn merge(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(existing)), Value::Table(incoming)) => merge(existing, incoming),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn interpolate_value(value: &mut Value) -> Result<()> {
+    match value {
+        Value::String(s) => *s = interpolate(s)?,
+        Value::Array(items) => {
+            for item in items {
+                interpolate_value(item)?;
+            }
+        }
+        Value::Table(table) => {
+            for (_, item) in table.iter_mut() {
+                interpolate_value(item)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Expands `${VAR}` and `${VAR:-default}` in `input` using the process environment.
+pub fn interpolate(input: &str) -> Result<String> {
+    interpolate_with(input, |name| std::env::var(name).ok())
+}
+
+/// Like [`interpolate`], but resolves variables through `lookup`.
+pub fn interpolate_with(input: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let tail = &rest[pos..];
+        if let Some(after) = tail.strip_prefix("$${") {
+            out.push_str("${");
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix("${") {
+            let end = after
+                .find('}')
+                .ok_or_else(|| anyhow!("Unterminated `${{` in \"{}\"", input))?;
+            let expr = &after[..end];
+            let (name, default) = match expr.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (expr, None),
+            };
+            if name.is_empty() {
+                bail!("Empty variable name in \"{}\"", input);
+            }
+            // As in the shell, `:-` also applies the default when the variable is empty.
+            match (lookup(name), default) {
+                (Some(v), Some(d)) if v.is_empty() => out.push_str(d),
+                (Some(v), _) => out.push_str(&v),
+                (None, Some(d)) => out.push_str(d),
+                (None, None) => bail!("Environment variable `{}` is not set and has no default", name),
+            }
+            rest = &after[end + 1..];
+        } else {
+            out.push('$');
+            rest = &tail[1..];
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Deserializes a layered config table into `T`. Strings also fill number
+/// and boolean fields, see the module docs.
+pub fn deserialize<T: DeserializeOwned>(table: Table) -> Result<T, toml::de::Error> {
+    T::deserialize(Lenient(Value::Table(table)))
+}
+
+/// For number and boolean fields of untagged and internally tagged enums:
+/// serde reads those before it knows the field's type, so [`deserialize`]
+/// cannot parse their strings.
+pub fn from_str_or<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromStr,
+    T::Err: Display,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OrString<T> {
+        Value(T),
+        Text(String),
+    }
+    match OrString::<T>::deserialize(deserializer)? {
+        OrString::Value(value) => Ok(value),
+        OrString::Text(text) => text.parse().map_err(|e| D::Error::custom(format!("invalid value \"{}\": {}", text, e))),
+    }
+}
+
+/// A TOML value whose strings also deserialize as numbers and booleans.
+struct Lenient(Value);
+
+impl Lenient {
+    /// Parses a string for a `T` field; any other value is passed on as is.
+    fn parse<'de, V, T>(self, visitor: V, visit: impl FnOnce(V, T) -> Result<V::Value, toml::de::Error>) -> Result<V::Value, toml::de::Error>
+    where
+        V: Visitor<'de>,
+        T: FromStr,
+    {
+        match self.0 {
+            Value::String(s) => match s.parse() {
+                Ok(parsed) => visit(visitor, parsed),
+                Err(_) => Err(toml::de::Error::invalid_value(Unexpected::Str(&s), &visitor)),
+            },
+            other => Lenient(other).deserialize_any(visitor),
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de, toml::de::Error> for Lenient {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! parse_as {
+    ($($method:ident => $ty:ty, $visit:ident;)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, toml::de::Error> {
+                self.parse(visitor, |visitor, value: $ty| visitor.$visit(value))
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Lenient {
+    type Error = toml::de::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, toml::de::Error> {
+        match self.0 {
+            Value::String(s) => visitor.visit_string(s),
+            Value::Integer(i) => visitor.visit_i64(i),
+            Value::Float(f) => visitor.visit_f64(f),
+            Value::Boolean(b) => visitor.visit_bool(b),
+            Value::Datetime(d) => visitor.visit_string(d.to_string()),
+            Value::Array(items) => {
+                let mut seq = SeqDeserializer::new(items.into_iter().map(Lenient));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Value::Table(table) => {
+                let mut map = MapDeserializer::new(table.into_iter().map(|(key, value)| (key, Lenient(value))));
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+        }
+    }
+
+    parse_as! {
+        deserialize_bool => bool, visit_bool;
+        deserialize_i8 => i64, visit_i64;
+        deserialize_i16 => i64, visit_i64;
+        deserialize_i32 => i64, visit_i64;
+        deserialize_i64 => i64, visit_i64;
+        deserialize_u8 => u64, visit_u64;
+        deserialize_u16 => u64, visit_u64;
+        deserialize_u32 => u64, visit_u64;
+        deserialize_u64 => u64, visit_u64;
+        deserialize_f32 => f64, visit_f64;
+        deserialize_f64 => f64, visit_f64;
+    }
+
+    /// TOML has no null: a present value is always `Some`.
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, toml::de::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, toml::de::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, toml::de::Error> {
+        match self.0 {
+            Value::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Value::Table(table) => visitor.visit_enum(MapAccessDeserializer::new(MapDeserializer::new(
+                table.into_iter().map(|(key, value)| (key, Lenient(value))),
+            ))),
+            other => Lenient(other).deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        char str string bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{MontrsConfig, ProcessConfig};
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "PORT" => Some("9000".to_string()),
+            "EMPTY" => Some(String::new()),
+            "DB_PASS" => Some("12345".to_string()),
+            "TOKEN" => Some("true".to_string()),
+            _ => None,
+        }
+    }
+
+    fn write(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_interpolate_defaults_escapes_and_missing_vars() {
+        assert_eq!(interpolate_with("http://localhost:${PORT}/", lookup).unwrap(), "http://localhost:9000/");
+        assert_eq!(interpolate_with("${MISSING:-8080}", lookup).unwrap(), "8080");
+        assert_eq!(interpolate_with("${EMPTY:-fallback}", lookup).unwrap(), "fallback");
+        assert_eq!(interpolate_with("${EMPTY}", lookup).unwrap(), "");
+        assert_eq!(interpolate_with("$${PORT} costs $5", lookup).unwrap(), "${PORT} costs $5");
+
+        assert!(interpolate_with("${MISSING}", lookup).unwrap_err().to_string().contains("`MISSING` is not set"));
+        assert!(interpolate_with("${PORT", lookup).unwrap_err().to_string().contains("Unterminated"));
+        assert!(interpolate_with("${:-x}", lookup).unwrap_err().to_string().contains("Empty variable name"));
+    }
+
+    #[test]
+    fn test_includes_merge_in_order_and_skip_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "base.toml", "[serve]\nport = 3000\naddr = \"0.0.0.0\"\n");
+        write(dir.path(), "local.toml", "[serve]\nport = 4000\n");
+        let root = write(
+            dir.path(),
+            "montrs.toml",
+            "include = [\"base.toml\", \"missing.toml\", \"local.toml\"]\n[project]\nname = \"shop\"\n[serve]\nport = 2000\n",
+        );
+
+        let table = read_layered(&root).unwrap();
+        assert!(!table.contains_key(INCLUDE_KEY));
+        assert_eq!(table["project"]["name"].as_str(), Some("shop"));
+        assert_eq!(table["serve"]["port"].as_integer(), Some(4000));
+        assert_eq!(table["serve"]["addr"].as_str(), Some("0.0.0.0"));
+    }
+
+    #[test]
+    fn test_include_cycles_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = write(dir.path(), "montrs.toml", "include = \"a.toml\"\n");
+        write(dir.path(), "a.toml", "include = \"montrs.toml\"\n");
+        let error = read_layered(&root).unwrap_err();
+        assert!(format!("{:#}", error).contains("include cycle"), "{:#}", error);
+    }
+
+    #[test]
+    fn test_fields_decide_the_type_of_interpolated_values() {
+        #[derive(Debug, Deserialize)]
+        struct Database {
+            password: String,
+            token: String,
+            port: u16,
+            pool: Option<u64>,
+            tls: bool,
+        }
+        let mut table: Table =
+            toml::from_str("password = \"${DB_PASS}\"\ntoken = \"${TOKEN}\"\nport = \"${PORT}\"\npool = \"${POOL:-8}\"\ntls = \"${TOKEN}\"\n")
+                .unwrap();
+        for (_, value) in table.iter_mut() {
+            let Value::String(s) = value else { unreachable!() };
+            *s = interpolate_with(s, lookup).unwrap();
+        }
+
+        let database: Database = deserialize(table.clone()).unwrap();
+        assert_eq!(database.password, "12345");
+        assert_eq!(database.token, "true");
+        assert_eq!(database.port, 9000);
+        assert_eq!(database.pool, Some(8));
+        assert!(database.tls);
+
+        table.insert("port".to_string(), Value::String("http".to_string()));
+        assert!(deserialize::<Database>(table).unwrap_err().to_string().contains("\"http\""));
+    }
+
+    #[test]
+    fn test_strings_fill_number_and_boolean_fields_of_untagged_enums() {
+        let table: Table =
+            toml::from_str("[serve]\nport = \"9000\"\n[serve.processes.api]\ncommand = \"cargo run\"\nrestart = \"false\"\n").unwrap();
+        let config: MontrsConfig = deserialize(table).unwrap();
+        assert_eq!(config.serve.port, 9000);
+        assert!(matches!(config.serve.processes["api"], ProcessConfig::Detailed { restart: false, .. }));
+    }
+
+    #[test]
+    fn test_preset_strings_become_the_preset_types() {
+        let table: Table = toml::from_str(
+            "[preset]\ntarget = \"edge\"\n[preset.limits]\nrequests_per_second = \"50\"\n[preset.edge.limits]\nrequests_per_second = \"20\"\n",
+        )
+        .unwrap();
+        let config: MontrsConfig = deserialize(table).unwrap();
+        let (target, preset) = config.preset.resolve(None).unwrap();
+        assert_eq!(target, montrs_core::Target::Edge);
+        assert_eq!(preset.limits.requests_per_second, 20);
+        assert_eq!(config.preset.to_json().unwrap()["limits"]["requests_per_second"], 50);
+    }
+}
@@ -7,9 +7,10 @@
 use anyhow::{Context, Result};
 use cargo_metadata::MetadataCommand;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use montrs_fmt::FormatterSettings;
 
+pub mod loader;
 pub mod tailwind;
 
 /// The root configuration structure for a MontRS project.
@@ -35,6 +36,36 @@ pub struct MontrsConfig {
     /// Custom task definitions.
     #[serde(default)]
     pub tasks: HashMap<String, TaskConfig>,
+    /// CLI self-update settings.
+    #[serde(default)]
+    pub upgrade: UpgradeConfig,
+    /// CLI plugins, keyed by subcommand name.
+    #[serde(default)]
+    pub plugins: HashMap<String, PluginConfig>,
+    /// Plate registry and the plates `montrs plate add` installed.
+    #[serde(default)]
+    pub plates: PlatesConfig,
+    /// Encrypted secrets settings.
+    #[serde(default)]
+    pub secrets: SecretsConfig,
+    /// Startup time budget.
+    #[serde(default)]
+    pub boot: BootConfig,
+    /// Database connection and query checking.
+    #[serde(default)]
+    pub database: DatabaseConfig,
+    /// Where CLI logs go and how verbose they are.
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    /// Panic reporting for the CLI and the app it serves.
+    #[serde(default)]
+    pub crash: CrashConfig,
+    /// Demo data and browser settings for `montrs demo`.
+    #[serde(default)]
+    pub demo: DemoConfig,
+    /// Overrides of the target's CORS, security header, limit and asset defaults.
+    #[serde(default)]
+    pub preset: PresetConfig,
 }
 
 /// Project metadata and feature flags.
@@ -43,6 +74,9 @@ pub struct ProjectConfig {
     /// The name of the project (defaults to package name).
     #[serde(default = "default_app_name")]
     pub name: String,
+    /// Semver requirement the `montrs` CLI must satisfy (e.g. "^0.2" or "=0.2.1").
+    #[serde(default)]
+    pub required_cli_version: Option<String>,
 
     // Internal fields for cargo-leptos compatibility
     #[serde(skip)]
@@ -75,6 +109,7 @@ impl Default for ProjectConfig {
     fn default() -> Self {
         Self {
             name: default_app_name(),
+            required_cli_version: None,
             verbose: 0,
             log: Vec::new(),
             release: false,
@@ -119,6 +154,88 @@ pub struct BuildConfig {
     /// Browser compatibility query (default: "defaults").
     #[serde(default = "default_browserquery")]
     pub browserquery: String,
+    /// Shared compilation cache used by `montrs build` (`[build.cache]`).
+    #[serde(default)]
+    pub cache: BuildCacheConfig,
+    /// WASI component settings used by `montrs build --wasi` (`[build.wasi]`).
+    #[serde(default)]
+    pub wasi: WasiBuildConfig,
+}
+
+/// WASI component settings (`[build.wasi]`).
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct WasiBuildConfig {
+    /// The package exporting the component (default: the current package).
+    #[serde(default)]
+    pub package: Option<String>,
+}
+
+/// Compilation cache settings (`[build.cache]`).
+///
+/// Credentials are not read from here: sccache takes them from its usual
+/// environment variables (`AWS_ACCESS_KEY_ID`, `SCCACHE_REDIS_PASSWORD`, ...).
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct BuildCacheConfig {
+    /// The `RUSTC_WRAPPER` to build with: "auto" (sccache when installed,
+    /// the default), "sccache", "none", or the path of another wrapper.
+    /// A `RUSTC_WRAPPER` already set in the environment always wins.
+    #[serde(default = "default_cache_wrapper")]
+    pub wrapper: String,
+    /// Where sccache keeps its cache (default: local disk).
+    #[serde(default)]
+    pub backend: CacheBackend,
+    /// Bucket of the S3 or GCS backend, container of the Azure one.
+    #[serde(default)]
+    pub bucket: Option<String>,
+    /// Region of the S3 bucket.
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Endpoint of an S3-compatible store, or the URL of a Redis or WebDAV backend.
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// Key prefix in the remote cache, to share a bucket between projects.
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Directory of the local cache.
+    #[serde(default)]
+    pub dir: Option<String>,
+    /// Maximum size of the local cache, e.g. "20G".
+    #[serde(default)]
+    pub size: Option<String>,
+}
+
+impl Default for BuildCacheConfig {
+    fn default() -> Self {
+        Self {
+            wrapper: default_cache_wrapper(),
+            backend: CacheBackend::default(),
+            bucket: None,
+            region: None,
+            endpoint: None,
+            prefix: None,
+            dir: None,
+            size: None,
+        }
+    }
+}
+
+fn default_cache_wrapper() -> String {
+    "auto".to_string()
+}
+
+/// A storage backend of sccache.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheBackend {
+    #[default]
+    Local,
+    S3,
+    Gcs,
+    Azure,
+    Redis,
+    Webdav,
+    /// The GitHub Actions cache.
+    Gha,
 }
 
 impl Default for BuildConfig {
@@ -133,6 +250,8 @@ impl Default for BuildConfig {
             tailwind_config_file: None,
             style_file: None,
             browserquery: default_browserquery(),
+            cache: BuildCacheConfig::default(),
+            wasi: WasiBuildConfig::default(),
         }
     }
 }
@@ -166,6 +285,142 @@ pub struct ServeConfig {
     /// The address to bind to (default: "127.0.0.1").
     #[serde(default = "default_addr")]
     pub addr: String,
+    /// Directory of JSON mock files used by `serve --mock` (default: "mocks").
+    #[serde(default = "default_mocks_dir")]
+    pub mocks_dir: String,
+    /// HTTPS front end used by `serve --tls`.
+    #[serde(default)]
+    pub tls: TlsConfig,
+    /// Backends the dev server forwards to, keyed by path prefix (e.g. "/api").
+    #[serde(default)]
+    pub proxy: HashMap<String, ProxyConfig>,
+    /// Run the app, front-end and CSS watchers as supervised child processes
+    /// (default: true). When false, `serve` runs cargo-leptos in-process.
+    #[serde(default = "default_orchestrate")]
+    pub orchestrate: bool,
+    /// Extra dev processes, keyed by name. An entry named like a built-in
+    /// process ("server", "trunk", "tailwind") replaces it.
+    #[serde(default)]
+    pub processes: HashMap<String, ProcessConfig>,
+    /// Crashes in a row after which a process is no longer restarted (default: 5).
+    #[serde(default = "default_max_restarts")]
+    pub max_restarts: u32,
+    /// Serve the `/_montrs` status page to this machine (default: true).
+    /// The dev server then always runs behind the front proxy.
+    #[serde(default = "default_dashboard")]
+    pub dashboard: bool,
+    /// Persistent build workers for the dev loop (`[serve.workers]`).
+    #[serde(default)]
+    pub workers: WorkersConfig,
+}
+
+/// A `[serve.processes]` entry.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+pub enum ProcessConfig {
+    /// Just the command line.
+    Simple(String),
+    /// A command with its own directory and environment.
+    Detailed {
+        /// The command line, split like a shell would.
+        command: String,
+        /// Working directory, relative to the project root.
+        #[serde(default)]
+        cwd: Option<String>,
+        /// Environment variables set for the process.
+        #[serde(default)]
+        env: HashMap<String, String>,
+        /// Restart the process when it exits with an error (default: true).
+        #[serde(default = "default_restart", deserialize_with = "loader::from_str_or")]
+        restart: bool,
+        /// Set to false to turn off a built-in process.
+        #[serde(default = "default_enabled", deserialize_with = "loader::from_str_or")]
+        enabled: bool,
+    },
+}
+
+/// A `[serve.proxy]` entry.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+pub enum ProxyConfig {
+    /// Just the upstream URL.
+    Simple(String),
+    /// An upstream with request rewriting.
+    Detailed {
+        /// The upstream base URL, e.g. "http://localhost:4000".
+        target: String,
+        /// Replaces the matched prefix in the forwarded path ("" strips it).
+        #[serde(default)]
+        rewrite: Option<String>,
+        /// Send the upstream's host as `Host` instead of the browser's.
+        #[serde(default)]
+        change_origin: bool,
+        /// Headers set on every forwarded request.
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+}
+
+/// Build workers kept running next to the dev server (`[serve.workers]`).
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct WorkersConfig {
+    /// Keep cargo incremental in every profile and run the workers below
+    /// instead of the Tailwind watcher (default: false).
+    #[serde(default)]
+    pub enabled: bool,
+    /// wasm-opt level applied to each rebuilt WASM bundle, e.g. "-O1".
+    /// Results are cached by module hash. Unset skips wasm-opt.
+    #[serde(default)]
+    pub wasm_opt: Option<String>,
+    /// Rebuild the CSS only when class-bearing sources change (default: true).
+    #[serde(default = "default_workers_tailwind")]
+    pub tailwind: bool,
+}
+
+impl Default for WorkersConfig {
+    fn default() -> Self {
+        Self { enabled: false, wasm_opt: None, tailwind: default_workers_tailwind() }
+    }
+}
+
+fn default_workers_tailwind() -> bool {
+    true
+}
+
+/// HTTPS settings for `serve --tls` (`[serve.tls]`).
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TlsConfig {
+    /// Port of the HTTPS listener (default: 8443).
+    #[serde(default = "default_tls_port")]
+    pub port: u16,
+    /// PEM certificate to serve instead of a generated one.
+    #[serde(default)]
+    pub cert: Option<String>,
+    /// PEM private key for `cert`.
+    #[serde(default)]
+    pub key: Option<String>,
+    /// Issue the certificate with mkcert when it is installed, so browsers
+    /// trust it without a warning (default: true).
+    #[serde(default = "default_mkcert")]
+    pub mkcert: bool,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            port: default_tls_port(),
+            cert: None,
+            key: None,
+            mkcert: default_mkcert(),
+        }
+    }
+}
+
+fn default_tls_port() -> u16 {
+    8443
+}
+fn default_mkcert() -> bool {
+    true
 }
 
 impl Default for ServeConfig {
@@ -173,6 +428,14 @@ impl Default for ServeConfig {
         Self {
             port: default_port(),
             addr: default_addr(),
+            mocks_dir: default_mocks_dir(),
+            tls: TlsConfig::default(),
+            proxy: HashMap::new(),
+            orchestrate: default_orchestrate(),
+            processes: HashMap::new(),
+            max_restarts: default_max_restarts(),
+            dashboard: default_dashboard(),
+            workers: WorkersConfig::default(),
         }
     }
 }
@@ -183,6 +446,24 @@ fn default_port() -> u16 {
 fn default_addr() -> String {
     "127.0.0.1".to_string()
 }
+fn default_mocks_dir() -> String {
+    "mocks".to_string()
+}
+fn default_orchestrate() -> bool {
+    true
+}
+fn default_max_restarts() -> u32 {
+    5
+}
+fn default_dashboard() -> bool {
+    true
+}
+fn default_restart() -> bool {
+    true
+}
+fn default_enabled() -> bool {
+    true
+}
 
 /// E2E testing configuration.
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
@@ -196,6 +477,500 @@ pub struct E2eConfig {
     /// Base URL for tests (overrides automatic detection).
     #[serde(default)]
     pub base_url: Option<String>,
+    /// Performance audit run after the e2e suite.
+    #[serde(default)]
+    pub perf: PerfConfig,
+    /// Loader responses pinned to JSON fixture files, keyed by route path.
+    #[serde(default)]
+    pub fixtures: HashMap<String, String>,
+}
+
+/// Performance audit settings (`[e2e.perf]`).
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PerfConfig {
+    /// Run the audit after every `montrs e2e` (same as `--perf`).
+    #[serde(default)]
+    pub enabled: bool,
+    /// Routes to audit, relative to the site URL.
+    #[serde(default = "default_perf_routes")]
+    pub routes: Vec<String>,
+    /// Where the JSON report is written.
+    #[serde(default = "default_perf_report")]
+    pub report: String,
+    /// Previous report to compare against. Missing files are ignored.
+    #[serde(default)]
+    pub baseline: Option<String>,
+    /// Allowed slowdown against the baseline, in percent.
+    #[serde(default = "default_perf_threshold")]
+    pub regression_threshold: f64,
+    /// Limits applied to every route.
+    #[serde(default)]
+    pub budget: PerfBudget,
+    /// Per-route limits, merged over `budget`.
+    #[serde(default)]
+    pub route_budgets: HashMap<String, PerfBudget>,
+}
+
+impl Default for PerfConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            routes: default_perf_routes(),
+            report: default_perf_report(),
+            baseline: None,
+            regression_threshold: default_perf_threshold(),
+            budget: PerfBudget::default(),
+            route_budgets: HashMap::new(),
+        }
+    }
+}
+
+fn default_perf_routes() -> Vec<String> {
+    vec!["/".to_string()]
+}
+fn default_perf_report() -> String {
+    "target/montrs/perf-report.json".to_string()
+}
+fn default_perf_threshold() -> f64 {
+    10.0
+}
+
+/// Upper limits for a route. Unset limits are not checked.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct PerfBudget {
+    /// First Contentful Paint, in milliseconds.
+    #[serde(default)]
+    pub fcp_ms: Option<f64>,
+    /// Largest Contentful Paint, in milliseconds.
+    #[serde(default)]
+    pub lcp_ms: Option<f64>,
+    /// Time to Interactive, in milliseconds.
+    #[serde(default)]
+    pub tti_ms: Option<f64>,
+    /// Combined size of the site's wasm, JS and CSS, in kilobytes.
+    /// Only read from the global `budget`.
+    #[serde(default)]
+    pub bundle_kb: Option<f64>,
+}
+
+impl PerfBudget {
+    /// Returns `self` with unset limits taken from `fallback`.
+    pub fn or(&self, fallback: &PerfBudget) -> PerfBudget {
+        PerfBudget {
+            fcp_ms: self.fcp_ms.or(fallback.fcp_ms),
+            lcp_ms: self.lcp_ms.or(fallback.lcp_ms),
+            tti_ms: self.tti_ms.or(fallback.tti_ms),
+            bundle_kb: self.bundle_kb.or(fallback.bundle_kb),
+        }
+    }
+}
+
+/// Release channel used by `montrs upgrade`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseChannel {
+    /// Tagged releases from crates.io.
+    #[default]
+    Stable,
+    /// The latest commit on the main branch.
+    Nightly,
+}
+
+impl std::fmt::Display for ReleaseChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReleaseChannel::Stable => write!(f, "stable"),
+            ReleaseChannel::Nightly => write!(f, "nightly"),
+        }
+    }
+}
+
+/// CLI self-update configuration.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct UpgradeConfig {
+    /// Default release channel (default: "stable").
+    #[serde(default)]
+    pub channel: ReleaseChannel,
+    /// Git source used for nightly builds.
+    #[serde(default = "default_nightly_git")]
+    pub nightly_git: String,
+    /// Minisign public key (base64) trusted for offline artifacts, along with
+    /// the release key built into the CLI.
+    #[serde(default)]
+    pub public_key: Option<String>,
+}
+
+impl Default for UpgradeConfig {
+    fn default() -> Self {
+        Self {
+            channel: ReleaseChannel::default(),
+            nightly_git: default_nightly_git(),
+            public_key: None,
+        }
+    }
+}
+
+fn default_nightly_git() -> String {
+    crate::command::upgrade::default_nightly_git()
+}
+
+/// Encrypted secrets configuration.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SecretsConfig {
+    /// Path of the encrypted secrets file (default: "secrets.enc.toml").
+    #[serde(default = "default_secrets_file")]
+    pub file: String,
+}
+
+impl Default for SecretsConfig {
+    fn default() -> Self {
+        Self {
+            file: default_secrets_file(),
+        }
+    }
+}
+
+fn default_secrets_file() -> String {
+    montrs_core::secrets::DEFAULT_SECRETS_FILE.to_string()
+}
+
+/// `montrs demo` settings (`[demo]`).
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DemoConfig {
+    /// Directory of seed files the app loads at boot (default: "seeds/demo").
+    #[serde(default = "default_demo_seeds")]
+    pub seeds: String,
+    /// Open the browser once the app answers (default: true).
+    #[serde(default = "default_demo_open")]
+    pub open: bool,
+    /// Page the browser opens (default: "/").
+    #[serde(default = "default_demo_path")]
+    pub path: String,
+}
+
+impl Default for DemoConfig {
+    fn default() -> Self {
+        Self {
+            seeds: default_demo_seeds(),
+            open: true,
+            path: default_demo_path(),
+        }
+    }
+}
+
+/// `[preset]` overrides of `montrs_core::Preset`. Fields set directly in
+/// the table apply to every target; those under `[preset.<target>]` (e.g.
+/// `[preset.edge.limits]`) to that target only.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct PresetConfig {
+    /// Target `montrs config resolve` shows when `--target` is not given (default: "server").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(flatten)]
+    pub overrides: toml::Table,
+}
+
+impl PresetConfig {
+    /// The table as JSON, as `AppSpec::boot` reads it from `MONTRS_PRESET`.
+    /// Strings left by `${VAR}` placeholders become numbers and booleans
+    /// where the preset has one.
+    pub fn to_json(&self) -> Result<serde_json::Value> {
+        let mut json = serde_json::to_value(self)?;
+        let defaults = serde_json::to_value(montrs_core::Preset::for_target(montrs_core::Target::Server))?;
+        if let serde_json::Value::Object(fields) = &mut json {
+            for (key, value) in fields.iter_mut() {
+                // `[preset.<target>]` tables hold the same fields as the shared ones.
+                match key.parse::<montrs_core::Target>() {
+                    Ok(_) => parse_like(value, &defaults),
+                    Err(_) => parse_like(value, defaults.get(key).unwrap_or(&serde_json::Value::Null)),
+                }
+            }
+        }
+        Ok(json)
+    }
+
+    /// The effective preset of `target`, or of the configured target.
+    pub fn resolve(&self, target: Option<montrs_core::Target>) -> Result<(montrs_core::Target, montrs_core::Preset)> {
+        let overrides = self.to_json()?;
+        let target = match target {
+            Some(target) => target,
+            None => montrs_core::Preset::configured_target(&overrides)?.unwrap_or(montrs_core::Target::Server),
+        };
+        let preset = montrs_core::Preset::resolve(target, &overrides).with_context(|| format!("Invalid [preset] for target {}", target))?;
+        Ok((target, preset))
+    }
+}
+
+/// Parses the strings in `value` that are numbers or booleans in `like`.
+fn parse_like(value: &mut serde_json::Value, like: &serde_json::Value) {
+    use serde_json::Value;
+    match (value, like) {
+        (Value::Object(fields), Value::Object(like)) => {
+            for (key, value) in fields.iter_mut() {
+                if let Some(like) = like.get(key) {
+                    parse_like(value, like);
+                }
+            }
+        }
+        (value @ Value::String(_), Value::Number(_) | Value::Bool(_)) => {
+            if let Some(parsed) = value.as_str().and_then(|s| serde_json::from_str::<Value>(s).ok()).filter(|p| p.is_number() || p.is_boolean()) {
+                *value = parsed;
+            }
+        }
+        _ => {}
+    }
+}
+
+fn default_demo_seeds() -> String {
+    "seeds/demo".to_string()
+}
+
+fn default_demo_open() -> bool {
+    true
+}
+
+fn default_demo_path() -> String {
+    "/".to_string()
+}
+
+/// Startup settings applied by `AppSpec::boot`.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct BootConfig {
+    /// Maximum time from boot start until the router is built, in milliseconds.
+    #[serde(default)]
+    pub budget_ms: Option<u64>,
+    /// `warn` (default) logs a warning when over budget; `fail` aborts startup.
+    #[serde(default)]
+    pub on_exceed: montrs_core::BudgetAction,
+    /// Maximum number of independent plates initialized at once (default: no limit).
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+}
+
+/// Database settings.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DatabaseConfig {
+    /// `sqlite` or `postgres`; inferred from `url` when omitted.
+    #[serde(default)]
+    pub backend: Option<String>,
+    /// Connection URL, or `env:NAME` to read it from an environment variable.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Directory of the migration scripts (default: "migrations").
+    #[serde(default = "default_migrations_dir")]
+    pub migrations: String,
+    /// Check the SQL in the sources before every `montrs build`.
+    #[serde(default)]
+    pub check: bool,
+    /// Schema queries are checked against (default: migrations).
+    #[serde(default)]
+    pub check_against: SchemaSource,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            backend: None,
+            url: None,
+            migrations: default_migrations_dir(),
+            check: false,
+            check_against: SchemaSource::default(),
+        }
+    }
+}
+
+impl DatabaseConfig {
+    /// The connection URL with any `env:` indirection resolved.
+    pub fn resolved_url(&self) -> Result<String> {
+        let url = self.url.as_deref().context("No `[database].url` in montrs.toml")?;
+        match url.strip_prefix("env:") {
+            Some(var) => std::env::var(var).with_context(|| format!("`{}` is not set", var)),
+            None => Ok(url.to_string()),
+        }
+    }
+}
+
+fn default_migrations_dir() -> String {
+    "migrations".to_string()
+}
+
+/// Where the schema used to check queries comes from.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaSource {
+    /// Replay the migration scripts; needs no database.
+    #[default]
+    Migrations,
+    /// Introspect the database at `url`.
+    Live,
+}
+
+/// Panic reporting settings.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct CrashConfig {
+    /// Webhook notified of every panic, e.g. a Slack incoming webhook.
+    #[serde(default)]
+    pub webhook: Option<String>,
+    /// Payload sent to `webhook`: `slack` (default) or `sentry`.
+    #[serde(default)]
+    pub format: montrs_core::WebhookFormat,
+}
+
+/// Logging settings for the CLI.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct LoggingConfig {
+    /// Default level or `EnvFilter` directive (default: "info").
+    #[serde(default = "default_log_level")]
+    pub level: String,
+    /// Level overrides keyed by module path, e.g. `"montrs_cli::devproxy" = "debug"`.
+    #[serde(default)]
+    pub modules: HashMap<String, String>,
+    /// Where log lines are written (default: pretty lines on stdout).
+    #[serde(default = "default_log_sinks")]
+    pub sinks: Vec<LogSink>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: default_log_level(),
+            modules: HashMap::new(),
+            sinks: default_log_sinks(),
+        }
+    }
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_log_sinks() -> Vec<LogSink> {
+    vec![LogSink::Stdout { format: LogFormat::default() }]
+}
+
+/// One destination for log lines, written as `[[logging.sinks]]`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum LogSink {
+    /// Standard output.
+    Stdout {
+        #[serde(default)]
+        format: LogFormat,
+    },
+    /// A file rotated on a schedule.
+    File {
+        /// Directory and file name prefix, e.g. "logs/montrs.log".
+        path: String,
+        #[serde(default)]
+        format: LogFormat,
+        #[serde(default)]
+        rotation: LogRotation,
+        /// Number of rotated files kept (default: 7).
+        #[serde(default = "default_log_retention", deserialize_with = "loader::from_str_or")]
+        retention: usize,
+    },
+    /// The system log, over a unix socket path or `udp://host:port`.
+    Syslog {
+        #[serde(default = "default_syslog_address")]
+        address: String,
+        /// Tag of every message (default: the project name).
+        #[serde(default)]
+        app_name: Option<String>,
+    },
+}
+
+fn default_log_retention() -> usize {
+    7
+}
+
+fn default_syslog_address() -> String {
+    "/dev/log".to_string()
+}
+
+/// Line format of a log sink.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Pretty,
+    /// One JSON object per line.
+    Json,
+}
+
+/// How often a file sink starts a new file.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+/// A plugin declared in `montrs.toml`.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct PluginConfig {
+    /// Executable to run (default: `montrs-<name>` on PATH).
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Short description shown in help output.
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Plate registry settings (`[plates]`).
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PlatesConfig {
+    /// URL or path of the registry index (default: the MontRS community index).
+    #[serde(default = "default_plate_registry")]
+    pub registry: String,
+    /// Plates added with `montrs plate add`, keyed by name.
+    #[serde(default)]
+    pub installed: BTreeMap<String, InstalledPlate>,
+}
+
+impl Default for PlatesConfig {
+    fn default() -> Self {
+        Self {
+            registry: default_plate_registry(),
+            installed: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_plate_registry() -> String {
+    crate::command::plate::DEFAULT_REGISTRY.to_string()
+}
+
+/// Where an installed plate came from and what it added to the project, so
+/// the install can be reproduced.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
+pub struct InstalledPlate {
+    /// Name of the plate's crate.
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    /// Version requirement, for plates from crates.io.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Exact version cargo resolved the requirement to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved: Option<String>,
+    /// Repository, for plates from git.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<String>,
+    /// Commit the git dependency is pinned to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    /// Registry index the name was resolved from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    /// Expression registered with `AppSpec::with_plate`.
+    pub register: String,
+    /// Files copied into the project by the install step.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
 }
 
 /// Configuration for custom tasks.
@@ -220,15 +995,25 @@ pub enum TaskConfig {
         /// Environment variables to set for this task.
         #[serde(default)]
         env: HashMap<String, String>,
+        /// Globs of the files the task reads, relative to the project root.
+        /// When set, the result is cached and an unchanged task is skipped.
+        #[serde(default)]
+        inputs: Vec<String>,
+        /// Globs of the files the task writes, restored from the cache on a hit.
+        #[serde(default)]
+        outputs: Vec<String>,
+        /// Directories to run the task in, one run each (e.g. `apps/*`).
+        /// `{dir}` in the command, inputs and outputs is replaced by each one.
+        #[serde(default)]
+        each: Option<String>,
     },
 }
 
 impl MontrsConfig {
     /// Loads configuration from a specific file.
     pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
-        let content = std::fs::read_to_string(path.as_ref())
-            .with_context(|| format!("Failed to read config file: {}", path.as_ref().display()))?;
-        let mut config: Self = toml::from_str(&content)
+        let table = loader::read_layered(path.as_ref())?;
+        let mut config: Self = loader::deserialize(table)
             .with_context(|| format!("Failed to parse config file: {}", path.as_ref().display()))?;
 
         // Try to resolve project name if it's default
@@ -0,0 +1,70 @@
+//! Panic reporting for the CLI and for the app under `montrs serve`.
+//!
+//! The CLI installs a [`CrashReporter`] that records its own panics as agent
+//! errors and notifies the `[crash]` webhook. The app cannot reach the agent,
+//! so `serve` has it append panics to `target/montrs/crashes.jsonl` and turns
+//! each new entry into an agent error and a warning.
+
+use crate::config::CrashConfig;
+use crate::report::reporter;
+use montrs_agent::AgentManager;
+use montrs_core::crash::{
+    CRASH_LOG_FILE, CRASH_LOG_VAR, CRASH_WEBHOOK_FORMAT_VAR, CRASH_WEBHOOK_VAR, CrashReporter, PanicReport,
+};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How often `serve` checks the app's crash log.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Reports the CLI's own panics to the agent in `root` and the configured webhook.
+pub fn install(config: &CrashConfig, root: PathBuf) {
+    let agent = AgentManager::new(root);
+    let mut reporter = CrashReporter::new().with_sink(move |report| {
+        let _ = agent.report_panic(report);
+    });
+    if let Some(url) = &config.webhook {
+        reporter = reporter.with_webhook(url, config.format);
+    }
+    reporter.install();
+}
+
+/// Has the app started by `serve` log its panics, and records each one as
+/// an agent error while the dev session runs.
+pub fn watch_app(config: &CrashConfig, root: PathBuf) {
+    let log = root.join(CRASH_LOG_FILE);
+    // Panics from an earlier session were already recorded.
+    let _ = std::fs::remove_file(&log);
+    unsafe {
+        std::env::set_var(CRASH_LOG_VAR, &log);
+        if let Some(url) = &config.webhook {
+            std::env::set_var(CRASH_WEBHOOK_VAR, url);
+            std::env::set_var(CRASH_WEBHOOK_FORMAT_VAR, config.format.as_str());
+        }
+    }
+
+    let agent = AgentManager::new(root);
+    tokio::spawn(async move {
+        let mut seen = 0;
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            let Ok(reports) = PanicReport::read_log(&log) else { continue };
+            for report in reports.iter().skip(seen) {
+                let recorded = match agent.report_panic(report) {
+                    Ok(id) => format!("agent error {}", id),
+                    Err(e) => format!("not recorded: {}", e),
+                };
+                reporter().warn(format!(
+                    "The app panicked in {} at {}: {} (correlation id {}; {})",
+                    report.scope.as_deref().unwrap_or("a background task"),
+                    report.location(),
+                    report.message,
+                    report.id,
+                    recorded
+                ));
+            }
+            seen = seen.max(reports.len());
+        }
+    });
+}
@@ -0,0 +1,364 @@
+//! Supervised dev processes for `montrs serve`.
+//!
+//! A full-stack project needs more than the app server while developing: a
+//! trunk watcher for a separate WASM front end, the Tailwind CLI rebuilding
+//! the stylesheet, and whatever else `[serve.processes]` lists. `serve` starts
+//! them all as child processes, prefixes each output line with the process
+//! name, restarts a process that crashes (backing off, and giving up after
+//! `[serve] max_restarts` crashes in a row) and stops every one of them on a
+//! single Ctrl-C.
+
+use crate::config::{MontrsConfig, ProcessConfig, TailwindStyle};
+use crate::devproxy::dashboard::builds;
+use crate::report::reporter;
+use anyhow::{Context, Result, anyhow};
+use console::{Color, style};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+
+/// Names of the processes `serve` starts on its own, in start order.
+pub const BUILTIN_PROCESSES: [&str; 3] = ["server", "trunk", "tailwind"];
+
+/// How long a process gets to exit after Ctrl-C before it is killed.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// A process that ran this long before crashing starts a fresh crash count.
+const STABLE_AFTER: Duration = Duration::from_secs(30);
+
+const COLORS: [Color; 5] = [Color::Cyan, Color::Magenta, Color::Yellow, Color::Green, Color::Blue];
+
+/// A command `serve` keeps running.
+#[derive(Debug, Clone)]
+pub struct DevProcess {
+    pub name: String,
+    pub program: String,
+    pub args: Vec<String>,
+    pub cwd: Option<PathBuf>,
+    pub env: HashMap<String, String>,
+    /// Restart the process when it exits with an error.
+    pub restart: bool,
+}
+
+impl DevProcess {
+    pub fn new(name: impl Into<String>, program: impl Into<String>, args: Vec<String>) -> Self {
+        Self {
+            name: name.into(),
+            program: program.into(),
+            args,
+            cwd: None,
+            env: HashMap::new(),
+            restart: true,
+        }
+    }
+
+    /// Builds the process a `[serve.processes]` entry describes, or `None`
+    /// when it is disabled.
+    pub fn from_config(name: &str, config: &ProcessConfig) -> Result<Option<Self>> {
+        let (command, cwd, env, restart) = match config {
+            ProcessConfig::Simple(command) => (command, None, HashMap::new(), true),
+            ProcessConfig::Detailed { enabled: false, .. } => return Ok(None),
+            ProcessConfig::Detailed { command, cwd, env, restart, .. } => {
+                (command, cwd.as_ref().map(PathBuf::from), env.clone(), *restart)
+            }
+        };
+        let mut words = shlex::split(command)
+            .ok_or_else(|| anyhow!("The command of process '{}' has unbalanced quotes", name))?
+            .into_iter();
+        let program = words
+            .next()
+            .ok_or_else(|| anyhow!("The command of process '{}' is empty", name))?;
+        Ok(Some(Self { name: name.to_string(), program, args: words.collect(), cwd, env, restart }))
+    }
+
+    fn command_line(&self) -> String {
+        std::iter::once(self.program.as_str())
+            .chain(self.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn spawn(&self) -> Result<Child> {
+        let mut cmd = Command::new(&self.program);
+        cmd.args(&self.args)
+            .envs(&self.env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(cwd) = &self.cwd {
+            cmd.current_dir(cwd);
+        }
+        cmd.spawn().with_context(|| format!("Failed to start '{}'", self.command_line()))
+    }
+}
+
+/// The processes to run for this project: the built-in ones that apply,
+/// replaced or turned off by `[serve.processes]` entries of the same name,
+/// followed by the other entries in name order.
+pub fn processes(config: &MontrsConfig) -> Result<Vec<DevProcess>> {
+    let configured = &config.serve.processes;
+    let mut processes = Vec::new();
+    for name in BUILTIN_PROCESSES {
+        let process = match configured.get(name) {
+            Some(entry) => DevProcess::from_config(name, entry)?,
+            None => builtin(name, config)?,
+        };
+        processes.extend(process);
+    }
+
+    let mut extra: Vec<_> = configured.iter().filter(|(name, _)| !BUILTIN_PROCESSES.contains(&name.as_str())).collect();
+    extra.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, entry) in extra {
+        processes.extend(DevProcess::from_config(name, entry)?);
+    }
+    Ok(processes)
+}
+
+fn builtin(name: &str, config: &MontrsConfig) -> Result<Option<DevProcess>> {
+    match name {
+        "server" => server_process(config).map(Some),
+        "trunk" => Ok(trunk_process()),
+        "tailwind" => Ok(tailwind_process(config)),
+        _ => Ok(None),
+    }
+}
+
+/// The app server: this CLI running `watch` with the same global flags, so
+/// cargo-leptos rebuilds and restarts it on changes.
+fn server_process(config: &MontrsConfig) -> Result<DevProcess> {
+    let exe = std::env::current_exe().context("Failed to locate the montrs executable")?;
+    let mut args = Vec::new();
+    // `cargo-montrs` expects to be called by cargo, with `montrs` first.
+    if exe.file_stem().is_some_and(|stem| stem == "cargo-montrs") {
+        args.push("montrs".to_string());
+    }
+    let project = &config.project;
+    if project.release {
+        args.push("--release".to_string());
+    }
+    if project.hot_reload {
+        args.push("--hot-reload".to_string());
+    }
+    for feature in &project.features {
+        args.push("--features".to_string());
+        args.push(feature.clone());
+    }
+    for _ in 0..project.verbose {
+        args.push("-v".to_string());
+    }
+    match project.tailwind_style {
+        Some(TailwindStyle::Toml) => args.push("--tailwind-toml".to_string()),
+        Some(TailwindStyle::V4) => args.push("--tailwind-v4".to_string()),
+        _ => {}
+    }
+    args.push("watch".to_string());
+    Ok(DevProcess::new("server", exe.to_string_lossy(), args))
+}
+
+/// `trunk watch`, for projects whose front end is built by trunk.
+fn trunk_process() -> Option<DevProcess> {
+    if !Path::new("Trunk.toml").exists() {
+        return None;
+    }
+    if which::which("trunk").is_err() {
+        reporter().warn("Trunk.toml found but trunk is not installed; run `cargo install trunk`");
+        return None;
+    }
+    Some(DevProcess::new("trunk", "trunk", vec!["watch".to_string()]))
+}
+
+/// The Tailwind CLI in watch mode, when `[build] tailwind_input_file` is set.
+/// With `[serve.workers]` enabled the build workers rebuild the CSS instead.
+fn tailwind_process(config: &MontrsConfig) -> Option<DevProcess> {
+    if config.serve.workers.enabled && config.serve.workers.tailwind {
+        return None;
+    }
+    let (program, args) = tailwind_command(config, true)?;
+    Some(DevProcess::new("tailwind", program, args))
+}
+
+/// The Tailwind CLI invocation for `[build] tailwind_input_file`, if any. It
+/// writes next to the WASM package, where cargo-leptos serves the site CSS.
+pub fn tailwind_command(config: &MontrsConfig, watch: bool) -> Option<(String, Vec<String>)> {
+    let input = config.build.tailwind_input_file.as_ref()?;
+    let output = Path::new(&config.build.site_root)
+        .join(&config.build.site_pkg_name)
+        .join(format!("{}.css", config.project.name));
+    let mut args = vec!["-i".to_string(), input.clone(), "-o".to_string(), output.to_string_lossy().into_owned()];
+    if watch {
+        args.push("--watch".to_string());
+    }
+    if let Some(tailwind_config) = &config.build.tailwind_config_file {
+        args.push("-c".to_string());
+        args.push(tailwind_config.clone());
+    }
+
+    if which::which("tailwindcss").is_ok() {
+        Some(("tailwindcss".to_string(), args))
+    } else if which::which("npx").is_ok() {
+        args.insert(0, "tailwindcss".to_string());
+        Some(("npx".to_string(), args))
+    } else {
+        reporter().warn("tailwind_input_file is set but neither tailwindcss nor npx is installed");
+        None
+    }
+}
+
+/// Runs a set of dev processes until Ctrl-C or until all of them stop.
+pub struct Supervisor {
+    processes: Vec<DevProcess>,
+    max_restarts: u32,
+}
+
+impl Supervisor {
+    pub fn new(processes: Vec<DevProcess>, max_restarts: u32) -> Self {
+        Self { processes, max_restarts }
+    }
+
+    /// Starts every process and supervises it. Returns once Ctrl-C has
+    /// stopped them all, or with an error when every process stopped on its
+    /// own and one of them failed.
+    pub async fn run(self) -> Result<()> {
+        if self.processes.is_empty() {
+            return Ok(());
+        }
+        let width = self.processes.iter().map(|p| p.name.len()).max().unwrap_or(0);
+        let (shutdown_tx, shutdown) = watch::channel(false);
+        let mut tasks = JoinSet::new();
+        for (i, process) in self.processes.into_iter().enumerate() {
+            let prefix = style(format!("{:>width$} |", process.name, width = width)).fg(COLORS[i % COLORS.len()]).to_string();
+            reporter().info(format!("{} {}", prefix, style(process.command_line()).dim()));
+            tasks.spawn(supervise(process, prefix, self.max_restarts, shutdown.clone()));
+        }
+
+        let mut failed = Vec::new();
+        loop {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {
+                    reporter().info(format!("{} Stopping dev processes...", style("■").yellow()));
+                    let _ = shutdown_tx.send(true);
+                    while tasks.join_next().await.is_some() {}
+                    return Ok(());
+                }
+                next = tasks.join_next() => match next {
+                    Some(Ok(Err(name))) => failed.push(name),
+                    Some(_) => {}
+                    None => break,
+                },
+            }
+        }
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("Dev process(es) stopped after failing: {}", failed.join(", ")))
+        }
+    }
+}
+
+/// Keeps one process running. Resolves with the process name as the error
+/// when it failed for good.
+async fn supervise(
+    process: DevProcess,
+    prefix: String,
+    max_restarts: u32,
+    mut shutdown: watch::Receiver<bool>,
+) -> std::result::Result<(), String> {
+    let mut crashes = 0u32;
+    loop {
+        let started = Instant::now();
+        let mut child = match process.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                reporter().warn(format!("{:#}", e));
+                return Err(process.name);
+            }
+        };
+        let track_build = process.name == "server";
+        forward_lines(child.stdout.take(), prefix.clone(), track_build);
+        forward_lines(child.stderr.take(), prefix.clone(), track_build);
+
+        let status = tokio::select! {
+            status = child.wait() => status,
+            _ = shutdown.changed() => {
+                stop(&mut child).await;
+                return Ok(());
+            }
+        };
+        if *shutdown.borrow() {
+            return Ok(());
+        }
+
+        let status = match status {
+            Ok(status) if status.success() => {
+                reporter().info(format!("{} {}", prefix, style("exited").dim()));
+                return Ok(());
+            }
+            Ok(status) => describe(status),
+            Err(e) => e.to_string(),
+        };
+        if !process.restart {
+            reporter().warn(format!("{} exited with {}", process.name, status));
+            return Err(process.name);
+        }
+
+        if started.elapsed() >= STABLE_AFTER {
+            crashes = 0;
+        }
+        crashes += 1;
+        if crashes > max_restarts {
+            reporter().warn(format!(
+                "{} crashed {} times in a row ({}); not restarting it",
+                process.name, crashes, status
+            ));
+            return Err(process.name);
+        }
+
+        let delay = Duration::from_millis(500 * 2u64.pow(crashes.min(6) - 1)).min(Duration::from_secs(10));
+        reporter().warn(format!(
+            "{} crashed ({}); restarting in {:.1}s",
+            process.name,
+            status,
+            delay.as_secs_f64()
+        ));
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = shutdown.changed() => return Ok(()),
+        }
+    }
+}
+
+/// Gives the process the grace period to exit (the terminal sent it the
+/// Ctrl-C as well), then kills it.
+async fn stop(child: &mut Child) {
+    if tokio::time::timeout(SHUTDOWN_GRACE, child.wait()).await.is_err() {
+        let _ = child.kill().await;
+    }
+}
+
+/// Prints each line of `stream` after `prefix`. The app server's lines also
+/// feed the dashboard's build status.
+fn forward_lines(stream: Option<impl AsyncRead + Unpin + Send + 'static>, prefix: String, track_build: bool) {
+    let Some(stream) = stream else { return };
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stream).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if track_build {
+                builds().observe(&line);
+            }
+            reporter().output(format!("{} {}", prefix, line));
+        }
+    });
+}
+
+fn describe(status: ExitStatus) -> String {
+    match status.code() {
+        Some(code) => format!("exit code {}", code),
+        None => "a signal".to_string(),
+    }
+}
@@ -0,0 +1,150 @@
+//! `montrs serve --capture`: records the requests the proxy forwards.
+
+use super::{ProxyBody, RELOAD_PATH};
+use futures::StreamExt;
+use http_body_util::{BodyExt, BodyStream, Full, StreamBody};
+use hyper::body::{Body, Bytes, Incoming};
+use hyper::header;
+use hyper::Request;
+use montrs_core::{Capture, WasiRequest};
+use std::path::{Path, PathBuf};
+
+/// Where captures go, under the project's agent directory.
+pub const CAPTURES_DIR: &str = "captures";
+
+/// Header `montrs replay` sends, so replayed requests are not captured again.
+pub const REPLAY_HEADER: &str = "x-montrs-replay";
+
+/// Largest request body that is captured. Larger requests are forwarded
+/// without being recorded.
+const MAX_CAPTURE_BYTES: usize = 1024 * 1024;
+
+/// The capture directory of the project at `root`, e.g. `.agent/captures`.
+pub fn captures_dir(root: &Path) -> PathBuf {
+    montrs_agent::AgentManager::new(root).agent_dir().join(CAPTURES_DIR)
+}
+
+/// Writes a sanitized [`Capture`] of each app request to a directory.
+pub struct Capturer {
+    dir: PathBuf,
+    /// Path prefixes of static files, which are not worth replaying.
+    skip: Vec<String>,
+}
+
+impl Capturer {
+    /// Captures into `dir`, skipping the site package under `/<pkg_dir>/`.
+    pub fn new(dir: impl Into<PathBuf>, pkg_dir: &str) -> Self {
+        let pkg = format!("/{}/", pkg_dir.trim_matches('/'));
+        Self { dir: dir.into(), skip: vec![pkg, "/favicon.ico".to_string(), RELOAD_PATH.to_string()] }
+    }
+
+    /// Whether `req` is recorded: not a websocket, a static file or a replay,
+    /// and with a body small enough to hold in memory.
+    pub fn wants(&self, req: &Request<Incoming>) -> bool {
+        let path = req.uri().path();
+        let too_large = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<usize>().ok())
+            .is_some_and(|len| len > MAX_CAPTURE_BYTES);
+        let skipped = req.headers().contains_key(header::UPGRADE) || req.headers().contains_key(REPLAY_HEADER);
+        !too_large && !skipped && !self.skip.iter().any(|s| path.starts_with(s.as_str()))
+    }
+
+    /// Reads the body of `req` to capture it, and returns the request to
+    /// forward with the same body. `path_with_query` is the path the browser
+    /// asked for, before it was rewritten for the upstream.
+    ///
+    /// A body without a length is only found to be too large while reading
+    /// it; the request is then forwarded with what was read and the rest of
+    /// the stream, and not captured.
+    pub async fn read<B>(&self, req: Request<B>, path_with_query: &str) -> Result<(Request<ProxyBody>, Option<Capture>), String>
+    where
+        B: Body<Data = Bytes, Error = hyper::Error> + Send + Sync + Unpin + 'static,
+    {
+        let (parts, mut body) = req.into_parts();
+        let mut frames = Vec::new();
+        let mut size = 0;
+        while let Some(frame) = body.frame().await {
+            let frame = frame.map_err(|e| format!("montrs: could not read the request body to capture it ({})", e))?;
+            size += frame.data_ref().map_or(0, Bytes::len);
+            frames.push(frame);
+            if size > MAX_CAPTURE_BYTES {
+                let rest = futures::stream::iter(frames.into_iter().map(Ok)).chain(BodyStream::new(body));
+                return Ok((Request::from_parts(parts, BodyExt::boxed(StreamBody::new(rest))), None));
+            }
+        }
+        let mut data = Vec::with_capacity(size);
+        for chunk in frames.into_iter().filter_map(|frame| frame.into_data().ok()) {
+            data.extend_from_slice(&chunk);
+        }
+        let body = Bytes::from(data);
+        let mut request = WasiRequest::new(parts.method.as_str(), path_with_query).with_body(body.to_vec());
+        for (name, value) in &parts.headers {
+            if let Ok(value) = value.to_str() {
+                request = request.with_header(name.as_str(), value);
+            }
+        }
+        let forwarded = Request::from_parts(parts, Full::new(body).map_err(|never| match never {}).boxed());
+        Ok((forwarded, Some(Capture::from_request(&request))))
+    }
+
+    /// Writes `capture` with the status the app answered with.
+    pub fn save(&self, capture: Capture, status: u16) {
+        if let Err(e) = capture.with_status(status).write_to(&self.dir) {
+            tracing::warn!(error = %e, "request not captured");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::body::Frame;
+    use hyper::service::service_fn;
+    use hyper::Response;
+    use hyper_util::rt::TokioIo;
+    use std::convert::Infallible;
+    use std::sync::Arc;
+
+    /// Sends `chunks` as a chunked request through [`Capturer::read`], and
+    /// returns whether it was captured and the size of the forwarded body.
+    async fn read_chunked(chunks: Vec<Bytes>) -> (bool, usize) {
+        let dir = tempfile::tempdir().unwrap();
+        let capturer = Arc::new(Capturer::new(dir.path(), "pkg"));
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let service = service_fn(move |req| {
+            let capturer = capturer.clone();
+            async move {
+                let (forwarded, capture) = capturer.read(req, "/upload").await.unwrap();
+                let body = forwarded.into_body().collect().await.unwrap().to_bytes();
+                let reply = format!("{} {}", capture.is_some(), body.len());
+                Ok::<_, Infallible>(Response::new(Full::new(Bytes::from(reply))))
+            }
+        });
+        tokio::spawn(hyper::server::conn::http1::Builder::new().serve_connection(TokioIo::new(server), service));
+
+        let (mut sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(client)).await.unwrap();
+        tokio::spawn(conn);
+        let body = StreamBody::new(futures::stream::iter(chunks.into_iter().map(|c| Ok::<_, Infallible>(Frame::data(c)))));
+        let req = Request::post("/upload").header(header::HOST, "localhost").body(body).unwrap();
+        let res = sender.send_request(req).await.unwrap();
+        let reply = res.into_body().collect().await.unwrap().to_bytes();
+        let (captured, len) = std::str::from_utf8(&reply).unwrap().split_once(' ').unwrap();
+        (captured == "true", len.parse().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_small_chunked_bodies_are_captured() {
+        assert_eq!(read_chunked(vec![Bytes::from("{\"a\":"), Bytes::from("1}")]).await, (true, 7));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_chunked_bodies_are_forwarded_whole_without_a_capture() {
+        let chunk = Bytes::from(vec![b'x'; 300 * 1024]);
+        let (captured, len) = read_chunked(vec![chunk; 5]).await;
+        assert!(!captured);
+        assert_eq!(len, 5 * 300 * 1024);
+    }
+}
@@ -0,0 +1,99 @@
+//! Certificates for the HTTPS dev server.
+//!
+//! In order of preference: the `cert`/`key` pair from `[serve.tls]`, a
+//! certificate issued by mkcert (trusted by browsers once `mkcert -install`
+//! has run), or a self-signed one from rcgen. Generated files are kept in
+//! `target/montrs/tls/` so a browser exception survives restarts.
+
+use crate::config::TlsConfig;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::rustls::crypto::aws_lc_rs;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+/// Where generated certificates are stored, relative to the project root.
+pub const TLS_DIR: &str = "target/montrs/tls";
+
+/// Hosts the generated certificates are valid for.
+const HOSTS: [&str; 3] = ["localhost", "127.0.0.1", "::1"];
+
+/// How the certificate in use was obtained.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertSource {
+    Configured,
+    Mkcert,
+    SelfSigned,
+}
+
+/// A PEM certificate chain and key on disk.
+pub struct DevCert {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    pub source: CertSource,
+}
+
+impl DevCert {
+    /// Picks or creates the certificate described by `[serve.tls]`.
+    pub fn resolve(config: &TlsConfig) -> Result<Self> {
+        if let (Some(cert), Some(key)) = (&config.cert, &config.key) {
+            return Ok(Self { cert: cert.into(), key: key.into(), source: CertSource::Configured });
+        }
+        if config.cert.is_some() != config.key.is_some() {
+            anyhow::bail!("[serve.tls] needs both `cert` and `key`, or neither");
+        }
+
+        let dir = Path::new(TLS_DIR);
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        if config.mkcert && which::which("mkcert").is_ok() {
+            let cert = Self { cert: dir.join("mkcert.pem"), key: dir.join("mkcert-key.pem"), source: CertSource::Mkcert };
+            if !cert.exists() {
+                let status = Command::new("mkcert")
+                    .arg("-cert-file")
+                    .arg(&cert.cert)
+                    .arg("-key-file")
+                    .arg(&cert.key)
+                    .args(HOSTS)
+                    .status()
+                    .context("Failed to run mkcert")?;
+                if !status.success() {
+                    anyhow::bail!("mkcert exited with {}", status);
+                }
+            }
+            return Ok(cert);
+        }
+
+        let cert = Self { cert: dir.join("cert.pem"), key: dir.join("key.pem"), source: CertSource::SelfSigned };
+        if !cert.exists() {
+            let generated = rcgen::generate_simple_self_signed(HOSTS.map(String::from).to_vec())
+                .context("Failed to generate a self-signed certificate")?;
+            std::fs::write(&cert.cert, generated.cert.pem())?;
+            std::fs::write(&cert.key, generated.signing_key.serialize_pem())?;
+        }
+        Ok(cert)
+    }
+
+    fn exists(&self) -> bool {
+        self.cert.exists() && self.key.exists()
+    }
+
+    /// A rustls config for this certificate that offers HTTP/2 and HTTP/1.1.
+    pub fn server_config(&self) -> Result<Arc<ServerConfig>> {
+        let chain = CertificateDer::pem_file_iter(&self.cert)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .with_context(|| format!("Failed to read certificate {}", self.cert.display()))?;
+        let key = PrivateKeyDer::from_pem_file(&self.key)
+            .with_context(|| format!("Failed to read private key {}", self.key.display()))?;
+
+        let mut config = ServerConfig::builder_with_provider(Arc::new(aws_lc_rs::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(chain, key)
+            .context("The TLS certificate and key do not match")?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(Arc::new(config))
+    }
+}
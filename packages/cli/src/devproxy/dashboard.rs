@@ -0,0 +1,483 @@
+//! The `/_montrs` dev dashboard.
+//!
+//! The front proxy answers `/_montrs` itself, and only to requests from this
+//! machine. The page shows the state of the last build with the build
+//! workers' timings, the routes and feature flags the app registered (read
+//! from the dev state it writes at boot), their usage when the app counts it,
+//! the server signal graph with its hot spots, and the active agent errors.
+//! `/_montrs.json`, or a request that accepts JSON, gets the same data as one
+//! JSON object for tooling, including every dependency edge of the graph.
+
+use super::{ProxyBody, error_response};
+use crate::workers::{BUILD_TIMINGS_FILE, BuildTiming, PhaseStats, summarize};
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper::header::{self, HeaderValue};
+use hyper::{HeaderMap, Method, Response, StatusCode};
+use montrs_core::analytics::AnalyticsSnapshot;
+use montrs_core::{DevState, FeatureFlag, GraphWarning, SignalGraph};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Path of the dashboard page.
+pub const DASHBOARD_PATH: &str = "/_montrs";
+/// Path of the dashboard's JSON variant.
+pub const DASHBOARD_JSON_PATH: &str = "/_montrs.json";
+/// Reads (`GET`) or replaces (`PUT`/`POST`) the CLI's log filter.
+pub const LOG_LEVEL_PATH: &str = "/_montrs/log-level";
+
+/// Number of agent errors listed, newest first.
+const RECENT_ERRORS: usize = 10;
+/// Number of signal graph nodes listed on the page, busiest first.
+const BUSIEST_NODES: usize = 25;
+
+static BUILDS: OnceLock<BuildTracker> = OnceLock::new();
+
+/// The process-wide build tracker, fed with the app server's output.
+pub fn builds() -> &'static BuildTracker {
+    BUILDS.get_or_init(BuildTracker::default)
+}
+
+/// Where the last build of the app stands.
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BuildState {
+    /// No build output seen yet, e.g. when cargo-leptos runs in-process.
+    #[default]
+    Unknown,
+    Building,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Default)]
+struct BuildRecord {
+    state: BuildState,
+    started: Option<Instant>,
+    finished: Option<Instant>,
+    last_duration: Option<Duration>,
+}
+
+/// Follows the builds by reading cargo's output line by line.
+#[derive(Default)]
+pub struct BuildTracker {
+    record: Mutex<BuildRecord>,
+}
+
+impl BuildTracker {
+    /// Updates the build state from one line of the app server's output.
+    pub fn observe(&self, line: &str) {
+        let line = console::strip_ansi_codes(line);
+        let line = line.trim_start();
+        let mut record = self.record.lock().unwrap();
+        if line.starts_with("Compiling ") {
+            if record.state != BuildState::Building {
+                record.state = BuildState::Building;
+                record.started = Some(Instant::now());
+            }
+        } else if line.starts_with("error[") || line.starts_with("error:") {
+            record.state = BuildState::Failed;
+            record.finished = Some(Instant::now());
+        } else if line.starts_with("Finished ") && record.state != BuildState::Failed {
+            let now = Instant::now();
+            record.last_duration = record.started.map(|started| now - started);
+            record.state = BuildState::Succeeded;
+            record.finished = Some(now);
+        }
+    }
+
+    fn summary(&self) -> BuildSummary {
+        let record = self.record.lock().unwrap();
+        BuildSummary {
+            state: record.state,
+            last_duration_ms: record.last_duration.map(|d| d.as_millis()),
+            finished_secs_ago: record.finished.map(|f| f.elapsed().as_secs()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct BuildSummary {
+    state: BuildState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_duration_ms: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    finished_secs_ago: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct AppSummary {
+    booted_at: String,
+    boot_ms: f64,
+}
+
+#[derive(Serialize)]
+struct RouteRow {
+    path: String,
+    loader: String,
+    action: String,
+    meta: HashMap<String, String>,
+    parent: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ErrorRow {
+    id: String,
+    level: String,
+    message: String,
+    location: String,
+    reported_at: String,
+}
+
+#[derive(Serialize)]
+struct Status {
+    build: BuildSummary,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    build_phases: Vec<PhaseStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    app: Option<AppSummary>,
+    routes: Vec<RouteRow>,
+    flags: Vec<FeatureFlag>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usage: Option<AnalyticsSnapshot>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signals: Option<SignalGraph>,
+    errors: Vec<ErrorRow>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    log_filter: Option<String>,
+}
+
+/// Serves the dashboard for the project at `root`.
+pub struct Dashboard {
+    root: PathBuf,
+    state_file: PathBuf,
+    graph_file: Option<PathBuf>,
+    analytics_file: Option<PathBuf>,
+}
+
+impl Dashboard {
+    /// `state_file` is where the app writes its [`DevState`].
+    pub fn new(root: impl Into<PathBuf>, state_file: impl Into<PathBuf>) -> Self {
+        Self { root: root.into(), state_file: state_file.into(), graph_file: None, analytics_file: None }
+    }
+
+    /// Shows the [`SignalGraph`] the app writes to `graph_file`.
+    pub fn with_signal_graph(mut self, graph_file: impl Into<PathBuf>) -> Self {
+        self.graph_file = Some(graph_file.into());
+        self
+    }
+
+    /// Shows the route usage the app writes to `analytics_file` when it
+    /// turned analytics on.
+    pub fn with_analytics(mut self, analytics_file: impl Into<PathBuf>) -> Self {
+        self.analytics_file = Some(analytics_file.into());
+        self
+    }
+
+    /// Whether the proxy should answer `path` with the dashboard.
+    pub fn handles(path: &str) -> bool {
+        path == DASHBOARD_PATH || path == DASHBOARD_JSON_PATH
+    }
+
+    pub(super) fn respond(&self, peer: SocketAddr, path: &str, headers: &HeaderMap) -> Response<ProxyBody> {
+        if !peer.ip().is_loopback() {
+            return error_response(StatusCode::FORBIDDEN, "montrs: the dev dashboard is only served to this machine".to_string());
+        }
+        let status = self.status();
+        let wants_json = path == DASHBOARD_JSON_PATH
+            || headers
+                .get(header::ACCEPT)
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|accept| accept.starts_with("application/json"));
+        let (body, content_type) = if wants_json {
+            (serde_json::to_string_pretty(&status).unwrap_or_default(), "application/json")
+        } else {
+            (render(&status), "text/html; charset=utf-8")
+        };
+        let mut res = Response::new(Full::new(Bytes::from(body)).map_err(|never| match never {}).boxed());
+        res.headers_mut().insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+        res.headers_mut().insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        res
+    }
+
+    /// Answers [`LOG_LEVEL_PATH`]; `body` holds the new filter directives.
+    pub(super) fn log_level(&self, peer: SocketAddr, method: &Method, body: &[u8]) -> Response<ProxyBody> {
+        if !peer.ip().is_loopback() {
+            return error_response(StatusCode::FORBIDDEN, "montrs: the log level can only be changed from this machine".to_string());
+        }
+        if *method == Method::PUT || *method == Method::POST {
+            let directives = String::from_utf8_lossy(body);
+            if let Err(e) = crate::logging::set_filter(&directives) {
+                return error_response(StatusCode::BAD_REQUEST, format!("montrs: {:#}", e));
+            }
+            tracing::info!("Log filter changed to `{}`", directives.trim());
+        } else if *method != Method::GET {
+            return error_response(StatusCode::METHOD_NOT_ALLOWED, "montrs: use GET, PUT or POST".to_string());
+        }
+        let filter = crate::logging::current_filter().unwrap_or_default();
+        let mut res = Response::new(Full::new(Bytes::from(filter + "\n")).map_err(|never| match never {}).boxed());
+        res.headers_mut()
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain; charset=utf-8"));
+        res.headers_mut().insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+        res
+    }
+
+    fn status(&self) -> Status {
+        let state = DevState::read_from(&self.state_file).ok();
+        let mut routes: Vec<RouteRow> = state
+            .as_ref()
+            .map(|s| {
+                s.router
+                    .routes
+                    .values()
+                    .map(|r| RouteRow {
+                        path: r.path.clone(),
+                        loader: r.loader_description.clone(),
+                        action: r.action_description.clone(),
+                        meta: r.meta.clone(),
+                        parent: r.parent.clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        routes.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut errors = montrs_agent::AgentManager::new(&self.root).list_active_errors().unwrap_or_default();
+        errors.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+        let errors = errors
+            .into_iter()
+            .take(RECENT_ERRORS)
+            .map(|e| ErrorRow {
+                location: format!("{}:{}:{}", e.detail.file, e.detail.line, e.detail.column),
+                reported_at: e.timestamp.to_rfc3339(),
+                id: e.id,
+                level: e.detail.level,
+                message: e.detail.message,
+            })
+            .collect();
+
+        Status {
+            build: builds().summary(),
+            build_phases: BuildTiming::read_log(&self.root.join(BUILD_TIMINGS_FILE))
+                .map(|timings| summarize(&timings))
+                .unwrap_or_default(),
+            app: state.as_ref().map(|s| AppSummary { booted_at: s.booted_at.to_rfc3339(), boot_ms: s.boot_ms }),
+            routes,
+            flags: state.map(|s| s.flags).unwrap_or_default(),
+            usage: self.analytics_file.as_deref().and_then(|path| AnalyticsSnapshot::read_from(path).ok()),
+            signals: self.graph_file.as_deref().and_then(|path| SignalGraph::read_from(path).ok()),
+            errors,
+            log_filter: crate::logging::current_filter(),
+        }
+    }
+}
+
+fn render(status: &Status) -> String {
+    let mut html = String::from(
+        "<!doctype html><html><head><meta charset=\"utf-8\"><meta http-equiv=\"refresh\" content=\"5\">\
+         <title>montrs dev</title><style>\
+         body{font:14px system-ui,sans-serif;margin:2rem;color:#222}\
+         table{border-collapse:collapse;margin-bottom:1.5rem}\
+         td,th{border-bottom:1px solid #ddd;padding:.3rem .8rem;text-align:left;vertical-align:top}\
+         code{font-size:13px}.muted{color:#888}\
+         .succeeded{color:#1a7f37}.failed{color:#cf222e}.building{color:#9a6700}\
+         </style></head><body><h1>montrs dev server</h1>",
+    );
+
+    let build = &status.build;
+    let state = serde_json::to_value(build.state).ok().and_then(|v| v.as_str().map(String::from)).unwrap_or_default();
+    let _ = write!(html, "<h2>Build</h2><p><b class=\"{0}\">{0}</b>", state);
+    if let Some(ms) = build.last_duration_ms {
+        let _ = write!(html, " &middot; last rebuild {:.1}s", ms as f64 / 1000.0);
+    }
+    if let Some(secs) = build.finished_secs_ago {
+        let _ = write!(html, " <span class=\"muted\">({}s ago)</span>", secs);
+    }
+    match &status.app {
+        Some(app) => {
+            let _ = write!(html, "<br>App booted in {:.1}ms at {}", app.boot_ms, escape(&app.booted_at));
+        }
+        None => html.push_str("<br><span class=\"muted\">The app has not booted yet.</span>"),
+    }
+    html.push_str("</p>");
+    if !status.build_phases.is_empty() {
+        html.push_str("<table><tr><th>Phase</th><th>Ran</th><th>Cached</th><th>Skipped</th><th>Mean</th><th>Saved</th></tr>");
+        for phase in &status.build_phases {
+            let _ = write!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.1}s</td><td class=\"succeeded\">{:.1}s</td></tr>",
+                escape(&phase.phase),
+                phase.ran,
+                phase.cached,
+                phase.skipped,
+                phase.mean_ms as f64 / 1000.0,
+                phase.saved_ms as f64 / 1000.0
+            );
+        }
+        html.push_str("</table>");
+    }
+
+    let _ = write!(html, "<h2>Routes ({})</h2>", status.routes.len());
+    if !status.routes.is_empty() {
+        html.push_str("<table><tr><th>Path</th><th>Layout</th><th>Loader</th><th>Action</th><th>Meta</th></tr>");
+        for route in &status.routes {
+            let mut meta: Vec<_> = route.meta.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+            meta.sort();
+            let _ = write!(
+                html,
+                "<tr><td><code>{}</code></td><td class=\"muted\">{}</td><td>{}</td><td>{}</td><td class=\"muted\">{}</td></tr>",
+                escape(&route.path),
+                escape(route.parent.as_deref().unwrap_or("")),
+                escape(&route.loader),
+                escape(&route.action),
+                escape(&meta.join(", "))
+            );
+        }
+        html.push_str("</table>");
+    }
+
+    let _ = write!(html, "<h2>Feature flags ({})</h2>", status.flags.len());
+    if !status.flags.is_empty() {
+        html.push_str("<table><tr><th>Flag</th><th>State</th><th>Segments</th><th>Description</th></tr>");
+        for flag in &status.flags {
+            let _ = write!(
+                html,
+                "<tr><td><code>{}</code></td><td class=\"{}\">{}</td><td>{}</td><td class=\"muted\">{}</td></tr>",
+                escape(&flag.name),
+                if flag.enabled { "succeeded" } else { "muted" },
+                if flag.enabled { "on" } else { "off" },
+                escape(&flag.segment_whitelist.join(", ")),
+                escape(flag.description.as_deref().unwrap_or(""))
+            );
+        }
+        html.push_str("</table>");
+    }
+
+    if let Some(usage) = &status.usage {
+        render_usage(&mut html, usage);
+    }
+
+    if let Some(graph) = &status.signals {
+        render_signals(&mut html, graph);
+    }
+
+    let _ = write!(html, "<h2>Agent errors ({})</h2>", status.errors.len());
+    if !status.errors.is_empty() {
+        html.push_str("<table><tr><th>ID</th><th>Level</th><th>Location</th><th>Message</th></tr>");
+        for error in &status.errors {
+            let _ = write!(
+                html,
+                "<tr><td><code>{}</code></td><td>{}</td><td><code>{}</code></td><td>{}</td></tr>",
+                escape(&error.id),
+                escape(&error.level),
+                escape(&error.location),
+                escape(&error.message)
+            );
+        }
+        html.push_str("</table>");
+    }
+
+    if let Some(filter) = &status.log_filter {
+        let _ = write!(
+            html,
+            "<p>Log filter: <code>{}</code> <span class=\"muted\">(PUT new directives to <code>{}</code>)</span></p>",
+            escape(filter),
+            LOG_LEVEL_PATH
+        );
+    }
+    let _ = write!(html, "<p class=\"muted\">JSON: <a href=\"{0}\">{0}</a></p></body></html>", DASHBOARD_JSON_PATH);
+    html
+}
+
+fn render_usage(html: &mut String, usage: &AnalyticsSnapshot) {
+    let hits: u64 = usage.routes.iter().map(|r| r.hits).sum();
+    let _ = write!(html, "<h2>Route usage ({} calls)</h2>", hits);
+    if usage.routes.is_empty() {
+        return;
+    }
+    html.push_str(
+        "<table><tr><th>Route</th><th>Operation</th><th>Hits</th><th>2xx</th><th>4xx</th><th>5xx</th>\
+         <th>Mean</th><th>p95</th><th>Max</th></tr>",
+    );
+    for route in &usage.routes {
+        let _ = write!(
+            html,
+            "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td{}>{}</td>\
+             <td>{:.1}ms</td><td>&le;{}ms</td><td>{:.1}ms</td></tr>",
+            escape(&route.route),
+            escape(&route.operation),
+            route.hits,
+            route.status.success,
+            route.status.client_error,
+            if route.status.server_error > 0 { " class=\"failed\"" } else { "" },
+            route.status.server_error,
+            route.mean_us() as f64 / 1000.0,
+            route.percentile_ms(95.0),
+            route.max_us as f64 / 1000.0
+        );
+    }
+    html.push_str("</table>");
+}
+
+fn render_signals(html: &mut String, graph: &SignalGraph) {
+    let _ = write!(html, "<h2>Signal graph ({} nodes)</h2>", graph.nodes.len());
+    if !graph.warnings.is_empty() {
+        html.push_str("<ul>");
+        for warning in &graph.warnings {
+            let text = match warning {
+                GraphWarning::ExcessiveRuns { name, peak_runs_per_sec, .. } => {
+                    format!("<code>{}</code> reran {} times in one second", escape(name), peak_runs_per_sec)
+                }
+                GraphWarning::WideFanout { name, dependents, .. } => {
+                    format!("<code>{}</code> has {} dependents; every write reruns all of them", escape(name), dependents)
+                }
+            };
+            let _ = write!(html, "<li class=\"failed\">{}</li>", text);
+        }
+        html.push_str("</ul>");
+    }
+    if graph.nodes.is_empty() {
+        return;
+    }
+
+    let flagged = |id: u64| {
+        graph.warnings.iter().any(|w| match w {
+            GraphWarning::ExcessiveRuns { node, .. } | GraphWarning::WideFanout { node, .. } => *node == id,
+        })
+    };
+    let mut nodes: Vec<_> = graph.nodes.iter().collect();
+    nodes.sort_by_key(|n| std::cmp::Reverse(n.runs + n.writes + n.reads));
+    html.push_str(
+        "<table><tr><th>Node</th><th>Kind</th><th>Reads</th><th>Writes</th><th>Runs (peak/s)</th>\
+         <th>Dependents</th><th>Scopes</th></tr>",
+    );
+    for node in nodes.into_iter().take(BUSIEST_NODES) {
+        let mut scopes: Vec<&str> = node.readers.keys().chain(node.writers.keys()).map(String::as_str).collect();
+        scopes.sort_unstable();
+        scopes.dedup();
+        let kind = serde_json::to_value(node.kind).ok().and_then(|v| v.as_str().map(String::from)).unwrap_or_default();
+        let _ = write!(
+            html,
+            "<tr><td><code{}>{}</code></td><td>{}</td><td>{}</td><td>{}</td><td>{} ({})</td><td>{}</td><td class=\"muted\">{}</td></tr>",
+            if flagged(node.id) { " class=\"failed\"" } else { "" },
+            escape(&node.name),
+            kind,
+            node.reads,
+            node.writes,
+            node.runs,
+            node.peak_runs_per_sec,
+            node.dependents.len(),
+            escape(&scopes.join(", "))
+        );
+    }
+    html.push_str("</table>");
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,298 @@
+//! The dev server's front proxy.
+//!
+//! `montrs serve` runs the app through cargo-leptos, which only speaks plain
+//! HTTP/1.1. When the dev server needs more than that, the CLI puts this proxy
+//! in front of it: it terminates TLS with HTTP/2 negotiated over ALPN, sends
+//! `[serve.proxy]` prefixes to their backends, answers the `/_montrs`
+//! dashboard itself and forwards everything else, including websocket upgrades
+//! such as the hot-reload socket, to the app. With `--capture` it also records
+//! the requests it forwards.
+
+pub mod capture;
+pub mod cert;
+pub mod dashboard;
+pub mod rule;
+
+pub use capture::Capturer;
+pub use dashboard::Dashboard;
+pub use rule::ProxyRule;
+
+use crate::config::MontrsConfig;
+use anyhow::{Context, Result};
+use http_body_util::{BodyExt, Full, Limited, combinators::BoxBody};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{self, HeaderValue};
+use hyper::service::service_fn;
+use hyper::{Request, Response, StatusCode, Uri, Version};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::rustls::crypto::aws_lc_rs;
+
+/// Path of the cargo-leptos live-reload websocket.
+pub const RELOAD_PATH: &str = "/live_reload";
+
+/// Largest request body accepted by the log-level endpoint.
+const MAX_LOG_FILTER_BYTES: usize = 4096;
+
+/// Reload port cargo-leptos uses when none is configured.
+const DEFAULT_RELOAD_PORT: u16 = 3001;
+
+type ProxyBody = BoxBody<Bytes, hyper::Error>;
+
+/// A reverse proxy in front of the app server.
+pub struct DevProxy {
+    listen: SocketAddr,
+    app: String,
+    reload: Option<String>,
+    rules: Vec<ProxyRule>,
+    tls: Option<Arc<ServerConfig>>,
+    dashboard: Option<Dashboard>,
+    capture: Option<Capturer>,
+}
+
+struct Upstreams {
+    app: String,
+    reload: Option<String>,
+    rules: Vec<ProxyRule>,
+    dashboard: Option<Dashboard>,
+    capture: Option<Capturer>,
+    scheme: &'static str,
+    client: Client<HttpsConnector<HttpConnector>, ProxyBody>,
+}
+
+impl DevProxy {
+    /// Proxies `listen` to the app at `app` (`host:port`).
+    pub fn new(listen: SocketAddr, app: impl Into<String>) -> Self {
+        Self { listen, app: app.into(), reload: None, rules: Vec::new(), tls: None, dashboard: None, capture: None }
+    }
+
+    /// Serves HTTPS (HTTP/2 and HTTP/1.1) with the given config.
+    pub fn with_tls(mut self, config: Arc<ServerConfig>) -> Self {
+        self.tls = Some(config);
+        self
+    }
+
+    /// Sends the live-reload websocket to `addr` instead of the app.
+    pub fn with_reload(mut self, addr: impl Into<String>) -> Self {
+        self.reload = Some(addr.into());
+        self
+    }
+
+    /// Sends requests matching `rules` to their backends. Rules are tried in
+    /// order, so pass them as returned by [`ProxyRule::from_config`].
+    pub fn with_rules(mut self, rules: Vec<ProxyRule>) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Answers `/_montrs` with `dashboard` instead of forwarding it.
+    pub fn with_dashboard(mut self, dashboard: Dashboard) -> Self {
+        self.dashboard = Some(dashboard);
+        self
+    }
+
+    /// Records the requests forwarded to the app and to `[serve.proxy]`
+    /// backends with `capturer`.
+    pub fn with_capture(mut self, capturer: Capturer) -> Self {
+        self.capture = Some(capturer);
+        self
+    }
+
+    /// Accepts connections until the process exits.
+    pub async fn run(self) -> Result<()> {
+        let listener = TcpListener::bind(self.listen)
+            .await
+            .with_context(|| format!("Failed to listen on {}", self.listen))?;
+        let acceptor = self.tls.map(TlsAcceptor::from);
+        let connector = HttpsConnectorBuilder::new()
+            .with_provider_and_native_roots(aws_lc_rs::default_provider())
+            .context("Failed to load the system's root certificates")?
+            .https_or_http()
+            .enable_http1()
+            .build();
+        let upstreams = Arc::new(Upstreams {
+            app: self.app,
+            reload: self.reload,
+            rules: self.rules,
+            dashboard: self.dashboard,
+            capture: self.capture,
+            scheme: if acceptor.is_some() { "https" } else { "http" },
+            client: Client::builder(TokioExecutor::new()).build(connector),
+        });
+
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            let upstreams = upstreams.clone();
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |req| forward(upstreams.clone(), peer, req));
+                let builder = auto::Builder::new(TokioExecutor::new());
+                // Connection errors only affect that browser tab; a failed
+                // handshake usually means the certificate was rejected.
+                let _ = match acceptor {
+                    Some(acceptor) => match acceptor.accept(stream).await {
+                        Ok(tls) => builder.serve_connection_with_upgrades(TokioIo::new(tls), service).await,
+                        Err(_) => return,
+                    },
+                    None => builder.serve_connection_with_upgrades(TokioIo::new(stream), service).await,
+                };
+            });
+        }
+    }
+}
+
+async fn forward(
+    upstreams: Arc<Upstreams>,
+    peer: SocketAddr,
+    mut req: Request<Incoming>,
+) -> Result<Response<ProxyBody>, Infallible> {
+    if let Some(dashboard) = &upstreams.dashboard
+        && req.uri().path() == dashboard::LOG_LEVEL_PATH
+    {
+        let method = req.method().clone();
+        let body = match Limited::new(req.into_body(), MAX_LOG_FILTER_BYTES).collect().await {
+            Ok(body) => body.to_bytes(),
+            Err(_) => return Ok(error_response(StatusCode::PAYLOAD_TOO_LARGE, "montrs: log filter too long".to_string())),
+        };
+        return Ok(dashboard.log_level(peer, &method, &body));
+    }
+    if let Some(dashboard) = &upstreams.dashboard
+        && Dashboard::handles(req.uri().path())
+    {
+        return Ok(dashboard.respond(peer, req.uri().path(), req.headers()));
+    }
+
+    let path = req.uri().path_and_query().map(|p| p.as_str()).unwrap_or("/").to_string();
+    let rule = upstreams.rules.iter().find(|rule| rule.matches(req.uri().path()));
+    let uri = match rule {
+        Some(rule) => rule.upstream_uri(&path),
+        None => {
+            let upstream = match &upstreams.reload {
+                Some(reload) if req.uri().path() == RELOAD_PATH => reload,
+                _ => &upstreams.app,
+            };
+            format!("http://{}{}", upstream, path).parse()
+        }
+    };
+    let uri: Uri = match uri {
+        Ok(uri) => uri,
+        Err(e) => return Ok(error_response(StatusCode::BAD_REQUEST, e.to_string())),
+    };
+
+    // HTTP/2 requests carry the host in the URI instead of a header.
+    let host = req
+        .headers()
+        .get(header::HOST)
+        .cloned()
+        .or_else(|| req.uri().authority().and_then(|a| HeaderValue::from_str(a.as_str()).ok()));
+    *req.uri_mut() = uri.clone();
+    *req.version_mut() = Version::HTTP_11;
+    let headers = req.headers_mut();
+    headers.insert("x-forwarded-proto", HeaderValue::from_static(upstreams.scheme));
+    if let Ok(peer) = HeaderValue::from_str(&peer.ip().to_string()) {
+        headers.insert("x-forwarded-for", peer);
+    }
+    if let Some(host) = host {
+        headers.insert("x-forwarded-host", host.clone());
+        headers.insert(header::HOST, host);
+    }
+    if let Some(rule) = rule {
+        rule.apply_headers(headers);
+    }
+
+    let upgrade = req.headers().contains_key(header::UPGRADE).then(|| hyper::upgrade::on(&mut req));
+    let (req, captured) = match &upstreams.capture {
+        Some(capturer) if capturer.wants(&req) => match capturer.read(req, &path).await {
+            Ok((req, capture)) => (req, capture),
+            Err(message) => return Ok(error_response(StatusCode::BAD_REQUEST, message)),
+        },
+        _ => (req.map(|body| body.boxed()), None),
+    };
+    let result = upstreams.client.request(req).await;
+    if let (Some(capturer), Some(capture)) = (&upstreams.capture, captured) {
+        let status = result.as_ref().map_or(StatusCode::BAD_GATEWAY, |res| res.status());
+        capturer.save(capture, status.as_u16());
+    }
+    match result {
+        Ok(mut res) => {
+            if res.status() == StatusCode::SWITCHING_PROTOCOLS
+                && let Some(client) = upgrade
+            {
+                let server = hyper::upgrade::on(&mut res);
+                tokio::spawn(async move {
+                    if let (Ok(client), Ok(server)) = (client.await, server.await) {
+                        let _ = tokio::io::copy_bidirectional(&mut TokioIo::new(client), &mut TokioIo::new(server)).await;
+                    }
+                });
+            }
+            Ok(res.map(|body| body.boxed()))
+        }
+        Err(e) => {
+            let hint = match rule {
+                Some(rule) => format!("Is the backend for {} running at {}?", rule.prefix, rule.target),
+                None => "The app may still be compiling.".to_string(),
+            };
+            Ok(error_response(
+                StatusCode::BAD_GATEWAY,
+                format!("montrs: cannot reach {} ({}). {}", uri, e, hint),
+            ))
+        }
+    }
+}
+
+fn error_response(status: StatusCode, message: String) -> Response<ProxyBody> {
+    let mut res = Response::new(Full::new(Bytes::from(message)).map_err(|never| match never {}).boxed());
+    *res.status_mut() = status;
+    res.headers_mut()
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain; charset=utf-8"));
+    res
+}
+
+/// Where the app server listens: `LEPTOS_SITE_ADDR`, the `site-addr` of the
+/// leptos metadata in `Cargo.toml`, then `[serve]`.
+pub fn app_addr(config: &MontrsConfig) -> String {
+    std::env::var("LEPTOS_SITE_ADDR")
+        .ok()
+        .or_else(|| leptos_metadata("site-addr").and_then(|v| v.as_str().map(String::from)))
+        .unwrap_or_else(|| format!("{}:{}", config.serve.addr, config.serve.port))
+}
+
+/// Where cargo-leptos serves the live-reload websocket.
+pub fn reload_addr(config: &MontrsConfig) -> String {
+    let port = std::env::var("LEPTOS_RELOAD_PORT")
+        .ok()
+        .and_then(|p| p.parse().ok())
+        .or_else(|| leptos_metadata("reload-port").and_then(|v| v.as_integer()).and_then(|p| u16::try_from(p).ok()))
+        .unwrap_or(DEFAULT_RELOAD_PORT);
+    let app = app_addr(config);
+    let host = app.rsplit_once(':').map(|(host, _)| host).unwrap_or("127.0.0.1");
+    format!("{}:{}", host, port)
+}
+
+/// Reads a key of `[package.metadata.leptos]`, or of the first
+/// `[[workspace.metadata.leptos]]` entry, from `./Cargo.toml`.
+pub(crate) fn leptos_metadata(key: &str) -> Option<toml::Value> {
+    let manifest: toml::Value = toml::from_str(&std::fs::read_to_string("Cargo.toml").ok()?).ok()?;
+    let leptos = manifest
+        .get("package")
+        .and_then(|p| p.get("metadata"))
+        .and_then(|m| m.get("leptos"))
+        .or_else(|| {
+            manifest
+                .get("workspace")
+                .and_then(|w| w.get("metadata"))
+                .and_then(|m| m.get("leptos"))
+                .and_then(|l| l.as_array())
+                .and_then(|l| l.first())
+        })?;
+    leptos.get(key).cloned()
+}
@@ -0,0 +1,105 @@
+//! `[serve.proxy]` rules: which requests leave the app for another backend.
+
+use crate::config::ProxyConfig;
+use anyhow::{Context, Result};
+use hyper::Uri;
+use hyper::header::{HeaderMap, HeaderName, HeaderValue};
+use std::collections::HashMap;
+
+/// Requests under `prefix` go to `target` instead of the app.
+#[derive(Debug, Clone)]
+pub struct ProxyRule {
+    pub prefix: String,
+    pub target: String,
+    scheme: String,
+    authority: String,
+    base_path: String,
+    rewrite: Option<String>,
+    change_origin: bool,
+    headers: HeaderMap,
+}
+
+impl ProxyRule {
+    /// Parses every `[serve.proxy]` entry, longest prefix first so the most
+    /// specific rule wins.
+    pub fn from_config(entries: &HashMap<String, ProxyConfig>) -> Result<Vec<Self>> {
+        let mut rules = entries
+            .iter()
+            .map(|(prefix, config)| Self::new(prefix, config))
+            .collect::<Result<Vec<_>>>()?;
+        rules.sort_by(|a, b| b.prefix.len().cmp(&a.prefix.len()).then_with(|| a.prefix.cmp(&b.prefix)));
+        Ok(rules)
+    }
+
+    pub fn new(prefix: &str, config: &ProxyConfig) -> Result<Self> {
+        let context = || format!("Invalid [serve.proxy] entry \"{}\"", prefix);
+        if !prefix.starts_with('/') {
+            anyhow::bail!("{}: the prefix must start with '/'", context());
+        }
+        let (target, rewrite, change_origin, extra) = match config {
+            ProxyConfig::Simple(target) => (target, None, false, None),
+            ProxyConfig::Detailed { target, rewrite, change_origin, headers } => {
+                (target, rewrite.clone(), *change_origin, Some(headers))
+            }
+        };
+
+        let uri: Uri = target.parse().with_context(context)?;
+        let scheme = match uri.scheme_str() {
+            Some(scheme @ ("http" | "https")) => scheme.to_string(),
+            _ => anyhow::bail!("{}: the target must be an http:// or https:// URL", context()),
+        };
+        let authority = uri
+            .authority()
+            .map(|a| a.to_string())
+            .with_context(|| format!("{}: the target has no host", context()))?;
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in extra.into_iter().flatten() {
+            headers.insert(
+                HeaderName::try_from(name.as_str()).with_context(context)?,
+                HeaderValue::try_from(value.as_str()).with_context(context)?,
+            );
+        }
+
+        Ok(Self {
+            prefix: prefix.trim_end_matches('/').to_string(),
+            target: target.clone(),
+            scheme,
+            authority,
+            base_path: uri.path().trim_end_matches('/').to_string(),
+            rewrite,
+            change_origin,
+            headers,
+        })
+    }
+
+    /// Whether `path` is the prefix itself or below it; `/api` matches
+    /// `/api` and `/api/users` but not `/apiary`.
+    pub fn matches(&self, path: &str) -> bool {
+        path.strip_prefix(&self.prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/') || rest.starts_with('?'))
+    }
+
+    /// The upstream URI for a matching request's path and query.
+    pub fn upstream_uri(&self, path_and_query: &str) -> Result<Uri, hyper::http::uri::InvalidUri> {
+        let rest = &path_and_query[self.prefix.len()..];
+        let path = match &self.rewrite {
+            Some(rewrite) => format!("{}{}", rewrite.trim_end_matches('/'), rest),
+            None => format!("{}{}", self.prefix, rest),
+        };
+        let path = if path.starts_with('/') { path } else { format!("/{}", path) };
+        format!("{}://{}{}{}", self.scheme, self.authority, self.base_path, path).parse()
+    }
+
+    /// Applies `change_origin` and the configured headers to a forwarded request.
+    pub fn apply_headers(&self, headers: &mut HeaderMap) {
+        if self.change_origin
+            && let Ok(host) = HeaderValue::from_str(&self.authority)
+        {
+            headers.insert(hyper::header::HOST, host);
+        }
+        for (name, value) in &self.headers {
+            headers.insert(name, value.clone());
+        }
+    }
+}
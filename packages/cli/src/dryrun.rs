@@ -0,0 +1,54 @@
+//! File system changes that honour `--dry-run`.
+//!
+//! Commands that write files go through these helpers instead of `std::fs`.
+//! Normally they make the change; with `--dry-run` they record a
+//! [`Planned`] action on the reporter and leave the disk alone. Changes that
+//! are not files (SQL, keychain entries) check [`enabled`] and call
+//! [`plan`] themselves.
+
+use crate::report::{ActionKind, Planned, reporter};
+use std::io;
+use std::path::Path;
+
+/// Whether changes are being planned instead of made.
+pub fn enabled() -> bool {
+    reporter().dry_run()
+}
+
+/// Records `planned` for the summary.
+pub fn plan(planned: Planned) {
+    reporter().plan(planned);
+}
+
+/// `fs::create_dir_all`, unless the directory exists or this is a dry run.
+pub fn create_dir_all(path: &Path) -> io::Result<()> {
+    if path.is_dir() {
+        return Ok(());
+    }
+    if enabled() {
+        plan(Planned::new(ActionKind::CreateDir, path.display().to_string()));
+        return Ok(());
+    }
+    std::fs::create_dir_all(path)
+}
+
+/// `fs::write`, or a planned write saying whether the file is created or
+/// replaced and how large it becomes.
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let contents = contents.as_ref();
+    if enabled() {
+        let verb = if path.exists() { "replace" } else { "create" };
+        plan(Planned::new(ActionKind::WriteFile, path.display().to_string()).with_detail(format!("{}, {} bytes", verb, contents.len())));
+        return Ok(());
+    }
+    std::fs::write(path, contents)
+}
+
+/// Like [`write`], with `detail` describing the change instead of its size.
+pub fn write_with(path: &Path, contents: impl AsRef<[u8]>, detail: impl Into<String>) -> io::Result<()> {
+    if enabled() {
+        plan(Planned::new(ActionKind::WriteFile, path.display().to_string()).with_detail(detail));
+        return Ok(());
+    }
+    std::fs::write(path, contents)
+}
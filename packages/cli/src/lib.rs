@@ -1,12 +1,26 @@
+pub mod buildcache;
 pub mod command;
 pub mod config;
+pub mod crash;
+pub mod devproc;
+pub mod devproxy;
+pub mod dryrun;
 pub mod utils;
+pub mod workers;
 pub mod ext;
 pub mod error;
+pub mod logging;
 pub mod mcp;
+pub mod plugin;
+pub mod report;
+pub mod sandbox;
+pub mod taskcache;
 
 use clap::{Parser, Subcommand};
 
+/// This crate's version and the `montrs-core` it needs; see [`montrs_core::compat`].
+pub const CRATE_VERSION: montrs_core::CrateVersion = montrs_core::crate_version!();
+
 #[derive(Parser, Debug)]
 #[command(name = "cargo")]
 #[command(bin_name = "cargo")]
@@ -37,6 +51,25 @@ pub struct MontrsCli {
     #[arg(short, action = clap::ArgAction::Count)]
     pub verbose: u8,
 
+    /// Only print warnings and errors.
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// Format of the final report: a summary table (human) or one JSON object on stdout (json).
+    #[arg(long, value_enum, default_value_t = report::OutputFormat::Human)]
+    pub output: report::OutputFormat,
+
+    /// Print the files, SQL statements and keys that generate, fmt, secrets and
+    /// db would change, without changing them. Plugins see it as `dry_run` in
+    /// their context.
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Write agent snapshots and send model prompts without redacting secrets
+    /// (see `[agent.redact]` in montrs.toml).
+    #[arg(long, global = true)]
+    pub no_redact: bool,
+
     /// Output logs from dependencies (multiple --log accepted).
     #[arg(long)]
     pub log: Vec<String>,
@@ -53,9 +86,47 @@ pub struct MontrsCli {
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Build the project for production.
-    Build,
+    Build {
+        /// Build in release mode with a profile tuned for `size` or `speed`,
+        /// run wasm-opt and report artifact sizes.
+        #[arg(long, value_enum)]
+        optimize: Option<command::build::Optimize>,
+        /// Build the app as a WASI HTTP component for `wasm32-wasip2`
+        /// instead of the cargo-leptos site.
+        #[arg(long, conflicts_with = "optimize")]
+        wasi: bool,
+    },
     /// Serve the project for development with hot-reload.
-    Serve,
+    Serve {
+        /// Answer loaders and actions from the `mocks/` directory. Optionally
+        /// restrict mocking to a comma-separated list of route paths.
+        #[arg(long, value_name = "ROUTES", num_args = 0..=1, default_missing_value = "*")]
+        mock: Option<String>,
+
+        /// Profile every loader and action call; view with `montrs profile`.
+        #[arg(long)]
+        profile: bool,
+
+        /// Serve over HTTPS with HTTP/2 on `[serve.tls] port`, using a
+        /// generated local certificate (or mkcert's, when installed).
+        #[arg(long)]
+        tls: bool,
+
+        /// Record sanitized incoming requests to `.agent/captures`; send
+        /// them again with `montrs replay`.
+        #[arg(long)]
+        capture: bool,
+    },
+    /// Serve the app with the demo data in `[demo] seeds` and open it in the browser.
+    Demo {
+        /// Seed directory to load instead of `[demo] seeds`.
+        #[arg(long)]
+        seeds: Option<String>,
+
+        /// Do not open the browser.
+        #[arg(long)]
+        no_open: bool,
+    },
     /// Watch for changes and rebuild automatically.
     Watch,
     /// Run cargo tests for app, client and server.
@@ -74,6 +145,14 @@ pub enum Commands {
         /// Run tests in parallel jobs.
         #[arg(short = 'j', long)]
         jobs: Option<usize>,
+
+        /// Only test packages affected by files changed since `--base`.
+        #[arg(long)]
+        changed: bool,
+
+        /// Git revision `--changed` compares against.
+        #[arg(long, default_value = "HEAD", requires = "changed")]
+        base: String,
     },
     /// Run performance benchmarks.
     Bench {
@@ -135,19 +214,84 @@ pub enum Commands {
         /// Specify browser to use (chromium, firefox, webkit).
         #[arg(long)]
         browser: Option<String>,
+
+        /// Run the performance audit after the tests (see `[e2e.perf]`).
+        #[arg(long)]
+        perf: bool,
+
+        /// Run only part of the test files, e.g. `2/4` for the second of four
+        /// shards. Each shard serves the app on its own port.
+        #[arg(long, value_name = "I/N")]
+        shard: Option<command::e2e::Shard>,
+
+        /// Write the results as a JUnit report (default with --shard:
+        /// target/montrs/e2e/junit-<i>-of-<n>.xml).
+        #[arg(long)]
+        junit: Option<std::path::PathBuf>,
+
+        /// Merge the JUnit reports of all shards found in DIR into one
+        /// (written to --junit, default target/montrs/e2e/junit.xml) instead of running tests.
+        #[arg(long, value_name = "DIR", num_args = 0..=1, default_missing_value = command::e2e::REPORTS_DIR)]
+        merge_reports: Option<std::path::PathBuf>,
+
+        /// Test command to run and record as JUnit; used by sharded runs.
+        #[arg(long, hide = true)]
+        record: Option<String>,
+    },
+    /// Audit route performance against the budgets in `[e2e.perf]`.
+    Perf {
+        /// Site URL to audit (default: detected from the config).
+        #[arg(long)]
+        url: Option<String>,
+
+        /// Save this run as the baseline for future comparisons.
+        #[arg(long)]
+        update_baseline: bool,
+
+        /// Command to run before auditing; used by `montrs e2e --perf`.
+        #[arg(long, hide = true)]
+        after: Option<String>,
+    },
+    /// Show the slowest routes from the last `serve --profile` session.
+    Profile {
+        /// Number of routes to show.
+        #[arg(long, default_value = "10")]
+        top: usize,
+
+        /// Print the folded stacks for a flamegraph tool instead of the summary.
+        #[arg(long)]
+        folded: bool,
+    },
+    /// Send requests recorded by `serve --capture` to a running server again.
+    Replay {
+        /// A capture file, or a directory of captures to send in order.
+        capture: String,
+
+        /// Base URL of the server; defaults to the dev server's address.
+        #[arg(long)]
+        url: Option<String>,
     },
     /// Create a new project from a template.
     New {
         /// Name of the project.
         name: String,
-        /// Template to use.
+        /// Template to use: a name under templates/, or a git URL (https://, git@, gh:owner/repo).
         #[arg(short, long, default_value = "default")]
         template: String,
+        /// Let post-generate hooks run this program (by name, or a path inside the project).
+        #[arg(long = "allow", value_name = "PROGRAM")]
+        allow: Vec<String>,
+        /// Let post-generate hooks reach the network.
+        #[arg(long)]
+        allow_network: bool,
     },
     /// Run custom tasks defined in montrs.toml.
     Run {
         /// Name of the task to run.
         task: String,
+        /// Run tasks with `inputs` even when the cache has their result.
+        #[arg(long)]
+        force: bool,
     },
     /// List available tasks.
     Tasks,
@@ -157,15 +301,60 @@ pub enum Commands {
         shell: clap_complete::Shell,
     },
     /// Upgrade the MontRS CLI to the latest version.
-    Upgrade,
+    Upgrade {
+        /// Release channel to install from (defaults to [upgrade].channel).
+        #[arg(long, value_enum)]
+        channel: Option<config::ReleaseChannel>,
+        /// Install a specific version instead of the latest.
+        #[arg(long)]
+        version: Option<String>,
+        /// Install a pre-downloaded binary instead of building from source.
+        #[arg(long)]
+        artifact: Option<std::path::PathBuf>,
+        /// Expected sha256 of the artifact (defaults to <artifact>.sha256).
+        #[arg(long)]
+        sha256: Option<String>,
+        /// Minisign signature of the artifact (defaults to <artifact>.minisig).
+        #[arg(long)]
+        signature: Option<std::path::PathBuf>,
+        /// Install the artifact without checking its signature; the sha256 is still checked.
+        #[arg(long, requires = "artifact")]
+        insecure: bool,
+        /// Only check the installed CLI against the workspace requirements.
+        #[arg(long)]
+        check: bool,
+    },
     /// Generate an agent-readable specification and snapshot of the project.
     Spec {
         /// Include documentation in the snapshot.
         #[arg(long)]
         include_docs: bool,
-        /// Output format (json, yaml, txt).
+        /// Output format: json, yaml, txt, md (also written to .agent/agent.md), jsonl (a streamed
+        /// snapshot in .agent/), or sqlite (.agent/agent.db with files, routes, plates and errors).
         #[arg(long, default_value = "json")]
         format: String,
+        /// Boot the app to take its AppSpec fingerprint and write it to montrs.fingerprint.json.
+        #[arg(long)]
+        fingerprint: bool,
+        /// With --fingerprint, fail when it differs from the committed montrs.fingerprint.json.
+        #[arg(long, requires = "fingerprint")]
+        check: bool,
+    },
+    /// Compare the app's exported spec with a previous release and print the changes as a Markdown
+    /// changelog section, classifying breaking route, param and access changes.
+    Changelog {
+        /// Tag, git revision or spec file to compare against (defaults to the latest tag).
+        #[arg(long)]
+        since: Option<String>,
+        /// Heading for the new section, such as the version being released.
+        #[arg(long)]
+        to: Option<String>,
+        /// Where to write the JSON diff for release tooling.
+        #[arg(long, default_value = "target/montrs/changelog.json")]
+        json: std::path::PathBuf,
+        /// Also write the current spec to montrs.spec.json, to commit with the release.
+        #[arg(long)]
+        save: bool,
     },
     /// Generate a single-file "sketch" of a MontRS component.
     Sketch {
@@ -180,7 +369,22 @@ pub enum Commands {
         /// Path to the sketch file.
         path: String,
     },
-    /// Generate boilerplate for plates and routes.
+    /// Render the package/plate dependency graph (DOT, Mermaid, or SVG).
+    Graph {
+        /// Output format (dot, mermaid, svg).
+        #[arg(short, long, default_value = "mermaid")]
+        format: String,
+        /// Write the graph to a file instead of stdout.
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+        /// Compare against an older snapshot file or git revision.
+        #[arg(long)]
+        diff: Option<String>,
+        /// Snapshot file or git revision to render instead of the working tree.
+        #[arg(long)]
+        to: Option<String>,
+    },
+    /// Generate boilerplate for plates, routes, and models.
     Generate {
         #[command(subcommand)]
         subcommand: GenerateSubcommand,
@@ -190,11 +394,51 @@ pub enum Commands {
         #[command(subcommand)]
         subcommand: AgentSubcommand,
     },
+    /// Check crate and CLI version compatibility and record compiler and clippy diagnostics (`agent doctor`).
+    Doctor {
+        /// Optional package to focus on.
+        #[arg(short, long)]
+        package: Option<String>,
+    },
     /// Model Context Protocol (MCP) server mode.
     Mcp {
         #[command(subcommand)]
         subcommand: McpSubcommand,
     },
+    /// Explain a tracked error with the configured language model and store the answer.
+    Explain {
+        /// ID of the error record (see `montrs agent list-errors`).
+        id: String,
+        /// Ask the model again instead of printing the stored explanation.
+        #[arg(long)]
+        refresh: bool,
+    },
+    /// Inspect the project configuration.
+    Config {
+        #[command(subcommand)]
+        subcommand: ConfigSubcommand,
+    },
+    /// Manage the encrypted secrets file.
+    Secrets {
+        #[command(subcommand)]
+        subcommand: SecretsSubcommand,
+    },
+    /// Database tools: check queries against the schema, detect schema drift,
+    /// export and import table data.
+    Db {
+        #[command(subcommand)]
+        subcommand: DbSubcommand,
+    },
+    /// Install community plates from the registry or git.
+    Plate {
+        #[command(subcommand)]
+        subcommand: PlateSubcommand,
+    },
+    /// List installed CLI plugins.
+    Plugins,
+    /// Run a plugin (`montrs-<name>` on PATH or declared in montrs.toml).
+    #[command(external_subcommand)]
+    External(Vec<String>),
 }
 
 #[derive(Subcommand, Debug)]
@@ -205,7 +449,8 @@ pub enum AgentSubcommand {
         #[arg(default_value = ".")]
         path: String,
     },
-    /// Assess project health and agent-readability.
+    /// Check that the montrs crates and the CLI are compatible, then run clippy with JSON diagnostics and
+    /// record each error and warning, with its suggested fixes.
     Doctor {
         /// Optional package to focus on.
         #[arg(short, long)]
@@ -216,11 +461,256 @@ pub enum AgentSubcommand {
         /// Path to the error file or diagnostic report.
         path: String,
     },
-    /// List all active and resolved errors tracked by the agent.
+    /// List, filter and page through the errors tracked by the agent.
+    #[command(visible_alias = "errors")]
     ListErrors {
         /// Filter by status (active or resolved).
         #[arg(short, long)]
         status: Option<String>,
+        /// Only errors in this package (`orm` also matches `montrs-orm`).
+        #[arg(short, long)]
+        package: Option<String>,
+        /// Only errors in files matching this glob, e.g. `src/routes/**`.
+        #[arg(short, long)]
+        file: Option<String>,
+        /// Only errors of this level (error or warning).
+        #[arg(short, long)]
+        level: Option<String>,
+        /// Only errors with this code, e.g. E0308.
+        #[arg(long)]
+        code: Option<String>,
+        /// Only errors updated since then: 30m, 12h, 7d, 2w, a date or an RFC 3339 timestamp.
+        #[arg(long)]
+        since: Option<String>,
+        /// Only errors updated before then, in the same formats as --since.
+        #[arg(long)]
+        until: Option<String>,
+        /// newest, oldest, file or package.
+        #[arg(long, default_value = "newest")]
+        sort: String,
+        /// Number of errors to show.
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+        /// Number of matching errors to skip, for paging.
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+    },
+    /// Index the projects of a workspace from ~/.montrs/workspaces.toml, or list workspaces.
+    Workspace {
+        /// Workspace to index; lists the configured workspaces when omitted.
+        name: Option<String>,
+        /// Search routes, packages and plates across the workspace's projects.
+        #[arg(short, long)]
+        search: Option<String>,
+    },
+    /// Apply the machine-applicable suggestion or stored diff of a tracked error, then re-check.
+    Fix {
+        /// ID of the error record.
+        id: String,
+        /// Apply without asking for confirmation.
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Record and review agent task sessions in .agent/sessions.
+    Session {
+        #[command(subcommand)]
+        subcommand: SessionSubcommand,
+    },
+    /// Search the framework and project documentation.
+    Search {
+        /// What to look for, e.g. "how do I add a route guard".
+        query: String,
+        /// Rank by meaning with the configured embedding model instead of by keywords.
+        #[arg(long)]
+        semantic: bool,
+        /// Number of snippets to return.
+        #[arg(short = 'k', long, default_value_t = 5)]
+        top: usize,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SessionSubcommand {
+    /// Open a session for a task and print its id.
+    Start {
+        /// What the session sets out to do.
+        goal: String,
+    },
+    /// Record files touched, a command run or a note in a session.
+    Log {
+        /// Session to record in; defaults to the latest open session.
+        #[arg(long)]
+        session: Option<String>,
+        /// A file the session changed (repeatable).
+        #[arg(short, long = "file")]
+        files: Vec<String>,
+        /// A command the session ran.
+        #[arg(short, long)]
+        command: Option<String>,
+        /// Exit code of `--command`.
+        #[arg(long, requires = "command")]
+        exit_code: Option<i32>,
+        /// A free-form note, e.g. a decision or a dead end.
+        #[arg(short, long)]
+        note: Option<String>,
+    },
+    /// Close a session with its outcome.
+    End {
+        /// What was achieved, or why it stopped.
+        summary: String,
+        /// completed, failed or abandoned.
+        #[arg(long, default_value = "completed")]
+        outcome: String,
+        /// Session to close; defaults to the latest open session.
+        #[arg(long)]
+        session: Option<String>,
+    },
+    /// Summarize the latest sessions, to resume earlier work.
+    List {
+        /// Number of sessions to show.
+        #[arg(short = 'n', long, default_value_t = 5)]
+        limit: usize,
+    },
+    /// Show one session in full.
+    Show {
+        /// ID of the session.
+        id: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigSubcommand {
+    /// Print the configuration in effect: `montrs.toml` and its layers with
+    /// defaults filled in, and the preset the app serves with on a target.
+    Resolve {
+        /// Target to resolve the preset for (default: `[preset] target`, or server).
+        #[arg(long)]
+        target: Option<montrs_core::Target>,
+        /// Output format (toml, json).
+        #[arg(long, default_value = "toml")]
+        format: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SecretsSubcommand {
+    /// Create the secrets file and a key for this machine.
+    Init,
+    /// Encrypt and store a secret (reads the value from stdin if omitted).
+    Set {
+        /// Name of the secret, as read through `EnvConfig`.
+        key: String,
+        /// The secret value.
+        value: Option<String>,
+    },
+    /// Decrypt and print a secret.
+    Get {
+        /// Name of the secret.
+        key: String,
+    },
+    /// List secret names without decrypting them.
+    List,
+    /// Remove a secret.
+    Rm {
+        /// Name of the secret.
+        key: String,
+    },
+    /// Give another public key access and re-encrypt every secret.
+    AddRecipient {
+        /// The recipient's `age1...` public key.
+        public_key: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PlateSubcommand {
+    /// Add a plate: depend on its crate, register it in the AppSpec, copy its
+    /// migrations and assets, run its install hooks and record it in montrs.toml.
+    Add {
+        /// Name in the registry (`auth`), or a git URL (https://, git@, gh:owner/repo).
+        name: String,
+        /// Version requirement, instead of the one the registry lists.
+        #[arg(long)]
+        version: Option<String>,
+        /// Git revision to pin (default: the remote's HEAD).
+        #[arg(long, conflicts_with = "version")]
+        rev: Option<String>,
+        /// Registry index URL or file (defaults to [plates].registry).
+        #[arg(long)]
+        registry: Option<String>,
+        /// File whose `AppSpec` the plate is registered with (default: the first main.rs that builds one).
+        #[arg(long)]
+        app: Option<std::path::PathBuf>,
+        /// Let install hooks run this program (by name, or a path inside the project).
+        #[arg(long = "allow", value_name = "PROGRAM")]
+        allow: Vec<String>,
+        /// Let install hooks reach the network.
+        #[arg(long)]
+        allow_network: bool,
+        /// Do not run the plate's post-install hooks.
+        #[arg(long)]
+        no_hooks: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DbSubcommand {
+    /// Check SQL literals passed to `execute`/`query` against the schema.
+    Check {
+        /// Project directory to scan.
+        #[arg(default_value = ".")]
+        path: String,
+        /// Check against the live database at `[database].url` instead of the migrations.
+        #[arg(long)]
+        live: bool,
+    },
+    /// Compare the live database with the schema the migrations describe.
+    Diff {
+        /// Project directory.
+        #[arg(default_value = ".")]
+        path: String,
+        /// Write the drift report as JSON to this file.
+        #[arg(long)]
+        report: Option<std::path::PathBuf>,
+        /// Draft a migration in the migrations directory that corrects the drift.
+        #[arg(long)]
+        migration: bool,
+    },
+    /// Re-encrypt `#[orm(encrypted)]` columns with the current key.
+    RotateKeys {
+        /// Project directory to scan for encrypted models.
+        #[arg(default_value = ".")]
+        path: String,
+        /// Add a new key to the secrets file and make it current first.
+        #[arg(long)]
+        generate: bool,
+    },
+    /// Write table data to one file per table.
+    Export {
+        /// File format.
+        #[arg(long, value_enum, default_value_t = command::db::TransferFormat::Jsonl)]
+        format: command::db::TransferFormat,
+        /// Table to export (repeatable); all tables when omitted.
+        #[arg(long = "table")]
+        tables: Vec<String>,
+        /// Directory the files are written to, as `<table>.<format>`.
+        #[arg(short, long, default_value = "db-export")]
+        out: std::path::PathBuf,
+    },
+    /// Load table data written by `db export`, all or nothing.
+    Import {
+        /// An exported file, or a directory of them.
+        path: std::path::PathBuf,
+        /// For a file, the table to load it into (defaults to the file name).
+        /// For a directory, the tables to load, in this order (repeatable).
+        #[arg(long = "table")]
+        tables: Vec<String>,
+        /// File format (defaults to the file extension).
+        #[arg(long, value_enum)]
+        format: Option<command::db::TransferFormat>,
+        /// What to do with rows whose primary key already exists.
+        #[arg(long, value_enum, default_value_t = command::db::ConflictStrategy::Skip)]
+        on_conflict: command::db::ConflictStrategy,
     },
 }
 
@@ -236,6 +726,9 @@ pub enum GenerateSubcommand {
     Plate {
         /// Name of the plate.
         name: String,
+        /// Skip the unit, integration, and e2e test scaffolds.
+        #[arg(long)]
+        no_tests: bool,
     },
     /// Generate a new route.
     Route {
@@ -244,23 +737,37 @@ pub enum GenerateSubcommand {
         /// The plate to add this route to.
         #[arg(short, long)]
         plate: String,
+        /// Skip the unit, integration, and e2e test scaffolds.
+        #[arg(long)]
+        no_tests: bool,
+    },
+    /// Generate a new validated data model.
+    Model {
+        /// Name of the model.
+        name: String,
+        /// Skip the unit and integration test scaffolds.
+        #[arg(long)]
+        no_tests: bool,
+    },
+    /// Move route params, loader outputs and action inputs/outputs into a crate
+    /// shared by the server and the front-end.
+    ApiTypes {
+        /// Name of the shared crate.
+        #[arg(long, default_value = "api-types")]
+        name: String,
+        /// Only verify that every route type is defined once, in the shared crate.
+        #[arg(long)]
+        check: bool,
     },
 }
 
 pub async fn run(cli: MontrsCli) -> anyhow::Result<()> {
-    // Setup logger based on verbosity
-    let log_level = match cli.verbose {
-        0 => tracing::Level::INFO,
-        1 => tracing::Level::DEBUG,
-        _ => tracing::Level::TRACE,
-    };
-    
-    // Initialize tracing if not already initialized
-    let _ = tracing_subscriber::fmt()
-        .with_max_level(log_level)
-        .try_init();
-
     let mut config = config::MontrsConfig::load()?;
+    // Held until the command returns so file sinks flush their last lines.
+    let _log_guard = logging::init(&config.logging, cli.verbose, cli.quiet, &config.project.name)?;
+    if let Ok(root) = std::env::current_dir() {
+        crash::install(&config.crash, root);
+    }
     config.project.verbose = cli.verbose;
     config.project.log = cli.log.clone();
     config.project.release = cli.release;
@@ -273,16 +780,51 @@ pub async fn run(cli: MontrsCli) -> anyhow::Result<()> {
         config.project.tailwind_style = Some(config::TailwindStyle::V4);
     }
 
-    match cli.command {
-        Commands::Build => command::build::run().await,
-        Commands::Serve => command::serve::run().await,
+    // Resolving the workspace runs `cargo metadata`, so only the commands
+    // that compile or generate code against montrs-core pay for it.
+    if matches!(
+        cli.command,
+        Commands::Build { .. }
+            | Commands::Serve { .. }
+            | Commands::Demo { .. }
+            | Commands::Watch
+            | Commands::Generate { .. }
+            | Commands::Sketch { .. }
+            | Commands::Expand { .. }
+    ) {
+        command::upgrade::warn_on_mismatch(&config);
+    }
+
+    // MCP speaks JSON-RPC on stdout, and agent and completion output is
+    // consumed by other programs, so they never get a summary.
+    let summarize = !matches!(
+        cli.command,
+        Commands::Mcp { .. } | Commands::Agent { .. } | Commands::Doctor { .. } | Commands::Completions { .. }
+    );
+    let reporter = report::init(&command_name(&cli.command), cli.output, cli.quiet, cli.dry_run);
+    if cli.no_redact {
+        montrs_agent::redact::disable();
+        reporter.warn(
+            "REDACTION DISABLED (--no-redact): keys, tokens and passwords in docs, .env files and source are written \
+             to the agent directory and sent to models as they are",
+        );
+    }
+
+    let result = match cli.command {
+        Commands::Build { optimize, wasi } => command::build::run(optimize, wasi, config.project.clone()).await,
+        Commands::Serve { mock, profile, tls, capture } => {
+            command::serve::run(mock, profile, tls, capture, config.project.clone()).await
+        }
+        Commands::Demo { seeds, no_open } => command::demo::run(seeds, no_open, config.project.clone()).await,
         Commands::Watch => command::watch::run().await,
         Commands::Test {
             filter,
             report,
             output,
             jobs,
-        } => command::test::run(filter, report, output, jobs).await,
+            changed,
+            base,
+        } => command::test::run(filter, report, output, jobs, changed.then_some(base)).await,
         Commands::Bench {
             target,
             iterations,
@@ -294,9 +836,28 @@ pub async fn run(cli: MontrsCli) -> anyhow::Result<()> {
             generate_weights,
         } => command::bench::run(target, iterations, warmup, timeout, filter, json_output, simple, generate_weights).await,
         Commands::Fmt { check, path, verbose } => command::fmt::run(config.fmt, check, path, verbose).await,
-        Commands::E2e { headless, keep_alive, browser } => command::e2e::run(headless, keep_alive, browser).await,
-        Commands::New { name, template } => command::new::run(name, template).await,
-        Commands::Run { task } => command::run::run(task).await,
+        Commands::E2e { headless, keep_alive, browser, perf, shard, junit, merge_reports, record } => {
+            command::e2e::run(command::e2e::E2eOptions {
+                headless,
+                keep_alive,
+                browser,
+                perf,
+                shard,
+                junit,
+                merge_reports,
+                record,
+            })
+            .await
+        }
+        Commands::Perf { url, update_baseline, after } => {
+            command::perf::run(url, after, update_baseline).await
+        }
+        Commands::Profile { top, folded } => command::profile::run(top, folded),
+        Commands::Replay { capture, url } => command::replay::run(capture, url).await,
+        Commands::New { name, template, allow, allow_network } => {
+            command::new::run(name, template, sandbox::Policy { allow, network: allow_network }, &config).await
+        }
+        Commands::Run { task, force } => command::run::run(task, force).await,
         Commands::Tasks => command::run::list().await,
         Commands::Completions { shell } => {
             use clap::CommandFactory;
@@ -305,22 +866,47 @@ pub async fn run(cli: MontrsCli) -> anyhow::Result<()> {
             clap_complete::generate(shell, &mut cmd, name, &mut std::io::stdout());
             Ok(())
         }
-        Commands::Spec { include_docs, format } => {
+        Commands::Spec { fingerprint: true, check, .. } => command::spec::fingerprint(check, &config.project).await,
+        Commands::Spec { include_docs, format, .. } => {
             command::spec::run(include_docs, format).await
         }
+        Commands::Changelog { since, to, json, save } => {
+            command::changelog::run(command::changelog::ChangelogOptions { since, to, json, save }, &config.project).await
+        }
         Commands::Sketch { name, kind } => {
             command::sketch::run(name, kind).await
         }
         Commands::Expand { path } => {
             command::expand::run(path).await
         }
-        Commands::Upgrade => command::upgrade::run().await,
+        Commands::Upgrade { channel, version, artifact, sha256, signature, insecure, check } => {
+            command::upgrade::run(command::upgrade::UpgradeOptions {
+                channel,
+                version,
+                artifact,
+                sha256,
+                signature,
+                insecure,
+                check,
+            })
+            .await
+        }
+        Commands::Graph { format, output, diff, to } => {
+            command::graph::run(config.project.name.clone(), format, output, diff, to).await
+        }
         Commands::Generate { subcommand } => match subcommand {
-            GenerateSubcommand::Plate { name } => command::generate::plate(name).await,
-            GenerateSubcommand::Route { path, plate } => {
-                command::generate::route(path, plate).await
+            GenerateSubcommand::Plate { name, no_tests } => command::generate::plate(name, no_tests).await,
+            GenerateSubcommand::Route { path, plate, no_tests } => {
+                command::generate::route(path, plate, no_tests).await
             }
+            GenerateSubcommand::Model { name, no_tests } => command::generate::model(name, no_tests).await,
+            GenerateSubcommand::ApiTypes { name, check } => command::api_types::run(name, check).await,
         },
+        Commands::Doctor { package } => {
+            let output = command::agent::run(AgentSubcommand::Doctor { package }).await?;
+            println!("{}", output);
+            Ok(())
+        }
         Commands::Agent { subcommand } => {
             match command::agent::run(subcommand).await {
                 Ok(output) => {
@@ -336,36 +922,71 @@ pub async fn run(cli: MontrsCli) -> anyhow::Result<()> {
         Commands::Mcp { subcommand } => {
             command::mcp::run(subcommand).await
         }
+        Commands::Config { subcommand } => command::config::run(subcommand, &config),
+        Commands::Secrets { subcommand } => command::secrets::run(subcommand, &config).await,
+        Commands::Db { subcommand } => command::db::run(subcommand, &config).await,
+        Commands::Explain { id, refresh } => command::explain::run(id, refresh, &config).await,
+        Commands::Plate { subcommand } => command::plate::run(subcommand, &config).await,
+        Commands::Plugins => command::plugin::list(&config).await,
+        Commands::External(args) => command::plugin::run(args, &config).await,
+    };
+
+    if summarize {
+        reporter.finish(&result);
     }
+    result
+}
+
+/// The subcommand as typed, e.g. `build` or `e2e`, for the report header.
+fn command_name(command: &Commands) -> String {
+    use clap::CommandFactory;
+    let debug = format!("{:?}", command);
+    let variant: String = debug.chars().take_while(|c| c.is_alphanumeric()).collect();
+    MontrsCli::command()
+        .get_subcommands()
+        .map(|s| s.get_name().to_string())
+        .find(|name| name.replace('-', "").eq_ignore_ascii_case(&variant))
+        .unwrap_or_else(|| variant.to_lowercase())
 }
 
 /// Main entry point for the CLI, handling both standalone and cargo subcommand modes.
 pub fn main_entry() {
     let args: Vec<String> = std::env::args().collect();
 
-    // Agent: Autogenerate .agent folder on every CLI interaction (even before parsing)
+    // Agent: Keep .agent up to date on every CLI interaction (even before parsing).
+    // Read-only checkouts fall back to a temp directory or to memory, in which
+    // case there is nothing to update.
     if let Ok(cwd) = std::env::current_dir() {
         let agent_manager = montrs_agent::AgentManager::new(&cwd);
+        if let Some(fallback) = agent_manager.storage_fallback() {
+            eprintln!("Warning: {}", fallback);
+        }
         let app_name = std::fs::read_to_string("montrs.toml")
             .ok()
             .and_then(|c| toml::from_str::<toml::Value>(&c).ok())
             .and_then(|v| v.get("project").and_then(|p| p.get("name")).and_then(|n| n.as_str()).map(|s| s.to_string()))
             .unwrap_or_else(|| "app".to_string());
 
-        // Initialize .agent if it doesn't exist
-        if !agent_manager.agent_dir().exists() {
-            if let Err(e) = agent_manager.ensure_dir() {
-                eprintln!("Warning: Failed to create .agent directory: {}", e);
-            }
+        // Initialize the agent directory if it doesn't exist
+        if let Err(e) = agent_manager.ensure_dir() {
+            eprintln!("Warning: Failed to create {} directory: {}", agent_manager.agent_dir().display(), e);
         }
 
         // Agent: Update tools and snapshot if we are in an existing project
-        if args.len() > 1 && args[1] != "new" {
+        if agent_manager.is_persistent() && args.len() > 1 && args[1] != "new" {
             // Check if we're in a MontRS project before doing agent work
             if cwd.join("montrs.toml").exists() || cwd.join("Cargo.toml").exists() {
                 if let Err(e) = agent_manager.write_tools_spec() {
                     eprintln!("Warning: Failed to update tools spec: {}", e);
                 }
+
+                match agent_manager.resolve_referenced_errors() {
+                    Ok(ids) if !ids.is_empty() => {
+                        eprintln!("Agent: Resolved {} error(s) referenced in commit messages", ids.len());
+                    }
+                    Ok(_) => {}
+                    Err(e) => eprintln!("Agent: Failed to link commits to errors: {}", e),
+                }
                 
                 match agent_manager.generate_snapshot(&app_name) {
                     Ok(snapshot) => {
@@ -412,9 +1033,11 @@ pub fn main_entry() {
         // Agent: On success, check if we resolved any active errors
         if let Ok(cwd) = std::env::current_dir() {
             let agent_manager = montrs_agent::AgentManager::new(&cwd);
-            let diff = agent_manager.generate_diff();
-            if let Err(err) = agent_manager.auto_resolve_active_errors("Build/Command succeeded".to_string(), diff) {
-                eprintln!("Agent: Failed to resolve active errors: {}", err);
+            if agent_manager.is_persistent() {
+                let diff = agent_manager.generate_diff();
+                if let Err(err) = agent_manager.auto_resolve_active_errors("Build/Command succeeded".to_string(), diff) {
+                    eprintln!("Agent: Failed to resolve active errors: {}", err);
+                }
             }
         }
     }
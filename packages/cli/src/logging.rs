@@ -0,0 +1,219 @@
+//! Logging for the CLI, configured by the `[logging]` section of `montrs.toml`.
+//!
+//! Every sink (stdout, rolling files, syslog) sits behind one reloadable
+//! `EnvFilter`, built from `level` plus the per-module overrides. `RUST_LOG`
+//! replaces the configured filter, and `-v`/`-q` replace its default level.
+//! While the CLI runs, [`set_filter`] swaps the filter in place; the dev
+//! dashboard exposes it at `/_montrs/log-level`.
+
+use crate::config::{LogFormat, LogRotation, LogSink, LoggingConfig};
+use anyhow::{Context, Result};
+use std::io::{self, Write};
+use std::net::UdpSocket;
+use std::path::Path;
+use std::sync::OnceLock;
+use tracing::{Level, Metadata};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::{Layer, Layered, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Registry, fmt, reload};
+
+type Filtered = Layered<reload::Layer<EnvFilter, Registry>, Registry>;
+type SinkLayer = Box<dyn Layer<Filtered> + Send + Sync>;
+
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// Keeps file sinks writing; dropping it flushes and stops them.
+#[must_use]
+pub struct LogGuard {
+    _workers: Vec<WorkerGuard>,
+}
+
+/// Installs the global subscriber described by `config`.
+///
+/// Does nothing if a subscriber is already installed.
+pub fn init(config: &LoggingConfig, verbose: u8, quiet: bool, app_name: &str) -> Result<LogGuard> {
+    let directives = directives(config, verbose, quiet);
+    let filter = EnvFilter::try_new(&directives)
+        .with_context(|| format!("Invalid log filter `{}` in `[logging]`", directives))?;
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let mut workers = Vec::new();
+    let mut sinks: Vec<SinkLayer> = Vec::new();
+    for sink in &config.sinks {
+        sinks.push(match sink {
+            LogSink::Stdout { format } => formatted(fmt::layer().with_writer(io::stdout), *format),
+            LogSink::File { path, format, rotation, retention } => {
+                let (writer, guard) = tracing_appender::non_blocking(rolling_file(path, *rotation, *retention)?);
+                workers.push(guard);
+                formatted(fmt::layer().with_writer(writer).with_ansi(false), *format)
+            }
+            LogSink::Syslog { address, app_name: tag } => {
+                let syslog = Syslog::connect(address, tag.as_deref().unwrap_or(app_name))?;
+                Box::new(fmt::layer().with_writer(syslog).with_ansi(false).without_time())
+            }
+        });
+    }
+
+    if tracing_subscriber::registry().with(filter).with(sinks).try_init().is_ok() {
+        let _ = FILTER.set(handle);
+    }
+    Ok(LogGuard { _workers: workers })
+}
+
+/// The filter currently applied to all sinks, if [`init`] installed one.
+pub fn current_filter() -> Option<String> {
+    FILTER.get()?.with_current(|filter| filter.to_string()).ok()
+}
+
+/// Replaces the filter of all sinks with `directives`, e.g. `"debug"` or
+/// `"info,montrs_cli::devproxy=trace"`.
+pub fn set_filter(directives: &str) -> Result<()> {
+    let handle = FILTER.get().context("Logging has not been initialized")?;
+    let filter = EnvFilter::try_new(directives.trim())
+        .with_context(|| format!("Invalid log filter `{}`", directives.trim()))?;
+    handle.reload(filter).context("The logging subscriber is gone")
+}
+
+fn directives(config: &LoggingConfig, verbose: u8, quiet: bool) -> String {
+    if let Ok(env) = std::env::var(EnvFilter::DEFAULT_ENV)
+        && !env.trim().is_empty()
+    {
+        return env;
+    }
+    let level = match verbose {
+        0 if quiet => "warn",
+        0 => config.level.as_str(),
+        1 => "debug",
+        _ => "trace",
+    };
+    let mut modules: Vec<_> = config.modules.iter().collect();
+    modules.sort();
+    std::iter::once(level.to_string())
+        .chain(modules.into_iter().map(|(module, level)| format!("{}={}", module, level)))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn formatted<W>(layer: fmt::Layer<Filtered, fmt::format::DefaultFields, fmt::format::Format, W>, format: LogFormat) -> SinkLayer
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    match format {
+        LogFormat::Pretty => Box::new(layer),
+        LogFormat::Json => Box::new(layer.json()),
+    }
+}
+
+fn rolling_file(path: &str, rotation: LogRotation, retention: usize) -> Result<RollingFileAppender> {
+    let path = Path::new(path);
+    let dir = path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let prefix = path.file_name().and_then(|n| n.to_str()).unwrap_or("montrs.log");
+    let rotation = match rotation {
+        LogRotation::Minutely => Rotation::MINUTELY,
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Never => Rotation::NEVER,
+    };
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(prefix)
+        .max_log_files(retention.max(1))
+        .build(dir)
+        .with_context(|| format!("Failed to open log file {}", path.display()))
+}
+
+/// Sends each event as an RFC 3164 datagram with the `user` facility.
+struct Syslog {
+    socket: SyslogSocket,
+    tag: String,
+    pid: u32,
+}
+
+enum SyslogSocket {
+    Udp(UdpSocket),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixDatagram),
+}
+
+impl Syslog {
+    fn connect(address: &str, tag: &str) -> Result<Self> {
+        let socket = match address.strip_prefix("udp://") {
+            Some(addr) => {
+                let socket = UdpSocket::bind("0.0.0.0:0")?;
+                socket.connect(addr).with_context(|| format!("Cannot reach syslog at {}", address))?;
+                SyslogSocket::Udp(socket)
+            }
+            #[cfg(unix)]
+            None => {
+                let socket = std::os::unix::net::UnixDatagram::unbound()?;
+                socket.connect(address).with_context(|| format!("Cannot reach syslog at {}", address))?;
+                SyslogSocket::Unix(socket)
+            }
+            #[cfg(not(unix))]
+            None => anyhow::bail!("Syslog address `{}` must be `udp://host:port` on this platform", address),
+        };
+        Ok(Self { socket, tag: tag.to_string(), pid: std::process::id() })
+    }
+
+    fn send(&self, severity: u8, line: &[u8]) {
+        let message = String::from_utf8_lossy(line);
+        let datagram = format!("<{}>{}[{}]: {}", 8 + severity, self.tag, self.pid, message.trim());
+        // A log line that cannot be delivered is dropped rather than failing the command.
+        let _ = match &self.socket {
+            SyslogSocket::Udp(socket) => socket.send(datagram.as_bytes()),
+            #[cfg(unix)]
+            SyslogSocket::Unix(socket) => socket.send(datagram.as_bytes()),
+        };
+    }
+}
+
+fn severity(level: &Level) -> u8 {
+    match *level {
+        Level::ERROR => 3,
+        Level::WARN => 4,
+        Level::INFO => 6,
+        _ => 7,
+    }
+}
+
+/// Collects one formatted event and sends it when dropped.
+struct SyslogLine<'a> {
+    syslog: &'a Syslog,
+    severity: u8,
+    buf: Vec<u8>,
+}
+
+impl Write for SyslogLine<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for SyslogLine<'_> {
+    fn drop(&mut self) {
+        if !self.buf.is_empty() {
+            self.syslog.send(self.severity, &self.buf);
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for Syslog {
+    type Writer = SyslogLine<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        SyslogLine { syslog: self, severity: severity(&Level::INFO), buf: Vec::new() }
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        SyslogLine { syslog: self, severity: severity(meta.level()), buf: Vec::new() }
+    }
+}
@@ -4,7 +4,11 @@ use tokio::io::{self, AsyncBufReadExt, AsyncWriteExt, BufReader};
 use protocol::*;
 use serde_json::{json, Value};
 use crate::command::agent;
-use crate::AgentSubcommand;
+use crate::{AgentSubcommand, SessionSubcommand};
+
+/// Errors per `agent_list_errors` page when the client asks for no limit, so a
+/// long history never floods the context window.
+const MCP_ERROR_PAGE: usize = 50;
 
 pub async fn run_server() -> anyhow::Result<()> {
     let stdin = io::stdin();
@@ -80,7 +84,7 @@ async fn handle_request(req: JsonRpcRequest) -> anyhow::Result<JsonRpcResponse>
                 },
                 Tool {
                     name: "agent_doctor".to_string(),
-                    description: "Assess project health and agent-readability.".to_string(),
+                    description: "Check montrs crate and CLI versions, then run clippy and record its errors and warnings, with suggested fixes.".to_string(),
                     input_schema: json!({
                         "type": "object",
                         "properties": {
@@ -116,20 +120,118 @@ async fn handle_request(req: JsonRpcRequest) -> anyhow::Result<JsonRpcResponse>
                 },
                 Tool {
                     name: "agent_list_errors".to_string(),
-                    description: "List all active and resolved errors tracked by the agent.".to_string(),
+                    description: "List errors tracked by the agent, newest first, a page at a time. The output ends with the offset of the next page.".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "status": { "type": "string", "enum": ["active", "resolved", "Pending", "Fixed"], "description": "Filter by status" },
+                            "package": { "type": "string", "description": "Package name; `orm` also matches `montrs-orm`" },
+                            "file": { "type": "string", "description": "Glob over file paths, e.g. `src/routes/**`" },
+                            "level": { "type": "string", "enum": ["error", "warning"] },
+                            "error_code": { "type": "string", "description": "e.g. E0308" },
+                            "since": { "type": "string", "description": "30m, 12h, 7d, 2w, a date or an RFC 3339 timestamp" },
+                            "until": { "type": "string", "description": "Same formats as `since`" },
+                            "sort": { "type": "string", "enum": ["newest", "oldest", "file", "package"] },
+                            "limit": { "type": "integer", "minimum": 1, "description": "Errors per page (default 50)" },
+                            "offset": { "type": "integer", "minimum": 0, "description": "Matching errors to skip" }
+                        }
+                    }),
+                },
+                Tool {
+                    name: "workspace_index".to_string(),
+                    description: "Summarize the projects of a multi-project workspace, or list the configured workspaces.".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "workspace": { "type": "string", "description": "Workspace name from ~/.montrs/workspaces.toml" }
+                        }
+                    }),
+                },
+                Tool {
+                    name: "workspace_search".to_string(),
+                    description: "Search routes, packages and plates across every project of a workspace.".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "workspace": { "type": "string", "description": "Workspace name from ~/.montrs/workspaces.toml" },
+                            "query": { "type": "string", "description": "Text to find, or a concrete path such as /users/42" }
+                        },
+                        "required": ["workspace", "query"]
+                    }),
+                },
+                Tool {
+                    name: "search_docs".to_string(),
+                    description: "Find the documentation snippets most relevant to a question, with scores. Semantic search uses the embedding model configured by MONTRS_LLM_*.".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "query": { "type": "string", "description": "Question or keywords, e.g. 'how do I add a route guard'" },
+                            "semantic": { "type": "boolean", "description": "Rank by embedding similarity instead of keywords (default true)" },
+                            "top_k": { "type": "integer", "description": "Number of snippets to return (default 5)" }
+                        },
+                        "required": ["query"]
+                    }),
+                },
+                Tool {
+                    name: "agent_sessions".to_string(),
+                    description: "Summarize earlier agent sessions (goal, files touched, commands run, outcome) to resume their work.".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "limit": { "type": "integer", "description": "Number of sessions to include (default 5)" }
+                        }
+                    }),
+                },
+                Tool {
+                    name: "agent_session_start".to_string(),
+                    description: "Open a session for the current task and return its id.".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "goal": { "type": "string", "description": "What the session sets out to do" }
+                        },
+                        "required": ["goal"]
+                    }),
+                },
+                Tool {
+                    name: "agent_session_log".to_string(),
+                    description: "Record files touched, a command run or a note in the open session.".to_string(),
                     input_schema: json!({
                         "type": "object",
                         "properties": {
-                            "status": { "type": "string", "enum": ["Pending", "Fixed"], "description": "Filter by status" }
+                            "files": { "type": "array", "items": { "type": "string" } },
+                            "command": { "type": "string" },
+                            "exit_code": { "type": "integer" },
+                            "note": { "type": "string" },
+                            "session": { "type": "string", "description": "Session id; defaults to the latest open session" }
                         }
                     }),
                 },
+                Tool {
+                    name: "agent_session_end".to_string(),
+                    description: "Close the open session with its outcome and a summary.".to_string(),
+                    input_schema: json!({
+                        "type": "object",
+                        "properties": {
+                            "summary": { "type": "string" },
+                            "outcome": { "type": "string", "enum": ["completed", "failed", "abandoned"] },
+                            "session": { "type": "string", "description": "Session id; defaults to the latest open session" }
+                        },
+                        "required": ["summary"]
+                    }),
+                },
                 Tool {
                     name: "get_agent_entry_point".to_string(),
                     description: "Get the unified entry point for agent operations, mapping tasks to guides.".to_string(),
                     input_schema: json!({ "type": "object", "properties": {} }),
                 },
             ];
+            let mut tools = tools;
+            tools.extend(plugin_tools().into_iter().map(|(name, _, tool)| Tool {
+                name,
+                description: tool.description,
+                input_schema: tool.input_schema,
+            }));
             Some(serde_json::to_value(ListToolsResult { tools })?)
         }
         "tools/call" => {
@@ -214,17 +316,113 @@ async fn handle_tool_call(params: CallToolParams) -> anyhow::Result<CallToolResu
                 is_error: false,
             })
         }
+        "workspace_index" | "workspace_search" => {
+            let name = params.arguments.get("workspace").and_then(|v| v.as_str()).map(|s| s.to_string());
+            let search = params.arguments.get("query").and_then(|v| v.as_str()).map(|s| s.to_string());
+            if params.name == "workspace_search" && (name.is_none() || search.is_none()) {
+                anyhow::bail!("Missing workspace or query argument");
+            }
+            let output = agent::run(AgentSubcommand::Workspace { name, search }).await?;
+            Ok(CallToolResult {
+                content: vec![ToolContent::Text { text: output }],
+                is_error: false,
+            })
+        }
+        "search_docs" => {
+            let query = params.arguments.get("query").and_then(|v| v.as_str()).ok_or_else(|| anyhow::anyhow!("Missing query argument"))?.to_string();
+            let semantic = params.arguments.get("semantic").and_then(|v| v.as_bool()).unwrap_or(true);
+            let top = params.arguments.get("top_k").and_then(|v| v.as_u64()).unwrap_or(5) as usize;
+            let output = agent::run(AgentSubcommand::Search { query, semantic, top }).await?;
+            Ok(CallToolResult {
+                content: vec![ToolContent::Text { text: output }],
+                is_error: false,
+            })
+        }
+        "agent_sessions" | "agent_session_start" | "agent_session_log" | "agent_session_end" => {
+            let args = &params.arguments;
+            let text = |key: &str| args.get(key).and_then(|v| v.as_str()).map(|s| s.to_string());
+            let subcommand = match params.name.as_str() {
+                "agent_sessions" => SessionSubcommand::List {
+                    limit: args.get("limit").and_then(|v| v.as_u64()).unwrap_or(5) as usize,
+                },
+                "agent_session_start" => SessionSubcommand::Start {
+                    goal: text("goal").ok_or_else(|| anyhow::anyhow!("Missing goal argument"))?,
+                },
+                "agent_session_log" => SessionSubcommand::Log {
+                    session: text("session"),
+                    files: args
+                        .get("files")
+                        .and_then(|v| v.as_array())
+                        .map(|files| files.iter().filter_map(|f| f.as_str().map(|s| s.to_string())).collect())
+                        .unwrap_or_default(),
+                    command: text("command"),
+                    exit_code: args.get("exit_code").and_then(|v| v.as_i64()).map(|c| c as i32),
+                    note: text("note"),
+                },
+                _ => SessionSubcommand::End {
+                    summary: text("summary").ok_or_else(|| anyhow::anyhow!("Missing summary argument"))?,
+                    outcome: text("outcome").unwrap_or_else(|| "completed".to_string()),
+                    session: text("session"),
+                },
+            };
+            let output = agent::run(AgentSubcommand::Session { subcommand }).await?;
+            Ok(CallToolResult {
+                content: vec![ToolContent::Text { text: output }],
+                is_error: false,
+            })
+        }
         "agent_list_errors" => {
-            let status = params.arguments.get("status").and_then(|v| v.as_str()).map(|s| s.to_string());
-            let output = agent::run(AgentSubcommand::ListErrors { status }).await?;
+            let text = |key: &str| params.arguments.get(key).and_then(|v| v.as_str()).map(|s| s.to_string());
+            let number = |key: &str| params.arguments.get(key).and_then(|v| v.as_u64()).map(|n| n as usize);
+            let output = agent::run(AgentSubcommand::ListErrors {
+                status: text("status"),
+                package: text("package"),
+                file: text("file"),
+                level: text("level"),
+                code: text("error_code"),
+                since: text("since"),
+                until: text("until"),
+                sort: text("sort").unwrap_or_else(|| "newest".to_string()),
+                limit: Some(number("limit").unwrap_or(MCP_ERROR_PAGE)),
+                offset: number("offset").unwrap_or(0),
+            })
+            .await?;
             Ok(CallToolResult {
                 content: vec![ToolContent::Text { text: output }],
                 is_error: false,
             })
         }
-        _ => Ok(CallToolResult {
-            content: vec![ToolContent::Text { text: format!("Unknown tool: {}", params.name) }],
-            is_error: true,
-        }),
+        name => {
+            let config = crate::config::MontrsConfig::load().unwrap_or_default();
+            let Some((_, plugin, tool)) = plugin_tools().into_iter().find(|(n, _, _)| n == name) else {
+                return Ok(CallToolResult {
+                    content: vec![ToolContent::Text { text: format!("Unknown tool: {}", params.name) }],
+                    is_error: true,
+                });
+            };
+            match crate::plugin::call_tool(&plugin, &tool.name, &params.arguments, &config) {
+                Ok(text) => Ok(CallToolResult {
+                    content: vec![ToolContent::Text { text }],
+                    is_error: false,
+                }),
+                Err(e) => Ok(CallToolResult {
+                    content: vec![ToolContent::Text { text: e.to_string() }],
+                    is_error: true,
+                }),
+            }
+        }
     }
 }
+
+/// Tools registered by plugins, exposed as `<plugin>_<tool>`.
+fn plugin_tools() -> Vec<(String, crate::plugin::Plugin, crate::plugin::PluginTool)> {
+    let config = crate::config::MontrsConfig::load().unwrap_or_default();
+    crate::plugin::manifests(&config)
+        .into_iter()
+        .flat_map(|(plugin, manifest)| {
+            manifest.tools.into_iter().map(move |tool| {
+                (format!("{}_{}", plugin.name, tool.name), plugin.clone(), tool)
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,306 @@
+//! CLI plugin system.
+//!
+//! Plugins are executables named `montrs-<name>` found on `PATH`, or commands
+//! declared under `[plugins.<name>]` in `montrs.toml`. Any unknown subcommand
+//! (`cargo montrs <name> ...`) is forwarded to the matching plugin, which receives
+//! a JSON `PluginContext` on stdin.
+//!
+//! Plugins may also answer `--montrs-manifest` with a JSON `PluginManifest` to
+//! register tasks and MCP tools. Post-generate hooks are only taken from
+//! plugins declared in `montrs.toml`, so installing an executable cannot add
+//! commands to every project `montrs new` creates.
+
+use crate::config::{MontrsConfig, TaskConfig};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+
+/// Prefix shared by all plugin executables.
+pub const PLUGIN_PREFIX: &str = "montrs-";
+
+/// Flag passed to a plugin to request its manifest.
+pub const MANIFEST_FLAG: &str = "--montrs-manifest";
+
+/// Flag passed to a plugin to invoke one of its MCP tools.
+pub const TOOL_FLAG: &str = "--montrs-tool";
+
+/// Where a plugin was discovered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PluginSource {
+    /// Declared under `[plugins]` in `montrs.toml`.
+    Config,
+    /// A `montrs-<name>` executable on `PATH`.
+    Path,
+}
+
+/// A resolved plugin executable.
+#[derive(Debug, Clone, Serialize)]
+pub struct Plugin {
+    pub name: String,
+    pub command: PathBuf,
+    pub source: PluginSource,
+    pub description: Option<String>,
+}
+
+/// The JSON document written to a plugin's stdin.
+#[derive(Debug, Serialize)]
+pub struct PluginContext<'a> {
+    pub cli_version: &'static str,
+    pub plugin: &'a str,
+    pub args: &'a [String],
+    pub project_root: PathBuf,
+    pub agent_dir: PathBuf,
+    pub snapshot_path: PathBuf,
+    pub config: &'a MontrsConfig,
+    /// `--dry-run` was given: report what would change instead of changing it.
+    pub dry_run: bool,
+    /// Set when the plugin is invoked as an MCP tool.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<ToolInvocation<'a>>,
+}
+
+/// The MCP tool being called, with its arguments.
+#[derive(Debug, Serialize)]
+pub struct ToolInvocation<'a> {
+    pub name: &'a str,
+    pub arguments: &'a Value,
+}
+
+/// Capabilities a plugin registers with the CLI.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PluginManifest {
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Tasks surfaced through `montrs run` / `montrs tasks`.
+    #[serde(default)]
+    pub tasks: HashMap<String, PluginTask>,
+    /// Tools exposed by `montrs mcp serve`.
+    #[serde(default)]
+    pub tools: Vec<PluginTool>,
+    /// Commands run in every project `montrs new` creates, after the
+    /// template's own hooks and under the same sandbox.
+    #[serde(default)]
+    pub post_generate: Vec<String>,
+}
+
+/// A task provided by a plugin; runs the plugin with `args`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PluginTask {
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+/// An MCP tool provided by a plugin.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PluginTool {
+    pub name: String,
+    pub description: String,
+    #[serde(default = "default_input_schema")]
+    pub input_schema: Value,
+}
+
+fn default_input_schema() -> Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+/// Lists every plugin visible from the current project, config entries first.
+pub fn discover(config: &MontrsConfig) -> Vec<Plugin> {
+    let mut plugins: Vec<Plugin> = config
+        .plugins
+        .iter()
+        .map(|(name, cfg)| Plugin {
+            name: name.clone(),
+            command: cfg
+                .command
+                .clone()
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from(format!("{}{}", PLUGIN_PREFIX, name))),
+            source: PluginSource::Config,
+            description: cfg.description.clone(),
+        })
+        .collect();
+
+    for dir in std::env::var_os("PATH")
+        .map(|p| std::env::split_paths(&p).collect::<Vec<_>>())
+        .unwrap_or_default()
+    {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let stem = file_name.strip_suffix(std::env::consts::EXE_SUFFIX).unwrap_or(&file_name);
+            let Some(name) = stem.strip_prefix(PLUGIN_PREFIX) else {
+                continue;
+            };
+            if name.is_empty() || plugins.iter().any(|p| p.name == name) || !is_executable(&entry.path()) {
+                continue;
+            }
+            plugins.push(Plugin {
+                name: name.to_string(),
+                command: entry.path(),
+                source: PluginSource::Path,
+                description: None,
+            });
+        }
+    }
+
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+    plugins
+}
+
+/// Resolves a plugin by subcommand name.
+pub fn find(name: &str, config: &MontrsConfig) -> Option<Plugin> {
+    discover(config).into_iter().find(|p| p.name == name)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+fn context<'a>(
+    plugin: &'a Plugin,
+    args: &'a [String],
+    config: &'a MontrsConfig,
+    tool: Option<ToolInvocation<'a>>,
+) -> Result<PluginContext<'a>> {
+    let project_root = std::env::current_dir()?;
+    let agent = montrs_agent::AgentManager::new(&project_root);
+    let agent_dir = agent.agent_dir();
+    Ok(PluginContext {
+        cli_version: env!("CARGO_PKG_VERSION"),
+        plugin: &plugin.name,
+        args,
+        snapshot_path: agent_dir.join("agent.json"),
+        agent_dir,
+        project_root,
+        config,
+        dry_run: crate::dryrun::enabled(),
+        tool,
+    })
+}
+
+fn spawn(plugin: &Plugin, args: &[String], ctx: &PluginContext<'_>, capture: bool) -> Result<std::process::Child> {
+    let mut cmd = Command::new(&plugin.command);
+    cmd.args(args)
+        .env("MONTRS_PLUGIN", &plugin.name)
+        .env("MONTRS_CLI_VERSION", env!("CARGO_PKG_VERSION"))
+        .stdin(Stdio::piped());
+    if capture {
+        cmd.stdout(Stdio::piped());
+    }
+
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("Failed to start plugin '{}' ({})", plugin.name, plugin.command.display()))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A plugin that never reads stdin closes the pipe early; that is not an error.
+        let _ = stdin.write_all(&serde_json::to_vec(ctx)?);
+    }
+    Ok(child)
+}
+
+/// Runs a plugin as a subcommand, forwarding `args` and streaming its output.
+pub fn invoke(plugin: &Plugin, args: &[String], config: &MontrsConfig) -> Result<ExitStatus> {
+    let ctx = context(plugin, args, config, None)?;
+    let mut child = spawn(plugin, args, &ctx, false)?;
+    Ok(child.wait()?)
+}
+
+/// Asks a plugin for its manifest.
+pub fn manifest(plugin: &Plugin, config: &MontrsConfig) -> Result<PluginManifest> {
+    let args = [MANIFEST_FLAG.to_string()];
+    let ctx = context(plugin, &args, config, None)?;
+    let output = spawn(plugin, &args, &ctx, true)?.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!("Plugin '{}' failed to produce a manifest: {}", plugin.name, output.status);
+    }
+    serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("Plugin '{}' returned an invalid manifest", plugin.name))
+}
+
+/// Collects the manifests of every discovered plugin. Plugins that fail are
+/// skipped: those in `montrs.toml` with a warning, those on `PATH`, which
+/// need not answer `--montrs-manifest`, quietly.
+pub fn manifests(config: &MontrsConfig) -> Vec<(Plugin, PluginManifest)> {
+    collect_manifests(discover(config), config)
+}
+
+/// Collects manifests from plugins declared in `montrs.toml` only.
+pub fn configured_manifests(config: &MontrsConfig) -> Vec<(Plugin, PluginManifest)> {
+    let plugins = discover(config).into_iter().filter(|p| p.source == PluginSource::Config).collect();
+    collect_manifests(plugins, config)
+}
+
+fn collect_manifests(plugins: Vec<Plugin>, config: &MontrsConfig) -> Vec<(Plugin, PluginManifest)> {
+    plugins
+        .into_iter()
+        .filter_map(|p| match manifest(&p, config) {
+            Ok(m) => Some((p, m)),
+            Err(e) if p.source == PluginSource::Config => {
+                tracing::warn!("{}", e);
+                None
+            }
+            Err(e) => {
+                tracing::debug!("{}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Adds plugin-provided tasks to `config.tasks` without overriding user-defined ones.
+pub fn merge_tasks(config: &mut MontrsConfig) {
+    for (plugin, manifest) in manifests(config) {
+        for (name, task) in manifest.tasks {
+            if config.tasks.contains_key(&name) {
+                continue;
+            }
+            let mut parts = vec![plugin.command.to_string_lossy().to_string()];
+            parts.extend(task.args);
+            let command = shlex::try_join(parts.iter().map(|s| s.as_str()))
+                .unwrap_or_else(|_| parts.join(" "));
+            config.tasks.insert(
+                name,
+                TaskConfig::Detailed {
+                    command,
+                    description: task.description,
+                    category: Some(format!("plugin:{}", plugin.name)),
+                    dependencies: Vec::new(),
+                    env: HashMap::from([("MONTRS_PLUGIN".to_string(), plugin.name.clone())]),
+                    inputs: Vec::new(),
+                    outputs: Vec::new(),
+                    each: None,
+                },
+            );
+        }
+    }
+}
+
+/// Calls a plugin's MCP tool and returns its stdout.
+pub fn call_tool(plugin: &Plugin, tool: &str, arguments: &Value, config: &MontrsConfig) -> Result<String> {
+    let args = [TOOL_FLAG.to_string(), tool.to_string()];
+    let ctx = context(plugin, &args, config, Some(ToolInvocation { name: tool, arguments }))?;
+    let output = spawn(plugin, &args, &ctx, true)?.wait_with_output()?;
+    let text = String::from_utf8_lossy(&output.stdout).to_string();
+    if !output.status.success() {
+        anyhow::bail!("Plugin tool '{}' failed ({}): {}", tool, output.status, text);
+    }
+    Ok(text)
+}
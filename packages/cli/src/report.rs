@@ -0,0 +1,341 @@
+//! Shared progress reporting for CLI commands.
+//!
+//! Commands record their work as steps on the process-wide [`Reporter`]
+//! instead of printing ad-hoc lines. Each step shows a spinner while it runs
+//! (or a header line when a subprocess streams its own output) and its timing
+//! once it ends. Warnings are collected as they come, and `run` prints a summary
+//! table when the command exits. `--quiet` keeps only warnings and errors, and
+//! `--output json` replaces the summary with one JSON object on stdout.
+//!
+//! With `--dry-run`, commands that change files, the database or other
+//! systems record each change as a [`Planned`] action instead of making it
+//! (see [`crate::dryrun`]); the summary lists them.
+
+use console::style;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde::Serialize;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+static REPORTER: OnceLock<Reporter> = OnceLock::new();
+
+/// How the final report is written.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+/// How a step ended.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Done,
+    Failed,
+    Skipped,
+}
+
+/// What kind of change a planned action makes.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionKind {
+    CreateDir,
+    WriteFile,
+    ExecuteSql,
+    StoreKey,
+}
+
+impl ActionKind {
+    fn verb(self) -> &'static str {
+        match self {
+            ActionKind::CreateDir => "create dir",
+            ActionKind::WriteFile => "write",
+            ActionKind::ExecuteSql => "execute",
+            ActionKind::StoreKey => "store key",
+        }
+    }
+}
+
+/// A change `--dry-run` held back.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Planned {
+    pub action: ActionKind,
+    /// The file, table or store the action touches.
+    pub target: String,
+    /// The SQL statement, or what changes in the target.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl Planned {
+    pub fn new(action: ActionKind, target: impl Into<String>) -> Self {
+        Self { action, target: target.into(), detail: None }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+struct StepRecord {
+    name: String,
+    status: StepStatus,
+    duration_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Summary<'a> {
+    command: &'a str,
+    success: bool,
+    duration_ms: u128,
+    steps: &'a [StepRecord],
+    warnings: &'a [String],
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    dry_run: bool,
+    #[serde(skip_serializing_if = "<[Planned]>::is_empty")]
+    planned: &'a [Planned],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Collects steps and warnings for the running command.
+pub struct Reporter {
+    command: String,
+    format: OutputFormat,
+    quiet: bool,
+    dry_run: bool,
+    progress: MultiProgress,
+    started: Instant,
+    steps: Mutex<Vec<StepRecord>>,
+    warnings: Mutex<Vec<String>>,
+    planned: Mutex<Vec<Planned>>,
+}
+
+/// Sets up the reporter for `command`. Only the first call has an effect.
+pub fn init(command: &str, format: OutputFormat, quiet: bool, dry_run: bool) -> &'static Reporter {
+    REPORTER.get_or_init(|| Reporter::new(command, format, quiet, dry_run))
+}
+
+/// The process-wide reporter; a plain human one if `init` was never called.
+pub fn reporter() -> &'static Reporter {
+    REPORTER.get_or_init(|| Reporter::new("montrs", OutputFormat::Human, false, false))
+}
+
+impl Reporter {
+    fn new(command: &str, format: OutputFormat, quiet: bool, dry_run: bool) -> Self {
+        let progress = if quiet || format == OutputFormat::Json {
+            MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+        } else {
+            MultiProgress::new()
+        };
+        Self {
+            command: command.to_string(),
+            format,
+            quiet,
+            dry_run,
+            progress,
+            started: Instant::now(),
+            steps: Mutex::new(Vec::new()),
+            warnings: Mutex::new(Vec::new()),
+            planned: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Whether `--dry-run` was given.
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Records a change the dry run did not make and prints it. Kept with
+    /// `--quiet`, since it is the output the dry run was asked for.
+    pub fn plan(&self, planned: Planned) {
+        let detail = planned.detail.as_deref().map(|d| format!("  {}", style(d).dim())).unwrap_or_default();
+        self.output(format!("{} would {} {}{}", style("○").cyan(), planned.action.verb(), planned.target, detail));
+        self.planned.lock().unwrap().push(planned);
+    }
+
+    /// Starts a step with a spinner. Use for work that prints nothing itself.
+    pub fn step(&'static self, name: impl Into<String>) -> Step {
+        let name = name.into();
+        let bar = self.progress.add(ProgressBar::new_spinner());
+        bar.set_style(
+            ProgressStyle::with_template("{spinner:.cyan} {msg} {elapsed:.dim}")
+                .expect("valid progress template"),
+        );
+        bar.set_message(name.clone());
+        bar.enable_steady_tick(Duration::from_millis(100));
+        Step { reporter: self, name, started: Instant::now(), bar: Some(bar), detail: None, done: false }
+    }
+
+    /// Starts a step whose subprocess writes to the terminal, so it gets a
+    /// header line instead of a spinner that the output would tear.
+    pub fn stream_step(&'static self, name: impl Into<String>) -> Step {
+        let name = name.into();
+        self.info(format!("{} {}", style("▶").cyan().bold(), style(&name).bold()));
+        Step { reporter: self, name, started: Instant::now(), bar: None, detail: None, done: false }
+    }
+
+    /// Prints a line above any running spinners. Hidden with `--quiet`; sent
+    /// to stderr with `--output json` so stdout stays machine-readable.
+    pub fn info(&self, message: impl AsRef<str>) {
+        if self.quiet {
+            return;
+        }
+        match self.format {
+            OutputFormat::Json => eprintln!("{}", message.as_ref()),
+            OutputFormat::Human if self.progress.is_hidden() => println!("{}", message.as_ref()),
+            OutputFormat::Human => {
+                let _ = self.progress.println(message.as_ref());
+            }
+        }
+    }
+
+    /// Prints a line of a child process's output. Unlike `info` it is kept
+    /// with `--quiet`, the same as a streaming step's output; it goes to
+    /// stderr with `--output json`.
+    pub fn output(&self, line: impl AsRef<str>) {
+        match self.format {
+            OutputFormat::Json => eprintln!("{}", line.as_ref()),
+            OutputFormat::Human if self.progress.is_hidden() => println!("{}", line.as_ref()),
+            OutputFormat::Human => {
+                let _ = self.progress.println(line.as_ref());
+            }
+        }
+    }
+
+    /// Records a warning for the summary and prints it right away.
+    pub fn warn(&self, message: impl Into<String>) {
+        let message = message.into();
+        if self.format == OutputFormat::Human {
+            let line = format!("{} {}", style("⚠").yellow(), message);
+            self.progress.suspend(|| eprintln!("{}", line));
+        }
+        self.warnings.lock().unwrap().push(message);
+    }
+
+    fn record(&self, record: StepRecord) {
+        self.steps.lock().unwrap().push(record);
+    }
+
+    /// Prints the summary table, or the JSON report. Commands that recorded
+    /// no steps or warnings print nothing in human mode.
+    pub fn finish(&self, result: &anyhow::Result<()>) {
+        let steps = self.steps.lock().unwrap();
+        let warnings = self.warnings.lock().unwrap();
+        let planned = self.planned.lock().unwrap();
+        let elapsed = self.started.elapsed();
+
+        if self.format == OutputFormat::Json {
+            let summary = Summary {
+                command: &self.command,
+                success: result.is_ok(),
+                duration_ms: elapsed.as_millis(),
+                steps: &steps,
+                warnings: &warnings,
+                dry_run: self.dry_run,
+                planned: &planned,
+                error: result.as_ref().err().map(|e| format!("{:#}", e)),
+            };
+            println!("{}", serde_json::to_string(&summary).unwrap_or_default());
+            return;
+        }
+        if self.quiet || (steps.is_empty() && warnings.is_empty() && !self.dry_run) {
+            return;
+        }
+
+        let width = steps.iter().map(|s| s.name.len()).max().unwrap_or(0);
+        println!();
+        println!("{} {}", style(&self.command).bold(), style(format!("({})", human_duration(elapsed))).dim());
+        for step in steps.iter() {
+            let icon = match step.status {
+                StepStatus::Done => style("✔").green(),
+                StepStatus::Failed => style("✘").red(),
+                StepStatus::Skipped => style("-").dim(),
+            };
+            let time = human_duration(Duration::from_millis(step.duration_ms as u64));
+            let detail = step.detail.as_deref().map(|d| format!("  {}", style(d).dim())).unwrap_or_default();
+            println!("  {} {:<width$}  {:>8}{}", icon, step.name, time, detail, width = width);
+        }
+        if !warnings.is_empty() {
+            println!("  {} {} warning(s)", style("⚠").yellow(), warnings.len());
+            for warning in warnings.iter() {
+                println!("    - {}", warning);
+            }
+        }
+        if self.dry_run {
+            println!("  {} dry run: {} change(s) planned, none made", style("○").cyan(), planned.len());
+        }
+    }
+}
+
+/// A running step. Dropping it without finishing records it as failed, so
+/// `?` inside a step still shows up in the summary.
+pub struct Step {
+    reporter: &'static Reporter,
+    name: String,
+    started: Instant,
+    bar: Option<ProgressBar>,
+    detail: Option<String>,
+    done: bool,
+}
+
+impl Step {
+    /// Updates the text next to the spinner, e.g. a running count.
+    pub fn set_detail(&mut self, detail: impl Into<String>) {
+        let detail = detail.into();
+        if let Some(bar) = &self.bar {
+            bar.set_message(format!("{} {}", self.name, style(&detail).dim()));
+        }
+        self.detail = Some(detail);
+    }
+
+    pub fn finish(mut self) {
+        self.close(StepStatus::Done);
+    }
+
+    pub fn skip(mut self, reason: impl Into<String>) {
+        self.detail = Some(reason.into());
+        self.close(StepStatus::Skipped);
+    }
+
+    pub fn fail(mut self) {
+        self.close(StepStatus::Failed);
+    }
+
+    fn close(&mut self, status: StepStatus) {
+        self.done = true;
+        if let Some(bar) = self.bar.take() {
+            bar.finish_and_clear();
+            self.reporter.progress.remove(&bar);
+        }
+        self.reporter.record(StepRecord {
+            name: std::mem::take(&mut self.name),
+            status,
+            duration_ms: self.started.elapsed().as_millis(),
+            detail: self.detail.take(),
+        });
+    }
+}
+
+impl Drop for Step {
+    fn drop(&mut self) {
+        if !self.done {
+            self.close(StepStatus::Failed);
+        }
+    }
+}
+
+fn human_duration(d: Duration) -> String {
+    if d.as_secs() >= 60 {
+        format!("{}m{:02}s", d.as_secs() / 60, d.as_secs() % 60)
+    } else if d.as_millis() >= 1000 {
+        format!("{:.1}s", d.as_secs_f64())
+    } else {
+        format!("{}ms", d.as_millis())
+    }
+}
@@ -0,0 +1,531 @@
+//! Restricted execution of post-generate hooks.
+//!
+//! `montrs new` runs the hooks a template declares in `montrs-template.toml`,
+//! and those plugins list in their manifest, inside a [`Sandbox`]:
+//!
+//! - Only programs given with `--allow` start; nothing is allowed by default,
+//!   since even `cargo fmt` runs whatever toolchain the template's
+//!   `rust-toolchain.toml` names. Commands are split with shell rules but
+//!   never run by a shell, and flags that point a program at other config or
+//!   executables (`-c`, `--config`, `--exec-path`, `-C`) are refused.
+//! - The working directory and every path argument must stay inside the new
+//!   project. That catches mistakes early; the enforcement is Landlock, which
+//!   lets the hook and everything it starts write only inside the project and
+//!   a private temp directory. Files elsewhere stay readable, since toolchains
+//!   live there. Where Landlock is unavailable hooks are refused.
+//! - The environment is reduced to a few variables, and the network is cut off
+//!   unless `--allow-network` is given: the command runs in its own network
+//!   namespace, and where `unshare` cannot create one hooks are refused
+//!   rather than run with only offline settings.
+//!
+//! Every hook, refused or run, is appended to `.agent/hooks.jsonl` in the
+//! project along with its output.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Flags refused in any position, with or without `=value`: they load
+/// config or run executables from outside the checked command.
+const REFUSED_FLAGS: [&str; 4] = ["-c", "--config", "--exec-path", "-C"];
+/// The transcript, relative to the project's `.agent` directory.
+pub const TRANSCRIPT_FILE: &str = "hooks.jsonl";
+
+/// Variables passed through to hooks; everything else is dropped.
+const KEPT_VARS: [&str; 9] = ["PATH", "HOME", "USER", "LANG", "TERM", "TMPDIR", "CARGO_HOME", "RUSTUP_HOME", "SYSTEMROOT"];
+/// Set when the network is not allowed, in case a tool reaches out anyway.
+/// The proxy is the discard port.
+const OFFLINE_VARS: [(&str, &str); 8] = [
+    ("CARGO_NET_OFFLINE", "true"),
+    ("npm_config_offline", "true"),
+    ("PIP_NO_INDEX", "1"),
+    ("http_proxy", "http://127.0.0.1:9"),
+    ("https_proxy", "http://127.0.0.1:9"),
+    ("HTTP_PROXY", "http://127.0.0.1:9"),
+    ("HTTPS_PROXY", "http://127.0.0.1:9"),
+    ("ALL_PROXY", "http://127.0.0.1:9"),
+];
+/// Output kept in the transcript per stream, from the end.
+const OUTPUT_LIMIT: usize = 4096;
+
+/// What hooks may do beyond the defaults.
+#[derive(Debug, Clone, Default)]
+pub struct Policy {
+    /// Extra programs, by name (`npm`) or by path inside the project (`./setup.sh`).
+    pub allow: Vec<String>,
+    /// Leave the network reachable.
+    pub network: bool,
+}
+
+/// A command to run in the project.
+#[derive(Debug, Clone)]
+pub struct Hook {
+    /// Who asked for it: `template` or `plugin:<name>`.
+    pub source: String,
+    pub run: String,
+    /// Working directory, relative to the project root.
+    pub dir: Option<String>,
+}
+
+/// Written by hooks that ask for the temp directory.
+const TEMP_VARS: [&str; 3] = ["TMPDIR", "TMP", "TEMP"];
+
+/// How a hook was cut off from the network.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Network {
+    /// `--allow-network` was given.
+    Allowed,
+    /// In a network namespace with only a loopback interface.
+    Isolated,
+}
+
+/// What happened to a hook.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Ran,
+    Failed,
+    Refused,
+}
+
+/// One entry of the transcript.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TranscriptEntry {
+    /// Seconds since the Unix epoch.
+    pub at: u64,
+    pub source: String,
+    pub command: String,
+    pub dir: String,
+    pub outcome: Outcome,
+    /// Why the hook was refused or how it failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<Network>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    pub ms: u64,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub stdout: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub stderr: String,
+}
+
+/// A hook that passed the checks, ready to start.
+#[derive(Debug)]
+struct Checked {
+    program: String,
+    args: Vec<String>,
+    dir: PathBuf,
+}
+
+/// Runs hooks confined to one project directory.
+pub struct Sandbox {
+    root: PathBuf,
+    policy: Policy,
+    /// `None` when agent artifacts are kept in memory.
+    transcript: Option<PathBuf>,
+}
+
+impl Sandbox {
+    pub fn new(root: &Path, policy: Policy) -> Result<Self> {
+        let root = root.canonicalize().with_context(|| format!("Project directory {} not found", root.display()))?;
+        let agent = montrs_agent::AgentManager::new(&root);
+        let transcript = agent.is_persistent().then(|| agent.agent_dir().join(TRANSCRIPT_FILE));
+        Ok(Self { root, policy, transcript })
+    }
+
+    /// Runs `hook` and records it. Fails when the hook is refused, cannot
+    /// start or exits unsuccessfully.
+    pub fn run(&self, hook: &Hook) -> Result<()> {
+        let started = Instant::now();
+        let mut entry = TranscriptEntry {
+            at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+            source: hook.source.clone(),
+            command: hook.run.clone(),
+            dir: hook.dir.clone().unwrap_or_else(|| ".".to_string()),
+            outcome: Outcome::Refused,
+            reason: None,
+            network: None,
+            exit_code: None,
+            ms: 0,
+            stdout: String::new(),
+            stderr: String::new(),
+        };
+
+        let checked = match self.check(hook).and_then(|checked| self.ready().map(|()| checked)) {
+            Ok(checked) => checked,
+            Err(reason) => {
+                entry.reason = Some(reason.clone());
+                self.record(&entry)?;
+                anyhow::bail!("Refused {} hook `{}`: {}", hook.source, hook.run, reason);
+            }
+        };
+
+        let (mut cmd, network) = self.command(&checked);
+        entry.network = Some(network);
+        let output = tempfile::Builder::new().prefix("montrs-hook-").tempdir().and_then(|tmp| {
+            cmd.stdin(Stdio::null()).envs(TEMP_VARS.map(|var| (var, tmp.path())));
+            output_confined(cmd, &[&self.root, tmp.path(), Path::new("/dev/null")])
+        });
+        entry.ms = started.elapsed().as_millis() as u64;
+        let result = match output {
+            Ok(output) => {
+                entry.exit_code = output.status.code();
+                entry.stdout = tail(&output.stdout);
+                entry.stderr = tail(&output.stderr);
+                if output.status.success() {
+                    entry.outcome = Outcome::Ran;
+                    Ok(())
+                } else {
+                    entry.outcome = Outcome::Failed;
+                    entry.reason = Some(format!("exited with {}", output.status));
+                    Err(anyhow::anyhow!("{} hook `{}` exited with {}:\n{}", hook.source, hook.run, output.status, entry.stderr))
+                }
+            }
+            Err(e) => {
+                entry.outcome = Outcome::Failed;
+                entry.reason = Some(e.to_string());
+                Err(anyhow::anyhow!("Failed to start {} hook `{}`: {}", hook.source, hook.run, e))
+            }
+        };
+        self.record(&entry)?;
+        result
+    }
+
+    /// Splits the command and checks it against the policy, returning why it
+    /// is refused. Whether the sandbox can run at all is [`Sandbox::ready`].
+    fn check(&self, hook: &Hook) -> Result<Checked, String> {
+        let words = shlex::split(&hook.run).ok_or("the command has unbalanced quotes")?;
+        let Some((program, args)) = words.split_first() else {
+            return Err("the command is empty".to_string());
+        };
+
+        let dir = self.confine(&self.root, hook.dir.as_deref().unwrap_or("."))?;
+        if !self.allowed(program, &dir) {
+            return Err(format!("`{}` is not allowed; rerun with `--allow {}` to permit it", program, program));
+        }
+        if let Some(flag) = args.iter().find(|arg| is_refused_flag(arg)) {
+            return Err(format!("`{}` is not allowed in hooks", flag));
+        }
+        for value in args.iter().flat_map(|arg| path_values(arg)) {
+            // Existing entries are checked too, since the template may have shipped a symlink.
+            if looks_like_path(value) || dir.join(value).symlink_metadata().is_ok() {
+                self.confine(&dir, value)?;
+            }
+        }
+        Ok(Checked { program: program.clone(), args: args.to_vec(), dir })
+    }
+
+    /// Fails when hooks cannot be confined on this machine.
+    fn ready(&self) -> Result<(), String> {
+        if !can_restrict_writes() {
+            return Err("files outside the project cannot be protected here (Landlock is unavailable)".to_string());
+        }
+        if !self.policy.network && !can_unshare() {
+            return Err(
+                "the network cannot be cut off here (`unshare --net` is unavailable); rerun with `--allow-network` \
+                 to run hooks with network access"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Bare names are allowed by name; anything with a path must be listed as
+    /// written and lie inside the project.
+    fn allowed(&self, program: &str, dir: &Path) -> bool {
+        let listed = self.policy.allow.iter().any(|a| a == program);
+        if !program.contains(['/', '\\']) {
+            return listed;
+        }
+        listed && self.confine(dir, program).is_ok()
+    }
+
+    /// Resolves `path` against `base` and fails unless it stays inside the
+    /// project. Symlinks are followed as far as the path exists.
+    fn confine(&self, base: &Path, path: &str) -> Result<PathBuf, String> {
+        let outside = || format!("`{}` is outside the project", path);
+        let expanded = match path.strip_prefix('~') {
+            // `~user` is someone else's home, and with no `HOME` there is nothing to resolve against.
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+                std::env::var_os("HOME").map(PathBuf::from).ok_or_else(outside)?.join(rest.trim_start_matches('/'))
+            }
+            Some(_) => return Err(outside()),
+            None => base.join(path),
+        };
+        let resolved = resolve(&expanded);
+        if resolved.starts_with(&self.root) {
+            Ok(resolved)
+        } else {
+            Err(outside())
+        }
+    }
+
+    fn command(&self, checked: &Checked) -> (Command, Network) {
+        let network = if self.policy.network { Network::Allowed } else { Network::Isolated };
+        let mut cmd = if network == Network::Isolated {
+            let mut cmd = Command::new("unshare");
+            cmd.args(["--user", "--map-root-user", "--net", "--"]).arg(&checked.program);
+            cmd
+        } else {
+            Command::new(&checked.program)
+        };
+        cmd.args(&checked.args).current_dir(&checked.dir).env_clear();
+        for var in KEPT_VARS {
+            if let Some(value) = std::env::var_os(var) {
+                cmd.env(var, value);
+            }
+        }
+        if network != Network::Allowed {
+            cmd.envs(OFFLINE_VARS);
+        }
+        cmd.env("MONTRS_HOOK", "1");
+        (cmd, network)
+    }
+
+    fn record(&self, entry: &TranscriptEntry) -> Result<()> {
+        let Some(transcript) = &self.transcript else {
+            return Ok(());
+        };
+        if let Some(dir) = transcript.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(transcript)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+}
+
+fn is_refused_flag(arg: &str) -> bool {
+    let flag = arg.split_once('=').map_or(arg, |(flag, _)| flag);
+    // A short option can have its value glued on, as in `-C/elsewhere`.
+    let short = if arg.starts_with("--") { flag } else { arg.get(..2).unwrap_or(arg) };
+    REFUSED_FLAGS.contains(&flag) || REFUSED_FLAGS.contains(&short)
+}
+
+/// The parts of an argument that may name a file: the argument itself, the
+/// value after `=` in `--out=/tmp/x`, and the value glued to a short option
+/// in `-o/tmp/x` or `-I../..`.
+fn path_values(arg: &str) -> Vec<&str> {
+    let mut values = vec![arg];
+    if let Some((_, value)) = arg.split_once('=') {
+        values.push(value);
+    }
+    if let Some(option) = arg.strip_prefix('-').filter(|option| !option.starts_with('-')) {
+        let mut chars = option.chars();
+        if chars.next().is_some() && !chars.as_str().is_empty() {
+            values.push(chars.as_str());
+        }
+    }
+    values
+}
+
+/// Whether an argument names a file rather than being a flag or a word.
+fn looks_like_path(arg: &str) -> bool {
+    let path = Path::new(arg);
+    path.is_absolute() || arg.starts_with('~') || path.components().any(|c| c == Component::ParentDir)
+}
+
+/// The canonical form of the longest existing prefix of `path`, with the
+/// rest appended after removing `.` and `..` lexically.
+fn resolve(path: &Path) -> PathBuf {
+    let mut existing = path.to_path_buf();
+    let mut rest = Vec::new();
+    let base = loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            break canonical;
+        }
+        match (existing.file_name().map(|n| n.to_os_string()), existing.parent()) {
+            (Some(name), Some(parent)) => {
+                rest.push(name);
+                existing = parent.to_path_buf();
+            }
+            // `..` or a root that does not exist: resolve what is left lexically.
+            _ => break PathBuf::new(),
+        }
+    };
+    let mut resolved = base;
+    let unresolved = if resolved.as_os_str().is_empty() { existing } else { PathBuf::new() };
+    for component in unresolved.components().chain(rest.iter().rev().map(|n| Component::Normal(n.as_os_str()))) {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::CurDir => {}
+            other => resolved.push(other),
+        }
+    }
+    resolved
+}
+
+/// Runs `cmd` from a thread that is confined first, so the hook and
+/// everything it starts can only write beneath `writable`. Landlock applies
+/// to the calling thread and its children, so the rest of the CLI is not
+/// affected.
+fn output_confined(mut cmd: Command, writable: &[&Path]) -> std::io::Result<Output> {
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                restrict_writes(writable)?;
+                cmd.output()
+            })
+            .join()
+            .unwrap_or_else(|_| Err(std::io::Error::other("the hook thread panicked")))
+    })
+}
+
+/// Lets the calling thread, and the processes it starts from now on, write
+/// only beneath `writable`. Everything stays readable and executable.
+#[cfg(target_os = "linux")]
+fn restrict_writes(writable: &[&Path]) -> std::io::Result<()> {
+    use landlock::{ABI, Access, AccessFs, Ruleset, RulesetAttr, RulesetCreatedAttr, RulesetStatus, path_beneath_rules};
+
+    let abi = ABI::V5;
+    let status = Ruleset::default()
+        .handle_access(AccessFs::from_all(abi))
+        .and_then(|ruleset| ruleset.create())
+        .and_then(|ruleset| ruleset.add_rules(path_beneath_rules(["/"], AccessFs::from_read(abi))))
+        .and_then(|ruleset| ruleset.add_rules(path_beneath_rules(writable, AccessFs::from_all(abi))))
+        // `unshare` writes the namespace's uid and gid maps under /proc.
+        .and_then(|ruleset| ruleset.add_rules(path_beneath_rules(["/proc"], AccessFs::WriteFile)))
+        .and_then(|ruleset| ruleset.restrict_self())
+        .map_err(std::io::Error::other)?;
+    if status.ruleset == RulesetStatus::NotEnforced {
+        return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "Landlock is not enabled in this kernel"));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn restrict_writes(_writable: &[&Path]) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "Landlock is only available on Linux"))
+}
+
+/// Whether [`restrict_writes`] works here, tried once on a throwaway thread.
+fn can_restrict_writes() -> bool {
+    static WORKS: OnceLock<bool> = OnceLock::new();
+    *WORKS.get_or_init(|| std::thread::spawn(|| restrict_writes(&[]).is_ok()).join().unwrap_or(false))
+}
+
+/// Whether `unshare` can give a command its own user and network namespaces.
+fn can_unshare() -> bool {
+    static WORKS: OnceLock<bool> = OnceLock::new();
+    *WORKS.get_or_init(|| {
+        cfg!(target_os = "linux")
+            && Command::new("unshare")
+                .args(["--user", "--map-root-user", "--net", "--", "true"])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|s| s.success())
+    })
+}
+
+fn tail(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    let start = text.len().saturating_sub(OUTPUT_LIMIT);
+    let start = (start..text.len()).find(|&i| text.is_char_boundary(i)).unwrap_or(text.len());
+    text[start..].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sandbox(allow: &[&str]) -> (tempfile::TempDir, Sandbox) {
+        let project = tempfile::tempdir().unwrap();
+        let root = project.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("src")).unwrap();
+        let policy = Policy { allow: allow.iter().map(|a| a.to_string()).collect(), network: false };
+        (project, Sandbox { root, policy, transcript: None })
+    }
+
+    fn check(sandbox: &Sandbox, run: &str) -> Result<Checked, String> {
+        sandbox.check(&Hook { source: "template".to_string(), run: run.to_string(), dir: None })
+    }
+
+    #[test]
+    fn test_resolve_follows_symlinks_and_dot_dot() {
+        let (_project, sandbox) = sandbox(&[]);
+        let root = &sandbox.root;
+        assert_eq!(resolve(&root.join("src/../Cargo.toml")), root.join("Cargo.toml"));
+        assert_eq!(resolve(&root.join("new/./dir/../file")), root.join("new/file"));
+        assert_eq!(resolve(&root.join("src/../..")), root.parent().unwrap());
+
+        let outside = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), root.join("link")).unwrap();
+        assert_eq!(resolve(&root.join("link/missing")), outside.path().canonicalize().unwrap().join("missing"));
+    }
+
+    #[test]
+    fn test_confine_keeps_paths_inside_the_project() {
+        let (_project, sandbox) = sandbox(&[]);
+        let root = sandbox.root.clone();
+        assert_eq!(sandbox.confine(&root.join("src"), "../Cargo.toml"), Ok(root.join("Cargo.toml")));
+        assert!(sandbox.confine(&root, "src/../..").is_err());
+        assert!(sandbox.confine(&root, "/etc/passwd").is_err());
+        assert!(sandbox.confine(&root, "~").is_err());
+        assert!(sandbox.confine(&root, "~/.ssh").is_err());
+        assert!(sandbox.confine(&root, "~root/.ssh").is_err());
+
+        std::os::unix::fs::symlink("/etc", root.join("etc")).unwrap();
+        assert!(sandbox.confine(&root, "etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_check_refuses_paths_outside_the_project() {
+        let (_project, sandbox) = sandbox(&["cc", "git"]);
+        for run in [
+            "cc -o /tmp/out main.c",
+            "cc -o/etc/passwd main.c",
+            "cc -I../.. main.c",
+            "cc --output=/tmp/out main.c",
+            "cc ~/.ssh/id_rsa",
+        ] {
+            assert!(check(&sandbox, run).unwrap_err().contains("outside the project"), "{run}");
+        }
+        for run in ["git -C /tmp status", "git -C/tmp status", "git -c core.pager=sh log", "git --config=x", "git --exec-path=/tmp"] {
+            assert!(check(&sandbox, run).unwrap_err().contains("not allowed in hooks"), "{run}");
+        }
+
+        let checked = check(&sandbox, "cc -Isrc -o out main.c").unwrap();
+        assert_eq!(checked.args, ["-Isrc", "-o", "out", "main.c"]);
+        assert_eq!(checked.dir, sandbox.root);
+    }
+
+    #[test]
+    fn test_check_allows_only_listed_programs() {
+        let (_project, sandbox) = sandbox(&["cargo", "./setup.sh", "/usr/bin/env"]);
+        assert!(check(&sandbox, "cargo fmt").is_ok());
+        assert!(check(&sandbox, "./setup.sh").is_ok());
+        assert!(check(&sandbox, "npm install").unwrap_err().contains("--allow npm"));
+        assert!(check(&sandbox, "/usr/bin/env").unwrap_err().contains("not allowed"));
+        assert!(check(&sandbox, "cargo 'fmt").unwrap_err().contains("quotes"));
+    }
+
+    #[test]
+    fn test_confined_hooks_only_write_inside_the_project() {
+        // Where Landlock is unavailable there is nothing to test: `ready` refuses hooks.
+        if !can_restrict_writes() {
+            return;
+        }
+        let (_project, sandbox) = sandbox(&[]);
+        let outside = tempfile::tempdir().unwrap();
+        let script = format!("echo in > {}/in; echo out > {}/out", sandbox.root.display(), outside.path().display());
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", &script]);
+        output_confined(cmd, &[&sandbox.root]).unwrap();
+        assert!(sandbox.root.join("in").exists());
+        assert!(!outside.path().join("out").exists());
+
+        // The rest of the process is not confined.
+        std::fs::write(outside.path().join("after"), "").unwrap();
+    }
+}
@@ -0,0 +1,194 @@
+//! Content-hash cache for `montrs run` tasks.
+//!
+//! A task that declares `inputs` is keyed by the sha256 of its command,
+//! environment, directory, the contents of every input file and the keys of
+//! the tasks it depends on. After a successful run its `outputs` are copied
+//! into `target/.montrs-cache/blobs` and listed under the key, so a later run
+//! with the same key restores them instead of running the command. Going back
+//! to an earlier state of the inputs hits the cache too.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Where results are kept, relative to the project root.
+pub const CACHE_DIR: &str = "target/.montrs-cache";
+
+/// What one cached run produced.
+#[derive(Serialize, Deserialize, Debug)]
+struct Entry {
+    task: String,
+    outputs: Vec<CachedFile>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CachedFile {
+    /// Relative to the project root, with `/` separators.
+    path: String,
+    /// sha256 of the contents, which is also the blob's name.
+    hash: String,
+}
+
+pub struct TaskCache {
+    root: PathBuf,
+    dir: PathBuf,
+}
+
+impl TaskCache {
+    /// The cache of the project at `root`.
+    pub fn new(root: &Path) -> Self {
+        Self { root: root.to_path_buf(), dir: root.join(CACHE_DIR) }
+    }
+
+    /// The key of a run: `task` names the run (including its directory for
+    /// `each` tasks) and `upstream` holds the keys of its dependencies.
+    pub fn key(&self, task: &str, command: &str, env: &HashMap<String, String>, inputs: &[String], upstream: &[String]) -> Result<String> {
+        let mut hasher = Sha256::new();
+        let mut field = |value: &str| {
+            hasher.update(value.as_bytes());
+            hasher.update([0]);
+        };
+        field(task);
+        field(command);
+        let mut env: Vec<_> = env.iter().collect();
+        env.sort();
+        for (name, value) in env {
+            field(name);
+            field(value);
+        }
+        for key in upstream {
+            field(key);
+        }
+        for path in matching_files(&self.root, inputs, true)? {
+            let contents = std::fs::read(self.root.join(&path)).with_context(|| format!("Failed to read input {}", path))?;
+            field(&path);
+            field(&hex::encode(Sha256::digest(&contents)));
+        }
+        Ok(hex::encode(hasher.finalize()))
+    }
+
+    /// Puts back the outputs recorded under `key`. Returns `false`, leaving
+    /// the tree alone, when there is no entry or one of its blobs is gone.
+    pub fn restore(&self, key: &str) -> Result<bool> {
+        let Some(entry) = std::fs::read_to_string(self.entry_path(key)).ok().and_then(|text| serde_json::from_str::<Entry>(&text).ok()) else {
+            return Ok(false);
+        };
+        if !entry.outputs.iter().all(|file| self.blob_path(&file.hash).exists()) {
+            return Ok(false);
+        }
+        for file in &entry.outputs {
+            let target = self.root.join(&file.path);
+            let current = std::fs::read(&target).ok().map(|contents| hex::encode(Sha256::digest(&contents)));
+            if current.as_deref() == Some(file.hash.as_str()) {
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::copy(self.blob_path(&file.hash), &target).with_context(|| format!("Failed to restore {}", file.path))?;
+        }
+        Ok(true)
+    }
+
+    /// Records the files matching `outputs` under `key` and returns how many
+    /// there were.
+    pub fn store(&self, key: &str, task: &str, outputs: &[String]) -> Result<usize> {
+        std::fs::create_dir_all(self.dir.join("blobs"))?;
+        std::fs::create_dir_all(self.dir.join("tasks"))?;
+        let mut entry = Entry { task: task.to_string(), outputs: Vec::new() };
+        for path in matching_files(&self.root, outputs, false)? {
+            let contents = std::fs::read(self.root.join(&path))?;
+            let hash = hex::encode(Sha256::digest(&contents));
+            let blob = self.blob_path(&hash);
+            if !blob.exists() {
+                std::fs::write(&blob, &contents)?;
+            }
+            entry.outputs.push(CachedFile { path, hash });
+        }
+        std::fs::write(self.entry_path(key), serde_json::to_string_pretty(&entry)?)?;
+        Ok(entry.outputs.len())
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join("tasks").join(format!("{}.json", key))
+    }
+
+    fn blob_path(&self, hash: &str) -> PathBuf {
+        self.dir.join("blobs").join(hash)
+    }
+}
+
+/// The files under `root` matching `globs` (gitignore syntax, `!` to
+/// exclude), as sorted relative paths. Inputs skip what `.gitignore` does;
+/// outputs, which are usually ignored build products, do not. The cache
+/// itself and `.git` are never matched.
+pub fn matching_files(root: &Path, globs: &[String], respect_ignore: bool) -> Result<Vec<String>> {
+    if globs.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut overrides = ignore::overrides::OverrideBuilder::new(root);
+    for glob in globs {
+        overrides.add(glob).with_context(|| format!("Invalid glob '{}'", glob))?;
+    }
+    let overrides = overrides.build()?;
+    let cache = root.join(CACHE_DIR);
+    let mut files: Vec<String> = ignore::WalkBuilder::new(root)
+        .standard_filters(respect_ignore)
+        .hidden(false)
+        .require_git(false)
+        .filter_entry(move |entry| entry.file_name() != ".git" && entry.path() != cache)
+        .build()
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        // Matched here rather than through `WalkBuilder::overrides`, which
+        // would let the globs pull in ignored files.
+        .filter(|entry| overrides.matched(entry.path(), false).is_whitelist())
+        .filter_map(|entry| Some(entry.path().strip_prefix(root).ok()?.to_string_lossy().replace('\\', "/")))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// The directories matching `pattern`, relative to `root` and sorted. Each
+/// component may use `*` and `?`.
+pub fn matching_dirs(root: &Path, pattern: &str) -> Vec<String> {
+    let mut dirs = vec![String::new()];
+    for component in pattern.split('/').filter(|c| !c.is_empty() && *c != ".") {
+        let mut next = Vec::new();
+        for dir in &dirs {
+            let base = root.join(dir);
+            if !component.contains(['*', '?']) {
+                if base.join(component).is_dir() {
+                    next.push(join(dir, component));
+                }
+                continue;
+            }
+            let Ok(entries) = std::fs::read_dir(&base) else { continue };
+            for entry in entries.flatten().filter(|e| e.path().is_dir()) {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if !name.starts_with('.') && wildcard(component, &name) {
+                    next.push(join(dir, &name));
+                }
+            }
+        }
+        dirs = next;
+    }
+    dirs.retain(|dir| !dir.is_empty());
+    dirs.sort();
+    dirs
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() { name.to_string() } else { format!("{}/{}", dir, name) }
+}
+
+fn wildcard(pattern: &str, name: &str) -> bool {
+    match pattern.chars().next() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).filter(|i| name.is_char_boundary(*i)).any(|i| wildcard(&pattern[1..], &name[i..])),
+        Some('?') => name.chars().next().is_some_and(|c| wildcard(&pattern[1..], &name[c.len_utf8()..])),
+        Some(p) => name.starts_with(p) && wildcard(&pattern[p.len_utf8()..], &name[p.len_utf8()..]),
+    }
+}
@@ -2,6 +2,28 @@ use crate::config::MontrsConfig;
 use anyhow::{Result, anyhow};
 use clap::Parser;
 
+/// Generates `tailwind.config.js` from `tailwind.toml` when the project uses
+/// it, and points the build at the generated file.
+pub fn prepare_tailwind(config: &mut MontrsConfig) {
+    let step = crate::report::reporter().step("tailwind config");
+    match crate::config::tailwind::ensure_tailwind_config(
+        std::path::Path::new("."),
+        config.project.tailwind_style.unwrap_or_default(),
+    ) {
+        Ok(Some(js_path)) => {
+            if config.build.tailwind_config_file.is_none() {
+                config.build.tailwind_config_file = Some(js_path.to_string_lossy().into_owned());
+            }
+            step.finish();
+        }
+        Ok(None) => step.skip("no tailwind.toml"),
+        Err(e) => {
+            crate::report::reporter().warn(format!("Failed to generate tailwind.config.js: {}", e));
+            step.fail();
+        }
+    }
+}
+
 pub async fn run_cargo_leptos(cmd: &str, args: &[String], config: &MontrsConfig) -> Result<()> {
     // Build arguments for cargo-leptos
     let mut args_list = vec!["cargo-leptos".to_string(), cmd.to_string()];
@@ -30,6 +52,8 @@ pub async fn run_cargo_leptos(cmd: &str, args: &[String], config: &MontrsConfig)
         }
     }
 
+    set_boot_env(config)?;
+
     let cli = cargo_leptos::config::Cli::try_parse_from(args_list)
         .map_err(|e| anyhow!("Failed to parse cargo-leptos arguments: {}", e))?;
 
@@ -48,8 +72,15 @@ pub async fn run_cargo_leptos(cmd: &str, args: &[String], config: &MontrsConfig)
                 let agent_manager = montrs_agent::AgentManager::new(cwd);
                 let error_msg = format!("{:?}", e);
                 
-                // Try to parse structured errors
-                let parsed_errors = montrs_agent::error_parser::parse_rustc_errors(&error_msg);
+                // Prefer structured diagnostics from a JSON check; fall back to the text output.
+                let mut parsed_errors: Vec<_> = collect_diagnostics(false, None)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|d| d.level == "Error")
+                    .collect();
+                if parsed_errors.is_empty() {
+                    parsed_errors = montrs_agent::error_parser::parse_rustc_errors(&error_msg);
+                }
                 if parsed_errors.is_empty() {
                     let _ = agent_manager.report_error(error_msg);
                 } else {
@@ -62,3 +93,54 @@ pub async fn run_cargo_leptos(cmd: &str, args: &[String], config: &MontrsConfig)
         }
     }
 }
+
+/// Runs `cargo check` (or `cargo clippy` when `clippy` is set) with JSON
+/// diagnostics and returns every error and warning it reports.
+pub fn collect_diagnostics(clippy: bool, package: Option<&str>) -> Result<Vec<montrs_agent::ProjectError>> {
+    let mut cmd = std::process::Command::new("cargo");
+    cmd.arg(if clippy { "clippy" } else { "check" });
+    match package {
+        Some(package) => cmd.args(["-p", package]),
+        None => cmd.arg("--workspace"),
+    };
+    cmd.args(["--all-targets", "--message-format=json"]);
+    cmd.stderr(std::process::Stdio::null());
+
+    let output = cmd.output().map_err(|e| anyhow!("Failed to run cargo: {}", e))?;
+    Ok(montrs_agent::error_parser::parse_json_diagnostics(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Tells `AppSpec::boot` in the app process where to save its trace, whether
+/// to print the waterfall, which budget to enforce and how many plates to
+/// initialize at once, and passes on the `[preset]` overrides and the CLI
+/// version it checks for compatibility.
+fn set_boot_env(config: &MontrsConfig) -> Result<()> {
+    use montrs_core::boot::{
+        BOOT_BUDGET_ACTION_VAR, BOOT_BUDGET_VAR, BOOT_CONCURRENCY_VAR, BOOT_TRACE_FILE, BOOT_TRACE_VAR, BOOT_VERBOSE_VAR,
+    };
+
+    let trace = std::env::current_dir()?.join(BOOT_TRACE_FILE);
+    let preset = config.preset.to_json()?;
+    unsafe {
+        std::env::set_var(BOOT_TRACE_VAR, trace);
+        std::env::set_var(montrs_core::compat::CLI_VERSION_VAR, crate::command::upgrade::CLI_VERSION);
+        if config.project.verbose > 0 {
+            std::env::set_var(BOOT_VERBOSE_VAR, "1");
+        }
+        if let Some(budget) = config.boot.budget_ms {
+            std::env::set_var(BOOT_BUDGET_VAR, budget.to_string());
+            let action = match config.boot.on_exceed {
+                montrs_core::BudgetAction::Warn => "warn",
+                montrs_core::BudgetAction::Fail => "fail",
+            };
+            std::env::set_var(BOOT_BUDGET_ACTION_VAR, action);
+        }
+        if let Some(limit) = config.boot.concurrency {
+            std::env::set_var(BOOT_CONCURRENCY_VAR, limit.to_string());
+        }
+        if !config.preset.overrides.is_empty() {
+            std::env::set_var(montrs_core::preset::PRESET_VAR, preset.to_string());
+        }
+    }
+    Ok(())
+}
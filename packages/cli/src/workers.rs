@@ -0,0 +1,420 @@
+//! Build workers for the dev loop (`[serve.workers]`).
+//!
+//! cargo-leptos already rebuilds the server and the WASM bundle from one
+//! long-lived watcher, so cargo and wasm-bindgen stay loaded between changes;
+//! with workers enabled that watcher also builds incrementally under
+//! `--release`. Next to it, a worker thread:
+//!
+//! - runs `wasm-opt` on every new bundle, reusing the output cached under
+//!   `target/montrs/wasm-opt-cache/` when the same module was optimized before;
+//! - rebuilds the Tailwind stylesheet only when a change touches class-bearing
+//!   source: the string literals and `class:` attributes of Rust files, and
+//!   any markup, script or stylesheet.
+//!
+//! Each phase is appended to `target/montrs/build-timings.jsonl`, which the
+//! dev dashboard sums up as runs, cache hits and time saved.
+
+use crate::config::MontrsConfig;
+use crate::report::reporter;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Where the workers log how long each phase took.
+pub const BUILD_TIMINGS_FILE: &str = "target/montrs/build-timings.jsonl";
+/// Optimized WASM modules, named by the hash of their input and wasm-opt arguments.
+pub const WASM_OPT_CACHE_DIR: &str = "target/montrs/wasm-opt-cache";
+
+/// Optimized modules kept in the cache; older ones are removed.
+const CACHE_ENTRIES: usize = 32;
+/// How often the workers look for changed sources and bundles.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// A bundle is only read once it has not been written to for this long.
+const SETTLE: Duration = Duration::from_millis(300);
+/// Source files whose changes can add or remove Tailwind classes.
+const SOURCE_EXTENSIONS: [&str; 7] = ["rs", "html", "css", "js", "ts", "jsx", "tsx"];
+/// Directories never scanned for sources.
+const SKIPPED_DIRS: [&str; 4] = ["target", "node_modules", "dist", "pkg"];
+
+/// What a phase did.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// The work was done.
+    Ran,
+    /// The result came from the cache.
+    Cached,
+    /// Nothing relevant changed, so the work was not needed.
+    Skipped,
+}
+
+/// One entry of the timings log.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BuildTiming {
+    /// Seconds since the Unix epoch.
+    pub at: u64,
+    /// `rebuild` (from a source change to the new WASM bundle), `wasm-opt` or `tailwind`.
+    pub phase: String,
+    pub ms: u64,
+    pub outcome: Outcome,
+}
+
+impl BuildTiming {
+    fn new(phase: &str, elapsed: Duration, outcome: Outcome) -> Self {
+        let at = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        Self { at, phase: phase.to_string(), ms: elapsed.as_millis() as u64, outcome }
+    }
+
+    /// Appends this entry to the log at `path`.
+    pub fn append_to(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Every entry in the log at `path`, oldest first. Unreadable lines are skipped.
+    pub fn read_log(path: &Path) -> Result<Vec<BuildTiming>> {
+        let content = std::fs::read_to_string(path)?;
+        Ok(content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+    }
+}
+
+/// The timings of one phase, summed up.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct PhaseStats {
+    pub phase: String,
+    pub ran: usize,
+    pub cached: usize,
+    pub skipped: usize,
+    /// Mean duration of the runs that did the work.
+    pub mean_ms: u64,
+    /// Estimated time saved by cache hits and skips, against `mean_ms`.
+    pub saved_ms: u64,
+}
+
+/// Sums up `timings` per phase, in phase name order.
+pub fn summarize(timings: &[BuildTiming]) -> Vec<PhaseStats> {
+    let mut phases: BTreeMap<&str, Vec<&BuildTiming>> = BTreeMap::new();
+    for timing in timings {
+        phases.entry(&timing.phase).or_default().push(timing);
+    }
+    phases
+        .into_iter()
+        .map(|(phase, timings)| {
+            let ran: Vec<u64> = timings.iter().filter(|t| t.outcome == Outcome::Ran).map(|t| t.ms).collect();
+            let mean_ms = if ran.is_empty() { 0 } else { ran.iter().sum::<u64>() / ran.len() as u64 };
+            let count = |outcome| timings.iter().filter(|t| t.outcome == outcome).count();
+            let saved_ms = timings
+                .iter()
+                .filter(|t| t.outcome != Outcome::Ran)
+                .map(|t| mean_ms.saturating_sub(t.ms))
+                .sum();
+            PhaseStats {
+                phase: phase.to_string(),
+                ran: ran.len(),
+                cached: count(Outcome::Cached),
+                skipped: count(Outcome::Skipped),
+                mean_ms,
+                saved_ms,
+            }
+        })
+        .collect()
+}
+
+/// wasm-opt output cached by the hash of the input module and the arguments.
+pub struct WasmOptCache {
+    dir: PathBuf,
+}
+
+impl WasmOptCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Runs `wasm_opt` with `args` on `bundle` in place, or copies the cached
+    /// result. Returns whether the cache had it.
+    pub fn optimize(&self, wasm_opt: &Path, args: &[String], bundle: &Path) -> Result<bool> {
+        let input = std::fs::read(bundle).with_context(|| format!("Failed to read {}", bundle.display()))?;
+        let mut hasher = Sha256::new();
+        for arg in args {
+            hasher.update(arg.as_bytes());
+            hasher.update([0]);
+        }
+        hasher.update(&input);
+        let cached = self.dir.join(format!("{}.wasm", hex::encode(hasher.finalize())));
+        if cached.exists() {
+            std::fs::copy(&cached, bundle)?;
+            return Ok(true);
+        }
+
+        std::fs::create_dir_all(&self.dir)?;
+        let output = cached.with_extension("tmp");
+        let status = Command::new(wasm_opt)
+            .args(args)
+            .arg(bundle)
+            .arg("-o")
+            .arg(&output)
+            .status()
+            .with_context(|| format!("Failed to run {}", wasm_opt.display()))?;
+        if !status.success() {
+            let _ = std::fs::remove_file(&output);
+            bail!("wasm-opt failed on {}", bundle.display());
+        }
+        std::fs::copy(&output, bundle)?;
+        std::fs::rename(&output, &cached)?;
+        self.prune();
+        Ok(false)
+    }
+
+    /// Keeps the most recently written [`CACHE_ENTRIES`] modules.
+    fn prune(&self) {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else { return };
+        let mut modules: Vec<(SystemTime, PathBuf)> = entries
+            .flatten()
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "wasm"))
+            .filter_map(|e| Some((e.metadata().ok()?.modified().ok()?, e.path())))
+            .collect();
+        modules.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+        for (_, path) in modules.into_iter().skip(CACHE_ENTRIES) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// What Tailwind sees of a file: the string literals and `class:` attributes
+/// of Rust source, all of any other file. Logic-only Rust edits keep it.
+pub fn class_fingerprint(path: &Path, contents: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    if path.extension().is_some_and(|e| e == "rs") {
+        let mut chars = contents.chars().peekable();
+        let mut in_string = false;
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    in_string = !in_string;
+                    hasher.update([0]);
+                }
+                '\\' if in_string => {
+                    if let Some(escaped) = chars.next() {
+                        hasher.update(escaped.to_string().as_bytes());
+                    }
+                }
+                c if in_string => hasher.update(c.to_string().as_bytes()),
+                _ => {}
+            }
+        }
+        for (i, _) in contents.match_indices("class:") {
+            let class = contents[i..].split(|c: char| c == '=' || c.is_whitespace()).next().unwrap_or_default();
+            hasher.update(class.as_bytes());
+            hasher.update([0]);
+        }
+    } else {
+        hasher.update(contents.as_bytes());
+    }
+    hasher.finalize().into()
+}
+
+/// Starts the workers `[serve.workers]` asks for. Does nothing when they are off.
+pub fn start(config: &MontrsConfig, root: PathBuf) {
+    let settings = &config.serve.workers;
+    if !settings.enabled {
+        return;
+    }
+    unsafe {
+        std::env::set_var("CARGO_INCREMENTAL", "1");
+    }
+
+    let wasm_opt = settings.wasm_opt.as_ref().and_then(|level| match which::which("wasm-opt") {
+        Ok(path) => Some((path, vec![level.clone(), "--strip-producers".to_string()])),
+        Err(_) => {
+            reporter().warn("[serve.workers] wasm_opt is set but wasm-opt is not installed; install binaryen");
+            None
+        }
+    });
+    let tailwind = if settings.tailwind { crate::devproc::tailwind_command(config, false) } else { None };
+    let mut jobs = vec!["incremental cargo".to_string()];
+    if let Some((_, args)) = &wasm_opt {
+        jobs.push(format!("cached wasm-opt {}", args[0]));
+    }
+    if tailwind.is_some() {
+        jobs.push("tailwind on class changes".to_string());
+    }
+    reporter().info(format!("  Build workers: {}", jobs.join(", ")));
+
+    let timings = root.join(BUILD_TIMINGS_FILE);
+    // Timings from an earlier session would blur this one's numbers.
+    let _ = std::fs::remove_file(&timings);
+    let mut worker = Worker {
+        pkg_dir: root.join(crate::command::build::pkg_dir(config)),
+        cache: WasmOptCache::new(root.join(WASM_OPT_CACHE_DIR)),
+        root,
+        timings,
+        wasm_opt,
+        tailwind,
+        sources: HashMap::new(),
+        classes: HashMap::new(),
+        bundles: None,
+        changed_at: None,
+    };
+    let spawned = std::thread::Builder::new().name("montrs-build-workers".to_string()).spawn(move || {
+        loop {
+            worker.tick();
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+    if let Err(e) = spawned {
+        reporter().warn(format!("Failed to start the build workers: {}", e));
+    }
+}
+
+#[derive(Default)]
+struct Changes {
+    any: bool,
+    /// A Rust file changed, so a new WASM bundle is on its way.
+    rust: bool,
+    /// A change can add or remove Tailwind classes.
+    classes: bool,
+}
+
+struct Worker {
+    root: PathBuf,
+    timings: PathBuf,
+    pkg_dir: PathBuf,
+    cache: WasmOptCache,
+    wasm_opt: Option<(PathBuf, Vec<String>)>,
+    tailwind: Option<(String, Vec<String>)>,
+    sources: HashMap<PathBuf, SystemTime>,
+    classes: HashMap<PathBuf, [u8; 32]>,
+    /// Bundles already handled; `None` until the first look at the package dir.
+    bundles: Option<HashMap<PathBuf, SystemTime>>,
+    /// When the first source change not yet seen in a bundle was noticed.
+    changed_at: Option<Instant>,
+}
+
+impl Worker {
+    fn tick(&mut self) {
+        let changes = self.scan_sources();
+        if changes.rust {
+            self.changed_at.get_or_insert_with(Instant::now);
+        }
+        if changes.any && self.tailwind.is_some() {
+            if changes.classes {
+                self.run_tailwind();
+            } else {
+                self.record(BuildTiming::new("tailwind", Duration::ZERO, Outcome::Skipped));
+            }
+        }
+        self.scan_bundles();
+    }
+
+    /// What changed in the sources since the last tick.
+    fn scan_sources(&mut self) -> Changes {
+        let mut current = HashMap::new();
+        let walker = walkdir::WalkDir::new(&self.root).into_iter().filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0 || !(name.starts_with('.') || (entry.file_type().is_dir() && SKIPPED_DIRS.contains(&name.as_ref())))
+        });
+        for entry in walker.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()).is_some_and(|e| SOURCE_EXTENSIONS.contains(&e))
+                && let Some(modified) = entry.metadata().ok().and_then(|m| m.modified().ok())
+            {
+                current.insert(path.to_path_buf(), modified);
+            }
+        }
+
+        let mut changes = Changes::default();
+        for (path, modified) in &current {
+            if self.sources.get(path) == Some(modified) {
+                continue;
+            }
+            changes.any = true;
+            changes.rust |= path.extension().is_some_and(|e| e == "rs");
+            let Ok(contents) = std::fs::read_to_string(path) else { continue };
+            let fingerprint = class_fingerprint(path, &contents);
+            if self.classes.insert(path.clone(), fingerprint) != Some(fingerprint) {
+                changes.classes = true;
+            }
+        }
+        let before = self.classes.len();
+        self.classes.retain(|path, _| current.contains_key(path));
+        if self.classes.len() != before {
+            changes.any = true;
+            changes.classes = true;
+        }
+        self.sources = current;
+        changes
+    }
+
+    /// Times each new bundle against the change that caused it and runs
+    /// wasm-opt on it. Bundles present before the first look are left alone.
+    fn scan_bundles(&mut self) {
+        let mut current: Vec<(PathBuf, SystemTime)> = walkdir::WalkDir::new(&self.pkg_dir)
+            .into_iter()
+            .flatten()
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "wasm"))
+            .filter_map(|e| Some((e.path().to_path_buf(), e.metadata().ok()?.modified().ok()?)))
+            .collect();
+        let Some(seen) = &mut self.bundles else {
+            self.bundles = Some(current.into_iter().collect());
+            return;
+        };
+        current.retain(|(path, modified)| {
+            seen.get(path) != Some(modified) && modified.elapsed().is_ok_and(|age| age >= SETTLE)
+        });
+
+        for (bundle, modified) in current {
+            if let Some(changed_at) = self.changed_at.take() {
+                self.record(BuildTiming::new("rebuild", changed_at.elapsed(), Outcome::Ran));
+            }
+            let mut modified = modified;
+            if let Some((wasm_opt, args)) = &self.wasm_opt {
+                let started = Instant::now();
+                match self.cache.optimize(wasm_opt, args, &bundle) {
+                    Ok(hit) => {
+                        let outcome = if hit { Outcome::Cached } else { Outcome::Ran };
+                        tracing::info!("wasm-opt {} in {:?} ({:?})", bundle.display(), started.elapsed(), outcome);
+                        self.record(BuildTiming::new("wasm-opt", started.elapsed(), outcome));
+                    }
+                    Err(e) => reporter().warn(format!("{:#}", e)),
+                }
+                modified = std::fs::metadata(&bundle).and_then(|m| m.modified()).unwrap_or(modified);
+            }
+            if let Some(seen) = &mut self.bundles {
+                seen.insert(bundle, modified);
+            }
+        }
+    }
+
+    fn run_tailwind(&mut self) {
+        let Some((program, args)) = &self.tailwind else { return };
+        let started = Instant::now();
+        let output = Command::new(program).args(args).current_dir(&self.root).stdin(Stdio::null()).output();
+        match output {
+            Ok(output) if output.status.success() => {
+                tracing::info!("Tailwind rebuilt the stylesheet in {:?}", started.elapsed());
+                self.record(BuildTiming::new("tailwind", started.elapsed(), Outcome::Ran));
+            }
+            Ok(output) => reporter().warn(format!(
+                "tailwindcss exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+            Err(e) => reporter().warn(format!("Failed to run {}: {}", program, e)),
+        }
+    }
+
+    fn record(&self, timing: BuildTiming) {
+        if let Err(e) = timing.append_to(&self.timings) {
+            tracing::warn!("Failed to record a build timing: {:#}", e);
+        }
+    }
+}
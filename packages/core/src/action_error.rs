@@ -0,0 +1,309 @@
+//! montrs-core/src/action_error.rs: Typed action errors, from the server to forms.
+//! An action that rejects its input fails with [`RouteError::Action`]
+//! carrying an [`ActionError`]: a stable code, a message, and the fields it
+//! is about. Validation errors from `sanitize_and_validate` become one
+//! [`FieldError`] per failing field; typed domain errors, enums deriving
+//! `DomainError`, keep their variant so the client can decode it again. The
+//! response body is `{"error": "...", "action_error": {...}}`, read back with
+//! [`ActionError::from_body`], and [`ErrorMessages`] turns codes and their
+//! params into localized text, so a form never matches on message strings.
+
+use crate::router::RouteError;
+use crate::validation::ValidationError;
+use crate::AgentError;
+use leptos::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// The code of an [`ActionError`] made from validation errors.
+pub const VALIDATION_FAILED: &str = "VALIDATION_FAILED";
+
+/// Why one field of an action's input was rejected.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    /// The field, as named in the input.
+    pub field: String,
+    /// A stable code, e.g. `VAL_MIN_LENGTH`, to pick a localized message by.
+    pub code: String,
+    /// The server's message, in English.
+    pub message: String,
+    /// Values a localized message can mention, e.g. `min`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, Value>,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { field: field.into(), code: code.into(), message: message.into(), params: BTreeMap::new() }
+    }
+
+    pub fn with_param(mut self, name: impl Into<String>, value: impl Serialize) -> Self {
+        self.params.insert(name.into(), serde_json::to_value(value).unwrap_or(Value::Null));
+        self
+    }
+}
+
+impl From<&ValidationError> for FieldError {
+    fn from(error: &ValidationError) -> Self {
+        let field = match error {
+            ValidationError::MinLength { field, .. }
+            | ValidationError::InvalidEmail { field }
+            | ValidationError::RegexMismatch { field, .. }
+            | ValidationError::Custom { field, .. } => *field,
+        };
+        let message = match error {
+            ValidationError::Custom { message, .. } => message.clone(),
+            other => other.to_string(),
+        };
+        let mut field_error = FieldError::new(field, error.error_code(), message);
+        match error {
+            ValidationError::MinLength { min, actual, .. } => {
+                field_error = field_error.with_param("min", min).with_param("actual", actual);
+            }
+            ValidationError::RegexMismatch { pattern, .. } => field_error = field_error.with_param("pattern", pattern),
+            _ => {}
+        }
+        field_error
+    }
+}
+
+/// The wire format of a failed action.
+///
+/// ```rust,ignore
+/// return Err(ActionError::new("EMAIL_TAKEN", "This email is already registered.")
+///     .with_status(409)
+///     .with_field(FieldError::new("email", "EMAIL_TAKEN", "Already registered."))
+///     .into());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActionError {
+    /// A stable code, e.g. [`VALIDATION_FAILED`] or a domain error's code.
+    pub code: String,
+    /// The server's message, in English.
+    pub message: String,
+    /// The HTTP status the action answers with.
+    #[serde(default = "default_status")]
+    pub status: u16,
+    /// The fields the error is about, in the order they were found.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+    /// Values a localized message can mention.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, Value>,
+    /// The typed domain error, as serialized, for [`ActionError::decode`].
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub detail: Value,
+}
+
+fn default_status() -> u16 {
+    422
+}
+
+impl ActionError {
+    /// An error answered with `422 Unprocessable Entity`.
+    pub fn new(code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            message: message.into(),
+            status: default_status(),
+            fields: Vec::new(),
+            params: BTreeMap::new(),
+            detail: Value::Null,
+        }
+    }
+
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn with_field(mut self, field: FieldError) -> Self {
+        self.fields.push(field);
+        self
+    }
+
+    pub fn with_param(mut self, name: impl Into<String>, value: impl Serialize) -> Self {
+        self.params.insert(name.into(), serde_json::to_value(value).unwrap_or(Value::Null));
+        self
+    }
+
+    /// A [`VALIDATION_FAILED`] error with one [`FieldError`] per validation error.
+    pub fn validation(errors: &[ValidationError]) -> Self {
+        let message = errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ");
+        let mut error = Self::new(VALIDATION_FAILED, message);
+        error.fields = errors.iter().map(FieldError::from).collect();
+        error
+    }
+
+    /// The error for a typed domain error. Its fields become the params, and
+    /// it is kept whole for [`ActionError::decode`].
+    pub fn domain<E: DomainError>(error: &E) -> Self {
+        let detail = serde_json::to_value(error).unwrap_or(Value::Null);
+        // Enums serialize as `{"Variant": {..fields}}`, or as `"Variant"` without fields.
+        let params: BTreeMap<String, Value> = match &detail {
+            Value::Object(variant) if variant.len() == 1 => match variant.values().next() {
+                Some(Value::Object(fields)) => fields.clone().into_iter().collect(),
+                _ => BTreeMap::new(),
+            },
+            Value::Object(fields) => fields.clone().into_iter().collect(),
+            _ => BTreeMap::new(),
+        };
+        let message = error.to_string();
+        let mut action_error = Self::new(error.code(), message.clone()).with_status(error.status());
+        if let Some(field) = error.field() {
+            let mut field_error = FieldError::new(field, error.code(), message);
+            field_error.params = params.clone();
+            action_error.fields.push(field_error);
+        }
+        action_error.params = params;
+        action_error.detail = detail;
+        action_error
+    }
+
+    /// The typed domain error this was made from, if it was made from an `E`.
+    pub fn decode<E: DomainError>(&self) -> Option<E> {
+        let error: E = serde_json::from_value(self.detail.clone()).ok()?;
+        (error.code() == self.code).then_some(error)
+    }
+
+    /// The errors about `field`.
+    pub fn field_errors<'a>(&'a self, field: &'a str) -> impl Iterator<Item = &'a FieldError> {
+        self.fields.iter().filter(move |error| error.field == field)
+    }
+
+    /// The error in a failed action's response. Bodies without an
+    /// `action_error`, e.g. from a route guard, give an error coded
+    /// `HTTP_<status>` with the body's `error` message.
+    pub fn from_body(status: u16, body: &[u8]) -> Self {
+        #[derive(Deserialize)]
+        struct Envelope {
+            #[serde(default)]
+            error: Option<String>,
+            #[serde(default)]
+            action_error: Option<ActionError>,
+        }
+        match serde_json::from_slice::<Envelope>(body) {
+            Ok(Envelope { action_error: Some(error), .. }) => error,
+            Ok(Envelope { error: Some(message), .. }) => Self::new(format!("HTTP_{}", status), message).with_status(status),
+            _ => Self::new(format!("HTTP_{}", status), String::from_utf8_lossy(body)).with_status(status),
+        }
+    }
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<ActionError> for RouteError {
+    fn from(error: ActionError) -> Self {
+        RouteError::Action(Box::new(error))
+    }
+}
+
+/// A typed error an action fails with, sent to the client as an
+/// [`ActionError`] and decoded there with [`ActionError::decode`].
+/// `#[derive(DomainError)]` implements it along with `From<Self> for
+/// RouteError`, so actions can return it with `?`:
+///
+/// ```rust,ignore
+/// #[derive(Debug, Serialize, Deserialize, thiserror::Error, DomainError)]
+/// enum CheckoutError {
+///     #[error("only {available} left")]
+///     #[domain_error(code = "OUT_OF_STOCK", status = 409, field = "quantity")]
+///     OutOfStock { available: u32 },
+///     #[error("the coupon has expired")]
+///     CouponExpired,
+/// }
+/// ```
+pub trait DomainError: Serialize + DeserializeOwned + fmt::Display {
+    /// A stable code for the error, e.g. `OUT_OF_STOCK`.
+    fn code(&self) -> &'static str;
+
+    /// The HTTP status the action answers with.
+    fn status(&self) -> u16 {
+        default_status()
+    }
+
+    /// The input field the error is about, if any.
+    fn field(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// Localized messages by error code, with `{param}` placeholders filled from
+/// the error's params. `{field}` is the field's name. Codes without a message
+/// keep the server's.
+///
+/// ```rust,ignore
+/// let messages = ErrorMessages::new()
+///     .with("VAL_MIN_LENGTH", "Mindestens {min} Zeichen")
+///     .with("OUT_OF_STOCK", "Nur noch {available} auf Lager");
+/// AppSpec::new(config, env).with_error_messages(messages);
+///
+/// // In a form, with the result of the action:
+/// let messages = use_error_messages();
+/// let email_errors = move || error.get().map(|e| messages.field(&e, "email")).unwrap_or_default();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ErrorMessages {
+    messages: HashMap<String, String>,
+}
+
+impl ErrorMessages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shows errors coded `code` as `template`.
+    pub fn with(mut self, code: impl Into<String>, template: impl Into<String>) -> Self {
+        self.messages.insert(code.into(), template.into());
+        self
+    }
+
+    /// The message for the whole error.
+    pub fn message(&self, error: &ActionError) -> String {
+        match self.messages.get(&error.code) {
+            Some(template) => fill(template, &error.params, None),
+            None => error.message.clone(),
+        }
+    }
+
+    /// The message for one field error.
+    pub fn field_message(&self, error: &FieldError) -> String {
+        match self.messages.get(&error.code) {
+            Some(template) => fill(template, &error.params, Some(&error.field)),
+            None => error.message.clone(),
+        }
+    }
+
+    /// The messages for the errors about `field`, in order.
+    pub fn field(&self, error: &ActionError, field: &str) -> Vec<String> {
+        error.field_errors(field).map(|e| self.field_message(e)).collect()
+    }
+}
+
+fn fill(template: &str, params: &BTreeMap<String, Value>, field: Option<&str>) -> String {
+    let mut text = template.to_string();
+    if let Some(field) = field {
+        text = text.replace("{field}", field);
+    }
+    for (name, value) in params {
+        let value = match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        text = text.replace(&format!("{{{}}}", name), &value);
+    }
+    text
+}
+
+/// Returns the `ErrorMessages` provided by `AppSpec::mount`, or an empty set
+/// that keeps the server's messages.
+pub fn use_error_messages() -> ErrorMessages {
+    use_context::<ErrorMessages>().unwrap_or_default()
+}
@@ -0,0 +1,354 @@
+//! montrs-core/src/analytics.rs: Opt-in route usage analytics.
+//! With [`crate::AppSpec::with_analytics`], the router counts every loader and
+//! action call per route pattern: hits, status classes and a latency
+//! histogram. Nothing about the visitor is kept: no addresses, request paths,
+//! params or user IDs. Counts aggregate in memory and are handed to the
+//! [`AnalyticsSink`]s one window at a time, e.g. a table written through
+//! `montrs_orm::analytics::RouteUsageTable`. No third-party service is involved.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Set by `montrs serve` to the file the totals are written to for the dev dashboard.
+pub const ANALYTICS_VAR: &str = "MONTRS_ANALYTICS";
+/// Where `montrs serve` asks the app to write its totals, relative to the project root.
+pub const ANALYTICS_FILE: &str = "target/montrs/analytics.json";
+/// Upper bounds of the latency histogram buckets, in milliseconds. Slower
+/// calls fall in one more bucket.
+pub const LATENCY_BUCKETS_MS: [u64; 10] = [5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000];
+
+/// How often a window is handed to the sinks by default.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+const DUMP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Calls per status class.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusClasses {
+    /// `2xx`: the handler succeeded.
+    pub success: u64,
+    /// `4xx`: not found, unauthorized, invalid input.
+    pub client_error: u64,
+    /// `5xx`: internal errors, failed upstreams and panics.
+    pub server_error: u64,
+}
+
+/// Usage of one route operation over a window or the whole session.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteUsage {
+    /// The registered pattern, e.g. `/users/:id`.
+    pub route: String,
+    /// `load` or `act`.
+    pub operation: String,
+    pub hits: u64,
+    pub status: StatusClasses,
+    pub total_us: u64,
+    pub max_us: u64,
+    /// Calls per [`LATENCY_BUCKETS_MS`] bucket, plus the overflow bucket.
+    pub latency_buckets: Vec<u64>,
+}
+
+impl RouteUsage {
+    fn new(route: &str, operation: &str) -> Self {
+        Self {
+            route: route.to_string(),
+            operation: operation.to_string(),
+            latency_buckets: vec![0; LATENCY_BUCKETS_MS.len() + 1],
+            ..Default::default()
+        }
+    }
+
+    fn record(&mut self, status: u16, elapsed: Duration) {
+        let us = elapsed.as_micros() as u64;
+        self.hits += 1;
+        match status / 100 {
+            2 => self.status.success += 1,
+            4 => self.status.client_error += 1,
+            5 => self.status.server_error += 1,
+            _ => {}
+        }
+        self.total_us += us;
+        self.max_us = self.max_us.max(us);
+        let bucket = LATENCY_BUCKETS_MS.iter().position(|&ms| us <= ms * 1_000).unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.latency_buckets[bucket] += 1;
+    }
+
+    pub fn mean_us(&self) -> u64 {
+        self.total_us.checked_div(self.hits).unwrap_or(0)
+    }
+
+    /// Latency under which `percentile` percent of the calls finished, as the
+    /// upper bound of their histogram bucket (or the slowest call), in milliseconds.
+    pub fn percentile_ms(&self, percentile: f64) -> u64 {
+        let target = (self.hits as f64 * percentile / 100.0).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (i, count) in self.latency_buckets.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                return LATENCY_BUCKETS_MS.get(i).copied().unwrap_or(self.max_us / 1_000);
+            }
+        }
+        self.max_us / 1_000
+    }
+
+    /// Share of calls that ended in a `5xx`, in percent.
+    pub fn error_rate(&self) -> f64 {
+        if self.hits == 0 { 0.0 } else { self.status.server_error as f64 / self.hits as f64 * 100.0 }
+    }
+}
+
+/// The calls counted between two flushes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsageWindow {
+    pub started: DateTime<Utc>,
+    pub ended: DateTime<Utc>,
+    /// Busiest first.
+    pub routes: Vec<RouteUsage>,
+}
+
+/// The session totals, as written for the dev dashboard.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalyticsSnapshot {
+    pub since: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Busiest first.
+    pub routes: Vec<RouteUsage>,
+}
+
+impl AnalyticsSnapshot {
+    pub fn read_from(path: &Path) -> std::io::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(std::io::Error::other)
+    }
+}
+
+/// Receives each window of counts. A failed flush is logged and the window dropped.
+#[async_trait]
+pub trait AnalyticsSink: Send + Sync + 'static {
+    async fn flush(&self, window: &UsageWindow) -> anyhow::Result<()>;
+}
+
+type Counts = HashMap<(String, &'static str), RouteUsage>;
+
+struct Window {
+    started: DateTime<Utc>,
+    opened: Instant,
+    routes: Counts,
+}
+
+impl Window {
+    fn new() -> Self {
+        Self { started: Utc::now(), opened: Instant::now(), routes: HashMap::new() }
+    }
+}
+
+/// Aggregates route usage and flushes it to the sinks.
+///
+/// ```rust,ignore
+/// let analytics = Analytics::new()
+///     .with_sink(RouteUsageTable::new(db.clone()))
+///     .with_flush_interval(Duration::from_secs(300));
+/// let app = AppSpec::new(config, env).with_analytics(analytics);
+/// ```
+pub struct Analytics {
+    since: DateTime<Utc>,
+    window: Mutex<Window>,
+    totals: Mutex<Counts>,
+    sinks: Vec<Arc<dyn AnalyticsSink>>,
+    interval: Duration,
+    output: Option<PathBuf>,
+    last_dump: Mutex<Option<Instant>>,
+}
+
+impl Default for Analytics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Analytics {
+    pub fn new() -> Self {
+        Self {
+            since: Utc::now(),
+            window: Mutex::new(Window::new()),
+            totals: Mutex::new(HashMap::new()),
+            sinks: Vec::new(),
+            interval: FLUSH_INTERVAL,
+            output: None,
+            last_dump: Mutex::new(None),
+        }
+    }
+
+    /// Hands every window to `sink`.
+    pub fn with_sink(mut self, sink: impl AnalyticsSink) -> Self {
+        self.sinks.push(Arc::new(sink));
+        self
+    }
+
+    /// How long a window collects calls before it is flushed (default: one minute).
+    pub fn with_flush_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Writes the session totals to `path`, at most once per second.
+    pub fn with_output(mut self, path: impl Into<PathBuf>) -> Self {
+        self.output = Some(path.into());
+        self
+    }
+
+    /// Adds the output requested by `montrs serve`, if any.
+    pub fn with_output_from_env(self) -> Self {
+        match std::env::var_os(ANALYTICS_VAR) {
+            Some(path) => self.with_output(path),
+            None => self,
+        }
+    }
+
+    /// Counts one call of `route`/`operation` that answered `status` after `elapsed`.
+    pub fn record(&self, route: &str, operation: &'static str, status: u16, elapsed: Duration) {
+        let due = {
+            let mut window = self.window.lock().unwrap_or_else(|e| e.into_inner());
+            window
+                .routes
+                .entry((route.to_string(), operation))
+                .or_insert_with(|| RouteUsage::new(route, operation))
+                .record(status, elapsed);
+            window.opened.elapsed() >= self.interval
+        };
+        {
+            let mut totals = self.totals.lock().unwrap_or_else(|e| e.into_inner());
+            totals
+                .entry((route.to_string(), operation))
+                .or_insert_with(|| RouteUsage::new(route, operation))
+                .record(status, elapsed);
+        }
+        self.maybe_dump();
+        if due {
+            self.flush_in_background();
+        }
+    }
+
+    /// The session totals, busiest first.
+    pub fn totals(&self) -> Vec<RouteUsage> {
+        busiest_first(self.totals.lock().unwrap_or_else(|e| e.into_inner()).values().cloned().collect())
+    }
+
+    /// The session totals with their time span.
+    pub fn snapshot(&self) -> AnalyticsSnapshot {
+        AnalyticsSnapshot { since: self.since, updated_at: Utc::now(), routes: self.totals() }
+    }
+
+    /// Closes the current window and hands it to every sink. Call it at
+    /// shutdown so the last window is not lost.
+    pub async fn flush(&self) -> UsageWindow {
+        let window = self.take_window();
+        send(&self.sinks, &window).await;
+        window
+    }
+
+    fn take_window(&self) -> UsageWindow {
+        let window = std::mem::replace(&mut *self.window.lock().unwrap_or_else(|e| e.into_inner()), Window::new());
+        UsageWindow { started: window.started, ended: Utc::now(), routes: busiest_first(window.routes.into_values().collect()) }
+    }
+
+    /// Flushes on the current tokio runtime without holding up the request.
+    fn flush_in_background(&self) {
+        let window = self.take_window();
+        if self.sinks.is_empty() || window.routes.is_empty() {
+            return;
+        }
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                let sinks = self.sinks.clone();
+                runtime.spawn(async move { send(&sinks, &window).await });
+            }
+            Err(_) => tracing::warn!(routes = window.routes.len(), "no tokio runtime to flush route analytics; window dropped"),
+        }
+    }
+
+    /// Writes [`Self::snapshot`] to `path`.
+    pub fn write_to(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let snapshot = serde_json::to_string_pretty(&self.snapshot()).map_err(std::io::Error::other)?;
+        std::fs::write(path, snapshot)
+    }
+
+    fn maybe_dump(&self) {
+        let Some(path) = &self.output else {
+            return;
+        };
+        {
+            let mut last = self.last_dump.lock().unwrap_or_else(|e| e.into_inner());
+            if last.is_some_and(|at| at.elapsed() < DUMP_INTERVAL) {
+                return;
+            }
+            *last = Some(Instant::now());
+        }
+        if let Err(e) = self.write_to(path) {
+            tracing::warn!(error = %e, path = %path.display(), "failed to write route analytics");
+        }
+    }
+}
+
+impl Drop for Analytics {
+    /// Writes the calls made since the last throttled write.
+    fn drop(&mut self) {
+        if let Some(path) = &self.output {
+            let _ = self.write_to(path);
+        }
+    }
+}
+
+async fn send(sinks: &[Arc<dyn AnalyticsSink>], window: &UsageWindow) {
+    if window.routes.is_empty() {
+        return;
+    }
+    for sink in sinks {
+        if let Err(e) = sink.flush(window).await {
+            tracing::warn!(error = %e, routes = window.routes.len(), "failed to flush route analytics");
+        }
+    }
+}
+
+fn busiest_first(mut routes: Vec<RouteUsage>) -> Vec<RouteUsage> {
+    routes.sort_by(|a, b| b.hits.cmp(&a.hits).then_with(|| a.route.cmp(&b.route)).then_with(|| a.operation.cmp(&b.operation)));
+    routes
+}
+
+/// A loader answering with the session totals, busiest first, for an admin
+/// page or an internal API.
+///
+/// ```rust,ignore
+/// fn loader(&self) -> Self::Loader {
+///     AnalyticsLoader::new(self.analytics.clone())
+/// }
+/// ```
+pub struct AnalyticsLoader {
+    analytics: Arc<Analytics>,
+}
+
+impl AnalyticsLoader {
+    pub fn new(analytics: Arc<Analytics>) -> Self {
+        Self { analytics }
+    }
+}
+
+#[async_trait]
+impl<P: crate::RouteParams, C: crate::AppConfig> crate::RouteLoader<P, C> for AnalyticsLoader {
+    type Output = Vec<RouteUsage>;
+
+    async fn load(&self, _ctx: crate::RouteContext<'_, C>, _params: P) -> Result<Vec<RouteUsage>, crate::RouteError> {
+        Ok(self.analytics.totals())
+    }
+
+    fn description(&self) -> &'static str {
+        "Route usage analytics: hits, status classes and latencies per route"
+    }
+}
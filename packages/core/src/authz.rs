@@ -0,0 +1,175 @@
+//! montrs-core/src/authz.rs: Role-based access control for routes.
+//! Routes declare what they need at registration with `.requires(..)`. The
+//! requirements are stored as route metadata (see [`crate::meta::REQUIRES`]), so
+//! they travel through `RouterSpec`, the agent snapshot and OpenAPI like
+//! ownership and deprecation do. An [`Rbac`] guard on the router finds the
+//! caller's roles and checks them before the loader or action runs.
+
+use crate::meta;
+use crate::router::{RouteContext, RouteError};
+use crate::AppConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+
+/// What a route requires of its caller.
+///
+/// ```rust,ignore
+/// router.register(UsersAdminRoute).requires(Permission::Admin);
+/// router.register(RefundRoute).requires(Permission::named("orders:refund"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "String", from = "String")]
+pub enum Permission {
+    /// The `admin` role.
+    Admin,
+    /// Any other role, held directly by the caller.
+    Role(String),
+    /// A permission granted to one or more roles with [`Rbac::grant`].
+    Named(String),
+}
+
+impl Permission {
+    pub fn role(name: impl Into<String>) -> Self {
+        match name.into() {
+            name if name == ADMIN => Permission::Admin,
+            name => Permission::Role(name),
+        }
+    }
+
+    pub fn named(name: impl Into<String>) -> Self {
+        Permission::Named(name.into())
+    }
+
+    /// Reads the requirements of a route back from its metadata, in declaration order.
+    pub fn from_meta(metadata: &HashMap<String, String>) -> Vec<Self> {
+        metadata
+            .get(meta::REQUIRES)
+            .map(|list| list.split(',').map(str::trim).filter(|p| !p.is_empty()).map(Self::from).collect())
+            .unwrap_or_default()
+    }
+
+    /// Adds this requirement to a metadata map, keeping earlier ones.
+    pub fn apply_to(&self, metadata: &mut HashMap<String, String>) {
+        let mut required = Self::from_meta(metadata);
+        if !required.contains(self) {
+            required.push(self.clone());
+        }
+        let list: Vec<String> = required.iter().map(ToString::to_string).collect();
+        metadata.insert(meta::REQUIRES.to_string(), list.join(","));
+    }
+}
+
+const ADMIN: &str = "admin";
+const ROLE_PREFIX: &str = "role:";
+
+/// Roles are written `role:<name>`, named permissions as is.
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Permission::Admin => write!(f, "{}{}", ROLE_PREFIX, ADMIN),
+            Permission::Role(role) => write!(f, "{}{}", ROLE_PREFIX, role),
+            Permission::Named(name) => f.write_str(name),
+        }
+    }
+}
+
+impl From<&str> for Permission {
+    fn from(value: &str) -> Self {
+        match value.strip_prefix(ROLE_PREFIX) {
+            Some(role) => Self::role(role),
+            None => Self::named(value),
+        }
+    }
+}
+
+impl From<String> for Permission {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+impl From<Permission> for String {
+    fn from(permission: Permission) -> Self {
+        permission.to_string()
+    }
+}
+
+/// The signed-in caller of a request, as found by an [`Rbac`] guard.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Principal {
+    pub id: String,
+    pub roles: Vec<String>,
+}
+
+impl Principal {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self { id: id.into(), roles: Vec::new() }
+    }
+
+    pub fn with_role(mut self, role: impl Into<String>) -> Self {
+        self.roles.push(role.into());
+        self
+    }
+}
+
+/// Finds the signed-in caller of a request, with their roles.
+pub type PrincipalResolver<C> = Arc<dyn Fn(&RouteContext<'_, C>) -> Option<Principal> + Send + Sync>;
+
+/// Checks route requirements against the caller's roles. A route passes when
+/// the caller satisfies every one of its requirements: a role must be held
+/// directly, a named permission must be granted to one of the caller's roles.
+/// The `admin` role gets nothing it is not granted.
+///
+/// ```rust,ignore
+/// let rbac = Rbac::new(|ctx: &RouteContext<'_, AppCfg>| session_principal(ctx))
+///     .grant("support", ["orders:read"])
+///     .grant("admin", ["orders:read", "orders:refund"]);
+/// AppSpec::new(config, env).with_rbac(rbac);
+/// ```
+pub struct Rbac<C: AppConfig> {
+    principal: PrincipalResolver<C>,
+    grants: HashMap<String, HashSet<String>>,
+}
+
+impl<C: AppConfig> Clone for Rbac<C> {
+    fn clone(&self) -> Self {
+        Self { principal: self.principal.clone(), grants: self.grants.clone() }
+    }
+}
+
+impl<C: AppConfig> Rbac<C> {
+    pub fn new(principal: impl Fn(&RouteContext<'_, C>) -> Option<Principal> + Send + Sync + 'static) -> Self {
+        Self { principal: Arc::new(principal), grants: HashMap::new() }
+    }
+
+    /// Grants named permissions to everyone holding `role`.
+    pub fn grant<P: Into<String>>(mut self, role: impl Into<String>, permissions: impl IntoIterator<Item = P>) -> Self {
+        self.grants.entry(role.into()).or_default().extend(permissions.into_iter().map(Into::into));
+        self
+    }
+
+    /// Whether `principal` satisfies `permission`.
+    pub fn allows(&self, principal: &Principal, permission: &Permission) -> bool {
+        let holds = |role: &str| principal.roles.iter().any(|r| r == role);
+        match permission {
+            Permission::Admin => holds(ADMIN),
+            Permission::Role(role) => holds(role),
+            Permission::Named(name) => principal
+                .roles
+                .iter()
+                .any(|role| self.grants.get(role).is_some_and(|granted| granted.contains(name))),
+        }
+    }
+
+    /// Answers `Unauthorized` when no one is signed in and `Forbidden` with
+    /// the first missing requirement when the caller lacks one.
+    pub fn check(&self, ctx: &RouteContext<'_, C>, required: &[Permission]) -> Result<Principal, RouteError> {
+        let principal = (self.principal)(ctx).ok_or(RouteError::Unauthorized)?;
+        match required.iter().find(|permission| !self.allows(&principal, permission)) {
+            Some(missing) => Err(RouteError::Forbidden(missing.to_string())),
+            None => Ok(principal),
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! montrs-core/src/body.rs: Non-JSON request bodies for Actions.
+//! This file lets Actions declare raw binary or protobuf payloads, validates
+//! the incoming content type, and decodes the body without a JSON round-trip.
+
+use crate::router::RouteError;
+use serde::de::{DeserializeOwned, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// The wire format an Action expects for its input body.
+///
+/// Recorded in the `RouterSpec` so agents and clients know which
+/// content types a route accepts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BodyFormat {
+    /// `application/json`, decoded with serde_json (the default).
+    #[default]
+    Json,
+    /// Raw bytes restricted to the listed media types (e.g. `image/*`).
+    Binary { content_types: Vec<String> },
+    /// A protobuf message, sent as `application/x-protobuf`.
+    Protobuf { message: String },
+}
+
+impl BodyFormat {
+    /// Declares a raw binary body accepting the given media types.
+    pub fn binary<I, S>(content_types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        BodyFormat::Binary {
+            content_types: content_types.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Declares a protobuf body for the message type `M`.
+    pub fn protobuf<M>() -> Self {
+        BodyFormat::Protobuf {
+            message: std::any::type_name::<M>().to_string(),
+        }
+    }
+
+    /// Returns the media types accepted by this format.
+    pub fn content_types(&self) -> Vec<String> {
+        match self {
+            BodyFormat::Json => vec!["application/json".to_string()],
+            BodyFormat::Binary { content_types } => content_types.clone(),
+            BodyFormat::Protobuf { .. } => vec![
+                "application/x-protobuf".to_string(),
+                "application/protobuf".to_string(),
+            ],
+        }
+    }
+
+    /// Checks whether a request `Content-Type` header satisfies this format.
+    /// Parameters such as `; charset=utf-8` are ignored and `type/*` wildcards are honored.
+    pub fn accepts(&self, content_type: &str) -> bool {
+        let media = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        self.content_types().iter().any(|allowed| {
+            let allowed = allowed.to_ascii_lowercase();
+            if allowed == "*/*" {
+                return true;
+            }
+            match allowed.strip_suffix("/*") {
+                Some(prefix) => media.split('/').next() == Some(prefix),
+                None => allowed == media,
+            }
+        })
+    }
+
+    /// Decodes a request body into `T` after validating the content type.
+    pub fn decode<T: DeserializeOwned>(&self, content_type: &str, body: &[u8]) -> Result<T, RouteError> {
+        if !self.accepts(content_type) {
+            return Err(RouteError::UnsupportedMediaType(content_type.to_string()));
+        }
+
+        match self {
+            BodyFormat::Json => serde_json::from_slice(body)
+                .map_err(|e| RouteError::ValidationFailed(e.to_string())),
+            BodyFormat::Binary { .. } | BodyFormat::Protobuf { .. } => {
+                let de = serde::de::value::BytesDeserializer::<serde::de::value::Error>::new(body);
+                T::deserialize(de).map_err(|e| RouteError::ValidationFailed(e.to_string()))
+            }
+        }
+    }
+}
+
+/// An opaque binary Action input (firmware blobs, images, sensor frames).
+///
+/// Serializes as bytes, so it can be received either as a raw body via
+/// `BodyFormat::Binary` or as a byte array inside a JSON payload.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawBody(pub Vec<u8>);
+
+impl RawBody {
+    /// Returns the body as a byte slice.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Consumes the body, returning the owned bytes.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl Serialize for RawBody {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for RawBody {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_byte_buf(BytesVisitor).map(RawBody)
+    }
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a byte buffer")
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E: serde::de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(v)
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut out = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(b) = seq.next_element::<u8>()? {
+            out.push(b);
+        }
+        Ok(out)
+    }
+}
+
+/// A protobuf-encoded Action input or output, backed by `prost`.
+///
+/// Pair it with `BodyFormat::protobuf::<M>()` in `RouteAction::body_format`.
+#[cfg(feature = "protobuf")]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Protobuf<M>(pub M);
+
+#[cfg(feature = "protobuf")]
+impl<M: prost::Message> Serialize for Protobuf<M> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0.encode_to_vec())
+    }
+}
+
+#[cfg(feature = "protobuf")]
+impl<'de, M: prost::Message + Default> Deserialize<'de> for Protobuf<M> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserializer.deserialize_byte_buf(BytesVisitor)?;
+        M::decode(bytes.as_slice())
+            .map(Protobuf)
+            .map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,431 @@
+//! montrs-core/src/boot.rs: Boot tracing and startup budgets.
+//! `AppSpec::boot` initializes the plates and builds the router, recording how
+//! long each step took. The resulting `BootTrace` can be printed as a waterfall,
+//! written to `target/montrs/boot-trace.json` for the agent snapshot, and checked
+//! against the startup budget configured under `[boot]` in `montrs.toml`.
+//! `AppSpec::shutdown` runs the plates' `shutdown` hooks in the reverse order.
+
+use crate::{AgentError, AppConfig, Plate};
+use crate::compat::CompatError;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::Instant;
+
+/// Where `AppSpec::boot` writes its trace (set by the CLI).
+pub const BOOT_TRACE_VAR: &str = "MONTRS_BOOT_TRACE";
+/// Prints the boot waterfall to stderr when set (`montrs serve -v`).
+pub const BOOT_VERBOSE_VAR: &str = "MONTRS_BOOT_VERBOSE";
+/// Startup budget in milliseconds.
+pub const BOOT_BUDGET_VAR: &str = "MONTRS_BOOT_BUDGET_MS";
+/// `warn` (default) or `fail` when the budget is exceeded.
+pub const BOOT_BUDGET_ACTION_VAR: &str = "MONTRS_BOOT_BUDGET_ACTION";
+/// Maximum number of plates initialized at once (`[boot] concurrency`).
+pub const BOOT_CONCURRENCY_VAR: &str = "MONTRS_BOOT_CONCURRENCY";
+/// Default location of the trace, relative to the project root.
+pub const BOOT_TRACE_FILE: &str = "target/montrs/boot-trace.json";
+
+/// Errors raised while booting an `AppSpec`.
+#[derive(Debug, thiserror::Error)]
+pub enum BootError {
+    #[error("Plate '{plate}' failed to initialize: {reason}")]
+    PlateInit { plate: String, reason: String },
+    #[error("Plate '{plate}' failed to shut down: {reason}")]
+    PlateShutdown { plate: String, reason: String },
+    #[error("Plate '{plate}' depends on '{dependency}', which is not registered")]
+    MissingDependency { plate: String, dependency: String },
+    #[error("Plate dependency cycle between: {}", plates.join(", "))]
+    DependencyCycle { plates: Vec<String> },
+    #[error("Startup took {total_ms:.1}ms, over the {budget_ms}ms budget")]
+    BudgetExceeded { total_ms: f64, budget_ms: u64, slowest: String },
+    #[error("Spec manifest '{path}' could not be read: {reason}")]
+    SpecManifest { path: String, reason: String },
+    #[error("Spec fingerprint {actual} does not match the deployed {expected}: {}", changes.join("; "))]
+    SpecMismatch { expected: String, actual: String, changes: Vec<String> },
+    #[error("Invalid `[preset]` for target {target}: {reason}")]
+    Preset { target: String, reason: String },
+    #[error("{0}")]
+    Incompatible(CompatError),
+}
+
+impl AgentError for BootError {
+    fn error_code(&self) -> &'static str {
+        match self {
+            BootError::PlateInit { .. } => "BOOT_PLATE_INIT",
+            BootError::PlateShutdown { .. } => "BOOT_PLATE_SHUTDOWN",
+            BootError::MissingDependency { .. } => "BOOT_MISSING_DEPENDENCY",
+            BootError::DependencyCycle { .. } => "BOOT_DEPENDENCY_CYCLE",
+            BootError::BudgetExceeded { .. } => "BOOT_BUDGET_EXCEEDED",
+            BootError::SpecManifest { .. } => "BOOT_SPEC_MANIFEST",
+            BootError::SpecMismatch { .. } => "BOOT_SPEC_MISMATCH",
+            BootError::Preset { .. } => "BOOT_PRESET",
+            BootError::Incompatible(_) => "BOOT_INCOMPATIBLE_CRATES",
+        }
+    }
+
+    fn explanation(&self) -> String {
+        match self {
+            BootError::PlateInit { plate, reason } => {
+                format!("The `init` method of plate '{}' returned an error: {}", plate, reason)
+            }
+            BootError::PlateShutdown { plate, reason } => {
+                format!("The `shutdown` method of plate '{}' returned an error: {}", plate, reason)
+            }
+            BootError::MissingDependency { plate, dependency } => format!(
+                "Plate '{}' lists '{}' in `dependencies()`, but no plate with that name was added to the AppSpec.",
+                plate, dependency
+            ),
+            BootError::DependencyCycle { plates } => format!(
+                "The plates {} depend on each other, so none of them can be initialized first.",
+                plates.join(", ")
+            ),
+            BootError::BudgetExceeded { total_ms, budget_ms, slowest } => format!(
+                "Booting the application took {:.1}ms, but `[boot] budget_ms` allows {}ms. The slowest phase was {}.",
+                total_ms, budget_ms, slowest
+            ),
+            BootError::SpecManifest { path, reason } => format!(
+                "Strict mode compares the app with the fingerprint in '{}', but the file could not be read: {}",
+                path, reason
+            ),
+            BootError::SpecMismatch { changes, .. } => format!(
+                "The running application is not the one the deployed manifest describes. Changes: {}.",
+                changes.join("; ")
+            ),
+            BootError::Preset { target, reason } => format!(
+                "The `[preset]` overrides in montrs.toml could not be applied to the {} defaults: {}",
+                target, reason
+            ),
+            BootError::Incompatible(e) => e.explanation(),
+        }
+    }
+
+    fn suggested_fixes(&self) -> Vec<String> {
+        match self {
+            BootError::PlateInit { .. } => vec![
+                "Check the plate's configuration and the services it connects to.".to_string(),
+                "Make sure the plates it depends on are registered before it.".to_string(),
+            ],
+            BootError::PlateShutdown { .. } => vec![
+                "Check that the plate's connections and tasks can still be reached when it shuts down.".to_string(),
+                "Make `shutdown` safe to call when `init` did not run or failed.".to_string(),
+            ],
+            BootError::MissingDependency { dependency, .. } => vec![
+                format!("Add the '{}' plate with `AppSpec::with_plate`.", dependency),
+                "Check that the name matches the dependency's `Plate::name`.".to_string(),
+            ],
+            BootError::DependencyCycle { .. } => vec![
+                "Remove one of the dependencies, or merge the plates.".to_string(),
+                "Run `montrs agent check` to see the dependency chain.".to_string(),
+            ],
+            BootError::BudgetExceeded { .. } => vec![
+                "Run `montrs serve -v` to print the boot waterfall.".to_string(),
+                "Defer expensive plate setup (warm-up queries, cache fills) until after startup.".to_string(),
+                "Raise `budget_ms` or set `on_exceed = \"warn\"` if the slower startup is expected.".to_string(),
+            ],
+            BootError::SpecManifest { .. } => vec![
+                "Run `montrs spec --fingerprint` and deploy the `montrs.fingerprint.json` it writes.".to_string(),
+                "Unset `MONTRS_SPEC_MANIFEST` to boot without the check.".to_string(),
+            ],
+            BootError::SpecMismatch { .. } => vec![
+                "Deploy the build the manifest was generated from.".to_string(),
+                "If the change is intended, rerun `montrs spec --fingerprint` and commit the manifest.".to_string(),
+            ],
+            BootError::Preset { .. } => vec![
+                "Run `montrs config resolve` to see the error and the effective settings.".to_string(),
+                "Check the field names under `[preset]` against the docs; unknown fields are rejected.".to_string(),
+            ],
+            BootError::Incompatible(e) => e.suggested_fixes(),
+        }
+    }
+
+    fn subsystem(&self) -> &'static str {
+        "boot"
+    }
+}
+
+/// What a boot phase did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BootPhaseKind {
+    /// Environment and secrets resolution.
+    Env,
+    /// A plate's `init`.
+    Plate,
+    /// Route registration.
+    Router,
+    /// Anything measured by the application itself.
+    Custom,
+}
+
+/// One timed step of the boot. Offsets are relative to the start of the trace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BootPhase {
+    pub kind: BootPhaseKind,
+    pub name: String,
+    pub start_us: u64,
+    pub duration_us: u64,
+}
+
+/// Timings of a single application boot.
+///
+/// Start the trace before resolving the environment to include it:
+///
+/// ```rust,ignore
+/// let mut trace = BootTrace::new();
+/// let env = trace.measure(BootPhaseKind::Env, "env", || AppEnv::from_env())?;
+/// let trace = AppSpec::new(config, env).with_plate(..).boot_with(trace).await?;
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootTrace {
+    #[serde(skip, default = "Instant::now")]
+    started: Instant,
+    pub phases: Vec<BootPhase>,
+    pub total_us: u64,
+    /// Plate names grouped by dependency level; plates in a level start together.
+    #[serde(default)]
+    pub plate_levels: Vec<Vec<String>>,
+    /// Summed plate init time divided by the wall time of the init stage.
+    /// `1.0` means plates ran one after another.
+    #[serde(default)]
+    pub parallelism: f64,
+}
+
+impl Default for BootTrace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BootTrace {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            phases: Vec::new(),
+            total_us: 0,
+            plate_levels: Vec::new(),
+            parallelism: 0.0,
+        }
+    }
+
+    /// Records a phase that began at `started` and ends now.
+    pub fn record(&mut self, kind: BootPhaseKind, name: impl Into<String>, started: Instant) {
+        self.record_span(kind, name, started, Instant::now());
+    }
+
+    /// Records a phase that ran from `started` to `finished`.
+    pub fn record_span(&mut self, kind: BootPhaseKind, name: impl Into<String>, started: Instant, finished: Instant) {
+        self.phases.push(BootPhase {
+            kind,
+            name: name.into(),
+            start_us: started.saturating_duration_since(self.started).as_micros() as u64,
+            duration_us: finished.saturating_duration_since(started).as_micros() as u64,
+        });
+    }
+
+    /// Runs `f` as a phase of the boot.
+    pub fn measure<T>(&mut self, kind: BootPhaseKind, name: impl Into<String>, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let value = f();
+        self.record(kind, name, started);
+        value
+    }
+
+    /// Stops the clock. Called by `AppSpec::boot` once the router is built.
+    pub fn finish(&mut self) {
+        self.total_us = self.started.elapsed().as_micros() as u64;
+    }
+
+    pub fn total_ms(&self) -> f64 {
+        self.total_us as f64 / 1000.0
+    }
+
+    /// The longest phase, if any.
+    pub fn slowest(&self) -> Option<&BootPhase> {
+        self.phases.iter().max_by_key(|p| p.duration_us)
+    }
+
+    /// A text waterfall with one bar per phase, scaled to the total boot time.
+    pub fn waterfall(&self) -> String {
+        const WIDTH: u64 = 40;
+        let total = self.total_us.max(self.phases.iter().map(|p| p.start_us + p.duration_us).max().unwrap_or(0)).max(1);
+        let name_width = self.phases.iter().map(|p| p.name.len()).max().unwrap_or(0).max(4);
+        let mut out = format!("boot {:.1}ms\n", self.total_ms());
+        for phase in &self.phases {
+            let offset = (phase.start_us * WIDTH / total).min(WIDTH - 1);
+            let len = (phase.duration_us * WIDTH / total).clamp(1, WIDTH - offset);
+            let bar = format!(
+                "{}{}{}",
+                ".".repeat(offset as usize),
+                "#".repeat(len as usize),
+                ".".repeat((WIDTH - offset - len) as usize)
+            );
+            let _ = writeln!(
+                out,
+                "  {:<width$} [{}] {:>8.1}ms",
+                phase.name,
+                bar,
+                phase.duration_us as f64 / 1000.0,
+                width = name_width
+            );
+        }
+        if !self.plate_levels.is_empty() {
+            let _ = writeln!(
+                out,
+                "  {} plates in {} levels, parallelism {:.1}x",
+                self.plate_levels.iter().map(Vec::len).sum::<usize>(),
+                self.plate_levels.len(),
+                self.parallelism
+            );
+        }
+        out
+    }
+
+    /// Writes the trace as JSON, creating parent directories.
+    pub fn write_to(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
+    }
+
+    /// Prints, saves and budget-checks the trace as configured by the CLI.
+    pub(crate) fn report(&self) -> Result<(), BootError> {
+        if std::env::var_os(BOOT_VERBOSE_VAR).is_some() {
+            eprint!("{}", self.waterfall());
+        }
+        if let Some(path) = std::env::var_os(BOOT_TRACE_VAR)
+            && let Err(e) = self.write_to(Path::new(&path))
+        {
+            tracing::warn!(error = %e, "failed to write boot trace");
+        }
+        match BootBudget::from_env() {
+            Some(budget) => budget.check(self),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Groups plates into levels so that each plate comes after all of its
+/// dependencies. Plates within a level do not depend on each other and keep
+/// their registration order. Returns indexes into `plates`.
+pub fn plate_levels(plates: &[(&'static str, Vec<&'static str>)]) -> Result<Vec<Vec<usize>>, BootError> {
+    for (name, dependencies) in plates {
+        if let Some(missing) = dependencies.iter().find(|d| !plates.iter().any(|(n, _)| n == *d)) {
+            return Err(BootError::MissingDependency {
+                plate: name.to_string(),
+                dependency: missing.to_string(),
+            });
+        }
+    }
+
+    let mut level_of: Vec<Option<usize>> = vec![None; plates.len()];
+    let mut levels: Vec<Vec<usize>> = Vec::new();
+    while level_of.iter().any(Option::is_none) {
+        let ready: Vec<usize> = (0..plates.len())
+            .filter(|&i| level_of[i].is_none())
+            .filter(|&i| {
+                plates[i].1.iter().all(|dep| {
+                    plates
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, (name, _))| name == dep)
+                        .all(|(j, _)| level_of[j].is_some())
+                })
+            })
+            .collect();
+        if ready.is_empty() {
+            return Err(BootError::DependencyCycle {
+                plates: (0..plates.len())
+                    .filter(|&i| level_of[i].is_none())
+                    .map(|i| plates[i].0.to_string())
+                    .collect(),
+            });
+        }
+        for &i in &ready {
+            level_of[i] = Some(levels.len());
+        }
+        levels.push(ready);
+    }
+    Ok(levels)
+}
+
+/// The plate concurrency limit passed down by the CLI, if any.
+pub fn concurrency_from_env() -> Option<usize> {
+    std::env::var(BOOT_CONCURRENCY_VAR).ok()?.parse().ok().filter(|n| *n > 0)
+}
+
+/// What to do when the boot exceeds its budget.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetAction {
+    #[default]
+    Warn,
+    Fail,
+}
+
+/// A maximum startup time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BootBudget {
+    pub max_ms: u64,
+    #[serde(default)]
+    pub action: BudgetAction,
+}
+
+impl BootBudget {
+    pub fn new(max_ms: u64, action: BudgetAction) -> Self {
+        Self { max_ms, action }
+    }
+
+    /// The budget passed down by the CLI from `[boot]` in `montrs.toml`.
+    pub fn from_env() -> Option<Self> {
+        let max_ms = std::env::var(BOOT_BUDGET_VAR).ok()?.parse().ok()?;
+        let action = match std::env::var(BOOT_BUDGET_ACTION_VAR).as_deref() {
+            Ok("fail") => BudgetAction::Fail,
+            _ => BudgetAction::Warn,
+        };
+        Some(Self { max_ms, action })
+    }
+
+    /// Logs a warning or returns an error when `trace` is over budget.
+    pub fn check(&self, trace: &BootTrace) -> Result<(), BootError> {
+        if trace.total_us <= self.max_ms * 1000 {
+            return Ok(());
+        }
+        let error = BootError::BudgetExceeded {
+            total_ms: trace.total_ms(),
+            budget_ms: self.max_ms,
+            slowest: trace
+                .slowest()
+                .map(|p| format!("'{}' ({:.1}ms)", p.name, p.duration_us as f64 / 1000.0))
+                .unwrap_or_else(|| "unknown".to_string()),
+        };
+        match self.action {
+            BudgetAction::Warn => {
+                tracing::warn!("{}", error);
+                Ok(())
+            }
+            BudgetAction::Fail => Err(error),
+        }
+    }
+}
+
+/// Shuts `plates` down in reverse boot order, or in reverse registration
+/// order when their dependencies do not resolve. Every plate is shut down;
+/// the first failure is returned.
+pub(crate) async fn shutdown<C: AppConfig>(plates: &[Box<dyn Plate<C>>]) -> Result<(), BootError> {
+    let graph: Vec<_> = plates.iter().map(|p| (p.name(), p.dependencies())).collect();
+    let order: Vec<usize> = match plate_levels(&graph) {
+        Ok(levels) => levels.into_iter().flatten().collect(),
+        Err(_) => (0..plates.len()).collect(),
+    };
+    let mut first_error = None;
+    for i in order.into_iter().rev() {
+        if let Err(e) = plates[i].shutdown().await {
+            tracing::error!(plate = plates[i].name(), error = %e, "plate failed to shut down");
+            let error = BootError::PlateShutdown { plate: plates[i].name().to_string(), reason: e.to_string() };
+            first_error.get_or_insert(error);
+        }
+    }
+    first_error.map_or(Ok(()), Err)
+}
@@ -0,0 +1,210 @@
+//! montrs-core/src/capture.rs: Recorded requests for reproducing bugs.
+//!
+//! `montrs serve --capture` writes each request the dev server receives to
+//! `.agent/captures` as a [`Capture`]: method, path, a subset of the headers
+//! and the body. `montrs replay` sends captures to a running server again, and
+//! `TestClient::replay` runs them through the app's router in a test.
+//!
+//! Captures are sanitized before they are written, so they can be attached
+//! to an issue: only the headers in [`CAPTURED_HEADERS`] are kept, which
+//! leaves out cookies and `Authorization`, and the values of query params,
+//! form fields and JSON keys whose names look secret become [`REDACTED`].
+
+use crate::wasi::WasiRequest;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Headers a capture keeps. The others are dropped.
+pub const CAPTURED_HEADERS: &[&str] = &[
+    "accept",
+    "accept-language",
+    "accept-version",
+    "content-type",
+    "origin",
+    "referer",
+    "user-agent",
+    "x-montrs-version",
+    "x-request-id",
+];
+
+/// Replaces the value of fields that look secret.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Name fragments, compared without case, `-` or `_`, that make a field secret.
+const SECRET_NAMES: &[&str] = &[
+    "password", "passwd", "secret", "token", "apikey", "accesskey", "privatekey", "authorization", "cookie",
+    "session", "cardnumber", "cvc", "cvv",
+];
+
+/// A sanitized request, as written by `montrs serve --capture`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Capture {
+    /// Upper-case method, e.g. `POST`.
+    pub method: String,
+    /// Path and query, e.g. `/todos/42?expand=tags`.
+    pub path_with_query: String,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    /// The body as text. Bodies that are not UTF-8 are left out.
+    #[serde(default)]
+    pub body: String,
+    /// Size of a body that was left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub omitted_bytes: Option<usize>,
+    /// Status of the original response, when it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    pub captured_at: DateTime<Utc>,
+}
+
+impl Capture {
+    /// Captures `request`, sanitized.
+    pub fn from_request(request: &WasiRequest) -> Self {
+        let headers: Vec<(String, String)> = request
+            .headers
+            .iter()
+            .filter(|(name, _)| CAPTURED_HEADERS.iter().any(|kept| name.eq_ignore_ascii_case(kept)))
+            .map(|(name, value)| (name.to_ascii_lowercase(), value.clone()))
+            .collect();
+        let path_with_query = match request.query() {
+            Some(query) => format!("{}?{}", request.path(), redact_pairs(query)),
+            None => request.path_with_query.clone(),
+        };
+        let (body, omitted_bytes) = match std::str::from_utf8(&request.body) {
+            Ok(text) => (redact_body(request.header("content-type"), text), None),
+            Err(_) => (String::new(), Some(request.body.len())),
+        };
+        Self {
+            method: request.method.to_ascii_uppercase(),
+            path_with_query,
+            headers,
+            body,
+            omitted_bytes,
+            status: None,
+            captured_at: Utc::now(),
+        }
+    }
+
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// The request to send again.
+    pub fn to_request(&self) -> WasiRequest {
+        WasiRequest {
+            method: self.method.clone(),
+            path_with_query: self.path_with_query.clone(),
+            headers: self.headers.clone(),
+            body: self.body.clone().into_bytes(),
+        }
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| anyhow::anyhow!("invalid capture {}: {}", path.display(), e))
+    }
+
+    /// Reads every `*.json` capture in `dir`, oldest first.
+    pub fn read_dir(dir: impl AsRef<Path>) -> anyhow::Result<Vec<(PathBuf, Self)>> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir.as_ref())?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("json"))
+            .collect();
+        // File names start with the capture time.
+        paths.sort();
+        paths.into_iter().map(|path| Self::read(&path).map(|capture| (path, capture))).collect()
+    }
+
+    /// Writes the capture to a new file in `dir`, named after its time,
+    /// method and path, e.g. `20261017T093000123Z-POST-todos-42.json`.
+    pub fn write_to(&self, dir: impl AsRef<Path>) -> anyhow::Result<PathBuf> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        let slug: String = self
+            .path_with_query
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect::<Vec<_>>()
+            .join("-")
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .take(60)
+            .collect();
+        let stem = format!(
+            "{}-{}-{}",
+            self.captured_at.format("%Y%m%dT%H%M%S%3fZ"),
+            self.method,
+            if slug.is_empty() { "root" } else { slug.as_str() }
+        );
+        let mut path = dir.join(format!("{}.json", stem));
+        let mut n = 1;
+        while path.exists() {
+            n += 1;
+            path = dir.join(format!("{}-{}.json", stem, n));
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(path)
+    }
+}
+
+fn is_secret(name: &str) -> bool {
+    let name: String = name.chars().filter(|c| *c != '-' && *c != '_').collect::<String>().to_ascii_lowercase();
+    SECRET_NAMES.iter().any(|secret| name.contains(secret))
+}
+
+/// Redacts the values of secret `name=value` pairs in a query or form body,
+/// percent-encoding [`REDACTED`] so the result is still a valid URL.
+fn redact_pairs(pairs: &str) -> String {
+    pairs
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if is_secret(name) => format!("{}=%5BREDACTED%5D", name),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn redact_body(content_type: Option<&str>, body: &str) -> String {
+    let content_type = content_type.unwrap_or_default().to_ascii_lowercase();
+    if content_type.starts_with("application/x-www-form-urlencoded") {
+        return redact_pairs(body);
+    }
+    // Bodies without secrets are kept byte for byte.
+    let Ok(mut value) = serde_json::from_str::<Value>(body) else {
+        return body.to_string();
+    };
+    if redact_json(&mut value) { serde_json::to_string(&value).unwrap_or_default() } else { body.to_string() }
+}
+
+/// Redacts secret fields at any depth. Returns whether there were any.
+fn redact_json(value: &mut Value) -> bool {
+    let mut found = false;
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                if is_secret(name) {
+                    *field = Value::String(REDACTED.to_string());
+                    found = true;
+                } else {
+                    found |= redact_json(field);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                found |= redact_json(item);
+            }
+        }
+        _ => {}
+    }
+    found
+}
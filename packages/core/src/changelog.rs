@@ -0,0 +1,289 @@
+//! montrs-core/src/changelog.rs: Release notes from two exported specs.
+//! `SpecDiff::between` compares the `AppSpecExport` of a previous release with
+//! the current one and classifies every difference a client or operator would
+//! notice: routes added or removed, params whose type or requiredness changed,
+//! action bodies that accept other content types, new permission requirements,
+//! deprecations, plates and montrs crate versions. `montrs changelog` renders
+//! the result as a Markdown section and writes it as JSON for release tooling.
+//!
+//! Descriptions and other annotations are not compared; they do not change
+//! what clients can call.
+
+use crate::authz::Permission;
+use crate::deprecation::Deprecation;
+use crate::param::ParamSpec;
+use crate::router::RouteMetadata;
+use crate::AppSpecExport;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as _};
+use std::path::{Path, PathBuf};
+
+/// Where `AppSpec::boot` writes its `AppSpecExport` before exiting instead of
+/// serving (set by `montrs changelog`).
+pub const SPEC_EXPORT_OUT_VAR: &str = "MONTRS_SPEC_EXPORT_OUT";
+/// The exported spec committed with a release, relative to the project root.
+pub const SPEC_EXPORT_FILE: &str = "montrs.spec.json";
+
+/// The export path in `MONTRS_SPEC_EXPORT_OUT`, if set.
+pub fn export_out_from_env() -> Option<PathBuf> {
+    std::env::var_os(SPEC_EXPORT_OUT_VAR).map(PathBuf::from)
+}
+
+/// The changelog section a change is listed under, most severe first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Section {
+    /// Existing clients may stop working.
+    Breaking,
+    Added,
+    Changed,
+    Deprecated,
+    Removed,
+}
+
+impl Section {
+    pub const ALL: [Section; 5] =
+        [Section::Breaking, Section::Added, Section::Changed, Section::Deprecated, Section::Removed];
+
+    fn heading(self) -> &'static str {
+        match self {
+            Section::Breaking => "⚠️ Breaking",
+            Section::Added => "Added",
+            Section::Changed => "Changed",
+            Section::Deprecated => "Deprecated",
+            Section::Removed => "Removed",
+        }
+    }
+}
+
+/// What a change is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Subject {
+    Route,
+    Param,
+    ActionBody,
+    Access,
+    Plate,
+    Crate,
+    Target,
+}
+
+/// One difference between the two specs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpecChange {
+    pub section: Section,
+    pub subject: Subject,
+    /// The route path, plate or crate name the change belongs to.
+    pub name: String,
+    pub description: String,
+}
+
+/// Every change from one exported spec to the next.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpecDiff {
+    /// Label of the earlier spec, usually its tag.
+    pub from: String,
+    /// Label of the later spec, such as the version being released.
+    pub to: String,
+    pub breaking: bool,
+    pub changes: Vec<SpecChange>,
+}
+
+impl SpecDiff {
+    /// Compares `before` with `after`. Changes are grouped by section, then
+    /// ordered by name.
+    pub fn between(from: impl Into<String>, before: &AppSpecExport, to: impl Into<String>, after: &AppSpecExport) -> Self {
+        let mut changes = Vec::new();
+        let mut push = |section, subject, name: &str, description: String| {
+            changes.push(SpecChange { section, subject, name: name.to_string(), description });
+        };
+
+        if before.target != after.target {
+            push(Section::Changed, Subject::Target, "target", format!("target changed from {} to {}", before.target, after.target));
+        }
+
+        let old_routes: BTreeMap<_, _> = before.router.routes.iter().collect();
+        let new_routes: BTreeMap<_, _> = after.router.routes.iter().collect();
+        for (path, route) in &new_routes {
+            match old_routes.get(path) {
+                None => push(Section::Added, Subject::Route, path, "route added".to_string()),
+                Some(old) => diff_route(old, route, &mut push),
+            }
+        }
+        for path in old_routes.keys().filter(|path| !new_routes.contains_key(*path)) {
+            push(Section::Breaking, Subject::Route, path, "route removed".to_string());
+        }
+
+        for plate in &after.plates {
+            if !before.plates.iter().any(|p| p.name == plate.name) {
+                push(Section::Added, Subject::Plate, &plate.name, format!("plate added: {}", plate.description));
+            }
+        }
+        for plate in &before.plates {
+            if !after.plates.iter().any(|p| p.name == plate.name) {
+                push(Section::Removed, Subject::Plate, &plate.name, "plate removed".to_string());
+            }
+        }
+
+        for krate in &after.crates {
+            match before.crates.iter().find(|c| c.name == krate.name) {
+                Some(old) if old.version != krate.version => push(
+                    Section::Changed,
+                    Subject::Crate,
+                    &krate.name,
+                    format!("{} {} -> {}", krate.name, old.version, krate.version),
+                ),
+                Some(_) => {}
+                None => push(Section::Added, Subject::Crate, &krate.name, format!("{} {} added", krate.name, krate.version)),
+            }
+        }
+
+        changes.sort_by(|a, b| (a.section, &a.name).cmp(&(b.section, &b.name)));
+        Self {
+            from: from.into(),
+            to: to.into(),
+            breaking: changes.iter().any(|c| c.section == Section::Breaking),
+            changes,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// The changes as a Markdown section headed `## <to>`, with one
+    /// subsection per non-empty [`Section`].
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("## {}\n\nChanges since {}.\n", self.to, self.from);
+        if self.changes.is_empty() {
+            out.push_str("\nNo changes to routes, plates or crates.\n");
+        }
+        for section in Section::ALL {
+            let changes: Vec<_> = self.changes.iter().filter(|c| c.section == section).collect();
+            if changes.is_empty() {
+                continue;
+            }
+            let _ = writeln!(out, "\n### {}\n", section.heading());
+            for change in changes {
+                let _ = match change.subject {
+                    Subject::Route | Subject::Param | Subject::ActionBody | Subject::Access => {
+                        writeln!(out, "- `{}`: {}", change.name, change.description)
+                    }
+                    _ => writeln!(out, "- {}", change.description),
+                };
+            }
+        }
+        out
+    }
+}
+
+impl fmt::Display for SpecDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_markdown())
+    }
+}
+
+fn diff_route(before: &RouteMetadata, after: &RouteMetadata, push: &mut impl FnMut(Section, Subject, &str, String)) {
+    let path = after.path.as_str();
+    for param in &after.params {
+        match before.params.iter().find(|p| p.name == param.name) {
+            None if param.required => push(Section::Breaking, Subject::Param, path, format!("new required param `{}`", param.name)),
+            None => push(Section::Added, Subject::Param, path, format!("new optional param `{}`", param.name)),
+            Some(old) => diff_param(path, old, param, push),
+        }
+    }
+    for param in before.params.iter().filter(|p| !after.params.iter().any(|n| n.name == p.name)) {
+        push(Section::Removed, Subject::Param, path, format!("param `{}` removed", param.name));
+    }
+
+    let (old_types, new_types) = (before.action_body.content_types(), after.action_body.content_types());
+    let dropped: Vec<_> = old_types.iter().filter(|t| !new_types.contains(t)).cloned().collect();
+    let added: Vec<_> = new_types.iter().filter(|t| !old_types.contains(t)).cloned().collect();
+    if !dropped.is_empty() {
+        push(Section::Breaking, Subject::ActionBody, path, format!("action no longer accepts {}", dropped.join(", ")));
+    }
+    if !added.is_empty() {
+        push(Section::Added, Subject::ActionBody, path, format!("action accepts {}", added.join(", ")));
+    }
+
+    let (old_access, new_access) = (Permission::from_meta(&before.meta), Permission::from_meta(&after.meta));
+    let required: Vec<_> = new_access.iter().filter(|p| !old_access.contains(p)).map(|p| format!("`{}`", p)).collect();
+    let lifted: Vec<_> = old_access.iter().filter(|p| !new_access.contains(p)).map(|p| format!("`{}`", p)).collect();
+    if !required.is_empty() {
+        push(Section::Breaking, Subject::Access, path, format!("now requires {}", required.join(", ")));
+    }
+    if !lifted.is_empty() {
+        push(Section::Changed, Subject::Access, path, format!("no longer requires {}", lifted.join(", ")));
+    }
+
+    let added: Vec<_> = after.guards.iter().filter(|g| !before.guards.contains(g)).map(|g| format!("`{}`", g)).collect();
+    let removed: Vec<_> = before.guards.iter().filter(|g| !after.guards.contains(g)).map(|g| format!("`{}`", g)).collect();
+    if !added.is_empty() {
+        push(Section::Breaking, Subject::Access, path, format!("now guarded by {}", added.join(", ")));
+    }
+    if !removed.is_empty() {
+        push(Section::Changed, Subject::Access, path, format!("no longer guarded by {}", removed.join(", ")));
+    }
+
+    match (&before.parent, &after.parent) {
+        (None, Some(parent)) => push(Section::Changed, Subject::Route, path, format!("now nested in `{}`", parent)),
+        (Some(parent), None) => push(Section::Changed, Subject::Route, path, format!("no longer nested in `{}`", parent)),
+        (Some(old), Some(new)) if old != new => push(Section::Changed, Subject::Route, path, format!("moved from `{}` to `{}`", old, new)),
+        _ => {}
+    }
+
+    if Deprecation::from_meta(&before.meta).is_none()
+        && let Some(deprecation) = Deprecation::from_meta(&after.meta)
+    {
+        let mut description = "deprecated".to_string();
+        if let Some(replacement) = &deprecation.replacement {
+            let _ = write!(description, "; use `{}` instead", replacement);
+        }
+        if let Some(sunset) = &deprecation.sunset {
+            let _ = write!(description, "; removed after {}", sunset);
+        }
+        push(Section::Deprecated, Subject::Route, path, description);
+    }
+}
+
+fn diff_param(path: &str, before: &ParamSpec, after: &ParamSpec, push: &mut impl FnMut(Section, Subject, &str, String)) {
+    let type_of = |p: &ParamSpec| match &p.schema.format {
+        Some(format) => format!("{} ({})", p.schema.kind, format),
+        None => p.schema.kind.clone(),
+    };
+    if (&before.schema.kind, &before.schema.format) != (&after.schema.kind, &after.schema.format) {
+        push(
+            Section::Breaking,
+            Subject::Param,
+            path,
+            format!("param `{}` changed from {} to {}", after.name, type_of(before), type_of(after)),
+        );
+    }
+    if before.schema.pattern != after.schema.pattern && after.schema.pattern.is_some() {
+        push(Section::Breaking, Subject::Param, path, format!("param `{}` must match a new pattern", after.name));
+    }
+    match (before.required, after.required) {
+        (false, true) => push(Section::Breaking, Subject::Param, path, format!("param `{}` is now required", after.name)),
+        (true, false) => push(Section::Changed, Subject::Param, path, format!("param `{}` is now optional", after.name)),
+        _ => {}
+    }
+}
+
+impl AppSpecExport {
+    /// Writes the export as pretty JSON, creating parent directories.
+    pub fn write_to(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json + "\n")
+    }
+
+    /// Reads an export written by [`AppSpecExport::write_to`].
+    pub fn read_from(path: &Path) -> std::io::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(std::io::Error::other)
+    }
+}
@@ -0,0 +1,215 @@
+//! montrs-core/src/compat.rs: Version compatibility between the montrs crates.
+//! Every montrs crate exposes a `CRATE_VERSION` built with [`crate_version!`]:
+//! its name, its version and the oldest `montrs-core` it works with. The
+//! crates are released together, so unless a crate says otherwise that is the
+//! core of its own version. `AppSpec::boot` checks the crates passed to
+//! `AppSpec::with_crates`, and the CLI that started the app, against the core
+//! it was built with; `montrs doctor` checks the versions Cargo resolved for
+//! the workspace. Either way a mismatch is a [`CompatError`] naming each crate
+//! and the version it has to move to.
+
+use crate::AgentError;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+
+/// The CLI version, passed to the app by `montrs serve` and `montrs watch`.
+pub const CLI_VERSION_VAR: &str = "MONTRS_CLI_VERSION";
+
+/// This build of `montrs-core`.
+pub const CORE: CrateVersion = crate::crate_version!();
+
+/// The version of a montrs crate and the oldest core it supports.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrateVersion {
+    pub name: Cow<'static, str>,
+    pub version: Cow<'static, str>,
+    /// Any core on the same release line, from this version on, is compatible.
+    pub min_core: Cow<'static, str>,
+}
+
+/// The calling crate's [`CrateVersion`], from its `Cargo.toml`.
+///
+/// ```rust,ignore
+/// pub const CRATE_VERSION: CrateVersion = montrs_core::crate_version!();
+/// // A crate that still works with an older core:
+/// pub const CRATE_VERSION: CrateVersion = montrs_core::crate_version!(min_core = "0.1.0");
+/// ```
+#[macro_export]
+macro_rules! crate_version {
+    () => {
+        $crate::compat::CrateVersion::lockstep(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+    };
+    (min_core = $min_core:literal) => {
+        $crate::compat::CrateVersion {
+            name: ::std::borrow::Cow::Borrowed(env!("CARGO_PKG_NAME")),
+            version: ::std::borrow::Cow::Borrowed(env!("CARGO_PKG_VERSION")),
+            min_core: ::std::borrow::Cow::Borrowed($min_core),
+        }
+    };
+}
+
+impl CrateVersion {
+    /// A crate released together with the core of the same version.
+    pub const fn lockstep(name: &'static str, version: &'static str) -> Self {
+        Self { name: Cow::Borrowed(name), version: Cow::Borrowed(version), min_core: Cow::Borrowed(version) }
+    }
+
+    /// A crate found at run time, such as a resolved dependency; it needs the
+    /// core of its own version.
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        let version = version.into();
+        Self { name: Cow::Owned(name.into()), min_core: Cow::Owned(version.clone()), version: Cow::Owned(version) }
+    }
+
+    /// The CLI that started the app, from `MONTRS_CLI_VERSION`.
+    pub fn cli_from_env() -> Option<Self> {
+        let version = std::env::var(CLI_VERSION_VAR).ok()?;
+        Some(Self::new("montrs-cli", version.trim()))
+    }
+
+    /// Why `core` cannot be used with this crate, if it cannot.
+    pub fn check(&self, core: &str) -> Option<Mismatch> {
+        let (min, actual) = (Version::parse(&self.min_core)?, Version::parse(core)?);
+        let fix = if actual < min {
+            Bump::Core { to: min.to_string() }
+        } else if actual.line() != min.line() {
+            Bump::Crate { to: actual.line() }
+        } else {
+            return None;
+        };
+        Some(Mismatch {
+            name: self.name.to_string(),
+            version: self.version.to_string(),
+            min_core: self.min_core.to_string(),
+            fix,
+        })
+    }
+}
+
+impl fmt::Display for CrateVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.name, self.version)
+    }
+}
+
+/// Checks every crate in `crates` against the core version `core`.
+pub fn check(core: &str, crates: &[CrateVersion]) -> Result<(), CompatError> {
+    let mismatches: Vec<Mismatch> = crates.iter().filter_map(|c| c.check(core)).collect();
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+    Err(CompatError { core: core.to_string(), mismatches })
+}
+
+/// A crate that does not work with the core in use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub name: String,
+    pub version: String,
+    pub min_core: String,
+    pub fix: Bump,
+}
+
+/// The upgrade that resolves a [`Mismatch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bump {
+    /// The core is older than the crate needs.
+    Core { to: String },
+    /// The crate is from an older release line than the core; `to` is the
+    /// line to move it to, such as `0.2`.
+    Crate { to: String },
+}
+
+impl Mismatch {
+    /// The fix, naming the crate and version.
+    pub fn suggestion(&self) -> String {
+        match &self.fix {
+            Bump::Core { to } => {
+                format!("Bump montrs-core to {} or later, as {} {} requires.", to, self.name, self.version)
+            }
+            Bump::Crate { to } if self.name == "montrs-cli" => {
+                format!("Upgrade the montrs CLI to {}.x with `montrs upgrade`.", to)
+            }
+            Bump::Crate { to } => format!("Bump {} from {} to {}.x.", self.name, self.version, to),
+        }
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} (needs core ^{})", self.name, self.version, self.min_core)
+    }
+}
+
+/// montrs crates that do not work with the core they are used with.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("montrs-core {core} is incompatible with {}", join(mismatches))]
+pub struct CompatError {
+    pub core: String,
+    pub mismatches: Vec<Mismatch>,
+}
+
+fn join(mismatches: &[Mismatch]) -> String {
+    mismatches.iter().map(Mismatch::to_string).collect::<Vec<_>>().join(", ")
+}
+
+impl AgentError for CompatError {
+    fn error_code(&self) -> &'static str {
+        "COMPAT_MISMATCH"
+    }
+
+    fn explanation(&self) -> String {
+        format!(
+            "The montrs crates are released together, and each one works with montrs-core from its own version up to \
+             the next breaking release. The app uses montrs-core {}, which does not satisfy {}.",
+            self.core,
+            join(&self.mismatches)
+        )
+    }
+
+    fn suggested_fixes(&self) -> Vec<String> {
+        let mut fixes: Vec<String> = self.mismatches.iter().map(Mismatch::suggestion).collect();
+        fixes.push("Keep every montrs-* dependency on the same version, then run `cargo update`.".to_string());
+        fixes
+    }
+
+    fn subsystem(&self) -> &'static str {
+        "compat"
+    }
+}
+
+/// `major.minor.patch`; pre-release and build suffixes are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Version {
+    major: u64,
+    minor: u64,
+    patch: u64,
+}
+
+impl Version {
+    fn parse(s: &str) -> Option<Self> {
+        let core = s.trim().split(['-', '+']).next()?;
+        let mut parts = core.split('.').map(|p| p.parse::<u64>());
+        let major = parts.next()?.ok()?;
+        let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+        let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+        Some(Self { major, minor, patch })
+    }
+
+    /// The part of the version a breaking release changes, as in Cargo's
+    /// caret requirements: `1` for 1.4.2, `0.4` for 0.4.2, `0.0.3` for 0.0.3.
+    fn line(&self) -> String {
+        match (self.major, self.minor) {
+            (0, 0) => self.to_string(),
+            (0, minor) => format!("0.{}", minor),
+            (major, _) => major.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
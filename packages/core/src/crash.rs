@@ -0,0 +1,374 @@
+//! montrs-core/src/crash.rs: Panic capture and crash reporting.
+//! [`CrashReporter::install`] replaces the panic hook with one that records a
+//! [`PanicReport`] (message, location, backtrace and a correlation ID) and
+//! hands it to its sinks: a JSON-lines log, callbacks such as an agent error
+//! recorder, and a Slack- or Sentry-compatible webhook. [`catch`] keeps a
+//! panicking loader or action from taking its worker down; the router turns
+//! the panic into a 500 that carries the correlation ID.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{BufRead, Write};
+use std::panic::{AssertUnwindSafe, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::Poll;
+
+/// Set by `montrs serve` to the file panics are appended to.
+pub const CRASH_LOG_VAR: &str = "MONTRS_CRASH_LOG";
+/// Where `montrs serve` asks the app to log panics, relative to the project root.
+pub const CRASH_LOG_FILE: &str = "target/montrs/crashes.jsonl";
+/// Webhook notified of every panic.
+pub const CRASH_WEBHOOK_VAR: &str = "MONTRS_CRASH_WEBHOOK";
+/// `slack` (default) or `sentry`.
+pub const CRASH_WEBHOOK_FORMAT_VAR: &str = "MONTRS_CRASH_WEBHOOK_FORMAT";
+
+/// How long a panic that will end the process waits for the webhook.
+#[cfg(feature = "crash-webhook")]
+const WEBHOOK_WAIT: std::time::Duration = std::time::Duration::from_secs(3);
+
+thread_local! {
+    /// The [`catch`] scope being polled on this thread.
+    static SCOPE: RefCell<Option<String>> = const { RefCell::new(None) };
+    /// The report of the last panic caught on this thread.
+    static LAST: RefCell<Option<PanicReport>> = const { RefCell::new(None) };
+}
+
+/// A receiver of panic reports. Runs inside the panic hook, so it must not panic.
+pub type CrashSink = Arc<dyn Fn(&PanicReport) + Send + Sync>;
+
+/// One captured panic.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PanicReport {
+    /// Correlation ID, shown to the client and written to every sink.
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub message: String,
+    /// Source file of the panic, if known.
+    pub file: Option<String>,
+    pub line: u32,
+    pub column: u32,
+    pub thread: Option<String>,
+    /// The route or task that was running, e.g. "route /users/:id (load)".
+    pub scope: Option<String>,
+    pub backtrace: String,
+}
+
+impl PanicReport {
+    fn capture(info: &PanicHookInfo<'_>) -> Self {
+        let location = info.location();
+        Self {
+            id: correlation_id(),
+            timestamp: Utc::now(),
+            message: payload_message(info.payload()),
+            file: location.map(|l| l.file().to_string()),
+            line: location.map_or(0, |l| l.line()),
+            column: location.map_or(0, |l| l.column()),
+            thread: std::thread::current().name().map(String::from),
+            scope: SCOPE.with(|s| s.borrow().clone()),
+            backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+        }
+    }
+
+    /// A report for a panic no hook saw, built from its payload alone.
+    fn from_payload(scope: &str, payload: &(dyn std::any::Any + Send)) -> Self {
+        Self {
+            id: correlation_id(),
+            timestamp: Utc::now(),
+            message: payload_message(payload),
+            file: None,
+            line: 0,
+            column: 0,
+            thread: std::thread::current().name().map(String::from),
+            scope: Some(scope.to_string()),
+            backtrace: String::new(),
+        }
+    }
+
+    /// `file:line:column`, or "unknown location".
+    pub fn location(&self) -> String {
+        match &self.file {
+            Some(file) => format!("{}:{}:{}", file, self.line, self.column),
+            None => "unknown location".to_string(),
+        }
+    }
+
+    /// Appends the report to a JSON-lines log, creating the file if needed.
+    pub fn append_to(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        let line = serde_json::to_string(self).map_err(std::io::Error::other)?;
+        writeln!(file, "{}", line)
+    }
+
+    /// Reads every report in a JSON-lines log, skipping lines that do not parse.
+    pub fn read_log(path: &Path) -> std::io::Result<Vec<PanicReport>> {
+        let file = std::fs::File::open(path)?;
+        Ok(std::io::BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect())
+    }
+}
+
+fn payload_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+pub(crate) fn correlation_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.write_u32(std::process::id());
+    format!("{:016x}", hasher.finish())
+}
+
+/// The payload a webhook receives.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    /// `{"text": ...}`, as Slack and compatible chat webhooks expect.
+    #[default]
+    Slack,
+    /// A Sentry event, for a store endpoint such as
+    /// `https://o0.ingest.sentry.io/api/1/store/?sentry_key=...`.
+    Sentry,
+}
+
+impl std::str::FromStr for WebhookFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "slack" => Ok(Self::Slack),
+            "sentry" => Ok(Self::Sentry),
+            other => Err(format!("unknown webhook format `{}` (expected slack or sentry)", other)),
+        }
+    }
+}
+
+impl WebhookFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Slack => "slack",
+            Self::Sentry => "sentry",
+        }
+    }
+
+    /// The JSON body sent for `report`.
+    pub fn payload(self, report: &PanicReport) -> serde_json::Value {
+        match self {
+            Self::Slack => serde_json::json!({
+                "text": format!(
+                    ":rotating_light: panic in {}: {}\n`{}` · correlation id `{}`",
+                    report.scope.as_deref().unwrap_or("the server"),
+                    report.message,
+                    report.location(),
+                    report.id
+                ),
+            }),
+            Self::Sentry => serde_json::json!({
+                "event_id": format!("{:0>32}", report.id),
+                "timestamp": report.timestamp.to_rfc3339(),
+                "platform": "rust",
+                "level": "fatal",
+                "logger": "montrs",
+                "transaction": report.scope,
+                "message": report.message,
+                "tags": { "correlation_id": report.id },
+                "exception": { "values": [{
+                    "type": "panic",
+                    "value": report.message,
+                    "stacktrace": { "frames": [{
+                        "filename": report.file,
+                        "lineno": report.line,
+                        "colno": report.column,
+                    }] },
+                }] },
+                "extra": { "backtrace": report.backtrace, "thread": report.thread },
+            }),
+        }
+    }
+}
+
+/// Where panics are reported. Build one, then [`install`](Self::install) it.
+///
+/// ```rust,ignore
+/// CrashReporter::new()
+///     .with_log("target/montrs/crashes.jsonl")
+///     .with_sink(|report| metrics::increment("panics", &report.scope))
+///     .with_webhook("https://hooks.slack.com/services/...", WebhookFormat::Slack)
+///     .install();
+/// ```
+#[derive(Clone, Default)]
+pub struct CrashReporter {
+    log: Option<PathBuf>,
+    sinks: Vec<CrashSink>,
+    #[cfg(feature = "crash-webhook")]
+    webhook: Option<(String, WebhookFormat)>,
+}
+
+impl CrashReporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reports to the log and webhook that `montrs serve` or the environment name.
+    pub fn from_env() -> Self {
+        let mut reporter = Self::new();
+        if let Some(path) = std::env::var_os(CRASH_LOG_VAR) {
+            reporter = reporter.with_log(path);
+        }
+        if let Ok(url) = std::env::var(CRASH_WEBHOOK_VAR)
+            && !url.is_empty()
+        {
+            let format = match std::env::var(CRASH_WEBHOOK_FORMAT_VAR) {
+                Ok(format) => format.parse().unwrap_or_else(|e| {
+                    tracing::warn!("{}; using slack", e);
+                    WebhookFormat::Slack
+                }),
+                Err(_) => WebhookFormat::Slack,
+            };
+            #[cfg(feature = "crash-webhook")]
+            {
+                reporter = reporter.with_webhook(url, format);
+            }
+            #[cfg(not(feature = "crash-webhook"))]
+            tracing::warn!(url, ?format, "crash webhook ignored: montrs-core was built without `crash-webhook`");
+        }
+        reporter
+    }
+
+    /// Appends every report to a JSON-lines file.
+    pub fn with_log(mut self, path: impl Into<PathBuf>) -> Self {
+        self.log = Some(path.into());
+        self
+    }
+
+    /// Calls `sink` with every report.
+    pub fn with_sink(mut self, sink: impl Fn(&PanicReport) + Send + Sync + 'static) -> Self {
+        self.sinks.push(Arc::new(sink));
+        self
+    }
+
+    /// POSTs every report to `url`. Delivery happens in the background, except
+    /// for panics that will end the process, which wait up to 3 seconds.
+    #[cfg(feature = "crash-webhook")]
+    pub fn with_webhook(mut self, url: impl Into<String>, format: WebhookFormat) -> Self {
+        self.webhook = Some((url.into(), format));
+        self
+    }
+
+    /// Replaces the process's panic hook with one that reports to this reporter.
+    ///
+    /// The hook logs the panic through `tracing` instead of printing it to stderr.
+    pub fn install(self) {
+        let reporter = Arc::new(self);
+        std::panic::set_hook(Box::new(move |info| {
+            let report = PanicReport::capture(info);
+            // Outside `catch`, nothing stops the unwind from ending the thread.
+            let caught = report.scope.is_some();
+            reporter.report(&report, !caught);
+            if caught {
+                LAST.with(|last| *last.borrow_mut() = Some(report));
+            }
+        }));
+    }
+
+    /// Sends `report` to every sink.
+    pub fn report(&self, report: &PanicReport, fatal: bool) {
+        tracing::error!(
+            correlation_id = %report.id,
+            location = %report.location(),
+            scope = report.scope.as_deref().unwrap_or("none"),
+            "panic: {}\n{}",
+            report.message,
+            report.backtrace
+        );
+        if let Some(path) = &self.log
+            && let Err(e) = report.append_to(path)
+        {
+            tracing::warn!(error = %e, path = %path.display(), "panic report not logged");
+        }
+        for sink in &self.sinks {
+            sink(report);
+        }
+        #[cfg(feature = "crash-webhook")]
+        if let Some((url, format)) = &self.webhook {
+            let delivered = notify(url.clone(), format.payload(report));
+            if fatal {
+                let _ = delivered.recv_timeout(WEBHOOK_WAIT);
+            }
+        }
+        #[cfg(not(feature = "crash-webhook"))]
+        let _ = fatal;
+    }
+}
+
+/// POSTs `payload` from a new thread; the receiver fires once it is done.
+#[cfg(feature = "crash-webhook")]
+fn notify(url: String, payload: serde_json::Value) -> std::sync::mpsc::Receiver<()> {
+    let (done, delivered) = std::sync::mpsc::channel();
+    let _ = std::thread::Builder::new().name("montrs-crash-webhook".to_string()).spawn(move || {
+        let sent = reqwest::blocking::Client::builder()
+            .timeout(WEBHOOK_WAIT)
+            .build()
+            .and_then(|client| client.post(&url).json(&payload).send())
+            .and_then(|res| res.error_for_status());
+        if let Err(e) = sent {
+            tracing::warn!(error = %e, "crash webhook not notified");
+        }
+        let _ = done.send(());
+    });
+    delivered
+}
+
+/// Installs [`CrashReporter::from_env`] when `montrs serve` or the environment
+/// configured a crash log or webhook.
+pub fn install_from_env() -> bool {
+    let configured = std::env::var_os(CRASH_LOG_VAR).is_some()
+        || std::env::var_os(CRASH_WEBHOOK_VAR).is_some_and(|url| !url.is_empty());
+    if configured {
+        CrashReporter::from_env().install();
+    }
+    configured
+}
+
+/// Runs `future`, turning a panic inside it into the [`PanicReport`] the hook
+/// recorded, attributed to `scope`. Without an installed reporter the report
+/// has no location or backtrace.
+pub async fn catch<F: Future>(scope: &str, future: F) -> Result<F::Output, PanicReport> {
+    let mut future = std::pin::pin!(future);
+    let outcome = std::future::poll_fn(|cx| {
+        let previous = SCOPE.with(|s| s.replace(Some(scope.to_string())));
+        let polled = std::panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx)));
+        SCOPE.with(|s| *s.borrow_mut() = previous);
+        match polled {
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    })
+    .await;
+    outcome.map_err(|payload| {
+        LAST.with(|last| last.borrow_mut().take()).unwrap_or_else(|| {
+            let report = PanicReport::from_payload(scope, &*payload);
+            tracing::error!(correlation_id = %report.id, scope, "panic: {}", report.message);
+            report
+        })
+    })
+}
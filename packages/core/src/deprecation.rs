@@ -0,0 +1,123 @@
+//! montrs-core/src/deprecation.rs: Deprecation notices for routes and plates.
+//! A deprecation is stored as route or plate metadata (see [`crate::meta`]), so it
+//! travels through `RouterSpec`, the agent snapshot and OpenAPI without extra fields.
+//! This file converts it to and from metadata and renders the `Deprecation`,
+//! `Sunset` (RFC 8594) and `Link: rel="successor-version"` response headers.
+
+use crate::meta;
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Marks a route or plate as deprecated.
+///
+/// ```rust,ignore
+/// router
+///     .register(UsersV1Route)
+///     .deprecated(Deprecation::new().sunset("2026-06-30").replacement("/api/v2/users"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deprecation {
+    /// Date the deprecation took effect, as `YYYY-MM-DD`.
+    pub since: Option<String>,
+    /// Date after which the route may be removed, as `YYYY-MM-DD`.
+    pub sunset: Option<String>,
+    /// Path or URL of the route or plate that replaces this one.
+    pub replacement: Option<String>,
+    /// Free-form explanation shown in reports.
+    pub note: Option<String>,
+}
+
+impl Deprecation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn since(mut self, date: impl Into<String>) -> Self {
+        self.since = Some(date.into());
+        self
+    }
+
+    pub fn sunset(mut self, date: impl Into<String>) -> Self {
+        self.sunset = Some(date.into());
+        self
+    }
+
+    pub fn replacement(mut self, target: impl Into<String>) -> Self {
+        self.replacement = Some(target.into());
+        self
+    }
+
+    pub fn note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+
+    /// Writes this deprecation into a metadata map, marking it `stability = "deprecated"`.
+    pub fn apply_to(&self, metadata: &mut HashMap<String, String>) {
+        metadata.insert(meta::STABILITY.to_string(), meta::DEPRECATED.to_string());
+        for (key, value) in [
+            (meta::DEPRECATED_SINCE, &self.since),
+            (meta::SUNSET, &self.sunset),
+            (meta::REPLACEMENT, &self.replacement),
+            (meta::DEPRECATION_NOTE, &self.note),
+        ] {
+            match value {
+                Some(value) => metadata.insert(key.to_string(), value.clone()),
+                None => metadata.remove(key),
+            };
+        }
+    }
+
+    /// Reads a deprecation back from metadata. Returns `None` unless the
+    /// stability is `deprecated`.
+    pub fn from_meta(metadata: &HashMap<String, String>) -> Option<Self> {
+        if metadata.get(meta::STABILITY).map(String::as_str) != Some(meta::DEPRECATED) {
+            return None;
+        }
+        let get = |key: &str| metadata.get(key).cloned();
+        Some(Self {
+            since: get(meta::DEPRECATED_SINCE),
+            sunset: get(meta::SUNSET),
+            replacement: get(meta::REPLACEMENT),
+            note: get(meta::DEPRECATION_NOTE),
+        })
+    }
+
+    /// Returns `true` once the sunset date has passed.
+    pub fn is_past_sunset(&self) -> bool {
+        self.sunset
+            .as_deref()
+            .and_then(parse_date)
+            .is_some_and(|sunset| Utc::now().date_naive() > sunset)
+    }
+
+    /// Response headers announcing the deprecation to clients.
+    pub fn headers(&self) -> Vec<(String, String)> {
+        let deprecation = match self.since.as_deref().and_then(parse_date) {
+            Some(date) => format!("@{}", date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().timestamp()),
+            None => "true".to_string(),
+        };
+        let mut headers = vec![("Deprecation".to_string(), deprecation)];
+        if let Some(sunset) = self.sunset.as_deref().and_then(parse_date) {
+            let value = sunset.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+            headers.push(("Sunset".to_string(), value.format("%a, %d %b %Y %H:%M:%S GMT").to_string()));
+        }
+        if let Some(replacement) = &self.replacement {
+            headers.push(("Link".to_string(), format!("<{}>; rel=\"successor-version\"", replacement)));
+        }
+        headers
+    }
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// How often a deprecated route was hit since the router was created.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeprecationUsage {
+    pub path: String,
+    pub deprecation: Deprecation,
+    pub hits: u64,
+}
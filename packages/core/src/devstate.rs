@@ -0,0 +1,54 @@
+//! montrs-core/src/devstate.rs: What the running app tells the dev server.
+//! Under `montrs serve`, `AppSpec::boot` writes the routes it registered and
+//! the feature flags it was given to `target/montrs/dev-state.json`. The CLI
+//! reads the file to fill in the `/_montrs` dashboard, which lives outside the
+//! app process.
+
+use crate::features::FeatureFlag;
+use crate::router::RouterSpec;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Where `AppSpec::boot` writes the dev state (set by the CLI).
+pub const DEV_STATE_VAR: &str = "MONTRS_DEV_STATE";
+/// Default location of the dev state, relative to the project root.
+pub const DEV_STATE_FILE: &str = "target/montrs/dev-state.json";
+
+/// A snapshot of the app taken at the end of its boot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevState {
+    /// When the app finished booting.
+    pub booted_at: DateTime<Utc>,
+    /// How long the boot took, in milliseconds.
+    pub boot_ms: f64,
+    pub router: RouterSpec,
+    /// Feature flags, sorted by name.
+    pub flags: Vec<FeatureFlag>,
+}
+
+impl DevState {
+    /// Writes the state as JSON, creating parent directories.
+    pub fn write_to(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
+    }
+
+    /// Reads a state written by [`DevState::write_to`].
+    pub fn read_from(path: &Path) -> std::io::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(std::io::Error::other)
+    }
+
+    /// Writes the state to the path in `MONTRS_DEV_STATE`, if it is set.
+    pub(crate) fn publish(&self) {
+        if let Some(path) = std::env::var_os(DEV_STATE_VAR)
+            && let Err(e) = self.write_to(Path::new(&path))
+        {
+            tracing::warn!(error = %e, "failed to write dev state");
+        }
+    }
+}
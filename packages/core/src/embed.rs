@@ -0,0 +1,224 @@
+//! montrs-core/src/embed.rs: Running a MontRS app inside an existing server.
+//!
+//! During an incremental adoption, the MontRS app does not have to own the
+//! process. [`Embedded`] holds a booted router with its config and env, and
+//! answers requests the same way a WASI component does (see [`crate::wasi`]).
+//! With the `axum` feature it becomes an `axum::Router` to `nest` under a
+//! prefix; with the `actix` feature, an `actix_web::Scope`. The host's
+//! runtime drives the loaders and actions, its middleware wraps them, and its
+//! body size limits apply. State the host wants to share, such as a database
+//! pool, goes into the app's config before booting. Requests opening a
+//! [subscription](crate::subscription) are answered with a streamed body.
+
+use crate::env::EnvConfig;
+use crate::prerender;
+use crate::response::StreamingResponse;
+use crate::subscription;
+use crate::router::Router;
+use crate::wasi::{self, WasiRequest, WasiResponse};
+use crate::{AppConfig, AppSpec, BootError, RouteError};
+use std::sync::Arc;
+
+/// A router, config and env shared by every request of the host server.
+/// Cloning is cheap.
+pub struct Embedded<C: AppConfig> {
+    inner: Arc<Inner<C>>,
+}
+
+struct Inner<C: AppConfig> {
+    router: Router<C>,
+    config: C,
+    env: C::Env,
+}
+
+impl<C: AppConfig> Clone for Embedded<C> {
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone() }
+    }
+}
+
+impl<C: AppConfig> Embedded<C> {
+    /// Embeds a router whose routes are already registered.
+    pub fn new(router: Router<C>, config: C, env: C::Env) -> Self {
+        Self { inner: Arc::new(Inner { router, config, env }) }
+    }
+
+    pub fn router(&self) -> &Router<C> {
+        &self.inner.router
+    }
+
+    pub fn config(&self) -> &C {
+        &self.inner.config
+    }
+
+    pub fn env(&self) -> &C::Env {
+        &self.inner.env
+    }
+
+    /// Answers `request`, whose path is relative to where the app is mounted.
+    /// `GET` and `HEAD` run loaders, `POST`, `PUT`, `PATCH` and `DELETE`
+    /// actions, as in [`wasi::handle`].
+    ///
+    /// Cached pages the request found stale are rendered again on a task of
+    /// the host's Tokio runtime, after the response is ready.
+    pub async fn handle(&self, request: WasiRequest) -> WasiResponse {
+        let env: &dyn EnvConfig = &self.inner.env;
+        let response = wasi::respond(&self.inner.router, &self.inner.config, env, request).await;
+        if self.inner.router.page_cache().is_some_and(|cache| cache.has_stale()) {
+            let app = self.clone();
+            let refresh = async move { prerender::refresh_stale(&app.inner.router, &app.inner.config, &app.inner.env).await };
+            match tokio::runtime::Handle::try_current() {
+                Ok(runtime) => drop(runtime.spawn(refresh)),
+                Err(_) => refresh.await,
+            }
+        }
+        response
+    }
+
+    /// Whether `request` opens a subscription, to answer with
+    /// [`stream`](Self::stream) instead of [`handle`](Self::handle).
+    pub fn is_subscription(&self, request: &WasiRequest) -> bool {
+        subscription::is_subscription(&self.inner.router, request)
+    }
+
+    /// Answers a request opening a subscription with its event stream, see
+    /// [`subscription::respond`].
+    pub async fn stream(&self, request: WasiRequest) -> StreamingResponse {
+        subscription::respond(&self.inner.router, &self.inner.config, &self.inner.env, request).await
+    }
+
+    /// Renders the pages at `paths`, e.g. every `/todos/:id` worth indexing,
+    /// into the router's [`PageCache`](crate::PageCache) ahead of their first
+    /// request. Stops at the first loader that fails.
+    pub async fn prerender<'p>(&self, paths: impl IntoIterator<Item = &'p str>) -> Result<(), RouteError> {
+        for path in paths {
+            prerender::render(&self.inner.router, &self.inner.config, &self.inner.env, path).await?;
+        }
+        Ok(())
+    }
+
+    /// An `axum::Router` answering every path below where it is nested.
+    ///
+    /// ```rust,ignore
+    /// let app = spec.embed().await?;
+    /// let service = axum::Router::new()
+    ///     .route("/health", get(health))
+    ///     .nest("/app", app.into_axum_router())
+    ///     .layer(TraceLayer::new_for_http());
+    /// ```
+    #[cfg(feature = "axum")]
+    pub fn into_axum_router<S: Clone + Send + Sync + 'static>(self) -> axum::Router<S> {
+        axum::Router::new().fallback(serve_axum::<C>).with_state(self)
+    }
+
+    /// An `actix_web::Scope` at `path` answering every path below it. Build it
+    /// in the `HttpServer::new` factory, from a clone of the `Embedded`.
+    ///
+    /// ```rust,ignore
+    /// let app = spec.embed().await?;
+    /// HttpServer::new(move || App::new().wrap(Logger::default()).service(app.actix_scope("/app")))
+    /// ```
+    #[cfg(feature = "actix")]
+    pub fn actix_scope(&self, path: &str) -> actix_web::Scope {
+        actix_web::web::scope(path)
+            .app_data(actix_web::web::Data::new(self.clone()))
+            .default_service(actix_web::web::to(serve_actix::<C>))
+    }
+}
+
+impl<C: AppConfig> AppSpec<C> {
+    /// Boots the app on the caller's runtime and keeps what serving needs.
+    pub async fn embed(mut self) -> Result<Embedded<C>, BootError> {
+        self.boot().await?;
+        Ok(Embedded::new(self.router, self.config, self.env))
+    }
+
+    /// Boots the app and returns it as an `axum::Router`. See
+    /// [`Embedded::into_axum_router`].
+    #[cfg(feature = "axum")]
+    pub async fn into_axum_router<S: Clone + Send + Sync + 'static>(self) -> Result<axum::Router<S>, BootError> {
+        Ok(self.embed().await?.into_axum_router())
+    }
+}
+
+#[cfg(feature = "axum")]
+impl<C: AppConfig> Router<C> {
+    /// This router, with the config and env its loaders and actions see, as
+    /// an `axum::Router`. See [`Embedded::into_axum_router`].
+    pub fn into_axum_router<S: Clone + Send + Sync + 'static>(self, config: C, env: C::Env) -> axum::Router<S> {
+        Embedded::new(self, config, env).into_axum_router()
+    }
+}
+
+#[cfg(feature = "axum")]
+async fn serve_axum<C: AppConfig>(
+    axum::extract::State(app): axum::extract::State<Embedded<C>>,
+    method: axum::http::Method,
+    uri: axum::http::Uri,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> axum::response::Response {
+    use axum::http::{HeaderName, HeaderValue, StatusCode};
+
+    let path = uri.path_and_query().map_or("/", |p| p.as_str());
+    let mut request = WasiRequest::new(method.as_str(), path).with_body(body.to_vec());
+    request.headers = header_pairs(headers.iter().map(|(name, value)| (name.as_str(), value.as_bytes())));
+
+    let (status, headers, body) = if app.is_subscription(&request) {
+        let response = app.stream(request).await;
+        (response.status, response.headers, axum::body::Body::from_stream(response.body))
+    } else {
+        let response = app.handle(request).await;
+        (response.status, response.headers, axum::body::Body::from(response.body))
+    };
+    let mut out = axum::response::Response::new(body);
+    *out.status_mut() = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    for (name, value) in headers {
+        if let (Ok(name), Ok(value)) = (HeaderName::try_from(name), HeaderValue::try_from(value)) {
+            out.headers_mut().append(name, value);
+        }
+    }
+    out
+}
+
+#[cfg(feature = "actix")]
+async fn serve_actix<C: AppConfig>(
+    app: actix_web::web::Data<Embedded<C>>,
+    request: actix_web::HttpRequest,
+    body: actix_web::web::Bytes,
+) -> actix_web::HttpResponse {
+    use actix_web::http::StatusCode;
+
+    // The part of the path below the scope.
+    let mut path = format!("/{}", request.match_info().unprocessed().trim_start_matches('/'));
+    if !request.query_string().is_empty() {
+        path = format!("{}?{}", path, request.query_string());
+    }
+    let mut incoming = WasiRequest::new(request.method().as_str(), path).with_body(body.to_vec());
+    incoming.headers = header_pairs(request.headers().iter().map(|(name, value)| (name.as_str(), value.as_bytes())));
+
+    if app.is_subscription(&incoming) {
+        use futures::StreamExt;
+
+        let response = app.stream(incoming).await;
+        let mut out = actix_web::HttpResponse::build(StatusCode::from_u16(response.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR));
+        for (name, value) in response.headers {
+            out.append_header((name, value));
+        }
+        return out.streaming(response.body.map(|chunk| chunk.map(actix_web::web::Bytes::from)));
+    }
+    let response = app.handle(incoming).await;
+    let mut out = actix_web::HttpResponse::build(StatusCode::from_u16(response.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR));
+    for (name, value) in response.headers {
+        out.append_header((name, value));
+    }
+    out.body(response.body)
+}
+
+/// Header names and values, skipping values that are not valid UTF-8.
+#[cfg(any(feature = "axum", feature = "actix"))]
+fn header_pairs<'h>(headers: impl Iterator<Item = (&'h str, &'h [u8])>) -> Vec<(String, String)> {
+    headers
+        .filter_map(|(name, value)| Some((name.to_string(), std::str::from_utf8(value).ok()?.to_string())))
+        .collect()
+}
@@ -100,3 +100,46 @@ impl EnvConfig for TypedEnv {
         std::env::var(key).map_err(|_| EnvError::MissingKey(key.to_string()))
     }
 }
+
+/// An ordered chain of providers. Lookups return the first provider's value,
+/// so earlier providers override later ones.
+///
+/// ```rust
+/// use montrs_core::{EnvChain, EnvConfig, TypedEnv};
+///
+/// let env = EnvChain::new().with(TypedEnv {});
+/// assert!(env.get_var("MONTRS_SURELY_UNSET_VAR").is_err());
+/// ```
+#[derive(Clone, Default)]
+pub struct EnvChain {
+    providers: Vec<std::sync::Arc<dyn EnvConfig>>,
+}
+
+impl EnvChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a provider with lower precedence than those already added.
+    pub fn with(mut self, provider: impl EnvConfig) -> Self {
+        self.providers.push(std::sync::Arc::new(provider));
+        self
+    }
+}
+
+impl EnvConfig for EnvChain {
+    fn get_var(&self, key: &str) -> Result<String, EnvError> {
+        self.providers
+            .iter()
+            .find_map(|p| p.get_var(key).ok())
+            .ok_or_else(|| EnvError::MissingKey(key.to_string()))
+    }
+
+    fn vars(&self) -> std::collections::HashMap<String, String> {
+        let mut vars = std::collections::HashMap::new();
+        for provider in self.providers.iter().rev() {
+            vars.extend(provider.vars());
+        }
+        vars
+    }
+}
@@ -0,0 +1,270 @@
+//! montrs-core/src/error_page.rs: Branded error views for loader and action failures.
+//! This file provides a themeable default error page, a registry of per-status
+//! overrides, and a fallback for Leptos' `<ErrorBoundary>` so that a failing
+//! loader renders a real page instead of a blank screen.
+
+use crate::router::RouteError;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Colors and branding used by the default error page.
+///
+/// Exposed to CSS as `--montrs-error-*` custom properties so stylesheets can
+/// restyle the page without replacing it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ErrorTheme {
+    pub brand: String,
+    pub accent: String,
+    pub background: String,
+    pub foreground: String,
+    pub logo: Option<String>,
+    pub home_href: String,
+    pub support_href: Option<String>,
+}
+
+impl Default for ErrorTheme {
+    fn default() -> Self {
+        Self {
+            brand: "MontRS".to_string(),
+            accent: "#2563eb".to_string(),
+            background: "#0f172a".to_string(),
+            foreground: "#e2e8f0".to_string(),
+            logo: None,
+            home_href: "/".to_string(),
+            support_href: None,
+        }
+    }
+}
+
+impl ErrorTheme {
+    pub fn with_brand(mut self, brand: impl Into<String>) -> Self {
+        self.brand = brand.into();
+        self
+    }
+
+    pub fn with_accent(mut self, color: impl Into<String>) -> Self {
+        self.accent = color.into();
+        self
+    }
+
+    pub fn with_colors(mut self, background: impl Into<String>, foreground: impl Into<String>) -> Self {
+        self.background = background.into();
+        self.foreground = foreground.into();
+        self
+    }
+
+    pub fn with_logo(mut self, src: impl Into<String>) -> Self {
+        self.logo = Some(src.into());
+        self
+    }
+
+    pub fn with_support(mut self, href: impl Into<String>) -> Self {
+        self.support_href = Some(href.into());
+        self
+    }
+
+    /// Inline `style` declaring the theme's CSS custom properties.
+    pub fn style(&self) -> String {
+        format!(
+            "--montrs-error-accent: {}; --montrs-error-bg: {}; --montrs-error-fg: {};",
+            self.accent, self.background, self.foreground
+        )
+    }
+}
+
+/// What went wrong, in a form an error page can display.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorInfo {
+    pub status: u16,
+    pub title: String,
+    pub message: String,
+}
+
+impl ErrorInfo {
+    pub fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            title: default_title(status).to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn not_found() -> Self {
+        Self::new(404, "The page you are looking for does not exist or has moved.")
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(500, message)
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Maps a router failure to the matching HTTP status.
+    pub fn from_route_error(err: &RouteError) -> Self {
+        let status = err.status();
+        match err {
+            RouteError::NotFound => Self::not_found(),
+            // Internal details are not shown to visitors.
+            RouteError::InternalError(_) => Self::new(status, "Something went wrong on our end."),
+            RouteError::Panicked(id) => Self::new(status, format!("Something went wrong on our end. Reference: {}", id)),
+            other => Self::new(status, other.to_string()),
+        }
+    }
+
+    /// Builds an `ErrorInfo` from any error, recognizing `RouteError`.
+    pub fn from_error(err: &(dyn std::error::Error + 'static)) -> Self {
+        match err.downcast_ref::<RouteError>() {
+            Some(route_err) => Self::from_route_error(route_err),
+            None => Self::internal("Something went wrong on our end."),
+        }
+    }
+}
+
+fn default_title(status: u16) -> &'static str {
+    match status {
+        400 => "Bad request",
+        401 => "Sign in required",
+        403 => "Access denied",
+        404 => "Page not found",
+        415 => "Unsupported content",
+        422 => "Invalid input",
+        429 => "Too many requests",
+        502..=504 => "Service unavailable",
+        _ => "Something went wrong",
+    }
+}
+
+/// A custom error view. Receives the error and the active theme.
+pub type ErrorRenderer = Arc<dyn Fn(ErrorInfo, &ErrorTheme) -> AnyView + Send + Sync>;
+
+/// The app's error views: a theme, per-status overrides, and an optional catch-all.
+///
+/// ```rust,ignore
+/// let pages = ErrorPages::new()
+///     .with_theme(ErrorTheme::default().with_brand("Acme").with_accent("#e11d48"))
+///     .with_page(404, |info, _theme| view! { <NotFound message=info.message/> }.into_any());
+///
+/// AppSpec::new(config, env).with_error_pages(pages).mount(App);
+/// ```
+#[derive(Clone, Default)]
+pub struct ErrorPages {
+    theme: ErrorTheme,
+    pages: HashMap<u16, ErrorRenderer>,
+    fallback: Option<ErrorRenderer>,
+}
+
+impl ErrorPages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_theme(mut self, theme: ErrorTheme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Replaces the view for a single status code.
+    pub fn with_page<F>(mut self, status: u16, render: F) -> Self
+    where
+        F: Fn(ErrorInfo, &ErrorTheme) -> AnyView + Send + Sync + 'static,
+    {
+        self.pages.insert(status, Arc::new(render));
+        self
+    }
+
+    /// Replaces the view for every status without a dedicated page.
+    pub fn with_fallback<F>(mut self, render: F) -> Self
+    where
+        F: Fn(ErrorInfo, &ErrorTheme) -> AnyView + Send + Sync + 'static,
+    {
+        self.fallback = Some(Arc::new(render));
+        self
+    }
+
+    pub fn theme(&self) -> &ErrorTheme {
+        &self.theme
+    }
+
+    /// Renders the most specific view registered for `info.status`.
+    pub fn render(&self, info: ErrorInfo) -> AnyView {
+        match self.pages.get(&info.status).or(self.fallback.as_ref()) {
+            Some(render) => render(info, &self.theme),
+            None => default_error_view(info, &self.theme),
+        }
+    }
+}
+
+/// Returns the `ErrorPages` provided by `AppSpec::mount`, or the defaults.
+pub fn use_error_pages() -> ErrorPages {
+    use_context::<ErrorPages>().unwrap_or_default()
+}
+
+/// The built-in branded error page.
+pub fn default_error_view(info: ErrorInfo, theme: &ErrorTheme) -> AnyView {
+    let logo = theme
+        .logo
+        .clone()
+        .map(|src| view! { <img class="montrs-error__logo" src=src alt=theme.brand.clone()/> });
+    let support = theme.support_href.clone().map(|href| {
+        view! { <a class="montrs-error__link" href=href>"Contact support"</a> }
+    });
+
+    view! {
+        <main class="montrs-error" role="alert" data-status=info.status style=theme.style()>
+            <style>{ERROR_PAGE_CSS}</style>
+            <div class="montrs-error__card">
+                {logo}
+                <p class="montrs-error__brand">{theme.brand.clone()}</p>
+                <p class="montrs-error__status">{info.status}</p>
+                <h1 class="montrs-error__title">{info.title}</h1>
+                <p class="montrs-error__message">{info.message}</p>
+                <nav class="montrs-error__actions">
+                    <a class="montrs-error__link montrs-error__link--primary" href=theme.home_href.clone()>"Go home"</a>
+                    {support}
+                </nav>
+            </div>
+        </main>
+    }
+    .into_any()
+}
+
+/// Fallback for `<ErrorBoundary>` that renders the app's error pages.
+///
+/// ```rust,ignore
+/// view! {
+///     <ErrorBoundary fallback=error_fallback>
+///         <PostList/>
+///     </ErrorBoundary>
+/// }
+/// ```
+pub fn error_fallback(errors: ArcRwSignal<Errors>) -> impl IntoView {
+    let pages = use_error_pages();
+    move || {
+        let info = errors.with(|errors| {
+            errors
+                .iter()
+                .next()
+                .map(|(_, err)| ErrorInfo::from_error(&**err))
+        });
+        pages.render(info.unwrap_or_else(|| ErrorInfo::internal("Something went wrong on our end.")))
+    }
+}
+
+const ERROR_PAGE_CSS: &str = "\
+.montrs-error{min-height:100vh;display:flex;align-items:center;justify-content:center;\
+background:var(--montrs-error-bg);color:var(--montrs-error-fg);font-family:system-ui,sans-serif;padding:2rem}\
+.montrs-error__card{max-width:32rem;text-align:center}\
+.montrs-error__logo{height:3rem;margin-bottom:1rem}\
+.montrs-error__brand{opacity:.7;letter-spacing:.05em;text-transform:uppercase;font-size:.8rem}\
+.montrs-error__status{font-size:4rem;font-weight:800;color:var(--montrs-error-accent);margin:.5rem 0}\
+.montrs-error__title{font-size:1.5rem;margin:0 0 .5rem}\
+.montrs-error__message{opacity:.8;margin-bottom:1.5rem}\
+.montrs-error__actions{display:flex;gap:1rem;justify-content:center}\
+.montrs-error__link{color:var(--montrs-error-fg);padding:.5rem 1rem;border-radius:.5rem;text-decoration:none}\
+.montrs-error__link--primary{background:var(--montrs-error-accent)}";
@@ -52,6 +52,23 @@ impl FeatureManager {
         }
     }
 
+    /// Adds a flag, replacing any flag with the same name.
+    pub fn add_flag(&mut self, flag: FeatureFlag) {
+        self.flags.insert(flag.name.clone(), flag);
+    }
+
+    /// Adds a segment, replacing any segment with the same id.
+    pub fn add_segment(&mut self, segment: Segment) {
+        self.segments.insert(segment.id.clone(), segment);
+    }
+
+    /// All flags, sorted by name.
+    pub fn flags(&self) -> Vec<&FeatureFlag> {
+        let mut flags: Vec<_> = self.flags.values().collect();
+        flags.sort_by(|a, b| a.name.cmp(&b.name));
+        flags
+    }
+
     /// Evaluates if a feature flag is enabled for a given user context.
     pub fn is_enabled(&self, flag_name: &str, user_ctx: &UserContext) -> bool {
         if let Some(flag) = self.flags.get(flag_name) {
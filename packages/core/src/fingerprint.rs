@@ -0,0 +1,157 @@
+//! montrs-core/src/fingerprint.rs: A checkable hash of the application blueprint.
+//! `AppSpec::fingerprint` reduces the parts of an `AppSpec` that define the
+//! application (config metadata, plates in registration order, routes and
+//! feature flags) to a stable hash. `montrs spec --fingerprint` commits it as
+//! `montrs.fingerprint.json`; CI compares against that file, and `boot`
+//! refuses to start when the deployed manifest disagrees with the running app.
+
+use crate::body::BodyFormat;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Path of the manifest `AppSpec::boot` checks the running app against.
+pub const SPEC_MANIFEST_VAR: &str = "MONTRS_SPEC_MANIFEST";
+/// Where `AppSpec::boot` writes the fingerprint before exiting instead of
+/// serving (set by `montrs spec --fingerprint`).
+pub const SPEC_FINGERPRINT_OUT_VAR: &str = "MONTRS_SPEC_FINGERPRINT_OUT";
+/// Default location of the committed manifest, relative to the project root.
+pub const SPEC_MANIFEST_FILE: &str = "montrs.fingerprint.json";
+
+/// The manifest path in `MONTRS_SPEC_MANIFEST`, if set.
+pub fn manifest_from_env() -> Option<PathBuf> {
+    std::env::var_os(SPEC_MANIFEST_VAR).map(PathBuf::from)
+}
+
+/// The inputs of the fingerprint and their hash. Maps are sorted so the same
+/// application always serializes, and hashes, the same way.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpecFingerprint {
+    /// 64-bit FNV-1a of the other fields as JSON, in hex.
+    pub hash: String,
+    /// `AppConfig::metadata`.
+    pub config: BTreeMap<String, String>,
+    /// Plates in registration order.
+    pub plates: Vec<PlateFingerprint>,
+    /// Routes by path.
+    pub routes: BTreeMap<String, RouteFingerprint>,
+    /// Feature flags by name, with whether they are on by default.
+    pub features: BTreeMap<String, bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlateFingerprint {
+    pub name: String,
+    pub dependencies: Vec<String>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteFingerprint {
+    /// The body format the action accepts.
+    pub action_body: BodyFormat,
+    /// Annotations attached at registration.
+    pub meta: BTreeMap<String, String>,
+    /// The names of the route's guards, in order. Left out when empty, so
+    /// apps without guards keep their fingerprint.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub guards: Vec<String>,
+    /// The layout the route is nested in. Left out when there is none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+}
+
+/// The fields that are hashed, in the order they are hashed.
+#[derive(Serialize)]
+struct Inputs<'a> {
+    config: &'a BTreeMap<String, String>,
+    plates: &'a [PlateFingerprint],
+    routes: &'a BTreeMap<String, RouteFingerprint>,
+    features: &'a BTreeMap<String, bool>,
+}
+
+impl SpecFingerprint {
+    /// Builds a fingerprint from its inputs and computes the hash.
+    pub fn new(
+        config: BTreeMap<String, String>,
+        plates: Vec<PlateFingerprint>,
+        routes: BTreeMap<String, RouteFingerprint>,
+        features: BTreeMap<String, bool>,
+    ) -> Self {
+        let inputs = Inputs { config: &config, plates: &plates, routes: &routes, features: &features };
+        let json = serde_json::to_string(&inputs).expect("fingerprint inputs serialize");
+        let hash = format!("{:016x}", fnv1a(json.as_bytes()));
+        Self { hash, config, plates, routes, features }
+    }
+
+    /// What changed from `expected` to `self`, one line per difference; empty
+    /// when the hashes match.
+    pub fn changes(&self, expected: &SpecFingerprint) -> Vec<String> {
+        if self.hash == expected.hash {
+            return Vec::new();
+        }
+        let mut changes = Vec::new();
+        diff_maps("config", &expected.config, &self.config, &mut changes);
+
+        let names = |plates: &[PlateFingerprint]| plates.iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+        let (before, after) = (names(&expected.plates), names(&self.plates));
+        if before != after {
+            changes.push(format!("plates: [{}] -> [{}]", before.join(", "), after.join(", ")));
+        } else {
+            for (old, new) in expected.plates.iter().zip(&self.plates) {
+                if old != new {
+                    changes.push(format!("plate `{}`: dependencies or metadata changed", new.name));
+                }
+            }
+        }
+
+        diff_maps("route", &expected.routes, &self.routes, &mut changes);
+        diff_maps("feature", &expected.features, &self.features, &mut changes);
+        if changes.is_empty() {
+            // The hash was computed by another version or edited by hand.
+            changes.push(format!("hash: {} -> {}", expected.hash, self.hash));
+        }
+        changes
+    }
+
+    /// Writes the fingerprint as pretty JSON, creating parent directories.
+    pub fn write_to(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json + "\n")
+    }
+
+    /// Reads a fingerprint written by [`SpecFingerprint::write_to`].
+    pub fn read_from(path: &Path) -> std::io::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(std::io::Error::other)
+    }
+}
+
+impl std::fmt::Display for SpecFingerprint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.hash)
+    }
+}
+
+/// Lists keys added to, removed from or changed between two maps.
+fn diff_maps<V: PartialEq>(label: &str, before: &BTreeMap<String, V>, after: &BTreeMap<String, V>, changes: &mut Vec<String>) {
+    for (key, value) in after {
+        match before.get(key) {
+            None => changes.push(format!("{} `{}` added", label, key)),
+            Some(old) if old != value => changes.push(format!("{} `{}` changed", label, key)),
+            Some(_) => {}
+        }
+    }
+    for key in before.keys().filter(|key| !after.contains_key(*key)) {
+        changes.push(format!("{} `{}` removed", label, key));
+    }
+}
+
+/// FNV-1a is fixed by its definition, unlike `DefaultHasher`, so fingerprints
+/// stay comparable across Rust versions.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+}
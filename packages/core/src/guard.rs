@@ -0,0 +1,117 @@
+//! montrs-core/src/guard.rs: Route guards.
+//! A [`RouteGuard`] looks at a request before the route's loader or action
+//! runs, typically at the session or a feature flag, and lets it through,
+//! denies it with a status, or sends the caller elsewhere, e.g. to sign in.
+//! Guards are attached to one route with
+//! [`RouteRegistration::guard`](crate::RouteRegistration::guard), or to every
+//! route a plate registers with [`Plate::guards`](crate::Plate::guards). Their
+//! names are listed under `guards` in the `RouterSpec`, so the agent snapshot,
+//! the fingerprint and `montrs changelog` see them.
+
+use crate::router::{RouteContext, RouteError};
+use crate::AppConfig;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// What a guard decided about a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuardOutcome {
+    Allow,
+    /// Answer with this status instead of running the route.
+    Deny(u16),
+    /// Send the caller to this path instead of running the route.
+    Redirect(String),
+}
+
+impl GuardOutcome {
+    /// `Deny(401)` and `Deny(404)` become `Unauthorized` and `NotFound`, other
+    /// statuses [`RouteError::Denied`]; `Redirect` becomes [`RouteError::Redirect`].
+    pub fn into_result(self) -> Result<(), RouteError> {
+        match self {
+            GuardOutcome::Allow => Ok(()),
+            GuardOutcome::Deny(401) => Err(RouteError::Unauthorized),
+            GuardOutcome::Deny(404) => Err(RouteError::NotFound),
+            GuardOutcome::Deny(status) => Err(RouteError::Denied(status)),
+            GuardOutcome::Redirect(path) => Err(RouteError::Redirect(path)),
+        }
+    }
+}
+
+/// Decides whether a request may reach a route.
+///
+/// ```rust,ignore
+/// struct SignedIn;
+///
+/// #[async_trait]
+/// impl RouteGuard<AppConfig> for SignedIn {
+///     fn name(&self) -> String {
+///         "signed_in".to_string()
+///     }
+///
+///     async fn check(&self, ctx: &RouteContext<'_, AppConfig>, _path: &str) -> GuardOutcome {
+///         match ctx.request().and_then(|r| r.header("cookie").map(sessions::find)) {
+///             Some(_) => GuardOutcome::Allow,
+///             None => GuardOutcome::Redirect("/login".to_string()),
+///         }
+///     }
+/// }
+///
+/// router.register(AccountRoute).guard(SignedIn);
+/// ```
+#[async_trait]
+pub trait RouteGuard<C: AppConfig>: Send + Sync + 'static {
+    /// Identifies the guard in the `RouterSpec`.
+    fn name(&self) -> String;
+
+    /// Decides about a request for the route registered at `path`.
+    async fn check(&self, ctx: &RouteContext<'_, C>, path: &str) -> GuardOutcome;
+}
+
+/// A guard from a name and a function, for checks that need no I/O.
+///
+/// ```rust,ignore
+/// let features = Arc::new(features);
+/// let beta = FnGuard::new("feature:beta", move |ctx: &RouteContext<'_, AppConfig>, _path: &str| {
+///     match features.is_enabled("beta", &user_context(ctx)) {
+///         true => GuardOutcome::Allow,
+///         false => GuardOutcome::Deny(404),
+///     }
+/// });
+/// ```
+pub struct FnGuard<F> {
+    name: String,
+    check: F,
+}
+
+impl<F> FnGuard<F> {
+    pub fn new(name: impl Into<String>, check: F) -> Self {
+        Self { name: name.into(), check }
+    }
+}
+
+#[async_trait]
+impl<C, F> RouteGuard<C> for FnGuard<F>
+where
+    C: AppConfig,
+    F: Fn(&RouteContext<'_, C>, &str) -> GuardOutcome + Send + Sync + 'static,
+{
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn check(&self, ctx: &RouteContext<'_, C>, path: &str) -> GuardOutcome {
+        (self.check)(ctx, path)
+    }
+}
+
+/// Runs `guards` in order; the first one that does not allow the request decides.
+pub(crate) async fn check<C: AppConfig>(guards: &[Arc<dyn RouteGuard<C>>], ctx: &RouteContext<'_, C>, path: &str) -> Result<(), RouteError> {
+    for guard in guards {
+        let outcome = guard.check(ctx, path).await;
+        if outcome != GuardOutcome::Allow {
+            tracing::debug!(route = path, guard = %guard.name(), outcome = ?outcome, "route guard stopped the request");
+            return outcome.into_result();
+        }
+    }
+    Ok(())
+}
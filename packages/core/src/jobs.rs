@@ -0,0 +1,483 @@
+//! montrs-core/src/jobs.rs: Delayed actions.
+//! [`RouteContext::defer`] schedules the action of a route to run later, e.g.
+//! a welcome email ten minutes after sign-up, and returns a [`JobHandle`].
+//! The job is kept in a [`JobStore`] until it is due; [`Jobs::work`] then runs
+//! it through [`Router::act`], so its input goes through the same decoding,
+//! `prepare_input` validation, permission checks and instrumentation as a
+//! request. [`JobsPlate`] registers a loader answering with the status of a
+//! job, which clients poll at [`JobHandle::status_path`], and an action that
+//! cancels it. `montrs_orm::jobs::JobTable`, behind the ORM's `jobs`
+//! feature, keeps the jobs in the application database.
+
+use crate::crash::correlation_id;
+use crate::env::EnvConfig;
+use crate::{AppConfig, Plate, PlateContext, Route, RouteAction, RouteContext, RouteError, RouteLoader, RouteParams, RouteView, Router};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+thread_local! {
+    /// The jobs of the router whose loader or action is being polled on this thread.
+    static CURRENT: RefCell<Option<Jobs>> = const { RefCell::new(None) };
+}
+
+/// Where a job is in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// Waiting for `run_at`, or for a retry.
+    Scheduled,
+    /// The action is running.
+    Running,
+    Succeeded,
+    /// The action failed and has no retries left.
+    Failed,
+    /// Cancelled before it ran.
+    Cancelled,
+}
+
+impl JobStatus {
+    /// Whether the job will not run again.
+    pub fn is_finished(self) -> bool {
+        matches!(self, JobStatus::Succeeded | JobStatus::Failed | JobStatus::Cancelled)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobStatus::Scheduled => "scheduled",
+            JobStatus::Running => "running",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<Self> {
+        [Self::Scheduled, Self::Running, Self::Succeeded, Self::Failed, Self::Cancelled]
+            .into_iter()
+            .find(|s| s.as_str() == status)
+    }
+}
+
+/// The persisted state of one delayed action.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobRecord {
+    pub id: String,
+    /// The route whose action runs, as passed to [`Router::act`].
+    pub route: String,
+    pub params: serde_json::Value,
+    pub input: serde_json::Value,
+    pub status: JobStatus,
+    /// When the job is due; moved forward by retries.
+    pub run_at: DateTime<Utc>,
+    /// Runs started so far.
+    pub attempts: u32,
+    /// The action's output once it succeeded.
+    pub output: Option<serde_json::Value>,
+    /// Why the last run failed.
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Returned when a job is scheduled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobHandle {
+    pub id: String,
+    pub run_at: DateTime<Utc>,
+    /// Where [`JobsPlate`] answers with the job's [`JobRecord`].
+    pub status_path: String,
+}
+
+/// Keeps job records. Saves overwrite the record with the same ID.
+#[async_trait]
+pub trait JobStore: Send + Sync + 'static {
+    async fn save(&self, record: &JobRecord) -> anyhow::Result<()>;
+
+    async fn get(&self, id: &str) -> anyhow::Result<Option<JobRecord>>;
+
+    /// Scheduled jobs with `run_at` at or before `now`, earliest first.
+    async fn due(&self, now: DateTime<Utc>, limit: usize) -> anyhow::Result<Vec<JobRecord>>;
+}
+
+#[async_trait]
+impl<T: JobStore> JobStore for Arc<T> {
+    async fn save(&self, record: &JobRecord) -> anyhow::Result<()> {
+        (**self).save(record).await
+    }
+
+    async fn get(&self, id: &str) -> anyhow::Result<Option<JobRecord>> {
+        (**self).get(id).await
+    }
+
+    async fn due(&self, now: DateTime<Utc>, limit: usize) -> anyhow::Result<Vec<JobRecord>> {
+        (**self).due(now, limit).await
+    }
+}
+
+/// A store that lives as long as the process, for tests and development.
+#[derive(Default)]
+pub struct MemoryJobStore {
+    records: Mutex<HashMap<String, JobRecord>>,
+}
+
+impl MemoryJobStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl JobStore for MemoryJobStore {
+    async fn save(&self, record: &JobRecord) -> anyhow::Result<()> {
+        self.records.lock().unwrap_or_else(|e| e.into_inner()).insert(record.id.clone(), record.clone());
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> anyhow::Result<Option<JobRecord>> {
+        Ok(self.records.lock().unwrap_or_else(|e| e.into_inner()).get(id).cloned())
+    }
+
+    async fn due(&self, now: DateTime<Utc>, limit: usize) -> anyhow::Result<Vec<JobRecord>> {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        let mut due: Vec<_> =
+            records.values().filter(|r| r.status == JobStatus::Scheduled && r.run_at <= now).cloned().collect();
+        due.sort_by_key(|r| r.run_at);
+        due.truncate(limit);
+        Ok(due)
+    }
+}
+
+/// Schedules delayed actions and runs them when they are due.
+///
+/// ```rust,ignore
+/// let jobs = Jobs::new(JobTable::new(db.clone())).with_retries(3, Duration::from_secs(30));
+/// let mut spec = AppSpec::new(config, env).with_plate(Box::new(JobsPlate::new(jobs.clone())));
+/// spec.boot().await?;
+///
+/// // One worker per store runs due jobs in the background.
+/// let spec = Arc::new(spec);
+/// let worker = spec.clone();
+/// tokio::spawn(async move { jobs.work(&worker.router, &worker.config, &worker.env).await });
+/// ```
+#[derive(Clone)]
+pub struct Jobs {
+    store: Arc<dyn JobStore>,
+    retries: u32,
+    retry_delay: Duration,
+    poll_interval: Duration,
+    status_prefix: String,
+}
+
+impl Jobs {
+    pub fn new(store: impl JobStore) -> Self {
+        Self {
+            store: Arc::new(store),
+            retries: 0,
+            retry_delay: Duration::from_secs(60),
+            poll_interval: Duration::from_secs(1),
+            status_prefix: "/jobs".to_string(),
+        }
+    }
+
+    /// Runs a job whose action failed with a server error (5xx) `times` more,
+    /// `delay` apart (default: no retries). Rejected input and missing
+    /// permissions fail the job at once.
+    pub fn with_retries(mut self, times: u32, delay: Duration) -> Self {
+        self.retries = times;
+        self.retry_delay = delay;
+        self
+    }
+
+    /// How often [`Jobs::work`] looks for due jobs (default: every second).
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Where [`JobsPlate`] mounts the status route (default: `/jobs`).
+    pub fn with_status_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.status_prefix = prefix.into();
+        self
+    }
+
+    pub fn store(&self) -> &Arc<dyn JobStore> {
+        &self.store
+    }
+
+    /// Schedules the action of `route` to run with `params` and `input` once
+    /// `delay` has passed.
+    pub async fn schedule(
+        &self,
+        route: impl Into<String>,
+        params: serde_json::Value,
+        input: serde_json::Value,
+        delay: Duration,
+    ) -> anyhow::Result<JobHandle> {
+        let now = Utc::now();
+        let record = JobRecord {
+            id: correlation_id(),
+            route: route.into(),
+            params,
+            input,
+            status: JobStatus::Scheduled,
+            run_at: now + chrono::Duration::from_std(delay)?,
+            attempts: 0,
+            output: None,
+            error: None,
+            created_at: now,
+            updated_at: now,
+        };
+        self.store.save(&record).await?;
+        tracing::info!(job = %record.id, route = %record.route, run_at = %record.run_at, "job scheduled");
+        Ok(JobHandle { status_path: self.status_path(&record.id), id: record.id, run_at: record.run_at })
+    }
+
+    /// The current record of job `id`.
+    pub async fn status(&self, id: &str) -> anyhow::Result<Option<JobRecord>> {
+        self.store.get(id).await
+    }
+
+    /// Cancels job `id` if it has not started, and returns its record.
+    /// Running and finished jobs are returned unchanged.
+    pub async fn cancel(&self, id: &str) -> anyhow::Result<Option<JobRecord>> {
+        let Some(mut record) = self.store.get(id).await? else {
+            return Ok(None);
+        };
+        if record.status == JobStatus::Scheduled {
+            record.status = JobStatus::Cancelled;
+            record.updated_at = Utc::now();
+            self.store.save(&record).await?;
+        }
+        Ok(Some(record))
+    }
+
+    /// Runs every due job through `router`, earliest first, and returns their
+    /// records. A job is saved as running before its action starts, so a
+    /// crash leaves it `Running` rather than running it twice.
+    pub async fn run_due<C: AppConfig>(&self, router: &Router<C>, config: &C, env: &dyn EnvConfig) -> anyhow::Result<Vec<JobRecord>> {
+        let mut ran = Vec::new();
+        for mut record in self.store.due(Utc::now(), 100).await? {
+            record.status = JobStatus::Running;
+            record.attempts += 1;
+            record.updated_at = Utc::now();
+            self.store.save(&record).await?;
+
+            let ctx = RouteContext { config, env };
+            match router.act(&record.route, ctx, record.params.clone(), record.input.clone()).await {
+                Ok(output) => {
+                    record.status = JobStatus::Succeeded;
+                    record.output = Some(output);
+                    record.error = None;
+                }
+                Err(e) if e.status() >= 500 && record.attempts <= self.retries => {
+                    tracing::warn!(job = %record.id, route = %record.route, attempt = record.attempts, error = %e, "job failed; retrying");
+                    record.status = JobStatus::Scheduled;
+                    record.run_at = Utc::now() + chrono::Duration::from_std(self.retry_delay)?;
+                    record.error = Some(e.to_string());
+                }
+                Err(e) => {
+                    tracing::error!(job = %record.id, route = %record.route, attempt = record.attempts, error = %e, "job failed");
+                    record.status = JobStatus::Failed;
+                    record.error = Some(e.to_string());
+                }
+            }
+            record.updated_at = Utc::now();
+            self.store.save(&record).await?;
+            ran.push(record);
+        }
+        Ok(ran)
+    }
+
+    /// Runs due jobs until the future is dropped, checking every poll
+    /// interval. Store errors are logged and retried on the next check.
+    pub async fn work<C: AppConfig>(&self, router: &Router<C>, config: &C, env: &dyn EnvConfig) {
+        loop {
+            if let Err(e) = self.run_due(router, config, env).await {
+                tracing::error!(error = %e, "failed to run due jobs");
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    fn status_path(&self, id: &str) -> String {
+        format!("{}/{}", self.status_prefix.trim_end_matches('/'), id)
+    }
+}
+
+/// Makes `jobs` available to [`RouteContext::defer`] while `future` is polled.
+pub(crate) async fn scoped<F: Future>(jobs: Option<&Jobs>, future: F) -> F::Output {
+    struct Restore(Option<Jobs>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            CURRENT.with(|current| *current.borrow_mut() = previous);
+        }
+    }
+    let mut future = std::pin::pin!(future);
+    std::future::poll_fn(|cx| {
+        let _restore = Restore(CURRENT.with(|current| current.replace(jobs.cloned())));
+        future.as_mut().poll(cx)
+    })
+    .await
+}
+
+impl<C: AppConfig> RouteContext<'_, C> {
+    /// Schedules the action of route `R` to run with `params` and `input`
+    /// once `delay` has passed. Needs a router with [`Jobs`], as installed by
+    /// [`JobsPlate`].
+    ///
+    /// ```rust,ignore
+    /// let handle = ctx.defer::<SendEmailRoute>(NoParams {}, email, Duration::from_secs(600)).await?;
+    /// ```
+    ///
+    /// The job runs without the request that scheduled it, so routes with
+    /// [permission requirements](crate::RouteRegistration::requires) only run
+    /// if the router's `Rbac` resolves a principal for background work.
+    pub async fn defer<R: Route<C>>(
+        &self,
+        params: R::Params,
+        input: <R::Action as RouteAction<R::Params, C>>::Input,
+        delay: Duration,
+    ) -> Result<JobHandle, RouteError> {
+        self.defer_at(R::path(), params, input, delay).await
+    }
+
+    /// [`defer`](Self::defer) for the route at `path`, such as one mounted
+    /// with [`Router::register_at`].
+    pub async fn defer_at(
+        &self,
+        path: &str,
+        params: impl Serialize,
+        input: impl Serialize,
+        delay: Duration,
+    ) -> Result<JobHandle, RouteError> {
+        let jobs = CURRENT.with(|current| current.borrow().clone()).ok_or_else(|| {
+            RouteError::InternalError("no job queue: add a JobsPlate to defer actions".to_string())
+        })?;
+        let params = serde_json::to_value(params).map_err(|e| RouteError::ValidationFailed(e.to_string()))?;
+        let input = serde_json::to_value(input).map_err(|e| RouteError::ValidationFailed(e.to_string()))?;
+        jobs.schedule(path, params, input, delay).await.map_err(|e| RouteError::InternalError(e.to_string()))
+    }
+}
+
+/// Installs [`Jobs`] in the router, so loaders and actions can
+/// [`defer`](RouteContext::defer) actions, and registers `<prefix>/:id`: its
+/// loader answers with the [`JobRecord`], its action cancels a job that has
+/// not started.
+pub struct JobsPlate {
+    jobs: Jobs,
+}
+
+impl JobsPlate {
+    pub fn new(jobs: Jobs) -> Self {
+        Self { jobs }
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> Plate<C> for JobsPlate {
+    fn name(&self) -> &'static str {
+        "jobs"
+    }
+
+    fn description(&self) -> &'static str {
+        "Delayed actions: scheduling, status polling and cancellation"
+    }
+
+    async fn init(&self, _ctx: &mut PlateContext<C>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    fn register_routes(&self, router: &mut Router<C>) {
+        let path = crate::router::intern(self.jobs.status_path(":id"));
+        router.set_jobs(self.jobs.clone());
+        router.register_at(path, JobStatusRoute(self.jobs.clone()));
+    }
+}
+
+/// Route params of the job status route: the job ID.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JobParams {
+    #[serde(deserialize_with = "crate::param::deserialize")]
+    pub id: String,
+}
+
+impl RouteParams for JobParams {
+    fn params() -> Vec<crate::ParamSpec> {
+        vec![crate::ParamSpec::of::<String>("id", true)]
+    }
+}
+
+/// Input of the job status action.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CancelJob {}
+
+struct NoView;
+
+impl RouteView for NoView {
+    fn render(&self) -> impl leptos::prelude::IntoView {}
+}
+
+#[derive(Clone)]
+struct JobStatusRoute(Jobs);
+
+impl<C: AppConfig> Route<C> for JobStatusRoute {
+    type Params = JobParams;
+    type Loader = Self;
+    type Action = Self;
+    type View = NoView;
+
+    fn path() -> &'static str {
+        "/jobs/:id"
+    }
+    fn loader(&self) -> Self {
+        self.clone()
+    }
+    fn action(&self) -> Self {
+        self.clone()
+    }
+    fn view(&self) -> NoView {
+        NoView
+    }
+}
+
+fn found(record: anyhow::Result<Option<JobRecord>>) -> Result<JobRecord, RouteError> {
+    match record {
+        Ok(Some(record)) => Ok(record),
+        Ok(None) => Err(RouteError::NotFound),
+        Err(e) => Err(RouteError::InternalError(e.to_string())),
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> RouteLoader<JobParams, C> for JobStatusRoute {
+    type Output = JobRecord;
+
+    async fn load(&self, _ctx: RouteContext<'_, C>, params: JobParams) -> Result<JobRecord, RouteError> {
+        found(self.0.status(&params.id).await)
+    }
+
+    fn description(&self) -> &'static str {
+        "Job status: route, due time, attempts, output and error"
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> RouteAction<JobParams, C> for JobStatusRoute {
+    type Input = CancelJob;
+    type Output = JobRecord;
+
+    async fn act(&self, _ctx: RouteContext<'_, C>, params: JobParams, _input: CancelJob) -> Result<JobRecord, RouteError> {
+        found(self.0.cancel(&params.id).await)
+    }
+
+    fn description(&self) -> &'static str {
+        "Cancels the job if it has not started"
+    }
+}
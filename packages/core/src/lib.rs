@@ -5,25 +5,165 @@
 //! for fine-grained reactivity and provides a modular system for composing
 //! complex applications.
 
+pub mod action_error;
+pub mod analytics;
+pub mod authz;
+pub mod body;
+pub mod boot;
+pub mod capture;
+pub mod changelog;
+pub mod compat;
+pub mod crash;
+pub mod deprecation;
+pub mod devstate;
+pub mod embed;
 pub mod env;
+pub mod error_page;
 pub mod features;
+pub mod fingerprint;
+pub mod guard;
+pub mod jobs;
+pub mod lifecycle;
 pub mod limiter;
+pub mod matcher;
+pub mod meta;
+pub mod mock;
+#[cfg(feature = "notifications")]
+pub mod notification;
+pub mod openapi;
+pub mod param;
+pub mod payload;
+#[cfg(feature = "payments")]
+pub mod payment;
+#[cfg(feature = "plate-config")]
+pub mod plate_config;
+pub mod prerender;
+pub mod preset;
+pub mod profile;
+pub mod response;
+pub mod rpc;
 pub mod router;
+pub mod sanitize;
+#[cfg(feature = "secrets")]
+pub mod secrets;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod server_signal;
+pub mod signal_graph;
+pub mod subscription;
+pub mod swr;
+pub mod sync;
+#[cfg(feature = "templates")]
+pub mod template;
+pub mod trace;
 pub mod validation;
+pub mod versioning;
+pub mod wasi;
+#[cfg(feature = "webhooks")]
+pub mod webhook;
+pub mod workflow;
 
-pub use env::{EnvConfig, EnvConfigExt, EnvError, FromEnv, TypedEnv};
+#[cfg(feature = "protobuf")]
+pub use body::Protobuf;
+pub use action_error::{ActionError, DomainError, ErrorMessages, FieldError, use_error_messages};
+pub use analytics::{Analytics, AnalyticsLoader, AnalyticsSink, RouteUsage, UsageWindow};
+pub use authz::{Permission, Principal, PrincipalResolver, Rbac};
+pub use body::{BodyFormat, RawBody};
+pub use boot::{BootBudget, BootError, BootPhase, BootPhaseKind, BootTrace, BudgetAction};
+pub use capture::Capture;
+pub use changelog::{Section, SpecChange, SpecDiff};
+pub use compat::{CompatError, CrateVersion};
+pub use crash::{CrashReporter, CrashSink, PanicReport, WebhookFormat};
+pub use deprecation::{Deprecation, DeprecationUsage};
+pub use devstate::DevState;
+pub use embed::Embedded;
+pub use env::{EnvChain, EnvConfig, EnvConfigExt, EnvError, FromEnv, TypedEnv};
+pub use error_page::{
+    ErrorInfo, ErrorPages, ErrorRenderer, ErrorTheme, default_error_view, error_fallback,
+    use_error_pages,
+};
 pub use features::{FeatureFlag, FeatureManager, Rule, Segment, UserContext};
+pub use fingerprint::{PlateFingerprint, RouteFingerprint, SpecFingerprint};
+pub use guard::{FnGuard, GuardOutcome, RouteGuard};
+pub use jobs::{JobHandle, JobRecord, JobStatus, JobStore, Jobs, JobsPlate, MemoryJobStore};
 pub use leptos::prelude::*;
-pub use limiter::{GovernorLimiter, Limiter};
+pub use lifecycle::{ErasureEntry, ErasureReport, PersonalData};
+pub use limiter::{GovernorLimiter, Limiter, LimiterClock, SystemClock};
+pub use matcher::{RouteMatch, RouteTrie};
+pub use meta::{Annotated, PlateMetaExt};
+pub use mock::{MockDefinition, MockError, MockResponse, MockSelection, Mocks};
+#[cfg(feature = "notifications")]
+pub use notification::{
+    Channel, Inbox, Mailer, MemoryNotificationStore, Notification, NotificationStore, Notifications, NotificationsPlate,
+    Preferences, PushKeys, PushOutcome, PushSubscription, PushTransport, VapidKeys, WebPush,
+};
+pub use param::{FromParam, ParamError, ParamSchema, ParamSpec};
+pub use payload::JsonBytes;
+#[cfg(feature = "payments")]
+pub use payment::{
+    Billing, BillingSubscription, Checkout, CheckoutMode, CheckoutSession, Customer, MemoryPaymentStore, PaymentError,
+    PaymentEvent, PaymentEventKind, PaymentStore, Payments, PaymentsPlate, PortalSession, Stripe, StripeEvent,
+    StripeTransport, SubscriptionStatus,
+};
+#[cfg(feature = "plate-config")]
+pub use plate_config::{PlateConfig, PlateConfigError, PlateConfigField};
+pub use prerender::{CacheStatus, CachedPage, DiskPageStore, MemoryPageStore, PageCache, PageStore};
+pub use preset::{Assets, Cors, LimiterBackend, Limits, Preset, PresetError, SecurityHeaders};
+pub use profile::{Profiler, RouteProfile, TrackingAllocator};
+pub use response::{
+    ByteRange, ContentDisposition, FileDownload, ResponseError, StreamingResponse,
+};
+pub use rpc::{ActionInput, ActionOutput, EventStream, Opened, RpcClient, RpcTransport};
+#[cfg(feature = "rpc")]
+pub use rpc::HttpTransport;
 pub use router::{
-    ActionResponse, LoaderResponse, Route, RouteAction, RouteContext, RouteError, RouteLoader,
-    RouteParams, RouteView, Router,
+    ActionResponse, CurrentUser, GroupSpec, LayoutData, LoaderResponse, NestedData, Route, RouteAction, RouteContext,
+    RouteError, RouteGroup, RouteLoader, RouteParams, RouteRegistration, RouteView, Router,
+    SubscriptionRegistration,
+};
+pub use sanitize::{Sanitize, SanitizeText, sanitize_and_validate, strip_html};
+#[cfg(feature = "secrets")]
+pub use secrets::{SecretKey, SecretsEnv, SecretsError, SecretsFile};
+#[cfg(feature = "server")]
+pub use server::ServeError;
+pub use server_signal::{ServerEffect, ServerMemo, ServerSignal};
+pub use signal_graph::{GraphNode, GraphWarning, NodeKind, SignalGraph, SignalInspector};
+#[cfg(feature = "local-storage")]
+pub use swr::LocalSwrStore;
+pub use swr::{Cached, MemorySwrStore, SwrCache, SwrResource, SwrStore, SwrSubscription};
+pub use subscription::{LiveSignal, LiveStatus, Messages, Subscription, SubscriptionMetadata};
+pub use sync::{
+    Causality, Conflict, MemorySyncStore, Resolution, SyncEngine, SyncPlate, SyncReport, SyncRow, SyncStatus, SyncStore,
+    VersionVector,
 };
+#[cfg(feature = "templates")]
+pub use template::{Html, TemplateEngine, TemplateError};
+pub use trace::Trace;
+#[cfg(feature = "rpc")]
+pub use trace::TracedRequest;
 pub use validation::{Validate, ValidationError};
+pub use versioning::{ApiVersions, Negotiated, VersionSpec, VersionStrategy};
+pub use wasi::{WasiRequest, WasiResponse};
+// `webhook::Subscription` stays in its module: `Subscription` is the router's live data trait.
+#[cfg(feature = "webhooks")]
+pub use webhook::{
+    DeadLetterLoader, Delivery, DeliveryAttempt, DeliveryLogLoader, DeliveryStatus, MemoryWebhookStore, WebhookStore,
+    WebhookTransport, Webhooks,
+};
+pub use workflow::{
+    MemoryStore, Saga, SagaStep, StepContext, WorkflowParams, WorkflowRecord, WorkflowStatus, WorkflowStatusLoader,
+    WorkflowStore, Workflows,
+};
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::error::Error as StdError;
+use std::sync::Arc;
+
+/// Prefix of the `#[deprecated]` note through which MontRS proc macros attach
+/// `AgentError` metadata, as JSON, to a compile error. rustc prints the note in
+/// a warning at the same span, where the agent's diagnostic parser reads it.
+pub const AGENT_ERROR_NOTE: &str = "montrs-agent-error: ";
 
 /// A trait for errors that provide agent-accessible metadata.
 pub trait AgentError: StdError {
@@ -66,6 +206,8 @@ pub enum Target {
     MobileAndroid,
     /// iOS mobile platform.
     MobileIos,
+    /// WASI HTTP components (`wasm32-wasip2`), served through `wasi:http/proxy`.
+    Wasi,
 }
 
 /// The unit of composition in MontRS.
@@ -108,6 +250,24 @@ pub trait Plate<C: AppConfig>: Send + Sync + 'static {
     /// This allows plates to define their own URL structure and link them to
     /// specific Loaders and Actions.
     fn register_routes(&self, _router: &mut Router<C>) {}
+
+    /// Releases what `init` acquired: connections, files, background tasks.
+    /// [`AppSpec::shutdown`] calls it in reverse boot order, so a plate shuts
+    /// down before the plates it depends on.
+    async fn shutdown(&self) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        Ok(())
+    }
+
+    /// Guards run before every route this plate registers, ahead of the
+    /// routes' own. See [`guard`].
+    fn guards(&self) -> Vec<Arc<dyn RouteGuard<C>>> {
+        Vec::new()
+    }
+
+    /// The personal data this plate keeps, erased by [`AppSpec::erase_user`].
+    fn personal_data(&self) -> Vec<Arc<dyn PersonalData>> {
+        Vec::new()
+    }
 }
 
 /// Dynamic context provided to plates during their `init` phase.
@@ -151,6 +311,20 @@ pub struct AppSpec<C: AppConfig> {
     pub router: Router<C>,
     /// The current execution target.
     pub target: Target,
+    /// Error views rendered by `error_fallback` inside `<ErrorBoundary>`.
+    pub error_pages: ErrorPages,
+    /// Localized action error messages, returned by `use_error_messages`.
+    pub error_messages: ErrorMessages,
+    /// Maximum number of plates `boot` initializes at once (`None`: no limit).
+    pub boot_concurrency: Option<usize>,
+    /// Feature flags, listed on the `/_montrs` dev dashboard.
+    pub features: FeatureManager,
+    /// Manifest `boot` compares the fingerprint with (`None`: not checked).
+    pub spec_manifest: Option<std::path::PathBuf>,
+    /// The montrs crates and CLI `boot` checks against this core.
+    pub crates: Vec<CrateVersion>,
+    /// Personal data kept by the app itself, erased by [`AppSpec::erase_user`].
+    pub personal_data: Vec<Arc<dyn PersonalData>>,
 }
 
 /// A serializable version of AppSpec for external consumption (e.g., by agents).
@@ -160,6 +334,12 @@ pub struct AppSpecExport {
     pub target: Target,
     pub plates: Vec<PlateMetadata>,
     pub router: crate::router::RouterSpec,
+    /// The CORS, security header, limit and asset settings in effect.
+    #[serde(default)]
+    pub preset: Option<Preset>,
+    /// The core and the montrs crates the app was built with.
+    #[serde(default)]
+    pub crates: Vec<CrateVersion>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -183,17 +363,54 @@ impl<C: AppConfig> AppSpec<C> {
                 metadata: m.metadata(),
             }).collect(),
             router: self.router.spec(),
+            preset: self.router.preset().cloned().or_else(|| Preset::from_env(self.target).ok()),
+            crates: std::iter::once(compat::CORE).chain(self.crates.iter().cloned()).collect(),
         }
     }
 
     /// Creates a new, empty AppSpec with required config and environment.
+    ///
+    /// When started by `montrs serve --mock`, the router answers the selected
+    /// routes from the `mocks/` directory; with `--profile` it profiles every call,
+    /// and with the dev dashboard on it records the server signal graph and
+    /// logs panics for the agent (see [`crash::install_from_env`]).
+    /// Under `montrs e2e`, loaders listed in `[e2e.fixtures]` answer from their
+    /// fixture files.
     pub fn new(config: C, env: C::Env) -> Self {
+        let mut router = Router::new();
+        let mut mocks = match Mocks::from_env() {
+            Ok(mocks) => mocks,
+            Err(e) => {
+                tracing::error!(error = %e, "mocks not loaded");
+                None
+            }
+        };
+        match Mocks::fixtures_from_env() {
+            Ok(Some(fixtures)) => mocks = Some(mocks.unwrap_or_default().merge(fixtures)),
+            Ok(None) => {}
+            Err(e) => tracing::error!(error = %e, "e2e fixtures not loaded"),
+        }
+        if let Some(mocks) = mocks {
+            router.set_mocks(mocks);
+        }
+        if let Some(profiler) = Profiler::from_env() {
+            router.set_profiler(profiler);
+        }
+        signal_graph::enable_from_env();
+        crash::install_from_env();
         Self {
             config,
             plates: Vec::new(),
             env,
-            router: Router::new(),
+            router,
             target: Target::Server,
+            error_pages: ErrorPages::default(),
+            error_messages: ErrorMessages::default(),
+            boot_concurrency: boot::concurrency_from_env(),
+            features: FeatureManager::new(),
+            spec_manifest: fingerprint::manifest_from_env(),
+            crates: CrateVersion::cli_from_env().into_iter().collect(),
+            personal_data: Vec::new(),
         }
     }
 
@@ -209,11 +426,292 @@ impl<C: AppConfig> AppSpec<C> {
         self
     }
 
+    /// Builder method to answer loaders and actions from mocks.
+    pub fn with_mocks(mut self, mocks: Mocks) -> Self {
+        self.router.set_mocks(mocks);
+        self
+    }
+
+    /// Builder method to limit how many plates `boot` initializes concurrently.
+    /// `1` initializes them one at a time.
+    pub fn with_boot_concurrency(mut self, limit: usize) -> Self {
+        self.boot_concurrency = Some(limit.max(1));
+        self
+    }
+
+    /// Builder method to set the feature flags and segments.
+    pub fn with_features(mut self, features: FeatureManager) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Builder method to make `boot` fail when the app's fingerprint differs
+    /// from the one in `path`, usually the committed `montrs.fingerprint.json`.
+    /// Defaults to the path in `MONTRS_SPEC_MANIFEST`.
+    pub fn with_spec_manifest(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.spec_manifest = Some(path.into());
+        self
+    }
+
+    /// Builder method to list the montrs crates the app uses, usually
+    /// `montrs::crates()`, so `boot` can check they work with this core.
+    pub fn with_crates(mut self, crates: impl IntoIterator<Item = CrateVersion>) -> Self {
+        self.crates.extend(crates);
+        self
+    }
+
+    /// Builder method to erase `data` in [`AppSpec::erase_user`], for
+    /// personal data the app keeps outside its plates.
+    pub fn with_personal_data(mut self, data: Arc<dyn PersonalData>) -> Self {
+        self.personal_data.push(data);
+        self
+    }
+
+    /// Erases everything kept about `user_id` and reports what was erased.
+    ///
+    /// The plates' [`Plate::personal_data`] hooks run first, last plate
+    /// first, so data is erased before the data it depends on. The app's own
+    /// hooks run after them. See [`lifecycle`].
+    pub async fn erase_user(&self, user_id: &str) -> ErasureReport {
+        let plates = self.plates.iter().rev().flat_map(|p| p.personal_data().into_iter().map(move |d| (p.name(), d)));
+        let app = self.personal_data.iter().map(|d| ("app", d.clone()));
+        lifecycle::erase_user(user_id, plates.chain(app)).await
+    }
+
+    /// A stable hash of the config metadata, the plates in order, the routes
+    /// and the feature flags, with the values it was computed from.
+    ///
+    /// Plates register their routes during [`AppSpec::boot`], so before boot
+    /// only routes added to `router` directly are covered.
+    pub fn fingerprint(&self) -> SpecFingerprint {
+        let sorted = |map: std::collections::HashMap<String, String>| map.into_iter().collect();
+        let plates = self
+            .plates
+            .iter()
+            .map(|p| PlateFingerprint {
+                name: p.name().to_string(),
+                dependencies: p.dependencies().iter().map(|d| d.to_string()).collect(),
+                metadata: sorted(p.metadata()),
+            })
+            .collect();
+        let routes = self
+            .router
+            .spec()
+            .routes
+            .into_iter()
+            .map(|(path, route)| {
+                let fingerprint = RouteFingerprint {
+                    action_body: route.action_body,
+                    meta: sorted(route.meta),
+                    guards: route.guards,
+                    parent: route.parent,
+                };
+                (path, fingerprint)
+            })
+            .collect();
+        let features = self.features.flags().into_iter().map(|f| (f.name.clone(), f.enabled)).collect();
+        SpecFingerprint::new(sorted(self.config.metadata()), plates, routes, features)
+    }
+
+    /// Builder method to count route usage in `analytics`. Under `montrs serve`
+    /// the totals also go to the `/_montrs` dev dashboard. The shared handle
+    /// is on the router, for an [`AnalyticsLoader`] or a flush at shutdown.
+    pub fn with_analytics(mut self, analytics: Analytics) -> Self {
+        self.router.set_analytics(Arc::new(analytics.with_output_from_env()));
+        self
+    }
+
+    /// Builder method to serve the routes `cache` lists from cached pages,
+    /// revalidated in the background. See [`prerender`].
+    pub fn with_page_cache(mut self, cache: Arc<PageCache>) -> Self {
+        self.router.set_page_cache(cache);
+        self
+    }
+
+    /// Builder method to enforce the requirements routes declare with
+    /// `.requires(..)`. See [`authz`].
+    pub fn with_rbac(mut self, rbac: Rbac<C>) -> Self {
+        self.router.set_rbac(rbac);
+        self
+    }
+
+    /// Builder method to serve with `preset` instead of the target's defaults
+    /// and the `[preset]` overrides `boot` would apply. See [`preset`].
+    pub fn with_preset(mut self, preset: Preset) -> Self {
+        self.router.set_preset(preset);
+        self
+    }
+
+    /// Builder method to rate limit requests with `limiter` instead of the
+    /// preset's. See [`Router::set_limiter`].
+    pub fn with_limiter(mut self, limiter: impl Limiter) -> Self {
+        self.router.set_limiter(limiter);
+        self
+    }
+
+    /// Builder method to customize the error pages and their theme.
+    pub fn with_error_pages(mut self, pages: ErrorPages) -> Self {
+        self.error_pages = pages;
+        self
+    }
+
+    /// Builder method to localize the messages of action errors in forms.
+    pub fn with_error_messages(mut self, messages: ErrorMessages) -> Self {
+        self.error_messages = messages;
+        self
+    }
+
+    /// Initializes the plates, then registers their routes, recording each
+    /// step in a [`BootTrace`].
+    ///
+    /// Before anything else, the crates from [`AppSpec::with_crates`] and
+    /// the CLI that started the app are checked against this core; see
+    /// [`compat`].
+    ///
+    /// Plates are grouped into levels by [`Plate::dependencies`]. Each level
+    /// starts after the previous one has finished, and the plates within a level
+    /// are initialized concurrently, up to `boot_concurrency` at a time.
+    ///
+    /// Under `montrs serve`, the trace is printed with `-v`, saved for the agent
+    /// snapshot and checked against the `[boot]` budget, and the routes and
+    /// feature flags are saved for the dev dashboard.
+    ///
+    /// Unless [`AppSpec::with_preset`] set one, the router gets the
+    /// [`Preset`] of the app's target with the `[preset]` overrides applied.
+    ///
+    /// Once the routes are registered, the [fingerprint](AppSpec::fingerprint)
+    /// is compared with the `spec_manifest`, if any. Under
+    /// `montrs spec --fingerprint` it is written out and the process exits.
+    /// Under `montrs changelog` the [exported spec](AppSpec::export_spec) is
+    /// written out instead, and the process exits.
+    pub async fn boot(&mut self) -> Result<BootTrace, BootError> {
+        self.boot_with(BootTrace::new()).await
+    }
+
+    /// Like [`AppSpec::boot`], continuing a trace that already timed earlier
+    /// phases such as environment resolution.
+    pub async fn boot_with(&mut self, mut trace: BootTrace) -> Result<BootTrace, BootError> {
+        use futures::StreamExt;
+        use std::time::Instant;
+
+        compat::check(&compat::CORE.version, &self.crates).map_err(BootError::Incompatible)?;
+        let graph: Vec<_> = self.plates.iter().map(|p| (p.name(), p.dependencies())).collect();
+        let levels = boot::plate_levels(&graph)?;
+        let limit = self.boot_concurrency.unwrap_or(self.plates.len()).max(1);
+
+        let init_started = Instant::now();
+        let mut busy = std::time::Duration::ZERO;
+        for level in &levels {
+            let config = &self.config;
+            let env = &self.env;
+            let plates = &self.plates;
+            let mut results: Vec<_> = futures::stream::iter(level.iter().copied())
+                .map(|i| async move {
+                    let mut ctx = PlateContext { config, env };
+                    let started = Instant::now();
+                    let scope = format!("plate {}", plates[i].name());
+                    let result = signal_graph::scoped(&scope, plates[i].init(&mut ctx)).await;
+                    (i, started, Instant::now(), result)
+                })
+                .buffer_unordered(limit)
+                .collect()
+                .await;
+            results.sort_by_key(|(i, ..)| *i);
+
+            for (i, started, finished, _) in &results {
+                busy += finished.saturating_duration_since(*started);
+                trace.record_span(BootPhaseKind::Plate, self.plates[*i].name(), *started, *finished);
+            }
+            if let Some((i, .., Err(e))) = results.into_iter().find(|(.., result)| result.is_err()) {
+                return Err(BootError::PlateInit {
+                    plate: self.plates[i].name().to_string(),
+                    reason: e.to_string(),
+                });
+            }
+        }
+        let init_wall = init_started.elapsed().as_secs_f64();
+        trace.parallelism = if init_wall > 0.0 { busy.as_secs_f64() / init_wall } else { 1.0 };
+        trace.plate_levels = levels
+            .iter()
+            .map(|level| level.iter().map(|&i| self.plates[i].name().to_string()).collect())
+            .collect();
+
+        let started = Instant::now();
+        for &i in levels.iter().flatten() {
+            let plate = &self.plates[i];
+            self.router.with_guards(plate.guards(), |router| plate.register_routes(router));
+        }
+        trace.record(BootPhaseKind::Router, "router", started);
+        if self.router.preset().is_none() {
+            let preset = Preset::from_env(self.target)
+                .map_err(|e| BootError::Preset { target: self.target.to_string(), reason: e.to_string() })?;
+            self.router.set_preset(preset);
+        }
+        self.check_fingerprint()?;
+
+        trace.finish();
+        DevState {
+            booted_at: chrono::Utc::now(),
+            boot_ms: trace.total_ms(),
+            router: self.router.spec(),
+            flags: self.features.flags().into_iter().cloned().collect(),
+        }
+        .publish();
+        trace.report()?;
+        Ok(trace)
+    }
+
+    /// Shuts every plate down in reverse boot order, after the app stops
+    /// serving. Every plate gets to shut down; the first failure is returned.
+    pub async fn shutdown(&self) -> Result<(), BootError> {
+        boot::shutdown(&self.plates).await
+    }
+
+    fn check_fingerprint(&self) -> Result<(), BootError> {
+        if let Some(out) = changelog::export_out_from_env() {
+            // The CLI only wanted the spec for a changelog; do not start serving.
+            let name = std::env::current_exe()
+                .ok()
+                .and_then(|exe| exe.file_stem().map(|s| s.to_string_lossy().into_owned()))
+                .unwrap_or_else(|| "app".to_string());
+            let code = match self.export_spec(&name).write_to(&out) {
+                Ok(()) => 0,
+                Err(e) => {
+                    eprintln!("failed to write the spec export: {}", e);
+                    1
+                }
+            };
+            std::process::exit(code);
+        }
+        let actual = self.fingerprint();
+        if let Some(out) = std::env::var_os(fingerprint::SPEC_FINGERPRINT_OUT_VAR) {
+            // The CLI only wanted the fingerprint; do not start serving.
+            let code = match actual.write_to(std::path::Path::new(&out)) {
+                Ok(()) => 0,
+                Err(e) => {
+                    eprintln!("failed to write the spec fingerprint: {}", e);
+                    1
+                }
+            };
+            std::process::exit(code);
+        }
+        let Some(path) = &self.spec_manifest else {
+            return Ok(());
+        };
+        let expected = SpecFingerprint::read_from(path)
+            .map_err(|e| BootError::SpecManifest { path: path.display().to_string(), reason: e.to_string() })?;
+        let changes = actual.changes(&expected);
+        if changes.is_empty() {
+            return Ok(());
+        }
+        Err(BootError::SpecMismatch { expected: expected.hash, actual: actual.hash, changes })
+    }
+
     /// Boots the application and mounts it to the document body.
     ///
     /// Inside this method:
-    /// 1. The global config and env are provided as Leptos contexts.
-    /// 2. All registered plates are initialized sequentially.
+    /// 1. The global config, env, error pages and error messages are provided as Leptos contexts.
+    /// 2. Registered plates are listed; run `boot` on the server to initialize them.
     /// 3. The `main_view` is rendered as the application root.
     pub fn mount<F, IV>(self, main_view: F)
     where
@@ -223,11 +721,15 @@ impl<C: AppConfig> AppSpec<C> {
         let config = self.config;
         let env = self.env;
         let plates = self.plates;
+        let error_pages = self.error_pages;
+        let error_messages = self.error_messages;
 
         leptos::mount::mount_to_body(move || {
             // Provide global application context for easy access via use_context().
             provide_context(config.clone());
             provide_context(env.clone());
+            provide_context(error_pages.clone());
+            provide_context(error_messages.clone());
 
             // Initialize plates.
             for plate in plates {
@@ -0,0 +1,100 @@
+//! montrs-core/src/lifecycle.rs: Erasing a user's personal data.
+//! Plates that keep data about users expose it through
+//! [`Plate::personal_data`] as [`PersonalData`] hooks, and the app adds its
+//! own tables with [`AppSpec::with_personal_data`]. [`AppSpec::erase_user`]
+//! calls every hook for one user, e.g. for a GDPR erasure request, and
+//! returns an [`ErasureReport`] saying what was erased where, and what
+//! failed, to keep as the record of the request. Retention periods for
+//! whole tables are declared on models with `#[orm(retain = "90d")]`; see
+//! `montrs_orm::lifecycle`.
+//!
+//! [`AppSpec::with_personal_data`]: crate::AppSpec::with_personal_data
+//! [`AppSpec::erase_user`]: crate::AppSpec::erase_user
+//! [`Plate::personal_data`]: crate::Plate::personal_data
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Data kept about users, which can be erased for one of them.
+#[async_trait]
+pub trait PersonalData: Send + Sync {
+    /// What the data is, for the report, e.g. a table name.
+    fn category(&self) -> String;
+
+    /// Deletes or anonymizes everything kept about `user_id`, returning the
+    /// number of records changed. Erasure may be retried after a failure, so
+    /// erasing data that is already gone should succeed with `0`.
+    async fn erase(&self, user_id: &str) -> anyhow::Result<u64>;
+}
+
+/// The outcome of one [`PersonalData`] hook.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErasureEntry {
+    /// The plate that registered the hook, or `app` for hooks from
+    /// `AppSpec::with_personal_data`.
+    pub source: String,
+    pub category: String,
+    /// Records deleted or anonymized.
+    pub records: u64,
+    /// Why the hook failed; `None` if it succeeded.
+    pub error: Option<String>,
+    pub finished_at: DateTime<Utc>,
+}
+
+/// What [`erase_user`] did for one user.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErasureReport {
+    pub user_id: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// One entry per hook, in the order they ran.
+    pub entries: Vec<ErasureEntry>,
+}
+
+impl ErasureReport {
+    /// Whether every hook succeeded. An incomplete erasure can be run again.
+    pub fn is_complete(&self) -> bool {
+        self.entries.iter().all(|entry| entry.error.is_none())
+    }
+
+    /// Records erased across all hooks.
+    pub fn records(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.records).sum()
+    }
+
+    /// The entries whose hook failed.
+    pub fn failures(&self) -> impl Iterator<Item = &ErasureEntry> {
+        self.entries.iter().filter(|entry| entry.error.is_some())
+    }
+}
+
+/// Calls each hook for `user_id`, one after the other, with the source it
+/// was registered by. A failing hook is recorded and the others still run.
+pub async fn erase_user<'a>(
+    user_id: &str,
+    hooks: impl IntoIterator<Item = (&'a str, Arc<dyn PersonalData>)>,
+) -> ErasureReport {
+    let started_at = Utc::now();
+    let mut entries = Vec::new();
+    for (source, hook) in hooks {
+        let category = hook.category();
+        let (records, error) = match hook.erase(user_id).await {
+            Ok(records) => (records, None),
+            Err(e) => {
+                tracing::error!(source, category = %category, error = %e, "personal data erasure failed");
+                (0, Some(e.to_string()))
+            }
+        };
+        entries.push(ErasureEntry { source: source.to_string(), category, records, error, finished_at: Utc::now() });
+    }
+    let report = ErasureReport { user_id: user_id.to_string(), started_at, finished_at: Utc::now(), entries };
+    tracing::info!(
+        user_id,
+        records = report.records(),
+        complete = report.is_complete(),
+        "personal data erased"
+    );
+    report
+}
@@ -1,10 +1,17 @@
 //! montrs-core/src/limiter.rs: Rate limiting primitives.
 //! This file provides a generic Limiter trait and a concrete implementation
 //! using the governor crate for sophisticated rate limiting strategies.
+//! Limiters read time through a [`LimiterClock`], so tests can drive them with
+//! a virtual clock (`montrs_test::limiter`) instead of sleeping.
 
-use governor::{Quota, RateLimiter, clock::DefaultClock, state::InMemoryState, state::NotKeyed};
+use governor::clock::Clock;
+use governor::middleware::NoOpMiddleware;
+use governor::nanos::Nanos;
+use governor::{Quota, RateLimiter, state::InMemoryState, state::NotKeyed};
 use nonzero_ext::nonzero;
 use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Trait for components that can perform rate limiting checks.
 pub trait Limiter: Send + Sync + 'static {
@@ -17,18 +24,59 @@ pub trait Limiter: Send + Sync + 'static {
     }
 }
 
+/// The time source of a limiter.
+pub trait LimiterClock: Send + Sync + 'static {
+    /// Time elapsed since a fixed origin. Must never go backwards.
+    fn now(&self) -> Duration;
+}
+
+/// Wall-clock time, measured from when the clock was created.
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    origin: Instant,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self { origin: Instant::now() }
+    }
+}
+
+impl LimiterClock for SystemClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+/// Adapts a [`LimiterClock`] to governor's clock.
+#[derive(Clone)]
+struct SharedClock(Arc<dyn LimiterClock>);
+
+impl Clock for SharedClock {
+    type Instant = Nanos;
+
+    fn now(&self) -> Nanos {
+        Nanos::from(self.0.now())
+    }
+}
+
 /// A rate limiter implementation backed by the governor crate.
 /// Uses an in-memory state and a simple per-second quota.
 pub struct GovernorLimiter {
-    limiter: RateLimiter<NotKeyed, InMemoryState, DefaultClock>,
+    limiter: RateLimiter<NotKeyed, InMemoryState, SharedClock, NoOpMiddleware<Nanos>>,
 }
 
 impl GovernorLimiter {
     /// Creates a new GovernorLimiter with the specified allows requests per second.
     pub fn new(per_second: u32) -> Self {
+        Self::with_clock(per_second, SystemClock::default())
+    }
+
+    /// Like [`GovernorLimiter::new`], reading time from `clock`.
+    pub fn with_clock(per_second: u32, clock: impl LimiterClock) -> Self {
         let quota = Quota::per_second(NonZeroU32::new(per_second).unwrap_or(nonzero!(1u32)));
         Self {
-            limiter: RateLimiter::direct(quota),
+            limiter: RateLimiter::direct_with_clock(quota, &SharedClock(Arc::new(clock))),
         }
     }
 }
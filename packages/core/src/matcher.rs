@@ -0,0 +1,196 @@
+//! montrs-core/src/matcher.rs: Segment trie that resolves request paths to route patterns.
+//! Patterns are split on `/` into static segments, `:name` params and a trailing
+//! `*name` wildcard. Lookup walks the path once, preferring static segments over
+//! params over wildcards, so its cost grows with the path length rather than the
+//! number of registered routes. Paths longer than `MAX_PATH_LEN` or deeper than
+//! `MAX_SEGMENTS` are rejected before matching.
+
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Longest request path the matcher will look at, in bytes.
+pub const MAX_PATH_LEN: usize = 4096;
+/// Deepest request path the matcher will look at, in segments.
+pub const MAX_SEGMENTS: usize = 64;
+
+/// A resolved request path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteMatch {
+    /// The registered pattern, e.g. `/users/:id`.
+    pub pattern: &'static str,
+    /// Captured params in pattern order. A wildcard captures the rest of the path.
+    pub params: Vec<(String, String)>,
+}
+
+impl RouteMatch {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    /// The captured params as a JSON object, ready for `RouteParams` decoding.
+    /// Values that are canonical integers (`42`, not `007`) become numbers.
+    pub fn params_json(&self) -> Value {
+        let object: Map<String, Value> = self
+            .params
+            .iter()
+            .map(|(name, value)| (name.clone(), param_value(value)))
+            .collect();
+        Value::Object(object)
+    }
+
+    /// Adds the captured params to `params`. Explicitly passed values win.
+    pub fn merge_into(&self, params: Value) -> Value {
+        match params {
+            Value::Object(mut object) => {
+                if let Value::Object(captured) = self.params_json() {
+                    for (name, value) in captured {
+                        object.entry(name).or_insert(value);
+                    }
+                }
+                Value::Object(object)
+            }
+            Value::Null => self.params_json(),
+            other => other,
+        }
+    }
+}
+
+pub(crate) fn param_value(value: &str) -> Value {
+    match value.parse::<i64>() {
+        Ok(n) if n.to_string() == value => Value::from(n),
+        _ => Value::String(value.to_string()),
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Leaf {
+    pattern: &'static str,
+    params: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct Node {
+    statics: HashMap<String, Node>,
+    param: Option<Box<Node>>,
+    /// A `*name` pattern ending at this node, matching the remaining segments.
+    wildcard: Option<(String, Leaf)>,
+    leaf: Option<Leaf>,
+}
+
+/// Route patterns indexed by segment.
+///
+/// ```rust
+/// use montrs_core::matcher::RouteTrie;
+///
+/// let mut trie = RouteTrie::new();
+/// trie.insert("/users/:id");
+/// trie.insert("/users/me");
+/// trie.insert("/files/*path");
+///
+/// assert_eq!(trie.at("/users/me").unwrap().pattern, "/users/me");
+/// assert_eq!(trie.at("/users/42").unwrap().param("id"), Some("42"));
+/// assert_eq!(trie.at("/files/a/b.txt").unwrap().param("path"), Some("a/b.txt"));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct RouteTrie {
+    root: Node,
+    len: usize,
+}
+
+impl RouteTrie {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a pattern. Re-inserting a pattern, or one that differs only in its
+    /// param names, replaces the earlier one.
+    pub fn insert(&mut self, pattern: &'static str) {
+        let mut node = &mut self.root;
+        let mut params = Vec::new();
+        for segment in segments(pattern) {
+            if let Some(name) = segment.strip_prefix('*') {
+                let replaced = node.wildcard.replace((name.to_string(), Leaf { pattern, params }));
+                self.len += usize::from(replaced.is_none());
+                return;
+            }
+            node = match segment.strip_prefix(':') {
+                Some(name) => {
+                    params.push(name.to_string());
+                    node.param.get_or_insert_with(Box::default)
+                }
+                None => node.statics.entry(segment.to_string()).or_default(),
+            };
+        }
+        let replaced = node.leaf.replace(Leaf { pattern, params });
+        self.len += usize::from(replaced.is_none());
+    }
+
+    /// Number of distinct patterns.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Resolves a request path, ignoring any query string or fragment.
+    pub fn at(&self, path: &str) -> Option<RouteMatch> {
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        if path.len() > MAX_PATH_LEN {
+            return None;
+        }
+        let segments: Vec<&str> = segments(path).take(MAX_SEGMENTS + 1).collect();
+        if segments.len() > MAX_SEGMENTS {
+            return None;
+        }
+        let mut captured = Vec::new();
+        let (leaf, rest) = find(&self.root, &segments, 0, &mut captured)?;
+        let mut params: Vec<(String, String)> = leaf
+            .params
+            .iter()
+            .cloned()
+            .zip(captured.into_iter().map(str::to_string))
+            .collect();
+        if let Some((name, rest)) = rest {
+            params.push((name.to_string(), rest));
+        }
+        Some(RouteMatch {
+            pattern: leaf.pattern,
+            params,
+        })
+    }
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|s| !s.is_empty())
+}
+
+type Found<'t> = (&'t Leaf, Option<(&'t str, String)>);
+
+/// Depth-first search. Every trie node sits at a fixed depth, so each node is
+/// visited at most once and backtracking is bounded by the size of the trie.
+fn find<'t, 'p>(node: &'t Node, segments: &[&'p str], i: usize, captured: &mut Vec<&'p str>) -> Option<Found<'t>> {
+    let Some(&segment) = segments.get(i) else {
+        return match (&node.leaf, &node.wildcard) {
+            (Some(leaf), _) => Some((leaf, None)),
+            (None, Some((name, leaf))) => Some((leaf, Some((name.as_str(), String::new())))),
+            (None, None) => None,
+        };
+    };
+    if let Some(child) = node.statics.get(segment)
+        && let Some(found) = find(child, segments, i + 1, captured)
+    {
+        return Some(found);
+    }
+    if let Some(child) = &node.param {
+        captured.push(segment);
+        if let Some(found) = find(child, segments, i + 1, captured) {
+            return Some(found);
+        }
+        captured.pop();
+    }
+    node.wildcard
+        .as_ref()
+        .map(|(name, leaf)| (leaf, Some((name.as_str(), segments[i..].join("/")))))
+}
@@ -0,0 +1,129 @@
+//! montrs-core/src/meta.rs: Ownership and stability annotations for routes and plates.
+//! Annotations are free-form key-value pairs; the keys below are the ones the
+//! agent tooling and generated API docs understand.
+
+use crate::deprecation::Deprecation;
+use crate::guard::RouteGuard;
+use crate::lifecycle::PersonalData;
+use crate::{AppConfig, Plate, PlateContext, Router};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::sync::Arc;
+
+/// The team or person responsible for a route or plate.
+pub const OWNER: &str = "owner";
+/// The broader group an owner belongs to.
+pub const TEAM: &str = "team";
+/// Maturity of the API: `experimental`, `beta`, `stable` or `deprecated`.
+pub const STABILITY: &str = "stability";
+/// The `STABILITY` value set by `.deprecated(..)`.
+pub const DEPRECATED: &str = "deprecated";
+/// Date a deprecation took effect (`YYYY-MM-DD`).
+pub const DEPRECATED_SINCE: &str = "deprecated_since";
+/// Date after which a deprecated route or plate may be removed (`YYYY-MM-DD`).
+pub const SUNSET: &str = "sunset";
+/// What to use instead of a deprecated route or plate.
+pub const REPLACEMENT: &str = "replacement";
+/// Why a route or plate was deprecated.
+pub const DEPRECATION_NOTE: &str = "deprecation_note";
+/// The API version a route belongs to, set from [`crate::ApiVersions`].
+pub const API_VERSION: &str = "api_version";
+/// The route's path without its version segment, e.g. `/api/users/:id`.
+pub const API_ROUTE: &str = "api_route";
+/// What a caller needs to use a route, comma-separated, set by `.requires(..)`.
+/// See [`crate::authz::Permission`].
+pub const REQUIRES: &str = "requires";
+
+/// A plate with extra annotations merged into its [`Plate::metadata`].
+///
+/// Created with [`PlateMetaExt::with_meta`]:
+///
+/// ```rust,ignore
+/// AppSpec::new(config, env)
+///     .with_plate(Box::new(BillingPlate.with_meta(meta::OWNER, "payments-team")));
+/// ```
+pub struct Annotated<P> {
+    plate: P,
+    meta: HashMap<String, String>,
+}
+
+impl<P> Annotated<P> {
+    /// Adds another annotation. Later values win over earlier ones and over the plate's own.
+    pub fn with_meta(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.meta.insert(key.into(), value.into());
+        self
+    }
+
+    /// Marks the plate deprecated; reported in the agent snapshot.
+    pub fn deprecated(mut self, deprecation: Deprecation) -> Self {
+        deprecation.apply_to(&mut self.meta);
+        self
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.plate
+    }
+}
+
+/// Adds `.with_meta(..)` and `.deprecated(..)` to every plate.
+pub trait PlateMetaExt<C: AppConfig>: Plate<C> + Sized {
+    fn with_meta(self, key: impl Into<String>, value: impl Into<String>) -> Annotated<Self> {
+        Annotated {
+            plate: self,
+            meta: HashMap::new(),
+        }
+        .with_meta(key, value)
+    }
+
+    fn deprecated(self, deprecation: Deprecation) -> Annotated<Self> {
+        Annotated {
+            plate: self,
+            meta: HashMap::new(),
+        }
+        .deprecated(deprecation)
+    }
+}
+
+impl<C: AppConfig, P: Plate<C>> PlateMetaExt<C> for P {}
+
+#[async_trait]
+impl<C: AppConfig, P: Plate<C>> Plate<C> for Annotated<P> {
+    fn name(&self) -> &'static str {
+        self.plate.name()
+    }
+
+    fn description(&self) -> &'static str {
+        self.plate.description()
+    }
+
+    fn metadata(&self) -> HashMap<String, String> {
+        let mut metadata = self.plate.metadata();
+        metadata.extend(self.meta.clone());
+        metadata
+    }
+
+    fn dependencies(&self) -> Vec<&'static str> {
+        self.plate.dependencies()
+    }
+
+    async fn init(&self, ctx: &mut PlateContext<C>) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        self.plate.init(ctx).await
+    }
+
+    fn register_routes(&self, router: &mut Router<C>) {
+        self.plate.register_routes(router)
+    }
+
+    async fn shutdown(&self) -> Result<(), Box<dyn StdError + Send + Sync>> {
+        self.plate.shutdown().await
+    }
+
+    fn guards(&self) -> Vec<Arc<dyn RouteGuard<C>>> {
+        self.plate.guards()
+    }
+
+    fn personal_data(&self) -> Vec<Arc<dyn PersonalData>> {
+        self.plate.personal_data()
+    }
+}
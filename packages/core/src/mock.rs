@@ -0,0 +1,365 @@
+//! montrs-core/src/mock.rs: Development-time API mocking for loaders and actions.
+//! Mocks are declared as JSON files in a `mocks/` directory or as Rust closures,
+//! and the `Router` answers from them instead of calling the real handlers.
+//! `montrs serve --mock` selects which routes are mocked through environment
+//! variables, so frontend work can start before the backend route exists.
+//! `montrs e2e` pins loaders to fixture files the same way, but only in an
+//! app started with `MONTRS_E2E=1`, so test runs do not depend on live data.
+
+use crate::action_error::ActionError;
+use crate::AgentError;
+use crate::router::RouteError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Directory holding the JSON mock files (set by `montrs serve --mock`).
+pub const MOCKS_DIR_VAR: &str = "MONTRS_MOCKS";
+/// Comma-separated route paths to mock, or `*` for all (set by `montrs serve --mock`).
+pub const MOCK_ROUTES_VAR: &str = "MONTRS_MOCK_ROUTES";
+
+/// Set to `1` by `montrs e2e` in the server it starts.
+pub const E2E_VAR: &str = "MONTRS_E2E";
+/// JSON object mapping route paths to fixture files (set by `montrs e2e`).
+pub const E2E_FIXTURES_VAR: &str = "MONTRS_E2E_FIXTURES";
+
+/// Errors raised while loading mock definitions.
+#[derive(Debug, thiserror::Error)]
+pub enum MockError {
+    #[error("Failed to read mock file {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Invalid mock file {path}: {reason}")]
+    Parse { path: PathBuf, reason: String },
+}
+
+impl AgentError for MockError {
+    fn error_code(&self) -> &'static str {
+        match self {
+            MockError::Io { .. } => "MOCK_IO",
+            MockError::Parse { .. } => "MOCK_PARSE",
+        }
+    }
+
+    fn explanation(&self) -> String {
+        match self {
+            MockError::Io { path, source } => format!("The mock file {} could not be read: {}", path.display(), source),
+            MockError::Parse { path, reason } => {
+                format!("The mock file {} is not a valid mock definition: {}", path.display(), reason)
+            }
+        }
+    }
+
+    fn suggested_fixes(&self) -> Vec<String> {
+        match self {
+            MockError::Io { .. } => vec!["Check that the mocks directory exists and is readable.".to_string()],
+            MockError::Parse { .. } => vec![
+                "Each file must hold an object (or array of objects) with a `route` and a `loader` and/or `action` response."
+                    .to_string(),
+                "Example: { \"route\": \"/users/:id\", \"loader\": { \"body\": { \"name\": \"Ada\" } } }".to_string(),
+            ],
+        }
+    }
+
+    fn subsystem(&self) -> &'static str {
+        "mock"
+    }
+}
+
+/// A canned response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MockResponse {
+    /// HTTP-style status. Anything outside 2xx is returned as a `RouteError`.
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default)]
+    pub body: Value,
+    /// Artificial latency, useful for exercising loading states.
+    #[serde(default)]
+    pub delay_ms: u64,
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+impl MockResponse {
+    pub fn ok(body: Value) -> Self {
+        Self {
+            status: 200,
+            body,
+            delay_ms: 0,
+        }
+    }
+
+    fn into_result(self) -> Result<Value, RouteError> {
+        let message = || match &self.body {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        // A body shaped like an `ActionError` fakes a typed action error.
+        if (400..500).contains(&self.status)
+            && let Ok(error) = serde_json::from_value::<ActionError>(self.body.clone())
+        {
+            return Err(error.with_status(self.status).into());
+        }
+        match self.status {
+            200..=299 => Ok(self.body),
+            300..=399 => Err(RouteError::Redirect(message())),
+            401 => Err(RouteError::Unauthorized),
+            403 => Err(RouteError::Forbidden(message())),
+            404 => Err(RouteError::NotFound),
+            406 => Err(RouteError::NotAcceptable(message())),
+            400 | 422 => Err(RouteError::ValidationFailed(message())),
+            415 => Err(RouteError::UnsupportedMediaType(message())),
+            502..=504 => Err(RouteError::External(message())),
+            _ => Err(RouteError::InternalError(message())),
+        }
+    }
+}
+
+/// One entry of a JSON mock file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockDefinition {
+    pub route: String,
+    #[serde(default)]
+    pub loader: Option<MockResponse>,
+    #[serde(default)]
+    pub action: Option<MockResponse>,
+}
+
+/// A mock written in Rust. Receives the params and, for actions, the input.
+pub type MockHandler = Arc<dyn Fn(&Value, Option<&Value>) -> Result<Value, RouteError> + Send + Sync>;
+
+#[derive(Clone)]
+enum MockSource {
+    Static(MockResponse),
+    Handler(MockHandler),
+}
+
+impl MockSource {
+    async fn respond(&self, params: &Value, input: Option<&Value>) -> Result<Value, RouteError> {
+        match self {
+            MockSource::Static(response) => {
+                if response.delay_ms > 0 {
+                    tokio::time::sleep(Duration::from_millis(response.delay_ms)).await;
+                }
+                response.clone().into_result()
+            }
+            MockSource::Handler(handler) => handler(params, input),
+        }
+    }
+}
+
+/// Which registered mocks are active.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum MockSelection {
+    /// Every route that has a mock.
+    #[default]
+    All,
+    /// Only the listed route paths.
+    Only(Vec<String>),
+}
+
+impl MockSelection {
+    /// Parses `*` or a comma-separated list of paths.
+    pub fn parse(value: &str) -> Self {
+        match value.trim() {
+            "" | "*" => MockSelection::All,
+            list => MockSelection::Only(list.split(',').map(|s| s.trim().to_string()).collect()),
+        }
+    }
+
+    fn includes(&self, path: &str) -> bool {
+        match self {
+            MockSelection::All => true,
+            MockSelection::Only(paths) => paths.iter().any(|p| p == path),
+        }
+    }
+}
+
+/// The set of mocked loaders and actions installed on a `Router`.
+///
+/// ```rust,ignore
+/// let mocks = Mocks::load_dir("mocks")?
+///     .loader("/users/:id", |params, _| Ok(json!({ "id": params["id"], "name": "Ada" })));
+/// router.set_mocks(mocks);
+/// ```
+#[derive(Clone, Default)]
+pub struct Mocks {
+    loaders: HashMap<String, MockSource>,
+    actions: HashMap<String, MockSource>,
+    selection: MockSelection,
+}
+
+impl Mocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mocks the loader of `path` with a Rust closure.
+    pub fn loader<F>(mut self, path: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(&Value, Option<&Value>) -> Result<Value, RouteError> + Send + Sync + 'static,
+    {
+        self.loaders.insert(path.into(), MockSource::Handler(Arc::new(handler)));
+        self
+    }
+
+    /// Mocks the action of `path` with a Rust closure.
+    pub fn action<F>(mut self, path: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(&Value, Option<&Value>) -> Result<Value, RouteError> + Send + Sync + 'static,
+    {
+        self.actions.insert(path.into(), MockSource::Handler(Arc::new(handler)));
+        self
+    }
+
+    /// Adds the responses of a JSON mock definition.
+    pub fn with_definition(mut self, definition: MockDefinition) -> Self {
+        if let Some(loader) = definition.loader {
+            self.loaders.insert(definition.route.clone(), MockSource::Static(loader));
+        }
+        if let Some(action) = definition.action {
+            self.actions.insert(definition.route, MockSource::Static(action));
+        }
+        self
+    }
+
+    /// Restricts the active mocks to `selection`.
+    pub fn with_selection(mut self, selection: MockSelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Reads every `*.json` file under `dir`. A missing directory yields no mocks.
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Self, MockError> {
+        let mut mocks = Self::new();
+        for path in json_files(dir.as_ref())? {
+            let content = std::fs::read_to_string(&path).map_err(|source| MockError::Io {
+                path: path.clone(),
+                source,
+            })?;
+            let parse_error = |e: serde_json::Error| MockError::Parse {
+                path: path.clone(),
+                reason: e.to_string(),
+            };
+            let value: Value = serde_json::from_str(&content).map_err(parse_error)?;
+            let definitions: Vec<MockDefinition> = match value {
+                Value::Array(_) => serde_json::from_value(value).map_err(parse_error)?,
+                _ => vec![serde_json::from_value(value).map_err(parse_error)?],
+            };
+            for definition in definitions {
+                mocks = mocks.with_definition(definition);
+            }
+        }
+        Ok(mocks)
+    }
+
+    /// Loads the mocks selected by `montrs serve --mock`. Returns `None` when
+    /// mocking is off.
+    pub fn from_env() -> Result<Option<Self>, MockError> {
+        let Ok(routes) = std::env::var(MOCK_ROUTES_VAR) else {
+            return Ok(None);
+        };
+        let dir = std::env::var(MOCKS_DIR_VAR).unwrap_or_else(|_| "mocks".to_string());
+        Ok(Some(Self::load_dir(dir)?.with_selection(MockSelection::parse(&routes))))
+    }
+
+    /// Answers the loader of each route with the JSON body stored in its
+    /// fixture file. Relative paths are resolved against the current directory.
+    pub fn load_fixtures<P: AsRef<Path>>(fixtures: &HashMap<String, P>) -> Result<Self, MockError> {
+        let mut mocks = Self::new();
+        for (route, path) in fixtures {
+            let path = path.as_ref();
+            let content = std::fs::read_to_string(path).map_err(|source| MockError::Io {
+                path: path.to_path_buf(),
+                source,
+            })?;
+            let body: Value = serde_json::from_str(&content).map_err(|e| MockError::Parse {
+                path: path.to_path_buf(),
+                reason: e.to_string(),
+            })?;
+            mocks.loaders.insert(route.clone(), MockSource::Static(MockResponse::ok(body)));
+        }
+        Ok(mocks)
+    }
+
+    /// Loads the fixtures pinned by `montrs e2e`. Returns `None` unless the
+    /// app runs under `MONTRS_E2E=1` with fixtures configured.
+    pub fn fixtures_from_env() -> Result<Option<Self>, MockError> {
+        if std::env::var(E2E_VAR).as_deref() != Ok("1") {
+            return Ok(None);
+        }
+        let Ok(value) = std::env::var(E2E_FIXTURES_VAR) else {
+            return Ok(None);
+        };
+        let fixtures: HashMap<String, PathBuf> = serde_json::from_str(&value).map_err(|e| MockError::Parse {
+            path: PathBuf::from(E2E_FIXTURES_VAR),
+            reason: e.to_string(),
+        })?;
+        Ok(Some(Self::load_fixtures(&fixtures)?))
+    }
+
+    /// Adds the mocks of `other`, which win for routes both define. Routes of
+    /// `other` stay active even if this set is restricted by a selection.
+    pub fn merge(mut self, other: Mocks) -> Self {
+        if let MockSelection::Only(paths) = &mut self.selection {
+            paths.extend(other.routes().into_iter().map(String::from));
+        }
+        self.loaders.extend(other.loaders);
+        self.actions.extend(other.actions);
+        self
+    }
+
+    /// Paths with an active loader or action mock, sorted.
+    pub fn routes(&self) -> Vec<&str> {
+        let mut routes: Vec<&str> = self
+            .loaders
+            .keys()
+            .chain(self.actions.keys())
+            .map(String::as_str)
+            .filter(|path| self.selection.includes(path))
+            .collect();
+        routes.sort();
+        routes.dedup();
+        routes
+    }
+
+    pub(crate) async fn load(&self, path: &str, params: &Value) -> Option<Result<Value, RouteError>> {
+        let source = self.loaders.get(path).filter(|_| self.selection.includes(path))?;
+        Some(source.respond(params, None).await)
+    }
+
+    pub(crate) async fn act(&self, path: &str, params: &Value, input: &Value) -> Option<Result<Value, RouteError>> {
+        let source = self.actions.get(path).filter(|_| self.selection.includes(path))?;
+        Some(source.respond(params, Some(input)).await)
+    }
+}
+
+fn json_files(dir: &Path) -> Result<Vec<PathBuf>, MockError> {
+    let mut files = Vec::new();
+    if !dir.exists() {
+        return Ok(files);
+    }
+    let entries = std::fs::read_dir(dir).map_err(|source| MockError::Io {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(json_files(&path)?);
+        } else if path.extension().and_then(|e| e.to_str()) == Some("json") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
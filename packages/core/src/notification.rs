@@ -0,0 +1,1057 @@
+//! montrs-core/src/notification.rs: User notifications.
+//! [`Notifications::notify`] stores a [`Notification`] in the
+//! [`NotificationStore`], where it stays unread until the user reads it, and
+//! sends it on the channels the user's [`Preferences`] allow: in-app to the
+//! user's open event streams (SSE, or a WebSocket fed from
+//! [`Notifications::subscribe`]), email through the application's [`Mailer`],
+//! and Web Push to every browser the user registered, encrypted per RFC 8291
+//! and signed with the application's VAPID keys (RFC 8292).
+//! [`NotificationsPlate`] registers the loaders and actions for the inbox,
+//! the preferences and push registration under a configurable prefix.
+//! `montrs_orm::notification::NotificationTables` keeps everything in the
+//! application database.
+
+use crate::crash::correlation_id;
+use crate::response::{ResponseError, StreamingResponse};
+use crate::{
+    AppConfig, CurrentUser, Messages, Plate, PlateContext, Route, RouteAction, RouteContext, RouteError, RouteLoader, RouteParams,
+    RouteView, Router, Subscription,
+};
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::{Aes128Gcm, Nonce};
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, BoxStream, StreamExt};
+use hkdf::Hkdf;
+use p256::ecdsa::signature::Signer;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Notifications a live subscriber may fall behind by before it misses some.
+const LIVE_CAPACITY: usize = 256;
+/// How often an idle event stream sends a comment to keep proxies from closing it.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+/// The record size of an encrypted push message. Push services accept 4096
+/// byte bodies, so the payload gets what the 86 byte header and the 17 bytes
+/// of padding delimiter and tag leave.
+const PUSH_RECORD_SIZE: usize = 4096;
+const PUSH_HEADER_LEN: usize = 86;
+/// Largest push payload, in bytes.
+pub const MAX_PUSH_PAYLOAD: usize = PUSH_RECORD_SIZE - PUSH_HEADER_LEN - 17;
+
+/// Something a user should hear about.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: String,
+    pub user_id: String,
+    /// What happened, e.g. `order.shipped`. Users mute notifications by kind.
+    pub kind: String,
+    pub title: String,
+    pub body: String,
+    /// Where opening the notification leads.
+    pub url: Option<String>,
+    /// Anything else the client needs.
+    pub data: serde_json::Value,
+    pub read_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Notification {
+    pub fn new(user_id: impl Into<String>, kind: impl Into<String>, title: impl Into<String>) -> Self {
+        Self {
+            id: correlation_id(),
+            user_id: user_id.into(),
+            kind: kind.into(),
+            title: title.into(),
+            body: String::new(),
+            url: None,
+            data: serde_json::Value::Null,
+            read_at: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn with_body(mut self, body: impl Into<String>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    pub fn with_url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    pub fn with_data(mut self, data: serde_json::Value) -> Self {
+        self.data = data;
+        self
+    }
+
+    pub fn is_read(&self) -> bool {
+        self.read_at.is_some()
+    }
+
+    /// The plain-text email body: the body, then the link.
+    fn email_text(&self) -> String {
+        match &self.url {
+            Some(url) if self.body.is_empty() => url.clone(),
+            Some(url) => format!("{}\n\n{}", self.body, url),
+            None => self.body.clone(),
+        }
+    }
+
+    /// The JSON a service worker receives in its `push` event.
+    fn push_payload(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "kind": self.kind,
+            "title": self.title,
+            "body": self.body,
+            "url": self.url,
+            "data": self.data,
+        })
+    }
+}
+
+/// A way of reaching the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+    /// The user's open event streams.
+    InApp,
+    Email,
+    /// Web Push, to the browsers the user registered.
+    Push,
+}
+
+/// What a user wants to hear about, and how.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub user_id: String,
+    /// Channels the user turned off.
+    pub disabled: Vec<Channel>,
+    /// Kinds the user does not want at all. `order.*` mutes every `order.` kind.
+    pub muted: Vec<String>,
+    /// Where email goes. No email is sent without one.
+    pub email: Option<String>,
+}
+
+impl Preferences {
+    /// Everything on, no email address.
+    pub fn new(user_id: impl Into<String>) -> Self {
+        Self { user_id: user_id.into(), ..Self::default() }
+    }
+
+    pub fn allows(&self, channel: Channel) -> bool {
+        !self.disabled.contains(&channel)
+    }
+
+    pub fn mutes(&self, kind: &str) -> bool {
+        self.muted.iter().any(|filter| match filter.strip_suffix('*') {
+            Some(prefix) => kind.starts_with(prefix),
+            None => filter == kind,
+        })
+    }
+}
+
+/// The keys of a browser's push subscription, as `PushSubscription.toJSON()`
+/// returns them (base64url).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PushKeys {
+    /// The browser's P-256 public key.
+    pub p256dh: String,
+    /// The 16 byte authentication secret.
+    pub auth: String,
+}
+
+impl PushKeys {
+    /// The browser's public key, as sent and parsed, and the auth secret.
+    fn decode(&self) -> anyhow::Result<(Vec<u8>, p256::PublicKey, Vec<u8>)> {
+        let public = decode_base64url(&self.p256dh)?;
+        let key = p256::PublicKey::from_sec1_bytes(&public).map_err(|_| anyhow::anyhow!("p256dh is not a P-256 public key"))?;
+        let auth = decode_base64url(&self.auth)?;
+        if auth.len() != 16 {
+            anyhow::bail!("auth is {} bytes; it should be 16", auth.len());
+        }
+        Ok((public, key, auth))
+    }
+}
+
+/// A browser that receives a user's push messages.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PushSubscription {
+    pub user_id: String,
+    /// The push service URL; it identifies the subscription.
+    pub endpoint: String,
+    pub keys: PushKeys,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PushSubscription {
+    pub fn new(user_id: impl Into<String>, endpoint: impl Into<String>, keys: PushKeys) -> Self {
+        Self { user_id: user_id.into(), endpoint: endpoint.into(), keys, created_at: Utc::now() }
+    }
+}
+
+/// A page of a user's notifications.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Inbox {
+    /// Unread notifications in total, not only on this page.
+    pub unread: u64,
+    /// Newest first.
+    pub notifications: Vec<Notification>,
+}
+
+/// Keeps notifications, preferences and push subscriptions.
+#[async_trait]
+pub trait NotificationStore: Send + Sync + 'static {
+    async fn save_notification(&self, notification: &Notification) -> anyhow::Result<()>;
+
+    /// A user's notifications, newest first, at most `limit`.
+    async fn notifications(&self, user_id: &str, unread_only: bool, limit: usize) -> anyhow::Result<Vec<Notification>>;
+
+    async fn unread_count(&self, user_id: &str) -> anyhow::Result<u64>;
+
+    /// Marks the user's notifications with these IDs, or all of them, read at
+    /// `at`. Returns how many were unread.
+    async fn mark_read(&self, user_id: &str, ids: Option<&[String]>, at: DateTime<Utc>) -> anyhow::Result<usize>;
+
+    async fn preferences(&self, user_id: &str) -> anyhow::Result<Option<Preferences>>;
+
+    /// Inserts or replaces the preferences of the same user.
+    async fn save_preferences(&self, preferences: &Preferences) -> anyhow::Result<()>;
+
+    /// Inserts or replaces the subscription with the same endpoint.
+    async fn save_push_subscription(&self, subscription: &PushSubscription) -> anyhow::Result<()>;
+
+    async fn push_subscriptions(&self, user_id: &str) -> anyhow::Result<Vec<PushSubscription>>;
+
+    /// Returns whether the subscription existed.
+    async fn remove_push_subscription(&self, endpoint: &str) -> anyhow::Result<bool>;
+}
+
+#[async_trait]
+impl<T: NotificationStore> NotificationStore for Arc<T> {
+    async fn save_notification(&self, notification: &Notification) -> anyhow::Result<()> {
+        (**self).save_notification(notification).await
+    }
+
+    async fn notifications(&self, user_id: &str, unread_only: bool, limit: usize) -> anyhow::Result<Vec<Notification>> {
+        (**self).notifications(user_id, unread_only, limit).await
+    }
+
+    async fn unread_count(&self, user_id: &str) -> anyhow::Result<u64> {
+        (**self).unread_count(user_id).await
+    }
+
+    async fn mark_read(&self, user_id: &str, ids: Option<&[String]>, at: DateTime<Utc>) -> anyhow::Result<usize> {
+        (**self).mark_read(user_id, ids, at).await
+    }
+
+    async fn preferences(&self, user_id: &str) -> anyhow::Result<Option<Preferences>> {
+        (**self).preferences(user_id).await
+    }
+
+    async fn save_preferences(&self, preferences: &Preferences) -> anyhow::Result<()> {
+        (**self).save_preferences(preferences).await
+    }
+
+    async fn save_push_subscription(&self, subscription: &PushSubscription) -> anyhow::Result<()> {
+        (**self).save_push_subscription(subscription).await
+    }
+
+    async fn push_subscriptions(&self, user_id: &str) -> anyhow::Result<Vec<PushSubscription>> {
+        (**self).push_subscriptions(user_id).await
+    }
+
+    async fn remove_push_subscription(&self, endpoint: &str) -> anyhow::Result<bool> {
+        (**self).remove_push_subscription(endpoint).await
+    }
+}
+
+/// A store that lives as long as the process, for tests and development.
+#[derive(Default)]
+pub struct MemoryNotificationStore {
+    notifications: Mutex<Vec<Notification>>,
+    preferences: Mutex<HashMap<String, Preferences>>,
+    push_subscriptions: Mutex<Vec<PushSubscription>>,
+}
+
+impl MemoryNotificationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl NotificationStore for MemoryNotificationStore {
+    async fn save_notification(&self, notification: &Notification) -> anyhow::Result<()> {
+        let mut notifications = self.notifications.lock().unwrap_or_else(|e| e.into_inner());
+        notifications.retain(|n| n.id != notification.id);
+        notifications.push(notification.clone());
+        Ok(())
+    }
+
+    async fn notifications(&self, user_id: &str, unread_only: bool, limit: usize) -> anyhow::Result<Vec<Notification>> {
+        let notifications = self.notifications.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(notifications
+            .iter()
+            .rev()
+            .filter(|n| n.user_id == user_id && !(unread_only && n.is_read()))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn unread_count(&self, user_id: &str) -> anyhow::Result<u64> {
+        let notifications = self.notifications.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(notifications.iter().filter(|n| n.user_id == user_id && !n.is_read()).count() as u64)
+    }
+
+    async fn mark_read(&self, user_id: &str, ids: Option<&[String]>, at: DateTime<Utc>) -> anyhow::Result<usize> {
+        let mut notifications = self.notifications.lock().unwrap_or_else(|e| e.into_inner());
+        let mut marked = 0;
+        for notification in notifications.iter_mut() {
+            if notification.user_id == user_id && !notification.is_read() && ids.is_none_or(|ids| ids.contains(&notification.id)) {
+                notification.read_at = Some(at);
+                marked += 1;
+            }
+        }
+        Ok(marked)
+    }
+
+    async fn preferences(&self, user_id: &str) -> anyhow::Result<Option<Preferences>> {
+        Ok(self.preferences.lock().unwrap_or_else(|e| e.into_inner()).get(user_id).cloned())
+    }
+
+    async fn save_preferences(&self, preferences: &Preferences) -> anyhow::Result<()> {
+        let mut all = self.preferences.lock().unwrap_or_else(|e| e.into_inner());
+        all.insert(preferences.user_id.clone(), preferences.clone());
+        Ok(())
+    }
+
+    async fn save_push_subscription(&self, subscription: &PushSubscription) -> anyhow::Result<()> {
+        let mut subscriptions = self.push_subscriptions.lock().unwrap_or_else(|e| e.into_inner());
+        subscriptions.retain(|s| s.endpoint != subscription.endpoint);
+        subscriptions.push(subscription.clone());
+        Ok(())
+    }
+
+    async fn push_subscriptions(&self, user_id: &str) -> anyhow::Result<Vec<PushSubscription>> {
+        let subscriptions = self.push_subscriptions.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(subscriptions.iter().filter(|s| s.user_id == user_id).cloned().collect())
+    }
+
+    async fn remove_push_subscription(&self, endpoint: &str) -> anyhow::Result<bool> {
+        let mut subscriptions = self.push_subscriptions.lock().unwrap_or_else(|e| e.into_inner());
+        let before = subscriptions.len();
+        subscriptions.retain(|s| s.endpoint != endpoint);
+        Ok(subscriptions.len() < before)
+    }
+}
+
+/// Sends email for the email channel. Implement it over your SMTP client or
+/// email API.
+#[async_trait]
+pub trait Mailer: Send + Sync + 'static {
+    async fn send(&self, to: &str, subject: &str, text: &str) -> anyhow::Result<()>;
+}
+
+/// The application server's VAPID key pair, which signs every push request.
+/// Browsers are given the public key when they subscribe, and push services
+/// reject requests signed with another key, so keep the private key stable
+/// (in the secrets file, for example).
+#[derive(Clone)]
+pub struct VapidKeys {
+    secret: p256::SecretKey,
+}
+
+impl VapidKeys {
+    /// A new key pair. Store [`private_key`](Self::private_key) and load it
+    /// with [`from_base64`](Self::from_base64) from then on.
+    pub fn generate() -> Self {
+        Self { secret: p256::SecretKey::random(&mut OsRng) }
+    }
+
+    /// Loads the base64url private key printed by [`private_key`](Self::private_key)
+    /// or by other Web Push tools.
+    pub fn from_base64(private_key: &str) -> anyhow::Result<Self> {
+        let bytes = decode_base64url(private_key)?;
+        let secret = p256::SecretKey::from_slice(&bytes).map_err(|_| anyhow::anyhow!("not a P-256 private key"))?;
+        Ok(Self { secret })
+    }
+
+    /// The private key, base64url.
+    pub fn private_key(&self) -> String {
+        URL_SAFE_NO_PAD.encode(self.secret.to_bytes())
+    }
+
+    /// The public key, base64url: the `applicationServerKey` browsers subscribe with.
+    pub fn public_key(&self) -> String {
+        URL_SAFE_NO_PAD.encode(self.secret.public_key().to_encoded_point(false).as_bytes())
+    }
+}
+
+/// Sends the push requests.
+#[async_trait]
+pub trait PushTransport: Send + Sync + 'static {
+    /// POSTs `body` and returns the response status. `Err` means no response,
+    /// e.g. a connection error or a timeout.
+    async fn post(&self, url: &str, headers: &[(String, String)], body: Vec<u8>) -> Result<u16, String>;
+}
+
+#[async_trait]
+impl PushTransport for reqwest::Client {
+    async fn post(&self, url: &str, headers: &[(String, String)], body: Vec<u8>) -> Result<u16, String> {
+        let mut request = reqwest::Client::post(self, url).body(body);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        request.send().await.map(|response| response.status().as_u16()).map_err(|e| e.to_string())
+    }
+}
+
+/// What the push service made of a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    Sent,
+    /// The subscription expired or the user revoked it (404 or 410).
+    Gone,
+}
+
+/// Sends Web Push messages.
+pub struct WebPush {
+    keys: VapidKeys,
+    subject: String,
+    ttl: Duration,
+    transport: Arc<dyn PushTransport>,
+}
+
+impl WebPush {
+    /// `subject` is a `mailto:` or `https:` URL where the push service can
+    /// reach you about your traffic. Messages are kept for a day while the
+    /// browser is offline.
+    pub fn new(keys: VapidKeys, subject: impl Into<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .user_agent(concat!("montrs-notifications/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .unwrap_or_default();
+        Self { keys, subject: subject.into(), ttl: Duration::from_secs(86_400), transport: Arc::new(client) }
+    }
+
+    /// How long the push service keeps a message for an offline browser.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Sends through `transport` instead of HTTP, e.g. in tests.
+    pub fn with_transport(mut self, transport: impl PushTransport) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    pub fn keys(&self) -> &VapidKeys {
+        &self.keys
+    }
+
+    /// Encrypts `payload` for `subscription` and sends it. Payloads are
+    /// limited to [`MAX_PUSH_PAYLOAD`] bytes.
+    pub async fn send(&self, subscription: &PushSubscription, payload: &[u8]) -> anyhow::Result<PushOutcome> {
+        let body = encrypt(&subscription.keys, payload)?;
+        let headers = vec![
+            ("Authorization".to_string(), self.authorization(&subscription.endpoint)?),
+            ("Content-Encoding".to_string(), "aes128gcm".to_string()),
+            ("Content-Type".to_string(), "application/octet-stream".to_string()),
+            ("TTL".to_string(), self.ttl.as_secs().to_string()),
+        ];
+        match self.transport.post(&subscription.endpoint, &headers, body).await {
+            Ok(status) if (200..300).contains(&status) => Ok(PushOutcome::Sent),
+            Ok(404 | 410) => Ok(PushOutcome::Gone),
+            Ok(status) => Err(anyhow::anyhow!("push service answered {}", status)),
+            Err(e) => Err(anyhow::anyhow!(e)),
+        }
+    }
+
+    /// The `vapid` authorization: a JWT for the endpoint's origin, signed with
+    /// ES256 and valid for 12 hours, and the public key.
+    fn authorization(&self, endpoint: &str) -> anyhow::Result<String> {
+        let audience = reqwest::Url::parse(endpoint)?.origin().ascii_serialization();
+        let claims = serde_json::json!({
+            "aud": audience,
+            "exp": Utc::now().timestamp() + 12 * 3600,
+            "sub": self.subject,
+        });
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(br#"{"typ":"JWT","alg":"ES256"}"#),
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&claims)?)
+        );
+        let signature: p256::ecdsa::Signature = p256::ecdsa::SigningKey::from(&self.keys.secret).sign(signing_input.as_bytes());
+        Ok(format!("vapid t={}.{}, k={}", signing_input, URL_SAFE_NO_PAD.encode(signature.to_bytes()), self.keys.public_key()))
+    }
+}
+
+/// Encrypts a push message with `aes128gcm` as a single record (RFC 8291).
+fn encrypt(keys: &PushKeys, payload: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if payload.len() > MAX_PUSH_PAYLOAD {
+        anyhow::bail!("push payload is {} bytes; the limit is {}", payload.len(), MAX_PUSH_PAYLOAD);
+    }
+    let (browser_public, browser_key, auth) = keys.decode()?;
+
+    let ephemeral = p256::ecdh::EphemeralSecret::random(&mut OsRng);
+    let server_public = ephemeral.public_key().to_encoded_point(false);
+    let shared = ephemeral.diffie_hellman(&browser_key);
+
+    let mut info = b"WebPush: info\0".to_vec();
+    info.extend_from_slice(&browser_public);
+    info.extend_from_slice(server_public.as_bytes());
+    let mut ikm = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&auth), shared.raw_secret_bytes())
+        .expand(&info, &mut ikm)
+        .map_err(|_| anyhow::anyhow!("key derivation failed"))?;
+
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let prk = Hkdf::<Sha256>::new(Some(&salt), &ikm);
+    let (mut key, mut nonce) = ([0u8; 16], [0u8; 12]);
+    prk.expand(b"Content-Encoding: aes128gcm\0", &mut key)
+        .and_then(|_| prk.expand(b"Content-Encoding: nonce\0", &mut nonce))
+        .map_err(|_| anyhow::anyhow!("key derivation failed"))?;
+
+    // The 0x02 delimiter marks the last (and only) record.
+    let mut plaintext = payload.to_vec();
+    plaintext.push(2);
+    let ciphertext = Aes128Gcm::new(&key.into())
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+        .map_err(|_| anyhow::anyhow!("encryption failed"))?;
+
+    let mut body = Vec::with_capacity(PUSH_HEADER_LEN + ciphertext.len());
+    body.extend_from_slice(&salt);
+    body.extend_from_slice(&(PUSH_RECORD_SIZE as u32).to_be_bytes());
+    body.push(server_public.len() as u8);
+    body.extend_from_slice(server_public.as_bytes());
+    body.extend_from_slice(&ciphertext);
+    Ok(body)
+}
+
+fn decode_base64url(text: &str) -> anyhow::Result<Vec<u8>> {
+    Ok(URL_SAFE_NO_PAD.decode(text.trim().trim_end_matches('='))?)
+}
+
+/// Stores notifications and sends them on each user's channels.
+///
+/// ```rust,ignore
+/// struct MyMailer;
+///
+/// #[async_trait]
+/// impl Mailer for MyMailer {
+///     async fn send(&self, to: &str, subject: &str, text: &str) -> anyhow::Result<()> {
+///         // Hand the message to your SMTP client or email API.
+///         Ok(())
+///     }
+/// }
+///
+/// let notifications = Arc::new(
+///     Notifications::new(NotificationTables::new(db.clone()))
+///         .with_mailer(MyMailer)
+///         .with_push(WebPush::new(VapidKeys::from_base64(&vapid_key)?, "mailto:ops@example.com")),
+/// );
+///
+/// notifications.notify(Notification::new(user.id, "order.shipped", "Your order is on its way").with_url(tracking_url)).await?;
+/// ```
+pub struct Notifications {
+    store: Arc<dyn NotificationStore>,
+    mailer: Option<Arc<dyn Mailer>>,
+    push: Option<WebPush>,
+    live: broadcast::Sender<Arc<Notification>>,
+}
+
+impl Notifications {
+    /// In-app only, until a mailer and Web Push are added.
+    pub fn new(store: impl NotificationStore) -> Self {
+        Self { store: Arc::new(store), mailer: None, push: None, live: broadcast::channel(LIVE_CAPACITY).0 }
+    }
+
+    /// Sends the email channel through `mailer`.
+    pub fn with_mailer(mut self, mailer: impl Mailer) -> Self {
+        self.mailer = Some(Arc::new(mailer));
+        self
+    }
+
+    /// Sends the push channel through `push`.
+    pub fn with_push(mut self, push: WebPush) -> Self {
+        self.push = Some(push);
+        self
+    }
+
+    pub fn store(&self) -> &Arc<dyn NotificationStore> {
+        &self.store
+    }
+
+    pub fn push(&self) -> Option<&WebPush> {
+        self.push.as_ref()
+    }
+
+    /// Stores `notification` and sends it on every channel the user allows.
+    /// Returns `None`, storing nothing, when the user muted its kind. Email
+    /// and push failures are logged rather than returned: the notification
+    /// is in the inbox either way.
+    pub async fn notify(&self, notification: Notification) -> anyhow::Result<Option<Notification>> {
+        let preferences = self.preferences(&notification.user_id).await?;
+        if preferences.mutes(&notification.kind) {
+            return Ok(None);
+        }
+        self.store.save_notification(&notification).await?;
+
+        if preferences.allows(Channel::InApp) {
+            // No receivers is fine: nobody has the app open.
+            let _ = self.live.send(Arc::new(notification.clone()));
+        }
+        if preferences.allows(Channel::Email)
+            && let (Some(mailer), Some(to)) = (&self.mailer, &preferences.email)
+            && let Err(e) = mailer.send(to, &notification.title, &notification.email_text()).await
+        {
+            tracing::warn!(user = %notification.user_id, kind = %notification.kind, error = %e, "notification email failed");
+        }
+        if preferences.allows(Channel::Push)
+            && let Some(push) = &self.push
+            && let Err(e) = self.send_push(push, &notification).await
+        {
+            tracing::warn!(user = %notification.user_id, kind = %notification.kind, error = %e, "push notifications failed");
+        }
+        Ok(Some(notification))
+    }
+
+    /// Pushes to every browser the user registered, forgetting the ones the
+    /// push service says are gone.
+    async fn send_push(&self, push: &WebPush, notification: &Notification) -> anyhow::Result<()> {
+        let payload = serde_json::to_vec(&notification.push_payload())?;
+        for subscription in self.store.push_subscriptions(&notification.user_id).await? {
+            match push.send(&subscription, &payload).await {
+                Ok(PushOutcome::Sent) => {}
+                Ok(PushOutcome::Gone) => {
+                    self.store.remove_push_subscription(&subscription.endpoint).await?;
+                }
+                Err(e) => {
+                    tracing::warn!(user = %notification.user_id, endpoint = %subscription.endpoint, error = %e, "push notification failed")
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The user's newest `limit` notifications and their unread count.
+    pub async fn inbox(&self, user_id: &str, unread_only: bool, limit: usize) -> anyhow::Result<Inbox> {
+        Ok(Inbox {
+            unread: self.store.unread_count(user_id).await?,
+            notifications: self.store.notifications(user_id, unread_only, limit).await?,
+        })
+    }
+
+    /// Marks the listed notifications, or all of them, read. Returns how many were unread.
+    pub async fn mark_read(&self, user_id: &str, ids: Option<&[String]>) -> anyhow::Result<usize> {
+        self.store.mark_read(user_id, ids, Utc::now()).await
+    }
+
+    /// The user's preferences, or the defaults when they never saved any.
+    pub async fn preferences(&self, user_id: &str) -> anyhow::Result<Preferences> {
+        Ok(self.store.preferences(user_id).await?.unwrap_or_else(|| Preferences::new(user_id)))
+    }
+
+    pub async fn set_preferences(&self, preferences: &Preferences) -> anyhow::Result<()> {
+        self.store.save_preferences(preferences).await
+    }
+
+    pub async fn register_push(&self, subscription: &PushSubscription) -> anyhow::Result<()> {
+        self.store.save_push_subscription(subscription).await
+    }
+
+    /// Forgets the user's subscription at `endpoint`. Returns whether it was theirs.
+    pub async fn unregister_push(&self, user_id: &str, endpoint: &str) -> anyhow::Result<bool> {
+        let owned = self.store.push_subscriptions(user_id).await?.iter().any(|s| s.endpoint == endpoint);
+        Ok(owned && self.store.remove_push_subscription(endpoint).await?)
+    }
+
+    /// The user's notifications as they are sent from now on, in this process.
+    /// Forward them to a WebSocket, or use [`event_stream`](Self::event_stream)
+    /// for Server-Sent Events.
+    pub fn subscribe(&self, user_id: impl Into<String>) -> BoxStream<'static, Notification> {
+        let user_id = user_id.into();
+        stream::unfold(self.live.subscribe(), move |mut receiver| {
+            let user_id = user_id.clone();
+            async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(notification) if notification.user_id == user_id => return Some(((*notification).clone(), receiver)),
+                        Ok(_) => {}
+                        Err(broadcast::error::RecvError::Lagged(missed)) => {
+                            tracing::debug!(user = %user_id, missed, "live notification subscriber fell behind");
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            }
+        })
+        .boxed()
+    }
+
+    /// A `text/event-stream` response sending each of the user's new
+    /// notifications as a `notification` event with the notification as JSON,
+    /// and a comment every 15 seconds while idle.
+    pub fn event_stream(&self, user_id: impl Into<String>) -> StreamingResponse {
+        let events = self.subscribe(user_id).map(|notification| -> Result<Vec<u8>, ResponseError> {
+            let json = serde_json::to_string(&notification).map_err(|e| ResponseError::Serialization(e.to_string()))?;
+            Ok(format!("event: notification\ndata: {}\n\n", json).into_bytes())
+        });
+        let keep_alive = stream::unfold((), |()| async {
+            tokio::time::sleep(KEEP_ALIVE).await;
+            Some((Ok(b": keep-alive\n\n".to_vec()), ()))
+        });
+        StreamingResponse::new(stream::select(events, keep_alive).boxed())
+            .with_content_type("text/event-stream")
+            .with_header("Cache-Control", "no-cache")
+    }
+}
+
+/// Registers the notification loaders and actions, under `/notifications`
+/// unless [`with_prefix`](Self::with_prefix) says otherwise:
+///
+/// | Path | Loader | Action |
+/// | --- | --- | --- |
+/// | `/notifications` | The [`Inbox`]; `?unread=true` and `?limit=` | Marks `{"ids": [...]}`, or everything, read |
+/// | `/notifications/preferences` | The user's [`Preferences`] | Saves them |
+/// | `/notifications/push` | `{"public_key": ...}` for `pushManager.subscribe` | Registers `subscription.toJSON()`; `{"endpoint"}` alone unregisters |
+///
+/// `/notifications/live` is a [`Subscription`] sending the user's new
+/// notifications in-app as they are sent, served as Server-Sent Events.
+/// Every route answers `Unauthorized` when `current_user` finds no user.
+///
+/// ```rust,ignore
+/// let plate = NotificationsPlate::new(notifications.clone(), |ctx: &RouteContext<'_, AppCfg>| {
+///     ctx.request().and_then(|r| r.header("x-user-id").map(str::to_string))
+/// })
+/// .with_prefix("/api/notifications");
+/// ```
+pub struct NotificationsPlate<C: AppConfig> {
+    handle: Handle<C>,
+    prefix: String,
+}
+
+impl<C: AppConfig> NotificationsPlate<C> {
+    pub fn new(
+        notifications: Arc<Notifications>,
+        current_user: impl Fn(&RouteContext<'_, C>) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        Self { handle: Handle { notifications, current_user: Arc::new(current_user) }, prefix: "/notifications".to_string() }
+    }
+
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// `prefix` followed by `suffix`, kept for the life of the router.
+    fn path(&self, suffix: &str) -> &'static str {
+        let path = format!("{}{}", self.prefix.trim_end_matches('/'), suffix);
+        crate::router::intern(if path.is_empty() { "/".to_string() } else { path })
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> Plate<C> for NotificationsPlate<C> {
+    fn name(&self) -> &'static str {
+        "notifications"
+    }
+
+    fn description(&self) -> &'static str {
+        "User notifications: inbox with read state, delivery preferences, a live stream and Web Push registration"
+    }
+
+    async fn init(&self, _ctx: &mut PlateContext<C>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    fn register_routes(&self, router: &mut Router<C>) {
+        router.register_at(self.path(""), InboxRoute(self.handle.clone()));
+        router.register_at(self.path("/preferences"), PreferencesRoute(self.handle.clone()));
+        router.register_at(self.path("/push"), PushRoute(self.handle.clone()));
+        router.subscription_at(self.path("/live"), LiveRoute(self.handle.clone()));
+    }
+}
+
+struct Handle<C: AppConfig> {
+    notifications: Arc<Notifications>,
+    current_user: CurrentUser<C>,
+}
+
+impl<C: AppConfig> Clone for Handle<C> {
+    fn clone(&self) -> Self {
+        Self { notifications: self.notifications.clone(), current_user: self.current_user.clone() }
+    }
+}
+
+impl<C: AppConfig> Handle<C> {
+    fn user(&self, ctx: &RouteContext<'_, C>) -> Result<String, RouteError> {
+        (self.current_user)(ctx).ok_or(RouteError::Unauthorized)
+    }
+}
+
+fn internal(e: anyhow::Error) -> RouteError {
+    RouteError::InternalError(e.to_string())
+}
+
+/// Query parameters of the inbox.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct InboxParams {
+    /// Only unread notifications.
+    #[serde(default, deserialize_with = "crate::param::deserialize")]
+    pub unread: bool,
+    /// At most this many; 50 by default.
+    pub limit: Option<usize>,
+}
+
+impl RouteParams for InboxParams {
+    fn params() -> Vec<crate::ParamSpec> {
+        vec![crate::ParamSpec::of::<bool>("unread", false), crate::ParamSpec::of::<usize>("limit", false)]
+    }
+}
+
+/// Input of the inbox action.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MarkRead {
+    /// The notifications to mark read; all of them when absent.
+    #[serde(default)]
+    pub ids: Option<Vec<String>>,
+}
+
+/// Output of the inbox action.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadReceipt {
+    pub marked: usize,
+    pub unread: u64,
+}
+
+/// Input of the push action: `subscription.toJSON()` to register, the
+/// endpoint alone to unregister.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PushRegistration {
+    pub endpoint: String,
+    #[serde(default)]
+    pub keys: Option<PushKeys>,
+}
+
+/// Output of the push loader.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PushConfig {
+    /// The VAPID public key, or `None` when push is not configured.
+    pub public_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NoParams {}
+
+impl RouteParams for NoParams {}
+
+struct NoView;
+
+impl RouteView for NoView {
+    fn render(&self) -> impl leptos::prelude::IntoView {}
+}
+
+#[derive(Clone)]
+struct InboxRoute<C: AppConfig>(Handle<C>);
+
+impl<C: AppConfig> Route<C> for InboxRoute<C> {
+    type Params = InboxParams;
+    type Loader = Self;
+    type Action = Self;
+    type View = NoView;
+
+    fn path() -> &'static str {
+        "/notifications"
+    }
+    fn loader(&self) -> Self {
+        self.clone()
+    }
+    fn action(&self) -> Self {
+        self.clone()
+    }
+    fn view(&self) -> NoView {
+        NoView
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> RouteLoader<InboxParams, C> for InboxRoute<C> {
+    type Output = Inbox;
+
+    async fn load(&self, ctx: RouteContext<'_, C>, params: InboxParams) -> Result<Inbox, RouteError> {
+        let user = self.0.user(&ctx)?;
+        self.0.notifications.inbox(&user, params.unread, params.limit.unwrap_or(50)).await.map_err(internal)
+    }
+
+    fn description(&self) -> &'static str {
+        "The signed-in user's notifications, newest first, with the unread count"
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> RouteAction<InboxParams, C> for InboxRoute<C> {
+    type Input = MarkRead;
+    type Output = ReadReceipt;
+
+    async fn act(&self, ctx: RouteContext<'_, C>, _params: InboxParams, input: MarkRead) -> Result<ReadReceipt, RouteError> {
+        let user = self.0.user(&ctx)?;
+        let notifications = &self.0.notifications;
+        let marked = notifications.mark_read(&user, input.ids.as_deref()).await.map_err(internal)?;
+        let unread = notifications.store().unread_count(&user).await.map_err(internal)?;
+        Ok(ReadReceipt { marked, unread })
+    }
+
+    fn description(&self) -> &'static str {
+        "Marks the listed notifications, or all of them, read"
+    }
+}
+
+#[derive(Clone)]
+struct PreferencesRoute<C: AppConfig>(Handle<C>);
+
+impl<C: AppConfig> Route<C> for PreferencesRoute<C> {
+    type Params = NoParams;
+    type Loader = Self;
+    type Action = Self;
+    type View = NoView;
+
+    fn path() -> &'static str {
+        "/notifications/preferences"
+    }
+    fn loader(&self) -> Self {
+        self.clone()
+    }
+    fn action(&self) -> Self {
+        self.clone()
+    }
+    fn view(&self) -> NoView {
+        NoView
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> RouteLoader<NoParams, C> for PreferencesRoute<C> {
+    type Output = Preferences;
+
+    async fn load(&self, ctx: RouteContext<'_, C>, _params: NoParams) -> Result<Preferences, RouteError> {
+        let user = self.0.user(&ctx)?;
+        self.0.notifications.preferences(&user).await.map_err(internal)
+    }
+
+    fn description(&self) -> &'static str {
+        "The signed-in user's notification channels, muted kinds and email address"
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> RouteAction<NoParams, C> for PreferencesRoute<C> {
+    type Input = Preferences;
+    type Output = Preferences;
+
+    async fn act(&self, ctx: RouteContext<'_, C>, _params: NoParams, input: Preferences) -> Result<Preferences, RouteError> {
+        let preferences = Preferences { user_id: self.0.user(&ctx)?, ..input };
+        self.0.notifications.set_preferences(&preferences).await.map_err(internal)?;
+        Ok(preferences)
+    }
+
+    fn description(&self) -> &'static str {
+        "Saves the signed-in user's notification preferences"
+    }
+}
+
+#[derive(Clone)]
+struct PushRoute<C: AppConfig>(Handle<C>);
+
+impl<C: AppConfig> Route<C> for PushRoute<C> {
+    type Params = NoParams;
+    type Loader = Self;
+    type Action = Self;
+    type View = NoView;
+
+    fn path() -> &'static str {
+        "/notifications/push"
+    }
+    fn loader(&self) -> Self {
+        self.clone()
+    }
+    fn action(&self) -> Self {
+        self.clone()
+    }
+    fn view(&self) -> NoView {
+        NoView
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> RouteLoader<NoParams, C> for PushRoute<C> {
+    type Output = PushConfig;
+
+    async fn load(&self, _ctx: RouteContext<'_, C>, _params: NoParams) -> Result<PushConfig, RouteError> {
+        Ok(PushConfig { public_key: self.0.notifications.push().map(|push| push.keys().public_key()) })
+    }
+
+    fn description(&self) -> &'static str {
+        "The VAPID public key browsers subscribe to push messages with"
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> RouteAction<NoParams, C> for PushRoute<C> {
+    type Input = PushRegistration;
+    type Output = bool;
+
+    async fn act(&self, ctx: RouteContext<'_, C>, _params: NoParams, input: PushRegistration) -> Result<bool, RouteError> {
+        let user = self.0.user(&ctx)?;
+        let notifications = &self.0.notifications;
+        match input.keys {
+            Some(keys) => {
+                keys.decode().map_err(|e| RouteError::ValidationFailed(e.to_string()))?;
+                notifications.register_push(&PushSubscription::new(user, input.endpoint, keys)).await.map_err(internal)?;
+                Ok(true)
+            }
+            None => notifications.unregister_push(&user, &input.endpoint).await.map_err(internal),
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        "Registers a browser's push subscription, or unregisters it when only the endpoint is sent"
+    }
+}
+
+struct LiveRoute<C: AppConfig>(Handle<C>);
+
+#[async_trait]
+impl<C: AppConfig> Subscription<C> for LiveRoute<C> {
+    type Params = NoParams;
+    type Message = Notification;
+
+    fn path() -> &'static str {
+        "/notifications/live"
+    }
+
+    async fn subscribe(&self, ctx: RouteContext<'_, C>, _params: NoParams) -> Result<Messages<Notification>, RouteError> {
+        let user = self.0.user(&ctx)?;
+        Ok(self.0.notifications.subscribe(user))
+    }
+
+    fn description(&self) -> &'static str {
+        "The signed-in user's new notifications as they are sent"
+    }
+}
@@ -0,0 +1,133 @@
+//! montrs-core/src/openapi.rs: OpenAPI 3 export of the router specification.
+//! Each route becomes a path with a `get` operation for its loader and a `post`
+//! operation for its action. Route annotations are carried over as the
+//! `x-montrs-meta` extension, and the owner (or team) becomes the operation tag.
+//! Typed params become path or query parameters with their `ParamSchema`.
+//! Routes with `.requires(..)` get the bearer security scheme, `401`/`403`
+//! responses and their requirements in `x-montrs-requires`.
+
+use crate::authz::Permission;
+use crate::meta;
+use crate::router::{RouteMetadata, RouterSpec};
+use serde_json::{Map, Value, json};
+
+impl RouterSpec {
+    /// Renders the routes as an OpenAPI 3.0 document.
+    pub fn to_openapi(&self, title: &str, version: &str) -> Value {
+        let mut paths = Map::new();
+        let mut routes: Vec<_> = self.routes.values().collect();
+        routes.sort_by(|a, b| a.path.cmp(&b.path));
+        for route in routes {
+            paths.insert(openapi_path(&route.path), path_item(route));
+        }
+        let mut doc = json!({
+            "openapi": "3.0.3",
+            "info": { "title": title, "version": version },
+            "paths": paths,
+        });
+        if self.routes.values().any(|route| route.meta.contains_key(meta::REQUIRES)) {
+            doc["components"] = json!({
+                "securitySchemes": {
+                    SECURITY_SCHEME: {
+                        "type": "http",
+                        "scheme": "bearer",
+                        "description": "Operations list the roles and permissions they require in `x-montrs-requires`.",
+                    }
+                }
+            });
+        }
+        doc
+    }
+}
+
+/// Name of the security scheme operations with requirements refer to.
+pub const SECURITY_SCHEME: &str = "bearerAuth";
+
+/// Converts `/users/:id` and `/files/*rest` to `/users/{id}` and `/files/{rest}`.
+fn openapi_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match segment.strip_prefix(':').or_else(|| segment.strip_prefix('*')) {
+            Some(name) if !name.is_empty() => format!("{{{}}}", name),
+            _ => segment.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Path captures, typed by the matching `RouteParams::params` entry when there
+/// is one, followed by the remaining params as query parameters.
+fn parameters(route: &RouteMetadata) -> Vec<Value> {
+    let captures: Vec<&str> = route
+        .path
+        .split('/')
+        .filter_map(|segment| segment.strip_prefix(':').or_else(|| segment.strip_prefix('*')))
+        .filter(|name| !name.is_empty())
+        .collect();
+    let mut params: Vec<Value> = captures
+        .iter()
+        .map(|name| {
+            let schema = match route.params.iter().find(|p| p.name == *name) {
+                Some(spec) => json!(spec.schema),
+                None => json!({ "type": "string" }),
+            };
+            json!({ "name": name, "in": "path", "required": true, "schema": schema })
+        })
+        .collect();
+    params.extend(
+        route
+            .params
+            .iter()
+            .filter(|spec| !captures.contains(&spec.name.as_str()))
+            .map(|spec| json!({ "name": spec.name, "in": "query", "required": spec.required, "schema": spec.schema })),
+    );
+    params
+}
+
+fn path_item(route: &RouteMetadata) -> Value {
+    let mut get = operation(route, &route.loader_description, "Loader data");
+    let mut post = operation(route, &route.action_description, "Action result");
+
+    let content: Map<String, Value> = route
+        .action_body
+        .content_types()
+        .into_iter()
+        .map(|content_type| (content_type, json!({})))
+        .collect();
+    post["requestBody"] = json!({ "required": true, "content": content });
+
+    let params = parameters(route);
+    if !params.is_empty() {
+        get["parameters"] = json!(params);
+        post["parameters"] = json!(params);
+    }
+    json!({ "get": get, "post": post })
+}
+
+fn operation(route: &RouteMetadata, description: &str, response: &str) -> Value {
+    let mut op = json!({
+        "responses": {
+            "200": { "description": response, "content": { "application/json": {} } }
+        }
+    });
+    if !description.is_empty() {
+        op["summary"] = json!(description);
+    }
+    if route.meta.get(meta::STABILITY).map(String::as_str) == Some(meta::DEPRECATED) {
+        op["deprecated"] = json!(true);
+    }
+    if let Some(tag) = route.meta.get(meta::OWNER).or_else(|| route.meta.get(meta::TEAM)) {
+        op["tags"] = json!([tag]);
+    }
+    let required = Permission::from_meta(&route.meta);
+    if !required.is_empty() {
+        op["security"] = json!([{ SECURITY_SCHEME: [] }]);
+        op["x-montrs-requires"] = json!(required);
+        op["responses"]["401"] = json!({ "description": "No signed-in caller" });
+        op["responses"]["403"] = json!({ "description": "The caller lacks a required role or permission" });
+    }
+    if !route.meta.is_empty() {
+        let sorted: std::collections::BTreeMap<_, _> = route.meta.iter().collect();
+        op["x-montrs-meta"] = json!(sorted);
+    }
+    op
+}
@@ -0,0 +1,234 @@
+//! montrs-core/src/param.rs: Typed path and query parameters.
+//! Path captures and query strings are text. A type implementing
+//! [`FromParam`] parses that text itself, so a route's params struct can hold
+//! domain types (`TodoId`, `Slug`, a date) that reject bad input with their own
+//! message, and describes itself with a [`ParamSchema`] that ends up in the
+//! `RouterSpec` and the OpenAPI export. `#[derive(FromParam)]` and
+//! `#[derive(RouteParams)]` in `montrs-schema` write the impls for newtypes and
+//! params structs.
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+
+#[doc(hidden)]
+pub use serde as __serde;
+
+/// A value that can be parsed from a path or query parameter.
+pub trait FromParam: Sized {
+    /// Parses the parameter's text.
+    fn from_param(value: &str) -> Result<Self, ParamError>;
+
+    /// How the parameter is described in the `RouterSpec`.
+    fn param_schema() -> ParamSchema {
+        ParamSchema::new("string")
+    }
+}
+
+/// A parameter that could not be parsed. Routes report it as
+/// `RouteError::ValidationFailed`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParamError {
+    type_name: String,
+    value: String,
+    reason: String,
+}
+
+impl ParamError {
+    /// `value` is not a valid `T` because of `reason`.
+    pub fn invalid<T>(value: &str, reason: impl fmt::Display) -> Self {
+        Self { type_name: short_type_name::<T>(), value: value.to_string(), reason: reason.to_string() }
+    }
+
+    /// The same error, reported against `T` instead; used by newtypes so the
+    /// message names the domain type rather than the one it wraps.
+    pub fn retype<T>(self) -> Self {
+        Self { type_name: short_type_name::<T>(), ..self }
+    }
+
+    /// The parameter's text.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Why it was rejected.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl fmt::Display for ParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {} `{}`: {}", self.type_name, self.value, self.reason)
+    }
+}
+
+impl std::error::Error for ParamError {}
+
+/// `chrono::naive::date::NaiveDate` -> `NaiveDate`.
+fn short_type_name<T>() -> String {
+    let full = std::any::type_name::<T>();
+    let base = full.split('<').next().unwrap_or(full);
+    base.rsplit("::").next().unwrap_or(base).to_string()
+}
+
+/// The schema of a parameter, in the shape of an OpenAPI schema object.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParamSchema {
+    /// `string`, `integer`, `number` or `boolean`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Refines `kind`, e.g. `int64`, `date` or `uuid`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// A regular expression the text must match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl ParamSchema {
+    pub fn new(kind: impl Into<String>) -> Self {
+        Self { kind: kind.into(), ..Self::default() }
+    }
+
+    pub fn with_format(mut self, format: impl Into<String>) -> Self {
+        self.format = Some(format.into());
+        self
+    }
+
+    pub fn with_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.pattern = Some(pattern.into());
+        self
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+}
+
+/// One field of a route's params, as listed by `RouteParams::params`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParamSpec {
+    pub name: String,
+    /// False for `Option` fields.
+    pub required: bool,
+    pub schema: ParamSchema,
+}
+
+impl ParamSpec {
+    /// A parameter `name` of type `T`, described by `T::param_schema`.
+    pub fn of<T: FromParam>(name: impl Into<String>, required: bool) -> Self {
+        Self { name: name.into(), required, schema: T::param_schema() }
+    }
+
+    /// Replaces the schema's description, e.g. with the field's doc comment.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.schema.description = Some(description.into());
+        self
+    }
+}
+
+/// A regular expression checked before a newtype parses its inner value
+/// (`#[param(pattern = "...")]`).
+pub struct ParamPattern(regex::Regex);
+
+impl ParamPattern {
+    /// Compiles `pattern`, which the derive has already checked.
+    pub fn new(pattern: &str) -> Self {
+        Self(regex::Regex::new(pattern).expect("param pattern is checked at compile time"))
+    }
+
+    /// Rejects `value` as a `T` unless it matches.
+    pub fn check<T>(&self, value: &str) -> Result<(), ParamError> {
+        if self.0.is_match(value) {
+            Ok(())
+        } else {
+            Err(ParamError::invalid::<T>(value, format!("does not match `{}`", self.0.as_str())))
+        }
+    }
+}
+
+/// Deserializes a [`FromParam`] type from the router's param values, which
+/// hold numbers for numeric path segments and strings otherwise. Use it as
+/// `#[serde(deserialize_with = "montrs_core::param::deserialize")]`;
+/// `#[derive(FromParam)]` types already deserialize this way.
+pub fn deserialize<'de, T: FromParam, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+    let text = match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(text) => text,
+        serde_json::Value::Number(number) => number.to_string(),
+        serde_json::Value::Bool(flag) => flag.to_string(),
+        other => return Err(D::Error::custom(format!("expected a parameter value, found {}", other))),
+    };
+    T::from_param(&text).map_err(D::Error::custom)
+}
+
+impl FromParam for String {
+    fn from_param(value: &str) -> Result<Self, ParamError> {
+        Ok(value.to_string())
+    }
+}
+
+fn number_schema(kind: &str, format: Option<&str>) -> ParamSchema {
+    ParamSchema { format: format.map(str::to_string), ..ParamSchema::new(kind) }
+}
+
+macro_rules! from_param_number {
+    ($kind:literal, $expected:literal: $($ty:ty => $format:expr),* $(,)?) => {
+        $(
+            impl FromParam for $ty {
+                fn from_param(value: &str) -> Result<Self, ParamError> {
+                    value.parse().map_err(|_| ParamError::invalid::<Self>(value, $expected))
+                }
+
+                fn param_schema() -> ParamSchema {
+                    number_schema($kind, $format)
+                }
+            }
+        )*
+    };
+}
+
+from_param_number!("integer", "expected an integer":
+    i8 => None, i16 => None, i32 => Some("int32"), i64 => Some("int64"), isize => None,
+    u8 => None, u16 => None, u32 => None, u64 => None, usize => None,
+);
+from_param_number!("number", "expected a number": f32 => Some("float"), f64 => Some("double"));
+
+impl FromParam for bool {
+    fn from_param(value: &str) -> Result<Self, ParamError> {
+        match value {
+            "true" | "1" => Ok(true),
+            "false" | "0" => Ok(false),
+            _ => Err(ParamError::invalid::<Self>(value, "expected true or false")),
+        }
+    }
+
+    fn param_schema() -> ParamSchema {
+        ParamSchema::new("boolean")
+    }
+}
+
+impl FromParam for chrono::NaiveDate {
+    fn from_param(value: &str) -> Result<Self, ParamError> {
+        chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|e| ParamError::invalid::<Self>(value, e))
+    }
+
+    fn param_schema() -> ParamSchema {
+        ParamSchema::new("string").with_format("date")
+    }
+}
+
+impl FromParam for chrono::DateTime<chrono::Utc> {
+    fn from_param(value: &str) -> Result<Self, ParamError> {
+        chrono::DateTime::parse_from_rfc3339(value)
+            .map(|time| time.with_timezone(&chrono::Utc))
+            .map_err(|e| ParamError::invalid::<Self>(value, e))
+    }
+
+    fn param_schema() -> ParamSchema {
+        ParamSchema::new("string").with_format("date-time")
+    }
+}
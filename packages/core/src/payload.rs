@@ -0,0 +1,101 @@
+//! montrs-core/src/payload.rs: Pre-serialized JSON for large Loader responses.
+//! A Loader that already holds its response as JSON bytes (from a cache, a file or
+//! an upstream service) returns `JsonBytes`. `Router::load_to` and `Router::load_bytes`
+//! hand those bytes to the response without parsing them, and serialize every other
+//! output directly into the body instead of going through `serde_json::Value`.
+
+use crate::router::RouteError;
+use bytes::Bytes;
+use serde::de::Error as _;
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::value::RawValue;
+
+/// JSON that has already been serialized, backed by reference-counted `Bytes`.
+///
+/// Cloning is cheap, and `Router::load_bytes` returns the same buffer without
+/// copying it. The bytes are trusted to be valid JSON: they are only checked when
+/// the value goes through a regular serializer, e.g. `Router::load`.
+///
+/// ```rust
+/// use montrs_core::JsonBytes;
+///
+/// let cached = JsonBytes::encode(&vec![1, 2, 3]).unwrap();
+/// assert_eq!(cached.as_bytes(), b"[1,2,3]");
+/// assert_eq!(serde_json::to_value(&cached).unwrap(), serde_json::json!([1, 2, 3]));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonBytes(Bytes);
+
+impl JsonBytes {
+    pub fn new(bytes: impl Into<Bytes>) -> Self {
+        Self(bytes.into())
+    }
+
+    pub fn from_static(bytes: &'static [u8]) -> Self {
+        Self(Bytes::from_static(bytes))
+    }
+
+    /// Serializes `value` once so it can be served repeatedly.
+    pub fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Self, RouteError> {
+        serde_json::to_vec(value)
+            .map(|json| Self(json.into()))
+            .map_err(|e| RouteError::InternalError(e.to_string()))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn bytes(&self) -> &Bytes {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Bytes> for JsonBytes {
+    fn from(bytes: Bytes) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<Vec<u8>> for JsonBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes.into())
+    }
+}
+
+impl From<String> for JsonBytes {
+    fn from(json: String) -> Self {
+        Self(json.into())
+    }
+}
+
+impl Serialize for JsonBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Borrowing a `RawValue` validates the bytes without building a tree, and
+        // serde_json writers emit it verbatim.
+        let raw: &RawValue = serde_json::from_slice(&self.0).map_err(S::Error::custom)?;
+        raw.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        serde_json::to_vec(&value)
+            .map(|json| Self(json.into()))
+            .map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,1083 @@
+//! montrs-core/src/payment.rs: Payments through Stripe.
+//! [`Stripe`] creates Checkout and customer portal sessions and verifies the
+//! events Stripe POSTs back. Their `Stripe-Signature` header uses the same
+//! `t=...,v1=...` HMAC-SHA256 scheme as outbound webhooks, so verification is
+//! [`crate::webhook::verify`]. [`Payments`] remembers which Stripe customer
+//! belongs to which user, keeps each subscription's state in a
+//! [`PaymentStore`] as events arrive, ignoring events older than the state it
+//! has, and publishes a typed [`PaymentEvent`] for every event it understands.
+//! [`PaymentsPlate`] registers the billing, portal and webhook routes under a
+//! configurable prefix. `montrs_orm::payment::PaymentTables` keeps the state
+//! in the application database.
+
+use crate::body::{BodyFormat, RawBody};
+use crate::{AppConfig, CurrentUser, Plate, PlateContext, Route, RouteAction, RouteContext, RouteError, RouteLoader, RouteParams, RouteView, Router};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, BoxStream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// The header carrying the signature of a Stripe event.
+pub const SIGNATURE_HEADER: &str = "Stripe-Signature";
+/// Metadata key under which checkout sessions, subscriptions and payments
+/// carry the ID of the user who started them.
+pub const USER_METADATA: &str = "montrs_user_id";
+
+const API_BASE: &str = "https://api.stripe.com";
+/// Events a slow subscriber can fall behind before it misses some.
+const EVENTS_CAPACITY: usize = 256;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PaymentError {
+    #[error("invalid or expired Stripe signature")]
+    InvalidSignature,
+    #[error("invalid Stripe event: {0}")]
+    InvalidEvent(String),
+    #[error("Stripe answered {status}: {message}")]
+    Api { status: u16, message: String },
+    #[error("Stripe request failed: {0}")]
+    Transport(String),
+    #[error("user {0} has no Stripe customer yet")]
+    NoCustomer(String),
+    #[error(transparent)]
+    Store(#[from] anyhow::Error),
+}
+
+/// What a Checkout session sells.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckoutMode {
+    /// One-off payment for one-time prices.
+    #[default]
+    Payment,
+    /// A subscription to recurring prices.
+    Subscription,
+}
+
+impl CheckoutMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            CheckoutMode::Payment => "payment",
+            CheckoutMode::Subscription => "subscription",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineItem {
+    /// A Stripe price ID, `price_...`.
+    pub price: String,
+    pub quantity: u64,
+}
+
+/// A Checkout session to create.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkout {
+    pub mode: CheckoutMode,
+    pub line_items: Vec<LineItem>,
+    /// Where Stripe sends the customer after paying. `{CHECKOUT_SESSION_ID}`
+    /// is replaced with the session ID.
+    pub success_url: String,
+    pub cancel_url: String,
+    /// Usually the user ID; [`Payments::checkout`] sets it.
+    pub client_reference_id: Option<String>,
+    /// An existing Stripe customer, so payment methods and subscriptions stay
+    /// together. Stripe creates a customer when this is `None`.
+    pub customer_id: Option<String>,
+    /// Prefills the email field for new customers.
+    pub customer_email: Option<String>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl Checkout {
+    pub fn new(mode: CheckoutMode, success_url: impl Into<String>, cancel_url: impl Into<String>) -> Self {
+        Self {
+            mode,
+            line_items: Vec::new(),
+            success_url: success_url.into(),
+            cancel_url: cancel_url.into(),
+            client_reference_id: None,
+            customer_id: None,
+            customer_email: None,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_item(mut self, price: impl Into<String>, quantity: u64) -> Self {
+        self.line_items.push(LineItem { price: price.into(), quantity });
+        self
+    }
+
+    pub fn with_customer_email(mut self, email: impl Into<String>) -> Self {
+        self.customer_email = Some(email.into());
+        self
+    }
+
+    /// Metadata on the session, and on the subscription or payment it creates.
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// The session as the form Stripe's API takes.
+    fn form(&self) -> Vec<(String, String)> {
+        let mut form = vec![
+            ("mode".to_string(), self.mode.as_str().to_string()),
+            ("success_url".to_string(), self.success_url.clone()),
+            ("cancel_url".to_string(), self.cancel_url.clone()),
+        ];
+        for (i, item) in self.line_items.iter().enumerate() {
+            form.push((format!("line_items[{}][price]", i), item.price.clone()));
+            form.push((format!("line_items[{}][quantity]", i), item.quantity.to_string()));
+        }
+        match (&self.customer_id, &self.customer_email) {
+            (Some(customer), _) => form.push(("customer".to_string(), customer.clone())),
+            (None, Some(email)) => form.push(("customer_email".to_string(), email.clone())),
+            (None, None) => {}
+        }
+        if self.customer_id.is_none() && self.mode == CheckoutMode::Payment {
+            // Subscriptions always get a customer; one-off payments only on request.
+            form.push(("customer_creation".to_string(), "always".to_string()));
+        }
+        let mut metadata = self.metadata.clone();
+        if let Some(user) = &self.client_reference_id {
+            form.push(("client_reference_id".to_string(), user.clone()));
+            metadata.insert(USER_METADATA.to_string(), user.clone());
+        }
+        let created = match self.mode {
+            CheckoutMode::Payment => "payment_intent_data",
+            CheckoutMode::Subscription => "subscription_data",
+        };
+        for (key, value) in &metadata {
+            form.push((format!("metadata[{}]", key), value.clone()));
+            form.push((format!("{}[metadata][{}]", created, key), value.clone()));
+        }
+        form
+    }
+}
+
+/// A created Checkout session. Redirect the customer to `url`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckoutSession {
+    pub id: String,
+    pub url: String,
+}
+
+/// A customer portal session. Redirect the customer to `url`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortalSession {
+    pub url: String,
+}
+
+/// An event as Stripe sends it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StripeEvent {
+    pub id: String,
+    /// E.g. `checkout.session.completed`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Unix seconds.
+    pub created: i64,
+    pub data: StripeEventData,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StripeEventData {
+    /// The session, payment intent, invoice or subscription the event is about.
+    pub object: Value,
+}
+
+/// Sends the API requests.
+#[async_trait]
+pub trait StripeTransport: Send + Sync + 'static {
+    /// POSTs `body` and returns the response status and body. `Err` means no
+    /// response, e.g. a connection error or a timeout.
+    async fn post(&self, url: &str, headers: &[(String, String)], body: Vec<u8>) -> Result<(u16, Vec<u8>), String>;
+}
+
+#[async_trait]
+impl StripeTransport for reqwest::Client {
+    async fn post(&self, url: &str, headers: &[(String, String)], body: Vec<u8>) -> Result<(u16, Vec<u8>), String> {
+        let mut request = reqwest::Client::post(self, url).body(body);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        let status = response.status().as_u16();
+        let body = response.bytes().await.map_err(|e| e.to_string())?;
+        Ok((status, body.to_vec()))
+    }
+}
+
+/// A Stripe API client.
+///
+/// ```rust,ignore
+/// let stripe = Stripe::new(env.get_var("STRIPE_SECRET_KEY")?, env.get_var("STRIPE_WEBHOOK_SECRET")?);
+/// ```
+pub struct Stripe {
+    secret_key: String,
+    webhook_secret: String,
+    api_base: String,
+    tolerance: Duration,
+    transport: Arc<dyn StripeTransport>,
+}
+
+impl Stripe {
+    /// `secret_key` is the `sk_...` API key, `webhook_secret` the `whsec_...`
+    /// signing secret of the webhook endpoint. Event signatures older than
+    /// five minutes are rejected.
+    pub fn new(secret_key: impl Into<String>, webhook_secret: impl Into<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .user_agent(concat!("montrs-payments/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .unwrap_or_default();
+        Self {
+            secret_key: secret_key.into(),
+            webhook_secret: webhook_secret.into(),
+            api_base: API_BASE.to_string(),
+            tolerance: Duration::from_secs(300),
+            transport: Arc::new(client),
+        }
+    }
+
+    /// Talks to another API server, e.g. `stripe-mock`.
+    pub fn with_api_base(mut self, api_base: impl Into<String>) -> Self {
+        self.api_base = api_base.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// How old an event signature may be.
+    pub fn with_tolerance(mut self, tolerance: Duration) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Sends through `transport` instead of HTTP, e.g. in tests.
+    pub fn with_transport(mut self, transport: impl StripeTransport) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    pub async fn create_checkout(&self, checkout: &Checkout) -> Result<CheckoutSession, PaymentError> {
+        self.post("/v1/checkout/sessions", &checkout.form()).await
+    }
+
+    /// A customer portal session, where the customer manages payment methods,
+    /// invoices and subscriptions, returning to `return_url`.
+    pub async fn create_portal(&self, customer_id: &str, return_url: &str) -> Result<PortalSession, PaymentError> {
+        let form = [("customer".to_string(), customer_id.to_string()), ("return_url".to_string(), return_url.to_string())];
+        self.post("/v1/billing_portal/sessions", &form).await
+    }
+
+    /// Verifies the [`SIGNATURE_HEADER`] of a webhook request against the raw
+    /// body and parses the event.
+    pub fn construct_event(&self, signature: &str, body: &[u8]) -> Result<StripeEvent, PaymentError> {
+        if !crate::webhook::verify(&self.webhook_secret, signature, body, self.tolerance) {
+            return Err(PaymentError::InvalidSignature);
+        }
+        serde_json::from_slice(body).map_err(|e| PaymentError::InvalidEvent(e.to_string()))
+    }
+
+    async fn post<T: DeserializeOwned>(&self, path: &str, form: &[(String, String)]) -> Result<T, PaymentError> {
+        let body = form_urlencoded::Serializer::new(String::new()).extend_pairs(form).finish();
+        let headers = vec![
+            ("Authorization".to_string(), format!("Bearer {}", self.secret_key)),
+            ("Content-Type".to_string(), "application/x-www-form-urlencoded".to_string()),
+        ];
+        let url = format!("{}{}", self.api_base, path);
+        let (status, body) = self.transport.post(&url, &headers, body.into_bytes()).await.map_err(PaymentError::Transport)?;
+        if !(200..300).contains(&status) {
+            let message = serde_json::from_slice::<Value>(&body)
+                .ok()
+                .and_then(|error| error["error"]["message"].as_str().map(str::to_string))
+                .unwrap_or_else(|| String::from_utf8_lossy(&body).into_owned());
+            return Err(PaymentError::Api { status, message });
+        }
+        serde_json::from_slice(&body).map_err(|e| PaymentError::Transport(format!("unexpected response: {}", e)))
+    }
+}
+
+/// The state of a subscription, as Stripe reports it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionStatus {
+    Incomplete,
+    IncompleteExpired,
+    Trialing,
+    Active,
+    PastDue,
+    Canceled,
+    Unpaid,
+    Paused,
+    /// A status added to Stripe after this version.
+    #[serde(other)]
+    Unknown,
+}
+
+/// The user whose Stripe customer this is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Customer {
+    pub user_id: String,
+    /// `cus_...`.
+    pub customer_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A subscription, as of the newest event about it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BillingSubscription {
+    /// `sub_...`.
+    pub id: String,
+    pub customer_id: String,
+    pub status: SubscriptionStatus,
+    /// The price of the first item.
+    pub price_id: Option<String>,
+    pub quantity: u64,
+    pub current_period_end: Option<DateTime<Utc>>,
+    /// The customer canceled; the subscription ends with the current period.
+    pub cancel_at_period_end: bool,
+    pub created_at: DateTime<Utc>,
+    /// When Stripe created the event this state comes from. Older events do
+    /// not overwrite it.
+    pub synced_at: DateTime<Utc>,
+}
+
+impl BillingSubscription {
+    /// Whether the customer should have what they subscribed to.
+    pub fn is_active(&self) -> bool {
+        matches!(self.status, SubscriptionStatus::Active | SubscriptionStatus::Trialing)
+    }
+
+    fn from_stripe(object: &Value, synced_at: DateTime<Utc>) -> Result<Self, PaymentError> {
+        let item = &object["items"]["data"][0];
+        Ok(Self {
+            id: required(object, "id")?,
+            customer_id: required(object, "customer")?,
+            status: serde_json::from_value(object["status"].clone()).map_err(|e| PaymentError::InvalidEvent(e.to_string()))?,
+            price_id: text(&item["price"], "id"),
+            quantity: item["quantity"].as_u64().unwrap_or(1),
+            // Newer API versions moved the period to the items.
+            current_period_end: timestamp(&object["current_period_end"]).or_else(|| timestamp(&item["current_period_end"])),
+            cancel_at_period_end: object["cancel_at_period_end"].as_bool().unwrap_or(false),
+            created_at: timestamp(&object["created"]).unwrap_or(synced_at),
+            synced_at,
+        })
+    }
+}
+
+/// A user's billing state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Billing {
+    pub customer_id: Option<String>,
+    /// Oldest first.
+    pub subscriptions: Vec<BillingSubscription>,
+}
+
+impl Billing {
+    pub fn is_subscribed(&self) -> bool {
+        self.subscriptions.iter().any(BillingSubscription::is_active)
+    }
+}
+
+/// A finished Checkout session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompletedCheckout {
+    pub session_id: String,
+    pub customer_id: Option<String>,
+    /// Set in subscription mode.
+    pub subscription_id: Option<String>,
+    /// In the smallest currency unit, e.g. cents.
+    pub amount_total: Option<i64>,
+    pub currency: Option<String>,
+    /// `paid`, `unpaid` (for delayed payment methods) or `no_payment_required`.
+    pub payment_status: Option<String>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// A one-off payment that went through or failed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Charge {
+    /// `pi_...`.
+    pub payment_intent_id: String,
+    pub customer_id: Option<String>,
+    /// In the smallest currency unit.
+    pub amount: i64,
+    pub currency: String,
+    /// Why the payment failed, as Stripe puts it.
+    pub failure: Option<String>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// An invoice that was paid or could not be collected.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Invoice {
+    /// `in_...`.
+    pub invoice_id: String,
+    pub customer_id: Option<String>,
+    pub subscription_id: Option<String>,
+    /// Paid, or due when collection failed, in the smallest currency unit.
+    pub amount: i64,
+    pub currency: String,
+}
+
+/// A Stripe event [`Payments`] understood.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaymentEvent {
+    /// The Stripe event ID, `evt_...`.
+    pub id: String,
+    /// The user it concerns, when known.
+    pub user_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub kind: PaymentEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PaymentEventKind {
+    /// `checkout.session.completed`.
+    CheckoutCompleted(CompletedCheckout),
+    /// `payment_intent.succeeded`.
+    PaymentSucceeded(Charge),
+    /// `payment_intent.payment_failed`.
+    PaymentFailed(Charge),
+    /// `invoice.paid`.
+    InvoicePaid(Invoice),
+    /// `invoice.payment_failed`.
+    InvoicePaymentFailed(Invoice),
+    /// `customer.subscription.created`, `updated`, `paused` or `resumed`.
+    SubscriptionUpdated(BillingSubscription),
+    /// `customer.subscription.deleted`: the subscription ended.
+    SubscriptionDeleted(BillingSubscription),
+}
+
+impl PaymentEventKind {
+    fn customer_id(&self) -> Option<&str> {
+        match self {
+            PaymentEventKind::CheckoutCompleted(checkout) => checkout.customer_id.as_deref(),
+            PaymentEventKind::PaymentSucceeded(charge) | PaymentEventKind::PaymentFailed(charge) => charge.customer_id.as_deref(),
+            PaymentEventKind::InvoicePaid(invoice) | PaymentEventKind::InvoicePaymentFailed(invoice) => invoice.customer_id.as_deref(),
+            PaymentEventKind::SubscriptionUpdated(subscription) | PaymentEventKind::SubscriptionDeleted(subscription) => {
+                Some(&subscription.customer_id)
+            }
+        }
+    }
+}
+
+fn text(object: &Value, key: &str) -> Option<String> {
+    object[key].as_str().map(str::to_string)
+}
+
+fn required(object: &Value, key: &str) -> Result<String, PaymentError> {
+    text(object, key).ok_or_else(|| PaymentError::InvalidEvent(format!("missing `{}`", key)))
+}
+
+fn timestamp(value: &Value) -> Option<DateTime<Utc>> {
+    value.as_i64().and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+}
+
+fn metadata(object: &Value) -> BTreeMap<String, String> {
+    serde_json::from_value(object["metadata"].clone()).unwrap_or_default()
+}
+
+/// Keeps customers, subscriptions and the IDs of handled events.
+#[async_trait]
+pub trait PaymentStore: Send + Sync + 'static {
+    /// Records that the event with this ID was handled. Returns `false` if it
+    /// already was: Stripe delivers events at least once.
+    async fn record_event(&self, event_id: &str, at: DateTime<Utc>) -> anyhow::Result<bool>;
+
+    /// Inserts or replaces the customer of the same user.
+    async fn save_customer(&self, customer: &Customer) -> anyhow::Result<()>;
+
+    async fn customer(&self, user_id: &str) -> anyhow::Result<Option<Customer>>;
+
+    async fn customer_by_id(&self, customer_id: &str) -> anyhow::Result<Option<Customer>>;
+
+    /// Inserts or replaces the subscription with the same ID.
+    async fn save_subscription(&self, subscription: &BillingSubscription) -> anyhow::Result<()>;
+
+    async fn subscription(&self, id: &str) -> anyhow::Result<Option<BillingSubscription>>;
+
+    /// A customer's subscriptions, oldest first.
+    async fn subscriptions(&self, customer_id: &str) -> anyhow::Result<Vec<BillingSubscription>>;
+}
+
+#[async_trait]
+impl<T: PaymentStore> PaymentStore for Arc<T> {
+    async fn record_event(&self, event_id: &str, at: DateTime<Utc>) -> anyhow::Result<bool> {
+        (**self).record_event(event_id, at).await
+    }
+
+    async fn save_customer(&self, customer: &Customer) -> anyhow::Result<()> {
+        (**self).save_customer(customer).await
+    }
+
+    async fn customer(&self, user_id: &str) -> anyhow::Result<Option<Customer>> {
+        (**self).customer(user_id).await
+    }
+
+    async fn customer_by_id(&self, customer_id: &str) -> anyhow::Result<Option<Customer>> {
+        (**self).customer_by_id(customer_id).await
+    }
+
+    async fn save_subscription(&self, subscription: &BillingSubscription) -> anyhow::Result<()> {
+        (**self).save_subscription(subscription).await
+    }
+
+    async fn subscription(&self, id: &str) -> anyhow::Result<Option<BillingSubscription>> {
+        (**self).subscription(id).await
+    }
+
+    async fn subscriptions(&self, customer_id: &str) -> anyhow::Result<Vec<BillingSubscription>> {
+        (**self).subscriptions(customer_id).await
+    }
+}
+
+/// A store that lives as long as the process, for tests and development.
+#[derive(Default)]
+pub struct MemoryPaymentStore {
+    events: Mutex<HashSet<String>>,
+    customers: Mutex<HashMap<String, Customer>>,
+    subscriptions: Mutex<Vec<BillingSubscription>>,
+}
+
+impl MemoryPaymentStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl PaymentStore for MemoryPaymentStore {
+    async fn record_event(&self, event_id: &str, _at: DateTime<Utc>) -> anyhow::Result<bool> {
+        Ok(self.events.lock().unwrap_or_else(|e| e.into_inner()).insert(event_id.to_string()))
+    }
+
+    async fn save_customer(&self, customer: &Customer) -> anyhow::Result<()> {
+        let mut customers = self.customers.lock().unwrap_or_else(|e| e.into_inner());
+        customers.insert(customer.user_id.clone(), customer.clone());
+        Ok(())
+    }
+
+    async fn customer(&self, user_id: &str) -> anyhow::Result<Option<Customer>> {
+        Ok(self.customers.lock().unwrap_or_else(|e| e.into_inner()).get(user_id).cloned())
+    }
+
+    async fn customer_by_id(&self, customer_id: &str) -> anyhow::Result<Option<Customer>> {
+        let customers = self.customers.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(customers.values().find(|c| c.customer_id == customer_id).cloned())
+    }
+
+    async fn save_subscription(&self, subscription: &BillingSubscription) -> anyhow::Result<()> {
+        let mut subscriptions = self.subscriptions.lock().unwrap_or_else(|e| e.into_inner());
+        match subscriptions.iter_mut().find(|s| s.id == subscription.id) {
+            Some(existing) => *existing = subscription.clone(),
+            None => subscriptions.push(subscription.clone()),
+        }
+        Ok(())
+    }
+
+    async fn subscription(&self, id: &str) -> anyhow::Result<Option<BillingSubscription>> {
+        Ok(self.subscriptions.lock().unwrap_or_else(|e| e.into_inner()).iter().find(|s| s.id == id).cloned())
+    }
+
+    async fn subscriptions(&self, customer_id: &str) -> anyhow::Result<Vec<BillingSubscription>> {
+        let subscriptions = self.subscriptions.lock().unwrap_or_else(|e| e.into_inner());
+        let mut found: Vec<_> = subscriptions.iter().filter(|s| s.customer_id == customer_id).cloned().collect();
+        found.sort_by_key(|s| s.created_at);
+        Ok(found)
+    }
+}
+
+/// Starts payments for users and keeps their billing state in sync with Stripe.
+///
+/// ```rust,ignore
+/// let payments = Arc::new(Payments::new(stripe, PaymentTables::new(db.clone())));
+///
+/// let session = payments
+///     .checkout(&user.id, Checkout::new(CheckoutMode::Subscription, success_url, cancel_url).with_item("price_pro", 1))
+///     .await?;
+///
+/// // In the webhook endpoint, with the raw body.
+/// payments.handle_webhook(signature, &body).await?;
+/// ```
+pub struct Payments {
+    stripe: Stripe,
+    store: Arc<dyn PaymentStore>,
+    events: broadcast::Sender<Arc<PaymentEvent>>,
+}
+
+impl Payments {
+    pub fn new(stripe: Stripe, store: impl PaymentStore) -> Self {
+        Self { stripe, store: Arc::new(store), events: broadcast::channel(EVENTS_CAPACITY).0 }
+    }
+
+    pub fn stripe(&self) -> &Stripe {
+        &self.stripe
+    }
+
+    pub fn store(&self) -> &Arc<dyn PaymentStore> {
+        &self.store
+    }
+
+    /// Creates a Checkout session for the user. The session, and the
+    /// subscription or payment it creates, carry the user ID, and a returning
+    /// user pays as their existing Stripe customer.
+    pub async fn checkout(&self, user_id: &str, mut checkout: Checkout) -> Result<CheckoutSession, PaymentError> {
+        checkout.client_reference_id = Some(user_id.to_string());
+        if let Some(customer) = self.store.customer(user_id).await? {
+            checkout.customer_id = Some(customer.customer_id);
+        }
+        self.stripe.create_checkout(&checkout).await
+    }
+
+    /// Opens the customer portal for a user who has paid before.
+    pub async fn portal(&self, user_id: &str, return_url: &str) -> Result<PortalSession, PaymentError> {
+        let customer = self.store.customer(user_id).await?.ok_or_else(|| PaymentError::NoCustomer(user_id.to_string()))?;
+        self.stripe.create_portal(&customer.customer_id, return_url).await
+    }
+
+    pub async fn billing(&self, user_id: &str) -> anyhow::Result<Billing> {
+        let Some(customer) = self.store.customer(user_id).await? else {
+            return Ok(Billing { customer_id: None, subscriptions: Vec::new() });
+        };
+        let subscriptions = self.store.subscriptions(&customer.customer_id).await?;
+        Ok(Billing { customer_id: Some(customer.customer_id), subscriptions })
+    }
+
+    /// Verifies and applies a webhook request: links the customer to the
+    /// user, stores the subscription state and publishes the event. Returns
+    /// `None` for events of other types, events already handled and
+    /// subscription events older than the stored state; answer Stripe with a
+    /// 2xx for those too. Errors other than a bad signature or body are worth
+    /// a 5xx, so Stripe retries.
+    pub async fn handle_webhook(&self, signature: &str, body: &[u8]) -> Result<Option<PaymentEvent>, PaymentError> {
+        let event = self.stripe.construct_event(signature, body)?;
+        let Some(event) = self.apply(event).await? else {
+            return Ok(None);
+        };
+        // Recorded after applying, so an event that failed halfway is applied again when Stripe retries.
+        if !self.store.record_event(&event.id, Utc::now()).await? {
+            return Ok(None);
+        }
+        // No receivers is fine: nothing subscribed.
+        let _ = self.events.send(Arc::new(event.clone()));
+        Ok(Some(event))
+    }
+
+    async fn apply(&self, event: StripeEvent) -> Result<Option<PaymentEvent>, PaymentError> {
+        let object = &event.data.object;
+        let created_at = DateTime::from_timestamp(event.created, 0).unwrap_or_else(Utc::now);
+        let kind = match event.kind.as_str() {
+            "checkout.session.completed" => PaymentEventKind::CheckoutCompleted(CompletedCheckout {
+                session_id: required(object, "id")?,
+                customer_id: text(object, "customer"),
+                subscription_id: text(object, "subscription"),
+                amount_total: object["amount_total"].as_i64(),
+                currency: text(object, "currency"),
+                payment_status: text(object, "payment_status"),
+                metadata: metadata(object),
+            }),
+            "payment_intent.succeeded" | "payment_intent.payment_failed" => {
+                let charge = Charge {
+                    payment_intent_id: required(object, "id")?,
+                    customer_id: text(object, "customer"),
+                    amount: object["amount"].as_i64().unwrap_or_default(),
+                    currency: text(object, "currency").unwrap_or_default(),
+                    failure: text(&object["last_payment_error"], "message"),
+                    metadata: metadata(object),
+                };
+                match event.kind.as_str() {
+                    "payment_intent.succeeded" => PaymentEventKind::PaymentSucceeded(charge),
+                    _ => PaymentEventKind::PaymentFailed(charge),
+                }
+            }
+            "invoice.paid" => PaymentEventKind::InvoicePaid(invoice(object, "amount_paid")?),
+            "invoice.payment_failed" => PaymentEventKind::InvoicePaymentFailed(invoice(object, "amount_due")?),
+            "customer.subscription.created"
+            | "customer.subscription.updated"
+            | "customer.subscription.paused"
+            | "customer.subscription.resumed"
+            | "customer.subscription.deleted" => {
+                let subscription = BillingSubscription::from_stripe(object, created_at)?;
+                let stored = self.store.subscription(&subscription.id).await?;
+                if stored.is_some_and(|stored| stored.synced_at > subscription.synced_at) {
+                    return Ok(None);
+                }
+                self.store.save_subscription(&subscription).await?;
+                match event.kind.as_str() {
+                    "customer.subscription.deleted" => PaymentEventKind::SubscriptionDeleted(subscription),
+                    _ => PaymentEventKind::SubscriptionUpdated(subscription),
+                }
+            }
+            _ => return Ok(None),
+        };
+
+        let tagged = text(object, "client_reference_id").or_else(|| metadata(object).remove(USER_METADATA));
+        let user_id = match (tagged, kind.customer_id()) {
+            (Some(user_id), Some(customer_id)) => {
+                if self.store.customer(&user_id).await?.is_none() {
+                    let customer = Customer { user_id: user_id.clone(), customer_id: customer_id.to_string(), created_at };
+                    self.store.save_customer(&customer).await?;
+                }
+                Some(user_id)
+            }
+            (Some(user_id), None) => Some(user_id),
+            (None, Some(customer_id)) => self.store.customer_by_id(customer_id).await?.map(|c| c.user_id),
+            (None, None) => None,
+        };
+        Ok(Some(PaymentEvent { id: event.id, user_id, created_at, kind }))
+    }
+
+    /// Payment events as webhooks deliver them from now on, in this process.
+    /// A subscriber that falls more than 256 events behind misses some; keep
+    /// anything that must not be lost in the store.
+    pub fn subscribe(&self) -> BoxStream<'static, PaymentEvent> {
+        stream::unfold(self.events.subscribe(), |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some(((*event).clone(), receiver)),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        tracing::warn!(missed, "payment event subscriber fell behind");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+        .boxed()
+    }
+}
+
+fn invoice(object: &Value, amount: &str) -> Result<Invoice, PaymentError> {
+    Ok(Invoice {
+        invoice_id: required(object, "id")?,
+        customer_id: text(object, "customer"),
+        // Newer API versions moved the subscription under `parent`.
+        subscription_id: text(object, "subscription")
+            .or_else(|| text(&object["parent"]["subscription_details"], "subscription")),
+        amount: object[amount].as_i64().unwrap_or_default(),
+        currency: text(object, "currency").unwrap_or_default(),
+    })
+}
+
+/// Registers the billing routes, under `/payments` unless
+/// [`with_prefix`](Self::with_prefix) says otherwise:
+///
+/// | Path | Loader | Action |
+/// | --- | --- | --- |
+/// | `/payments` | The user's [`Billing`] | Starts Checkout for `{"price", "quantity"}`; returns the [`CheckoutSession`] |
+/// | `/payments/portal` | A [`PortalSession`] | The same |
+/// | `/payments/webhook` | — | Stripe's events, with the raw body |
+///
+/// Only prices listed with [`with_price`](Self::with_price) can be bought.
+/// The billing and portal routes answer `Unauthorized` when `current_user`
+/// finds no user. The webhook route reads [`SIGNATURE_HEADER`] from
+/// [`RouteContext::request`], so it needs an embedded or WASI app; other
+/// servers call [`Payments::handle_webhook`] themselves.
+///
+/// ```rust,ignore
+/// let plate = PaymentsPlate::new(payments.clone(), "https://shop.example", |ctx: &RouteContext<'_, AppCfg>| current_user_id(ctx))
+///     .with_price("price_pro_monthly", CheckoutMode::Subscription)
+///     .with_price("price_credits", CheckoutMode::Payment);
+/// ```
+pub struct PaymentsPlate<C: AppConfig> {
+    handle: Handle<C>,
+    prefix: String,
+}
+
+impl<C: AppConfig> PaymentsPlate<C> {
+    /// Customers come back to `<app_url>/billing`, with `?checkout=success`
+    /// or `?checkout=canceled` after Checkout.
+    pub fn new(
+        payments: Arc<Payments>,
+        app_url: impl Into<String>,
+        current_user: impl Fn(&RouteContext<'_, C>) -> Option<String> + Send + Sync + 'static,
+    ) -> Self {
+        let app_url = app_url.into();
+        let handle = Handle {
+            payments,
+            current_user: Arc::new(current_user),
+            prices: Arc::new(HashMap::new()),
+            app_url: app_url.trim_end_matches('/').to_string(),
+            billing_path: "/billing".to_string(),
+        };
+        Self { handle, prefix: "/payments".to_string() }
+    }
+
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Allows Checkout for `price` in `mode`.
+    pub fn with_price(mut self, price: impl Into<String>, mode: CheckoutMode) -> Self {
+        Arc::make_mut(&mut self.handle.prices).insert(price.into(), mode);
+        self
+    }
+
+    /// The page of the app customers return to, `/billing` by default.
+    pub fn with_billing_path(mut self, path: impl Into<String>) -> Self {
+        self.handle.billing_path = path.into();
+        self
+    }
+
+    /// `prefix` followed by `suffix`, kept for the life of the router.
+    fn path(&self, suffix: &str) -> &'static str {
+        let path = format!("{}{}", self.prefix.trim_end_matches('/'), suffix);
+        crate::router::intern(if path.is_empty() { "/".to_string() } else { path })
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> Plate<C> for PaymentsPlate<C> {
+    fn name(&self) -> &'static str {
+        "payments"
+    }
+
+    fn description(&self) -> &'static str {
+        "Stripe payments: Checkout, the customer portal and webhook-synced subscription state"
+    }
+
+    async fn init(&self, _ctx: &mut PlateContext<C>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    fn register_routes(&self, router: &mut Router<C>) {
+        router.register_at(self.path(""), BillingRoute(self.handle.clone()));
+        router.register_at(self.path("/portal"), PortalRoute(self.handle.clone()));
+        router.register_at(self.path("/webhook"), WebhookRoute(self.handle.clone()));
+    }
+}
+
+struct Handle<C: AppConfig> {
+    payments: Arc<Payments>,
+    current_user: CurrentUser<C>,
+    prices: Arc<HashMap<String, CheckoutMode>>,
+    app_url: String,
+    billing_path: String,
+}
+
+impl<C: AppConfig> Clone for Handle<C> {
+    fn clone(&self) -> Self {
+        Self {
+            payments: self.payments.clone(),
+            current_user: self.current_user.clone(),
+            prices: self.prices.clone(),
+            app_url: self.app_url.clone(),
+            billing_path: self.billing_path.clone(),
+        }
+    }
+}
+
+impl<C: AppConfig> Handle<C> {
+    fn user(&self, ctx: &RouteContext<'_, C>) -> Result<String, RouteError> {
+        (self.current_user)(ctx).ok_or(RouteError::Unauthorized)
+    }
+
+    fn billing_url(&self, query: &str) -> String {
+        format!("{}{}{}", self.app_url, self.billing_path, query)
+    }
+
+    async fn portal(&self, ctx: &RouteContext<'_, C>) -> Result<PortalSession, RouteError> {
+        let user = self.user(ctx)?;
+        self.payments.portal(&user, &self.billing_url("")).await.map_err(route_error)
+    }
+}
+
+fn route_error(e: PaymentError) -> RouteError {
+    match e {
+        PaymentError::InvalidSignature => RouteError::Unauthorized,
+        PaymentError::InvalidEvent(_) | PaymentError::NoCustomer(_) => RouteError::ValidationFailed(e.to_string()),
+        PaymentError::Api { .. } | PaymentError::Transport(_) => RouteError::External(e.to_string()),
+        PaymentError::Store(e) => RouteError::InternalError(e.to_string()),
+    }
+}
+
+/// Input of the billing action.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StartCheckout {
+    /// One of the plate's prices.
+    pub price: String,
+    /// 1 by default.
+    #[serde(default)]
+    pub quantity: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NoParams {}
+
+impl RouteParams for NoParams {}
+
+struct NoView;
+
+impl RouteView for NoView {
+    fn render(&self) -> impl leptos::prelude::IntoView {}
+}
+
+#[derive(Clone)]
+struct BillingRoute<C: AppConfig>(Handle<C>);
+
+impl<C: AppConfig> Route<C> for BillingRoute<C> {
+    type Params = NoParams;
+    type Loader = Self;
+    type Action = Self;
+    type View = NoView;
+
+    fn path() -> &'static str {
+        "/payments"
+    }
+    fn loader(&self) -> Self {
+        self.clone()
+    }
+    fn action(&self) -> Self {
+        self.clone()
+    }
+    fn view(&self) -> NoView {
+        NoView
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> RouteLoader<NoParams, C> for BillingRoute<C> {
+    type Output = Billing;
+
+    async fn load(&self, ctx: RouteContext<'_, C>, _params: NoParams) -> Result<Billing, RouteError> {
+        let user = self.0.user(&ctx)?;
+        self.0.payments.billing(&user).await.map_err(|e| RouteError::InternalError(e.to_string()))
+    }
+
+    fn description(&self) -> &'static str {
+        "The signed-in user's Stripe customer and subscriptions"
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> RouteAction<NoParams, C> for BillingRoute<C> {
+    type Input = StartCheckout;
+    type Output = CheckoutSession;
+
+    async fn act(&self, ctx: RouteContext<'_, C>, _params: NoParams, input: StartCheckout) -> Result<CheckoutSession, RouteError> {
+        let user = self.0.user(&ctx)?;
+        let mode = *self.0.prices.get(&input.price).ok_or_else(|| RouteError::ValidationFailed(format!("unknown price {}", input.price)))?;
+        let checkout = Checkout::new(mode, self.0.billing_url("?checkout=success"), self.0.billing_url("?checkout=canceled"))
+            .with_item(input.price, input.quantity.unwrap_or(1).max(1));
+        self.0.payments.checkout(&user, checkout).await.map_err(route_error)
+    }
+
+    fn description(&self) -> &'static str {
+        "Starts Stripe Checkout for one of the app's prices"
+    }
+}
+
+#[derive(Clone)]
+struct PortalRoute<C: AppConfig>(Handle<C>);
+
+impl<C: AppConfig> Route<C> for PortalRoute<C> {
+    type Params = NoParams;
+    type Loader = Self;
+    type Action = Self;
+    type View = NoView;
+
+    fn path() -> &'static str {
+        "/payments/portal"
+    }
+    fn loader(&self) -> Self {
+        self.clone()
+    }
+    fn action(&self) -> Self {
+        self.clone()
+    }
+    fn view(&self) -> NoView {
+        NoView
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> RouteLoader<NoParams, C> for PortalRoute<C> {
+    type Output = PortalSession;
+
+    async fn load(&self, ctx: RouteContext<'_, C>, _params: NoParams) -> Result<PortalSession, RouteError> {
+        self.0.portal(&ctx).await
+    }
+
+    fn description(&self) -> &'static str {
+        "A Stripe customer portal session for the signed-in user"
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> RouteAction<NoParams, C> for PortalRoute<C> {
+    type Input = NoParams;
+    type Output = PortalSession;
+
+    async fn act(&self, ctx: RouteContext<'_, C>, _params: NoParams, _input: NoParams) -> Result<PortalSession, RouteError> {
+        self.0.portal(&ctx).await
+    }
+
+    fn description(&self) -> &'static str {
+        "A Stripe customer portal session for the signed-in user"
+    }
+}
+
+#[derive(Clone)]
+struct WebhookRoute<C: AppConfig>(Handle<C>);
+
+impl<C: AppConfig> Route<C> for WebhookRoute<C> {
+    type Params = NoParams;
+    type Loader = Self;
+    type Action = Self;
+    type View = NoView;
+
+    fn path() -> &'static str {
+        "/payments/webhook"
+    }
+    fn loader(&self) -> Self {
+        self.clone()
+    }
+    fn action(&self) -> Self {
+        self.clone()
+    }
+    fn view(&self) -> NoView {
+        NoView
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> RouteLoader<NoParams, C> for WebhookRoute<C> {
+    type Output = ();
+
+    async fn load(&self, _ctx: RouteContext<'_, C>, _params: NoParams) -> Result<(), RouteError> {
+        Err(RouteError::NotFound)
+    }
+
+    fn description(&self) -> &'static str {
+        "Not available; Stripe POSTs events to the action"
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> RouteAction<NoParams, C> for WebhookRoute<C> {
+    type Input = RawBody;
+    type Output = Option<PaymentEvent>;
+
+    async fn act(&self, ctx: RouteContext<'_, C>, _params: NoParams, input: RawBody) -> Result<Option<PaymentEvent>, RouteError> {
+        let request = ctx.request().ok_or(RouteError::Unauthorized)?;
+        let signature = request.header(SIGNATURE_HEADER).ok_or(RouteError::Unauthorized)?;
+        self.0.payments.handle_webhook(signature, input.as_bytes()).await.map_err(route_error)
+    }
+
+    fn body_format(&self) -> BodyFormat {
+        BodyFormat::binary(["application/json"])
+    }
+
+    fn description(&self) -> &'static str {
+        "Receives Stripe events and syncs billing state"
+    }
+}
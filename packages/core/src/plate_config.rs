@@ -0,0 +1,280 @@
+//! montrs-core/src/plate_config.rs: Per-plate configuration sections.
+//! A plate reads its settings from `[plates.<name>]` in `montrs.toml`, and any
+//! field can be overridden with `MONTRS_<PLATE>_<FIELD>` through an `EnvConfig`.
+//! `#[derive(PlateConfig)]` from `montrs-schema` implements [`PlateConfig`] on
+//! top of [`PlateSection`], so plates rarely touch this module directly.
+
+use crate::AgentError;
+use crate::env::EnvConfig;
+use crate::validation::Validate;
+use serde::de::DeserializeOwned;
+use std::path::Path;
+
+pub use toml::Table;
+
+/// The project configuration file plate sections are read from.
+pub const CONFIG_FILE: &str = "montrs.toml";
+
+/// Errors raised while loading a plate's configuration.
+#[derive(Debug, thiserror::Error)]
+pub enum PlateConfigError {
+    #[error("Failed to read {path}: {reason}")]
+    Read { path: String, reason: String },
+    #[error("Unknown key '{key}' in [plates.{plate}]")]
+    UnknownKey { plate: String, key: String },
+    #[error("Missing config value {plate}.{field}: set it in [plates.{plate}] or {env_key}")]
+    Missing { plate: String, field: String, env_key: String },
+    #[error("Secret {plate}.{field} must not be stored in montrs.toml")]
+    SecretInFile { plate: String, field: String, env_key: String },
+    #[error("Invalid value for {plate}.{field} from {origin}: {reason}")]
+    Invalid { plate: String, field: String, origin: String, reason: String },
+    #[error("Invalid configuration for plate '{plate}': {reason}")]
+    Validation { plate: String, reason: String },
+}
+
+impl AgentError for PlateConfigError {
+    fn error_code(&self) -> &'static str {
+        match self {
+            PlateConfigError::Read { .. } => "PLATE_CONFIG_READ",
+            PlateConfigError::UnknownKey { .. } => "PLATE_CONFIG_UNKNOWN_KEY",
+            PlateConfigError::Missing { .. } => "PLATE_CONFIG_MISSING",
+            PlateConfigError::SecretInFile { .. } => "PLATE_CONFIG_SECRET_IN_FILE",
+            PlateConfigError::Invalid { .. } => "PLATE_CONFIG_INVALID",
+            PlateConfigError::Validation { .. } => "PLATE_CONFIG_VALIDATION",
+        }
+    }
+
+    fn explanation(&self) -> String {
+        match self {
+            PlateConfigError::Read { path, reason } => {
+                format!("The configuration file '{}' exists but could not be read as TOML: {}", path, reason)
+            }
+            PlateConfigError::UnknownKey { plate, key } => format!(
+                "The [plates.{}] section sets '{}', which is not a field of the plate's config struct. It is most likely a typo.",
+                plate, key
+            ),
+            PlateConfigError::Missing { plate, field, env_key } => format!(
+                "The plate '{}' requires '{}', but neither montrs.toml nor the environment variable {} provides it.",
+                plate, field, env_key
+            ),
+            PlateConfigError::SecretInFile { plate, field, .. } => format!(
+                "'{}' of plate '{}' is marked as a secret, so it is only read from the environment or the secrets file, never from montrs.toml, which is committed to the repository.",
+                field, plate
+            ),
+            PlateConfigError::Invalid { plate, field, origin, reason } => format!(
+                "The value of {}.{} taken from {} does not have the type the plate expects: {}",
+                plate, field, origin, reason
+            ),
+            PlateConfigError::Validation { plate, reason } => {
+                format!("The configuration of plate '{}' loaded, but failed validation: {}", plate, reason)
+            }
+        }
+    }
+
+    fn suggested_fixes(&self) -> Vec<String> {
+        match self {
+            PlateConfigError::Read { path, .. } => vec![format!("Fix the TOML syntax of '{}'.", path)],
+            PlateConfigError::UnknownKey { plate, key } => vec![
+                format!("Check the spelling of '{}' in [plates.{}].", key, plate),
+                "Remove the key if the plate no longer uses it.".to_string(),
+            ],
+            PlateConfigError::Missing { plate, field, env_key } => vec![
+                format!("Add `{} = ...` under [plates.{}] in montrs.toml.", field, plate),
+                format!("Set the {} environment variable.", env_key),
+            ],
+            PlateConfigError::SecretInFile { plate, field, env_key } => vec![
+                format!("Remove '{}' from [plates.{}] and rotate the value if it was committed.", field, plate),
+                format!("Set {} in the environment, or store it with `montrs secrets set {}`.", env_key, env_key),
+            ],
+            PlateConfigError::Invalid { .. } => vec![
+                "Environment values are parsed as JSON first, so lists are written as `[\"a\", \"b\"]`.".to_string(),
+            ],
+            PlateConfigError::Validation { .. } => {
+                vec!["Adjust the values to satisfy the `#[schema(...)]` rules on the config struct.".to_string()]
+            }
+        }
+    }
+
+    fn subsystem(&self) -> &'static str {
+        "config"
+    }
+}
+
+/// Describes one field of a plate's configuration, for docs and agent tooling.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct PlateConfigField {
+    pub name: &'static str,
+    pub env_key: String,
+    pub secret: bool,
+    pub required: bool,
+    pub default: Option<&'static str>,
+    pub description: &'static str,
+}
+
+/// A plate's typed configuration section.
+///
+/// Usually derived:
+///
+/// ```rust,ignore
+/// #[derive(PlateConfig)]
+/// struct BillingConfig {
+///     #[plate_config(secret)]
+///     api_key: String,
+///     #[plate_config(default = 30)]
+///     timeout_secs: u64,
+/// }
+///
+/// let config = BillingConfig::load(&env)?;
+/// ```
+pub trait PlateConfig: Sized {
+    /// The plate's name: its section is `[plates.<PLATE>]` and its variables
+    /// start with `MONTRS_<PLATE>_`.
+    const PLATE: &'static str;
+
+    /// Builds the config from its `montrs.toml` section, if there is one,
+    /// with values in `env` taking precedence.
+    fn from_section(section: Option<&Table>, env: &dyn EnvConfig) -> Result<Self, PlateConfigError>;
+
+    /// The fields of the config, in declaration order.
+    fn fields() -> Vec<PlateConfigField>;
+
+    /// Loads the config from `montrs.toml` in the current directory.
+    fn load(env: &dyn EnvConfig) -> Result<Self, PlateConfigError> {
+        Self::load_from(Path::new(CONFIG_FILE), env)
+    }
+
+    /// Loads the config from the given `montrs.toml`. A missing file is
+    /// treated as an empty one, so env-only configuration works.
+    fn load_from(path: &Path, env: &dyn EnvConfig) -> Result<Self, PlateConfigError> {
+        let section = read_section(path, Self::PLATE)?;
+        Self::from_section(section.as_ref(), env)
+    }
+}
+
+/// The environment variable that overrides `field` of `plate`,
+/// e.g. `MONTRS_RATE_LIMIT_BURST` for `rate-limit.burst`.
+pub fn env_key(plate: &str, field: &str) -> String {
+    let upper = |s: &str| -> String {
+        s.chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+            .collect()
+    };
+    format!("MONTRS_{}_{}", upper(plate), upper(field))
+}
+
+/// Reads `[plates.<plate>]` from a `montrs.toml`.
+pub fn read_section(path: &Path, plate: &str) -> Result<Option<Table>, PlateConfigError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let read_error = |reason: String| PlateConfigError::Read {
+        path: path.display().to_string(),
+        reason,
+    };
+    let content = std::fs::read_to_string(path).map_err(|e| read_error(e.to_string()))?;
+    let mut root: Table = toml::from_str(&content).map_err(|e| read_error(e.to_string()))?;
+    let Some(toml::Value::Table(mut plates)) = root.remove("plates") else {
+        return Ok(None);
+    };
+    match plates.remove(plate) {
+        Some(toml::Value::Table(section)) => Ok(Some(section)),
+        Some(_) => Err(read_error(format!("[plates.{}] must be a table", plate))),
+        None => Ok(None),
+    }
+}
+
+/// Field-level access to one plate's sources, used by the derived
+/// [`PlateConfig::from_section`].
+pub struct PlateSection<'a> {
+    plate: &'static str,
+    table: Option<&'a Table>,
+    env: &'a dyn EnvConfig,
+}
+
+impl<'a> PlateSection<'a> {
+    /// Wraps the section, rejecting keys that are not in `fields`.
+    pub fn new(
+        plate: &'static str,
+        table: Option<&'a Table>,
+        env: &'a dyn EnvConfig,
+        fields: &[&str],
+    ) -> Result<Self, PlateConfigError> {
+        if let Some(key) = table.and_then(|t| t.keys().find(|k| !fields.contains(&k.as_str()))) {
+            return Err(PlateConfigError::UnknownKey {
+                plate: plate.to_string(),
+                key: key.clone(),
+            });
+        }
+        Ok(Self { plate, table, env })
+    }
+
+    /// The value of `field`, from `env_key` if it is set and from the section
+    /// otherwise. Secret fields are never read from the section.
+    pub fn value<T: DeserializeOwned>(
+        &self,
+        field: &str,
+        env_key: &str,
+        secret: bool,
+    ) -> Result<Option<T>, PlateConfigError> {
+        if let Ok(raw) = self.env.get_var(env_key) {
+            return self.parse(field, env_key, &raw).map(Some);
+        }
+        let Some(value) = self.table.and_then(|t| t.get(field)) else {
+            return Ok(None);
+        };
+        if secret {
+            return Err(PlateConfigError::SecretInFile {
+                plate: self.plate.to_string(),
+                field: field.to_string(),
+                env_key: env_key.to_string(),
+            });
+        }
+        value
+            .clone()
+            .try_into()
+            .map(Some)
+            .map_err(|e| self.invalid(field, CONFIG_FILE, e.to_string()))
+    }
+
+    /// Parses a `#[plate_config(default = "...")]` string the way an
+    /// environment value would be.
+    pub fn default_value<T: DeserializeOwned>(&self, field: &str, raw: &str) -> Result<T, PlateConfigError> {
+        self.parse(field, "the default", raw)
+    }
+
+    /// The error for a required field with no value.
+    pub fn missing(&self, field: &str, env_key: &str) -> PlateConfigError {
+        PlateConfigError::Missing {
+            plate: self.plate.to_string(),
+            field: field.to_string(),
+            env_key: env_key.to_string(),
+        }
+    }
+
+    /// Runs the config's `Validate` impl, as `#[plate_config(validate)]` requests.
+    pub fn validate<T: Validate>(&self, config: &T) -> Result<(), PlateConfigError> {
+        config.validate().map_err(|errors| PlateConfigError::Validation {
+            plate: self.plate.to_string(),
+            reason: errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "),
+        })
+    }
+
+    // Raw strings are read as JSON first, so `8080`, `true` and `["a", "b"]`
+    // reach numeric, boolean and list fields; anything else is a plain string.
+    fn parse<T: DeserializeOwned>(&self, field: &str, origin: &str, raw: &str) -> Result<T, PlateConfigError> {
+        if let Ok(value) = serde_json::from_str(raw) {
+            return Ok(value);
+        }
+        serde_json::from_value(serde_json::Value::String(raw.to_string()))
+            .map_err(|e| self.invalid(field, origin, e.to_string()))
+    }
+
+    fn invalid(&self, field: &str, origin: &str, reason: String) -> PlateConfigError {
+        PlateConfigError::Invalid {
+            plate: self.plate.to_string(),
+            field: field.to_string(),
+            origin: origin.to_string(),
+            reason,
+        }
+    }
+}
@@ -0,0 +1,312 @@
+//! montrs-core/src/prerender.rs: Cached renders of dynamic routes.
+//!
+//! A [`PageCache`] keeps what the loaders of selected routes returned, keyed
+//! by request path, in a [`PageStore`]: on disk with [`DiskPageStore`], in the
+//! process with [`MemoryPageStore`], or anywhere else through the trait. A
+//! `GET` for a cached page is answered from the store without running the
+//! loader. A page older than its route's TTL is still served, and rendered
+//! again in the background. [`PageCache::revalidate`], usually called from
+//! the action that changed the data, drops a page so the next request renders
+//! it anew. Each response says which of these happened in its
+//! `X-Montrs-Cache` header.
+//!
+//! Requests with a query string, loads that fail, and the pages of routes
+//! with guards or `.requires(..)` permissions are never cached: a stored page
+//! would be served without the checks, and to users it was not rendered for.
+
+use crate::env::EnvConfig;
+use crate::router::{RouteContext, RouteError, Router};
+use crate::wasi::{self, WasiRequest};
+use crate::AppConfig;
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The response header carrying the [`CacheStatus`].
+pub const CACHE_HEADER: &str = "X-Montrs-Cache";
+
+/// How a request for a cached route was answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+    /// From the store, within the TTL.
+    Hit,
+    /// From the store, past the TTL; a new render is on its way.
+    Stale,
+    /// Rendered for this request, then stored.
+    Miss,
+    /// Rendered for this request and not stored, because it has a query
+    /// string or its route is guarded.
+    Bypass,
+}
+
+impl CacheStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CacheStatus::Hit => "HIT",
+            CacheStatus::Stale => "STALE",
+            CacheStatus::Miss => "MISS",
+            CacheStatus::Bypass => "BYPASS",
+        }
+    }
+}
+
+/// A stored render: the loader's JSON output and when it was produced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedPage {
+    pub body: Vec<u8>,
+    pub rendered_at: DateTime<Utc>,
+}
+
+/// Where a [`PageCache`] keeps its pages.
+#[async_trait]
+pub trait PageStore: Send + Sync + 'static {
+    async fn get(&self, path: &str) -> anyhow::Result<Option<CachedPage>>;
+    async fn put(&self, path: &str, page: &CachedPage) -> anyhow::Result<()>;
+    /// Removes the page, if there is one.
+    async fn remove(&self, path: &str) -> anyhow::Result<()>;
+}
+
+#[async_trait]
+impl<T: PageStore> PageStore for Arc<T> {
+    async fn get(&self, path: &str) -> anyhow::Result<Option<CachedPage>> {
+        (**self).get(path).await
+    }
+
+    async fn put(&self, path: &str, page: &CachedPage) -> anyhow::Result<()> {
+        (**self).put(path, page).await
+    }
+
+    async fn remove(&self, path: &str) -> anyhow::Result<()> {
+        (**self).remove(path).await
+    }
+}
+
+/// Keeps pages in the process. Each instance of the app has its own.
+#[derive(Default)]
+pub struct MemoryPageStore {
+    pages: Mutex<HashMap<String, CachedPage>>,
+}
+
+impl MemoryPageStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl PageStore for MemoryPageStore {
+    async fn get(&self, path: &str) -> anyhow::Result<Option<CachedPage>> {
+        Ok(self.pages.lock().unwrap().get(path).cloned())
+    }
+
+    async fn put(&self, path: &str, page: &CachedPage) -> anyhow::Result<()> {
+        self.pages.lock().unwrap().insert(path.to_string(), page.clone());
+        Ok(())
+    }
+
+    async fn remove(&self, path: &str) -> anyhow::Result<()> {
+        self.pages.lock().unwrap().remove(path);
+        Ok(())
+    }
+}
+
+/// Keeps one file per page in a directory, so pages outlive restarts and
+/// instances sharing the directory share them. Files are named after the
+/// percent-encoded path and replaced atomically.
+pub struct DiskPageStore {
+    dir: PathBuf,
+}
+
+impl DiskPageStore {
+    /// Stores pages in `dir`, which is created on the first write.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn file(&self, path: &str) -> PathBuf {
+        let mut name = String::with_capacity(path.len() + 5);
+        for byte in path.bytes() {
+            match byte {
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' => name.push(byte as char),
+                _ => name.push_str(&format!("%{:02X}", byte)),
+            }
+        }
+        name.push_str(".page");
+        self.dir.join(name)
+    }
+}
+
+#[async_trait]
+impl PageStore for DiskPageStore {
+    /// A file is the render time in unix milliseconds, a newline, and the body.
+    async fn get(&self, path: &str) -> anyhow::Result<Option<CachedPage>> {
+        let bytes = match tokio::fs::read(self.file(path)).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let newline = bytes.iter().position(|b| *b == b'\n').ok_or_else(|| anyhow::anyhow!("invalid page file for {}", path))?;
+        let millis: i64 = std::str::from_utf8(&bytes[..newline])?.parse()?;
+        Ok(Some(CachedPage {
+            body: bytes[newline + 1..].to_vec(),
+            rendered_at: DateTime::<Utc>::from_timestamp_millis(millis).unwrap_or_default(),
+        }))
+    }
+
+    async fn put(&self, path: &str, page: &CachedPage) -> anyhow::Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let file = self.file(path);
+        let mut contents = format!("{}\n", page.rendered_at.timestamp_millis()).into_bytes();
+        contents.extend_from_slice(&page.body);
+        let partial = file.with_extension(format!("page.{}.tmp", std::process::id()));
+        tokio::fs::write(&partial, contents).await?;
+        tokio::fs::rename(&partial, &file).await?;
+        Ok(())
+    }
+
+    async fn remove(&self, path: &str) -> anyhow::Result<()> {
+        match tokio::fs::remove_file(self.file(path)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The routes whose pages are cached, with their TTLs, and the store the
+/// pages are in. Installed with [`AppSpec::with_page_cache`](crate::AppSpec::with_page_cache);
+/// put a clone of the `Arc` in the app's config so actions can revalidate.
+///
+/// ```rust,ignore
+/// let pages = Arc::new(
+///     PageCache::new(DiskPageStore::new("target/pages")).with_route("/todos/:id", Duration::from_secs(300)),
+/// );
+/// let spec = AppSpec::new(AppCfg { pages: pages.clone(), .. }, env).with_page_cache(pages);
+/// ```
+pub struct PageCache {
+    store: Box<dyn PageStore>,
+    routes: HashMap<String, Duration>,
+    /// Paths found stale and not rendered again yet.
+    stale: Mutex<Vec<String>>,
+    /// Paths queued or being rendered again, so each is rendered once.
+    refreshing: Mutex<HashSet<String>>,
+}
+
+/// What [`PageCache::serve`] answered with.
+pub(crate) struct Served {
+    pub status: CacheStatus,
+    pub age: Option<u64>,
+    pub result: Result<Vec<u8>, RouteError>,
+}
+
+impl PageCache {
+    pub fn new(store: impl PageStore) -> Self {
+        Self {
+            store: Box::new(store),
+            routes: HashMap::new(),
+            stale: Mutex::new(Vec::new()),
+            refreshing: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Caches the pages of the route registered at `pattern`, e.g.
+    /// `/todos/:id`, and renders them again once they are older than `ttl`.
+    pub fn with_route(mut self, pattern: impl Into<String>, ttl: Duration) -> Self {
+        self.routes.insert(pattern.into(), ttl);
+        self
+    }
+
+    /// The TTL of the route at `pattern`, if its pages are cached.
+    pub fn ttl(&self, pattern: &str) -> Option<Duration> {
+        self.routes.get(pattern).copied()
+    }
+
+    pub fn store(&self) -> &dyn PageStore {
+        self.store.as_ref()
+    }
+
+    /// Drops the page at `path`, e.g. `/todos/42`, so the next request
+    /// renders it with fresh data.
+    pub async fn revalidate(&self, path: &str) -> anyhow::Result<()> {
+        self.store.remove(path).await
+    }
+
+    /// Answers a request for `path`, whose route has `ttl`, from the store or
+    /// with `load`.
+    pub(crate) async fn serve(&self, path: &str, ttl: Duration, bypass: bool, load: impl Future<Output = Result<Bytes, RouteError>>) -> Served {
+        if bypass {
+            return Served { status: CacheStatus::Bypass, age: None, result: load.await.map(|body| body.to_vec()) };
+        }
+        match self.store.get(path).await {
+            Ok(Some(page)) => {
+                let age = (Utc::now() - page.rendered_at).to_std().unwrap_or_default();
+                let status = if age < ttl {
+                    CacheStatus::Hit
+                } else {
+                    self.mark_stale(path);
+                    CacheStatus::Stale
+                };
+                return Served { status, age: Some(age.as_secs()), result: Ok(page.body) };
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!(path, error = %e, "cached page unreadable; rendering it"),
+        }
+        let result = load.await;
+        if let Ok(body) = &result {
+            self.put(path, body).await;
+        }
+        Served { status: CacheStatus::Miss, age: None, result: result.map(|body| body.to_vec()) }
+    }
+
+    async fn put(&self, path: &str, body: &[u8]) {
+        let page = CachedPage { body: body.to_vec(), rendered_at: Utc::now() };
+        if let Err(e) = self.store.put(path, &page).await {
+            tracing::warn!(path, error = %e, "page not cached");
+        }
+    }
+
+    fn mark_stale(&self, path: &str) {
+        if self.refreshing.lock().unwrap().insert(path.to_string()) {
+            self.stale.lock().unwrap().push(path.to_string());
+        }
+    }
+
+    /// Whether pages found stale are waiting to be rendered again.
+    pub(crate) fn has_stale(&self) -> bool {
+        !self.stale.lock().unwrap().is_empty()
+    }
+}
+
+/// Renders `path` with its loader and stores the output, whether or not a
+/// page is already stored. The route must be cached.
+pub(crate) async fn render<C: AppConfig>(router: &Router<C>, config: &C, env: &dyn EnvConfig, path: &str) -> Result<(), RouteError> {
+    let cache = router.page_cache().ok_or_else(|| RouteError::InternalError("the router has no page cache".to_string()))?;
+    let matched = router.resolve(path).ok_or(RouteError::NotFound)?;
+    if cache.ttl(matched.pattern).is_none() || router.is_restricted(matched.pattern) {
+        return Err(RouteError::InternalError(format!("pages of {} are not cached", matched.pattern)));
+    }
+    let request = Arc::new(WasiRequest::new("GET", path));
+    let load = router.load_bytes(path, RouteContext { config, env }, serde_json::Value::Object(Default::default()));
+    let body = wasi::with_request(&request, load).await?;
+    cache.put(path, &body).await;
+    Ok(())
+}
+
+/// Renders the pages found stale since the last call again. A page whose
+/// loader fails stays stale and is retried on its next request.
+pub(crate) async fn refresh_stale<C: AppConfig>(router: &Router<C>, config: &C, env: &dyn EnvConfig) {
+    let Some(cache) = router.page_cache() else {
+        return;
+    };
+    let paths = std::mem::take(&mut *cache.stale.lock().unwrap());
+    for path in paths {
+        if let Err(e) = render(router, config, env, &path).await {
+            tracing::warn!(path, error = %e, "stale page not rendered again");
+        }
+        cache.refreshing.lock().unwrap().remove(&path);
+    }
+}
@@ -0,0 +1,322 @@
+//! montrs-core/src/preset.rs: Target-aware defaults for CORS, security headers,
+//! rate limits and asset serving.
+//!
+//! Each [`Target`] starts from its own [`Preset`]. A server sends HSTS and keeps
+//! an in-memory rate limiter. An edge isolate leaves rate limiting and static
+//! assets to the platform. A desktop shell allows its `tauri://` origin and
+//! skips HSTS. The `[preset]` table of `montrs.toml` overrides any field, for
+//! every target or under `[preset.<target>]` for one. `montrs serve` passes the
+//! table to the app in [`PRESET_VAR`], and `AppSpec::boot` resolves it for the
+//! app's target. `montrs config resolve` prints the result.
+
+use crate::limiter::{GovernorLimiter, Limiter};
+use crate::Target;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// Environment variable holding the `[preset]` table of `montrs.toml` as JSON.
+pub const PRESET_VAR: &str = "MONTRS_PRESET";
+
+#[derive(Debug, thiserror::Error)]
+pub enum PresetError {
+    #[error("Unknown target '{0}'; expected one of: server, wasm, edge, desktop, mobile-android, mobile-ios, wasi")]
+    UnknownTarget(String),
+    #[error("Invalid preset override: {0}")]
+    Invalid(String),
+}
+
+/// The serving defaults of one target.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Preset {
+    pub cors: Cors,
+    pub security: SecurityHeaders,
+    pub limits: Limits,
+    pub assets: Assets,
+}
+
+/// Which browser origins may call the app.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Cors {
+    /// Allowed origins. `*` allows any; empty allows none but the app's own.
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    /// Allows cookies. A `*` origin is then echoed back instead of sent as is.
+    pub allow_credentials: bool,
+    /// How long browsers may cache a preflight answer.
+    pub max_age_secs: u64,
+}
+
+/// Headers sent with every response. `None` leaves a header out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecurityHeaders {
+    pub content_security_policy: Option<String>,
+    pub strict_transport_security: Option<String>,
+    pub frame_options: Option<String>,
+    pub referrer_policy: Option<String>,
+    /// Sends `X-Content-Type-Options: nosniff`.
+    pub nosniff: bool,
+}
+
+/// Where rate limiting happens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LimiterBackend {
+    /// No rate limiting.
+    Off,
+    /// A [`GovernorLimiter`] in the app's memory.
+    Memory,
+    /// The hosting platform's rules; the app does not count requests.
+    Platform,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Limits {
+    pub limiter: LimiterBackend,
+    /// Requests per second the `memory` limiter lets through.
+    pub requests_per_second: u32,
+    /// Larger request bodies are answered `413`.
+    pub max_body_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Assets {
+    /// Whether the app serves the site root itself, rather than a CDN or the platform.
+    pub serve: bool,
+    /// `Cache-Control` for fingerprinted files.
+    pub immutable_cache_control: String,
+    /// `Cache-Control` for HTML and other files without a hash in their name.
+    pub cache_control: String,
+    /// Serves `.br` and `.gz` siblings to clients that accept them.
+    pub precompressed: bool,
+}
+
+impl Preset {
+    /// The defaults of `target`. A browser (`Wasm`) build gets the server's,
+    /// since the server hosting it sends the headers.
+    pub fn for_target(target: Target) -> Self {
+        let security = SecurityHeaders {
+            content_security_policy: Some("default-src 'self'".to_string()),
+            strict_transport_security: Some("max-age=31536000; includeSubDomains".to_string()),
+            frame_options: Some("DENY".to_string()),
+            referrer_policy: Some("strict-origin-when-cross-origin".to_string()),
+            nosniff: true,
+        };
+        let assets = Assets {
+            serve: true,
+            immutable_cache_control: "public, max-age=31536000, immutable".to_string(),
+            cache_control: "no-cache".to_string(),
+            precompressed: true,
+        };
+        let server = Self {
+            cors: Cors::same_origin(),
+            security,
+            limits: Limits { limiter: LimiterBackend::Memory, requests_per_second: 100, max_body_bytes: 10 * 1024 * 1024 },
+            assets,
+        };
+        match target {
+            Target::Server | Target::Wasm => server,
+            // Isolates share no memory, and the platform serves static files.
+            Target::Edge | Target::Wasi => Self {
+                limits: Limits { limiter: LimiterBackend::Platform, max_body_bytes: 1024 * 1024, ..server.limits },
+                assets: Assets { serve: false, precompressed: false, ..server.assets },
+                ..server
+            },
+            // The webview loads the app from a custom scheme, without TLS.
+            Target::Desktop | Target::MobileAndroid | Target::MobileIos => Self {
+                cors: Cors {
+                    allowed_origins: ["tauri://localhost", "http://tauri.localhost", "https://tauri.localhost"]
+                        .map(String::from)
+                        .to_vec(),
+                    ..Cors::same_origin()
+                },
+                security: SecurityHeaders {
+                    content_security_policy: Some("default-src 'self' ipc: http://ipc.localhost; img-src 'self' asset: data:".to_string()),
+                    strict_transport_security: None,
+                    referrer_policy: Some("no-referrer".to_string()),
+                    ..server.security
+                },
+                limits: Limits { limiter: LimiterBackend::Off, max_body_bytes: 100 * 1024 * 1024, ..server.limits },
+                assets: Assets { precompressed: false, ..server.assets },
+            },
+        }
+    }
+
+    /// The defaults of `target` with `overrides`, the `[preset]` table of
+    /// `montrs.toml`, applied: first its fields shared by every target, then
+    /// the table named after `target`. Tables are merged key by key; any other
+    /// value, lists included, replaces the default.
+    pub fn resolve(target: Target, overrides: &Value) -> Result<Self, PresetError> {
+        let mut preset = serde_json::to_value(Self::for_target(target)).map_err(|e| PresetError::Invalid(e.to_string()))?;
+        if let Value::Object(overrides) = overrides {
+            let shared: Map<String, Value> = overrides
+                .iter()
+                .filter(|(key, _)| key.as_str() != "target" && key.parse::<Target>().is_err())
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+            merge(&mut preset, Value::Object(shared));
+            if let Some(specific) = overrides.get(target.name()) {
+                merge(&mut preset, specific.clone());
+            }
+        }
+        serde_json::from_value(preset).map_err(|e| PresetError::Invalid(e.to_string()))
+    }
+
+    /// The target named by the `target` key of `overrides`, for tools that
+    /// resolve the preset outside the app.
+    pub fn configured_target(overrides: &Value) -> Result<Option<Target>, PresetError> {
+        overrides.get("target").and_then(Value::as_str).map(str::parse).transpose()
+    }
+
+    /// [`Preset::resolve`] with the overrides in [`PRESET_VAR`], if it is set.
+    pub fn from_env(target: Target) -> Result<Self, PresetError> {
+        let overrides = match std::env::var(PRESET_VAR) {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| PresetError::Invalid(format!("{}: {}", PRESET_VAR, e)))?,
+            Err(_) => Value::Null,
+        };
+        Self::resolve(target, &overrides)
+    }
+
+    /// The in-memory limiter, when `limits.limiter` is `memory`.
+    pub fn limiter(&self) -> Option<Box<dyn Limiter>> {
+        match self.limits.limiter {
+            LimiterBackend::Memory => Some(Box::new(GovernorLimiter::new(self.limits.requests_per_second.max(1)))),
+            LimiterBackend::Off | LimiterBackend::Platform => None,
+        }
+    }
+
+    /// The security headers, plus the CORS headers for a request from `origin`.
+    pub fn response_headers(&self, origin: Option<&str>) -> Vec<(String, String)> {
+        let mut headers = self.security.headers();
+        headers.extend(self.cors.headers(origin));
+        headers
+    }
+}
+
+impl fmt::Display for LimiterBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LimiterBackend::Off => "off",
+            LimiterBackend::Memory => "memory",
+            LimiterBackend::Platform => "platform",
+        })
+    }
+}
+
+fn merge(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
+
+impl Cors {
+    fn same_origin() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allowed_methods: ["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE"].map(String::from).to_vec(),
+            allowed_headers: ["content-type", "authorization", "accept"].map(String::from).to_vec(),
+            allow_credentials: false,
+            max_age_secs: 600,
+        }
+    }
+
+    pub fn allows(&self, origin: &str) -> bool {
+        self.allowed_origins.iter().any(|allowed| allowed == "*" || allowed.eq_ignore_ascii_case(origin))
+    }
+
+    /// `Access-Control-Allow-*` headers for a request from `origin`; none when
+    /// the origin is not allowed or the request is same-origin.
+    pub fn headers(&self, origin: Option<&str>) -> Vec<(String, String)> {
+        let Some(origin) = origin.filter(|origin| self.allows(origin)) else {
+            return Vec::new();
+        };
+        let any = !self.allow_credentials && self.allowed_origins.iter().any(|allowed| allowed == "*");
+        let mut headers = vec![("access-control-allow-origin".to_string(), if any { "*" } else { origin }.to_string())];
+        if !any {
+            headers.push(("vary".to_string(), "Origin".to_string()));
+        }
+        if self.allow_credentials {
+            headers.push(("access-control-allow-credentials".to_string(), "true".to_string()));
+        }
+        headers
+    }
+
+    /// The headers answering a preflight `OPTIONS` request from `origin`.
+    pub fn preflight_headers(&self, origin: Option<&str>) -> Vec<(String, String)> {
+        let mut headers = self.headers(origin);
+        if !headers.is_empty() {
+            headers.push(("access-control-allow-methods".to_string(), self.allowed_methods.join(", ")));
+            headers.push(("access-control-allow-headers".to_string(), self.allowed_headers.join(", ")));
+            headers.push(("access-control-max-age".to_string(), self.max_age_secs.to_string()));
+        }
+        headers
+    }
+}
+
+impl SecurityHeaders {
+    pub fn headers(&self) -> Vec<(String, String)> {
+        let nosniff = self.nosniff.then(|| "nosniff".to_string());
+        [
+            ("content-security-policy", &self.content_security_policy),
+            ("strict-transport-security", &self.strict_transport_security),
+            ("x-frame-options", &self.frame_options),
+            ("referrer-policy", &self.referrer_policy),
+            ("x-content-type-options", &nosniff),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.clone()?)))
+        .collect()
+    }
+}
+
+impl Target {
+    pub const ALL: [Target; 7] =
+        [Target::Server, Target::Wasm, Target::Edge, Target::Desktop, Target::MobileAndroid, Target::MobileIos, Target::Wasi];
+
+    /// The name used in `montrs.toml` and on the command line, e.g. `mobile-ios`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Target::Server => "server",
+            Target::Wasm => "wasm",
+            Target::Edge => "edge",
+            Target::Desktop => "desktop",
+            Target::MobileAndroid => "mobile-android",
+            Target::MobileIos => "mobile-ios",
+            Target::Wasi => "wasi",
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Target {
+    type Err = PresetError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Target::ALL
+            .into_iter()
+            .find(|target| target.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| PresetError::UnknownTarget(name.to_string()))
+    }
+}
@@ -0,0 +1,275 @@
+//! montrs-core/src/profile.rs: Per-route profiling for development sessions.
+//! When enabled (`montrs serve --profile`), the router measures every loader and
+//! action call: wall time, time spent inside `poll` (CPU), how often the handler
+//! yielded (await points), allocations and database time. Totals are kept per
+//! route for the whole session and written as a JSON summary plus a folded-stack
+//! file that `inferno-flamegraph` or speedscope can render.
+
+use serde::{Deserialize, Serialize};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Output directory for the profile, set by `montrs serve --profile`.
+pub const PROFILE_VAR: &str = "MONTRS_PROFILE";
+/// Summary of every profiled route, sorted slowest first.
+pub const SUMMARY_FILE: &str = "profile.json";
+/// Folded stacks (`route;operation;frame microseconds`) for flamegraph tools.
+pub const FOLDED_FILE: &str = "profile.folded";
+
+const DUMP_INTERVAL: Duration = Duration::from_secs(1);
+
+thread_local! {
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+    static ALLOCATED_BYTES: Cell<u64> = const { Cell::new(0) };
+    static DB_MICROS: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Counts allocations per thread so the profiler can attribute them to routes.
+/// Without it, allocation columns stay at zero.
+///
+/// ```rust,ignore
+/// #[global_allocator]
+/// static ALLOC: montrs_core::profile::TrackingAllocator = montrs_core::profile::TrackingAllocator;
+/// ```
+pub struct TrackingAllocator;
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|c| c.set(c.get() + 1));
+        let _ = ALLOCATED_BYTES.try_with(|c| c.set(c.get() + layout.size() as u64));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|c| c.set(c.get() + 1));
+        let _ = ALLOCATED_BYTES.try_with(|c| c.set(c.get() + new_size as u64));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+fn allocation_counters() -> (u64, u64) {
+    (
+        ALLOCATIONS.try_with(Cell::get).unwrap_or(0),
+        ALLOCATED_BYTES.try_with(Cell::get).unwrap_or(0),
+    )
+}
+
+/// Attributes database time to the route being profiled on this thread.
+/// Called by the ORM backends; a no-op outside a profiled handler.
+pub fn record_db_time(elapsed: Duration) {
+    DB_MICROS.with(|db| {
+        if let Some(total) = db.get() {
+            db.set(Some(total + elapsed.as_micros() as u64));
+        }
+    });
+}
+
+/// Records the time until it is dropped with [`record_db_time`].
+pub struct DbTimer(Instant);
+
+impl DbTimer {
+    pub fn start() -> Self {
+        Self(Instant::now())
+    }
+}
+
+impl Drop for DbTimer {
+    fn drop(&mut self) {
+        record_db_time(self.0.elapsed());
+    }
+}
+
+/// Session totals for one route operation. Times are in microseconds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RouteProfile {
+    pub route: String,
+    /// `load` or `act`.
+    pub operation: String,
+    pub calls: u64,
+    pub wall_us: u64,
+    pub max_wall_us: u64,
+    /// Time spent inside `poll`, i.e. on a CPU rather than waiting.
+    pub cpu_us: u64,
+    /// Times the handler returned `Pending` before completing.
+    pub await_points: u64,
+    pub allocations: u64,
+    pub allocated_bytes: u64,
+    pub db_us: u64,
+}
+
+impl RouteProfile {
+    pub fn avg_wall_us(&self) -> u64 {
+        self.wall_us.checked_div(self.calls).unwrap_or(0)
+    }
+
+    /// Splits the wall time into `db`, `cpu` and `await` frames that add up to it.
+    fn frames(&self) -> [(&'static str, u64); 3] {
+        let db = self.db_us.min(self.wall_us);
+        let cpu = self.cpu_us.min(self.wall_us - db);
+        [("db", db), ("cpu", cpu), ("await", self.wall_us - db - cpu)]
+    }
+}
+
+#[derive(Default)]
+struct Sample {
+    cpu_us: u64,
+    polls: u64,
+    allocations: u64,
+    allocated_bytes: u64,
+    db_us: u64,
+}
+
+/// Aggregates route measurements for a session.
+pub struct Profiler {
+    routes: Mutex<HashMap<(String, &'static str), RouteProfile>>,
+    output: Option<PathBuf>,
+    last_dump: Mutex<Option<Instant>>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            routes: Mutex::new(HashMap::new()),
+            output: None,
+            last_dump: Mutex::new(None),
+        }
+    }
+
+    /// Writes the summary and folded stacks to `dir`, at most once per second.
+    pub fn with_output(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.output = Some(dir.into());
+        self
+    }
+
+    /// The profiler requested by `montrs serve --profile`, if any.
+    pub fn from_env() -> Option<Self> {
+        let dir = std::env::var_os(PROFILE_VAR)?;
+        Some(Self::new().with_output(dir))
+    }
+
+    /// Runs `handler` and adds its measurements to `route`/`operation`.
+    pub async fn measure<F: Future>(&self, route: &str, operation: &'static str, handler: F) -> F::Output {
+        let started = Instant::now();
+        let mut sample = Sample::default();
+        let mut handler = std::pin::pin!(handler);
+        let output = std::future::poll_fn(|cx| {
+            let poll_started = Instant::now();
+            let (allocations, bytes) = allocation_counters();
+            let outer_db = DB_MICROS.with(|db| db.replace(Some(0)));
+
+            let poll = handler.as_mut().poll(cx);
+
+            let db_us = DB_MICROS.with(|db| db.replace(outer_db)).unwrap_or(0);
+            let (allocations_after, bytes_after) = allocation_counters();
+            sample.cpu_us += poll_started.elapsed().as_micros() as u64;
+            sample.polls += 1;
+            sample.allocations += allocations_after.saturating_sub(allocations);
+            sample.allocated_bytes += bytes_after.saturating_sub(bytes);
+            sample.db_us += db_us;
+            poll
+        })
+        .await;
+        self.record(route, operation, started.elapsed(), sample);
+        output
+    }
+
+    fn record(&self, route: &str, operation: &'static str, wall: Duration, sample: Sample) {
+        let wall_us = wall.as_micros() as u64;
+        {
+            let mut routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+            let entry = routes
+                .entry((route.to_string(), operation))
+                .or_insert_with(|| RouteProfile {
+                    route: route.to_string(),
+                    operation: operation.to_string(),
+                    ..Default::default()
+                });
+            entry.calls += 1;
+            entry.wall_us += wall_us;
+            entry.max_wall_us = entry.max_wall_us.max(wall_us);
+            entry.cpu_us += sample.cpu_us;
+            entry.await_points += sample.polls.saturating_sub(1);
+            entry.allocations += sample.allocations;
+            entry.allocated_bytes += sample.allocated_bytes;
+            entry.db_us += sample.db_us;
+        }
+        self.maybe_dump();
+    }
+
+    /// Every profiled route, slowest (by total wall time) first.
+    pub fn report(&self) -> Vec<RouteProfile> {
+        let routes = self.routes.lock().unwrap_or_else(|e| e.into_inner());
+        let mut report: Vec<_> = routes.values().cloned().collect();
+        report.sort_by(|a, b| b.wall_us.cmp(&a.wall_us).then_with(|| a.route.cmp(&b.route)));
+        report
+    }
+
+    /// The `n` routes with the highest average wall time.
+    pub fn slowest(&self, n: usize) -> Vec<RouteProfile> {
+        let mut report = self.report();
+        report.sort_by_key(|p| std::cmp::Reverse(p.avg_wall_us()));
+        report.truncate(n);
+        report
+    }
+
+    /// The session in folded-stack format, one `route;operation;frame micros` line per frame.
+    pub fn folded(&self) -> String {
+        let mut out = String::new();
+        for profile in self.report() {
+            for (frame, micros) in profile.frames() {
+                if micros > 0 {
+                    out.push_str(&format!("{};{};{} {}\n", profile.route, profile.operation, frame, micros));
+                }
+            }
+        }
+        out
+    }
+
+    /// Writes `profile.json` and `profile.folded` to `dir`.
+    pub fn write_to(&self, dir: &Path) -> std::io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+        let summary = serde_json::to_string_pretty(&self.report()).map_err(std::io::Error::other)?;
+        std::fs::write(dir.join(SUMMARY_FILE), summary)?;
+        std::fs::write(dir.join(FOLDED_FILE), self.folded())
+    }
+
+    fn maybe_dump(&self) {
+        let Some(dir) = &self.output else {
+            return;
+        };
+        {
+            let mut last = self.last_dump.lock().unwrap_or_else(|e| e.into_inner());
+            if last.is_some_and(|at| at.elapsed() < DUMP_INTERVAL) {
+                return;
+            }
+            *last = Some(Instant::now());
+        }
+        if let Err(e) = self.write_to(dir) {
+            tracing::warn!(error = %e, dir = %dir.display(), "failed to write route profile");
+        }
+    }
+}
+
+impl Drop for Profiler {
+    /// Flushes calls made since the last throttled write.
+    fn drop(&mut self) {
+        if let Some(dir) = &self.output {
+            let _ = self.write_to(dir);
+        }
+    }
+}
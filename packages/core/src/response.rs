@@ -0,0 +1,511 @@
+//! montrs-core/src/response.rs: Streaming response helpers for Loaders.
+//! This file provides file downloads with HTTP range support, Content-Disposition
+//! helpers, bandwidth throttling, and CSV/NDJSON serializers that stream rows
+//! from an async source without buffering the whole payload in memory.
+
+use crate::AgentError;
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// Default chunk size used when streaming files (64 KiB).
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Errors that can occur while building or streaming a response.
+#[derive(Debug, thiserror::Error)]
+pub enum ResponseError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid Range header: {0}")]
+    InvalidRange(String),
+    #[error("Requested range not satisfiable for a body of {0} bytes")]
+    RangeNotSatisfiable(u64),
+    #[error("Serialization error: {0}")]
+    Serialization(String),
+}
+
+impl AgentError for ResponseError {
+    fn error_code(&self) -> &'static str {
+        match self {
+            ResponseError::Io(_) => "RESPONSE_IO",
+            ResponseError::InvalidRange(_) => "RESPONSE_INVALID_RANGE",
+            ResponseError::RangeNotSatisfiable(_) => "RESPONSE_RANGE_NOT_SATISFIABLE",
+            ResponseError::Serialization(_) => "RESPONSE_SERIALIZATION",
+        }
+    }
+
+    fn explanation(&self) -> String {
+        match self {
+            ResponseError::Io(e) => format!("Reading the response body failed: {}", e),
+            ResponseError::InvalidRange(h) => format!("The Range header '{}' could not be parsed as a byte range.", h),
+            ResponseError::RangeNotSatisfiable(len) => format!("The requested byte range lies outside the {} byte body.", len),
+            ResponseError::Serialization(e) => format!("A streamed row could not be serialized: {}", e),
+        }
+    }
+
+    fn suggested_fixes(&self) -> Vec<String> {
+        match self {
+            ResponseError::Io(_) => vec!["Check that the file exists and is readable by the server process.".to_string()],
+            ResponseError::InvalidRange(_) => vec!["Send a Range header of the form 'bytes=start-end', 'bytes=start-' or 'bytes=-suffix'.".to_string()],
+            ResponseError::RangeNotSatisfiable(_) => vec!["Request a range that starts before the end of the body.".to_string()],
+            ResponseError::Serialization(_) => vec![
+                "Ensure each row serializes to a flat struct or map when streaming CSV.".to_string(),
+            ],
+        }
+    }
+
+    fn subsystem(&self) -> &'static str {
+        "response"
+    }
+}
+
+/// A chunked response body.
+pub type BodyStream = BoxStream<'static, Result<Vec<u8>, ResponseError>>;
+
+/// A streaming HTTP response produced by a Loader helper.
+pub struct StreamingResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: BodyStream,
+}
+
+impl StreamingResponse {
+    /// Creates a `200 OK` response from a body stream.
+    pub fn new(body: BodyStream) -> Self {
+        Self {
+            status: 200,
+            headers: Vec::new(),
+            body,
+        }
+    }
+
+    /// Sets the status code.
+    pub fn with_status(mut self, status: u16) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Appends a header.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the `Content-Type` header.
+    pub fn with_content_type(self, content_type: impl Into<String>) -> Self {
+        self.with_header("Content-Type", content_type)
+    }
+
+    /// Sets the `Content-Disposition` header.
+    pub fn with_disposition(self, disposition: ContentDisposition) -> Self {
+        self.with_header("Content-Disposition", disposition.to_header_value())
+    }
+
+    /// Limits the body to roughly `bytes_per_second`.
+    pub fn throttled(mut self, bytes_per_second: u64) -> Self {
+        self.body = throttle(self.body, bytes_per_second);
+        self
+    }
+
+    /// Returns the first header matching `name` (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Collects the whole body into memory. Intended for tests and small payloads.
+    pub async fn collect_body(self) -> Result<Vec<u8>, ResponseError> {
+        let mut out = Vec::new();
+        let mut body = self.body;
+        while let Some(chunk) = body.next().await {
+            out.extend_from_slice(&chunk?);
+        }
+        Ok(out)
+    }
+}
+
+/// A `Content-Disposition` header value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentDisposition {
+    /// Display the body in the browser.
+    Inline,
+    /// Prompt a download with the given filename.
+    Attachment(String),
+}
+
+impl ContentDisposition {
+    /// Creates an attachment disposition.
+    pub fn attachment(filename: impl Into<String>) -> Self {
+        ContentDisposition::Attachment(filename.into())
+    }
+
+    /// Renders the header value. Non-ASCII filenames get an ASCII fallback
+    /// plus an RFC 5987 `filename*` parameter.
+    pub fn to_header_value(&self) -> String {
+        match self {
+            ContentDisposition::Inline => "inline".to_string(),
+            ContentDisposition::Attachment(name) => {
+                let fallback: String = name
+                    .chars()
+                    .map(|c| match c {
+                        '"' | '\\' => '_',
+                        c if c.is_ascii() && !c.is_ascii_control() => c,
+                        _ => '_',
+                    })
+                    .collect();
+                if fallback == *name {
+                    format!("attachment; filename=\"{}\"", fallback)
+                } else {
+                    format!(
+                        "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+                        fallback,
+                        percent_encode(name)
+                    )
+                }
+            }
+        }
+    }
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for b in value.bytes() {
+        if b.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+/// An inclusive byte range resolved against a body length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    /// Parses a `Range` header against a body of `len` bytes.
+    ///
+    /// Returns `Ok(None)` for multi-range requests, which are served as the
+    /// full body as permitted by RFC 9110.
+    pub fn parse(header: &str, len: u64) -> Result<Option<Self>, ResponseError> {
+        let spec = header
+            .trim()
+            .strip_prefix("bytes=")
+            .ok_or_else(|| ResponseError::InvalidRange(header.to_string()))?;
+        if spec.contains(',') {
+            return Ok(None);
+        }
+        let (start, end) = spec
+            .split_once('-')
+            .ok_or_else(|| ResponseError::InvalidRange(header.to_string()))?;
+        let parse = |s: &str| {
+            s.trim()
+                .parse::<u64>()
+                .map_err(|_| ResponseError::InvalidRange(header.to_string()))
+        };
+
+        let range = match (start.trim().is_empty(), end.trim().is_empty()) {
+            (true, true) => return Err(ResponseError::InvalidRange(header.to_string())),
+            (true, false) => {
+                let suffix = parse(end)?;
+                if suffix == 0 || len == 0 {
+                    return Err(ResponseError::RangeNotSatisfiable(len));
+                }
+                ByteRange {
+                    start: len.saturating_sub(suffix),
+                    end: len - 1,
+                }
+            }
+            (false, true) => ByteRange {
+                start: parse(start)?,
+                end: len.saturating_sub(1),
+            },
+            (false, false) => {
+                let (s, e) = (parse(start)?, parse(end)?);
+                if e < s {
+                    return Err(ResponseError::InvalidRange(header.to_string()));
+                }
+                ByteRange {
+                    start: s,
+                    end: e.min(len.saturating_sub(1)),
+                }
+            }
+        };
+
+        if range.start >= len {
+            return Err(ResponseError::RangeNotSatisfiable(len));
+        }
+        Ok(Some(range))
+    }
+
+    /// The number of bytes covered by this range.
+    pub fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// Always false; a resolved range covers at least one byte.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+/// Builder for streaming a file from disk, honoring `Range` requests.
+pub struct FileDownload {
+    path: PathBuf,
+    filename: Option<String>,
+    content_type: Option<String>,
+    range: Option<String>,
+    chunk_size: usize,
+}
+
+impl FileDownload {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            filename: None,
+            content_type: None,
+            range: None,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+
+    /// Serves the file as an attachment with the given filename.
+    pub fn with_filename(mut self, filename: impl Into<String>) -> Self {
+        self.filename = Some(filename.into());
+        self
+    }
+
+    /// Overrides the content type guessed from the file extension.
+    pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Passes through the request's `Range` header, if any.
+    pub fn with_range(mut self, range: Option<&str>) -> Self {
+        self.range = range.map(str::to_string);
+        self
+    }
+
+    /// Sets the read chunk size in bytes.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Opens the file and builds a `200` or `206` response.
+    pub async fn into_response(self) -> Result<StreamingResponse, ResponseError> {
+        let mut file = tokio::fs::File::open(&self.path).await?;
+        let len = file.metadata().await?.len();
+        let range = match &self.range {
+            Some(header) => ByteRange::parse(header, len)?,
+            None => None,
+        };
+
+        let (start, count) = match range {
+            Some(r) => (r.start, r.len()),
+            None => (0, len),
+        };
+        if start > 0 {
+            file.seek(std::io::SeekFrom::Start(start)).await?;
+        }
+
+        let chunk_size = self.chunk_size;
+        let body = stream::try_unfold((file, count), move |(mut file, remaining)| async move {
+            if remaining == 0 {
+                return Ok(None);
+            }
+            let want = remaining.min(chunk_size as u64) as usize;
+            let mut buf = vec![0u8; want];
+            let read = file.read(&mut buf).await?;
+            if read == 0 {
+                return Ok(None);
+            }
+            buf.truncate(read);
+            Ok::<_, ResponseError>(Some((buf, (file, remaining - read as u64))))
+        })
+        .boxed();
+
+        let content_type = self
+            .content_type
+            .unwrap_or_else(|| guess_content_type(&self.path).to_string());
+        let mut response = StreamingResponse::new(body)
+            .with_content_type(content_type)
+            .with_header("Accept-Ranges", "bytes")
+            .with_header("Content-Length", count.to_string());
+
+        if let Some(r) = range {
+            response = response
+                .with_status(206)
+                .with_header("Content-Range", format!("bytes {}-{}/{}", r.start, r.end, len));
+        }
+        if let Some(name) = self.filename {
+            response = response.with_disposition(ContentDisposition::Attachment(name));
+        }
+        Ok(response)
+    }
+}
+
+/// Guesses a content type from a file extension, falling back to `application/octet-stream`.
+pub fn guess_content_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "txt" => "text/plain; charset=utf-8",
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "json" => "application/json",
+        "ndjson" => "application/x-ndjson",
+        "csv" => "text/csv; charset=utf-8",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "wasm" => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Wraps a body so it is emitted at roughly `bytes_per_second`.
+/// Useful for large exports that should not saturate the server's uplink.
+pub fn throttle(body: BodyStream, bytes_per_second: u64) -> BodyStream {
+    let rate = bytes_per_second.max(1);
+    body.then(move |chunk| async move {
+        if let Ok(bytes) = &chunk {
+            let secs = bytes.len() as f64 / rate as f64;
+            tokio::time::sleep(Duration::from_secs_f64(secs)).await;
+        }
+        chunk
+    })
+    .boxed()
+}
+
+/// Serializes rows as newline-delimited JSON, one row per chunk.
+pub fn ndjson<S, T>(rows: S) -> StreamingResponse
+where
+    S: Stream<Item = T> + Send + 'static,
+    T: Serialize + Send + 'static,
+{
+    let body = rows
+        .map(|row| {
+            let mut line = serde_json::to_vec(&row)
+                .map_err(|e| ResponseError::Serialization(e.to_string()))?;
+            line.push(b'\n');
+            Ok(line)
+        })
+        .boxed();
+    StreamingResponse::new(body).with_content_type("application/x-ndjson")
+}
+
+/// Serializes rows as CSV. The header row is the first row's fields in the
+/// order they serialize, i.e. a struct's declaration order; use
+/// [`csv_with_columns`] to pick and order the columns yourself.
+pub fn csv<S, T>(rows: S) -> StreamingResponse
+where
+    S: Stream<Item = T> + Send + 'static,
+    T: Serialize + Send + 'static,
+{
+    csv_stream(rows, None)
+}
+
+/// Serializes rows as CSV with an explicit column order.
+pub fn csv_with_columns<S, T>(rows: S, columns: Vec<String>) -> StreamingResponse
+where
+    S: Stream<Item = T> + Send + 'static,
+    T: Serialize + Send + 'static,
+{
+    csv_stream(rows, Some(columns))
+}
+
+fn csv_stream<S, T>(rows: S, columns: Option<Vec<String>>) -> StreamingResponse
+where
+    S: Stream<Item = T> + Send + 'static,
+    T: Serialize + Send + 'static,
+{
+    let body = stream::try_unfold(
+        (rows.boxed(), columns, false),
+        |(mut rows, mut columns, mut wrote_header)| async move {
+            let Some(row) = rows.next().await else {
+                return Ok(None);
+            };
+            let json = serde_json::to_vec(&row).map_err(|e| ResponseError::Serialization(e.to_string()))?;
+            let CsvRow(fields) = serde_json::from_slice(&json).map_err(|_| {
+                ResponseError::Serialization("CSV rows must serialize to an object".to_string())
+            })?;
+
+            let cols = columns.get_or_insert_with(|| fields.iter().map(|(name, _)| name.clone()).collect());
+            let mut chunk = String::new();
+            if !wrote_header {
+                chunk.push_str(&csv_line(cols.iter().map(|c| c.as_str())));
+                wrote_header = true;
+            }
+            let cells: Vec<String> = cols
+                .iter()
+                .map(|c| match fields.iter().find(|(name, _)| name == c).map(|(_, value)| value) {
+                    None | Some(serde_json::Value::Null) => String::new(),
+                    Some(serde_json::Value::String(s)) => s.clone(),
+                    Some(other) => other.to_string(),
+                })
+                .collect();
+            chunk.push_str(&csv_line(cells.iter().map(|c| c.as_str())));
+
+            Ok(Some((chunk.into_bytes(), (rows, columns, wrote_header))))
+        },
+    )
+    .boxed();
+    StreamingResponse::new(body).with_content_type("text/csv; charset=utf-8")
+}
+
+/// A row's fields in the order they were serialized, which a
+/// `serde_json::Map` would sort by name.
+struct CsvRow(Vec<(String, serde_json::Value)>);
+
+impl<'de> Deserialize<'de> for CsvRow {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Fields;
+        impl<'de> Visitor<'de> for Fields {
+            type Value = CsvRow;
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("an object")
+            }
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<CsvRow, A::Error> {
+                let mut fields = Vec::new();
+                while let Some(field) = map.next_entry()? {
+                    fields.push(field);
+                }
+                Ok(CsvRow(fields))
+            }
+        }
+        deserializer.deserialize_map(Fields)
+    }
+}
+
+fn csv_line<'a>(cells: impl Iterator<Item = &'a str>) -> String {
+    let mut line = cells
+        .map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
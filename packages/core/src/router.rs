@@ -3,14 +3,49 @@
 //! This file defines the core traits and structs for the MontRS Router,
 //! ensuring deterministic data loading, mutation, and navigation across platforms.
 
+use crate::action_error::ActionError;
+use crate::analytics::Analytics;
+use crate::authz::{Permission, Rbac};
+use crate::body::BodyFormat;
+use crate::crash;
+use crate::deprecation::{Deprecation, DeprecationUsage};
+use crate::guard::{self, FnGuard, GuardOutcome, RouteGuard};
+use crate::jobs::{self, Jobs};
+use crate::limiter::Limiter;
+use crate::matcher::{RouteMatch, RouteTrie};
+use crate::meta;
+use crate::mock::Mocks;
+use crate::param::ParamSpec;
+use crate::payload::JsonBytes;
+use crate::prerender::PageCache;
+use crate::preset::Preset;
+use crate::profile::Profiler;
+use crate::signal_graph;
+use crate::subscription::{Messages, Subscription, SubscriptionInfo, SubscriptionMetadata};
+use crate::trace::{self, Trace};
+use crate::versioning::{ApiVersions, Negotiated};
+use crate::wasi;
 use crate::AppConfig;
 use async_trait::async_trait;
+use bytes::Bytes;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::io::Write;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
 use leptos::prelude::*;
 
 /// Trait for route parameters. Must be serializable and deserializable.
-pub trait RouteParams: Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static {}
+pub trait RouteParams: Serialize + for<'de> Deserialize<'de> + Send + Sync + 'static {
+    /// The path and query parameters, for the `RouterSpec` and OpenAPI
+    /// export. `#[derive(RouteParams)]` lists every field; hand-written impls
+    /// may leave this empty.
+    fn params() -> Vec<ParamSpec> {
+        Vec::new()
+    }
+}
 
 /// Trait for data loading components. Loaders are responsible for fetching data
 /// for a specific route. They are read-only and idempotent.
@@ -48,6 +83,19 @@ pub trait RouteAction<P: RouteParams, C: AppConfig>: Send + Sync + 'static {
     fn description(&self) -> &'static str {
         ""
     }
+
+    /// Returns the wire format of this action's input body.
+    /// Override to accept raw binary (`BodyFormat::binary`) or protobuf payloads.
+    fn body_format(&self) -> BodyFormat {
+        BodyFormat::Json
+    }
+
+    /// Runs on the decoded input before [`act`](Self::act). The default passes it
+    /// through; return [`crate::sanitize_and_validate`] to sanitize and validate
+    /// `Schema` inputs on every request.
+    fn prepare_input(&self, input: Self::Input) -> Result<Self::Input, RouteError> {
+        Ok(input)
+    }
 }
 
 /// Trait for the visual representation of a route.
@@ -81,6 +129,17 @@ pub struct RouteContext<'a, C: AppConfig> {
     pub env: &'a dyn crate::env::EnvConfig,
 }
 
+/// Finds the signed-in user of a request, for plates that serve per-user data.
+pub type CurrentUser<C> = Arc<dyn Fn(&RouteContext<'_, C>) -> Option<String> + Send + Sync>;
+
+/// A registered subscription and what is checked when it is opened.
+struct GuardedSubscription<C: AppConfig> {
+    subscription: Box<dyn SubscriptionInfo<C>>,
+    guards: Vec<Arc<dyn RouteGuard<C>>>,
+    /// Annotations, with the requirements given with `.requires(..)`.
+    meta: HashMap<String, String>,
+}
+
 /// Standard error type for router operations.
 #[derive(Debug, thiserror::Error, Serialize, Deserialize)]
 pub enum RouteError {
@@ -88,12 +147,52 @@ pub enum RouteError {
     NotFound,
     #[error("Unauthorized access")]
     Unauthorized,
+    /// The caller is signed in but lacks the permission it holds.
+    #[error("Forbidden: requires {0}")]
+    Forbidden(String),
     #[error("Validation failed: {0}")]
     ValidationFailed(String),
+    /// The action rejected its input, with a code and the fields concerned.
+    /// Boxed to keep `Result<_, RouteError>` small; build it with `.into()`.
+    #[error("{0}")]
+    Action(Box<ActionError>),
+    #[error("Unsupported media type: {0}")]
+    UnsupportedMediaType(String),
+    /// The request asked for a representation or API version the server does not have.
+    #[error("Not acceptable: {0}")]
+    NotAcceptable(String),
     #[error("Internal router error: {0}")]
     InternalError(String),
     #[error("External error: {0}")]
     External(String),
+    /// A route guard sends the caller to this path instead.
+    #[error("Redirect to {0}")]
+    Redirect(String),
+    /// A route guard answered with this status instead.
+    #[error("Denied by a route guard (status {0})")]
+    Denied(u16),
+    /// The loader or action panicked; holds the correlation ID of its `PanicReport`.
+    #[error("Handler panicked (correlation id {0})")]
+    Panicked(String),
+}
+
+impl RouteError {
+    /// The HTTP status this error is answered with.
+    pub fn status(&self) -> u16 {
+        match self {
+            RouteError::Redirect(_) => 303,
+            RouteError::Denied(status) => *status,
+            RouteError::NotFound => 404,
+            RouteError::Unauthorized => 401,
+            RouteError::Forbidden(_) => 403,
+            RouteError::ValidationFailed(_) => 422,
+            RouteError::Action(error) => error.status,
+            RouteError::NotAcceptable(_) => 406,
+            RouteError::UnsupportedMediaType(_) => 415,
+            RouteError::InternalError(_) | RouteError::Panicked(_) => 500,
+            RouteError::External(_) => 502,
+        }
+    }
 }
 
 /// Standard response format for a Loader (for serialization).
@@ -108,9 +207,256 @@ pub struct ActionResponse {
     pub data: serde_json::Value,
 }
 
+/// The output of one layout loader in [`Router::load_nested`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LayoutData {
+    pub path: String,
+    pub data: serde_json::Value,
+}
+
+/// What [`Router::load_nested`] returns: the layouts' data, outermost first,
+/// and the route's own.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NestedData {
+    pub layouts: Vec<LayoutData>,
+    pub data: serde_json::Value,
+}
+
 /// The Application Router which maintains the static route graph.
 pub struct Router<C: AppConfig> {
     routes: HashMap<&'static str, Box<dyn RouteInfo<C>>>,
+    trie: RouteTrie,
+    meta: HashMap<&'static str, HashMap<String, String>>,
+    deprecated_hits: Mutex<HashMap<&'static str, u64>>,
+    mocks: Option<Mocks>,
+    profiler: Option<Profiler>,
+    analytics: Option<Arc<Analytics>>,
+    versions: Option<ApiVersions>,
+    page_cache: Option<Arc<PageCache>>,
+    rbac: Option<Rbac<C>>,
+    preset: Option<Preset>,
+    limiter: Option<Box<dyn Limiter>>,
+    /// Set with [`Router::set_limiter`]; takes the place of the preset's.
+    custom_limiter: Option<Box<dyn Limiter>>,
+    jobs: Option<Jobs>,
+    guards: HashMap<&'static str, Vec<Arc<dyn RouteGuard<C>>>>,
+    /// Added to every route registered, see [`Router::with_guards`].
+    scope_guards: Vec<Arc<dyn RouteGuard<C>>>,
+    /// Routes registered with [`Router::layout`].
+    layouts: HashSet<&'static str>,
+    /// Put in front of every path registered, see [`Router::scope`].
+    scope_prefix: String,
+    /// Groups defined with [`Router::group`].
+    groups: Vec<GroupSpec>,
+    /// Registered with [`Router::subscription`], with the guards of their scope.
+    subscriptions: HashMap<&'static str, GuardedSubscription<C>>,
+    subscription_trie: RouteTrie,
+}
+
+/// Returned by [`Router::register`] to annotate the route just registered.
+///
+/// ```rust,ignore
+/// router
+///     .register(CheckoutRoute)
+///     .with_meta(meta::OWNER, "payments-team")
+///     .with_meta(meta::STABILITY, "beta");
+/// ```
+pub struct RouteRegistration<'a, C: AppConfig> {
+    meta: &'a mut HashMap<String, String>,
+    guards: &'a mut Vec<Arc<dyn RouteGuard<C>>>,
+}
+
+impl<C: AppConfig> RouteRegistration<'_, C> {
+    /// Attaches a key-value annotation, such as an owner or stability level.
+    pub fn with_meta(self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.meta.insert(key.into(), value.into());
+        self
+    }
+
+    /// Marks the route deprecated. Hits are logged and counted, and
+    /// [`Router::response_headers`] starts returning `Deprecation`/`Sunset` headers.
+    pub fn deprecated(self, deprecation: Deprecation) -> Self {
+        deprecation.apply_to(self.meta);
+        self
+    }
+
+    /// Requires `permission` of every caller of the loader and the action.
+    /// Chained calls add up: the caller needs all of them. Checked by the
+    /// router's [`Rbac`] guard; without one, the route answers `Forbidden`.
+    pub fn requires(self, permission: impl Into<Permission>) -> Self {
+        permission.into().apply_to(self.meta);
+        self
+    }
+
+    /// Runs `guard` before the loader and the action. Chained guards run in
+    /// order, after those of the plate or [`Router::with_guards`] scope.
+    pub fn guard(self, guard: impl RouteGuard<C>) -> Self {
+        self.guards.push(Arc::new(guard));
+        self
+    }
+}
+
+/// Returned by [`Router::subscription`] to protect the subscription just
+/// registered.
+pub struct SubscriptionRegistration<'a, C: AppConfig> {
+    meta: &'a mut HashMap<String, String>,
+    guards: &'a mut Vec<Arc<dyn RouteGuard<C>>>,
+}
+
+impl<C: AppConfig> SubscriptionRegistration<'_, C> {
+    /// Requires `permission` of every subscriber, checked when a stream is
+    /// opened as for routes, see [`RouteRegistration::requires`].
+    pub fn requires(self, permission: impl Into<Permission>) -> Self {
+        permission.into().apply_to(self.meta);
+        self
+    }
+
+    /// Runs `guard` when a stream is opened, after those of the plate,
+    /// scope or group.
+    pub fn guard(self, guard: impl RouteGuard<C>) -> Self {
+        self.guards.push(Arc::new(guard));
+        self
+    }
+}
+
+/// Passed to the closure of [`Router::group`] to set up the routes of a
+/// group: what is added here applies to every route registered in the group,
+/// before or after it.
+///
+/// ```rust,ignore
+/// router.group("/admin", |admin| {
+///     admin.use_middleware(SignedIn).requires(Permission::role("admin")).with_meta(meta::TEAM, "ops");
+///     admin.register(UsersRoute);
+///     admin.register(AuditRoute);
+/// });
+/// ```
+pub struct RouteGroup<'a, C: AppConfig> {
+    router: &'a mut Router<C>,
+    spec: GroupSpec,
+    guards: Vec<Arc<dyn RouteGuard<C>>>,
+    routes: Vec<&'static str>,
+    subscriptions: Vec<&'static str>,
+}
+
+impl<C: AppConfig> RouteGroup<'_, C> {
+    /// Runs `guard` before every route of the group, after the guards of the
+    /// plate and of enclosing groups and before the routes' own.
+    pub fn use_middleware(&mut self, guard: impl RouteGuard<C>) -> &mut Self {
+        self.spec.middleware.push(guard.name());
+        self.guards.push(Arc::new(guard));
+        self
+    }
+
+    /// Annotates every route of the group. A route's own value for `key` wins.
+    pub fn with_meta(&mut self, key: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.spec.meta.insert(key.into(), value.into());
+        self
+    }
+
+    /// Requires `permission` of every caller of the group's routes, on top of
+    /// what the routes require themselves.
+    pub fn requires(&mut self, permission: impl Into<Permission>) -> &mut Self {
+        permission.into().apply_to(&mut self.spec.meta);
+        self
+    }
+
+    /// Hides the group's routes behind the feature flag `flag`: they answer
+    /// `404` unless `enabled` says the flag is on for the request. Runs as the
+    /// middleware `feature:<flag>`.
+    pub fn feature<F>(&mut self, flag: &str, enabled: F) -> &mut Self
+    where
+        F: Fn(&RouteContext<'_, C>) -> bool + Send + Sync + 'static,
+    {
+        self.spec.features.push(flag.to_string());
+        self.use_middleware(FnGuard::new(format!("feature:{}", flag), move |ctx: &RouteContext<'_, C>, _: &str| {
+            match enabled(ctx) {
+                true => GuardOutcome::Allow,
+                false => GuardOutcome::Deny(404),
+            }
+        }))
+    }
+
+    /// Registers a route under the group's prefix, see [`Router::register`].
+    pub fn register<R: Route<C>>(&mut self, route: R) -> RouteRegistration<'_, C> {
+        self.register_at(R::path(), route)
+    }
+
+    /// Registers a route at `path` under the group's prefix, see [`Router::register_at`].
+    pub fn register_at<R: Route<C>>(&mut self, path: &'static str, route: R) -> RouteRegistration<'_, C> {
+        let path = self.router.scoped(path);
+        self.routes.push(path);
+        self.router.layouts.remove(path);
+        self.router.mount(path, route)
+    }
+
+    /// Registers a layout under the group's prefix, see [`Router::layout`].
+    pub fn layout<R: Route<C>>(&mut self, route: R) -> RouteRegistration<'_, C> {
+        let path = self.router.scoped(R::path());
+        self.routes.push(path);
+        self.router.layouts.insert(path);
+        self.router.mount(path, route)
+    }
+
+    /// Registers a subscription under the group's prefix, see
+    /// [`Router::subscription`]. The group's middleware runs and its
+    /// requirements are checked when a stream is opened.
+    pub fn subscription<S: Subscription<C>>(&mut self, subscription: S) -> SubscriptionRegistration<'_, C> {
+        self.subscription_at(S::path(), subscription)
+    }
+
+    /// Registers a subscription at `path` under the group's prefix.
+    pub fn subscription_at<S: Subscription<C>>(&mut self, path: &'static str, subscription: S) -> SubscriptionRegistration<'_, C> {
+        let path = self.router.scoped(path);
+        self.subscriptions.push(path);
+        self.router.subscribe_at(path, subscription)
+    }
+
+    /// Defines a group inside this one. Its routes also get everything set
+    /// on this group.
+    pub fn group(&mut self, prefix: &str, define: impl FnOnce(&mut RouteGroup<'_, C>)) {
+        let parent = Some(self.spec.prefix.clone());
+        let (routes, subscriptions) = self.router.define_group(prefix, parent, define);
+        self.routes.extend(routes);
+        self.subscriptions.extend(subscriptions);
+    }
+
+    /// Applies the group's middleware and metadata to its routes and
+    /// subscriptions and records the group, returning both.
+    fn finish(self) -> (Vec<&'static str>, Vec<&'static str>) {
+        let RouteGroup { router, mut spec, guards, routes, subscriptions } = self;
+        let outer = router.scope_guards.len();
+        let permissions = Permission::from_meta(&spec.meta);
+        for path in &subscriptions {
+            if let Some(subscribed) = router.subscriptions.get_mut(path) {
+                let at = outer.min(subscribed.guards.len());
+                subscribed.guards.splice(at..at, guards.iter().cloned());
+                for permission in &permissions {
+                    permission.apply_to(&mut subscribed.meta);
+                }
+            }
+        }
+        for path in &routes {
+            if let Some(route_guards) = router.guards.get_mut(path) {
+                let at = outer.min(route_guards.len());
+                route_guards.splice(at..at, guards.iter().cloned());
+            }
+            let meta = router.meta.entry(path).or_default();
+            for (key, value) in spec.meta.iter().filter(|(key, _)| *key != meta::REQUIRES) {
+                meta.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+            for permission in &permissions {
+                permission.apply_to(meta);
+            }
+        }
+        spec.routes = routes.iter().map(|path| path.to_string()).collect();
+        spec.routes.sort();
+        spec.routes.dedup();
+        spec.subscriptions = subscriptions.iter().map(|path| path.to_string()).collect();
+        spec.subscriptions.sort();
+        spec.subscriptions.dedup();
+        router.groups.push(spec);
+        (routes, subscriptions)
+    }
 }
 
 /// Internal trait to erase the associated types of a Route for storage in the Router.
@@ -119,75 +465,767 @@ pub struct Router<C: AppConfig> {
 trait RouteInfo<C: AppConfig>: Send + Sync + 'static {
     fn path(&self) -> &'static str;
     async fn handle_load(&self, ctx: RouteContext<'_, C>, params: serde_json::Value) -> Result<serde_json::Value, RouteError>;
+    /// Writes the loader output to `sink`, or returns it untouched if it is `JsonBytes`.
+    async fn handle_load_raw(&self, ctx: RouteContext<'_, C>, params: serde_json::Value, sink: &mut (dyn Write + Send)) -> Result<Option<Bytes>, RouteError>;
     async fn handle_act(&self, ctx: RouteContext<'_, C>, params: serde_json::Value, input: serde_json::Value) -> Result<serde_json::Value, RouteError>;
+    async fn handle_act_body(&self, ctx: RouteContext<'_, C>, params: serde_json::Value, content_type: &str, body: &[u8]) -> Result<serde_json::Value, RouteError>;
     fn render(&self) -> Box<dyn Fn() -> AnyView + Send + Sync>;
     fn metadata(&self) -> RouteMetadata;
 }
 
+/// A route with the path it answers at: `R::path()`, or the one given to
+/// [`Router::register_at`].
+struct Mounted<R> {
+    path: &'static str,
+    route: R,
+}
+
 #[async_trait]
-impl<C: AppConfig, R: Route<C>> RouteInfo<C> for R {
+impl<C: AppConfig, R: Route<C>> RouteInfo<C> for Mounted<R> {
     fn path(&self) -> &'static str {
-        R::path()
+        self.path
     }
 
     async fn handle_load(&self, ctx: RouteContext<'_, C>, params: serde_json::Value) -> Result<serde_json::Value, RouteError> {
         let params: R::Params = serde_json::from_value(params)
             .map_err(|e| RouteError::ValidationFailed(e.to_string()))?;
         
-        let loader = self.loader();
+        let loader = self.route.loader();
         let output = loader.load(ctx, params).await?;
         serde_json::to_value(output).map_err(|e| RouteError::InternalError(e.to_string()))
     }
 
+    async fn handle_load_raw(&self, ctx: RouteContext<'_, C>, params: serde_json::Value, sink: &mut (dyn Write + Send)) -> Result<Option<Bytes>, RouteError> {
+        let params: R::Params = serde_json::from_value(params)
+            .map_err(|e| RouteError::ValidationFailed(e.to_string()))?;
+
+        let output = self.route.loader().load(ctx, params).await?;
+        if let Some(json) = (&output as &dyn Any).downcast_ref::<JsonBytes>() {
+            return Ok(Some(json.bytes().clone()));
+        }
+        serde_json::to_writer(sink, &output).map_err(|e| RouteError::InternalError(e.to_string()))?;
+        Ok(None)
+    }
+
     async fn handle_act(&self, ctx: RouteContext<'_, C>, params: serde_json::Value, input: serde_json::Value) -> Result<serde_json::Value, RouteError> {
         let params: R::Params = serde_json::from_value(params)
             .map_err(|e| RouteError::ValidationFailed(e.to_string()))?;
         let input: <R::Action as RouteAction<R::Params, C>>::Input = serde_json::from_value(input)
             .map_err(|e| RouteError::ValidationFailed(e.to_string()))?;
 
-        let action = self.action();
+        let action = self.route.action();
+        let input = action.prepare_input(input)?;
+        let output = action.act(ctx, params, input).await?;
+        serde_json::to_value(output).map_err(|e| RouteError::InternalError(e.to_string()))
+    }
+
+    async fn handle_act_body(&self, ctx: RouteContext<'_, C>, params: serde_json::Value, content_type: &str, body: &[u8]) -> Result<serde_json::Value, RouteError> {
+        let params: R::Params = serde_json::from_value(params)
+            .map_err(|e| RouteError::ValidationFailed(e.to_string()))?;
+
+        let action = self.route.action();
+        let input: <R::Action as RouteAction<R::Params, C>>::Input =
+            action.body_format().decode(content_type, body)?;
+        let input = action.prepare_input(input)?;
         let output = action.act(ctx, params, input).await?;
         serde_json::to_value(output).map_err(|e| RouteError::InternalError(e.to_string()))
     }
 
     fn render(&self) -> Box<dyn Fn() -> AnyView + Send + Sync> {
-        let view = self.view();
+        let view = self.route.view();
         Box::new(move || view.render().into_any())
     }
 
     fn metadata(&self) -> RouteMetadata {
         RouteMetadata {
-            path: R::path().to_string(),
-            loader_description: self.loader().description().to_string(),
-            action_description: self.action().description().to_string(),
+            path: self.path.to_string(),
+            loader_description: self.route.loader().description().to_string(),
+            action_description: self.route.action().description().to_string(),
+            action_body: self.route.action().body_format(),
+            params: R::Params::params(),
+            meta: HashMap::new(),
+            guards: Vec::new(),
+            parent: None,
+            children: Vec::new(),
         }
     }
 }
 
+impl<C: AppConfig> Default for Router<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<C: AppConfig> Router<C> {
     pub fn new() -> Self {
         Self {
             routes: HashMap::new(),
+            trie: RouteTrie::new(),
+            meta: HashMap::new(),
+            deprecated_hits: Mutex::new(HashMap::new()),
+            mocks: None,
+            profiler: None,
+            analytics: None,
+            versions: None,
+            page_cache: None,
+            rbac: None,
+            preset: None,
+            limiter: None,
+            custom_limiter: None,
+            jobs: None,
+            guards: HashMap::new(),
+            scope_guards: Vec::new(),
+            layouts: HashSet::new(),
+            scope_prefix: String::new(),
+            groups: Vec::new(),
+            subscriptions: HashMap::new(),
+            subscription_trie: RouteTrie::new(),
         }
     }
 
-    pub fn register<R: Route<C>>(&mut self, route: R) {
-        self.routes.insert(R::path(), Box::new(route));
+    /// Measures every loader and action call with `profiler`.
+    pub fn set_profiler(&mut self, profiler: Profiler) {
+        self.profiler = Some(profiler);
+    }
+
+    /// The session profile, if profiling is on.
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    /// Counts every loader and action call in `analytics`.
+    pub fn set_analytics(&mut self, analytics: Arc<Analytics>) {
+        self.analytics = Some(analytics);
+    }
+
+    /// The route usage analytics, if they are on.
+    pub fn analytics(&self) -> Option<&Arc<Analytics>> {
+        self.analytics.as_ref()
+    }
+
+    /// Answers `GET` requests for the routes `cache` lists from their cached
+    /// pages, when the app is embedded or runs as a WASI component.
+    pub fn set_page_cache(&mut self, cache: Arc<PageCache>) {
+        self.page_cache = Some(cache);
+    }
+
+    /// The page cache, if the router has one.
+    pub fn page_cache(&self) -> Option<&Arc<PageCache>> {
+        self.page_cache.as_ref()
+    }
+
+    /// Answers requests with the CORS, security header and body size
+    /// settings of `preset`, and rate limits them when its limiter is `memory`.
+    pub fn set_preset(&mut self, preset: Preset) {
+        self.limiter = preset.limiter();
+        self.preset = Some(preset);
+    }
+
+    /// The serving preset, if the router has one.
+    pub fn preset(&self) -> Option<&Preset> {
+        self.preset.as_ref()
+    }
+
+    /// Rate limits requests with `limiter`, e.g. one shared between
+    /// instances, instead of the preset's, with or without a preset.
+    pub fn set_limiter(&mut self, limiter: impl Limiter) {
+        self.custom_limiter = Some(Box::new(limiter));
+    }
+
+    /// Whether the rate limiter lets one more request through.
+    pub(crate) fn admit(&self) -> bool {
+        self.custom_limiter.as_ref().or(self.limiter.as_ref()).is_none_or(|limiter| limiter.check())
+    }
+
+    /// Serves the routes under `versions.prefix()` as versions of one API.
+    pub fn set_versions(&mut self, versions: ApiVersions) {
+        self.versions = Some(versions);
+    }
+
+    /// The API versions, if the router has them.
+    pub fn versions(&self) -> Option<&ApiVersions> {
+        self.versions.as_ref()
+    }
+
+    /// Picks the API version for a request and the path to pass to `load` or
+    /// `act`. Without versions, or outside their prefix, the path is returned as is.
+    pub fn negotiate<'h>(&self, path: &str, headers: impl IntoIterator<Item = (&'h str, &'h str)>) -> Result<Negotiated, RouteError> {
+        match &self.versions {
+            Some(versions) => versions.negotiate(path, headers),
+            None => Ok(Negotiated { version: None, path: path.to_string(), headers: Vec::new() }),
+        }
+    }
+
+    /// Checks the requirements routes declare with `.requires(..)` against the
+    /// caller's roles.
+    pub fn set_rbac(&mut self, rbac: Rbac<C>) {
+        self.rbac = Some(rbac);
+    }
+
+    /// The access control guard, if the router has one.
+    pub fn rbac(&self) -> Option<&Rbac<C>> {
+        self.rbac.as_ref()
+    }
+
+    /// Lets loaders and actions [`defer`](RouteContext::defer) actions to `jobs`.
+    pub fn set_jobs(&mut self, jobs: Jobs) {
+        self.jobs = Some(jobs);
+    }
+
+    /// The job queue, if the router has one.
+    pub fn jobs(&self) -> Option<&Jobs> {
+        self.jobs.as_ref()
+    }
+
+    /// Answers the mocked loaders and actions from `mocks` instead of the
+    /// registered handlers. Mocked paths do not need a registered route.
+    pub fn set_mocks(&mut self, mocks: Mocks) {
+        self.mocks = Some(mocks);
+    }
+
+    /// The installed mocks, if mocking is on.
+    pub fn mocks(&self) -> Option<&Mocks> {
+        self.mocks.as_ref()
+    }
+
+    /// Registers a route. Re-registering a path replaces the route and clears
+    /// its metadata and guards.
+    pub fn register<R: Route<C>>(&mut self, route: R) -> RouteRegistration<'_, C> {
+        self.register_at(R::path(), route)
+    }
+
+    /// Registers a route at `path` instead of `R::path()`, for plates that
+    /// mount their routes under a configurable prefix.
+    pub fn register_at<R: Route<C>>(&mut self, path: &'static str, route: R) -> RouteRegistration<'_, C> {
+        let path = self.scoped(path);
+        self.layouts.remove(path);
+        self.mount(path, route)
+    }
+
+    /// Registers `route` at exactly `path`.
+    fn mount<R: Route<C>>(&mut self, path: &'static str, route: R) -> RouteRegistration<'_, C> {
+        self.routes.insert(path, Box::new(Mounted { path, route }));
+        self.trie.insert(path);
+        let meta = self.meta.entry(path).or_default();
+        meta.clear();
+        let guards = self.guards.entry(path).or_default();
+        guards.clone_from(&self.scope_guards);
+        RouteRegistration { meta, guards }
+    }
+
+    /// Registers a subscription, served as Server-Sent Events; see
+    /// [`crate::subscription`]. The guards of the current plate or
+    /// [`Router::with_guards`] scope run when a stream is opened, and the
+    /// requirements given with [`SubscriptionRegistration::requires`] are
+    /// checked like those of routes.
+    pub fn subscription<S: Subscription<C>>(&mut self, subscription: S) -> SubscriptionRegistration<'_, C> {
+        self.subscription_at(S::path(), subscription)
+    }
+
+    /// Registers a subscription at `path` instead of `S::path()`.
+    pub fn subscription_at<S: Subscription<C>>(&mut self, path: &'static str, subscription: S) -> SubscriptionRegistration<'_, C> {
+        let path = self.scoped(path);
+        self.subscribe_at(path, subscription)
+    }
+
+    /// Registers `subscription` at exactly `path`.
+    fn subscribe_at<S: Subscription<C>>(&mut self, path: &'static str, subscription: S) -> SubscriptionRegistration<'_, C> {
+        let subscribed = GuardedSubscription { subscription: Box::new(subscription), guards: self.scope_guards.clone(), meta: HashMap::new() };
+        self.subscription_trie.insert(path);
+        let GuardedSubscription { meta, guards, .. } = self.subscriptions.entry(path).insert_entry(subscribed).into_mut();
+        SubscriptionRegistration { meta, guards }
+    }
+
+    /// Runs `register` with `guards` added to every route it registers, ahead
+    /// of the routes' own. `AppSpec::boot` registers each plate's routes this
+    /// way with its [`Plate::guards`](crate::Plate::guards).
+    pub fn with_guards(&mut self, guards: Vec<Arc<dyn RouteGuard<C>>>, register: impl FnOnce(&mut Self)) {
+        let outer = self.scope_guards.clone();
+        self.scope_guards.extend(guards);
+        register(self);
+        self.scope_guards = outer;
+    }
+
+    /// Runs `register` with `prefix` put in front of every path it registers.
+    /// A route at `/` lands on the prefix itself. Scopes nest, and their
+    /// routes become children of a [`Router::layout`] registered in them.
+    ///
+    /// ```rust,ignore
+    /// router.scope("/admin", |admin| {
+    ///     admin.layout(AdminShell); // "/", so at /admin
+    ///     admin.register(UsersRoute); // "/users", so at /admin/users
+    /// });
+    /// ```
+    pub fn scope(&mut self, prefix: &str, register: impl FnOnce(&mut Self)) {
+        let prefix = prefix.trim_end_matches('/');
+        let outer = self.scope_prefix.clone();
+        if !prefix.is_empty() && !prefix.starts_with('/') {
+            self.scope_prefix.push('/');
+        }
+        self.scope_prefix.push_str(prefix);
+        register(self);
+        self.scope_prefix = outer;
+    }
+
+    /// Registers a route that lays out the routes beneath its path: each of
+    /// them gets the nearest layout above it as its parent, listed in the
+    /// `RouterSpec`, and [`Router::load_nested`] runs the layouts' loaders
+    /// along with the route's.
+    pub fn layout<R: Route<C>>(&mut self, route: R) -> RouteRegistration<'_, C> {
+        let path = self.scoped(R::path());
+        self.layouts.insert(path);
+        self.mount(path, route)
+    }
+
+    /// Registers related routes under `prefix`, like [`Router::scope`], with
+    /// middleware, metadata and feature gates they share, set on the
+    /// [`RouteGroup`]. The group is listed under `groups` in the `RouterSpec`.
+    pub fn group(&mut self, prefix: &str, define: impl FnOnce(&mut RouteGroup<'_, C>)) {
+        self.define_group(prefix, None, define);
+    }
+
+    fn define_group(
+        &mut self,
+        prefix: &str,
+        parent: Option<String>,
+        define: impl FnOnce(&mut RouteGroup<'_, C>),
+    ) -> (Vec<&'static str>, Vec<&'static str>) {
+        let mut registered = (Vec::new(), Vec::new());
+        self.scope(prefix, |router| {
+            let prefix = if router.scope_prefix.is_empty() { "/".to_string() } else { router.scope_prefix.clone() };
+            let spec = GroupSpec { prefix, parent, ..GroupSpec::default() };
+            let mut group = RouteGroup { router, spec, guards: Vec::new(), routes: Vec::new(), subscriptions: Vec::new() };
+            define(&mut group);
+            registered = group.finish();
+        });
+        registered
+    }
+
+    /// `path` under the current [`Router::scope`].
+    fn scoped(&self, path: &'static str) -> &'static str {
+        if self.scope_prefix.is_empty() {
+            return path;
+        }
+        intern(if path == "/" { self.scope_prefix.clone() } else { format!("{}{}", self.scope_prefix, path) })
+    }
+
+    /// The layout the route at `path` is nested in: the nearest one whose
+    /// path is a parent of it.
+    pub fn parent(&self, path: &str) -> Option<&'static str> {
+        self.layouts
+            .iter()
+            .copied()
+            .filter(|layout| {
+                *layout != path && (*layout == "/" || path.strip_prefix(*layout).is_some_and(|rest| rest.starts_with('/')))
+            })
+            .max_by_key(|layout| layout.len())
+    }
+
+    /// The layouts the route at `path` is nested in, outermost first.
+    pub fn layouts(&self, path: &str) -> Vec<&'static str> {
+        let mut layouts = Vec::new();
+        let mut current = self.parent(path);
+        while let Some(layout) = current {
+            layouts.push(layout);
+            current = self.parent(layout);
+        }
+        layouts.reverse();
+        layouts
+    }
+
+    /// The names of the guards of the route at `path`, in the order they run.
+    pub fn guards(&self, path: &str) -> Vec<String> {
+        self.guards.get(path).map(|guards| guards.iter().map(|g| g.name()).collect()).unwrap_or_default()
+    }
+
+    /// Resolves a request path such as `/users/42` to its registered pattern
+    /// and captured params.
+    pub fn resolve(&self, path: &str) -> Option<RouteMatch> {
+        self.trie.at(path)
+    }
+
+    /// Resolves a request path to the pattern and params of a subscription.
+    pub fn resolve_subscription(&self, path: &str) -> Option<RouteMatch> {
+        self.subscription_trie.at(path)
+    }
+
+    /// Finds the route for `path`, which is either a registered pattern or a
+    /// request path. Params captured from a request path are merged into `params`.
+    fn route_for(&self, path: &str, params: serde_json::Value) -> Result<(&dyn RouteInfo<C>, serde_json::Value), RouteError> {
+        if let Some(route) = self.routes.get(path) {
+            return Ok((route.as_ref(), params));
+        }
+        let matched = self.trie.at(path).ok_or(RouteError::NotFound)?;
+        let route = self.routes.get(matched.pattern).ok_or(RouteError::NotFound)?;
+        Ok((route.as_ref(), matched.merge_into(params)))
+    }
+
+    /// Returns the annotations attached to the route at `path`.
+    pub fn meta(&self, path: &str) -> Option<&HashMap<String, String>> {
+        self.meta.get(path)
+    }
+
+    /// Returns the deprecation notice of the route at `path`, if any: its own,
+    /// or the one of the API version it belongs to.
+    pub fn deprecation(&self, path: &str) -> Option<Deprecation> {
+        self.meta.get(path).and_then(Deprecation::from_meta).or_else(|| self.version_deprecation(path))
+    }
+
+    /// The deprecation of the version `path` belongs to. Without a replacement,
+    /// it points to the same route in the next version, or to that version's
+    /// prefix when the route was dropped.
+    fn version_deprecation(&self, path: &str) -> Option<Deprecation> {
+        let versions = self.versions.as_ref()?;
+        let (version, route) = versions.split(path)?;
+        let mut deprecation = versions.deprecation(version)?.clone();
+        if deprecation.replacement.is_none()
+            && let Some(next) = versions.successor(version)
+        {
+            let successor = versions.join(next, route);
+            deprecation.replacement = Some(if self.routes.contains_key(successor.as_str()) { successor } else { versions.join(next, "") });
+        }
+        Some(deprecation)
+    }
+
+    /// Headers the server should add to responses for `path`.
+    pub fn response_headers(&self, path: &str) -> Vec<(String, String)> {
+        self.deprecation(path).map(|d| d.headers()).unwrap_or_default()
+    }
+
+    /// Deprecated routes with the number of times each was hit.
+    pub fn deprecation_report(&self) -> Vec<DeprecationUsage> {
+        let hits = self.deprecated_hits.lock().unwrap_or_else(|e| e.into_inner());
+        let mut report: Vec<_> = self
+            .meta
+            .keys()
+            .filter_map(|path| {
+                self.deprecation(path).map(|deprecation| DeprecationUsage {
+                    path: path.to_string(),
+                    deprecation,
+                    hits: hits.get(path).copied().unwrap_or(0),
+                })
+            })
+            .collect();
+        report.sort_by(|a, b| a.path.cmp(&b.path));
+        report
+    }
+
+    /// Runs a loader or action in its [`Trace`], profiled, counted and
+    /// attributed in the signal graph when those are on. A panic in the
+    /// handler becomes [`RouteError::Panicked`] instead of unwinding into the
+    /// server.
+    async fn instrument<T, F>(&self, path: &str, operation: &'static str, handler: F) -> Result<T, RouteError>
+    where
+        F: Future<Output = Result<T, RouteError>>,
+    {
+        let started = Instant::now();
+        let scope = format!("route {} ({})", path, operation);
+        let trace = Trace::start(path, operation, wasi::current_request().as_deref());
+        let handler = trace::scoped(trace.clone(), jobs::scoped(self.jobs.as_ref(), handler));
+        let handler = crash::catch(&scope, signal_graph::scoped(&scope, handler));
+        let outcome = match &self.profiler {
+            Some(profiler) => profiler.measure(path, operation, handler).await,
+            None => handler.await,
+        };
+        let result = outcome.unwrap_or_else(|report| Err(RouteError::Panicked(report.id)));
+        let status = result.as_ref().map_or_else(RouteError::status, |_| 200);
+        trace.record_status(status);
+        if let Some(analytics) = &self.analytics {
+            analytics.record(path, operation, status, started.elapsed());
+        }
+        result
+    }
+
+    /// Checks the caller against the requirements of the route at `path`.
+    /// Routes without requirements are open to everyone.
+    fn authorize(&self, path: &'static str, ctx: &RouteContext<'_, C>) -> Result<(), RouteError> {
+        self.permit(path, self.meta.get(path), ctx)
+    }
+
+    /// Checks the caller against the requirements in `meta`, those of the
+    /// route or subscription at `path`.
+    fn permit(&self, path: &'static str, meta: Option<&HashMap<String, String>>, ctx: &RouteContext<'_, C>) -> Result<(), RouteError> {
+        let required = meta.map(Permission::from_meta).unwrap_or_default();
+        if required.is_empty() {
+            return Ok(());
+        }
+        let Some(rbac) = &self.rbac else {
+            tracing::error!(route = path, "route declares requirements but the router has no Rbac guard");
+            return Err(RouteError::Forbidden(required[0].to_string()));
+        };
+        rbac.check(ctx, &required).map(|_| ()).inspect_err(|err| {
+            tracing::warn!(route = path, error = %err, "access denied");
+        })
+    }
+
+    /// Whether the route at `pattern` has guards or permission requirements,
+    /// so that what it answers may depend on who asks.
+    pub(crate) fn is_restricted(&self, pattern: &str) -> bool {
+        self.guards.get(pattern).is_some_and(|guards| !guards.is_empty())
+            || self.meta.get(pattern).is_some_and(|meta| !Permission::from_meta(meta).is_empty())
+    }
+
+    /// Runs the guards of the route at `path`.
+    async fn guard(&self, path: &'static str, ctx: &RouteContext<'_, C>) -> Result<(), RouteError> {
+        match self.guards.get(path) {
+            Some(guards) => guard::check(guards, ctx, path).await,
+            None => Ok(()),
+        }
+    }
+
+    fn record_hit(&self, path: &'static str) {
+        let Some(deprecation) = self.deprecation(path) else {
+            return;
+        };
+        let mut hits = self.deprecated_hits.lock().unwrap_or_else(|e| e.into_inner());
+        let count = hits.entry(path).or_insert(0);
+        *count += 1;
+        tracing::warn!(
+            route = path,
+            hits = *count,
+            sunset = deprecation.sunset.as_deref().unwrap_or("unset"),
+            replacement = deprecation.replacement.as_deref().unwrap_or("none"),
+            "deprecated route called"
+        );
+    }
+
+    /// Runs the loader for `path` (a pattern or a request path) with JSON-encoded params.
+    pub async fn load(&self, path: &str, ctx: RouteContext<'_, C>, params: serde_json::Value) -> Result<serde_json::Value, RouteError> {
+        if let Some(mocks) = &self.mocks
+            && let Some(result) = mocks.load(path, &params).await
+        {
+            return result;
+        }
+        let (route, params) = self.route_for(path, params)?;
+        self.record_hit(route.path());
+        let allowed = self.authorize(route.path(), &ctx);
+        self.instrument(route.path(), "load", async move {
+            self.guard(route.path(), &ctx).await?;
+            allowed?;
+            route.handle_load(ctx, params).await
+        })
+        .await
+    }
+
+    /// Runs the loader for `path` together with those of the layouts it is
+    /// nested in, concurrently, so a page and its layouts load in one round
+    /// trip. Each loader gets the route's params and goes through
+    /// [`Router::load`], guards and permissions included; the first error
+    /// fails the whole load.
+    pub async fn load_nested(&self, path: &str, ctx: RouteContext<'_, C>, params: serde_json::Value) -> Result<NestedData, RouteError> {
+        let (pattern, params) = match self.routes.get_key_value(path) {
+            Some((pattern, _)) => (*pattern, params),
+            None => {
+                let matched = self.trie.at(path).ok_or(RouteError::NotFound)?;
+                (matched.pattern, matched.merge_into(params))
+            }
+        };
+        let layouts = self.layouts(pattern);
+        let loads = layouts.iter().chain([&pattern]).map(|path| {
+            let ctx = RouteContext { config: ctx.config, env: ctx.env };
+            self.load(path, ctx, params.clone())
+        });
+        let mut data = futures::future::try_join_all(loads).await?;
+        let route = data.pop().unwrap_or_default();
+        let layouts = layouts
+            .into_iter()
+            .zip(data)
+            .map(|(path, data)| LayoutData { path: path.to_string(), data })
+            .collect();
+        Ok(NestedData { layouts, data: route })
+    }
+
+    /// Runs the loader for `path` and serializes its output straight into `writer`,
+    /// without building a `serde_json::Value` first. `JsonBytes` outputs are copied
+    /// through as-is. Unbuffered writers such as sockets should be wrapped in a `BufWriter`.
+    pub async fn load_to<W: Write + Send>(&self, path: &str, ctx: RouteContext<'_, C>, params: serde_json::Value, writer: &mut W) -> Result<(), RouteError> {
+        match self.load_raw(path, ctx, params, writer).await? {
+            Some(bytes) => writer.write_all(&bytes).map_err(|e| RouteError::InternalError(e.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Runs the loader for `path` and returns its JSON-encoded output. A `JsonBytes`
+    /// output is returned without copying.
+    pub async fn load_bytes(&self, path: &str, ctx: RouteContext<'_, C>, params: serde_json::Value) -> Result<Bytes, RouteError> {
+        let mut buffer = Vec::new();
+        Ok(self
+            .load_raw(path, ctx, params, &mut buffer)
+            .await?
+            .unwrap_or_else(|| Bytes::from(buffer)))
+    }
+
+    async fn load_raw(&self, path: &str, ctx: RouteContext<'_, C>, params: serde_json::Value, sink: &mut (dyn Write + Send)) -> Result<Option<Bytes>, RouteError> {
+        if let Some(mocks) = &self.mocks
+            && let Some(result) = mocks.load(path, &params).await
+        {
+            serde_json::to_writer(sink, &result?).map_err(|e| RouteError::InternalError(e.to_string()))?;
+            return Ok(None);
+        }
+        let (route, params) = self.route_for(path, params)?;
+        self.record_hit(route.path());
+        let allowed = self.authorize(route.path(), &ctx);
+        self.instrument(route.path(), "load", async move {
+            self.guard(route.path(), &ctx).await?;
+            allowed?;
+            route.handle_load_raw(ctx, params, sink).await
+        })
+        .await
+    }
+
+    /// Runs the action for `path` (a pattern or a request path) with a JSON input.
+    pub async fn act(&self, path: &str, ctx: RouteContext<'_, C>, params: serde_json::Value, input: serde_json::Value) -> Result<serde_json::Value, RouteError> {
+        if let Some(mocks) = &self.mocks
+            && let Some(result) = mocks.act(path, &params, &input).await
+        {
+            return result;
+        }
+        let (route, params) = self.route_for(path, params)?;
+        self.record_hit(route.path());
+        let allowed = self.authorize(route.path(), &ctx);
+        self.instrument(route.path(), "act", async move {
+            self.guard(route.path(), &ctx).await?;
+            allowed?;
+            route.handle_act(ctx, params, input).await
+        })
+        .await
+    }
+
+    /// Runs the action for `path` (a pattern or a request path) with a raw request body.
+    /// The body is decoded according to the action's `BodyFormat`, rejecting
+    /// content types it does not accept.
+    pub async fn act_body(&self, path: &str, ctx: RouteContext<'_, C>, params: serde_json::Value, content_type: &str, body: &[u8]) -> Result<serde_json::Value, RouteError> {
+        if let Some(mocks) = &self.mocks {
+            // Mocks see the body as JSON when it parses, `null` otherwise.
+            let input = serde_json::from_slice(body).unwrap_or(serde_json::Value::Null);
+            if let Some(result) = mocks.act(path, &params, &input).await {
+                return result;
+            }
+        }
+        let (route, params) = self.route_for(path, params)?;
+        self.record_hit(route.path());
+        let allowed = self.authorize(route.path(), &ctx);
+        self.instrument(route.path(), "act", async move {
+            self.guard(route.path(), &ctx).await?;
+            allowed?;
+            route.handle_act_body(ctx, params, content_type, body).await
+        })
+        .await
+    }
+
+    /// Opens the subscription at `path` (a pattern or a request path) with
+    /// JSON-encoded params, after its guards and requirements, and returns
+    /// its messages as JSON.
+    pub async fn subscribe(&self, path: &str, ctx: RouteContext<'_, C>, params: serde_json::Value) -> Result<Messages<serde_json::Value>, RouteError> {
+        let (pattern, params) = match self.subscriptions.get_key_value(path) {
+            Some((pattern, _)) => (*pattern, params),
+            None => {
+                let matched = self.subscription_trie.at(path).ok_or(RouteError::NotFound)?;
+                (matched.pattern, matched.merge_into(params))
+            }
+        };
+        let subscribed = &self.subscriptions[pattern];
+        let allowed = self.permit(pattern, Some(&subscribed.meta), &ctx);
+        self.instrument(pattern, "subscribe", async move {
+            guard::check(&subscribed.guards, &ctx, pattern).await?;
+            allowed?;
+            subscribed.subscription.open(ctx, params).await
+        })
+        .await
     }
 
     pub fn spec(&self) -> RouterSpec {
         let mut routes = HashMap::new();
         for (path, route) in &self.routes {
-            routes.insert(path.to_string(), route.metadata());
+            let mut metadata = route.metadata();
+            metadata.meta = self.meta.get(path).cloned().unwrap_or_default();
+            metadata.guards = self.guards(path);
+            metadata.parent = self.parent(path).map(str::to_string);
+            if self.layouts.contains(path) {
+                metadata.children = self.routes.keys().filter(|child| self.parent(child) == Some(*path)).map(|child| child.to_string()).collect();
+                metadata.children.sort();
+            }
+            if let Some(versions) = &self.versions
+                && let Some((version, route)) = versions.split(path)
+            {
+                metadata.meta.insert(meta::API_VERSION.to_string(), version.to_string());
+                metadata.meta.insert(meta::API_ROUTE.to_string(), format!("{}{}", versions.prefix(), route));
+                if Deprecation::from_meta(&metadata.meta).is_none()
+                    && let Some(deprecation) = self.version_deprecation(path)
+                {
+                    deprecation.apply_to(&mut metadata.meta);
+                }
+            }
+            routes.insert(path.to_string(), metadata);
         }
-        RouterSpec { routes }
+        let mut groups = self.groups.clone();
+        groups.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+        let mut subscriptions: Vec<_> = self
+            .subscriptions
+            .iter()
+            .map(|(path, subscribed)| SubscriptionMetadata {
+                guards: subscribed.guards.iter().map(|g| g.name()).collect(),
+                meta: subscribed.meta.clone(),
+                ..subscribed.subscription.metadata(path)
+            })
+            .collect();
+        subscriptions.sort_by(|a, b| a.path.cmp(&b.path));
+        RouterSpec { routes, groups, subscriptions }
     }
 }
 
+/// `path` as the `&'static str` the router's tables are keyed by. Paths built
+/// at registration, under a scope or a plate's prefix, are allocated once per
+/// distinct path, however many routers register them.
+pub(crate) fn intern(path: String) -> &'static str {
+    static PATHS: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+    let mut paths = PATHS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(&interned) = paths.get(path.as_str()) {
+        return interned;
+    }
+    let interned: &'static str = Box::leak(path.into_boxed_str());
+    paths.insert(interned);
+    interned
+}
+
 /// A machine-readable specification of the router.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+///
+/// Fields are added as the router grows, so build one outside this crate
+/// with `..Default::default()` for the fields you don't set.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct RouterSpec {
     pub routes: HashMap<String, RouteMetadata>,
+    /// The route groups, sorted by prefix.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<GroupSpec>,
+    /// The subscriptions, sorted by path.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subscriptions: Vec<SubscriptionMetadata>,
+}
+
+/// A group of routes defined with [`Router::group`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct GroupSpec {
+    pub prefix: String,
+    /// The prefix of the group this one is nested in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// The names of the group's middleware, in the order they run.
+    #[serde(default)]
+    pub middleware: Vec<String>,
+    /// Annotations given to every route of the group.
+    #[serde(default)]
+    pub meta: HashMap<String, String>,
+    /// The feature flags the group is gated behind.
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// The routes of the group, nested groups' included, sorted.
+    #[serde(default)]
+    pub routes: Vec<String>,
+    /// The subscriptions of the group, nested groups' included, sorted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subscriptions: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -195,4 +1233,22 @@ pub struct RouteMetadata {
     pub path: String,
     pub loader_description: String,
     pub action_description: String,
+    /// The body format accepted by the action, including its content types.
+    #[serde(default)]
+    pub action_body: BodyFormat,
+    /// The typed path and query parameters, from `RouteParams::params`.
+    #[serde(default)]
+    pub params: Vec<ParamSpec>,
+    /// Annotations attached at registration (owner, team, stability, ...).
+    #[serde(default)]
+    pub meta: HashMap<String, String>,
+    /// The names of the route's guards, in the order they run.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub guards: Vec<String>,
+    /// The layout the route is nested in, see [`Router::layout`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// For a layout, the routes nested directly in it, sorted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<String>,
 }
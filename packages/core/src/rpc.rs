@@ -0,0 +1,415 @@
+//! montrs-core/src/rpc.rs: Calling actions from the client as typed async fns.
+//!
+//! On the server every registered action already answers `POST` at its path,
+//! whether the app runs as a WASI component ([`crate::wasi`]) or embedded in
+//! another server ([`Embedded`]). [`RpcClient`] is the other end: given a
+//! route type, it fills the route's params into its path, sends the action's
+//! input as JSON, and decodes the output the action declared. Failures come
+//! back as the [`RouteError`] the server answered with, typed action errors
+//! included, so a form handles them the same way on both targets.
+//!
+//! [`RpcTransport`] carries the requests. With the `rpc` feature,
+//! [`HttpTransport`] sends them with `reqwest`, which uses `fetch` in the
+//! browser; an [`Embedded`] app answers them in-process, e.g. during SSR and
+//! in tests.
+//!
+//! [Subscriptions](crate::subscription) work the same way:
+//! [`RpcClient::subscribe`] opens one and decodes its events into the
+//! messages it declared. In the browser, `HttpTransport` opens them with an
+//! `EventSource`, which needs the `event-source` feature.
+
+use crate::action_error::ActionError;
+use crate::embed::Embedded;
+use crate::router::{Route, RouteAction, RouteError};
+use crate::subscription::{self, EVENT_STREAM, LiveSignal, Subscription};
+use crate::wasi::{WasiRequest, WasiResponse};
+use crate::AppConfig;
+use async_trait::async_trait;
+use futures::stream::{LocalBoxStream, StreamExt};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// The input of route `R`'s action.
+pub type ActionInput<R, C> = <<R as Route<C>>::Action as RouteAction<<R as Route<C>>::Params, C>>::Input;
+/// The output of route `R`'s action.
+pub type ActionOutput<R, C> = <<R as Route<C>>::Action as RouteAction<<R as Route<C>>::Params, C>>::Output;
+
+/// The `data` of each event of an opened subscription. An `Err` says the
+/// connection was lost, and ends the stream.
+pub type EventStream = LocalBoxStream<'static, Result<String, String>>;
+
+/// What [`RpcTransport::open`] got back.
+pub enum Opened {
+    /// The subscription's events.
+    Events(EventStream),
+    /// The server answered with this instead, e.g. a guard's `401`.
+    Refused(WasiResponse),
+}
+
+/// Sends the requests of an [`RpcClient`]. Futures need not be `Send`, as
+/// the browser's `fetch` is not.
+#[async_trait(?Send)]
+pub trait RpcTransport: Send + Sync + 'static {
+    /// Sends `request` and returns the response. `Err` means no response,
+    /// e.g. a connection error.
+    async fn send(&self, request: WasiRequest) -> Result<WasiResponse, String>;
+
+    /// Sends `request`, which accepts `text/event-stream`, and returns the
+    /// events of the response as they arrive. The default cannot stream.
+    async fn open(&self, _request: WasiRequest) -> Result<Opened, String> {
+        Err("this transport does not support subscriptions".to_string())
+    }
+}
+
+#[async_trait(?Send)]
+impl<C: AppConfig> RpcTransport for Embedded<C> {
+    async fn send(&self, request: WasiRequest) -> Result<WasiResponse, String> {
+        Ok(self.handle(request).await)
+    }
+
+    async fn open(&self, request: WasiRequest) -> Result<Opened, String> {
+        if !self.is_subscription(&request) {
+            return Ok(Opened::Refused(WasiResponse::error(&RouteError::NotFound)));
+        }
+        let response = self.stream(request).await;
+        if response.status == 200 {
+            return Ok(Opened::Events(subscription::events(response.body)));
+        }
+        let body: Vec<Vec<u8>> = response.body.filter_map(|chunk| async { chunk.ok() }).collect().await;
+        Ok(Opened::Refused(WasiResponse { status: response.status, headers: response.headers, body: body.concat() }))
+    }
+}
+
+/// Sends requests over HTTP to the server at `base_url`.
+#[cfg(feature = "rpc")]
+pub struct HttpTransport {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "rpc")]
+impl HttpTransport {
+    /// `base_url` is where the app is served, e.g. `https://example.com/app`;
+    /// in the browser, `window.location.origin`.
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_client(base_url, reqwest::Client::new())
+    }
+
+    pub fn with_client(base_url: impl Into<String>, client: reqwest::Client) -> Self {
+        Self { base_url: base_url.into().trim_end_matches('/').to_string(), client }
+    }
+}
+
+#[cfg(feature = "rpc")]
+#[async_trait(?Send)]
+impl RpcTransport for HttpTransport {
+    async fn send(&self, request: WasiRequest) -> Result<WasiResponse, String> {
+        let url = reqwest::Url::parse(&format!("{}{}", self.base_url, request.path_with_query)).map_err(|e| e.to_string())?;
+        let method = reqwest::Method::from_bytes(request.method.as_bytes()).map_err(|e| e.to_string())?;
+        let mut builder = self.client.request(method, url.clone()).body(request.body);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        let response = builder.send().await.map_err(|e| e.to_string())?;
+        // A guard's redirect was followed; report it like the `303` it was.
+        if *response.url() != url {
+            let location = response.url().path().to_string();
+            return Ok(WasiResponse { status: 303, headers: vec![("location".to_string(), location)], body: Vec::new() });
+        }
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let body = response.bytes().await.map_err(|e| e.to_string())?.to_vec();
+        Ok(WasiResponse { status, headers, body })
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn open(&self, request: WasiRequest) -> Result<Opened, String> {
+        let url = format!("{}{}", self.base_url, request.path_with_query);
+        let mut builder = self.client.get(url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        let response = builder.send().await.map_err(|e| e.to_string())?;
+        let status = response.status().as_u16();
+        if status != 200 {
+            let headers = response.headers().iter().filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string()))).collect();
+            let body = response.bytes().await.map_err(|e| e.to_string())?.to_vec();
+            return Ok(Opened::Refused(WasiResponse { status, headers, body }));
+        }
+        let chunks = futures::stream::unfold(Some(response), |response| async move {
+            let mut response = response?;
+            match response.chunk().await {
+                Ok(Some(chunk)) => Some((Ok(chunk), Some(response))),
+                Ok(None) => None,
+                Err(e) => Some((Err(e), None)),
+            }
+        });
+        Ok(Opened::Events(subscription::events(chunks)))
+    }
+
+    /// Opens an `EventSource`, which cannot send the request's headers and
+    /// reconnects by itself; the stream ends when the browser gives up.
+    #[cfg(all(target_arch = "wasm32", feature = "event-source"))]
+    async fn open(&self, request: WasiRequest) -> Result<Opened, String> {
+        use web_sys::wasm_bindgen::JsCast;
+        use web_sys::wasm_bindgen::closure::Closure;
+
+        let source = web_sys::EventSource::new(&format!("{}{}", self.base_url, request.path_with_query))
+            .map_err(|e| format!("{:?}", e))?;
+        let (sender, events) = futures::channel::mpsc::unbounded();
+        let on_message = Closure::<dyn FnMut(web_sys::MessageEvent)>::new({
+            let sender = sender.clone();
+            move |event: web_sys::MessageEvent| {
+                if let Some(data) = event.data().as_string() {
+                    let _ = sender.unbounded_send(Ok(data));
+                }
+            }
+        });
+        let on_error = Closure::<dyn FnMut(web_sys::Event)>::new({
+            let source = source.clone();
+            move |_: web_sys::Event| {
+                if source.ready_state() == web_sys::EventSource::CLOSED {
+                    let _ = sender.unbounded_send(Err("the event stream was closed".to_string()));
+                    sender.close_channel();
+                }
+            }
+        });
+        source.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        source.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+        let open = OpenEventSource { source, _handlers: (on_message, on_error) };
+        Ok(Opened::Events(
+            events
+                .map(move |event| {
+                    let _open = &open;
+                    event
+                })
+                .boxed_local(),
+        ))
+    }
+}
+
+/// Closes the `EventSource` when its stream is dropped.
+#[cfg(all(feature = "rpc", target_arch = "wasm32", feature = "event-source"))]
+struct OpenEventSource {
+    source: web_sys::EventSource,
+    _handlers: (
+        web_sys::wasm_bindgen::closure::Closure<dyn FnMut(web_sys::MessageEvent)>,
+        web_sys::wasm_bindgen::closure::Closure<dyn FnMut(web_sys::Event)>,
+    ),
+}
+
+#[cfg(all(feature = "rpc", target_arch = "wasm32", feature = "event-source"))]
+impl Drop for OpenEventSource {
+    fn drop(&mut self) {
+        self.source.close();
+    }
+}
+
+/// Calls the actions of an app whose config is `C`.
+///
+/// ```rust,ignore
+/// let client = RpcClient::<AppConfig>::http(window().location().origin()?);
+/// let save = Action::new_local(move |input: &UpdateUserInput| {
+///     let (client, input) = (client.clone(), input.clone());
+///     async move { client.act::<UserRoute>(&UserParams { id: 42 }, &input).await }
+/// });
+/// ```
+pub struct RpcClient<C: AppConfig> {
+    transport: Arc<dyn RpcTransport>,
+    headers: Vec<(String, String)>,
+    _config: PhantomData<fn() -> C>,
+}
+
+impl<C: AppConfig> Clone for RpcClient<C> {
+    fn clone(&self) -> Self {
+        Self { transport: self.transport.clone(), headers: self.headers.clone(), _config: PhantomData }
+    }
+}
+
+impl<C: AppConfig> RpcClient<C> {
+    pub fn new(transport: impl RpcTransport) -> Self {
+        Self { transport: Arc::new(transport), headers: Vec::new(), _config: PhantomData }
+    }
+
+    /// A client for the app served at `base_url`. See [`HttpTransport::new`].
+    #[cfg(feature = "rpc")]
+    pub fn http(base_url: impl Into<String>) -> Self {
+        Self::new(HttpTransport::new(base_url))
+    }
+
+    /// Sends `name: value` with every call, e.g. a CSRF token.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Runs the action of route `R` on the server.
+    pub async fn act<R: Route<C>>(&self, params: &R::Params, input: &ActionInput<R, C>) -> Result<ActionOutput<R, C>, RouteError> {
+        let params = serde_json::to_value(params).map_err(|e| RouteError::ValidationFailed(e.to_string()))?;
+        self.act_path(R::path(), params, input).await
+    }
+
+    /// Runs the action registered under the pattern `path`, e.g. `/users/:id`,
+    /// filling its params from `params`. Params the path does not capture go
+    /// in the query string.
+    pub async fn act_path<T: DeserializeOwned>(&self, path: &str, params: impl Serialize, input: impl Serialize) -> Result<T, RouteError> {
+        let params = serde_json::to_value(params).map_err(|e| RouteError::ValidationFailed(e.to_string()))?;
+        let body = serde_json::to_vec(&input).map_err(|e| RouteError::ValidationFailed(e.to_string()))?;
+        let mut request = WasiRequest::new("POST", request_path(path, params)?)
+            .with_header("content-type", "application/json")
+            .with_header("accept", "application/json")
+            .with_body(body);
+        request.headers.extend(self.headers.iter().cloned());
+
+        let response = self.transport.send(request).await.map_err(RouteError::External)?;
+        if !(200..300).contains(&response.status) {
+            return Err(error(&response));
+        }
+        serde_json::from_slice(&response.body).map_err(|e| RouteError::InternalError(e.to_string()))
+    }
+
+    /// Opens subscription `S` on the server and decodes its messages.
+    pub async fn subscribe<S: Subscription<C>>(&self, params: &S::Params) -> Result<LocalBoxStream<'static, Result<S::Message, RouteError>>, RouteError> {
+        self.subscribe_path(S::path(), params).await
+    }
+
+    /// Opens the subscription registered under the pattern `path`, filling
+    /// its params from `params` as [`act_path`](Self::act_path) does.
+    pub async fn subscribe_path<T: DeserializeOwned + 'static>(&self, path: &str, params: impl Serialize) -> Result<LocalBoxStream<'static, Result<T, RouteError>>, RouteError> {
+        let params = serde_json::to_value(params).map_err(|e| RouteError::ValidationFailed(e.to_string()))?;
+        let mut request = WasiRequest::new("GET", request_path(path, params)?).with_header("accept", EVENT_STREAM);
+        request.headers.extend(self.headers.iter().cloned());
+
+        let events = match self.transport.open(request).await.map_err(RouteError::External)? {
+            Opened::Events(events) => events,
+            Opened::Refused(response) => return Err(error(&response)),
+        };
+        Ok(events
+            .map(|event| match event {
+                Ok(data) => serde_json::from_str(&data).map_err(|e| RouteError::InternalError(e.to_string())),
+                Err(e) => Err(RouteError::External(e)),
+            })
+            .boxed_local())
+    }
+
+    /// The latest message of subscription `S` as a signal. Like
+    /// `LocalResource`, it only subscribes in the browser; during server
+    /// rendering it stays `None` and `Connecting`.
+    pub fn live<S: Subscription<C>>(&self, params: &S::Params) -> LiveSignal<S::Message> {
+        let live = LiveSignal::new();
+        if !cfg!(target_arch = "wasm32") {
+            return live;
+        }
+        let params = serde_json::to_value(params);
+        let (client, signal) = (self.clone(), live.clone());
+        leptos::task::spawn_local(async move {
+            let opened = match params {
+                Ok(params) => client.subscribe_path::<S::Message>(S::path(), params).await,
+                Err(e) => Err(RouteError::ValidationFailed(e.to_string())),
+            };
+            match opened {
+                Ok(messages) => signal.follow(messages).await,
+                Err(e) => signal.fail(&e),
+            }
+        });
+        live
+    }
+}
+
+/// `pattern` with its `:name` and `*name` segments filled from `params`,
+/// and the remaining params as the query string.
+fn request_path(pattern: &str, params: Value) -> Result<String, RouteError> {
+    let mut params = match params {
+        Value::Object(object) => object,
+        Value::Null => Map::new(),
+        other => return Err(RouteError::ValidationFailed(format!("route params must be an object, not {}", other))),
+    };
+    let mut path = String::new();
+    for segment in pattern.split('/').filter(|s| !s.is_empty()) {
+        let (name, wildcard) = match (segment.strip_prefix(':'), segment.strip_prefix('*')) {
+            (Some(name), _) => (name, false),
+            (_, Some(name)) => (name, true),
+            _ => {
+                path.push('/');
+                path.push_str(segment);
+                continue;
+            }
+        };
+        let value = params.remove(name).filter(|v| !v.is_null());
+        let value = value.ok_or_else(|| RouteError::ValidationFailed(format!("missing route param `{}`", name)))?;
+        path.push('/');
+        path.push_str(&encode(&text(&value), wildcard));
+    }
+    if path.is_empty() {
+        path.push('/');
+    }
+    let query: Vec<String> = params
+        .iter()
+        .filter(|(_, value)| !value.is_null())
+        .map(|(name, value)| format!("{}={}", encode(name, false), encode(&text(value), false)))
+        .collect();
+    if !query.is_empty() {
+        path.push('?');
+        path.push_str(&query.join("&"));
+    }
+    Ok(path)
+}
+
+/// A param as it appears in a path or query: strings as they are, other
+/// values as JSON.
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Percent-encodes everything but unreserved characters, and `/` in wildcards.
+fn encode(text: &str, keep_slashes: bool) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            b'/' if keep_slashes => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// The error a failed response stands for: its `action_error` if it has
+/// one, else the [`RouteError`] answered with its status.
+fn error(response: &WasiResponse) -> RouteError {
+    let body: Value = serde_json::from_slice(&response.body).unwrap_or(Value::Null);
+    if let Some(Ok(action_error)) = body.get("action_error").map(|e| serde_json::from_value::<ActionError>(e.clone())) {
+        return action_error.into();
+    }
+    let message = match body["error"].as_str() {
+        Some(message) => message.to_string(),
+        None => String::from_utf8_lossy(&response.body).into_owned(),
+    };
+    match response.status {
+        300..=399 => {
+            let location = response.headers.iter().find(|(name, _)| name.eq_ignore_ascii_case("location"));
+            RouteError::Redirect(location.map_or(message, |(_, value)| value.clone()))
+        }
+        401 => RouteError::Unauthorized,
+        403 if message.starts_with("Forbidden: requires ") => {
+            RouteError::Forbidden(message.trim_start_matches("Forbidden: requires ").to_string())
+        }
+        404 => RouteError::NotFound,
+        406 => RouteError::NotAcceptable(message.trim_start_matches("Not acceptable: ").to_string()),
+        400 | 422 => RouteError::ValidationFailed(message.trim_start_matches("Validation failed: ").to_string()),
+        415 => RouteError::UnsupportedMediaType(message.trim_start_matches("Unsupported media type: ").to_string()),
+        500 => RouteError::InternalError(message.trim_start_matches("Internal router error: ").to_string()),
+        502..=504 => RouteError::External(message.trim_start_matches("External error: ").to_string()),
+        status => RouteError::Denied(status),
+    }
+}
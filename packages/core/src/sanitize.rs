@@ -0,0 +1,100 @@
+//! Input sanitization that runs before validation.
+//!
+//! `#[derive(Schema)]` implements [`Sanitize`] from the transform attributes
+//! `#[schema(trim)]`, `#[schema(lowercase)]`, `#[schema(strip_html)]` and
+//! `#[schema(transform = "path::to::fn")]`, applied in the order they are written.
+//! Actions opt in to running it on every request by returning
+//! [`sanitize_and_validate`] from [`RouteAction::prepare_input`](crate::RouteAction::prepare_input).
+
+use crate::action_error::ActionError;
+use crate::router::RouteError;
+use crate::validation::Validate;
+
+/// Types that normalize their own fields, e.g. trimming whitespace.
+pub trait Sanitize {
+    /// Applies the field transforms in place.
+    fn sanitize(&mut self);
+}
+
+/// Field types the built-in text transforms apply to.
+pub trait SanitizeText {
+    /// Replaces every string in `self` with `f` of it.
+    fn map_text(&mut self, f: impl Fn(&str) -> String);
+}
+
+impl SanitizeText for String {
+    fn map_text(&mut self, f: impl Fn(&str) -> String) {
+        *self = f(self);
+    }
+}
+
+impl<T: SanitizeText> SanitizeText for Option<T> {
+    fn map_text(&mut self, f: impl Fn(&str) -> String) {
+        if let Some(value) = self {
+            value.map_text(f);
+        }
+    }
+}
+
+impl<T: SanitizeText> SanitizeText for Vec<T> {
+    fn map_text(&mut self, f: impl Fn(&str) -> String) {
+        for value in self {
+            value.map_text(&f);
+        }
+    }
+}
+
+/// Removes HTML tags and comments, along with the contents of `<script>` and
+/// `<style>` elements. Entities are left encoded, so `&lt;b&gt;` stays inert.
+pub fn strip_html(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        let tag = &rest[start..];
+        if let Some(comment) = tag.strip_prefix("<!--") {
+            rest = comment.find("-->").map(|end| &comment[end + 3..]).unwrap_or("");
+            continue;
+        }
+        // A `<` that does not open a tag, as in `a < b`, is text.
+        if !tag[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!' || c == '?') {
+            out.push('<');
+            rest = &tag[1..];
+            continue;
+        }
+        let Some(end) = tag.find('>') else {
+            rest = "";
+            break;
+        };
+        let name: String = tag[1..end]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        rest = &tag[end + 1..];
+        if name == "script" || name == "style" {
+            let close = format!("</{}", name);
+            rest = match rest.to_ascii_lowercase().find(&close) {
+                Some(at) => rest[at..].find('>').map(|end| &rest[at + end + 1..]).unwrap_or(""),
+                None => "",
+            };
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Sanitizes `input`, then validates it, failing with a
+/// [`RouteError::Action`] that lists the invalid fields. Meant for
+/// [`RouteAction::prepare_input`](crate::RouteAction::prepare_input):
+///
+/// ```rust,ignore
+/// fn prepare_input(&self, input: SignupForm) -> Result<SignupForm, RouteError> {
+///     montrs_core::sanitize_and_validate(input)
+/// }
+/// ```
+pub fn sanitize_and_validate<T: Sanitize + Validate>(mut input: T) -> Result<T, RouteError> {
+    input.sanitize();
+    input.validate().map_err(|errors| RouteError::from(ActionError::validation(&errors)))?;
+    Ok(input)
+}
@@ -0,0 +1,371 @@
+//! montrs-core/src/secrets.rs: Encrypted secrets that can be committed to the repo.
+//! Each value in the secrets file is encrypted separately with age (X25519) to every
+//! listed recipient, so keys can be listed and added without the private key and
+//! diffs stay readable. At boot, `SecretsEnv` decrypts the file with a key from
+//! `MONTRS_SECRETS_KEY` or the OS keychain and plugs into an `EnvChain`.
+
+use crate::AgentError;
+use crate::env::{EnvConfig, EnvError};
+use age::secrecy::ExposeSecret;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+/// The conventional location of the secrets file, relative to the project root.
+pub const DEFAULT_SECRETS_FILE: &str = "secrets.enc.toml";
+
+/// Environment variable holding the private key (`AGE-SECRET-KEY-...`).
+pub const SECRETS_KEY_VAR: &str = "MONTRS_SECRETS_KEY";
+
+/// Environment variable naming a file that holds the private key, e.g. a
+/// mounted container secret or the output of `age-keygen`.
+pub const SECRETS_KEY_FILE_VAR: &str = "MONTRS_SECRETS_KEY_FILE";
+
+/// Keychain service under which private keys are stored, one entry per project.
+pub const KEYCHAIN_SERVICE: &str = "montrs";
+
+/// Errors that can occur while reading, writing, or decrypting secrets.
+#[derive(Debug, thiserror::Error)]
+pub enum SecretsError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid secrets file: {0}")]
+    Parse(String),
+    #[error("The secrets file has no recipients")]
+    NoRecipients,
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+    #[error("Failed to encrypt secret: {0}")]
+    Encrypt(String),
+    #[error("Failed to decrypt secret '{key}': {reason}")]
+    Decrypt { key: String, reason: String },
+    #[error("No secrets key available: {0}")]
+    KeyUnavailable(String),
+}
+
+impl AgentError for SecretsError {
+    fn error_code(&self) -> &'static str {
+        match self {
+            SecretsError::Io(_) => "SECRETS_IO",
+            SecretsError::Parse(_) => "SECRETS_PARSE",
+            SecretsError::NoRecipients => "SECRETS_NO_RECIPIENTS",
+            SecretsError::InvalidKey(_) => "SECRETS_INVALID_KEY",
+            SecretsError::Encrypt(_) => "SECRETS_ENCRYPT",
+            SecretsError::Decrypt { .. } => "SECRETS_DECRYPT",
+            SecretsError::KeyUnavailable(_) => "SECRETS_KEY_UNAVAILABLE",
+        }
+    }
+
+    fn explanation(&self) -> String {
+        match self {
+            SecretsError::Io(e) => format!("Reading or writing the secrets file failed: {}", e),
+            SecretsError::Parse(e) => format!("The secrets file is not valid TOML in the expected shape: {}", e),
+            SecretsError::NoRecipients => "A secret cannot be encrypted because the file lists no recipient public keys.".to_string(),
+            SecretsError::InvalidKey(e) => format!("An age key could not be parsed: {}", e),
+            SecretsError::Encrypt(e) => format!("age encryption failed: {}", e),
+            SecretsError::Decrypt { key, reason } => format!(
+                "The secret '{}' could not be decrypted with the available key: {}",
+                key, reason
+            ),
+            SecretsError::KeyUnavailable(e) => format!("No private key was found to decrypt secrets: {}", e),
+        }
+    }
+
+    fn suggested_fixes(&self) -> Vec<String> {
+        match self {
+            SecretsError::Io(_) => vec!["Run `montrs secrets init` to create the secrets file.".to_string()],
+            SecretsError::Parse(_) => vec!["Restore the secrets file from version control; it should not be edited by hand.".to_string()],
+            SecretsError::NoRecipients => vec!["Run `montrs secrets init` or `montrs secrets add-recipient <age1...>`.".to_string()],
+            SecretsError::InvalidKey(_) => vec![
+                "Private keys start with `AGE-SECRET-KEY-` and public keys with `age1`.".to_string(),
+            ],
+            SecretsError::Encrypt(_) => vec!["Check that every entry in `recipients` is a valid age public key.".to_string()],
+            SecretsError::Decrypt { .. } => vec![
+                "Ask a teammate to add your public key with `montrs secrets add-recipient`.".to_string(),
+                format!("Verify that {} holds the key for this project.", SECRETS_KEY_VAR),
+            ],
+            SecretsError::KeyUnavailable(_) => vec![
+                format!("Set {} to your `AGE-SECRET-KEY-...` value (e.g. in CI).", SECRETS_KEY_VAR),
+                format!("Point {} at a file containing the key.", SECRETS_KEY_FILE_VAR),
+                "Run `montrs secrets init` to generate a key and store it in the OS keychain.".to_string(),
+            ],
+        }
+    }
+
+    fn subsystem(&self) -> &'static str {
+        "secrets"
+    }
+}
+
+/// A private age key used to decrypt secrets.
+pub struct SecretKey {
+    identity: age::x25519::Identity,
+}
+
+impl SecretKey {
+    /// Generates a fresh key pair.
+    pub fn generate() -> Self {
+        Self {
+            identity: age::x25519::Identity::generate(),
+        }
+    }
+
+    /// Parses an `AGE-SECRET-KEY-...` string.
+    pub fn parse(s: &str) -> Result<Self, SecretsError> {
+        s.trim()
+            .parse()
+            .map(|identity| Self { identity })
+            .map_err(|e: &str| SecretsError::InvalidKey(e.to_string()))
+    }
+
+    /// Returns the private key in its `AGE-SECRET-KEY-...` form.
+    pub fn expose(&self) -> String {
+        self.identity.to_string().expose_secret().to_string()
+    }
+
+    /// Returns the matching `age1...` public key.
+    pub fn public_key(&self) -> String {
+        self.identity.to_public().to_string()
+    }
+
+    /// Reads the key from `MONTRS_SECRETS_KEY`, or from the file named by
+    /// `MONTRS_SECRETS_KEY_FILE`, if either is set.
+    pub fn from_env() -> Result<Option<Self>, SecretsError> {
+        if let Ok(value) = std::env::var(SECRETS_KEY_VAR)
+            && !value.trim().is_empty()
+        {
+            return Self::parse(&value).map(Some);
+        }
+        match std::env::var(SECRETS_KEY_FILE_VAR) {
+            Ok(path) if !path.trim().is_empty() => Self::from_file(path.trim()).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Reads a key file, skipping blank lines and `#` comments as written by `age-keygen`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, SecretsError> {
+        let content = std::fs::read_to_string(path)?;
+        let line = content
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty() && !l.starts_with('#'))
+            .ok_or_else(|| SecretsError::InvalidKey("key file is empty".to_string()))?;
+        Self::parse(line)
+    }
+
+    /// Reads the key stored for `project` in the OS keychain, if any.
+    #[cfg(feature = "keychain")]
+    pub fn from_keychain(project: &str) -> Result<Option<Self>, SecretsError> {
+        let entry = keyring::Entry::new(KEYCHAIN_SERVICE, project)
+            .map_err(|e| SecretsError::KeyUnavailable(e.to_string()))?;
+        match entry.get_password() {
+            Ok(value) => Self::parse(&value).map(Some),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(SecretsError::KeyUnavailable(e.to_string())),
+        }
+    }
+
+    /// Stores this key for `project` in the OS keychain.
+    #[cfg(feature = "keychain")]
+    pub fn store_in_keychain(&self, project: &str) -> Result<(), SecretsError> {
+        keyring::Entry::new(KEYCHAIN_SERVICE, project)
+            .and_then(|entry| entry.set_password(&self.expose()))
+            .map_err(|e| SecretsError::KeyUnavailable(e.to_string()))
+    }
+
+    /// Finds the key for `project`: the environment first, then the OS keychain.
+    pub fn resolve(project: &str) -> Result<Self, SecretsError> {
+        if let Some(key) = Self::from_env()? {
+            return Ok(key);
+        }
+        #[cfg(feature = "keychain")]
+        if let Some(key) = Self::from_keychain(project)? {
+            return Ok(key);
+        }
+        Err(SecretsError::KeyUnavailable(format!(
+            "neither {} nor {} is set and no keychain entry exists for '{}'",
+            SECRETS_KEY_VAR, SECRETS_KEY_FILE_VAR, project
+        )))
+    }
+}
+
+/// The on-disk secrets file: recipient public keys plus per-key ciphertexts.
+///
+/// ```toml
+/// recipients = ["age1..."]
+///
+/// [secrets]
+/// DATABASE_URL = "YWdlLWVuY3J5cHRpb24ub3JnL3Yx..."
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SecretsFile {
+    #[serde(default)]
+    pub recipients: Vec<String>,
+    #[serde(default)]
+    secrets: BTreeMap<String, String>,
+}
+
+impl SecretsFile {
+    /// Reads a secrets file from disk.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SecretsError> {
+        let content = std::fs::read_to_string(path)?;
+        toml::from_str(&content).map_err(|e| SecretsError::Parse(e.to_string()))
+    }
+
+    /// Writes the secrets file to disk.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SecretsError> {
+        let content = toml::to_string_pretty(self).map_err(|e| SecretsError::Parse(e.to_string()))?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Adds a recipient public key. Returns false if it was already listed.
+    /// Existing secrets stay readable only by the old recipients until [`SecretsFile::rekey`].
+    pub fn add_recipient(&mut self, public_key: &str) -> Result<bool, SecretsError> {
+        let public_key = public_key.trim();
+        parse_recipient(public_key)?;
+        if self.recipients.iter().any(|r| r == public_key) {
+            return Ok(false);
+        }
+        self.recipients.push(public_key.to_string());
+        Ok(true)
+    }
+
+    /// Names of all stored secrets, sorted.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.secrets.keys().map(|k| k.as_str())
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.secrets.contains_key(key)
+    }
+
+    /// Encrypts `value` to every recipient and stores it under `key`.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), SecretsError> {
+        let ciphertext = self.encrypt(value.as_bytes())?;
+        self.secrets.insert(key.to_string(), ciphertext);
+        Ok(())
+    }
+
+    /// Removes a secret. Returns false if it did not exist.
+    pub fn remove(&mut self, key: &str) -> bool {
+        self.secrets.remove(key).is_some()
+    }
+
+    /// Decrypts a single secret.
+    pub fn get(&self, key: &str, secret_key: &SecretKey) -> Result<Option<String>, SecretsError> {
+        self.secrets
+            .get(key)
+            .map(|ciphertext| decrypt(key, ciphertext, secret_key))
+            .transpose()
+    }
+
+    /// Decrypts every secret.
+    pub fn decrypt_all(&self, secret_key: &SecretKey) -> Result<HashMap<String, String>, SecretsError> {
+        self.secrets
+            .iter()
+            .map(|(key, ciphertext)| Ok((key.clone(), decrypt(key, ciphertext, secret_key)?)))
+            .collect()
+    }
+
+    /// Re-encrypts every secret to the current recipients.
+    pub fn rekey(&mut self, secret_key: &SecretKey) -> Result<(), SecretsError> {
+        for (key, value) in self.decrypt_all(secret_key)? {
+            self.set(&key, &value)?;
+        }
+        Ok(())
+    }
+
+    fn encrypt(&self, plaintext: &[u8]) -> Result<String, SecretsError> {
+        let recipients = self
+            .recipients
+            .iter()
+            .map(|r| parse_recipient(r))
+            .collect::<Result<Vec<_>, _>>()?;
+        if recipients.is_empty() {
+            return Err(SecretsError::NoRecipients);
+        }
+
+        let encryptor = age::Encryptor::with_recipients(recipients.iter().map(|r| r as &dyn age::Recipient))
+            .map_err(|e| SecretsError::Encrypt(e.to_string()))?;
+        let mut ciphertext = Vec::new();
+        let mut writer = encryptor.wrap_output(&mut ciphertext)?;
+        writer.write_all(plaintext)?;
+        writer.finish()?;
+        Ok(base64::engine::general_purpose::STANDARD.encode(ciphertext))
+    }
+}
+
+fn parse_recipient(s: &str) -> Result<age::x25519::Recipient, SecretsError> {
+    s.parse()
+        .map_err(|e: &str| SecretsError::InvalidKey(format!("{} ({})", e, s)))
+}
+
+fn decrypt(key: &str, ciphertext: &str, secret_key: &SecretKey) -> Result<String, SecretsError> {
+    let failed = |reason: String| SecretsError::Decrypt {
+        key: key.to_string(),
+        reason,
+    };
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(ciphertext)
+        .map_err(|e| failed(e.to_string()))?;
+    let plaintext = age::decrypt(&secret_key.identity, &bytes).map_err(|e| failed(e.to_string()))?;
+    String::from_utf8(plaintext).map_err(|e| failed(e.to_string()))
+}
+
+/// Decrypted secrets exposed as an environment provider.
+///
+/// ```rust,ignore
+/// let env = EnvChain::new()
+///     .with(TypedEnv {})
+///     .with(SecretsEnv::load("secrets.enc.toml", "my-app")?);
+/// let url = env.get_var("DATABASE_URL")?;
+/// ```
+#[derive(Clone, Default)]
+pub struct SecretsEnv {
+    values: Arc<HashMap<String, String>>,
+}
+
+impl SecretsEnv {
+    pub fn new(values: HashMap<String, String>) -> Self {
+        Self {
+            values: Arc::new(values),
+        }
+    }
+
+    /// Decrypts every secret in `file` with `key`.
+    pub fn decrypt(file: &SecretsFile, key: &SecretKey) -> Result<Self, SecretsError> {
+        file.decrypt_all(key).map(Self::new)
+    }
+
+    /// Loads and decrypts the secrets file at boot, resolving the key for `project`
+    /// with [`SecretKey::resolve`]. A missing file yields an empty provider.
+    pub fn load(path: impl AsRef<Path>, project: &str) -> Result<Self, SecretsError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = SecretsFile::load(path)?;
+        let key = SecretKey::resolve(project)?;
+        Self::decrypt(&file, &key)
+    }
+}
+
+impl EnvConfig for SecretsEnv {
+    fn get_var(&self, key: &str) -> Result<String, EnvError> {
+        self.values
+            .get(key)
+            .cloned()
+            .ok_or_else(|| EnvError::MissingKey(key.to_string()))
+    }
+
+    fn vars(&self) -> HashMap<String, String> {
+        self.values
+            .keys()
+            .map(|k| (k.clone(), "Encrypted secret".to_string()))
+            .collect()
+    }
+}
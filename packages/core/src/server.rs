@@ -0,0 +1,104 @@
+//! montrs-core/src/server.rs: Serving an app on its own, with Axum.
+//!
+//! [`AppSpec::serve`] boots the app and answers HTTP on an address until the
+//! process gets Ctrl-C. Requests reach the router as in an embedded app
+//! ([`crate::embed`]): `GET` and `HEAD` run loaders, `POST`, `PUT`, `PATCH`
+//! and `DELETE` actions, within the preset's limits and the rate limit of its
+//! limiter or the one given to [`AppSpec::with_limiter`]. Every request
+//! carries the booted app as an `Extension<Embedded<C>>`, so handlers added
+//! with [`AppSpec::serve_with`] see the same config and env as the plates.
+//! Once the server has stopped, the plates are [shut down](AppSpec::shutdown).
+
+use crate::embed::Embedded;
+use crate::{AgentError, AppConfig, AppSpec, BootError, boot};
+
+/// Errors raised while serving an `AppSpec`.
+#[derive(Debug, thiserror::Error)]
+pub enum ServeError {
+    #[error("{0}")]
+    Boot(#[from] BootError),
+    #[error("Could not listen on {addr}: {reason}")]
+    Bind { addr: String, reason: String },
+    #[error("The server stopped: {0}")]
+    Io(String),
+}
+
+impl AgentError for ServeError {
+    fn error_code(&self) -> &'static str {
+        match self {
+            ServeError::Boot(error) => error.error_code(),
+            ServeError::Bind { .. } => "SERVE_BIND",
+            ServeError::Io(_) => "SERVE_IO",
+        }
+    }
+
+    fn explanation(&self) -> String {
+        match self {
+            ServeError::Boot(error) => error.explanation(),
+            ServeError::Bind { addr, reason } => {
+                format!("The server could not bind {}: {}. Another process may be using the port.", addr, reason)
+            }
+            ServeError::Io(reason) => format!("Accepting connections failed: {}", reason),
+        }
+    }
+
+    fn suggested_fixes(&self) -> Vec<String> {
+        match self {
+            ServeError::Boot(error) => error.suggested_fixes(),
+            ServeError::Bind { .. } => vec![
+                "Stop the process listening on the port, or serve on another one.".to_string(),
+                "Ports below 1024 need elevated privileges on most systems.".to_string(),
+            ],
+            ServeError::Io(_) => Vec::new(),
+        }
+    }
+}
+
+impl<C: AppConfig> AppSpec<C> {
+    /// Boots the app and serves it at `addr`, e.g. `"0.0.0.0:8080"`, until
+    /// Ctrl-C.
+    ///
+    /// ```rust,ignore
+    /// AppSpec::new(config, env).with_plate(Box::new(TodoPlate)).serve("0.0.0.0:8080").await?;
+    /// ```
+    pub async fn serve(self, addr: &str) -> Result<(), ServeError> {
+        self.serve_with(addr, |router| router).await
+    }
+
+    /// [`serve`](Self::serve), with routes and layers added to the
+    /// `axum::Router` first. The app answers every path the added routes do not.
+    ///
+    /// ```rust,ignore
+    /// spec.serve_with("0.0.0.0:8080", |router| {
+    ///     router.route("/health", get(health)).layer(TraceLayer::new_for_http())
+    /// })
+    /// .await?;
+    ///
+    /// async fn health(Extension(app): Extension<Embedded<AppConfig>>) -> String {
+    ///     app.config().version.clone()
+    /// }
+    /// ```
+    pub async fn serve_with(mut self, addr: &str, extend: impl FnOnce(axum::Router) -> axum::Router) -> Result<(), ServeError> {
+        self.boot().await?;
+        let plates = std::mem::take(&mut self.plates);
+        let app = Embedded::new(self.router, self.config, self.env);
+        let service = extend(app.clone().into_axum_router()).layer(axum::Extension(app));
+        let listener = match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                // Failures are logged; the bind error is the one to report.
+                let _ = boot::shutdown(&plates).await;
+                return Err(ServeError::Bind { addr: addr.to_string(), reason: e.to_string() });
+            }
+        };
+        tracing::info!(addr = %listener.local_addr().map_or(addr.to_string(), |a| a.to_string()), "serving");
+        let served = axum::serve(listener, service)
+            .with_graceful_shutdown(async {
+                let _ = tokio::signal::ctrl_c().await;
+            })
+            .await
+            .map_err(|e| ServeError::Io(e.to_string()));
+        boot::shutdown(&plates).await?;
+        served
+    }
+}
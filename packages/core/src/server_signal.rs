@@ -0,0 +1,454 @@
+//! montrs-core/src/server_signal.rs: Thread-safe reactive state for the server.
+//! Leptos signals belong to a reactive owner and its effects need an async
+//! executor, neither of which exists for state shared across Tokio tasks.
+//! `ServerSignal`, `ServerMemo` and `ServerEffect` form a standalone graph of
+//! `Arc`-backed, `Send + Sync` nodes for server state such as feature flags
+//! or hot-reloaded configuration. Dependencies are tracked automatically:
+//! whatever a memo or effect reads while it runs is what it reruns on.
+//!
+//! Memos and effects rerun synchronously on the thread that made the change.
+//! No lock is held while user code runs or while dependents are notified, and
+//! a change that arrives while a node is already running (on any thread)
+//! queues one more run instead of blocking, so concurrent updates cannot
+//! deadlock and every node settles on the latest values.
+//!
+//! Under `montrs serve`, the graph is recorded for the dev dashboard; see
+//! [`crate::signal_graph`].
+
+use crate::signal_graph::{self, NodeKind};
+use std::cell::RefCell;
+use std::fmt;
+use std::panic::Location;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    /// The memo or effect running on this thread, which reads subscribe to.
+    static OBSERVER: RefCell<Option<Arc<dyn Observer>>> = const { RefCell::new(None) };
+}
+
+/// A memo or effect: reruns when a source it read changes.
+trait Observer: Send + Sync {
+    fn id(&self) -> u64;
+    /// Records that the current run read `source`.
+    fn add_source(&self, source: Arc<dyn Source>);
+    /// Called by a source after it changed.
+    fn notify(self: Arc<Self>);
+}
+
+/// A signal or memo that observers subscribe to.
+trait Source: Send + Sync {
+    fn id(&self) -> u64;
+    fn unsubscribe(&self, observer: u64);
+}
+
+/// The observers of one source, held weakly so dropped ones fall away.
+#[derive(Default)]
+struct Subscribers(Mutex<Vec<(u64, Weak<dyn Observer>)>>);
+
+impl Subscribers {
+    fn add(&self, observer: &Arc<dyn Observer>) {
+        let mut subscribers = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if !subscribers.iter().any(|(id, _)| *id == observer.id()) {
+            subscribers.push((observer.id(), Arc::downgrade(observer)));
+        }
+    }
+
+    fn remove(&self, observer: u64) {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).retain(|(id, _)| *id != observer);
+    }
+
+    fn notify(&self) {
+        let observers: Vec<_> = {
+            let mut subscribers = self.0.lock().unwrap_or_else(|e| e.into_inner());
+            subscribers.retain(|(_, o)| o.strong_count() > 0);
+            subscribers.iter().filter_map(|(_, o)| o.upgrade()).collect()
+        };
+        for observer in observers {
+            observer.notify();
+        }
+    }
+}
+
+/// Subscribes the running observer, if any, to `source`. Called before the
+/// value is read, so a change after the read always notifies.
+fn track(source: Arc<dyn Source>, subscribers: &Subscribers) {
+    OBSERVER.with(|current| {
+        if let Some(observer) = current.borrow().as_ref() {
+            subscribers.add(observer);
+            signal_graph::subscribed(source.id(), observer.id());
+            observer.add_source(source);
+        }
+    });
+}
+
+/// Runs `f` with `observer` tracking reads, after dropping the sources the
+/// previous run subscribed to.
+fn run_tracked<R>(observer: Arc<dyn Observer>, sources: &Mutex<Vec<Arc<dyn Source>>>, f: impl FnOnce() -> R) -> R {
+    let previous = std::mem::take(&mut *sources.lock().unwrap_or_else(|e| e.into_inner()));
+    for source in previous {
+        source.unsubscribe(observer.id());
+        signal_graph::unsubscribed(source.id(), observer.id());
+    }
+
+    struct Restore(Option<Arc<dyn Observer>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            OBSERVER.with(|current| *current.borrow_mut() = previous);
+        }
+    }
+    let _restore = Restore(OBSERVER.with(|current| current.replace(Some(observer))));
+    f()
+}
+
+/// Serializes the runs of one node. A change that arrives during a run
+/// queues a single rerun rather than waiting for the run to finish.
+#[derive(Default)]
+struct Runner(Mutex<RunState>);
+
+#[derive(Default)]
+struct RunState {
+    running: bool,
+    pending: bool,
+}
+
+impl Runner {
+    fn schedule(&self, mut run: impl FnMut()) {
+        {
+            let mut state = self.0.lock().unwrap_or_else(|e| e.into_inner());
+            if state.running {
+                state.pending = true;
+                return;
+            }
+            state.running = true;
+        }
+
+        // A panicking run must not leave the node marked as running forever.
+        struct Reset<'a>(&'a Runner);
+        impl Drop for Reset<'_> {
+            fn drop(&mut self) {
+                *self.0.0.lock().unwrap_or_else(|e| e.into_inner()) = RunState::default();
+            }
+        }
+        let _reset = Reset(self);
+
+        loop {
+            run();
+            let mut state = self.0.lock().unwrap_or_else(|e| e.into_inner());
+            if !state.pending {
+                return;
+            }
+            state.pending = false;
+        }
+    }
+}
+
+/// A value shared across threads whose readers rerun when it changes.
+///
+/// ```rust,ignore
+/// let limit = ServerSignal::new(100);
+/// let doubled = ServerMemo::new({
+///     let limit = limit.clone();
+///     move || limit.get() * 2
+/// });
+/// limit.set(150);
+/// assert_eq!(doubled.get(), 300);
+/// ```
+pub struct ServerSignal<T>(Arc<SignalNode<T>>);
+
+struct SignalNode<T> {
+    id: u64,
+    value: RwLock<T>,
+    subscribers: Subscribers,
+}
+
+impl<T: Send + Sync> Source for SignalNode<T> {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn unsubscribe(&self, observer: u64) {
+        self.subscribers.remove(observer);
+    }
+}
+
+impl<T: Send + Sync + 'static> ServerSignal<T> {
+    #[track_caller]
+    pub fn new(value: T) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        signal_graph::created(id, NodeKind::Signal, Location::caller());
+        Self(Arc::new(SignalNode { id, value: RwLock::new(value), subscribers: Subscribers::default() }))
+    }
+
+    /// Names the signal in the dev dashboard's signal graph.
+    pub fn named(self, name: &str) -> Self {
+        signal_graph::renamed(self.0.id, name.to_string());
+        self
+    }
+
+    /// Reads the value, subscribing the running memo or effect. `f` must not
+    /// write this signal.
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        track(self.0.clone(), &self.0.subscribers);
+        self.with_untracked(f)
+    }
+
+    /// Reads the value without subscribing.
+    pub fn with_untracked<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        signal_graph::read(self.0.id);
+        f(&self.0.value.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.with(T::clone)
+    }
+
+    pub fn get_untracked(&self) -> T
+    where
+        T: Clone,
+    {
+        self.with_untracked(T::clone)
+    }
+
+    /// Replaces the value and reruns its dependents on this thread.
+    pub fn set(&self, value: T) {
+        self.update(|current| *current = value);
+    }
+
+    /// Changes the value in place and reruns its dependents on this thread.
+    /// `f` must not read this signal.
+    pub fn update(&self, f: impl FnOnce(&mut T)) {
+        f(&mut self.0.value.write().unwrap_or_else(|e| e.into_inner()));
+        signal_graph::wrote(self.0.id);
+        self.0.subscribers.notify();
+    }
+}
+
+impl<T> Clone for ServerSignal<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Drop for SignalNode<T> {
+    fn drop(&mut self) {
+        signal_graph::dropped(self.id);
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ServerSignal<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ServerSignal").field(&*self.0.value.read().unwrap_or_else(|e| e.into_inner())).finish()
+    }
+}
+
+/// A value derived from signals and other memos. It recomputes as soon as
+/// one of them changes and only notifies its own readers when the result
+/// differs from the previous one.
+pub struct ServerMemo<T>(Arc<MemoNode<T>>);
+
+struct MemoNode<T> {
+    id: u64,
+    compute: Box<dyn Fn() -> T + Send + Sync>,
+    /// `None` only until the first computation, which `ServerMemo::new` runs.
+    value: RwLock<Option<T>>,
+    sources: Mutex<Vec<Arc<dyn Source>>>,
+    subscribers: Subscribers,
+    runner: Runner,
+}
+
+impl<T: PartialEq + Send + Sync + 'static> MemoNode<T> {
+    fn recompute(self: Arc<Self>) {
+        self.runner.schedule(|| {
+            signal_graph::ran(self.id);
+            let value = run_tracked(self.clone(), &self.sources, &self.compute);
+            let changed = {
+                let mut current = self.value.write().unwrap_or_else(|e| e.into_inner());
+                let changed = current.as_ref() != Some(&value);
+                if changed {
+                    *current = Some(value);
+                }
+                changed
+            };
+            if changed {
+                signal_graph::wrote(self.id);
+                self.subscribers.notify();
+            }
+        });
+    }
+}
+
+impl<T: PartialEq + Send + Sync + 'static> Observer for MemoNode<T> {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn add_source(&self, source: Arc<dyn Source>) {
+        self.sources.lock().unwrap_or_else(|e| e.into_inner()).push(source);
+    }
+
+    fn notify(self: Arc<Self>) {
+        self.recompute();
+    }
+}
+
+impl<T: Send + Sync> Source for MemoNode<T> {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn unsubscribe(&self, observer: u64) {
+        self.subscribers.remove(observer);
+    }
+}
+
+impl<T: PartialEq + Send + Sync + 'static> ServerMemo<T> {
+    /// Creates the memo and computes its first value.
+    #[track_caller]
+    pub fn new(compute: impl Fn() -> T + Send + Sync + 'static) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        signal_graph::created(id, NodeKind::Memo, Location::caller());
+        let node = Arc::new(MemoNode {
+            id,
+            compute: Box::new(compute),
+            value: RwLock::new(None),
+            sources: Mutex::new(Vec::new()),
+            subscribers: Subscribers::default(),
+            runner: Runner::default(),
+        });
+        node.clone().recompute();
+        Self(node)
+    }
+
+    /// Names the memo in the dev dashboard's signal graph.
+    pub fn named(self, name: &str) -> Self {
+        signal_graph::renamed(self.0.id, name.to_string());
+        self
+    }
+
+    /// Reads the value, subscribing the running memo or effect.
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        track(self.0.clone(), &self.0.subscribers);
+        self.with_untracked(f)
+    }
+
+    /// Reads the value without subscribing.
+    pub fn with_untracked<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        signal_graph::read(self.0.id);
+        let value = self.0.value.read().unwrap_or_else(|e| e.into_inner());
+        f(value.as_ref().expect("ServerMemo::new computes the first value"))
+    }
+
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.with(T::clone)
+    }
+
+    pub fn get_untracked(&self) -> T
+    where
+        T: Clone,
+    {
+        self.with_untracked(T::clone)
+    }
+}
+
+impl<T> Clone for ServerMemo<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Drop for MemoNode<T> {
+    fn drop(&mut self) {
+        signal_graph::dropped(self.id);
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ServerMemo<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ServerMemo").field(&*self.0.value.read().unwrap_or_else(|e| e.into_inner())).finish()
+    }
+}
+
+/// A side effect that runs once on creation and again whenever a signal or
+/// memo it read changes. It stops when the handle is dropped.
+///
+/// ```rust,ignore
+/// let _reload = ServerEffect::new(move || {
+///     tracing::info!(limit = config.with(|c| c.rate_limit), "rate limit changed");
+/// });
+/// ```
+#[must_use = "the effect stops running when it is dropped"]
+pub struct ServerEffect(Arc<EffectNode>);
+
+struct EffectNode {
+    id: u64,
+    /// Only ever locked inside `runner`, which serializes runs.
+    run: Mutex<Box<dyn FnMut() + Send>>,
+    sources: Mutex<Vec<Arc<dyn Source>>>,
+    runner: Runner,
+}
+
+impl Observer for EffectNode {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn add_source(&self, source: Arc<dyn Source>) {
+        self.sources.lock().unwrap_or_else(|e| e.into_inner()).push(source);
+    }
+
+    fn notify(self: Arc<Self>) {
+        self.runner.schedule(|| {
+            signal_graph::ran(self.id);
+            let mut run = self.run.lock().unwrap_or_else(|e| e.into_inner());
+            run_tracked(self.clone(), &self.sources, &mut *run);
+        });
+    }
+}
+
+impl ServerEffect {
+    #[track_caller]
+    pub fn new(run: impl FnMut() + Send + 'static) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        signal_graph::created(id, NodeKind::Effect, Location::caller());
+        let node = Arc::new(EffectNode {
+            id,
+            run: Mutex::new(Box::new(run)),
+            sources: Mutex::new(Vec::new()),
+            runner: Runner::default(),
+        });
+        node.clone().notify();
+        Self(node)
+    }
+
+    /// Names the effect in the dev dashboard's signal graph.
+    pub fn named(self, name: &str) -> Self {
+        signal_graph::renamed(self.0.id, name.to_string());
+        self
+    }
+}
+
+impl Drop for ServerEffect {
+    fn drop(&mut self) {
+        let sources = std::mem::take(&mut *self.0.sources.lock().unwrap_or_else(|e| e.into_inner()));
+        for source in sources {
+            source.unsubscribe(self.0.id);
+        }
+        signal_graph::dropped(self.0.id);
+    }
+}
+
+impl fmt::Debug for ServerEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerEffect").field("id", &self.0.id).finish()
+    }
+}
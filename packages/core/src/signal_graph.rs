@@ -0,0 +1,333 @@
+//! montrs-core/src/signal_graph.rs: Dev-mode inspector for the server signal graph.
+//! When enabled (by `montrs serve` through `MONTRS_SIGNAL_GRAPH`, or with
+//! [`enable`]), every `ServerSignal`, `ServerMemo` and `ServerEffect` reports
+//! its reads, writes and reruns, and the dependency edges between them. Reads
+//! and writes are attributed to the plate, route or component running when
+//! they happen: `AppSpec::boot` scopes each plate's `init`, the router scopes
+//! loaders and actions, and [`with_scope`] or [`scoped`] label anything else.
+//! The graph is written as JSON for the `/_montrs` dashboard, which flags
+//! memos and effects that rerun too often and sources with too many
+//! dependents. Disabled, each hook costs one atomic load.
+
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::future::Future;
+use std::panic::Location;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Where the app writes the signal graph (set by the CLI).
+pub const SIGNAL_GRAPH_VAR: &str = "MONTRS_SIGNAL_GRAPH";
+/// Default location of the signal graph, relative to the project root.
+pub const SIGNAL_GRAPH_FILE: &str = "target/montrs/signal-graph.json";
+/// A memo or effect that reruns more often than this within one second is flagged.
+pub const EXCESSIVE_RUNS_PER_SEC: u64 = 30;
+/// A signal or memo with at least this many dependents is flagged.
+pub const WIDE_FANOUT: usize = 16;
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+/// Reads and writes outside any scope are counted under this label.
+const UNSCOPED: &str = "(unscoped)";
+
+static INSPECTOR: OnceLock<SignalInspector> = OnceLock::new();
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static SCOPE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    Signal,
+    Memo,
+    Effect,
+}
+
+/// One signal, memo or effect in a [`SignalGraph`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub id: u64,
+    pub kind: NodeKind,
+    /// The name given with `named`, or the file and line that created the node.
+    pub name: String,
+    /// The scope that created the node, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_in: Option<String>,
+    pub reads: u64,
+    pub writes: u64,
+    /// Times a memo recomputed or an effect ran.
+    pub runs: u64,
+    /// Most runs within one second.
+    pub peak_runs_per_sec: u64,
+    /// Reads per scope.
+    pub readers: BTreeMap<String, u64>,
+    /// Writes per scope.
+    pub writers: BTreeMap<String, u64>,
+    /// Memos and effects that read this node during their last run.
+    pub dependents: Vec<u64>,
+}
+
+/// Something in the graph worth a look.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GraphWarning {
+    /// A memo or effect reran more than [`EXCESSIVE_RUNS_PER_SEC`] times in a second.
+    ExcessiveRuns { node: u64, name: String, peak_runs_per_sec: u64 },
+    /// A signal or memo with at least [`WIDE_FANOUT`] dependents: every write reruns all of them.
+    WideFanout { node: u64, name: String, dependents: usize },
+}
+
+/// A snapshot of the live nodes, ordered by id, and the warnings they raise.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SignalGraph {
+    pub nodes: Vec<GraphNode>,
+    pub warnings: Vec<GraphWarning>,
+}
+
+impl SignalGraph {
+    /// Reads a graph written by [`SignalInspector::write_to`].
+    pub fn read_from(path: &Path) -> std::io::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(std::io::Error::other)
+    }
+
+    pub fn node(&self, id: u64) -> Option<&GraphNode> {
+        self.nodes.iter().find(|n| n.id == id)
+    }
+}
+
+struct NodeRecord {
+    node: GraphNode,
+    dependents: BTreeSet<u64>,
+    window_started: Instant,
+    window_runs: u64,
+}
+
+/// Records the signal graph of this process.
+pub struct SignalInspector {
+    nodes: Mutex<HashMap<u64, NodeRecord>>,
+    output: Option<PathBuf>,
+    dirty: AtomicBool,
+}
+
+impl SignalInspector {
+    fn new(output: Option<PathBuf>) -> Self {
+        Self { nodes: Mutex::new(HashMap::new()), output, dirty: AtomicBool::new(false) }
+    }
+
+    /// The current graph.
+    pub fn snapshot(&self) -> SignalGraph {
+        let nodes = self.nodes.lock().unwrap_or_else(|e| e.into_inner());
+        let mut graph = SignalGraph::default();
+        for record in nodes.values() {
+            let mut node = record.node.clone();
+            node.dependents = record.dependents.iter().copied().collect();
+            if node.peak_runs_per_sec > EXCESSIVE_RUNS_PER_SEC {
+                graph.warnings.push(GraphWarning::ExcessiveRuns {
+                    node: node.id,
+                    name: node.name.clone(),
+                    peak_runs_per_sec: node.peak_runs_per_sec,
+                });
+            }
+            if node.dependents.len() >= WIDE_FANOUT {
+                graph.warnings.push(GraphWarning::WideFanout {
+                    node: node.id,
+                    name: node.name.clone(),
+                    dependents: node.dependents.len(),
+                });
+            }
+            graph.nodes.push(node);
+        }
+        graph.nodes.sort_by_key(|n| n.id);
+        graph.warnings.sort_by_key(|w| match w {
+            GraphWarning::ExcessiveRuns { node, .. } | GraphWarning::WideFanout { node, .. } => *node,
+        });
+        graph
+    }
+
+    /// Writes the current graph as JSON, creating parent directories.
+    pub fn write_to(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(&self.snapshot()).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
+    }
+
+    fn flush(&self) {
+        let Some(path) = &self.output else {
+            return;
+        };
+        if self.dirty.swap(false, Ordering::Relaxed)
+            && let Err(e) = self.write_to(path)
+        {
+            tracing::warn!(error = %e, path = %path.display(), "failed to write signal graph");
+        }
+    }
+
+    fn edit(&self, id: u64, f: impl FnOnce(&mut NodeRecord)) {
+        if let Some(record) = self.nodes.lock().unwrap_or_else(|e| e.into_inner()).get_mut(&id) {
+            f(record);
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Starts recording without writing the graph anywhere, e.g. in tests, and
+/// returns the process-wide inspector.
+pub fn enable() -> &'static SignalInspector {
+    let inspector = INSPECTOR.get_or_init(|| SignalInspector::new(None));
+    ENABLED.store(true, Ordering::Relaxed);
+    inspector
+}
+
+/// Starts recording if `montrs serve` asked for the graph, writing it to the
+/// path in `MONTRS_SIGNAL_GRAPH` once a second while it changes.
+pub fn enable_from_env() -> Option<&'static SignalInspector> {
+    let path = std::env::var_os(SIGNAL_GRAPH_VAR)?;
+    let mut installed = false;
+    let inspector = INSPECTOR.get_or_init(|| {
+        installed = true;
+        SignalInspector::new(Some(PathBuf::from(path)))
+    });
+    ENABLED.store(true, Ordering::Relaxed);
+    if installed {
+        let flusher = std::thread::Builder::new().name("montrs-signal-graph".to_string()).spawn(move || {
+            loop {
+                std::thread::sleep(FLUSH_INTERVAL);
+                inspector.flush();
+            }
+        });
+        if let Err(e) = flusher {
+            tracing::warn!(error = %e, "signal graph will not be written");
+        }
+    }
+    Some(inspector)
+}
+
+/// The inspector, if recording is on.
+pub fn inspector() -> Option<&'static SignalInspector> {
+    if ENABLED.load(Ordering::Relaxed) { INSPECTOR.get() } else { None }
+}
+
+/// Runs `f` with reads and writes attributed to `scope`, such as a component name.
+pub fn with_scope<R>(scope: &str, f: impl FnOnce() -> R) -> R {
+    if inspector().is_none() {
+        return f();
+    }
+    struct Restore(Option<String>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            SCOPE.with(|s| *s.borrow_mut() = previous);
+        }
+    }
+    let _restore = Restore(SCOPE.with(|s| s.replace(Some(scope.to_string()))));
+    f()
+}
+
+/// Runs `future` with reads and writes attributed to `scope` whenever it is polled.
+pub async fn scoped<F: Future>(scope: &str, future: F) -> F::Output {
+    if inspector().is_none() {
+        return future.await;
+    }
+    let mut future = std::pin::pin!(future);
+    std::future::poll_fn(|cx| with_scope(scope, || future.as_mut().poll(cx))).await
+}
+
+fn current_scope() -> String {
+    SCOPE.with(|s| s.borrow().clone()).unwrap_or_else(|| UNSCOPED.to_string())
+}
+
+pub(crate) fn created(id: u64, kind: NodeKind, location: &Location<'_>) {
+    let Some(inspector) = inspector() else {
+        return;
+    };
+    let node = GraphNode {
+        id,
+        kind,
+        name: format!("{}:{}", location.file(), location.line()),
+        created_in: SCOPE.with(|s| s.borrow().clone()),
+        reads: 0,
+        writes: 0,
+        runs: 0,
+        peak_runs_per_sec: 0,
+        readers: BTreeMap::new(),
+        writers: BTreeMap::new(),
+        dependents: Vec::new(),
+    };
+    let record = NodeRecord { node, dependents: BTreeSet::new(), window_started: Instant::now(), window_runs: 0 };
+    inspector.nodes.lock().unwrap_or_else(|e| e.into_inner()).insert(id, record);
+    inspector.dirty.store(true, Ordering::Relaxed);
+}
+
+pub(crate) fn renamed(id: u64, name: String) {
+    if let Some(inspector) = inspector() {
+        inspector.edit(id, |r| r.node.name = name);
+    }
+}
+
+pub(crate) fn read(id: u64) {
+    if let Some(inspector) = inspector() {
+        let scope = current_scope();
+        inspector.edit(id, |r| {
+            r.node.reads += 1;
+            *r.node.readers.entry(scope).or_default() += 1;
+        });
+    }
+}
+
+pub(crate) fn wrote(id: u64) {
+    if let Some(inspector) = inspector() {
+        let scope = current_scope();
+        inspector.edit(id, |r| {
+            r.node.writes += 1;
+            *r.node.writers.entry(scope).or_default() += 1;
+        });
+    }
+}
+
+pub(crate) fn ran(id: u64) {
+    if let Some(inspector) = inspector() {
+        inspector.edit(id, |r| {
+            if r.window_started.elapsed() >= Duration::from_secs(1) {
+                r.window_started = Instant::now();
+                r.window_runs = 0;
+            }
+            r.window_runs += 1;
+            r.node.runs += 1;
+            r.node.peak_runs_per_sec = r.node.peak_runs_per_sec.max(r.window_runs);
+        });
+    }
+}
+
+pub(crate) fn subscribed(source: u64, observer: u64) {
+    if let Some(inspector) = inspector() {
+        inspector.edit(source, |r| {
+            r.dependents.insert(observer);
+        });
+    }
+}
+
+pub(crate) fn unsubscribed(source: u64, observer: u64) {
+    if let Some(inspector) = inspector() {
+        inspector.edit(source, |r| {
+            r.dependents.remove(&observer);
+        });
+    }
+}
+
+pub(crate) fn dropped(id: u64) {
+    if let Some(inspector) = inspector() {
+        let mut nodes = inspector.nodes.lock().unwrap_or_else(|e| e.into_inner());
+        nodes.remove(&id);
+        for record in nodes.values_mut() {
+            record.dependents.remove(&id);
+        }
+        inspector.dirty.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,340 @@
+//! montrs-core/src/subscription.rs: Live data pushed from the server.
+//!
+//! Loaders answer once. A [`Subscription`] answers with a stream of typed
+//! messages, e.g. every change to a todo list, for as long as the client
+//! listens. Subscriptions are registered on the [`Router`] next to routes and
+//! are served as Server-Sent Events: a `GET` at their path that accepts
+//! `text/event-stream` gets one `data:` event per message, JSON-encoded, and
+//! a comment every 15 seconds while idle so proxies keep the connection open.
+//! Guards of the enclosing plate, scope or group run when the stream is
+//! opened, and requirements given with `.requires(..)` are checked there.
+//!
+//! On the client, [`RpcClient::subscribe`](crate::RpcClient::subscribe)
+//! decodes the events back into messages and
+//! [`RpcClient::live`](crate::RpcClient::live) feeds them into a
+//! [`LiveSignal`] for Leptos views.
+//!
+//! An [`Embedded`](crate::Embedded) app streams under axum and actix; a WASI
+//! component cannot keep a response open and does not serve subscriptions.
+
+use crate::env::EnvConfig;
+use crate::param::ParamSpec;
+use crate::response::{ResponseError, StreamingResponse};
+use crate::router::{RouteContext, RouteError, RouteParams, Router};
+use crate::wasi::{self, WasiRequest, WasiResponse};
+use crate::AppConfig;
+use async_trait::async_trait;
+use futures::stream::{self, BoxStream, LocalBoxStream, Stream, StreamExt};
+use leptos::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The content type subscriptions are served with and requests must accept.
+pub const EVENT_STREAM: &str = "text/event-stream";
+
+/// How long an idle stream waits before sending a keep-alive comment.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// A stream of messages pushed to one subscriber.
+pub type Messages<T> = BoxStream<'static, T>;
+
+/// A source of live messages, registered with [`Router::subscription`].
+///
+/// ```rust,ignore
+/// struct TodoFeed {
+///     changes: broadcast::Sender<TodoChange>,
+/// }
+///
+/// #[async_trait]
+/// impl Subscription<AppConfig> for TodoFeed {
+///     type Params = ListParams;
+///     type Message = TodoChange;
+///
+///     fn path() -> &'static str {
+///         "/lists/:list/live"
+///     }
+///
+///     async fn subscribe(&self, _ctx: RouteContext<'_, AppConfig>, params: ListParams) -> Result<Messages<TodoChange>, RouteError> {
+///         let changes = BroadcastStream::new(self.changes.subscribe()).filter_map(|change| async { change.ok() });
+///         Ok(changes.filter(move |change| futures::future::ready(change.list == params.list)).boxed())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait Subscription<C: AppConfig>: Send + Sync + 'static {
+    type Params: RouteParams;
+    type Message: Serialize + DeserializeOwned + Send + Sync + 'static;
+
+    /// The path pattern the subscription answers at (e.g. "/todos/live").
+    fn path() -> &'static str;
+
+    /// Starts a subscriber's stream. [`RouteContext::request`] is the
+    /// opening request while this runs, but not while the stream is polled.
+    /// The stream ends the subscription when it ends.
+    async fn subscribe(&self, ctx: RouteContext<'_, C>, params: Self::Params) -> Result<Messages<Self::Message>, RouteError>;
+
+    /// Returns a description of what this subscription sends.
+    fn description(&self) -> &'static str {
+        ""
+    }
+}
+
+/// A registered subscription as the `RouterSpec` lists it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SubscriptionMetadata {
+    pub path: String,
+    pub description: String,
+    #[serde(default)]
+    pub params: Vec<ParamSpec>,
+    /// The names of the guards run when a stream is opened.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub guards: Vec<String>,
+    /// Annotations, such as the requirements given with `.requires(..)`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub meta: HashMap<String, String>,
+}
+
+/// Erases the associated types of a [`Subscription`] for storage in the Router.
+#[async_trait]
+pub(crate) trait SubscriptionInfo<C: AppConfig>: Send + Sync + 'static {
+    async fn open(&self, ctx: RouteContext<'_, C>, params: Value) -> Result<Messages<Value>, RouteError>;
+    fn metadata(&self, path: &str) -> SubscriptionMetadata;
+}
+
+#[async_trait]
+impl<C: AppConfig, S: Subscription<C>> SubscriptionInfo<C> for S {
+    async fn open(&self, ctx: RouteContext<'_, C>, params: Value) -> Result<Messages<Value>, RouteError> {
+        let params: S::Params = serde_json::from_value(params).map_err(|e| RouteError::ValidationFailed(e.to_string()))?;
+        let messages = self.subscribe(ctx, params).await?;
+        Ok(messages
+            .filter_map(|message| async move {
+                serde_json::to_value(message)
+                    .inspect_err(|e| tracing::warn!(path = S::path(), error = %e, "subscription message skipped"))
+                    .ok()
+            })
+            .boxed())
+    }
+
+    fn metadata(&self, path: &str) -> SubscriptionMetadata {
+        SubscriptionMetadata {
+            path: path.to_string(),
+            description: self.description().to_string(),
+            params: S::Params::params(),
+            guards: Vec::new(),
+            meta: HashMap::new(),
+        }
+    }
+}
+
+/// Whether `request` opens one of the router's subscriptions: a `GET` that
+/// accepts `text/event-stream` at a subscription's path. Other requests go to
+/// the loaders and actions, so a subscription may share a path with a route.
+pub fn is_subscription<C: AppConfig>(router: &Router<C>, request: &WasiRequest) -> bool {
+    request.method == "GET"
+        && request.header("accept").is_some_and(|accept| accept.contains(EVENT_STREAM))
+        && router.resolve_subscription(request.path()).is_some()
+}
+
+/// Opens the subscription `request` is for as an event stream. Query
+/// parameters are merged into the params as for loaders. A refused request,
+/// by a guard or the rate limiter, gets its JSON error instead.
+pub async fn respond<C: AppConfig>(router: &Router<C>, config: &C, env: &dyn EnvConfig, request: WasiRequest) -> StreamingResponse {
+    let origin = request.header("origin").map(str::to_string);
+    let mut response = if !router.admit() {
+        refused(WasiResponse::json(429, br#"{"error":"Too many requests"}"#.to_vec()))
+    } else {
+        let mut params = request.query_params();
+        if let Some(Value::Object(captured)) = router.resolve_subscription(request.path()).map(|m| m.params_json()) {
+            params.extend(captured);
+        }
+        let request = Arc::new(request);
+        let ctx = RouteContext { config, env };
+        match wasi::with_request(&request, router.subscribe(request.path(), ctx, Value::Object(params))).await {
+            Ok(messages) => event_stream(messages),
+            Err(error) => refused(WasiResponse::error(&error)),
+        }
+    };
+    if let Some(preset) = router.preset() {
+        response.headers.extend(preset.response_headers(origin.as_deref()));
+    }
+    response
+}
+
+/// A `text/event-stream` response sending each message as a `data:` event.
+pub fn event_stream(messages: Messages<Value>) -> StreamingResponse {
+    let events = messages.map(|message| Ok(format!("data: {}\n\n", message).into_bytes()));
+    let keep_alive = stream::unfold((), |()| async {
+        tokio::time::sleep(KEEP_ALIVE).await;
+        Some((Ok(b": keep-alive\n\n".to_vec()), ()))
+    });
+    // Ends with the messages; the keep-alives alone would run forever.
+    let body = stream::select(events.map(Some).chain(stream::once(async { None })), keep_alive.map(Some))
+        .take_while(|event| std::future::ready(event.is_some()))
+        .filter_map(std::future::ready);
+    StreamingResponse::new(body.boxed())
+        .with_content_type(EVENT_STREAM)
+        .with_header("Cache-Control", "no-cache")
+}
+
+fn refused(response: WasiResponse) -> StreamingResponse {
+    let body: Result<Vec<u8>, ResponseError> = Ok(response.body);
+    let mut streaming = StreamingResponse::new(stream::once(async move { body }).boxed()).with_status(response.status);
+    streaming.headers.extend(response.headers);
+    streaming
+}
+
+/// The `data` of each message event in a chunked event stream, as chunks
+/// arrive. `Err` items end the stream.
+pub(crate) fn events<S, B, E>(chunks: S) -> LocalBoxStream<'static, Result<String, String>>
+where
+    S: Stream<Item = Result<B, E>> + 'static,
+    B: AsRef<[u8]>,
+    E: fmt::Display,
+{
+    let mut parser = EventParser::default();
+    chunks
+        .flat_map(move |chunk| {
+            let events: Vec<_> = match chunk {
+                Ok(bytes) => parser.push(bytes.as_ref()).into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e.to_string())],
+            };
+            stream::iter(events)
+        })
+        .scan(false, |failed, event| {
+            let stop = *failed;
+            *failed = event.is_err();
+            std::future::ready((!stop).then_some(event))
+        })
+        .boxed_local()
+}
+
+/// Splits an event stream into events. Only the `data` of events without a
+/// type, or of type `message`, is kept, as `EventSource.onmessage` does.
+#[derive(Default)]
+struct EventParser {
+    line: Vec<u8>,
+    data: Vec<String>,
+    kind: Option<String>,
+}
+
+impl EventParser {
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        let mut events = Vec::new();
+        for &byte in chunk {
+            if byte != b'\n' {
+                self.line.push(byte);
+                continue;
+            }
+            let line = String::from_utf8_lossy(&self.line).trim_end_matches('\r').to_string();
+            self.line.clear();
+            if line.is_empty() {
+                let data = std::mem::take(&mut self.data);
+                if !data.is_empty() && self.kind.take().is_none_or(|kind| kind == "message") {
+                    events.push(data.join("\n"));
+                }
+                continue;
+            }
+            let (field, value) = line.split_once(':').unwrap_or((&line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "data" => self.data.push(value.to_string()),
+                "event" => self.kind = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// Whether a [`LiveSignal`] is receiving messages.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LiveStatus {
+    /// The stream is being opened.
+    Connecting,
+    /// Messages are arriving.
+    Open,
+    /// The server ended the stream.
+    Closed,
+    /// The stream could not be opened or was cut off.
+    Failed(String),
+}
+
+/// The latest message of a subscription as a signal, for Leptos views.
+///
+/// ```rust,ignore
+/// let todos = client.live::<TodoListFeed>(&ListParams { list: 1 });
+/// view! {
+///     <Show when=move || todos.status().get() != LiveStatus::Open>"Reconnecting…"</Show>
+///     <For each=move || todos.get().unwrap_or_default() key=|t| t.id let:todo>
+///         <li>{todo.title}</li>
+///     </For>
+/// }
+/// ```
+pub struct LiveSignal<T: Send + Sync + 'static> {
+    value: ArcRwSignal<Option<T>>,
+    status: ArcRwSignal<LiveStatus>,
+}
+
+impl<T: Send + Sync + 'static> Clone for LiveSignal<T> {
+    fn clone(&self) -> Self {
+        Self { value: self.value.clone(), status: self.status.clone() }
+    }
+}
+
+impl<T: Send + Sync + 'static> Default for LiveSignal<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send + Sync + 'static> LiveSignal<T> {
+    /// A signal with no message yet, `Connecting`.
+    pub fn new() -> Self {
+        Self { value: ArcRwSignal::new(None), status: ArcRwSignal::new(LiveStatus::Connecting) }
+    }
+
+    /// The latest message, subscribing the running effect.
+    pub fn get(&self) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.value.get()
+    }
+
+    /// Reads the latest message without cloning it.
+    pub fn with<R>(&self, f: impl FnOnce(&Option<T>) -> R) -> R {
+        self.value.with(f)
+    }
+
+    pub fn value(&self) -> Signal<Option<T>> {
+        self.value.clone().into()
+    }
+
+    pub fn status(&self) -> Signal<LiveStatus> {
+        self.status.clone().into()
+    }
+
+    /// Sets the signal to each message of `messages` until the stream ends or
+    /// fails. The last message stays after it does.
+    pub async fn follow(&self, messages: impl Stream<Item = Result<T, RouteError>>) {
+        self.status.set(LiveStatus::Open);
+        let mut messages = std::pin::pin!(messages);
+        while let Some(message) = messages.next().await {
+            match message {
+                Ok(message) => self.value.set(Some(message)),
+                Err(e) => return self.fail(&e),
+            }
+        }
+        self.status.set(LiveStatus::Closed);
+    }
+
+    pub(crate) fn fail(&self, error: &RouteError) {
+        tracing::warn!(error = %error, "live subscription failed");
+        self.status.set(LiveStatus::Failed(error.to_string()));
+    }
+}
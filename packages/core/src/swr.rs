@@ -0,0 +1,470 @@
+//! Stale-while-revalidate caching for data the client fetches.
+//!
+//! [`SwrCache`] holds the last value fetched for each key, in memory and
+//! optionally in a persistent [`SwrStore`] such as the browser's
+//! `localStorage`. [`SwrResource`] serves that value at once, then fetches a
+//! fresh one in the background when it is stale, exposing `is_stale` and
+//! `is_revalidating` as signals.
+//!
+//! Keys are route paths, optionally with a query, e.g. `/todos?page=2`.
+//! [`SwrCache::invalidate`] marks every key at or below a route stale and
+//! revalidates the resources showing them, which is what a mutation of that
+//! route calls; [`SwrCache::mutate`] does it after a mutation succeeds.
+
+use chrono::{DateTime, Utc};
+use leptos::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+/// Prefix of the keys a persistent store holds entries under.
+pub const STORE_PREFIX: &str = "montrs:swr:";
+
+/// A persistent copy of the cache that outlives the page, keyed by cache key.
+/// Entries are JSON text.
+pub trait SwrStore: Send + Sync + 'static {
+    fn get(&self, key: &str) -> Option<String>;
+    fn set(&self, key: &str, entry: &str);
+    fn remove(&self, key: &str);
+    /// Every key held, to find the ones a route invalidates.
+    fn keys(&self) -> Vec<String>;
+}
+
+impl<T: SwrStore> SwrStore for Arc<T> {
+    fn get(&self, key: &str) -> Option<String> {
+        (**self).get(key)
+    }
+
+    fn set(&self, key: &str, entry: &str) {
+        (**self).set(key, entry)
+    }
+
+    fn remove(&self, key: &str) {
+        (**self).remove(key)
+    }
+
+    fn keys(&self) -> Vec<String> {
+        (**self).keys()
+    }
+}
+
+/// Keeps entries in the process, e.g. to share them between caches in tests.
+#[derive(Default)]
+pub struct MemorySwrStore {
+    entries: Mutex<HashMap<String, String>>,
+}
+
+impl MemorySwrStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SwrStore for MemorySwrStore {
+    fn get(&self, key: &str) -> Option<String> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    fn set(&self, key: &str, entry: &str) {
+        self.entries.lock().unwrap().insert(key.to_string(), entry.to_string());
+    }
+
+    fn remove(&self, key: &str) {
+        self.entries.lock().unwrap().remove(key);
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.entries.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+/// Keeps entries in the browser's `localStorage` under [`STORE_PREFIX`], so
+/// a reload or a new tab starts from the last data seen. Storage errors,
+/// such as a full quota, leave the entry in memory only.
+#[cfg(feature = "local-storage")]
+pub struct LocalSwrStore {
+    // A JS handle, usable from the page's one thread.
+    storage: send_wrapper::SendWrapper<web_sys::Storage>,
+}
+
+#[cfg(feature = "local-storage")]
+impl LocalSwrStore {
+    /// The page's `localStorage`, or `None` outside a browser or when
+    /// storage is disabled.
+    pub fn open() -> Option<Self> {
+        if !cfg!(target_arch = "wasm32") {
+            return None;
+        }
+        let storage = web_sys::window()?.local_storage().ok()??;
+        Some(Self { storage: send_wrapper::SendWrapper::new(storage) })
+    }
+}
+
+#[cfg(feature = "local-storage")]
+impl SwrStore for LocalSwrStore {
+    fn get(&self, key: &str) -> Option<String> {
+        self.storage.get_item(&format!("{}{}", STORE_PREFIX, key)).ok().flatten()
+    }
+
+    fn set(&self, key: &str, entry: &str) {
+        let _ = self.storage.set_item(&format!("{}{}", STORE_PREFIX, key), entry);
+    }
+
+    fn remove(&self, key: &str) {
+        let _ = self.storage.remove_item(&format!("{}{}", STORE_PREFIX, key));
+    }
+
+    fn keys(&self) -> Vec<String> {
+        let len = self.storage.length().unwrap_or(0);
+        (0..len)
+            .filter_map(|i| self.storage.key(i).ok().flatten())
+            .filter_map(|key| key.strip_prefix(STORE_PREFIX).map(str::to_string))
+            .collect()
+    }
+}
+
+/// A cached value and when it was fetched.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cached<T> {
+    pub value: T,
+    pub fetched_at: DateTime<Utc>,
+    /// Older than the cache's max age, or invalidated since it was fetched.
+    pub stale: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Entry {
+    value: Value,
+    fetched_at: DateTime<Utc>,
+    #[serde(default)]
+    invalidated: bool,
+}
+
+type Listener = Arc<dyn Fn() + Send + Sync>;
+
+#[derive(Default)]
+struct CacheInner {
+    entries: Mutex<HashMap<String, Entry>>,
+    listeners: Mutex<Vec<(u64, String, Listener)>>,
+    next_listener: AtomicU64,
+}
+
+/// The values behind every [`SwrResource`] of an app. Cloning shares it;
+/// provide one as context at the root of the app.
+///
+/// ```rust,ignore
+/// let cache = SwrCache::new().with_max_age(Duration::from_secs(30));
+/// let cache = match LocalSwrStore::open() {
+///     Some(store) => cache.with_store(store),
+///     None => cache,
+/// };
+/// provide_context(cache);
+/// ```
+#[derive(Clone)]
+pub struct SwrCache {
+    inner: Arc<CacheInner>,
+    store: Option<Arc<dyn SwrStore>>,
+    max_age: Duration,
+}
+
+impl Default for SwrCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for SwrCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SwrCache")
+            .field("entries", &self.inner.entries.lock().unwrap().len())
+            .field("max_age", &self.max_age)
+            .finish()
+    }
+}
+
+impl SwrCache {
+    /// An in-memory cache whose values are stale as soon as they are served,
+    /// so every resource revalidates when it is created.
+    pub fn new() -> Self {
+        Self { inner: Arc::default(), store: None, max_age: Duration::ZERO }
+    }
+
+    /// How long a fetched value counts as fresh. Fresh values are served
+    /// without revalidating.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Also keeps entries in `store`, and reads the ones missing from memory
+    /// from it.
+    pub fn with_store(mut self, store: impl SwrStore) -> Self {
+        self.store = Some(Arc::new(store));
+        self
+    }
+
+    /// The value cached for `key`, if there is one of type `T`.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<Cached<T>> {
+        let entry = self.entry(key)?;
+        let stale = entry.invalidated || self.expired(entry.fetched_at);
+        let value = serde_json::from_value(entry.value).ok()?;
+        Some(Cached { value, fetched_at: entry.fetched_at, stale })
+    }
+
+    fn entry(&self, key: &str) -> Option<Entry> {
+        if let Some(entry) = self.inner.entries.lock().unwrap().get(key) {
+            return Some(entry.clone());
+        }
+        let entry: Entry = serde_json::from_str(&self.store.as_ref()?.get(key)?).ok()?;
+        self.inner.entries.lock().unwrap().insert(key.to_string(), entry.clone());
+        Some(entry)
+    }
+
+    fn expired(&self, fetched_at: DateTime<Utc>) -> bool {
+        let age = Utc::now().signed_duration_since(fetched_at).to_std().unwrap_or_default();
+        age >= self.max_age
+    }
+
+    /// Caches `value` for `key` as fetched now.
+    pub fn put<T: Serialize>(&self, key: &str, value: &T) {
+        let Ok(value) = serde_json::to_value(value) else {
+            tracing::warn!(key, "value not cached: it does not serialize to JSON");
+            return;
+        };
+        self.write(key, Entry { value, fetched_at: Utc::now(), invalidated: false });
+    }
+
+    fn write(&self, key: &str, entry: Entry) {
+        if let (Some(store), Ok(text)) = (&self.store, serde_json::to_string(&entry)) {
+            store.set(key, &text);
+        }
+        self.inner.entries.lock().unwrap().insert(key.to_string(), entry);
+    }
+
+    /// Drops the value cached for `key`.
+    pub fn remove(&self, key: &str) {
+        self.inner.entries.lock().unwrap().remove(key);
+        if let Some(store) = &self.store {
+            store.remove(key);
+        }
+    }
+
+    /// Marks every key at or below `route` stale, e.g. `/todos` covers
+    /// `/todos?page=2` and `/todos/42`, and revalidates the resources showing
+    /// them. Returns the number of cached keys marked.
+    pub fn invalidate(&self, route: &str) -> usize {
+        let mut keys: Vec<String> = self.inner.entries.lock().unwrap().keys().cloned().collect();
+        if let Some(store) = &self.store {
+            keys.extend(store.keys());
+        }
+        keys.sort();
+        keys.dedup();
+
+        let mut marked = 0;
+        for key in keys.iter().filter(|key| covers(route, key)) {
+            if let Some(mut entry) = self.entry(key) {
+                entry.invalidated = true;
+                self.write(key, entry);
+                marked += 1;
+            }
+        }
+
+        let listeners: Vec<Listener> = {
+            let listeners = self.inner.listeners.lock().unwrap();
+            listeners.iter().filter(|(_, key, _)| covers(route, key)).map(|(_, _, f)| f.clone()).collect()
+        };
+        for listener in listeners {
+            listener();
+        }
+        marked
+    }
+
+    /// Runs `mutation` and, when it succeeds, invalidates `route`.
+    ///
+    /// ```rust,ignore
+    /// cache.mutate("/todos", add_todo(title)).await?;
+    /// ```
+    pub async fn mutate<R, E>(&self, route: &str, mutation: impl Future<Output = Result<R, E>>) -> Result<R, E> {
+        let result = mutation.await?;
+        self.invalidate(route);
+        Ok(result)
+    }
+
+    /// Calls `f` whenever a route covering `key` is invalidated, until the
+    /// returned subscription is dropped.
+    pub fn subscribe(&self, key: &str, f: impl Fn() + Send + Sync + 'static) -> SwrSubscription {
+        let id = self.inner.next_listener.fetch_add(1, Ordering::Relaxed);
+        self.inner.listeners.lock().unwrap().push((id, key.to_string(), Arc::new(f)));
+        SwrSubscription { cache: Arc::downgrade(&self.inner), id }
+    }
+}
+
+/// Whether invalidating `route` covers `key`.
+fn covers(route: &str, key: &str) -> bool {
+    let path = key.split(['?', '#']).next().unwrap_or(key);
+    let route = route.trim_end_matches('/');
+    route.is_empty() || path == route || path.strip_prefix(route).is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Keeps a [`SwrCache::subscribe`] listener registered while it is alive.
+pub struct SwrSubscription {
+    cache: Weak<CacheInner>,
+    id: u64,
+}
+
+impl Drop for SwrSubscription {
+    fn drop(&mut self) {
+        if let Some(cache) = self.cache.upgrade() {
+            cache.listeners.lock().unwrap().retain(|(id, _, _)| *id != self.id);
+        }
+    }
+}
+
+/// Data fetched on the client, served from a [`SwrCache`] at once and
+/// refreshed in the background when stale.
+///
+/// ```rust,ignore
+/// let todos = SwrResource::new(&expect_context::<SwrCache>(), "/todos", || fetch_todos());
+/// view! {
+///     <Show when=move || todos.is_revalidating().get()>"Refreshing…"</Show>
+///     <For each=move || todos.get().unwrap_or_default() key=|t| t.id let:todo>
+///         <li>{todo.title}</li>
+///     </For>
+/// }
+/// ```
+///
+/// Like `LocalResource`, it only fetches in the browser; during server
+/// rendering it serves what is cached, if anything. A failed fetch keeps the
+/// stale value and logs the error.
+pub struct SwrResource<T: Send + Sync + 'static> {
+    key: Arc<str>,
+    cache: SwrCache,
+    data: ArcRwSignal<Option<T>>,
+    stale: ArcRwSignal<bool>,
+    revalidating: ArcRwSignal<bool>,
+    revalidate: Arc<dyn Fn() + Send + Sync>,
+    _subscription: Arc<SwrSubscription>,
+}
+
+impl<T: Send + Sync + 'static> Clone for SwrResource<T> {
+    fn clone(&self) -> Self {
+        Self {
+            key: self.key.clone(),
+            cache: self.cache.clone(),
+            data: self.data.clone(),
+            stale: self.stale.clone(),
+            revalidating: self.revalidating.clone(),
+            revalidate: self.revalidate.clone(),
+            _subscription: self._subscription.clone(),
+        }
+    }
+}
+
+impl<T> SwrResource<T>
+where
+    T: Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
+{
+    /// Serves the value cached for `key` and calls `fetcher` in the
+    /// background when there is none or it is stale, and again whenever a
+    /// route covering `key` is invalidated.
+    pub fn new<F, Fut, E>(cache: &SwrCache, key: impl Into<String>, fetcher: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, E>> + 'static,
+        E: fmt::Display + 'static,
+    {
+        let key: Arc<str> = key.into().into();
+        let cached = cache.get::<T>(&key);
+        let needs_fetch = cached.as_ref().is_none_or(|c| c.stale);
+        let data = ArcRwSignal::new(cached.as_ref().map(|c| c.value.clone()));
+        let stale = ArcRwSignal::new(cached.is_some_and(|c| c.stale));
+        let revalidating = ArcRwSignal::new(false);
+
+        let revalidate: Arc<dyn Fn() + Send + Sync> = Arc::new({
+            let (key, cache, data, stale, revalidating) =
+                (key.clone(), cache.clone(), data.clone(), stale.clone(), revalidating.clone());
+            let fetcher = Arc::new(fetcher);
+            move || {
+                if !cfg!(target_arch = "wasm32") || revalidating.get_untracked() {
+                    return;
+                }
+                if data.with_untracked(Option::is_some) {
+                    stale.set(true);
+                }
+                revalidating.set(true);
+                let (key, cache, data, stale, revalidating, fetcher) =
+                    (key.clone(), cache.clone(), data.clone(), stale.clone(), revalidating.clone(), fetcher.clone());
+                leptos::task::spawn_local(async move {
+                    match fetcher().await {
+                        Ok(value) => {
+                            cache.put(&key, &value);
+                            data.set(Some(value));
+                            stale.set(false);
+                        }
+                        Err(e) => tracing::warn!(key = %key, error = %e, "revalidation failed; serving stale data"),
+                    }
+                    revalidating.set(false);
+                });
+            }
+        });
+
+        let subscription = cache.subscribe(&key, {
+            let revalidate = revalidate.clone();
+            move || revalidate()
+        });
+        if needs_fetch {
+            revalidate();
+        }
+        Self {
+            key,
+            cache: cache.clone(),
+            data,
+            stale,
+            revalidating,
+            revalidate,
+            _subscription: Arc::new(subscription),
+        }
+    }
+
+    /// The current value, subscribing the running effect. `None` until the
+    /// first fetch finishes when nothing was cached.
+    pub fn get(&self) -> Option<T> {
+        self.data.get()
+    }
+
+    /// Reads the current value without cloning it.
+    pub fn with<R>(&self, f: impl FnOnce(&Option<T>) -> R) -> R {
+        self.data.with(f)
+    }
+
+    /// Whether the value shown is older than the cache's max age or was
+    /// invalidated, and a fresh one has not arrived yet.
+    pub fn is_stale(&self) -> Signal<bool> {
+        self.stale.clone().into()
+    }
+
+    /// Whether a fetch is in flight.
+    pub fn is_revalidating(&self) -> Signal<bool> {
+        self.revalidating.clone().into()
+    }
+
+    /// Fetches a fresh value in the background, keeping the current one
+    /// until it arrives.
+    pub fn revalidate(&self) {
+        (self.revalidate)()
+    }
+
+    /// Replaces the value locally and in the cache, e.g. optimistically
+    /// after a mutation, without fetching.
+    pub fn mutate(&self, value: T) {
+        self.cache.put(&self.key, &value);
+        self.data.set(Some(value));
+        self.stale.set(false);
+    }
+}
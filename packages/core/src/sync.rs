@@ -0,0 +1,717 @@
+//! montrs-core/src/sync.rs: Offline-first data sync.
+//! Desktop and mobile apps keep the rows of designated models in a local
+//! [`SyncStore`], so they read and write them without a connection, and a
+//! [`SyncEngine`] exchanges changes with the server in the background.
+//!
+//! Every row carries a [`VersionVector`]: one counter per replica that wrote
+//! it. A local write bumps the replica's counter and marks the row dirty; a
+//! sync pushes the dirty rows to the route [`SyncPlate`] registers and pulls
+//! the rows the server saved since the last sync. Versions tell whether one
+//! side's row already includes the other's changes; when neither does, the
+//! engine asks the model's resolver, last writer wins by default. The status,
+//! pending changes and last sync are signals, for an "offline" badge.
+//!
+//! `montrs_orm::sync::SyncTable` keeps the rows in SQLite on the device, and
+//! in the application database on the server.
+
+use crate::router::{Route, RouteAction, RouteError, RouteLoader, RouteParams, RouteView};
+use crate::rpc::RpcClient;
+use crate::{AppConfig, Plate, PlateContext, RouteContext, Router};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use leptos::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How many rows a sync pushes or pulls at once.
+const BATCH: usize = 100;
+
+/// Per-replica counts of the writes a row has seen.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct VersionVector(BTreeMap<String, u64>);
+
+/// How two versions of a row relate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Causality {
+    Equal,
+    /// The other version includes every write of this one, and more.
+    Before,
+    /// This version includes every write of the other, and more.
+    After,
+    /// Each has writes the other has not seen: a conflict.
+    Concurrent,
+}
+
+impl VersionVector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The writes of `replica` seen.
+    pub fn get(&self, replica: &str) -> u64 {
+        self.0.get(replica).copied().unwrap_or(0)
+    }
+
+    /// Counts a write by `replica`.
+    pub fn bump(&mut self, replica: &str) {
+        *self.0.entry(replica.to_string()).or_insert(0) += 1;
+    }
+
+    /// Takes the highest count of each replica, so the result includes both.
+    pub fn merge(&mut self, other: &VersionVector) {
+        for (replica, count) in &other.0 {
+            let entry = self.0.entry(replica.clone()).or_insert(0);
+            *entry = (*entry).max(*count);
+        }
+    }
+
+    pub fn compare(&self, other: &VersionVector) -> Causality {
+        let (mut behind, mut ahead) = (false, false);
+        for replica in self.0.keys().chain(other.0.keys()) {
+            let (mine, theirs) = (self.get(replica), other.get(replica));
+            behind |= mine < theirs;
+            ahead |= mine > theirs;
+        }
+        match (behind, ahead) {
+            (false, false) => Causality::Equal,
+            (true, false) => Causality::Before,
+            (false, true) => Causality::After,
+            (true, true) => Causality::Concurrent,
+        }
+    }
+}
+
+/// One row of a synced model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncRow {
+    pub model: String,
+    pub id: String,
+    /// The row's fields; `null` once deleted.
+    pub data: Value,
+    /// Deleted rows are kept, so the deletion syncs like any other write.
+    pub deleted: bool,
+    pub version: VersionVector,
+    /// Where the row is in the store's change order, set when it is saved.
+    /// The server's is the cursor clients pull from.
+    #[serde(default)]
+    pub seq: u64,
+    /// Written locally and not yet accepted by the server.
+    #[serde(default)]
+    pub dirty: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Keeps synced rows and the change order. On a device it holds the local
+/// copy; on the server, the copy clients sync with.
+#[async_trait]
+pub trait SyncStore: Send + Sync + 'static {
+    async fn get(&self, model: &str, id: &str) -> anyhow::Result<Option<SyncRow>>;
+
+    /// Saves `row` over the one with the same model and ID, giving it the
+    /// next sequence number, which is returned.
+    async fn save(&self, row: &SyncRow) -> anyhow::Result<u64>;
+
+    /// The rows of `model` not deleted, in ID order.
+    async fn rows(&self, model: &str) -> anyhow::Result<Vec<SyncRow>>;
+
+    /// Rows saved after sequence number `since`, in order.
+    async fn changes(&self, since: u64, limit: usize) -> anyhow::Result<Vec<SyncRow>>;
+
+    /// Dirty rows, in the order they were written.
+    async fn dirty(&self, limit: usize) -> anyhow::Result<Vec<SyncRow>>;
+
+    /// The server sequence number the last sync pulled up to.
+    async fn cursor(&self) -> anyhow::Result<u64>;
+
+    async fn set_cursor(&self, cursor: u64) -> anyhow::Result<()>;
+}
+
+#[async_trait]
+impl<T: SyncStore> SyncStore for Arc<T> {
+    async fn get(&self, model: &str, id: &str) -> anyhow::Result<Option<SyncRow>> {
+        (**self).get(model, id).await
+    }
+
+    async fn save(&self, row: &SyncRow) -> anyhow::Result<u64> {
+        (**self).save(row).await
+    }
+
+    async fn rows(&self, model: &str) -> anyhow::Result<Vec<SyncRow>> {
+        (**self).rows(model).await
+    }
+
+    async fn changes(&self, since: u64, limit: usize) -> anyhow::Result<Vec<SyncRow>> {
+        (**self).changes(since, limit).await
+    }
+
+    async fn dirty(&self, limit: usize) -> anyhow::Result<Vec<SyncRow>> {
+        (**self).dirty(limit).await
+    }
+
+    async fn cursor(&self) -> anyhow::Result<u64> {
+        (**self).cursor().await
+    }
+
+    async fn set_cursor(&self, cursor: u64) -> anyhow::Result<()> {
+        (**self).set_cursor(cursor).await
+    }
+}
+
+/// A store that lives as long as the process, for tests and development.
+#[derive(Default)]
+pub struct MemorySyncStore {
+    state: Mutex<MemoryState>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    rows: HashMap<(String, String), SyncRow>,
+    seq: u64,
+    cursor: u64,
+}
+
+impl MemorySyncStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MemoryState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn sorted(&self, filter: impl Fn(&SyncRow) -> bool, limit: usize) -> Vec<SyncRow> {
+        let mut rows: Vec<_> = self.state().rows.values().filter(|r| filter(r)).cloned().collect();
+        rows.sort_by_key(|r| r.seq);
+        rows.truncate(limit);
+        rows
+    }
+}
+
+#[async_trait]
+impl SyncStore for MemorySyncStore {
+    async fn get(&self, model: &str, id: &str) -> anyhow::Result<Option<SyncRow>> {
+        Ok(self.state().rows.get(&(model.to_string(), id.to_string())).cloned())
+    }
+
+    async fn save(&self, row: &SyncRow) -> anyhow::Result<u64> {
+        let mut state = self.state();
+        state.seq += 1;
+        let row = SyncRow { seq: state.seq, ..row.clone() };
+        state.rows.insert((row.model.clone(), row.id.clone()), row);
+        Ok(state.seq)
+    }
+
+    async fn rows(&self, model: &str) -> anyhow::Result<Vec<SyncRow>> {
+        let mut rows: Vec<_> = self.state().rows.values().filter(|r| r.model == model && !r.deleted).cloned().collect();
+        rows.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(rows)
+    }
+
+    async fn changes(&self, since: u64, limit: usize) -> anyhow::Result<Vec<SyncRow>> {
+        Ok(self.sorted(|r| r.seq > since, limit))
+    }
+
+    async fn dirty(&self, limit: usize) -> anyhow::Result<Vec<SyncRow>> {
+        Ok(self.sorted(|r| r.dirty, limit))
+    }
+
+    async fn cursor(&self) -> anyhow::Result<u64> {
+        Ok(self.state().cursor)
+    }
+
+    async fn set_cursor(&self, cursor: u64) -> anyhow::Result<()> {
+        self.state().cursor = cursor;
+        Ok(())
+    }
+}
+
+/// Two versions of a row written without seeing each other.
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    /// The row on this device, with its unpushed writes.
+    pub local: SyncRow,
+    /// The row on the server.
+    pub remote: SyncRow,
+}
+
+/// What a resolver keeps of a [`Conflict`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    Local,
+    Remote,
+    /// New fields combining both, e.g. a union of two lists.
+    Merged(Value),
+}
+
+type Resolver = Arc<dyn Fn(&Conflict) -> Resolution + Send + Sync>;
+
+/// The default resolver: the row written last wins, the server's on a tie.
+pub fn last_writer_wins(conflict: &Conflict) -> Resolution {
+    match conflict.local.updated_at > conflict.remote.updated_at {
+        true => Resolution::Local,
+        false => Resolution::Remote,
+    }
+}
+
+/// Where the engine is, for the UI.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "status", content = "error")]
+pub enum SyncStatus {
+    #[default]
+    Idle,
+    Syncing,
+    /// The last sync could not reach the server; changes wait locally.
+    Offline,
+    /// The server or the store answered the last sync with an error.
+    Failed(String),
+}
+
+/// What one [`SyncEngine::sync`] did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Dirty rows sent to the server.
+    pub pushed: usize,
+    /// Rows the server sent that changed the local store.
+    pub pulled: usize,
+    /// Conflicts passed to a resolver.
+    pub conflicts: usize,
+}
+
+/// Input of the sync action.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SyncRequest {
+    /// The server sequence number the client pulled up to.
+    pub since: u64,
+    /// The client's dirty rows.
+    pub changes: Vec<SyncRow>,
+}
+
+/// Output of the sync route.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SyncResponse {
+    /// The server's row for each pushed change: the change itself when it was
+    /// saved, the server's own when it is newer or concurrent.
+    pub accepted: Vec<SyncRow>,
+    /// Rows saved after `since`, in order.
+    pub changes: Vec<SyncRow>,
+    /// The sequence number to pull from next time.
+    pub cursor: u64,
+    /// Whether more changes are waiting past `cursor`.
+    pub more: bool,
+}
+
+/// Keeps the rows of designated models on the device and syncs them with the
+/// server's [`SyncPlate`].
+///
+/// ```rust,ignore
+/// let local = SyncTable::new(SqliteBackend::new(app_data_dir.join("sync.db"))?);
+/// local.create_table().await?;
+/// let engine = SyncEngine::new(device_id, local, RpcClient::<AppConfig>::http(server_url))
+///     .with_model("todos")
+///     .with_resolver("todos", |conflict| Resolution::Merged(merge_todos(conflict)));
+/// leptos::task::spawn_local({
+///     let engine = engine.clone();
+///     async move { engine.run(Duration::from_secs(30)).await }
+/// });
+///
+/// engine.put("todos", &todo.id, &todo).await?;
+/// let offline = move || engine.status().get() == SyncStatus::Offline;
+/// ```
+pub struct SyncEngine<C: AppConfig> {
+    replica: String,
+    store: Arc<dyn SyncStore>,
+    client: RpcClient<C>,
+    path: String,
+    models: Vec<String>,
+    resolvers: HashMap<String, Resolver>,
+    status: ArcRwSignal<SyncStatus>,
+    pending: ArcRwSignal<usize>,
+    last_synced: ArcRwSignal<Option<DateTime<Utc>>>,
+}
+
+impl<C: AppConfig> Clone for SyncEngine<C> {
+    fn clone(&self) -> Self {
+        Self {
+            replica: self.replica.clone(),
+            store: self.store.clone(),
+            client: self.client.clone(),
+            path: self.path.clone(),
+            models: self.models.clone(),
+            resolvers: self.resolvers.clone(),
+            status: self.status.clone(),
+            pending: self.pending.clone(),
+            last_synced: self.last_synced.clone(),
+        }
+    }
+}
+
+impl<C: AppConfig> SyncEngine<C> {
+    /// `replica` names this device in version vectors; it must stay the same
+    /// across launches and differ between devices, e.g. a UUID kept in the
+    /// app's data directory.
+    pub fn new(replica: impl Into<String>, store: impl SyncStore, client: RpcClient<C>) -> Self {
+        Self {
+            replica: replica.into(),
+            store: Arc::new(store),
+            client,
+            path: "/sync".to_string(),
+            models: Vec::new(),
+            resolvers: HashMap::new(),
+            status: ArcRwSignal::new(SyncStatus::Idle),
+            pending: ArcRwSignal::new(0),
+            last_synced: ArcRwSignal::new(None),
+        }
+    }
+
+    /// Keeps `model` on the device. Writes to other models are refused.
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.models.push(model.into());
+        self
+    }
+
+    /// Resolves conflicting rows of `model` with `resolver` instead of
+    /// [`last_writer_wins`].
+    pub fn with_resolver(
+        mut self,
+        model: impl Into<String>,
+        resolver: impl Fn(&Conflict) -> Resolution + Send + Sync + 'static,
+    ) -> Self {
+        self.resolvers.insert(model.into(), Arc::new(resolver));
+        self
+    }
+
+    /// Where the server's [`SyncPlate`] is mounted (default: `/sync`).
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    pub fn store(&self) -> &Arc<dyn SyncStore> {
+        &self.store
+    }
+
+    pub fn status(&self) -> Signal<SyncStatus> {
+        self.status.clone().into()
+    }
+
+    /// Local writes the server has not accepted yet, as of the last write or sync.
+    pub fn pending(&self) -> Signal<usize> {
+        self.pending.clone().into()
+    }
+
+    /// When a sync last completed.
+    pub fn last_synced(&self) -> Signal<Option<DateTime<Utc>>> {
+        self.last_synced.clone().into()
+    }
+
+    /// Row `id` of `model`, unless it does not exist or was deleted.
+    pub async fn get<T: DeserializeOwned>(&self, model: &str, id: &str) -> anyhow::Result<Option<T>> {
+        match self.store.get(model, id).await? {
+            Some(row) if !row.deleted => Ok(Some(serde_json::from_value(row.data)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// The rows of `model`, in ID order.
+    pub async fn list<T: DeserializeOwned>(&self, model: &str) -> anyhow::Result<Vec<T>> {
+        let rows = self.store.rows(model).await?;
+        Ok(rows.into_iter().map(|row| serde_json::from_value(row.data)).collect::<Result<_, _>>()?)
+    }
+
+    /// Writes row `id` of `model` locally; the next sync pushes it.
+    pub async fn put(&self, model: &str, id: &str, data: &impl Serialize) -> anyhow::Result<()> {
+        self.write(model, id, serde_json::to_value(data)?, false).await
+    }
+
+    /// Deletes row `id` of `model` locally; the next sync pushes the deletion.
+    pub async fn delete(&self, model: &str, id: &str) -> anyhow::Result<()> {
+        self.write(model, id, Value::Null, true).await
+    }
+
+    async fn write(&self, model: &str, id: &str, data: Value, deleted: bool) -> anyhow::Result<()> {
+        if !self.models.iter().any(|m| m == model) {
+            anyhow::bail!("model `{}` is not synced: add it with SyncEngine::with_model", model);
+        }
+        let mut version = self.store.get(model, id).await?.map(|row| row.version).unwrap_or_default();
+        version.bump(&self.replica);
+        let row = SyncRow {
+            model: model.to_string(),
+            id: id.to_string(),
+            data,
+            deleted,
+            version,
+            seq: 0,
+            dirty: true,
+            updated_at: Utc::now(),
+        };
+        self.store.save(&row).await?;
+        self.refresh_pending().await
+    }
+
+    /// Pushes dirty rows and pulls the server's changes, a batch at a time,
+    /// until none are left either way. Rows written while the sync runs may
+    /// wait for the next one.
+    pub async fn sync(&self) -> anyhow::Result<SyncReport> {
+        self.status.set(SyncStatus::Syncing);
+        let result = self.exchange().await;
+        let status = match &result {
+            Ok(_) => {
+                self.last_synced.set(Some(Utc::now()));
+                SyncStatus::Idle
+            }
+            Err(e) if matches!(e.downcast_ref::<RouteError>(), Some(RouteError::External(_))) => SyncStatus::Offline,
+            Err(e) => SyncStatus::Failed(e.to_string()),
+        };
+        self.status.set(status);
+        let _ = self.refresh_pending().await;
+        result
+    }
+
+    /// Syncs every `interval` until the future is dropped. Failures are
+    /// logged and shown in [`status`](Self::status); local writes keep
+    /// working while offline.
+    pub async fn run(&self, interval: Duration) {
+        loop {
+            if let Err(e) = self.sync().await {
+                tracing::warn!(replica = %self.replica, error = %e, "sync failed");
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    async fn exchange(&self) -> anyhow::Result<SyncReport> {
+        let mut report = SyncReport::default();
+        loop {
+            // Accepted rows are clean once pulled, so each round pushes the next batch.
+            let changes = self.store.dirty(BATCH).await?;
+            let pushed = changes.len();
+            report.pushed += pushed;
+            let request = SyncRequest { since: self.store.cursor().await?, changes };
+            let response: SyncResponse = self.client.act_path(&self.path, Value::Null, &request).await?;
+            for remote in response.accepted.into_iter().chain(response.changes) {
+                self.pull(remote, &mut report).await?;
+            }
+            self.store.set_cursor(response.cursor).await?;
+            if pushed < BATCH && !response.more {
+                return Ok(report);
+            }
+        }
+    }
+
+    /// Applies a row from the server to the local store.
+    async fn pull(&self, remote: SyncRow, report: &mut SyncReport) -> anyhow::Result<()> {
+        let remote = SyncRow { dirty: false, ..remote };
+        let Some(local) = self.store.get(&remote.model, &remote.id).await? else {
+            report.pulled += 1;
+            self.store.save(&remote).await?;
+            return Ok(());
+        };
+        match local.version.compare(&remote.version) {
+            // The server accepted this row as it is now.
+            Causality::Equal if local.dirty => {
+                self.store.save(&SyncRow { dirty: false, ..local }).await?;
+            }
+            Causality::Equal | Causality::After => {}
+            Causality::Before => {
+                report.pulled += 1;
+                self.store.save(&remote).await?;
+            }
+            Causality::Concurrent => {
+                report.conflicts += 1;
+                let conflict = Conflict { local, remote };
+                let resolution = match self.resolvers.get(&conflict.local.model) {
+                    Some(resolver) => resolver(&conflict),
+                    None => last_writer_wins(&conflict),
+                };
+                let Conflict { local, remote } = conflict;
+                let (data, deleted) = match resolution {
+                    Resolution::Remote => {
+                        report.pulled += 1;
+                        self.store.save(&remote).await?;
+                        return Ok(());
+                    }
+                    Resolution::Local => (local.data, local.deleted),
+                    Resolution::Merged(data) => (data, false),
+                };
+                // A version past both, pushed next time so the server takes it.
+                let mut version = local.version;
+                version.merge(&remote.version);
+                version.bump(&self.replica);
+                let resolved = SyncRow { data, deleted, version, dirty: true, updated_at: Utc::now(), ..remote };
+                self.store.save(&resolved).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn refresh_pending(&self) -> anyhow::Result<()> {
+        self.pending.set(self.store.dirty(usize::MAX).await?.len());
+        Ok(())
+    }
+}
+
+/// Registers the route [`SyncEngine`]s sync with, at `/sync` unless
+/// [`with_path`](Self::with_path) says otherwise. Its action saves the pushed
+/// rows that are newer than the server's and answers with the changes since
+/// the client's cursor; its loader answers with the changes since `?since=`,
+/// for clients that only read.
+///
+/// Every client sees every row of the designated models, so keep per-user
+/// data out of them.
+pub struct SyncPlate {
+    server: SyncServer,
+    path: String,
+}
+
+#[derive(Clone)]
+struct SyncServer {
+    store: Arc<dyn SyncStore>,
+    models: Arc<Vec<String>>,
+}
+
+impl SyncPlate {
+    pub fn new(store: impl SyncStore) -> Self {
+        Self { server: SyncServer { store: Arc::new(store), models: Arc::new(Vec::new()) }, path: "/sync".to_string() }
+    }
+
+    /// Accepts rows of `model` from clients.
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.server.models).push(model.into());
+        self
+    }
+
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> Plate<C> for SyncPlate {
+    fn name(&self) -> &'static str {
+        "sync"
+    }
+
+    fn description(&self) -> &'static str {
+        "Offline-first sync: accepts pushed rows and serves changes since a cursor"
+    }
+
+    async fn init(&self, _ctx: &mut PlateContext<C>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    fn register_routes(&self, router: &mut Router<C>) {
+        let path = crate::router::intern(self.path.clone());
+        router.register_at(path, SyncRoute(self.server.clone()));
+    }
+}
+
+impl SyncServer {
+    async fn exchange(&self, request: SyncRequest) -> Result<SyncResponse, RouteError> {
+        let mut accepted = Vec::with_capacity(request.changes.len());
+        for change in request.changes {
+            if !self.models.contains(&change.model) {
+                return Err(RouteError::ValidationFailed(format!("model `{}` is not synced", change.model)));
+            }
+            let current = self.store.get(&change.model, &change.id).await.map_err(internal)?;
+            let newer = current.as_ref().is_none_or(|c| change.version.compare(&c.version) == Causality::After);
+            match current {
+                Some(current) if !newer => accepted.push(current),
+                _ => {
+                    let mut change = SyncRow { dirty: false, ..change };
+                    change.seq = self.store.save(&change).await.map_err(internal)?;
+                    accepted.push(change);
+                }
+            }
+        }
+        let mut response = self.changes(request.since).await?;
+        response.accepted = accepted;
+        Ok(response)
+    }
+
+    async fn changes(&self, since: u64) -> Result<SyncResponse, RouteError> {
+        let rows = self.store.changes(since, BATCH).await.map_err(internal)?;
+        let cursor = rows.last().map_or(since, |row| row.seq);
+        let more = rows.len() == BATCH;
+        let changes = rows.into_iter().filter(|row| self.models.contains(&row.model)).collect();
+        Ok(SyncResponse { accepted: Vec::new(), changes, cursor, more })
+    }
+}
+
+fn internal(e: anyhow::Error) -> RouteError {
+    RouteError::InternalError(e.to_string())
+}
+
+/// Route params of the sync route: the cursor its loader pulls from.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SyncParams {
+    #[serde(default, deserialize_with = "crate::param::deserialize")]
+    pub since: u64,
+}
+
+impl RouteParams for SyncParams {
+    fn params() -> Vec<crate::ParamSpec> {
+        vec![crate::ParamSpec::of::<u64>("since", false)]
+    }
+}
+
+struct NoView;
+
+impl RouteView for NoView {
+    fn render(&self) -> impl leptos::prelude::IntoView {}
+}
+
+#[derive(Clone)]
+struct SyncRoute(SyncServer);
+
+impl<C: AppConfig> Route<C> for SyncRoute {
+    type Params = SyncParams;
+    type Loader = Self;
+    type Action = Self;
+    type View = NoView;
+
+    fn path() -> &'static str {
+        "/sync"
+    }
+    fn loader(&self) -> Self {
+        self.clone()
+    }
+    fn action(&self) -> Self {
+        self.clone()
+    }
+    fn view(&self) -> NoView {
+        NoView
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> RouteLoader<SyncParams, C> for SyncRoute {
+    type Output = SyncResponse;
+
+    async fn load(&self, _ctx: RouteContext<'_, C>, params: SyncParams) -> Result<SyncResponse, RouteError> {
+        self.0.changes(params.since).await
+    }
+
+    fn description(&self) -> &'static str {
+        "Synced rows saved after a cursor, in order"
+    }
+}
+
+#[async_trait]
+impl<C: AppConfig> RouteAction<SyncParams, C> for SyncRoute {
+    type Input = SyncRequest;
+    type Output = SyncResponse;
+
+    async fn act(&self, _ctx: RouteContext<'_, C>, _params: SyncParams, input: SyncRequest) -> Result<SyncResponse, RouteError> {
+        self.0.exchange(input).await
+    }
+
+    fn description(&self) -> &'static str {
+        "Saves the pushed rows newer than the server's and answers with the changes since the client's cursor"
+    }
+}
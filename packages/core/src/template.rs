@@ -0,0 +1,183 @@
+//! montrs-core/src/template.rs: Server-side HTML templates for non-Leptos pages.
+//! This file wraps minijinja so plates can render emails, error pages, and admin
+//! endpoints from a loader without a WASM bundle. Output is HTML-escaped by default
+//! and shared layouts are expressed with `{% extends %}` / `{% block %}`.
+
+use crate::AgentError;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Errors that can occur while loading or rendering templates.
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    #[error("Template not found: {0}")]
+    NotFound(String),
+    #[error("Template syntax error: {0}")]
+    Syntax(String),
+    #[error("Template render error: {0}")]
+    Render(String),
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl From<minijinja::Error> for TemplateError {
+    fn from(err: minijinja::Error) -> Self {
+        let detail = err.to_string();
+        match err.kind() {
+            minijinja::ErrorKind::TemplateNotFound => TemplateError::NotFound(detail),
+            minijinja::ErrorKind::SyntaxError => TemplateError::Syntax(detail),
+            _ => TemplateError::Render(detail),
+        }
+    }
+}
+
+impl AgentError for TemplateError {
+    fn error_code(&self) -> &'static str {
+        match self {
+            TemplateError::NotFound(_) => "TEMPLATE_NOT_FOUND",
+            TemplateError::Syntax(_) => "TEMPLATE_SYNTAX",
+            TemplateError::Render(_) => "TEMPLATE_RENDER",
+            TemplateError::Io(_) => "TEMPLATE_IO",
+        }
+    }
+
+    fn explanation(&self) -> String {
+        match self {
+            TemplateError::NotFound(n) => format!("No template named '{}' has been registered with the TemplateEngine.", n),
+            TemplateError::Syntax(e) => format!("A template failed to parse: {}", e),
+            TemplateError::Render(e) => format!("A template parsed correctly but failed while rendering: {}", e),
+            TemplateError::Io(e) => format!("Reading templates from disk failed: {}", e),
+        }
+    }
+
+    fn suggested_fixes(&self) -> Vec<String> {
+        match self {
+            TemplateError::NotFound(_) => vec![
+                "Register the template with `add_template` or place it under the directory passed to `load_dir`.".to_string(),
+                "Template names include their relative path, e.g. 'emails/welcome.html'.".to_string(),
+            ],
+            TemplateError::Syntax(_) => vec!["Check for unclosed `{% block %}` / `{{ }}` tags near the reported line.".to_string()],
+            TemplateError::Render(_) => vec!["Ensure the context passed to `render` contains every variable the template uses.".to_string()],
+            TemplateError::Io(_) => vec!["Verify the template directory exists and is readable.".to_string()],
+        }
+    }
+
+    fn subsystem(&self) -> &'static str {
+        "template"
+    }
+}
+
+/// A rendered HTML document. Serializes as a plain string so it can be
+/// returned directly as a Loader output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Html(pub String);
+
+impl Html {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl std::fmt::Display for Html {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A registry of server-side templates.
+///
+/// Every template is HTML-escaped unless its name ends in `.txt`, which keeps
+/// plain-text email bodies readable. Use `|safe` in a template to opt out.
+pub struct TemplateEngine {
+    env: minijinja::Environment<'static>,
+}
+
+impl Default for TemplateEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TemplateEngine {
+    pub fn new() -> Self {
+        let mut env = minijinja::Environment::new();
+        env.set_auto_escape_callback(|name| {
+            if name.ends_with(".txt") {
+                minijinja::AutoEscape::None
+            } else {
+                minijinja::AutoEscape::Html
+            }
+        });
+        Self { env }
+    }
+
+    /// Registers a template (or shared layout) under `name`.
+    pub fn add_template(&mut self, name: impl Into<String>, source: impl Into<String>) -> Result<(), TemplateError> {
+        self.env.add_template_owned(name.into(), source.into())?;
+        Ok(())
+    }
+
+    /// Builder form of [`TemplateEngine::add_template`].
+    pub fn with_template(mut self, name: impl Into<String>, source: impl Into<String>) -> Result<Self, TemplateError> {
+        self.add_template(name, source)?;
+        Ok(self)
+    }
+
+    /// Loads every `.html` and `.txt` file below `dir`, named by its
+    /// `/`-separated path relative to `dir`.
+    pub fn load_dir(&mut self, dir: impl AsRef<Path>) -> Result<usize, TemplateError> {
+        let root = dir.as_ref();
+        let mut loaded = 0;
+        let mut pending = vec![root.to_path_buf()];
+        while let Some(current) = pending.pop() {
+            for entry in std::fs::read_dir(&current)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+                let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+                if ext != "html" && ext != "txt" {
+                    continue;
+                }
+                let name = path
+                    .strip_prefix(root)
+                    .unwrap_or(&path)
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                self.add_template(name, std::fs::read_to_string(&path)?)?;
+                loaded += 1;
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Adds a global value available to every template (e.g. the site name).
+    pub fn add_global<V: Serialize>(&mut self, name: impl Into<String>, value: V) {
+        self.env
+            .add_global(name.into(), minijinja::Value::from_serialize(&value));
+    }
+
+    /// Renders a registered template with the given context.
+    pub fn render<S: Serialize>(&self, name: &str, ctx: S) -> Result<Html, TemplateError> {
+        let template = self.env.get_template(name)?;
+        Ok(Html(template.render(ctx)?))
+    }
+
+    /// Renders an ad-hoc template string, escaping as HTML.
+    pub fn render_str<S: Serialize>(&self, source: &str, ctx: S) -> Result<Html, TemplateError> {
+        Ok(Html(self.env.render_named_str("inline.html", source, ctx)?))
+    }
+
+    /// Returns true if a template with this name has been registered.
+    pub fn has_template(&self, name: &str) -> bool {
+        self.env.get_template(name).is_ok()
+    }
+}
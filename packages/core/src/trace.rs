@@ -0,0 +1,333 @@
+//! montrs-core/src/trace.rs: Domain attributes and baggage on route traces.
+//!
+//! Every loader, action and subscription runs in a `route` span, and
+//! [`RouteContext::trace`] is its [`Trace`]: handlers attach attributes such
+//! as an order ID with `ctx.trace().set_attr("order_id", id)` instead of
+//! reaching into `tracing`, and set baggage that travels with the request to
+//! the services it calls. A subscriber such as `tracing-opentelemetry`
+//! exports the span with the attributes as its `attributes` field.
+//!
+//! Traces follow W3C Trace Context. A request's `traceparent` header makes
+//! the route span a child of the caller's and its `baggage` header is the
+//! starting baggage; a loader or action called from another one joins the
+//! caller's trace. [`Trace::headers`] carries both on to outbound calls, and
+//! with the `rpc` feature [`TracedRequest::traced`] adds them to a `reqwest`
+//! request. `montrs_orm::TracedBackend` puts the trace and its attributes on
+//! a span per statement.
+//!
+//! Nothing is propagated by itself: baggage is meant for the app's own
+//! services, and Stripe or a webhook receiver should not see it.
+
+use crate::crash::correlation_id;
+use crate::router::RouteContext;
+use crate::wasi::WasiRequest;
+use crate::AppConfig;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+/// The W3C header naming the trace and the caller's span.
+pub const TRACEPARENT: &str = "traceparent";
+/// The W3C header carrying baggage.
+pub const BAGGAGE: &str = "baggage";
+
+/// Baggage entries past this many are dropped, as W3C Baggage allows.
+const MAX_BAGGAGE_ENTRIES: usize = 64;
+
+thread_local! {
+    static CURRENT: RefCell<Option<Trace>> = const { RefCell::new(None) };
+}
+
+/// The trace of one loader, action or subscription. Clones share their
+/// attributes and baggage.
+///
+/// ```rust,ignore
+/// async fn act(&self, ctx: RouteContext<'_, AppConfig>, _params: NoParams, order: NewOrder) -> Result<Order, RouteError> {
+///     let order = self.orders.create(order).await?;
+///     ctx.trace().set_attr("order_id", order.id).set_baggage("tenant", &order.tenant);
+///     self.billing.act::<ChargeRoute>(&ChargeParams { order: order.id }, &order.total).await?;
+///     Ok(order)
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Trace {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    trace_id: String,
+    span_id: String,
+    parent_id: Option<String>,
+    sampled: bool,
+    span: tracing::Span,
+    attributes: Mutex<BTreeMap<String, Value>>,
+    baggage: Mutex<BTreeMap<String, String>>,
+}
+
+impl Trace {
+    /// Starts the trace of a handler: a child of the current trace if there
+    /// is one, else of the request's `traceparent`, else a new trace.
+    pub(crate) fn start(path: &str, operation: &'static str, request: Option<&WasiRequest>) -> Self {
+        let (trace_id, parent_id, sampled, baggage) = match (current(), request) {
+            (Some(parent), _) => (parent.inner.trace_id.clone(), Some(parent.inner.span_id.clone()), parent.inner.sampled, parent.baggage_items()),
+            (None, Some(request)) => {
+                let baggage = request.header(BAGGAGE).map(parse_baggage).unwrap_or_default();
+                match request.header(TRACEPARENT).and_then(parse_traceparent) {
+                    Some((trace_id, parent_id, sampled)) => (trace_id, Some(parent_id), sampled, baggage),
+                    None => (new_trace_id(), None, true, baggage),
+                }
+            }
+            (None, None) => (new_trace_id(), None, true, BTreeMap::new()),
+        };
+        let span_id = correlation_id();
+        let span = tracing::info_span!(
+            "route",
+            route = path,
+            operation,
+            trace_id = %trace_id,
+            span_id = %span_id,
+            parent_id = parent_id.as_deref(),
+            status = tracing::field::Empty,
+            attributes = tracing::field::Empty,
+        );
+        Self::new(trace_id, span_id, parent_id, sampled, span, baggage)
+    }
+
+    /// A new trace without a span, for code running outside any handler.
+    pub(crate) fn detached() -> Self {
+        Self::new(new_trace_id(), correlation_id(), None, true, tracing::Span::none(), BTreeMap::new())
+    }
+
+    fn new(trace_id: String, span_id: String, parent_id: Option<String>, sampled: bool, span: tracing::Span, baggage: BTreeMap<String, String>) -> Self {
+        let inner = Inner { trace_id, span_id, parent_id, sampled, span, attributes: Mutex::default(), baggage: Mutex::new(baggage) };
+        Self { inner: Arc::new(inner) }
+    }
+
+    /// The 32 hex digits shared by every span of the trace.
+    pub fn trace_id(&self) -> &str {
+        &self.inner.trace_id
+    }
+
+    /// The 16 hex digits of this handler's span.
+    pub fn span_id(&self) -> &str {
+        &self.inner.span_id
+    }
+
+    /// The caller's span, if the trace was continued.
+    pub fn parent_id(&self) -> Option<&str> {
+        self.inner.parent_id.as_deref()
+    }
+
+    /// Whether the caller asked for the trace to be recorded. New traces are.
+    pub fn sampled(&self) -> bool {
+        self.inner.sampled
+    }
+
+    /// The `route` span, to record fields of your own or to parent spans on.
+    pub fn span(&self) -> &tracing::Span {
+        &self.inner.span
+    }
+
+    /// Sets the attribute `key` on the span, replacing an earlier value.
+    pub fn set_attr(&self, key: impl Into<String>, value: impl Into<Value>) -> &Self {
+        let mut attributes = self.inner.attributes.lock().unwrap_or_else(|e| e.into_inner());
+        attributes.insert(key.into(), value.into());
+        let recorded = Value::Object(attributes.iter().map(|(k, v)| (k.clone(), v.clone())).collect());
+        self.inner.span.record("attributes", tracing::field::display(recorded));
+        self
+    }
+
+    pub fn attr(&self, key: &str) -> Option<Value> {
+        self.inner.attributes.lock().unwrap_or_else(|e| e.into_inner()).get(key).cloned()
+    }
+
+    pub fn attributes(&self) -> BTreeMap<String, Value> {
+        self.inner.attributes.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Sets the baggage entry `key`, sent with outbound calls. Keys that are
+    /// not HTTP tokens are ignored with a warning.
+    pub fn set_baggage(&self, key: impl Into<String>, value: impl Into<String>) -> &Self {
+        let key = key.into();
+        if !is_token(&key) {
+            tracing::warn!(key = %key, "baggage key is not an HTTP token; ignored");
+            return self;
+        }
+        let mut baggage = self.inner.baggage.lock().unwrap_or_else(|e| e.into_inner());
+        if baggage.len() < MAX_BAGGAGE_ENTRIES || baggage.contains_key(&key) {
+            baggage.insert(key, value.into());
+        }
+        self
+    }
+
+    pub fn baggage(&self, key: &str) -> Option<String> {
+        self.inner.baggage.lock().unwrap_or_else(|e| e.into_inner()).get(key).cloned()
+    }
+
+    pub fn baggage_items(&self) -> BTreeMap<String, String> {
+        self.inner.baggage.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// The `traceparent` header naming this span as the parent.
+    pub fn traceparent(&self) -> String {
+        format!("00-{}-{}-{}", self.inner.trace_id, self.inner.span_id, if self.inner.sampled { "01" } else { "00" })
+    }
+
+    /// The `traceparent` and `baggage` headers for a call made by the handler.
+    pub fn headers(&self) -> Vec<(String, String)> {
+        let mut headers = vec![(TRACEPARENT.to_string(), self.traceparent())];
+        let baggage = self.baggage_items();
+        if !baggage.is_empty() {
+            let entries: Vec<String> = baggage.iter().map(|(key, value)| format!("{}={}", key, encode(value))).collect();
+            headers.push((BAGGAGE.to_string(), entries.join(",")));
+        }
+        headers
+    }
+
+    pub(crate) fn record_status(&self, status: u16) {
+        self.inner.span.record("status", status);
+    }
+}
+
+impl std::fmt::Debug for Trace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Trace")
+            .field("trace_id", &self.inner.trace_id)
+            .field("span_id", &self.inner.span_id)
+            .field("parent_id", &self.inner.parent_id)
+            .field("attributes", &self.attributes())
+            .field("baggage", &self.baggage_items())
+            .finish()
+    }
+}
+
+impl<C: AppConfig> RouteContext<'_, C> {
+    /// The trace of the loader, action or subscription being run. Outside
+    /// one, a new trace whose attributes go nowhere.
+    pub fn trace(&self) -> Trace {
+        current().unwrap_or_else(Trace::detached)
+    }
+}
+
+/// Propagates the current trace on a `reqwest` request.
+///
+/// ```rust,ignore
+/// let stock: Stock = client.get(format!("{}/stock/{}", inventory_url, sku)).traced().send().await?.json().await?;
+/// ```
+#[cfg(feature = "rpc")]
+pub trait TracedRequest {
+    /// Adds the `traceparent` and `baggage` headers of the loader or action
+    /// being run; outside one, the request is unchanged.
+    fn traced(self) -> Self;
+}
+
+#[cfg(feature = "rpc")]
+impl TracedRequest for reqwest::RequestBuilder {
+    fn traced(self) -> Self {
+        let Some(trace) = current() else {
+            return self;
+        };
+        trace.headers().into_iter().fold(self, |request, (name, value)| request.header(name, value))
+    }
+}
+
+/// The trace of the handler being polled on this thread, for clients and
+/// backends that propagate it.
+pub fn current() -> Option<Trace> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Makes `trace` the current trace and enters its span while `future` is polled.
+pub(crate) async fn scoped<F: Future>(trace: Trace, future: F) -> F::Output {
+    struct Restore(Option<Trace>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            CURRENT.with(|current| *current.borrow_mut() = previous);
+        }
+    }
+    let mut future = std::pin::pin!(future);
+    std::future::poll_fn(|cx| {
+        let _entered = trace.inner.span.enter();
+        let _restore = Restore(CURRENT.with(|current| current.replace(Some(trace.clone()))));
+        future.as_mut().poll(cx)
+    })
+    .await
+}
+
+fn new_trace_id() -> String {
+    format!("{}{}", correlation_id(), correlation_id())
+}
+
+/// The trace ID, parent span ID and sampled flag of a `traceparent` header.
+/// Headers of later versions are read by their first four fields, as W3C
+/// Trace Context asks.
+fn parse_traceparent(header: &str) -> Option<(String, String, bool)> {
+    let mut fields = header.trim().split('-');
+    let (version, trace_id, parent_id, flags) = (fields.next()?, fields.next()?, fields.next()?, fields.next()?);
+    let is_hex = |field: &str, len: usize| field.len() == len && field.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+    if !is_hex(version, 2) || version == "ff" || (version == "00" && fields.next().is_some()) {
+        return None;
+    }
+    if !is_hex(trace_id, 32) || !is_hex(parent_id, 16) || !is_hex(flags, 2) {
+        return None;
+    }
+    if trace_id.bytes().all(|b| b == b'0') || parent_id.bytes().all(|b| b == b'0') {
+        return None;
+    }
+    let sampled = u8::from_str_radix(flags, 16).ok()? & 1 == 1;
+    Some((trace_id.to_string(), parent_id.to_string(), sampled))
+}
+
+/// The entries of a `baggage` header, without their properties. Malformed
+/// entries are skipped.
+fn parse_baggage(header: &str) -> BTreeMap<String, String> {
+    header
+        .split(',')
+        .filter_map(|entry| {
+            let (key, value) = entry.split(';').next()?.split_once('=')?;
+            let key = key.trim();
+            is_token(key).then(|| (key.to_string(), decode(value.trim())))
+        })
+        .take(MAX_BAGGAGE_ENTRIES)
+        .collect()
+}
+
+fn is_token(key: &str) -> bool {
+    !key.is_empty() && key.bytes().all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Percent-encodes what a baggage value may not hold, and `%` itself.
+fn encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'!'..=b'~' if !b"\",;\\%".contains(&byte) => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Percent-decodes a baggage value. Malformed escapes are kept as they are.
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
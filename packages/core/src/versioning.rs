@@ -0,0 +1,299 @@
+//! montrs-core/src/versioning.rs: API versions and content negotiation for routes.
+//! Every version of a route is registered under its own path (`/api/v1/users/:id`,
+//! `/api/v2/users/:id`). `ApiVersions` picks the version a request asks for, from
+//! the path, a header or the `Accept` media type, and rewrites the request to that
+//! path, so matching, metadata, deprecation and analytics work per version. The
+//! version of each route is added to its metadata, so it travels through
+//! `RouterSpec` and OpenAPI like ownership and deprecation do.
+
+use crate::deprecation::Deprecation;
+use crate::meta;
+use crate::router::{RouteError, RouterSpec};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Where a request names the API version it wants.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VersionStrategy {
+    /// Only the path: `/api/v2/users/42`.
+    Path,
+    /// A request header, e.g. `Api-Version: 2`.
+    Header(String),
+    /// A vendor media type in `Accept`, e.g. `application/vnd.acme.v2+json`.
+    /// Holds the type without the version (`application/vnd.acme`).
+    MediaType(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ApiVersion {
+    name: String,
+    deprecation: Option<Deprecation>,
+}
+
+/// The versions of an API mounted under one path prefix.
+///
+/// ```rust,ignore
+/// router.set_versions(
+///     ApiVersions::header("/api", "Api-Version")
+///         .version("v1")
+///         .version("v2")
+///         .deprecate("v1", Deprecation::new().sunset("2026-06-30")),
+/// );
+/// router.register(UsersV1Route); // "/api/v1/users/:id"
+/// router.register(UsersV2Route); // "/api/v2/users/:id"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiVersions {
+    prefix: String,
+    strategy: VersionStrategy,
+    versions: Vec<ApiVersion>,
+    default: Option<String>,
+}
+
+/// The version picked for a request and the path to dispatch it to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Negotiated {
+    /// `None` for paths outside the versioned prefix.
+    pub version: Option<String>,
+    /// The request path with the version segment, e.g. `/api/v2/users/42`.
+    pub path: String,
+    /// Headers to send back: the version served and the matching `Vary`.
+    pub headers: Vec<(String, String)>,
+}
+
+impl ApiVersions {
+    pub fn new(prefix: impl Into<String>, strategy: VersionStrategy) -> Self {
+        let prefix = prefix.into().trim_end_matches('/').to_string();
+        Self { prefix, strategy, versions: Vec::new(), default: None }
+    }
+
+    /// Versions selected by the path only (`/api/v2/...`).
+    pub fn path(prefix: impl Into<String>) -> Self {
+        Self::new(prefix, VersionStrategy::Path)
+    }
+
+    /// Versions selected by the `header` request header, or the path.
+    pub fn header(prefix: impl Into<String>, header: impl Into<String>) -> Self {
+        Self::new(prefix, VersionStrategy::Header(header.into()))
+    }
+
+    /// Versions selected by `Accept: <media_type>.<version>+json`, or the path.
+    pub fn media_type(prefix: impl Into<String>, media_type: impl Into<String>) -> Self {
+        Self::new(prefix, VersionStrategy::MediaType(media_type.into()))
+    }
+
+    /// Declares a version. Declare them oldest first; each one's successor is the next.
+    pub fn version(mut self, name: impl Into<String>) -> Self {
+        self.versions.push(ApiVersion { name: name.into(), deprecation: None });
+        self
+    }
+
+    /// Deprecates every route of `version`. Without a replacement, clients are
+    /// pointed to the same route in the next version.
+    pub fn deprecate(mut self, version: &str, deprecation: Deprecation) -> Self {
+        if let Some(v) = self.versions.iter_mut().find(|v| v.name == version) {
+            v.deprecation = Some(deprecation);
+        }
+        self
+    }
+
+    /// The version served when a request names none (default: the newest).
+    pub fn default_version(mut self, version: impl Into<String>) -> Self {
+        self.default = Some(version.into());
+        self
+    }
+
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    pub fn strategy(&self) -> &VersionStrategy {
+        &self.strategy
+    }
+
+    /// The declared versions, oldest first.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.versions.iter().map(|v| v.name.as_str())
+    }
+
+    fn default_name(&self) -> Option<&str> {
+        self.default.as_deref().or_else(|| self.versions.last().map(|v| v.name.as_str()))
+    }
+
+    /// The version after `version`, if any.
+    pub fn successor(&self, version: &str) -> Option<&str> {
+        let index = self.versions.iter().position(|v| v.name == version)?;
+        self.versions.get(index + 1).map(|v| v.name.as_str())
+    }
+
+    /// The deprecation declared for `version`.
+    pub fn deprecation(&self, version: &str) -> Option<&Deprecation> {
+        self.versions.iter().find(|v| v.name == version)?.deprecation.as_ref()
+    }
+
+    /// Finds a declared version by name, accepting `2` for `v2` and the other way round.
+    fn find(&self, requested: &str) -> Option<&str> {
+        let requested = requested.trim();
+        let bare = |name: &str| name.strip_prefix(['v', 'V']).unwrap_or(name).to_string();
+        self.versions
+            .iter()
+            .map(|v| v.name.as_str())
+            .find(|name| *name == requested || bare(name) == bare(requested))
+    }
+
+    /// Splits `/api/v1/users/:id` into `("v1", "/users/:id")`. Returns `None` for
+    /// paths outside the prefix or without a declared version.
+    pub fn split<'a>(&self, path: &'a str) -> Option<(&str, &'a str)> {
+        let rest = path.strip_prefix(&self.prefix)?.strip_prefix('/')?;
+        let (segment, route) = match rest.find('/') {
+            Some(end) => (&rest[..end], &rest[end..]),
+            None => (rest, ""),
+        };
+        let version = self.versions.iter().find(|v| v.name == segment)?;
+        Some((version.name.as_str(), route))
+    }
+
+    /// `/api/<version><route>`.
+    pub fn join(&self, version: &str, route: &str) -> String {
+        format!("{}/{}{}", self.prefix, version, route)
+    }
+
+    /// Picks the version for a request and the path to dispatch it to. A version
+    /// in the path wins over headers; a request naming no version gets the
+    /// default. Asking for an undeclared version is [`RouteError::NotAcceptable`].
+    pub fn negotiate<'h>(&self, path: &str, headers: impl IntoIterator<Item = (&'h str, &'h str)>) -> Result<Negotiated, RouteError> {
+        let route = match path.strip_prefix(&self.prefix) {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => rest,
+            _ => return Ok(Negotiated { version: None, path: path.to_string(), headers: Vec::new() }),
+        };
+        if let Some((version, _)) = self.split(path) {
+            return Ok(self.negotiated(version, path.to_string()));
+        }
+        let version = match self.requested(headers) {
+            Some(requested) => self
+                .find(&requested)
+                .ok_or_else(|| RouteError::NotAcceptable(format!("API version `{}` does not exist", requested)))?,
+            None => self
+                .default_name()
+                .ok_or_else(|| RouteError::NotAcceptable("no API versions are declared".to_string()))?,
+        };
+        Ok(self.negotiated(version, self.join(version, route)))
+    }
+
+    fn negotiated(&self, version: &str, path: String) -> Negotiated {
+        let headers = match &self.strategy {
+            VersionStrategy::Path => Vec::new(),
+            VersionStrategy::Header(name) => vec![(name.clone(), version.to_string()), ("Vary".to_string(), name.clone())],
+            VersionStrategy::MediaType(media_type) => vec![
+                ("Content-Type".to_string(), format!("{}.{}+json", media_type, version)),
+                ("Vary".to_string(), "Accept".to_string()),
+            ],
+        };
+        Negotiated { version: Some(version.to_string()), path, headers }
+    }
+
+    /// The version named by the request headers, as written by the client.
+    fn requested<'h>(&self, headers: impl IntoIterator<Item = (&'h str, &'h str)>) -> Option<String> {
+        match &self.strategy {
+            VersionStrategy::Path => None,
+            VersionStrategy::Header(name) => headers
+                .into_iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.trim().to_string())
+                .filter(|value| !value.is_empty()),
+            VersionStrategy::MediaType(media_type) => headers
+                .into_iter()
+                .filter(|(key, _)| key.eq_ignore_ascii_case("accept"))
+                .flat_map(|(_, value)| value.split(','))
+                .find_map(|accepted| media_type_version(accepted, media_type)),
+        }
+    }
+}
+
+/// Reads the version out of `application/vnd.acme.v2+json` or
+/// `application/vnd.acme+json; version=2`.
+fn media_type_version(accepted: &str, media_type: &str) -> Option<String> {
+    let mut parts = accepted.split(';').map(str::trim);
+    let essence = parts.next()?;
+    let rest = essence.get(..media_type.len()).filter(|head| head.eq_ignore_ascii_case(media_type)).map(|_| &essence[media_type.len()..])?;
+    let rest = rest.split('+').next().unwrap_or("");
+    if let Some(version) = rest.strip_prefix('.').filter(|v| !v.is_empty()) {
+        return Some(version.to_string());
+    }
+    if !rest.is_empty() {
+        return None;
+    }
+    parts.find_map(|param| param.strip_prefix("version=").map(|v| v.trim_matches('"').to_string()))
+}
+
+/// One API version as seen in a [`RouterSpec`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionSpec {
+    pub version: String,
+    /// The routes of this version without the version segment, e.g. `/api/users/:id`.
+    pub routes: Vec<String>,
+    /// Routes that the previous version did not have.
+    pub added: Vec<String>,
+    /// Routes of the previous version that this one dropped.
+    pub removed: Vec<String>,
+    /// Whether every route of this version is deprecated.
+    pub deprecated: bool,
+}
+
+impl RouterSpec {
+    /// Groups the versioned routes by API version, oldest first, with what
+    /// changed from one version to the next.
+    pub fn api_versions(&self) -> Vec<VersionSpec> {
+        let mut grouped: BTreeMap<(Vec<u64>, String), (BTreeSet<String>, bool)> = BTreeMap::new();
+        for route in self.routes.values() {
+            let (Some(version), Some(api_route)) = (route.meta.get(meta::API_VERSION), route.meta.get(meta::API_ROUTE)) else {
+                continue;
+            };
+            let deprecated = route.meta.get(meta::STABILITY).map(String::as_str) == Some(meta::DEPRECATED);
+            let entry = grouped.entry((version_order(version), version.clone())).or_insert_with(|| (BTreeSet::new(), true));
+            entry.0.insert(api_route.clone());
+            entry.1 &= deprecated;
+        }
+
+        let mut previous: Option<BTreeSet<String>> = None;
+        let mut versions = Vec::new();
+        for ((_, version), (routes, deprecated)) in grouped {
+            let (added, removed) = match &previous {
+                Some(previous) => (routes.difference(previous).cloned().collect(), previous.difference(&routes).cloned().collect()),
+                None => (Vec::new(), Vec::new()),
+            };
+            versions.push(VersionSpec { version, routes: routes.iter().cloned().collect(), added, removed, deprecated });
+            previous = Some(routes);
+        }
+        versions
+    }
+
+    /// Renders one OpenAPI 3.0 document per API version, keyed by version. Each
+    /// document lists the routes added and removed since the previous version
+    /// under `x-montrs-changes`.
+    pub fn to_openapi_versions(&self, title: &str) -> BTreeMap<String, Value> {
+        self.api_versions()
+            .into_iter()
+            .enumerate()
+            .map(|(index, version)| {
+                let routes = self
+                    .routes
+                    .iter()
+                    .filter(|(_, route)| route.meta.get(meta::API_VERSION) == Some(&version.version))
+                    .map(|(path, route)| (path.clone(), route.clone()))
+                    .collect();
+                let mut doc = RouterSpec { routes, ..Default::default() }.to_openapi(title, &version.version);
+                if index > 0 {
+                    doc["x-montrs-changes"] = json!({ "added": version.added, "removed": version.removed });
+                }
+                (version.version, doc)
+            })
+            .collect()
+    }
+}
+
+/// Orders `v2` before `v10` and `2024-01-15` before `2024-03-01`.
+fn version_order(version: &str) -> Vec<u64> {
+    version.split(|c: char| !c.is_ascii_digit()).filter_map(|n| n.parse().ok()).collect()
+}
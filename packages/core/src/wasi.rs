@@ -0,0 +1,394 @@
+//! montrs-core/src/wasi.rs: Serving loaders and actions as a WASI HTTP component.
+//!
+//! On `wasm32-wasip2` an app is a `wasi:http/proxy` component: the host hands
+//! every request to its `incoming-handler` export. [`WasiRequest`] and
+//! [`WasiResponse`] are those requests and responses reduced to what routes
+//! need, and [`handle`] answers one with the app's router: `GET` and `HEAD` run
+//! the loader of the matched route, `POST`, `PUT`, `PATCH` and `DELETE` its
+//! action. Query parameters are merged into the route params, with captured
+//! path params taking precedence. While a loader or action runs,
+//! [`RouteContext::request`] returns the request it is answering.
+//!
+//! With the `wasi` feature, `export_component!` turns a function building the
+//! `AppSpec` into the component's export. The request model itself compiles on
+//! every target, so it can be tested natively.
+
+use crate::env::EnvConfig;
+use crate::prerender::{self, CACHE_HEADER};
+use crate::router::{RouteContext, Router};
+use crate::{AppConfig, AppSpec, RouteError};
+use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::sync::Arc;
+
+#[cfg(all(feature = "wasi", target_os = "wasi"))]
+pub use wasip2 as bindings;
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<WasiRequest>>> = const { RefCell::new(None) };
+}
+
+/// An incoming `wasi:http` request, with its body read.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WasiRequest {
+    /// Upper-case method, e.g. `GET`.
+    pub method: String,
+    /// Path and query, e.g. `/users/42?expand=posts`.
+    pub path_with_query: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl WasiRequest {
+    pub fn new(method: impl Into<String>, path_with_query: impl Into<String>) -> Self {
+        Self { method: method.into().to_ascii_uppercase(), path_with_query: path_with_query.into(), ..Default::default() }
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn with_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// The path without the query string.
+    pub fn path(&self) -> &str {
+        self.path_with_query.split_once('?').map_or(self.path_with_query.as_str(), |(path, _)| path)
+    }
+
+    pub fn query(&self) -> Option<&str> {
+        self.path_with_query.split_once('?').map(|(_, query)| query)
+    }
+
+    /// The first header named `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+
+    /// The query string as route params. Values are percent-decoded, canonical
+    /// integers become numbers as with path params, and a repeated key keeps
+    /// its last value.
+    pub fn query_params(&self) -> Map<String, Value> {
+        self.query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (decode(name), crate::matcher::param_value(&decode(value)))
+            })
+            .collect()
+    }
+}
+
+/// The response [`handle`] produced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WasiResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl WasiResponse {
+    pub(crate) fn json(status: u16, body: Vec<u8>) -> Self {
+        Self { status, headers: vec![("content-type".to_string(), "application/json".to_string())], body }
+    }
+
+    pub(crate) fn error(error: &RouteError) -> Self {
+        let mut body = serde_json::json!({ "error": error.to_string() });
+        if let RouteError::Action(action_error) = error {
+            body["action_error"] = serde_json::to_value(action_error).unwrap_or_default();
+        }
+        let mut response = Self::json(error.status(), serde_json::to_vec(&body).unwrap_or_default());
+        if let RouteError::Redirect(location) = error {
+            response.headers.push(("location".to_string(), location.clone()));
+        }
+        response
+    }
+
+    /// The first header named `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+}
+
+impl<C: AppConfig> RouteContext<'_, C> {
+    /// The request being answered, when the app runs as a WASI component or
+    /// is embedded in another server.
+    pub fn request(&self) -> Option<Arc<WasiRequest>> {
+        current_request()
+    }
+}
+
+pub(crate) fn current_request() -> Option<Arc<WasiRequest>> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Answers `request` with the loaders and actions of a booted `spec`.
+/// Errors become their [`RouteError::status`] with `{"error": "..."}`, plus
+/// the `action_error` of a [`RouteError::Action`]; redirects from route
+/// guards also get a `location` header.
+///
+/// A component cannot run work after answering, so cached pages the request
+/// found stale are rendered again before `handle` returns.
+pub async fn handle<C: AppConfig>(spec: &AppSpec<C>, request: WasiRequest) -> WasiResponse {
+    let response = respond(&spec.router, &spec.config, &spec.env, request).await;
+    prerender::refresh_stale(&spec.router, &spec.config, &spec.env).await;
+    response
+}
+
+/// [`handle`] for a router kept apart from its `AppSpec`, as when embedded in
+/// another server or driven by a test client. Stale cached pages are left to
+/// the caller.
+///
+/// When the router has a [`Preset`](crate::Preset), CORS preflights are
+/// answered from it, oversized bodies get `413`, and every response carries
+/// its security and CORS headers. Requests over the rate limit of the preset,
+/// or of the limiter given to [`Router::set_limiter`], get `429`.
+pub async fn respond<C: AppConfig>(router: &Router<C>, config: &C, env: &dyn EnvConfig, request: WasiRequest) -> WasiResponse {
+    let request = Arc::new(request);
+    let path = request.path();
+    let ctx = || RouteContext { config, env };
+
+    let preset = router.preset();
+    let origin = request.header("origin");
+    let refused = match preset {
+        Some(preset) if request.method == "OPTIONS" && request.header("access-control-request-method").is_some() => {
+            Some(WasiResponse { status: 204, headers: preset.cors.preflight_headers(origin), body: Vec::new() })
+        }
+        Some(preset) if request.body.len() as u64 > preset.limits.max_body_bytes => {
+            Some(WasiResponse::json(413, br#"{"error":"Request body too large"}"#.to_vec()))
+        }
+        _ if !router.admit() => Some(WasiResponse::json(429, br#"{"error":"Too many requests"}"#.to_vec())),
+        _ => None,
+    };
+    if let Some(mut response) = refused {
+        if let Some(preset) = preset {
+            response.headers.extend(preset.security.headers());
+        }
+        return response;
+    }
+
+    let mut params = request.query_params();
+    let matched = router.resolve(path);
+    if let Some(Value::Object(captured)) = matched.as_ref().map(|m| m.params_json()) {
+        params.extend(captured);
+    }
+    let params = Value::Object(params);
+
+    let mut cached = None;
+    let result = match request.method.as_str() {
+        "GET" | "HEAD" => {
+            let load = with_request(&request, router.load_bytes(path, ctx(), params));
+            let cache = router
+                .page_cache()
+                .zip(matched.as_ref())
+                .and_then(|(cache, m)| Some((cache, cache.ttl(m.pattern)?, router.is_restricted(m.pattern))));
+            match cache {
+                Some((cache, ttl, restricted)) => {
+                    // A guarded page could differ per user and would skip the checks on a hit.
+                    let served = cache.serve(path, ttl, restricted || request.query().is_some(), load).await;
+                    cached = Some((served.status, served.age));
+                    served.result
+                }
+                None => load.await.map(|bytes| bytes.to_vec()),
+            }
+        }
+        "POST" | "PUT" | "PATCH" | "DELETE" => {
+            let content_type = request.header("content-type").unwrap_or("application/json");
+            with_request(&request, router.act_body(path, ctx(), params, content_type, &request.body))
+                .await
+                .and_then(|value| serde_json::to_vec(&value).map_err(|e| RouteError::InternalError(e.to_string())))
+        }
+        _ => {
+            let mut response = WasiResponse::json(405, br#"{"error":"Method not allowed"}"#.to_vec());
+            response.headers.push(("allow".to_string(), "GET, HEAD, POST, PUT, PATCH, DELETE".to_string()));
+            return response;
+        }
+    };
+
+    let mut response = match result {
+        Ok(body) => WasiResponse::json(200, body),
+        Err(error) => WasiResponse::error(&error),
+    };
+    if let Some(matched) = matched {
+        response.headers.extend(router.response_headers(matched.pattern));
+    }
+    if let Some(preset) = preset {
+        response.headers.extend(preset.response_headers(origin));
+    }
+    if let Some((status, age)) = cached {
+        response.headers.push((CACHE_HEADER.to_string(), status.as_str().to_string()));
+        if let Some(age) = age {
+            response.headers.push(("age".to_string(), age.to_string()));
+        }
+    }
+    if request.method == "HEAD" {
+        response.body.clear();
+    }
+    response
+}
+
+/// Runs `future` with `request` as the current request on every poll.
+pub(crate) async fn with_request<F: Future>(request: &Arc<WasiRequest>, future: F) -> F::Output {
+    struct Restore(Option<Arc<WasiRequest>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            CURRENT.with(|current| *current.borrow_mut() = previous);
+        }
+    }
+    let mut future = std::pin::pin!(future);
+    std::future::poll_fn(|cx| {
+        let _restore = Restore(CURRENT.with(|current| current.replace(Some(request.clone()))));
+        future.as_mut().poll(cx)
+    })
+    .await
+}
+
+/// Percent-decodes a query component, reading `+` as a space. Malformed
+/// escapes are kept as they are.
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], escaped) {
+            (b'+', _) => decoded.push(b' '),
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 2;
+            }
+            (byte, _) => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// An app booted for the component's lifetime, type-erased so that
+/// `export_component!` needs no config type.
+pub trait Handler {
+    fn handle(&self, request: WasiRequest) -> WasiResponse;
+}
+
+impl<C: AppConfig> Handler for AppSpec<C> {
+    fn handle(&self, request: WasiRequest) -> WasiResponse {
+        futures::executor::block_on(handle(self, request))
+    }
+}
+
+/// Answers every request with 500 after the app failed to boot.
+struct BootFailed(String);
+
+impl Handler for BootFailed {
+    fn handle(&self, _request: WasiRequest) -> WasiResponse {
+        WasiResponse::error(&RouteError::InternalError(format!("app failed to boot: {}", self.0)))
+    }
+}
+
+/// Boots `spec` for [`Target::Wasi`](crate::Target::Wasi) on the current
+/// thread, as the component does on its first request.
+pub fn boot<C: AppConfig>(spec: AppSpec<C>) -> Box<dyn Handler> {
+    let mut spec = spec.with_target(crate::Target::Wasi);
+    match futures::executor::block_on(spec.boot()) {
+        Ok(_) => Box::new(spec),
+        Err(e) => {
+            tracing::error!(error = %e, "boot failed");
+            Box::new(BootFailed(e.to_string()))
+        }
+    }
+}
+
+/// Answers a `wasi:http` request with `handler`, for the export generated by
+/// `export_component!`.
+#[cfg(all(feature = "wasi", target_os = "wasi"))]
+pub fn serve(handler: &dyn Handler, request: bindings::http::types::IncomingRequest, out: bindings::http::types::ResponseOutparam) {
+    use bindings::http::types::{Fields, IncomingBody, Method, OutgoingBody, OutgoingResponse, ResponseOutparam};
+    use std::io::{Read, Write};
+
+    let method = match request.method() {
+        Method::Get => "GET".to_string(),
+        Method::Head => "HEAD".to_string(),
+        Method::Post => "POST".to_string(),
+        Method::Put => "PUT".to_string(),
+        Method::Delete => "DELETE".to_string(),
+        Method::Connect => "CONNECT".to_string(),
+        Method::Options => "OPTIONS".to_string(),
+        Method::Trace => "TRACE".to_string(),
+        Method::Patch => "PATCH".to_string(),
+        Method::Other(other) => other,
+    };
+    let mut incoming = WasiRequest::new(method, request.path_with_query().unwrap_or_else(|| "/".to_string()));
+    incoming.headers = request
+        .headers()
+        .entries()
+        .into_iter()
+        .map(|(name, value)| (name, String::from_utf8_lossy(&value).into_owned()))
+        .collect();
+    if let Ok(body) = request.consume() {
+        if let Ok(mut stream) = body.stream()
+            && let Err(e) = stream.read_to_end(&mut incoming.body)
+        {
+            tracing::warn!(error = %e, "failed to read the request body");
+        }
+        drop(IncomingBody::finish(body));
+    }
+
+    let response = handler.handle(incoming);
+    let headers: Vec<(String, Vec<u8>)> = response.headers.into_iter().map(|(name, value)| (name, value.into_bytes())).collect();
+    let fields = Fields::from_list(&headers).unwrap_or_else(|_| Fields::new());
+    let outgoing = OutgoingResponse::new(fields);
+    let _ = outgoing.set_status_code(response.status);
+    let Ok(body) = outgoing.body() else {
+        return;
+    };
+    ResponseOutparam::set(out, Ok(outgoing));
+    if let Ok(mut stream) = body.write()
+        && let Err(e) = stream.write_all(&response.body).and_then(|_| stream.flush())
+    {
+        tracing::warn!(error = %e, "failed to write the response body");
+    }
+    let _ = OutgoingBody::finish(body, None);
+}
+
+/// Exports an app as the `wasi:http/proxy` component of a `cdylib` built for
+/// `wasm32-wasip2`. `$app` is a function returning the `AppSpec`; it is booted
+/// on the first request and serves every later one.
+///
+/// ```ignore
+/// fn app() -> AppSpec<MyConfig> {
+///     AppSpec::new(MyConfig, MyEnv).with_plate(Box::new(UsersPlate))
+/// }
+///
+/// montrs_core::export_component!(app);
+/// ```
+#[cfg(all(feature = "wasi", target_os = "wasi"))]
+#[macro_export]
+macro_rules! export_component {
+    ($app:path) => {
+        struct __MontrsComponent;
+
+        impl $crate::wasi::bindings::exports::http::incoming_handler::Guest for __MontrsComponent {
+            fn handle(
+                request: $crate::wasi::bindings::http::types::IncomingRequest,
+                out: $crate::wasi::bindings::http::types::ResponseOutparam,
+            ) {
+                thread_local! {
+                    static APP: ::std::cell::OnceCell<::std::boxed::Box<dyn $crate::wasi::Handler>> =
+                        const { ::std::cell::OnceCell::new() };
+                }
+                APP.with(|app| {
+                    let handler = app.get_or_init(|| $crate::wasi::boot($app()));
+                    $crate::wasi::serve(handler.as_ref(), request, out);
+                });
+            }
+        }
+
+        $crate::wasi::bindings::http::proxy::export!(__MontrsComponent);
+    };
+}
@@ -0,0 +1,625 @@
+//! montrs-core/src/webhook.rs: Outbound webhook delivery.
+//! Applications register [`Subscription`]s (URL, secret, event filter) and
+//! [`Webhooks::publish`] events. Each matching subscription gets a [`Delivery`]
+//! in the [`WebhookStore`], which doubles as the delivery queue: a worker POSTs
+//! due deliveries with an HMAC-SHA256 signature, rate-limited per subscription,
+//! and retries failures with exponential backoff. Deliveries that run out of
+//! attempts become dead letters until they are redelivered. Every attempt is
+//! logged for debugging. `montrs_orm::webhook::WebhookTables` keeps all of it in
+//! the application database.
+
+use crate::crash::correlation_id;
+use crate::limiter::{GovernorLimiter, Limiter};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// `t=<unix seconds>,v1=<hex HMAC-SHA256 of "<t>.<body>">`.
+pub const SIGNATURE_HEADER: &str = "X-Montrs-Signature";
+/// The event name, e.g. `order.paid`.
+pub const EVENT_HEADER: &str = "X-Montrs-Event";
+/// The delivery ID. It stays the same across retries, so receivers can drop duplicates.
+pub const DELIVERY_HEADER: &str = "X-Montrs-Delivery";
+
+/// Deliveries a worker sends per tick.
+const BATCH: usize = 100;
+
+/// An endpoint that receives some events.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Subscription {
+    pub id: String,
+    pub url: String,
+    /// Signs every delivery. Share it with the receiver only.
+    pub secret: String,
+    /// Event names to deliver. `order.*` matches every `order.` event; an
+    /// empty list matches everything.
+    pub events: Vec<String>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Subscription {
+    pub fn new(url: impl Into<String>, secret: impl Into<String>) -> Self {
+        Self {
+            id: correlation_id(),
+            url: url.into(),
+            secret: secret.into(),
+            events: Vec::new(),
+            active: true,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Only delivers the events matching `events`.
+    pub fn with_events<I, S>(mut self, events: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.events = events.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Whether `event` passes the filter.
+    pub fn matches(&self, event: &str) -> bool {
+        self.events.is_empty()
+            || self.events.iter().any(|filter| match filter.strip_suffix('*') {
+                Some(prefix) => event.starts_with(prefix),
+                None => filter == event,
+            })
+    }
+}
+
+/// Where a delivery stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Waiting for its first attempt or a retry.
+    Pending,
+    Delivered,
+    /// Out of attempts, or its subscription is gone. Kept until redelivered.
+    Dead,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Dead => "dead",
+        }
+    }
+}
+
+/// One event on its way to one subscription.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Delivery {
+    pub id: String,
+    pub subscription_id: String,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub status: DeliveryStatus,
+    /// Attempts made so far.
+    pub attempts: u32,
+    /// When the next attempt is due, while pending.
+    pub next_attempt_at: DateTime<Utc>,
+    /// The HTTP status of the last attempt, if the endpoint answered.
+    pub last_status: Option<u16>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One POST to a subscription, for the delivery log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeliveryAttempt {
+    pub delivery_id: String,
+    pub subscription_id: String,
+    pub event: String,
+    pub url: String,
+    /// Counts from 1.
+    pub attempt: u32,
+    pub at: DateTime<Utc>,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+impl DeliveryAttempt {
+    pub fn succeeded(&self) -> bool {
+        self.status.is_some_and(|status| (200..300).contains(&status))
+    }
+}
+
+/// Keeps subscriptions, the delivery queue and the delivery log.
+#[async_trait]
+pub trait WebhookStore: Send + Sync + 'static {
+    /// Inserts or replaces the subscription with the same ID.
+    async fn save_subscription(&self, subscription: &Subscription) -> anyhow::Result<()>;
+
+    /// Returns whether the subscription existed.
+    async fn remove_subscription(&self, id: &str) -> anyhow::Result<bool>;
+
+    async fn subscriptions(&self) -> anyhow::Result<Vec<Subscription>>;
+
+    /// Inserts or replaces the delivery with the same ID.
+    async fn save_delivery(&self, delivery: &Delivery) -> anyhow::Result<()>;
+
+    async fn delivery(&self, id: &str) -> anyhow::Result<Option<Delivery>>;
+
+    /// Pending deliveries due at `now`, oldest first, at most `limit`.
+    async fn due(&self, now: DateTime<Utc>, limit: usize) -> anyhow::Result<Vec<Delivery>>;
+
+    /// Dead deliveries, newest first.
+    async fn dead_letters(&self) -> anyhow::Result<Vec<Delivery>>;
+
+    async fn log_attempt(&self, attempt: &DeliveryAttempt) -> anyhow::Result<()>;
+
+    /// The newest `limit` attempts, newest first, for one subscription or all.
+    async fn attempts(&self, subscription_id: Option<&str>, limit: usize) -> anyhow::Result<Vec<DeliveryAttempt>>;
+}
+
+#[async_trait]
+impl<T: WebhookStore> WebhookStore for Arc<T> {
+    async fn save_subscription(&self, subscription: &Subscription) -> anyhow::Result<()> {
+        (**self).save_subscription(subscription).await
+    }
+
+    async fn remove_subscription(&self, id: &str) -> anyhow::Result<bool> {
+        (**self).remove_subscription(id).await
+    }
+
+    async fn subscriptions(&self) -> anyhow::Result<Vec<Subscription>> {
+        (**self).subscriptions().await
+    }
+
+    async fn save_delivery(&self, delivery: &Delivery) -> anyhow::Result<()> {
+        (**self).save_delivery(delivery).await
+    }
+
+    async fn delivery(&self, id: &str) -> anyhow::Result<Option<Delivery>> {
+        (**self).delivery(id).await
+    }
+
+    async fn due(&self, now: DateTime<Utc>, limit: usize) -> anyhow::Result<Vec<Delivery>> {
+        (**self).due(now, limit).await
+    }
+
+    async fn dead_letters(&self) -> anyhow::Result<Vec<Delivery>> {
+        (**self).dead_letters().await
+    }
+
+    async fn log_attempt(&self, attempt: &DeliveryAttempt) -> anyhow::Result<()> {
+        (**self).log_attempt(attempt).await
+    }
+
+    async fn attempts(&self, subscription_id: Option<&str>, limit: usize) -> anyhow::Result<Vec<DeliveryAttempt>> {
+        (**self).attempts(subscription_id, limit).await
+    }
+}
+
+/// A store that lives as long as the process, for tests and development.
+#[derive(Default)]
+pub struct MemoryWebhookStore {
+    subscriptions: Mutex<HashMap<String, Subscription>>,
+    deliveries: Mutex<HashMap<String, Delivery>>,
+    attempts: Mutex<Vec<DeliveryAttempt>>,
+}
+
+impl MemoryWebhookStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl WebhookStore for MemoryWebhookStore {
+    async fn save_subscription(&self, subscription: &Subscription) -> anyhow::Result<()> {
+        let mut subscriptions = self.subscriptions.lock().unwrap_or_else(|e| e.into_inner());
+        subscriptions.insert(subscription.id.clone(), subscription.clone());
+        Ok(())
+    }
+
+    async fn remove_subscription(&self, id: &str) -> anyhow::Result<bool> {
+        Ok(self.subscriptions.lock().unwrap_or_else(|e| e.into_inner()).remove(id).is_some())
+    }
+
+    async fn subscriptions(&self) -> anyhow::Result<Vec<Subscription>> {
+        let subscriptions = self.subscriptions.lock().unwrap_or_else(|e| e.into_inner());
+        let mut all: Vec<_> = subscriptions.values().cloned().collect();
+        all.sort_by_key(|s| s.created_at);
+        Ok(all)
+    }
+
+    async fn save_delivery(&self, delivery: &Delivery) -> anyhow::Result<()> {
+        self.deliveries.lock().unwrap_or_else(|e| e.into_inner()).insert(delivery.id.clone(), delivery.clone());
+        Ok(())
+    }
+
+    async fn delivery(&self, id: &str) -> anyhow::Result<Option<Delivery>> {
+        Ok(self.deliveries.lock().unwrap_or_else(|e| e.into_inner()).get(id).cloned())
+    }
+
+    async fn due(&self, now: DateTime<Utc>, limit: usize) -> anyhow::Result<Vec<Delivery>> {
+        let deliveries = self.deliveries.lock().unwrap_or_else(|e| e.into_inner());
+        let mut due: Vec<_> = deliveries
+            .values()
+            .filter(|d| d.status == DeliveryStatus::Pending && d.next_attempt_at <= now)
+            .cloned()
+            .collect();
+        due.sort_by_key(|d| d.next_attempt_at);
+        due.truncate(limit);
+        Ok(due)
+    }
+
+    async fn dead_letters(&self) -> anyhow::Result<Vec<Delivery>> {
+        let deliveries = self.deliveries.lock().unwrap_or_else(|e| e.into_inner());
+        let mut dead: Vec<_> = deliveries.values().filter(|d| d.status == DeliveryStatus::Dead).cloned().collect();
+        dead.sort_by_key(|d| std::cmp::Reverse(d.created_at));
+        Ok(dead)
+    }
+
+    async fn log_attempt(&self, attempt: &DeliveryAttempt) -> anyhow::Result<()> {
+        self.attempts.lock().unwrap_or_else(|e| e.into_inner()).push(attempt.clone());
+        Ok(())
+    }
+
+    async fn attempts(&self, subscription_id: Option<&str>, limit: usize) -> anyhow::Result<Vec<DeliveryAttempt>> {
+        let attempts = self.attempts.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(attempts
+            .iter()
+            .rev()
+            .filter(|a| subscription_id.is_none_or(|id| a.subscription_id == id))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+}
+
+/// Sends the signed POST requests.
+#[async_trait]
+pub trait WebhookTransport: Send + Sync + 'static {
+    /// POSTs `body` and returns the response status. `Err` means no response,
+    /// e.g. a connection error or a timeout.
+    async fn post(&self, url: &str, headers: &[(String, String)], body: Vec<u8>) -> Result<u16, String>;
+}
+
+/// The default transport, over `reqwest` with a 10 second timeout.
+pub struct HttpTransport {
+    client: reqwest::Client,
+}
+
+impl HttpTransport {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .user_agent(concat!("montrs-webhooks/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .unwrap_or_default();
+        Self { client }
+    }
+}
+
+impl Default for HttpTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl WebhookTransport for HttpTransport {
+    async fn post(&self, url: &str, headers: &[(String, String)], body: Vec<u8>) -> Result<u16, String> {
+        let mut request = self.client.post(url).header("Content-Type", "application/json").body(body);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        request.send().await.map(|response| response.status().as_u16()).map_err(|e| e.to_string())
+    }
+}
+
+/// Signs `body` as sent at `timestamp` (unix seconds), in the
+/// [`SIGNATURE_HEADER`] format.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    format!("t={},v1={}", timestamp, hex::encode(mac(secret, timestamp, body).finalize().into_bytes()))
+}
+
+/// Checks a [`SIGNATURE_HEADER`] value on the receiving side. Signatures
+/// older than `tolerance` are rejected, so a captured request cannot be replayed.
+/// A header may carry several `v1` signatures, as senders rotating their
+/// secret do; one matching is enough.
+pub fn verify(secret: &str, header: &str, body: &[u8], tolerance: Duration) -> bool {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", t)) => timestamp = t.parse::<i64>().ok(),
+            Some(("v1", v)) => signatures.extend(hex::decode(v).ok()),
+            _ => {}
+        }
+    }
+    let Some(timestamp) = timestamp else {
+        return false;
+    };
+    let age = Utc::now().timestamp().abs_diff(timestamp);
+    age <= tolerance.as_secs() && signatures.iter().any(|signature| mac(secret, timestamp, body).verify_slice(signature).is_ok())
+}
+
+fn mac(secret: &str, timestamp: i64, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// Publishes events to subscriptions and delivers them.
+///
+/// ```rust,ignore
+/// let webhooks = Arc::new(Webhooks::new(WebhookTables::new(db.clone())).with_rate_limit(5));
+/// webhooks.subscribe(Subscription::new("https://example.com/hooks", secret).with_events(["order.*"])).await?;
+/// webhooks.clone().spawn(Duration::from_secs(1));
+///
+/// webhooks.publish("order.paid", json!({ "order": order.id })).await?;
+/// ```
+pub struct Webhooks {
+    store: Arc<dyn WebhookStore>,
+    transport: Arc<dyn WebhookTransport>,
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    per_second: Option<u32>,
+    limiters: Mutex<HashMap<String, Arc<GovernorLimiter>>>,
+}
+
+impl Webhooks {
+    /// Delivers over HTTP, with 8 attempts per delivery backing off from 30
+    /// seconds up to an hour, and no rate limit.
+    pub fn new(store: impl WebhookStore) -> Self {
+        Self {
+            store: Arc::new(store),
+            transport: Arc::new(HttpTransport::new()),
+            max_attempts: 8,
+            initial_backoff: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(3600),
+            per_second: None,
+            limiters: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sends through `transport` instead of HTTP, e.g. in tests.
+    pub fn with_transport(mut self, transport: impl WebhookTransport) -> Self {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    /// Tries each delivery up to `max_attempts` times. The wait after a failed
+    /// attempt starts at `initial` and doubles up to `max`.
+    pub fn with_retries(mut self, max_attempts: u32, initial: Duration, max: Duration) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Sends at most `per_second` requests per second to each subscription.
+    /// Deliveries over the limit wait for the next tick.
+    pub fn with_rate_limit(mut self, per_second: u32) -> Self {
+        self.per_second = Some(per_second);
+        self
+    }
+
+    pub fn store(&self) -> &Arc<dyn WebhookStore> {
+        &self.store
+    }
+
+    pub async fn subscribe(&self, subscription: Subscription) -> anyhow::Result<Subscription> {
+        self.store.save_subscription(&subscription).await?;
+        Ok(subscription)
+    }
+
+    /// Removes a subscription. Its pending deliveries become dead letters when they come due.
+    pub async fn unsubscribe(&self, id: &str) -> anyhow::Result<bool> {
+        self.limiters.lock().unwrap_or_else(|e| e.into_inner()).remove(id);
+        self.store.remove_subscription(id).await
+    }
+
+    /// Queues `event` for every active subscription it matches and returns the deliveries.
+    pub async fn publish(&self, event: &str, payload: serde_json::Value) -> anyhow::Result<Vec<Delivery>> {
+        let now = Utc::now();
+        let mut queued = Vec::new();
+        for subscription in self.store.subscriptions().await? {
+            if !subscription.active || !subscription.matches(event) {
+                continue;
+            }
+            let delivery = Delivery {
+                id: correlation_id(),
+                subscription_id: subscription.id,
+                event: event.to_string(),
+                payload: payload.clone(),
+                status: DeliveryStatus::Pending,
+                attempts: 0,
+                next_attempt_at: now,
+                last_status: None,
+                last_error: None,
+                created_at: now,
+            };
+            self.store.save_delivery(&delivery).await?;
+            queued.push(delivery);
+        }
+        Ok(queued)
+    }
+
+    /// Sends the deliveries that are due and returns how many were attempted.
+    pub async fn deliver_due(&self) -> anyhow::Result<usize> {
+        let due = self.store.due(Utc::now(), BATCH).await?;
+        if due.is_empty() {
+            return Ok(0);
+        }
+        let subscriptions: HashMap<String, Subscription> =
+            self.store.subscriptions().await?.into_iter().map(|s| (s.id.clone(), s)).collect();
+        let mut attempted = 0;
+        for mut delivery in due {
+            let Some(subscription) = subscriptions.get(&delivery.subscription_id).filter(|s| s.active) else {
+                delivery.status = DeliveryStatus::Dead;
+                delivery.last_error = Some("subscription removed or inactive".to_string());
+                self.store.save_delivery(&delivery).await?;
+                continue;
+            };
+            if !self.allowed(&subscription.id) {
+                continue;
+            }
+            self.attempt(subscription, &mut delivery).await?;
+            attempted += 1;
+        }
+        Ok(attempted)
+    }
+
+    fn allowed(&self, subscription_id: &str) -> bool {
+        let Some(per_second) = self.per_second else {
+            return true;
+        };
+        let limiter = self
+            .limiters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(subscription_id.to_string())
+            .or_insert_with(|| Arc::new(GovernorLimiter::new(per_second)))
+            .clone();
+        limiter.check()
+    }
+
+    async fn attempt(&self, subscription: &Subscription, delivery: &mut Delivery) -> anyhow::Result<()> {
+        let body = serde_json::to_vec(&serde_json::json!({
+            "id": delivery.id,
+            "event": delivery.event,
+            "created_at": delivery.created_at,
+            "data": delivery.payload,
+        }))?;
+        let now = Utc::now();
+        let headers = vec![
+            (SIGNATURE_HEADER.to_string(), sign(&subscription.secret, now.timestamp(), &body)),
+            (EVENT_HEADER.to_string(), delivery.event.clone()),
+            (DELIVERY_HEADER.to_string(), delivery.id.clone()),
+        ];
+        let started = Instant::now();
+        let outcome = self.transport.post(&subscription.url, &headers, body).await;
+        delivery.attempts += 1;
+
+        let attempt = DeliveryAttempt {
+            delivery_id: delivery.id.clone(),
+            subscription_id: subscription.id.clone(),
+            event: delivery.event.clone(),
+            url: subscription.url.clone(),
+            attempt: delivery.attempts,
+            at: now,
+            status: outcome.as_ref().ok().copied(),
+            error: match &outcome {
+                Ok(status) if !(200..300).contains(status) => Some(format!("endpoint answered {}", status)),
+                Ok(_) => None,
+                Err(e) => Some(e.clone()),
+            },
+            duration_ms: started.elapsed().as_millis() as u64,
+        };
+        self.store.log_attempt(&attempt).await?;
+
+        delivery.last_status = attempt.status;
+        delivery.last_error = attempt.error.clone();
+        if attempt.succeeded() {
+            delivery.status = DeliveryStatus::Delivered;
+        } else if delivery.attempts >= self.max_attempts {
+            delivery.status = DeliveryStatus::Dead;
+            tracing::warn!(delivery = %delivery.id, url = %subscription.url, event = %delivery.event, attempts = delivery.attempts, "webhook delivery dead-lettered");
+        } else {
+            delivery.next_attempt_at = now + self.backoff(delivery.attempts);
+        }
+        self.store.save_delivery(delivery).await
+    }
+
+    /// The wait after the `attempts`th failed attempt.
+    fn backoff(&self, attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+
+    /// Queues a dead letter again with a fresh set of attempts.
+    pub async fn redeliver(&self, delivery_id: &str) -> anyhow::Result<Option<Delivery>> {
+        let Some(mut delivery) = self.store.delivery(delivery_id).await? else {
+            return Ok(None);
+        };
+        delivery.status = DeliveryStatus::Pending;
+        delivery.attempts = 0;
+        delivery.next_attempt_at = Utc::now();
+        self.store.save_delivery(&delivery).await?;
+        Ok(Some(delivery))
+    }
+
+    /// Runs [`deliver_due`](Self::deliver_due) every `interval` on the Tokio runtime.
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.deliver_due().await {
+                    tracing::warn!(error = %e, "webhook delivery tick failed");
+                }
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+}
+
+/// A loader answering with the newest 100 delivery attempts, for a debugging page.
+///
+/// ```rust,ignore
+/// fn loader(&self) -> Self::Loader {
+///     DeliveryLogLoader::new(self.webhooks.clone())
+/// }
+/// ```
+pub struct DeliveryLogLoader {
+    webhooks: Arc<Webhooks>,
+}
+
+impl DeliveryLogLoader {
+    pub fn new(webhooks: Arc<Webhooks>) -> Self {
+        Self { webhooks }
+    }
+}
+
+#[async_trait]
+impl<P: crate::RouteParams, C: crate::AppConfig> crate::RouteLoader<P, C> for DeliveryLogLoader {
+    type Output = Vec<DeliveryAttempt>;
+
+    async fn load(&self, _ctx: crate::RouteContext<'_, C>, _params: P) -> Result<Vec<DeliveryAttempt>, crate::RouteError> {
+        self.webhooks.store.attempts(None, 100).await.map_err(|e| crate::RouteError::InternalError(e.to_string()))
+    }
+
+    fn description(&self) -> &'static str {
+        "Webhook delivery log: the newest attempts with status, error and duration"
+    }
+}
+
+/// A loader answering with the dead-lettered deliveries.
+pub struct DeadLetterLoader {
+    webhooks: Arc<Webhooks>,
+}
+
+impl DeadLetterLoader {
+    pub fn new(webhooks: Arc<Webhooks>) -> Self {
+        Self { webhooks }
+    }
+}
+
+#[async_trait]
+impl<P: crate::RouteParams, C: crate::AppConfig> crate::RouteLoader<P, C> for DeadLetterLoader {
+    type Output = Vec<Delivery>;
+
+    async fn load(&self, _ctx: crate::RouteContext<'_, C>, _params: P) -> Result<Vec<Delivery>, crate::RouteError> {
+        self.webhooks.store.dead_letters().await.map_err(|e| crate::RouteError::InternalError(e.to_string()))
+    }
+
+    fn description(&self) -> &'static str {
+        "Webhook dead letters: deliveries that ran out of attempts"
+    }
+}
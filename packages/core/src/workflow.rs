@@ -0,0 +1,397 @@
+//! montrs-core/src/workflow.rs: Sagas for long-running business processes.
+//! A [`Saga`] is an ordered list of steps, each with a compensation that undoes
+//! it. [`Workflows`] runs a saga and saves its state to a [`WorkflowStore`]
+//! after every step, so a process that crashed halfway resumes where it
+//! stopped. When a step fails for good, the steps that already ran are
+//! compensated newest first. `montrs_orm::workflow::WorkflowTable` keeps the
+//! records in the application database.
+
+use crate::crash::correlation_id;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Where a workflow is in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkflowStatus {
+    /// Steps are running forward.
+    Running,
+    /// A step failed; completed steps are being undone.
+    Compensating,
+    /// Every step ran.
+    Completed,
+    /// A step failed and every completed step was undone.
+    Compensated,
+    /// A compensation failed. The state needs someone to look at it.
+    Failed,
+}
+
+impl WorkflowStatus {
+    /// Whether the workflow still has work to do.
+    pub fn is_active(self) -> bool {
+        matches!(self, WorkflowStatus::Running | WorkflowStatus::Compensating)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WorkflowStatus::Running => "running",
+            WorkflowStatus::Compensating => "compensating",
+            WorkflowStatus::Completed => "completed",
+            WorkflowStatus::Compensated => "compensated",
+            WorkflowStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(status: &str) -> Option<Self> {
+        [Self::Running, Self::Compensating, Self::Completed, Self::Compensated, Self::Failed]
+            .into_iter()
+            .find(|s| s.as_str() == status)
+    }
+}
+
+/// The persisted state of one workflow.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowRecord {
+    pub id: String,
+    /// The [`Saga::name`] it runs.
+    pub saga: String,
+    pub status: WorkflowStatus,
+    /// Steps that completed and have not been compensated. While running, the
+    /// index of the next step; while compensating, how many are left to undo.
+    pub step: usize,
+    /// The saga state after the last completed step or compensation.
+    pub state: serde_json::Value,
+    /// Why the workflow is compensating or failed.
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Passed to steps so they can derive idempotency keys from the workflow.
+#[derive(Debug, Clone)]
+pub struct StepContext {
+    pub workflow_id: String,
+    /// Starts at 1 and counts retries of the same step.
+    pub attempt: u32,
+}
+
+/// One unit of a saga. A step may run again after a crash, so it should be
+/// idempotent, e.g. by keying its side effects on [`StepContext::workflow_id`].
+///
+/// ```rust,ignore
+/// struct ReserveStock;
+///
+/// #[async_trait]
+/// impl SagaStep<Order> for ReserveStock {
+///     fn name(&self) -> &'static str {
+///         "reserve_stock"
+///     }
+///     async fn run(&self, ctx: &StepContext, order: &mut Order) -> anyhow::Result<()> {
+///         order.reservation = Some(inventory::reserve(&ctx.workflow_id, &order.items).await?);
+///         Ok(())
+///     }
+///     async fn compensate(&self, _ctx: &StepContext, order: &mut Order) -> anyhow::Result<()> {
+///         if let Some(id) = order.reservation.take() {
+///             inventory::release(&id).await?;
+///         }
+///         Ok(())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait SagaStep<S>: Send + Sync + 'static {
+    fn name(&self) -> &'static str;
+
+    async fn run(&self, ctx: &StepContext, state: &mut S) -> anyhow::Result<()>;
+
+    /// Undoes [`run`](Self::run). The default does nothing, for steps without side effects.
+    async fn compensate(&self, _ctx: &StepContext, _state: &mut S) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// An ordered list of steps sharing a state `S`.
+pub struct Saga<S> {
+    name: &'static str,
+    steps: Vec<Box<dyn SagaStep<S>>>,
+    retries: u32,
+    retry_delay: Duration,
+}
+
+impl<S: Serialize + DeserializeOwned + Send + Sync + 'static> Saga<S> {
+    /// `name` identifies the saga's records in the store, so keep it stable across releases.
+    pub fn new(name: &'static str) -> Self {
+        Self { name, steps: Vec::new(), retries: 0, retry_delay: Duration::from_secs(1) }
+    }
+
+    pub fn step(mut self, step: impl SagaStep<S>) -> Self {
+        self.steps.push(Box::new(step));
+        self
+    }
+
+    /// Retries a failing step or compensation `times` more, `delay` apart (default: no retries).
+    pub fn with_retries(mut self, times: u32, delay: Duration) -> Self {
+        self.retries = times;
+        self.retry_delay = delay;
+        self
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The step names, in order.
+    pub fn steps(&self) -> Vec<&'static str> {
+        self.steps.iter().map(|step| step.name()).collect()
+    }
+}
+
+/// Keeps workflow records. Saves overwrite the record with the same ID.
+#[async_trait]
+pub trait WorkflowStore: Send + Sync + 'static {
+    async fn save(&self, record: &WorkflowRecord) -> anyhow::Result<()>;
+
+    async fn get(&self, id: &str) -> anyhow::Result<Option<WorkflowRecord>>;
+
+    /// Records of `saga` that are running or compensating.
+    async fn active(&self, saga: &str) -> anyhow::Result<Vec<WorkflowRecord>>;
+}
+
+#[async_trait]
+impl<T: WorkflowStore> WorkflowStore for Arc<T> {
+    async fn save(&self, record: &WorkflowRecord) -> anyhow::Result<()> {
+        (**self).save(record).await
+    }
+
+    async fn get(&self, id: &str) -> anyhow::Result<Option<WorkflowRecord>> {
+        (**self).get(id).await
+    }
+
+    async fn active(&self, saga: &str) -> anyhow::Result<Vec<WorkflowRecord>> {
+        (**self).active(saga).await
+    }
+}
+
+/// A store that lives as long as the process, for tests and development.
+#[derive(Default)]
+pub struct MemoryStore {
+    records: Mutex<HashMap<String, WorkflowRecord>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl WorkflowStore for MemoryStore {
+    async fn save(&self, record: &WorkflowRecord) -> anyhow::Result<()> {
+        self.records.lock().unwrap_or_else(|e| e.into_inner()).insert(record.id.clone(), record.clone());
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> anyhow::Result<Option<WorkflowRecord>> {
+        Ok(self.records.lock().unwrap_or_else(|e| e.into_inner()).get(id).cloned())
+    }
+
+    async fn active(&self, saga: &str) -> anyhow::Result<Vec<WorkflowRecord>> {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        let mut active: Vec<_> = records.values().filter(|r| r.saga == saga && r.status.is_active()).cloned().collect();
+        active.sort_by_key(|r| r.started_at);
+        Ok(active)
+    }
+}
+
+/// Runs sagas against a store.
+///
+/// ```rust,ignore
+/// let workflows = Workflows::new(WorkflowTable::new(db.clone()));
+/// let checkout = Saga::new("checkout").step(ReserveStock).step(ChargeCard).step(ShipOrder);
+///
+/// // At start-up, finish what the last process left behind.
+/// workflows.resume(&checkout).await?;
+///
+/// let record = workflows.start(&checkout, order).await?;
+/// ```
+#[derive(Clone)]
+pub struct Workflows {
+    store: Arc<dyn WorkflowStore>,
+}
+
+impl Workflows {
+    pub fn new(store: impl WorkflowStore) -> Self {
+        Self { store: Arc::new(store) }
+    }
+
+    /// Runs `saga` from its first step with `state` and returns the final
+    /// record: `Completed`, `Compensated` or `Failed`. Store errors are returned
+    /// as they happen; the workflow then resumes from its last saved step.
+    pub async fn start<S>(&self, saga: &Saga<S>, state: S) -> anyhow::Result<WorkflowRecord>
+    where
+        S: Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        let now = Utc::now();
+        let mut record = WorkflowRecord {
+            id: correlation_id(),
+            saga: saga.name.to_string(),
+            status: WorkflowStatus::Running,
+            step: 0,
+            state: serde_json::to_value(&state)?,
+            error: None,
+            started_at: now,
+            updated_at: now,
+        };
+        self.store.save(&record).await?;
+        self.drive(saga, &mut record, state).await?;
+        Ok(record)
+    }
+
+    /// Continues every running or compensating workflow of `saga`, oldest first.
+    /// A step that was interrupted runs again.
+    pub async fn resume<S>(&self, saga: &Saga<S>) -> anyhow::Result<Vec<WorkflowRecord>>
+    where
+        S: Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        let mut resumed = Vec::new();
+        for mut record in self.store.active(saga.name).await? {
+            tracing::info!(workflow = %record.id, saga = saga.name, step = record.step, status = record.status.as_str(), "resuming workflow");
+            let state: S = serde_json::from_value(record.state.clone())?;
+            self.drive(saga, &mut record, state).await?;
+            resumed.push(record);
+        }
+        Ok(resumed)
+    }
+
+    /// The current record of workflow `id`.
+    pub async fn status(&self, id: &str) -> anyhow::Result<Option<WorkflowRecord>> {
+        self.store.get(id).await
+    }
+
+    async fn drive<S>(&self, saga: &Saga<S>, record: &mut WorkflowRecord, mut state: S) -> anyhow::Result<()>
+    where
+        S: Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        while record.status == WorkflowStatus::Running {
+            let Some(step) = saga.steps.get(record.step) else {
+                record.status = WorkflowStatus::Completed;
+                self.save(record, &state).await?;
+                return Ok(());
+            };
+            match self.attempt(saga, &record.id, step.as_ref(), false, &mut state).await {
+                Ok(()) => record.step += 1,
+                Err(e) => {
+                    tracing::warn!(workflow = %record.id, saga = saga.name, step = step.name(), error = %e, "workflow step failed; compensating");
+                    record.status = WorkflowStatus::Compensating;
+                    record.error = Some(format!("{}: {:#}", step.name(), e));
+                }
+            }
+            self.save(record, &state).await?;
+        }
+
+        while record.status == WorkflowStatus::Compensating {
+            let Some(index) = record.step.checked_sub(1) else {
+                record.status = WorkflowStatus::Compensated;
+                self.save(record, &state).await?;
+                return Ok(());
+            };
+            let Some(step) = saga.steps.get(index) else {
+                anyhow::bail!("workflow {} has completed step {}, but saga `{}` has {} steps", record.id, index, saga.name, saga.steps.len());
+            };
+            match self.attempt(saga, &record.id, step.as_ref(), true, &mut state).await {
+                Ok(()) => record.step = index,
+                Err(e) => {
+                    tracing::error!(workflow = %record.id, saga = saga.name, step = step.name(), error = %e, "workflow compensation failed");
+                    record.status = WorkflowStatus::Failed;
+                    let cause = record.error.take().map(|cause| format!(" after {}", cause)).unwrap_or_default();
+                    record.error = Some(format!("compensating {}: {:#}{}", step.name(), e, cause));
+                }
+            }
+            self.save(record, &state).await?;
+        }
+        Ok(())
+    }
+
+    /// Runs or compensates `step` with the saga's retries. Changes to the state
+    /// are only kept when an attempt succeeds.
+    async fn attempt<S>(&self, saga: &Saga<S>, id: &str, step: &dyn SagaStep<S>, compensate: bool, state: &mut S) -> anyhow::Result<()>
+    where
+        S: Serialize + DeserializeOwned + Send + Sync + 'static,
+    {
+        let mut attempt = 1;
+        loop {
+            let ctx = StepContext { workflow_id: id.to_string(), attempt };
+            let mut scratch: S = serde_json::from_value(serde_json::to_value(&*state)?)?;
+            let outcome = match compensate {
+                false => step.run(&ctx, &mut scratch).await,
+                true => step.compensate(&ctx, &mut scratch).await,
+            };
+            match outcome {
+                Ok(()) => {
+                    *state = scratch;
+                    return Ok(());
+                }
+                Err(e) if attempt > saga.retries => return Err(e),
+                Err(_) => {
+                    attempt += 1;
+                    tokio::time::sleep(saga.retry_delay).await;
+                }
+            }
+        }
+    }
+
+    async fn save<S: Serialize>(&self, record: &mut WorkflowRecord, state: &S) -> anyhow::Result<()> {
+        record.state = serde_json::to_value(state)?;
+        record.updated_at = Utc::now();
+        self.store.save(record).await
+    }
+}
+
+/// Route params for [`WorkflowStatusLoader`]: the workflow ID.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkflowParams {
+    pub id: String,
+}
+
+impl crate::RouteParams for WorkflowParams {}
+
+/// A loader answering with the record of the workflow named by the `id` param,
+/// for a status page or an API clients poll.
+///
+/// ```rust,ignore
+/// // "/orders/checkout/:id"
+/// fn loader(&self) -> Self::Loader {
+///     WorkflowStatusLoader::new(self.workflows.clone())
+/// }
+/// ```
+pub struct WorkflowStatusLoader {
+    workflows: Workflows,
+}
+
+impl WorkflowStatusLoader {
+    pub fn new(workflows: Workflows) -> Self {
+        Self { workflows }
+    }
+}
+
+#[async_trait]
+impl<C: crate::AppConfig> crate::RouteLoader<WorkflowParams, C> for WorkflowStatusLoader {
+    type Output = WorkflowRecord;
+
+    async fn load(&self, _ctx: crate::RouteContext<'_, C>, params: WorkflowParams) -> Result<WorkflowRecord, crate::RouteError> {
+        match self.workflows.status(&params.id).await {
+            Ok(Some(record)) => Ok(record),
+            Ok(None) => Err(crate::RouteError::NotFound),
+            Err(e) => Err(crate::RouteError::InternalError(e.to_string())),
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        "Workflow status: saga, current step, state and error"
+    }
+}
@@ -0,0 +1,66 @@
+use montrs_core::action_error::VALIDATION_FAILED;
+use montrs_core::{
+    ActionError, AppConfig, EnvConfig, ErrorMessages, FieldError, MockDefinition, Mocks, RouteContext, RouteError,
+    Router, ValidationError,
+};
+use serde_json::json;
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[test]
+fn test_validation_errors_map_onto_fields_with_localized_messages() {
+    let error = ActionError::validation(&[
+        ValidationError::MinLength { field: "name", min: 3, actual: 1 },
+        ValidationError::InvalidEmail { field: "email" },
+    ]);
+    assert_eq!((error.code.as_str(), error.status), (VALIDATION_FAILED, 422));
+    assert_eq!(error.message, "name is too short: 1 (min 3); email must be a valid email");
+    let name = error.field_errors("name").next().unwrap();
+    assert_eq!((name.code.as_str(), &name.params["min"]), ("VAL_MIN_LENGTH", &json!(3)));
+
+    let messages = ErrorMessages::new().with("VAL_MIN_LENGTH", "{field}: mindestens {min} Zeichen");
+    assert_eq!(messages.field(&error, "name"), ["name: mindestens 3 Zeichen"]);
+    assert_eq!(messages.field(&error, "email"), ["email must be a valid email"], "codes without a message keep the server's");
+    assert!(messages.field(&error, "age").is_empty());
+
+    // The wire format: the response body of a failed action, read back on the client.
+    let body = json!({ "error": error.message, "action_error": error }).to_string();
+    assert_eq!(ActionError::from_body(422, body.as_bytes()), error);
+    let guard = ActionError::from_body(403, br#"{"error":"Forbidden: admin"}"#);
+    assert_eq!((guard.code.as_str(), guard.message.as_str(), guard.status), ("HTTP_403", "Forbidden: admin", 403));
+}
+
+#[tokio::test]
+async fn test_mocked_actions_fail_with_typed_errors() {
+    let taken = ActionError::new("EMAIL_TAKEN", "This email is already registered.")
+        .with_field(FieldError::new("email", "EMAIL_TAKEN", "Already registered."));
+    let definition: MockDefinition = serde_json::from_value(json!({
+        "route": "/signup",
+        "action": { "status": 409, "body": taken },
+    }))
+    .unwrap();
+    let mut router = Router::<TestConfig>::new();
+    router.set_mocks(Mocks::new().with_definition(definition));
+
+    let ctx = RouteContext { config: &TestConfig, env: &TestEnv };
+    match router.act("/signup", ctx, json!({}), json!({})).await {
+        Err(RouteError::Action(error)) => {
+            assert_eq!(*error, taken.clone().with_status(409));
+            assert_eq!(RouteError::Action(error).status(), 409);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
@@ -0,0 +1,142 @@
+use async_trait::async_trait;
+use leptos::prelude::*;
+use montrs_core::analytics::{Analytics, AnalyticsLoader, AnalyticsSink, UsageWindow};
+use montrs_core::{
+    AppConfig, EnvConfig, Route, RouteAction, RouteContext, RouteError, RouteLoader, RouteParams, RouteView, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct UserParams {
+    id: u32,
+}
+impl RouteParams for UserParams {}
+
+struct UserLoader;
+#[async_trait]
+impl RouteLoader<UserParams, TestConfig> for UserLoader {
+    type Output = String;
+    async fn load(&self, _ctx: RouteContext<'_, TestConfig>, params: UserParams) -> Result<String, RouteError> {
+        if params.id == 0 {
+            return Err(RouteError::NotFound);
+        }
+        Ok(format!("user {}", params.id))
+    }
+}
+
+struct NoAction;
+#[async_trait]
+impl RouteAction<UserParams, TestConfig> for NoAction {
+    type Input = ();
+    type Output = ();
+    async fn act(&self, _ctx: RouteContext<'_, TestConfig>, _params: UserParams, _input: ()) -> Result<(), RouteError> {
+        Err(RouteError::InternalError("read only".to_string()))
+    }
+}
+
+struct EmptyView;
+impl RouteView for EmptyView {
+    fn render(&self) -> impl IntoView {
+        view! { <div></div> }
+    }
+}
+
+struct UserRoute;
+impl Route<TestConfig> for UserRoute {
+    type Params = UserParams;
+    type Loader = UserLoader;
+    type Action = NoAction;
+    type View = EmptyView;
+
+    fn path() -> &'static str {
+        "/users/:id"
+    }
+    fn loader(&self) -> Self::Loader {
+        UserLoader
+    }
+    fn action(&self) -> Self::Action {
+        NoAction
+    }
+    fn view(&self) -> Self::View {
+        EmptyView
+    }
+}
+
+struct Recorder(Arc<Mutex<Vec<UsageWindow>>>);
+
+#[async_trait]
+impl AnalyticsSink for Recorder {
+    async fn flush(&self, window: &UsageWindow) -> anyhow::Result<()> {
+        self.0.lock().unwrap().push(window.clone());
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_router_counts_calls_by_pattern_and_status() {
+    let windows = Arc::new(Mutex::new(Vec::new()));
+    let analytics = Arc::new(Analytics::new().with_sink(Recorder(windows.clone())));
+    let mut router = Router::<TestConfig>::new();
+    router.register(UserRoute);
+    router.set_analytics(analytics.clone());
+    let ctx = || RouteContext { config: &TestConfig, env: &TestEnv };
+
+    router.load("/users/1", ctx(), serde_json::json!({})).await.unwrap();
+    router.load("/users/2", ctx(), serde_json::json!({})).await.unwrap();
+    router.load("/users/0", ctx(), serde_json::json!({})).await.unwrap_err();
+    router.act("/users/1", ctx(), serde_json::json!({}), serde_json::json!(null)).await.unwrap_err();
+    // Unknown paths have no pattern and are not counted.
+    router.load("/nope", ctx(), serde_json::json!({})).await.unwrap_err();
+
+    let totals = analytics.totals();
+    assert_eq!(totals.len(), 2);
+    let load = &totals[0];
+    assert_eq!((load.route.as_str(), load.operation.as_str(), load.hits), ("/users/:id", "load", 3));
+    assert_eq!((load.status.success, load.status.client_error), (2, 1));
+    assert_eq!(load.latency_buckets.iter().sum::<u64>(), 3);
+    assert_eq!(totals[1].status.server_error, 1);
+    assert_eq!(totals[1].error_rate(), 100.0);
+
+    let window = analytics.flush().await;
+    assert_eq!(window.routes, totals);
+    assert_eq!(windows.lock().unwrap().len(), 1);
+    assert!(analytics.flush().await.routes.is_empty(), "a flush starts a new window");
+    assert_eq!(windows.lock().unwrap().len(), 1, "empty windows are not sent");
+    assert_eq!(analytics.totals(), totals, "totals outlive the windows");
+}
+
+#[tokio::test]
+async fn test_due_window_is_flushed_in_the_background_and_loader_serves_totals() {
+    let windows = Arc::new(Mutex::new(Vec::new()));
+    let analytics = Arc::new(
+        Analytics::new()
+            .with_sink(Recorder(windows.clone()))
+            .with_flush_interval(Duration::ZERO),
+    );
+    analytics.record("/cart", "act", 200, Duration::from_millis(30));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(windows.lock().unwrap().len(), 1);
+
+    let loader = AnalyticsLoader::new(analytics.clone());
+    let ctx = RouteContext { config: &TestConfig, env: &TestEnv };
+    let totals = RouteLoader::<UserParams, TestConfig>::load(&loader, ctx, UserParams { id: 1 }).await.unwrap();
+    assert_eq!(totals[0].route, "/cart");
+    assert_eq!(totals[0].percentile_ms(50.0), 50);
+}
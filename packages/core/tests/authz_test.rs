@@ -0,0 +1,155 @@
+use montrs_core::{
+    AppConfig, EnvConfig, EnvError, Permission, Principal, Rbac, Route, RouteAction, RouteContext, RouteError,
+    RouteLoader, RouteParams, RouteView, Router, meta,
+};
+use async_trait::async_trait;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+/// The signed-in user and their comma-separated roles, as the session would give them.
+#[derive(Clone)]
+struct TestEnv {
+    user: Option<&'static str>,
+    roles: &'static str,
+}
+impl EnvConfig for TestEnv {
+    fn get_var(&self, key: &str) -> Result<String, EnvError> {
+        match key {
+            "USER" => self.user.map(str::to_string).ok_or_else(|| EnvError::MissingKey(key.to_string())),
+            _ => Ok(self.roles.to_string()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct NoParams {}
+impl RouteParams for NoParams {}
+
+struct Echo;
+#[async_trait]
+impl RouteLoader<NoParams, TestConfig> for Echo {
+    type Output = String;
+    async fn load(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams) -> Result<Self::Output, RouteError> {
+        Ok("ok".to_string())
+    }
+}
+#[async_trait]
+impl RouteAction<NoParams, TestConfig> for Echo {
+    type Input = String;
+    type Output = String;
+    async fn act(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams, input: String) -> Result<String, RouteError> {
+        Ok(input)
+    }
+}
+impl RouteView for Echo {
+    fn render(&self) -> impl IntoView {
+        view! { <div>"echo"</div> }
+    }
+}
+
+macro_rules! route {
+    ($name:ident, $path:literal) => {
+        struct $name;
+        impl Route<TestConfig> for $name {
+            type Params = NoParams;
+            type Loader = Echo;
+            type Action = Echo;
+            type View = Echo;
+            fn path() -> &'static str {
+                $path
+            }
+            fn loader(&self) -> Echo {
+                Echo
+            }
+            fn action(&self) -> Echo {
+                Echo
+            }
+            fn view(&self) -> Echo {
+                Echo
+            }
+        }
+    };
+}
+
+route!(HealthRoute, "/health");
+route!(UsersAdminRoute, "/admin/users");
+route!(RefundRoute, "/orders/refund");
+
+fn router() -> Router<TestConfig> {
+    let mut router = Router::<TestConfig>::new();
+    router.register(HealthRoute);
+    router.register(UsersAdminRoute).requires(Permission::Admin);
+    router
+        .register(RefundRoute)
+        .with_meta(meta::OWNER, "payments")
+        .requires(Permission::role("support"))
+        .requires("orders:refund");
+    router
+}
+
+fn rbac() -> Rbac<TestConfig> {
+    Rbac::new(|ctx: &RouteContext<'_, TestConfig>| {
+        let user = ctx.env.get_var("USER").ok()?;
+        let roles = ctx.env.get_var("ROLES").ok()?;
+        Some(roles.split(',').filter(|r| !r.is_empty()).fold(Principal::new(user), Principal::with_role))
+    })
+    .grant("support", ["orders:read"])
+    .grant("lead", ["orders:refund"])
+}
+
+#[tokio::test]
+async fn test_requirements_are_enforced_by_the_rbac_guard() {
+    let mut router = router();
+    let config = TestConfig;
+    let ctx = |env| RouteContext { config: &config, env };
+    let anonymous = TestEnv { user: None, roles: "" };
+    let support = TestEnv { user: Some("ada"), roles: "support" };
+    let lead = TestEnv { user: Some("ada"), roles: "lead" };
+    let support_lead = TestEnv { user: Some("ada"), roles: "support,lead" };
+    let admin = TestEnv { user: Some("grace"), roles: "admin" };
+
+    // Without a guard, routes with requirements are closed.
+    assert!(matches!(router.load("/admin/users", ctx(&admin), json!({})).await, Err(RouteError::Forbidden(_))));
+
+    router.set_rbac(rbac());
+    assert_eq!(router.load("/health", ctx(&anonymous), json!({})).await.unwrap(), json!("ok"));
+    assert!(matches!(router.load("/admin/users", ctx(&anonymous), json!({})).await, Err(RouteError::Unauthorized)));
+    let denied = router.load("/admin/users", ctx(&support), json!({})).await.unwrap_err();
+    assert_eq!((denied.status(), denied.to_string()), (403, "Forbidden: requires role:admin".to_string()));
+    assert!(router.load("/admin/users", ctx(&admin), json!({})).await.is_ok());
+
+    // Every requirement must hold: the role itself, and a permission granted to one of the roles.
+    let refund = |env| router.act("/orders/refund", ctx(env), json!({}), json!("order 7"));
+    assert!(matches!(refund(&support).await, Err(RouteError::Forbidden(p)) if p == "orders:refund"));
+    assert!(matches!(refund(&lead).await, Err(RouteError::Forbidden(p)) if p == "role:support"));
+    assert_eq!(refund(&support_lead).await.unwrap(), json!("order 7"));
+}
+
+#[test]
+fn test_requirements_are_exported_to_the_spec_and_openapi() {
+    let router = router();
+    let spec = router.spec();
+    assert_eq!(spec.routes["/orders/refund"].meta[meta::REQUIRES], "role:support,orders:refund");
+    assert_eq!(
+        Permission::from_meta(&spec.routes["/admin/users"].meta),
+        [Permission::Admin],
+        "`role:admin` reads back as `Permission::Admin`"
+    );
+    assert!(!spec.routes["/health"].meta.contains_key(meta::REQUIRES));
+
+    let doc = spec.to_openapi("shop", "1.0.0");
+    assert_eq!(doc["components"]["securitySchemes"]["bearerAuth"]["scheme"], "bearer");
+    let refund = &doc["paths"]["/orders/refund"]["post"];
+    assert_eq!(refund["security"], json!([{ "bearerAuth": [] }]));
+    assert_eq!(refund["x-montrs-requires"], json!(["role:support", "orders:refund"]));
+    assert!(refund["responses"]["403"].is_object());
+    assert!(doc["paths"]["/health"]["get"].get("security").is_none());
+}
@@ -0,0 +1,129 @@
+use async_trait::async_trait;
+use leptos::prelude::*;
+use montrs_core::{
+    AppConfig, BodyFormat, EnvConfig, RawBody, Route, RouteAction, RouteContext, RouteError,
+    RouteLoader, RouteParams, RouteView, Router,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct UploadParams {
+    device: String,
+}
+impl RouteParams for UploadParams {}
+
+struct UploadLoader;
+#[async_trait]
+impl RouteLoader<UploadParams, TestConfig> for UploadLoader {
+    type Output = ();
+    async fn load(
+        &self,
+        _ctx: RouteContext<'_, TestConfig>,
+        _params: UploadParams,
+    ) -> Result<Self::Output, RouteError> {
+        Ok(())
+    }
+}
+
+struct UploadAction;
+#[async_trait]
+impl RouteAction<UploadParams, TestConfig> for UploadAction {
+    type Input = RawBody;
+    type Output = usize;
+    async fn act(
+        &self,
+        _ctx: RouteContext<'_, TestConfig>,
+        _params: UploadParams,
+        input: Self::Input,
+    ) -> Result<Self::Output, RouteError> {
+        Ok(input.as_bytes().len())
+    }
+
+    fn body_format(&self) -> BodyFormat {
+        BodyFormat::binary(["application/octet-stream", "image/*"])
+    }
+}
+
+struct UploadView;
+impl RouteView for UploadView {
+    fn render(&self) -> impl IntoView {
+        view! { <div>"Upload"</div> }
+    }
+}
+
+struct UploadRoute;
+impl Route<TestConfig> for UploadRoute {
+    type Params = UploadParams;
+    type Loader = UploadLoader;
+    type Action = UploadAction;
+    type View = UploadView;
+
+    fn path() -> &'static str {
+        "/devices/:device/firmware"
+    }
+    fn loader(&self) -> Self::Loader {
+        UploadLoader
+    }
+    fn action(&self) -> Self::Action {
+        UploadAction
+    }
+    fn view(&self) -> Self::View {
+        UploadView
+    }
+}
+
+#[test]
+fn test_body_format_accepts() {
+    let format = BodyFormat::binary(["image/*"]);
+    assert!(format.accepts("image/png"));
+    assert!(format.accepts("IMAGE/JPEG; q=0.9"));
+    assert!(!format.accepts("application/json"));
+    assert!(BodyFormat::Json.accepts("application/json; charset=utf-8"));
+}
+
+#[tokio::test]
+async fn test_binary_action_body() {
+    let mut router = Router::<TestConfig>::new();
+    router.register(UploadRoute);
+
+    let config = TestConfig;
+    let env = TestEnv;
+    let params = serde_json::json!({ "device": "sensor-1" });
+    let body = [0u8, 159, 146, 150, 255];
+
+    let ctx = RouteContext { config: &config, env: &env };
+    let res = router
+        .act_body("/devices/:device/firmware", ctx, params.clone(), "application/octet-stream", &body)
+        .await
+        .unwrap();
+    assert_eq!(res, serde_json::json!(5));
+
+    let ctx = RouteContext { config: &config, env: &env };
+    let err = router
+        .act_body("/devices/:device/firmware", ctx, params, "text/plain", &body)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, RouteError::UnsupportedMediaType(_)));
+
+    let spec = router.spec();
+    let meta = spec.routes.get("/devices/:device/firmware").unwrap();
+    assert_eq!(
+        meta.action_body.content_types(),
+        vec!["application/octet-stream".to_string(), "image/*".to_string()]
+    );
+}
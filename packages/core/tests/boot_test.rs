@@ -0,0 +1,166 @@
+use async_trait::async_trait;
+use montrs_core::{
+    AppConfig, AppSpec, BootBudget, BootError, BootPhaseKind, BootTrace, BudgetAction, EnvConfig, Plate,
+    PlateContext, Router,
+};
+use montrs_core::boot::plate_levels;
+use std::time::Duration;
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+struct SlowPlate;
+#[async_trait]
+impl Plate<TestConfig> for SlowPlate {
+    fn name(&self) -> &'static str {
+        "slow"
+    }
+    async fn init(&self, _ctx: &mut PlateContext<TestConfig>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        Ok(())
+    }
+}
+
+/// Sleeps during init; depends on the listed plates.
+struct IoPlate(&'static str, Vec<&'static str>);
+#[async_trait]
+impl Plate<TestConfig> for IoPlate {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+    fn dependencies(&self) -> Vec<&'static str> {
+        self.1.clone()
+    }
+    async fn init(&self, _ctx: &mut PlateContext<TestConfig>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        Ok(())
+    }
+}
+
+struct BrokenPlate;
+#[async_trait]
+impl Plate<TestConfig> for BrokenPlate {
+    fn name(&self) -> &'static str {
+        "broken"
+    }
+    async fn init(&self, _ctx: &mut PlateContext<TestConfig>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err("database unreachable".into())
+    }
+    fn register_routes(&self, _router: &mut Router<TestConfig>) {
+        panic!("routes must not be registered after a failed init");
+    }
+}
+
+#[tokio::test]
+async fn test_boot_records_each_phase() {
+    let mut trace = BootTrace::new();
+    let env = trace.measure(BootPhaseKind::Env, "env", || TestEnv);
+    let mut spec = AppSpec::new(TestConfig, env).with_plate(Box::new(SlowPlate));
+
+    let trace = spec.boot_with(trace).await.unwrap();
+    let names: Vec<_> = trace.phases.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, vec!["env", "slow", "router"]);
+    assert_eq!(trace.slowest().unwrap().name, "slow");
+    assert!(trace.phases[1].duration_us >= 20_000);
+    assert!(trace.total_us >= trace.phases[1].duration_us);
+
+    let waterfall = trace.waterfall();
+    assert!(waterfall.starts_with("boot "));
+    assert!(waterfall.lines().any(|l| l.trim_start().starts_with("slow") && l.contains('#')));
+
+    let json = serde_json::to_string(&trace).unwrap();
+    let restored: BootTrace = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.phases, trace.phases);
+}
+
+#[tokio::test]
+async fn test_boot_stops_at_failing_plate() {
+    let mut spec = AppSpec::new(TestConfig, TestEnv).with_plate(Box::new(BrokenPlate));
+    match spec.boot().await {
+        Err(BootError::PlateInit { plate, reason }) => {
+            assert_eq!(plate, "broken");
+            assert_eq!(reason, "database unreachable");
+        }
+        other => panic!("unexpected boot result: {:?}", other.map(|t| t.total_us)),
+    }
+}
+
+#[tokio::test]
+async fn test_boot_budget() {
+    let mut spec = AppSpec::new(TestConfig, TestEnv).with_plate(Box::new(SlowPlate));
+    let trace = spec.boot().await.unwrap();
+
+    assert!(BootBudget::new(10_000, BudgetAction::Fail).check(&trace).is_ok());
+    assert!(BootBudget::new(1, BudgetAction::Warn).check(&trace).is_ok());
+    match BootBudget::new(1, BudgetAction::Fail).check(&trace) {
+        Err(BootError::BudgetExceeded { budget_ms, slowest, .. }) => {
+            assert_eq!(budget_ms, 1);
+            assert!(slowest.contains("'slow'"));
+        }
+        other => panic!("expected a budget error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_plate_levels_follow_dependencies() {
+    let levels = plate_levels(&[
+        ("api", vec!["db", "cache"]),
+        ("db", vec![]),
+        ("cache", vec![]),
+        ("jobs", vec!["db"]),
+    ])
+    .unwrap();
+    assert_eq!(levels, vec![vec![1, 2], vec![0, 3]]);
+
+    assert!(matches!(
+        plate_levels(&[("api", vec!["db"])]),
+        Err(BootError::MissingDependency { plate, dependency }) if plate == "api" && dependency == "db"
+    ));
+    match plate_levels(&[("a", vec!["b"]), ("b", vec!["a"]), ("c", vec![])]) {
+        Err(BootError::DependencyCycle { plates }) => assert_eq!(plates, vec!["a", "b"]),
+        other => panic!("expected a cycle, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_independent_plates_boot_concurrently() {
+    let mut spec = AppSpec::new(TestConfig, TestEnv)
+        .with_plate(Box::new(IoPlate("api", vec!["db"])))
+        .with_plate(Box::new(IoPlate("db", vec![])))
+        .with_plate(Box::new(IoPlate("cache", vec![])))
+        .with_plate(Box::new(IoPlate("search", vec![])));
+
+    let trace = spec.boot().await.unwrap();
+    assert_eq!(trace.plate_levels, vec![vec!["db", "cache", "search"], vec!["api"]]);
+    // Two levels of 40ms each rather than four sequential inits.
+    assert!(trace.total_us < 150_000, "boot took {}us", trace.total_us);
+    assert!(trace.parallelism > 1.5, "parallelism {}", trace.parallelism);
+
+    let phase = |name: &str| trace.phases.iter().find(|p| p.name == name).unwrap().clone();
+    assert!(phase("api").start_us >= phase("db").start_us + phase("db").duration_us);
+    assert!(trace.waterfall().contains("4 plates in 2 levels"));
+}
+
+#[tokio::test]
+async fn test_boot_concurrency_limit() {
+    let mut spec = AppSpec::new(TestConfig, TestEnv)
+        .with_plate(Box::new(IoPlate("a", vec![])))
+        .with_plate(Box::new(IoPlate("b", vec![])))
+        .with_boot_concurrency(1);
+
+    let trace = spec.boot().await.unwrap();
+    assert!(trace.total_us >= 80_000);
+    assert!(trace.parallelism < 1.2);
+}
@@ -0,0 +1,59 @@
+use montrs_core::capture::REDACTED;
+use montrs_core::{Capture, WasiRequest};
+
+#[test]
+fn test_capture_keeps_safe_headers_and_redacts_secrets() {
+    let request = WasiRequest::new("post", "/login?next=/todos&api_key=abc123")
+        .with_header("Content-Type", "application/json")
+        .with_header("Authorization", "Bearer abc123")
+        .with_header("Cookie", "session=abc123")
+        .with_header("User-Agent", "curl/8.0")
+        .with_body(r#"{"email":"ada@example.com","password":"hunter2","card":{"card_number":"4242","brand":"visa"}}"#);
+    let capture = Capture::from_request(&request);
+
+    assert_eq!(capture.method, "POST");
+    assert_eq!(capture.path_with_query, "/login?next=/todos&api_key=%5BREDACTED%5D");
+    assert_eq!(
+        capture.headers,
+        [
+            ("content-type".to_string(), "application/json".to_string()),
+            ("user-agent".to_string(), "curl/8.0".to_string())
+        ]
+    );
+    let body: serde_json::Value = serde_json::from_str(&capture.body).unwrap();
+    assert_eq!(body["email"], "ada@example.com");
+    assert_eq!(body["password"], REDACTED);
+    assert_eq!(body["card"]["card_number"], REDACTED);
+    assert_eq!(body["card"]["brand"], "visa");
+
+    let form = WasiRequest::new("POST", "/login")
+        .with_header("content-type", "application/x-www-form-urlencoded")
+        .with_body("user=ada&Password=hunter2");
+    assert_eq!(Capture::from_request(&form).body, "user=ada&Password=%5BREDACTED%5D");
+
+    // Bodies without secrets are kept as sent, and binary bodies are left out.
+    let plain = WasiRequest::new("PUT", "/todos/1").with_body("{ \"title\": \"Buy milk\" }");
+    assert_eq!(Capture::from_request(&plain).body, "{ \"title\": \"Buy milk\" }");
+    let binary = Capture::from_request(&WasiRequest::new("PUT", "/avatar").with_body(vec![0xff, 0xfe, 0x00]));
+    assert_eq!((binary.body.as_str(), binary.omitted_bytes), ("", Some(3)));
+}
+
+#[test]
+fn test_captures_round_trip_through_files() {
+    let dir = std::env::temp_dir().join(format!("montrs-captures-{}", std::process::id()));
+    let first = Capture::from_request(&WasiRequest::new("GET", "/todos/42?expand=tags")).with_status(500);
+    let mut second = Capture::from_request(&WasiRequest::new("DELETE", "/")).with_status(204);
+    second.captured_at = first.captured_at + chrono::Duration::seconds(1);
+
+    let path = first.write_to(&dir).unwrap();
+    assert!(path.file_name().unwrap().to_str().unwrap().ends_with("-GET-todos-42.json"));
+    assert_ne!(first.write_to(&dir).unwrap(), path);
+    second.write_to(&dir).unwrap();
+
+    assert_eq!(Capture::read(&path).unwrap(), first);
+    let all = Capture::read_dir(&dir).unwrap();
+    assert_eq!(all.len(), 3);
+    assert_eq!(all[2].1, second);
+    assert_eq!(all[0].1.to_request(), WasiRequest::new("GET", "/todos/42?expand=tags"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,100 @@
+use montrs_core::changelog::Subject;
+use montrs_core::{AppSpecExport, Section, SpecDiff};
+use serde_json::{Value, json};
+
+fn export(routes: Value, plates: &[&str], core: &str) -> AppSpecExport {
+    let plates: Vec<Value> = plates
+        .iter()
+        .map(|name| json!({ "name": name, "description": format!("{} plate", name), "dependencies": [], "metadata": {} }))
+        .collect();
+    serde_json::from_value(json!({
+        "name": "shop",
+        "target": "Server",
+        "plates": plates,
+        "router": { "routes": routes },
+        "crates": [{ "name": "montrs-core", "version": core, "min_core": core }],
+    }))
+    .unwrap()
+}
+
+fn route(path: &str, params: Value, meta: Value) -> Value {
+    json!({ "path": path, "loader_description": "", "action_description": "", "params": params, "meta": meta })
+}
+
+fn param(name: &str, required: bool, kind: &str) -> Value {
+    json!({ "name": name, "required": required, "schema": { "type": kind } })
+}
+
+fn previous() -> AppSpecExport {
+    export(
+        json!({
+            "/users/:id": route("/users/:id", json!([param("id", true, "integer"), param("expand", false, "boolean")]), json!({})),
+            "/orders": route("/orders", json!([param("page", false, "integer")]), json!({})),
+            "/legacy": route("/legacy", json!([]), json!({})),
+        }),
+        &["auth", "billing"],
+        "0.1.0",
+    )
+}
+
+fn current() -> AppSpecExport {
+    export(
+        json!({
+            "/users/:id": route(
+                "/users/:id",
+                json!([param("id", true, "string"), param("fields", false, "string")]),
+                json!({ "stability": "deprecated", "sunset": "2026-12-31", "replacement": "/v2/users/:id" }),
+            ),
+            "/orders": route("/orders", json!([param("page", true, "integer")]), json!({ "requires": "orders:read" })),
+            "/reports": route("/reports", json!([]), json!({})),
+        }),
+        &["auth", "analytics"],
+        "0.2.0",
+    )
+}
+
+#[test]
+fn test_changes_are_classified_by_section() {
+    let diff = SpecDiff::between("v0.1.0", &previous(), "v0.2.0", &current());
+    assert!(diff.breaking);
+
+    let lines: Vec<(Section, &str, &str)> =
+        diff.changes.iter().map(|c| (c.section, c.name.as_str(), c.description.as_str())).collect();
+    assert_eq!(
+        lines,
+        [
+            (Section::Breaking, "/legacy", "route removed"),
+            (Section::Breaking, "/orders", "param `page` is now required"),
+            (Section::Breaking, "/orders", "now requires `orders:read`"),
+            (Section::Breaking, "/users/:id", "param `id` changed from integer to string"),
+            (Section::Added, "/reports", "route added"),
+            (Section::Added, "/users/:id", "new optional param `fields`"),
+            (Section::Added, "analytics", "plate added: analytics plate"),
+            (Section::Changed, "montrs-core", "montrs-core 0.1.0 -> 0.2.0"),
+            (Section::Deprecated, "/users/:id", "deprecated; use `/v2/users/:id` instead; removed after 2026-12-31"),
+            (Section::Removed, "/users/:id", "param `expand` removed"),
+            (Section::Removed, "billing", "plate removed"),
+        ]
+    );
+    assert_eq!(diff.changes[0].subject, Subject::Route);
+}
+
+#[test]
+fn test_markdown_and_json_output() {
+    let diff = SpecDiff::between("v0.1.0", &previous(), "v0.2.0", &current());
+    let markdown = diff.to_markdown();
+    assert!(markdown.starts_with("## v0.2.0\n\nChanges since v0.1.0.\n\n### ⚠️ Breaking\n\n- `/legacy`: route removed\n"));
+    assert!(markdown.contains("\n### Added\n\n- `/reports`: route added\n"));
+    assert!(markdown.contains("- plate added: analytics plate\n"), "plates and crates are not code-formatted");
+    assert!(markdown.contains("\n### Removed\n"));
+
+    let value = serde_json::to_value(&diff).unwrap();
+    assert_eq!(value["breaking"], true);
+    assert_eq!(value["changes"][0], json!({ "section": "breaking", "subject": "route", "name": "/legacy", "description": "route removed" }));
+    let back: SpecDiff = serde_json::from_value(value).unwrap();
+    assert_eq!(back, diff);
+
+    let same = SpecDiff::between("v0.2.0", &current(), "HEAD", &current());
+    assert!(same.is_empty() && !same.breaking);
+    assert!(same.to_markdown().contains("No changes to routes, plates or crates."));
+}
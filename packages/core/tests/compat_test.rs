@@ -0,0 +1,76 @@
+use montrs_core::compat::{self, Bump, CORE};
+use montrs_core::{AgentError, AppConfig, AppSpec, BootError, CrateVersion, EnvConfig};
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[test]
+fn test_crates_need_a_core_on_their_release_line() {
+    assert_eq!(CORE.version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(CORE.min_core, CORE.version);
+
+    let orm = CrateVersion::new("montrs-orm", "0.2.3");
+    assert!(orm.check("0.2.3").is_none());
+    assert!(orm.check("0.2.9").is_none(), "a later core on the same line is fine");
+
+    let older_core = orm.check("0.2.1").unwrap();
+    assert_eq!(older_core.fix, Bump::Core { to: "0.2.3".to_string() });
+    assert_eq!(older_core.suggestion(), "Bump montrs-core to 0.2.3 or later, as montrs-orm 0.2.3 requires.");
+
+    let newer_line = orm.check("0.3.0").unwrap();
+    assert_eq!(newer_line.fix, Bump::Crate { to: "0.3".to_string() });
+    assert_eq!(newer_line.suggestion(), "Bump montrs-orm from 0.2.3 to 0.3.x.");
+
+    assert!(CrateVersion::new("montrs-test", "1.2.0").check("1.9.0").is_none());
+    assert_eq!(CrateVersion::new("montrs-test", "1.2.0").check("2.0.0").unwrap().fix, Bump::Crate { to: "2".to_string() });
+}
+
+#[test]
+fn test_mismatches_name_every_crate() {
+    let crates = [
+        CrateVersion::new("montrs-orm", "0.2.0"),
+        CrateVersion::new("montrs-test", "0.3.0"),
+        CrateVersion::new("montrs-cli", "0.1.0"),
+        CrateVersion { min_core: "0.2.0".into(), ..CrateVersion::new("montrs-bench", "0.3.1") },
+    ];
+    let error = compat::check("0.2.5", &crates).unwrap_err();
+    let names: Vec<_> = error.mismatches.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, ["montrs-test", "montrs-cli"], "an explicit min_core keeps montrs-bench compatible");
+    assert_eq!(error.error_code(), "COMPAT_MISMATCH");
+    assert_eq!(
+        error.to_string(),
+        "montrs-core 0.2.5 is incompatible with montrs-test 0.3.0 (needs core ^0.3.0), montrs-cli 0.1.0 (needs core ^0.1.0)"
+    );
+    let fixes = error.suggested_fixes();
+    assert_eq!(fixes[0], "Bump montrs-core to 0.3.0 or later, as montrs-test 0.3.0 requires.");
+    assert_eq!(fixes[1], "Upgrade the montrs CLI to 0.2.x with `montrs upgrade`.");
+
+    assert!(compat::check("0.2.5", &crates[..1]).is_ok());
+}
+
+#[tokio::test]
+async fn test_boot_refuses_incompatible_crates() {
+    let mut spec = AppSpec::new(TestConfig, TestEnv).with_crates([montrs_core::crate_version!()]);
+    spec.boot().await.unwrap();
+    assert_eq!(spec.export_spec("app").crates.len(), 2, "the core is exported with the listed crates");
+
+    let mut spec = AppSpec::new(TestConfig, TestEnv).with_crates([CrateVersion::new("montrs-orm", "999.0.0")]);
+    match spec.boot().await {
+        Err(BootError::Incompatible(e)) => {
+            assert_eq!(e.mismatches[0].fix, Bump::Core { to: "999.0.0".to_string() });
+        }
+        other => panic!("expected an incompatible crate, got {:?}", other.map(|_| ())),
+    }
+}
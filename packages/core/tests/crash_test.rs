@@ -0,0 +1,140 @@
+use async_trait::async_trait;
+use leptos::prelude::*;
+use montrs_core::crash::{self, CrashReporter, PanicReport, WebhookFormat};
+use montrs_core::{
+    AppConfig, EnvConfig, ErrorInfo, Route, RouteAction, RouteContext, RouteError, RouteLoader, RouteParams,
+    RouteView, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct NoParams {}
+impl RouteParams for NoParams {}
+
+struct PanickingLoader;
+#[async_trait]
+impl RouteLoader<NoParams, TestConfig> for PanickingLoader {
+    type Output = String;
+    async fn load(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams) -> Result<String, RouteError> {
+        panic!("loader exploded")
+    }
+}
+
+struct NoAction;
+#[async_trait]
+impl RouteAction<NoParams, TestConfig> for NoAction {
+    type Input = ();
+    type Output = ();
+    async fn act(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams, _input: ()) -> Result<(), RouteError> {
+        Ok(())
+    }
+}
+
+struct EmptyView;
+impl RouteView for EmptyView {
+    fn render(&self) -> impl IntoView {
+        view! { <div></div> }
+    }
+}
+
+struct ExplodingRoute;
+impl Route<TestConfig> for ExplodingRoute {
+    type Params = NoParams;
+    type Loader = PanickingLoader;
+    type Action = NoAction;
+    type View = EmptyView;
+
+    fn path() -> &'static str {
+        "/explode"
+    }
+    fn loader(&self) -> Self::Loader {
+        PanickingLoader
+    }
+    fn action(&self) -> Self::Action {
+        NoAction
+    }
+    fn view(&self) -> Self::View {
+        EmptyView
+    }
+}
+
+#[tokio::test]
+async fn test_panicking_loader_becomes_a_500_with_correlation_id() {
+    let mut router = Router::<TestConfig>::new();
+    router.register(ExplodingRoute);
+    let ctx = RouteContext { config: &TestConfig, env: &TestEnv };
+
+    let err = router.load("/explode", ctx, serde_json::json!({})).await.unwrap_err();
+    let RouteError::Panicked(id) = &err else { panic!("expected a panic, got {:?}", err) };
+    assert_eq!(id.len(), 16);
+    let info = ErrorInfo::from_route_error(&err);
+    assert_eq!(info.status, 500);
+    assert!(info.message.ends_with(id.as_str()), "{}", info.message);
+    assert!(!info.message.contains("exploded"), "the panic message is not shown to visitors");
+}
+
+#[tokio::test]
+async fn test_installed_reporter_feeds_its_sinks() {
+    let log = std::env::temp_dir().join(format!("montrs-crashes-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&log);
+    let seen: Arc<Mutex<Vec<PanicReport>>> = Arc::default();
+    CrashReporter::new()
+        .with_log(&log)
+        .with_sink({
+            let seen = seen.clone();
+            move |report| seen.lock().unwrap().push(report.clone())
+        })
+        .install();
+
+    let report = crash::catch("job nightly-export", async { panic!("disk {} is full", "/dev/sda") }).await.unwrap_err();
+    assert_eq!(report.message, "disk /dev/sda is full");
+    assert_eq!(report.scope.as_deref(), Some("job nightly-export"));
+    assert!(report.file.as_deref().is_some_and(|f| f.ends_with("crash_test.rs")));
+    assert!(!report.backtrace.is_empty());
+    assert!(seen.lock().unwrap().iter().any(|r| r.id == report.id));
+    assert!(PanicReport::read_log(&log).unwrap().contains(&report));
+
+    assert_eq!(crash::catch("job ok", async { 7 }).await.unwrap(), 7);
+    let _ = std::fs::remove_file(&log);
+}
+
+#[test]
+fn test_webhook_payloads() {
+    let report = PanicReport {
+        id: "00c0ffee00c0ffee".to_string(),
+        timestamp: chrono::Utc::now(),
+        message: "index out of bounds".to_string(),
+        file: Some("src/cart.rs".to_string()),
+        line: 42,
+        column: 9,
+        thread: Some("tokio-runtime-worker".to_string()),
+        scope: Some("route /cart (load)".to_string()),
+        backtrace: "0: cart::total".to_string(),
+    };
+
+    let slack = WebhookFormat::Slack.payload(&report);
+    let text = slack["text"].as_str().unwrap();
+    assert!(text.contains("route /cart (load)") && text.contains("src/cart.rs:42:9") && text.contains(&report.id));
+
+    let sentry = WebhookFormat::Sentry.payload(&report);
+    assert_eq!(sentry["event_id"].as_str().unwrap().len(), 32);
+    assert_eq!(sentry["tags"]["correlation_id"], report.id);
+    assert_eq!(sentry["exception"]["values"][0]["stacktrace"]["frames"][0]["lineno"], 42);
+    assert_eq!("SENTRY".parse::<WebhookFormat>(), Ok(WebhookFormat::Sentry));
+}
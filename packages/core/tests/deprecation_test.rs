@@ -0,0 +1,31 @@
+use montrs_core::{Deprecation, meta};
+use std::collections::HashMap;
+
+#[test]
+fn test_headers() {
+    let deprecation = Deprecation::new()
+        .since("2024-01-01")
+        .sunset("2024-12-31")
+        .replacement("/api/v2/orders");
+
+    let headers: HashMap<_, _> = deprecation.headers().into_iter().collect();
+    assert_eq!(headers["Deprecation"], "@1704067200");
+    assert_eq!(headers["Sunset"], "Tue, 31 Dec 2024 00:00:00 GMT");
+    assert_eq!(headers["Link"], "</api/v2/orders>; rel=\"successor-version\"");
+    assert!(deprecation.is_past_sunset());
+}
+
+#[test]
+fn test_metadata_round_trip() {
+    let mut metadata = HashMap::new();
+    metadata.insert(meta::OWNER.to_string(), "orders".to_string());
+    assert_eq!(Deprecation::from_meta(&metadata), None);
+
+    let deprecation = Deprecation::new().sunset("2099-01-01").note("Use bulk orders");
+    deprecation.apply_to(&mut metadata);
+
+    assert_eq!(metadata[meta::STABILITY], meta::DEPRECATED);
+    assert_eq!(metadata[meta::OWNER], "orders");
+    assert_eq!(Deprecation::from_meta(&metadata), Some(deprecation.clone()));
+    assert!(!deprecation.is_past_sunset());
+}
@@ -0,0 +1,48 @@
+use montrs_core::router::RouterSpec;
+use montrs_core::{DevState, FeatureFlag, FeatureManager, UserContext};
+use std::collections::HashMap;
+
+fn flag(name: &str, enabled: bool) -> FeatureFlag {
+    FeatureFlag {
+        name: name.to_string(),
+        description: None,
+        enabled,
+        segment_whitelist: Vec::new(),
+    }
+}
+
+#[test]
+fn test_feature_manager_lists_flags_by_name() {
+    let mut features = FeatureManager::new();
+    features.add_flag(flag("search", true));
+    features.add_flag(flag("beta-nav", false));
+    features.add_flag(flag("search", false));
+
+    let names: Vec<_> = features.flags().iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, ["beta-nav", "search"]);
+
+    let user = UserContext { id: "u1".to_string(), attributes: HashMap::new() };
+    assert!(!features.is_enabled("search", &user), "the later flag replaces the earlier one");
+}
+
+#[test]
+fn test_dev_state_round_trips_through_file() {
+    let path = std::env::temp_dir()
+        .join(format!("montrs-devstate-{}", std::process::id()))
+        .join("dev-state.json");
+    let state = DevState {
+        booted_at: chrono::Utc::now(),
+        boot_ms: 12.5,
+        router: RouterSpec::default(),
+        flags: vec![flag("search", true)],
+    };
+    state.write_to(&path).unwrap();
+
+    let read = DevState::read_from(&path).unwrap();
+    assert_eq!(read.booted_at, state.booted_at);
+    assert_eq!(read.boot_ms, 12.5);
+    assert_eq!(read.flags.len(), 1);
+    assert!(read.flags[0].enabled);
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
@@ -0,0 +1,110 @@
+use async_trait::async_trait;
+use leptos::prelude::*;
+use montrs_core::{
+    AppConfig, AppSpec, Embedded, EnvConfig, Route, RouteAction, RouteContext, RouteError, RouteLoader, RouteParams,
+    RouteView, Router, WasiRequest,
+};
+use serde::{Deserialize, Serialize};
+
+/// Host state shared with the embedded app through its config.
+#[derive(Clone)]
+struct TestConfig {
+    greeting: &'static str,
+}
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct GreetParams {
+    name: String,
+}
+impl RouteParams for GreetParams {}
+
+struct GreetLoader;
+#[async_trait]
+impl RouteLoader<GreetParams, TestConfig> for GreetLoader {
+    type Output = String;
+    async fn load(&self, ctx: RouteContext<'_, TestConfig>, params: GreetParams) -> Result<Self::Output, RouteError> {
+        let host = ctx.request().and_then(|r| r.header("host").map(str::to_string)).unwrap_or_default();
+        Ok(format!("{} {} from {}", ctx.config.greeting, params.name, host))
+    }
+}
+
+struct GreetAction;
+#[async_trait]
+impl RouteAction<GreetParams, TestConfig> for GreetAction {
+    type Input = serde_json::Value;
+    type Output = serde_json::Value;
+    async fn act(&self, _ctx: RouteContext<'_, TestConfig>, params: GreetParams, input: Self::Input) -> Result<Self::Output, RouteError> {
+        Ok(serde_json::json!({ "name": params.name, "input": input }))
+    }
+}
+
+struct GreetView;
+impl RouteView for GreetView {
+    fn render(&self) -> impl IntoView {
+        view! { <div>"Greet"</div> }
+    }
+}
+
+struct GreetRoute;
+impl Route<TestConfig> for GreetRoute {
+    type Params = GreetParams;
+    type Loader = GreetLoader;
+    type Action = GreetAction;
+    type View = GreetView;
+
+    fn path() -> &'static str {
+        "/greet/:name"
+    }
+    fn loader(&self) -> Self::Loader {
+        GreetLoader
+    }
+    fn action(&self) -> Self::Action {
+        GreetAction
+    }
+    fn view(&self) -> Self::View {
+        GreetView
+    }
+}
+
+#[tokio::test]
+async fn test_embedded_router_answers_requests() {
+    let mut router = Router::new();
+    router.register(GreetRoute);
+    let app = Embedded::new(router, TestConfig { greeting: "hello" }, TestEnv);
+    assert!(app.router().resolve("/greet/ada").is_some());
+
+    let request = WasiRequest::new("GET", "/greet/ada").with_header("Host", "legacy.internal");
+    let response = app.clone().handle(request).await;
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, br#""hello ada from legacy.internal""#);
+
+    let request = WasiRequest::new("POST", "/greet/ada").with_body(r#"{"loud":true}"#);
+    let response = app.handle(request).await;
+    let body: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+    assert_eq!(body, serde_json::json!({ "name": "ada", "input": { "loud": true } }));
+
+    assert_eq!(app.handle(WasiRequest::new("GET", "/elsewhere")).await.status, 404);
+}
+
+#[tokio::test]
+async fn test_spec_boots_into_embedded() {
+    let mut spec = AppSpec::new(TestConfig { greeting: "hi" }, TestEnv);
+    spec.router.register(GreetRoute);
+    let app = spec.embed().await.unwrap();
+    assert_eq!(app.config().greeting, "hi");
+
+    let response = app.handle(WasiRequest::new("GET", "/greet/bob")).await;
+    assert_eq!(response.body, br#""hi bob from ""#);
+}
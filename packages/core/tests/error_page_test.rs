@@ -0,0 +1,61 @@
+use leptos::prelude::*;
+use montrs_core::{ErrorInfo, ErrorPages, ErrorTheme, RouteError};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU16, Ordering};
+
+#[test]
+fn test_route_errors_map_to_status() {
+    assert_eq!(ErrorInfo::from_route_error(&RouteError::NotFound).status, 404);
+    assert_eq!(ErrorInfo::from_route_error(&RouteError::Unauthorized).status, 401);
+    assert_eq!(
+        ErrorInfo::from_route_error(&RouteError::ValidationFailed("bad id".into())).status,
+        422
+    );
+
+    let internal = ErrorInfo::from_route_error(&RouteError::InternalError("db password wrong".into()));
+    assert_eq!(internal.status, 500);
+    assert!(!internal.message.contains("password"));
+}
+
+#[test]
+fn test_from_error_downcasts_route_errors() {
+    let err: Box<dyn std::error::Error> = Box::new(RouteError::NotFound);
+    assert_eq!(ErrorInfo::from_error(err.as_ref()).status, 404);
+
+    let io = std::io::Error::other("disk");
+    let info = ErrorInfo::from_error(&io);
+    assert_eq!(info.status, 500);
+    assert_eq!(info.title, "Something went wrong");
+}
+
+#[test]
+fn test_theme_defaults_and_style() {
+    let theme: ErrorTheme = serde_json::from_str(r#"{"brand":"Acme"}"#).unwrap();
+    assert_eq!(theme.brand, "Acme");
+    assert_eq!(theme.home_href, "/");
+
+    let style = theme.with_accent("#e11d48").style();
+    assert!(style.contains("--montrs-error-accent: #e11d48"));
+}
+
+#[test]
+fn test_pages_prefer_status_then_fallback() {
+    let rendered = Arc::new(AtomicU16::new(0));
+    let on_404 = rendered.clone();
+    let on_other = rendered.clone();
+    let pages = ErrorPages::new()
+        .with_page(404, move |info, _| {
+            on_404.store(info.status, Ordering::SeqCst);
+            ().into_any()
+        })
+        .with_fallback(move |info, _| {
+            on_other.store(info.status + 1000, Ordering::SeqCst);
+            ().into_any()
+        });
+
+    pages.render(ErrorInfo::not_found());
+    assert_eq!(rendered.load(Ordering::SeqCst), 404);
+
+    pages.render(ErrorInfo::internal("boom"));
+    assert_eq!(rendered.load(Ordering::SeqCst), 1500);
+}
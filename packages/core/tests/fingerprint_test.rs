@@ -0,0 +1,150 @@
+use async_trait::async_trait;
+use montrs_core::{
+    AppConfig, AppSpec, BootError, EnvConfig, FeatureFlag, FeatureManager, Plate, PlateContext, Route, RouteAction,
+    RouteContext, RouteError, RouteLoader, RouteParams, RouteView, Router, SpecFingerprint,
+};
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+
+    fn metadata(&self) -> HashMap<String, String> {
+        HashMap::from([("region".to_string(), "eu".to_string())])
+    }
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct NoParams {}
+impl RouteParams for NoParams {}
+
+struct Health;
+#[async_trait]
+impl RouteLoader<NoParams, TestConfig> for Health {
+    type Output = String;
+    async fn load(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams) -> Result<String, RouteError> {
+        Ok("ok".to_string())
+    }
+}
+#[async_trait]
+impl RouteAction<NoParams, TestConfig> for Health {
+    type Input = ();
+    type Output = ();
+    async fn act(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams, _input: ()) -> Result<(), RouteError> {
+        Ok(())
+    }
+}
+impl RouteView for Health {
+    fn render(&self) -> impl IntoView {
+        view! { <p>"ok"</p> }
+    }
+}
+impl Route<TestConfig> for Health {
+    type Params = NoParams;
+    type Loader = Health;
+    type Action = Health;
+    type View = Health;
+
+    fn path() -> &'static str {
+        "/health"
+    }
+    fn loader(&self) -> Health {
+        Health
+    }
+    fn action(&self) -> Health {
+        Health
+    }
+    fn view(&self) -> Health {
+        Health
+    }
+}
+
+/// Registers `/health` when `routes` is set.
+struct TestPlate(&'static str, bool);
+#[async_trait]
+impl Plate<TestConfig> for TestPlate {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+    async fn init(&self, _ctx: &mut PlateContext<TestConfig>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+    fn register_routes(&self, router: &mut Router<TestConfig>) {
+        if self.1 {
+            router.register(Health).with_meta("owner", "ops");
+        }
+    }
+}
+
+fn spec(plates: &[&'static str], flag: bool) -> AppSpec<TestConfig> {
+    let mut features = FeatureManager::new();
+    features.add_flag(FeatureFlag { name: "beta".to_string(), description: None, enabled: flag, segment_whitelist: Vec::new() });
+    let mut spec = AppSpec::new(TestConfig, TestEnv).with_features(features);
+    for (i, name) in plates.iter().enumerate() {
+        spec = spec.with_plate(Box::new(TestPlate(name, i == 0)));
+    }
+    spec
+}
+
+fn manifest_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("montrs-fingerprint-{}-{}", std::process::id(), name)).join("montrs.fingerprint.json")
+}
+
+#[test]
+fn test_fingerprint_is_stable_and_tracks_the_blueprint() {
+    let fingerprint = spec(&["auth", "billing"], true).fingerprint();
+    assert_eq!(fingerprint, spec(&["auth", "billing"], true).fingerprint());
+    assert_eq!(fingerprint.hash.len(), 16);
+    assert_eq!(fingerprint.config["region"], "eu");
+
+    let reordered = spec(&["billing", "auth"], true).fingerprint();
+    assert_ne!(reordered.hash, fingerprint.hash);
+    assert_eq!(reordered.changes(&fingerprint), ["plates: [auth, billing] -> [billing, auth]"]);
+
+    let toggled = spec(&["auth", "billing"], false).fingerprint();
+    assert_eq!(toggled.changes(&fingerprint), ["feature `beta` changed"]);
+    assert!(fingerprint.changes(&fingerprint).is_empty());
+}
+
+#[tokio::test]
+async fn test_boot_adds_plate_routes_and_checks_the_manifest() {
+    let mut app = spec(&["auth"], true);
+    let before = app.fingerprint();
+    app.boot().await.unwrap();
+    let booted = app.fingerprint();
+    assert_eq!(booted.routes["/health"].meta["owner"], "ops");
+    assert_eq!(booted.changes(&before), ["route `/health` added"]);
+
+    let path = manifest_path("boot");
+    booted.write_to(&path).unwrap();
+    assert_eq!(SpecFingerprint::read_from(&path).unwrap(), booted);
+
+    let mut same = spec(&["auth"], true).with_spec_manifest(&path);
+    same.boot().await.unwrap();
+
+    let mut drifted = spec(&["auth"], false).with_spec_manifest(&path);
+    match drifted.boot().await {
+        Err(BootError::SpecMismatch { expected, changes, .. }) => {
+            assert_eq!(expected, booted.hash);
+            assert_eq!(changes, ["feature `beta` changed"]);
+        }
+        other => panic!("expected a fingerprint mismatch, got {:?}", other.map(|_| ())),
+    }
+
+    let mut missing = spec(&["auth"], true).with_spec_manifest(manifest_path("missing"));
+    assert!(matches!(missing.boot().await, Err(BootError::SpecManifest { .. })));
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
@@ -0,0 +1,147 @@
+use async_trait::async_trait;
+use montrs_core::{
+    AppConfig, EnvConfig, GuardOutcome, Route, RouteAction, RouteContext, RouteError, RouteGuard, RouteLoader,
+    RouteParams, RouteView, Router, meta,
+};
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Whether the caller is signed in, and in the labs beta.
+#[derive(Clone)]
+struct TestConfig {
+    signed_in: bool,
+    beta: bool,
+}
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct NoParams {}
+impl RouteParams for NoParams {}
+
+/// Loads its own path, relative to the group.
+struct Page<const N: usize>;
+#[async_trait]
+impl<const N: usize> RouteLoader<NoParams, TestConfig> for Page<N> {
+    type Output = String;
+    async fn load(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams) -> Result<String, RouteError> {
+        Ok(Self::path().to_string())
+    }
+}
+#[async_trait]
+impl<const N: usize> RouteAction<NoParams, TestConfig> for Page<N> {
+    type Input = ();
+    type Output = ();
+    async fn act(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams, _input: ()) -> Result<(), RouteError> {
+        Ok(())
+    }
+}
+impl<const N: usize> RouteView for Page<N> {
+    fn render(&self) -> impl IntoView {}
+}
+impl<const N: usize> Route<TestConfig> for Page<N> {
+    type Params = NoParams;
+    type Loader = Self;
+    type Action = Self;
+    type View = Self;
+    fn path() -> &'static str {
+        ["/users", "/audit", "/beta", "/"][N]
+    }
+    fn loader(&self) -> Self {
+        Page
+    }
+    fn action(&self) -> Self {
+        Page
+    }
+    fn view(&self) -> Self {
+        Page
+    }
+}
+
+/// Sends callers who are not signed in to `/login`.
+struct SignedIn;
+#[async_trait]
+impl RouteGuard<TestConfig> for SignedIn {
+    fn name(&self) -> String {
+        "signed_in".to_string()
+    }
+    async fn check(&self, ctx: &RouteContext<'_, TestConfig>, _path: &str) -> GuardOutcome {
+        match ctx.config.signed_in {
+            true => GuardOutcome::Allow,
+            false => GuardOutcome::Redirect("/login".to_string()),
+        }
+    }
+}
+
+fn router() -> Router<TestConfig> {
+    let mut router = Router::new();
+    router.register(Page::<0>);
+    router.group("/admin", |admin| {
+        admin.register(Page::<0>).with_meta(meta::TEAM, "identity");
+        admin.use_middleware(SignedIn).with_meta(meta::TEAM, "ops").with_meta(meta::OWNER, "platform");
+        admin.register(Page::<1>);
+        admin.group("/labs", |labs| {
+            labs.feature("labs", |ctx: &RouteContext<'_, TestConfig>| ctx.config.beta);
+            labs.register(Page::<2>);
+        });
+    });
+    router.group("/reports", |reports| {
+        reports.requires("reports.read");
+        reports.register(Page::<3>);
+    });
+    router
+}
+
+async fn load(router: &Router<TestConfig>, path: &str, signed_in: bool, beta: bool) -> Result<String, RouteError> {
+    let config = TestConfig { signed_in, beta };
+    let ctx = RouteContext { config: &config, env: &TestEnv };
+    Ok(serde_json::from_value(router.load(path, ctx, serde_json::json!({})).await?).unwrap())
+}
+
+#[tokio::test]
+async fn test_group_middleware_and_feature_gates_cover_every_route_of_the_group() {
+    let router = router();
+    assert_eq!(load(&router, "/users", false, false).await.unwrap(), "/users");
+    assert!(matches!(load(&router, "/admin/users", false, false).await, Err(RouteError::Redirect(_))), "middleware added after a route still covers it");
+    assert_eq!(load(&router, "/admin/users", true, false).await.unwrap(), "/users");
+    assert_eq!(load(&router, "/admin/audit", true, false).await.unwrap(), "/audit");
+
+    assert!(matches!(load(&router, "/admin/labs/beta", false, true).await, Err(RouteError::Redirect(_))));
+    assert!(matches!(load(&router, "/admin/labs/beta", true, false).await, Err(RouteError::NotFound)));
+    assert_eq!(load(&router, "/admin/labs/beta", true, true).await.unwrap(), "/beta");
+    assert_eq!(router.guards("/admin/labs/beta"), ["signed_in", "feature:labs"]);
+    assert!(router.guards("/users").is_empty());
+
+    assert!(matches!(load(&router, "/reports", true, false).await, Err(RouteError::Forbidden(_))), "group requirements need an Rbac guard");
+}
+
+#[test]
+fn test_groups_share_metadata_and_are_listed_in_the_spec() {
+    let router = router();
+    assert_eq!(router.meta("/admin/users").unwrap()[meta::TEAM], "identity", "a route's own metadata wins");
+    assert_eq!(router.meta("/admin/users").unwrap()[meta::OWNER], "platform");
+    assert_eq!(router.meta("/admin/audit").unwrap()[meta::TEAM], "ops");
+    assert!(!router.meta("/users").unwrap().contains_key(meta::OWNER));
+
+    let spec = router.spec();
+    let prefixes: Vec<_> = spec.groups.iter().map(|g| g.prefix.as_str()).collect();
+    assert_eq!(prefixes, ["/admin", "/admin/labs", "/reports"]);
+    let (admin, labs, reports) = (&spec.groups[0], &spec.groups[1], &spec.groups[2]);
+    assert_eq!(admin.routes, ["/admin/audit", "/admin/labs/beta", "/admin/users"]);
+    assert_eq!(admin.middleware, ["signed_in"]);
+    assert_eq!(labs.parent.as_deref(), Some("/admin"));
+    assert_eq!((labs.features.as_slice(), labs.middleware.as_slice()), (&["labs".to_string()][..], &["feature:labs".to_string()][..]));
+    assert_eq!(reports.meta[meta::REQUIRES], "reports.read");
+    assert_eq!(spec.routes["/reports"].meta[meta::REQUIRES], "reports.read");
+    assert_eq!(spec.routes["/admin/labs/beta"].guards, ["signed_in", "feature:labs"]);
+}
@@ -0,0 +1,161 @@
+use async_trait::async_trait;
+use montrs_core::{
+    AppConfig, AppSpec, EnvConfig, FnGuard, GuardOutcome, Plate, PlateContext, Route, RouteAction, RouteContext,
+    RouteError, RouteGuard, RouteLoader, RouteParams, RouteView, Router,
+};
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Whether the caller is signed in, and as staff.
+#[derive(Clone)]
+struct TestConfig {
+    signed_in: bool,
+    staff: bool,
+}
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct NoParams {}
+impl RouteParams for NoParams {}
+
+/// Answers with its path from both the loader and the action.
+struct Page<const N: usize>;
+#[async_trait]
+impl<const N: usize> RouteLoader<NoParams, TestConfig> for Page<N> {
+    type Output = String;
+    async fn load(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams) -> Result<String, RouteError> {
+        Ok(Self::path().to_string())
+    }
+}
+#[async_trait]
+impl<const N: usize> RouteAction<NoParams, TestConfig> for Page<N> {
+    type Input = ();
+    type Output = String;
+    async fn act(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams, _input: ()) -> Result<String, RouteError> {
+        Ok(Self::path().to_string())
+    }
+}
+impl<const N: usize> RouteView for Page<N> {
+    fn render(&self) -> impl IntoView {}
+}
+impl<const N: usize> Route<TestConfig> for Page<N> {
+    type Params = NoParams;
+    type Loader = Self;
+    type Action = Self;
+    type View = Self;
+    fn path() -> &'static str {
+        ["/", "/account", "/admin/users", "/admin/beta"][N]
+    }
+    fn loader(&self) -> Self {
+        Page
+    }
+    fn action(&self) -> Self {
+        Page
+    }
+    fn view(&self) -> Self {
+        Page
+    }
+}
+
+/// Sends callers who are not signed in to `/login`.
+struct SignedIn;
+#[async_trait]
+impl RouteGuard<TestConfig> for SignedIn {
+    fn name(&self) -> String {
+        "signed_in".to_string()
+    }
+    async fn check(&self, ctx: &RouteContext<'_, TestConfig>, _path: &str) -> GuardOutcome {
+        match ctx.config.signed_in {
+            true => GuardOutcome::Allow,
+            false => GuardOutcome::Redirect("/login".to_string()),
+        }
+    }
+}
+
+/// Registers the admin pages, all of them for staff only.
+struct AdminPlate;
+#[async_trait]
+impl Plate<TestConfig> for AdminPlate {
+    fn name(&self) -> &'static str {
+        "admin"
+    }
+    async fn init(&self, _ctx: &mut PlateContext<TestConfig>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+    fn register_routes(&self, router: &mut Router<TestConfig>) {
+        router.register(Page::<2>);
+        router.register(Page::<3>).guard(FnGuard::new("feature:beta", |_: &RouteContext<'_, TestConfig>, _: &str| GuardOutcome::Deny(404)));
+    }
+    fn guards(&self) -> Vec<Arc<dyn RouteGuard<TestConfig>>> {
+        let staff = |ctx: &RouteContext<'_, TestConfig>, _: &str| match ctx.config.staff {
+            true => GuardOutcome::Allow,
+            false => GuardOutcome::Deny(403),
+        };
+        vec![Arc::new(SignedIn), Arc::new(FnGuard::new("staff", staff))]
+    }
+}
+
+async fn spec() -> AppSpec<TestConfig> {
+    let mut spec = AppSpec::new(TestConfig { signed_in: false, staff: false }, TestEnv).with_plate(Box::new(AdminPlate));
+    spec.router.register(Page::<0>);
+    spec.router.register(Page::<1>).guard(SignedIn);
+    spec.boot().await.unwrap();
+    spec
+}
+
+async fn load(router: &Router<TestConfig>, path: &str, signed_in: bool, staff: bool) -> Result<String, RouteError> {
+    let config = TestConfig { signed_in, staff };
+    let ctx = RouteContext { config: &config, env: &TestEnv };
+    Ok(serde_json::from_value(router.load(path, ctx, serde_json::json!({})).await?).unwrap())
+}
+
+#[tokio::test]
+async fn test_guards_allow_deny_or_redirect_before_the_route_runs() {
+    let spec = spec().await;
+    let router = &spec.router;
+    assert_eq!(load(router, "/", false, false).await.unwrap(), "/");
+    assert_eq!(load(router, "/account", true, false).await.unwrap(), "/account");
+
+    let redirect = load(router, "/account", false, false).await.unwrap_err();
+    assert!(matches!(&redirect, RouteError::Redirect(path) if path == "/login"));
+    assert_eq!(redirect.status(), 303);
+
+    let config = TestConfig { signed_in: false, staff: false };
+    let ctx = RouteContext { config: &config, env: &TestEnv };
+    let acted = router.act("/account", ctx, serde_json::json!({}), serde_json::json!(null)).await;
+    assert!(matches!(acted, Err(RouteError::Redirect(_))), "actions are guarded too");
+}
+
+#[tokio::test]
+async fn test_plate_guards_cover_the_plate_routes_and_run_first() {
+    let spec = spec().await;
+    let router = &spec.router;
+    assert!(matches!(load(router, "/admin/users", false, true).await, Err(RouteError::Redirect(_))));
+    let denied = load(router, "/admin/users", true, false).await.unwrap_err();
+    assert!(matches!(denied, RouteError::Denied(403)));
+    assert_eq!(denied.status(), 403);
+    assert_eq!(load(router, "/admin/users", true, true).await.unwrap(), "/admin/users");
+    assert!(matches!(load(router, "/admin/beta", true, true).await, Err(RouteError::NotFound)));
+
+    assert_eq!(router.guards("/admin/beta"), ["signed_in", "staff", "feature:beta"]);
+    assert_eq!(router.guards("/account"), ["signed_in"]);
+    assert!(router.guards("/").is_empty(), "plate guards stay with the plate's routes");
+
+    let exported = router.spec();
+    assert_eq!(exported.routes["/admin/users"].guards, ["signed_in", "staff"]);
+    let json = serde_json::to_value(&exported.routes["/"]).unwrap();
+    assert!(json.get("guards").is_none(), "unguarded routes export as before");
+    assert_eq!(spec.fingerprint().routes["/admin/users"].guards, ["signed_in", "staff"]);
+}
@@ -0,0 +1,204 @@
+use montrs_core::{
+    AppConfig, EnvConfig, EnvError, JobHandle, JobRecord, JobStatus, Jobs, JobsPlate, MemoryJobStore, Plate, Route,
+    RouteAction, RouteContext, RouteError, RouteLoader, RouteParams, RouteView, Router,
+};
+use async_trait::async_trait;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct NoParams {}
+impl RouteParams for NoParams {}
+
+static SENT: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Sends the email given as input. Empty addresses are rejected; "down@"
+/// addresses fail as if the mail server were unreachable.
+struct SendEmail;
+#[async_trait]
+impl RouteLoader<NoParams, TestConfig> for SendEmail {
+    type Output = ();
+    async fn load(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams) -> Result<(), RouteError> {
+        Ok(())
+    }
+}
+#[async_trait]
+impl RouteAction<NoParams, TestConfig> for SendEmail {
+    type Input = String;
+    type Output = String;
+    async fn act(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams, to: String) -> Result<String, RouteError> {
+        if to.starts_with("down@") {
+            return Err(RouteError::InternalError("mail server unreachable".to_string()));
+        }
+        SENT.lock().unwrap().push(to.clone());
+        Ok(format!("sent to {}", to))
+    }
+    fn prepare_input(&self, to: String) -> Result<String, RouteError> {
+        if to.is_empty() {
+            return Err(RouteError::ValidationFailed("empty address".to_string()));
+        }
+        Ok(to)
+    }
+}
+impl RouteView for SendEmail {
+    fn render(&self) -> impl IntoView {}
+}
+impl Route<TestConfig> for SendEmail {
+    type Params = NoParams;
+    type Loader = SendEmail;
+    type Action = SendEmail;
+    type View = SendEmail;
+    fn path() -> &'static str {
+        "/emails/send"
+    }
+    fn loader(&self) -> SendEmail {
+        SendEmail
+    }
+    fn action(&self) -> SendEmail {
+        SendEmail
+    }
+    fn view(&self) -> SendEmail {
+        SendEmail
+    }
+}
+
+/// Signs a user up and defers their welcome email by the delay in seconds.
+#[derive(Serialize, Deserialize)]
+struct Signup {
+    email: String,
+    delay: u64,
+}
+
+struct SignupRoute;
+#[async_trait]
+impl RouteLoader<NoParams, TestConfig> for SignupRoute {
+    type Output = ();
+    async fn load(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams) -> Result<(), RouteError> {
+        Ok(())
+    }
+}
+#[async_trait]
+impl RouteAction<NoParams, TestConfig> for SignupRoute {
+    type Input = Signup;
+    type Output = JobHandle;
+    async fn act(&self, ctx: RouteContext<'_, TestConfig>, _params: NoParams, signup: Signup) -> Result<JobHandle, RouteError> {
+        ctx.defer::<SendEmail>(NoParams {}, signup.email, Duration::from_secs(signup.delay)).await
+    }
+}
+impl RouteView for SignupRoute {
+    fn render(&self) -> impl IntoView {}
+}
+impl Route<TestConfig> for SignupRoute {
+    type Params = NoParams;
+    type Loader = SignupRoute;
+    type Action = SignupRoute;
+    type View = SignupRoute;
+    fn path() -> &'static str {
+        "/signup"
+    }
+    fn loader(&self) -> SignupRoute {
+        SignupRoute
+    }
+    fn action(&self) -> SignupRoute {
+        SignupRoute
+    }
+    fn view(&self) -> SignupRoute {
+        SignupRoute
+    }
+}
+
+fn router(jobs: &Jobs) -> Router<TestConfig> {
+    let mut router = Router::new();
+    router.register(SendEmail);
+    router.register(SignupRoute);
+    JobsPlate::new(jobs.clone()).register_routes(&mut router);
+    router
+}
+
+async fn signup(router: &Router<TestConfig>, email: &str, delay: u64) -> Result<JobHandle, RouteError> {
+    let ctx = RouteContext { config: &TestConfig, env: &TestEnv };
+    let handle = router.act("/signup", ctx, json!({}), json!({ "email": email, "delay": delay })).await?;
+    Ok(serde_json::from_value(handle).unwrap())
+}
+
+async fn poll(router: &Router<TestConfig>, handle: &JobHandle) -> JobRecord {
+    let ctx = RouteContext { config: &TestConfig, env: &TestEnv };
+    serde_json::from_value(router.load(&handle.status_path, ctx, json!({})).await.unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn test_deferred_actions_run_when_due() {
+    let jobs = Jobs::new(MemoryJobStore::new()).with_status_prefix("/api/jobs");
+    let router = router(&jobs);
+
+    let now = signup(&router, "ada@example.com", 0).await.unwrap();
+    let later = signup(&router, "bob@example.com", 600).await.unwrap();
+    assert_eq!(now.status_path, format!("/api/jobs/{}", now.id));
+    assert_eq!(poll(&router, &now).await.status, JobStatus::Scheduled);
+
+    let ran = jobs.run_due(&router, &TestConfig, &TestEnv).await.unwrap();
+    assert_eq!(ran.len(), 1, "the job due in ten minutes waits");
+    let record = poll(&router, &now).await;
+    assert_eq!(record.status, JobStatus::Succeeded);
+    assert_eq!(record.route, "/emails/send");
+    assert_eq!(record.attempts, 1);
+    assert_eq!(record.output, Some(json!("sent to ada@example.com")));
+    assert!(SENT.lock().unwrap().contains(&"ada@example.com".to_string()));
+
+    // Cancelling through the status route's action.
+    let ctx = RouteContext { config: &TestConfig, env: &TestEnv };
+    let cancelled: JobRecord =
+        serde_json::from_value(router.act(&later.status_path, ctx, json!({}), json!({})).await.unwrap()).unwrap();
+    assert_eq!(cancelled.status, JobStatus::Cancelled);
+    assert_eq!(jobs.cancel("missing").await.unwrap(), None);
+    let ctx = RouteContext { config: &TestConfig, env: &TestEnv };
+    assert!(matches!(router.load("/api/jobs/missing", ctx, json!({})).await, Err(RouteError::NotFound)));
+}
+
+#[tokio::test]
+async fn test_deferred_input_is_validated_and_server_errors_are_retried() {
+    let jobs = Jobs::new(MemoryJobStore::new()).with_retries(1, Duration::ZERO);
+    let router = router(&jobs);
+
+    let invalid = signup(&router, "", 0).await.unwrap();
+    let down = signup(&router, "down@example.com", 0).await.unwrap();
+
+    jobs.run_due(&router, &TestConfig, &TestEnv).await.unwrap();
+    let record = poll(&router, &invalid).await;
+    assert_eq!(record.status, JobStatus::Failed, "rejected input is not retried");
+    assert_eq!(record.error.as_deref(), Some("Validation failed: empty address"));
+    assert_eq!(poll(&router, &down).await.status, JobStatus::Scheduled);
+
+    jobs.run_due(&router, &TestConfig, &TestEnv).await.unwrap();
+    let record = poll(&router, &down).await;
+    assert_eq!((record.status, record.attempts), (JobStatus::Failed, 2));
+    assert!(record.error.unwrap().contains("mail server unreachable"));
+}
+
+#[tokio::test]
+async fn test_defer_needs_a_job_queue() {
+    let mut router = Router::new();
+    router.register(SignupRoute);
+    match signup(&router, "ada@example.com", 0).await {
+        Err(RouteError::InternalError(message)) => assert!(message.contains("JobsPlate")),
+        other => panic!("expected an internal error, got {:?}", other),
+    }
+}
@@ -0,0 +1,117 @@
+use async_trait::async_trait;
+use montrs_core::{AppConfig, AppSpec, EnvConfig, ErasureReport, PersonalData, Plate, PlateContext};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+/// Records per user; erasing logs the category so the order can be checked.
+struct Records {
+    category: &'static str,
+    rows: Mutex<HashMap<String, u64>>,
+    erased: Arc<Mutex<Vec<&'static str>>>,
+}
+
+impl Records {
+    fn new(category: &'static str, erased: &Arc<Mutex<Vec<&'static str>>>) -> Arc<Self> {
+        let rows = HashMap::from([("ada".to_string(), 2), ("bob".to_string(), 1)]);
+        Arc::new(Self { category, rows: Mutex::new(rows), erased: erased.clone() })
+    }
+}
+
+#[async_trait]
+impl PersonalData for Records {
+    fn category(&self) -> String {
+        self.category.to_string()
+    }
+    async fn erase(&self, user_id: &str) -> anyhow::Result<u64> {
+        if self.category == "offline" {
+            anyhow::bail!("backup server unreachable");
+        }
+        self.erased.lock().unwrap().push(self.category);
+        Ok(self.rows.lock().unwrap().remove(user_id).unwrap_or(0))
+    }
+}
+
+struct DataPlate(&'static str, Vec<Arc<Records>>);
+
+#[async_trait]
+impl Plate<TestConfig> for DataPlate {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+    async fn init(&self, _ctx: &mut PlateContext<TestConfig>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+    fn personal_data(&self) -> Vec<Arc<dyn PersonalData>> {
+        self.1.iter().map(|r| r.clone() as Arc<dyn PersonalData>).collect()
+    }
+}
+
+struct NoDataPlate;
+
+#[async_trait]
+impl Plate<TestConfig> for NoDataPlate {
+    fn name(&self) -> &'static str {
+        "health"
+    }
+    async fn init(&self, _ctx: &mut PlateContext<TestConfig>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_erase_user_calls_every_hook_and_reports() {
+    let erased = Arc::new(Mutex::new(Vec::new()));
+    let spec = AppSpec::new(TestConfig, TestEnv)
+        .with_plate(Box::new(DataPlate("users", vec![Records::new("profiles", &erased)])))
+        .with_plate(Box::new(NoDataPlate))
+        .with_plate(Box::new(DataPlate("shop", vec![Records::new("orders", &erased), Records::new("carts", &erased)])))
+        .with_personal_data(Records::new("newsletter", &erased));
+
+    let report = spec.erase_user("ada").await;
+    assert_eq!(*erased.lock().unwrap(), ["orders", "carts", "profiles", "newsletter"], "last plate first, then the app");
+    let sources: Vec<_> = report.entries.iter().map(|e| (e.source.as_str(), e.category.as_str(), e.records)).collect();
+    assert_eq!(
+        sources,
+        [("shop", "orders", 2), ("shop", "carts", 2), ("users", "profiles", 2), ("app", "newsletter", 2)]
+    );
+    assert!(report.is_complete());
+    assert_eq!((report.user_id.as_str(), report.records()), ("ada", 8));
+    assert!(report.started_at <= report.finished_at);
+
+    // The report is what gets kept as the record of the request.
+    let saved: ErasureReport = serde_json::from_str(&serde_json::to_string(&report).unwrap()).unwrap();
+    assert_eq!(saved, report);
+
+    let again = spec.erase_user("ada").await;
+    assert_eq!(again.records(), 0);
+}
+
+#[tokio::test]
+async fn test_failing_hooks_are_reported_and_the_rest_still_run() {
+    let erased = Arc::new(Mutex::new(Vec::new()));
+    let spec = AppSpec::new(TestConfig, TestEnv)
+        .with_plate(Box::new(DataPlate("users", vec![Records::new("profiles", &erased)])))
+        .with_plate(Box::new(DataPlate("backups", vec![Records::new("offline", &erased)])));
+
+    let report = spec.erase_user("bob").await;
+    assert!(!report.is_complete());
+    assert_eq!(*erased.lock().unwrap(), ["profiles"]);
+    let failures: Vec<_> = report.failures().map(|e| (e.source.as_str(), e.error.as_deref())).collect();
+    assert_eq!(failures, [("backups", Some("backup server unreachable"))]);
+    assert_eq!(report.records(), 1);
+}
@@ -0,0 +1,85 @@
+use montrs_core::RouteTrie;
+use montrs_core::matcher::{MAX_PATH_LEN, MAX_SEGMENTS};
+use serde_json::json;
+
+fn trie(patterns: &[&'static str]) -> RouteTrie {
+    let mut trie = RouteTrie::new();
+    for pattern in patterns {
+        trie.insert(pattern);
+    }
+    trie
+}
+
+#[test]
+fn test_static_param_and_wildcard_priority() {
+    let trie = trie(&["/", "/users", "/users/me", "/users/:id", "/users/:id/posts/:post", "/files/*path"]);
+    assert_eq!(trie.len(), 6);
+
+    assert_eq!(trie.at("/").unwrap().pattern, "/");
+    assert_eq!(trie.at("/users/").unwrap().pattern, "/users");
+    assert_eq!(trie.at("/users/me").unwrap().pattern, "/users/me");
+
+    let user = trie.at("/users/42?tab=posts").unwrap();
+    assert_eq!(user.pattern, "/users/:id");
+    assert_eq!(user.params, vec![("id".to_string(), "42".to_string())]);
+
+    let post = trie.at("/users/7/posts/hello").unwrap();
+    assert_eq!(post.param("id"), Some("7"));
+    assert_eq!(post.param("post"), Some("hello"));
+
+    assert_eq!(trie.at("/files/docs/a.txt").unwrap().param("path"), Some("docs/a.txt"));
+    assert_eq!(trie.at("/files").unwrap().param("path"), Some(""));
+
+    assert!(trie.at("/users/7/posts").is_none());
+    assert!(trie.at("/nope").is_none());
+}
+
+#[test]
+fn test_backtracks_from_static_to_param() {
+    // `/a/b` goes down the static `a` branch, which has no `b` leaf, so the
+    // matcher must fall back to the `:x/b` pattern.
+    let trie = trie(&["/a/c", "/:x/b"]);
+    let found = trie.at("/a/b").unwrap();
+    assert_eq!(found.pattern, "/:x/b");
+    assert_eq!(found.param("x"), Some("a"));
+}
+
+#[test]
+fn test_params_json_and_merge() {
+    let trie = trie(&["/orders/:id/:code"]);
+    let found = trie.at("/orders/42/007").unwrap();
+    assert_eq!(found.params_json(), json!({ "id": 42, "code": "007" }));
+    assert_eq!(
+        found.merge_into(json!({ "id": 1, "page": 2 })),
+        json!({ "id": 1, "code": "007", "page": 2 })
+    );
+}
+
+#[test]
+fn test_rejects_pathological_paths() {
+    // Many overlapping param/static alternatives: lookup stays bounded by the
+    // trie size instead of exploring every combination.
+    let mut trie = RouteTrie::new();
+    let patterns: Vec<&'static str> = (0..20)
+        .map(|i| {
+            let mut pattern = String::new();
+            for depth in 0..20 {
+                pattern.push_str(if depth == i { "/x" } else { "/:p" });
+            }
+            pattern.push_str("/end");
+            &*Box::leak(pattern.into_boxed_str())
+        })
+        .collect();
+    for pattern in &patterns {
+        trie.insert(pattern);
+    }
+    let miss = "/x".repeat(20) + "/nope";
+    assert!(trie.at(&miss).is_none());
+
+    let deep = "/a".repeat(MAX_SEGMENTS + 1);
+    let long = format!("/{}", "a".repeat(MAX_PATH_LEN));
+    let catch_all = self::trie(&["/*rest"]);
+    assert!(catch_all.at("/a/b").is_some());
+    assert!(catch_all.at(&deep).is_none());
+    assert!(catch_all.at(&long).is_none());
+}
@@ -0,0 +1,117 @@
+use montrs_core::{AppConfig, EnvConfig, MockSelection, Mocks, RouteContext, RouteError, Router};
+use serde_json::json;
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[tokio::test]
+async fn test_closure_mocks_answer_unregistered_routes() {
+    let mut router = Router::<TestConfig>::new();
+    router.set_mocks(
+        Mocks::new()
+            .loader("/users/:id", |params, _| Ok(json!({ "id": params["id"], "name": "Ada" })))
+            .action("/users/:id", |_, input| Ok(json!({ "saved": input.cloned() }))),
+    );
+
+    let config = TestConfig;
+    let env = TestEnv;
+    let ctx = || RouteContext { config: &config, env: &env };
+
+    let data = router.load("/users/:id", ctx(), json!({ "id": 7 })).await.unwrap();
+    assert_eq!(data, json!({ "id": 7, "name": "Ada" }));
+
+    let saved = router
+        .act_body("/users/:id", ctx(), json!({ "id": 7 }), "application/json", br#""Grace""#)
+        .await
+        .unwrap();
+    assert_eq!(saved, json!({ "saved": "Grace" }));
+
+    let missing = router.load("/posts", ctx(), json!({})).await;
+    assert!(matches!(missing, Err(RouteError::NotFound)));
+}
+
+#[tokio::test]
+async fn test_mock_directory_and_selection() {
+    let dir = std::env::temp_dir().join(format!("montrs-mocks-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("users")).unwrap();
+    std::fs::write(
+        dir.join("users/profile.json"),
+        r#"{ "route": "/users/:id", "loader": { "body": { "name": "Ada" } } }"#,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("errors.json"),
+        r#"[
+            { "route": "/admin", "loader": { "status": 401 } },
+            { "route": "/signup", "action": { "status": 422, "body": "email is taken" } }
+        ]"#,
+    )
+    .unwrap();
+
+    let mocks = Mocks::load_dir(&dir).unwrap();
+    assert_eq!(mocks.routes(), vec!["/admin", "/signup", "/users/:id"]);
+
+    let mut router = Router::<TestConfig>::new();
+    router.set_mocks(mocks.clone());
+    let config = TestConfig;
+    let env = TestEnv;
+    let ctx = || RouteContext { config: &config, env: &env };
+
+    let data = router.load("/users/:id", ctx(), json!({ "id": 1 })).await.unwrap();
+    assert_eq!(data, json!({ "name": "Ada" }));
+    assert!(matches!(router.load("/admin", ctx(), json!({})).await, Err(RouteError::Unauthorized)));
+    match router.act("/signup", ctx(), json!({}), json!({})).await {
+        Err(RouteError::ValidationFailed(message)) => assert_eq!(message, "email is taken"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let only_users = mocks.with_selection(MockSelection::parse("/users/:id"));
+    assert_eq!(only_users.routes(), vec!["/users/:id"]);
+    router.set_mocks(only_users);
+    assert!(matches!(router.load("/admin", ctx(), json!({})).await, Err(RouteError::NotFound)));
+
+    std::fs::write(dir.join("broken.json"), "{ not json").unwrap();
+    assert!(Mocks::load_dir(&dir).is_err());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_fixtures_pin_loaders_over_mocks() {
+    let dir = std::env::temp_dir().join(format!("montrs-fixtures-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("todos.json"), r#"[{ "id": 1, "title": "Write tests" }]"#).unwrap();
+
+    let fixtures = std::collections::HashMap::from([("/api/todos".to_string(), dir.join("todos.json"))]);
+    let mocks = Mocks::new()
+        .loader("/api/todos", |_, _| Ok(json!([])))
+        .loader("/users/:id", |_, _| Ok(json!({ "name": "Ada" })))
+        .with_selection(MockSelection::parse("/users/:id"))
+        .merge(Mocks::load_fixtures(&fixtures).unwrap());
+    assert_eq!(mocks.routes(), vec!["/api/todos", "/users/:id"]);
+
+    let mut router = Router::<TestConfig>::new();
+    router.set_mocks(mocks);
+    let config = TestConfig;
+    let env = TestEnv;
+    let ctx = || RouteContext { config: &config, env: &env };
+    let todos = router.load("/api/todos", ctx(), json!({})).await.unwrap();
+    assert_eq!(todos, json!([{ "id": 1, "title": "Write tests" }]));
+
+    let missing = std::collections::HashMap::from([("/posts".to_string(), dir.join("missing.json"))]);
+    assert!(Mocks::load_fixtures(&missing).is_err());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
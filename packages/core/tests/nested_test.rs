@@ -0,0 +1,137 @@
+use async_trait::async_trait;
+use montrs_core::{
+    AppConfig, EnvConfig, FnGuard, GuardOutcome, LayoutData, Route, RouteAction, RouteContext, RouteError, RouteLoader,
+    RouteParams, RouteView, Router,
+};
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Whether the caller is a member of the team they look at.
+#[derive(Clone)]
+struct TestConfig {
+    member: bool,
+}
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct TeamParams {
+    #[serde(default)]
+    team: Option<String>,
+}
+impl RouteParams for TeamParams {}
+
+/// Loads its own path, relative to the scope, and the team it was given.
+struct Page<const N: usize>;
+#[async_trait]
+impl<const N: usize> RouteLoader<TeamParams, TestConfig> for Page<N> {
+    type Output = String;
+    async fn load(&self, _ctx: RouteContext<'_, TestConfig>, params: TeamParams) -> Result<String, RouteError> {
+        Ok(format!("{} {}", Self::path(), params.team.unwrap_or_default()).trim_end().to_string())
+    }
+}
+#[async_trait]
+impl<const N: usize> RouteAction<TeamParams, TestConfig> for Page<N> {
+    type Input = ();
+    type Output = ();
+    async fn act(&self, _ctx: RouteContext<'_, TestConfig>, _params: TeamParams, _input: ()) -> Result<(), RouteError> {
+        Ok(())
+    }
+}
+impl<const N: usize> RouteView for Page<N> {
+    fn render(&self) -> impl IntoView {}
+}
+impl<const N: usize> Route<TestConfig> for Page<N> {
+    type Params = TeamParams;
+    type Loader = Self;
+    type Action = Self;
+    type View = Self;
+    fn path() -> &'static str {
+        ["/", "/users", "/:team", "/:team/members"][N]
+    }
+    fn loader(&self) -> Self {
+        Page
+    }
+    fn action(&self) -> Self {
+        Page
+    }
+    fn view(&self) -> Self {
+        Page
+    }
+}
+
+fn router() -> Router<TestConfig> {
+    let members_only = |ctx: &RouteContext<'_, TestConfig>, _: &str| match ctx.config.member {
+        true => GuardOutcome::Allow,
+        false => GuardOutcome::Deny(403),
+    };
+    let mut router = Router::new();
+    router.register(Page::<1>);
+    router.scope("/admin", |admin| {
+        admin.layout(Page::<0>);
+        admin.register(Page::<1>);
+        admin.scope("teams/", |teams| {
+            teams.layout(Page::<2>).guard(FnGuard::new("member", members_only));
+            teams.register(Page::<3>);
+        });
+    });
+    router
+}
+
+#[test]
+fn test_scopes_nest_routes_under_their_layouts() {
+    let router = router();
+    assert_eq!(router.resolve("/admin").unwrap().pattern, "/admin");
+    assert_eq!(router.resolve("/admin/users").unwrap().pattern, "/admin/users");
+    assert_eq!(router.resolve("/admin/teams/core/members").unwrap().pattern, "/admin/teams/:team/members");
+    assert_eq!(router.resolve("/users").unwrap().pattern, "/users");
+
+    assert_eq!(router.parent("/admin/users"), Some("/admin"));
+    assert_eq!(router.parent("/admin/teams/:team"), Some("/admin"));
+    assert_eq!(router.layouts("/admin/teams/:team/members"), ["/admin", "/admin/teams/:team"]);
+    assert_eq!(router.parent("/admin"), None);
+    assert_eq!(router.parent("/users"), None, "routes outside the scope are not nested");
+
+    let spec = router.spec();
+    assert_eq!(spec.routes["/admin"].children, ["/admin/teams/:team", "/admin/users"]);
+    assert_eq!(spec.routes["/admin/teams/:team"].children, ["/admin/teams/:team/members"]);
+    assert_eq!(spec.routes["/admin/teams/:team/members"].parent.as_deref(), Some("/admin/teams/:team"));
+    let json = serde_json::to_value(&spec.routes["/users"]).unwrap();
+    assert!(json.get("parent").is_none() && json.get("children").is_none(), "flat routes export as before");
+}
+
+#[tokio::test]
+async fn test_nested_load_runs_the_layout_loaders_with_the_route() {
+    let router = router();
+    let config = TestConfig { member: true };
+    let ctx = RouteContext { config: &config, env: &TestEnv };
+    let nested = router.load_nested("/admin/teams/core/members", ctx, serde_json::json!({})).await.unwrap();
+    assert_eq!(
+        nested.layouts,
+        [
+            LayoutData { path: "/admin".to_string(), data: serde_json::json!("/ core") },
+            LayoutData { path: "/admin/teams/:team".to_string(), data: serde_json::json!("/:team core") },
+        ]
+    );
+    assert_eq!(nested.data, serde_json::json!("/:team/members core"));
+
+    let ctx = RouteContext { config: &config, env: &TestEnv };
+    let flat = router.load_nested("/users", ctx, serde_json::json!({})).await.unwrap();
+    assert!(flat.layouts.is_empty());
+    assert_eq!(flat.data, serde_json::json!("/users"));
+
+    let outsider = TestConfig { member: false };
+    let ctx = RouteContext { config: &outsider, env: &TestEnv };
+    let denied = router.load_nested("/admin/teams/core/members", ctx, serde_json::json!({})).await;
+    assert!(matches!(denied, Err(RouteError::Denied(403))), "a layout's guard covers the routes nested in it");
+}
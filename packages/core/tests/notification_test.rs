@@ -0,0 +1,264 @@
+#![cfg(feature = "notifications")]
+
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::{Aes128Gcm, Nonce};
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use futures::StreamExt;
+use hkdf::Hkdf;
+use montrs_core::{
+    AppConfig, Channel, EnvConfig, Mailer, MemoryNotificationStore, Notification, NotificationStore, Notifications,
+    NotificationsPlate, Plate, Preferences, PushKeys, PushSubscription, PushTransport, RouteContext, RouteError, Router,
+    VapidKeys, WebPush,
+};
+use p256::ecdsa::signature::Verifier;
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use serde_json::json;
+use sha2::Sha256;
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Clone, Default)]
+struct Outbox(Arc<Mutex<Vec<(String, String, String)>>>);
+
+#[async_trait]
+impl Mailer for Outbox {
+    async fn send(&self, to: &str, subject: &str, text: &str) -> anyhow::Result<()> {
+        self.0.lock().unwrap().push((to.to_string(), subject.to_string(), text.to_string()));
+        Ok(())
+    }
+}
+
+/// A push request: URL, headers and body.
+type PushRequest = (String, Vec<(String, String)>, Vec<u8>);
+
+/// Records push requests and answers with `status`.
+#[derive(Clone)]
+struct PushService {
+    status: u16,
+    requests: Arc<Mutex<Vec<PushRequest>>>,
+}
+
+impl PushService {
+    fn answering(status: u16) -> Self {
+        Self { status, requests: Arc::default() }
+    }
+}
+
+#[async_trait]
+impl PushTransport for PushService {
+    async fn post(&self, url: &str, headers: &[(String, String)], body: Vec<u8>) -> Result<u16, String> {
+        self.requests.lock().unwrap().push((url.to_string(), headers.to_vec(), body));
+        Ok(self.status)
+    }
+}
+
+/// A browser's push keys.
+struct Browser {
+    secret: p256::SecretKey,
+    auth: [u8; 16],
+}
+
+impl Browser {
+    fn new() -> Self {
+        Self { secret: p256::SecretKey::random(&mut OsRng), auth: *b"0123456789abcdef" }
+    }
+
+    fn keys(&self) -> PushKeys {
+        PushKeys {
+            p256dh: URL_SAFE_NO_PAD.encode(self.secret.public_key().to_encoded_point(false).as_bytes()),
+            auth: URL_SAFE_NO_PAD.encode(self.auth),
+        }
+    }
+
+    /// Decrypts an `aes128gcm` push body the way a browser does (RFC 8291).
+    fn decrypt(&self, body: &[u8]) -> Vec<u8> {
+        let (salt, rest) = body.split_at(16);
+        assert_eq!(u32::from_be_bytes(rest[..4].try_into().unwrap()), 4096);
+        let id_len = rest[4] as usize;
+        let (server_public, ciphertext) = rest[5..].split_at(id_len);
+
+        let server_key = p256::PublicKey::from_sec1_bytes(server_public).unwrap();
+        let shared = p256::ecdh::diffie_hellman(self.secret.to_nonzero_scalar(), server_key.as_affine());
+        let mut info = b"WebPush: info\0".to_vec();
+        info.extend_from_slice(self.secret.public_key().to_encoded_point(false).as_bytes());
+        info.extend_from_slice(server_public);
+        let mut ikm = [0u8; 32];
+        Hkdf::<Sha256>::new(Some(&self.auth), shared.raw_secret_bytes()).expand(&info, &mut ikm).unwrap();
+        let prk = Hkdf::<Sha256>::new(Some(salt), &ikm);
+        let (mut key, mut nonce) = ([0u8; 16], [0u8; 12]);
+        prk.expand(b"Content-Encoding: aes128gcm\0", &mut key).unwrap();
+        prk.expand(b"Content-Encoding: nonce\0", &mut nonce).unwrap();
+
+        let mut plaintext = Aes128Gcm::new(&key.into()).decrypt(Nonce::from_slice(&nonce), ciphertext).unwrap();
+        assert_eq!(plaintext.pop(), Some(2));
+        plaintext
+    }
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> &'a str {
+    headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str()).unwrap()
+}
+
+#[tokio::test]
+async fn test_notify_follows_preferences() {
+    let outbox = Outbox::default();
+    let notifications = Notifications::new(MemoryNotificationStore::new()).with_mailer(outbox.clone());
+    let mut preferences = Preferences::new("ada");
+    preferences.email = Some("ada@example.com".to_string());
+    preferences.muted = vec!["marketing.*".to_string()];
+    notifications.set_preferences(&preferences).await.unwrap();
+
+    let mut live = notifications.subscribe("ada");
+    let sent = notifications
+        .notify(Notification::new("ada", "order.shipped", "Your order is on its way").with_url("https://shop.example/orders/7"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(live.next().await.unwrap(), sent);
+    assert_eq!(
+        outbox.0.lock().unwrap()[0],
+        ("ada@example.com".to_string(), "Your order is on its way".to_string(), "https://shop.example/orders/7".to_string())
+    );
+
+    assert!(notifications.notify(Notification::new("ada", "marketing.sale", "Sale!")).await.unwrap().is_none());
+    preferences.disabled = vec![Channel::Email];
+    notifications.set_preferences(&preferences).await.unwrap();
+    notifications.notify(Notification::new("ada", "order.delivered", "Delivered")).await.unwrap();
+    notifications.notify(Notification::new("bob", "order.delivered", "Delivered")).await.unwrap();
+    assert_eq!(outbox.0.lock().unwrap().len(), 1);
+
+    let inbox = notifications.inbox("ada", false, 10).await.unwrap();
+    assert_eq!(inbox.unread, 2);
+    assert_eq!(inbox.notifications[0].kind, "order.delivered");
+    assert_eq!(notifications.mark_read("ada", Some(std::slice::from_ref(&sent.id))).await.unwrap(), 1);
+    let inbox = notifications.inbox("ada", true, 10).await.unwrap();
+    assert_eq!((inbox.unread, inbox.notifications.len()), (1, 1));
+    assert_eq!(notifications.mark_read("ada", None).await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn test_event_stream_sends_server_sent_events() {
+    let notifications = Notifications::new(MemoryNotificationStore::new());
+    let mut response = notifications.event_stream("ada");
+    assert_eq!(response.header("Content-Type"), Some("text/event-stream"));
+
+    notifications.notify(Notification::new("ada", "comment.created", "New comment")).await.unwrap();
+    let event = String::from_utf8(response.body.next().await.unwrap().unwrap()).unwrap();
+    assert!(event.starts_with("event: notification\ndata: {"), "{}", event);
+    assert!(event.ends_with("}\n\n"));
+    assert!(event.contains(r#""title":"New comment""#));
+}
+
+#[tokio::test]
+async fn test_web_push_is_encrypted_and_signed() {
+    let service = PushService::answering(201);
+    let keys = VapidKeys::generate();
+    let push = WebPush::new(VapidKeys::from_base64(&keys.private_key()).unwrap(), "mailto:ops@example.com").with_transport(service.clone());
+    let notifications = Notifications::new(MemoryNotificationStore::new()).with_push(push);
+    let browser = Browser::new();
+    let endpoint = "https://push.example.com/send/abc";
+    notifications.register_push(&PushSubscription::new("ada", endpoint, browser.keys())).await.unwrap();
+
+    notifications
+        .notify(Notification::new("ada", "order.shipped", "Shipped").with_data(json!({ "order": 7 })))
+        .await
+        .unwrap();
+    let (url, headers, body) = service.requests.lock().unwrap().pop().unwrap();
+    assert_eq!(url, endpoint);
+    assert_eq!(header(&headers, "Content-Encoding"), "aes128gcm");
+    assert_eq!(header(&headers, "TTL"), "86400");
+
+    let payload: serde_json::Value = serde_json::from_slice(&browser.decrypt(&body)).unwrap();
+    assert_eq!(payload["title"], "Shipped");
+    assert_eq!(payload["data"]["order"], 7);
+
+    let (jwt, key) = header(&headers, "Authorization").strip_prefix("vapid t=").unwrap().split_once(", k=").unwrap();
+    assert_eq!(key, keys.public_key());
+    let (signing_input, signature) = jwt.rsplit_once('.').unwrap();
+    let verifying_key = p256::ecdsa::VerifyingKey::from_sec1_bytes(&URL_SAFE_NO_PAD.decode(key).unwrap()).unwrap();
+    let signature = p256::ecdsa::Signature::from_slice(&URL_SAFE_NO_PAD.decode(signature).unwrap()).unwrap();
+    verifying_key.verify(signing_input.as_bytes(), &signature).unwrap();
+    let claims: serde_json::Value =
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(signing_input.split('.').nth(1).unwrap()).unwrap()).unwrap();
+    assert_eq!(claims["aud"], "https://push.example.com");
+    assert_eq!(claims["sub"], "mailto:ops@example.com");
+}
+
+#[tokio::test]
+async fn test_gone_push_subscriptions_are_forgotten() {
+    let service = PushService::answering(410);
+    let store = Arc::new(MemoryNotificationStore::new());
+    let push = WebPush::new(VapidKeys::generate(), "mailto:ops@example.com").with_transport(service);
+    let notifications = Notifications::new(store.clone()).with_push(push);
+    notifications
+        .register_push(&PushSubscription::new("ada", "https://push.example.com/send/old", Browser::new().keys()))
+        .await
+        .unwrap();
+
+    notifications.notify(Notification::new("ada", "order.shipped", "Shipped")).await.unwrap();
+    assert!(store.push_subscriptions("ada").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_plate_registers_routes_under_its_prefix() {
+    let notifications = Arc::new(Notifications::new(MemoryNotificationStore::new()));
+    let plate = NotificationsPlate::new(notifications.clone(), |_ctx: &RouteContext<'_, TestConfig>| Some("ada".to_string()))
+        .with_prefix("/api/notifications/");
+    let mut router = Router::new();
+    plate.register_routes(&mut router);
+    assert!(router.resolve("/notifications").is_none());
+    assert!(router.spec().routes["/api/notifications/preferences"].path == "/api/notifications/preferences");
+
+    let ctx = || RouteContext { config: &TestConfig, env: &TestEnv };
+    notifications.notify(Notification::new("ada", "order.shipped", "Shipped")).await.unwrap();
+    notifications.notify(Notification::new("bob", "order.shipped", "Shipped")).await.unwrap();
+
+    let inbox = router.load("/api/notifications", ctx(), json!({ "unread": "true", "limit": 5 })).await.unwrap();
+    assert_eq!(inbox["unread"], 1);
+    let receipt = router.act("/api/notifications", ctx(), json!({}), json!({})).await.unwrap();
+    assert_eq!(receipt, json!({ "marked": 1, "unread": 0 }));
+
+    let saved = router
+        .act("/api/notifications/preferences", ctx(), json!({}), json!({ "user_id": "bob", "disabled": ["push"] }))
+        .await
+        .unwrap();
+    assert_eq!(saved["user_id"], "ada");
+    assert!(!notifications.preferences("ada").await.unwrap().allows(Channel::Push));
+
+    let registration = json!({ "endpoint": "https://push.example.com/send/x", "keys": { "p256dh": "bad", "auth": "bad" } });
+    let err = router.act("/api/notifications/push", ctx(), json!({}), registration).await.unwrap_err();
+    assert!(matches!(err, RouteError::ValidationFailed(_)));
+    let push = router.load("/api/notifications/push", ctx(), json!({})).await.unwrap();
+    assert_eq!(push, json!({ "public_key": null }));
+
+    // The live subscription sends the user's notifications as they are sent.
+    assert_eq!(router.spec().subscriptions[0].path, "/api/notifications/live");
+    let mut live = router.subscribe("/api/notifications/live", ctx(), json!({})).await.unwrap();
+    notifications.notify(Notification::new("bob", "order.shipped", "Bob's")).await.unwrap();
+    notifications.notify(Notification::new("ada", "order.shipped", "Ada's")).await.unwrap();
+    assert_eq!(live.next().await.unwrap()["title"], "Ada's");
+
+    let anonymous = NotificationsPlate::new(notifications, |_ctx: &RouteContext<'_, TestConfig>| None);
+    let mut router = Router::new();
+    anonymous.register_routes(&mut router);
+    let err = router.load("/notifications", ctx(), json!({})).await.unwrap_err();
+    assert!(matches!(err, RouteError::Unauthorized));
+    let err = router.subscribe("/notifications/live", ctx(), json!({})).await.err();
+    assert!(matches!(err, Some(RouteError::Unauthorized)));
+}
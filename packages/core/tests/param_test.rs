@@ -0,0 +1,44 @@
+use chrono::NaiveDate;
+use montrs_core::{FromParam, ParamSchema};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Archive {
+    #[serde(deserialize_with = "montrs_core::param::deserialize")]
+    day: NaiveDate,
+    #[serde(deserialize_with = "montrs_core::param::deserialize")]
+    page: u32,
+    #[serde(deserialize_with = "montrs_core::param::deserialize")]
+    drafts: bool,
+}
+
+#[test]
+fn test_builtin_params_parse_text() {
+    assert_eq!(u32::from_param("12"), Ok(12));
+    assert_eq!(i64::from_param("-3"), Ok(-3));
+    assert_eq!(bool::from_param("1"), Ok(true));
+    assert_eq!(String::from_param("42"), Ok("42".to_string()));
+    assert_eq!(NaiveDate::from_param("2024-02-29"), Ok(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()));
+
+    assert_eq!(u32::from_param("-1").unwrap_err().to_string(), "invalid u32 `-1`: expected an integer");
+    assert_eq!(bool::from_param("yes").unwrap_err().reason(), "expected true or false");
+    assert!(NaiveDate::from_param("2023-02-29").unwrap_err().to_string().starts_with("invalid NaiveDate `2023-02-29`"));
+}
+
+#[test]
+fn test_builtin_param_schemas() {
+    assert_eq!(String::param_schema(), ParamSchema::new("string"));
+    assert_eq!(i64::param_schema(), ParamSchema::new("integer").with_format("int64"));
+    assert_eq!(f64::param_schema(), ParamSchema::new("number").with_format("double"));
+    assert_eq!(NaiveDate::param_schema(), ParamSchema::new("string").with_format("date"));
+    assert_eq!(serde_json::to_value(NaiveDate::param_schema()).unwrap(), serde_json::json!({ "type": "string", "format": "date" }));
+}
+
+#[test]
+fn test_deserialize_accepts_numbers_and_strings() {
+    let archive: Archive = serde_json::from_value(serde_json::json!({ "day": "2024-01-31", "page": 2, "drafts": "false" })).unwrap();
+    assert_eq!(archive, Archive { day: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(), page: 2, drafts: false });
+
+    let err = serde_json::from_value::<Archive>(serde_json::json!({ "day": "2024-01-31", "page": "two", "drafts": true })).unwrap_err();
+    assert_eq!(err.to_string(), "invalid u32 `two`: expected an integer");
+}
@@ -0,0 +1,159 @@
+use async_trait::async_trait;
+use leptos::prelude::*;
+use montrs_core::{
+    AppConfig, EnvConfig, JsonBytes, Mocks, Route, RouteAction, RouteContext, RouteError, RouteLoader,
+    RouteParams, RouteView, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct NoParams {}
+impl RouteParams for NoParams {}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Row {
+    id: u32,
+    name: String,
+}
+
+struct RowsLoader;
+#[async_trait]
+impl RouteLoader<NoParams, TestConfig> for RowsLoader {
+    type Output = Vec<Row>;
+    async fn load(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams) -> Result<Self::Output, RouteError> {
+        Ok((0..3).map(|id| Row { id, name: format!("row {}", id) }).collect())
+    }
+}
+
+static CACHED: &[u8] = br#"{"report":[1,2,3]}"#;
+
+struct CachedLoader;
+#[async_trait]
+impl RouteLoader<NoParams, TestConfig> for CachedLoader {
+    type Output = JsonBytes;
+    async fn load(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams) -> Result<Self::Output, RouteError> {
+        Ok(JsonBytes::from_static(CACHED))
+    }
+}
+
+struct NoAction;
+#[async_trait]
+impl RouteAction<NoParams, TestConfig> for NoAction {
+    type Input = ();
+    type Output = ();
+    async fn act(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams, _input: ()) -> Result<(), RouteError> {
+        Ok(())
+    }
+}
+
+struct EmptyView;
+impl RouteView for EmptyView {
+    fn render(&self) -> impl IntoView {
+        view! { <div></div> }
+    }
+}
+
+macro_rules! route {
+    ($name:ident, $path:literal, $loader:ident) => {
+        struct $name;
+        impl Route<TestConfig> for $name {
+            type Params = NoParams;
+            type Loader = $loader;
+            type Action = NoAction;
+            type View = EmptyView;
+            fn path() -> &'static str {
+                $path
+            }
+            fn loader(&self) -> Self::Loader {
+                $loader
+            }
+            fn action(&self) -> Self::Action {
+                NoAction
+            }
+            fn view(&self) -> Self::View {
+                EmptyView
+            }
+        }
+    };
+}
+
+route!(RowsRoute, "/rows", RowsLoader);
+route!(CachedRoute, "/report", CachedLoader);
+
+fn router() -> Router<TestConfig> {
+    let mut router = Router::<TestConfig>::new();
+    router.register(RowsRoute);
+    router.register(CachedRoute);
+    router
+}
+
+#[tokio::test]
+async fn test_load_to_writes_the_same_json_as_load() {
+    let router = router();
+    let config = TestConfig;
+    let env = TestEnv;
+    let ctx = || RouteContext { config: &config, env: &env };
+
+    let value = router.load("/rows", ctx(), json!({})).await.unwrap();
+    let mut body = Vec::new();
+    router.load_to("/rows", ctx(), json!({}), &mut body).await.unwrap();
+    assert_eq!(body, serde_json::to_vec(&value).unwrap());
+
+    let bytes = router.load_bytes("/rows", ctx(), json!({})).await.unwrap();
+    let rows: Vec<Row> = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(rows[2], Row { id: 2, name: "row 2".to_string() });
+
+    let mut body = Vec::new();
+    let missing = router.load_to("/nope", ctx(), json!({}), &mut body).await;
+    assert!(matches!(missing, Err(RouteError::NotFound)));
+    assert!(body.is_empty());
+}
+
+#[tokio::test]
+async fn test_json_bytes_pass_through() {
+    let mut router = router();
+    let config = TestConfig;
+    let env = TestEnv;
+    let ctx = || RouteContext { config: &config, env: &env };
+
+    // The pre-serialized buffer is handed back as-is, not re-encoded.
+    let bytes = router.load_bytes("/report", ctx(), json!({})).await.unwrap();
+    assert_eq!(bytes.as_ptr(), CACHED.as_ptr());
+
+    let mut body = Vec::new();
+    router.load_to("/report", ctx(), json!({}), &mut body).await.unwrap();
+    assert_eq!(body, CACHED);
+
+    assert_eq!(router.load("/report", ctx(), json!({})).await.unwrap(), json!({ "report": [1, 2, 3] }));
+
+    router.set_mocks(Mocks::new().loader("/report", |_, _| Ok(json!({ "mocked": true }))));
+    let bytes = router.load_bytes("/report", ctx(), json!({})).await.unwrap();
+    assert_eq!(&bytes[..], br#"{"mocked":true}"#);
+}
+
+#[test]
+fn test_json_bytes_serde() {
+    let json = JsonBytes::from(r#"{"a": [1, 2]}"#.to_string());
+    assert_eq!(serde_json::to_string(&json).unwrap(), r#"{"a": [1, 2]}"#);
+
+    let restored: JsonBytes = serde_json::from_value(json!({ "a": [1, 2] })).unwrap();
+    assert_eq!(restored.as_bytes(), br#"{"a":[1,2]}"#);
+
+    assert!(serde_json::to_string(&JsonBytes::from_static(b"{not json")).is_err());
+}
@@ -0,0 +1,204 @@
+#![cfg(feature = "payments")]
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use montrs_core::payment::{SIGNATURE_HEADER, USER_METADATA};
+use montrs_core::webhook::sign;
+use montrs_core::{
+    AppConfig, Checkout, CheckoutMode, Embedded, EnvConfig, MemoryPaymentStore, PaymentError, PaymentEventKind, Payments,
+    PaymentsPlate, Plate, RouteContext, RouteError, Router, Stripe, StripeTransport, SubscriptionStatus, WasiRequest,
+};
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+const WEBHOOK_SECRET: &str = "whsec_test";
+
+/// An API request: URL, headers and form.
+type ApiRequest = (String, Vec<(String, String)>, Vec<(String, String)>);
+
+/// Records API requests and answers with `status` and `body`.
+#[derive(Clone)]
+struct StripeApi {
+    status: u16,
+    body: Value,
+    requests: Arc<Mutex<Vec<ApiRequest>>>,
+}
+
+impl StripeApi {
+    fn answering(status: u16, body: Value) -> Self {
+        Self { status, body, requests: Arc::default() }
+    }
+
+    fn form(&self, n: usize) -> Vec<(String, String)> {
+        self.requests.lock().unwrap()[n].2.clone()
+    }
+}
+
+#[async_trait]
+impl StripeTransport for StripeApi {
+    async fn post(&self, url: &str, headers: &[(String, String)], body: Vec<u8>) -> Result<(u16, Vec<u8>), String> {
+        let form = form_urlencoded::parse(&body).into_owned().collect();
+        self.requests.lock().unwrap().push((url.to_string(), headers.to_vec(), form));
+        Ok((self.status, serde_json::to_vec(&self.body).unwrap()))
+    }
+}
+
+fn connect(api: &StripeApi) -> Arc<Payments> {
+    let stripe = Stripe::new("sk_test", WEBHOOK_SECRET).with_transport(api.clone());
+    Arc::new(Payments::new(stripe, MemoryPaymentStore::new()))
+}
+
+fn event(id: &str, kind: &str, created: i64, object: Value) -> (String, Vec<u8>) {
+    let body = serde_json::to_vec(&json!({ "id": id, "type": kind, "created": created, "data": { "object": object } })).unwrap();
+    (sign(WEBHOOK_SECRET, chrono::Utc::now().timestamp(), &body), body)
+}
+
+fn subscription(status: &str) -> Value {
+    json!({
+        "id": "sub_1",
+        "customer": "cus_1",
+        "status": status,
+        "created": 1_700_000_000,
+        "metadata": { USER_METADATA: "ada" },
+        "items": { "data": [{ "price": { "id": "price_pro" }, "quantity": 2, "current_period_end": 1_702_592_000 }] },
+    })
+}
+
+fn has(form: &[(String, String)], key: &str, value: &str) -> bool {
+    form.iter().any(|(k, v)| k == key && v == value)
+}
+
+#[tokio::test]
+async fn test_checkout_carries_the_user_and_reuses_their_customer() {
+    let api = StripeApi::answering(200, json!({ "id": "cs_1", "url": "https://checkout.stripe.com/c/cs_1" }));
+    let payments = connect(&api);
+    let checkout = Checkout::new(CheckoutMode::Subscription, "https://shop.example/ok", "https://shop.example/no")
+        .with_item("price_pro", 2)
+        .with_metadata("plan", "pro");
+
+    let session = payments.checkout("ada", checkout.clone()).await.unwrap();
+    assert_eq!(session.url, "https://checkout.stripe.com/c/cs_1");
+    let (url, headers, _) = api.requests.lock().unwrap()[0].clone();
+    assert_eq!(url, "https://api.stripe.com/v1/checkout/sessions");
+    assert!(headers.contains(&("Authorization".to_string(), "Bearer sk_test".to_string())));
+    let form = api.form(0);
+    assert!(has(&form, "mode", "subscription"));
+    assert!(has(&form, "line_items[0][price]", "price_pro") && has(&form, "line_items[0][quantity]", "2"));
+    assert!(has(&form, "client_reference_id", "ada"));
+    assert!(has(&form, "subscription_data[metadata][montrs_user_id]", "ada"));
+    assert!(has(&form, "subscription_data[metadata][plan]", "pro"));
+    assert!(!form.iter().any(|(k, _)| k == "customer"));
+
+    let (signature, body) = event(
+        "evt_1",
+        "checkout.session.completed",
+        1_700_000_000,
+        json!({ "id": "cs_1", "customer": "cus_1", "client_reference_id": "ada", "subscription": "sub_1" }),
+    );
+    payments.handle_webhook(&signature, &body).await.unwrap();
+    payments.checkout("ada", checkout).await.unwrap();
+    assert!(has(&api.form(1), "customer", "cus_1"));
+
+    let portal = payments.portal("bob", "https://shop.example/billing").await.unwrap_err();
+    assert!(matches!(portal, PaymentError::NoCustomer(_)));
+
+    let failing = StripeApi::answering(400, json!({ "error": { "message": "No such price: 'price_gone'" } }));
+    let err = connect(&failing)
+        .checkout("ada", Checkout::new(CheckoutMode::Payment, "https://a", "https://b").with_item("price_gone", 1))
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "Stripe answered 400: No such price: 'price_gone'");
+    assert!(has(&failing.form(0), "customer_creation", "always"));
+}
+
+#[tokio::test]
+async fn test_webhooks_sync_subscriptions_and_publish_events() {
+    let api = StripeApi::answering(200, json!({}));
+    let payments = connect(&api);
+    let mut events = payments.subscribe();
+
+    let (signature, body) = event("evt_2", "customer.subscription.updated", 1_700_000_100, subscription("active"));
+    let published = payments.handle_webhook(&signature, &body).await.unwrap().unwrap();
+    assert_eq!(published.user_id.as_deref(), Some("ada"));
+    let PaymentEventKind::SubscriptionUpdated(sub) = &published.kind else { panic!("{:?}", published.kind) };
+    assert_eq!((sub.status, sub.price_id.as_deref(), sub.quantity), (SubscriptionStatus::Active, Some("price_pro"), 2));
+    assert_eq!(sub.current_period_end.unwrap().timestamp(), 1_702_592_000);
+    assert_eq!(events.next().await.unwrap(), published);
+
+    // Stripe retries deliveries, and does not promise their order.
+    assert!(payments.handle_webhook(&signature, &body).await.unwrap().is_none());
+    let (signature, body) = event("evt_1", "customer.subscription.created", 1_700_000_000, subscription("incomplete"));
+    assert!(payments.handle_webhook(&signature, &body).await.unwrap().is_none());
+    let billing = payments.billing("ada").await.unwrap();
+    assert_eq!(billing.customer_id.as_deref(), Some("cus_1"));
+    assert!(billing.is_subscribed());
+
+    let (signature, body) = event("evt_3", "customer.subscription.deleted", 1_700_000_200, subscription("canceled"));
+    let ended = payments.handle_webhook(&signature, &body).await.unwrap().unwrap();
+    assert!(matches!(ended.kind, PaymentEventKind::SubscriptionDeleted(_)));
+    assert!(!payments.billing("ada").await.unwrap().is_subscribed());
+
+    // Events without the user's metadata find them through the customer.
+    let invoice = json!({ "id": "in_1", "customer": "cus_1", "subscription": "sub_1", "amount_paid": 1999, "currency": "usd" });
+    let (signature, body) = event("evt_4", "invoice.paid", 1_700_000_300, invoice);
+    let paid = payments.handle_webhook(&signature, &body).await.unwrap().unwrap();
+    assert_eq!(paid.user_id.as_deref(), Some("ada"));
+    let PaymentEventKind::InvoicePaid(invoice) = paid.kind else { panic!() };
+    assert_eq!((invoice.amount, invoice.subscription_id.as_deref()), (1999, Some("sub_1")));
+
+    let (signature, body) = event("evt_5", "customer.created", 1_700_000_400, json!({ "id": "cus_2" }));
+    assert!(payments.handle_webhook(&signature, &body).await.unwrap().is_none());
+    let forged = sign("whsec_other", chrono::Utc::now().timestamp(), &body);
+    assert!(matches!(payments.handle_webhook(&forged, &body).await, Err(PaymentError::InvalidSignature)));
+    assert!(api.requests.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_plate_routes() {
+    let api = StripeApi::answering(200, json!({ "id": "cs_1", "url": "https://checkout.stripe.com/c/cs_1" }));
+    let payments = connect(&api);
+    let plate = PaymentsPlate::new(payments.clone(), "https://shop.example/", |_ctx: &RouteContext<'_, TestConfig>| {
+        Some("ada".to_string())
+    })
+    .with_price("price_pro", CheckoutMode::Subscription)
+    .with_prefix("/api/billing");
+    let mut router = Router::new();
+    plate.register_routes(&mut router);
+    let ctx = || RouteContext { config: &TestConfig, env: &TestEnv };
+
+    let billing = router.load("/api/billing", ctx(), json!({})).await.unwrap();
+    assert_eq!(billing, json!({ "customer_id": null, "subscriptions": [] }));
+    let err = router.act("/api/billing", ctx(), json!({}), json!({ "price": "price_internal" })).await.unwrap_err();
+    assert!(matches!(err, RouteError::ValidationFailed(_)));
+    let session = router.act("/api/billing", ctx(), json!({}), json!({ "price": "price_pro" })).await.unwrap();
+    assert_eq!(session["id"], "cs_1");
+    let form = api.form(0);
+    assert!(has(&form, "mode", "subscription"));
+    assert!(has(&form, "success_url", "https://shop.example/billing?checkout=success"));
+    let err = router.load("/api/billing/portal", ctx(), json!({})).await.unwrap_err();
+    assert!(matches!(err, RouteError::ValidationFailed(_)));
+
+    let app = Embedded::new(router, TestConfig, TestEnv);
+    let (signature, body) = event("evt_1", "customer.subscription.created", 1_700_000_000, subscription("trialing"));
+    let request = WasiRequest::new("POST", "/api/billing/webhook").with_header("content-type", "application/json").with_body(body);
+    assert_eq!(app.handle(request.clone()).await.status, 401);
+    let response = app.handle(request.with_header(SIGNATURE_HEADER, signature)).await;
+    assert_eq!(response.status, 200);
+    let stored = payments.store().subscription("sub_1").await.unwrap().unwrap();
+    assert_eq!(stored.status, SubscriptionStatus::Trialing);
+}
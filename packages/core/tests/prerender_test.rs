@@ -0,0 +1,217 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use leptos::prelude::*;
+use montrs_core::{
+    AppConfig, CachedPage, DiskPageStore, Embedded, EnvConfig, FnGuard, GuardOutcome, MemoryPageStore, PageCache, PageStore,
+    Route, RouteAction, RouteContext, RouteError, RouteLoader, RouteParams, RouteView, Router, WasiRequest,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+static LOADS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone)]
+struct TestConfig {
+    pages: Arc<PageCache>,
+}
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct TodoParams {
+    id: i64,
+}
+impl RouteParams for TodoParams {}
+
+struct TodoLoader;
+#[async_trait]
+impl RouteLoader<TodoParams, TestConfig> for TodoLoader {
+    type Output = serde_json::Value;
+    async fn load(&self, _ctx: RouteContext<'_, TestConfig>, params: TodoParams) -> Result<Self::Output, RouteError> {
+        let render = LOADS.fetch_add(1, Ordering::SeqCst);
+        Ok(serde_json::json!({ "id": params.id, "render": render }))
+    }
+}
+
+/// Renames the todo, then drops its cached page.
+struct TodoAction;
+#[async_trait]
+impl RouteAction<TodoParams, TestConfig> for TodoAction {
+    type Input = serde_json::Value;
+    type Output = serde_json::Value;
+    async fn act(&self, ctx: RouteContext<'_, TestConfig>, params: TodoParams, _input: Self::Input) -> Result<Self::Output, RouteError> {
+        let path = format!("/todos/{}", params.id);
+        ctx.config.pages.revalidate(&path).await.map_err(|e| RouteError::InternalError(e.to_string()))?;
+        Ok(serde_json::json!({ "revalidated": path }))
+    }
+}
+
+struct TodoView;
+impl RouteView for TodoView {
+    fn render(&self) -> impl IntoView {
+        view! { <div>"Todo"</div> }
+    }
+}
+
+struct TodoRoute;
+impl Route<TestConfig> for TodoRoute {
+    type Params = TodoParams;
+    type Loader = TodoLoader;
+    type Action = TodoAction;
+    type View = TodoView;
+
+    fn path() -> &'static str {
+        "/todos/:id"
+    }
+    fn loader(&self) -> Self::Loader {
+        TodoLoader
+    }
+    fn action(&self) -> Self::Action {
+        TodoAction
+    }
+    fn view(&self) -> Self::View {
+        TodoView
+    }
+}
+
+fn app(store: Arc<MemoryPageStore>) -> Embedded<TestConfig> {
+    let pages = Arc::new(PageCache::new(store).with_route("/todos/:id", Duration::from_secs(60)));
+    let mut router = Router::new();
+    router.register(TodoRoute);
+    router.set_page_cache(pages.clone());
+    Embedded::new(router, TestConfig { pages }, TestEnv)
+}
+
+fn get(path: &str) -> WasiRequest {
+    WasiRequest::new("GET", path)
+}
+
+#[tokio::test]
+async fn test_pages_are_served_from_the_cache_until_revalidated() {
+    let app = app(Arc::new(MemoryPageStore::new()));
+
+    let first = app.handle(get("/todos/1")).await;
+    assert_eq!(first.status, 200);
+    assert_eq!(first.header("x-montrs-cache"), Some("MISS"));
+    let cached = app.handle(get("/todos/1")).await;
+    assert_eq!(cached.header("x-montrs-cache"), Some("HIT"));
+    assert_eq!(cached.header("age"), Some("0"));
+    assert_eq!(cached.body, first.body);
+
+    let head = app.handle(WasiRequest::new("HEAD", "/todos/1")).await;
+    assert_eq!(head.header("x-montrs-cache"), Some("HIT"));
+    assert!(head.body.is_empty());
+    let query = app.handle(get("/todos/1?expand=tags")).await;
+    assert_eq!(query.header("x-montrs-cache"), Some("BYPASS"));
+    assert_ne!(query.body, first.body);
+
+    let acted = app.handle(WasiRequest::new("POST", "/todos/1").with_body("{}")).await;
+    assert_eq!(acted.status, 200);
+    assert_eq!(acted.header("x-montrs-cache"), None);
+    let fresh = app.handle(get("/todos/1")).await;
+    assert_eq!(fresh.header("x-montrs-cache"), Some("MISS"));
+    assert_ne!(fresh.body, first.body);
+
+    // Errors are not cached.
+    assert_eq!(app.handle(get("/todos/abc")).await.header("x-montrs-cache"), Some("MISS"));
+    assert_eq!(app.handle(get("/todos/abc")).await.header("x-montrs-cache"), Some("MISS"));
+}
+
+#[tokio::test]
+async fn test_stale_pages_are_served_then_rendered_again() {
+    let store = Arc::new(MemoryPageStore::new());
+    let app = app(store.clone());
+    let old = CachedPage { body: b"{\"old\":true}".to_vec(), rendered_at: Utc::now() - chrono::Duration::hours(1) };
+    store.put("/todos/2", &old).await.unwrap();
+
+    let stale = app.handle(get("/todos/2")).await;
+    assert_eq!(stale.header("x-montrs-cache"), Some("STALE"));
+    assert_eq!(stale.header("age"), Some("3600"));
+    assert_eq!(stale.body, old.body);
+
+    // The new render runs on a background task.
+    for _ in 0..100 {
+        if store.get("/todos/2").await.unwrap() != Some(old.clone()) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    let refreshed = app.handle(get("/todos/2")).await;
+    assert_eq!(refreshed.header("x-montrs-cache"), Some("HIT"));
+    assert_ne!(refreshed.body, old.body);
+}
+
+#[tokio::test]
+async fn test_prerender_fills_the_cache() {
+    let store = Arc::new(MemoryPageStore::new());
+    let app = app(store.clone());
+
+    app.prerender(["/todos/3", "/todos/4"]).await.unwrap();
+    assert!(store.get("/todos/4").await.unwrap().is_some());
+    assert_eq!(app.handle(get("/todos/3")).await.header("x-montrs-cache"), Some("HIT"));
+    assert!(matches!(app.prerender(["/users/1"]).await, Err(RouteError::NotFound)));
+}
+
+#[tokio::test]
+async fn test_guarded_pages_are_never_served_from_the_cache() {
+    let store = Arc::new(MemoryPageStore::new());
+    let pages = Arc::new(PageCache::new(store.clone()).with_route("/todos/:id", Duration::from_secs(60)));
+    let signed_in = FnGuard::new("signed_in", |ctx: &RouteContext<'_, TestConfig>, _: &str| {
+        match ctx.request().and_then(|r| r.header("x-user").map(str::to_string)) {
+            Some(_) => GuardOutcome::Allow,
+            None => GuardOutcome::Deny(401),
+        }
+    });
+    let mut router = Router::new();
+    router.with_guards(vec![Arc::new(signed_in)], |router| {
+        router.register(TodoRoute);
+    });
+    router.set_page_cache(pages.clone());
+    let app = Embedded::new(router, TestConfig { pages }, TestEnv);
+
+    // Even a page already in the store, e.g. from before the guard was added.
+    let page = CachedPage { body: b"{\"secret\":true}".to_vec(), rendered_at: Utc::now() };
+    store.put("/todos/5", &page).await.unwrap();
+    let anonymous = app.handle(get("/todos/5")).await;
+    assert_eq!(anonymous.status, 401);
+    assert_ne!(anonymous.body, page.body);
+
+    let signed_in = app.handle(get("/todos/6").with_header("x-user", "ada")).await;
+    assert_eq!(signed_in.status, 200);
+    assert_eq!(signed_in.header("x-montrs-cache"), Some("BYPASS"));
+    assert_eq!(store.get("/todos/6").await.unwrap(), None);
+    assert_eq!(app.handle(get("/todos/6")).await.status, 401);
+    assert!(matches!(app.prerender(["/todos/6"]).await, Err(RouteError::InternalError(_))));
+}
+
+#[tokio::test]
+async fn test_disk_store_round_trips_pages() {
+    let dir = std::env::temp_dir().join(format!("montrs-pages-{}", std::process::id()));
+    let store = DiskPageStore::new(&dir);
+    assert_eq!(store.get("/todos/1").await.unwrap(), None);
+
+    let page = CachedPage {
+        body: b"{\"title\":\"line\\nbreak\"}\n".to_vec(),
+        rendered_at: chrono::DateTime::from_timestamp_millis(1_700_000_000_123).unwrap(),
+    };
+    store.put("/todos/1", &page).await.unwrap();
+    assert!(dir.join("%2Ftodos%2F1.page").exists());
+    assert_eq!(store.get("/todos/1").await.unwrap(), Some(page));
+
+    store.remove("/todos/1").await.unwrap();
+    store.remove("/todos/1").await.unwrap();
+    assert_eq!(store.get("/todos/1").await.unwrap(), None);
+    std::fs::remove_dir_all(dir).unwrap();
+}
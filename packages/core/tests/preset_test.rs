@@ -0,0 +1,195 @@
+use async_trait::async_trait;
+use leptos::prelude::*;
+use montrs_core::wasi;
+use montrs_core::{
+    AppConfig, EnvConfig, EnvError, Limiter, LimiterBackend, Preset, Route, RouteAction, RouteContext, RouteError,
+    RouteLoader, RouteParams, RouteView, Router, Target, WasiRequest,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, key: &str) -> Result<String, EnvError> {
+        Err(EnvError::MissingKey(key.to_string()))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct NoParams {}
+impl RouteParams for NoParams {}
+
+struct Echo;
+#[async_trait]
+impl RouteLoader<NoParams, TestConfig> for Echo {
+    type Output = String;
+    async fn load(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams) -> Result<Self::Output, RouteError> {
+        Ok("ok".to_string())
+    }
+}
+#[async_trait]
+impl RouteAction<NoParams, TestConfig> for Echo {
+    type Input = String;
+    type Output = String;
+    async fn act(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams, input: String) -> Result<String, RouteError> {
+        Ok(input)
+    }
+}
+impl RouteView for Echo {
+    fn render(&self) -> impl IntoView {
+        view! { <div>"echo"</div> }
+    }
+}
+
+struct EchoRoute;
+impl Route<TestConfig> for EchoRoute {
+    type Params = NoParams;
+    type Loader = Echo;
+    type Action = Echo;
+    type View = Echo;
+    fn path() -> &'static str {
+        "/echo"
+    }
+    fn loader(&self) -> Echo {
+        Echo
+    }
+    fn action(&self) -> Echo {
+        Echo
+    }
+    fn view(&self) -> Echo {
+        Echo
+    }
+}
+
+#[test]
+fn test_targets_start_from_their_own_defaults() {
+    let server = Preset::for_target(Target::Server);
+    let edge = Preset::for_target(Target::Edge);
+    let desktop = Preset::for_target(Target::Desktop);
+
+    assert_eq!(server.limits.limiter, LimiterBackend::Memory);
+    assert!(server.limiter().is_some() && server.assets.serve);
+    assert_eq!(edge.limits.limiter, LimiterBackend::Platform);
+    assert!(edge.limiter().is_none() && !edge.assets.serve);
+    assert_eq!(desktop.limits.limiter, LimiterBackend::Off);
+    assert!(desktop.cors.allows("tauri://localhost") && !server.cors.allows("tauri://localhost"));
+    assert!(server.security.strict_transport_security.is_some() && desktop.security.strict_transport_security.is_none());
+    assert_eq!(Preset::for_target(Target::Wasm), server);
+
+    assert_eq!("mobile-ios".parse::<Target>().unwrap(), Target::MobileIos);
+    assert!("mainframe".parse::<Target>().is_err());
+}
+
+#[test]
+fn test_overrides_apply_shared_fields_then_the_targets_table() {
+    let overrides = json!({
+        "target": "edge",
+        "cors": { "allowed_origins": ["https://app.example.com"] },
+        "limits": { "max_body_bytes": 2048 },
+        "edge": { "limits": { "max_body_bytes": 512 }, "security": { "frame_options": null } },
+    });
+
+    let edge = Preset::resolve(Target::Edge, &overrides).unwrap();
+    assert_eq!(edge.cors.allowed_origins, ["https://app.example.com"]);
+    assert_eq!(edge.limits.max_body_bytes, 512);
+    assert_eq!(edge.limits.limiter, LimiterBackend::Platform, "fields left out keep the target's default");
+    assert_eq!(edge.security.frame_options, None);
+
+    let server = Preset::resolve(Target::Server, &overrides).unwrap();
+    assert_eq!(server.limits.max_body_bytes, 2048);
+    assert_eq!(server.security.frame_options.as_deref(), Some("DENY"));
+
+    assert!(Preset::resolve(Target::Server, &json!({ "cors": { "origins": ["*"] } })).is_err());
+    assert!(Preset::resolve(Target::Server, &json!({ "limits": { "limiter": "redis" } })).is_err());
+}
+
+#[test]
+fn test_cors_headers_follow_the_allowed_origins() {
+    let mut cors = Preset::for_target(Target::Server).cors;
+    assert!(cors.headers(Some("https://evil.example")).is_empty());
+    assert!(cors.headers(None).is_empty());
+
+    cors.allowed_origins = vec!["*".to_string()];
+    assert_eq!(cors.headers(Some("https://a.example")), [("access-control-allow-origin".to_string(), "*".to_string())]);
+
+    cors.allow_credentials = true;
+    let headers = cors.headers(Some("https://a.example"));
+    assert!(headers.contains(&("access-control-allow-origin".to_string(), "https://a.example".to_string())));
+    assert!(headers.contains(&("access-control-allow-credentials".to_string(), "true".to_string())));
+}
+
+#[tokio::test]
+async fn test_requests_are_answered_with_the_routers_preset() {
+    let mut router = Router::<TestConfig>::new();
+    router.register(EchoRoute);
+    let mut preset = Preset::resolve(
+        Target::Server,
+        &json!({ "cors": { "allowed_origins": ["https://app.example.com"] }, "limits": { "max_body_bytes": 16 } }),
+    )
+    .unwrap();
+    preset.limits.requests_per_second = 1;
+    router.set_preset(preset);
+    let respond = |request| wasi::respond(&router, &TestConfig, &TestEnv, request);
+
+    let response = respond(
+        WasiRequest::new("OPTIONS", "/echo")
+            .with_header("origin", "https://app.example.com")
+            .with_header("access-control-request-method", "POST"),
+    )
+    .await;
+    assert_eq!(response.status, 204);
+    assert_eq!(response.header("access-control-allow-origin"), Some("https://app.example.com"));
+    assert!(response.header("access-control-allow-methods").unwrap().contains("POST"));
+
+    let response = respond(WasiRequest::new("GET", "/echo").with_header("origin", "https://app.example.com")).await;
+    assert_eq!(response.status, 200);
+    assert_eq!(response.header("x-frame-options"), Some("DENY"));
+    assert_eq!(response.header("x-content-type-options"), Some("nosniff"));
+    assert_eq!(response.header("vary"), Some("Origin"));
+
+    let response = respond(WasiRequest::new("POST", "/echo").with_body(r#""more than sixteen bytes""#)).await;
+    assert_eq!(response.status, 413);
+    assert!(response.header("content-security-policy").is_some());
+
+    // Preflights and refused bodies are not counted; the second request in a second is.
+    assert_eq!(respond(WasiRequest::new("GET", "/echo")).await.status, 429);
+}
+
+/// Lets through as many requests as it was given.
+struct Budget(AtomicU32);
+impl Limiter for Budget {
+    fn check(&self) -> bool {
+        self.0.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)).is_ok()
+    }
+}
+
+#[tokio::test]
+async fn test_a_configured_limiter_replaces_the_presets() {
+    let mut router = Router::<TestConfig>::new();
+    router.register(EchoRoute);
+    router.set_limiter(Budget(2.into()));
+    let respond = |request| wasi::respond(&router, &TestConfig, &TestEnv, request);
+    assert_eq!(respond(WasiRequest::new("GET", "/echo")).await.status, 200);
+    assert_eq!(respond(WasiRequest::new("GET", "/echo")).await.status, 200);
+    assert_eq!(respond(WasiRequest::new("GET", "/echo")).await.status, 429, "limited without a preset");
+
+    let mut router = Router::<TestConfig>::new();
+    router.register(EchoRoute);
+    router.set_limiter(Budget(2.into()));
+    let mut preset = Preset::resolve(Target::Server, &json!({})).unwrap();
+    preset.limits.requests_per_second = 1;
+    router.set_preset(preset);
+    let respond = |request| wasi::respond(&router, &TestConfig, &TestEnv, request);
+    assert_eq!(respond(WasiRequest::new("GET", "/echo")).await.status, 200);
+    assert_eq!(respond(WasiRequest::new("GET", "/echo")).await.status, 200, "the preset's limit no longer applies");
+    assert_eq!(respond(WasiRequest::new("GET", "/echo")).await.status, 429);
+}
@@ -0,0 +1,68 @@
+use montrs_core::Profiler;
+use montrs_core::profile::{DbTimer, FOLDED_FILE, SUMMARY_FILE, record_db_time};
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_profiler_aggregates_calls() {
+    let profiler = Profiler::new();
+
+    for _ in 0..3 {
+        let value = profiler
+            .measure("/users/:id", "load", async {
+                tokio::task::yield_now().await;
+                {
+                    let _timer = DbTimer::start();
+                    tokio::time::sleep(Duration::from_millis(2)).await;
+                }
+                tokio::task::yield_now().await;
+                42
+            })
+            .await;
+        assert_eq!(value, 42);
+    }
+    profiler.measure("/health", "load", async {}).await;
+
+    // Outside a measured handler, database time is not attributed anywhere.
+    record_db_time(Duration::from_secs(5));
+
+    let report = profiler.report();
+    assert_eq!(report.len(), 2);
+    let users = &report[0];
+    assert_eq!(users.route, "/users/:id");
+    assert_eq!(users.calls, 3);
+    assert!(users.await_points >= 9);
+    assert!(users.db_us >= 6_000);
+    assert!(users.db_us < 5_000_000);
+    assert!(users.max_wall_us >= users.avg_wall_us());
+    assert_eq!(report[1].await_points, 0);
+
+    assert_eq!(profiler.slowest(1)[0].route, "/users/:id");
+
+    let folded = profiler.folded();
+    assert!(folded.lines().any(|l| l.starts_with("/users/:id;load;db ")));
+    let total: u64 = folded
+        .lines()
+        .filter(|l| l.starts_with("/users/:id;"))
+        .map(|l| l.rsplit(' ').next().unwrap().parse::<u64>().unwrap())
+        .sum();
+    assert_eq!(total, users.wall_us);
+}
+
+#[tokio::test]
+async fn test_profiler_writes_session_files() {
+    let dir = std::env::temp_dir().join(format!("montrs-profile-{}", std::process::id()));
+    {
+        let profiler = Profiler::new().with_output(&dir);
+        profiler.measure("/posts", "act", async {}).await;
+        profiler.measure("/posts", "act", async {}).await;
+    }
+
+    let summary: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.join(SUMMARY_FILE)).unwrap()).unwrap();
+    assert_eq!(summary[0]["route"], "/posts");
+    assert_eq!(summary[0]["operation"], "act");
+    assert_eq!(summary[0]["calls"], 2);
+    assert!(dir.join(FOLDED_FILE).exists());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,98 @@
+use futures::stream;
+use montrs_core::response::{csv, csv_with_columns, ndjson};
+use montrs_core::{ByteRange, ContentDisposition, FileDownload, ResponseError};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Row {
+    id: u32,
+    name: String,
+}
+
+#[derive(Serialize)]
+struct Invoice {
+    number: u32,
+    customer: String,
+    amount: Option<f64>,
+}
+
+#[test]
+fn test_range_parsing() {
+    assert_eq!(ByteRange::parse("bytes=0-9", 100).unwrap(), Some(ByteRange { start: 0, end: 9 }));
+    assert_eq!(ByteRange::parse("bytes=90-", 100).unwrap(), Some(ByteRange { start: 90, end: 99 }));
+    assert_eq!(ByteRange::parse("bytes=-5", 100).unwrap(), Some(ByteRange { start: 95, end: 99 }));
+    assert_eq!(ByteRange::parse("bytes=0-1,5-6", 100).unwrap(), None);
+    assert!(matches!(ByteRange::parse("bytes=200-", 100), Err(ResponseError::RangeNotSatisfiable(100))));
+    assert!(matches!(ByteRange::parse("items=0-1", 100), Err(ResponseError::InvalidRange(_))));
+}
+
+#[test]
+fn test_content_disposition() {
+    assert_eq!(
+        ContentDisposition::attachment("report.csv").to_header_value(),
+        "attachment; filename=\"report.csv\""
+    );
+    assert_eq!(
+        ContentDisposition::attachment("résumé.pdf").to_header_value(),
+        "attachment; filename=\"r_sum_.pdf\"; filename*=UTF-8''r%C3%A9sum%C3%A9.pdf"
+    );
+}
+
+#[tokio::test]
+async fn test_file_download_with_range() {
+    let dir = std::env::temp_dir().join(format!("montrs-response-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("data.bin");
+    std::fs::write(&path, b"0123456789").unwrap();
+
+    let res = FileDownload::new(&path)
+        .with_filename("data.bin")
+        .with_range(Some("bytes=2-5"))
+        .with_chunk_size(3)
+        .into_response()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status, 206);
+    assert_eq!(res.header("content-range"), Some("bytes 2-5/10"));
+    assert_eq!(res.header("content-type"), Some("application/octet-stream"));
+    assert_eq!(res.collect_body().await.unwrap(), b"2345");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_streaming_serializers() {
+    let rows = || {
+        stream::iter(vec![
+            Row { id: 1, name: "Ada".to_string() },
+            Row { id: 2, name: "Lovelace, A.".to_string() },
+        ])
+    };
+
+    let body = ndjson(rows()).collect_body().await.unwrap();
+    assert_eq!(
+        String::from_utf8(body).unwrap(),
+        "{\"id\":1,\"name\":\"Ada\"}\n{\"id\":2,\"name\":\"Lovelace, A.\"}\n"
+    );
+
+    let res = csv_with_columns(rows(), vec!["name".to_string(), "id".to_string()]);
+    assert_eq!(res.header("Content-Type"), Some("text/csv; charset=utf-8"));
+    let body = res.collect_body().await.unwrap();
+    assert_eq!(
+        String::from_utf8(body).unwrap(),
+        "name,id\r\nAda,1\r\n\"Lovelace, A.\",2\r\n"
+    );
+}
+
+#[tokio::test]
+async fn test_csv_header_follows_field_order() {
+    let invoices = stream::iter(vec![
+        Invoice { number: 7, customer: "Ada".to_string(), amount: Some(12.5) },
+        Invoice { number: 8, customer: "Grace".to_string(), amount: None },
+    ]);
+    let body = csv(invoices).collect_body().await.unwrap();
+    let body = String::from_utf8(body).unwrap();
+    assert_eq!(body.lines().next(), Some("number,customer,amount"));
+    assert_eq!(body, "number,customer,amount\r\n7,Ada,12.5\r\n8,Grace,\r\n");
+}
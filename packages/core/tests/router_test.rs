@@ -1,6 +1,6 @@
 use montrs_core::{
-    AppConfig, EnvConfig, Route, RouteAction, RouteContext, RouteError, RouteLoader, RouteParams,
-    RouteView, Router,
+    AppConfig, Deprecation, EnvConfig, Plate, PlateContext, PlateMetaExt, Route, RouteAction, RouteContext,
+    RouteError, RouteLoader, RouteParams, RouteView, Router, meta,
 };
 use async_trait::async_trait;
 use leptos::prelude::*;
@@ -98,10 +98,109 @@ async fn test_router_registration_and_handling() {
     let params = serde_json::json!({ "id": 123 });
     
     // Test load
-    let load_res = router.spec().routes.get("/users/:id").unwrap();
+    let spec = router.spec();
+    let load_res = spec.routes.get("/users/:id").unwrap();
     assert_eq!(load_res.path, "/users/:id");
     
-    // In a real scenario, we'd call handle_load on the RouteInfo, but it's internal.
-    // However, we can verify the spec is correct.
     assert_eq!(router.spec().routes.len(), 1);
+    assert_eq!(router.load("/users/:id", ctx, params).await.unwrap(), "User 123");
+}
+
+#[test]
+fn test_route_meta_in_spec_and_openapi() {
+    let mut router = Router::<TestConfig>::new();
+    router
+        .register(UserRoute)
+        .with_meta(meta::OWNER, "identity-team")
+        .with_meta(meta::STABILITY, "beta");
+
+    let spec = router.spec();
+    let route = &spec.routes["/users/:id"];
+    assert_eq!(route.meta[meta::OWNER], "identity-team");
+    assert_eq!(route.meta[meta::STABILITY], "beta");
+
+    let doc = spec.to_openapi("test", "1.0.0");
+    let get = &doc["paths"]["/users/{id}"]["get"];
+    assert_eq!(get["tags"][0], "identity-team");
+    assert_eq!(get["x-montrs-meta"]["stability"], "beta");
+    assert_eq!(get["parameters"][0]["name"], "id");
+
+    // Registering again starts from a clean slate.
+    router.register(UserRoute);
+    assert!(router.spec().routes["/users/:id"].meta.is_empty());
+}
+
+struct UsersPlate;
+#[async_trait]
+impl Plate<TestConfig> for UsersPlate {
+    fn name(&self) -> &'static str {
+        "users"
+    }
+    fn metadata(&self) -> std::collections::HashMap<String, String> {
+        [(meta::STABILITY.to_string(), "stable".to_string())].into()
+    }
+    async fn init(
+        &self,
+        _ctx: &mut PlateContext<TestConfig>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_plate_meta_merges_over_plate_metadata() {
+    let plate = UsersPlate
+        .with_meta(meta::OWNER, "identity-team")
+        .with_meta(meta::STABILITY, "experimental");
+
+    assert_eq!(plate.name(), "users");
+    let metadata = plate.metadata();
+    assert_eq!(metadata[meta::OWNER], "identity-team");
+    assert_eq!(metadata[meta::STABILITY], "experimental");
+}
+
+#[tokio::test]
+async fn test_deprecated_route_headers_and_hits() {
+    let mut router = Router::<TestConfig>::new();
+    router
+        .register(UserRoute)
+        .deprecated(Deprecation::new().sunset("2030-01-31").replacement("/v2/users/:id"));
+
+    let headers = router.response_headers("/users/:id");
+    assert!(headers.contains(&("Deprecation".to_string(), "true".to_string())));
+    assert!(headers.contains(&("Sunset".to_string(), "Thu, 31 Jan 2030 00:00:00 GMT".to_string())));
+
+    let (config, env) = (TestConfig, TestEnv);
+    for _ in 0..2 {
+        let ctx = RouteContext { config: &config, env: &env };
+        let data = router.load("/users/:id", ctx, serde_json::json!({ "id": 7 })).await.unwrap();
+        assert_eq!(data, "User 7");
+    }
+
+    let report = router.deprecation_report();
+    assert_eq!(report.len(), 1);
+    assert_eq!(report[0].hits, 2);
+    assert_eq!(router.spec().routes["/users/:id"].meta[meta::STABILITY], meta::DEPRECATED);
+}
+
+#[tokio::test]
+async fn test_router_dispatches_request_paths() {
+    let mut router = Router::<TestConfig>::new();
+    router.register(UserRoute);
+
+    let config = TestConfig;
+    let env = TestEnv;
+    let ctx = || RouteContext { config: &config, env: &env };
+
+    let matched = router.resolve("/users/42?tab=posts").unwrap();
+    assert_eq!(matched.pattern, "/users/:id");
+    assert_eq!(matched.param("id"), Some("42"));
+
+    let data = router.load("/users/42", ctx(), serde_json::Value::Null).await.unwrap();
+    assert_eq!(data, serde_json::json!("User 42"));
+    let data = router.act("/users/9", ctx(), serde_json::json!({}), serde_json::json!("a name")).await.unwrap();
+    assert_eq!(data, serde_json::json!("Updated user 9 with a name"));
+
+    assert!(router.resolve("/users").is_none());
+    assert!(matches!(router.load("/posts/1", ctx(), serde_json::Value::Null).await, Err(RouteError::NotFound)));
 }
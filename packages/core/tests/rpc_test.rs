@@ -0,0 +1,130 @@
+use async_trait::async_trait;
+use montrs_core::{
+    ActionError, AppConfig, Embedded, EnvConfig, FieldError, FnGuard, GuardOutcome, Route, RouteAction, RouteContext,
+    RouteError, RouteLoader, RouteParams, RouteView, Router, RpcClient,
+};
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Whether the caller is signed in.
+#[derive(Clone)]
+struct TestConfig {
+    signed_in: bool,
+}
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct UserParams {
+    id: u32,
+    #[serde(default)]
+    notify: Option<String>,
+}
+impl RouteParams for UserParams {}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Rename {
+    name: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Renamed {
+    id: u32,
+    name: String,
+    notify: Option<String>,
+}
+
+struct RenameUser;
+#[async_trait]
+impl RouteLoader<UserParams, TestConfig> for RenameUser {
+    type Output = ();
+    async fn load(&self, _ctx: RouteContext<'_, TestConfig>, _params: UserParams) -> Result<(), RouteError> {
+        Ok(())
+    }
+}
+#[async_trait]
+impl RouteAction<UserParams, TestConfig> for RenameUser {
+    type Input = Rename;
+    type Output = Renamed;
+    async fn act(&self, _ctx: RouteContext<'_, TestConfig>, params: UserParams, input: Rename) -> Result<Renamed, RouteError> {
+        if input.name.is_empty() {
+            return Err(ActionError::new("NAME_REQUIRED", "A name is required.")
+                .with_field(FieldError::new("name", "NAME_REQUIRED", "Required."))
+                .into());
+        }
+        Ok(Renamed { id: params.id, name: input.name, notify: params.notify })
+    }
+}
+impl RouteView for RenameUser {
+    fn render(&self) -> impl IntoView {}
+}
+impl Route<TestConfig> for RenameUser {
+    type Params = UserParams;
+    type Loader = Self;
+    type Action = Self;
+    type View = Self;
+    fn path() -> &'static str {
+        "/users/:id"
+    }
+    fn loader(&self) -> Self {
+        RenameUser
+    }
+    fn action(&self) -> Self {
+        RenameUser
+    }
+    fn view(&self) -> Self {
+        RenameUser
+    }
+}
+
+fn client(signed_in: bool) -> RpcClient<TestConfig> {
+    let mut router = Router::new();
+    router.register(RenameUser).guard(FnGuard::new("signed_in", |ctx: &RouteContext<'_, TestConfig>, _: &str| {
+        match ctx.config.signed_in {
+            true => GuardOutcome::Allow,
+            false => GuardOutcome::Redirect("/login".to_string()),
+        }
+    }));
+    RpcClient::new(Embedded::new(router, TestConfig { signed_in }, TestEnv))
+}
+
+#[tokio::test]
+async fn test_actions_are_called_as_typed_async_fns() {
+    let client = client(true);
+    let params = UserParams { id: 7, notify: Some("email me".to_string()) };
+    let renamed = client.act::<RenameUser>(&params, &Rename { name: "Ada".to_string() }).await.unwrap();
+    assert_eq!(renamed, Renamed { id: 7, name: "Ada".to_string(), notify: Some("email me".to_string()) });
+
+    let by_path: Renamed = client.act_path("/users/:id", serde_json::json!({ "id": 8 }), Rename { name: "Grace".to_string() }).await.unwrap();
+    assert_eq!((by_path.id, by_path.notify), (8, None));
+
+    let missing = client.act_path::<Renamed>("/users/:id", serde_json::json!({}), Rename { name: "Ada".to_string() }).await;
+    assert!(matches!(missing, Err(RouteError::ValidationFailed(message)) if message == "missing route param `id`"));
+}
+
+#[tokio::test]
+async fn test_server_errors_come_back_typed() {
+    let params = UserParams { id: 7, notify: None };
+    match client(true).act::<RenameUser>(&params, &Rename { name: String::new() }).await {
+        Err(RouteError::Action(error)) => {
+            assert_eq!((error.code.as_str(), error.status), ("NAME_REQUIRED", 422));
+            assert_eq!(error.field_errors("name").count(), 1);
+        }
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+
+    let redirected = client(false).act::<RenameUser>(&params, &Rename { name: "Ada".to_string() }).await;
+    assert!(matches!(redirected, Err(RouteError::Redirect(location)) if location == "/login"));
+    let unknown = client(true).act_path::<Renamed>("/teams", serde_json::json!({}), ()).await;
+    assert!(matches!(unknown, Err(RouteError::NotFound)));
+}
@@ -0,0 +1,140 @@
+use async_trait::async_trait;
+use leptos::prelude::*;
+use montrs_core::{
+    AppConfig, EnvConfig, Route, RouteAction, RouteContext, RouteError, RouteLoader, RouteParams,
+    RouteView, Router, Sanitize, SanitizeText, Validate, ValidationError, sanitize_and_validate,
+    strip_html,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct NoParams {}
+impl RouteParams for NoParams {}
+
+/// What `#[derive(Schema)]` generates for `#[schema(trim, lowercase, email)]`.
+#[derive(Serialize, Deserialize)]
+struct Signup {
+    email: String,
+}
+
+impl Sanitize for Signup {
+    fn sanitize(&mut self) {
+        self.email.map_text(|s| s.trim().to_string());
+        self.email.map_text(|s| s.to_lowercase());
+    }
+}
+
+impl Validate for Signup {
+    fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        if self.email.contains('@') {
+            Ok(())
+        } else {
+            Err(vec![ValidationError::InvalidEmail { field: "email" }])
+        }
+    }
+}
+
+struct SignupLoader;
+#[async_trait]
+impl RouteLoader<NoParams, TestConfig> for SignupLoader {
+    type Output = ();
+    async fn load(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams) -> Result<(), RouteError> {
+        Ok(())
+    }
+}
+
+struct SignupAction;
+#[async_trait]
+impl RouteAction<NoParams, TestConfig> for SignupAction {
+    type Input = Signup;
+    type Output = String;
+    async fn act(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams, input: Signup) -> Result<String, RouteError> {
+        Ok(input.email)
+    }
+
+    fn prepare_input(&self, input: Signup) -> Result<Signup, RouteError> {
+        sanitize_and_validate(input)
+    }
+}
+
+struct SignupView;
+impl RouteView for SignupView {
+    fn render(&self) -> impl IntoView {
+        view! { <form></form> }
+    }
+}
+
+struct SignupRoute;
+impl Route<TestConfig> for SignupRoute {
+    type Params = NoParams;
+    type Loader = SignupLoader;
+    type Action = SignupAction;
+    type View = SignupView;
+
+    fn path() -> &'static str {
+        "/signup"
+    }
+    fn loader(&self) -> Self::Loader {
+        SignupLoader
+    }
+    fn action(&self) -> Self::Action {
+        SignupAction
+    }
+    fn view(&self) -> Self::View {
+        SignupView
+    }
+}
+
+#[test]
+fn test_strip_html() {
+    assert_eq!(strip_html("<p>Hello <b>world</b></p>"), "Hello world");
+    assert_eq!(strip_html("a<script>alert('<b>')</script>b<STYLE>p{}</style>c"), "abc");
+    assert_eq!(strip_html("1 < 2 <!-- hidden -->and 3 > 2"), "1 < 2 and 3 > 2");
+    assert_eq!(strip_html("&lt;b&gt; stays <i"), "&lt;b&gt; stays ");
+    assert_eq!(strip_html("naïve <br/>café"), "naïve café");
+}
+
+#[test]
+fn test_map_text_reaches_options_and_lists() {
+    let mut maybe = Some(" x ".to_string());
+    maybe.map_text(|s| s.trim().to_string());
+    assert_eq!(maybe.as_deref(), Some("x"));
+
+    let mut tags = vec!["A".to_string(), "B".to_string()];
+    tags.map_text(|s| s.to_lowercase());
+    assert_eq!(tags, ["a", "b"]);
+}
+
+#[tokio::test]
+async fn test_action_input_is_sanitized_before_act() {
+    let mut router = Router::<TestConfig>::new();
+    router.register(SignupRoute);
+    let (config, env) = (TestConfig, TestEnv);
+
+    let ctx = RouteContext { config: &config, env: &env };
+    let input = serde_json::json!({ "email": "  Ada@Example.COM " });
+    let res = router.act("/signup", ctx, serde_json::json!({}), input).await.unwrap();
+    assert_eq!(res, serde_json::json!("ada@example.com"));
+
+    let ctx = RouteContext { config: &config, env: &env };
+    let body = br#"{"email": "   "}"#;
+    let err = router.act_body("/signup", ctx, serde_json::json!({}), "application/json", body).await.unwrap_err();
+    let RouteError::Action(err) = err else { panic!("expected an action error, got {:?}", err) };
+    assert_eq!((err.code.as_str(), err.message.as_str()), ("VALIDATION_FAILED", "email must be a valid email"));
+    assert_eq!((err.fields[0].field.as_str(), err.fields[0].code.as_str()), ("email", "VAL_INVALID_EMAIL"));
+}
@@ -0,0 +1,75 @@
+#![cfg(feature = "secrets")]
+
+use montrs_core::{EnvChain, EnvConfig, SecretKey, SecretsEnv, SecretsError, SecretsFile};
+use std::collections::HashMap;
+
+fn file_for(key: &SecretKey) -> SecretsFile {
+    let mut file = SecretsFile::default();
+    file.add_recipient(&key.public_key()).unwrap();
+    file
+}
+
+#[test]
+fn test_set_get_roundtrip() {
+    let key = SecretKey::generate();
+    let mut file = file_for(&key);
+    file.set("DATABASE_URL", "postgres://secret").unwrap();
+
+    assert_eq!(file.keys().collect::<Vec<_>>(), vec!["DATABASE_URL"]);
+    assert_eq!(file.get("DATABASE_URL", &key).unwrap().as_deref(), Some("postgres://secret"));
+    assert_eq!(file.get("MISSING", &key).unwrap(), None);
+}
+
+#[test]
+fn test_saved_file_does_not_contain_plaintext() {
+    let key = SecretKey::generate();
+    let mut file = file_for(&key);
+    file.set("API_TOKEN", "plaintext-value").unwrap();
+
+    let path = std::env::temp_dir().join(format!("montrs-secrets-{}.toml", std::process::id()));
+    file.save(&path).unwrap();
+    let raw = std::fs::read_to_string(&path).unwrap();
+    let reloaded = SecretsFile::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(!raw.contains("plaintext-value"));
+    assert_eq!(reloaded.get("API_TOKEN", &key).unwrap().as_deref(), Some("plaintext-value"));
+}
+
+#[test]
+fn test_other_keys_cannot_decrypt_until_rekeyed() {
+    let owner = SecretKey::generate();
+    let teammate = SecretKey::generate();
+    let mut file = file_for(&owner);
+    file.set("TOKEN", "abc").unwrap();
+
+    assert!(matches!(file.get("TOKEN", &teammate), Err(SecretsError::Decrypt { .. })));
+
+    file.add_recipient(&teammate.public_key()).unwrap();
+    file.rekey(&owner).unwrap();
+    assert_eq!(file.get("TOKEN", &teammate).unwrap().as_deref(), Some("abc"));
+}
+
+#[test]
+fn test_set_without_recipients_fails() {
+    let mut file = SecretsFile::default();
+    assert!(matches!(file.set("TOKEN", "abc"), Err(SecretsError::NoRecipients)));
+}
+
+#[test]
+fn test_secrets_env_in_chain() {
+    let key = SecretKey::generate();
+    let mut file = file_for(&key);
+    file.set("SHARED", "from-secrets").unwrap();
+    file.set("ONLY_SECRET", "hidden").unwrap();
+
+    let overrides = SecretsEnv::new(HashMap::from([("SHARED".to_string(), "override".to_string())]));
+    let env = EnvChain::new()
+        .with(overrides)
+        .with(SecretsEnv::decrypt(&file, &key).unwrap());
+
+    assert_eq!(env.get_var("SHARED").unwrap(), "override");
+    assert_eq!(env.get_var("ONLY_SECRET").unwrap(), "hidden");
+    assert!(env.get_var("NOPE").is_err());
+    assert!(env.vars().contains_key("ONLY_SECRET"));
+}
@@ -0,0 +1,101 @@
+use montrs_core::{ServerEffect, ServerMemo, ServerSignal};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+#[test]
+fn test_memos_and_effects_follow_their_signals() {
+    let limit = ServerSignal::new(10);
+    let enabled = ServerSignal::new(true);
+    let effective = ServerMemo::new({
+        let (limit, enabled) = (limit.clone(), enabled.clone());
+        move || if enabled.get() { limit.get() } else { 0 }
+    });
+
+    let runs = Arc::new(AtomicUsize::new(0));
+    let seen = ServerSignal::new(Vec::new());
+    let effect = ServerEffect::new({
+        let (effective, seen, runs) = (effective.clone(), seen.clone(), runs.clone());
+        move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            let value = effective.get();
+            seen.update(|s| s.push(value));
+        }
+    });
+
+    limit.set(20);
+    enabled.set(false);
+    // Disabled: the memo no longer reads `limit`, and its result is unchanged.
+    limit.set(30);
+    enabled.set(false);
+    assert_eq!(effective.get(), 0);
+    assert_eq!(seen.get(), [10, 20, 0]);
+    assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+    drop(effect);
+    enabled.set(true);
+    assert_eq!(effective.get(), 30);
+    assert_eq!(runs.load(Ordering::SeqCst), 3, "a dropped effect stops running");
+}
+
+#[test]
+fn test_effect_may_write_the_signal_it_reads() {
+    let count = ServerSignal::new(0);
+    let _clamp = ServerEffect::new({
+        let count = count.clone();
+        move || {
+            if count.get() > 5 {
+                count.set(5);
+            }
+        }
+    });
+    count.set(9);
+    assert_eq!(count.get(), 5);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_updates_do_not_deadlock() {
+    let signals: Vec<ServerSignal<u64>> = (0..4).map(|_| ServerSignal::new(0)).collect();
+    let total = ServerMemo::new({
+        let signals = signals.clone();
+        move || signals.iter().map(ServerSignal::get).sum::<u64>()
+    });
+    let doubled = ServerMemo::new({
+        let total = total.clone();
+        move || total.get() * 2
+    });
+    let mirror = ServerSignal::new(0);
+    let _effect = ServerEffect::new({
+        let (doubled, mirror) = (doubled.clone(), mirror.clone());
+        move || mirror.set(doubled.get())
+    });
+
+    let writers = signals.iter().cloned().map(|signal| {
+        tokio::spawn(async move {
+            for _ in 0..500 {
+                signal.update(|n| *n += 1);
+                tokio::task::yield_now().await;
+            }
+        })
+    });
+    let readers = (0..4).map(|_| {
+        let (total, mirror) = (total.clone(), mirror.clone());
+        tokio::spawn(async move {
+            for _ in 0..500 {
+                let _ = (total.get(), mirror.get());
+                tokio::task::yield_now().await;
+            }
+        })
+    });
+    let tasks: Vec<_> = writers.chain(readers).collect();
+
+    tokio::time::timeout(Duration::from_secs(30), futures::future::join_all(tasks))
+        .await
+        .expect("concurrent updates deadlocked")
+        .into_iter()
+        .for_each(|task| task.unwrap());
+
+    assert_eq!(total.get(), 2000);
+    assert_eq!(doubled.get(), 4000);
+    assert_eq!(mirror.get(), 4000);
+}
@@ -0,0 +1,96 @@
+use montrs_core::signal_graph::{self, EXCESSIVE_RUNS_PER_SEC, WIDE_FANOUT};
+use montrs_core::{GraphNode, GraphWarning, NodeKind, ServerEffect, ServerMemo, ServerSignal, SignalGraph};
+
+fn node<'a>(graph: &'a SignalGraph, name: &str) -> &'a GraphNode {
+    graph.nodes.iter().find(|n| n.name == name).unwrap_or_else(|| panic!("no node named {}", name))
+}
+
+#[test]
+fn test_reads_writes_and_edges_are_attributed() {
+    let inspector = signal_graph::enable();
+    let (limit, doubled, effect) = signal_graph::with_scope("plate config", || {
+        let limit = ServerSignal::new(1).named("attr.limit");
+        let doubled = ServerMemo::new({
+            let limit = limit.clone();
+            move || limit.get() * 2
+        })
+        .named("attr.doubled");
+        let effect = ServerEffect::new({
+            let doubled = doubled.clone();
+            move || {
+                doubled.get();
+            }
+        })
+        .named("attr.effect");
+        (limit, doubled, effect)
+    });
+    signal_graph::with_scope("route /limit (act)", || limit.set(5));
+
+    let graph = inspector.snapshot();
+    let (limit_node, doubled_node, effect_node) =
+        (node(&graph, "attr.limit"), node(&graph, "attr.doubled"), node(&graph, "attr.effect"));
+    assert_eq!(limit_node.kind, NodeKind::Signal);
+    assert_eq!(limit_node.created_in.as_deref(), Some("plate config"));
+    assert_eq!(limit_node.writers.get("route /limit (act)"), Some(&1));
+    assert_eq!(limit_node.dependents, [doubled_node.id]);
+    assert_eq!(doubled_node.dependents, [effect_node.id]);
+    assert_eq!((doubled_node.runs, doubled_node.writes), (2, 2));
+    assert_eq!(effect_node.runs, 2);
+    // The memo and effect reran inside the write, so their reads share its scope.
+    assert_eq!(limit_node.readers.get("route /limit (act)"), Some(&1));
+
+    let effect_id = effect_node.id;
+    drop(effect);
+    let graph = inspector.snapshot();
+    assert!(graph.nodes.iter().all(|n| n.id != effect_id));
+    assert!(node(&graph, "attr.doubled").dependents.is_empty());
+    drop((limit, doubled));
+}
+
+#[test]
+fn test_hot_spots_are_flagged() {
+    let inspector = signal_graph::enable();
+    let source = ServerSignal::new(0).named("hot.source");
+    let readers: Vec<_> = (0..WIDE_FANOUT)
+        .map(|_| {
+            let source = source.clone();
+            ServerMemo::new(move || source.get())
+        })
+        .collect();
+    let _effect = ServerEffect::new({
+        let source = source.clone();
+        move || {
+            source.get();
+        }
+    })
+    .named("hot.effect");
+    for n in 0..=EXCESSIVE_RUNS_PER_SEC {
+        source.set(n + 1);
+    }
+
+    let graph = inspector.snapshot();
+    let source_id = node(&graph, "hot.source").id;
+    let effect_id = node(&graph, "hot.effect").id;
+    assert!(graph.warnings.contains(&GraphWarning::WideFanout {
+        node: source_id,
+        name: "hot.source".to_string(),
+        dependents: WIDE_FANOUT + 1,
+    }));
+    assert!(graph.warnings.iter().any(|w| matches!(w,
+        GraphWarning::ExcessiveRuns { node, peak_runs_per_sec, .. }
+            if *node == effect_id && *peak_runs_per_sec > EXCESSIVE_RUNS_PER_SEC)));
+    drop(readers);
+}
+
+#[test]
+fn test_graph_round_trips_through_file() {
+    let inspector = signal_graph::enable();
+    let _signal = ServerSignal::new("on").named("file.flag");
+    let path = std::env::temp_dir()
+        .join(format!("montrs-signal-graph-{}", std::process::id()))
+        .join("signal-graph.json");
+    inspector.write_to(&path).unwrap();
+    let read = SignalGraph::read_from(&path).unwrap();
+    assert_eq!(node(&read, "file.flag").kind, NodeKind::Signal);
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
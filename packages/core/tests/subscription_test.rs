@@ -0,0 +1,179 @@
+use async_trait::async_trait;
+use futures::StreamExt;
+use leptos::prelude::*;
+use montrs_core::wasi::WasiRequest;
+use montrs_core::{
+    AppConfig, Embedded, EnvConfig, FnGuard, GuardOutcome, LiveSignal, LiveStatus, Messages, Permission, Principal, Rbac,
+    RouteContext, RouteError, RouteGuard, RouteParams, Router, RpcClient, Subscription,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TodoChange {
+    list: u32,
+    title: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ListParams {
+    list: u32,
+}
+impl RouteParams for ListParams {}
+
+/// Sends the changes to one list as they are published.
+struct TodoFeed {
+    changes: broadcast::Sender<TodoChange>,
+}
+
+#[async_trait]
+impl Subscription<TestConfig> for TodoFeed {
+    type Params = ListParams;
+    type Message = TodoChange;
+
+    fn path() -> &'static str {
+        "/lists/:list/live"
+    }
+
+    async fn subscribe(&self, _ctx: RouteContext<'_, TestConfig>, params: ListParams) -> Result<Messages<TodoChange>, RouteError> {
+        let changes = futures::stream::unfold(self.changes.subscribe(), |mut receiver| async move {
+            let change = receiver.recv().await.ok()?;
+            Some((change, receiver))
+        });
+        Ok(changes.filter(move |change| std::future::ready(change.list == params.list)).boxed())
+    }
+
+    fn description(&self) -> &'static str {
+        "Changes to a todo list"
+    }
+}
+
+fn change(list: u32, title: &str) -> TodoChange {
+    TodoChange { list, title: title.to_string() }
+}
+
+fn app(changes: &broadcast::Sender<TodoChange>, guards: Vec<Arc<dyn RouteGuard<TestConfig>>>) -> Embedded<TestConfig> {
+    let mut router = Router::new();
+    router.with_guards(guards, |router| {
+        router.subscription(TodoFeed { changes: changes.clone() });
+    });
+    Embedded::new(router, TestConfig, TestEnv)
+}
+
+#[tokio::test]
+async fn test_client_receives_the_messages_of_its_subscription() {
+    let (changes, _) = broadcast::channel(16);
+    let client = RpcClient::<TestConfig>::new(app(&changes, Vec::new()));
+
+    let mut messages = client.subscribe::<TodoFeed>(&ListParams { list: 1 }).await.unwrap();
+    changes.send(change(2, "someone else's")).unwrap();
+    changes.send(change(1, "milk")).unwrap();
+    changes.send(change(1, "bread")).unwrap();
+    assert_eq!(messages.next().await.unwrap().unwrap(), change(1, "milk"));
+    assert_eq!(messages.next().await.unwrap().unwrap(), change(1, "bread"));
+
+    // The signal keeps the last message and is closed when its stream ends.
+    let live = LiveSignal::new();
+    assert_eq!(live.status().get_untracked(), LiveStatus::Connecting);
+    changes.send(change(1, "eggs")).unwrap();
+    live.follow(messages.take(1)).await;
+    assert_eq!(live.get(), Some(change(1, "eggs")));
+    assert_eq!(live.status().get_untracked(), LiveStatus::Closed);
+}
+
+#[tokio::test]
+async fn test_subscriptions_are_guarded_and_listed_in_the_spec() {
+    let (changes, _) = broadcast::channel(16);
+    let signed_in = FnGuard::new("signed_in", |ctx: &RouteContext<'_, TestConfig>, _: &str| {
+        match ctx.request().and_then(|r| r.header("x-user").map(str::to_string)) {
+            Some(_) => GuardOutcome::Allow,
+            None => GuardOutcome::Deny(401),
+        }
+    });
+    let app = app(&changes, vec![Arc::new(signed_in)]);
+
+    let anonymous = RpcClient::<TestConfig>::new(app.clone());
+    let refused = anonymous.subscribe::<TodoFeed>(&ListParams { list: 1 }).await.err();
+    assert!(matches!(refused, Some(RouteError::Unauthorized)), "{:?}", refused);
+    let missing = anonymous.subscribe_path::<TodoChange>("/lists/:list/archive", ListParams { list: 1 }).await.err();
+    assert!(matches!(missing, Some(RouteError::NotFound)), "{:?}", missing);
+    let signed_in = RpcClient::<TestConfig>::new(app.clone()).with_header("x-user", "ada");
+    assert!(signed_in.subscribe::<TodoFeed>(&ListParams { list: 1 }).await.is_ok());
+
+    // Only requests for an event stream open the subscription.
+    let events = WasiRequest::new("GET", "/lists/1/live").with_header("accept", "text/event-stream");
+    assert!(app.is_subscription(&events));
+    assert!(!app.is_subscription(&WasiRequest::new("GET", "/lists/1/live")));
+    let response = app.stream(events.with_header("x-user", "ada")).await;
+    assert_eq!(response.status, 200);
+    assert!(response.headers.iter().any(|(name, value)| name == "Content-Type" && value == "text/event-stream"));
+
+    let spec = app.router().spec();
+    assert_eq!(spec.subscriptions.len(), 1);
+    assert_eq!(spec.subscriptions[0].path, "/lists/:list/live");
+    assert_eq!(spec.subscriptions[0].description, "Changes to a todo list");
+    assert_eq!(spec.subscriptions[0].guards, ["signed_in"]);
+}
+
+#[tokio::test]
+async fn test_subscriptions_check_requirements_and_group_middleware() {
+    let (changes, _) = broadcast::channel(16);
+    let mut router = Router::new();
+    router.set_rbac(Rbac::new(|ctx: &RouteContext<'_, TestConfig>| {
+        let request = ctx.request()?;
+        let roles = request.header("x-roles").unwrap_or_default();
+        Some(roles.split(',').filter(|r| !r.is_empty()).fold(Principal::new(request.header("x-user")?), Principal::with_role))
+    }));
+    router.subscription(TodoFeed { changes: changes.clone() }).requires(Permission::role("member"));
+    router.group("/admin", |admin| {
+        admin.use_middleware(FnGuard::new("staff_network", |ctx: &RouteContext<'_, TestConfig>, _: &str| {
+            match ctx.request().and_then(|r| r.header("x-staff").map(str::to_string)) {
+                Some(_) => GuardOutcome::Allow,
+                None => GuardOutcome::Deny(403),
+            }
+        }));
+        admin.requires(Permission::Admin);
+        admin.subscription(TodoFeed { changes: changes.clone() });
+    });
+    let app = Embedded::new(router, TestConfig, TestEnv);
+    let client = |headers: &[(&str, &str)]| {
+        headers.iter().fold(RpcClient::<TestConfig>::new(app.clone()), |client, &(name, value)| client.with_header(name, value))
+    };
+    let list = ListParams { list: 1 };
+
+    let refused = client(&[]).subscribe::<TodoFeed>(&list).await.err();
+    assert!(matches!(refused, Some(RouteError::Unauthorized)), "{:?}", refused);
+    let refused = client(&[("x-user", "ada")]).subscribe::<TodoFeed>(&list).await.err();
+    assert!(matches!(refused, Some(RouteError::Forbidden(_))), "{:?}", refused);
+    assert!(client(&[("x-user", "ada"), ("x-roles", "member")]).subscribe::<TodoFeed>(&list).await.is_ok());
+
+    let admin_feed = "/admin/lists/:list/live";
+    let admin = [("x-user", "grace"), ("x-roles", "admin")];
+    let refused = client(&admin).subscribe_path::<TodoChange>(admin_feed, &list).await.err();
+    assert!(matches!(refused, Some(RouteError::Denied(403))), "the group's middleware runs: {:?}", refused);
+    let refused = client(&[("x-user", "ada"), ("x-roles", "member"), ("x-staff", "1")]).subscribe_path::<TodoChange>(admin_feed, &list).await.err();
+    assert!(matches!(refused, Some(RouteError::Forbidden(_))), "the group's requirements are checked: {:?}", refused);
+    assert!(client(&[admin[0], admin[1], ("x-staff", "1")]).subscribe_path::<TodoChange>(admin_feed, &list).await.is_ok());
+
+    let spec = app.router().spec();
+    let listed = spec.subscriptions.iter().find(|s| s.path == admin_feed).unwrap();
+    assert_eq!(listed.guards, ["staff_network"]);
+    assert_eq!(Permission::from_meta(&listed.meta), [Permission::Admin]);
+    assert_eq!(spec.groups[0].subscriptions, [admin_feed]);
+}
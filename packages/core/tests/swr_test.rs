@@ -0,0 +1,67 @@
+use montrs_core::{MemorySwrStore, SwrCache, SwrStore};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+#[test]
+fn test_values_are_fresh_until_max_age_and_persist_through_the_store() {
+    let store = Arc::new(MemorySwrStore::new());
+    let cache = SwrCache::new().with_max_age(Duration::from_secs(60)).with_store(store.clone());
+    assert!(cache.get::<Vec<String>>("/todos").is_none());
+
+    cache.put("/todos", &vec!["milk".to_string()]);
+    let cached = cache.get::<Vec<String>>("/todos").unwrap();
+    assert_eq!((cached.value, cached.stale), (vec!["milk".to_string()], false));
+    assert!(cache.get::<u32>("/todos").is_none(), "a value of another type is a miss");
+
+    // A new page load starts from the store; with no max age it revalidates.
+    let reloaded = SwrCache::new().with_store(store.clone());
+    let cached = reloaded.get::<Vec<String>>("/todos").unwrap();
+    assert_eq!((cached.value, cached.stale), (vec!["milk".to_string()], true));
+
+    cache.remove("/todos");
+    assert!(store.get("/todos").is_none());
+}
+
+#[test]
+fn test_invalidating_a_route_marks_keys_below_it_and_notifies_subscribers() {
+    let store = Arc::new(MemorySwrStore::new());
+    let cache = SwrCache::new().with_max_age(Duration::from_secs(60)).with_store(store.clone());
+    for key in ["/todos", "/todos?page=2", "/todos/42", "/todosx", "/users"] {
+        cache.put(key, &key.len());
+    }
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let subscription = cache.subscribe("/todos/42", {
+        let calls = calls.clone();
+        move || {
+            calls.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+    let _unrelated = cache.subscribe("/users", || panic!("/users was not invalidated"));
+
+    assert_eq!(cache.invalidate("/todos/"), 3);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    let stale = |key: &str| cache.get::<usize>(key).unwrap().stale;
+    assert!(stale("/todos") && stale("/todos?page=2") && stale("/todos/42"));
+    assert!(!stale("/todosx") && !stale("/users"));
+    assert!(SwrCache::new().with_max_age(Duration::from_secs(60)).with_store(store).get::<usize>("/todos/42").unwrap().stale);
+
+    drop(subscription);
+    cache.invalidate("/todos");
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_mutate_invalidates_only_on_success() {
+    let cache = SwrCache::new().with_max_age(Duration::from_secs(60));
+    cache.put("/todos", &1);
+
+    let failed: Result<(), &str> = cache.mutate("/todos", async { Err("offline") }).await;
+    assert!(failed.is_err());
+    assert!(!cache.get::<i32>("/todos").unwrap().stale);
+
+    let id = cache.mutate("/todos", async { Ok::<_, &str>(7) }).await.unwrap();
+    assert_eq!(id, 7);
+    assert!(cache.get::<i32>("/todos").unwrap().stale);
+}
@@ -0,0 +1,169 @@
+use async_trait::async_trait;
+use leptos::prelude::*;
+use montrs_core::wasi::{WasiRequest, WasiResponse};
+use montrs_core::{
+    AppConfig, Causality, Embedded, EnvConfig, MemorySyncStore, Plate, Resolution, Router, RpcClient, RpcTransport,
+    SyncEngine, SyncPlate, SyncStatus, SyncStore, VersionVector,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Todo {
+    title: String,
+    tags: Vec<String>,
+}
+
+fn todo(title: &str, tags: &[&str]) -> Todo {
+    Todo { title: title.to_string(), tags: tags.iter().map(|t| t.to_string()).collect() }
+}
+
+/// The server app, reachable only while `online` is set.
+#[derive(Clone)]
+struct Network {
+    app: Embedded<TestConfig>,
+    online: Arc<AtomicBool>,
+}
+
+#[async_trait(?Send)]
+impl RpcTransport for Network {
+    async fn send(&self, request: WasiRequest) -> Result<WasiResponse, String> {
+        match self.online.load(Ordering::SeqCst) {
+            true => Ok(self.app.handle(request).await),
+            false => Err("connection refused".to_string()),
+        }
+    }
+}
+
+fn network() -> (Network, Arc<MemorySyncStore>) {
+    let store = Arc::new(MemorySyncStore::new());
+    let mut router = Router::new();
+    SyncPlate::new(store.clone()).with_model("todos").register_routes(&mut router);
+    let app = Embedded::new(router, TestConfig, TestEnv);
+    (Network { app, online: Arc::new(AtomicBool::new(true)) }, store)
+}
+
+fn device(replica: &str, network: &Network) -> SyncEngine<TestConfig> {
+    SyncEngine::new(replica, MemorySyncStore::new(), RpcClient::new(network.clone())).with_model("todos")
+}
+
+#[test]
+fn test_version_vectors_order_writes() {
+    let mut phone = VersionVector::new();
+    phone.bump("phone");
+    let mut laptop = phone.clone();
+    assert_eq!(phone.compare(&laptop), Causality::Equal);
+
+    laptop.bump("laptop");
+    assert_eq!((phone.compare(&laptop), laptop.compare(&phone)), (Causality::Before, Causality::After));
+    phone.bump("phone");
+    assert_eq!(phone.compare(&laptop), Causality::Concurrent);
+
+    phone.merge(&laptop);
+    assert_eq!((phone.get("phone"), phone.get("laptop")), (2, 1));
+    assert_eq!(phone.compare(&laptop), Causality::After);
+}
+
+#[tokio::test]
+async fn test_devices_exchange_writes_and_deletions_through_the_server() {
+    let (network, server) = network();
+    let (phone, laptop) = (device("phone", &network), device("laptop", &network));
+
+    phone.put("todos", "1", &todo("milk", &[])).await.unwrap();
+    phone.put("todos", "2", &todo("bread", &[])).await.unwrap();
+    assert_eq!(phone.pending().get_untracked(), 2);
+    assert!(phone.put("notes", "1", &todo("secret", &[])).await.is_err(), "notes are not synced");
+
+    let report = phone.sync().await.unwrap();
+    assert_eq!((report.pushed, report.conflicts), (2, 0));
+    assert_eq!(phone.pending().get_untracked(), 0);
+    assert_eq!(phone.status().get_untracked(), SyncStatus::Idle);
+    assert!(phone.last_synced().get_untracked().is_some());
+    assert_eq!(server.rows("todos").await.unwrap().len(), 2);
+
+    let report = laptop.sync().await.unwrap();
+    assert_eq!((report.pushed, report.pulled), (0, 2));
+    assert_eq!(laptop.get::<Todo>("todos", "1").await.unwrap(), Some(todo("milk", &[])));
+
+    laptop.delete("todos", "2").await.unwrap();
+    laptop.sync().await.unwrap();
+    phone.sync().await.unwrap();
+    assert_eq!(phone.list::<Todo>("todos").await.unwrap(), [todo("milk", &[])]);
+    assert_eq!(phone.get::<Todo>("todos", "2").await.unwrap(), None);
+    assert_eq!(laptop.sync().await.unwrap(), Default::default(), "nothing left to exchange");
+}
+
+#[tokio::test]
+async fn test_sync_pushes_and_pulls_more_rows_than_one_batch() {
+    let (network, server) = network();
+    let (phone, laptop) = (device("phone", &network), device("laptop", &network));
+    for id in 0..250 {
+        phone.put("todos", &id.to_string(), &todo("task", &[])).await.unwrap();
+    }
+
+    let report = phone.sync().await.unwrap();
+    assert_eq!(report.pushed, 250);
+    assert_eq!(phone.pending().get_untracked(), 0);
+    assert_eq!(server.rows("todos").await.unwrap().len(), 250);
+
+    let report = laptop.sync().await.unwrap();
+    assert_eq!(report.pulled, 250);
+    assert_eq!(laptop.list::<Todo>("todos").await.unwrap().len(), 250);
+}
+
+#[tokio::test]
+async fn test_offline_writes_wait_and_conflicts_go_to_the_resolver() {
+    let (network, _server) = network();
+    let phone = device("phone", &network).with_resolver("todos", |conflict| {
+        let (mut local, remote): (Todo, Todo) = (
+            serde_json::from_value(conflict.local.data.clone()).unwrap(),
+            serde_json::from_value(conflict.remote.data.clone()).unwrap(),
+        );
+        local.tags.extend(remote.tags);
+        local.tags.sort();
+        Resolution::Merged(json!(local))
+    });
+    let laptop = device("laptop", &network);
+    phone.put("todos", "1", &todo("milk", &[])).await.unwrap();
+    phone.sync().await.unwrap();
+    laptop.sync().await.unwrap();
+
+    network.online.store(false, Ordering::SeqCst);
+    phone.put("todos", "1", &todo("milk", &["dairy"])).await.unwrap();
+    assert!(phone.sync().await.is_err());
+    assert_eq!(phone.status().get_untracked(), SyncStatus::Offline);
+    assert_eq!(phone.pending().get_untracked(), 1);
+    assert_eq!(phone.get::<Todo>("todos", "1").await.unwrap(), Some(todo("milk", &["dairy"])));
+
+    // The laptop edits the same row meanwhile, and reaches the server first.
+    network.online.store(true, Ordering::SeqCst);
+    laptop.put("todos", "1", &todo("milk", &["urgent"])).await.unwrap();
+    laptop.sync().await.unwrap();
+
+    let report = phone.sync().await.unwrap();
+    assert_eq!(report.conflicts, 1);
+    assert_eq!(phone.get::<Todo>("todos", "1").await.unwrap(), Some(todo("milk", &["dairy", "urgent"])));
+    assert_eq!(phone.pending().get_untracked(), 1, "the merge is pushed on the next sync");
+
+    phone.sync().await.unwrap();
+    laptop.sync().await.unwrap();
+    assert_eq!(laptop.get::<Todo>("todos", "1").await.unwrap(), Some(todo("milk", &["dairy", "urgent"])));
+    assert_eq!(phone.pending().get_untracked(), 0);
+}
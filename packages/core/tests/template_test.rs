@@ -0,0 +1,42 @@
+#![cfg(feature = "templates")]
+
+use montrs_core::TemplateEngine;
+use serde_json::json;
+
+#[test]
+fn test_layout_and_escaping() {
+    let engine = TemplateEngine::new()
+        .with_template(
+            "layout.html",
+            "<html><title>{% block title %}{% endblock %}</title><body>{% block body %}{% endblock %}</body></html>",
+        )
+        .unwrap()
+        .with_template(
+            "admin/user.html",
+            "{% extends \"layout.html\" %}{% block title %}User{% endblock %}{% block body %}<p>{{ name }}</p>{% endblock %}",
+        )
+        .unwrap();
+
+    let html = engine
+        .render("admin/user.html", json!({ "name": "<script>alert(1)</script>" }))
+        .unwrap();
+    assert_eq!(
+        html.as_str(),
+        "<html><title>User</title><body><p>&lt;script&gt;alert(1)&lt;&#x2f;script&gt;</p></body></html>"
+    );
+}
+
+#[test]
+fn test_plain_text_templates_are_not_escaped() {
+    let mut engine = TemplateEngine::new();
+    engine.add_template("welcome.txt", "Hi {{ name }} & welcome").unwrap();
+    let out = engine.render("welcome.txt", json!({ "name": "<Ada>" })).unwrap();
+    assert_eq!(out.as_str(), "Hi <Ada> & welcome");
+}
+
+#[test]
+fn test_missing_template() {
+    let engine = TemplateEngine::new();
+    let err = engine.render("missing.html", json!({})).unwrap_err();
+    assert!(matches!(err, montrs_core::TemplateError::NotFound(_)));
+}
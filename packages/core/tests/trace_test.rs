@@ -0,0 +1,132 @@
+use async_trait::async_trait;
+use leptos::prelude::*;
+use montrs_core::{
+    AppConfig, Embedded, EnvConfig, Route, RouteAction, RouteContext, RouteError, RouteLoader, RouteParams, RouteView,
+    Router, WasiRequest,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct OrderParams {
+    id: u64,
+}
+impl RouteParams for OrderParams {}
+
+/// Answers with what the handler sees of its trace.
+struct OrderLoader;
+#[async_trait]
+impl RouteLoader<OrderParams, TestConfig> for OrderLoader {
+    type Output = Value;
+    async fn load(&self, ctx: RouteContext<'_, TestConfig>, params: OrderParams) -> Result<Value, RouteError> {
+        let trace = ctx.trace();
+        trace.set_attr("order_id", params.id).set_baggage("region", "eu, west");
+        Ok(json!({
+            "trace_id": trace.trace_id(),
+            "parent_id": trace.parent_id(),
+            "order_id": trace.attr("order_id"),
+            "tenant": trace.baggage("tenant"),
+            "headers": trace.headers(),
+        }))
+    }
+}
+
+struct OrderAction;
+#[async_trait]
+impl RouteAction<OrderParams, TestConfig> for OrderAction {
+    type Input = Value;
+    type Output = Value;
+    async fn act(&self, ctx: RouteContext<'_, TestConfig>, _params: OrderParams, _input: Value) -> Result<Value, RouteError> {
+        Ok(json!({ "attributes": ctx.trace().attributes() }))
+    }
+}
+
+struct OrderView;
+impl RouteView for OrderView {
+    fn render(&self) -> impl IntoView {
+        view! { <div>"Order"</div> }
+    }
+}
+
+struct OrderRoute;
+impl Route<TestConfig> for OrderRoute {
+    type Params = OrderParams;
+    type Loader = OrderLoader;
+    type Action = OrderAction;
+    type View = OrderView;
+
+    fn path() -> &'static str {
+        "/orders/:id"
+    }
+    fn loader(&self) -> Self::Loader {
+        OrderLoader
+    }
+    fn action(&self) -> Self::Action {
+        OrderAction
+    }
+    fn view(&self) -> Self::View {
+        OrderView
+    }
+}
+
+fn app() -> Embedded<TestConfig> {
+    let mut router = Router::new();
+    router.register(OrderRoute);
+    Embedded::new(router, TestConfig, TestEnv)
+}
+
+async fn load(app: &Embedded<TestConfig>, request: WasiRequest) -> Value {
+    let response = app.handle(request).await;
+    assert_eq!(response.status, 200);
+    serde_json::from_slice(&response.body).unwrap()
+}
+
+#[tokio::test]
+async fn test_handler_continues_the_callers_trace() {
+    let request = WasiRequest::new("GET", "/orders/42")
+        .with_header("traceparent", "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+        .with_header("baggage", "tenant=acme%20corp;ttl=60,plan=pro");
+    let seen = load(&app(), request).await;
+
+    assert_eq!(seen["trace_id"], "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_eq!(seen["parent_id"], "00f067aa0ba902b7");
+    assert_eq!(seen["order_id"], 42);
+    assert_eq!(seen["tenant"], "acme corp");
+
+    // Outbound calls name the handler's span as their parent and carry the baggage on.
+    let traceparent = seen["headers"][0][1].as_str().unwrap();
+    assert!(traceparent.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"), "{}", traceparent);
+    assert!(!traceparent.contains("00f067aa0ba902b7"));
+    assert!(traceparent.ends_with("-01"));
+    assert_eq!(seen["headers"][1], json!(["baggage", "plan=pro,region=eu%2C%20west,tenant=acme%20corp"]));
+}
+
+#[tokio::test]
+async fn test_each_request_gets_a_trace_of_its_own() {
+    let app = app();
+    let first = load(&app, WasiRequest::new("GET", "/orders/1")).await;
+    let second = load(&app, WasiRequest::new("GET", "/orders/2").with_header("traceparent", "00-not-a-trace-01")).await;
+    assert_eq!(first["trace_id"].as_str().unwrap().len(), 32);
+    assert_ne!(first["trace_id"], second["trace_id"]);
+    assert_eq!(second["parent_id"], Value::Null);
+    assert_eq!(second["tenant"], Value::Null);
+
+    // Attributes belong to the handler that set them.
+    let acted = load(&app, WasiRequest::new("POST", "/orders/1").with_body("{}")).await;
+    assert_eq!(acted["attributes"], json!({}));
+}
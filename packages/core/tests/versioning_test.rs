@@ -0,0 +1,158 @@
+use montrs_core::{
+    ApiVersions, AppConfig, Deprecation, EnvConfig, Route, RouteAction, RouteContext, RouteError, RouteLoader,
+    RouteParams, RouteView, Router, meta,
+};
+use async_trait::async_trait;
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ItemParams {
+    id: u32,
+}
+impl RouteParams for ItemParams {}
+
+struct ItemLoader(&'static str);
+#[async_trait]
+impl RouteLoader<ItemParams, TestConfig> for ItemLoader {
+    type Output = String;
+    async fn load(&self, _ctx: RouteContext<'_, TestConfig>, params: ItemParams) -> Result<Self::Output, RouteError> {
+        Ok(format!("{} item {}", self.0, params.id))
+    }
+}
+
+struct NoAction;
+#[async_trait]
+impl RouteAction<ItemParams, TestConfig> for NoAction {
+    type Input = ();
+    type Output = ();
+    async fn act(&self, _ctx: RouteContext<'_, TestConfig>, _params: ItemParams, _input: ()) -> Result<(), RouteError> {
+        Ok(())
+    }
+}
+
+struct EmptyView;
+impl RouteView for EmptyView {
+    fn render(&self) -> impl IntoView {
+        view! { <div></div> }
+    }
+}
+
+/// `/api/v1/items/:id`, `/api/v2/items/:id` and `/api/v1/legacy/:id`.
+struct VersionedRoute<const V: u8>;
+impl<const V: u8> Route<TestConfig> for VersionedRoute<V> {
+    type Params = ItemParams;
+    type Loader = ItemLoader;
+    type Action = NoAction;
+    type View = EmptyView;
+
+    fn path() -> &'static str {
+        match V {
+            1 => "/api/v1/items/:id",
+            2 => "/api/v2/items/:id",
+            _ => "/api/v1/legacy/:id",
+        }
+    }
+    fn loader(&self) -> Self::Loader {
+        ItemLoader(match V {
+            2 => "v2",
+            _ => "v1",
+        })
+    }
+    fn action(&self) -> Self::Action {
+        NoAction
+    }
+    fn view(&self) -> Self::View {
+        EmptyView
+    }
+}
+
+fn versions(api: ApiVersions) -> ApiVersions {
+    api.version("v1").version("v2").deprecate("v1", Deprecation::new().sunset("2030-01-31"))
+}
+
+#[test]
+fn test_negotiates_from_path_header_and_media_type() {
+    let by_header = versions(ApiVersions::header("/api", "Api-Version"));
+    let negotiated = by_header.negotiate("/api/items/7", [("api-version", "1")]).unwrap();
+    assert_eq!(negotiated.version.as_deref(), Some("v1"));
+    assert_eq!(negotiated.path, "/api/v1/items/7");
+    assert!(negotiated.headers.contains(&("Vary".to_string(), "Api-Version".to_string())));
+
+    // The newest version is the default, and a version in the path wins.
+    assert_eq!(by_header.negotiate("/api/items/7", []).unwrap().path, "/api/v2/items/7");
+    assert_eq!(by_header.negotiate("/api/v1/items/7", [("Api-Version", "v2")]).unwrap().path, "/api/v1/items/7");
+    assert!(matches!(by_header.negotiate("/api/items/7", [("Api-Version", "v9")]), Err(RouteError::NotAcceptable(_))));
+
+    // Paths outside the prefix are left alone.
+    let outside = by_header.negotiate("/health", [("Api-Version", "v9")]).unwrap();
+    assert_eq!((outside.version, outside.path.as_str()), (None, "/health"));
+
+    let by_media = versions(ApiVersions::media_type("/api", "application/vnd.acme"));
+    let accept = [("Accept", "text/html, application/vnd.acme.v1+json;q=0.9")];
+    let negotiated = by_media.negotiate("/api/items/7", accept).unwrap();
+    assert_eq!(negotiated.path, "/api/v1/items/7");
+    assert!(negotiated.headers.contains(&("Content-Type".to_string(), "application/vnd.acme.v1+json".to_string())));
+    let accept = [("Accept", "application/vnd.acme+json; version=2")];
+    assert_eq!(by_media.negotiate("/api/items/7", accept).unwrap().version.as_deref(), Some("v2"));
+
+    let by_path = versions(ApiVersions::path("/api"));
+    assert_eq!(by_path.negotiate("/api/items/7", [("Api-Version", "v1")]).unwrap().path, "/api/v2/items/7");
+}
+
+#[tokio::test]
+async fn test_versioned_routes_in_router_and_spec() {
+    let mut router = Router::<TestConfig>::new();
+    router.set_versions(versions(ApiVersions::header("/api", "Api-Version")));
+    router.register(VersionedRoute::<1>);
+    router.register(VersionedRoute::<2>);
+    router.register(VersionedRoute::<3>);
+
+    let (config, env) = (TestConfig, TestEnv);
+    let negotiated = router.negotiate("/api/items/5", [("Api-Version", "v1")]).unwrap();
+    let ctx = RouteContext { config: &config, env: &env };
+    let data = router.load(&negotiated.path, ctx, serde_json::Value::Null).await.unwrap();
+    assert_eq!(data, "v1 item 5");
+
+    // v1 routes inherit the version's deprecation and point to their v2 successor.
+    let deprecation = router.deprecation("/api/v1/items/:id").unwrap();
+    assert_eq!(deprecation.replacement.as_deref(), Some("/api/v2/items/:id"));
+    assert_eq!(router.deprecation("/api/v1/legacy/:id").unwrap().replacement.as_deref(), Some("/api/v2"));
+    assert!(router.deprecation("/api/v2/items/:id").is_none());
+    assert_eq!(router.deprecation_report().iter().map(|usage| usage.hits).sum::<u64>(), 1);
+
+    let spec = router.spec();
+    let route = &spec.routes["/api/v1/items/:id"];
+    assert_eq!(route.meta[meta::API_VERSION], "v1");
+    assert_eq!(route.meta[meta::API_ROUTE], "/api/items/:id");
+    assert_eq!(route.meta[meta::STABILITY], meta::DEPRECATED);
+
+    let api_versions = spec.api_versions();
+    assert_eq!(api_versions.len(), 2);
+    assert_eq!(api_versions[0].version, "v1");
+    assert!(api_versions[0].deprecated);
+    assert_eq!(api_versions[1].routes, vec!["/api/items/:id"]);
+    assert_eq!(api_versions[1].removed, vec!["/api/legacy/:id"]);
+
+    let docs = spec.to_openapi_versions("test");
+    assert_eq!(docs["v2"]["info"]["version"], "v2");
+    assert!(docs["v2"]["paths"]["/api/v2/items/{id}"].is_object());
+    assert!(docs["v2"]["paths"]["/api/v1/items/{id}"].is_null());
+    assert_eq!(docs["v2"]["x-montrs-changes"]["removed"][0], "/api/legacy/:id");
+    assert_eq!(docs["v1"]["paths"]["/api/v1/items/{id}"]["get"]["deprecated"], true);
+}
@@ -0,0 +1,153 @@
+use async_trait::async_trait;
+use leptos::prelude::*;
+use montrs_core::wasi;
+use montrs_core::{
+    AppConfig, AppSpec, EnvConfig, Route, RouteAction, RouteContext, RouteError, RouteLoader, RouteParams, RouteView,
+    Target, WasiRequest,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone)]
+struct TestConfig;
+impl AppConfig for TestConfig {
+    type Error = std::io::Error;
+    type Env = TestEnv;
+}
+
+#[derive(Clone)]
+struct TestEnv;
+impl EnvConfig for TestEnv {
+    fn get_var(&self, _key: &str) -> Result<String, montrs_core::EnvError> {
+        Ok("test".to_string())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ItemParams {
+    id: u32,
+    q: Option<String>,
+}
+impl RouteParams for ItemParams {}
+
+struct ItemLoader;
+#[async_trait]
+impl RouteLoader<ItemParams, TestConfig> for ItemLoader {
+    type Output = serde_json::Value;
+    async fn load(&self, ctx: RouteContext<'_, TestConfig>, params: ItemParams) -> Result<Self::Output, RouteError> {
+        let agent = ctx.request().and_then(|r| r.header("user-agent").map(str::to_string));
+        Ok(serde_json::json!({ "id": params.id, "q": params.q, "agent": agent }))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Rename {
+    name: String,
+}
+
+struct ItemAction;
+#[async_trait]
+impl RouteAction<ItemParams, TestConfig> for ItemAction {
+    type Input = Rename;
+    type Output = String;
+    async fn act(&self, ctx: RouteContext<'_, TestConfig>, params: ItemParams, input: Self::Input) -> Result<Self::Output, RouteError> {
+        let method = ctx.request().map(|r| r.method.clone()).unwrap_or_default();
+        Ok(format!("{} {} -> {}", method, params.id, input.name))
+    }
+}
+
+struct ItemView;
+impl RouteView for ItemView {
+    fn render(&self) -> impl IntoView {
+        view! { <div>"Item"</div> }
+    }
+}
+
+struct ItemRoute;
+impl Route<TestConfig> for ItemRoute {
+    type Params = ItemParams;
+    type Loader = ItemLoader;
+    type Action = ItemAction;
+    type View = ItemView;
+
+    fn path() -> &'static str {
+        "/items/:id"
+    }
+    fn loader(&self) -> Self::Loader {
+        ItemLoader
+    }
+    fn action(&self) -> Self::Action {
+        ItemAction
+    }
+    fn view(&self) -> Self::View {
+        ItemView
+    }
+}
+
+fn spec() -> AppSpec<TestConfig> {
+    let mut spec = AppSpec::new(TestConfig, TestEnv);
+    spec.router.register(ItemRoute);
+    spec
+}
+
+fn json(body: &[u8]) -> serde_json::Value {
+    serde_json::from_slice(body).unwrap()
+}
+
+#[test]
+fn test_request_model() {
+    let request = WasiRequest::new("get", "/items/7?q=red+shoes&page=2&empty=&tag=%E2%9C%93&bad=%zz")
+        .with_header("User-Agent", "curl");
+    assert_eq!(request.method, "GET");
+    assert_eq!(request.path(), "/items/7");
+    assert_eq!(request.header("user-agent"), Some("curl"));
+    assert_eq!(
+        serde_json::Value::Object(request.query_params()),
+        serde_json::json!({ "q": "red shoes", "page": 2, "empty": "", "tag": "✓", "bad": "%zz" })
+    );
+    assert_eq!(WasiRequest::new("GET", "/").query(), None);
+}
+
+#[tokio::test]
+async fn test_loads_and_acts() {
+    let spec = spec();
+
+    let request = WasiRequest::new("GET", "/items/7?q=shoes&id=9").with_header("user-agent", "curl");
+    let response = wasi::handle(&spec, request).await;
+    assert_eq!(response.status, 200);
+    assert_eq!(response.header("Content-Type"), Some("application/json"));
+    // The path param wins over the query string.
+    assert_eq!(json(&response.body), serde_json::json!({ "id": 7, "q": "shoes", "agent": "curl" }));
+
+    let response = wasi::handle(&spec, WasiRequest::new("HEAD", "/items/7")).await;
+    assert_eq!(response.status, 200);
+    assert!(response.body.is_empty());
+
+    let request = WasiRequest::new("PUT", "/items/3").with_body(r#"{"name":"hat"}"#);
+    let response = wasi::handle(&spec, request).await;
+    assert_eq!((response.status, json(&response.body)), (200, serde_json::json!("PUT 3 -> hat")));
+}
+
+#[tokio::test]
+async fn test_errors() {
+    let spec = spec();
+
+    let response = wasi::handle(&spec, WasiRequest::new("GET", "/missing")).await;
+    assert_eq!((response.status, json(&response.body)), (404, serde_json::json!({ "error": "Route not found" })));
+
+    let request = WasiRequest::new("POST", "/items/3").with_header("content-type", "text/plain").with_body("hat");
+    assert_eq!(wasi::handle(&spec, request).await.status, 415);
+
+    let response = wasi::handle(&spec, WasiRequest::new("OPTIONS", "/items/3")).await;
+    assert_eq!(response.status, 405);
+    assert!(response.header("allow").unwrap().contains("PATCH"));
+}
+
+#[test]
+fn test_boot_serves_requests() {
+    let handler = wasi::boot(spec());
+    let response = handler.handle(WasiRequest::new("GET", "/items/1"));
+    assert_eq!(json(&response.body)["id"], 1);
+
+    let spec = spec().with_target(Target::Wasi);
+    assert_eq!(spec.target, Target::Wasi);
+}
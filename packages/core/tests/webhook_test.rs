@@ -0,0 +1,119 @@
+#![cfg(feature = "webhooks")]
+
+use async_trait::async_trait;
+use montrs_core::webhook::{DELIVERY_HEADER, SIGNATURE_HEADER, sign, verify};
+use montrs_core::webhook::Subscription;
+use montrs_core::{DeliveryStatus, MemoryWebhookStore, WebhookTransport, Webhooks};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// URL, headers and body of a request.
+type Sent = (String, Vec<(String, String)>, Vec<u8>);
+
+/// Answers with the scripted statuses in order, then 200, and keeps the requests.
+#[derive(Clone, Default)]
+struct Script {
+    statuses: Arc<Mutex<Vec<Result<u16, String>>>>,
+    sent: Arc<Mutex<Vec<Sent>>>,
+}
+
+#[async_trait]
+impl WebhookTransport for Script {
+    async fn post(&self, url: &str, headers: &[(String, String)], body: Vec<u8>) -> Result<u16, String> {
+        self.sent.lock().unwrap().push((url.to_string(), headers.to_vec(), body));
+        let mut statuses = self.statuses.lock().unwrap();
+        if statuses.is_empty() { Ok(200) } else { statuses.remove(0) }
+    }
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> &'a str {
+    headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str()).unwrap()
+}
+
+#[test]
+fn test_signatures_verify() {
+    let now = chrono::Utc::now().timestamp();
+    let signature = sign("s3cret", now, b"{}");
+    assert!(verify("s3cret", &signature, b"{}", Duration::from_secs(300)));
+    assert!(!verify("other", &signature, b"{}", Duration::from_secs(300)));
+    assert!(!verify("s3cret", &signature, b"{\"x\":1}", Duration::from_secs(300)));
+    assert!(!verify("s3cret", &sign("s3cret", now - 600, b"{}"), b"{}", Duration::from_secs(300)));
+    let rotated = format!("{},v1={}", sign("old", now, b"{}"), signature.split_once("v1=").unwrap().1);
+    assert!(verify("s3cret", &rotated, b"{}", Duration::from_secs(300)));
+    assert!(verify("old", &rotated, b"{}", Duration::from_secs(300)));
+
+    let subscription = Subscription::new("https://example.com", "s").with_events(["order.*", "user.created"]);
+    assert!(subscription.matches("order.paid"));
+    assert!(subscription.matches("user.created"));
+    assert!(!subscription.matches("user.deleted"));
+}
+
+#[tokio::test]
+async fn test_publish_signs_and_delivers_matching_subscriptions() {
+    let transport = Script::default();
+    let webhooks = Webhooks::new(MemoryWebhookStore::new()).with_transport(transport.clone());
+    let orders = webhooks
+        .subscribe(Subscription::new("https://a.example/hooks", "secret-a").with_events(["order.*"]))
+        .await
+        .unwrap();
+    webhooks.subscribe(Subscription::new("https://b.example/hooks", "secret-b").with_events(["user.*"])).await.unwrap();
+
+    let queued = webhooks.publish("order.paid", serde_json::json!({ "order": 7 })).await.unwrap();
+    assert_eq!(queued.len(), 1);
+    assert_eq!(webhooks.deliver_due().await.unwrap(), 1);
+    assert_eq!(webhooks.deliver_due().await.unwrap(), 0);
+
+    let (url, headers, body) = transport.sent.lock().unwrap()[0].clone();
+    assert_eq!(url, "https://a.example/hooks");
+    assert_eq!(header(&headers, DELIVERY_HEADER), queued[0].id);
+    assert!(verify("secret-a", header(&headers, SIGNATURE_HEADER), &body, Duration::from_secs(60)));
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["event"], "order.paid");
+    assert_eq!(body["data"]["order"], 7);
+
+    let delivery = webhooks.store().delivery(&queued[0].id).await.unwrap().unwrap();
+    assert_eq!(delivery.status, DeliveryStatus::Delivered);
+    let log = webhooks.store().attempts(Some(&orders.id), 10).await.unwrap();
+    assert_eq!(log.len(), 1);
+    assert!(log[0].succeeded());
+}
+
+#[tokio::test]
+async fn test_failures_back_off_then_dead_letter() {
+    let transport = Script::default();
+    *transport.statuses.lock().unwrap() = vec![Ok(500), Err("connection refused".to_string()), Ok(503)];
+    let webhooks = Webhooks::new(MemoryWebhookStore::new())
+        .with_transport(transport.clone())
+        .with_retries(3, Duration::ZERO, Duration::ZERO);
+    webhooks.subscribe(Subscription::new("https://a.example/hooks", "s")).await.unwrap();
+    let id = webhooks.publish("order.paid", serde_json::json!({})).await.unwrap()[0].id.clone();
+
+    for _ in 0..4 {
+        webhooks.deliver_due().await.unwrap();
+    }
+    let delivery = webhooks.store().delivery(&id).await.unwrap().unwrap();
+    assert_eq!((delivery.status, delivery.attempts, delivery.last_status), (DeliveryStatus::Dead, 3, Some(503)));
+    assert_eq!(webhooks.store().dead_letters().await.unwrap().len(), 1);
+    let log = webhooks.store().attempts(None, 10).await.unwrap();
+    assert_eq!(log.iter().map(|a| a.attempt).collect::<Vec<_>>(), [3, 2, 1]);
+    assert_eq!(log[1].error.as_deref(), Some("connection refused"));
+
+    // Redelivery starts over and succeeds.
+    webhooks.redeliver(&id).await.unwrap();
+    webhooks.deliver_due().await.unwrap();
+    let delivery = webhooks.store().delivery(&id).await.unwrap().unwrap();
+    assert_eq!((delivery.status, delivery.attempts), (DeliveryStatus::Delivered, 1));
+}
+
+#[tokio::test]
+async fn test_rate_limit_defers_deliveries() {
+    let transport = Script::default();
+    let webhooks = Webhooks::new(MemoryWebhookStore::new()).with_transport(transport.clone()).with_rate_limit(1);
+    webhooks.subscribe(Subscription::new("https://a.example/hooks", "s")).await.unwrap();
+    for n in 0..3 {
+        webhooks.publish("tick", serde_json::json!(n)).await.unwrap();
+    }
+
+    assert_eq!(webhooks.deliver_due().await.unwrap(), 1);
+    assert_eq!(transport.sent.lock().unwrap().len(), 1);
+}
@@ -0,0 +1,106 @@
+use montrs_core::{MemoryStore, Saga, SagaStep, StepContext, WorkflowStatus, WorkflowStore, Workflows};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Order {
+    log: Vec<String>,
+}
+
+/// Appends its name to the log; fails its first `failures` attempts.
+struct Step {
+    name: &'static str,
+    failures: Arc<AtomicU32>,
+    compensation_fails: bool,
+}
+
+fn step(name: &'static str, failures: u32) -> Step {
+    Step { name, failures: Arc::new(AtomicU32::new(failures)), compensation_fails: false }
+}
+
+#[async_trait]
+impl SagaStep<Order> for Step {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn run(&self, _ctx: &StepContext, order: &mut Order) -> anyhow::Result<()> {
+        order.log.push(format!("partial {}", self.name));
+        if self.failures.load(Ordering::SeqCst) > 0 {
+            self.failures.fetch_sub(1, Ordering::SeqCst);
+            anyhow::bail!("{} is down", self.name);
+        }
+        order.log.pop();
+        order.log.push(format!("run {}", self.name));
+        Ok(())
+    }
+
+    async fn compensate(&self, _ctx: &StepContext, order: &mut Order) -> anyhow::Result<()> {
+        if self.compensation_fails {
+            anyhow::bail!("cannot undo {}", self.name);
+        }
+        order.log.push(format!("undo {}", self.name));
+        Ok(())
+    }
+}
+
+fn log(record: &montrs_core::WorkflowRecord) -> Vec<String> {
+    serde_json::from_value::<Order>(record.state.clone()).unwrap().log
+}
+
+#[tokio::test]
+async fn test_saga_completes_with_retries() {
+    let workflows = Workflows::new(MemoryStore::new());
+    let saga = Saga::new("checkout")
+        .step(step("reserve", 0))
+        .step(step("charge", 2))
+        .with_retries(2, Duration::from_millis(1));
+
+    let record = workflows.start(&saga, Order::default()).await.unwrap();
+    assert_eq!(record.status, WorkflowStatus::Completed);
+    assert_eq!(record.step, 2);
+    // Failed attempts leave no trace in the state.
+    assert_eq!(log(&record), ["run reserve", "run charge"]);
+    assert_eq!(workflows.status(&record.id).await.unwrap(), Some(record));
+}
+
+#[tokio::test]
+async fn test_failed_step_compensates_completed_steps() {
+    let workflows = Workflows::new(MemoryStore::new());
+    let saga = Saga::new("checkout").step(step("reserve", 0)).step(step("charge", 0)).step(step("ship", 1));
+
+    let record = workflows.start(&saga, Order::default()).await.unwrap();
+    assert_eq!(record.status, WorkflowStatus::Compensated);
+    assert_eq!(record.step, 0);
+    assert_eq!(log(&record), ["run reserve", "run charge", "undo charge", "undo reserve"]);
+    assert_eq!(record.error.as_deref(), Some("ship: ship is down"));
+
+    let mut stuck = step("charge", 0);
+    stuck.compensation_fails = true;
+    let saga = Saga::new("refund").step(step("reserve", 0)).step(stuck).step(step("ship", 1));
+    let record = workflows.start(&saga, Order::default()).await.unwrap();
+    assert_eq!(record.status, WorkflowStatus::Failed);
+    assert_eq!(record.step, 2);
+    assert_eq!(record.error.as_deref(), Some("compensating charge: cannot undo charge after ship: ship is down"));
+}
+
+#[tokio::test]
+async fn test_resume_continues_after_crash() {
+    let store = Arc::new(MemoryStore::new());
+    let workflows = Workflows::new(store.clone());
+    let saga = Saga::new("checkout").step(step("reserve", 0)).step(step("charge", 0));
+
+    // A process that died after the first step left this record behind.
+    let mut record = workflows.start(&Saga::new("checkout").step(step("reserve", 0)), Order::default()).await.unwrap();
+    record.status = WorkflowStatus::Running;
+    store.save(&record).await.unwrap();
+
+    let resumed = workflows.resume(&saga).await.unwrap();
+    assert_eq!(resumed.len(), 1);
+    assert_eq!(resumed[0].status, WorkflowStatus::Completed);
+    assert_eq!(log(&resumed[0]), ["run reserve", "run charge"]);
+    assert!(workflows.resume(&saga).await.unwrap().is_empty());
+}
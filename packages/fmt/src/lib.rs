@@ -10,6 +10,9 @@ pub mod macro_fmt;
 
 pub use config::FormatterSettings;
 
+/// This crate's version and the `montrs-core` it needs; see [`montrs_core::compat`].
+pub const CRATE_VERSION: montrs_core::CrateVersion = montrs_core::crate_version!();
+
 #[derive(Error, Debug)]
 pub enum FormatError {
     #[error("Parse error: {0}")]
@@ -11,13 +11,48 @@ pub use montrs_schema as schema;
 #[cfg(feature = "test")]
 pub use montrs_test as test;
 
+use montrs_core::CrateVersion;
+
+/// The montrs crates this build enables, for `AppSpec::with_crates` to check
+/// against the core at boot.
+///
+/// ```rust,ignore
+/// AppSpec::new(config, env).with_crates(montrs::crates()).boot().await?;
+/// ```
+pub fn crates() -> Vec<CrateVersion> {
+    let mut crates = vec![montrs_core::crate_version!()];
+    #[cfg(feature = "orm")]
+    crates.push(montrs_orm::CRATE_VERSION);
+    #[cfg(feature = "test")]
+    crates.push(montrs_test::CRATE_VERSION);
+    crates
+}
+
 /// A convenience plate for importing the most commonly used types and traits.
 pub mod prelude {
     pub use montrs_core::*;
     
     #[cfg(feature = "orm")]
     pub use montrs_orm::*;
-    
+
+    // Modules both crates have would be ambiguous through the globs: the
+    // core's keep their names and the ORM's get an `orm_` prefix.
+    pub use montrs_core::analytics;
+    #[cfg(feature = "orm")]
+    pub use montrs_orm::analytics as orm_analytics;
+    pub use montrs_core::workflow;
+    #[cfg(feature = "orm")]
+    pub use montrs_orm::workflow as orm_workflow;
+    pub use montrs_core::lifecycle;
+    #[cfg(feature = "lifecycle")]
+    pub use montrs_orm::lifecycle as orm_lifecycle;
+    pub use montrs_core::sync;
+    #[cfg(feature = "orm")]
+    pub use montrs_orm::sync as orm_sync;
+    pub use montrs_core::trace;
+    #[cfg(feature = "orm")]
+    pub use montrs_orm::trace as orm_trace;
+
     // montrs_schema is a proc-macro crate, we re-export its main macro
     #[cfg(feature = "schema")]
     pub use montrs_schema::Schema;
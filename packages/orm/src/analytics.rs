@@ -0,0 +1,117 @@
+//! Route usage analytics stored in the application database.
+//! `RouteUsageTable` is an `AnalyticsSink` that appends one row per route
+//! operation and window, so the counts survive restarts and can be queried
+//! like any other table. Rows hold route patterns and counters only.
+
+use crate::{DbBackend, DbError, Insert, ToSql};
+use async_trait::async_trait;
+use montrs_core::analytics::{AnalyticsSink, UsageWindow};
+
+/// The table rows are written to unless [`RouteUsageTable::with_table`] says otherwise.
+pub const DEFAULT_TABLE: &str = "montrs_route_usage";
+
+const COLUMNS: [&str; 12] = [
+    "window_start",
+    "window_end",
+    "route",
+    "operation",
+    "hits",
+    "status_2xx",
+    "status_4xx",
+    "status_5xx",
+    "total_ms",
+    "max_ms",
+    "p50_ms",
+    "p95_ms",
+];
+
+/// Writes each analytics window to a table.
+///
+/// ```rust,ignore
+/// let table = RouteUsageTable::new(db.clone());
+/// table.create_table().await?;
+/// let analytics = Analytics::new().with_sink(table);
+/// ```
+pub struct RouteUsageTable<B: DbBackend> {
+    db: B,
+    table: String,
+}
+
+impl<B: DbBackend> RouteUsageTable<B> {
+    pub fn new(db: B) -> Self {
+        Self { db, table: DEFAULT_TABLE.to_string() }
+    }
+
+    /// Writes to `table` instead of [`DEFAULT_TABLE`].
+    pub fn with_table(mut self, table: impl Into<String>) -> Self {
+        self.table = table.into();
+        self
+    }
+
+    /// Creates the table if it does not exist yet. The column types work on
+    /// both SQLite and PostgreSQL; timestamps are RFC 3339 text.
+    pub async fn create_table(&self) -> Result<(), DbError> {
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {} (\
+             window_start TEXT NOT NULL, window_end TEXT NOT NULL, route TEXT NOT NULL, operation TEXT NOT NULL, \
+             hits BIGINT NOT NULL, status_2xx BIGINT NOT NULL, status_4xx BIGINT NOT NULL, status_5xx BIGINT NOT NULL, \
+             total_ms DOUBLE PRECISION NOT NULL, max_ms DOUBLE PRECISION NOT NULL, p50_ms BIGINT NOT NULL, p95_ms BIGINT NOT NULL)",
+            self.table
+        );
+        self.db.execute(&sql, &[]).await?;
+        Ok(())
+    }
+
+    /// Inserts one row per route operation in `window`.
+    pub async fn insert(&self, window: &UsageWindow) -> Result<usize, DbError> {
+        let started = window.started.to_rfc3339();
+        let ended = window.ended.to_rfc3339();
+        let rows: Vec<Row> = window
+            .routes
+            .iter()
+            .map(|usage| Row {
+                route: usage.route.clone(),
+                operation: usage.operation.clone(),
+                counts: [
+                    usage.hits as i64,
+                    usage.status.success as i64,
+                    usage.status.client_error as i64,
+                    usage.status.server_error as i64,
+                ],
+                total_ms: usage.total_us as f64 / 1_000.0,
+                max_ms: usage.max_us as f64 / 1_000.0,
+                percentiles: [usage.percentile_ms(50.0) as i64, usage.percentile_ms(95.0) as i64],
+            })
+            .collect();
+        let params: Vec<Vec<&dyn ToSql>> = rows
+            .iter()
+            .map(|row| {
+                let mut params: Vec<&dyn ToSql> = vec![&started, &ended, &row.route, &row.operation];
+                params.extend(row.counts.iter().map(|n| n as &dyn ToSql));
+                params.push(&row.total_ms);
+                params.push(&row.max_ms);
+                params.extend(row.percentiles.iter().map(|n| n as &dyn ToSql));
+                params
+            })
+            .collect();
+        let params: Vec<&[&dyn ToSql]> = params.iter().map(Vec::as_slice).collect();
+        Insert::into(&self.table, &COLUMNS).execute(&self.db, &params).await
+    }
+}
+
+struct Row {
+    route: String,
+    operation: String,
+    counts: [i64; 4],
+    total_ms: f64,
+    max_ms: f64,
+    percentiles: [i64; 2],
+}
+
+#[async_trait]
+impl<B: DbBackend> AnalyticsSink for RouteUsageTable<B> {
+    async fn flush(&self, window: &UsageWindow) -> anyhow::Result<()> {
+        self.insert(window).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+//! Multi-row inserts and upserts.
+//! `Insert` builds `INSERT ... VALUES (...), (...)` statements for a backend's
+//! dialect, with an optional `ON CONFLICT` clause, and splits large batches
+//! into chunks that stay under the backend's bind parameter limit. Values are
+//! always bound, never spliced into the SQL.
+
+use crate::{DbBackend, DbError, ToSql};
+
+/// The SQL flavour a backend speaks, as far as generated statements care.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// `?` placeholders and a conservative parameter limit.
+    Generic,
+    Sqlite,
+    Postgres,
+}
+
+impl Dialect {
+    /// The placeholder for the `n`th parameter, counting from 1.
+    pub fn placeholder(self, n: usize) -> String {
+        match self {
+            Dialect::Postgres => format!("${}", n),
+            Dialect::Generic | Dialect::Sqlite => "?".to_string(),
+        }
+    }
+
+    /// Most parameters one statement may bind.
+    pub fn max_params(self) -> usize {
+        match self {
+            Dialect::Generic => 999,
+            Dialect::Sqlite => 32_766,
+            Dialect::Postgres => 65_535,
+        }
+    }
+}
+
+/// What an insert does with a row that violates a unique constraint.
+#[derive(Debug, Clone, PartialEq)]
+enum Conflict {
+    /// `ON CONFLICT [(target)] DO NOTHING`.
+    Ignore { target: Vec<String> },
+    /// `ON CONFLICT (target) DO UPDATE SET col = excluded.col, ...`.
+    Update { target: Vec<String>, update: Vec<String> },
+}
+
+/// A multi-row `INSERT`, optionally an upsert.
+///
+/// ```rust,ignore
+/// Insert::into("users", &["email", "name"])
+///     .on_conflict_update(&["email"], &["name"])
+///     .execute(&db, &[&[&email, &name], &[&email2, &name2]])
+///     .await?;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Insert {
+    table: String,
+    columns: Vec<String>,
+    conflict: Option<Conflict>,
+    chunk_rows: Option<usize>,
+}
+
+impl Insert {
+    pub fn into(table: &str, columns: &[&str]) -> Self {
+        Self {
+            table: table.to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            conflict: None,
+            chunk_rows: None,
+        }
+    }
+
+    /// Skips rows that conflict on `target`, or on any unique constraint when
+    /// `target` is empty.
+    pub fn on_conflict_do_nothing(mut self, target: &[&str]) -> Self {
+        self.conflict = Some(Conflict::Ignore { target: target.iter().map(|c| c.to_string()).collect() });
+        self
+    }
+
+    /// Updates the `update` columns of the existing row when a row conflicts
+    /// on `target`, which must match a unique index.
+    pub fn on_conflict_update(mut self, target: &[&str], update: &[&str]) -> Self {
+        self.conflict = Some(Conflict::Update {
+            target: target.iter().map(|c| c.to_string()).collect(),
+            update: update.iter().map(|c| c.to_string()).collect(),
+        });
+        self
+    }
+
+    /// Caps the rows per statement below what the parameter limit allows.
+    pub fn chunk_size(mut self, rows: usize) -> Self {
+        self.chunk_rows = Some(rows.max(1));
+        self
+    }
+
+    /// Rows per statement for `dialect`.
+    pub fn rows_per_statement(&self, dialect: Dialect) -> usize {
+        let by_params = (dialect.max_params() / self.columns.len().max(1)).max(1);
+        self.chunk_rows.map_or(by_params, |rows| rows.min(by_params))
+    }
+
+    /// The statement inserting `rows` rows.
+    pub fn sql(&self, dialect: Dialect, rows: usize) -> String {
+        let width = self.columns.len();
+        let values: Vec<String> = (0..rows)
+            .map(|row| {
+                let params: Vec<String> = (1..=width).map(|col| dialect.placeholder(row * width + col)).collect();
+                format!("({})", params.join(", "))
+            })
+            .collect();
+        format!(
+            "INSERT INTO {} ({}) VALUES {}{}",
+            self.table,
+            self.columns.join(", "),
+            values.join(", "),
+            self.conflict_clause()
+        )
+    }
+
+    /// The `ON CONFLICT` clause, with a leading space, or nothing.
+    pub(crate) fn conflict_clause(&self) -> String {
+        match &self.conflict {
+            None => String::new(),
+            Some(Conflict::Ignore { target }) if target.is_empty() => " ON CONFLICT DO NOTHING".to_string(),
+            Some(Conflict::Ignore { target }) => format!(" ON CONFLICT ({}) DO NOTHING", target.join(", ")),
+            Some(Conflict::Update { target, update }) if update.is_empty() => {
+                format!(" ON CONFLICT ({}) DO NOTHING", target.join(", "))
+            }
+            Some(Conflict::Update { target, update }) => {
+                let set: Vec<String> = update.iter().map(|c| format!("{} = excluded.{}", c, c)).collect();
+                format!(" ON CONFLICT ({}) DO UPDATE SET {}", target.join(", "), set.join(", "))
+            }
+        }
+    }
+
+    /// Inserts `rows`, one statement per chunk, and returns the number of
+    /// rows affected. Chunks are separate statements: a failure leaves the
+    /// earlier chunks in place.
+    pub async fn execute<B: DbBackend>(&self, db: &B, rows: &[&[&dyn ToSql]]) -> Result<usize, DbError> {
+        if self.columns.is_empty() {
+            return Err(DbError::Query(format!("insert into {} names no columns", self.table)));
+        }
+        if let Some(row) = rows.iter().position(|r| r.len() != self.columns.len()) {
+            return Err(DbError::Query(format!(
+                "row {} has {} values for {} columns",
+                row,
+                rows[row].len(),
+                self.columns.len()
+            )));
+        }
+        let dialect = db.dialect();
+        let mut affected = 0;
+        for chunk in rows.chunks(self.rows_per_statement(dialect)) {
+            let params: Vec<&dyn ToSql> = chunk.iter().flat_map(|row| row.iter().copied()).collect();
+            affected += db.execute(&self.sql(dialect, chunk.len()), &params).await?;
+        }
+        Ok(affected)
+    }
+}
@@ -0,0 +1,301 @@
+//! Query checking against a schema snapshot.
+//! `check_query` finds the tables and columns a SQL string refers to and
+//! reports the ones the schema does not have, along with placeholder counts
+//! that do not match the parameters passed. It errs on the side of silence:
+//! anything it cannot resolve, such as columns of a subquery, is not flagged.
+
+use crate::schema::SchemaSnapshot;
+use crate::sql::{Param, Token, TokenKind, tokenize};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// What is wrong with a query.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    UnknownTable,
+    UnknownColumn,
+    /// The number of parameters passed differs from the placeholders.
+    ParamCount,
+}
+
+/// A problem found in one query.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct QueryIssue {
+    pub kind: IssueKind,
+    pub message: String,
+    /// Byte offset of the offending token in the query, if there is one.
+    pub offset: Option<usize>,
+}
+
+/// Statements whose identifiers name things the schema does not describe.
+const UNCHECKED_STATEMENTS: &[&str] =
+    &["create", "alter", "drop", "pragma", "begin", "commit", "rollback", "savepoint", "release", "vacuum", "analyze", "set", "show"];
+
+/// Words that are never column references.
+const KEYWORDS: &[&str] = &[
+    "select", "from", "where", "and", "or", "not", "in", "is", "null", "like", "ilike", "glob", "between", "exists",
+    "as", "on", "using", "join", "inner", "left", "right", "full", "outer", "cross", "natural", "lateral", "group",
+    "by", "order", "having", "limit", "offset", "asc", "desc", "nulls", "first", "last", "distinct", "all", "any",
+    "some", "union", "intersect", "except", "insert", "into", "values", "default", "update", "set", "delete",
+    "returning", "with", "recursive", "case", "when", "then", "else", "end", "cast", "true", "false", "conflict",
+    "do", "nothing", "replace", "ignore", "abort", "fail", "upsert", "over", "partition", "window", "rows", "range",
+    "preceding", "following", "unbounded", "current", "row", "filter", "collate", "nocase", "binary", "rtrim",
+    "escape", "interval", "current_date", "current_time", "current_timestamp", "localtime", "localtimestamp",
+    "rowid", "oid", "_rowid_", "for", "share", "nowait", "skip", "locked", "only", "fetch", "next", "similar", "to",
+    "array", "of", "excluded", "zone", "at", "time", "timestamp", "date", "materialized",
+];
+
+/// Checks `sql` against `schema`. `params` is the number of bind parameters
+/// passed alongside it, when known.
+pub fn check_query(sql: &str, params: Option<usize>, schema: &SchemaSnapshot) -> Vec<QueryIssue> {
+    let tokens = tokenize(sql);
+    let mut issues = Vec::new();
+
+    let expected = placeholder_count(&tokens);
+    if let Some(passed) = params
+        && passed != expected
+    {
+        issues.push(QueryIssue {
+            kind: IssueKind::ParamCount,
+            message: format!("query has {} placeholder(s) but {} parameter(s) are passed", expected, passed),
+            offset: None,
+        });
+    }
+
+    if tokens.first().and_then(Token::ident).is_none_or(|w| UNCHECKED_STATEMENTS.contains(&w)) {
+        return issues;
+    }
+
+    let scope = Scope::collect(&tokens, schema);
+    for (name, offset) in &scope.unknown_tables {
+        issues.push(QueryIssue {
+            kind: IssueKind::UnknownTable,
+            message: format!("no table `{}` in the schema", name),
+            offset: Some(*offset),
+        });
+    }
+
+    for (i, token) in tokens.iter().enumerate() {
+        if scope.consumed.contains(&i) {
+            continue;
+        }
+        let Some(word) = token.ident() else { continue };
+        let prev = i.checked_sub(1).and_then(|p| tokens.get(p));
+        let next = tokens.get(i + 1);
+
+        // `qualifier.column`
+        if next.is_some_and(|t| t.is_punct('.')) {
+            let Some(column) = tokens.get(i + 2).and_then(Token::ident) else { continue };
+            if let Some(table) = scope.resolve(word)
+                && let Some(columns) = schema.table(table)
+                && columns.column(column).is_none()
+            {
+                issues.push(QueryIssue {
+                    kind: IssueKind::UnknownColumn,
+                    message: format!("no column `{}` in table `{}`", column, table),
+                    offset: Some(tokens[i + 2].offset),
+                });
+            }
+            continue;
+        }
+        if prev.is_some_and(|t| t.is_punct('.') || t.kind == TokenKind::Cast)
+            || next.is_some_and(|t| t.is_punct('('))
+            || (matches!(token.kind, TokenKind::Word(_)) && KEYWORDS.contains(&word))
+            || scope.aliases.contains(word)
+            || !scope.checks_unqualified()
+        {
+            continue;
+        }
+        let found = scope.tables.iter().any(|t| schema.table(t).is_some_and(|t| t.column(word).is_some()));
+        if !found {
+            issues.push(QueryIssue {
+                kind: IssueKind::UnknownColumn,
+                message: format!("no column `{}` in {}", word, scope.describe_tables()),
+                offset: Some(token.offset),
+            });
+        }
+    }
+    issues
+}
+
+/// How many parameters a query expects: the highest `$n`, or the number of
+/// `?` and distinct named placeholders.
+fn placeholder_count(tokens: &[Token]) -> usize {
+    let mut numbered = 0;
+    let mut positional = 0;
+    let mut named = HashSet::new();
+    for token in tokens {
+        match &token.kind {
+            TokenKind::Param(Param::Numbered(n)) => numbered = numbered.max(*n),
+            TokenKind::Param(Param::Positional) => positional += 1,
+            TokenKind::Param(Param::Named(name)) => {
+                named.insert(name.as_str());
+            }
+            _ => {}
+        }
+    }
+    numbered.max(positional + named.len())
+}
+
+/// The tables a query reads or writes, and the names standing for them.
+#[derive(Default)]
+struct Scope {
+    /// Schema tables referenced by the query.
+    tables: Vec<String>,
+    /// `(alias, table)`; a table is its own alias.
+    table_aliases: Vec<(String, String)>,
+    /// Referenced names missing from the schema, with their offsets.
+    unknown_tables: Vec<(String, usize)>,
+    /// Names that are not columns of a schema table: CTEs, subquery and
+    /// select-list aliases.
+    aliases: HashSet<String>,
+    /// Token indices already accounted for as table names or aliases.
+    consumed: HashSet<usize>,
+    /// The query reads from something whose columns are unknown.
+    opaque: bool,
+}
+
+impl Scope {
+    fn collect(tokens: &[Token], schema: &SchemaSnapshot) -> Self {
+        let mut scope = Self::default();
+        let word = |i: usize| tokens.get(i).and_then(Token::ident);
+
+        // `name AS (` introduces a CTE.
+        for i in 0..tokens.len() {
+            if word(i + 1) == Some("as")
+                && tokens.get(i + 2).is_some_and(|t| t.is_punct('('))
+                && let Some(name) = word(i)
+            {
+                scope.aliases.insert(name.to_string());
+                scope.consumed.insert(i);
+            }
+        }
+
+        // For each open parenthesis, whether it belongs to a function call,
+        // where `FROM` is an argument separator as in `extract(year FROM d)`.
+        let mut calls = Vec::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            if token.is_punct('(') {
+                calls.push(i > 0 && tokens[i - 1].ident().is_some_and(|w| !KEYWORDS.contains(&w)));
+            } else if token.is_punct(')') {
+                calls.pop();
+            }
+            let starts_ref = matches!(
+                token.kind,
+                TokenKind::Word(ref w) if matches!(w.as_str(), "from" | "join" | "into" | "update")
+            ) && !calls.last().copied().unwrap_or(false);
+            if !starts_ref {
+                // `expr AS alias` and `expr alias` name a result column.
+                if let Some(name) = word(i)
+                    && !scope.consumed.contains(&i)
+                    && i > 0
+                    && (tokens[i - 1].is_word("as") || implicit_alias(&tokens[i - 1]))
+                    && !KEYWORDS.contains(&name)
+                    && !tokens.get(i + 1).is_some_and(|t| t.is_punct('(') || t.is_punct('.'))
+                {
+                    scope.aliases.insert(name.to_string());
+                    scope.consumed.insert(i);
+                }
+                i += 1;
+                continue;
+            }
+            let is_from = token.is_word("from");
+            let is_into = token.is_word("into");
+            i += 1;
+            loop {
+                i = scope.table_ref(tokens, i, schema, is_into);
+                if is_from && tokens.get(i).is_some_and(|t| t.is_punct(',')) {
+                    i += 1;
+                    continue;
+                }
+                break;
+            }
+        }
+        scope
+    }
+
+    /// Reads `[schema.]table [[AS] alias]` at `i`; a subquery or table
+    /// function there makes the scope opaque. After `INTO`, a parenthesis
+    /// starts the column list rather than a function call.
+    fn table_ref(&mut self, tokens: &[Token], mut i: usize, schema: &SchemaSnapshot, is_into: bool) -> usize {
+        while tokens.get(i).is_some_and(|t| t.is_word("only") || t.is_word("lateral")) {
+            i += 1;
+        }
+        let name = match tokens.get(i) {
+            Some(t) if t.is_punct('(') => {
+                self.opaque = true;
+                return i;
+            }
+            Some(t) => match t.ident() {
+                Some(name) if !(matches!(t.kind, TokenKind::Word(_)) && KEYWORDS.contains(&name)) => name,
+                _ => return i,
+            },
+            None => return i,
+        };
+        let mut name = name.to_string();
+        let mut offset = tokens[i].offset;
+        self.consumed.insert(i);
+        i += 1;
+        while tokens.get(i).is_some_and(|t| t.is_punct('.'))
+            && let Some(part) = tokens.get(i + 1).and_then(Token::ident)
+        {
+            self.consumed.insert(i + 1);
+            name = part.to_string();
+            offset = tokens[i + 1].offset;
+            i += 2;
+        }
+        if !is_into && tokens.get(i).is_some_and(|t| t.is_punct('(')) {
+            // A table-valued function such as `json_each(...)`.
+            self.opaque = true;
+            return i;
+        }
+
+        if self.aliases.contains(&name) {
+            self.opaque = true;
+        } else if schema.table(&name).is_some() {
+            self.tables.push(name.clone());
+        } else {
+            self.unknown_tables.push((name.clone(), offset));
+        }
+        self.table_aliases.push((name.clone(), name.clone()));
+
+        let alias_at = if tokens.get(i).is_some_and(|t| t.is_word("as")) { i + 1 } else { i };
+        if let Some(alias) = tokens.get(alias_at).and_then(Token::ident)
+            && !KEYWORDS.contains(&alias)
+        {
+            self.consumed.insert(alias_at);
+            self.table_aliases.push((alias.to_string(), name));
+            return alias_at + 1;
+        }
+        i
+    }
+
+    fn resolve(&self, qualifier: &str) -> Option<&str> {
+        self.table_aliases.iter().find(|(alias, _)| alias == qualifier).map(|(_, table)| table.as_str())
+    }
+
+    fn checks_unqualified(&self) -> bool {
+        !self.opaque && !self.tables.is_empty() && self.unknown_tables.is_empty()
+    }
+
+    fn describe_tables(&self) -> String {
+        let names: Vec<_> = self.tables.iter().map(|t| format!("`{}`", t)).collect();
+        match names.len() {
+            1 => format!("table {}", names[0]),
+            _ => format!("tables {}", names.join(", ")),
+        }
+    }
+}
+
+/// Whether a word right after `prev` is an implicit alias, as in
+/// `SELECT count(*) total` or `SELECT title t`.
+fn implicit_alias(prev: &Token) -> bool {
+    match &prev.kind {
+        TokenKind::Punct(')') | TokenKind::Str | TokenKind::Number(_) | TokenKind::Quoted(_) => true,
+        TokenKind::Word(w) => w == "end" || !KEYWORDS.contains(&w.as_str()),
+        _ => false,
+    }
+}
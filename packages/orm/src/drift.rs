@@ -0,0 +1,200 @@
+//! Schema drift between what the migrations describe and what a live
+//! database has. `SchemaDrift::between` compares two snapshots;
+//! `SchemaDrift::migration` drafts the SQL that would bring the database back
+//! in line.
+
+use crate::schema::{ColumnSchema, SchemaSnapshot, TableSchema};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// One difference between the expected and the actual schema.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Drift {
+    /// The migrations create the table but the database lacks it.
+    MissingTable { table: String, columns: Vec<ColumnSchema> },
+    /// The database has a table no migration creates.
+    ExtraTable { table: String },
+    MissingColumn { table: String, column: ColumnSchema },
+    ExtraColumn { table: String, column: String },
+    TypeMismatch { table: String, column: String, expected: String, actual: String },
+    NullabilityMismatch { table: String, column: String, expected_nullable: bool },
+}
+
+/// All differences, tables in name order.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SchemaDrift {
+    pub drifts: Vec<Drift>,
+}
+
+impl SchemaDrift {
+    /// Compares the `actual` schema of a database with the `expected` one.
+    /// Types are compared after [`normalize_type`], so `INT4` matches
+    /// `INTEGER` and `VARCHAR(255)` matches `CHARACTER VARYING`.
+    pub fn between(expected: &SchemaSnapshot, actual: &SchemaSnapshot) -> Self {
+        let mut drifts = Vec::new();
+        for (name, table) in &expected.tables {
+            let Some(live) = actual.tables.get(name) else {
+                drifts.push(Drift::MissingTable { table: name.clone(), columns: table.columns.clone() });
+                continue;
+            };
+            compare_table(name, table, live, &mut drifts);
+        }
+        for name in actual.tables.keys().filter(|t| !expected.tables.contains_key(*t)) {
+            drifts.push(Drift::ExtraTable { table: name.clone() });
+        }
+        drifts.sort_by(|a, b| a.table().cmp(b.table()));
+        Self { drifts }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.drifts.is_empty()
+    }
+
+    /// A migration skeleton that brings the database to the expected schema.
+    /// Statements that drop data or change types are commented out, to be
+    /// reviewed by hand.
+    pub fn migration(&self) -> String {
+        let mut sql = String::from("-- Corrects schema drift detected by `montrs db diff`.\n-- Review before applying.\n\n");
+        for drift in &self.drifts {
+            let statement = match drift {
+                Drift::MissingTable { table, columns } => {
+                    let defs: Vec<_> = columns.iter().map(|c| format!("    {}", column_sql(c))).collect();
+                    format!("CREATE TABLE {} (\n{}\n);", table, defs.join(",\n"))
+                }
+                Drift::ExtraTable { table } => format!("-- DROP TABLE {};", table),
+                Drift::MissingColumn { table, column } => {
+                    format!("ALTER TABLE {} ADD COLUMN {};", table, column_sql(column))
+                }
+                Drift::ExtraColumn { table, column } => format!("-- ALTER TABLE {} DROP COLUMN {};", table, column),
+                Drift::TypeMismatch { table, column, expected, .. } => {
+                    format!("-- ALTER TABLE {} ALTER COLUMN {} TYPE {};", table, column, expected)
+                }
+                Drift::NullabilityMismatch { table, column, expected_nullable } => format!(
+                    "-- ALTER TABLE {} ALTER COLUMN {} {} NOT NULL;",
+                    table,
+                    column,
+                    if *expected_nullable { "DROP" } else { "SET" }
+                ),
+            };
+            sql.push_str(&format!("-- {}\n{}\n\n", drift, statement));
+        }
+        sql
+    }
+}
+
+impl Drift {
+    pub fn table(&self) -> &str {
+        match self {
+            Drift::MissingTable { table, .. }
+            | Drift::ExtraTable { table }
+            | Drift::MissingColumn { table, .. }
+            | Drift::ExtraColumn { table, .. }
+            | Drift::TypeMismatch { table, .. }
+            | Drift::NullabilityMismatch { table, .. } => table,
+        }
+    }
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Drift::MissingTable { table, .. } => write!(f, "table `{}` is missing from the database", table),
+            Drift::ExtraTable { table } => write!(f, "table `{}` is not created by any migration", table),
+            Drift::MissingColumn { table, column } => {
+                write!(f, "column `{}.{}` is missing from the database", table, column.name)
+            }
+            Drift::ExtraColumn { table, column } => {
+                write!(f, "column `{}.{}` is not created by any migration", table, column)
+            }
+            Drift::TypeMismatch { table, column, expected, actual } => {
+                write!(f, "column `{}.{}` is {} in the database, {} in the migrations", table, column, actual, expected)
+            }
+            Drift::NullabilityMismatch { table, column, expected_nullable } => write!(
+                f,
+                "column `{}.{}` should be {}",
+                table,
+                column,
+                if *expected_nullable { "nullable" } else { "NOT NULL" }
+            ),
+        }
+    }
+}
+
+fn compare_table(name: &str, expected: &TableSchema, actual: &TableSchema, drifts: &mut Vec<Drift>) {
+    for column in &expected.columns {
+        let Some(live) = actual.column(&column.name) else {
+            drifts.push(Drift::MissingColumn { table: name.to_string(), column: column.clone() });
+            continue;
+        };
+        let (want, have) = (normalize_type(&column.sql_type), normalize_type(&live.sql_type));
+        // SQLite accepts untyped columns; there is nothing to compare.
+        if !want.is_empty() && !have.is_empty() && want != have {
+            drifts.push(Drift::TypeMismatch {
+                table: name.to_string(),
+                column: column.name.clone(),
+                expected: column.sql_type.clone(),
+                actual: live.sql_type.clone(),
+            });
+        }
+        if column.nullable != live.nullable && !column.primary_key {
+            drifts.push(Drift::NullabilityMismatch {
+                table: name.to_string(),
+                column: column.name.clone(),
+                expected_nullable: column.nullable,
+            });
+        }
+    }
+    for column in actual.columns.iter().filter(|c| expected.column(&c.name).is_none()) {
+        drifts.push(Drift::ExtraColumn { table: name.to_string(), column: column.name.clone() });
+    }
+}
+
+fn column_sql(column: &ColumnSchema) -> String {
+    let mut sql = column.name.clone();
+    if !column.sql_type.is_empty() {
+        sql.push(' ');
+        sql.push_str(&column.sql_type);
+    }
+    if column.primary_key {
+        sql.push_str(" PRIMARY KEY");
+    } else if !column.nullable {
+        sql.push_str(" NOT NULL");
+    }
+    sql
+}
+
+/// Reduces a declared type to a canonical name, dropping lengths and
+/// precision, so the spellings SQLite and Postgres report compare equal.
+pub fn normalize_type(sql_type: &str) -> String {
+    let mut base = String::new();
+    let mut depth = 0;
+    for c in sql_type.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if depth == 0 => base.push(c.to_ascii_uppercase()),
+            _ => {}
+        }
+    }
+    let base = base.split_whitespace().collect::<Vec<_>>().join(" ");
+    let canonical = match base.as_str() {
+        "INT" | "INT4" | "INTEGER" | "SERIAL" | "SERIAL4" | "MEDIUMINT" => "INTEGER",
+        "BIGINT" | "INT8" | "BIGSERIAL" | "SERIAL8" => "BIGINT",
+        "SMALLINT" | "INT2" | "SMALLSERIAL" | "TINYINT" => "SMALLINT",
+        "BOOL" | "BOOLEAN" => "BOOLEAN",
+        "REAL" | "FLOAT4" => "REAL",
+        "DOUBLE" | "DOUBLE PRECISION" | "FLOAT8" | "FLOAT" => "DOUBLE PRECISION",
+        "DECIMAL" | "NUMERIC" => "NUMERIC",
+        "VARCHAR" | "CHARACTER VARYING" | "NVARCHAR" => "VARCHAR",
+        "CHAR" | "CHARACTER" | "BPCHAR" | "NCHAR" => "CHAR",
+        "TEXT" | "CLOB" => "TEXT",
+        "BLOB" | "BYTEA" => "BLOB",
+        "TIMESTAMP" | "TIMESTAMP WITHOUT TIME ZONE" | "DATETIME" => "TIMESTAMP",
+        "TIMESTAMPTZ" | "TIMESTAMP WITH TIME ZONE" => "TIMESTAMPTZ",
+        "TIME" | "TIME WITHOUT TIME ZONE" => "TIME",
+        "TIMETZ" | "TIME WITH TIME ZONE" => "TIMETZ",
+        other => other,
+    };
+    canonical.to_string()
+}
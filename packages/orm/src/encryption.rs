@@ -0,0 +1,378 @@
+//! Field-level encryption.
+//! Columns marked `#[orm(encrypted)]` (see `#[derive(EncryptedModel)]` in
+//! `montrs-schema`) are stored as `enc:v1:<key id>:<base64>` with
+//! XChaCha20-Poly1305. Keys come from `DATABASE_ENCRYPTION_KEYS`, usually kept
+//! in the secrets file; the first key encrypts, every key decrypts, so keys
+//! can be rotated with `montrs db rotate-keys` without downtime.
+//!
+//! Ciphertext is randomized, so the database cannot compare it. An
+//! `EncryptedBackend` rejects queries that filter, join, group or sort on an
+//! encrypted column instead of letting them silently match nothing.
+
+use crate::sql::{TokenKind, tokenize};
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+use crate::Transaction;
+use crate::{DbBackend, DbError, Dialect, FromRow, ToSql};
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use montrs_core::EnvConfig;
+use std::sync::Arc;
+
+/// Comma-separated `id:base64key` pairs, current key first.
+pub const ENCRYPTION_KEYS_VAR: &str = "DATABASE_ENCRYPTION_KEYS";
+
+const PREFIX: &str = "enc:v1:";
+const NONCE_LEN: usize = 24;
+
+/// Encrypts and decrypts column values with a ring of named keys.
+#[derive(Clone)]
+pub struct FieldCipher {
+    keys: Vec<(String, XChaCha20Poly1305)>,
+}
+
+impl FieldCipher {
+    /// Parses `id:base64key[,id:base64key...]`. Keys are 32 bytes; the first
+    /// one encrypts new values.
+    pub fn parse(spec: &str) -> Result<Self, DbError> {
+        let mut keys = Vec::new();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (id, key) = entry
+                .split_once(':')
+                .ok_or_else(|| DbError::Encryption(format!("key `{}` is not in the form id:base64key", entry)))?;
+            if id.is_empty() || id.contains(':') {
+                return Err(DbError::Encryption(format!("invalid key id `{}`", id)));
+            }
+            let bytes = STANDARD
+                .decode(key)
+                .map_err(|e| DbError::Encryption(format!("key `{}` is not valid base64: {}", id, e)))?;
+            let cipher = XChaCha20Poly1305::new_from_slice(&bytes)
+                .map_err(|_| DbError::Encryption(format!("key `{}` must be 32 bytes, got {}", id, bytes.len())))?;
+            if keys.iter().any(|(k, _)| k == id) {
+                return Err(DbError::Encryption(format!("key id `{}` is listed twice", id)));
+            }
+            keys.push((id.to_string(), cipher));
+        }
+        if keys.is_empty() {
+            return Err(DbError::Encryption("no encryption keys given".to_string()));
+        }
+        Ok(Self { keys })
+    }
+
+    /// Reads the keys from `DATABASE_ENCRYPTION_KEYS`.
+    pub fn from_env(env: &dyn EnvConfig) -> Result<Self, DbError> {
+        let spec = env
+            .get_var(ENCRYPTION_KEYS_VAR)
+            .map_err(|e| DbError::Encryption(format!("{}: {}", ENCRYPTION_KEYS_VAR, e)))?;
+        Self::parse(&spec)
+    }
+
+    /// A new random key, base64-encoded for `DATABASE_ENCRYPTION_KEYS`.
+    pub fn generate_key() -> String {
+        STANDARD.encode(XChaCha20Poly1305::generate_key(&mut OsRng))
+    }
+
+    /// The id of the key new values are encrypted with.
+    pub fn current_key_id(&self) -> &str {
+        &self.keys[0].0
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, DbError> {
+        let (id, cipher) = &self.keys[0];
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| DbError::Encryption("encryption failed".to_string()))?;
+        let mut payload = nonce.to_vec();
+        payload.extend(ciphertext);
+        Ok(format!("{}{}:{}", PREFIX, id, STANDARD.encode(payload)))
+    }
+
+    /// Decrypts a stored value. Values without the `enc:v1:` prefix were
+    /// written before the column was encrypted and are returned as they are
+    /// until `montrs db rotate-keys` encrypts them.
+    pub fn decrypt(&self, stored: &str) -> Result<String, DbError> {
+        let Some(rest) = stored.strip_prefix(PREFIX) else { return Ok(stored.to_string()) };
+        let (id, data) = rest
+            .split_once(':')
+            .ok_or_else(|| DbError::Encryption("malformed encrypted value".to_string()))?;
+        let (_, cipher) = self
+            .keys
+            .iter()
+            .find(|(k, _)| k == id)
+            .ok_or_else(|| DbError::Encryption(format!("value is encrypted with unknown key `{}`", id)))?;
+        let payload = STANDARD
+            .decode(data)
+            .map_err(|_| DbError::Encryption("malformed encrypted value".to_string()))?;
+        if payload.len() < NONCE_LEN {
+            return Err(DbError::Encryption("malformed encrypted value".to_string()));
+        }
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let plaintext = cipher
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| DbError::Encryption(format!("value does not decrypt with key `{}`", id)))?;
+        String::from_utf8(plaintext).map_err(|_| DbError::Encryption("decrypted value is not UTF-8".to_string()))
+    }
+
+    /// The id of the key `stored` was encrypted with, or `None` for plaintext.
+    pub fn key_id(stored: &str) -> Option<&str> {
+        stored.strip_prefix(PREFIX)?.split_once(':').map(|(id, _)| id)
+    }
+
+    pub fn is_encrypted(stored: &str) -> bool {
+        Self::key_id(stored).is_some()
+    }
+}
+
+/// A field type that can be stored encrypted.
+pub trait EncryptedField {
+    fn encrypt_with(&mut self, cipher: &FieldCipher) -> Result<(), DbError>;
+    fn decrypt_with(&mut self, cipher: &FieldCipher) -> Result<(), DbError>;
+}
+
+impl EncryptedField for String {
+    fn encrypt_with(&mut self, cipher: &FieldCipher) -> Result<(), DbError> {
+        if !FieldCipher::is_encrypted(self) {
+            *self = cipher.encrypt(self)?;
+        }
+        Ok(())
+    }
+
+    fn decrypt_with(&mut self, cipher: &FieldCipher) -> Result<(), DbError> {
+        *self = cipher.decrypt(self)?;
+        Ok(())
+    }
+}
+
+impl<T: EncryptedField> EncryptedField for Option<T> {
+    fn encrypt_with(&mut self, cipher: &FieldCipher) -> Result<(), DbError> {
+        self.as_mut().map_or(Ok(()), |v| v.encrypt_with(cipher))
+    }
+
+    fn decrypt_with(&mut self, cipher: &FieldCipher) -> Result<(), DbError> {
+        self.as_mut().map_or(Ok(()), |v| v.decrypt_with(cipher))
+    }
+}
+
+/// A row type with encrypted columns, usually from `#[derive(EncryptedModel)]`.
+pub trait EncryptedModel {
+    /// The table the model is stored in.
+    const TABLE: &'static str;
+    /// Column names of the `#[orm(encrypted)]` fields.
+    const ENCRYPTED_COLUMNS: &'static [&'static str];
+
+    /// Encrypts the marked fields in place, before binding them to a write.
+    fn encrypt_fields(&mut self, cipher: &FieldCipher) -> Result<(), DbError>;
+    /// Decrypts the marked fields in place, after reading a row.
+    fn decrypt_fields(&mut self, cipher: &FieldCipher) -> Result<(), DbError>;
+}
+
+/// A backend that decrypts models it reads and refuses queries that would
+/// compare encrypted columns.
+pub struct EncryptedBackend<B> {
+    inner: B,
+    cipher: Arc<FieldCipher>,
+    /// `(table, encrypted columns)` of every registered model.
+    columns: Vec<(&'static str, &'static [&'static str])>,
+}
+
+impl<B: Clone> Clone for EncryptedBackend<B> {
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone(), cipher: self.cipher.clone(), columns: self.columns.clone() }
+    }
+}
+
+impl<B: DbBackend> EncryptedBackend<B> {
+    pub fn new(inner: B, cipher: FieldCipher) -> Self {
+        Self { inner, cipher: Arc::new(cipher), columns: Vec::new() }
+    }
+
+    /// Guards the encrypted columns of `M` against filtering.
+    pub fn register<M: EncryptedModel>(mut self) -> Self {
+        self.columns.push((M::TABLE, M::ENCRYPTED_COLUMNS));
+        self
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    pub fn cipher(&self) -> &FieldCipher {
+        &self.cipher
+    }
+
+    /// Encrypts the marked fields of `model` so it can be written.
+    pub fn encrypt<M: EncryptedModel>(&self, model: &mut M) -> Result<(), DbError> {
+        model.encrypt_fields(&self.cipher)
+    }
+
+    /// Runs a query and decrypts the marked fields of every row.
+    pub async fn fetch<M: FromRow + EncryptedModel>(&self, sql: &str, params: &[&dyn ToSql]) -> Result<Vec<M>, DbError> {
+        let mut rows: Vec<M> = self.query(sql, params).await?;
+        for row in &mut rows {
+            row.decrypt_fields(&self.cipher)?;
+        }
+        Ok(rows)
+    }
+
+    /// Fails with `DbError::EncryptedFilter` when `sql` compares, joins,
+    /// groups or sorts on an encrypted column of a table it mentions.
+    pub fn guard(&self, sql: &str) -> Result<(), DbError> {
+        guard(&self.columns, sql)
+    }
+}
+
+fn guard(columns: &[(&'static str, &'static [&'static str])], sql: &str) -> Result<(), DbError> {
+    let tokens = tokenize(sql);
+    let mentioned: Vec<_> = columns
+        .iter()
+        .filter(|(table, _)| tokens.iter().any(|t| t.ident().is_some_and(|w| w.eq_ignore_ascii_case(table))))
+        .collect();
+    if mentioned.is_empty() {
+        return Ok(());
+    }
+    let mut filtering = false;
+    for (i, token) in tokens.iter().enumerate() {
+        let Some(word) = token.ident() else { continue };
+        if let TokenKind::Word(w) = &token.kind {
+            match w.as_str() {
+                "where" | "on" | "having" => {
+                    filtering = true;
+                    continue;
+                }
+                "by" if i > 0 && (tokens[i - 1].is_word("group") || tokens[i - 1].is_word("order")) => {
+                    filtering = true;
+                    continue;
+                }
+                "select" | "from" | "join" | "set" | "values" | "returning" | "limit" | "union" | "into" => {
+                    filtering = false;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        if !filtering || tokens.get(i + 1).is_some_and(|t| t.is_punct('.') || t.is_punct('(')) {
+            continue;
+        }
+        if let Some((table, _)) = mentioned.iter().find(|(_, cols)| cols.iter().any(|c| c.eq_ignore_ascii_case(word))) {
+            return Err(DbError::EncryptedFilter(format!("{}.{}", table, word)));
+        }
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl<B: DbBackend> DbBackend for EncryptedBackend<B> {
+    async fn execute(&self, sql: &str, params: &[&dyn ToSql]) -> Result<usize, DbError> {
+        self.guard(sql)?;
+        self.inner.execute(sql, params).await
+    }
+
+    async fn query<T: FromRow>(&self, sql: &str, params: &[&dyn ToSql]) -> Result<Vec<T>, DbError> {
+        self.guard(sql)?;
+        self.inner.query(sql, params).await
+    }
+
+    async fn execute_batch(&self, sql: &str, batch: &[&[&dyn ToSql]]) -> Result<usize, DbError> {
+        self.guard(sql)?;
+        self.inner.execute_batch(sql, batch).await
+    }
+
+    /// A transaction on the inner backend, guarded like the backend itself.
+    #[cfg(any(feature = "sqlite", feature = "postgres"))]
+    async fn transaction(&self) -> Result<Transaction, DbError> {
+        let columns = self.columns.clone();
+        Ok(self.inner.transaction().await?.with_guard(Arc::new(move |sql: &str| guard(&columns, sql))))
+    }
+
+    fn dialect(&self) -> Dialect {
+        self.inner.dialect()
+    }
+}
+
+/// A `(key, value)` pair read for re-encryption.
+struct StoredValue {
+    key: String,
+    value: Option<String>,
+}
+
+impl FromRow for StoredValue {
+    #[cfg(feature = "sqlite")]
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self { key: row.get(0)?, value: row.get(1)? })
+    }
+
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        Ok(Self {
+            key: row.try_get(0).map_err(|e| DbError::Query(e.to_string()))?,
+            value: row.try_get(1).map_err(|e| DbError::Query(e.to_string()))?,
+        })
+    }
+}
+
+/// Re-encrypts `table.column` with the current key: values under an older
+/// key and values still in plaintext are rewritten, one row at a time, keyed
+/// by `key_column`. Returns the number of rows rewritten.
+pub async fn rotate_column<B: DbBackend>(
+    db: &B,
+    cipher: &FieldCipher,
+    table: &str,
+    key_column: &str,
+    column: &str,
+) -> Result<usize, DbError> {
+    let mut rewritten = 0;
+    for (key, stored) in stale_values(db, cipher, table, key_column, column).await? {
+        let value = cipher.encrypt(&cipher.decrypt(&stored)?)?;
+        db.execute(
+            &format!(
+                "UPDATE {} SET {} = {} WHERE CAST({} AS TEXT) = {}",
+                table,
+                column,
+                literal(&value),
+                key_column,
+                literal(&key)
+            ),
+            &[],
+        )
+        .await?;
+        rewritten += 1;
+    }
+    Ok(rewritten)
+}
+
+/// The `key_column` values of the rows [`rotate_column`] would rewrite, for
+/// previewing a rotation.
+pub async fn stale_rows<B: DbBackend>(
+    db: &B,
+    cipher: &FieldCipher,
+    table: &str,
+    key_column: &str,
+    column: &str,
+) -> Result<Vec<String>, DbError> {
+    Ok(stale_values(db, cipher, table, key_column, column).await?.into_iter().map(|(key, _)| key).collect())
+}
+
+/// `(key, stored value)` of the rows not under the current key.
+async fn stale_values<B: DbBackend>(
+    db: &B,
+    cipher: &FieldCipher,
+    table: &str,
+    key_column: &str,
+    column: &str,
+) -> Result<Vec<(String, String)>, DbError> {
+    let rows: Vec<StoredValue> = db
+        .query(&format!("SELECT CAST({} AS TEXT), {} FROM {}", key_column, column, table), &[])
+        .await?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| Some((row.key, row.value?)))
+        .filter(|(_, stored)| FieldCipher::key_id(stored) != Some(cipher.current_key_id()))
+        .collect())
+}
+
+fn literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
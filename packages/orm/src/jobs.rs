@@ -0,0 +1,109 @@
+//! Delayed jobs stored in the application database.
+//! `JobTable` is a `JobStore` that keeps one row per job, so jobs scheduled
+//! with `RouteContext::defer` survive restarts and deploys. The status and due
+//! time are plain columns, indexed for the worker's due query; the full
+//! record, input included, is a JSON text column. The due time is unix
+//! milliseconds.
+
+use crate::{DbBackend, DbError, FromRow, Insert, ToSql};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use montrs_core::jobs::{JobRecord, JobStatus, JobStore};
+
+/// The table rows are written to unless [`JobTable::with_table`] says otherwise.
+pub const DEFAULT_TABLE: &str = "montrs_jobs";
+
+const COLUMNS: [&str; 6] = ["id", "route", "status", "run_at", "updated_at", "record"];
+
+/// Keeps delayed jobs in a table.
+///
+/// ```rust,ignore
+/// let table = JobTable::new(db.clone());
+/// table.create_table().await?;
+/// let jobs = Jobs::new(table);
+/// ```
+pub struct JobTable<B: DbBackend> {
+    db: B,
+    table: String,
+}
+
+impl<B: DbBackend> JobTable<B> {
+    pub fn new(db: B) -> Self {
+        Self { db, table: DEFAULT_TABLE.to_string() }
+    }
+
+    /// Writes to `table` instead of [`DEFAULT_TABLE`].
+    pub fn with_table(mut self, table: impl Into<String>) -> Self {
+        self.table = table.into();
+        self
+    }
+
+    /// Creates the table and its due index if they do not exist yet. The
+    /// column types work on both SQLite and PostgreSQL.
+    pub async fn create_table(&self) -> Result<(), DbError> {
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {} (\
+             id TEXT PRIMARY KEY, route TEXT NOT NULL, status TEXT NOT NULL, run_at BIGINT NOT NULL, \
+             updated_at TEXT NOT NULL, record TEXT NOT NULL)",
+            self.table
+        );
+        self.db.execute(&sql, &[]).await?;
+        let index = format!("CREATE INDEX IF NOT EXISTS {0}_status_run_at ON {0} (status, run_at)", self.table);
+        self.db.execute(&index, &[]).await?;
+        Ok(())
+    }
+
+    async fn records(&self, filter: &str, params: &[&dyn ToSql]) -> Result<Vec<JobRecord>, DbError> {
+        let sql = format!("SELECT record FROM {} WHERE {}", self.table, filter);
+        let rows: Vec<RecordRow> = self.db.query(&sql, params).await?;
+        rows.into_iter()
+            .map(|row| serde_json::from_str(&row.0).map_err(|e| DbError::Query(format!("invalid job record: {}", e))))
+            .collect()
+    }
+}
+
+struct RecordRow(String);
+
+impl FromRow for RecordRow {
+    #[cfg(feature = "sqlite")]
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self(row.get(0)?))
+    }
+
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        Ok(Self(row.try_get(0).map_err(|e| DbError::Query(e.to_string()))?))
+    }
+}
+
+#[async_trait]
+impl<B: DbBackend> JobStore for JobTable<B> {
+    async fn save(&self, record: &JobRecord) -> anyhow::Result<()> {
+        let status = record.status.as_str().to_string();
+        let (run_at, updated_at) = (record.run_at.timestamp_millis(), record.updated_at.to_rfc3339());
+        let json = serde_json::to_string(record)?;
+        let row: [&dyn ToSql; 6] = [&record.id, &record.route, &status, &run_at, &updated_at, &json];
+        Insert::into(&self.table, &COLUMNS)
+            .on_conflict_update(&["id"], &COLUMNS[1..])
+            .execute(&self.db, &[&row])
+            .await?;
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> anyhow::Result<Option<JobRecord>> {
+        let filter = format!("id = {}", self.db.dialect().placeholder(1));
+        Ok(self.records(&filter, &[&id]).await?.pop())
+    }
+
+    async fn due(&self, now: DateTime<Utc>, limit: usize) -> anyhow::Result<Vec<JobRecord>> {
+        let dialect = self.db.dialect();
+        let filter = format!(
+            "status = {} AND run_at <= {} ORDER BY run_at LIMIT {}",
+            dialect.placeholder(1),
+            dialect.placeholder(2),
+            limit
+        );
+        let (scheduled, now) = (JobStatus::Scheduled.as_str(), now.timestamp_millis());
+        Ok(self.records(&filter, &[&scheduled, &now]).await?)
+    }
+}
@@ -0,0 +1,192 @@
+//! Typed JSON columns.
+//! `Json<T>` stores a serializable value in a JSON column: `TEXT` on SQLite,
+//! `JSON` or `JSONB` on Postgres. Values built with `Json::new` are checked
+//! with their `Validate` implementation, e.g. from `#[derive(Schema)]`, when
+//! they are bound to a statement, so an invalid payload never reaches the
+//! database. `json_path` reads a field inside a JSON column in SQL.
+
+use crate::{DbError, Dialect, ToSql};
+use montrs_core::{Validate, ValidationError};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+/// Validates a payload before it is written.
+type Check<T> = fn(&T) -> Result<(), Vec<ValidationError>>;
+
+/// A value stored as JSON.
+#[derive(Clone)]
+pub struct Json<T> {
+    value: T,
+    check: Option<Check<T>>,
+}
+
+impl<T: Validate> Json<T> {
+    /// Wraps `value`, validating it whenever it is written.
+    pub fn new(value: T) -> Self {
+        Self { value, check: Some(T::validate) }
+    }
+}
+
+impl<T> Json<T> {
+    /// Wraps `value` without validation, for types that have no `Validate`.
+    pub fn unchecked(value: T) -> Self {
+        Self { value, check: None }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: Serialize> Json<T> {
+    /// Validates the value, if it was built with `Json::new`, and serializes it.
+    pub fn to_json(&self) -> Result<String, DbError> {
+        if let Some(check) = self.check {
+            check(&self.value).map_err(|errors| {
+                let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+                DbError::Json(format!("payload is invalid: {}", errors.join("; ")))
+            })?;
+        }
+        serde_json::to_string(&self.value).map_err(|e| DbError::Json(e.to_string()))
+    }
+}
+
+impl<T: DeserializeOwned> Json<T> {
+    /// Parses a stored JSON document. The result is not validated again.
+    pub fn from_json(text: &str) -> Result<Self, DbError> {
+        serde_json::from_str(text).map(Self::unchecked).map_err(|e| DbError::Json(e.to_string()))
+    }
+}
+
+impl<T> Deref for Json<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Json<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: PartialEq> PartialEq for Json<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Json<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Json").field(&self.value).finish()
+    }
+}
+
+impl<T: Serialize + Send + Sync + fmt::Debug> ToSql for Json<T> {
+    #[cfg(feature = "sqlite")]
+    fn as_rusqlite(&self) -> &dyn rusqlite::ToSql {
+        self
+    }
+
+    #[cfg(feature = "postgres")]
+    fn as_postgres(&self) -> &(dyn tokio_postgres::types::ToSql + Sync) {
+        self
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl<T: Serialize> rusqlite::ToSql for Json<T> {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        let json = self.to_json().map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        Ok(rusqlite::types::ToSqlOutput::from(json))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl<T: DeserializeOwned> rusqlite::types::FromSql for Json<T> {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        let text = value.as_str()?;
+        Self::from_json(text).map_err(|e| rusqlite::types::FromSqlError::Other(Box::new(e)))
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl<T: Serialize + fmt::Debug> tokio_postgres::types::ToSql for Json<T> {
+    fn to_sql(
+        &self,
+        ty: &tokio_postgres::types::Type,
+        out: &mut bytes::BytesMut,
+    ) -> Result<tokio_postgres::types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        let json = self.to_json()?;
+        if *ty == tokio_postgres::types::Type::JSONB {
+            // The binary JSONB format is a version byte followed by the text.
+            out.extend_from_slice(&[1]);
+        }
+        out.extend_from_slice(json.as_bytes());
+        Ok(tokio_postgres::types::IsNull::No)
+    }
+
+    fn accepts(ty: &tokio_postgres::types::Type) -> bool {
+        use tokio_postgres::types::Type;
+        matches!(*ty, Type::JSON | Type::JSONB | Type::TEXT | Type::VARCHAR)
+    }
+
+    tokio_postgres::types::to_sql_checked!();
+}
+
+#[cfg(feature = "postgres")]
+impl<'a, T: DeserializeOwned> tokio_postgres::types::FromSql<'a> for Json<T> {
+    fn from_sql(
+        ty: &tokio_postgres::types::Type,
+        raw: &'a [u8],
+    ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        let raw = match (ty, raw.split_first()) {
+            (&tokio_postgres::types::Type::JSONB, Some((1, rest))) => rest,
+            (&tokio_postgres::types::Type::JSONB, _) => return Err("unsupported JSONB version".into()),
+            _ => raw,
+        };
+        let text = std::str::from_utf8(raw)?;
+        Ok(Self::from_json(text)?)
+    }
+
+    fn accepts(ty: &tokio_postgres::types::Type) -> bool {
+        use tokio_postgres::types::Type;
+        matches!(*ty, Type::JSON | Type::JSONB | Type::TEXT | Type::VARCHAR)
+    }
+}
+
+/// SQL reading the text at `path` inside the JSON column `column`, e.g.
+/// `payload -> 'address' ->> 'city'` on Postgres. Segments made of digits
+/// index arrays.
+pub fn json_path(dialect: Dialect, column: &str, path: &[&str]) -> String {
+    let is_index = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    match dialect {
+        Dialect::Postgres => {
+            let mut sql = column.to_string();
+            for (i, segment) in path.iter().enumerate() {
+                sql.push_str(if i + 1 == path.len() { " ->> " } else { " -> " });
+                if is_index(segment) {
+                    sql.push_str(segment);
+                } else {
+                    sql.push_str(&format!("'{}'", segment.replace('\'', "''")));
+                }
+            }
+            sql
+        }
+        Dialect::Sqlite | Dialect::Generic => {
+            let mut json_path = String::from("$");
+            for segment in path {
+                if is_index(segment) {
+                    json_path.push_str(&format!("[{}]", segment));
+                } else {
+                    json_path.push_str(&format!(".\"{}\"", segment.replace('"', "\\\"")));
+                }
+            }
+            format!("json_extract({}, '{}')", column, json_path.replace('\'', "''"))
+        }
+    }
+}
@@ -4,12 +4,81 @@
 //!
 //! // @agent-tool: name="db_query" desc="Executes a SQL query on the configured database backend."
 
+pub mod analytics;
+pub mod bulk;
+pub mod check;
+pub mod drift;
+pub mod encryption;
+#[cfg(feature = "jobs")]
+pub mod jobs;
+pub mod json;
+#[cfg(feature = "lifecycle")]
+pub mod lifecycle;
+#[cfg(feature = "notifications")]
+pub mod notification;
+#[cfg(feature = "payments")]
+pub mod payment;
+#[cfg(feature = "sqlite")]
+mod pool;
+pub mod replica;
+pub mod resilience;
+pub mod schema;
+#[cfg(feature = "seed")]
+pub mod seed;
+mod sql;
+pub mod sync;
+pub mod trace;
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+pub mod transaction;
+pub mod transfer;
+mod types;
+#[cfg(feature = "webhooks")]
+pub mod webhook;
+pub mod workflow;
+
+pub use analytics::RouteUsageTable;
+pub use bulk::{Dialect, Insert};
+pub use check::{IssueKind, QueryIssue, check_query};
+pub use drift::{Drift, SchemaDrift};
+pub use encryption::{EncryptedBackend, EncryptedField, EncryptedModel, FieldCipher};
+#[cfg(feature = "jobs")]
+pub use jobs::JobTable;
+pub use json::{Json, json_path};
+#[cfg(feature = "lifecycle")]
+pub use lifecycle::{PersonalDataTable, Purged, Retained, Retention, RetentionPolicy, TimestampFormat};
+#[cfg(feature = "notifications")]
+pub use notification::NotificationTables;
+#[cfg(feature = "payments")]
+pub use payment::PaymentTables;
+pub use replica::{ReplicaConfig, ReplicatedBackend};
+pub use resilience::{DbPoolConfig, RetryPolicy, RetryingBackend};
+pub use schema::{ColumnSchema, SchemaSnapshot, TableSchema};
+pub use trace::TracedBackend;
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+pub use transaction::Transaction;
+#[cfg(feature = "seed")]
+pub use seed::{SeedTable, SeedValue, Seeds};
+pub use sync::SyncTable;
+#[cfg(feature = "webhooks")]
+pub use webhook::WebhookTables;
+pub use workflow::WorkflowTable;
+
+/// This crate's version and the `montrs-core` it needs; see [`montrs_core::compat`].
+pub const CRATE_VERSION: montrs_core::CrateVersion = montrs_core::crate_version!();
+
 use async_trait::async_trait;
 use montrs_core::AgentError;
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+use montrs_core::profile::DbTimer;
 #[cfg(feature = "postgres")]
-use deadpool_postgres::{Config, Pool, Runtime};
+use deadpool_postgres::{Config, ManagerConfig, Pool, PoolConfig, RecyclingMethod, Runtime};
+/// Connection settings for [`PostgresBackend`].
+#[cfg(feature = "postgres")]
+pub use deadpool_postgres::Config as PostgresConfig;
 #[cfg(feature = "sqlite")]
 use rusqlite::Connection;
+#[cfg(feature = "sqlite")]
+use std::sync::Arc;
 use thiserror::Error;
 #[cfg(feature = "postgres")]
 use tokio_postgres::NoTls;
@@ -23,6 +92,18 @@ pub enum DbError {
     Query(String),
     #[error("Migration error: {0}")]
     Migration(String),
+    #[error("Encryption error: {0}")]
+    Encryption(String),
+    #[error("Cannot filter on encrypted column {0}")]
+    EncryptedFilter(String),
+    #[error("JSON column error: {0}")]
+    Json(String),
+    #[error("Type conversion error: {0}")]
+    Type(String),
+    #[error("Seed data error: {0}")]
+    Seed(String),
+    #[error("Data export/import error: {0}")]
+    Transfer(String),
 }
 
 impl AgentError for DbError {
@@ -31,6 +112,12 @@ impl AgentError for DbError {
             DbError::Connection(_) => "DB_CONNECTION",
             DbError::Query(_) => "DB_QUERY",
             DbError::Migration(_) => "DB_MIGRATION",
+            DbError::Encryption(_) => "DB_ENCRYPTION",
+            DbError::EncryptedFilter(_) => "DB_ENCRYPTED_FILTER",
+            DbError::Json(_) => "DB_JSON",
+            DbError::Type(_) => "DB_TYPE",
+            DbError::Seed(_) => "DB_SEED",
+            DbError::Transfer(_) => "DB_TRANSFER",
         }
     }
 
@@ -39,6 +126,15 @@ impl AgentError for DbError {
             DbError::Connection(e) => format!("Failed to establish a connection to the database: {}.", e),
             DbError::Query(e) => format!("An error occurred while executing a SQL query: {}.", e),
             DbError::Migration(e) => format!("Database migration failed: {}.", e),
+            DbError::Encryption(e) => format!("A column value could not be encrypted or decrypted: {}.", e),
+            DbError::EncryptedFilter(c) => format!(
+                "The query compares, joins, groups or sorts on {}, which is encrypted. Encrypted values are randomized, so the database cannot compare them.",
+                c
+            ),
+            DbError::Json(e) => format!("A JSON column value could not be validated, serialized or parsed: {}.", e),
+            DbError::Type(e) => format!("A value could not be converted between Rust and the database: {}.", e),
+            DbError::Seed(e) => format!("Seed data could not be read or inserted: {}.", e),
+            DbError::Transfer(e) => format!("A table could not be exported or imported: {}.", e),
         }
     }
 
@@ -59,6 +155,33 @@ impl AgentError for DbError {
                 "Ensure the database user has sufficient permissions to modify the schema.".to_string(),
                 "Verify that the migration scripts are compatible with the target database backend.".to_string(),
             ],
+            DbError::Encryption(_) => vec![
+                "Set DATABASE_ENCRYPTION_KEYS to id:base64key pairs, current key first, e.g. with `montrs db rotate-keys --generate`.".to_string(),
+                "Keep old keys in the list until `montrs db rotate-keys` has re-encrypted every row.".to_string(),
+            ],
+            DbError::EncryptedFilter(_) => vec![
+                "Filter on another column, such as the primary key, and compare the decrypted value in Rust.".to_string(),
+                "For lookups, store a keyed hash of the value (a blind index) in a separate plain column and filter on that.".to_string(),
+            ],
+            DbError::Json(_) => vec![
+                "Fix the fields the validation errors name before writing the value.".to_string(),
+                "Check that the stored document matches the Rust type it is read into; add #[serde(default)] for fields older rows lack.".to_string(),
+            ],
+            DbError::Type(_) => vec![
+                "Match the Rust type to the column type, e.g. i64 for BIGINT, f64 for DOUBLE PRECISION, Vec<u8> for BYTEA/BLOB.".to_string(),
+                "Enable the orm's uuid, chrono or decimal feature for those types.".to_string(),
+                "SQLite has no DECIMAL type: store decimals as TEXT or as integer minor units.".to_string(),
+            ],
+            DbError::Seed(_) => vec![
+                "Give each seed file a `table` and a `[[rows]]` list whose keys are column names.".to_string(),
+                "Create the tables (run the migrations) before applying seeds.".to_string(),
+                "Number the files so referenced tables are seeded first, e.g. 01_users.toml before 02_todos.toml.".to_string(),
+            ],
+            DbError::Transfer(_) => vec![
+                "Give tables without a primary key the columns to page and match rows by.".to_string(),
+                "Check that each line of a JSON lines file is an object, and that CSV rows have as many cells as the header.".to_string(),
+                "Import referenced tables before the tables that reference them.".to_string(),
+            ],
         }
     }
 
@@ -73,32 +196,9 @@ pub trait ToSql: Send + Sync {
     /// Returns a reference that can be used by rusqlite.
     #[cfg(feature = "sqlite")]
     fn as_rusqlite(&self) -> &dyn rusqlite::ToSql;
-}
-
-// Implementations for common types to be used as query parameters.
-impl ToSql for String {
-    #[cfg(feature = "sqlite")]
-    fn as_rusqlite(&self) -> &dyn rusqlite::ToSql {
-        self
-    }
-}
-impl ToSql for i32 {
-    #[cfg(feature = "sqlite")]
-    fn as_rusqlite(&self) -> &dyn rusqlite::ToSql {
-        self
-    }
-}
-impl ToSql for bool {
-    #[cfg(feature = "sqlite")]
-    fn as_rusqlite(&self) -> &dyn rusqlite::ToSql {
-        self
-    }
-}
-impl ToSql for &str {
-    #[cfg(feature = "sqlite")]
-    fn as_rusqlite(&self) -> &dyn rusqlite::ToSql {
-        self
-    }
+    /// Returns a reference that can be used by tokio-postgres.
+    #[cfg(feature = "postgres")]
+    fn as_postgres(&self) -> &(dyn tokio_postgres::types::ToSql + Sync);
 }
 
 /// Trait for mapping database rows to Rust types.
@@ -120,63 +220,189 @@ pub trait DbBackend: Send + Sync + 'static {
     async fn execute(&self, sql: &str, params: &[&dyn ToSql]) -> Result<usize, DbError>;
     /// Executes a query SQL statement and returns a vector of results.
     async fn query<T: FromRow>(&self, sql: &str, params: &[&dyn ToSql]) -> Result<Vec<T>, DbError>;
+
+    /// Executes `sql` once per parameter set and returns the total number of
+    /// rows affected. Backends may run the batch in a single transaction.
+    async fn execute_batch(&self, sql: &str, batch: &[&[&dyn ToSql]]) -> Result<usize, DbError> {
+        let mut affected = 0;
+        for params in batch {
+            affected += self.execute(sql, params).await?;
+        }
+        Ok(affected)
+    }
+
+    /// Starts a transaction on a connection of its own. Backends without
+    /// transactions answer `DB_QUERY`.
+    #[cfg(any(feature = "sqlite", feature = "postgres"))]
+    async fn transaction(&self) -> Result<Transaction, DbError> {
+        Err(DbError::Query("this backend does not support transactions".to_string()))
+    }
+
+    /// Checks that the database answers, with `SELECT 1`.
+    async fn ping(&self) -> Result<(), DbError> {
+        self.query::<Ping>("SELECT 1", &[]).await.map(|_| ())
+    }
+
+    /// The SQL dialect statements built by [`Insert`] use.
+    fn dialect(&self) -> Dialect {
+        Dialect::Generic
+    }
+}
+
+/// A row read for [`DbBackend::ping`], whose columns are ignored.
+struct Ping;
+
+impl FromRow for Ping {
+    #[cfg(feature = "sqlite")]
+    fn from_row_sqlite(_row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Ping)
+    }
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(_row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        Ok(Ping)
+    }
 }
 
 /// SQLite-specific database backend implementation.
-/// Uses synchronous rusqlite under the hood with internal locking.
+/// Runs synchronous rusqlite on a small pool of connections, so concurrent
+/// loaders do not wait on one lock: a file database gets up to four
+/// connections in WAL mode, an in-memory database exactly one. On a
+/// multi-threaded runtime each statement runs in `block_in_place`, so it does
+/// not stall the other tasks of its worker. An open [`Transaction`] keeps its
+/// connection until it ends. Clones share the pool.
 #[cfg(feature = "sqlite")]
 #[derive(Clone)]
 pub struct SqliteBackend {
-    conn: Arc<Mutex<Connection>>,
+    pool: Arc<pool::SqlitePool>,
 }
 
 #[cfg(feature = "sqlite")]
 impl SqliteBackend {
     /// Creates a new SqliteBackend connecting to the specified path (or :memory:).
     pub fn new(path: &str) -> Result<Self, DbError> {
-        let conn = if path == ":memory:" {
-            Connection::open_in_memory()
-        } else {
-            Connection::open(path)
+        Ok(Self { pool: Arc::new(pool::SqlitePool::open(path)?) })
+    }
+
+    /// Allows up to `size` connections to a file database, e.g. one per
+    /// worker thread. Writes still run one at a time.
+    pub fn with_pool_size(mut self, size: usize) -> Self {
+        match Arc::get_mut(&mut self.pool) {
+            Some(pool) => pool.resize(size),
+            None => tracing::warn!("SQLite pool size changed after the backend was cloned; ignored"),
         }
-        .map_err(|e| DbError::Connection(e.to_string()))?;
+        self
+    }
 
-        Ok(Self {
-            conn: Arc::new(Mutex::new(conn)),
-        })
+    /// Applies the size, acquire timeout and health check interval of
+    /// `config`. An in-memory database keeps its one connection unchecked.
+    pub fn with_pool_config(mut self, config: &DbPoolConfig) -> Self {
+        match Arc::get_mut(&mut self.pool) {
+            Some(pool) => pool.configure(config),
+            None => tracing::warn!("SQLite pool settings changed after the backend was cloned; ignored"),
+        }
+        self
     }
+
+    /// The most connections the backend opens.
+    pub fn pool_size(&self) -> usize {
+        self.pool.size()
+    }
+
+    /// Reads the tables and columns of the live database.
+    pub async fn introspect(&self) -> Result<SchemaSnapshot, DbError> {
+        let conn = self.pool.get().await?;
+        pool::blocking(|| introspect_sqlite(&conn))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn introspect_sqlite(conn: &Connection) -> Result<SchemaSnapshot, DbError> {
+    let query_err = |e: rusqlite::Error| DbError::Query(e.to_string());
+    let mut stmt = conn
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'")
+        .map_err(query_err)?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(query_err)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(query_err)?;
+
+    let mut schema = SchemaSnapshot::default();
+    for name in names {
+        let mut stmt = conn
+            .prepare(&format!("PRAGMA table_info(\"{}\")", name.replace('"', "\"\"")))
+            .map_err(query_err)?;
+        let columns = stmt
+            .query_map([], |row| {
+                let primary_key = row.get::<_, i64>(5)? > 0;
+                Ok(ColumnSchema {
+                    name: row.get::<_, String>(1)?.to_lowercase(),
+                    sql_type: row.get::<_, String>(2)?.to_uppercase(),
+                    nullable: row.get::<_, i64>(3)? == 0 && !primary_key,
+                    primary_key,
+                })
+            })
+            .map_err(query_err)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(query_err)?;
+        schema.tables.insert(name.to_lowercase(), TableSchema { columns });
+    }
+    Ok(schema)
 }
 
 #[cfg(feature = "sqlite")]
 #[async_trait]
 impl DbBackend for SqliteBackend {
     async fn execute(&self, sql: &str, params: &[&dyn ToSql]) -> Result<usize, DbError> {
-        let conn = self.conn.lock().unwrap();
+        let _timer = DbTimer::start();
+        let conn = self.pool.get().await?;
         // Convert unified params to rusqlite-compatible params.
         let sqlite_params: Vec<&dyn rusqlite::ToSql> =
             params.iter().map(|p| p.as_rusqlite()).collect();
-        conn.execute(sql, rusqlite::params_from_iter(sqlite_params))
-            .map_err(|e| DbError::Query(e.to_string()))
+        pool::blocking(|| conn.execute(sql, rusqlite::params_from_iter(sqlite_params)))
+            .map_err(types::sqlite_error)
     }
 
     async fn query<T: FromRow>(&self, sql: &str, params: &[&dyn ToSql]) -> Result<Vec<T>, DbError> {
-        let conn = self.conn.lock().unwrap();
+        let _timer = DbTimer::start();
+        let conn = self.pool.get().await?;
         let sqlite_params: Vec<&dyn rusqlite::ToSql> =
             params.iter().map(|p| p.as_rusqlite()).collect();
-        let mut stmt = conn
-            .prepare(sql)
-            .map_err(|e| DbError::Query(e.to_string()))?;
-        let rows = stmt
-            .query_map(rusqlite::params_from_iter(sqlite_params), |row| {
-                T::from_row_sqlite(row)
-            })
-            .map_err(|e| DbError::Query(e.to_string()))?;
+        pool::blocking(|| {
+            let mut stmt = conn.prepare(sql)?;
+            let rows = stmt.query_map(rusqlite::params_from_iter(sqlite_params), |row| T::from_row_sqlite(row))?;
+            rows.collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(types::sqlite_error)
+    }
 
-        let mut results = Vec::new();
-        for row in rows {
-            results.push(row.map_err(|e| DbError::Query(e.to_string()))?);
-        }
-        Ok(results)
+    /// Runs the batch in one transaction with one prepared statement; any
+    /// failure rolls the whole batch back.
+    async fn execute_batch(&self, sql: &str, batch: &[&[&dyn ToSql]]) -> Result<usize, DbError> {
+        let _timer = DbTimer::start();
+        let mut conn = self.pool.get().await?;
+        pool::blocking(|| {
+            let tx = conn.transaction()?;
+            let mut affected = 0;
+            {
+                let mut stmt = tx.prepare(sql)?;
+                for params in batch {
+                    let sqlite_params: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_rusqlite()).collect();
+                    affected += stmt.execute(rusqlite::params_from_iter(sqlite_params))?;
+                }
+            }
+            tx.commit()?;
+            Ok(affected)
+        })
+        .map_err(types::sqlite_error)
+    }
+
+    async fn transaction(&self) -> Result<Transaction, DbError> {
+        Transaction::sqlite(self.pool.get().await?)
+    }
+
+    fn dialect(&self) -> Dialect {
+        Dialect::Sqlite
     }
 }
 
@@ -197,39 +423,96 @@ impl PostgresBackend {
             .map_err(|e| DbError::Connection(e.to_string()))?;
         Ok(Self { pool })
     }
+
+    /// [`PostgresBackend::new`] with the size and acquire timeout of `pool`.
+    /// With a health check interval, every connection is verified with a
+    /// query when it is taken from the pool.
+    pub fn with_pool_config(mut config: Config, pool: &DbPoolConfig) -> Result<Self, DbError> {
+        let mut pool_config = PoolConfig::new(pool.max_connections);
+        pool_config.timeouts.wait = Some(pool.acquire_timeout);
+        config.pool = Some(pool_config);
+        if pool.health_check_interval.is_some() {
+            config.manager = Some(ManagerConfig { recycling_method: RecyclingMethod::Verified });
+        }
+        Self::new(config)
+    }
+
+    /// Reads the tables and columns of the `public` schema of the live database.
+    pub async fn introspect(&self) -> Result<SchemaSnapshot, DbError> {
+        let client = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| DbError::Connection(e.to_string()))?;
+        let rows = client
+            .query(
+                "SELECT c.table_name::text, c.column_name::text, c.data_type::text, c.is_nullable = 'YES', \
+                 EXISTS (SELECT 1 FROM information_schema.table_constraints tc \
+                 JOIN information_schema.key_column_usage k \
+                 ON k.constraint_name = tc.constraint_name AND k.table_schema = tc.table_schema \
+                 WHERE tc.constraint_type = 'PRIMARY KEY' AND tc.table_schema = c.table_schema \
+                 AND tc.table_name = c.table_name AND k.column_name = c.column_name) \
+                 FROM information_schema.columns c \
+                 JOIN information_schema.tables t ON t.table_schema = c.table_schema AND t.table_name = c.table_name \
+                 WHERE c.table_schema = 'public' AND t.table_type = 'BASE TABLE' \
+                 ORDER BY c.table_name, c.ordinal_position",
+                &[],
+            )
+            .await
+            .map_err(|e| DbError::Query(e.to_string()))?;
+
+        let mut schema = SchemaSnapshot::default();
+        for row in rows {
+            let table: String = row.get(0);
+            let column: String = row.get(1);
+            let sql_type: String = row.get(2);
+            schema.tables.entry(table.to_lowercase()).or_default().columns.push(ColumnSchema {
+                name: column.to_lowercase(),
+                sql_type: sql_type.to_uppercase(),
+                nullable: row.get(3),
+                primary_key: row.get(4),
+            });
+        }
+        Ok(schema)
+    }
 }
 
 #[cfg(feature = "postgres")]
 #[async_trait]
 impl DbBackend for PostgresBackend {
-    async fn execute(&self, sql: &str, _params: &[&dyn ToSql]) -> Result<usize, DbError> {
+    async fn execute(&self, sql: &str, params: &[&dyn ToSql]) -> Result<usize, DbError> {
+        let _timer = DbTimer::start();
         let client = self
             .pool
             .get()
             .await
             .map_err(|e| DbError::Connection(e.to_string()))?;
-        // tokio-postgres requires different params handling, implementing skeleton
+        let pg_params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> =
+            params.iter().map(|p| p.as_postgres()).collect();
         client
-            .execute(sql, &[])
+            .execute(sql, &pg_params)
             .await
             .map(|n| n as usize)
-            .map_err(|e| DbError::Query(e.to_string()))
+            .map_err(types::postgres_error)
     }
 
     async fn query<T: FromRow>(
         &self,
         sql: &str,
-        _params: &[&dyn ToSql],
+        params: &[&dyn ToSql],
     ) -> Result<Vec<T>, DbError> {
+        let _timer = DbTimer::start();
         let client = self
             .pool
             .get()
             .await
             .map_err(|e| DbError::Connection(e.to_string()))?;
+        let pg_params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> =
+            params.iter().map(|p| p.as_postgres()).collect();
         let rows = client
-            .query(sql, &[])
+            .query(sql, &pg_params)
             .await
-            .map_err(|e| DbError::Query(e.to_string()))?;
+            .map_err(types::postgres_error)?;
 
         let mut results = Vec::new();
         for row in rows {
@@ -237,4 +520,17 @@ impl DbBackend for PostgresBackend {
         }
         Ok(results)
     }
+
+    async fn transaction(&self) -> Result<Transaction, DbError> {
+        let client = self
+            .pool
+            .get()
+            .await
+            .map_err(|e| DbError::Connection(e.to_string()))?;
+        Transaction::postgres(client).await
+    }
+
+    fn dialect(&self) -> Dialect {
+        Dialect::Postgres
+    }
 }
@@ -0,0 +1,207 @@
+//! Data lifecycle: retention periods and personal data erasure.
+//! A model declares how long its rows are kept with
+//! `#[derive(Retained)] #[orm(retain = "90d")]`; [`Retention`] deletes the
+//! rows older than that, in one pass or in a background loop.
+//! [`PersonalDataTable`] erases one user's rows from a table when
+//! `AppSpec::erase_user` is called, by deleting them or by clearing the
+//! columns that identify the user.
+
+use crate::{DbBackend, DbError};
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use montrs_core::lifecycle::PersonalData;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How a timestamp column is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampFormat {
+    /// RFC 3339 text in UTC, as `DateTime<Utc>::to_rfc3339` writes it.
+    /// Compared as text, to the second.
+    Rfc3339,
+    /// Seconds since the unix epoch.
+    Unix,
+    /// Milliseconds since the unix epoch.
+    UnixMs,
+}
+
+/// A model whose rows are deleted once they are older than `RETAIN`.
+///
+/// ```rust,ignore
+/// #[derive(Retained)]
+/// #[orm(table = "sessions", retain = "30d", retain_by = "last_seen")]
+/// struct Session { /* ... */ }
+/// ```
+pub trait Retained {
+    const TABLE: &'static str;
+    const RETAIN: Duration;
+    /// The timestamp column the age of a row is taken from.
+    const RETAIN_BY: &'static str;
+    const FORMAT: TimestampFormat;
+}
+
+/// How long rows of one table are kept.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub table: String,
+    /// The timestamp column the age of a row is taken from.
+    pub column: String,
+    pub retain: Duration,
+    pub format: TimestampFormat,
+}
+
+impl RetentionPolicy {
+    /// Keeps rows of `table` for `retain` after their RFC 3339 `column`.
+    pub fn new(table: impl Into<String>, column: impl Into<String>, retain: Duration) -> Self {
+        Self { table: table.into(), column: column.into(), retain, format: TimestampFormat::Rfc3339 }
+    }
+
+    /// The policy `T` declares.
+    pub fn of<T: Retained>() -> Self {
+        Self::new(T::TABLE, T::RETAIN_BY, T::RETAIN).with_format(T::FORMAT)
+    }
+
+    pub fn with_format(mut self, format: TimestampFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Rows with a timestamp before this are expired at `now`.
+    pub fn cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        chrono::Duration::from_std(self.retain)
+            .ok()
+            .and_then(|retain| now.checked_sub_signed(retain))
+            .unwrap_or(DateTime::<Utc>::MIN_UTC)
+    }
+
+    async fn purge<B: DbBackend>(&self, db: &B, now: DateTime<Utc>) -> Result<usize, DbError> {
+        let sql = format!("DELETE FROM {} WHERE {} < {}", self.table, self.column, db.dialect().placeholder(1));
+        let cutoff = self.cutoff(now);
+        match self.format {
+            TimestampFormat::Rfc3339 => {
+                let cutoff = cutoff.to_rfc3339_opts(SecondsFormat::Secs, true);
+                db.execute(&sql, &[&cutoff]).await
+            }
+            TimestampFormat::Unix => db.execute(&sql, &[&cutoff.timestamp()]).await,
+            TimestampFormat::UnixMs => db.execute(&sql, &[&cutoff.timestamp_millis()]).await,
+        }
+    }
+}
+
+/// The rows one retention pass deleted from a table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Purged {
+    pub table: String,
+    pub rows: usize,
+}
+
+/// Deletes expired rows according to a set of [`RetentionPolicy`]s.
+///
+/// ```rust,ignore
+/// let retention = Retention::new(db.clone()).policy::<Session>().policy::<AuditLog>();
+/// tokio::spawn(async move { retention.work().await });
+/// ```
+pub struct Retention<B: DbBackend> {
+    db: B,
+    policies: Vec<RetentionPolicy>,
+    interval: Duration,
+}
+
+impl<B: DbBackend> Retention<B> {
+    pub fn new(db: B) -> Self {
+        Self { db, policies: Vec::new(), interval: Duration::from_secs(3600) }
+    }
+
+    /// Enforces the policy `T` declares.
+    pub fn policy<T: Retained>(self) -> Self {
+        self.with_policy(RetentionPolicy::of::<T>())
+    }
+
+    pub fn with_policy(mut self, policy: RetentionPolicy) -> Self {
+        self.policies.push(policy);
+        self
+    }
+
+    /// How often [`Retention::work`] runs a pass. Defaults to an hour.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn policies(&self) -> &[RetentionPolicy] {
+        &self.policies
+    }
+
+    /// Deletes the rows expired at `now` from every table, stopping at the
+    /// first error.
+    pub async fn enforce(&self, now: DateTime<Utc>) -> Result<Vec<Purged>, DbError> {
+        let mut purged = Vec::new();
+        for policy in &self.policies {
+            let rows = policy.purge(&self.db, now).await?;
+            if rows > 0 {
+                tracing::info!(table = %policy.table, rows, "expired rows deleted");
+            }
+            purged.push(Purged { table: policy.table.clone(), rows });
+        }
+        Ok(purged)
+    }
+
+    /// Enforces the policies every interval until the future is dropped.
+    /// Errors are logged and retried on the next pass.
+    pub async fn work(&self) {
+        loop {
+            if let Err(e) = self.enforce(Utc::now()).await {
+                tracing::error!(error = %e, "failed to enforce retention policies");
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+}
+
+/// The rows of a table that belong to a user, erased by deleting them or by
+/// clearing the columns that identify the user.
+///
+/// ```rust,ignore
+/// let spec = AppSpec::new(config, env)
+///     .with_personal_data(Arc::new(PersonalDataTable::new(db.clone(), "orders", "user_id").anonymize(&["email", "address"])));
+/// ```
+pub struct PersonalDataTable<B: DbBackend> {
+    db: B,
+    table: String,
+    user_column: String,
+    anonymize: Vec<String>,
+}
+
+impl<B: DbBackend> PersonalDataTable<B> {
+    /// Deletes the rows of `table` whose `user_column` is the user's id.
+    pub fn new(db: B, table: impl Into<String>, user_column: impl Into<String>) -> Self {
+        Self { db, table: table.into(), user_column: user_column.into(), anonymize: Vec::new() }
+    }
+
+    /// Keeps the rows, e.g. orders needed for accounting, and sets `columns`
+    /// and the user column to NULL instead. The columns must be nullable.
+    pub fn anonymize(mut self, columns: &[&str]) -> Self {
+        self.anonymize = columns.iter().map(|c| c.to_string()).collect();
+        self
+    }
+}
+
+#[async_trait]
+impl<B: DbBackend> PersonalData for PersonalDataTable<B> {
+    fn category(&self) -> String {
+        self.table.clone()
+    }
+
+    async fn erase(&self, user_id: &str) -> anyhow::Result<u64> {
+        let filter = format!("{} = {}", self.user_column, self.db.dialect().placeholder(1));
+        let sql = if self.anonymize.is_empty() {
+            format!("DELETE FROM {} WHERE {}", self.table, filter)
+        } else {
+            let columns = self.anonymize.iter().chain(std::iter::once(&self.user_column));
+            let set = columns.map(|c| format!("{} = NULL", c)).collect::<Vec<_>>().join(", ");
+            format!("UPDATE {} SET {} WHERE {}", self.table, set, filter)
+        };
+        Ok(self.db.execute(&sql, &[&user_id]).await? as u64)
+    }
+}
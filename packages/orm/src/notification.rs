@@ -0,0 +1,237 @@
+//! Notifications, notification preferences and push subscriptions stored in
+//! the application database.
+//! `NotificationTables` is a `NotificationStore` over three tables. Read state
+//! lives in the `read_ms` column rather than in the JSON record, so marking
+//! notifications read is a single `UPDATE`. Timestamps used for ordering are
+//! unix milliseconds.
+
+use crate::{DbBackend, DbError, FromRow, Insert, ToSql};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use montrs_core::notification::{Notification, NotificationStore, Preferences, PushSubscription};
+use serde::de::DeserializeOwned;
+
+/// The prefix of the table names unless [`NotificationTables::with_prefix`] says otherwise.
+pub const DEFAULT_PREFIX: &str = "montrs_notification";
+
+/// Keeps notification state in `<prefix>s`, `<prefix>_preferences` and
+/// `<prefix>_push_subscriptions`.
+///
+/// ```rust,ignore
+/// let tables = NotificationTables::new(db.clone());
+/// tables.create_tables().await?;
+/// let notifications = Notifications::new(tables);
+/// ```
+pub struct NotificationTables<B: DbBackend> {
+    db: B,
+    prefix: String,
+}
+
+impl<B: DbBackend> NotificationTables<B> {
+    pub fn new(db: B) -> Self {
+        Self { db, prefix: DEFAULT_PREFIX.to_string() }
+    }
+
+    /// Names the tables `<prefix>s`, `<prefix>_preferences` and so on.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    fn notifications_table(&self) -> String {
+        format!("{}s", self.prefix)
+    }
+
+    fn table(&self, name: &str) -> String {
+        format!("{}_{}", self.prefix, name)
+    }
+
+    /// Creates the tables and their indexes if they do not exist yet. The
+    /// column types work on both SQLite and PostgreSQL.
+    pub async fn create_tables(&self) -> Result<(), DbError> {
+        let (notifications, preferences, push) =
+            (self.notifications_table(), self.table("preferences"), self.table("push_subscriptions"));
+        for sql in [
+            format!(
+                "CREATE TABLE IF NOT EXISTS {} (id TEXT PRIMARY KEY, user_id TEXT NOT NULL, kind TEXT NOT NULL, \
+                 created_ms BIGINT NOT NULL, read_ms BIGINT, record TEXT NOT NULL)",
+                notifications
+            ),
+            format!("CREATE INDEX IF NOT EXISTS {0}_user ON {0} (user_id, created_ms)", notifications),
+            format!("CREATE TABLE IF NOT EXISTS {} (user_id TEXT PRIMARY KEY, record TEXT NOT NULL)", preferences),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {} (endpoint TEXT PRIMARY KEY, user_id TEXT NOT NULL, created_ms BIGINT NOT NULL, \
+                 record TEXT NOT NULL)",
+                push
+            ),
+            format!("CREATE INDEX IF NOT EXISTS {0}_user ON {0} (user_id)", push),
+        ] {
+            self.db.execute(&sql, &[]).await?;
+        }
+        Ok(())
+    }
+
+    async fn records<T: DeserializeOwned>(&self, sql: &str, params: &[&dyn ToSql]) -> Result<Vec<T>, DbError> {
+        let rows: Vec<RecordRow> = self.db.query(sql, params).await?;
+        rows.into_iter().map(|row| parse(&row.0)).collect()
+    }
+
+    fn placeholder(&self, n: usize) -> String {
+        self.db.dialect().placeholder(n)
+    }
+}
+
+fn parse<T: DeserializeOwned>(record: &str) -> Result<T, DbError> {
+    serde_json::from_str(record).map_err(|e| DbError::Query(format!("invalid notification record: {}", e)))
+}
+
+struct RecordRow(String);
+
+impl FromRow for RecordRow {
+    #[cfg(feature = "sqlite")]
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self(row.get(0)?))
+    }
+
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        Ok(Self(row.try_get(0).map_err(|e| DbError::Query(e.to_string()))?))
+    }
+}
+
+/// A notification's record and when it was read.
+struct NotificationRow(String, Option<i64>);
+
+impl FromRow for NotificationRow {
+    #[cfg(feature = "sqlite")]
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self(row.get(0)?, row.get(1)?))
+    }
+
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        let column = |e: tokio_postgres::Error| DbError::Query(e.to_string());
+        Ok(Self(row.try_get(0).map_err(column)?, row.try_get(1).map_err(column)?))
+    }
+}
+
+struct CountRow(i64);
+
+impl FromRow for CountRow {
+    #[cfg(feature = "sqlite")]
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self(row.get(0)?))
+    }
+
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        Ok(Self(row.try_get(0).map_err(|e| DbError::Query(e.to_string()))?))
+    }
+}
+
+#[async_trait]
+impl<B: DbBackend> NotificationStore for NotificationTables<B> {
+    async fn save_notification(&self, notification: &Notification) -> anyhow::Result<()> {
+        let created_ms = notification.created_at.timestamp_millis();
+        let read_ms = notification.read_at.map(|at| at.timestamp_millis());
+        let json = serde_json::to_string(notification)?;
+        let row: [&dyn ToSql; 6] =
+            [&notification.id, &notification.user_id, &notification.kind, &created_ms, &read_ms, &json];
+        let columns = ["id", "user_id", "kind", "created_ms", "read_ms", "record"];
+        Insert::into(&self.notifications_table(), &columns)
+            .on_conflict_update(&["id"], &columns[1..])
+            .execute(&self.db, &[&row])
+            .await?;
+        Ok(())
+    }
+
+    async fn notifications(&self, user_id: &str, unread_only: bool, limit: usize) -> anyhow::Result<Vec<Notification>> {
+        let sql = format!(
+            "SELECT record, read_ms FROM {} WHERE user_id = {}{} ORDER BY created_ms DESC LIMIT {}",
+            self.notifications_table(),
+            self.placeholder(1),
+            if unread_only { " AND read_ms IS NULL" } else { "" },
+            limit
+        );
+        let rows: Vec<NotificationRow> = self.db.query(&sql, &[&user_id]).await?;
+        Ok(rows
+            .into_iter()
+            .map(|NotificationRow(record, read_ms)| {
+                let mut notification: Notification = parse(&record)?;
+                notification.read_at = read_ms.and_then(DateTime::<Utc>::from_timestamp_millis);
+                Ok(notification)
+            })
+            .collect::<Result<_, DbError>>()?)
+    }
+
+    async fn unread_count(&self, user_id: &str) -> anyhow::Result<u64> {
+        let sql = format!(
+            "SELECT COUNT(*) FROM {} WHERE user_id = {} AND read_ms IS NULL",
+            self.notifications_table(),
+            self.placeholder(1)
+        );
+        let rows: Vec<CountRow> = self.db.query(&sql, &[&user_id]).await?;
+        Ok(rows.first().map_or(0, |row| row.0 as u64))
+    }
+
+    async fn mark_read(&self, user_id: &str, ids: Option<&[String]>, at: DateTime<Utc>) -> anyhow::Result<usize> {
+        let at_ms = at.timestamp_millis();
+        let mut params: Vec<&dyn ToSql> = vec![&at_ms, &user_id];
+        let mut sql = format!(
+            "UPDATE {} SET read_ms = {} WHERE user_id = {} AND read_ms IS NULL",
+            self.notifications_table(),
+            self.placeholder(1),
+            self.placeholder(2)
+        );
+        if let Some(ids) = ids {
+            if ids.is_empty() {
+                return Ok(0);
+            }
+            let placeholders: Vec<String> = (3..3 + ids.len()).map(|n| self.placeholder(n)).collect();
+            sql.push_str(&format!(" AND id IN ({})", placeholders.join(", ")));
+            params.extend(ids.iter().map(|id| id as &dyn ToSql));
+        }
+        Ok(self.db.execute(&sql, &params).await?)
+    }
+
+    async fn preferences(&self, user_id: &str) -> anyhow::Result<Option<Preferences>> {
+        let sql = format!("SELECT record FROM {} WHERE user_id = {}", self.table("preferences"), self.placeholder(1));
+        Ok(self.records(&sql, &[&user_id]).await?.pop())
+    }
+
+    async fn save_preferences(&self, preferences: &Preferences) -> anyhow::Result<()> {
+        let json = serde_json::to_string(preferences)?;
+        let row: [&dyn ToSql; 2] = [&preferences.user_id, &json];
+        Insert::into(&self.table("preferences"), &["user_id", "record"])
+            .on_conflict_update(&["user_id"], &["record"])
+            .execute(&self.db, &[&row])
+            .await?;
+        Ok(())
+    }
+
+    async fn save_push_subscription(&self, subscription: &PushSubscription) -> anyhow::Result<()> {
+        let created_ms = subscription.created_at.timestamp_millis();
+        let json = serde_json::to_string(subscription)?;
+        let row: [&dyn ToSql; 4] = [&subscription.endpoint, &subscription.user_id, &created_ms, &json];
+        let columns = ["endpoint", "user_id", "created_ms", "record"];
+        Insert::into(&self.table("push_subscriptions"), &columns)
+            .on_conflict_update(&["endpoint"], &columns[1..])
+            .execute(&self.db, &[&row])
+            .await?;
+        Ok(())
+    }
+
+    async fn push_subscriptions(&self, user_id: &str) -> anyhow::Result<Vec<PushSubscription>> {
+        let sql = format!(
+            "SELECT record FROM {} WHERE user_id = {} ORDER BY created_ms",
+            self.table("push_subscriptions"),
+            self.placeholder(1)
+        );
+        Ok(self.records(&sql, &[&user_id]).await?)
+    }
+
+    async fn remove_push_subscription(&self, endpoint: &str) -> anyhow::Result<bool> {
+        let sql = format!("DELETE FROM {} WHERE endpoint = {}", self.table("push_subscriptions"), self.placeholder(1));
+        Ok(self.db.execute(&sql, &[&endpoint]).await? > 0)
+    }
+}
@@ -0,0 +1,174 @@
+//! Stripe customers, subscription state and handled events stored in the
+//! application database.
+//! `PaymentTables` is a `PaymentStore` over three tables. Handled event IDs
+//! go in a table of their own whose primary key makes recording a duplicate a
+//! no-op, so two workers receiving the same event cannot both publish it.
+//! Timestamps are unix milliseconds.
+
+use crate::{DbBackend, DbError, FromRow, Insert, ToSql};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use montrs_core::payment::{BillingSubscription, Customer, PaymentStore};
+
+/// The prefix of the table names unless [`PaymentTables::with_prefix`] says otherwise.
+pub const DEFAULT_PREFIX: &str = "montrs_payment";
+
+/// Keeps payment state in `<prefix>_events`, `<prefix>_customers` and
+/// `<prefix>_subscriptions`.
+///
+/// ```rust,ignore
+/// let tables = PaymentTables::new(db.clone());
+/// tables.create_tables().await?;
+/// let payments = Payments::new(stripe, tables);
+/// ```
+pub struct PaymentTables<B: DbBackend> {
+    db: B,
+    prefix: String,
+}
+
+impl<B: DbBackend> PaymentTables<B> {
+    pub fn new(db: B) -> Self {
+        Self { db, prefix: DEFAULT_PREFIX.to_string() }
+    }
+
+    /// Names the tables `<prefix>_events`, `<prefix>_customers` and so on.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    fn table(&self, name: &str) -> String {
+        format!("{}_{}", self.prefix, name)
+    }
+
+    /// Creates the tables and their indexes if they do not exist yet. The
+    /// column types work on both SQLite and PostgreSQL.
+    pub async fn create_tables(&self) -> Result<(), DbError> {
+        let (events, customers, subscriptions) = (self.table("events"), self.table("customers"), self.table("subscriptions"));
+        for sql in [
+            format!("CREATE TABLE IF NOT EXISTS {} (id TEXT PRIMARY KEY, handled_ms BIGINT NOT NULL)", events),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {} (user_id TEXT PRIMARY KEY, customer_id TEXT NOT NULL UNIQUE, created_ms BIGINT NOT NULL)",
+                customers
+            ),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {} (id TEXT PRIMARY KEY, customer_id TEXT NOT NULL, status TEXT NOT NULL, \
+                 created_ms BIGINT NOT NULL, synced_ms BIGINT NOT NULL, record TEXT NOT NULL)",
+                subscriptions
+            ),
+            format!("CREATE INDEX IF NOT EXISTS {0}_customer ON {0} (customer_id, created_ms)", subscriptions),
+        ] {
+            self.db.execute(&sql, &[]).await?;
+        }
+        Ok(())
+    }
+
+    async fn customer_where(&self, column: &str, value: &str) -> Result<Option<Customer>, DbError> {
+        let sql = format!(
+            "SELECT user_id, customer_id, created_ms FROM {} WHERE {} = {}",
+            self.table("customers"),
+            column,
+            self.db.dialect().placeholder(1)
+        );
+        let rows: Vec<CustomerRow> = self.db.query(&sql, &[&value]).await?;
+        Ok(rows.into_iter().next().map(|CustomerRow(user_id, customer_id, created_ms)| Customer {
+            user_id,
+            customer_id,
+            created_at: DateTime::<Utc>::from_timestamp_millis(created_ms).unwrap_or_default(),
+        }))
+    }
+
+    async fn subscriptions_where(&self, column: &str, value: &str) -> Result<Vec<BillingSubscription>, DbError> {
+        let sql = format!(
+            "SELECT record FROM {} WHERE {} = {} ORDER BY created_ms, id",
+            self.table("subscriptions"),
+            column,
+            self.db.dialect().placeholder(1)
+        );
+        let rows: Vec<RecordRow> = self.db.query(&sql, &[&value]).await?;
+        rows.into_iter()
+            .map(|row| serde_json::from_str(&row.0).map_err(|e| DbError::Query(format!("invalid subscription record: {}", e))))
+            .collect()
+    }
+}
+
+struct RecordRow(String);
+
+impl FromRow for RecordRow {
+    #[cfg(feature = "sqlite")]
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self(row.get(0)?))
+    }
+
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        Ok(Self(row.try_get(0).map_err(|e| DbError::Query(e.to_string()))?))
+    }
+}
+
+struct CustomerRow(String, String, i64);
+
+impl FromRow for CustomerRow {
+    #[cfg(feature = "sqlite")]
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self(row.get(0)?, row.get(1)?, row.get(2)?))
+    }
+
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        let column = |e: tokio_postgres::Error| DbError::Query(e.to_string());
+        Ok(Self(row.try_get(0).map_err(column)?, row.try_get(1).map_err(column)?, row.try_get(2).map_err(column)?))
+    }
+}
+
+#[async_trait]
+impl<B: DbBackend> PaymentStore for PaymentTables<B> {
+    async fn record_event(&self, event_id: &str, at: DateTime<Utc>) -> anyhow::Result<bool> {
+        let handled_ms = at.timestamp_millis();
+        let row: [&dyn ToSql; 2] = [&event_id, &handled_ms];
+        let inserted = Insert::into(&self.table("events"), &["id", "handled_ms"])
+            .on_conflict_do_nothing(&["id"])
+            .execute(&self.db, &[&row])
+            .await?;
+        Ok(inserted > 0)
+    }
+
+    async fn save_customer(&self, customer: &Customer) -> anyhow::Result<()> {
+        let created_ms = customer.created_at.timestamp_millis();
+        let row: [&dyn ToSql; 3] = [&customer.user_id, &customer.customer_id, &created_ms];
+        Insert::into(&self.table("customers"), &["user_id", "customer_id", "created_ms"])
+            .on_conflict_update(&["user_id"], &["customer_id", "created_ms"])
+            .execute(&self.db, &[&row])
+            .await?;
+        Ok(())
+    }
+
+    async fn customer(&self, user_id: &str) -> anyhow::Result<Option<Customer>> {
+        Ok(self.customer_where("user_id", user_id).await?)
+    }
+
+    async fn customer_by_id(&self, customer_id: &str) -> anyhow::Result<Option<Customer>> {
+        Ok(self.customer_where("customer_id", customer_id).await?)
+    }
+
+    async fn save_subscription(&self, subscription: &BillingSubscription) -> anyhow::Result<()> {
+        let status = serde_json::to_value(subscription.status)?.as_str().unwrap_or_default().to_string();
+        let (created_ms, synced_ms) = (subscription.created_at.timestamp_millis(), subscription.synced_at.timestamp_millis());
+        let json = serde_json::to_string(subscription)?;
+        let row: [&dyn ToSql; 6] = [&subscription.id, &subscription.customer_id, &status, &created_ms, &synced_ms, &json];
+        let columns = ["id", "customer_id", "status", "created_ms", "synced_ms", "record"];
+        Insert::into(&self.table("subscriptions"), &columns)
+            .on_conflict_update(&["id"], &columns[1..])
+            .execute(&self.db, &[&row])
+            .await?;
+        Ok(())
+    }
+
+    async fn subscription(&self, id: &str) -> anyhow::Result<Option<BillingSubscription>> {
+        Ok(self.subscriptions_where("id", id).await?.pop())
+    }
+
+    async fn subscriptions(&self, customer_id: &str) -> anyhow::Result<Vec<BillingSubscription>> {
+        Ok(self.subscriptions_where("customer_id", customer_id).await?)
+    }
+}
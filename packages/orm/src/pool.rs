@@ -0,0 +1,155 @@
+//! A small pool of SQLite connections for [`SqliteBackend`](crate::SqliteBackend).
+//! Connections are opened on demand, up to the pool size, and go back to the
+//! pool when the statement or transaction using them is done. File
+//! databases are switched to WAL mode so readers on one connection do not
+//! wait for a writer on another; writers still take turns, waiting up to
+//! [`BUSY_TIMEOUT`] for each other. An in-memory database lives and dies
+//! with its connection, so it always has exactly one.
+//!
+//! With a [`DbPoolConfig`], waiting for a connection gives up after the
+//! acquire timeout, and a connection idle for longer than the health check
+//! interval answers `SELECT 1` before it is handed out, or is replaced.
+
+use crate::{DbError, DbPoolConfig, types};
+use rusqlite::Connection;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::runtime::RuntimeFlavor;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Connections a file database is opened with unless configured otherwise.
+pub(crate) const DEFAULT_POOL_SIZE: usize = 4;
+
+/// How long a write waits for another connection's write to finish.
+pub(crate) const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub(crate) struct SqlitePool {
+    /// `None` for an in-memory database.
+    path: Option<String>,
+    idle: Mutex<Vec<Idle>>,
+    permits: Arc<Semaphore>,
+    size: usize,
+    acquire_timeout: Option<Duration>,
+    health_check_interval: Option<Duration>,
+}
+
+struct Idle {
+    conn: Connection,
+    since: Instant,
+}
+
+impl SqlitePool {
+    /// Opens the first connection, so a bad path fails here.
+    pub(crate) fn open(path: &str) -> Result<Self, DbError> {
+        let path = (path != ":memory:").then(|| path.to_string());
+        let size = if path.is_some() { DEFAULT_POOL_SIZE } else { 1 };
+        let first = Idle { conn: connect(path.as_deref())?, since: Instant::now() };
+        Ok(Self {
+            path,
+            idle: Mutex::new(vec![first]),
+            permits: Arc::new(Semaphore::new(size)),
+            size,
+            acquire_timeout: None,
+            health_check_interval: None,
+        })
+    }
+
+    /// Allows up to `size` connections. An in-memory database keeps one.
+    pub(crate) fn resize(&mut self, size: usize) {
+        if self.path.is_some() {
+            self.size = size.max(1);
+            self.permits = Arc::new(Semaphore::new(self.size));
+        }
+    }
+
+    pub(crate) fn configure(&mut self, config: &DbPoolConfig) {
+        self.resize(config.max_connections);
+        self.acquire_timeout = Some(config.acquire_timeout);
+        self.health_check_interval = config.health_check_interval.filter(|_| self.path.is_some());
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Waits for a free connection, opening one if none is idle.
+    pub(crate) async fn get(self: &Arc<Self>) -> Result<PooledConnection, DbError> {
+        let acquire = self.permits.clone().acquire_owned();
+        let permit = match self.acquire_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, acquire)
+                .await
+                .map_err(|_| DbError::Connection(format!("no free connection after {:?}", timeout)))?,
+            None => acquire.await,
+        }
+        .expect("the pool's semaphore is never closed");
+        let conn = loop {
+            let idle = self.idle.lock().unwrap_or_else(|e| e.into_inner()).pop();
+            match idle {
+                Some(idle) if self.needs_check(&idle) && !blocking(|| healthy(&idle.conn)) => {
+                    tracing::warn!("dropping an SQLite connection that failed its health check");
+                }
+                Some(idle) => break idle.conn,
+                None => break blocking(|| connect(self.path.as_deref()))?,
+            }
+        };
+        Ok(PooledConnection { conn: Some(conn), pool: self.clone(), _permit: permit })
+    }
+
+    fn needs_check(&self, idle: &Idle) -> bool {
+        self.health_check_interval.is_some_and(|interval| idle.since.elapsed() >= interval)
+    }
+}
+
+fn healthy(conn: &Connection) -> bool {
+    conn.query_row("SELECT 1", [], |_| Ok(())).is_ok()
+}
+
+fn connect(path: Option<&str>) -> Result<Connection, DbError> {
+    let Some(path) = path else {
+        return Connection::open_in_memory().map_err(|e| DbError::Connection(e.to_string()));
+    };
+    let conn = Connection::open(path).map_err(|e| DbError::Connection(e.to_string()))?;
+    conn.busy_timeout(BUSY_TIMEOUT).map_err(types::sqlite_error)?;
+    conn.pragma_update(None, "journal_mode", "WAL").map_err(types::sqlite_error)?;
+    Ok(conn)
+}
+
+/// A connection taken from the pool, returned to it on drop.
+pub(crate) struct PooledConnection {
+    conn: Option<Connection>,
+    pool: Arc<SqlitePool>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("present until dropped")
+    }
+}
+
+impl DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.conn.as_mut().expect("present until dropped")
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            self.pool.idle.lock().unwrap_or_else(|e| e.into_inner()).push(Idle { conn, since: Instant::now() });
+        }
+    }
+}
+
+/// Runs blocking SQLite work. On a multi-threaded runtime the worker hands
+/// its other tasks to the rest of the pool first, so a slow query does not
+/// stall them; a current-thread runtime has no one to hand them to.
+pub(crate) fn blocking<R>(f: impl FnOnce() -> R) -> R {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => tokio::task::block_in_place(f),
+        _ => f(),
+    }
+}
@@ -0,0 +1,260 @@
+//! Read/write splitting across a primary and its read replicas.
+//! `ReplicatedBackend` is a `DbBackend` that sends writes to the primary and
+//! spreads reads over the replicas in turn. After a write, reads can stick to
+//! the primary for a while so the writer sees its own changes despite
+//! replication lag. A replica that fails to connect is skipped until its
+//! retry delay has passed, and reads fall back to the primary when no replica
+//! is left. `.on_primary()` sends a single query to the primary.
+
+use crate::sql::{TokenKind, tokenize};
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+use crate::Transaction;
+use crate::{DbBackend, DbError, Dialect, FromRow, ToSql};
+use async_trait::async_trait;
+use montrs_core::EnvConfig;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Connection URL of the primary.
+pub const PRIMARY_URL_VAR: &str = "DATABASE_URL";
+/// Comma-separated connection URLs of the read replicas.
+pub const REPLICA_URLS_VAR: &str = "DATABASE_REPLICA_URLS";
+/// How long reads stay on the primary after a write, in milliseconds.
+pub const STICKY_MS_VAR: &str = "DATABASE_REPLICA_STICKY_MS";
+/// How long a failed replica is skipped, in milliseconds.
+pub const RETRY_MS_VAR: &str = "DATABASE_REPLICA_RETRY_MS";
+
+const DEFAULT_RETRY: Duration = Duration::from_secs(30);
+
+/// Where a `ReplicatedBackend` connects to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplicaConfig {
+    pub primary_url: String,
+    pub replica_urls: Vec<String>,
+    /// Reads go to the primary for this long after a write; zero disables it.
+    pub sticky_for: Duration,
+    pub retry_after: Duration,
+}
+
+impl ReplicaConfig {
+    /// Reads `DATABASE_URL`, `DATABASE_REPLICA_URLS`,
+    /// `DATABASE_REPLICA_STICKY_MS` and `DATABASE_REPLICA_RETRY_MS`. Only the
+    /// primary URL is required.
+    pub fn from_env(env: &dyn EnvConfig) -> Result<Self, DbError> {
+        let primary_url = env.get_var(PRIMARY_URL_VAR).map_err(|e| DbError::Connection(e.to_string()))?;
+        let replica_urls = env
+            .get_var(REPLICA_URLS_VAR)
+            .map(|urls| urls.split(',').map(str::trim).filter(|u| !u.is_empty()).map(String::from).collect())
+            .unwrap_or_default();
+        let millis = |key: &str| -> Result<Option<Duration>, DbError> {
+            match env.get_var(key) {
+                Ok(value) => value
+                    .trim()
+                    .parse()
+                    .map(|ms| Some(Duration::from_millis(ms)))
+                    .map_err(|_| DbError::Connection(format!("{} must be a number of milliseconds", key))),
+                Err(_) => Ok(None),
+            }
+        };
+        Ok(Self {
+            primary_url,
+            replica_urls,
+            sticky_for: millis(STICKY_MS_VAR)?.unwrap_or_default(),
+            retry_after: millis(RETRY_MS_VAR)?.unwrap_or(DEFAULT_RETRY),
+        })
+    }
+}
+
+struct Replica<B> {
+    backend: B,
+    down_until: Mutex<Option<Instant>>,
+}
+
+struct Inner<B> {
+    primary: B,
+    replicas: Vec<Replica<B>>,
+    next: AtomicUsize,
+    sticky_for: Duration,
+    retry_after: Duration,
+    last_write: Mutex<Option<Instant>>,
+}
+
+/// A primary with read replicas. Clones share replica health and stickiness.
+pub struct ReplicatedBackend<B> {
+    inner: Arc<Inner<B>>,
+}
+
+impl<B> Clone for ReplicatedBackend<B> {
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone() }
+    }
+}
+
+impl<B: DbBackend> ReplicatedBackend<B> {
+    pub fn new(primary: B, replicas: Vec<B>) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                primary,
+                replicas: replicas
+                    .into_iter()
+                    .map(|backend| Replica { backend, down_until: Mutex::new(None) })
+                    .collect(),
+                next: AtomicUsize::new(0),
+                sticky_for: Duration::ZERO,
+                retry_after: DEFAULT_RETRY,
+                last_write: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Connects to every URL in `config` with `connect`, e.g.
+    /// `ReplicatedBackend::connect(&config, SqliteBackend::new)`.
+    pub fn connect(config: &ReplicaConfig, connect: impl Fn(&str) -> Result<B, DbError>) -> Result<Self, DbError> {
+        let primary = connect(&config.primary_url)?;
+        let replicas = config.replica_urls.iter().map(|url| connect(url)).collect::<Result<_, _>>()?;
+        Ok(Self::new(primary, replicas)
+            .with_stickiness(config.sticky_for)
+            .with_retry_after(config.retry_after))
+    }
+
+    /// Keeps reads on the primary for `duration` after each write.
+    pub fn with_stickiness(self, duration: Duration) -> Self {
+        self.configure(|inner| inner.sticky_for = duration)
+    }
+
+    /// Skips a replica for `duration` after it fails to connect.
+    pub fn with_retry_after(self, duration: Duration) -> Self {
+        self.configure(|inner| inner.retry_after = duration)
+    }
+
+    fn configure(mut self, f: impl FnOnce(&mut Inner<B>)) -> Self {
+        match Arc::get_mut(&mut self.inner) {
+            Some(inner) => f(inner),
+            None => tracing::warn!("replica settings changed after the backend was cloned; ignored"),
+        }
+        self
+    }
+
+    pub fn primary(&self) -> &B {
+        &self.inner.primary
+    }
+
+    /// Runs the next query on the primary, whatever it is.
+    pub fn on_primary(&self) -> OnPrimary<'_, B> {
+        OnPrimary { backend: self }
+    }
+
+    /// Number of replicas not currently skipped after a failure.
+    pub fn healthy_replicas(&self) -> usize {
+        let now = Instant::now();
+        self.inner
+            .replicas
+            .iter()
+            .filter(|r| r.down_until.lock().unwrap().is_none_or(|until| until <= now))
+            .count()
+    }
+
+    fn record_write(&self) {
+        *self.inner.last_write.lock().unwrap() = Some(Instant::now());
+    }
+
+    fn sticky(&self) -> bool {
+        !self.inner.sticky_for.is_zero()
+            && self.inner.last_write.lock().unwrap().is_some_and(|at| at.elapsed() < self.inner.sticky_for)
+    }
+
+    async fn read<T: FromRow>(&self, sql: &str, params: &[&dyn ToSql]) -> Result<Vec<T>, DbError> {
+        let replicas = &self.inner.replicas;
+        if !replicas.is_empty() && !self.sticky() {
+            let start = self.inner.next.fetch_add(1, Ordering::Relaxed);
+            for n in 0..replicas.len() {
+                let replica = &replicas[(start + n) % replicas.len()];
+                if replica.down_until.lock().unwrap().is_some_and(|until| until > Instant::now()) {
+                    continue;
+                }
+                match replica.backend.query(sql, params).await {
+                    Err(DbError::Connection(e)) => {
+                        tracing::warn!(error = %e, "read replica unavailable; trying the next one");
+                        *replica.down_until.lock().unwrap() = Some(Instant::now() + self.inner.retry_after);
+                    }
+                    result => {
+                        *replica.down_until.lock().unwrap() = None;
+                        return result;
+                    }
+                }
+            }
+        }
+        self.inner.primary.query(sql, params).await
+    }
+}
+
+#[async_trait]
+impl<B: DbBackend> DbBackend for ReplicatedBackend<B> {
+    async fn execute(&self, sql: &str, params: &[&dyn ToSql]) -> Result<usize, DbError> {
+        let result = self.inner.primary.execute(sql, params).await;
+        self.record_write();
+        result
+    }
+
+    async fn query<T: FromRow>(&self, sql: &str, params: &[&dyn ToSql]) -> Result<Vec<T>, DbError> {
+        if is_read(sql) {
+            return self.read(sql, params).await;
+        }
+        let result = self.inner.primary.query(sql, params).await;
+        self.record_write();
+        result
+    }
+
+    async fn execute_batch(&self, sql: &str, batch: &[&[&dyn ToSql]]) -> Result<usize, DbError> {
+        let result = self.inner.primary.execute_batch(sql, batch).await;
+        self.record_write();
+        result
+    }
+
+    /// Transactions run on the primary.
+    #[cfg(any(feature = "sqlite", feature = "postgres"))]
+    async fn transaction(&self) -> Result<Transaction, DbError> {
+        let result = self.inner.primary.transaction().await;
+        self.record_write();
+        result
+    }
+
+    fn dialect(&self) -> Dialect {
+        self.inner.primary.dialect()
+    }
+}
+
+/// A `ReplicatedBackend` pinned to its primary for one query.
+pub struct OnPrimary<'a, B> {
+    backend: &'a ReplicatedBackend<B>,
+}
+
+impl<B: DbBackend> OnPrimary<'_, B> {
+    pub async fn execute(&self, sql: &str, params: &[&dyn ToSql]) -> Result<usize, DbError> {
+        self.backend.execute(sql, params).await
+    }
+
+    pub async fn query<T: FromRow>(&self, sql: &str, params: &[&dyn ToSql]) -> Result<Vec<T>, DbError> {
+        let result = self.backend.primary().query(sql, params).await;
+        if !is_read(sql) {
+            self.backend.record_write();
+        }
+        result
+    }
+}
+
+/// Whether `sql` only reads: a `SELECT`, `WITH` or `VALUES` that neither
+/// modifies data nor takes row locks.
+pub fn is_read(sql: &str) -> bool {
+    let tokens = tokenize(sql);
+    let starts_read = tokens
+        .first()
+        .and_then(|t| t.ident())
+        .is_some_and(|w| matches!(w, "select" | "with" | "values" | "explain" | "show"));
+    starts_read
+        && !tokens.windows(2).any(|w| w[0].is_word("for") && (w[1].is_word("update") || w[1].is_word("share")))
+        && !tokens.iter().any(|t| {
+            matches!(&t.kind, TokenKind::Word(w) if matches!(w.as_str(), "insert" | "update" | "delete" | "merge"))
+        })
+}
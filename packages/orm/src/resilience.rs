@@ -0,0 +1,191 @@
+//! Connection pool limits, health checks and retries.
+//! `DbPoolConfig` bounds how many connections a backend opens and how long a
+//! statement waits for one, and how often an idle connection is checked
+//! before it is reused. `RetryingBackend` wraps any `DbBackend` and runs a
+//! statement again when it fails with `DbError::Connection`, waiting longer
+//! before each attempt, so a database that blips does not fail the loaders
+//! running at that moment.
+//!
+//! Only reads and the start of a transaction are retried by default: a write
+//! whose connection dropped mid-flight may have been applied, and running it
+//! again could apply it twice. `.retry_writes()` opts in for idempotent writes.
+
+use crate::replica::is_read;
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+use crate::Transaction;
+use crate::{DbBackend, DbError, Dialect, FromRow, ToSql};
+use async_trait::async_trait;
+use montrs_core::EnvConfig;
+use std::future::Future;
+use std::time::Duration;
+
+/// The most connections a backend opens.
+pub const MAX_CONNECTIONS_VAR: &str = "DATABASE_MAX_CONNECTIONS";
+/// How long a statement waits for a free connection, in milliseconds.
+pub const ACQUIRE_TIMEOUT_MS_VAR: &str = "DATABASE_ACQUIRE_TIMEOUT_MS";
+/// How long a connection may sit idle before it is checked, in milliseconds; `0` disables checks.
+pub const HEALTH_CHECK_MS_VAR: &str = "DATABASE_HEALTH_CHECK_MS";
+/// How many times a statement is tried before a connection error is returned.
+pub const RETRY_ATTEMPTS_VAR: &str = "DATABASE_RETRY_ATTEMPTS";
+
+/// Pool settings for [`SqliteBackend`](crate::SqliteBackend) and
+/// [`PostgresBackend`](crate::PostgresBackend), with the retry policy a
+/// [`RetryingBackend`] around them uses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbPoolConfig {
+    pub max_connections: usize,
+    /// Waiting longer for a free connection fails with `DbError::Connection`.
+    pub acquire_timeout: Duration,
+    /// Idle connections older than this are checked with `SELECT 1` before
+    /// they are reused, and replaced when the check fails. `None` disables it.
+    pub health_check_interval: Option<Duration>,
+    pub retry: RetryPolicy,
+}
+
+impl Default for DbPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 4,
+            acquire_timeout: Duration::from_secs(30),
+            health_check_interval: Some(Duration::from_secs(30)),
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+impl DbPoolConfig {
+    /// Reads `DATABASE_MAX_CONNECTIONS`, `DATABASE_ACQUIRE_TIMEOUT_MS`,
+    /// `DATABASE_HEALTH_CHECK_MS` and `DATABASE_RETRY_ATTEMPTS`; unset ones
+    /// keep their defaults.
+    pub fn from_env(env: &dyn EnvConfig) -> Result<Self, DbError> {
+        let number = |key: &str| -> Result<Option<u64>, DbError> {
+            match env.get_var(key) {
+                Ok(value) => value
+                    .trim()
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| DbError::Connection(format!("{} must be a whole number", key))),
+                Err(_) => Ok(None),
+            }
+        };
+        let mut config = Self::default();
+        if let Some(max) = number(MAX_CONNECTIONS_VAR)? {
+            config.max_connections = max.max(1) as usize;
+        }
+        if let Some(ms) = number(ACQUIRE_TIMEOUT_MS_VAR)? {
+            config.acquire_timeout = Duration::from_millis(ms);
+        }
+        if let Some(ms) = number(HEALTH_CHECK_MS_VAR)? {
+            config.health_check_interval = (ms > 0).then(|| Duration::from_millis(ms));
+        }
+        if let Some(attempts) = number(RETRY_ATTEMPTS_VAR)? {
+            config.retry.max_attempts = attempts.max(1) as u32;
+        }
+        Ok(config)
+    }
+}
+
+/// How often and how patiently a [`RetryingBackend`] retries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in all, the first included; `1` never retries.
+    pub max_attempts: u32,
+    /// The wait before the first retry, doubled before each one after it.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 3, initial_backoff: Duration::from_millis(50), max_backoff: Duration::from_secs(2) }
+    }
+}
+
+impl RetryPolicy {
+    /// The wait after failed attempt number `attempt`, counted from 1.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let doublings = attempt.saturating_sub(1).min(31);
+        self.initial_backoff.saturating_mul(1 << doublings).min(self.max_backoff)
+    }
+}
+
+/// A backend whose connection failures are retried with backoff.
+///
+/// ```rust,ignore
+/// let pool = DbPoolConfig::from_env(&env)?;
+/// let db = RetryingBackend::new(SqliteBackend::new("app.db")?.with_pool_config(&pool), pool.retry);
+/// ```
+#[derive(Clone)]
+pub struct RetryingBackend<B> {
+    inner: B,
+    policy: RetryPolicy,
+    retry_writes: bool,
+}
+
+impl<B: DbBackend> RetryingBackend<B> {
+    pub fn new(inner: B, policy: RetryPolicy) -> Self {
+        Self { inner, policy, retry_writes: false }
+    }
+
+    /// Retries writes as well, for backends whose writes are all idempotent.
+    pub fn retry_writes(mut self) -> Self {
+        self.retry_writes = true;
+        self
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    async fn run<T, F, Fut>(&self, retry: bool, mut op: F) -> Result<T, DbError>
+    where
+        F: FnMut() -> Fut + Send,
+        Fut: Future<Output = Result<T, DbError>> + Send,
+    {
+        let attempts = if retry { self.policy.max_attempts.max(1) } else { 1 };
+        let mut attempt = 1;
+        loop {
+            let error = match op().await {
+                Err(DbError::Connection(e)) if attempt < attempts => e,
+                result => return result,
+            };
+            let wait = self.policy.backoff(attempt);
+            tracing::warn!(error = %error, attempt, ?wait, "database connection failed; retrying");
+            tokio::time::sleep(wait).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[async_trait]
+impl<B: DbBackend> DbBackend for RetryingBackend<B> {
+    async fn execute(&self, sql: &str, params: &[&dyn ToSql]) -> Result<usize, DbError> {
+        self.run(self.retry_writes, || self.inner.execute(sql, params)).await
+    }
+
+    async fn query<T: FromRow>(&self, sql: &str, params: &[&dyn ToSql]) -> Result<Vec<T>, DbError> {
+        self.run(self.retry_writes || is_read(sql), || self.inner.query(sql, params)).await
+    }
+
+    async fn execute_batch(&self, sql: &str, batch: &[&[&dyn ToSql]]) -> Result<usize, DbError> {
+        self.run(self.retry_writes, || self.inner.execute_batch(sql, batch)).await
+    }
+
+    /// Retried: nothing has run on a transaction that failed to start.
+    #[cfg(any(feature = "sqlite", feature = "postgres"))]
+    async fn transaction(&self) -> Result<Transaction, DbError> {
+        self.run(true, || self.inner.transaction()).await
+    }
+
+    async fn ping(&self) -> Result<(), DbError> {
+        self.run(true, || self.inner.ping()).await
+    }
+
+    fn dialect(&self) -> Dialect {
+        self.inner.dialect()
+    }
+}
@@ -0,0 +1,263 @@
+//! Database schema snapshots.
+//! A `SchemaSnapshot` lists the tables and columns of a database. It can be
+//! derived from the migration scripts, by replaying their `CREATE TABLE` and
+//! `ALTER TABLE` statements, or read from a live database through
+//! `SqliteBackend::introspect` and `PostgresBackend::introspect`.
+
+use crate::DbError;
+use crate::sql::{Token, TokenKind, statements, tokenize};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Tables of a database, keyed by lowercased name.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SchemaSnapshot {
+    pub tables: BTreeMap<String, TableSchema>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TableSchema {
+    /// Columns in declaration order.
+    pub columns: Vec<ColumnSchema>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ColumnSchema {
+    /// Lowercased column name.
+    pub name: String,
+    /// The declared type as written, e.g. `VARCHAR(255)`; empty when untyped.
+    pub sql_type: String,
+    pub nullable: bool,
+    pub primary_key: bool,
+}
+
+impl TableSchema {
+    pub fn column(&self, name: &str) -> Option<&ColumnSchema> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+}
+
+impl SchemaSnapshot {
+    pub fn table(&self, name: &str) -> Option<&TableSchema> {
+        self.tables.get(&name.to_lowercase())
+    }
+
+    /// Replays every `.sql` migration under `dir`, in path order. Files named
+    /// `down.sql` or `*.down.sql` are rollbacks and are skipped.
+    pub fn from_migrations(dir: &Path) -> Result<Self, DbError> {
+        let mut files = Vec::new();
+        collect_migrations(dir, &mut files)
+            .map_err(|e| DbError::Migration(format!("cannot read {}: {}", dir.display(), e)))?;
+        files.sort();
+
+        let mut schema = Self::default();
+        for file in files {
+            let sql = std::fs::read_to_string(&file)
+                .map_err(|e| DbError::Migration(format!("cannot read {}: {}", file.display(), e)))?;
+            schema.apply(&sql);
+        }
+        Ok(schema)
+    }
+
+    /// Applies the DDL statements of `sql` to the snapshot. Statements other
+    /// than `CREATE TABLE`, `ALTER TABLE` and `DROP TABLE` are ignored.
+    pub fn apply(&mut self, sql: &str) {
+        let tokens = tokenize(sql);
+        for statement in statements(&tokens) {
+            let words: Vec<&str> = statement.iter().take(6).filter_map(Token::ident).collect();
+            match words.as_slice() {
+                ["create", rest @ ..] if rest.contains(&"table") => self.create_table(statement),
+                ["alter", "table", ..] => self.alter_table(statement),
+                ["drop", "table", ..] => {
+                    if let Some((name, _)) = table_name(statement, 2) {
+                        self.tables.remove(&name);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn create_table(&mut self, statement: &[Token]) {
+        let Some(pos) = statement.iter().position(|t| t.is_word("table")) else { return };
+        let Some((name, next)) = table_name(statement, pos + 1) else { return };
+        if !statement.get(next).is_some_and(|t| t.is_punct('(')) {
+            // CREATE TABLE ... AS SELECT: the columns are not declared.
+            self.tables.entry(name).or_default();
+            return;
+        }
+        let mut table = TableSchema::default();
+        let mut table_keys = Vec::new();
+        for def in split_top_level(&statement[next + 1..]) {
+            let Some(first) = def.first().and_then(Token::ident) else { continue };
+            match first {
+                "constraint" | "primary" | "unique" | "foreign" | "check" | "exclude" => {
+                    if first == "primary" || def.get(2).is_some_and(|t| t.is_word("primary")) {
+                        table_keys.extend(parenthesized_names(def));
+                    }
+                }
+                _ => table.columns.push(column_def(def)),
+            }
+        }
+        for column in &mut table.columns {
+            if table_keys.contains(&column.name) {
+                column.primary_key = true;
+                column.nullable = false;
+            }
+        }
+        self.tables.insert(name, table);
+    }
+
+    fn alter_table(&mut self, statement: &[Token]) {
+        let Some((name, mut i)) = table_name(statement, 2) else { return };
+        let word = |i: usize| statement.get(i).and_then(Token::ident);
+        match word(i) {
+            Some("add") => {
+                i += 1;
+                if word(i) == Some("column") {
+                    i += 1;
+                }
+                if matches!(word(i), Some("constraint" | "primary" | "unique" | "foreign" | "check")) {
+                    return;
+                }
+                let def = &statement[i..];
+                let def = if word(i) == Some("if") { &def[3.min(def.len())..] } else { def };
+                let column = column_def(def);
+                let table = self.tables.entry(name).or_default();
+                table.columns.retain(|c| c.name != column.name);
+                table.columns.push(column);
+            }
+            Some("drop") => {
+                i += 1;
+                if word(i) == Some("column") {
+                    i += 1;
+                }
+                if word(i) == Some("if") {
+                    i += 2;
+                }
+                if let (Some(column), Some(table)) = (word(i), self.tables.get_mut(&name)) {
+                    table.columns.retain(|c| c.name != column);
+                }
+            }
+            Some("rename") => match (word(i + 1), word(i + 2), word(i + 3), word(i + 4)) {
+                (Some("to"), Some(new), ..) => {
+                    if let Some(table) = self.tables.remove(&name) {
+                        self.tables.insert(new.to_string(), table);
+                    }
+                }
+                (Some("column"), Some(old), Some("to"), Some(new)) | (Some(old), Some("to"), Some(new), _) => {
+                    if let Some(column) = self
+                        .tables
+                        .get_mut(&name)
+                        .and_then(|t| t.columns.iter_mut().find(|c| c.name == old))
+                    {
+                        column.name = new.to_string();
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Reads a possibly schema-qualified table name at `i`, skipping
+/// `IF [NOT] EXISTS` and `ONLY`. Returns the unqualified name and the index
+/// of the token after it.
+fn table_name(tokens: &[Token], mut i: usize) -> Option<(String, usize)> {
+    while tokens.get(i).is_some_and(|t| t.is_word("if") || t.is_word("not") || t.is_word("exists") || t.is_word("only")) {
+        i += 1;
+    }
+    let mut name = tokens.get(i)?.ident()?.to_string();
+    i += 1;
+    while tokens.get(i).is_some_and(|t| t.is_punct('.')) {
+        name = tokens.get(i + 1)?.ident()?.to_string();
+        i += 2;
+    }
+    Some((name, i))
+}
+
+/// Words that end a column's type in its definition.
+const COLUMN_CONSTRAINTS: &[&str] = &[
+    "not", "null", "primary", "default", "references", "unique", "check", "constraint", "generated", "collate",
+    "autoincrement", "identity", "on",
+];
+
+fn column_def(def: &[Token]) -> ColumnSchema {
+    let name = def.first().and_then(Token::ident).unwrap_or_default().to_string();
+    let type_end = def
+        .iter()
+        .skip(1)
+        .position(|t| t.ident().is_some_and(|w| COLUMN_CONSTRAINTS.contains(&w)))
+        .map_or(def.len(), |n| n + 1);
+    let sql_type = render(&def[1.min(def.len())..type_end]).to_uppercase();
+    let has = |a: &str, b: &str| def.windows(2).any(|w| w[0].is_word(a) && w[1].is_word(b));
+    let primary_key = has("primary", "key");
+    ColumnSchema { name, sql_type, nullable: !has("not", "null") && !primary_key, primary_key }
+}
+
+/// Splits the tokens inside a parenthesized list at top-level commas,
+/// stopping at the closing parenthesis.
+fn split_top_level(tokens: &[Token]) -> Vec<&[Token]> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0usize, 0);
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::Punct('(') => depth += 1,
+            TokenKind::Punct(')') if depth == 0 => {
+                parts.push(&tokens[start..i]);
+                return parts;
+            }
+            TokenKind::Punct(')') => depth -= 1,
+            TokenKind::Punct(',') if depth == 0 => {
+                parts.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&tokens[start..]);
+    parts
+}
+
+/// Identifiers in the first parenthesized list of `tokens`.
+fn parenthesized_names(tokens: &[Token]) -> Vec<String> {
+    let Some(open) = tokens.iter().position(|t| t.is_punct('(')) else { return Vec::new() };
+    split_top_level(&tokens[open + 1..])
+        .iter()
+        .filter_map(|part| part.first().and_then(Token::ident).map(String::from))
+        .collect()
+}
+
+/// Renders type tokens back to text, e.g. `varchar ( 255 )` to `varchar(255)`.
+fn render(tokens: &[Token]) -> String {
+    let mut out = String::new();
+    for token in tokens {
+        let text = match &token.kind {
+            TokenKind::Word(w) | TokenKind::Quoted(w) | TokenKind::Number(w) => w.clone(),
+            TokenKind::Punct(c) => c.to_string(),
+            _ => continue,
+        };
+        if !out.is_empty() && !text.starts_with(['(', ')', ',']) && !out.ends_with('(') {
+            out.push(' ');
+        }
+        out.push_str(&text);
+    }
+    out
+}
+
+fn collect_migrations(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_migrations(&path, files)?;
+        } else if path.extension().is_some_and(|e| e == "sql") {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if name != "down.sql" && !name.ends_with(".down.sql") {
+                files.push(path);
+            }
+        }
+    }
+    Ok(())
+}
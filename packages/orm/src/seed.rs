@@ -0,0 +1,223 @@
+//! Declarative seed data.
+//! A seed directory holds one file per table, in TOML or YAML, applied in
+//! file name order so that `01_users.toml` lands before `02_todos.toml`
+//! references it:
+//!
+//! ```toml
+//! table = "todos"
+//! key = ["id"]          # optional: rows whose key already exists are skipped
+//!
+//! [[rows]]
+//! id = 1
+//! title = "Read the MontRS guide"
+//! completed = true
+//! ```
+//!
+//! Cells may be strings, integers, floats, booleans or YAML `null`; arrays
+//! and tables are stored as JSON text. Dates go in as strings. Rows are
+//! written with [`Insert`], consecutive rows with the same columns in one
+//! statement. `montrs demo` points [`SEED_DIR_VAR`] at the project's demo
+//! seeds, and [`Seeds::from_env`] picks them up at boot.
+
+use crate::{DbBackend, DbError, Insert, ToSql};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Directory of seed files to apply at boot (set by `montrs demo`).
+pub const SEED_DIR_VAR: &str = "MONTRS_SEED_DIR";
+
+/// One cell of a seed row.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SeedValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+/// A cell as written in the file; nested values become JSON text.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawValue {
+    Null(()),
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Nested(serde_json::Value),
+}
+
+impl From<RawValue> for SeedValue {
+    fn from(raw: RawValue) -> Self {
+        match raw {
+            RawValue::Null(()) => SeedValue::Null,
+            RawValue::Bool(b) => SeedValue::Bool(b),
+            RawValue::Int(n) => SeedValue::Int(n),
+            RawValue::Float(f) => SeedValue::Float(f),
+            RawValue::Text(s) => SeedValue::Text(s),
+            RawValue::Nested(value) => SeedValue::Text(value.to_string()),
+        }
+    }
+}
+
+impl ToSql for SeedValue {
+    #[cfg(feature = "sqlite")]
+    fn as_rusqlite(&self) -> &dyn rusqlite::ToSql {
+        match self {
+            SeedValue::Null => &rusqlite::types::Null,
+            SeedValue::Bool(b) => b,
+            SeedValue::Int(n) => n,
+            SeedValue::Float(f) => f,
+            SeedValue::Text(s) => s,
+        }
+    }
+
+    #[cfg(feature = "postgres")]
+    fn as_postgres(&self) -> &(dyn tokio_postgres::types::ToSql + Sync) {
+        match self {
+            SeedValue::Null => &crate::types::PostgresNull,
+            SeedValue::Bool(b) => b,
+            SeedValue::Int(n) => n,
+            SeedValue::Float(f) => f,
+            SeedValue::Text(s) => s,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SeedFile {
+    table: String,
+    #[serde(default)]
+    key: Vec<String>,
+    #[serde(default)]
+    rows: Vec<BTreeMap<String, RawValue>>,
+}
+
+/// A row, by column name.
+type Row = BTreeMap<String, SeedValue>;
+
+/// The rows of one table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeedTable {
+    pub table: String,
+    /// Columns of a unique key; conflicting rows are skipped when set.
+    pub key: Vec<String>,
+    pub rows: Vec<BTreeMap<String, SeedValue>>,
+    /// The file the rows came from, if any.
+    pub source: Option<PathBuf>,
+}
+
+/// Seed tables in the order they are applied.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Seeds {
+    pub tables: Vec<SeedTable>,
+}
+
+impl Seeds {
+    pub fn from_toml(text: &str) -> Result<Self, DbError> {
+        let file: SeedFile = toml::from_str(text).map_err(|e| DbError::Seed(e.to_string()))?;
+        Ok(Self { tables: vec![file.into()] })
+    }
+
+    pub fn from_yaml(text: &str) -> Result<Self, DbError> {
+        let file: SeedFile = serde_yaml::from_str(text).map_err(|e| DbError::Seed(e.to_string()))?;
+        Ok(Self { tables: vec![file.into()] })
+    }
+
+    /// Reads every `.toml`, `.yaml` and `.yml` file in `dir`, sorted by name.
+    pub fn load_dir(dir: &Path) -> Result<Self, DbError> {
+        let entries = std::fs::read_dir(dir).map_err(|e| DbError::Seed(format!("{}: {}", dir.display(), e)))?;
+        let mut files: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().and_then(|e| e.to_str()).is_some_and(|e| matches!(e, "toml" | "yaml" | "yml")))
+            .collect();
+        files.sort();
+
+        let mut seeds = Seeds::default();
+        for path in files {
+            let text = std::fs::read_to_string(&path).map_err(|e| DbError::Seed(format!("{}: {}", path.display(), e)))?;
+            let file: SeedFile = match path.extension().and_then(|e| e.to_str()) {
+                Some("toml") => toml::from_str(&text).map_err(|e| e.to_string()),
+                _ => serde_yaml::from_str(&text).map_err(|e| e.to_string()),
+            }
+            .map_err(|e| DbError::Seed(format!("{}: {}", path.display(), e)))?;
+            seeds.tables.push(SeedTable { source: Some(path), ..file.into() });
+        }
+        Ok(seeds)
+    }
+
+    /// The seeds in the directory named by [`SEED_DIR_VAR`], or `None` when it
+    /// is not set.
+    pub fn from_env() -> Result<Option<Self>, DbError> {
+        match std::env::var_os(SEED_DIR_VAR) {
+            Some(dir) => Self::load_dir(Path::new(&dir)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Number of rows across all tables.
+    pub fn len(&self) -> usize {
+        self.tables.iter().map(|t| t.rows.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Inserts every row and returns the number of rows written. Tables with
+    /// a `key` can be applied again without duplicating rows.
+    pub async fn apply<B: DbBackend>(&self, db: &B) -> Result<usize, DbError> {
+        let mut written = 0;
+        for table in &self.tables {
+            for (columns, rows) in table.batches() {
+                let names: Vec<&str> = columns.iter().map(String::as_str).collect();
+                let mut insert = Insert::into(&table.table, &names);
+                if !table.key.is_empty() {
+                    let key: Vec<&str> = table.key.iter().map(String::as_str).collect();
+                    insert = insert.on_conflict_do_nothing(&key);
+                }
+                let params: Vec<Vec<&dyn ToSql>> =
+                    rows.iter().map(|row| columns.iter().map(|c| &row[c] as &dyn ToSql).collect()).collect();
+                let params: Vec<&[&dyn ToSql]> = params.iter().map(Vec::as_slice).collect();
+                written += insert.execute(db, &params).await.map_err(|e| match &table.source {
+                    Some(path) => DbError::Seed(format!("{} ({}): {}", table.table, path.display(), e)),
+                    None => DbError::Seed(format!("{}: {}", table.table, e)),
+                })?;
+            }
+        }
+        Ok(written)
+    }
+}
+
+impl SeedTable {
+    /// Runs of consecutive rows that share their columns.
+    fn batches(&self) -> Vec<(Vec<String>, Vec<&Row>)> {
+        let mut batches: Vec<(Vec<String>, Vec<&Row>)> = Vec::new();
+        for row in &self.rows {
+            let columns: Vec<String> = row.keys().cloned().collect();
+            match batches.last_mut() {
+                Some((last, rows)) if *last == columns => rows.push(row),
+                _ => batches.push((columns, vec![row])),
+            }
+        }
+        batches
+    }
+}
+
+impl From<SeedFile> for SeedTable {
+    fn from(file: SeedFile) -> Self {
+        Self {
+            table: file.table,
+            key: file.key,
+            rows: file
+                .rows
+                .into_iter()
+                .map(|row| row.into_iter().map(|(column, value)| (column, value.into())).collect())
+                .collect(),
+            source: None,
+        }
+    }
+}
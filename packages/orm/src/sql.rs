@@ -0,0 +1,141 @@
+//! A small SQL tokenizer shared by schema derivation and query checking.
+//! It knows just enough SQL to find identifiers, placeholders and statement
+//! boundaries; it is not a parser.
+
+/// One lexical token, with the byte offset where it starts.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Token {
+    pub kind: TokenKind,
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TokenKind {
+    /// A bare word, lowercased: a keyword, identifier or type name.
+    Word(String),
+    /// A `"quoted"`, `` `quoted` `` or `[quoted]` identifier, lowercased.
+    Quoted(String),
+    /// A string literal; its content is irrelevant here.
+    Str,
+    Number(String),
+    Param(Param),
+    /// The Postgres `::` cast operator.
+    Cast,
+    Punct(char),
+}
+
+/// A bind placeholder.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Param {
+    /// `$1`, `?1`
+    Numbered(usize),
+    /// `?`
+    Positional,
+    /// `:name`, `@name`, `$name`
+    Named(String),
+}
+
+impl Token {
+    /// The identifier this token names, if it is a word or quoted identifier.
+    pub fn ident(&self) -> Option<&str> {
+        match &self.kind {
+            TokenKind::Word(w) | TokenKind::Quoted(w) => Some(w),
+            _ => None,
+        }
+    }
+
+    pub fn is_word(&self, word: &str) -> bool {
+        matches!(&self.kind, TokenKind::Word(w) if w == word)
+    }
+
+    pub fn is_punct(&self, c: char) -> bool {
+        self.kind == TokenKind::Punct(c)
+    }
+}
+
+pub(crate) fn tokenize(sql: &str) -> Vec<Token> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let c = sql[i..].chars().next().unwrap_or_default();
+        let start = i;
+        if c.is_whitespace() {
+            i += c.len_utf8();
+        } else if sql[i..].starts_with("--") {
+            i = sql[i..].find('\n').map_or(bytes.len(), |n| i + n);
+        } else if sql[i..].starts_with("/*") {
+            i = sql[i + 2..].find("*/").map_or(bytes.len(), |n| i + n + 4);
+        } else if c == '\'' {
+            i = skip_quoted(bytes, i, b'\'');
+            tokens.push(Token { kind: TokenKind::Str, offset: start });
+        } else if c == '"' || c == '`' || c == '[' {
+            let close = if c == '[' { b']' } else { c as u8 };
+            i = skip_quoted(bytes, i, close);
+            let name = sql[start + 1..i.saturating_sub(1).max(start + 1)].to_lowercase();
+            tokens.push(Token { kind: TokenKind::Quoted(name), offset: start });
+        } else if c.is_ascii_digit() {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.' || bytes[i] == b'_') {
+                i += 1;
+            }
+            tokens.push(Token { kind: TokenKind::Number(sql[start..i].to_string()), offset: start });
+        } else if c.is_alphabetic() || c == '_' {
+            i = word_end(sql, i);
+            tokens.push(Token { kind: TokenKind::Word(sql[start..i].to_lowercase()), offset: start });
+        } else if c == ':' && bytes.get(i + 1) == Some(&b':') {
+            i += 2;
+            tokens.push(Token { kind: TokenKind::Cast, offset: start });
+        } else if (c == '$' || c == '?') && bytes.get(i + 1).is_some_and(u8::is_ascii_digit) {
+            i += 1;
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+            let n = sql[start + 1..i].parse().unwrap_or(0);
+            tokens.push(Token { kind: TokenKind::Param(Param::Numbered(n)), offset: start });
+        } else if c == '?' {
+            i += 1;
+            tokens.push(Token { kind: TokenKind::Param(Param::Positional), offset: start });
+        } else if matches!(c, ':' | '@' | '$')
+            && sql[i + 1..].chars().next().is_some_and(|n| n.is_alphabetic() || n == '_')
+        {
+            i = word_end(sql, i + 1);
+            tokens.push(Token { kind: TokenKind::Param(Param::Named(sql[start + 1..i].to_string())), offset: start });
+        } else {
+            i += c.len_utf8();
+            tokens.push(Token { kind: TokenKind::Punct(c), offset: start });
+        }
+    }
+    tokens
+}
+
+/// Splits a script into statements at top-level semicolons.
+pub(crate) fn statements(tokens: &[Token]) -> Vec<&[Token]> {
+    tokens
+        .split(|t| t.is_punct(';'))
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Index just past a quoted run starting at `start`; a doubled closing quote
+/// is an escaped one.
+fn skip_quoted(bytes: &[u8], start: usize, close: u8) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        if bytes[i] == close {
+            if bytes.get(i + 1) == Some(&close) && close != b']' {
+                i += 2;
+                continue;
+            }
+            return i + 1;
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+fn word_end(sql: &str, start: usize) -> usize {
+    sql[start..]
+        .char_indices()
+        .find(|(_, c)| !(c.is_alphanumeric() || *c == '_' || *c == '$'))
+        .map_or(sql.len(), |(n, _)| start + n)
+}
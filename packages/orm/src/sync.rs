@@ -0,0 +1,163 @@
+//! Synced rows stored in a database: SQLite on a device, the application
+//! database on the server.
+//! `SyncTable` is a `SyncStore` that keeps one row per synced model row, with
+//! the model, ID, sequence number and flags as plain columns and the full
+//! row, version vector included, as a JSON text column. The sequence number
+//! is computed in the upserting statement, one past the table's highest. The
+//! pull cursor lives in a second table, `<table>_state`.
+
+use crate::{DbBackend, DbError, FromRow, ToSql};
+use async_trait::async_trait;
+use montrs_core::sync::{SyncRow, SyncStore};
+
+/// The table rows are written to unless [`SyncTable::with_table`] says otherwise.
+pub const DEFAULT_TABLE: &str = "montrs_sync";
+
+/// Keeps synced rows in a table.
+///
+/// ```rust,ignore
+/// let local = SyncTable::new(SqliteBackend::new(app_data_dir.join("sync.db"))?);
+/// local.create_table().await?;
+/// let engine = SyncEngine::new(device_id, local, client).with_model("todos");
+/// ```
+pub struct SyncTable<B: DbBackend> {
+    db: B,
+    table: String,
+}
+
+impl<B: DbBackend> SyncTable<B> {
+    pub fn new(db: B) -> Self {
+        Self { db, table: DEFAULT_TABLE.to_string() }
+    }
+
+    /// Writes to `table` and `<table>_state` instead of [`DEFAULT_TABLE`].
+    pub fn with_table(mut self, table: impl Into<String>) -> Self {
+        self.table = table.into();
+        self
+    }
+
+    /// Creates the tables and the sequence index if they do not exist yet.
+    /// The column types work on both SQLite and PostgreSQL.
+    pub async fn create_table(&self) -> Result<(), DbError> {
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {} (\
+             model TEXT NOT NULL, id TEXT NOT NULL, seq BIGINT NOT NULL, dirty BOOLEAN NOT NULL, \
+             deleted BOOLEAN NOT NULL, record TEXT NOT NULL, PRIMARY KEY (model, id))",
+            self.table
+        );
+        self.db.execute(&sql, &[]).await?;
+        let index = format!("CREATE INDEX IF NOT EXISTS {0}_seq ON {0} (seq)", self.table);
+        self.db.execute(&index, &[]).await?;
+        let state = format!("CREATE TABLE IF NOT EXISTS {}_state (name TEXT PRIMARY KEY, value BIGINT NOT NULL)", self.table);
+        self.db.execute(&state, &[]).await?;
+        Ok(())
+    }
+
+    async fn rows_where(&self, filter: &str, params: &[&dyn ToSql]) -> Result<Vec<SyncRow>, DbError> {
+        let sql = format!("SELECT seq, record FROM {} WHERE {}", self.table, filter);
+        let rows: Vec<StoredRow> = self.db.query(&sql, params).await?;
+        rows.into_iter()
+            .map(|StoredRow(seq, row)| {
+                let row: SyncRow = serde_json::from_str(&row).map_err(|e| DbError::Query(format!("invalid sync row: {}", e)))?;
+                Ok(SyncRow { seq: seq as u64, ..row })
+            })
+            .collect()
+    }
+}
+
+/// A row's sequence number and JSON.
+struct StoredRow(i64, String);
+
+impl FromRow for StoredRow {
+    #[cfg(feature = "sqlite")]
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self(row.get(0)?, row.get(1)?))
+    }
+
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        let column = |e: tokio_postgres::Error| DbError::Query(e.to_string());
+        Ok(Self(row.try_get(0).map_err(column)?, row.try_get(1).map_err(column)?))
+    }
+}
+
+/// A sequence number.
+struct SeqRow(i64);
+
+impl FromRow for SeqRow {
+    #[cfg(feature = "sqlite")]
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self(row.get(0)?))
+    }
+
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        Ok(Self(row.try_get(0).map_err(|e| DbError::Query(e.to_string()))?))
+    }
+}
+
+#[async_trait]
+impl<B: DbBackend> SyncStore for SyncTable<B> {
+    async fn get(&self, model: &str, id: &str) -> anyhow::Result<Option<SyncRow>> {
+        let dialect = self.db.dialect();
+        let filter = format!("model = {} AND id = {}", dialect.placeholder(1), dialect.placeholder(2));
+        Ok(self.rows_where(&filter, &[&model, &id]).await?.pop())
+    }
+
+    async fn save(&self, row: &SyncRow) -> anyhow::Result<u64> {
+        let dialect = self.db.dialect();
+        let p = |n| dialect.placeholder(n);
+        let sql = format!(
+            "INSERT INTO {0} (model, id, seq, dirty, deleted, record) \
+             VALUES ({1}, {2}, (SELECT COALESCE(MAX(seq), 0) + 1 FROM {0}), {3}, {4}, {5}) \
+             ON CONFLICT (model, id) DO UPDATE SET seq = excluded.seq, dirty = excluded.dirty, \
+             deleted = excluded.deleted, record = excluded.record",
+            self.table,
+            p(1),
+            p(2),
+            p(3),
+            p(4),
+            p(5)
+        );
+        let json = serde_json::to_string(row)?;
+        self.db.execute(&sql, &[&row.model, &row.id, &row.dirty, &row.deleted, &json]).await?;
+
+        let sql = format!("SELECT seq FROM {} WHERE model = {} AND id = {}", self.table, p(1), p(2));
+        let seq: Vec<SeqRow> = self.db.query(&sql, &[&row.model, &row.id]).await?;
+        Ok(seq.first().map_or(0, |row| row.0 as u64))
+    }
+
+    async fn rows(&self, model: &str) -> anyhow::Result<Vec<SyncRow>> {
+        let dialect = self.db.dialect();
+        let filter = format!("model = {} AND deleted = {} ORDER BY id", dialect.placeholder(1), dialect.placeholder(2));
+        Ok(self.rows_where(&filter, &[&model, &false]).await?)
+    }
+
+    async fn changes(&self, since: u64, limit: usize) -> anyhow::Result<Vec<SyncRow>> {
+        let filter = format!("seq > {} ORDER BY seq LIMIT {}", self.db.dialect().placeholder(1), limit);
+        Ok(self.rows_where(&filter, &[&(since as i64)]).await?)
+    }
+
+    async fn dirty(&self, limit: usize) -> anyhow::Result<Vec<SyncRow>> {
+        // SQLite takes limits up to `i64::MAX`.
+        let limit = limit.min(i64::MAX as usize);
+        let filter = format!("dirty = {} ORDER BY seq LIMIT {}", self.db.dialect().placeholder(1), limit);
+        Ok(self.rows_where(&filter, &[&true]).await?)
+    }
+
+    async fn cursor(&self) -> anyhow::Result<u64> {
+        let sql = format!("SELECT value FROM {}_state WHERE name = 'cursor'", self.table);
+        let cursor: Vec<SeqRow> = self.db.query(&sql, &[]).await?;
+        Ok(cursor.first().map_or(0, |row| row.0 as u64))
+    }
+
+    async fn set_cursor(&self, cursor: u64) -> anyhow::Result<()> {
+        let sql = format!(
+            "INSERT INTO {}_state (name, value) VALUES ('cursor', {}) ON CONFLICT (name) DO UPDATE SET value = excluded.value",
+            self.table,
+            self.db.dialect().placeholder(1)
+        );
+        self.db.execute(&sql, &[&(cursor as i64)]).await?;
+        Ok(())
+    }
+}
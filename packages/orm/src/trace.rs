@@ -0,0 +1,104 @@
+//! Database spans that carry the route's trace.
+//! `TracedBackend` wraps any `DbBackend` and runs each statement in a
+//! `db.query` span, a child of the `route` span of the loader or action that
+//! issued it. The span holds the statement (its parameters are left out), the
+//! `trace_id` of the route's [`Trace`](montrs_core::Trace) and the attributes
+//! set on it with `ctx.trace().set_attr`, so a slow query can be found by
+//! order ID without every query site logging it.
+//!
+//! Statements run on a `Transaction` go to its connection directly and are
+//! not traced one by one.
+
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+use crate::Transaction;
+use crate::{DbBackend, DbError, Dialect, FromRow, ToSql};
+use async_trait::async_trait;
+use montrs_core::trace;
+use serde_json::Value;
+use std::future::Future;
+use tracing::Instrument;
+
+/// A backend whose statements are recorded as spans.
+///
+/// ```rust,ignore
+/// let db = TracedBackend::new(RetryingBackend::new(SqliteBackend::new("app.db")?, pool.retry));
+/// ```
+#[derive(Clone)]
+pub struct TracedBackend<B> {
+    inner: B,
+}
+
+impl<B: DbBackend> TracedBackend<B> {
+    pub fn new(inner: B) -> Self {
+        Self { inner }
+    }
+
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    async fn run<T, F>(&self, sql: &str, statement: F) -> Result<T, DbError>
+    where
+        F: Future<Output = Result<T, DbError>> + Send,
+    {
+        let system = match self.inner.dialect() {
+            Dialect::Sqlite => "sqlite",
+            Dialect::Postgres => "postgresql",
+            Dialect::Generic => "other_sql",
+        };
+        let span = tracing::info_span!(
+            "db.query",
+            db.system = system,
+            db.operation = operation(sql),
+            db.statement = sql,
+            trace_id = tracing::field::Empty,
+            attributes = tracing::field::Empty,
+            error = tracing::field::Empty,
+        );
+        if let Some(trace) = trace::current() {
+            span.record("trace_id", trace.trace_id());
+            let attributes = trace.attributes();
+            if !attributes.is_empty() {
+                span.record("attributes", tracing::field::display(Value::Object(attributes.into_iter().collect())));
+            }
+        }
+        let result = statement.instrument(span.clone()).await;
+        if let Err(e) = &result {
+            span.record("error", tracing::field::display(e));
+        }
+        result
+    }
+}
+
+/// The statement's first keyword, upper-cased, e.g. `SELECT`.
+fn operation(sql: &str) -> String {
+    sql.split_whitespace().next().unwrap_or_default().to_ascii_uppercase()
+}
+
+#[async_trait]
+impl<B: DbBackend> DbBackend for TracedBackend<B> {
+    async fn execute(&self, sql: &str, params: &[&dyn ToSql]) -> Result<usize, DbError> {
+        self.run(sql, self.inner.execute(sql, params)).await
+    }
+
+    async fn query<T: FromRow>(&self, sql: &str, params: &[&dyn ToSql]) -> Result<Vec<T>, DbError> {
+        self.run(sql, self.inner.query(sql, params)).await
+    }
+
+    async fn execute_batch(&self, sql: &str, batch: &[&[&dyn ToSql]]) -> Result<usize, DbError> {
+        self.run(sql, self.inner.execute_batch(sql, batch)).await
+    }
+
+    #[cfg(any(feature = "sqlite", feature = "postgres"))]
+    async fn transaction(&self) -> Result<Transaction, DbError> {
+        self.inner.transaction().await
+    }
+
+    async fn ping(&self) -> Result<(), DbError> {
+        self.run("SELECT 1", self.inner.ping()).await
+    }
+
+    fn dialect(&self) -> Dialect {
+        self.inner.dialect()
+    }
+}
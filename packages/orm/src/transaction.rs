@@ -0,0 +1,210 @@
+//! Transactions.
+//! [`DbBackend::transaction`] hands out a `Transaction`: one connection kept
+//! for the transaction's lifetime, with the backend's execute and query
+//! surface. Statements run through it are applied together by
+//! [`Transaction::commit`], or not at all.
+//!
+//! A statement that fails poisons the transaction: `commit` then rolls back
+//! and returns an error, on SQLite as on PostgreSQL (where the server would
+//! otherwise turn the `COMMIT` into a silent `ROLLBACK`). A transaction
+//! dropped without `commit` or `rollback` is rolled back.
+
+use crate::{DbBackend, DbError, Dialect, FromRow, ToSql, types};
+use async_trait::async_trait;
+use montrs_core::profile::DbTimer;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+#[cfg(feature = "sqlite")]
+use crate::pool::{self, PooledConnection};
+#[cfg(feature = "sqlite")]
+use std::sync::Mutex;
+
+/// Checks a statement before it runs, as [`EncryptedBackend`](crate::EncryptedBackend) does.
+pub(crate) type Guard = Arc<dyn Fn(&str) -> Result<(), DbError> + Send + Sync>;
+
+/// An open transaction on one connection.
+///
+/// ```rust,ignore
+/// let tx = db.transaction().await?;
+/// tx.execute("UPDATE accounts SET balance = balance - ? WHERE id = ?", &[&amount, &from]).await?;
+/// tx.execute("UPDATE accounts SET balance = balance + ? WHERE id = ?", &[&amount, &to]).await?;
+/// tx.commit().await?;
+/// ```
+///
+/// `Transaction` is itself a [`DbBackend`], so [`Insert`](crate::Insert) and
+/// anything else written against the trait can run inside it. On SQLite it
+/// keeps one of the backend's pooled connections: other statements run on
+/// the rest, but writes wait for the transaction to end, and an in-memory
+/// database, which has a single connection, runs nothing else until then.
+/// Use the transaction, not the backend, until it is committed.
+pub struct Transaction {
+    conn: Conn,
+    guards: Vec<Guard>,
+    failed: AtomicBool,
+    done: bool,
+}
+
+enum Conn {
+    #[cfg(feature = "sqlite")]
+    Sqlite(Mutex<PooledConnection>),
+    #[cfg(feature = "postgres")]
+    Postgres(Option<Box<deadpool_postgres::Object>>),
+}
+
+impl Transaction {
+    /// Begins a transaction on a pooled connection, which it keeps until the end.
+    #[cfg(feature = "sqlite")]
+    pub(crate) fn sqlite(conn: PooledConnection) -> Result<Self, DbError> {
+        pool::blocking(|| conn.execute_batch("BEGIN")).map_err(types::sqlite_error)?;
+        Ok(Self::new(Conn::Sqlite(Mutex::new(conn))))
+    }
+
+    /// Begins a transaction on a pooled client, which it keeps until the end.
+    #[cfg(feature = "postgres")]
+    pub(crate) async fn postgres(client: deadpool_postgres::Object) -> Result<Self, DbError> {
+        client.batch_execute("BEGIN").await.map_err(types::postgres_error)?;
+        Ok(Self::new(Conn::Postgres(Some(Box::new(client)))))
+    }
+
+    fn new(conn: Conn) -> Self {
+        Self { conn, guards: Vec::new(), failed: AtomicBool::new(false), done: false }
+    }
+
+    /// Runs `guard` on every statement before it is sent.
+    pub(crate) fn with_guard(mut self, guard: Guard) -> Self {
+        self.guards.push(guard);
+        self
+    }
+
+    fn check(&self, sql: &str) -> Result<(), DbError> {
+        self.guards.iter().try_for_each(|guard| guard(sql))
+    }
+
+    fn poison<T>(&self, result: Result<T, DbError>) -> Result<T, DbError> {
+        if result.is_err() {
+            self.failed.store(true, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// Applies every statement of the transaction. Fails, after rolling
+    /// back, if one of them failed.
+    pub async fn commit(mut self) -> Result<(), DbError> {
+        if self.failed.load(Ordering::Relaxed) {
+            self.finish("ROLLBACK").await?;
+            return Err(DbError::Query("a statement in the transaction failed; it was rolled back".to_string()));
+        }
+        self.finish("COMMIT").await
+    }
+
+    /// Discards every statement of the transaction.
+    pub async fn rollback(mut self) -> Result<(), DbError> {
+        self.finish("ROLLBACK").await
+    }
+
+    async fn finish(&mut self, sql: &str) -> Result<(), DbError> {
+        self.done = true;
+        match &self.conn {
+            #[cfg(feature = "sqlite")]
+            Conn::Sqlite(conn) => pool::blocking(|| conn.lock().unwrap().execute_batch(sql)).map_err(types::sqlite_error),
+            #[cfg(feature = "postgres")]
+            Conn::Postgres(client) => client.as_ref().unwrap().batch_execute(sql).await.map_err(types::postgres_error),
+        }
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        match &mut self.conn {
+            #[cfg(feature = "sqlite")]
+            Conn::Sqlite(conn) => {
+                if let Err(e) = conn.get_mut().unwrap().execute_batch("ROLLBACK") {
+                    tracing::warn!(error = %e, "could not roll back a dropped transaction");
+                }
+            }
+            // Rolling back needs a round trip, which `drop` cannot wait for.
+            // Closing the connection instead makes the server roll back.
+            #[cfg(feature = "postgres")]
+            Conn::Postgres(client) => {
+                if let Some(client) = client.take() {
+                    drop(deadpool_postgres::Object::take(*client));
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl DbBackend for Transaction {
+    async fn execute(&self, sql: &str, params: &[&dyn ToSql]) -> Result<usize, DbError> {
+        self.check(sql)?;
+        let _timer = DbTimer::start();
+        let result = match &self.conn {
+            #[cfg(feature = "sqlite")]
+            Conn::Sqlite(conn) => {
+                let sqlite_params: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_rusqlite()).collect();
+                pool::blocking(|| conn.lock().unwrap().execute(sql, rusqlite::params_from_iter(sqlite_params)))
+                    .map_err(types::sqlite_error)
+            }
+            #[cfg(feature = "postgres")]
+            Conn::Postgres(client) => {
+                let pg_params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> =
+                    params.iter().map(|p| p.as_postgres()).collect();
+                client
+                    .as_ref()
+                    .unwrap()
+                    .execute(sql, &pg_params)
+                    .await
+                    .map(|n| n as usize)
+                    .map_err(types::postgres_error)
+            }
+        };
+        self.poison(result)
+    }
+
+    async fn query<T: FromRow>(&self, sql: &str, params: &[&dyn ToSql]) -> Result<Vec<T>, DbError> {
+        self.check(sql)?;
+        let _timer = DbTimer::start();
+        let result = match &self.conn {
+            #[cfg(feature = "sqlite")]
+            Conn::Sqlite(conn) => {
+                let conn = conn.lock().unwrap();
+                let sqlite_params: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_rusqlite()).collect();
+                pool::blocking(|| {
+                    conn.prepare(sql).and_then(|mut stmt| {
+                        stmt.query_map(rusqlite::params_from_iter(sqlite_params), |row| T::from_row_sqlite(row))?
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                })
+                .map_err(types::sqlite_error)
+            }
+            #[cfg(feature = "postgres")]
+            Conn::Postgres(client) => {
+                let pg_params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> =
+                    params.iter().map(|p| p.as_postgres()).collect();
+                match client.as_ref().unwrap().query(sql, &pg_params).await {
+                    Ok(rows) => rows.iter().map(T::from_row_postgres).collect(),
+                    Err(e) => Err(types::postgres_error(e)),
+                }
+            }
+        };
+        self.poison(result)
+    }
+
+    /// Transactions do not nest.
+    async fn transaction(&self) -> Result<Transaction, DbError> {
+        Err(DbError::Query("a transaction is already open on this connection".to_string()))
+    }
+
+    fn dialect(&self) -> Dialect {
+        match &self.conn {
+            #[cfg(feature = "sqlite")]
+            Conn::Sqlite(_) => Dialect::Sqlite,
+            #[cfg(feature = "postgres")]
+            Conn::Postgres(_) => Dialect::Postgres,
+        }
+    }
+}
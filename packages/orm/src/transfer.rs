@@ -0,0 +1,543 @@
+//! Table export and import.
+//! [`Export`] writes a table as JSON lines or CSV, reading it one page at a
+//! time in key order, so tables larger than memory stream through.
+//! [`Import`] reads the same files back in batches; [`OnConflict`] says what
+//! happens to rows whose key already exists.
+//!
+//! On PostgreSQL the server converts the values: pages are read with
+//! `row_to_json` and batches are written with `json_populate_recordset`, so
+//! timestamps, UUIDs and numerics round-trip through their JSON text. SQLite
+//! values are read and bound as they are stored; BLOBs are exported as base64
+//! text.
+
+use crate::{DbBackend, DbError, Dialect, FromRow, Insert, ToSql};
+use serde_json::{Map, Value};
+use std::io::{BufRead, Write};
+
+/// Rows per page read, and per batch written, unless configured otherwise.
+pub const DEFAULT_BATCH_ROWS: usize = 1000;
+
+/// Column SQLite tables without a key are paged by.
+const ROWID: &str = "_montrs_rowid";
+
+/// File format of an export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFormat {
+    /// One JSON object per line.
+    Jsonl,
+    /// A header line of column names, then one line per row. Empty unquoted
+    /// cells are `NULL`, `""` is the empty string.
+    Csv,
+}
+
+impl DataFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            DataFormat::Jsonl => "jsonl",
+            DataFormat::Csv => "csv",
+        }
+    }
+
+    /// The format of a file with extension `ext`.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "jsonl" | "ndjson" => Some(DataFormat::Jsonl),
+            "csv" => Some(DataFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// What an import does with a row whose key is already in the table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnConflict {
+    /// Keeps the existing row.
+    #[default]
+    Skip,
+    /// Overwrites the existing row's other columns. Needs a key.
+    Upsert,
+    /// Empties the table before importing, so it ends up holding exactly the file's rows.
+    Replace,
+}
+
+/// A row, by column name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JsonRow(pub Map<String, Value>);
+
+impl FromRow for JsonRow {
+    #[cfg(feature = "sqlite")]
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        use base64::Engine;
+        use rusqlite::types::ValueRef;
+        let statement = row.as_ref();
+        let mut map = Map::new();
+        for i in 0..statement.column_count() {
+            let value = match row.get_ref(i)? {
+                ValueRef::Null => Value::Null,
+                ValueRef::Integer(n) => n.into(),
+                ValueRef::Real(f) => serde_json::Number::from_f64(f).map_or(Value::Null, Value::Number),
+                ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned().into(),
+                ValueRef::Blob(bytes) => base64::engine::general_purpose::STANDARD.encode(bytes).into(),
+            };
+            map.insert(statement.column_name(i)?.to_string(), value);
+        }
+        Ok(JsonRow(map))
+    }
+
+    /// Reads the first column as the row's JSON text, as selected by
+    /// `row_to_json(t)::text`.
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        let text: String = row.try_get(0).map_err(crate::types::postgres_error)?;
+        match serde_json::from_str(&text) {
+            Ok(Value::Object(map)) => Ok(JsonRow(map)),
+            _ => Err(DbError::Transfer(format!("expected a JSON row, got {}", text))),
+        }
+    }
+}
+
+/// A JSON value bound as the closest SQL value; arrays and objects are bound
+/// as JSON text.
+#[cfg_attr(not(any(feature = "sqlite", feature = "postgres")), allow(dead_code))]
+enum Cell {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl From<&Value> for Cell {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => Cell::Null,
+            Value::Bool(b) => Cell::Bool(*b),
+            Value::Number(n) => match n.as_i64() {
+                Some(n) => Cell::Int(n),
+                None => Cell::Float(n.as_f64().unwrap_or_default()),
+            },
+            Value::String(s) => Cell::Text(s.clone()),
+            nested => Cell::Text(nested.to_string()),
+        }
+    }
+}
+
+impl ToSql for Cell {
+    #[cfg(feature = "sqlite")]
+    fn as_rusqlite(&self) -> &dyn rusqlite::ToSql {
+        match self {
+            Cell::Null => &rusqlite::types::Null,
+            Cell::Bool(b) => b,
+            Cell::Int(n) => n,
+            Cell::Float(f) => f,
+            Cell::Text(s) => s,
+        }
+    }
+
+    #[cfg(feature = "postgres")]
+    fn as_postgres(&self) -> &(dyn tokio_postgres::types::ToSql + Sync) {
+        match self {
+            Cell::Null => &crate::types::PostgresNull,
+            Cell::Bool(b) => b,
+            Cell::Int(n) => n,
+            Cell::Float(f) => f,
+            Cell::Text(s) => s,
+        }
+    }
+}
+
+/// Writes one table to a file.
+///
+/// ```rust,ignore
+/// let mut out = BufWriter::new(File::create("todos.jsonl")?);
+/// let rows = Export::table("todos").key(&["id"]).write(&db, DataFormat::Jsonl, &mut out, |n| println!("{n}")).await?;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Export {
+    table: String,
+    key: Vec<String>,
+    batch_rows: usize,
+}
+
+impl Export {
+    pub fn table(table: &str) -> Self {
+        Self { table: table.to_string(), key: Vec::new(), batch_rows: DEFAULT_BATCH_ROWS }
+    }
+
+    /// Pages through the table in the order of `columns`, usually its primary
+    /// key. Without a key, SQLite tables are paged by `rowid`; PostgreSQL
+    /// tables need one.
+    pub fn key(mut self, columns: &[&str]) -> Self {
+        self.key = columns.iter().map(|c| c.to_string()).collect();
+        self
+    }
+
+    pub fn batch_rows(mut self, rows: usize) -> Self {
+        self.batch_rows = rows.max(1);
+        self
+    }
+
+    /// The page of rows after `after`, or the first page.
+    pub async fn page<B: DbBackend>(&self, db: &B, after: Option<&JsonRow>) -> Result<Vec<JsonRow>, DbError> {
+        let key = self.key.join(", ");
+        if db.dialect() == Dialect::Postgres {
+            if self.key.is_empty() {
+                return Err(DbError::Transfer(format!("{} has no primary key; name the columns to page it by", self.table)));
+            }
+            let filter = match after {
+                Some(_) => format!(
+                    " WHERE ({}) > (SELECT {} FROM json_populate_record(NULL::{}, $1::text::json))",
+                    key, key, self.table
+                ),
+                None => String::new(),
+            };
+            let sql = format!(
+                "SELECT row_to_json(t)::text FROM (SELECT * FROM {}{} ORDER BY {} LIMIT {}) t",
+                self.table, filter, key, self.batch_rows
+            );
+            let last = after.map(|row| Value::Object(row.0.clone()).to_string());
+            let params: Vec<&dyn ToSql> = last.iter().map(|text| text as &dyn ToSql).collect();
+            return db.query(&sql, &params).await;
+        }
+
+        let (select, order, fields) = if self.key.is_empty() {
+            (format!("rowid AS {}, *", ROWID), vec!["rowid".to_string()], vec![ROWID.to_string()])
+        } else {
+            ("*".to_string(), self.key.clone(), self.key.clone())
+        };
+        let cells: Vec<Cell> = match after {
+            Some(row) => fields.iter().map(|f| row.0.get(f).unwrap_or(&Value::Null).into()).collect(),
+            None => Vec::new(),
+        };
+        let filter = match cells.len() {
+            0 => String::new(),
+            1 => format!(" WHERE {} > ?", order[0]),
+            n => format!(" WHERE ({}) > ({})", order.join(", "), vec!["?"; n].join(", ")),
+        };
+        let sql = format!(
+            "SELECT {} FROM {}{} ORDER BY {} LIMIT {}",
+            select,
+            self.table,
+            filter,
+            order.join(", "),
+            self.batch_rows
+        );
+        let params: Vec<&dyn ToSql> = cells.iter().map(|c| c as &dyn ToSql).collect();
+        db.query(&sql, &params).await
+    }
+
+    /// Writes every row to `out` and returns how many there were. `progress`
+    /// gets the running count after each page.
+    pub async fn write<B: DbBackend, W: Write>(
+        &self,
+        db: &B,
+        format: DataFormat,
+        out: &mut W,
+        mut progress: impl FnMut(u64),
+    ) -> Result<u64, DbError> {
+        let io = |e: std::io::Error| DbError::Transfer(format!("{}: {}", self.table, e));
+        let mut written = 0;
+        let mut header: Option<Vec<String>> = None;
+        let mut last: Option<JsonRow> = None;
+        loop {
+            let page = self.page(db, last.as_ref()).await?;
+            let full = page.len() == self.batch_rows;
+            for row in &page {
+                let mut fields = row.0.clone();
+                fields.remove(ROWID);
+                match format {
+                    DataFormat::Jsonl => {
+                        serde_json::to_writer(&mut *out, &fields).map_err(|e| DbError::Transfer(e.to_string()))?;
+                        out.write_all(b"\n").map_err(io)?;
+                    }
+                    DataFormat::Csv => {
+                        if header.is_none() {
+                            let columns: Vec<String> = fields.keys().cloned().collect();
+                            write_csv_record(out, columns.iter().map(|c| Some(c.clone()))).map_err(io)?;
+                            header = Some(columns);
+                        }
+                        let columns = header.as_deref().unwrap_or_default();
+                        write_csv_record(out, columns.iter().map(|c| csv_cell(fields.get(c).unwrap_or(&Value::Null))))
+                            .map_err(io)?;
+                    }
+                }
+            }
+            written += page.len() as u64;
+            progress(written);
+            last = page.into_iter().last();
+            if !full {
+                break;
+            }
+        }
+        out.flush().map_err(io)?;
+        Ok(written)
+    }
+}
+
+/// Loads rows from a file into one table.
+///
+/// ```rust,ignore
+/// let file = BufReader::new(File::open("todos.csv")?);
+/// let rows = Import::table("todos").key(&["id"]).on_conflict(OnConflict::Upsert)
+///     .read(&db, DataFormat::Csv, file, |n| println!("{n}")).await?;
+/// ```
+///
+/// Each batch is its own statement. Run the import in a
+/// [`Transaction`](crate::Transaction) to load the file all or nothing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    table: String,
+    key: Vec<String>,
+    on_conflict: OnConflict,
+    batch_rows: usize,
+}
+
+impl Import {
+    pub fn table(table: &str) -> Self {
+        Self { table: table.to_string(), key: Vec::new(), on_conflict: OnConflict::Skip, batch_rows: DEFAULT_BATCH_ROWS }
+    }
+
+    /// The columns rows conflict on, usually the primary key. Without one,
+    /// `Skip` skips rows that violate any unique constraint.
+    pub fn key(mut self, columns: &[&str]) -> Self {
+        self.key = columns.iter().map(|c| c.to_string()).collect();
+        self
+    }
+
+    pub fn on_conflict(mut self, on_conflict: OnConflict) -> Self {
+        self.on_conflict = on_conflict;
+        self
+    }
+
+    pub fn batch_rows(mut self, rows: usize) -> Self {
+        self.batch_rows = rows.max(1);
+        self
+    }
+
+    /// Reads every row of `input` into the table and returns how many rows
+    /// were read. `progress` gets the running count after each batch.
+    pub async fn read<B: DbBackend, R: BufRead>(
+        &self,
+        db: &B,
+        format: DataFormat,
+        input: R,
+        mut progress: impl FnMut(u64),
+    ) -> Result<u64, DbError> {
+        if self.on_conflict == OnConflict::Upsert && self.key.is_empty() {
+            return Err(DbError::Transfer(format!("upserting into {} needs its key columns", self.table)));
+        }
+        if self.on_conflict == OnConflict::Replace {
+            db.execute(&format!("DELETE FROM {}", self.table), &[]).await?;
+        }
+        let mut rows = RowReader::new(format, input);
+        let mut read = 0;
+        loop {
+            let mut batch = Vec::with_capacity(self.batch_rows);
+            while batch.len() < self.batch_rows {
+                match rows.next_row().map_err(|e| DbError::Transfer(format!("{}: {}", self.table, e)))? {
+                    Some(row) => batch.push(row),
+                    None => break,
+                }
+            }
+            if batch.is_empty() {
+                break;
+            }
+            self.insert(db, &batch).await?;
+            read += batch.len() as u64;
+            progress(read);
+        }
+        Ok(read)
+    }
+
+    /// Inserts `rows`, one statement per run of rows with the same columns,
+    /// and returns the number of rows written.
+    pub async fn insert<B: DbBackend>(&self, db: &B, rows: &[JsonRow]) -> Result<usize, DbError> {
+        let mut written = 0;
+        let mut start = 0;
+        while start < rows.len() {
+            let columns: Vec<&str> = rows[start].0.keys().map(String::as_str).collect();
+            let end = rows[start..]
+                .iter()
+                .position(|row| !row.0.keys().map(String::as_str).eq(columns.iter().copied()))
+                .map_or(rows.len(), |n| start + n);
+            written += self.insert_run(db, &columns, &rows[start..end]).await?;
+            start = end;
+        }
+        Ok(written)
+    }
+
+    async fn insert_run<B: DbBackend>(&self, db: &B, columns: &[&str], rows: &[JsonRow]) -> Result<usize, DbError> {
+        let key: Vec<&str> = self.key.iter().map(String::as_str).collect();
+        let mut insert = Insert::into(&self.table, columns);
+        match self.on_conflict {
+            OnConflict::Skip => insert = insert.on_conflict_do_nothing(&key),
+            OnConflict::Upsert => {
+                let update: Vec<&str> = columns.iter().copied().filter(|c| !key.contains(c)).collect();
+                insert = insert.on_conflict_update(&key, &update);
+            }
+            OnConflict::Replace => {}
+        }
+
+        if db.dialect() == Dialect::Postgres {
+            let sql = format!(
+                "INSERT INTO {} ({}) SELECT {} FROM json_populate_recordset(NULL::{}, $1::text::json){}",
+                self.table,
+                columns.join(", "),
+                columns.join(", "),
+                self.table,
+                insert.conflict_clause()
+            );
+            let json = Value::Array(rows.iter().map(|row| Value::Object(row.0.clone())).collect()).to_string();
+            return db.execute(&sql, &[&json]).await;
+        }
+        let cells: Vec<Vec<Cell>> =
+            rows.iter().map(|row| columns.iter().map(|c| Cell::from(&row.0[*c])).collect()).collect();
+        let params: Vec<Vec<&dyn ToSql>> =
+            cells.iter().map(|row| row.iter().map(|c| c as &dyn ToSql).collect()).collect();
+        let params: Vec<&[&dyn ToSql]> = params.iter().map(Vec::as_slice).collect();
+        insert.execute(db, &params).await
+    }
+}
+
+/// Reads rows one at a time from a JSON lines or CSV file.
+struct RowReader<R> {
+    format: DataFormat,
+    input: R,
+    header: Option<Vec<String>>,
+    line: usize,
+}
+
+impl<R: BufRead> RowReader<R> {
+    fn new(format: DataFormat, input: R) -> Self {
+        Self { format, input, header: None, line: 0 }
+    }
+
+    fn next_row(&mut self) -> Result<Option<JsonRow>, String> {
+        match self.format {
+            DataFormat::Jsonl => loop {
+                let mut line = String::new();
+                self.line += 1;
+                if self.input.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+                    return Ok(None);
+                }
+                if line.trim().is_empty() {
+                    continue;
+                }
+                return match serde_json::from_str(&line) {
+                    Ok(Value::Object(map)) => Ok(Some(JsonRow(map))),
+                    Ok(_) => Err(format!("line {}: expected a JSON object", self.line)),
+                    Err(e) => Err(format!("line {}: {}", self.line, e)),
+                };
+            },
+            DataFormat::Csv => {
+                if self.header.is_none() {
+                    let Some(header) = self.csv_record()? else {
+                        return Ok(None);
+                    };
+                    self.header = Some(header.into_iter().map(Option::unwrap_or_default).collect());
+                }
+                let Some(record) = self.csv_record()? else {
+                    return Ok(None);
+                };
+                let header = self.header.as_ref().unwrap();
+                if record.len() != header.len() {
+                    return Err(format!("line {}: {} cells for {} columns", self.line, record.len(), header.len()));
+                }
+                let map = header
+                    .iter()
+                    .zip(record)
+                    .map(|(column, cell)| (column.clone(), cell.map_or(Value::Null, Value::String)))
+                    .collect();
+                Ok(Some(JsonRow(map)))
+            }
+        }
+    }
+
+    /// The next non-empty CSV record; `None` cells were empty and unquoted.
+    fn csv_record(&mut self) -> Result<Option<Vec<Option<String>>>, String> {
+        let mut text = String::new();
+        loop {
+            let start = self.line + 1;
+            text.clear();
+            // A quoted cell may span lines: read until the quotes balance.
+            loop {
+                self.line += 1;
+                if self.input.read_line(&mut text).map_err(|e| e.to_string())? == 0 {
+                    if text.is_empty() {
+                        return Ok(None);
+                    }
+                    break;
+                }
+                if text.matches('"').count().is_multiple_of(2) {
+                    break;
+                }
+            }
+            let record = text.strip_suffix('\n').unwrap_or(&text);
+            let record = record.strip_suffix('\r').unwrap_or(record);
+            if record.is_empty() {
+                continue;
+            }
+            return parse_csv_record(record).map(Some).map_err(|e| format!("line {}: {}", start, e));
+        }
+    }
+}
+
+fn parse_csv_record(record: &str) -> Result<Vec<Option<String>>, String> {
+    let mut cells = Vec::new();
+    let mut chars = record.chars().peekable();
+    loop {
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            let mut cell = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        cell.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => cell.push(c),
+                    None => return Err("unterminated quoted cell".to_string()),
+                }
+            }
+            cells.push(Some(cell));
+            match chars.next() {
+                Some(',') => continue,
+                None => return Ok(cells),
+                Some(c) => return Err(format!("unexpected `{}` after a quoted cell", c)),
+            }
+        }
+        let mut cell = String::new();
+        loop {
+            match chars.next() {
+                Some(',') => break,
+                Some(c) => cell.push(c),
+                None => {
+                    cells.push((!cell.is_empty()).then_some(cell));
+                    return Ok(cells);
+                }
+            }
+        }
+        cells.push((!cell.is_empty()).then_some(cell));
+    }
+}
+
+/// A cell as CSV text; `None` is `NULL`.
+fn csv_cell(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn write_csv_record<W: Write>(out: &mut W, cells: impl Iterator<Item = Option<String>>) -> std::io::Result<()> {
+    let cells: Vec<String> = cells
+        .map(|cell| match cell {
+            None => String::new(),
+            Some(s) if s.is_empty() || s.contains([',', '"', '\n', '\r']) => format!("\"{}\"", s.replace('"', "\"\"")),
+            Some(s) => s,
+        })
+        .collect();
+    writeln!(out, "{}", cells.join(","))
+}
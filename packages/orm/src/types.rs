@@ -0,0 +1,159 @@
+//! `ToSql` implementations for the parameter types both backends share, and
+//! the conversion errors that name the Rust type a backend cannot store.
+//! UUID, chrono and decimal support sit behind the `uuid`, `chrono` and
+//! `decimal` features.
+
+use crate::ToSql;
+
+/// Implements `ToSql` for types both drivers bind natively.
+macro_rules! native {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl ToSql for $ty {
+                #[cfg(feature = "sqlite")]
+                fn as_rusqlite(&self) -> &dyn rusqlite::ToSql {
+                    self
+                }
+                #[cfg(feature = "postgres")]
+                fn as_postgres(&self) -> &(dyn tokio_postgres::types::ToSql + Sync) {
+                    self
+                }
+            }
+        )*
+    };
+}
+
+// PostgreSQL checks parameter types strictly: `SMALLINT` takes an i16 and
+// `REAL` an f32, so those are bindable alongside the wider types.
+native!(String, &str, bool, i16, i32, i64, f32, f64, Vec<u8>, &[u8]);
+
+#[cfg(feature = "uuid")]
+native!(uuid::Uuid);
+
+#[cfg(feature = "chrono")]
+native!(
+    chrono::DateTime<chrono::Utc>,
+    chrono::DateTime<chrono::FixedOffset>,
+    chrono::NaiveDate,
+    chrono::NaiveDateTime,
+    chrono::NaiveTime,
+);
+
+/// `NULL` for `None`, the value's own conversion for `Some`.
+impl<T: ToSql> ToSql for Option<T> {
+    #[cfg(feature = "sqlite")]
+    fn as_rusqlite(&self) -> &dyn rusqlite::ToSql {
+        match self {
+            Some(value) => value.as_rusqlite(),
+            None => &rusqlite::types::Null,
+        }
+    }
+
+    #[cfg(feature = "postgres")]
+    fn as_postgres(&self) -> &(dyn tokio_postgres::types::ToSql + Sync) {
+        match self {
+            Some(value) => value.as_postgres(),
+            None => &PostgresNull,
+        }
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl ToSql for rust_decimal::Decimal {
+    /// SQLite has no exact decimal type; binding one fails instead of
+    /// silently rounding through a float.
+    #[cfg(feature = "sqlite")]
+    fn as_rusqlite(&self) -> &dyn rusqlite::ToSql {
+        &Unsupported {
+            rust_type: "rust_decimal::Decimal",
+            backend: "SQLite",
+            hint: "bind `value.to_string()` into a TEXT column, or an i64 of minor units",
+        }
+    }
+
+    #[cfg(feature = "postgres")]
+    fn as_postgres(&self) -> &(dyn tokio_postgres::types::ToSql + Sync) {
+        self
+    }
+}
+
+/// A `NULL` of whatever type the statement expects.
+#[cfg(feature = "postgres")]
+#[derive(Debug)]
+pub(crate) struct PostgresNull;
+
+#[cfg(feature = "postgres")]
+impl tokio_postgres::types::ToSql for PostgresNull {
+    fn to_sql(
+        &self,
+        _ty: &tokio_postgres::types::Type,
+        _out: &mut bytes::BytesMut,
+    ) -> Result<tokio_postgres::types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(tokio_postgres::types::IsNull::Yes)
+    }
+
+    fn accepts(_ty: &tokio_postgres::types::Type) -> bool {
+        true
+    }
+
+    tokio_postgres::types::to_sql_checked!();
+}
+
+/// A parameter the backend has no column type for; binding it fails with
+/// `DbError::Type`.
+#[cfg(all(feature = "sqlite", feature = "decimal"))]
+struct Unsupported {
+    rust_type: &'static str,
+    backend: &'static str,
+    hint: &'static str,
+}
+
+#[cfg(all(feature = "sqlite", feature = "decimal"))]
+impl rusqlite::ToSql for Unsupported {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        Err(rusqlite::Error::ToSqlConversionFailure(Box::new(crate::DbError::Type(format!(
+            "{} has no {} column type; {}",
+            self.rust_type, self.backend, self.hint
+        )))))
+    }
+}
+
+/// Maps a rusqlite error, keeping conversion failures apart from query errors.
+#[cfg(feature = "sqlite")]
+pub(crate) fn sqlite_error(e: rusqlite::Error) -> crate::DbError {
+    use crate::DbError;
+    match e {
+        rusqlite::Error::ToSqlConversionFailure(e) => match e.downcast::<DbError>() {
+            Ok(e) => *e,
+            Err(e) => DbError::Type(e.to_string()),
+        },
+        rusqlite::Error::InvalidColumnType(index, name, ty) => DbError::Type(format!(
+            "column {} (`{}`) holds {} in SQLite, which does not convert to the requested Rust type",
+            index, name, ty
+        )),
+        rusqlite::Error::FromSqlConversionFailure(index, ty, e) => match e.downcast::<DbError>() {
+            Ok(e) => *e,
+            Err(e) => DbError::Type(format!("column {} ({} in SQLite) could not be converted: {}", index, ty, e)),
+        },
+        rusqlite::Error::IntegralValueOutOfRange(index, value) => {
+            DbError::Type(format!("column {} holds {}, which does not fit the requested integer type", index, value))
+        }
+        e => DbError::Query(e.to_string()),
+    }
+}
+
+/// Maps a tokio-postgres error, keeping conversion failures apart from
+/// query errors.
+#[cfg(feature = "postgres")]
+pub(crate) fn postgres_error(e: tokio_postgres::Error) -> crate::DbError {
+    use crate::DbError;
+    use std::error::Error;
+    let source = e.source();
+    if let Some(DbError::Json(message)) = source.and_then(|s| s.downcast_ref::<DbError>()) {
+        return DbError::Json(message.clone());
+    }
+    if source.is_some_and(|s| s.is::<tokio_postgres::types::WrongType>() || s.is::<DbError>()) {
+        return DbError::Type(e.to_string());
+    }
+    DbError::Query(e.to_string())
+}
@@ -0,0 +1,206 @@
+//! Webhook subscriptions, deliveries and the delivery log stored in the
+//! application database.
+//! `WebhookTables` is a `WebhookStore` over three tables. Pending deliveries
+//! are the queue the webhook worker polls, so they survive restarts. Each row
+//! keeps its columns for querying and the full record as JSON text;
+//! timestamps used for ordering are unix milliseconds.
+
+use crate::{DbBackend, DbError, FromRow, Insert, ToSql};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use montrs_core::webhook::{Delivery, DeliveryAttempt, DeliveryStatus, Subscription, WebhookStore};
+use serde::de::DeserializeOwned;
+
+/// The prefix of the table names unless [`WebhookTables::with_prefix`] says otherwise.
+pub const DEFAULT_PREFIX: &str = "montrs_webhook";
+
+/// Keeps webhook state in `<prefix>_subscriptions`, `<prefix>_deliveries` and
+/// `<prefix>_attempts`.
+///
+/// ```rust,ignore
+/// let tables = WebhookTables::new(db.clone());
+/// tables.create_tables().await?;
+/// let webhooks = Webhooks::new(tables);
+/// ```
+pub struct WebhookTables<B: DbBackend> {
+    db: B,
+    prefix: String,
+}
+
+impl<B: DbBackend> WebhookTables<B> {
+    pub fn new(db: B) -> Self {
+        Self { db, prefix: DEFAULT_PREFIX.to_string() }
+    }
+
+    /// Names the tables `<prefix>_subscriptions` and so on.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    fn table(&self, name: &str) -> String {
+        format!("{}_{}", self.prefix, name)
+    }
+
+    /// Creates the tables and their indexes if they do not exist yet. The
+    /// column types work on both SQLite and PostgreSQL.
+    pub async fn create_tables(&self) -> Result<(), DbError> {
+        let (subscriptions, deliveries, attempts) = (self.table("subscriptions"), self.table("deliveries"), self.table("attempts"));
+        for sql in [
+            format!(
+                "CREATE TABLE IF NOT EXISTS {} (id TEXT PRIMARY KEY, url TEXT NOT NULL, active BOOLEAN NOT NULL, \
+                 created_ms BIGINT NOT NULL, record TEXT NOT NULL)",
+                subscriptions
+            ),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {} (id TEXT PRIMARY KEY, subscription_id TEXT NOT NULL, event TEXT NOT NULL, \
+                 status TEXT NOT NULL, attempts BIGINT NOT NULL, next_attempt_ms BIGINT NOT NULL, created_ms BIGINT NOT NULL, \
+                 record TEXT NOT NULL)",
+                deliveries
+            ),
+            format!("CREATE INDEX IF NOT EXISTS {0}_due ON {0} (status, next_attempt_ms)", deliveries),
+            format!(
+                "CREATE TABLE IF NOT EXISTS {} (delivery_id TEXT NOT NULL, subscription_id TEXT NOT NULL, attempt BIGINT NOT NULL, \
+                 at_ms BIGINT NOT NULL, status BIGINT, error TEXT, record TEXT NOT NULL)",
+                attempts
+            ),
+            format!("CREATE INDEX IF NOT EXISTS {0}_at ON {0} (subscription_id, at_ms)", attempts),
+        ] {
+            self.db.execute(&sql, &[]).await?;
+        }
+        Ok(())
+    }
+
+    async fn records<T: DeserializeOwned>(&self, sql: &str, params: &[&dyn ToSql]) -> Result<Vec<T>, DbError> {
+        let rows: Vec<RecordRow> = self.db.query(sql, params).await?;
+        rows.into_iter()
+            .map(|row| serde_json::from_str(&row.0).map_err(|e| DbError::Query(format!("invalid webhook record: {}", e))))
+            .collect()
+    }
+
+    fn placeholder(&self, n: usize) -> String {
+        self.db.dialect().placeholder(n)
+    }
+}
+
+struct RecordRow(String);
+
+impl FromRow for RecordRow {
+    #[cfg(feature = "sqlite")]
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self(row.get(0)?))
+    }
+
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        Ok(Self(row.try_get(0).map_err(|e| DbError::Query(e.to_string()))?))
+    }
+}
+
+#[async_trait]
+impl<B: DbBackend> WebhookStore for WebhookTables<B> {
+    async fn save_subscription(&self, subscription: &Subscription) -> anyhow::Result<()> {
+        let created_ms = subscription.created_at.timestamp_millis();
+        let json = serde_json::to_string(subscription)?;
+        let row: [&dyn ToSql; 5] = [&subscription.id, &subscription.url, &subscription.active, &created_ms, &json];
+        let columns = ["id", "url", "active", "created_ms", "record"];
+        Insert::into(&self.table("subscriptions"), &columns)
+            .on_conflict_update(&["id"], &columns[1..])
+            .execute(&self.db, &[&row])
+            .await?;
+        Ok(())
+    }
+
+    async fn remove_subscription(&self, id: &str) -> anyhow::Result<bool> {
+        let sql = format!("DELETE FROM {} WHERE id = {}", self.table("subscriptions"), self.placeholder(1));
+        Ok(self.db.execute(&sql, &[&id]).await? > 0)
+    }
+
+    async fn subscriptions(&self) -> anyhow::Result<Vec<Subscription>> {
+        let sql = format!("SELECT record FROM {} ORDER BY created_ms", self.table("subscriptions"));
+        Ok(self.records(&sql, &[]).await?)
+    }
+
+    async fn save_delivery(&self, delivery: &Delivery) -> anyhow::Result<()> {
+        let status = delivery.status.as_str().to_string();
+        let attempts = delivery.attempts as i64;
+        let next_attempt_ms = delivery.next_attempt_at.timestamp_millis();
+        let created_ms = delivery.created_at.timestamp_millis();
+        let json = serde_json::to_string(delivery)?;
+        let row: [&dyn ToSql; 8] = [
+            &delivery.id,
+            &delivery.subscription_id,
+            &delivery.event,
+            &status,
+            &attempts,
+            &next_attempt_ms,
+            &created_ms,
+            &json,
+        ];
+        let columns = ["id", "subscription_id", "event", "status", "attempts", "next_attempt_ms", "created_ms", "record"];
+        Insert::into(&self.table("deliveries"), &columns)
+            .on_conflict_update(&["id"], &columns[1..])
+            .execute(&self.db, &[&row])
+            .await?;
+        Ok(())
+    }
+
+    async fn delivery(&self, id: &str) -> anyhow::Result<Option<Delivery>> {
+        let sql = format!("SELECT record FROM {} WHERE id = {}", self.table("deliveries"), self.placeholder(1));
+        Ok(self.records(&sql, &[&id]).await?.pop())
+    }
+
+    async fn due(&self, now: DateTime<Utc>, limit: usize) -> anyhow::Result<Vec<Delivery>> {
+        let sql = format!(
+            "SELECT record FROM {} WHERE status = {} AND next_attempt_ms <= {} ORDER BY next_attempt_ms LIMIT {}",
+            self.table("deliveries"),
+            self.placeholder(1),
+            self.placeholder(2),
+            limit
+        );
+        let (pending, now_ms) = (DeliveryStatus::Pending.as_str(), now.timestamp_millis());
+        Ok(self.records(&sql, &[&pending, &now_ms]).await?)
+    }
+
+    async fn dead_letters(&self) -> anyhow::Result<Vec<Delivery>> {
+        let sql = format!(
+            "SELECT record FROM {} WHERE status = {} ORDER BY created_ms DESC",
+            self.table("deliveries"),
+            self.placeholder(1)
+        );
+        Ok(self.records(&sql, &[&DeliveryStatus::Dead.as_str()]).await?)
+    }
+
+    async fn log_attempt(&self, attempt: &DeliveryAttempt) -> anyhow::Result<()> {
+        let number = attempt.attempt as i64;
+        let at_ms = attempt.at.timestamp_millis();
+        let status = attempt.status.map(i64::from);
+        let json = serde_json::to_string(attempt)?;
+        let row: [&dyn ToSql; 7] =
+            [&attempt.delivery_id, &attempt.subscription_id, &number, &at_ms, &status, &attempt.error, &json];
+        Insert::into(&self.table("attempts"), &["delivery_id", "subscription_id", "attempt", "at_ms", "status", "error", "record"])
+            .execute(&self.db, &[&row])
+            .await?;
+        Ok(())
+    }
+
+    async fn attempts(&self, subscription_id: Option<&str>, limit: usize) -> anyhow::Result<Vec<DeliveryAttempt>> {
+        let table = self.table("attempts");
+        // Attempts logged in the same millisecond keep their insertion order.
+        Ok(match subscription_id {
+            Some(id) => {
+                let sql = format!(
+                    "SELECT record FROM {} WHERE subscription_id = {} ORDER BY at_ms DESC, attempt DESC LIMIT {}",
+                    table,
+                    self.placeholder(1),
+                    limit
+                );
+                self.records(&sql, &[&id]).await?
+            }
+            None => {
+                let sql = format!("SELECT record FROM {} ORDER BY at_ms DESC, attempt DESC LIMIT {}", table, limit);
+                self.records(&sql, &[]).await?
+            }
+        })
+    }
+}
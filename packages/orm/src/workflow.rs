@@ -0,0 +1,110 @@
+//! Workflow records stored in the application database.
+//! `WorkflowTable` is a `WorkflowStore` that keeps one row per workflow,
+//! upserted after every step, so sagas resume from the database after a
+//! crash or deploy. The status columns are plain columns for querying; the
+//! full record, state included, is a JSON text column.
+
+use crate::{DbBackend, DbError, FromRow, Insert, ToSql};
+use async_trait::async_trait;
+use montrs_core::workflow::{WorkflowRecord, WorkflowStatus, WorkflowStore};
+
+/// The table rows are written to unless [`WorkflowTable::with_table`] says otherwise.
+pub const DEFAULT_TABLE: &str = "montrs_workflows";
+
+const COLUMNS: [&str; 7] = ["id", "saga", "status", "step", "error", "updated_at", "record"];
+
+/// Keeps workflow records in a table.
+///
+/// ```rust,ignore
+/// let table = WorkflowTable::new(db.clone());
+/// table.create_table().await?;
+/// let workflows = Workflows::new(table);
+/// ```
+pub struct WorkflowTable<B: DbBackend> {
+    db: B,
+    table: String,
+}
+
+impl<B: DbBackend> WorkflowTable<B> {
+    pub fn new(db: B) -> Self {
+        Self { db, table: DEFAULT_TABLE.to_string() }
+    }
+
+    /// Writes to `table` instead of [`DEFAULT_TABLE`].
+    pub fn with_table(mut self, table: impl Into<String>) -> Self {
+        self.table = table.into();
+        self
+    }
+
+    /// Creates the table and its status index if they do not exist yet. The
+    /// column types work on both SQLite and PostgreSQL; timestamps are RFC 3339 text.
+    pub async fn create_table(&self) -> Result<(), DbError> {
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {} (\
+             id TEXT PRIMARY KEY, saga TEXT NOT NULL, status TEXT NOT NULL, step BIGINT NOT NULL, \
+             error TEXT, updated_at TEXT NOT NULL, record TEXT NOT NULL)",
+            self.table
+        );
+        self.db.execute(&sql, &[]).await?;
+        let index = format!("CREATE INDEX IF NOT EXISTS {0}_saga_status ON {0} (saga, status)", self.table);
+        self.db.execute(&index, &[]).await?;
+        Ok(())
+    }
+
+    async fn records(&self, filter: &str, params: &[&dyn ToSql]) -> Result<Vec<WorkflowRecord>, DbError> {
+        let sql = format!("SELECT record FROM {} WHERE {}", self.table, filter);
+        let rows: Vec<RecordRow> = self.db.query(&sql, params).await?;
+        rows.into_iter()
+            .map(|row| serde_json::from_str(&row.0).map_err(|e| DbError::Query(format!("invalid workflow record: {}", e))))
+            .collect()
+    }
+}
+
+struct RecordRow(String);
+
+impl FromRow for RecordRow {
+    #[cfg(feature = "sqlite")]
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self(row.get(0)?))
+    }
+
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        Ok(Self(row.try_get(0).map_err(|e| DbError::Query(e.to_string()))?))
+    }
+}
+
+#[async_trait]
+impl<B: DbBackend> WorkflowStore for WorkflowTable<B> {
+    async fn save(&self, record: &WorkflowRecord) -> anyhow::Result<()> {
+        let status = record.status.as_str().to_string();
+        let step = record.step as i64;
+        let updated_at = record.updated_at.to_rfc3339();
+        let json = serde_json::to_string(record)?;
+        let row: [&dyn ToSql; 7] = [&record.id, &record.saga, &status, &step, &record.error, &updated_at, &json];
+        Insert::into(&self.table, &COLUMNS)
+            .on_conflict_update(&["id"], &COLUMNS[1..])
+            .execute(&self.db, &[&row])
+            .await?;
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> anyhow::Result<Option<WorkflowRecord>> {
+        let filter = format!("id = {}", self.db.dialect().placeholder(1));
+        Ok(self.records(&filter, &[&id]).await?.pop())
+    }
+
+    async fn active(&self, saga: &str) -> anyhow::Result<Vec<WorkflowRecord>> {
+        let dialect = self.db.dialect();
+        let filter = format!(
+            "saga = {} AND status IN ({}, {})",
+            dialect.placeholder(1),
+            dialect.placeholder(2),
+            dialect.placeholder(3)
+        );
+        let (running, compensating) = (WorkflowStatus::Running.as_str(), WorkflowStatus::Compensating.as_str());
+        let mut records = self.records(&filter, &[&saga, &running, &compensating]).await?;
+        records.sort_by_key(|record| record.started_at);
+        Ok(records)
+    }
+}
@@ -0,0 +1,67 @@
+#![cfg(feature = "sqlite")]
+
+use montrs_core::analytics::Analytics;
+use montrs_orm::{DbBackend, DbError, FromRow, RouteUsageTable, SqliteBackend};
+use std::time::Duration;
+
+struct UsageRow {
+    route: String,
+    operation: String,
+    hits: i64,
+    status_4xx: i64,
+    max_ms: f64,
+    p95_ms: i64,
+}
+
+impl FromRow for UsageRow {
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            route: row.get(0)?,
+            operation: row.get(1)?,
+            hits: row.get(2)?,
+            status_4xx: row.get(3)?,
+            max_ms: row.get(4)?,
+            p95_ms: row.get(5)?,
+        })
+    }
+
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        Ok(Self {
+            route: row.get(0),
+            operation: row.get(1),
+            hits: row.get(2),
+            status_4xx: row.get(3),
+            max_ms: row.get(4),
+            p95_ms: row.get(5),
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_windows_become_rows() -> Result<(), DbError> {
+    let db = SqliteBackend::new(":memory:")?;
+    let table = RouteUsageTable::new(db.clone()).with_table("usage");
+    table.create_table().await?;
+    table.create_table().await?;
+    let analytics = Analytics::new().with_sink(table);
+
+    for ms in [4, 20, 30, 40, 45] {
+        analytics.record("/users/:id", "load", 200, Duration::from_millis(ms));
+    }
+    analytics.record("/users/:id", "load", 404, Duration::from_millis(1));
+    analytics.record("/cart", "act", 201, Duration::from_millis(120));
+    analytics.flush().await;
+    analytics.record("/cart", "act", 500, Duration::from_millis(80));
+    analytics.flush().await;
+
+    let rows: Vec<UsageRow> = db
+        .query("SELECT route, operation, hits, status_4xx, max_ms, p95_ms FROM usage ORDER BY rowid", &[])
+        .await?;
+    assert_eq!(rows.len(), 3);
+    assert_eq!((rows[0].route.as_str(), rows[0].operation.as_str()), ("/users/:id", "load"));
+    assert_eq!((rows[0].hits, rows[0].status_4xx, rows[0].p95_ms), (6, 1, 50));
+    assert!(rows[0].max_ms >= 45.0);
+    assert_eq!((rows[2].route.as_str(), rows[2].hits), ("/cart", 1));
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use async_trait::async_trait;
+use montrs_orm::{DbBackend, DbError, Dialect, FromRow, Insert, ToSql};
+use std::sync::Mutex;
+
+/// Records each statement with the number of parameters bound to it.
+#[derive(Default)]
+struct Recorder {
+    statements: Mutex<Vec<(String, usize)>>,
+}
+
+#[async_trait]
+impl DbBackend for Recorder {
+    async fn execute(&self, sql: &str, params: &[&dyn ToSql]) -> Result<usize, DbError> {
+        self.statements.lock().unwrap().push((sql.to_string(), params.len()));
+        Ok(params.len())
+    }
+
+    async fn query<T: FromRow>(&self, _sql: &str, _params: &[&dyn ToSql]) -> Result<Vec<T>, DbError> {
+        Ok(Vec::new())
+    }
+}
+
+#[test]
+fn test_statements_follow_the_dialect() {
+    let insert = Insert::into("users", &["email", "name"]);
+    assert_eq!(insert.sql(Dialect::Sqlite, 2), "INSERT INTO users (email, name) VALUES (?, ?), (?, ?)");
+    assert_eq!(insert.sql(Dialect::Postgres, 2), "INSERT INTO users (email, name) VALUES ($1, $2), ($3, $4)");
+
+    let upsert = insert.clone().on_conflict_update(&["email"], &["name"]);
+    assert_eq!(
+        upsert.sql(Dialect::Postgres, 1),
+        "INSERT INTO users (email, name) VALUES ($1, $2) ON CONFLICT (email) DO UPDATE SET name = excluded.name"
+    );
+    assert_eq!(
+        insert.clone().on_conflict_do_nothing(&[]).sql(Dialect::Sqlite, 1),
+        "INSERT INTO users (email, name) VALUES (?, ?) ON CONFLICT DO NOTHING"
+    );
+}
+
+#[tokio::test]
+async fn test_large_batches_are_chunked_under_the_parameter_limit() {
+    let db = Recorder::default();
+    let values: Vec<String> = (0..1200).map(|n| n.to_string()).collect();
+    let rows: Vec<Vec<&dyn ToSql>> = values.iter().map(|v| vec![v as &dyn ToSql, v as &dyn ToSql]).collect();
+    let rows: Vec<&[&dyn ToSql]> = rows.iter().map(Vec::as_slice).collect();
+
+    // The generic dialect allows 999 parameters: 499 rows of two columns.
+    let affected = Insert::into("t", &["a", "b"]).execute(&db, &rows).await.unwrap();
+    assert_eq!(affected, 2400);
+    let params: Vec<usize> = db.statements.lock().unwrap().iter().map(|(_, n)| *n).collect();
+    assert_eq!(params, [998, 998, 404]);
+
+    db.statements.lock().unwrap().clear();
+    Insert::into("t", &["a", "b"]).chunk_size(500).execute(&db, &rows[..1000]).await.unwrap();
+    assert_eq!(db.statements.lock().unwrap().len(), 3);
+
+    let short: &[&dyn ToSql] = &[&1];
+    let err = Insert::into("t", &["a", "b"]).execute(&db, &[short]).await.unwrap_err();
+    assert!(matches!(err, DbError::Query(ref m) if m.contains("row 0 has 1 values for 2 columns")));
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_sqlite_upsert_and_batch() {
+    use montrs_orm::SqliteBackend;
+
+    struct Count(i64);
+    impl FromRow for Count {
+        fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+            Ok(Count(row.get(0)?))
+        }
+        #[cfg(feature = "postgres")]
+        fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+            Ok(Count(row.get(0)))
+        }
+    }
+
+    let db = SqliteBackend::new(":memory:").unwrap();
+    db.execute("CREATE TABLE users (email TEXT PRIMARY KEY, visits INTEGER NOT NULL)", &[]).await.unwrap();
+    let insert = Insert::into("users", &["email", "visits"]).on_conflict_update(&["email"], &["visits"]);
+    insert.execute(&db, &[&[&"a@x.io", &1], &[&"b@x.io", &1]]).await.unwrap();
+    insert.execute(&db, &[&[&"a@x.io", &5]]).await.unwrap();
+    let total: Vec<Count> = db.query("SELECT SUM(visits) FROM users", &[]).await.unwrap();
+    assert_eq!(total[0].0, 6);
+
+    let affected = db
+        .execute_batch("UPDATE users SET visits = visits + ? WHERE email = ?", &[&[&1, &"a@x.io"], &[&1, &"b@x.io"]])
+        .await
+        .unwrap();
+    assert_eq!(affected, 2);
+
+    // A failing statement rolls back the whole batch.
+    let err = db
+        .execute_batch("INSERT INTO users (email, visits) VALUES (?, ?)", &[&[&"c@x.io", &1], &[&"c@x.io", &1]])
+        .await;
+    assert!(err.is_err());
+    let count: Vec<Count> = db.query("SELECT COUNT(*) FROM users", &[]).await.unwrap();
+    assert_eq!(count[0].0, 2);
+}
@@ -0,0 +1,71 @@
+use montrs_orm::{IssueKind, SchemaSnapshot, check_query};
+
+fn schema() -> SchemaSnapshot {
+    let mut schema = SchemaSnapshot::default();
+    schema.apply(
+        "CREATE TABLE todos (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            title VARCHAR(255) NOT NULL,
+            completed BOOLEAN NOT NULL DEFAULT 0
+        );
+        CREATE TABLE users (id INTEGER NOT NULL, name TEXT, PRIMARY KEY (id));
+        ALTER TABLE todos ADD COLUMN owner_id INTEGER REFERENCES users(id);",
+    );
+    schema
+}
+
+fn kinds(sql: &str, params: Option<usize>) -> Vec<IssueKind> {
+    check_query(sql, params, &schema()).into_iter().map(|i| i.kind).collect()
+}
+
+#[test]
+fn test_migrations_build_the_schema() {
+    let schema = schema();
+    let todos = schema.table("todos").unwrap();
+    let names: Vec<_> = todos.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, ["id", "title", "completed", "owner_id"]);
+    assert_eq!(todos.column("title").unwrap().sql_type, "VARCHAR(255)");
+    assert!(!todos.column("title").unwrap().nullable);
+    assert!(schema.table("users").unwrap().column("id").unwrap().primary_key);
+
+    let mut schema = schema;
+    schema.apply("ALTER TABLE todos RENAME COLUMN title TO summary; DROP TABLE users;");
+    assert!(schema.table("todos").unwrap().column("summary").is_some());
+    assert!(schema.table("users").is_none());
+}
+
+#[test]
+fn test_valid_queries_pass() {
+    for sql in [
+        "SELECT id, title, completed FROM todos WHERE id = ?",
+        "SELECT t.title, u.name AS owner FROM todos t JOIN users u ON u.id = t.owner_id ORDER BY owner",
+        "INSERT INTO todos (title, completed) VALUES ($1, $2) RETURNING id",
+        "UPDATE todos SET completed = ? WHERE id = ? AND title LIKE ?",
+        "SELECT count(*) total FROM todos GROUP BY completed HAVING total > 1",
+        "WITH open AS (SELECT * FROM todos WHERE NOT completed) SELECT whatever FROM open",
+        "SELECT CASE WHEN completed THEN 'done' ELSE 'open' END status FROM todos",
+    ] {
+        let expected = sql.matches('?').count().max(sql.matches('$').count());
+        assert_eq!(kinds(sql, Some(expected)), [], "{}", sql);
+    }
+}
+
+#[test]
+fn test_typos_are_reported_with_offsets() {
+    let issues = check_query("SELECT titel FROM todos", None, &schema());
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, IssueKind::UnknownColumn);
+    assert_eq!(issues[0].offset, Some(7));
+    assert!(issues[0].message.contains("titel"));
+
+    assert_eq!(kinds("SELECT t.nme FROM todos t", None), [IssueKind::UnknownColumn]);
+    assert_eq!(kinds("SELECT id FROM todo", None), [IssueKind::UnknownTable]);
+    assert_eq!(kinds("INSERT INTO todos (title, done) VALUES (?, ?)", Some(2)), [IssueKind::UnknownColumn]);
+}
+
+#[test]
+fn test_placeholder_arity() {
+    assert_eq!(kinds("SELECT id FROM todos WHERE id = ? AND title = ?", Some(1)), [IssueKind::ParamCount]);
+    assert_eq!(kinds("SELECT id FROM todos WHERE id = $2", Some(2)), []);
+    assert_eq!(kinds("SELECT id FROM todos WHERE title = '?'", Some(0)), []);
+}
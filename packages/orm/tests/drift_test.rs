@@ -0,0 +1,44 @@
+use montrs_orm::drift::normalize_type;
+use montrs_orm::{Drift, SchemaDrift, SchemaSnapshot};
+
+fn snapshot(sql: &str) -> SchemaSnapshot {
+    let mut schema = SchemaSnapshot::default();
+    schema.apply(sql);
+    schema
+}
+
+#[test]
+fn test_equivalent_spellings_do_not_drift() {
+    assert_eq!(normalize_type("int4"), "INTEGER");
+    assert_eq!(normalize_type("character varying"), normalize_type("VARCHAR(255)"));
+    assert_eq!(normalize_type("TIMESTAMP(3) WITH TIME ZONE"), "TIMESTAMPTZ");
+
+    let expected = snapshot("CREATE TABLE users (id SERIAL PRIMARY KEY, name VARCHAR(80) NOT NULL)");
+    let actual = snapshot("CREATE TABLE users (id INT4 PRIMARY KEY, name CHARACTER VARYING NOT NULL)");
+    assert!(SchemaDrift::between(&expected, &actual).is_empty());
+}
+
+#[test]
+fn test_drift_is_reported_and_corrected() {
+    let expected = snapshot(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT NOT NULL, age INTEGER);
+         CREATE TABLE posts (id INTEGER PRIMARY KEY, body TEXT);",
+    );
+    let actual = snapshot(
+        "CREATE TABLE users (id INTEGER PRIMARY KEY, age TEXT, legacy TEXT);
+         CREATE TABLE audit (id INTEGER);",
+    );
+    let drift = SchemaDrift::between(&expected, &actual);
+    assert_eq!(
+        drift.drifts.iter().map(|d| d.table()).collect::<Vec<_>>(),
+        ["audit", "posts", "users", "users", "users"]
+    );
+    assert!(drift.drifts.contains(&Drift::ExtraColumn { table: "users".into(), column: "legacy".into() }));
+    assert!(drift.drifts.iter().any(|d| matches!(d, Drift::TypeMismatch { column, .. } if column == "age")));
+
+    let sql = drift.migration();
+    assert!(sql.contains("ALTER TABLE users ADD COLUMN email TEXT NOT NULL;"));
+    assert!(sql.contains("CREATE TABLE posts (\n    id INTEGER PRIMARY KEY,\n    body TEXT\n);"));
+    assert!(sql.contains("-- DROP TABLE audit;"));
+    assert!(sql.contains("-- ALTER TABLE users DROP COLUMN legacy;"));
+}
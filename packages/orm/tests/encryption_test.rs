@@ -0,0 +1,147 @@
+use montrs_core::AgentError;
+use montrs_orm::encryption::ENCRYPTION_KEYS_VAR;
+use montrs_orm::{DbBackend, DbError, EncryptedBackend, EncryptedField, EncryptedModel, FieldCipher, FromRow, ToSql};
+use async_trait::async_trait;
+
+fn ring(ids: &[&str]) -> (FieldCipher, Vec<String>) {
+    let keys: Vec<String> = ids.iter().map(|id| format!("{}:{}", id, FieldCipher::generate_key())).collect();
+    (FieldCipher::parse(&keys.join(",")).unwrap(), keys)
+}
+
+struct User {
+    email: String,
+    phone: Option<String>,
+}
+
+impl EncryptedModel for User {
+    const TABLE: &'static str = "users";
+    const ENCRYPTED_COLUMNS: &'static [&'static str] = &["email", "phone"];
+
+    fn encrypt_fields(&mut self, cipher: &FieldCipher) -> Result<(), DbError> {
+        self.email.encrypt_with(cipher)?;
+        self.phone.encrypt_with(cipher)
+    }
+
+    fn decrypt_fields(&mut self, cipher: &FieldCipher) -> Result<(), DbError> {
+        self.email.decrypt_with(cipher)?;
+        self.phone.decrypt_with(cipher)
+    }
+}
+
+struct NoDb;
+
+#[async_trait]
+impl DbBackend for NoDb {
+    async fn execute(&self, _sql: &str, _params: &[&dyn ToSql]) -> Result<usize, DbError> {
+        Ok(0)
+    }
+
+    async fn query<T: FromRow>(&self, _sql: &str, _params: &[&dyn ToSql]) -> Result<Vec<T>, DbError> {
+        Ok(Vec::new())
+    }
+}
+
+#[test]
+fn test_values_round_trip_and_old_keys_still_decrypt() {
+    let (old, old_keys) = ring(&["k1"]);
+    let stored = old.encrypt("ada@example.com").unwrap();
+    assert!(stored.starts_with("enc:v1:k1:"));
+    assert_ne!(old.encrypt("ada@example.com").unwrap(), stored, "nonces are random");
+
+    let (_, new_keys) = ring(&["k2"]);
+    let rotated = FieldCipher::parse(&format!("{},{}", new_keys[0], old_keys[0])).unwrap();
+    assert_eq!(rotated.current_key_id(), "k2");
+    assert_eq!(rotated.decrypt(&stored).unwrap(), "ada@example.com");
+    assert_eq!(FieldCipher::key_id(&rotated.encrypt("x").unwrap()), Some("k2"));
+
+    let (stranger, _) = ring(&["k3"]);
+    assert!(matches!(stranger.decrypt(&stored), Err(DbError::Encryption(_))));
+    assert_eq!(stranger.decrypt("written before encryption").unwrap(), "written before encryption");
+    assert!(FieldCipher::parse("k1:c2hvcnQ=").is_err());
+    assert!(FieldCipher::parse("").is_err());
+    assert_eq!(ENCRYPTION_KEYS_VAR, "DATABASE_ENCRYPTION_KEYS");
+}
+
+#[test]
+fn test_model_fields_encrypt_in_place() {
+    let (cipher, _) = ring(&["k1"]);
+    let mut user = User { email: "ada@example.com".into(), phone: None };
+    user.encrypt_fields(&cipher).unwrap();
+    assert!(FieldCipher::is_encrypted(&user.email));
+    assert_eq!(user.phone, None);
+
+    // Encrypting twice does not wrap the ciphertext again.
+    let once = user.email.clone();
+    user.encrypt_fields(&cipher).unwrap();
+    assert_eq!(user.email, once);
+
+    user.decrypt_fields(&cipher).unwrap();
+    assert_eq!(user.email, "ada@example.com");
+}
+
+#[tokio::test]
+async fn test_filtering_on_encrypted_columns_is_rejected() {
+    let (cipher, _) = ring(&["k1"]);
+    let db = EncryptedBackend::new(NoDb, cipher).register::<User>();
+
+    for sql in [
+        "SELECT id FROM users WHERE email = ?",
+        "SELECT u.id FROM users u WHERE u.phone LIKE '%1'",
+        "SELECT * FROM users ORDER BY email",
+        "SELECT o.id FROM orders o JOIN users u ON u.email = o.email",
+        "UPDATE users SET name = ? WHERE email = ?",
+    ] {
+        let err = db.execute(sql, &[]).await.unwrap_err();
+        assert!(matches!(err, DbError::EncryptedFilter(ref c) if c.starts_with("users.")), "{}", sql);
+        assert_eq!(err.error_code(), "DB_ENCRYPTED_FILTER");
+    }
+
+    for sql in [
+        "SELECT id, email, phone FROM users WHERE id = ?",
+        "INSERT INTO users (email, phone) VALUES (?, ?)",
+        "UPDATE users SET email = ? WHERE id = ?",
+        "SELECT * FROM contacts WHERE email = ?",
+    ] {
+        db.execute(sql, &[]).await.unwrap_or_else(|e| panic!("{}: {}", sql, e));
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_rotate_column_re_encrypts_old_and_plain_values() {
+    use montrs_orm::SqliteBackend;
+    use montrs_orm::encryption::{rotate_column, stale_rows};
+
+    let (old, old_keys) = ring(&["k1"]);
+    let db = SqliteBackend::new(":memory:").unwrap();
+    db.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, email TEXT)", &[]).await.unwrap();
+    let encrypted = old.encrypt("ada@example.com").unwrap();
+    db.execute("INSERT INTO users (id, email) VALUES (1, ?), (2, 'bob@example.com'), (3, NULL)", &[&encrypted])
+        .await
+        .unwrap();
+
+    let (_, new_keys) = ring(&["k2"]);
+    let cipher = FieldCipher::parse(&format!("{},{}", new_keys[0], old_keys[0])).unwrap();
+    assert_eq!(stale_rows(&db, &cipher, "users", "id", "email").await.unwrap(), ["1", "2"]);
+    assert_eq!(rotate_column(&db, &cipher, "users", "id", "email").await.unwrap(), 2);
+    assert!(stale_rows(&db, &cipher, "users", "id", "email").await.unwrap().is_empty());
+    assert_eq!(rotate_column(&db, &cipher, "users", "id", "email").await.unwrap(), 0);
+
+    struct Email(String);
+    impl FromRow for Email {
+        fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+            Ok(Email(row.get(0)?))
+        }
+        #[cfg(feature = "postgres")]
+        fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+            Ok(Email(row.get(0)))
+        }
+    }
+    let rows: Vec<Email> = db.query("SELECT email FROM users WHERE email IS NOT NULL ORDER BY id", &[]).await.unwrap();
+    let plain: Vec<_> = rows
+        .iter()
+        .inspect(|Email(e)| assert_eq!(FieldCipher::key_id(e), Some("k2")))
+        .map(|Email(e)| cipher.decrypt(e).unwrap())
+        .collect();
+    assert_eq!(plain, ["ada@example.com", "bob@example.com"]);
+}
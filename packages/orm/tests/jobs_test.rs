@@ -0,0 +1,34 @@
+#![cfg(all(feature = "sqlite", feature = "jobs"))]
+
+use montrs_core::jobs::{JobStatus, JobStore, Jobs};
+use montrs_orm::{DbError, JobTable, SqliteBackend};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_jobs_wait_in_the_table_until_due() -> Result<(), DbError> {
+    let db = SqliteBackend::new(":memory:")?;
+    let table = Arc::new(JobTable::new(db.clone()).with_table("delayed"));
+    table.create_table().await?;
+    table.create_table().await?;
+    let jobs = Jobs::new(table.clone());
+
+    let later = jobs.schedule("/emails/send", json!({}), json!("ada@example.com"), Duration::from_secs(600)).await.unwrap();
+    let soon = jobs.schedule("/emails/send", json!({}), json!("bob@example.com"), Duration::from_secs(60)).await.unwrap();
+    let now = chrono::Utc::now();
+    assert!(table.due(now, 10).await.unwrap().is_empty());
+
+    let due = table.due(now + chrono::Duration::hours(1), 10).await.unwrap();
+    let ids: Vec<_> = due.iter().map(|r| r.id.as_str()).collect();
+    assert_eq!(ids, [soon.id.as_str(), later.id.as_str()], "earliest first");
+    assert_eq!(due[0].input, json!("bob@example.com"));
+    assert_eq!(table.due(now + chrono::Duration::hours(1), 1).await.unwrap().len(), 1);
+
+    let cancelled = jobs.cancel(&soon.id).await.unwrap().unwrap();
+    assert_eq!(cancelled.status, JobStatus::Cancelled);
+    assert_eq!(jobs.status(&soon.id).await.unwrap(), Some(cancelled));
+    assert_eq!(table.due(now + chrono::Duration::hours(1), 10).await.unwrap().len(), 1);
+    assert_eq!(table.get("missing").await.unwrap(), None);
+    Ok(())
+}
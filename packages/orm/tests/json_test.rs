@@ -0,0 +1,72 @@
+use montrs_core::{Validate, ValidationError};
+use montrs_orm::{DbError, Dialect, Json, json_path};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Address {
+    city: String,
+    zip: String,
+}
+
+impl Validate for Address {
+    fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        if self.zip.len() < 5 {
+            return Err(vec![ValidationError::MinLength { field: "zip", min: 5, actual: self.zip.len() }]);
+        }
+        Ok(())
+    }
+}
+
+fn address(zip: &str) -> Address {
+    Address { city: "Lisbon".into(), zip: zip.into() }
+}
+
+#[test]
+fn test_payloads_are_validated_when_written() {
+    let json = Json::new(address("1000-001")).to_json().unwrap();
+    assert_eq!(json, r#"{"city":"Lisbon","zip":"1000-001"}"#);
+
+    let err = Json::new(address("10")).to_json().unwrap_err();
+    assert!(matches!(err, DbError::Json(ref m) if m.contains("zip")), "{}", err);
+    assert!(Json::unchecked(address("10")).to_json().is_ok());
+
+    let parsed = Json::<Address>::from_json(&json).unwrap();
+    assert_eq!(parsed.city, "Lisbon");
+    assert!(matches!(Json::<Address>::from_json("{}"), Err(DbError::Json(_))));
+}
+
+#[test]
+fn test_json_paths_per_dialect() {
+    assert_eq!(json_path(Dialect::Postgres, "payload", &["address", "city"]), "payload -> 'address' ->> 'city'");
+    assert_eq!(json_path(Dialect::Postgres, "tags", &["0"]), "tags ->> 0");
+    assert_eq!(json_path(Dialect::Sqlite, "payload", &["items", "0", "name"]), r#"json_extract(payload, '$."items"[0]."name"')"#);
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_sqlite_round_trip() {
+    use montrs_orm::{DbBackend, FromRow, SqliteBackend};
+
+    struct Customer {
+        address: Json<Address>,
+    }
+    impl FromRow for Customer {
+        fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+            Ok(Customer { address: row.get(0)? })
+        }
+        #[cfg(feature = "postgres")]
+        fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+            Ok(Customer { address: row.get(0) })
+        }
+    }
+
+    let db = SqliteBackend::new(":memory:").unwrap();
+    db.execute("CREATE TABLE customers (address TEXT NOT NULL)", &[]).await.unwrap();
+    db.execute("INSERT INTO customers (address) VALUES (?)", &[&Json::new(address("1000-001"))]).await.unwrap();
+    assert!(db.execute("INSERT INTO customers (address) VALUES (?)", &[&Json::new(address("1"))]).await.is_err());
+
+    let sql = format!("SELECT address FROM customers WHERE {} = ?", json_path(Dialect::Sqlite, "address", &["city"]));
+    let rows: Vec<Customer> = db.query(&sql, &[&"Lisbon"]).await.unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(*rows[0].address, address("1000-001"));
+}
@@ -0,0 +1,102 @@
+#![cfg(all(feature = "sqlite", feature = "lifecycle"))]
+
+use chrono::{Duration as Age, Utc};
+use montrs_core::lifecycle::PersonalData;
+use montrs_orm::{
+    DbBackend, DbError, FromRow, PersonalDataTable, Purged, Retention, RetentionPolicy, SqliteBackend, TimestampFormat,
+};
+use std::time::Duration;
+
+#[derive(Debug, PartialEq)]
+struct Order {
+    id: i64,
+    user_id: Option<String>,
+    email: Option<String>,
+}
+
+impl FromRow for Order {
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Order { id: row.get(0)?, user_id: row.get(1)?, email: row.get(2)? })
+    }
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        Ok(Order { id: row.get(0), user_id: row.get(1), email: row.get(2) })
+    }
+}
+
+struct Id(i64);
+
+impl FromRow for Id {
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Id(row.get(0)?))
+    }
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        Ok(Id(row.get(0)))
+    }
+}
+
+async fn ids(db: &SqliteBackend, table: &str) -> Vec<i64> {
+    let rows: Vec<Id> = db.query(&format!("SELECT id FROM {} ORDER BY id", table), &[]).await.unwrap();
+    rows.into_iter().map(|row| row.0).collect()
+}
+
+#[tokio::test]
+async fn test_retention_deletes_expired_rows() -> Result<(), DbError> {
+    let db = SqliteBackend::new(":memory:")?;
+    db.execute("CREATE TABLE audit_log (id INTEGER PRIMARY KEY, created_at TEXT NOT NULL)", &[]).await?;
+    db.execute("CREATE TABLE sessions (id INTEGER PRIMARY KEY, last_seen BIGINT NOT NULL)", &[]).await?;
+    let now = Utc::now();
+    for (id, age) in [(1, Age::days(100)), (2, Age::days(89)), (3, Age::zero())] {
+        let created_at = (now - age).to_rfc3339();
+        db.execute("INSERT INTO audit_log (id, created_at) VALUES (?, ?)", &[&id, &created_at]).await?;
+        let last_seen = (now - age).timestamp_millis();
+        db.execute("INSERT INTO sessions (id, last_seen) VALUES (?, ?)", &[&id, &last_seen]).await?;
+    }
+
+    let retention = Retention::new(db.clone())
+        .with_policy(RetentionPolicy::new("audit_log", "created_at", Duration::from_secs(90 * 24 * 60 * 60)))
+        .with_policy(
+            RetentionPolicy::new("sessions", "last_seen", Duration::from_secs(24 * 60 * 60)).with_format(TimestampFormat::UnixMs),
+        );
+    let purged = retention.enforce(now).await?;
+    assert_eq!(
+        purged,
+        [Purged { table: "audit_log".into(), rows: 1 }, Purged { table: "sessions".into(), rows: 2 }]
+    );
+    assert_eq!(ids(&db, "audit_log").await, [2, 3]);
+    assert_eq!(ids(&db, "sessions").await, [3]);
+    assert_eq!(retention.enforce(now).await?.iter().map(|p| p.rows).sum::<usize>(), 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_personal_data_is_deleted_or_anonymized() -> Result<(), DbError> {
+    let db = SqliteBackend::new(":memory:")?;
+    db.execute("CREATE TABLE comments (id INTEGER PRIMARY KEY, author TEXT NOT NULL)", &[]).await?;
+    db.execute("CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id TEXT, email TEXT)", &[]).await?;
+    for (id, user) in [(1, "ada"), (2, "bob"), (3, "ada")] {
+        db.execute("INSERT INTO comments (id, author) VALUES (?, ?)", &[&id, &user]).await?;
+        let email = format!("{}@example.com", user);
+        db.execute("INSERT INTO orders (id, user_id, email) VALUES (?, ?, ?)", &[&id, &user, &email]).await?;
+    }
+
+    let comments = PersonalDataTable::new(db.clone(), "comments", "author");
+    let orders = PersonalDataTable::new(db.clone(), "orders", "user_id").anonymize(&["email"]);
+    assert_eq!(orders.category(), "orders");
+    assert_eq!(comments.erase("ada").await.unwrap(), 2);
+    assert_eq!(orders.erase("ada").await.unwrap(), 2);
+    assert_eq!(orders.erase("ada").await.unwrap(), 0, "erasing again finds nothing");
+
+    assert_eq!(ids(&db, "comments").await, [2]);
+    let orders: Vec<Order> = db.query("SELECT id, user_id, email FROM orders ORDER BY id", &[]).await?;
+    assert_eq!(
+        orders,
+        [
+            Order { id: 1, user_id: None, email: None },
+            Order { id: 2, user_id: Some("bob".into()), email: Some("bob@example.com".into()) },
+            Order { id: 3, user_id: None, email: None },
+        ]
+    );
+    Ok(())
+}
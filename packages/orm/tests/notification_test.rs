@@ -0,0 +1,68 @@
+#![cfg(all(feature = "sqlite", feature = "notifications"))]
+
+use chrono::{Duration, Utc};
+use montrs_core::notification::{Channel, Notification, NotificationStore, Notifications, Preferences, PushKeys, PushSubscription};
+use montrs_orm::{DbError, NotificationTables, SqliteBackend};
+use std::sync::Arc;
+
+fn at(notification: Notification, minutes_ago: i64) -> Notification {
+    Notification { created_at: Utc::now() - Duration::minutes(minutes_ago), ..notification }
+}
+
+#[tokio::test]
+async fn test_notifications_and_read_state_live_in_the_tables() -> Result<(), DbError> {
+    let db = SqliteBackend::new(":memory:")?;
+    let tables = Arc::new(NotificationTables::new(db.clone()).with_prefix("app_notification"));
+    tables.create_tables().await?;
+    tables.create_tables().await?;
+    let notifications = Notifications::new(tables.clone());
+
+    let old = notifications.notify(at(Notification::new("ada", "order.paid", "Paid"), 2)).await.unwrap().unwrap();
+    let new = notifications
+        .notify(at(Notification::new("ada", "order.shipped", "Shipped").with_data(serde_json::json!({ "order": 7 })), 1))
+        .await
+        .unwrap()
+        .unwrap();
+    notifications.notify(Notification::new("bob", "order.paid", "Paid")).await.unwrap();
+
+    let inbox = notifications.inbox("ada", false, 10).await.unwrap();
+    assert_eq!(inbox.unread, 2);
+    assert_eq!(inbox.notifications, [new.clone(), old.clone()]);
+
+    assert_eq!(tables.mark_read("ada", Some(&[old.id.clone(), "missing".to_string()]), Utc::now()).await.unwrap(), 1);
+    let stored = tables.notifications("ada", false, 10).await.unwrap();
+    assert!(!stored[0].is_read() && stored[1].is_read());
+    assert_eq!(tables.notifications("ada", true, 10).await.unwrap(), [new]);
+    assert_eq!(tables.mark_read("ada", None, Utc::now()).await.unwrap(), 1);
+    assert_eq!(tables.unread_count("ada").await.unwrap(), 0);
+    assert_eq!(tables.unread_count("bob").await.unwrap(), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_preferences_and_push_subscriptions_are_upserted() -> Result<(), DbError> {
+    let db = SqliteBackend::new(":memory:")?;
+    let tables = NotificationTables::new(db);
+    tables.create_tables().await?;
+
+    assert_eq!(tables.preferences("ada").await.unwrap(), None);
+    let mut preferences = Preferences::new("ada");
+    tables.save_preferences(&preferences).await.unwrap();
+    preferences.disabled = vec![Channel::Email];
+    preferences.muted = vec!["marketing.*".to_string()];
+    tables.save_preferences(&preferences).await.unwrap();
+    assert_eq!(tables.preferences("ada").await.unwrap(), Some(preferences));
+
+    let keys = PushKeys { p256dh: "key".to_string(), auth: "auth".to_string() };
+    let laptop = PushSubscription::new("ada", "https://push.example.com/1", keys.clone());
+    tables.save_push_subscription(&laptop).await.unwrap();
+    // The same browser, signed in as someone else.
+    let handed_over = PushSubscription::new("bob", "https://push.example.com/1", keys);
+    tables.save_push_subscription(&handed_over).await.unwrap();
+    assert!(tables.push_subscriptions("ada").await.unwrap().is_empty());
+    assert_eq!(tables.push_subscriptions("bob").await.unwrap(), [handed_over]);
+
+    assert!(tables.remove_push_subscription("https://push.example.com/1").await.unwrap());
+    assert!(!tables.remove_push_subscription("https://push.example.com/1").await.unwrap());
+    Ok(())
+}
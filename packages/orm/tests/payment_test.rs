@@ -0,0 +1,65 @@
+#![cfg(all(feature = "sqlite", feature = "payments"))]
+
+use chrono::Utc;
+use montrs_core::payment::{PaymentStore, Payments, Stripe, SubscriptionStatus};
+use montrs_core::webhook::sign;
+use montrs_orm::{DbError, PaymentTables, SqliteBackend};
+use serde_json::json;
+use std::sync::Arc;
+
+fn event(id: &str, kind: &str, created: i64, object: serde_json::Value) -> (String, Vec<u8>) {
+    let body = serde_json::to_vec(&json!({ "id": id, "type": kind, "created": created, "data": { "object": object } })).unwrap();
+    (sign("whsec_test", Utc::now().timestamp(), &body), body)
+}
+
+fn subscription(status: &str) -> serde_json::Value {
+    json!({
+        "id": "sub_1",
+        "customer": "cus_1",
+        "status": status,
+        "created": 1_700_000_000,
+        "metadata": { "montrs_user_id": "ada" },
+        "items": { "data": [{ "price": { "id": "price_pro" }, "quantity": 1 }] },
+    })
+}
+
+#[tokio::test]
+async fn test_webhooks_sync_billing_state_into_the_tables() -> Result<(), DbError> {
+    let db = SqliteBackend::new(":memory:")?;
+    let tables = Arc::new(PaymentTables::new(db.clone()).with_prefix("billing"));
+    tables.create_tables().await?;
+    tables.create_tables().await?;
+    let payments = Payments::new(Stripe::new("sk_test", "whsec_test"), tables.clone());
+
+    let (signature, body) = event("evt_2", "customer.subscription.updated", 1_700_000_100, subscription("past_due"));
+    assert!(payments.handle_webhook(&signature, &body).await.unwrap().is_some());
+    assert!(payments.handle_webhook(&signature, &body).await.unwrap().is_none());
+    let (signature, body) = event("evt_1", "customer.subscription.created", 1_700_000_000, subscription("active"));
+    assert!(payments.handle_webhook(&signature, &body).await.unwrap().is_none());
+
+    let customer = tables.customer_by_id("cus_1").await.unwrap().unwrap();
+    assert_eq!(customer.user_id, "ada");
+    let billing = payments.billing("ada").await.unwrap();
+    assert_eq!(billing.subscriptions.len(), 1);
+    assert_eq!(billing.subscriptions[0].status, SubscriptionStatus::PastDue);
+    assert_eq!(billing.subscriptions[0].price_id.as_deref(), Some("price_pro"));
+
+    let (signature, body) = event("evt_3", "customer.subscription.updated", 1_700_000_200, subscription("active"));
+    payments.handle_webhook(&signature, &body).await.unwrap();
+    assert!(payments.billing("ada").await.unwrap().is_subscribed());
+    assert_eq!(tables.customer("bob").await.unwrap(), None);
+    assert!(tables.subscriptions("cus_2").await.unwrap().is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_events_are_recorded_once() -> Result<(), DbError> {
+    let db = SqliteBackend::new(":memory:")?;
+    let tables = PaymentTables::new(db);
+    tables.create_tables().await?;
+
+    assert!(tables.record_event("evt_1", Utc::now()).await.unwrap());
+    assert!(!tables.record_event("evt_1", Utc::now()).await.unwrap());
+    assert!(tables.record_event("evt_2", Utc::now()).await.unwrap());
+    Ok(())
+}
@@ -0,0 +1,109 @@
+#![cfg(feature = "sqlite")]
+
+use montrs_orm::{DbBackend, DbError, DbPoolConfig, FromRow, SqliteBackend};
+use std::time::Duration;
+
+struct Count(i64);
+
+impl FromRow for Count {
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Count(row.get(0)?))
+    }
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        Ok(Count(row.get(0)))
+    }
+}
+
+async fn count(db: &impl DbBackend) -> Result<i64, DbError> {
+    Ok(db.query::<Count>("SELECT COUNT(*) FROM todos", &[]).await?[0].0)
+}
+
+async fn file_db(name: &str) -> Result<(SqliteBackend, std::path::PathBuf), DbError> {
+    let dir = std::env::temp_dir().join(format!("montrs-pool-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let db = SqliteBackend::new(dir.join("app.db").to_str().unwrap())?;
+    db.execute("CREATE TABLE todos (id INTEGER PRIMARY KEY, title TEXT NOT NULL)", &[]).await?;
+    Ok((db, dir))
+}
+
+#[test]
+fn test_pool_size() -> Result<(), DbError> {
+    assert_eq!(SqliteBackend::new(":memory:")?.with_pool_size(8).pool_size(), 1);
+    let path = std::env::temp_dir().join(format!("montrs-pool-size-{}.db", std::process::id()));
+    let db = SqliteBackend::new(path.to_str().unwrap())?;
+    assert_eq!(db.pool_size(), 4);
+    assert_eq!(db.with_pool_size(2).pool_size(), 2);
+    std::fs::remove_file(path).unwrap();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_reads_run_while_a_transaction_is_open() -> Result<(), DbError> {
+    let (db, dir) = file_db("tx").await?;
+    let tx = db.transaction().await?;
+    tx.execute("INSERT INTO todos (title) VALUES (?)", &[&"milk"]).await?;
+
+    // Other connections keep reading the last committed state.
+    let readers: Vec<_> = (0..8)
+        .map(|_| {
+            let db = db.clone();
+            tokio::spawn(async move { count(&db).await })
+        })
+        .collect();
+    for reader in readers {
+        assert_eq!(reader.await.unwrap()?, 0);
+    }
+    assert_eq!(count(&tx).await?, 1);
+
+    tx.commit().await?;
+    assert_eq!(count(&db).await?, 1);
+    std::fs::remove_dir_all(dir).unwrap();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_writes_take_turns() -> Result<(), DbError> {
+    let (db, dir) = file_db("writes").await?;
+    let writers: Vec<_> = (0..16)
+        .map(|i| {
+            let db = db.clone();
+            tokio::spawn(async move { db.execute("INSERT INTO todos (title) VALUES (?)", &[&format!("todo {}", i)]).await })
+        })
+        .collect();
+    for writer in writers {
+        assert_eq!(writer.await.unwrap()?, 1);
+    }
+    assert_eq!(count(&db).await?, 16);
+    std::fs::remove_dir_all(dir).unwrap();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_pool_config_bounds_the_wait_for_a_connection() -> Result<(), DbError> {
+    let config = DbPoolConfig { acquire_timeout: Duration::from_millis(20), ..DbPoolConfig::default() };
+    let db = SqliteBackend::new(":memory:")?.with_pool_config(&config);
+    db.execute("CREATE TABLE todos (id INTEGER PRIMARY KEY, title TEXT NOT NULL)", &[]).await?;
+    assert_eq!(db.pool_size(), 1, "an in-memory database keeps its one connection");
+
+    // The transaction holds the only connection.
+    let tx = db.transaction().await?;
+    assert!(matches!(count(&db).await, Err(DbError::Connection(_))));
+    tx.rollback().await?;
+    assert_eq!(count(&db).await?, 0);
+    db.ping().await
+}
+
+#[tokio::test]
+async fn test_idle_connections_are_checked_before_reuse() -> Result<(), DbError> {
+    let (db, dir) = file_db("health").await?;
+    let config = DbPoolConfig { max_connections: 2, health_check_interval: Some(Duration::ZERO), ..DbPoolConfig::default() };
+    let db = db.with_pool_config(&config);
+    assert_eq!(db.pool_size(), 2);
+    for _ in 0..3 {
+        assert_eq!(count(&db).await?, 0);
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+    Ok(())
+}
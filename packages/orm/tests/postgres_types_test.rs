@@ -0,0 +1,49 @@
+#![cfg(all(feature = "postgres", feature = "chrono"))]
+
+use bytes::BytesMut;
+use montrs_orm::ToSql;
+use tokio_postgres::types::{IsNull, Type};
+
+/// Encodes `value` as a parameter of type `ty`, the way the driver does
+/// before sending it; `None` is `NULL`.
+fn bind(value: &dyn ToSql, ty: &Type) -> Result<Option<Vec<u8>>, String> {
+    let mut out = BytesMut::new();
+    match value.as_postgres().to_sql_checked(ty, &mut out) {
+        Ok(IsNull::No) => Ok(Some(out.to_vec())),
+        Ok(IsNull::Yes) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[test]
+fn test_parameters_bind_to_the_column_types_postgres_checks() {
+    assert_eq!(
+        bind(&-7i16, &Type::INT2),
+        Ok(Some((-7i16).to_be_bytes().to_vec()))
+    );
+    assert_eq!(
+        bind(&0.5f32, &Type::FLOAT4),
+        Ok(Some(0.5f32.to_be_bytes().to_vec()))
+    );
+    assert_eq!(
+        bind(&1.5f64, &Type::FLOAT8),
+        Ok(Some(1.5f64.to_be_bytes().to_vec()))
+    );
+
+    // Timestamps are microseconds since 2000-01-01.
+    let at = chrono::DateTime::from_timestamp(946_684_801, 0).unwrap();
+    assert_eq!(
+        bind(&at, &Type::TIMESTAMPTZ),
+        Ok(Some(1_000_000i64.to_be_bytes().to_vec()))
+    );
+
+    assert_eq!(bind(&None::<i16>, &Type::INT2), Ok(None));
+    assert_eq!(
+        bind(&None::<chrono::DateTime<chrono::Utc>>, &Type::TIMESTAMPTZ),
+        Ok(None)
+    );
+
+    // A wider type than the column's is refused, which is why the narrow ones bind.
+    assert!(bind(&7i32, &Type::INT2).is_err());
+    assert!(bind(&0.5f64, &Type::FLOAT4).is_err());
+}
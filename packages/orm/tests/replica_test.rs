@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use montrs_core::{EnvConfig, EnvError};
+use montrs_orm::replica::is_read;
+use montrs_orm::{DbBackend, DbError, FromRow, ReplicaConfig, ReplicatedBackend, ToSql};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+struct Row;
+
+impl FromRow for Row {
+    #[cfg(feature = "sqlite")]
+    fn from_row_sqlite(_row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Row)
+    }
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(_row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        Ok(Row)
+    }
+}
+
+/// Counts the statements it receives; fails to connect while `down` is set.
+#[derive(Clone, Default)]
+struct FakeDb {
+    calls: Arc<AtomicUsize>,
+    down: Arc<AtomicBool>,
+}
+
+impl FakeDb {
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl DbBackend for FakeDb {
+    async fn execute(&self, _sql: &str, _params: &[&dyn ToSql]) -> Result<usize, DbError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(1)
+    }
+
+    async fn query<T: FromRow>(&self, _sql: &str, _params: &[&dyn ToSql]) -> Result<Vec<T>, DbError> {
+        if self.down.load(Ordering::SeqCst) {
+            return Err(DbError::Connection("refused".to_string()));
+        }
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(Vec::new())
+    }
+}
+
+fn cluster() -> (FakeDb, FakeDb, FakeDb, ReplicatedBackend<FakeDb>) {
+    let (primary, a, b) = (FakeDb::default(), FakeDb::default(), FakeDb::default());
+    let db = ReplicatedBackend::new(primary.clone(), vec![a.clone(), b.clone()]);
+    (primary, a, b, db)
+}
+
+#[tokio::test]
+async fn test_reads_rotate_over_replicas_and_writes_hit_primary() {
+    let (primary, a, b, db) = cluster();
+    for _ in 0..4 {
+        db.query::<Row>("SELECT * FROM todos", &[]).await.unwrap();
+    }
+    db.execute("UPDATE todos SET done = 1", &[]).await.unwrap();
+    db.query::<Row>("INSERT INTO todos (title) VALUES ('x') RETURNING id", &[]).await.unwrap();
+    assert_eq!((primary.calls(), a.calls(), b.calls()), (2, 2, 2));
+
+    db.on_primary().query::<Row>("SELECT * FROM todos", &[]).await.unwrap();
+    assert_eq!(primary.calls(), 3);
+}
+
+#[tokio::test]
+async fn test_reads_stick_to_primary_after_a_write() {
+    let (primary, a, b, db) = cluster();
+    let db = db.with_stickiness(Duration::from_secs(60));
+    db.query::<Row>("SELECT 1", &[]).await.unwrap();
+    db.execute("DELETE FROM todos", &[]).await.unwrap();
+    db.query::<Row>("SELECT 1", &[]).await.unwrap();
+    assert_eq!(primary.calls(), 2);
+    assert_eq!(a.calls() + b.calls(), 1);
+}
+
+#[tokio::test]
+async fn test_failed_replica_is_skipped_then_primary_takes_over() {
+    let (primary, a, b, db) = cluster();
+    a.down.store(true, Ordering::SeqCst);
+    db.query::<Row>("SELECT 1", &[]).await.unwrap();
+    db.query::<Row>("SELECT 1", &[]).await.unwrap();
+    assert_eq!((a.calls(), b.calls()), (0, 2));
+    assert_eq!(db.healthy_replicas(), 1);
+
+    b.down.store(true, Ordering::SeqCst);
+    db.query::<Row>("SELECT 1", &[]).await.unwrap();
+    assert_eq!(primary.calls(), 1);
+    assert_eq!(db.healthy_replicas(), 0);
+}
+
+#[test]
+fn test_read_detection() {
+    assert!(is_read("select * from todos"));
+    assert!(is_read("WITH t AS (SELECT 1) SELECT * FROM t"));
+    assert!(!is_read("SELECT * FROM todos FOR UPDATE"));
+    assert!(!is_read("WITH gone AS (DELETE FROM todos RETURNING id) SELECT count(*) FROM gone"));
+    assert!(!is_read("INSERT INTO todos DEFAULT VALUES"));
+}
+
+struct Env(HashMap<&'static str, &'static str>);
+
+impl EnvConfig for Env {
+    fn get_var(&self, key: &str) -> Result<String, EnvError> {
+        self.0.get(key).map(|v| v.to_string()).ok_or_else(|| EnvError::MissingKey(key.to_string()))
+    }
+}
+
+#[test]
+fn test_config_from_env() {
+    let env = Env(HashMap::from([
+        ("DATABASE_URL", "postgres://primary/app"),
+        ("DATABASE_REPLICA_URLS", "postgres://r1/app, postgres://r2/app"),
+        ("DATABASE_REPLICA_STICKY_MS", "250"),
+    ]));
+    let config = ReplicaConfig::from_env(&env).unwrap();
+    assert_eq!(config.replica_urls, ["postgres://r1/app", "postgres://r2/app"]);
+    assert_eq!(config.sticky_for, Duration::from_millis(250));
+    assert_eq!(config.retry_after, Duration::from_secs(30));
+    assert!(ReplicaConfig::from_env(&Env(HashMap::new())).is_err());
+}
@@ -0,0 +1,129 @@
+use async_trait::async_trait;
+use montrs_core::{EnvConfig, EnvError};
+use montrs_orm::{DbBackend, DbError, DbPoolConfig, FromRow, RetryPolicy, RetryingBackend, ToSql};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+struct Row;
+
+impl FromRow for Row {
+    #[cfg(feature = "sqlite")]
+    fn from_row_sqlite(_row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Row)
+    }
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(_row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        Ok(Row)
+    }
+}
+
+/// Fails the first `failures` statements with `error`, then succeeds.
+#[derive(Clone)]
+struct FlakyDb {
+    calls: Arc<AtomicUsize>,
+    failures: usize,
+    error: fn() -> DbError,
+}
+
+impl FlakyDb {
+    fn new(failures: usize) -> Self {
+        Self { calls: Arc::default(), failures, error: || DbError::Connection("connection reset".to_string()) }
+    }
+
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    fn attempt(&self) -> Result<(), DbError> {
+        if self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+            return Err((self.error)());
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DbBackend for FlakyDb {
+    async fn execute(&self, _sql: &str, _params: &[&dyn ToSql]) -> Result<usize, DbError> {
+        self.attempt().map(|_| 1)
+    }
+
+    async fn query<T: FromRow>(&self, _sql: &str, _params: &[&dyn ToSql]) -> Result<Vec<T>, DbError> {
+        self.attempt().map(|_| Vec::new())
+    }
+}
+
+fn policy(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy { max_attempts, initial_backoff: Duration::from_millis(1), max_backoff: Duration::from_millis(4) }
+}
+
+#[tokio::test]
+async fn test_reads_are_retried_until_the_connection_recovers() {
+    let flaky = FlakyDb::new(2);
+    let db = RetryingBackend::new(flaky.clone(), policy(3));
+    assert!(db.query::<Row>("SELECT * FROM todos", &[]).await.is_ok());
+    assert_eq!(flaky.calls(), 3);
+    assert!(db.ping().await.is_ok(), "ping goes through the same retries");
+
+    let down = FlakyDb::new(usize::MAX);
+    let db = RetryingBackend::new(down.clone(), policy(3));
+    assert!(matches!(db.query::<Row>("SELECT 1", &[]).await, Err(DbError::Connection(_))));
+    assert_eq!(down.calls(), 3, "gives up after max_attempts");
+}
+
+#[tokio::test]
+async fn test_writes_and_other_errors_are_not_retried() {
+    let flaky = FlakyDb::new(1);
+    let db = RetryingBackend::new(flaky.clone(), policy(3));
+    assert!(db.execute("INSERT INTO todos (title) VALUES ('milk')", &[]).await.is_err());
+    assert!(db.query::<Row>("DELETE FROM todos RETURNING id", &[]).await.is_ok());
+    assert_eq!(flaky.calls(), 2, "a write that may have been applied runs once");
+
+    let flaky = FlakyDb::new(1);
+    let db = RetryingBackend::new(flaky.clone(), policy(3)).retry_writes();
+    assert_eq!(db.execute("INSERT INTO todos (title) VALUES ('milk')", &[]).await.unwrap(), 1);
+    assert_eq!(flaky.calls(), 2);
+
+    let broken = FlakyDb { error: || DbError::Query("no such table: todos".to_string()), ..FlakyDb::new(1) };
+    let db = RetryingBackend::new(broken.clone(), policy(3));
+    assert!(matches!(db.query::<Row>("SELECT * FROM todos", &[]).await, Err(DbError::Query(_))));
+    assert_eq!(broken.calls(), 1);
+}
+
+#[test]
+fn test_backoff_doubles_up_to_the_cap() {
+    let policy = RetryPolicy { max_attempts: 5, initial_backoff: Duration::from_millis(50), max_backoff: Duration::from_millis(300) };
+    let waits: Vec<_> = (1..=5).map(|attempt| policy.backoff(attempt).as_millis()).collect();
+    assert_eq!(waits, [50, 100, 200, 300, 300]);
+}
+
+struct MapEnv(HashMap<&'static str, &'static str>);
+
+impl EnvConfig for MapEnv {
+    fn get_var(&self, key: &str) -> Result<String, EnvError> {
+        self.0.get(key).map(|v| v.to_string()).ok_or_else(|| EnvError::MissingKey(key.to_string()))
+    }
+}
+
+#[test]
+fn test_pool_config_from_env() {
+    let config = DbPoolConfig::from_env(&MapEnv(HashMap::new())).unwrap();
+    assert_eq!(config, DbPoolConfig::default());
+
+    let env = MapEnv(HashMap::from([
+        ("DATABASE_MAX_CONNECTIONS", "16"),
+        ("DATABASE_ACQUIRE_TIMEOUT_MS", "250"),
+        ("DATABASE_HEALTH_CHECK_MS", "0"),
+        ("DATABASE_RETRY_ATTEMPTS", "5"),
+    ]));
+    let config = DbPoolConfig::from_env(&env).unwrap();
+    assert_eq!(config.max_connections, 16);
+    assert_eq!(config.acquire_timeout, Duration::from_millis(250));
+    assert_eq!(config.health_check_interval, None);
+    assert_eq!(config.retry.max_attempts, 5);
+
+    let env = MapEnv(HashMap::from([("DATABASE_ACQUIRE_TIMEOUT_MS", "soon")]));
+    assert!(matches!(DbPoolConfig::from_env(&env), Err(DbError::Connection(_))));
+}
@@ -0,0 +1,89 @@
+#![cfg(all(feature = "sqlite", feature = "seed"))]
+
+use montrs_orm::seed::SEED_DIR_VAR;
+use montrs_orm::{DbBackend, DbError, FromRow, SeedValue, Seeds, SqliteBackend};
+
+#[derive(Debug, PartialEq)]
+struct Todo {
+    id: i64,
+    title: String,
+    done: bool,
+    owner: Option<i64>,
+}
+
+impl FromRow for Todo {
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Todo { id: row.get(0)?, title: row.get(1)?, done: row.get(2)?, owner: row.get(3)? })
+    }
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        Ok(Todo { id: row.get(0), title: row.get(1), done: row.get(2), owner: row.get(3) })
+    }
+}
+
+async fn database() -> SqliteBackend {
+    let db = SqliteBackend::new(":memory:").unwrap();
+    db.execute("CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL, tags TEXT)", &[]).await.unwrap();
+    db.execute(
+        "CREATE TABLE todos (id INTEGER PRIMARY KEY, title TEXT NOT NULL, done BOOLEAN NOT NULL DEFAULT 0, owner INTEGER REFERENCES users(id))",
+        &[],
+    )
+    .await
+    .unwrap();
+    db
+}
+
+#[test]
+fn test_parses_toml_and_yaml() {
+    let seeds = Seeds::from_toml("table = \"users\"\n[[rows]]\nid = 1\nname = \"Ada\"\ntags = [\"admin\"]\nscore = 1.5\n").unwrap();
+    let row = &seeds.tables[0].rows[0];
+    assert_eq!(row["id"], SeedValue::Int(1));
+    assert_eq!(row["tags"], SeedValue::Text(r#"["admin"]"#.to_string()));
+    assert_eq!(row["score"], SeedValue::Float(1.5));
+
+    let seeds = Seeds::from_yaml("table: todos\nkey: [id]\nrows:\n  - id: 1\n    title: Write docs\n    owner: null\n").unwrap();
+    assert_eq!(seeds.tables[0].key, ["id"]);
+    assert_eq!(seeds.tables[0].rows[0]["owner"], SeedValue::Null);
+    assert_eq!(seeds.len(), 1);
+
+    assert!(matches!(Seeds::from_toml("[[rows]]\nid = 1\n"), Err(DbError::Seed(ref m)) if m.contains("table")));
+}
+
+#[tokio::test]
+async fn test_applies_a_directory_in_order() {
+    let dir = std::env::temp_dir().join(format!("montrs-seed-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("02_todos.yaml"),
+        "table: todos\nkey: [id]\nrows:\n  - { id: 1, title: Try MontRS, owner: 1 }\n  - { id: 2, title: Ship it, done: true, owner: 1 }\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("01_users.toml"), "table = \"users\"\nkey = [\"id\"]\n[[rows]]\nid = 1\nname = \"Ada\"\n").unwrap();
+    std::fs::write(dir.join("notes.md"), "not a seed").unwrap();
+
+    let seeds = Seeds::load_dir(&dir).unwrap();
+    let tables: Vec<&str> = seeds.tables.iter().map(|t| t.table.as_str()).collect();
+    assert_eq!(tables, ["users", "todos"]);
+
+    let db = database().await;
+    assert_eq!(seeds.apply(&db).await.unwrap(), 3);
+    // Keyed tables can be seeded again without duplicates.
+    seeds.apply(&db).await.unwrap();
+    let todos: Vec<Todo> = db.query("SELECT id, title, done, owner FROM todos ORDER BY id", &[]).await.unwrap();
+    assert_eq!(todos.len(), 2);
+    assert_eq!(todos[0], Todo { id: 1, title: "Try MontRS".to_string(), done: false, owner: Some(1) });
+    assert!(todos[1].done);
+
+    unsafe {
+        std::env::set_var(SEED_DIR_VAR, &dir);
+    }
+    assert_eq!(Seeds::from_env().unwrap().unwrap(), seeds);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_errors_name_the_table_and_file() {
+    let seeds = Seeds::from_toml("table = \"missing\"\n[[rows]]\nid = 1\n").unwrap();
+    let err = seeds.apply(&database().await).await.unwrap_err();
+    assert!(matches!(err, DbError::Seed(ref m) if m.starts_with("missing:")), "{}", err);
+}
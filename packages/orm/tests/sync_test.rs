@@ -0,0 +1,53 @@
+#![cfg(feature = "sqlite")]
+
+use montrs_core::sync::{SyncRow, SyncStore};
+use montrs_orm::{DbError, SqliteBackend, SyncTable};
+use serde_json::json;
+
+fn row(id: &str, title: &str, dirty: bool) -> SyncRow {
+    serde_json::from_value(json!({
+        "model": "todos",
+        "id": id,
+        "data": { "title": title },
+        "deleted": false,
+        "version": { "phone": 1 },
+        "dirty": dirty,
+        "updated_at": "2026-10-17T09:00:00Z",
+    }))
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_rows_keep_their_change_order_and_dirty_flags() -> Result<(), DbError> {
+    let db = SqliteBackend::new(":memory:")?;
+    let table = SyncTable::new(db).with_table("local_sync");
+    table.create_table().await?;
+    table.create_table().await?;
+
+    assert_eq!(table.save(&row("1", "milk", true)).await.unwrap(), 1);
+    assert_eq!(table.save(&row("2", "bread", false)).await.unwrap(), 2);
+    let saved = table.get("todos", "1").await.unwrap().unwrap();
+    assert_eq!((saved.seq, saved.data["title"].as_str()), (1, Some("milk")));
+    assert_eq!(saved.version.get("phone"), 1);
+    assert_eq!(table.get("todos", "3").await.unwrap(), None);
+
+    // Saving again moves the row to the end of the change order.
+    let mut edited = row("1", "oat milk", false);
+    edited.version.bump("phone");
+    assert_eq!(table.save(&edited).await.unwrap(), 3);
+    let changes: Vec<_> = table.changes(1, 10).await.unwrap().into_iter().map(|r| (r.id, r.seq)).collect();
+    assert_eq!(changes, [("2".to_string(), 2), ("1".to_string(), 3)]);
+    assert!(table.dirty(10).await.unwrap().is_empty());
+
+    let deleted = SyncRow { deleted: true, data: json!(null), dirty: true, ..row("2", "", true) };
+    table.save(&deleted).await.unwrap();
+    let live: Vec<_> = table.rows("todos").await.unwrap().into_iter().map(|r| r.id).collect();
+    assert_eq!(live, ["1"]);
+    assert_eq!(table.dirty(usize::MAX).await.unwrap().len(), 1);
+
+    assert_eq!(table.cursor().await.unwrap(), 0);
+    table.set_cursor(42).await.unwrap();
+    table.set_cursor(43).await.unwrap();
+    assert_eq!(table.cursor().await.unwrap(), 43);
+    Ok(())
+}
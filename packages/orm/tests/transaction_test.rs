@@ -0,0 +1,80 @@
+#![cfg(feature = "sqlite")]
+
+use montrs_orm::{DbBackend, DbError, FromRow, Insert, SqliteBackend};
+
+struct Balance(String, i64);
+
+impl FromRow for Balance {
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Balance(row.get(0)?, row.get(1)?))
+    }
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        Ok(Balance(row.get(0), row.get(1)))
+    }
+}
+
+async fn accounts() -> Result<SqliteBackend, DbError> {
+    let db = SqliteBackend::new(":memory:")?;
+    db.execute("CREATE TABLE accounts (id TEXT PRIMARY KEY, balance INTEGER NOT NULL CHECK (balance >= 0))", &[])
+        .await?;
+    Insert::into("accounts", &["id", "balance"]).execute(&db, &[&[&"ada", &100], &[&"bob", &0]]).await?;
+    Ok(db)
+}
+
+async fn balances(db: &impl DbBackend) -> Result<Vec<(String, i64)>, DbError> {
+    let rows: Vec<Balance> = db.query("SELECT id, balance FROM accounts ORDER BY id", &[]).await?;
+    Ok(rows.into_iter().map(|Balance(id, balance)| (id, balance)).collect())
+}
+
+async fn transfer(db: &impl DbBackend, amount: i64) -> Result<(), DbError> {
+    db.execute("UPDATE accounts SET balance = balance + ? WHERE id = ?", &[&amount, &"bob"]).await?;
+    db.execute("UPDATE accounts SET balance = balance - ? WHERE id = ?", &[&amount, &"ada"]).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_commit_applies_every_statement() -> Result<(), DbError> {
+    let db = accounts().await?;
+    let tx = db.transaction().await?;
+    transfer(&tx, 40).await?;
+    assert_eq!(balances(&tx).await?, [("ada".to_string(), 60), ("bob".to_string(), 40)]);
+    tx.commit().await?;
+    assert_eq!(balances(&db).await?, [("ada".to_string(), 60), ("bob".to_string(), 40)]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rollback_and_drop_discard_the_statements() -> Result<(), DbError> {
+    let db = accounts().await?;
+    let before = balances(&db).await?;
+
+    let tx = db.transaction().await?;
+    transfer(&tx, 40).await?;
+    tx.rollback().await?;
+    assert_eq!(balances(&db).await?, before);
+
+    let tx = db.transaction().await?;
+    Insert::into("accounts", &["id", "balance"]).execute(&tx, &[&[&"cy", &5]]).await?;
+    drop(tx);
+    assert_eq!(balances(&db).await?, before);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_a_failed_statement_rolls_back_on_commit() -> Result<(), DbError> {
+    let db = accounts().await?;
+    let before = balances(&db).await?;
+
+    let tx = db.transaction().await?;
+    // Credits bob, then fails the balance check on ada.
+    assert!(transfer(&tx, 500).await.is_err());
+    let err = tx.commit().await.unwrap_err();
+    assert!(matches!(err, DbError::Query(ref m) if m.contains("rolled back")));
+    assert_eq!(balances(&db).await?, before);
+
+    let tx = db.transaction().await?;
+    assert!(matches!(tx.transaction().await, Err(DbError::Query(_))));
+    tx.rollback().await?;
+    Ok(())
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "sqlite")]
+
+use montrs_orm::transfer::{DataFormat, Export, Import, JsonRow, OnConflict};
+use montrs_orm::{DbBackend, DbError, SqliteBackend};
+
+async fn todos() -> Result<SqliteBackend, DbError> {
+    let db = SqliteBackend::new(":memory:")?;
+    db.execute("CREATE TABLE todos (id INTEGER PRIMARY KEY, title TEXT NOT NULL, note TEXT, done INTEGER NOT NULL)", &[])
+        .await?;
+    for id in 1..=5i64 {
+        let note: Option<String> = (id % 2 == 0).then(|| format!("line one\nsays \"{}\", ok", id));
+        db.execute("INSERT INTO todos (id, title, note, done) VALUES (?, ?, ?, ?)", &[&id, &format!("todo {}", id), &note, &(id % 2)])
+            .await?;
+    }
+    Ok(db)
+}
+
+async fn rows(db: &SqliteBackend) -> Result<Vec<JsonRow>, DbError> {
+    db.query("SELECT * FROM todos ORDER BY id", &[]).await
+}
+
+async fn export(db: &SqliteBackend, export: Export, format: DataFormat) -> Result<(Vec<u8>, Vec<u64>), DbError> {
+    let mut out = Vec::new();
+    let mut progress = Vec::new();
+    export.batch_rows(2).write(db, format, &mut out, |n| progress.push(n)).await?;
+    Ok((out, progress))
+}
+
+#[tokio::test]
+async fn test_tables_round_trip_through_both_formats() -> Result<(), DbError> {
+    let source = todos().await?;
+    for format in [DataFormat::Jsonl, DataFormat::Csv] {
+        let (file, progress) = export(&source, Export::table("todos").key(&["id"]), format).await?;
+        assert_eq!(progress, [2, 4, 5]);
+
+        let target = todos().await?;
+        target.execute("DELETE FROM todos", &[]).await?;
+        let read = Import::table("todos").key(&["id"]).batch_rows(2).read(&target, format, file.as_slice(), |_| {}).await?;
+        assert_eq!(read, 5);
+        assert_eq!(rows(&target).await?, rows(&source).await?, "{:?}", format);
+    }
+
+    let (csv, _) = export(&source, Export::table("todos"), DataFormat::Csv).await?;
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(csv.starts_with("done,id,note,title\n1,1,,todo 1\n0,2,\"line one\nsays \"\"2\"\", ok\",todo 2\n"), "{}", csv);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_conflict_strategies() -> Result<(), DbError> {
+    let file = "{\"id\":1,\"title\":\"renamed\",\"done\":1}\n\n{\"id\":9,\"title\":\"new\",\"done\":0}\n";
+    let titles = |rows: Vec<JsonRow>| rows.into_iter().map(|r| r.0["title"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+
+    let db = todos().await?;
+    let import = Import::table("todos").key(&["id"]);
+    import.clone().read(&db, DataFormat::Jsonl, file.as_bytes(), |_| {}).await?;
+    assert_eq!(titles(rows(&db).await?), ["todo 1", "todo 2", "todo 3", "todo 4", "todo 5", "new"]);
+
+    import.clone().on_conflict(OnConflict::Upsert).read(&db, DataFormat::Jsonl, file.as_bytes(), |_| {}).await?;
+    assert_eq!(titles(rows(&db).await?)[0], "renamed");
+
+    import.clone().on_conflict(OnConflict::Replace).read(&db, DataFormat::Jsonl, file.as_bytes(), |_| {}).await?;
+    assert_eq!(titles(rows(&db).await?), ["renamed", "new"]);
+
+    let upsert_without_key = Import::table("todos").on_conflict(OnConflict::Upsert);
+    assert!(matches!(upsert_without_key.read(&db, DataFormat::Jsonl, file.as_bytes(), |_| {}).await, Err(DbError::Transfer(_))));
+    let ragged = "id,title,done\n3,short\n";
+    assert!(matches!(import.read(&db, DataFormat::Csv, ragged.as_bytes(), |_| {}).await, Err(DbError::Transfer(_))));
+    Ok(())
+}
@@ -0,0 +1,104 @@
+#![cfg(feature = "sqlite")]
+
+use montrs_core::AgentError;
+use montrs_orm::{DbBackend, DbError, FromRow, SqliteBackend};
+
+/// A row of every column type the `values` table holds.
+#[derive(Debug, PartialEq)]
+struct Values {
+    big: i64,
+    ratio: f64,
+    blob: Vec<u8>,
+    note: Option<String>,
+}
+
+impl FromRow for Values {
+    fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Values { big: row.get(0)?, ratio: row.get(1)?, blob: row.get(2)?, note: row.get(3)? })
+    }
+    #[cfg(feature = "postgres")]
+    fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+        Ok(Values { big: row.get(0), ratio: row.get(1), blob: row.get(2), note: row.get(3) })
+    }
+}
+
+async fn values_table() -> SqliteBackend {
+    let db = SqliteBackend::new(":memory:").unwrap();
+    db.execute("CREATE TABLE \"values\" (big INTEGER, ratio REAL, blob BLOB, note TEXT)", &[]).await.unwrap();
+    db
+}
+
+#[tokio::test]
+async fn test_scalars_bytes_and_options_round_trip() {
+    let db = values_table().await;
+    let blob = vec![0u8, 159, 255];
+    let none: Option<String> = None;
+    db.execute("INSERT INTO \"values\" VALUES (?, ?, ?, ?)", &[&i64::MAX, &0.25, &blob, &none]).await.unwrap();
+    db.execute("INSERT INTO \"values\" VALUES (?, ?, ?, ?)", &[&-1i64, &1.5, &blob.as_slice(), &Some("hi")])
+        .await
+        .unwrap();
+
+    let rows: Vec<Values> = db.query("SELECT big, ratio, blob, note FROM \"values\" ORDER BY big DESC", &[]).await.unwrap();
+    assert_eq!(rows[0], Values { big: i64::MAX, ratio: 0.25, blob: blob.clone(), note: None });
+    assert_eq!(rows[1], Values { big: -1, ratio: 1.5, blob, note: Some("hi".to_string()) });
+}
+
+#[tokio::test]
+async fn test_narrow_ints_and_floats_bind() {
+    let db = values_table().await;
+    db.execute("INSERT INTO \"values\" (big, ratio) VALUES (?, ?)", &[&-7i16, &0.5f32]).await.unwrap();
+    let rows: Vec<Values> = db.query("SELECT big, ratio, x'', note FROM \"values\" WHERE big = ?", &[&-7i16]).await.unwrap();
+    assert_eq!((rows[0].big, rows[0].ratio), (-7, 0.5));
+}
+
+#[tokio::test]
+async fn test_mismatched_column_reports_a_type_error() {
+    let db = values_table().await;
+    db.execute("INSERT INTO \"values\" VALUES (1, 1.0, x'00', 'text')", &[]).await.unwrap();
+
+    // `note` holds TEXT, which does not read back as an i64.
+    let err = db.query::<Values>("SELECT note, ratio, blob, note FROM \"values\"", &[]).await.unwrap_err();
+    assert!(matches!(err, DbError::Type(ref m) if m.contains("note")), "{:?}", err);
+    assert_eq!(err.error_code(), "DB_TYPE");
+}
+
+#[cfg(feature = "decimal")]
+#[tokio::test]
+async fn test_decimal_is_rejected_on_sqlite() {
+    let db = values_table().await;
+    let price = rust_decimal::Decimal::new(1999, 2);
+    let err = db.execute("INSERT INTO \"values\" (note) VALUES (?)", &[&price]).await.unwrap_err();
+    assert!(matches!(err, DbError::Type(ref m) if m.contains("rust_decimal::Decimal") && m.contains("SQLite")), "{:?}", err);
+}
+
+#[cfg(all(feature = "uuid", feature = "chrono"))]
+#[tokio::test]
+async fn test_uuid_and_chrono_round_trip() {
+    use chrono::{DateTime, NaiveDate, Utc};
+
+    struct Event {
+        id: uuid::Uuid,
+        at: DateTime<Utc>,
+        day: NaiveDate,
+    }
+
+    impl FromRow for Event {
+        fn from_row_sqlite(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+            Ok(Event { id: row.get(0)?, at: row.get(1)?, day: row.get(2)? })
+        }
+        #[cfg(feature = "postgres")]
+        fn from_row_postgres(row: &tokio_postgres::Row) -> Result<Self, DbError> {
+            Ok(Event { id: row.get(0), at: row.get(1), day: row.get(2) })
+        }
+    }
+
+    let db = SqliteBackend::new(":memory:").unwrap();
+    db.execute("CREATE TABLE events (id BLOB, at TEXT, day TEXT)", &[]).await.unwrap();
+    let id = uuid::Uuid::from_u128(0x1234_5678_9abc_def0_1234_5678_9abc_def0);
+    let at = DateTime::parse_from_rfc3339("2024-05-01T12:30:00Z").unwrap().with_timezone(&Utc);
+    let day = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+    db.execute("INSERT INTO events VALUES (?, ?, ?)", &[&id, &at, &day]).await.unwrap();
+
+    let events: Vec<Event> = db.query("SELECT id, at, day FROM events", &[]).await.unwrap();
+    assert_eq!((events[0].id, events[0].at, events[0].day), (id, at, day));
+}
@@ -0,0 +1,53 @@
+#![cfg(all(feature = "sqlite", feature = "webhooks"))]
+
+use async_trait::async_trait;
+use montrs_core::webhook::{DeliveryStatus, Subscription, WebhookStore, WebhookTransport, Webhooks};
+use montrs_orm::{DbError, SqliteBackend, WebhookTables};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Fails every request to `down.example`.
+struct Transport;
+
+#[async_trait]
+impl WebhookTransport for Transport {
+    async fn post(&self, url: &str, _headers: &[(String, String)], _body: Vec<u8>) -> Result<u16, String> {
+        Ok(if url.contains("down.example") { 502 } else { 204 })
+    }
+}
+
+#[tokio::test]
+async fn test_deliveries_and_attempts_live_in_the_tables() -> Result<(), DbError> {
+    let db = SqliteBackend::new(":memory:")?;
+    let tables = Arc::new(WebhookTables::new(db.clone()).with_prefix("hooks"));
+    tables.create_tables().await?;
+    tables.create_tables().await?;
+    let webhooks = Webhooks::new(tables.clone())
+        .with_transport(Transport)
+        .with_retries(2, Duration::ZERO, Duration::ZERO);
+
+    let up = webhooks.subscribe(Subscription::new("https://up.example/hooks", "a")).await.unwrap();
+    let down = webhooks.subscribe(Subscription::new("https://down.example/hooks", "b")).await.unwrap();
+    let stored = tables.subscriptions().await.unwrap();
+    assert!(stored.len() == 2 && stored.contains(&up) && stored.contains(&down));
+
+    let queued = webhooks.publish("order.paid", serde_json::json!({ "order": 1 })).await.unwrap();
+    assert_eq!(queued.len(), 2);
+    assert_eq!(webhooks.deliver_due().await.unwrap(), 2);
+    assert_eq!(webhooks.deliver_due().await.unwrap(), 1);
+    assert!(tables.due(chrono::Utc::now(), 10).await.unwrap().is_empty());
+
+    let dead = tables.dead_letters().await.unwrap();
+    assert_eq!(dead.len(), 1);
+    assert_eq!((dead[0].subscription_id.as_str(), dead[0].attempts, dead[0].last_status), (down.id.as_str(), 2, Some(502)));
+    let delivered = queued.iter().find(|d| d.subscription_id == up.id).unwrap();
+    assert_eq!(tables.delivery(&delivered.id).await.unwrap().unwrap().status, DeliveryStatus::Delivered);
+
+    assert_eq!(tables.attempts(None, 10).await.unwrap().len(), 3);
+    let failed = tables.attempts(Some(&down.id), 10).await.unwrap();
+    assert_eq!(failed.iter().map(|a| a.attempt).collect::<Vec<_>>(), [2, 1]);
+
+    assert!(webhooks.unsubscribe(&down.id).await.unwrap());
+    assert_eq!(tables.subscriptions().await.unwrap(), vec![up]);
+    Ok(())
+}
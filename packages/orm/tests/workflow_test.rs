@@ -0,0 +1,48 @@
+#![cfg(feature = "sqlite")]
+
+use async_trait::async_trait;
+use montrs_core::workflow::{Saga, SagaStep, StepContext, WorkflowStatus, WorkflowStore, Workflows};
+use montrs_orm::{DbError, SqliteBackend, WorkflowTable};
+use std::sync::Arc;
+
+struct Count(&'static str);
+
+#[async_trait]
+impl SagaStep<Vec<String>> for Count {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+
+    async fn run(&self, _ctx: &StepContext, done: &mut Vec<String>) -> anyhow::Result<()> {
+        done.push(self.0.to_string());
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_records_survive_in_the_table() -> Result<(), DbError> {
+    let db = SqliteBackend::new(":memory:")?;
+    let table = Arc::new(WorkflowTable::new(db.clone()).with_table("flows"));
+    table.create_table().await?;
+    table.create_table().await?;
+    let workflows = Workflows::new(table.clone());
+
+    let record = workflows.start(&Saga::new("signup").step(Count("account")), Vec::new()).await.unwrap();
+    assert_eq!(record.status, WorkflowStatus::Completed);
+    let stored = workflows.status(&record.id).await.unwrap().unwrap();
+    assert_eq!(stored, record);
+    assert_eq!(table.get("missing").await.unwrap(), None);
+
+    // Pretend the process died before the second step.
+    let mut interrupted = record.clone();
+    interrupted.status = WorkflowStatus::Running;
+    table.save(&interrupted).await.unwrap();
+    assert_eq!(table.active("signup").await.unwrap().len(), 1);
+    assert!(table.active("other").await.unwrap().is_empty());
+
+    let saga = Saga::new("signup").step(Count("account")).step(Count("welcome_email"));
+    let resumed = workflows.resume(&saga).await.unwrap();
+    assert_eq!(resumed[0].state, serde_json::json!(["account", "welcome_email"]));
+    assert!(table.active("signup").await.unwrap().is_empty());
+    Ok(())
+}
@@ -0,0 +1,132 @@
+//! `#[derive(DomainError)]`: typed action errors with stable codes, sent to
+//! the client as a `montrs_core::ActionError`.
+
+use crate::SchemaError;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{Attribute, Data, DeriveInput};
+
+/// The attributes accepted inside `#[domain_error(...)]`, in the form they are written.
+pub(crate) const SUPPORTED_ATTRIBUTES: &str = "code = \"CODE\", status = N, field = \"name\"";
+
+/// What `#[domain_error(...)]` says about the enum's variant or the struct.
+struct ErrorAttrs {
+    code: String,
+    status: u16,
+    field: Option<String>,
+}
+
+pub(crate) fn expand(input: DeriveInput) -> TokenStream2 {
+    let name = &input.ident;
+    let mut errors: Vec<(SchemaError, Span)> = Vec::new();
+
+    // One match arm per variant; a struct is a single `Self { .. }` arm.
+    let arms: Vec<(TokenStream2, ErrorAttrs)> = match &input.data {
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .map(|variant| {
+                let ident = &variant.ident;
+                (quote!(Self::#ident { .. }), parse_attrs(&variant.attrs, &ident.to_string(), &mut errors))
+            })
+            .collect(),
+        Data::Struct(_) => vec![(quote!(Self { .. }), parse_attrs(&input.attrs, &name.to_string(), &mut errors))],
+        Data::Union(_) => {
+            errors.push((SchemaError::DomainErrorAttribute(format!("{} is a union; derive DomainError on an enum or a struct", name)), name.span()));
+            Vec::new()
+        }
+    };
+    if let Data::Enum(data) = &input.data {
+        if data.variants.is_empty() {
+            errors.push((SchemaError::DomainErrorAttribute(format!("{} has no variants", name)), name.span()));
+        }
+        if let Some(attr) = input.attrs.iter().find(|a| a.path().is_ident("domain_error")) {
+            errors.push((SchemaError::DomainErrorAttribute("put #[domain_error(...)] on the variants of an enum".to_string()), attr.span()));
+        }
+    }
+
+    if !errors.is_empty() {
+        let errors = errors.iter().map(|(error, span)| error.to_compile_error(*span));
+        return quote! { #(#errors)* };
+    }
+
+    let codes = arms.iter().map(|(pattern, attrs)| {
+        let code = &attrs.code;
+        quote!(#pattern => #code)
+    });
+    let statuses = arms.iter().map(|(pattern, attrs)| {
+        let status = attrs.status;
+        quote!(#pattern => #status)
+    });
+    let fields = arms.iter().map(|(pattern, attrs)| match &attrs.field {
+        Some(field) => quote!(#pattern => ::std::option::Option::Some(#field)),
+        None => quote!(#pattern => ::std::option::Option::None),
+    });
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::montrs_core::action_error::DomainError for #name #ty_generics #where_clause {
+            fn code(&self) -> &'static str {
+                match self { #(#codes,)* }
+            }
+
+            fn status(&self) -> u16 {
+                match self { #(#statuses,)* }
+            }
+
+            fn field(&self) -> ::std::option::Option<&'static str> {
+                match self { #(#fields,)* }
+            }
+        }
+
+        impl #impl_generics ::std::convert::From<#name #ty_generics> for ::montrs_core::RouteError #where_clause {
+            fn from(error: #name #ty_generics) -> Self {
+                ::montrs_core::action_error::ActionError::domain(&error).into()
+            }
+        }
+    }
+}
+
+fn parse_attrs(attrs: &[Attribute], ident: &str, errors: &mut Vec<(SchemaError, Span)>) -> ErrorAttrs {
+    let mut parsed = ErrorAttrs { code: screaming_snake_case(ident), status: 422, field: None };
+    for attr in attrs.iter().filter(|a| a.path().is_ident("domain_error")) {
+        let result = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("code") {
+                let lit = meta.value()?.parse::<syn::LitStr>()?;
+                match lit.value() {
+                    code if !code.is_empty() && code.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_') => parsed.code = code,
+                    code => errors.push((SchemaError::DomainErrorAttribute(format!("`{}` is not a code like OUT_OF_STOCK", code)), lit.span())),
+                }
+            } else if meta.path.is_ident("status") {
+                let lit = meta.value()?.parse::<syn::LitInt>()?;
+                match lit.base10_parse::<u16>() {
+                    Ok(status) if (400..600).contains(&status) => parsed.status = status,
+                    _ => errors.push((SchemaError::DomainErrorAttribute(format!("`{}` is not an error status (400-599)", lit)), lit.span())),
+                }
+            } else if meta.path.is_ident("field") {
+                parsed.field = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+            } else {
+                let path = &meta.path;
+                errors.push((SchemaError::DomainErrorAttribute(format!("`{}` is not a domain_error attribute", quote!(#path))), path.span()));
+                crate::skip_value(&meta)?;
+            }
+            Ok(())
+        });
+        if let Err(e) = result {
+            errors.push((SchemaError::DomainErrorAttribute(e.to_string()), e.span()));
+        }
+    }
+    parsed
+}
+
+/// `OutOfStock` as `OUT_OF_STOCK`.
+fn screaming_snake_case(ident: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in ident.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            out.push('_');
+        }
+        out.push(c.to_ascii_uppercase());
+    }
+    out
+}
@@ -0,0 +1,112 @@
+//! `#[derive(EncryptedModel)]`: encrypting a row type's `#[orm(encrypted)]`
+//! fields with a `montrs_orm::FieldCipher`.
+
+use crate::SchemaError;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Fields};
+
+/// The attributes accepted inside `#[orm(...)]`, in the form they are written.
+pub(crate) const SUPPORTED_ATTRIBUTES: &str = "table = \"name\", retain = \"90d\", retain_by = \"column\", retain_format = \"rfc3339|unix|unix_ms\" on the struct; encrypted, column = \"name\" on fields";
+
+/// Struct-level keys read by `#[derive(Retained)]`, which `EncryptedModel` skips.
+pub(crate) const RETENTION_ATTRIBUTES: [&str; 3] = ["retain", "retain_by", "retain_format"];
+
+pub(crate) fn expand(input: DeriveInput) -> TokenStream2 {
+    let name = &input.ident;
+    let mut errors: Vec<(SchemaError, Span)> = Vec::new();
+    let mut table = None;
+
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("orm")) {
+        let parsed = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("table") {
+                table = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+            } else if RETENTION_ATTRIBUTES.iter().any(|key| meta.path.is_ident(key)) {
+                crate::skip_value(&meta)?;
+            } else {
+                let path = &meta.path;
+                errors.push((SchemaError::OrmAttribute(format!("`{}` is not a struct-level attribute", quote!(#path))), path.span()));
+                crate::skip_value(&meta)?;
+            }
+            Ok(())
+        });
+        if let Err(e) = parsed {
+            errors.push((SchemaError::OrmAttribute(e.to_string()), e.span()));
+        }
+    }
+    let table = table.unwrap_or_else(|| snake_case(&name.to_string()));
+
+    let fields = match &input.data {
+        Data::Struct(syn::DataStruct { fields: Fields::Named(fields), .. }) => fields.named.iter().collect(),
+        _ => {
+            errors.push((SchemaError::InvalidStructType(format!("{} (EncryptedModel needs named fields)", name)), name.span()));
+            Vec::new()
+        }
+    };
+
+    let mut encrypted = Vec::new();
+    for field in fields {
+        let Some(ident) = field.ident.clone() else { continue };
+        let mut is_encrypted = false;
+        let mut column = None;
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("orm")) {
+            let parsed = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("encrypted") {
+                    is_encrypted = true;
+                } else if meta.path.is_ident("column") {
+                    column = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+                } else {
+                    let path = &meta.path;
+                    errors.push((SchemaError::OrmAttribute(format!("`{}` is not a field attribute", quote!(#path))), path.span()));
+                    crate::skip_value(&meta)?;
+                }
+                Ok(())
+            });
+            if let Err(e) = parsed {
+                errors.push((SchemaError::OrmAttribute(e.to_string()), e.span()));
+            }
+        }
+        if is_encrypted {
+            let column = column.unwrap_or_else(|| ident.to_string());
+            encrypted.push((ident, column));
+        }
+    }
+
+    if !errors.is_empty() {
+        let errors = errors.iter().map(|(error, span)| error.to_compile_error(*span));
+        return quote! { #(#errors)* };
+    }
+
+    let idents: Vec<_> = encrypted.iter().map(|(ident, _)| ident).collect();
+    let columns: Vec<_> = encrypted.iter().map(|(_, column)| column).collect();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::montrs_orm::EncryptedModel for #name #ty_generics #where_clause {
+            const TABLE: &'static str = #table;
+            const ENCRYPTED_COLUMNS: &'static [&'static str] = &[#(#columns),*];
+
+            fn encrypt_fields(&mut self, cipher: &::montrs_orm::FieldCipher) -> Result<(), ::montrs_orm::DbError> {
+                #(::montrs_orm::EncryptedField::encrypt_with(&mut self.#idents, cipher)?;)*
+                Ok(())
+            }
+
+            fn decrypt_fields(&mut self, cipher: &::montrs_orm::FieldCipher) -> Result<(), ::montrs_orm::DbError> {
+                #(::montrs_orm::EncryptedField::decrypt_with(&mut self.#idents, cipher)?;)*
+                Ok(())
+            }
+        }
+    }
+}
+
+/// `User` -> `user`, `AuditLog` -> `audit_log`.
+pub(crate) fn snake_case(ident: &str) -> String {
+    let mut name = String::new();
+    for (i, c) in ident.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            name.push('_');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    name
+}
@@ -1,25 +1,52 @@
 //! montrs-schema: Procedural macros for schema validation in MontRS.
 //! This crate provides the `#[derive(Schema)]` macro which generates
-//! compile-time validation logic for structs based on field attributes.
+//! compile-time validation logic for structs based on field attributes, and
+//! `#[derive(PlateConfig)]`, which loads a plate's section of `montrs.toml`,
+//! `#[derive(EncryptedModel)]`, which encrypts `#[orm(encrypted)]` fields,
+//! `#[derive(Retained)]`, which declares how long rows are kept,
+//! `#[derive(FromParam)]` and `#[derive(RouteParams)]` for typed route params,
+//! and `#[derive(DomainError)]` for typed action errors.
 
 extern crate proc_macro;
 use proc_macro::TokenStream;
-use quote::quote;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote, quote_spanned};
+use syn::meta::ParseNestedMeta;
 use syn::{Data, DeriveInput, Fields, LitInt, parse_macro_input};
 use montrs_core::AgentError;
 use thiserror::Error;
 
+mod domain_error;
+mod encrypted_model;
+mod param;
+mod plate_config;
+mod retained;
+
+/// The attributes accepted inside `#[schema(...)]`, in the form they are written.
+const SUPPORTED_ATTRIBUTES: &str =
+    "min_len = N, email, regex = \"pattern\", custom = \"method\", trim, lowercase, strip_html, transform = \"path::to::fn\"";
+
 /// Errors that can occur during schema derivation or validation setup.
 #[derive(Error, Debug)]
-pub enum SchemaError {
+enum SchemaError {
     #[error("Invalid struct type: {0}")]
     InvalidStructType(String),
     #[error("Missing field identifier: {0}")]
     MissingFieldIdent(String),
     #[error("Invalid regex pattern: {0}")]
     InvalidRegexPattern(String),
-    #[error("Unsupported schema attribute: {0}")]
+    #[error("Unsupported schema attribute `{0}`; supported attributes are {SUPPORTED_ATTRIBUTES}")]
     UnsupportedAttribute(String),
+    #[error("Malformed schema attribute: {0}; supported attributes are {SUPPORTED_ATTRIBUTES}")]
+    MalformedAttribute(String),
+    #[error("Invalid plate_config attribute: {0}; supported attributes are {}", plate_config::SUPPORTED_ATTRIBUTES)]
+    PlateConfigAttribute(String),
+    #[error("Invalid orm attribute: {0}; supported attributes are {}", encrypted_model::SUPPORTED_ATTRIBUTES)]
+    OrmAttribute(String),
+    #[error("Invalid param attribute: {0}; supported attributes are {}", param::SUPPORTED_ATTRIBUTES)]
+    ParamAttribute(String),
+    #[error("Invalid domain_error attribute: {0}; supported attributes are {}", domain_error::SUPPORTED_ATTRIBUTES)]
+    DomainErrorAttribute(String),
 }
 
 impl AgentError for SchemaError {
@@ -29,6 +56,11 @@ impl AgentError for SchemaError {
             SchemaError::MissingFieldIdent(_) => "SCHEMA_MISSING_FIELD_IDENT",
             SchemaError::InvalidRegexPattern(_) => "SCHEMA_INVALID_REGEX_PATTERN",
             SchemaError::UnsupportedAttribute(_) => "SCHEMA_UNSUPPORTED_ATTRIBUTE",
+            SchemaError::MalformedAttribute(_) => "SCHEMA_MALFORMED_ATTRIBUTE",
+            SchemaError::PlateConfigAttribute(_) => "SCHEMA_PLATE_CONFIG_ATTRIBUTE",
+            SchemaError::OrmAttribute(_) => "SCHEMA_ORM_ATTRIBUTE",
+            SchemaError::ParamAttribute(_) => "SCHEMA_PARAM_ATTRIBUTE",
+            SchemaError::DomainErrorAttribute(_) => "SCHEMA_DOMAIN_ERROR_ATTRIBUTE",
         }
     }
 
@@ -37,7 +69,12 @@ impl AgentError for SchemaError {
             SchemaError::InvalidStructType(t) => format!("The struct type '{}' is not supported for schema derivation. Only named-field structs are allowed.", t),
             SchemaError::MissingFieldIdent(f) => format!("The field '{}' is missing an identifier. Only named fields are allowed for schema derivation.", f),
             SchemaError::InvalidRegexPattern(p) => format!("The regex pattern '{}' is invalid. Please provide a valid regex pattern.", p),
-            SchemaError::UnsupportedAttribute(a) => format!("The schema attribute '{}' is not supported. Supported attributes are min_len, email, regex, custom.", a),
+            SchemaError::UnsupportedAttribute(a) => format!("The schema attribute '{}' is not supported. Supported attributes are min_len, email, regex, custom, trim, lowercase, strip_html, transform.", a),
+            SchemaError::MalformedAttribute(reason) => format!("A schema attribute could not be parsed ({}). Supported attributes are {}.", reason, SUPPORTED_ATTRIBUTES),
+            SchemaError::PlateConfigAttribute(reason) => format!("A #[plate_config(...)] attribute is invalid ({}). Supported attributes are {}.", reason, plate_config::SUPPORTED_ATTRIBUTES),
+            SchemaError::OrmAttribute(reason) => format!("An #[orm(...)] attribute is invalid ({}). Supported attributes are {}.", reason, encrypted_model::SUPPORTED_ATTRIBUTES),
+            SchemaError::ParamAttribute(reason) => format!("A #[param(...)] attribute is invalid ({}). Supported attributes are {}.", reason, param::SUPPORTED_ATTRIBUTES),
+            SchemaError::DomainErrorAttribute(reason) => format!("A #[domain_error(...)] attribute is invalid ({}). Supported attributes are {}.", reason, domain_error::SUPPORTED_ATTRIBUTES),
         }
     }
 
@@ -54,9 +91,30 @@ impl AgentError for SchemaError {
                 "Check the regex pattern for syntax errors.".to_string(),
             ],
             SchemaError::UnsupportedAttribute(_) => vec![
-                "Use only supported schema attributes (min_len, email, regex, custom).".to_string(),
+                "Use only supported schema attributes (min_len, email, regex, custom, trim, lowercase, strip_html, transform).".to_string(),
                 "Check the schema attribute documentation for valid options.".to_string(),
             ],
+            SchemaError::MalformedAttribute(_) => vec![
+                format!("Write the attribute in one of the supported forms: {}.", SUPPORTED_ATTRIBUTES),
+                "Separate several attributes with commas, e.g. #[schema(min_len = 3, email)].".to_string(),
+            ],
+            SchemaError::PlateConfigAttribute(_) => vec![
+                format!("Use only the supported plate_config attributes: {}.", plate_config::SUPPORTED_ATTRIBUTES),
+                "Secrets are always required; read optional credentials into an Option field instead of giving a default.".to_string(),
+            ],
+            SchemaError::OrmAttribute(_) => vec![
+                format!("Use only the supported orm attributes: {}.", encrypted_model::SUPPORTED_ATTRIBUTES),
+                "Encrypted fields must be String or Option<String>.".to_string(),
+                "Retention periods are a number followed by m, h, d or w, e.g. #[orm(retain = \"90d\")].".to_string(),
+            ],
+            SchemaError::ParamAttribute(_) => vec![
+                format!("Use only the supported param attributes: {}.", param::SUPPORTED_ATTRIBUTES),
+                "Rename a parameter with #[serde(rename = \"...\")], which both the router and the spec read.".to_string(),
+            ],
+            SchemaError::DomainErrorAttribute(_) => vec![
+                format!("Use only the supported domain_error attributes: {}.", domain_error::SUPPORTED_ATTRIBUTES),
+                "Codes are upper case with underscores, e.g. #[domain_error(code = \"OUT_OF_STOCK\")]; without one the variant name is used.".to_string(),
+            ],
         }
     }
 
@@ -65,12 +123,49 @@ impl AgentError for SchemaError {
     }
 }
 
+impl SchemaError {
+    /// A `compile_error!` at `span`, followed by a deprecated constant used at
+    /// the same span whose note carries this error's metadata as JSON. rustc
+    /// reports the use as a warning next to the error, which is how the agent's
+    /// diagnostic parser learns the error code and fixes.
+    fn to_compile_error(&self, span: Span) -> TokenStream2 {
+        let error = syn::Error::new(span, self).to_compile_error();
+        let metadata = serde_json::json!({
+            "error_code": self.error_code(),
+            "explanation": self.explanation(),
+            "suggested_fixes": self.suggested_fixes(),
+            "subsystem": self.subsystem(),
+        });
+        let note = format!("{}{}", montrs_core::AGENT_ERROR_NOTE, metadata);
+        let code = format_ident!("{}", self.error_code(), span = span);
+        quote_spanned! {span=>
+            #error
+            const _: () = {
+                #[deprecated(note = #note)]
+                const #code: () = ();
+                #code
+            };
+        }
+    }
+}
+
 /// Procedural macro to derive validation logic for a struct.
 /// Supported attributes:
 /// - `#[schema(min_len = N)]`: Validates that a string has at least N characters.
 /// - `#[schema(email)]`: Basic check for the presence of an '@' character.
 /// - `#[schema(regex = "pattern")]`: Placeholder for regex-based validation.
 /// - `#[schema(custom = "fn_name")]`: Calls a custom validation method on the struct.
+///
+/// Transform attributes generate `Sanitize::sanitize`, which normalizes fields
+/// in attribute order and is meant to run before `validate`:
+/// - `#[schema(trim)]`: Trims surrounding whitespace.
+/// - `#[schema(lowercase)]`: Lowercases the value.
+/// - `#[schema(strip_html)]`: Removes HTML tags, comments and script/style contents.
+/// - `#[schema(transform = "path::to::fn")]`: Calls `fn(&mut FieldType)`.
+///
+/// The built-in transforms apply to `String`, `Option<String>` and `Vec<String>`.
+///
+/// Every invalid attribute is reported as a compile error at its own span.
 #[proc_macro_derive(Schema, attributes(schema))]
 pub fn derive_schema(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
@@ -78,95 +173,170 @@ pub fn derive_schema(input: TokenStream) -> TokenStream {
 
     let mut all_field_validations = Vec::new();
     let mut regex_statics = Vec::new();
+    let mut sanitize_steps = Vec::new();
+    let mut errors: Vec<(SchemaError, Span)> = Vec::new();
 
     // Parse the struct data and iterate over named fields.
-    if let Data::Struct(syn::DataStruct {
-        fields: Fields::Named(fields),
-        ..
-    }) = input.data
-    {
-        for f in fields.named {
-            let field_name = f.ident.expect("Named fields must have idents");
-            let field_name_str = field_name.to_string();
-
-            // Iterate over attributes on each field.
-            for attr in f.attrs {
-                if attr.path().is_ident("schema") {
-                    let _ = attr.parse_nested_meta(|meta| {
-                        if meta.path.is_ident("min_len") {
-                            let value = meta.value()?;
-                            let lit: LitInt = value.parse()?;
-                            let min = lit.base10_parse::<usize>()?;
-                            all_field_validations.push(quote! {
-                                if self.#field_name.len() < #min {
-                                    errors.push(::montrs_core::ValidationError::MinLength {
-                                        field: #field_name_str,
-                                        min: #min,
-                                        actual: self.#field_name.len(),
-                                    });
-                                }
-                            });
-                        } else if meta.path.is_ident("email") {
-                            all_field_validations.push(quote! {
-                                if !self.#field_name.contains('@') {
-                                    errors.push(::montrs_core::ValidationError::InvalidEmail {
-                                        field: #field_name_str,
-                                    });
-                                }
+    let fields = match input.data {
+        Data::Struct(syn::DataStruct { fields: Fields::Named(fields), .. }) => fields.named,
+        Data::Struct(syn::DataStruct { fields: Fields::Unit, .. }) => Default::default(),
+        Data::Struct(_) => {
+            errors.push((SchemaError::InvalidStructType(format!("tuple struct {}", name)), name.span()));
+            Default::default()
+        }
+        Data::Enum(_) => {
+            errors.push((SchemaError::InvalidStructType(format!("enum {}", name)), name.span()));
+            Default::default()
+        }
+        Data::Union(_) => {
+            errors.push((SchemaError::InvalidStructType(format!("union {}", name)), name.span()));
+            Default::default()
+        }
+    };
+
+    for f in fields {
+        let Some(field_name) = f.ident else {
+            errors.push((SchemaError::MissingFieldIdent(quote!(#f).to_string()), name.span()));
+            continue;
+        };
+        let field_name_str = field_name.to_string();
+
+        // Iterate over attributes on each field.
+        for attr in f.attrs {
+            if !attr.path().is_ident("schema") {
+                continue;
+            }
+            let parsed = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("min_len") {
+                    let value: syn::Expr = meta.value()?.parse()?;
+                    let Some(lit) = expect_lit::<LitInt>(&value, "a length, e.g. min_len = 3", &mut errors) else {
+                        return Ok(());
+                    };
+                    let min = match lit.base10_parse::<usize>() {
+                        Ok(min) => min,
+                        Err(e) => {
+                            errors.push((SchemaError::MalformedAttribute(e.to_string()), lit.span()));
+                            return Ok(());
+                        }
+                    };
+                    all_field_validations.push(quote! {
+                        if self.#field_name.len() < #min {
+                            errors.push(::montrs_core::ValidationError::MinLength {
+                                field: #field_name_str,
+                                min: #min,
+                                actual: self.#field_name.len(),
                             });
-                        } else if meta.path.is_ident("regex") {
-                            let value = meta.value()?;
-                            let lit: syn::LitStr = value.parse()?;
-                            let regex_str = lit.value();
-
-                            // Compile-time validation of the regex pattern.
-                            if let Err(e) = regex::Regex::new(&regex_str) {
-                                return Err(meta.error(format!("Invalid regex pattern: {}", e)));
-                            }
-
-                            // Generate a unique identifier for the static regex.
-                            let static_ident = syn::Ident::new(
-                                &format!("__REGEX_{}_{}", name, field_name).to_uppercase(),
-                                proc_macro2::Span::call_site(),
-                            );
-
-                            regex_statics.push(quote! {
-                                static #static_ident: ::std::sync::OnceLock<::regex::Regex> = ::std::sync::OnceLock::new();
+                        }
+                    });
+                } else if meta.path.is_ident("email") {
+                    all_field_validations.push(quote! {
+                        if !self.#field_name.contains('@') {
+                            errors.push(::montrs_core::ValidationError::InvalidEmail {
+                                field: #field_name_str,
                             });
+                        }
+                    });
+                } else if meta.path.is_ident("regex") {
+                    let value: syn::Expr = meta.value()?.parse()?;
+                    let Some(lit) = expect_lit::<syn::LitStr>(&value, "a pattern string, e.g. regex = \"^[a-z]+$\"", &mut errors) else {
+                        return Ok(());
+                    };
+                    let regex_str = lit.value();
 
-                            all_field_validations.push(quote! {
-                                let re = #static_ident.get_or_init(|| ::regex::Regex::new(#regex_str).unwrap());
-                                if !re.is_match(&self.#field_name) {
-                                    errors.push(::montrs_core::ValidationError::RegexMismatch {
-                                        field: #field_name_str,
-                                        pattern: #regex_str,
-                                    });
-                                }
+                    // Compile-time validation of the regex pattern.
+                    if let Err(e) = regex::Regex::new(&regex_str) {
+                        errors.push((SchemaError::InvalidRegexPattern(format!("`{}`: {}", regex_str, e)), lit.span()));
+                        return Ok(());
+                    }
+
+                    // Generate a unique identifier for the static regex.
+                    let static_ident = syn::Ident::new(
+                        &format!("__REGEX_{}_{}", name, field_name).to_uppercase(),
+                        proc_macro2::Span::call_site(),
+                    );
+
+                    regex_statics.push(quote! {
+                        static #static_ident: ::std::sync::OnceLock<::regex::Regex> = ::std::sync::OnceLock::new();
+                    });
+
+                    all_field_validations.push(quote! {
+                        let re = #static_ident.get_or_init(|| ::regex::Regex::new(#regex_str).unwrap());
+                        if !re.is_match(&self.#field_name) {
+                            errors.push(::montrs_core::ValidationError::RegexMismatch {
+                                field: #field_name_str,
+                                pattern: #regex_str,
                             });
-                        } else if meta.path.is_ident("custom") {
-                            let value = meta.value()?;
-                            let lit: syn::LitStr = value.parse()?;
-                            let custom_fn = syn::Ident::new(&lit.value(), lit.span());
-                            all_field_validations.push(quote! {
-                                if let Err(e) = self.#custom_fn() {
-                                    errors.push(::montrs_core::ValidationError::Custom {
-                                        field: #field_name_str,
-                                        message: e,
-                                    });
-                                }
+                        }
+                    });
+                } else if meta.path.is_ident("custom") {
+                    let value: syn::Expr = meta.value()?.parse()?;
+                    let Some(lit) = expect_lit::<syn::LitStr>(&value, "a method name string, e.g. custom = \"check_status\"", &mut errors) else {
+                        return Ok(());
+                    };
+                    let Ok(custom_fn) = lit.parse::<syn::Ident>() else {
+                        errors.push((SchemaError::MalformedAttribute(format!("`{}` is not a method name", lit.value())), lit.span()));
+                        return Ok(());
+                    };
+                    all_field_validations.push(quote! {
+                        if let Err(e) = self.#custom_fn() {
+                            errors.push(::montrs_core::ValidationError::Custom {
+                                field: #field_name_str,
+                                message: e,
                             });
                         }
-                        Ok(())
                     });
+                } else if meta.path.is_ident("trim") || meta.path.is_ident("lowercase") || meta.path.is_ident("strip_html") {
+                    let span = syn::spanned::Spanned::span(&meta.path);
+                    let transform = if meta.path.is_ident("trim") {
+                        quote!(|s: &str| s.trim().to_string())
+                    } else if meta.path.is_ident("lowercase") {
+                        quote!(|s: &str| s.to_lowercase())
+                    } else {
+                        quote!(::montrs_core::strip_html)
+                    };
+                    sanitize_steps.push(quote_spanned! {span=>
+                        ::montrs_core::SanitizeText::map_text(&mut self.#field_name, #transform);
+                    });
+                } else if meta.path.is_ident("transform") {
+                    let value: syn::Expr = meta.value()?.parse()?;
+                    let Some(lit) = expect_lit::<syn::LitStr>(&value, "a function path string, e.g. transform = \"slug::normalize\"", &mut errors) else {
+                        return Ok(());
+                    };
+                    let Ok(transform) = lit.parse::<syn::Path>() else {
+                        errors.push((SchemaError::MalformedAttribute(format!("`{}` is not a function path", lit.value())), lit.span()));
+                        return Ok(());
+                    };
+                    sanitize_steps.push(quote_spanned! {lit.span()=>
+                        #transform(&mut self.#field_name);
+                    });
+                } else {
+                    let path = &meta.path;
+                    errors.push((SchemaError::UnsupportedAttribute(quote!(#path).to_string()), syn::spanned::Spanned::span(path)));
+                    skip_value(&meta)?;
                 }
+                Ok(())
+            });
+            if let Err(e) = parsed {
+                errors.push((SchemaError::MalformedAttribute(e.to_string()), e.span()));
             }
         }
     }
 
-    // Generate the implementation of the Validate trait.
+    if !errors.is_empty() {
+        let errors = errors.iter().map(|(error, span)| error.to_compile_error(*span));
+        return TokenStream::from(quote! { #(#errors)* });
+    }
+
+    // Generate the implementations of the Validate and Sanitize traits.
     let expanded = quote! {
         #(#regex_statics)*
 
+        impl ::montrs_core::Sanitize for #name {
+            fn sanitize(&mut self) {
+                #(#sanitize_steps)*
+            }
+        }
+
         impl ::montrs_core::Validate for #name {
             fn validate(&self) -> Result<(), Vec<::montrs_core::ValidationError>> {
                 let mut errors = Vec::new();
@@ -184,3 +354,131 @@ pub fn derive_schema(input: TokenStream) -> TokenStream {
 
     TokenStream::from(expanded)
 }
+
+/// Derives `montrs_core::PlateConfig`, loading the struct from the plate's
+/// `[plates.<name>]` section of `montrs.toml` with environment overrides.
+/// Requires the `plate-config` feature of `montrs-core`.
+///
+/// On the struct:
+/// - `#[plate_config(plate = "name")]`: The section name. Defaults to the
+///   struct name in snake case without a `Config` suffix.
+/// - `#[plate_config(validate)]`: Runs `Validate::validate` after loading,
+///   e.g. from `#[derive(Schema)]`.
+///
+/// On fields, each of which is read from `MONTRS_<PLATE>_<FIELD>` first:
+/// - `#[plate_config(default)]`: Falls back to `Default::default()`.
+/// - `#[plate_config(default = value)]`: Falls back to `value`. A string is
+///   parsed like an environment value, so `default = "30"` works for a `u64`.
+/// - `#[plate_config(secret)]`: Never read from `montrs.toml`; set it in the
+///   environment or the secrets file.
+/// - `#[plate_config(env = "VAR")]`: Reads `VAR` instead of the default name.
+///
+/// Fields without a default are required unless they are an `Option`.
+#[proc_macro_derive(PlateConfig, attributes(plate_config))]
+pub fn derive_plate_config(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    TokenStream::from(plate_config::expand(input))
+}
+
+/// Derives `montrs_orm::EncryptedModel`, so the fields marked
+/// `#[orm(encrypted)]` are encrypted before writes and decrypted after reads
+/// with a `montrs_orm::FieldCipher`. Requires `montrs-orm`.
+///
+/// On the struct:
+/// - `#[orm(table = "name")]`: The table the rows live in. Defaults to the
+///   struct name in snake case.
+///
+/// On fields:
+/// - `#[orm(encrypted)]`: Stores the field encrypted. The field must be a
+///   `String` or `Option<String>`; encrypted columns cannot be filtered on.
+/// - `#[orm(column = "name")]`: The column name, if it differs from the field.
+#[proc_macro_derive(EncryptedModel, attributes(orm))]
+pub fn derive_encrypted_model(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    TokenStream::from(encrypted_model::expand(input))
+}
+
+/// Derives `montrs_orm::lifecycle::Retained`, so `montrs_orm::Retention`
+/// deletes the rows once they are older than the retention period. Requires
+/// `montrs-orm` with the `lifecycle` feature.
+///
+/// On the struct:
+/// - `#[orm(retain = "90d")]`: How long rows are kept: a number followed by
+///   `m`, `h`, `d` or `w`. Required.
+/// - `#[orm(retain_by = "column")]`: The timestamp column the age of a row is
+///   taken from. Defaults to `created_at`.
+/// - `#[orm(retain_format = "unix_ms")]`: How that column is stored: `rfc3339`
+///   (the default), `unix` or `unix_ms`.
+/// - `#[orm(table = "name")]`: As for `EncryptedModel`.
+#[proc_macro_derive(Retained, attributes(orm))]
+pub fn derive_retained(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    TokenStream::from(retained::expand(input))
+}
+
+/// Derives `montrs_core::FromParam` for a newtype, parsing the parameter as
+/// the wrapped type, plus `Serialize` and `Deserialize` as that type. The
+/// struct's doc comment becomes the schema description.
+///
+/// - `#[param(pattern = "regex")]`: The text must match before it is parsed.
+/// - `#[param(format = "name")]`: The schema format, e.g. `uuid` or `slug`.
+/// - `#[param(validate = "path::to::fn")]`: Calls `fn(&Inner) -> Result<(), E>`
+///   after parsing; `E`'s message becomes the validation error.
+#[proc_macro_derive(FromParam, attributes(param))]
+pub fn derive_from_param(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    TokenStream::from(param::expand_from_param(input))
+}
+
+/// Derives `montrs_core::RouteParams`, listing every field in the
+/// `RouterSpec` with its type's `ParamSchema`. Each field's type must
+/// implement `FromParam`; `Option` fields are optional, `#[serde(rename)]`
+/// is honoured and the field's doc comment becomes its description.
+///
+/// - `#[param(skip)]`: Leaves the field out of the spec.
+#[proc_macro_derive(RouteParams, attributes(param))]
+pub fn derive_route_params(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    TokenStream::from(param::expand_route_params(input))
+}
+
+/// Derives `montrs_core::DomainError` and `From<Self> for RouteError`, so an
+/// action can fail with the error through `?` and the client can decode it
+/// from the `ActionError` it receives. The type must also implement
+/// `Serialize`, `Deserialize` and `Display`. On each variant of an enum, or on
+/// a struct:
+///
+/// - `#[domain_error(code = "OUT_OF_STOCK")]`: The stable code. Defaults to
+///   the variant or struct name in upper snake case.
+/// - `#[domain_error(status = 409)]`: The HTTP status. Defaults to `422`.
+/// - `#[domain_error(field = "quantity")]`: The input field the error is shown on.
+#[proc_macro_derive(DomainError, attributes(domain_error))]
+pub fn derive_domain_error(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    TokenStream::from(domain_error::expand(input))
+}
+
+/// Parses an attribute value as a `T` literal, recording a spanned error when it
+/// is something else so the remaining attributes are still checked.
+fn expect_lit<T: syn::parse::Parse>(value: &syn::Expr, expected: &str, errors: &mut Vec<(SchemaError, Span)>) -> Option<T> {
+    match syn::parse2::<T>(quote!(#value)) {
+        Ok(lit) => Some(lit),
+        Err(_) => {
+            errors.push((SchemaError::MalformedAttribute(format!("expected {}", expected)), syn::spanned::Spanned::span(value)));
+            None
+        }
+    }
+}
+
+/// Consumes the `= value` or `(...)` of an unsupported attribute so the ones
+/// after it are still checked.
+fn skip_value(meta: &ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(syn::Token![=]) {
+        meta.value()?.parse::<syn::Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        let content;
+        syn::parenthesized!(content in meta.input);
+        content.parse::<TokenStream2>()?;
+    }
+    Ok(())
+}
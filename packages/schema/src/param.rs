@@ -0,0 +1,210 @@
+//! `#[derive(FromParam)]` for newtypes parsed from a path or query parameter,
+//! and `#[derive(RouteParams)]`, which lists a params struct's fields in the
+//! `RouterSpec`.
+
+use crate::SchemaError;
+use crate::plate_config::{doc_text, is_option};
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Fields};
+
+/// The attributes accepted inside `#[param(...)]`, in the form they are written.
+pub(crate) const SUPPORTED_ATTRIBUTES: &str =
+    "pattern = \"regex\", format = \"name\", validate = \"path::to::fn\" on FromParam newtypes; skip on RouteParams fields";
+
+pub(crate) fn expand_from_param(input: DeriveInput) -> TokenStream2 {
+    let name = &input.ident;
+    let mut errors: Vec<(SchemaError, Span)> = Vec::new();
+    let mut pattern = None;
+    let mut format = None;
+    let mut validate = None;
+
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("param")) {
+        let parsed = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("pattern") {
+                let lit = meta.value()?.parse::<syn::LitStr>()?;
+                match regex::Regex::new(&lit.value()) {
+                    Ok(_) => pattern = Some(lit.value()),
+                    Err(e) => errors.push((SchemaError::InvalidRegexPattern(e.to_string()), lit.span())),
+                }
+            } else if meta.path.is_ident("format") {
+                format = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+            } else if meta.path.is_ident("validate") {
+                validate = Some(meta.value()?.parse::<syn::LitStr>()?.parse::<syn::Path>()?);
+            } else {
+                let path = &meta.path;
+                errors.push((SchemaError::ParamAttribute(format!("`{}` is not a FromParam attribute", quote!(#path))), path.span()));
+                crate::skip_value(&meta)?;
+            }
+            Ok(())
+        });
+        if let Err(e) = parsed {
+            errors.push((SchemaError::ParamAttribute(e.to_string()), e.span()));
+        }
+    }
+
+    let inner = match &input.data {
+        _ if !input.generics.params.is_empty() => {
+            errors.push((SchemaError::InvalidStructType(format!("{} (FromParam newtypes cannot be generic)", name)), input.generics.span()));
+            None
+        }
+        Data::Struct(syn::DataStruct { fields: Fields::Unnamed(fields), .. }) if fields.unnamed.len() == 1 => {
+            Some(fields.unnamed[0].ty.clone())
+        }
+        _ => {
+            errors.push((SchemaError::InvalidStructType(format!("{} (FromParam needs a newtype, e.g. struct TodoId(u64))", name)), name.span()));
+            None
+        }
+    };
+
+    if !errors.is_empty() {
+        let errors = errors.iter().map(|(error, span)| error.to_compile_error(*span));
+        return quote! { #(#errors)* };
+    }
+    let inner = inner.expect("newtype checked above");
+
+    let check_pattern = pattern.as_ref().map(|pattern| {
+        quote! {
+            static PATTERN: ::std::sync::OnceLock<::montrs_core::param::ParamPattern> = ::std::sync::OnceLock::new();
+            PATTERN.get_or_init(|| ::montrs_core::param::ParamPattern::new(#pattern)).check::<Self>(value)?;
+        }
+    });
+    let check_validate = validate.map(|validate| {
+        quote! {
+            #validate(&inner).map_err(|reason| ::montrs_core::param::ParamError::invalid::<Self>(value, reason))?;
+        }
+    });
+    let set_format = format.map(|format| quote! { schema.format = Some(#format.to_string()); });
+    let set_pattern = pattern.map(|pattern| quote! { schema.pattern = Some(#pattern.to_string()); });
+    let description = doc_text(&input.attrs);
+    let set_description = (!description.is_empty()).then(|| quote! { schema.description = Some(#description.to_string()); });
+
+    quote! {
+        impl ::montrs_core::param::FromParam for #name {
+            fn from_param(value: &str) -> Result<Self, ::montrs_core::param::ParamError> {
+                #check_pattern
+                let inner = <#inner as ::montrs_core::param::FromParam>::from_param(value).map_err(|e| e.retype::<Self>())?;
+                #check_validate
+                Ok(Self(inner))
+            }
+
+            fn param_schema() -> ::montrs_core::param::ParamSchema {
+                #[allow(unused_mut)]
+                let mut schema = <#inner as ::montrs_core::param::FromParam>::param_schema();
+                #set_format
+                #set_pattern
+                #set_description
+                schema
+            }
+        }
+
+        impl ::montrs_core::param::__serde::Serialize for #name {
+            fn serialize<S: ::montrs_core::param::__serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                ::montrs_core::param::__serde::Serialize::serialize(&self.0, serializer)
+            }
+        }
+
+        impl<'de> ::montrs_core::param::__serde::Deserialize<'de> for #name {
+            fn deserialize<D: ::montrs_core::param::__serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                ::montrs_core::param::deserialize(deserializer)
+            }
+        }
+    }
+}
+
+pub(crate) fn expand_route_params(input: DeriveInput) -> TokenStream2 {
+    let name = &input.ident;
+    let mut errors: Vec<(SchemaError, Span)> = Vec::new();
+
+    let fields = match &input.data {
+        Data::Struct(syn::DataStruct { fields: Fields::Named(fields), .. }) => fields.named.iter().collect(),
+        Data::Struct(syn::DataStruct { fields: Fields::Unit, .. }) => Vec::new(),
+        _ => {
+            errors.push((SchemaError::InvalidStructType(format!("{} (RouteParams needs named fields)", name)), name.span()));
+            Vec::new()
+        }
+    };
+
+    let mut specs = Vec::new();
+    for field in fields {
+        let Some(ident) = &field.ident else { continue };
+        let param_name = serde_rename(&field.attrs).unwrap_or_else(|| ident.to_string());
+        let mut skip = false;
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("param")) {
+            let parsed = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                } else {
+                    let path = &meta.path;
+                    errors.push((SchemaError::ParamAttribute(format!("`{}` is not a RouteParams field attribute", quote!(#path))), path.span()));
+                    crate::skip_value(&meta)?;
+                }
+                Ok(())
+            });
+            if let Err(e) = parsed {
+                errors.push((SchemaError::ParamAttribute(e.to_string()), e.span()));
+            }
+        }
+        if skip {
+            continue;
+        }
+
+        let (ty, required) = match option_inner(&field.ty) {
+            Some(inner) => (inner, false),
+            None => (&field.ty, true),
+        };
+        let description = doc_text(&field.attrs);
+        let with_description = (!description.is_empty()).then(|| quote! { .with_description(#description) });
+        // Spanned so a type without `FromParam` is reported at the field.
+        specs.push(quote_spanned! {ty.span()=>
+            ::montrs_core::param::ParamSpec::of::<#ty>(#param_name, #required) #with_description
+        });
+    }
+
+    if !errors.is_empty() {
+        let errors = errors.iter().map(|(error, span)| error.to_compile_error(*span));
+        return quote! { #(#errors)* };
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::montrs_core::RouteParams for #name #ty_generics #where_clause {
+            fn params() -> Vec<::montrs_core::param::ParamSpec> {
+                vec![#(#specs),*]
+            }
+        }
+    }
+}
+
+/// `T` for an `Option<T>` field.
+fn option_inner(ty: &syn::Type) -> Option<&syn::Type> {
+    if !is_option(ty) {
+        return None;
+    }
+    let syn::Type::Path(path) = ty else { return None };
+    match &path.path.segments.last()?.arguments {
+        syn::PathArguments::AngleBracketed(args) => match args.args.first()? {
+            syn::GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The name from `#[serde(rename = "...")]`, which is what the field is
+/// called in the request.
+fn serde_rename(attrs: &[syn::Attribute]) -> Option<String> {
+    let mut rename = None;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") && meta.input.peek(syn::Token![=]) {
+                rename = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+            } else {
+                crate::skip_value(&meta)?;
+            }
+            Ok(())
+        });
+    }
+    rename
+}
@@ -0,0 +1,231 @@
+//! `#[derive(PlateConfig)]`: loading a plate's `[plates.<name>]` section of
+//! `montrs.toml`, with `MONTRS_<PLATE>_<FIELD>` environment overrides.
+
+use crate::SchemaError;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Fields};
+
+/// The attributes accepted inside `#[plate_config(...)]`, in the form they are written.
+pub(crate) const SUPPORTED_ATTRIBUTES: &str =
+    "plate = \"name\" and validate on the struct; default, default = value, secret, env = \"VAR\" on fields";
+
+/// How a field falls back when no source provides it.
+enum Fallback {
+    Required,
+    /// `#[plate_config(default)]`.
+    Default,
+    /// `#[plate_config(default = "...")]`, parsed like an environment value.
+    Raw(syn::LitStr),
+    /// `#[plate_config(default = <expr>)]`.
+    Expr(Box<syn::Expr>),
+}
+
+struct FieldConfig {
+    ident: syn::Ident,
+    secret: bool,
+    env_key: Option<String>,
+    fallback: Fallback,
+    optional: bool,
+    description: String,
+}
+
+pub(crate) fn expand(input: DeriveInput) -> TokenStream2 {
+    let name = &input.ident;
+    let mut errors: Vec<(SchemaError, Span)> = Vec::new();
+    let mut plate = None;
+    let mut validate = false;
+
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("plate_config")) {
+        let parsed = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("plate") {
+                plate = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+            } else if meta.path.is_ident("validate") {
+                validate = true;
+            } else {
+                let path = &meta.path;
+                errors.push((
+                    SchemaError::PlateConfigAttribute(format!("`{}` is not a struct-level attribute", quote!(#path))),
+                    path.span(),
+                ));
+                crate::skip_value(&meta)?;
+            }
+            Ok(())
+        });
+        if let Err(e) = parsed {
+            errors.push((SchemaError::PlateConfigAttribute(e.to_string()), e.span()));
+        }
+    }
+    let plate = plate.unwrap_or_else(|| default_plate_name(&name.to_string()));
+
+    let fields = match &input.data {
+        Data::Struct(syn::DataStruct { fields: Fields::Named(fields), .. }) => fields.named.iter().collect(),
+        Data::Struct(syn::DataStruct { fields: Fields::Unit, .. }) => Vec::new(),
+        _ => {
+            errors.push((SchemaError::InvalidStructType(format!("{} (PlateConfig needs named fields)", name)), name.span()));
+            Vec::new()
+        }
+    };
+
+    let mut configs = Vec::new();
+    for field in fields {
+        let Some(ident) = field.ident.clone() else { continue };
+        let mut config = FieldConfig {
+            ident,
+            secret: false,
+            env_key: None,
+            fallback: Fallback::Required,
+            optional: is_option(&field.ty),
+            description: doc_text(&field.attrs),
+        };
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("plate_config")) {
+            let parsed = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("secret") {
+                    config.secret = true;
+                } else if meta.path.is_ident("env") {
+                    config.env_key = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+                } else if meta.path.is_ident("default") {
+                    config.fallback = if meta.input.peek(syn::Token![=]) {
+                        match meta.value()?.parse::<syn::Expr>()? {
+                            syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(raw), .. }) => Fallback::Raw(raw),
+                            expr => Fallback::Expr(Box::new(expr)),
+                        }
+                    } else {
+                        Fallback::Default
+                    };
+                } else {
+                    let path = &meta.path;
+                    errors.push((
+                        SchemaError::PlateConfigAttribute(format!("`{}` is not a field attribute", quote!(#path))),
+                        path.span(),
+                    ));
+                    crate::skip_value(&meta)?;
+                }
+                Ok(())
+            });
+            if let Err(e) = parsed {
+                errors.push((SchemaError::PlateConfigAttribute(e.to_string()), e.span()));
+            }
+        }
+        if config.secret && !matches!(config.fallback, Fallback::Required) {
+            errors.push((
+                SchemaError::PlateConfigAttribute(format!("secret field `{}` cannot have a default", config.ident)),
+                config.ident.span(),
+            ));
+        }
+        configs.push(config);
+    }
+
+    if !errors.is_empty() {
+        let errors = errors.iter().map(|(error, span)| error.to_compile_error(*span));
+        return quote! { #(#errors)* };
+    }
+
+    let keys: Vec<String> = configs.iter().map(|c| c.ident.to_string()).collect();
+    let mut inits = Vec::new();
+    let mut descriptors = Vec::new();
+    for config in &configs {
+        let ident = &config.ident;
+        let key = ident.to_string();
+        let env_key = config
+            .env_key
+            .clone()
+            .unwrap_or_else(|| montrs_core::plate_config::env_key(&plate, &key));
+        let secret = config.secret;
+        let found = if config.optional { quote!(Some(value)) } else { quote!(value) };
+        let fallback = match &config.fallback {
+            Fallback::Required if config.optional => quote!(None),
+            Fallback::Required => quote!(return Err(section.missing(#key, #env_key))),
+            Fallback::Default => quote!(::std::default::Default::default()),
+            Fallback::Raw(raw) => quote!(section.default_value(#key, #raw)?),
+            Fallback::Expr(expr) => quote!(#expr),
+        };
+        inits.push(quote! {
+            #ident: match section.value(#key, #env_key, #secret)? {
+                Some(value) => #found,
+                None => #fallback,
+            }
+        });
+
+        let required = matches!(config.fallback, Fallback::Required) && !config.optional;
+        let default = match &config.fallback {
+            Fallback::Required => quote!(None),
+            Fallback::Default => quote!(Some("Default::default()")),
+            Fallback::Raw(raw) => quote!(Some(#raw)),
+            Fallback::Expr(expr) => {
+                let text = quote!(#expr).to_string();
+                quote!(Some(#text))
+            }
+        };
+        let description = &config.description;
+        descriptors.push(quote! {
+            ::montrs_core::PlateConfigField {
+                name: #key,
+                env_key: #env_key.to_string(),
+                secret: #secret,
+                required: #required,
+                default: #default,
+                description: #description,
+            }
+        });
+    }
+    let validation = validate.then(|| quote!(section.validate(&config)?;));
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::montrs_core::PlateConfig for #name #ty_generics #where_clause {
+            const PLATE: &'static str = #plate;
+
+            fn from_section(
+                table: Option<&::montrs_core::plate_config::Table>,
+                env: &dyn ::montrs_core::EnvConfig,
+            ) -> Result<Self, ::montrs_core::PlateConfigError> {
+                let section = ::montrs_core::plate_config::PlateSection::new(#plate, table, env, &[#(#keys),*])?;
+                let config = Self {
+                    #(#inits,)*
+                };
+                #validation
+                Ok(config)
+            }
+
+            fn fields() -> Vec<::montrs_core::PlateConfigField> {
+                vec![#(#descriptors),*]
+            }
+        }
+    }
+}
+
+/// `BillingConfig` -> `billing`, `RateLimitConfig` -> `rate_limit`.
+fn default_plate_name(ident: &str) -> String {
+    let stem = ident.strip_suffix("Config").filter(|s| !s.is_empty()).unwrap_or(ident);
+    let mut name = String::new();
+    for (i, c) in stem.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            name.push('_');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    name
+}
+
+pub(crate) fn is_option(ty: &syn::Type) -> bool {
+    matches!(ty, syn::Type::Path(p) if p.qself.is_none() && p.path.segments.last().is_some_and(|s| s.ident == "Option"))
+}
+
+/// The field's doc comment, joined into one line.
+pub(crate) fn doc_text(attrs: &[syn::Attribute]) -> String {
+    attrs
+        .iter()
+        .filter(|a| a.path().is_ident("doc"))
+        .filter_map(|a| match &a.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(s), .. }),
+                ..
+            }) => Some(s.value().trim().to_string()),
+            _ => None,
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
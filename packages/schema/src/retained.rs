@@ -0,0 +1,87 @@
+//! `#[derive(Retained)]`: declaring how long a model's rows are kept with
+//! `#[orm(retain = "90d")]`, for `montrs_orm::Retention`.
+
+use crate::SchemaError;
+use crate::encrypted_model::snake_case;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::DeriveInput;
+use syn::spanned::Spanned;
+
+pub(crate) fn expand(input: DeriveInput) -> TokenStream2 {
+    let name = &input.ident;
+    let mut errors: Vec<(SchemaError, Span)> = Vec::new();
+    let (mut table, mut retain, mut retain_by, mut format) = (None, None, None, None);
+
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("orm")) {
+        let parsed = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("table") {
+                table = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+            } else if meta.path.is_ident("retain") {
+                let lit = meta.value()?.parse::<syn::LitStr>()?;
+                match parse_period(&lit.value()) {
+                    Some(secs) => retain = Some(secs),
+                    None => errors.push((
+                        SchemaError::OrmAttribute(format!("`{}` is not a retention period like 30m, 12h, 90d or 2w", lit.value())),
+                        lit.span(),
+                    )),
+                }
+            } else if meta.path.is_ident("retain_by") {
+                retain_by = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+            } else if meta.path.is_ident("retain_format") {
+                let lit = meta.value()?.parse::<syn::LitStr>()?;
+                match lit.value().as_str() {
+                    "rfc3339" => format = Some(quote!(Rfc3339)),
+                    "unix" => format = Some(quote!(Unix)),
+                    "unix_ms" => format = Some(quote!(UnixMs)),
+                    other => errors.push((
+                        SchemaError::OrmAttribute(format!("`{}` is not a timestamp format (rfc3339, unix or unix_ms)", other)),
+                        lit.span(),
+                    )),
+                }
+            } else {
+                let path = &meta.path;
+                errors.push((SchemaError::OrmAttribute(format!("`{}` is not a struct-level attribute", quote!(#path))), path.span()));
+                crate::skip_value(&meta)?;
+            }
+            Ok(())
+        });
+        if let Err(e) = parsed {
+            errors.push((SchemaError::OrmAttribute(e.to_string()), e.span()));
+        }
+    }
+    if retain.is_none() && errors.is_empty() {
+        errors.push((SchemaError::OrmAttribute(format!("{} needs #[orm(retain = \"...\")]", name)), name.span()));
+    }
+
+    if !errors.is_empty() {
+        let errors = errors.iter().map(|(error, span)| error.to_compile_error(*span));
+        return quote! { #(#errors)* };
+    }
+
+    let table = table.unwrap_or_else(|| snake_case(&name.to_string()));
+    let retain = retain.unwrap_or_default();
+    let retain_by = retain_by.unwrap_or_else(|| "created_at".to_string());
+    let format = format.unwrap_or_else(|| quote!(Rfc3339));
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::montrs_orm::lifecycle::Retained for #name #ty_generics #where_clause {
+            const TABLE: &'static str = #table;
+            const RETAIN: ::std::time::Duration = ::std::time::Duration::from_secs(#retain);
+            const RETAIN_BY: &'static str = #retain_by;
+            const FORMAT: ::montrs_orm::lifecycle::TimestampFormat = ::montrs_orm::lifecycle::TimestampFormat::#format;
+        }
+    }
+}
+
+/// `30m`, `12h`, `90d` or `2w`, in seconds.
+fn parse_period(period: &str) -> Option<u64> {
+    let unit = match period.chars().last()? {
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    period[..period.len() - 1].parse::<u64>().ok()?.checked_mul(unit)
+}
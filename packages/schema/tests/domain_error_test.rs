@@ -0,0 +1,58 @@
+use montrs_core::{ActionError, DomainError, ErrorMessages, RouteError};
+use montrs_schema::DomainError;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, thiserror::Error, DomainError)]
+enum CheckoutError {
+    #[error("only {available} left")]
+    #[domain_error(code = "OUT_OF_STOCK", status = 409, field = "quantity")]
+    OutOfStock { available: u32 },
+    #[error("the coupon has expired")]
+    CouponExpired,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, thiserror::Error, DomainError)]
+#[error("account {0} is locked")]
+#[domain_error(status = 423)]
+struct AccountLocked(String);
+
+fn action_error(error: impl Into<RouteError>) -> ActionError {
+    match error.into() {
+        RouteError::Action(error) => *error,
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn test_derive_gives_codes_statuses_and_fields() {
+    let out_of_stock = CheckoutError::OutOfStock { available: 3 };
+    assert_eq!((out_of_stock.code(), out_of_stock.status(), out_of_stock.field()), ("OUT_OF_STOCK", 409, Some("quantity")));
+    let expired = CheckoutError::CouponExpired;
+    assert_eq!((expired.code(), expired.status(), expired.field()), ("COUPON_EXPIRED", 422, None));
+    let locked = AccountLocked("ada".to_string());
+    assert_eq!((locked.code(), locked.status()), ("ACCOUNT_LOCKED", 423));
+
+    let error = action_error(out_of_stock);
+    assert_eq!((error.code.as_str(), error.message.as_str(), error.status), ("OUT_OF_STOCK", "only 3 left", 409));
+    assert_eq!(error.params["available"], json!(3));
+    let quantity = error.field_errors("quantity").next().unwrap();
+    assert_eq!((quantity.code.as_str(), &quantity.params["available"]), ("OUT_OF_STOCK", &json!(3)));
+    assert_eq!(RouteError::from(CheckoutError::CouponExpired).status(), 422);
+}
+
+#[test]
+fn test_clients_decode_the_typed_error_and_localize_it() {
+    let sent = action_error(CheckoutError::OutOfStock { available: 3 });
+    let body = json!({ "error": sent.message, "action_error": sent }).to_string();
+    let received = ActionError::from_body(409, body.as_bytes());
+
+    assert_eq!(received.decode::<CheckoutError>(), Some(CheckoutError::OutOfStock { available: 3 }));
+    assert_eq!(received.decode::<AccountLocked>(), None);
+    assert_eq!(action_error(CheckoutError::CouponExpired).decode::<CheckoutError>(), Some(CheckoutError::CouponExpired));
+    assert_eq!(action_error(AccountLocked("ada".to_string())).decode::<AccountLocked>(), Some(AccountLocked("ada".to_string())));
+
+    let messages = ErrorMessages::new().with("OUT_OF_STOCK", "Nur noch {available} auf Lager");
+    assert_eq!(messages.message(&received), "Nur noch 3 auf Lager");
+    assert_eq!(messages.field(&received, "quantity"), ["Nur noch 3 auf Lager"]);
+}
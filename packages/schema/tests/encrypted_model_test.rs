@@ -0,0 +1,42 @@
+use montrs_orm::{EncryptedModel, FieldCipher};
+use montrs_schema::EncryptedModel;
+
+#[derive(EncryptedModel)]
+#[orm(table = "users")]
+struct User {
+    id: i64,
+    #[orm(encrypted)]
+    email: String,
+    #[orm(encrypted, column = "phone_number")]
+    phone: Option<String>,
+    name: String,
+}
+
+#[derive(EncryptedModel)]
+struct AuditLog {
+    #[orm(encrypted)]
+    detail: String,
+}
+
+#[test]
+fn test_marked_fields_are_encrypted_and_decrypted() {
+    let cipher = FieldCipher::parse(&format!("k1:{}", FieldCipher::generate_key())).unwrap();
+    let mut user = User { id: 1, email: "ada@example.com".into(), phone: Some("555".into()), name: "Ada".into() };
+
+    user.encrypt_fields(&cipher).unwrap();
+    assert!(FieldCipher::is_encrypted(&user.email));
+    assert!(user.phone.as_deref().is_some_and(FieldCipher::is_encrypted));
+    assert_eq!((user.id, user.name.as_str()), (1, "Ada"));
+
+    user.decrypt_fields(&cipher).unwrap();
+    assert_eq!(user.email, "ada@example.com");
+    assert_eq!(user.phone.as_deref(), Some("555"));
+}
+
+#[test]
+fn test_table_and_column_names() {
+    assert_eq!(User::TABLE, "users");
+    assert_eq!(User::ENCRYPTED_COLUMNS, ["email", "phone_number"]);
+    assert_eq!(AuditLog::TABLE, "audit_log");
+    assert_eq!(AuditLog::ENCRYPTED_COLUMNS, ["detail"]);
+}
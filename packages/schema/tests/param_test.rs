@@ -0,0 +1,90 @@
+use montrs_core::router::{RouteMetadata, RouterSpec};
+use montrs_core::{BodyFormat, FromParam, ParamSpec, RouteParams};
+use montrs_schema::{FromParam, RouteParams};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A todo's numeric id.
+#[derive(FromParam, Debug, PartialEq)]
+#[param(validate = "non_zero")]
+struct TodoId(u64);
+
+fn non_zero(id: &u64) -> Result<(), &'static str> {
+    if *id == 0 { Err("ids start at 1") } else { Ok(()) }
+}
+
+#[derive(FromParam, Debug, PartialEq)]
+#[param(pattern = "^[a-z0-9-]+$", format = "slug")]
+struct Slug(String);
+
+#[derive(RouteParams, Serialize, Deserialize, Debug, PartialEq)]
+struct TodoParams {
+    id: TodoId,
+    /// The list the todo belongs to.
+    #[serde(rename = "list")]
+    list_slug: Option<Slug>,
+    #[param(skip)]
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[test]
+fn test_newtypes_parse_and_validate() {
+    assert_eq!(TodoId::from_param("42"), Ok(TodoId(42)));
+    assert_eq!(TodoId::from_param("abc").unwrap_err().to_string(), "invalid TodoId `abc`: expected an integer");
+    assert_eq!(TodoId::from_param("0").unwrap_err().to_string(), "invalid TodoId `0`: ids start at 1");
+
+    assert_eq!(Slug::from_param("buy-milk"), Ok(Slug("buy-milk".to_string())));
+    let err = Slug::from_param("Buy Milk").unwrap_err();
+    assert_eq!(err.value(), "Buy Milk");
+    assert_eq!(err.reason(), "does not match `^[a-z0-9-]+$`");
+
+    let schema = TodoId::param_schema();
+    assert_eq!((schema.kind.as_str(), schema.description.as_deref()), ("integer", Some("A todo's numeric id.")));
+    let schema = Slug::param_schema();
+    assert_eq!((schema.format.as_deref(), schema.pattern.as_deref()), (Some("slug"), Some("^[a-z0-9-]+$")));
+}
+
+#[test]
+fn test_params_deserialize_from_router_values() {
+    // The router turns numeric path segments into numbers.
+    let params: TodoParams = serde_json::from_value(serde_json::json!({ "id": 7, "list": "home" })).unwrap();
+    assert_eq!(params, TodoParams { id: TodoId(7), list_slug: Some(Slug("home".to_string())), tags: Vec::new() });
+    assert_eq!(serde_json::to_value(&params).unwrap()["id"], 7);
+
+    let params: TodoParams = serde_json::from_value(serde_json::json!({ "id": "7" })).unwrap();
+    assert_eq!(params.list_slug, None);
+
+    let err = serde_json::from_value::<TodoParams>(serde_json::json!({ "id": 0 })).unwrap_err();
+    assert_eq!(err.to_string(), "invalid TodoId `0`: ids start at 1");
+}
+
+#[test]
+fn test_route_params_are_listed_in_the_spec_and_openapi() {
+    let params = TodoParams::params();
+    assert_eq!(params.len(), 2);
+    assert_eq!(params[0], ParamSpec::of::<TodoId>("id", true));
+    assert_eq!((params[1].name.as_str(), params[1].required), ("list", false));
+    assert_eq!(params[1].schema.description.as_deref(), Some("The list the todo belongs to."));
+
+    let route = RouteMetadata {
+        path: "/todos/:id".to_string(),
+        loader_description: String::new(),
+        action_description: String::new(),
+        action_body: BodyFormat::Json,
+        params,
+        meta: HashMap::new(),
+        guards: Vec::new(),
+        parent: None,
+        children: Vec::new(),
+    };
+    let spec = RouterSpec { routes: HashMap::from([(route.path.clone(), route)]), ..Default::default() };
+    let doc = spec.to_openapi("todos", "1.0.0");
+    let parameters = &doc["paths"]["/todos/{id}"]["get"]["parameters"];
+    assert_eq!(parameters[0]["in"], "path");
+    assert_eq!(parameters[0]["schema"]["type"], "integer");
+    assert_eq!(parameters[1]["name"], "list");
+    assert_eq!(parameters[1]["in"], "query");
+    assert_eq!(parameters[1]["required"], false);
+    assert_eq!(parameters[1]["schema"]["format"], "slug");
+}
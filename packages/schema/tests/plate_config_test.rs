@@ -0,0 +1,99 @@
+use montrs_core::{AgentError, EnvConfig, EnvError, PlateConfig, PlateConfigError};
+use montrs_schema::{PlateConfig, Schema};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+struct MapEnv(HashMap<&'static str, &'static str>);
+impl EnvConfig for MapEnv {
+    fn get_var(&self, key: &str) -> Result<String, EnvError> {
+        self.0.get(key).map(|v| v.to_string()).ok_or_else(|| EnvError::MissingKey(key.to_string()))
+    }
+}
+
+fn env(vars: &[(&'static str, &'static str)]) -> MapEnv {
+    MapEnv(vars.iter().copied().collect())
+}
+
+#[derive(PlateConfig, Schema, Debug)]
+#[plate_config(validate)]
+struct BillingConfig {
+    /// Key used to sign API requests.
+    #[plate_config(secret)]
+    api_key: String,
+    #[plate_config(default = "30")]
+    timeout_secs: u64,
+    #[plate_config(default)]
+    retries: u32,
+    #[schema(min_len = 3)]
+    #[plate_config(default = String::from("usd"))]
+    currency: String,
+    #[plate_config(env = "BILLING_WEBHOOK")]
+    webhook_url: Option<String>,
+    regions: Vec<String>,
+}
+
+fn manifest(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("montrs-plate-config-{}-{}.toml", name, std::process::id()));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn test_loads_section_with_env_overrides_and_defaults() {
+    let path = manifest(
+        "ok",
+        "[project]\nname = \"shop\"\n\n[plates.billing]\ntimeout_secs = 10\nregions = [\"eu\"]\n",
+    );
+    let config = BillingConfig::load_from(
+        &path,
+        &env(&[
+            ("MONTRS_BILLING_API_KEY", "sk_test"),
+            ("MONTRS_BILLING_TIMEOUT_SECS", "45"),
+            ("BILLING_WEBHOOK", "https://example.com/hook"),
+        ]),
+    )
+    .unwrap();
+    assert_eq!(config.api_key, "sk_test");
+    assert_eq!(config.timeout_secs, 45);
+    assert_eq!(config.retries, 0);
+    assert_eq!(config.currency, "usd");
+    assert_eq!(config.webhook_url.as_deref(), Some("https://example.com/hook"));
+    assert_eq!(config.regions, ["eu"]);
+
+    // Env-only configuration, with a list parsed from JSON.
+    let config = BillingConfig::load_from(
+        &path.with_extension("missing"),
+        &env(&[("MONTRS_BILLING_API_KEY", "sk"), ("MONTRS_BILLING_REGIONS", "[\"us\", \"ca\"]")]),
+    )
+    .unwrap();
+    assert_eq!(config.timeout_secs, 30);
+    assert_eq!(config.webhook_url, None);
+    assert_eq!(config.regions, ["us", "ca"]);
+
+    let fields = BillingConfig::fields();
+    assert_eq!(BillingConfig::PLATE, "billing");
+    assert_eq!(fields[0].env_key, "MONTRS_BILLING_API_KEY");
+    assert_eq!(fields[0].description, "Key used to sign API requests.");
+    assert!(fields[0].secret && fields[0].required);
+    assert_eq!(fields[1].default, Some("30"));
+    assert!(!fields[4].required);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_reports_config_mistakes() {
+    let key = [("MONTRS_BILLING_API_KEY", "sk")];
+    let cases = [
+        ("[plates.billing]\nregions = []\napi_key = \"sk_live\"\n", &key[..0], "PLATE_CONFIG_SECRET_IN_FILE"),
+        ("[plates.billing]\nregions = []\ntimeout = 5\n", &key[..], "PLATE_CONFIG_UNKNOWN_KEY"),
+        ("[plates.billing]\ntimeout_secs = 5\n", &key[..], "PLATE_CONFIG_MISSING"),
+        ("[plates.billing]\nregions = []\ntimeout_secs = \"soon\"\n", &key[..], "PLATE_CONFIG_INVALID"),
+        ("[plates.billing]\nregions = []\ncurrency = \"$\"\n", &key[..], "PLATE_CONFIG_VALIDATION"),
+    ];
+    for (i, (content, vars, code)) in cases.into_iter().enumerate() {
+        let path = manifest(&format!("err{}", i), content);
+        let err: PlateConfigError = BillingConfig::load_from(&path, &env(vars)).unwrap_err();
+        assert_eq!(err.error_code(), code, "{}", err);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,34 @@
+use montrs_orm::lifecycle::{Retained, RetentionPolicy, TimestampFormat};
+use montrs_orm::EncryptedModel;
+use montrs_schema::{EncryptedModel, Retained};
+use std::time::Duration;
+
+#[derive(Retained)]
+#[orm(retain = "90d")]
+struct AuditLog {}
+
+#[derive(EncryptedModel, Retained)]
+#[orm(table = "sessions", retain = "12h", retain_by = "last_seen", retain_format = "unix_ms")]
+struct Session {
+    #[orm(encrypted)]
+    token: String,
+}
+
+#[test]
+fn test_retention_is_declared_on_the_model() {
+    assert_eq!(AuditLog::TABLE, "audit_log");
+    assert_eq!(AuditLog::RETAIN, Duration::from_secs(90 * 24 * 60 * 60));
+    assert_eq!((AuditLog::RETAIN_BY, AuditLog::FORMAT), ("created_at", TimestampFormat::Rfc3339));
+
+    let policy = RetentionPolicy::of::<Session>();
+    assert_eq!((policy.table.as_str(), policy.column.as_str()), ("sessions", "last_seen"));
+    assert_eq!((policy.retain, policy.format), (Duration::from_secs(12 * 60 * 60), TimestampFormat::UnixMs));
+}
+
+#[test]
+fn test_retention_attributes_do_not_disturb_encryption() {
+    assert_eq!(<Session as EncryptedModel>::TABLE, "sessions");
+    assert_eq!(Session::ENCRYPTED_COLUMNS, ["token"]);
+    let session = Session { token: "t".into() };
+    assert_eq!(session.token, "t");
+}
@@ -0,0 +1,54 @@
+use montrs_core::{Sanitize, Validate, sanitize_and_validate};
+use montrs_schema::Schema;
+
+mod slug {
+    pub fn normalize(value: &mut String) {
+        *value = value.replace(' ', "-");
+    }
+}
+
+#[derive(Schema)]
+struct Post {
+    #[schema(trim, min_len = 3)]
+    title: String,
+    #[schema(trim, lowercase, email)]
+    author_email: String,
+    #[schema(strip_html, trim)]
+    summary: Option<String>,
+    #[schema(lowercase, transform = "slug::normalize")]
+    slug: String,
+    #[schema(trim)]
+    tags: Vec<String>,
+}
+
+fn post() -> Post {
+    Post {
+        title: "  Hi  ".to_string(),
+        author_email: " Ada@Example.com ".to_string(),
+        summary: Some(" <p>Short <em>intro</em></p> ".to_string()),
+        slug: "Hello World".to_string(),
+        tags: vec![" rust ".to_string()],
+    }
+}
+
+#[test]
+fn test_transforms_run_in_attribute_order() {
+    let mut post = post();
+    post.sanitize();
+    assert_eq!(post.title, "Hi");
+    assert_eq!(post.author_email, "ada@example.com");
+    assert_eq!(post.summary.as_deref(), Some("Short intro"));
+    assert_eq!(post.slug, "hello-world");
+    assert_eq!(post.tags, ["rust"]);
+}
+
+#[test]
+fn test_validation_sees_sanitized_values() {
+    // "  Hi  " passes `min_len = 3` only before trimming.
+    assert!(post().validate().is_ok());
+    assert!(sanitize_and_validate(post()).is_err());
+
+    let mut long = post();
+    long.title = " Hello ".to_string();
+    assert_eq!(sanitize_and_validate(long).unwrap().title, "Hello");
+}
@@ -0,0 +1,381 @@
+//! Conformance checks for plate authors.
+//!
+//! [`PlateConformance`] boots a minimal `AppSpec` holding just the plate under
+//! test, plus the plates it depends on, and checks the contract every plate
+//! is expected to keep:
+//!
+//! - **name**: a lowercase identifier, not taken by a dependency or a
+//!   first-party plate, since `[plates.<name>]` and the boot order key on it.
+//! - **boot**: the app boots with the plate and its declared dependencies.
+//! - **init**: `init` can run twice on the same plate, as it does when an app
+//!   is rebooted in-process by the dev server and tests.
+//! - **routes**: `register_routes` does not panic, registers absolute paths that
+//!   no dependency already uses, and registers the same routes every time.
+//! - **shutdown**: `shutdown` succeeds, twice, within the timeout, and leaves no
+//!   Tokio tasks running that `init` spawned.
+//!
+//! The [`ConformanceReport`] lists every check with its outcome and the routes
+//! the plate registers. Saved as JSON, it can be attached to a published plate.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! // One line in tests/conformance_test.rs:
+//! montrs_test::plate_conformance!(test_blog_plate_conforms, BlogPlate::new());
+//!
+//! // Or, with the plate's own config and a dependency:
+//! let report = PlateConformance::with_config(config, env, BlogPlate::new)
+//!     .with_dependency(AuthPlate::new)
+//!     .run()
+//!     .await;
+//! report.save("conformance.json")?;
+//! report.assert_passed()?;
+//! ```
+//!
+//! `plate_conformance!` also saves the report to the path in
+//! `MONTRS_CONFORMANCE_REPORT` when it is set.
+
+use crate::TestError;
+use crate::integration::{TestConfig, TestEnv};
+use montrs_core::{AppConfig, AppSpec, Plate, PlateContext, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// When set, `plate_conformance!` writes its report to this path.
+pub const CONFORMANCE_REPORT_VAR: &str = "MONTRS_CONFORMANCE_REPORT";
+
+/// Names of the plates montrs ships; community plates need others.
+pub const FIRST_PARTY_PLATES: [&str; 4] = ["jobs", "notifications", "payments", "sync"];
+
+type Factory<C> = Arc<dyn Fn() -> Box<dyn Plate<C>> + Send + Sync>;
+
+/// One part of the plate contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Check {
+    Name,
+    Boot,
+    Init,
+    Routes,
+    Shutdown,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Check::Name => "name",
+            Check::Boot => "boot",
+            Check::Init => "init",
+            Check::Routes => "routes",
+            Check::Shutdown => "shutdown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The outcome of one check. `detail` says what failed, or what was checked.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckResult {
+    pub check: Check,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Boots a plate on its own and checks it against the plate contract.
+pub struct PlateConformance<C: AppConfig> {
+    config: C,
+    env: C::Env,
+    plate: Factory<C>,
+    dependencies: Vec<Factory<C>>,
+    shutdown_timeout: Duration,
+}
+
+impl PlateConformance<TestConfig> {
+    /// Checks the plates `plate` returns, under [`TestConfig`]. It is called
+    /// once per boot, so every check starts from a fresh plate.
+    pub fn new<P: Plate<TestConfig>>(plate: impl Fn() -> P + Send + Sync + 'static) -> Self {
+        Self::with_config(TestConfig, TestEnv::new(), plate)
+    }
+}
+
+impl<C: AppConfig> PlateConformance<C> {
+    /// Checks a plate that needs the app's own config.
+    pub fn with_config<P: Plate<C>>(
+        config: C,
+        env: C::Env,
+        plate: impl Fn() -> P + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            config,
+            env,
+            plate: Arc::new(move || Box::new(plate())),
+            dependencies: Vec::new(),
+            shutdown_timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Boots a plate the one under test depends on alongside it.
+    pub fn with_dependency<P: Plate<C>>(
+        mut self,
+        plate: impl Fn() -> P + Send + Sync + 'static,
+    ) -> Self {
+        self.dependencies.push(Arc::new(move || Box::new(plate())));
+        self
+    }
+
+    /// How long `shutdown` and the tasks it stops may take (default: 5 seconds).
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Runs every check and reports the outcomes.
+    pub async fn run(&self) -> ConformanceReport {
+        let tasks_before = alive_tasks();
+        let plate = (self.plate)();
+        let mut report = ConformanceReport {
+            plate: plate.name().to_string(),
+            description: plate.description().to_string(),
+            dependencies: plate.dependencies().iter().map(|d| d.to_string()).collect(),
+            core_version: montrs_core::compat::CORE.version.to_string(),
+            routes: Vec::new(),
+            checks: Vec::new(),
+        };
+        report.checks.push(self.check_name(plate.as_ref()));
+
+        let mut spec = AppSpec::new(self.config.clone(), self.env.clone());
+        for dependency in &self.dependencies {
+            spec = spec.with_plate(dependency());
+        }
+        let mut spec = spec.with_plate((self.plate)());
+        report.checks.push(match spec.boot().await {
+            Ok(_) => pass(Check::Boot, format!("booted with {} plate(s)", spec.plates.len())),
+            Err(e) => fail(Check::Boot, e.to_string()),
+        });
+
+        report.checks.push(self.check_init(plate.as_ref()).await);
+        let (routes, check) = self.check_routes();
+        report.routes = routes;
+        report.checks.push(check);
+
+        report.checks.push(self.check_shutdown(&spec, plate.as_ref(), tasks_before).await);
+        report
+    }
+
+    /// [`run`](Self::run) on a new current-thread runtime, for plain `#[test]`s.
+    pub fn run_blocking(&self) -> ConformanceReport {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("failed to build a Tokio runtime for the conformance checks")
+            .block_on(self.run())
+    }
+
+    fn check_name(&self, plate: &dyn Plate<C>) -> CheckResult {
+        let name = plate.name();
+        let valid = name.starts_with(|c: char| c.is_ascii_lowercase())
+            && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '-'));
+        if !valid {
+            return fail(
+                Check::Name,
+                format!("'{}' is not a lowercase identifier (a-z, 0-9, '_' and '-')", name),
+            );
+        }
+        if self.dependencies.iter().any(|dependency| dependency().name() == name) {
+            return fail(Check::Name, format!("'{}' is also the name of a dependency", name));
+        }
+        if FIRST_PARTY_PLATES.contains(&name) {
+            return fail(Check::Name, format!("'{}' is the name of a first-party plate", name));
+        }
+        pass(Check::Name, format!("'{}' is unique", name))
+    }
+
+    async fn check_init(&self, plate: &dyn Plate<C>) -> CheckResult {
+        for attempt in ["first", "second"] {
+            let mut ctx = PlateContext { config: &self.config, env: &self.env };
+            if let Err(e) = plate.init(&mut ctx).await {
+                return fail(Check::Init, format!("the {} `init` failed: {}", attempt, e));
+            }
+        }
+        pass(Check::Init, "`init` succeeded twice on the same plate".to_string())
+    }
+
+    /// The plate's routes, and whether they register cleanly.
+    fn check_routes(&self) -> (Vec<String>, CheckResult) {
+        let mut taken = Router::new();
+        for dependency in &self.dependencies {
+            dependency().register_routes(&mut taken);
+        }
+        let taken = paths(&taken);
+
+        let mut registrations = Vec::new();
+        for _ in 0..2 {
+            let plate = (self.plate)();
+            let mut router = Router::new();
+            if std::panic::catch_unwind(AssertUnwindSafe(|| plate.register_routes(&mut router))).is_err() {
+                return (Vec::new(), fail(Check::Routes, "`register_routes` panicked".to_string()));
+            }
+            registrations.push(router);
+        }
+        let routes: Vec<String> = paths(&registrations[0]).into_iter().collect();
+
+        let relative: Vec<&str> = routes.iter().filter(|r| !r.starts_with('/')).map(String::as_str).collect();
+        if !relative.is_empty() {
+            let detail = format!("paths must start with '/': {}", relative.join(", "));
+            return (routes.clone(), fail(Check::Routes, detail));
+        }
+        let collisions: Vec<&str> = routes.iter().filter(|r| taken.contains(*r)).map(String::as_str).collect();
+        if !collisions.is_empty() {
+            let detail = format!("a dependency already registers {}", collisions.join(", "));
+            return (routes.clone(), fail(Check::Routes, detail));
+        }
+        let specs: Vec<Value> =
+            registrations.iter().map(|router| serde_json::to_value(router.spec()).unwrap_or(Value::Null)).collect();
+        if specs[0] != specs[1] {
+            let detail = "a second plate registered different routes; keep `register_routes` deterministic";
+            return (routes.clone(), fail(Check::Routes, detail.to_string()));
+        }
+        let detail = format!("{} route(s) registered", routes.len());
+        (routes, pass(Check::Routes, detail))
+    }
+
+    async fn check_shutdown(
+        &self,
+        spec: &AppSpec<C>,
+        plate: &dyn Plate<C>,
+        tasks_before: Option<usize>,
+    ) -> CheckResult {
+        let started = Instant::now();
+        for attempt in ["first", "second"] {
+            let shutdown = async {
+                spec.shutdown().await.map_err(|e| e.to_string())?;
+                plate.shutdown().await.map_err(|e| e.to_string())
+            };
+            match tokio::time::timeout(self.shutdown_timeout, shutdown).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    return fail(Check::Shutdown, format!("the {} `shutdown` failed: {}", attempt, e));
+                }
+                Err(_) => {
+                    let detail = format!("the {} `shutdown` took over {:?}", attempt, self.shutdown_timeout);
+                    return fail(Check::Shutdown, detail);
+                }
+            }
+        }
+
+        let Some(before) = tasks_before else {
+            let detail = "`shutdown` succeeded twice; tasks are only counted on a Tokio runtime";
+            return pass(Check::Shutdown, detail.to_string());
+        };
+        // Stopped tasks finish on their next poll; give them until the timeout.
+        let mut running = alive_tasks().unwrap_or(0);
+        while running > before && started.elapsed() < self.shutdown_timeout {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            running = alive_tasks().unwrap_or(0);
+        }
+        match running.saturating_sub(before) {
+            0 => pass(Check::Shutdown, "`shutdown` succeeded twice and stopped every task".to_string()),
+            left => {
+                let detail = format!("{} task(s) spawned by the plate still running after `shutdown`", left);
+                fail(Check::Shutdown, detail)
+            }
+        }
+    }
+}
+
+/// The outcome of [`PlateConformance::run`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConformanceReport {
+    pub plate: String,
+    pub description: String,
+    pub dependencies: Vec<String>,
+    /// The `montrs-core` the checks ran against.
+    pub core_version: String,
+    /// The paths the plate registers, sorted.
+    pub routes: Vec<String>,
+    pub checks: Vec<CheckResult>,
+}
+
+impl ConformanceReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+
+    pub fn check(&self, check: Check) -> Option<&CheckResult> {
+        self.checks.iter().find(|c| c.check == check)
+    }
+
+    /// Fails with a `TestError::Expectation` listing the failed checks.
+    pub fn assert_passed(&self) -> Result<(), TestError> {
+        if self.passed() {
+            return Ok(());
+        }
+        Err(TestError::Expectation(format!("plate '{}' does not conform\n{}", self.plate, self)))
+    }
+
+    /// Writes the report to `path` as JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), TestError> {
+        if let Some(dir) = path.as_ref().parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| TestError::Expectation(e.to_string()))?;
+        std::fs::write(path, json + "\n")?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "plate '{}' (montrs-core {})", self.plate, self.core_version)?;
+        for check in &self.checks {
+            let label = if check.passed { "ok" } else { "FAILED" };
+            writeln!(f, "{:<7} {:<9} {}", label, check.check, check.detail)?;
+        }
+        let passed = self.checks.iter().filter(|c| c.passed).count();
+        write!(f, "{}/{} checks passed", passed, self.checks.len())
+    }
+}
+
+/// Checks a plate with [`PlateConformance`] in a `#[test]`, printing the
+/// report and saving it to `MONTRS_CONFORMANCE_REPORT` when that is set.
+///
+/// ```rust,ignore
+/// montrs_test::plate_conformance!(test_blog_plate_conforms, BlogPlate::new());
+/// ```
+#[macro_export]
+macro_rules! plate_conformance {
+    ($name:ident, $plate:expr) => {
+        #[test]
+        fn $name() {
+            let report = $crate::conformance::PlateConformance::new(|| $plate).run_blocking();
+            println!("{}", report);
+            if let Some(path) = ::std::env::var_os($crate::conformance::CONFORMANCE_REPORT_VAR) {
+                report.save(path).expect("failed to save the conformance report");
+            }
+            report.assert_passed().unwrap();
+        }
+    };
+}
+
+fn pass(check: Check, detail: String) -> CheckResult {
+    CheckResult { check, passed: true, detail }
+}
+
+fn fail(check: Check, detail: String) -> CheckResult {
+    CheckResult { check, passed: false, detail }
+}
+
+fn paths<C: AppConfig>(router: &Router<C>) -> BTreeSet<String> {
+    router.spec().routes.into_keys().collect()
+}
+
+/// Tasks alive on the current Tokio runtime, if there is one.
+fn alive_tasks() -> Option<usize> {
+    tokio::runtime::Handle::try_current().ok().map(|handle| handle.metrics().num_alive_tasks())
+}
@@ -0,0 +1,314 @@
+//! Contract testing for route loaders and actions.
+//!
+//! A contract is a recorded request/response pair stored as JSON under
+//! `contracts/`. Replaying the fixtures against the current router catches
+//! status code changes and response shape drift before clients see them.
+//! Each difference is classified as additive (new fields, a value that used to
+//! be `null`) or breaking (removed fields, changed types, a different status).
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! let client = TestClient::from_spec(spec);
+//! let report = client.verify_contracts("contracts").await?;
+//! println!("{}", report);
+//! assert!(!report.is_breaking());
+//! ```
+//!
+//! Run with `MONTRS_UPDATE_CONTRACTS=1` to re-record the fixtures from the
+//! current router instead of failing on drift.
+
+use crate::TestError;
+use montrs_core::{ErrorInfo, RouteError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// When set, `verify_contracts` rewrites the fixtures with the current responses.
+pub const UPDATE_CONTRACTS_VAR: &str = "MONTRS_UPDATE_CONTRACTS";
+
+/// Which handler of a route a fixture exercises.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    Load,
+    Act,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Load => write!(f, "load"),
+            Operation::Act => write!(f, "act"),
+        }
+    }
+}
+
+/// A recorded request and the response the client relies on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractFixture {
+    pub name: String,
+    pub route: String,
+    pub operation: Operation,
+    #[serde(default)]
+    pub params: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<Value>,
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default)]
+    pub response: Value,
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+impl ContractFixture {
+    /// A loader fixture. The response is filled in by [`crate::TestClient::record`].
+    pub fn load(name: impl Into<String>, route: impl Into<String>, params: Value) -> Self {
+        Self {
+            name: name.into(),
+            route: route.into(),
+            operation: Operation::Load,
+            params,
+            input: None,
+            status: default_status(),
+            response: Value::Null,
+        }
+    }
+
+    /// An action fixture. The response is filled in by [`crate::TestClient::record`].
+    pub fn act(name: impl Into<String>, route: impl Into<String>, params: Value, input: Value) -> Self {
+        Self {
+            operation: Operation::Act,
+            input: Some(input),
+            ..Self::load(name, route, params)
+        }
+    }
+
+    /// Stores the outcome of a router call as the expected response.
+    pub fn with_outcome(mut self, outcome: Result<Value, RouteError>) -> Self {
+        (self.status, self.response) = match outcome {
+            Ok(value) => (200, value),
+            Err(e) => (
+                ErrorInfo::from_route_error(&e).status,
+                serde_json::to_value(&e).unwrap_or(Value::Null),
+            ),
+        };
+        self
+    }
+
+    /// Writes the fixture to `<dir>/<name>.json`.
+    pub fn save(&self, dir: impl AsRef<Path>) -> Result<PathBuf, TestError> {
+        std::fs::create_dir_all(dir.as_ref())?;
+        let path = dir.as_ref().join(format!("{}.json", self.name));
+        self.write_to(&path)?;
+        Ok(path)
+    }
+
+    pub(crate) fn write_to(&self, path: &Path) -> Result<(), TestError> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| TestError::Contract(e.to_string()))?;
+        std::fs::write(path, json + "\n")?;
+        Ok(())
+    }
+
+    /// Reads every `*.json` fixture in `dir`, sorted by file name.
+    pub fn read_dir(dir: impl AsRef<Path>) -> Result<Vec<(PathBuf, Self)>, TestError> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir.as_ref())?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("json"))
+            .collect();
+        paths.sort();
+        paths
+            .into_iter()
+            .map(|path| {
+                let content = std::fs::read_to_string(&path)?;
+                let fixture = serde_json::from_str(&content)
+                    .map_err(|e| TestError::Contract(format!("{}: {}", path.display(), e)))?;
+                Ok((path, fixture))
+            })
+            .collect()
+    }
+}
+
+/// How a response differs from its recording. Ordered from harmless to breaking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compatibility {
+    Unchanged,
+    Additive,
+    Breaking,
+}
+
+/// One difference found at a JSON pointer inside the response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Change {
+    pub pointer: String,
+    pub description: String,
+    pub compatibility: Compatibility,
+}
+
+/// The outcome of replaying one fixture.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContractResult {
+    pub fixture: String,
+    pub route: String,
+    pub operation: Operation,
+    pub compatibility: Compatibility,
+    pub changes: Vec<Change>,
+}
+
+/// Compatibility of the current router with every recorded fixture.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompatibilityReport {
+    pub results: Vec<ContractResult>,
+}
+
+impl CompatibilityReport {
+    /// The worst compatibility across all fixtures.
+    pub fn compatibility(&self) -> Compatibility {
+        self.results
+            .iter()
+            .map(|r| r.compatibility)
+            .max()
+            .unwrap_or(Compatibility::Unchanged)
+    }
+
+    pub fn is_breaking(&self) -> bool {
+        self.compatibility() == Compatibility::Breaking
+    }
+
+    /// Fails with a `TestError::Expectation` listing the breaking changes.
+    pub fn assert_compatible(&self) -> Result<(), TestError> {
+        if self.is_breaking() {
+            return Err(TestError::Expectation(format!("contract drift detected\n{}", self)));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CompatibilityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in &self.results {
+            let label = match result.compatibility {
+                Compatibility::Unchanged => "ok",
+                Compatibility::Additive => "additive",
+                Compatibility::Breaking => "BREAKING",
+            };
+            writeln!(f, "{:<9} {} ({} {})", label, result.fixture, result.operation, result.route)?;
+            for change in &result.changes {
+                writeln!(f, "          {}: {}", display_pointer(&change.pointer), change.description)?;
+            }
+        }
+        let count = |c| self.results.iter().filter(|r| r.compatibility == c).count();
+        write!(
+            f,
+            "{} fixtures: {} unchanged, {} additive, {} breaking",
+            self.results.len(),
+            count(Compatibility::Unchanged),
+            count(Compatibility::Additive),
+            count(Compatibility::Breaking)
+        )
+    }
+}
+
+fn display_pointer(pointer: &str) -> &str {
+    if pointer.is_empty() { "(root)" } else { pointer }
+}
+
+/// Compares a replayed response with its fixture.
+pub fn compare(fixture: &ContractFixture, status: u16, response: &Value) -> ContractResult {
+    let mut changes = Vec::new();
+    if status != fixture.status {
+        changes.push(change(
+            String::new(),
+            format!("status changed from {} to {}", fixture.status, status),
+            Compatibility::Breaking,
+        ));
+    } else if (200..300).contains(&status) {
+        compare_shape("", &fixture.response, response, &mut changes);
+    }
+    ContractResult {
+        fixture: fixture.name.clone(),
+        route: fixture.route.clone(),
+        operation: fixture.operation,
+        compatibility: changes
+            .iter()
+            .map(|c| c.compatibility)
+            .max()
+            .unwrap_or(Compatibility::Unchanged),
+        changes,
+    }
+}
+
+/// Compares the structure of two JSON values. Differing values of the same
+/// type are not a contract change; recorded data is only an example.
+fn compare_shape(pointer: &str, recorded: &Value, current: &Value, changes: &mut Vec<Change>) {
+    match (recorded, current) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_value) in old {
+                let child = child_pointer(pointer, key);
+                match new.get(key) {
+                    Some(new_value) => compare_shape(&child, old_value, new_value, changes),
+                    None => changes.push(change(child, "field removed".to_string(), Compatibility::Breaking)),
+                }
+            }
+            for key in new.keys().filter(|key| !old.contains_key(*key)) {
+                changes.push(change(
+                    child_pointer(pointer, key),
+                    "field added".to_string(),
+                    Compatibility::Additive,
+                ));
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            if let (Some(old_item), Some(new_item)) = (old.first(), new.first()) {
+                compare_shape(&format!("{}/0", pointer), old_item, new_item, changes);
+            }
+        }
+        (Value::Null, Value::Null) => {}
+        (Value::Null, new) => changes.push(change(
+            pointer.to_string(),
+            format!("null became {}", kind(new)),
+            Compatibility::Additive,
+        )),
+        (old, Value::Null) => changes.push(change(
+            pointer.to_string(),
+            format!("{} became null", kind(old)),
+            Compatibility::Breaking,
+        )),
+        (old, new) if kind(old) != kind(new) => changes.push(change(
+            pointer.to_string(),
+            format!("type changed from {} to {}", kind(old), kind(new)),
+            Compatibility::Breaking,
+        )),
+        _ => {}
+    }
+}
+
+fn change(pointer: String, description: String, compatibility: Compatibility) -> Change {
+    Change {
+        pointer,
+        description,
+        compatibility,
+    }
+}
+
+/// Appends `key` to a JSON pointer, escaping `~` and `/` per RFC 6901.
+fn child_pointer(pointer: &str, key: &str) -> String {
+    format!("{}/{}", pointer, key.replace('~', "~0").replace('/', "~1"))
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
@@ -7,6 +7,8 @@
 //! - [`TestEnv`]: For mocking environment variables.
 //! - [`TestRuntime`]: For executing app logic in a controlled context.
 //! - [`Fixture`]: For managing test setup and teardown.
+//! - [`TestClient`]: For calling route loaders and actions in-process, and replaying contracts and captured requests.
+//! - [`TestConfig`]: A ready-made `AppConfig` for plates and routes under test.
 //!
 //! # Example
 //!
@@ -22,10 +24,14 @@
 //! // let runtime = TestRuntime::new(spec);
 //! ```
 
-use montrs_core::{AppConfig, AppSpec};
+use crate::TestError;
+use crate::contract::{self, CompatibilityReport, ContractFixture, Operation, UPDATE_CONTRACTS_VAR};
+use montrs_core::{AppConfig, AppSpec, Capture, ErrorInfo, Plate, Route, RouteContext, RouteError, Router, WasiResponse};
 use async_trait::async_trait;
 use montrs_core::env::EnvError;
 use montrs_core::EnvConfig;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
@@ -48,6 +54,7 @@ use crate::e2e;
 ///
 /// assert_eq!(env.get_var("API_KEY").unwrap(), "test-secret");
 /// ```
+#[derive(Clone)]
 pub struct TestEnv {
     vars: Arc<RwLock<HashMap<String, String>>>,
 }
@@ -90,6 +97,16 @@ impl EnvConfig for TestEnv {
     }
 }
 
+/// A minimal `AppConfig` for tests that don't need application-specific
+/// configuration. Pairs with [`TestEnv`].
+#[derive(Debug, Clone, Default)]
+pub struct TestConfig;
+
+impl AppConfig for TestConfig {
+    type Error = crate::TestError;
+    type Env = TestEnv;
+}
+
 /// A trait for defining test fixtures with setup and teardown logic.
 ///
 /// Implement this trait to manage resources that are needed for a test case,
@@ -225,6 +242,145 @@ impl<C: AppConfig> TestRuntime<C> {
     }
 }
 
+/// An in-process client for exercising routes without a server.
+///
+/// Loaders and actions are dispatched through the same `Router` the app uses,
+/// with params and inputs serialized to JSON exactly as they would be over the wire.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let client = TestClient::new(MyConfig, MyEnv).with_plate(&BlogPlate);
+/// let posts: Vec<Post> = client.load("/posts", json!({})).await?;
+/// let created: Post = client.act("/posts", json!({}), new_post).await?;
+/// ```
+pub struct TestClient<C: AppConfig> {
+    config: C,
+    env: Box<dyn EnvConfig>,
+    router: Router<C>,
+}
+
+impl<C: AppConfig> TestClient<C> {
+    /// Creates a client with an empty router.
+    pub fn new(config: C, env: impl EnvConfig + 'static) -> Self {
+        Self {
+            config,
+            env: Box::new(env),
+            router: Router::new(),
+        }
+    }
+
+    /// Creates a client that serves every route registered in `spec`.
+    pub fn from_spec(spec: AppSpec<C>) -> Self {
+        Self {
+            config: spec.config,
+            env: Box::new(spec.env),
+            router: spec.router,
+        }
+    }
+
+    /// Registers all routes of a plate.
+    pub fn with_plate(mut self, plate: &dyn Plate<C>) -> Self {
+        plate.register_routes(&mut self.router);
+        self
+    }
+
+    /// Registers a single route.
+    pub fn with_route<R: Route<C>>(mut self, route: R) -> Self {
+        self.router.register(route);
+        self
+    }
+
+    /// Returns the underlying router.
+    pub fn router(&self) -> &Router<C> {
+        &self.router
+    }
+
+    fn context(&self) -> RouteContext<'_, C> {
+        RouteContext {
+            config: &self.config,
+            env: self.env.as_ref(),
+        }
+    }
+
+    /// Runs the loader registered under `path` and deserializes its output.
+    pub async fn load<T: DeserializeOwned>(&self, path: &str, params: impl Serialize) -> Result<T, RouteError> {
+        let value = self.router.load(path, self.context(), to_value(params)?).await?;
+        from_value(value)
+    }
+
+    /// Runs the action registered under `path` and deserializes its output.
+    pub async fn act<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        params: impl Serialize,
+        input: impl Serialize,
+    ) -> Result<T, RouteError> {
+        let value = self
+            .router
+            .act(path, self.context(), to_value(params)?, to_value(input)?)
+            .await?;
+        from_value(value)
+    }
+
+    async fn run_contract(&self, fixture: &ContractFixture) -> Result<serde_json::Value, RouteError> {
+        match fixture.operation {
+            Operation::Load => self.router.load(&fixture.route, self.context(), fixture.params.clone()).await,
+            Operation::Act => {
+                let input = fixture.input.clone().unwrap_or(serde_json::Value::Null);
+                self.router.act(&fixture.route, self.context(), fixture.params.clone(), input).await
+            }
+        }
+    }
+
+    /// Runs the fixture's request and records the current response in it.
+    pub async fn record(&self, fixture: ContractFixture) -> ContractFixture {
+        let outcome = self.run_contract(&fixture).await;
+        fixture.with_outcome(outcome)
+    }
+
+    /// Replays every fixture in `dir` and reports how the responses drifted.
+    ///
+    /// With `MONTRS_UPDATE_CONTRACTS` set, the fixtures are re-recorded and the
+    /// report describes the changes that were accepted.
+    pub async fn verify_contracts(&self, dir: impl AsRef<std::path::Path>) -> Result<CompatibilityReport, TestError> {
+        let update = std::env::var_os(UPDATE_CONTRACTS_VAR).is_some();
+        let mut report = CompatibilityReport::default();
+        for (path, fixture) in ContractFixture::read_dir(dir.as_ref())? {
+            let outcome = self.run_contract(&fixture).await;
+            let (status, response) = match &outcome {
+                Ok(value) => (200, value.clone()),
+                Err(e) => (ErrorInfo::from_route_error(e).status, serde_json::Value::Null),
+            };
+            report.results.push(contract::compare(&fixture, status, &response));
+            if update {
+                fixture.with_outcome(outcome).write_to(&path)?;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Sends a request recorded by `montrs serve --capture` through the
+    /// router, as the app would answer it embedded.
+    ///
+    /// ```rust,ignore
+    /// let capture = Capture::read(".agent/captures/20261017T093000123Z-POST-todos-42.json")?;
+    /// let response = client.replay(&capture).await;
+    /// assert_eq!(response.status, 200);
+    /// ```
+    pub async fn replay(&self, capture: &Capture) -> WasiResponse {
+        montrs_core::wasi::respond(&self.router, &self.config, self.env.as_ref(), capture.to_request()).await
+    }
+}
+
+fn to_value(value: impl Serialize) -> Result<serde_json::Value, RouteError> {
+    serde_json::to_value(value).map_err(|e| RouteError::ValidationFailed(e.to_string()))
+}
+
+fn from_value<T: DeserializeOwned>(value: serde_json::Value) -> Result<T, RouteError> {
+    serde_json::from_value(value).map_err(|e| RouteError::InternalError(e.to_string()))
+}
+
 #[cfg(feature = "e2e")]
 impl<C: AppConfig> TestRuntime<C> {
     /// Creates a new E2E driver instance.
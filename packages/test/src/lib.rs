@@ -11,6 +11,10 @@
 //! - **Manage Test Lifecycles**: Use `Fixture` and `run_fixture_test` for setup/teardown logic.
 //! - **Run E2E Tests**: Use `MontrsDriver` (via the `e2e` feature) to control browsers with Playwright.
 //! - **Simulate Application Runtime**: Use `TestRuntime` to execute application logic in-process.
+//! - **Call Routes In-Process**: Use `TestClient` to run loaders and actions through the router.
+//! - **Check API Contracts**: Replay recorded fixtures with `TestClient::verify_contracts`.
+//! - **Test Rate Limiters**: Drive a `Limiter` with a `VirtualClock` through `LimiterHarness`.
+//! - **Check Plate Conformance**: Run a plate through `PlateConformance` or `plate_conformance!`.
 //!
 //! The E2E capabilities are integrated with `TestRuntime`, allowing you to easily spin up
 //! browser tests alongside your integration tests.
@@ -31,13 +35,22 @@
 
 pub mod unit;
 pub mod integration;
+pub mod contract;
+pub mod limiter;
+pub mod conformance;
 
 #[cfg(feature = "e2e")]
 pub mod e2e;
 
-pub use integration::{Fixture, TestRuntime, TestEnv, run_fixture_test};
+pub use conformance::{Check, CheckResult, ConformanceReport, PlateConformance};
+pub use contract::{CompatibilityReport, Compatibility, ContractFixture};
+pub use integration::{Fixture, TestClient, TestConfig, TestRuntime, TestEnv, run_fixture_test};
+pub use limiter::{Decision, LimiterHarness, VirtualClock};
 pub use unit::{expect, Spy, Mock, simple_bench};
 
+/// This crate's version and the `montrs-core` it needs; see [`montrs_core::compat`].
+pub const CRATE_VERSION: montrs_core::CrateVersion = montrs_core::crate_version!();
+
 use montrs_core::AgentError;
 use thiserror::Error;
 
@@ -52,6 +65,8 @@ pub enum TestError {
     E2e(String),
     #[error("Expectation failed: {0}")]
     Expectation(String),
+    #[error("Invalid contract fixture: {0}")]
+    Contract(String),
     #[error("IO error during testing: {0}")]
     Io(#[from] std::io::Error),
 }
@@ -63,6 +78,7 @@ impl AgentError for TestError {
             TestError::Teardown(_) => "TEST_TEARDOWN",
             TestError::E2e(_) => "TEST_E2E",
             TestError::Expectation(_) => "TEST_EXPECTATION",
+            TestError::Contract(_) => "TEST_CONTRACT",
             TestError::Io(_) => "TEST_IO",
         }
     }
@@ -73,6 +89,7 @@ impl AgentError for TestError {
             TestError::Teardown(e) => format!("A test fixture failed to tear down: {}.", e),
             TestError::E2e(e) => format!("An error occurred in the E2E driver (Playwright): {}.", e),
             TestError::Expectation(e) => format!("A test expectation was not met: {}.", e),
+            TestError::Contract(e) => format!("A contract fixture could not be read or written: {}.", e),
             TestError::Io(e) => format!("An I/O error occurred during the test execution: {}.", e),
         }
     }
@@ -95,6 +112,10 @@ impl AgentError for TestError {
                 "Review the test logic and the actual vs. expected values.".to_string(),
                 "Debug the code being tested to find the cause of the discrepancy.".to_string(),
             ],
+            TestError::Contract(_) => vec![
+                "Check that the fixture is valid JSON with `name`, `route` and `operation` fields.".to_string(),
+                "Re-record the fixtures with MONTRS_UPDATE_CONTRACTS=1.".to_string(),
+            ],
             TestError::Io(_) => vec![
                 "Check if the file system is accessible and you have the necessary permissions.".to_string(),
             ],
@@ -0,0 +1,190 @@
+//! Deterministic tests for `Limiter` implementations.
+//!
+//! A limiter that reads time through a [`LimiterClock`] can be driven by a
+//! [`VirtualClock`], which only moves when the test advances it. A
+//! [`LimiterHarness`] sends simulated traffic (single requests, bursts,
+//! steady streams), records every decision with its virtual timestamp, and
+//! checks the invariants a limiter should keep: it never allows more than its
+//! rate, and it lets requests through again once the window has passed. No
+//! test sleeps, so results do not depend on machine load.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use montrs_core::GovernorLimiter;
+//! use montrs_test::limiter::{LimiterHarness, VirtualClock};
+//! use std::time::Duration;
+//!
+//! let clock = VirtualClock::new();
+//! let mut harness = LimiterHarness::new(GovernorLimiter::with_clock(10, clock.clone()), clock);
+//! assert_eq!(harness.burst(25), 10);
+//! harness.steady(50, Duration::from_secs(3));
+//! harness.assert_never_exceeds(20, Duration::from_secs(1)).unwrap();
+//! harness.assert_recovers(Duration::from_secs(1)).unwrap();
+//! ```
+
+use crate::TestError;
+use montrs_core::limiter::{Limiter, LimiterClock};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Most requests [`LimiterHarness::exhaust`] sends before giving up.
+const EXHAUST_LIMIT: usize = 100_000;
+
+/// A clock that stands still until it is advanced. Clones share the same time.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualClock {
+    nanos: Arc<AtomicU64>,
+}
+
+impl VirtualClock {
+    /// A clock at time zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.nanos.fetch_add(by.as_nanos() as u64, Ordering::SeqCst);
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::SeqCst))
+    }
+}
+
+impl LimiterClock for VirtualClock {
+    fn now(&self) -> Duration {
+        self.elapsed()
+    }
+}
+
+/// One request sent through the harness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decision {
+    /// Virtual time of the request.
+    pub at: Duration,
+    pub allowed: bool,
+}
+
+/// Sends simulated traffic to a limiter and checks how it responded.
+pub struct LimiterHarness<L> {
+    limiter: L,
+    clock: VirtualClock,
+    decisions: Vec<Decision>,
+}
+
+impl<L: Limiter> LimiterHarness<L> {
+    /// `clock` must be the clock `limiter` reads.
+    pub fn new(limiter: L, clock: VirtualClock) -> Self {
+        Self { limiter, clock, decisions: Vec::new() }
+    }
+
+    pub fn limiter(&self) -> &L {
+        &self.limiter
+    }
+
+    pub fn clock(&self) -> &VirtualClock {
+        &self.clock
+    }
+
+    /// Every request sent so far, in order.
+    pub fn decisions(&self) -> &[Decision] {
+        &self.decisions
+    }
+
+    /// Number of requests allowed so far.
+    pub fn allowed(&self) -> usize {
+        self.decisions.iter().filter(|d| d.allowed).count()
+    }
+
+    /// Sends one request at the current virtual time.
+    pub fn check(&mut self) -> bool {
+        let allowed = self.limiter.check();
+        self.decisions.push(Decision { at: self.clock.elapsed(), allowed });
+        allowed
+    }
+
+    pub fn advance(&mut self, by: Duration) {
+        self.clock.advance(by);
+    }
+
+    /// Sends `n` requests at the same instant and returns how many were allowed.
+    pub fn burst(&mut self, n: usize) -> usize {
+        (0..n).filter(|_| self.check()).count()
+    }
+
+    /// Sends `count` bursts of `size` requests, `every` apart, and returns how
+    /// many each burst got through.
+    pub fn bursts(&mut self, size: usize, every: Duration, count: usize) -> Vec<usize> {
+        (0..count)
+            .map(|i| {
+                if i > 0 {
+                    self.advance(every);
+                }
+                self.burst(size)
+            })
+            .collect()
+    }
+
+    /// Sends `per_second` evenly spaced requests per second for `duration`
+    /// and returns how many were allowed.
+    pub fn steady(&mut self, per_second: u32, duration: Duration) -> usize {
+        let interval = Duration::from_secs(1) / per_second.max(1);
+        let count = duration.as_nanos() / interval.as_nanos().max(1);
+        let mut allowed = 0;
+        for _ in 0..count {
+            if self.check() {
+                allowed += 1;
+            }
+            self.advance(interval);
+        }
+        allowed
+    }
+
+    /// Sends requests at the current instant until one is denied and returns
+    /// how many were allowed, or `None` if the limiter never denied one.
+    pub fn exhaust(&mut self) -> Option<usize> {
+        (0..EXHAUST_LIMIT).position(|_| !self.check())
+    }
+
+    /// Checks that no span of `window` virtual time allowed more than `limit`
+    /// requests, over every request sent so far.
+    pub fn assert_never_exceeds(&self, limit: usize, window: Duration) -> Result<(), TestError> {
+        let allowed: Vec<Duration> = self.decisions.iter().filter(|d| d.allowed).map(|d| d.at).collect();
+        let mut start = 0;
+        for end in 0..allowed.len() {
+            while allowed[end] - allowed[start] >= window {
+                start += 1;
+            }
+            let count = end - start + 1;
+            if count > limit {
+                return Err(TestError::Expectation(format!(
+                    "the limiter allowed {} requests between {:?} and {:?}, more than {} per {:?}",
+                    count, allowed[start], allowed[end], limit, window
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Exhausts the limiter, advances the clock by `after` and checks that a
+    /// request is allowed again.
+    pub fn assert_recovers(&mut self, after: Duration) -> Result<(), TestError> {
+        if self.exhaust().is_none() {
+            return Err(TestError::Expectation(format!(
+                "the limiter allowed {} requests in a row at {:?} and never denied one",
+                EXHAUST_LIMIT,
+                self.clock.elapsed()
+            )));
+        }
+        self.advance(after);
+        if !self.check() {
+            return Err(TestError::Expectation(format!(
+                "the limiter still denied requests {:?} after it was exhausted",
+                after
+            )));
+        }
+        Ok(())
+    }
+}
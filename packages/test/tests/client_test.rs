@@ -0,0 +1,118 @@
+use async_trait::async_trait;
+use leptos::prelude::*;
+use montrs_core::{
+    Capture, Route, RouteAction, RouteContext, RouteError, RouteLoader, RouteParams, RouteView, WasiRequest,
+};
+use montrs_test::unit::expect;
+use montrs_test::{TestClient, TestConfig, TestEnv};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+struct GreetParams {
+    name: String,
+}
+impl RouteParams for GreetParams {}
+
+struct GreetLoader;
+#[async_trait]
+impl RouteLoader<GreetParams, TestConfig> for GreetLoader {
+    type Output = String;
+    async fn load(
+        &self,
+        ctx: RouteContext<'_, TestConfig>,
+        params: GreetParams,
+    ) -> Result<Self::Output, RouteError> {
+        let greeting = ctx.env.get_var("GREETING").unwrap_or_else(|_| "Hello".to_string());
+        Ok(format!("{}, {}", greeting, params.name))
+    }
+}
+
+struct GreetAction;
+#[async_trait]
+impl RouteAction<GreetParams, TestConfig> for GreetAction {
+    type Input = Vec<u32>;
+    type Output = u32;
+    async fn act(
+        &self,
+        _ctx: RouteContext<'_, TestConfig>,
+        _params: GreetParams,
+        input: Self::Input,
+    ) -> Result<Self::Output, RouteError> {
+        Ok(input.iter().sum())
+    }
+}
+
+struct GreetView;
+impl RouteView for GreetView {
+    fn render(&self) -> impl IntoView {
+        view! { <p>"greet"</p> }
+    }
+}
+
+struct GreetRoute;
+impl Route<TestConfig> for GreetRoute {
+    type Params = GreetParams;
+    type Loader = GreetLoader;
+    type Action = GreetAction;
+    type View = GreetView;
+
+    fn path() -> &'static str {
+        "/greet/:name"
+    }
+    fn loader(&self) -> Self::Loader {
+        GreetLoader
+    }
+    fn action(&self) -> Self::Action {
+        GreetAction
+    }
+    fn view(&self) -> Self::View {
+        GreetView
+    }
+}
+
+fn params(name: &str) -> GreetParams {
+    GreetParams { name: name.to_string() }
+}
+
+#[tokio::test]
+async fn test_client_runs_loader_with_env() {
+    let env = TestEnv::new();
+    env.set("GREETING", "Hi");
+    let client = TestClient::new(TestConfig, env).with_route(GreetRoute);
+
+    let out: String = client.load("/greet/:name", params("Ada")).await.unwrap();
+    expect(out).to_equal("Hi, Ada".to_string());
+}
+
+#[tokio::test]
+async fn test_client_runs_action() {
+    let client = TestClient::new(TestConfig, TestEnv::new()).with_route(GreetRoute);
+
+    let total: u32 = client.act("/greet/:name", params("Ada"), vec![1, 2, 3]).await.unwrap();
+    expect(total).to_equal(6);
+}
+
+#[tokio::test]
+async fn test_client_reports_unknown_routes() {
+    let client = TestClient::new(TestConfig, TestEnv::new());
+
+    let out: Result<String, RouteError> = client.load("/missing", params("Ada")).await;
+    expect(matches!(out, Err(RouteError::NotFound))).to_be_true();
+}
+
+#[tokio::test]
+async fn test_client_replays_captured_requests() {
+    let client = TestClient::new(TestConfig, TestEnv::new()).with_route(GreetRoute);
+    let capture = Capture::from_request(
+        &WasiRequest::new("POST", "/greet/Ada?token=abc")
+            .with_header("Authorization", "Bearer abc")
+            .with_header("Content-Type", "application/json")
+            .with_body("[1,2,3]"),
+    );
+
+    let response = client.replay(&capture).await;
+    expect(response.status).to_equal(200);
+    expect(response.body).to_equal(b"6".to_vec());
+    let missing = client.replay(&Capture::from_request(&WasiRequest::new("GET", "/missing"))).await;
+    expect(missing.status).to_equal(404);
+}
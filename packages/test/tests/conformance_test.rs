@@ -0,0 +1,147 @@
+use async_trait::async_trait;
+use leptos::prelude::*;
+use montrs_core::{Plate, PlateContext, Route, RouteAction, RouteContext, RouteError, RouteLoader, RouteParams, RouteView, Router};
+use montrs_test::{Check, PlateConformance, TestConfig};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+#[derive(Serialize, Deserialize)]
+struct NoParams {}
+impl RouteParams for NoParams {}
+
+struct PostsLoader;
+#[async_trait]
+impl RouteLoader<NoParams, TestConfig> for PostsLoader {
+    type Output = Vec<String>;
+    async fn load(&self, _ctx: RouteContext<'_, TestConfig>, _params: NoParams) -> Result<Vec<String>, RouteError> {
+        Ok(vec!["hello".to_string()])
+    }
+}
+
+struct PostsAction;
+#[async_trait]
+impl RouteAction<NoParams, TestConfig> for PostsAction {
+    type Input = String;
+    type Output = String;
+    async fn act(
+        &self,
+        _ctx: RouteContext<'_, TestConfig>,
+        _params: NoParams,
+        input: String,
+    ) -> Result<String, RouteError> {
+        Ok(input)
+    }
+}
+
+struct PostsView;
+impl RouteView for PostsView {
+    fn render(&self) -> impl IntoView {
+        view! { <p>"posts"</p> }
+    }
+}
+
+struct PostsRoute;
+impl Route<TestConfig> for PostsRoute {
+    type Params = NoParams;
+    type Loader = PostsLoader;
+    type Action = PostsAction;
+    type View = PostsView;
+
+    fn path() -> &'static str {
+        "/posts"
+    }
+    fn loader(&self) -> Self::Loader {
+        PostsLoader
+    }
+    fn action(&self) -> Self::Action {
+        PostsAction
+    }
+    fn view(&self) -> Self::View {
+        PostsView
+    }
+}
+
+/// Registers `/posts` and runs a background task until it is shut down,
+/// unless `leaky` forgets to stop it.
+struct BlogPlate {
+    name: &'static str,
+    leaky: bool,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl BlogPlate {
+    fn new(name: &'static str) -> Self {
+        Self { name, leaky: false, tasks: Mutex::new(Vec::new()) }
+    }
+}
+
+#[async_trait]
+impl Plate<TestConfig> for BlogPlate {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn init(&self, _ctx: &mut PlateContext<TestConfig>) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let task = tokio::spawn(async {
+            loop {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        });
+        self.tasks.lock().unwrap().push(task);
+        Ok(())
+    }
+
+    fn register_routes(&self, router: &mut Router<TestConfig>) {
+        router.register(PostsRoute);
+    }
+
+    async fn shutdown(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+        if !self.leaky {
+            tasks.iter().for_each(JoinHandle::abort);
+        }
+        Ok(())
+    }
+}
+
+montrs_test::plate_conformance!(test_macro_checks_a_plate, BlogPlate::new("blog"));
+
+#[tokio::test]
+async fn test_conforming_plate_passes_every_check() {
+    let report = PlateConformance::new(|| BlogPlate::new("blog")).run().await;
+    assert!(report.passed(), "{}", report);
+    assert_eq!(report.checks.len(), 5);
+    assert_eq!(report.routes, ["/posts"]);
+
+    let dir = std::env::temp_dir().join(format!("montrs-conformance-{}", std::process::id()));
+    report.save(dir.join("blog.json")).unwrap();
+    let saved: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(dir.join("blog.json")).unwrap()).unwrap();
+    assert_eq!(saved["plate"], "blog");
+    assert_eq!(saved["checks"][0]["check"], "name");
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_broken_contracts_are_reported() {
+    let leaky = PlateConformance::new(|| BlogPlate { leaky: true, ..BlogPlate::new("blog") })
+        .with_shutdown_timeout(Duration::from_millis(100))
+        .run()
+        .await;
+    let shutdown = leaky.check(Check::Shutdown).unwrap();
+    assert!(!shutdown.passed);
+    assert!(shutdown.detail.contains("still running"), "{}", shutdown.detail);
+    assert!(leaky.assert_passed().is_err());
+
+    // The dependency takes the name and the route first.
+    let clash = PlateConformance::new(|| BlogPlate::new("blog")).with_dependency(|| BlogPlate::new("blog")).run().await;
+    assert!(!clash.check(Check::Name).unwrap().passed);
+    assert!(clash.check(Check::Routes).unwrap().detail.contains("/posts"));
+
+    let reserved = PlateConformance::new(|| BlogPlate::new("jobs")).run().await;
+    assert!(!reserved.check(Check::Name).unwrap().passed);
+    let invalid = PlateConformance::new(|| BlogPlate::new("My Blog")).run().await;
+    assert!(!invalid.check(Check::Name).unwrap().passed);
+}
@@ -0,0 +1,143 @@
+use async_trait::async_trait;
+use leptos::prelude::*;
+use montrs_core::{Route, RouteAction, RouteContext, RouteError, RouteLoader, RouteParams, RouteView};
+use montrs_test::contract::{self, Compatibility, ContractFixture};
+use montrs_test::{TestClient, TestConfig, TestEnv};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Serialize, Deserialize)]
+struct UserParams {
+    id: u32,
+}
+impl RouteParams for UserParams {}
+
+#[derive(Serialize, Deserialize)]
+struct User {
+    id: u32,
+    name: String,
+    tags: Vec<String>,
+}
+
+struct UserLoader;
+#[async_trait]
+impl RouteLoader<UserParams, TestConfig> for UserLoader {
+    type Output = User;
+    async fn load(&self, _ctx: RouteContext<'_, TestConfig>, params: UserParams) -> Result<User, RouteError> {
+        if params.id == 0 {
+            return Err(RouteError::NotFound);
+        }
+        Ok(User {
+            id: params.id,
+            name: "Ada".to_string(),
+            tags: vec!["admin".to_string()],
+        })
+    }
+}
+
+struct UserAction;
+#[async_trait]
+impl RouteAction<UserParams, TestConfig> for UserAction {
+    type Input = String;
+    type Output = String;
+    async fn act(
+        &self,
+        _ctx: RouteContext<'_, TestConfig>,
+        _params: UserParams,
+        input: String,
+    ) -> Result<String, RouteError> {
+        Ok(input)
+    }
+}
+
+struct UserView;
+impl RouteView for UserView {
+    fn render(&self) -> impl IntoView {
+        view! { <p>"user"</p> }
+    }
+}
+
+struct UserRoute;
+impl Route<TestConfig> for UserRoute {
+    type Params = UserParams;
+    type Loader = UserLoader;
+    type Action = UserAction;
+    type View = UserView;
+
+    fn path() -> &'static str {
+        "/users/:id"
+    }
+    fn loader(&self) -> Self::Loader {
+        UserLoader
+    }
+    fn action(&self) -> Self::Action {
+        UserAction
+    }
+    fn view(&self) -> Self::View {
+        UserView
+    }
+}
+
+#[tokio::test]
+async fn test_recorded_contracts_replay_cleanly() {
+    let dir = std::env::temp_dir().join(format!("montrs-contracts-{}", std::process::id()));
+    let client = TestClient::new(TestConfig, TestEnv::new()).with_route(UserRoute);
+
+    let found = client.record(ContractFixture::load("get-user", "/users/:id", json!({ "id": 1 }))).await;
+    assert_eq!(found.status, 200);
+    assert_eq!(found.response["name"], "Ada");
+    found.save(&dir).unwrap();
+
+    let missing = client.record(ContractFixture::load("missing-user", "/users/:id", json!({ "id": 0 }))).await;
+    assert_eq!(missing.status, 404);
+    missing.save(&dir).unwrap();
+
+    client
+        .record(ContractFixture::act("rename", "/users/:id", json!({ "id": 1 }), json!("Grace")))
+        .await
+        .save(&dir)
+        .unwrap();
+
+    let report = client.verify_contracts(&dir).await.unwrap();
+    assert_eq!(report.results.len(), 3);
+    assert_eq!(report.compatibility(), Compatibility::Unchanged);
+    assert!(report.assert_compatible().is_ok());
+
+    // A fixture recorded against an older server that returned a field we no longer send.
+    let mut stale = found.clone();
+    stale.name = "stale".to_string();
+    stale.response["email"] = json!("ada@example.com");
+    stale.save(&dir).unwrap();
+    let report = client.verify_contracts(&dir).await.unwrap();
+    assert!(report.is_breaking());
+    assert!(report.to_string().contains("/email: field removed"));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_shape_changes_are_classified() {
+    let fixture = ContractFixture::load("user", "/users/:id", json!({ "id": 1 }))
+        .with_outcome(Ok(json!({ "id": 1, "name": "Ada", "avatar": null, "tags": ["a"] })));
+
+    let same_shape = contract::compare(&fixture, 200, &json!({ "id": 2, "name": "Bob", "avatar": null, "tags": [] }));
+    assert_eq!(same_shape.compatibility, Compatibility::Unchanged);
+
+    let additive = contract::compare(
+        &fixture,
+        200,
+        &json!({ "id": 1, "name": "Ada", "avatar": "a.png", "tags": ["a"], "bio": "" }),
+    );
+    assert_eq!(additive.compatibility, Compatibility::Additive);
+    assert_eq!(additive.changes.len(), 2);
+
+    let retyped = contract::compare(&fixture, 200, &json!({ "id": "1", "name": "Ada", "avatar": null, "tags": [1] }));
+    assert_eq!(retyped.compatibility, Compatibility::Breaking);
+    let pointers: Vec<_> = retyped.changes.iter().map(|c| c.pointer.as_str()).collect();
+    assert!(pointers.contains(&"/id"));
+    assert!(pointers.contains(&"/tags/0"));
+
+    let status = contract::compare(&fixture, 404, &json!(null));
+    assert_eq!(status.compatibility, Compatibility::Breaking);
+    assert!(status.changes[0].description.contains("200 to 404"));
+}
@@ -0,0 +1,90 @@
+use montrs_core::{GovernorLimiter, Limiter, LimiterClock};
+use montrs_test::{LimiterHarness, TestError, VirtualClock};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const SECOND: Duration = Duration::from_secs(1);
+
+/// Allows `limit` requests per whole second of clock time.
+struct FixedWindow {
+    clock: VirtualClock,
+    limit: u32,
+    /// Off by one when set: allows `limit + 1` per window.
+    lenient: bool,
+    /// Never starts a new window when set.
+    stuck: bool,
+    state: Mutex<(u64, u32)>,
+}
+
+impl FixedWindow {
+    fn new(clock: &VirtualClock, limit: u32) -> Self {
+        Self { clock: clock.clone(), limit, lenient: false, stuck: false, state: Mutex::new((0, 0)) }
+    }
+}
+
+impl Limiter for FixedWindow {
+    fn check(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let window = self.clock.now().as_secs();
+        if window != state.0 && !self.stuck {
+            *state = (window, 0);
+        }
+        let limit = if self.lenient { self.limit + 1 } else { self.limit };
+        if state.1 < limit {
+            state.1 += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[test]
+fn test_fixed_window_keeps_its_invariants() {
+    let clock = VirtualClock::new();
+    let mut harness = LimiterHarness::new(FixedWindow::new(&clock, 5), clock);
+
+    assert_eq!(harness.burst(8), 5);
+    assert_eq!(harness.bursts(3, Duration::from_millis(400), 4), [0, 0, 0, 3]);
+    harness.advance(Duration::from_millis(800));
+    assert_eq!(harness.steady(20, Duration::from_secs(3)), 15);
+
+    // Fixed windows allow a double burst across a boundary.
+    harness.assert_never_exceeds(10, SECOND).unwrap();
+    harness.assert_recovers(SECOND).unwrap();
+    assert_eq!(harness.decisions().len(), 8 + 12 + 60 + 6 + 1);
+}
+
+#[test]
+fn test_broken_limiters_are_caught() {
+    let clock = VirtualClock::new();
+    let mut lenient = FixedWindow::new(&clock, 5);
+    lenient.lenient = true;
+    let mut harness = LimiterHarness::new(lenient, clock);
+    harness.burst(10);
+    let err = harness.assert_never_exceeds(5, SECOND).unwrap_err();
+    assert!(matches!(err, TestError::Expectation(ref m) if m.contains("allowed 6 requests")), "{}", err);
+
+    let clock = VirtualClock::new();
+    let mut stuck = FixedWindow::new(&clock, 5);
+    stuck.stuck = true;
+    let mut harness = LimiterHarness::new(stuck, clock);
+    let err = harness.assert_recovers(Duration::from_secs(10)).unwrap_err();
+    assert!(err.to_string().contains("still denied"), "{}", err);
+}
+
+#[test]
+fn test_governor_limiter_on_a_virtual_clock() {
+    let clock = VirtualClock::new();
+    let mut harness = LimiterHarness::new(GovernorLimiter::with_clock(10, clock.clone()), clock);
+
+    assert_eq!(harness.burst(25), 10);
+    harness.advance(Duration::from_millis(150));
+    assert_eq!(harness.burst(5), 1, "one request is replenished every 100ms");
+
+    harness.advance(SECOND);
+    harness.steady(50, Duration::from_secs(5));
+    // A full burst plus one request per 100ms of the window.
+    harness.assert_never_exceeds(20, SECOND).unwrap();
+    harness.assert_recovers(SECOND).unwrap();
+}
@@ -1,5 +1,8 @@
 use leptos::prelude::*;
-use montrs_core::{AppSpec, Target, AppConfig, EnvConfig, EnvError, FromEnv};
+use montrs_core::{
+    AppSpec, Target, AppConfig, EnvConfig, EnvError, FromEnv,
+    ErrorInfo, ErrorPages, ErrorTheme, error_fallback,
+};
 use tailwind_fuse::*;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -47,9 +50,45 @@ enum BtnVariant {
     Secondary,
 }
 
-// [REQUIRED] 5. Root View
+// [OPTIONAL] 5. Branded Error Pages
+// Loader failures and thrown errors inside <ErrorBoundary> render these views.
+// Tweak the theme, or register your own component per status code.
+fn error_pages() -> ErrorPages {
+    ErrorPages::new()
+        .with_theme(
+            ErrorTheme::default()
+                .with_brand("MontRS Default App")
+                .with_accent("#2563eb"),
+        )
+        .with_page(404, |info, theme| view! { <NotFound info theme=theme.clone() /> }.into_any())
+}
+
+#[component]
+fn NotFound(info: ErrorInfo, theme: ErrorTheme) -> impl IntoView {
+    let style = theme.style();
+
+    view! {
+        <main class="flex flex-col items-center justify-center min-h-screen bg-slate-900 text-white" style=style>
+            <p class="text-sm uppercase tracking-widest text-gray-400">{theme.brand}</p>
+            <h1 class="text-6xl font-extrabold my-4" style="color: var(--montrs-error-accent)">"404"</h1>
+            <p class="text-gray-300 mb-6">{info.message}</p>
+            <a class="px-6 py-2 rounded-lg bg-blue-600 hover:bg-blue-500" href=theme.home_href>"Back home"</a>
+        </main>
+    }
+}
+
+// [REQUIRED] 6. Root View
 #[component]
 fn App() -> impl IntoView {
+    view! {
+        <ErrorBoundary fallback=error_fallback>
+            <Counter />
+        </ErrorBoundary>
+    }
+}
+
+#[component]
+fn Counter() -> impl IntoView {
     let (count, set_count) = signal(0);
     let btn_class = CounterBtn { variant: BtnVariant::Primary };
 
@@ -69,14 +108,17 @@ fn App() -> impl IntoView {
     }
 }
 
-// [REQUIRED] 6. Main Entry Point
+// [REQUIRED] 7. Main Entry Point
 fn main() {
     // [EXPLICIT] Manual bootstrapping of AppSpec
     let spec = AppSpec::new(MyAppConfig, MyEnv)
-        .with_target(Target::Wasm);
+        .with_target(Target::Wasm)
+        // [OPTIONAL] `montrs plate add` registers community plates here.
+        // montrs:plates:begin
+        // montrs:plates:end
+        .with_error_pages(error_pages());
     
     // [EXPLICIT] Explicit mounting to the DOM
-    // In a real MontRS app, we'd use spec.boot() which handles SSR/Hydration
-    // but for a simple Wasm mount, we can use this:
-    mount_to_body(|| view! { <App /> });
+    // `mount` provides config, env and error pages as contexts before rendering.
+    spec.mount(|| view! { <App /> });
 }
@@ -4,10 +4,11 @@
 
 use leptos::prelude::*;
 use montrs_core::{
-    AppConfig, AppSpec, Plate, PlateContext, Route, RouteAction, RouteContext, RouteError,
-    RouteLoader, RouteParams, RouteView, Router, Target,
+    AppConfig, AppSpec, ErrorPages, ErrorTheme, Plate, PlateContext, Route, RouteAction,
+    RouteContext, RouteError, RouteLoader, RouteParams, RouteView, Router, Target, error_fallback,
+    meta,
 };
-use montrs_orm::{DbBackend, FromRow, SqliteBackend};
+use montrs_orm::{DbBackend, FromRow, Seeds, SqliteBackend};
 use montrs_schema::Schema;
 use serde::{Deserialize, Serialize};
 
@@ -49,6 +50,13 @@ pub struct CreateTodo {
     pub title: String,
 }
 
+/// The table behind `Todo`; `seeds/demo/01_todos.toml` fills it for `montrs demo`.
+const TODOS_TABLE: &str = "CREATE TABLE IF NOT EXISTS todos (
+    id INTEGER PRIMARY KEY,
+    title TEXT NOT NULL,
+    completed BOOLEAN NOT NULL DEFAULT 0
+)";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Todo {
     pub id: i32,
@@ -124,7 +132,7 @@ impl Plate<MyConfig> for TodoPlate {
         Ok(())
     }
     fn register_routes(&self, router: &mut Router<MyConfig>) {
-        router.register(TodoRoute);
+        router.register(TodoRoute).with_meta(meta::OWNER, "todo-team");
     }
 }
 
@@ -145,9 +153,23 @@ async fn main() -> anyhow::Result<()> {
     let config = MyConfig { db_url: ":memory:".to_string() };
     let env = MyEnv;
 
+    // [OPTIONAL] Demo data: `montrs demo` points MONTRS_SEED_DIR at seeds/demo.
+    let db = SqliteBackend::new(&config.db_url)?;
+    db.execute(TODOS_TABLE, &[]).await?;
+    if let Some(seeds) = Seeds::from_env()? {
+        println!("Seeded {} demo rows", seeds.apply(&db).await?);
+    }
+
     let spec = AppSpec::new(config, env)
         .with_target(Target::Server)
-        .with_plate(Box::new(TodoPlate));
+        .with_plate(Box::new(TodoPlate))
+        // [OPTIONAL] `montrs plate add` registers community plates here.
+        // montrs:plates:begin
+        // montrs:plates:end
+        // [OPTIONAL] Branded error pages for loader/action failures.
+        .with_error_pages(
+            ErrorPages::new().with_theme(ErrorTheme::default().with_brand("MontRS Todo").with_accent("#16a34a")),
+        );
 
     println!("App ready with plates: {:?}", spec.plates.iter().map(|p| p.name()).collect::<Vec<_>>());
 
@@ -159,7 +181,11 @@ async fn main() -> anyhow::Result<()> {
 
     // [EXPLICIT] Mount or boot the application
     println!("Mounting Leptos application...");
-    spec.mount(|| view! { <TodoApp /> });
+    spec.mount(|| view! {
+        <ErrorBoundary fallback=error_fallback>
+            <TodoApp />
+        </ErrorBoundary>
+    });
 
     Ok(())
 }
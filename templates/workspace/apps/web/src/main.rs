@@ -1,6 +1,9 @@
 use leptos::prelude::*;
-use montrs_core::{AppSpec, Target, AppConfig, EnvConfig, EnvError, FromEnv};
-use ui::Button;
+use montrs_core::{
+    AppSpec, Target, AppConfig, EnvConfig, EnvError, FromEnv,
+    ErrorPages, ErrorTheme, error_fallback,
+};
+use ui::{Button, ErrorCard};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
@@ -31,9 +34,27 @@ impl AppConfig for MyAppConfig {
     type Env = MyEnv;
 }
 
-// [REQUIRED] 4. UI Components
+// [OPTIONAL] 4. Branded Error Pages
+// Every status renders the shared `ui::ErrorCard`; add `.with_page(404, ...)`
+// to give a single status its own view.
+fn error_pages() -> ErrorPages {
+    ErrorPages::new()
+        .with_theme(ErrorTheme::default().with_brand("MontRS Workspace"))
+        .with_fallback(|info, theme| view! { <ErrorCard info theme=theme.clone() /> }.into_any())
+}
+
+// [REQUIRED] 5. UI Components
 #[component]
 fn App() -> impl IntoView {
+    view! {
+        <ErrorBoundary fallback=error_fallback>
+            <Home />
+        </ErrorBoundary>
+    }
+}
+
+#[component]
+fn Home() -> impl IntoView {
     let (count, set_count) = signal(0);
 
     view! {
@@ -49,12 +70,16 @@ fn App() -> impl IntoView {
     }
 }
 
-// [REQUIRED] 5. Main Entry Point
+// [REQUIRED] 6. Main Entry Point
 fn main() {
     // [EXPLICIT] Manual bootstrapping
     let spec = AppSpec::new(MyAppConfig, MyEnv)
-        .with_target(Target::Wasm);
+        .with_target(Target::Wasm)
+        // [OPTIONAL] `montrs plate add` registers community plates here.
+        // montrs:plates:begin
+        // montrs:plates:end
+        .with_error_pages(error_pages());
     
-    // [EXPLICIT] Explicit mount
-    mount_to_body(|| view! { <App /> });
+    // [EXPLICIT] Explicit mount (provides config, env and error pages as contexts)
+    spec.mount(|| view! { <App /> });
 }
@@ -1,6 +1,7 @@
 //! Shared UI components for the workspace.
 
 use leptos::prelude::*;
+use montrs_core::{ErrorInfo, ErrorTheme};
 use tailwind_fuse::*;
 
 /// A reusable button component with type-safe variants.
@@ -53,3 +54,23 @@ pub enum ButtonSize {
     #[tw(class = "text-lg px-8 py-3")]
     Large,
 }
+
+/// A branded error page shared by every app in the workspace.
+///
+/// Register it with `ErrorPages::with_fallback` so loader failures render it
+/// instead of a blank screen.
+#[component]
+pub fn ErrorCard(info: ErrorInfo, theme: ErrorTheme) -> impl IntoView {
+    let style = theme.style();
+    let home = ButtonClass { variant: ButtonVariant::Primary, size: ButtonSize::Medium };
+
+    view! {
+        <main class="flex flex-col items-center justify-center min-h-screen bg-slate-900 text-white" style=style>
+            <p class="text-sm uppercase tracking-widest text-gray-400">{theme.brand}</p>
+            <h1 class="text-6xl font-extrabold my-4" style="color: var(--montrs-error-accent)">{info.status}</h1>
+            <h2 class="text-2xl font-semibold mb-2">{info.title}</h2>
+            <p class="text-gray-300 mb-6">{info.message}</p>
+            <a class=home.to_class() href=theme.home_href>"Back home"</a>
+        </main>
+    }
+}